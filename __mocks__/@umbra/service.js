@@ -168,6 +168,7 @@ const mockInstance = {
     })
   ),
   getPinnedMessages: jest.fn(() => Promise.resolve([])),
+  searchMessages: jest.fn(() => Promise.resolve([])),
 
   // Incoming messages
   storeIncomingMessage: jest.fn(() => Promise.resolve()),
@@ -307,9 +308,17 @@ const mockInstance = {
       relayMessage: JSON.stringify({ type: 'send', to_did: toDid, payload }),
     })
   ),
+  relayAuthenticate: jest.fn((nonce) =>
+    Promise.resolve({
+      relayMessage: JSON.stringify({ type: 'authenticate', signature: 'mock-signature' }),
+    })
+  ),
   relayFetchOffline: jest.fn(() =>
     Promise.resolve(JSON.stringify({ type: 'fetch_offline' }))
   ),
+  relayAckOffline: jest.fn((ids) =>
+    Promise.resolve(JSON.stringify({ type: 'ack_offline', ids }))
+  ),
 
   // Events
   onMessageEvent: jest.fn(() => jest.fn()),
@@ -970,15 +970,17 @@ describe('T3.10 Key rotation', () => {
   });
 
   it('T3.10.5 — updateFriendEncryptionKey updates friend record', async () => {
-    await mockService.updateFriendEncryptionKey(
-      'did:key:z6MkTestFriend',
-      'c'.repeat(64),
-      'd'.repeat(128)
-    );
+    const rotation = {
+      did: 'did:key:z6MkTestFriend',
+      new_encryption_key: 'c'.repeat(64),
+      previous_encryption_key: 'e'.repeat(64),
+      rotated_at: 1700000000,
+      signature: 'd'.repeat(128),
+    };
+    await mockService.updateFriendEncryptionKey('did:key:z6MkTestFriend', rotation);
     expect(mockService.updateFriendEncryptionKey).toHaveBeenCalledWith(
       'did:key:z6MkTestFriend',
-      'c'.repeat(64),
-      'd'.repeat(128)
+      rotation
     );
   });
 
@@ -987,7 +989,7 @@ describe('T3.10 Key rotation', () => {
       new Error('Signature verification failed')
     );
     await expect(
-      mockService.updateFriendEncryptionKey('did:key:z6MkTest', 'badkey', 'badsig')
+      mockService.updateFriendEncryptionKey('did:key:z6MkTest', { signature: 'badsig' })
     ).rejects.toThrow('Signature verification failed');
   });
 
@@ -996,7 +998,7 @@ describe('T3.10 Key rotation', () => {
       new Error('Friend not found')
     );
     await expect(
-      mockService.updateFriendEncryptionKey('did:key:z6MkUnknown', 'abc', 'def')
+      mockService.updateFriendEncryptionKey('did:key:z6MkUnknown', {})
     ).rejects.toThrow('Friend not found');
   });
 
@@ -59,7 +59,11 @@ const mockService = {
   relaySend: jest.fn(() =>
     Promise.resolve({ relayMessage: '{}' }),
   ),
+  relayAuthenticate: jest.fn(() =>
+    Promise.resolve({ relayMessage: '{}' }),
+  ),
   relayFetchOffline: jest.fn(() => Promise.resolve('{}')),
+  relayAckOffline: jest.fn(() => Promise.resolve('{}')),
   getFriends: jest.fn(() => Promise.resolve([])),
   getCommunities: jest.fn(() => Promise.resolve([])),
 };
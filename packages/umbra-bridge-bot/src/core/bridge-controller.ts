@@ -72,6 +72,7 @@ export class BridgeController {
     this.relay = new RelayConnection({
       url: botConfig.relayUrl,
       did: identity.did,
+      privateKeyHex: identity.privateKeyHex,
       keepaliveInterval: botConfig.keepaliveInterval,
       maxReconnectDelay: botConfig.maxReconnectDelay,
     });
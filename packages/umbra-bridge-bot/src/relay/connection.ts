@@ -2,13 +2,14 @@
  * Relay WebSocket client.
  *
  * Connects to the Umbra relay server and handles:
- * - Registration with the bridge bot's DID
+ * - Registration with the bridge bot's DID (signed auth challenge)
  * - Sending messages to community members
  * - Receiving community events
  * - Keepalive pings with exponential backoff reconnection
  */
 
 import WebSocket from 'ws';
+import * as ed from '@noble/ed25519';
 import { EventEmitter } from 'events';
 import { getLogger } from '../util/logger';
 import type { RelayOutbound, RelayInbound, CommunityEventEnvelope } from '../types';
@@ -16,10 +17,15 @@ import type { RelayOutbound, RelayInbound, CommunityEventEnvelope } from '../typ
 export interface RelayConnectionOptions {
   url: string;
   did: string;
+  /** Hex-encoded Ed25519 private key for the DID, used to sign auth challenges. */
+  privateKeyHex: string;
   keepaliveInterval: number;
   maxReconnectDelay: number;
 }
 
+/** Domain-separation prefix the relay expects in signed auth challenges. */
+const AUTH_CHALLENGE_PREFIX = 'umbra-relay-auth-v1:';
+
 export declare interface RelayConnection {
   on(event: 'community_event', listener: (envelope: CommunityEventEnvelope, fromDid: string) => void): this;
   on(event: 'connected', listener: () => void): this;
@@ -126,6 +132,12 @@ export class RelayConnection extends EventEmitter {
 
   private handleMessage(msg: RelayInbound): void {
     switch (msg.type) {
+      case 'auth_challenge':
+        this.answerChallenge(msg.nonce).catch((err) => {
+          this.log.error({ err }, 'Failed to sign auth challenge');
+        });
+        break;
+
       case 'registered':
         this.log.info({ did: msg.did }, 'Registered with relay');
         this.isRegistered = true;
@@ -159,6 +171,13 @@ export class RelayConnection extends EventEmitter {
     }
   }
 
+  private async answerChallenge(nonce: string): Promise<void> {
+    const message = new TextEncoder().encode(AUTH_CHALLENGE_PREFIX + nonce);
+    const privateKey = Buffer.from(this.options.privateKeyHex, 'hex');
+    const signature = await ed.signAsync(message, privateKey);
+    this._send({ type: 'authenticate', signature: Buffer.from(signature).toString('base64') });
+  }
+
   private _send(msg: RelayOutbound): boolean {
     if (!this.ws || this.ws.readyState !== WebSocket.OPEN) {
       this.log.warn({ type: msg.type }, 'Cannot send, WebSocket not open');
@@ -51,11 +51,13 @@ export interface BridgeConfigSummary {
 /** Message sent over WebSocket to the relay. */
 export type RelayOutbound =
   | { type: 'register'; did: string }
+  | { type: 'authenticate'; signature: string }
   | { type: 'send'; to_did: string; payload: string }
   | { type: 'ping' };
 
 /** Message received from the relay over WebSocket. */
 export type RelayInbound =
+  | { type: 'auth_challenge'; nonce: string }
   | { type: 'registered'; did: string }
   | { type: 'message'; from_did: string; payload: string }
   | { type: 'pong' }
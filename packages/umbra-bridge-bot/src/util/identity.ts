@@ -3,7 +3,8 @@
  *
  * Generates an Ed25519 keypair on first run and persists it to disk.
  * The DID is used to identify the bridge bot on the relay — the relay
- * accepts any well-formed DID without signature challenge.
+ * challenges every registration, and the bot proves ownership of the DID
+ * by signing the challenge with this key.
  */
 
 import * as ed from '@noble/ed25519';
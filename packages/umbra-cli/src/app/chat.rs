@@ -1194,12 +1194,22 @@ impl App {
                 self.handle_incoming_message(&from_did, &payload, timestamp);
             }
             RelayEvent::OfflineMessages { messages } => {
+                let mut ids = Vec::with_capacity(messages.len());
                 for msg in messages {
                     self.handle_incoming_message(
                         &msg.from_did,
                         &msg.payload,
                         msg.timestamp,
                     );
+                    ids.push(msg.id);
+                }
+
+                // Only now is it safe for the relay to drop them
+                ids.retain(|id| !id.is_empty());
+                if !ids.is_empty() {
+                    if let Some(ref relay) = self.relay_handle {
+                        relay.ack_offline(ids);
+                    }
                 }
             }
             RelayEvent::InviteResolved {
@@ -1253,6 +1263,11 @@ impl App {
                     self.handle_incoming_chat_envelope(from_did, &envelope);
                     return;
                 }
+                t if t.starts_with("group_") => {
+                    // Group envelopes are handled by the core GroupService
+                    self.handle_incoming_group_envelope(from_did, payload);
+                    return;
+                }
                 _ => {}
             }
         }
@@ -1294,14 +1309,6 @@ impl App {
                 return;
             }
             // Group DM message types
-            "group_create" => {
-                self.handle_incoming_group_create(from_did, &envelope);
-                return;
-            }
-            "group_chat" => {
-                self.handle_incoming_group_chat(from_did, &envelope);
-                return;
-            }
             "group_leave" => {
                 self.handle_incoming_group_leave(from_did, &envelope);
                 return;
@@ -1,6 +1,12 @@
 //! Group DM handlers — create, message, receive group messages.
+//!
+//! Group state and encryption go through umbra-core's `GroupService`;
+//! this module drives it from the TUI and relays what it produces.
 
 use crossterm::event::{KeyCode, KeyEvent};
+use umbra_core::groups::{
+    conversation_id, GroupEnvelope, GroupEvent, GroupMessage, GroupRole, RelayMessage,
+};
 
 use super::*;
 
@@ -131,92 +137,62 @@ impl App {
         None
     }
 
-    /// Create a group and notify all members via relay.
+    /// Create a group and invite the selected friends via relay.
     fn create_group(&mut self, name: &str, selected_members: &[bool]) {
-        let my_did = match &self.my_did {
-            Some(d) => d.clone(),
-            None => {
-                self.error_message = Some("Identity not loaded".into());
-                return;
-            }
-        };
-
-        let my_name = self
-            .identity
-            .as_ref()
-            .map(|id| id.profile().display_name.clone())
-            .unwrap_or_else(|| "Me".to_string());
-
         // Gather selected friend DIDs
         let friends = match &self.screen {
             Screen::CreateGroup { friends, .. } => friends.clone(),
             _ => return,
         };
 
-        let mut member_dids: Vec<(String, String)> = Vec::new(); // (did, display_name)
-        for (i, selected) in selected_members.iter().enumerate() {
-            if *selected && i < friends.len() {
-                member_dids.push((
-                    friends[i].did.clone(),
-                    friends[i].display_name.clone(),
-                ));
-            }
-        }
+        let member_dids: Vec<String> = selected_members
+            .iter()
+            .enumerate()
+            .filter(|(i, selected)| **selected && *i < friends.len())
+            .map(|(i, _)| friends[i].did.clone())
+            .collect();
 
         if member_dids.is_empty() {
             self.error_message = Some("Select at least one friend".into());
             return;
         }
 
-        let group_id = uuid::Uuid::new_v4().to_string();
-        let ts = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs() as i64;
-
-        // Save group to local DB
-        if let Some(ref db) = self.db {
-            let _ = db.save_group(&group_id, name, None, &my_did, ts);
-            // Add ourselves as a member
-            let _ = db.add_group_member(&group_id, &my_did, Some(&my_name), ts);
-            // Add selected friends as members
-            for (did, display_name) in &member_dids {
-                let _ = db.add_group_member(&group_id, did, Some(display_name), ts);
+        let svc = match &self.group_service {
+            Some(svc) => svc,
+            None => {
+                self.error_message = Some("Identity not loaded".into());
+                return;
             }
-        }
-
-        // Build member list for the relay invite
-        let all_member_dids: Vec<String> = std::iter::once(my_did.clone())
-            .chain(member_dids.iter().map(|(did, _)| did.clone()))
-            .collect();
-
-        // Send group_create to each invited member
-        let payload = serde_json::json!({
-            "type": "group_create",
-            "group_id": group_id,
-            "name": name,
-            "members": all_member_dids,
-            "creator_did": my_did,
-            "creator_name": my_name,
-            "timestamp": ts,
-        })
-        .to_string();
+        };
 
-        if let Some(ref relay) = self.relay_handle {
-            for (did, _) in &member_dids {
-                relay.send(did.clone(), payload.clone());
+        let group = match svc.create_group(name, None) {
+            Ok(g) => g,
+            Err(e) => {
+                self.error_message = Some(format!("Failed to create group: {e}"));
+                return;
             }
+        };
+
+        // Invite each selected friend; they join once they accept
+        let mut invited = 0;
+        for did in &member_dids {
+            self.sync_friend_to_core(did);
+            let invite = match svc.invite_member(&group.id, did) {
+                Ok(invite) => invite,
+                Err(_) => continue,
+            };
+            self.send_relay_messages(&invite.relay_messages);
+            invited += 1;
         }
 
-        // Reload groups from DB
+        // Reload groups
         self.load_groups_from_db();
 
-        let member_count = member_dids.len();
         self.error_message = Some(format!(
-            "Group '{}' created with {} member{}",
+            "Group '{}' created, invited {} member{}",
             name,
-            member_count,
-            if member_count != 1 { "s" } else { "" }
+            invited,
+            if invited != 1 { "s" } else { "" }
         ));
 
         // Return to chat with groups sidebar
@@ -224,6 +200,54 @@ impl App {
         self.return_from_create_group();
     }
 
+    /// Copy a friend's keys into core.db so the group service can wrap
+    /// group keys for them and verify what they send.
+    fn sync_friend_to_core(&self, did: &str) {
+        let (core_db, db) = match (&self.core_db, &self.db) {
+            (Some(core_db), Some(db)) => (core_db, db),
+            _ => return,
+        };
+        if let Ok(Some(_)) = core_db.get_friend(did) {
+            return;
+        }
+
+        let friend = match db
+            .load_friends()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|f| f.did == did)
+        {
+            Some(f) => f,
+            None => return,
+        };
+
+        let decode = |key_hex: Option<&str>| -> Option<[u8; 32]> {
+            let bytes = hex::decode(key_hex?).ok()?;
+            bytes.try_into().ok()
+        };
+        if let (Some(signing_key), Some(encryption_key)) = (
+            decode(friend.signing_key.as_deref()),
+            decode(friend.encryption_key.as_deref()),
+        ) {
+            let _ = core_db.add_friend(
+                did,
+                &friend.display_name,
+                &signing_key,
+                &encryption_key,
+                None,
+            );
+        }
+    }
+
+    /// Send relay messages produced by the group service.
+    fn send_relay_messages(&self, messages: &[RelayMessage]) {
+        if let Some(ref relay) = self.relay_handle {
+            for msg in messages {
+                relay.send(msg.to_did.clone(), msg.payload.clone());
+            }
+        }
+    }
+
     /// Return from CreateGroup screen back to Chat.
     fn return_from_create_group(&mut self) {
         if let Screen::CreateGroup {
@@ -245,65 +269,46 @@ impl App {
 
     // ── Group messaging ─────────────────────────────────────────────────
 
-    /// Send a message to a group (fan-out to all members).
+    /// Encrypt a message for a group and fan it out to all members.
     pub(super) fn send_group_message(&mut self, text: &str) {
         let group_id = match &self.active_group {
             Some(id) => id.clone(),
             None => return,
         };
 
-        let my_did = match &self.my_did {
-            Some(d) => d.clone(),
+        let svc = match &self.group_service {
+            Some(svc) => svc,
             None => {
                 self.error_message = Some("Identity not loaded".into());
                 return;
             }
         };
 
+        let sent = match svc.send_message(&group_id, &conversation_id(&group_id), text) {
+            Ok(sent) => sent,
+            Err(e) => {
+                self.error_message = Some(format!("Failed to send: {e}"));
+                return;
+            }
+        };
+        self.send_relay_messages(&sent.relay_messages);
+
         let my_name = self
             .identity
             .as_ref()
             .map(|id| id.profile().display_name.clone())
             .unwrap_or_else(|| "Me".to_string());
-
-        let ts = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs() as i64;
-        let msg_id = uuid::Uuid::new_v4().to_string();
+        let ts = sent.timestamp / 1000;
 
         // Save to local DB
         if let Some(ref db) = self.db {
-            let _ = db.save_group_message(&msg_id, &group_id, &my_did, text, ts);
-        }
-
-        // Build payload
-        let payload = serde_json::json!({
-            "type": "group_chat",
-            "group_id": group_id,
-            "message_id": msg_id,
-            "content": text,
-            "sender_did": my_did,
-            "sender_name": my_name,
-            "timestamp": ts,
-        })
-        .to_string();
-
-        // Fan-out: send to each group member (except ourselves)
-        if let Some(group) = self.groups.iter().find(|g| g.id == group_id) {
-            if let Some(ref relay) = self.relay_handle {
-                for member in &group.members {
-                    if member.did != my_did {
-                        relay.send(member.did.clone(), payload.clone());
-                    }
-                }
-            }
+            let _ = db.save_group_message(&sent.id, &group_id, &sent.sender_did, text, ts);
         }
 
         // Add to displayed messages
         self.messages.push(DisplayMessage {
-            id: msg_id,
-            sender_did: my_did,
+            id: sent.id,
+            sender_did: sent.sender_did,
             sender_name: my_name,
             content: text.to_string(),
             timestamp: ts,
@@ -375,140 +380,87 @@ impl App {
 
     // ── Incoming group message handlers ──────────────────────────────────
 
-    /// Handle an incoming group_create message.
-    pub(super) fn handle_incoming_group_create(
-        &mut self,
-        from_did: &str,
-        envelope: &serde_json::Value,
-    ) {
-        let group_id = match envelope.get("group_id").and_then(|v| v.as_str()) {
-            Some(id) => id,
-            None => return,
+    /// Handle an incoming group envelope (invites, messages, key changes).
+    pub(super) fn handle_incoming_group_envelope(&mut self, from_did: &str, payload: &str) {
+        let envelope = match GroupEnvelope::from_payload(payload) {
+            Ok(e) => e,
+            Err(_) => return,
         };
-        let name = match envelope.get("name").and_then(|v| v.as_str()) {
-            Some(n) => n,
-            None => return,
-        };
-        let creator_did = envelope
-            .get("creator_did")
-            .and_then(|v| v.as_str())
-            .unwrap_or(from_did);
-        let creator_name = envelope
-            .get("creator_name")
-            .and_then(|v| v.as_str())
-            .unwrap_or("Unknown");
-        let ts = envelope
-            .get("timestamp")
-            .and_then(|v| v.as_i64())
-            .unwrap_or_else(|| {
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs() as i64
-            });
-
-        let members: Vec<String> = envelope
-            .get("members")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        // Save group to local DB
-        if let Some(ref db) = self.db {
-            let _ = db.save_group(group_id, name, None, creator_did, ts);
 
-            // Add all members
-            for member_did in &members {
-                let display_name = if member_did == creator_did {
-                    Some(creator_name.to_string())
-                } else {
-                    self.find_friend_name(member_did)
-                };
-                let _ = db.add_group_member(
-                    group_id,
-                    member_did,
-                    display_name.as_deref(),
-                    ts,
-                );
-            }
+        // Invites carry a group key wrapped for us by the inviter
+        if let GroupEnvelope::Invite(ref invite) = envelope {
+            self.sync_friend_to_core(&invite.inviter_did);
         }
 
-        // Reload groups
-        self.load_groups_from_db();
-
-        self.error_message = Some(format!("Added to group '{}'", name));
-    }
-
-    /// Handle an incoming group_chat message.
-    pub(super) fn handle_incoming_group_chat(
-        &mut self,
-        _from_did: &str,
-        envelope: &serde_json::Value,
-    ) {
-        let group_id = match envelope.get("group_id").and_then(|v| v.as_str()) {
-            Some(id) => id,
+        let svc = match &self.group_service {
+            Some(svc) => svc,
             None => return,
         };
-        let msg_id = match envelope.get("message_id").and_then(|v| v.as_str()) {
-            Some(id) => id,
-            None => return,
-        };
-        let content = match envelope.get("content").and_then(|v| v.as_str()) {
-            Some(c) => c,
-            None => return,
-        };
-        let sender_did = match envelope.get("sender_did").and_then(|v| v.as_str()) {
-            Some(d) => d,
-            None => return,
+        let received = match svc.receive(from_did, envelope) {
+            Ok(r) => r,
+            Err(_) => return,
         };
-        let sender_name = envelope
-            .get("sender_name")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .or_else(|| self.find_group_member_name(group_id, sender_did))
-            .unwrap_or_else(|| sender_did[..16.min(sender_did.len())].to_string());
-        let ts = envelope
-            .get("timestamp")
-            .and_then(|v| v.as_i64())
-            .unwrap_or_else(|| {
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs() as i64
-            });
-
-        // Skip if we sent this message
-        if let Some(ref my_did) = self.my_did {
-            if sender_did == my_did {
-                return;
+        self.send_relay_messages(&received.relay_messages);
+
+        match received.event {
+            GroupEvent::InviteReceived(invite) => {
+                // Invites come from friends, so join right away
+                let accept = svc
+                    .accept_invite(&invite.id)
+                    .and_then(|_| svc.invite_accept_envelope(&invite.id));
+                match accept {
+                    Ok(msg) => {
+                        self.send_relay_messages(&[msg]);
+                        self.error_message =
+                            Some(format!("Added to group '{}'", invite.group_name));
+                    }
+                    Err(e) => {
+                        self.error_message = Some(format!("Failed to join group: {e}"));
+                    }
+                }
+                self.load_groups_from_db();
             }
-        }
-
-        // Verify we're a member
-        if let Some(ref db) = self.db {
-            if let Some(ref my_did) = self.my_did {
-                if !db.is_group_member(group_id, my_did).unwrap_or(false) {
-                    return;
+            GroupEvent::Message(msg) => {
+                self.handle_incoming_group_chat(msg);
+            }
+            GroupEvent::RemovedFromGroup { group_id } => {
+                if let Some(ref db) = self.db {
+                    let _ = db.delete_group_messages(&group_id);
                 }
+                if self.active_group.as_deref() == Some(group_id.as_str()) {
+                    self.active_group = None;
+                    self.messages.clear();
+                }
+                self.load_groups_from_db();
+            }
+            GroupEvent::MemberJoined { .. } | GroupEvent::MemberRemoved { .. } => {
+                self.load_groups_from_db();
             }
+            GroupEvent::InviteDeclined { .. }
+            | GroupEvent::KeyRotated { .. }
+            | GroupEvent::EpochChanged { .. } => {}
         }
+    }
+
+    /// Store and display a decrypted group message.
+    fn handle_incoming_group_chat(&mut self, msg: GroupMessage) {
+        let sender_name = self
+            .find_group_member_name(&msg.group_id, &msg.sender_did)
+            .unwrap_or_else(|| msg.sender_did[..16.min(msg.sender_did.len())].to_string());
+        let ts = msg.timestamp / 1000;
 
         // Save to DB
         if let Some(ref db) = self.db {
-            let _ = db.save_group_message(msg_id, group_id, sender_did, content, ts);
+            let _ = db.save_group_message(&msg.id, &msg.group_id, &msg.sender_did, &msg.text, ts);
         }
 
         // If this group is currently active, add to displayed messages
-        if self.active_group.as_deref() == Some(group_id) {
+        if self.active_group.as_deref() == Some(msg.group_id.as_str()) {
             self.messages.push(DisplayMessage {
-                id: msg_id.to_string(),
-                sender_did: sender_did.to_string(),
+                id: msg.id,
+                sender_did: msg.sender_did,
                 sender_name,
-                content: content.to_string(),
+                content: msg.text,
                 timestamp: ts,
                 is_mine: false,
                 edited_at: None,
@@ -522,34 +474,40 @@ impl App {
     }
 
     /// Handle an incoming group_leave message.
+    ///
+    /// If we're an admin, the leaver is removed with a key change so they
+    /// can't read later messages; otherwise we just drop them locally and
+    /// wait for the admin's commit.
     pub(super) fn handle_incoming_group_leave(
         &mut self,
-        _from_did: &str,
+        from_did: &str,
         envelope: &serde_json::Value,
     ) {
         let group_id = match envelope.get("group_id").and_then(|v| v.as_str()) {
             Some(id) => id,
             None => return,
         };
+
+        // Only members can announce their own departure
         let member_did = match envelope.get("member_did").and_then(|v| v.as_str()) {
-            Some(d) => d,
-            None => return,
+            Some(d) if d == from_did => d,
+            _ => return,
         };
 
-        // Remove member from local DB
-        if let Some(ref db) = self.db {
-            let _ = db.remove_group_member(group_id, member_did);
-        }
-
-        // If the leaver is us, remove the group entirely
-        if self.my_did.as_deref() == Some(member_did) {
-            if let Some(ref db) = self.db {
-                let _ = db.delete_group(group_id);
-            }
-            if self.active_group.as_deref() == Some(group_id) {
-                self.active_group = None;
-                self.messages.clear();
+        let svc = match &self.group_service {
+            Some(svc) => svc,
+            None => return,
+        };
+        let we_are_admin = matches!(
+            self.my_did.as_deref().map(|did| svc.member_role(group_id, did)),
+            Some(Ok(Some(GroupRole::Admin)))
+        );
+        if we_are_admin {
+            if let Ok(removal) = svc.remove_member_with_rotation(group_id, member_did) {
+                self.send_relay_messages(&removal.relay_messages);
             }
+        } else {
+            let _ = svc.remove_member(group_id, member_did);
         }
 
         // Reload groups
@@ -726,9 +684,12 @@ impl App {
             }
         }
 
-        // Remove from local DB
+        // Drop our copy of the group and its messages
+        if let Some(ref svc) = self.group_service {
+            let _ = svc.leave_group(group_id);
+        }
         if let Some(ref db) = self.db {
-            let _ = db.delete_group(group_id);
+            let _ = db.delete_group_messages(group_id);
         }
 
         // Clear active group if it was this one
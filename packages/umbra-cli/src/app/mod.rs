@@ -13,10 +13,13 @@ mod community;
 pub use types::*;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Instant;
 
 use crossterm::event::{KeyCode, KeyEvent};
+use umbra_core::groups::{GroupRole, GroupService};
 use umbra_core::identity::{Identity, RecoveryPhrase};
+use umbra_core::storage::{Database, GroupMemberRecord, GroupRecord, SecureStore};
 
 use crate::db::Db;
 use crate::relay::RelayHandle;
@@ -55,6 +58,12 @@ pub struct App {
     pub welcome_tick: usize,
     /// Database handle for persistence (None if DB failed to open).
     pub db: Option<Db>,
+    /// umbra-core database backing core services (None if it failed to open).
+    pub core_db: Option<Arc<Database>>,
+    /// Passphrase vault whose secret keys `core.db` with the identity.
+    pub secure_store: SecureStore,
+    /// Group service for our identity (None until an identity is loaded).
+    pub group_service: Option<GroupService>,
     /// Search results from user discovery.
     pub search_results: Vec<SearchResult>,
     /// Selected index within search results.
@@ -131,7 +140,7 @@ pub struct App {
 impl App {
     /// Create a new app. If a database is provided and contains a stored
     /// identity, the app skips onboarding and goes directly to Chat.
-    pub fn new(db: Option<Db>) -> Self {
+    pub fn new(db: Option<Db>, core_db: Option<Arc<Database>>, secure_store: SecureStore) -> Self {
         let mut app = Self {
             screen: Screen::Welcome,
             input: String::new(),
@@ -148,6 +157,9 @@ impl App {
             tick_counter: 0,
             welcome_tick: 0,
             db,
+            core_db,
+            secure_store,
+            group_service: None,
             search_results: Vec::new(),
             selected_result: 0,
             searching: false,
@@ -232,7 +244,9 @@ impl App {
             })
             .collect();
 
-        // Load groups from DB
+        // Load groups from core.db
+        self.init_group_service(&identity);
+        self.migrate_legacy_groups();
         self.load_groups_from_db();
 
         // Load communities from DB
@@ -620,27 +634,90 @@ impl App {
             .collect()
     }
 
-    /// Load all groups from the local DB and populate in-memory list.
+    /// Move groups an older version kept in cli.db over to core.db.
+    ///
+    /// The old tables are dropped once every group is imported; if any
+    /// fails they're kept so the next start can try again.
+    fn migrate_legacy_groups(&mut self) {
+        let (db, svc) = match (&self.db, &self.group_service) {
+            (Some(db), Some(svc)) => (db, svc),
+            _ => return,
+        };
+        let legacy = match db.load_legacy_groups() {
+            Ok(groups) if !groups.is_empty() => groups,
+            _ => return,
+        };
+
+        let mut failed = 0;
+        for group in legacy {
+            let members: Vec<GroupMemberRecord> = group
+                .members
+                .iter()
+                .map(|m| {
+                    // The old schema had no roles; the creator ran the group
+                    let role = if m.did == group.created_by {
+                        GroupRole::Admin
+                    } else {
+                        GroupRole::Member
+                    };
+                    GroupMemberRecord {
+                        group_id: group.id.clone(),
+                        member_did: m.did.clone(),
+                        display_name: m.display_name.clone(),
+                        role: role.as_str().to_string(),
+                        joined_at: m.joined_at,
+                    }
+                })
+                .collect();
+            let record = GroupRecord {
+                id: group.id,
+                name: group.name,
+                description: group.description,
+                avatar: None,
+                created_by: group.created_by,
+                created_at: group.created_at,
+                updated_at: group.created_at,
+            };
+            if svc.import_group(&record, &members).is_err() {
+                failed += 1;
+            }
+        }
+
+        if failed == 0 {
+            let _ = db.drop_legacy_groups();
+        } else {
+            self.error_message = Some(format!(
+                "Could not migrate {} group{}",
+                failed,
+                if failed != 1 { "s" } else { "" }
+            ));
+        }
+    }
+
+    /// Load all groups from the group service and populate in-memory list.
     pub(super) fn load_groups_from_db(&mut self) {
-        let db = match &self.db {
-            Some(db) => db,
+        let svc = match &self.group_service {
+            Some(svc) => svc,
             None => return,
         };
 
-        let stored_groups = db.load_groups().unwrap_or_default();
+        let stored_groups = svc.list_groups().unwrap_or_default();
         self.groups = stored_groups
             .into_iter()
             .map(|g| {
-                let members = db
-                    .load_group_members(&g.id)
+                let members = svc
+                    .members(&g.id)
                     .unwrap_or_default()
                     .into_iter()
                     .map(|m| GroupMemberEntry {
-                        did: m.did,
+                        did: m.member_did,
                         display_name: m.display_name,
                     })
                     .collect();
-                let last_message_at = db.group_last_message_at(&g.id);
+                let last_message_at = self
+                    .db
+                    .as_ref()
+                    .and_then(|db| db.group_last_message_at(&g.id));
                 GroupEntry {
                     id: g.id,
                     name: g.name,
@@ -660,6 +737,26 @@ impl App {
 
     // ── Identity operations ────────────────────────────────────────────
 
+    /// Create the group service for a newly loaded identity.
+    fn init_group_service(&mut self, identity: &Identity) {
+        let core_db = match &self.core_db {
+            Some(db) => db.clone(),
+            None => return,
+        };
+        // First identity on this machine: core.db was opened without a key
+        if !core_db.is_encrypted() {
+            let encrypted = crate::db::core_db_key(identity, &self.secure_store)
+                .map(|key| core_db.encrypt(&key));
+            if let Some(Err(e)) = encrypted {
+                self.error_message = Some(format!("Could not encrypt core database: {e}"));
+            }
+        }
+        self.group_service = identity
+            .clone_for_service()
+            .ok()
+            .map(|cloned| GroupService::new(Arc::new(cloned), core_db));
+    }
+
     pub(super) fn create_identity(
         &mut self,
         name: &str,
@@ -672,6 +769,7 @@ impl App {
             Ok(cloned) => Some(cloned),
             Err(_) => None,
         };
+        self.init_group_service(&identity);
 
         Ok((identity, phrase))
     }
@@ -690,6 +788,7 @@ impl App {
             Ok(cloned) => Some(cloned),
             Err(_) => None,
         };
+        self.init_group_service(&identity);
 
         Ok(DashboardInfo {
             display_name: identity.profile().display_name.clone(),
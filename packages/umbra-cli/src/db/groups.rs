@@ -1,24 +1,26 @@
-//! Group DM persistence — groups, members, and group messages.
+//! Group DM persistence — decrypted group messages for display.
+//!
+//! Groups, members, and keys live in the umbra-core database and are
+//! managed by `GroupService`. Older versions kept groups and members
+//! here; they're read once so they can be moved over, then dropped.
 
 use rusqlite::params;
 use super::Db;
 
 // ── Types ──────────────────────────────────────────────────────────────
 
-/// A group stored in the database.
-#[allow(dead_code)]
+/// A group from an older version's `groups` table.
 pub struct StoredGroup {
     pub id: String,
     pub name: String,
     pub description: Option<String>,
     pub created_by: String,
     pub created_at: i64,
+    pub members: Vec<StoredGroupMember>,
 }
 
-/// A group member stored in the database.
-#[allow(dead_code)]
+/// A member from an older version's `group_members` table.
 pub struct StoredGroupMember {
-    pub group_id: String,
     pub did: String,
     pub display_name: Option<String>,
     pub joined_at: i64,
@@ -36,182 +38,9 @@ pub struct StoredGroupMessage {
     pub deleted: bool,
 }
 
-// ── Group CRUD ─────────────────────────────────────────────────────────
+// ── Group Messages ─────────────────────────────────────────────────────
 
 impl Db {
-    /// Save a new group.
-    pub fn save_group(
-        &self,
-        id: &str,
-        name: &str,
-        description: Option<&str>,
-        created_by: &str,
-        created_at: i64,
-    ) -> Result<(), String> {
-        self.conn
-            .execute(
-                "INSERT OR IGNORE INTO groups (id, name, description, created_by, created_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![id, name, description, created_by, created_at],
-            )
-            .map_err(|e| format!("Failed to save group: {e}"))?;
-        Ok(())
-    }
-
-    /// Load all groups.
-    pub fn load_groups(&self) -> Result<Vec<StoredGroup>, String> {
-        let mut stmt = self
-            .conn
-            .prepare(
-                "SELECT id, name, description, created_by, created_at
-                 FROM groups ORDER BY created_at DESC",
-            )
-            .map_err(|e| format!("Failed to prepare groups query: {e}"))?;
-
-        let groups = stmt
-            .query_map([], |row| {
-                Ok(StoredGroup {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    description: row.get(2)?,
-                    created_by: row.get(3)?,
-                    created_at: row.get(4)?,
-                })
-            })
-            .map_err(|e| format!("Failed to load groups: {e}"))?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        Ok(groups)
-    }
-
-    /// Load a single group by ID.
-    #[allow(dead_code)]
-    pub fn load_group(&self, id: &str) -> Result<Option<StoredGroup>, String> {
-        let result = self
-            .conn
-            .query_row(
-                "SELECT id, name, description, created_by, created_at
-                 FROM groups WHERE id = ?1",
-                params![id],
-                |row| {
-                    Ok(StoredGroup {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        description: row.get(2)?,
-                        created_by: row.get(3)?,
-                        created_at: row.get(4)?,
-                    })
-                },
-            );
-
-        match result {
-            Ok(group) => Ok(Some(group)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(format!("Failed to load group: {e}")),
-        }
-    }
-
-    /// Delete a group and its members/messages.
-    #[allow(dead_code)]
-    pub fn delete_group(&self, id: &str) -> Result<(), String> {
-        self.conn
-            .execute("DELETE FROM group_messages WHERE group_id = ?1", params![id])
-            .map_err(|e| format!("Failed to delete group messages: {e}"))?;
-        self.conn
-            .execute("DELETE FROM group_members WHERE group_id = ?1", params![id])
-            .map_err(|e| format!("Failed to delete group members: {e}"))?;
-        self.conn
-            .execute("DELETE FROM groups WHERE id = ?1", params![id])
-            .map_err(|e| format!("Failed to delete group: {e}"))?;
-        Ok(())
-    }
-
-    /// Update a group's name.
-    #[allow(dead_code)]
-    pub fn update_group_name(&self, id: &str, name: &str) -> Result<(), String> {
-        self.conn
-            .execute(
-                "UPDATE groups SET name = ?1 WHERE id = ?2",
-                params![name, id],
-            )
-            .map_err(|e| format!("Failed to update group name: {e}"))?;
-        Ok(())
-    }
-
-    // ── Group Members ──────────────────────────────────────────────────
-
-    /// Add a member to a group.
-    pub fn add_group_member(
-        &self,
-        group_id: &str,
-        did: &str,
-        display_name: Option<&str>,
-        joined_at: i64,
-    ) -> Result<(), String> {
-        self.conn
-            .execute(
-                "INSERT OR IGNORE INTO group_members (group_id, did, display_name, joined_at)
-                 VALUES (?1, ?2, ?3, ?4)",
-                params![group_id, did, display_name, joined_at],
-            )
-            .map_err(|e| format!("Failed to add group member: {e}"))?;
-        Ok(())
-    }
-
-    /// Remove a member from a group.
-    pub fn remove_group_member(&self, group_id: &str, did: &str) -> Result<(), String> {
-        self.conn
-            .execute(
-                "DELETE FROM group_members WHERE group_id = ?1 AND did = ?2",
-                params![group_id, did],
-            )
-            .map_err(|e| format!("Failed to remove group member: {e}"))?;
-        Ok(())
-    }
-
-    /// Load all members of a group.
-    pub fn load_group_members(&self, group_id: &str) -> Result<Vec<StoredGroupMember>, String> {
-        let mut stmt = self
-            .conn
-            .prepare(
-                "SELECT group_id, did, display_name, joined_at
-                 FROM group_members WHERE group_id = ?1
-                 ORDER BY joined_at ASC",
-            )
-            .map_err(|e| format!("Failed to prepare members query: {e}"))?;
-
-        let members = stmt
-            .query_map(params![group_id], |row| {
-                Ok(StoredGroupMember {
-                    group_id: row.get(0)?,
-                    did: row.get(1)?,
-                    display_name: row.get(2)?,
-                    joined_at: row.get(3)?,
-                })
-            })
-            .map_err(|e| format!("Failed to load group members: {e}"))?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        Ok(members)
-    }
-
-    /// Check if a DID is a member of a group.
-    pub fn is_group_member(&self, group_id: &str, did: &str) -> Result<bool, String> {
-        let count: i32 = self
-            .conn
-            .query_row(
-                "SELECT COUNT(*) FROM group_members WHERE group_id = ?1 AND did = ?2",
-                params![group_id, did],
-                |row| row.get(0),
-            )
-            .map_err(|e| format!("Failed to check membership: {e}"))?;
-        Ok(count > 0)
-    }
-
-    // ── Group Messages ─────────────────────────────────────────────────
-
     /// Save a group message.
     pub fn save_group_message(
         &self,
@@ -295,6 +124,14 @@ impl Db {
         Ok(())
     }
 
+    /// Delete all messages of a group.
+    pub fn delete_group_messages(&self, group_id: &str) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM group_messages WHERE group_id = ?1", params![group_id])
+            .map_err(|e| format!("Failed to delete group messages: {e}"))?;
+        Ok(())
+    }
+
     /// Get the timestamp of the most recent message in a group.
     pub fn group_last_message_at(&self, group_id: &str) -> Option<i64> {
         self.conn
@@ -306,4 +143,81 @@ impl Db {
             .ok()
             .flatten()
     }
+
+    // ── Legacy Groups ──────────────────────────────────────────────────
+
+    /// Load the groups and members older versions kept here.
+    ///
+    /// Returns an empty list once they've been dropped.
+    pub fn load_legacy_groups(&self) -> Result<Vec<StoredGroup>, String> {
+        let exists: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master
+                                WHERE type = 'table' AND name = 'groups')",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to check for legacy groups: {e}"))?;
+        if !exists {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, name, description, created_by, created_at
+                 FROM groups ORDER BY created_at ASC",
+            )
+            .map_err(|e| format!("Failed to prepare legacy groups query: {e}"))?;
+        let mut groups: Vec<StoredGroup> = stmt
+            .query_map([], |row| {
+                Ok(StoredGroup {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    description: row.get(2)?,
+                    created_by: row.get(3)?,
+                    created_at: row.get(4)?,
+                    members: Vec::new(),
+                })
+            })
+            .map_err(|e| format!("Failed to load legacy groups: {e}"))?
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read legacy group: {e}"))?;
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT did, display_name, joined_at
+                 FROM group_members WHERE group_id = ?1
+                 ORDER BY joined_at ASC",
+            )
+            .map_err(|e| format!("Failed to prepare legacy members query: {e}"))?;
+        for group in &mut groups {
+            group.members = stmt
+                .query_map(params![group.id], |row| {
+                    Ok(StoredGroupMember {
+                        did: row.get(0)?,
+                        display_name: row.get(1)?,
+                        joined_at: row.get(2)?,
+                    })
+                })
+                .map_err(|e| format!("Failed to load legacy group members: {e}"))?
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("Failed to read legacy group member: {e}"))?;
+        }
+
+        Ok(groups)
+    }
+
+    /// Drop the legacy `groups` and `group_members` tables.
+    pub fn drop_legacy_groups(&self) -> Result<(), String> {
+        self.conn
+            .execute_batch(
+                "DROP TABLE IF EXISTS group_members;
+                 DROP TABLE IF EXISTS groups;",
+            )
+            .map_err(|e| format!("Failed to drop legacy group tables: {e}"))?;
+        Ok(())
+    }
 }
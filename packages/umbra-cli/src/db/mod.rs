@@ -22,6 +22,8 @@ pub use community::{
 
 use std::path::{Path, PathBuf};
 use rusqlite::Connection;
+use umbra_core::identity::Identity;
+use umbra_core::storage::{derive_database_key, SecureStore, VAULT_FILE_NAME};
 
 // ── Types ──────────────────────────────────────────────────────────────
 
@@ -40,6 +42,33 @@ pub fn default_db_path() -> PathBuf {
         .join("cli.db")
 }
 
+/// Return the umbra-core database path: `~/.umbra/core.db`.
+///
+/// Holds the state managed by umbra-core services (groups, group keys).
+pub fn core_db_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".umbra")
+        .join("core.db")
+}
+
+/// Return the passphrase vault path: `~/.umbra/secure_store.vault`.
+pub fn vault_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".umbra")
+        .join(VAULT_FILE_NAME)
+}
+
+/// Derive the SQLCipher key for `core.db` from the identity's private keys.
+///
+/// The key is bound to the vault's passphrase-derived secret, so the
+/// recovery phrase stored in plaintext in `cli.db` is not enough to open
+/// `core.db` on its own.
+pub fn core_db_key(identity: &Identity, store: &SecureStore) -> Option<[u8; 32]> {
+    derive_database_key(store, identity).ok().map(|key| *key)
+}
+
 // ── Implementation ─────────────────────────────────────────────────────
 
 impl Db {
@@ -190,26 +219,10 @@ impl Db {
             )
             .map_err(|e| format!("Phase 1 migration failed: {e}"))?;
 
-        // Phase 2: Group DM tables
+        // Phase 2: Group DM messages (groups and members live in core.db)
         self.conn
             .execute_batch(
-                "CREATE TABLE IF NOT EXISTS groups (
-                    id TEXT PRIMARY KEY,
-                    name TEXT NOT NULL,
-                    description TEXT,
-                    created_by TEXT NOT NULL,
-                    created_at INTEGER NOT NULL
-                );
-
-                CREATE TABLE IF NOT EXISTS group_members (
-                    group_id TEXT NOT NULL,
-                    did TEXT NOT NULL,
-                    display_name TEXT,
-                    joined_at INTEGER NOT NULL,
-                    PRIMARY KEY (group_id, did)
-                );
-
-                CREATE TABLE IF NOT EXISTS group_messages (
+                "CREATE TABLE IF NOT EXISTS group_messages (
                     id TEXT PRIMARY KEY,
                     group_id TEXT NOT NULL,
                     sender_did TEXT NOT NULL,
@@ -6,6 +6,10 @@
 //! management. Implements the full onboarding flow: create/import identity,
 //! profile import from external platforms, username registration, and
 //! friend discovery opt-in.
+//!
+//! Group state lives in an SQLCipher `core.db` whose key is bound to a
+//! passphrase vault (`~/.umbra/secure_store.vault`), unlocked at startup
+//! from `UMBRA_PASSPHRASE` or a terminal prompt.
 
 mod api;
 mod app;
@@ -15,15 +19,31 @@ mod relay;
 mod tui;
 mod ui;
 
+use std::sync::Arc;
+
 use app::{App, AsyncAction, AsyncResult};
+use color_eyre::eyre::eyre;
 use event::{AppEvent, EventHandler};
 use relay::{RelayEvent, RelayHandle};
 use tokio::sync::mpsc;
+use umbra_core::crypto::SigningKeyPair;
+use umbra_core::identity::{Identity, RecoveryPhrase};
+use umbra_core::storage::{Database, DatabaseConfig, FileVault, SecureStore};
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
 
+    // core.db holds group keys, so never fall back to a plaintext database
+    if !Database::encryption_available() {
+        return Err(eyre!(
+            "This build has no SQLCipher support; refusing to store group keys unencrypted"
+        ));
+    }
+
+    // Unlock the vault before the TUI takes over the screen
+    let secure_store = unlock_secure_store()?;
+
     // Initialize terminal
     let mut terminal = tui::init()?;
 
@@ -36,8 +56,24 @@ async fn main() -> color_eyre::Result<()> {
         }
     };
 
+    // Open the umbra-core database holding group state, keyed to the
+    // stored identity (a new identity encrypts it in `init_group_service`)
+    let config = DatabaseConfig {
+        path: Some(db::core_db_path().to_string_lossy().into_owned()),
+        encryption_key: db
+            .as_ref()
+            .and_then(|db| stored_core_db_key(db, &secure_store)),
+    };
+    let core_db = match Database::open_with_config(config).await {
+        Ok(db) => Some(Arc::new(db)),
+        Err(e) => {
+            eprintln!("Warning: Could not open core database: {e}");
+            None
+        }
+    };
+
     // Create app state and event handler
-    let mut app = App::new(db);
+    let mut app = App::new(db, core_db, secure_store);
     let mut events = EventHandler::new(250);
 
     // Channel for async operation results
@@ -47,8 +83,8 @@ async fn main() -> color_eyre::Result<()> {
     let (relay_tx, mut relay_rx) = mpsc::unbounded_channel::<RelayEvent>();
 
     // Start relay connection if we have an identity
-    if let Some(ref did) = app.my_did {
-        app.relay_handle = Some(RelayHandle::connect(did.clone(), relay_tx.clone()));
+    if let Some(ref identity) = app.identity {
+        app.relay_handle = Some(connect_relay(identity, relay_tx.clone()));
     }
 
     // Main event loop
@@ -66,12 +102,10 @@ async fn main() -> color_eyre::Result<()> {
                         }
 
                         // Check if identity was just created/imported and relay not started
-                        if app.my_did.is_some() && app.relay_handle.is_none() {
-                            if let Some(ref did) = app.my_did {
-                                app.relay_handle = Some(RelayHandle::connect(
-                                    did.clone(),
-                                    relay_tx.clone(),
-                                ));
+                        if app.relay_handle.is_none() {
+                            if let Some(ref identity) = app.identity {
+                                app.relay_handle =
+                                    Some(connect_relay(identity, relay_tx.clone()));
                             }
                         }
                     }
@@ -106,6 +140,48 @@ async fn main() -> color_eyre::Result<()> {
     Ok(())
 }
 
+/// Unlock the passphrase vault, creating it on first run.
+///
+/// The passphrase comes from `UMBRA_PASSPHRASE` if set, otherwise it is
+/// read from the terminal. Its secret keys `core.db` together with the
+/// identity, so nothing on disk alone can open the group database.
+fn unlock_secure_store() -> color_eyre::Result<SecureStore> {
+    let path = db::vault_path();
+    let passphrase = match std::env::var("UMBRA_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) if path.exists() => tui::read_passphrase("Umbra passphrase: ")?,
+        Err(_) => {
+            let passphrase = tui::read_passphrase("Choose an Umbra passphrase: ")?;
+            if tui::read_passphrase("Repeat passphrase: ")? != passphrase {
+                return Err(eyre!("Passphrases did not match"));
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(eyre!("The passphrase can't be empty"));
+    }
+    let vault = FileVault::open(&path, &passphrase)
+        .map_err(|e| eyre!("Could not unlock {}: {e}", path.display()))?;
+    Ok(SecureStore::with_backend(vault))
+}
+
+/// Derive the `core.db` key from the identity stored in `cli.db`, if any.
+fn stored_core_db_key(db: &db::Db, store: &SecureStore) -> Option<[u8; 32]> {
+    let stored = db.load_identity().ok()??;
+    let phrase = RecoveryPhrase::from_phrase(&stored.recovery_phrase).ok()?;
+    let identity = Identity::from_recovery_phrase(&phrase, stored.display_name).ok()?;
+    db::core_db_key(&identity, store)
+}
+
+/// Open the relay connection for an identity, signing its auth challenge
+/// with the identity's Ed25519 key.
+fn connect_relay(identity: &Identity, tx: mpsc::UnboundedSender<RelayEvent>) -> RelayHandle {
+    let signing_key = SigningKeyPair::from_bytes(&identity.keypair().signing.secret_bytes())
+        .expect("32-byte Ed25519 secret is always valid");
+    RelayHandle::connect(identity.did_string(), signing_key, tx)
+}
+
 /// Spawn an async action on the tokio runtime and send the result back.
 fn spawn_async_action(action: AsyncAction, tx: mpsc::UnboundedSender<AsyncResult>) {
     tokio::spawn(async move {
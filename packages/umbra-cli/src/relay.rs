@@ -1,25 +1,45 @@
 //! WebSocket relay client for real-time messaging.
 //!
-//! Maintains a persistent WebSocket connection to the Umbra relay server.
-//! Handles registration, message sending/receiving, offline message fetching,
-//! and automatic reconnection with exponential backoff.
+//! Maintains a persistent WebSocket connection to an Umbra relay server.
+//! Handles registration (including the relay's auth challenge), message
+//! sending/receiving, offline message fetching, and automatic reconnection.
+//!
+//! The relay is picked from the seed relay's directory by measured latency
+//! and load. When the connection drops the client fails over to the next
+//! best relay, backing off once every relay has failed, and registers its
+//! DID again wherever it lands.
+
+use std::time::Instant;
 
+use futures_util::future::join_all;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, timeout, Duration};
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::Message as WsMessage;
-
-/// WebSocket URL for the relay server.
+use umbra_core::crypto::SigningKeyPair;
+use umbra_core::network::relay_client::{
+    parse_relay_directory, relay_http_url, sign_auth_challenge, RelayCandidate,
+    RelayClientMessage, RelayDirectoryEntry, RelayInvite, RelaySelector, RELAY_DIRECTORY_PATH,
+    RELAY_RECONNECT_MAX_DELAY,
+};
+
+/// WebSocket URL of the seed relay, whose directory lists the others.
 const RELAY_WS_URL: &str = "wss://relay.umbra.chat/ws";
 
-/// Maximum reconnect delay (30 seconds).
-const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// How long to wait for the relay directory.
+const DIRECTORY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for a relay to answer a latency probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
 
 /// Ping interval to keep the connection alive.
 const PING_INTERVAL: Duration = Duration::from_secs(30);
 
+/// How long to wait for the relay to challenge and confirm registration.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
 // ── Events sent from relay to the app ───────────────────────────────────
 
 /// Events the relay client sends to the application.
@@ -59,6 +79,8 @@ pub enum RelayEvent {
 /// A single message from the relay.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RelayMessage {
+    /// Relay-assigned ID, used to acknowledge offline messages.
+    pub id: String,
     pub from_did: String,
     pub payload: String,
     pub timestamp: Option<u64>,
@@ -73,6 +95,8 @@ enum RelayCommand {
     Send { to_did: String, payload: String },
     /// Request offline messages from the relay.
     FetchOffline,
+    /// Acknowledge processed offline messages so the relay deletes them.
+    AckOffline { ids: Vec<String> },
     /// Publish a community invite to the relay.
     PublishInvite {
         code: String,
@@ -101,6 +125,13 @@ struct RegisterMsg {
     did: String,
 }
 
+#[derive(Serialize)]
+struct AuthenticateMsg {
+    #[serde(rename = "type")]
+    msg_type: String,
+    signature: String,
+}
+
 #[derive(Serialize)]
 struct SendMsg {
     #[serde(rename = "type")]
@@ -116,36 +147,24 @@ struct FetchOfflineMsg {
 }
 
 #[derive(Serialize)]
-struct PingMsg {
+struct AckOfflineMsg {
     #[serde(rename = "type")]
     msg_type: String,
+    ids: Vec<String>,
 }
 
 #[derive(Serialize)]
-#[allow(dead_code)]
-struct AckMsg {
+struct PingMsg {
     #[serde(rename = "type")]
     msg_type: String,
-    id: String,
 }
 
 #[derive(Serialize)]
-struct PublishInviteMsg {
+#[allow(dead_code)]
+struct AckMsg {
     #[serde(rename = "type")]
     msg_type: String,
-    code: String,
-    community_id: String,
-    community_name: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    community_description: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    community_icon: Option<String>,
-    member_count: u32,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    max_uses: Option<i32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    expires_at: Option<i64>,
-    invite_payload: String,
+    id: String,
 }
 
 #[derive(Serialize)]
@@ -179,6 +198,12 @@ struct IncomingMsg {
     id: Option<String>,
     #[serde(default)]
     message: Option<String>,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(default)]
+    to_did: Option<String>,
+    #[serde(default)]
+    retry_after_ms: Option<u64>,
     // Invite-related fields
     #[serde(default)]
     code: Option<String>,
@@ -196,6 +221,8 @@ struct IncomingMsg {
 
 #[derive(Deserialize, Clone)]
 struct OfflineMsg {
+    #[serde(default)]
+    id: String,
     from_did: String,
     payload: String,
     timestamp: Option<u64>,
@@ -213,13 +240,17 @@ impl RelayHandle {
     ///
     /// Returns a handle for sending commands and spawns a tokio task
     /// that maintains the connection, sending events via `event_tx`.
+    ///
+    /// `signing_key` is the identity's Ed25519 key, used to answer the
+    /// relay's registration challenge.
     pub fn connect(
         did: String,
+        signing_key: SigningKeyPair,
         event_tx: mpsc::UnboundedSender<RelayEvent>,
     ) -> Self {
         let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
 
-        tokio::spawn(relay_task(did, event_tx, cmd_rx));
+        tokio::spawn(relay_task(did, signing_key, event_tx, cmd_rx));
 
         Self { cmd_tx }
     }
@@ -235,6 +266,12 @@ impl RelayHandle {
         let _ = self.cmd_tx.send(RelayCommand::FetchOffline);
     }
 
+    /// Acknowledge offline messages once they've been processed.
+    /// Until then the relay keeps them and redelivers on the next fetch.
+    pub fn ack_offline(&self, ids: Vec<String>) {
+        let _ = self.cmd_tx.send(RelayCommand::AckOffline { ids });
+    }
+
     /// Publish a community invite to the relay for resolution.
     pub fn publish_invite(
         &self,
@@ -281,13 +318,33 @@ impl RelayHandle {
 
 async fn relay_task(
     did: String,
+    signing_key: SigningKeyPair,
     event_tx: mpsc::UnboundedSender<RelayEvent>,
     mut cmd_rx: mpsc::UnboundedReceiver<RelayCommand>,
 ) {
-    let mut reconnect_delay = Duration::from_secs(2);
+    let mut selector = RelaySelector::new(discover_relays().await);
 
     loop {
-        match connect_and_run(&did, &event_tx, &mut cmd_rx).await {
+        // Every relay failed: look for relays again before the next round
+        if selector.exhausted() {
+            selector.refresh(discover_relays().await);
+        }
+        let Some((relay, delay)) = selector.next_relay() else {
+            sleep(RELAY_RECONNECT_MAX_DELAY).await;
+            continue;
+        };
+        sleep(delay).await;
+
+        match connect_and_run(
+            relay.url(),
+            &did,
+            &signing_key,
+            &event_tx,
+            &mut cmd_rx,
+            &mut selector,
+        )
+        .await
+        {
             Ok(should_shutdown) => {
                 if should_shutdown {
                     return;
@@ -299,21 +356,73 @@ async fn relay_task(
         }
 
         let _ = event_tx.send(RelayEvent::Disconnected);
-
-        // Exponential backoff reconnect
-        sleep(reconnect_delay).await;
-        reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
     }
 }
 
-/// Connect to the relay, register, and run the message loop.
+/// Fetch the seed relay's directory and measure latency to each relay.
+///
+/// Falls back to the seed relay alone if the directory can't be fetched.
+async fn discover_relays() -> Vec<RelayCandidate> {
+    let client = reqwest::Client::new();
+
+    let url = format!("{}{RELAY_DIRECTORY_PATH}", relay_http_url(RELAY_WS_URL));
+    let directory = async {
+        let response = client
+            .get(&url)
+            .timeout(DIRECTORY_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {e}"))?;
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read directory: {e}"))?;
+        parse_relay_directory(&body, RELAY_WS_URL).map_err(|e| e.to_string())
+    };
+    let entries = directory.await.unwrap_or_else(|_| {
+        vec![RelayDirectoryEntry {
+            relay_id: None,
+            relay_url: Some(RELAY_WS_URL.to_string()),
+            region: String::new(),
+            location: String::new(),
+            load: None,
+        }]
+    });
+
+    join_all(entries.into_iter().map(|relay| {
+        let client = client.clone();
+        async move {
+            let latency_ms =
+                probe_latency(&client, relay.relay_url.as_deref().unwrap_or_default()).await;
+            RelayCandidate { relay, latency_ms }
+        }
+    }))
+    .await
+}
+
+/// Time a request to the relay's health endpoint.
+/// Returns `None` if the relay doesn't answer in time.
+async fn probe_latency(client: &reqwest::Client, ws_url: &str) -> Option<u64> {
+    let url = format!("{}/health", relay_http_url(ws_url));
+    let start = Instant::now();
+    let response = client.get(&url).timeout(PROBE_TIMEOUT).send().await.ok()?;
+    response
+        .status()
+        .is_success()
+        .then(|| start.elapsed().as_millis() as u64)
+}
+
+/// Connect to the relay at `relay_url`, register, and run the message loop.
 /// Returns Ok(true) if shutdown was requested, Ok(false) for reconnect.
 async fn connect_and_run(
+    relay_url: &str,
     did: &str,
+    signing_key: &SigningKeyPair,
     event_tx: &mpsc::UnboundedSender<RelayEvent>,
     cmd_rx: &mut mpsc::UnboundedReceiver<RelayCommand>,
+    selector: &mut RelaySelector,
 ) -> Result<bool, String> {
-    let (ws_stream, _) = connect_async(RELAY_WS_URL)
+    let (ws_stream, _) = connect_async(relay_url)
         .await
         .map_err(|e| format!("WebSocket connect failed: {e}"))?;
 
@@ -331,6 +440,12 @@ async fn connect_and_run(
         .await
         .map_err(|e| format!("Send register failed: {e}"))?;
 
+    // Answer the relay's challenge before doing anything else
+    timeout(AUTH_TIMEOUT, authenticate(&mut write, &mut read, signing_key))
+        .await
+        .map_err(|_| "Timed out waiting for relay registration".to_string())??;
+    selector.connected();
+
     // Fetch offline messages
     let fetch = serde_json::to_string(&FetchOfflineMsg {
         msg_type: "fetch_offline".into(),
@@ -391,12 +506,23 @@ async fn connect_and_run(
                             return Ok(false);
                         }
                     }
+                    Some(RelayCommand::AckOffline { ids }) => {
+                        let msg = serde_json::to_string(&AckOfflineMsg {
+                            msg_type: "ack_offline".into(),
+                            ids,
+                        })
+                        .unwrap_or_default();
+
+                        if write.send(WsMessage::Text(msg)).await.is_err() {
+                            return Ok(false);
+                        }
+                    }
                     Some(RelayCommand::PublishInvite {
                         code, community_id, community_name, community_description,
                         member_count, max_uses, expires_at, invite_payload,
                     }) => {
-                        let msg = serde_json::to_string(&PublishInviteMsg {
-                            msg_type: "publish_invite".into(),
+                        // Relays only accept invites signed by the publisher
+                        let invite = RelayInvite {
                             code,
                             community_id,
                             community_name,
@@ -406,7 +532,12 @@ async fn connect_and_run(
                             max_uses,
                             expires_at,
                             invite_payload,
-                        })
+                        };
+                        let msg = serde_json::to_string(&RelayClientMessage::publish_invite(
+                            signing_key,
+                            did,
+                            invite,
+                        ))
                         .unwrap_or_default();
 
                         if write.send(WsMessage::Text(msg)).await.is_err() {
@@ -462,6 +593,56 @@ async fn connect_and_run(
     }
 }
 
+/// Wait for the relay's auth challenge, sign it, and wait for `registered`.
+async fn authenticate<W, R>(
+    write: &mut W,
+    read: &mut R,
+    signing_key: &SigningKeyPair,
+) -> Result<(), String>
+where
+    W: SinkExt<WsMessage> + Unpin,
+    W::Error: std::fmt::Display,
+    R: StreamExt<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    while let Some(msg) = read.next().await {
+        let text = match msg.map_err(|e| format!("WebSocket read error: {e}"))? {
+            WsMessage::Text(text) => text,
+            WsMessage::Close(_) => break,
+            _ => continue,
+        };
+
+        let Ok(msg) = serde_json::from_str::<IncomingMsg>(&text) else {
+            continue;
+        };
+
+        match msg.msg_type.as_str() {
+            "auth_challenge" => {
+                let nonce = msg.nonce.ok_or("Auth challenge without nonce")?;
+                let reply = serde_json::to_string(&AuthenticateMsg {
+                    msg_type: "authenticate".into(),
+                    signature: sign_auth_challenge(signing_key, &nonce),
+                })
+                .map_err(|e| format!("Serialize error: {e}"))?;
+
+                write
+                    .send(WsMessage::Text(reply))
+                    .await
+                    .map_err(|e| format!("Send authenticate failed: {e}"))?;
+            }
+            "registered" => return Ok(()),
+            "error" => {
+                return Err(format!(
+                    "Relay registration failed: {}",
+                    msg.message.unwrap_or_default()
+                ));
+            }
+            _ => {} // Nothing else is sent before registration
+        }
+    }
+
+    Err("Connection closed during registration".into())
+}
+
 /// Parse and dispatch an incoming relay message.
 fn handle_incoming_message(
     text: &str,
@@ -487,6 +668,7 @@ fn handle_incoming_message(
                 let relay_msgs: Vec<RelayMessage> = messages
                     .into_iter()
                     .map(|m| RelayMessage {
+                        id: m.id,
                         from_did: m.from_did,
                         payload: m.payload,
                         timestamp: m.timestamp,
@@ -517,8 +699,18 @@ fn handle_incoming_message(
         "ack" | "pong" | "registered" => {
             // Expected responses — no action needed
         }
+        "queue_full" => {
+            let to_did = msg.to_did.unwrap_or_default();
+            let _ = event_tx.send(RelayEvent::Error(format!(
+                "Offline queue full for {to_did}, message not delivered"
+            )));
+        }
         "error" => {
             if let Some(message) = msg.message {
+                let message = match msg.retry_after_ms {
+                    Some(ms) => format!("{message} (retry in {:.1}s)", ms as f64 / 1000.0),
+                    None => message,
+                };
                 let _ = event_tx.send(RelayEvent::Error(message));
             }
         }
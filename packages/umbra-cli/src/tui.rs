@@ -1,12 +1,14 @@
 //! Terminal initialization, restoration, and panic handling.
 //!
 //! Manages raw mode, alternate screen, and ensures the terminal
-//! is always restored even on panic or unexpected exit.
+//! is always restored even on panic or unexpected exit. Also reads the
+//! vault passphrase before the TUI takes over the screen.
 
-use std::io::{self, stdout, Stdout};
+use std::io::{self, stdout, Stdout, Write};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
 use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -39,6 +41,44 @@ pub fn restore() -> Result<()> {
     Ok(())
 }
 
+/// Prompt for a passphrase on the normal screen without echoing it.
+///
+/// Called before [`init`]. Esc or Ctrl-C cancels the prompt.
+pub fn read_passphrase(prompt: &str) -> Result<String> {
+    eprint!("{prompt}");
+    io::stderr().flush()?;
+    enable_raw_mode()?;
+    let passphrase = read_hidden_line();
+    disable_raw_mode()?;
+    eprintln!();
+    passphrase
+}
+
+/// Collect key presses until Enter, without echoing them.
+fn read_hidden_line() -> Result<String> {
+    let mut line = String::new();
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(line),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Esc => return Err(eyre!("Passphrase entry cancelled")),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Err(eyre!("Passphrase entry cancelled"));
+            }
+            KeyCode::Char(c) => line.push(c),
+            _ => {}
+        }
+    }
+}
+
 /// Install a panic hook that restores the terminal before printing
 /// the panic message. Without this, a panic would leave the terminal
 /// in raw mode with the alternate screen active.
@@ -0,0 +1,890 @@
+//! # Authorization
+//!
+//! Resolving what a member may do, and the guards every mutating
+//! `CommunityService` method checks its actor against. Log-backed
+//! communities replay their ops through the same checks (see
+//! [`Access`], [`Rank`]).
+//!
+//! ## Resolution
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                     EFFECTIVE PERMISSIONS                               │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  1. Not a member → NotMember; owner → everything                       │
+//! │  2. Community permissions = OR of the member's roles                   │
+//! │  3. In a channel, apply its overrides (Permissions::                   │
+//! │     compute_channel_permissions); without ViewChannels, nothing else   │
+//! │  4. Active timeouts take away what they restrict (mute: speaking;      │
+//! │     restrict: also attachments, invites, nickname, voice)              │
+//! │                                                                         │
+//! │  Acting on a member or role also needs a higher highest role           │
+//! │  (the owner outranks everyone and can't be acted on).                  │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+
+use super::permissions::{Permission, Permissions};
+use crate::error::{Error, Result};
+use crate::storage::{CommunityChannelRecord, CommunityRoleRecord};
+
+/// What a mute takes away
+const MUTED: &[Permission] = &[
+    Permission::SendMessages,
+    Permission::SendThreadMessages,
+    Permission::CreateThreads,
+    Permission::AddReactions,
+    Permission::VoiceSpeak,
+    Permission::VoiceStream,
+];
+
+/// What a restriction takes away on top of a mute
+const RESTRICTED: &[Permission] = &[
+    Permission::EmbedLinks,
+    Permission::AttachFiles,
+    Permission::UploadFiles,
+    Permission::CreateInvites,
+    Permission::ChangeNickname,
+    Permission::VoiceConnect,
+];
+
+/// A member's permissions and what their timeouts withhold from them
+pub(crate) struct Access {
+    granted: Permissions,
+    withheld: Permissions,
+}
+
+impl Access {
+    /// Access granted by a member's roles (the owner has every permission).
+    pub(crate) fn from_roles(is_owner: bool, roles: impl IntoIterator<Item = Permissions>) -> Self {
+        let granted = if is_owner {
+            Permissions::ALL
+        } else {
+            roles
+                .into_iter()
+                .fold(Permissions::NONE, |acc, role| acc.merge(&role))
+        };
+        Self {
+            granted,
+            withheld: Permissions::NONE,
+        }
+    }
+
+    /// Permissions left after timeouts (administrators can't be timed out)
+    fn effective(&self) -> Permissions {
+        if self.granted.has(Permission::Administrator) {
+            return self.granted;
+        }
+        Permissions::from_bits(self.granted.bits() & !self.withheld.bits())
+    }
+
+    pub(crate) fn check(&self, required: Permission) -> Result<()> {
+        if self.effective().has(required) {
+            Ok(())
+        } else if self.granted.has(required) {
+            Err(Error::MemberTimedOut(
+                "You are currently timed out in this community".to_string(),
+            ))
+        } else {
+            Err(Error::PermissionDenied { required })
+        }
+    }
+
+    /// Roles can only grant permissions their editor holds. Granting
+    /// anything else takes Administrator, which is what a refusal names.
+    pub(crate) fn check_grantable(&self, permissions: &Permissions) -> Result<()> {
+        let own = self.effective();
+        if !own.has(Permission::Administrator) && permissions.bits() & !own.bits() != 0 {
+            return Err(Error::PermissionDenied {
+                required: Permission::Administrator,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Where a member stands in the role hierarchy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Rank {
+    /// Position of the member's highest role (`i32::MIN` without one)
+    Member(i32),
+    /// The owner, above everyone
+    Owner,
+}
+
+impl Rank {
+    /// Rank of a member holding roles at `positions`.
+    pub(crate) fn of(positions: impl IntoIterator<Item = i32>) -> Self {
+        Rank::Member(positions.into_iter().max().unwrap_or(i32::MIN))
+    }
+
+    /// Require that we may exercise `required` on a member ranked `target`;
+    /// it only reaches members below us.
+    pub(crate) fn check_above(self, target: Rank, required: Permission) -> Result<()> {
+        if target == Rank::Owner {
+            return Err(Error::CannotModifyOwner);
+        }
+        if self <= target {
+            return Err(Error::PermissionDenied { required });
+        }
+        Ok(())
+    }
+
+    /// Require that a role at `position` sits below us, as ManageRoles
+    /// only reaches those.
+    pub(crate) fn check_position_below(self, position: i32) -> Result<()> {
+        if self <= Rank::Member(position) {
+            return Err(Error::PermissionDenied {
+                required: Permission::ManageRoles,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl super::CommunityService {
+    // ── Resolution ──────────────────────────────────────────────────────
+
+    /// A member's community-wide permissions.
+    pub fn member_permissions(&self, community_id: &str, member_did: &str) -> Result<Permissions> {
+        Ok(self.access(community_id, member_did, None)?.effective())
+    }
+
+    /// A member's permissions in a channel, with its overrides applied.
+    pub fn channel_permissions(&self, channel_id: &str, member_did: &str) -> Result<Permissions> {
+        let channel = self.get_channel(channel_id)?;
+        Ok(self
+            .access(&channel.community_id, member_did, Some(channel_id))?
+            .effective())
+    }
+
+    /// The channels `member_did` can view across every community they're in.
+    pub fn visible_channels(&self, member_did: &str) -> Result<Vec<String>> {
+        let mut visible = Vec::new();
+        for community in self.db().get_communities_for_member(member_did)? {
+            for channel in self.db().get_community_channels(&community.id)? {
+                if self
+                    .access(&community.id, member_did, Some(&channel.id))?
+                    .effective()
+                    .has(Permission::ViewChannels)
+                {
+                    visible.push(channel.id);
+                }
+            }
+        }
+        Ok(visible)
+    }
+
+    fn access(
+        &self,
+        community_id: &str,
+        member_did: &str,
+        channel_id: Option<&str>,
+    ) -> Result<Access> {
+        let community = self.get_community(community_id)?;
+        if community.owner_did == member_did {
+            return Ok(Access::from_roles(true, []));
+        }
+        if self
+            .db()
+            .get_community_member(community_id, member_did)?
+            .is_none()
+        {
+            return Err(Error::NotMember);
+        }
+
+        let roles = self
+            .db()
+            .get_member_community_roles(community_id, member_did)?;
+        let mut granted = Access::from_roles(
+            false,
+            roles
+                .iter()
+                .map(|role| Permissions::from_string(&role.permissions_bitfield)),
+        )
+        .granted;
+
+        if let Some(channel_id) = channel_id {
+            let mut role_overrides = Vec::new();
+            let mut member_override = None;
+            for o in self.db().get_channel_permission_overrides(channel_id)? {
+                let pair = (
+                    Permissions::from_string(&o.allow_bitfield),
+                    Permissions::from_string(&o.deny_bitfield),
+                );
+                match o.target_type.as_str() {
+                    "role" if roles.iter().any(|r| r.id == o.target_id) => {
+                        role_overrides.push(pair)
+                    }
+                    "member" if o.target_id == member_did => member_override = Some(pair),
+                    _ => {}
+                }
+            }
+            granted = Permissions::compute_channel_permissions(
+                &granted,
+                &role_overrides,
+                member_override.as_ref(),
+            );
+            // A channel you can't see grants nothing
+            if !granted.has(Permission::ViewChannels) {
+                granted = Permissions::NONE;
+            }
+        }
+
+        let now = crate::time::now_timestamp();
+        let mut withheld = Permissions::NONE;
+        for timeout in self
+            .db()
+            .get_active_timeouts(community_id, member_did, now)?
+        {
+            let restricted: &[Permission] = match timeout.timeout_type.as_str() {
+                "restrict" => RESTRICTED,
+                _ => &[],
+            };
+            for perm in MUTED.iter().chain(restricted) {
+                withheld.add(*perm);
+            }
+        }
+
+        Ok(Access { granted, withheld })
+    }
+
+    // ── Guards ──────────────────────────────────────────────────────────
+
+    /// Require that a community isn't log-backed. The spaces, channels,
+    /// roles, members, invites and messages of a log-backed community
+    /// only change through signed ops (see [`submit_op`](Self::submit_op)).
+    pub(crate) fn require_unsigned(&self, community_id: &str) -> Result<()> {
+        if self.is_log_backed(community_id)? {
+            return Err(Error::InvalidCommunityOperation(
+                "This community only changes through signed ops".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Require a community-wide permission of `actor_did`.
+    pub(crate) fn require_permission(
+        &self,
+        community_id: &str,
+        actor_did: &str,
+        required: Permission,
+    ) -> Result<()> {
+        self.access(community_id, actor_did, None)?.check(required)
+    }
+
+    /// Require a permission of `actor_did` in a channel, returning the channel.
+    pub(crate) fn require_channel_permission(
+        &self,
+        channel_id: &str,
+        actor_did: &str,
+        required: Permission,
+    ) -> Result<CommunityChannelRecord> {
+        let channel = self.get_channel(channel_id)?;
+        self.access(&channel.community_id, actor_did, Some(channel_id))?
+            .check(required)?;
+        Ok(channel)
+    }
+
+    /// Require that `actor_did` outranks `target_did`, so the `required`
+    /// permission they hold reaches them.
+    ///
+    /// Members rank by their highest role; the owner outranks everyone
+    /// and can't be acted on.
+    pub(crate) fn require_above(
+        &self,
+        community_id: &str,
+        actor_did: &str,
+        target_did: &str,
+        required: Permission,
+    ) -> Result<()> {
+        self.rank(community_id, actor_did)?
+            .check_above(self.rank(community_id, target_did)?, required)
+    }
+
+    /// Require that `actor_did` may manage `role`: it must sit below the
+    /// actor's highest role, and the owner role can't be managed at all.
+    pub(crate) fn require_role_below(
+        &self,
+        actor_did: &str,
+        role: &CommunityRoleRecord,
+    ) -> Result<()> {
+        if let Some(owner_role) = self.db().get_owner_role(&role.community_id)? {
+            if owner_role.id == role.id {
+                return Err(Error::CannotModifyOwner);
+            }
+        }
+        self.require_position_below(&role.community_id, actor_did, role.position)
+    }
+
+    /// Require that a role at `position` would sit below `actor_did`'s
+    /// highest role.
+    pub(crate) fn require_position_below(
+        &self,
+        community_id: &str,
+        actor_did: &str,
+        position: i32,
+    ) -> Result<()> {
+        self.rank(community_id, actor_did)?
+            .check_position_below(position)
+    }
+
+    /// Require that `actor_did` holds every permission in `permissions`
+    /// (administrators can grant anything).
+    pub(crate) fn require_grantable(
+        &self,
+        community_id: &str,
+        actor_did: &str,
+        permissions: &Permissions,
+    ) -> Result<()> {
+        self.access(community_id, actor_did, None)?
+            .check_grantable(permissions)
+    }
+
+    /// Where a member stands in the role hierarchy.
+    fn rank(&self, community_id: &str, member_did: &str) -> Result<Rank> {
+        if self.get_community(community_id)?.owner_did == member_did {
+            return Ok(Rank::Owner);
+        }
+        Ok(Rank::of(
+            self.db()
+                .get_member_community_roles(community_id, member_did)?
+                .iter()
+                .map(|role| role.position),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::service::CommunityCreateResult;
+    use crate::community::CommunityService;
+    use crate::storage::Database;
+    use std::sync::Arc;
+
+    const OWNER: &str = "did:key:owner";
+    const ADMIN: &str = "did:key:admin";
+    const MOD: &str = "did:key:mod";
+    const MEMBER: &str = "did:key:member";
+    const OUTSIDER: &str = "did:key:outsider";
+
+    /// A community with one member per preset role, and an outsider.
+    async fn community() -> (CommunityService, CommunityCreateResult) {
+        let svc = CommunityService::new(Arc::new(Database::open(None).await.unwrap()));
+        let created = svc
+            .create_community("Umbra", None, OWNER, None, None)
+            .unwrap();
+        let cid = &created.community_id;
+        for did in [ADMIN, MOD, MEMBER] {
+            svc.join_community(cid, did, None).unwrap();
+        }
+        svc.assign_role(cid, ADMIN, &created.role_ids.admin, OWNER)
+            .unwrap();
+        svc.assign_role(cid, MOD, &created.role_ids.moderator, OWNER)
+            .unwrap();
+        (svc, created)
+    }
+
+    fn denied<T>(result: Result<T>, permission: Permission) -> bool {
+        matches!(result, Err(Error::PermissionDenied { required }) if required == permission)
+    }
+
+    fn timed_out<T>(result: Result<T>) -> bool {
+        matches!(result, Err(Error::MemberTimedOut(_)))
+    }
+
+    /// Bitfield holding just `permissions`, for channel overrides.
+    fn bits(permissions: &[Permission]) -> String {
+        let mut bits = Permissions::NONE;
+        for permission in permissions {
+            bits.add(*permission);
+        }
+        bits.to_string_repr()
+    }
+
+    #[tokio::test]
+    async fn test_role_permission_matrix() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let create_channel = |actor: &str| {
+            svc.create_channel(cid, &created.space_id, "new", "text", None, 1, actor, None)
+        };
+
+        assert!(create_channel(OWNER).is_ok());
+        assert!(create_channel(ADMIN).is_ok());
+        assert!(denied(create_channel(MOD), Permission::ManageChannels));
+        assert!(denied(create_channel(MEMBER), Permission::ManageChannels));
+        assert!(matches!(create_channel(OUTSIDER), Err(Error::NotMember)));
+
+        let create_invite = |actor: &str| svc.create_invite(cid, actor, None, None);
+        assert!(create_invite(ADMIN).is_ok());
+        assert!(denied(create_invite(MOD), Permission::CreateInvites));
+        assert!(denied(create_invite(MEMBER), Permission::CreateInvites));
+
+        assert!(svc
+            .member_permissions(cid, OWNER)
+            .unwrap()
+            .has(Permission::Administrator));
+        assert!(!svc
+            .member_permissions(cid, ADMIN)
+            .unwrap()
+            .has(Permission::Administrator));
+        assert!(matches!(
+            svc.member_permissions(cid, OUTSIDER),
+            Err(Error::NotMember)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_member_hierarchy() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+
+        // (target, actor)
+        assert!(denied(
+            svc.kick_member(cid, MOD, MEMBER),
+            Permission::KickMembers
+        ));
+        assert!(denied(
+            svc.kick_member(cid, ADMIN, MOD),
+            Permission::KickMembers
+        ));
+        assert!(matches!(
+            svc.kick_member(cid, OWNER, ADMIN),
+            Err(Error::CannotModifyOwner)
+        ));
+        assert!(denied(
+            svc.ban_member(cid, MEMBER, None, None, None, MOD),
+            Permission::BanMembers
+        ));
+        svc.kick_member(cid, MEMBER, MOD).unwrap();
+        svc.kick_member(cid, MOD, ADMIN).unwrap();
+        svc.kick_member(cid, ADMIN, OWNER).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_role_hierarchy() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let roles = &created.role_ids;
+
+        svc.assign_role(cid, MEMBER, &roles.moderator, ADMIN)
+            .unwrap();
+        assert!(denied(
+            svc.assign_role(cid, MEMBER, &roles.admin, ADMIN),
+            Permission::ManageRoles
+        ));
+        assert!(matches!(
+            svc.assign_role(cid, MEMBER, &roles.owner, ADMIN),
+            Err(Error::CannotModifyOwner)
+        ));
+        assert!(denied(
+            svc.assign_role(cid, MEMBER, &roles.moderator, MOD),
+            Permission::ManageRoles
+        ));
+
+        let create_role = |position: i32, permissions: Permissions| {
+            svc.create_custom_role(
+                cid,
+                "custom",
+                None,
+                position,
+                false,
+                false,
+                &permissions.to_string_repr(),
+                ADMIN,
+            )
+        };
+        assert!(create_role(10, Permissions::moderator()).is_ok());
+        assert!(denied(
+            create_role(100, Permissions::moderator()),
+            Permission::ManageRoles
+        ));
+        let mut escalated = Permissions::NONE;
+        escalated.add(Permission::Administrator);
+        assert!(denied(
+            create_role(10, escalated),
+            Permission::Administrator
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_channel_overrides() {
+        let (svc, created) = community().await;
+        let channel = &created.general_channel_id;
+        let send = |did: &str| svc.send_message(channel, did, "hi", None, None, None, None);
+        let mut send_messages = Permissions::NONE;
+        send_messages.add(Permission::SendMessages);
+        let send_messages = send_messages.to_string_repr();
+
+        assert!(send(MEMBER).is_ok());
+        svc.set_channel_override(
+            channel,
+            "role",
+            &created.role_ids.member,
+            "0",
+            &send_messages,
+            ADMIN,
+        )
+        .unwrap();
+        assert!(denied(send(MEMBER), Permission::SendMessages));
+
+        // A member override beats the role override
+        svc.set_channel_override(channel, "member", MEMBER, &send_messages, "0", ADMIN)
+            .unwrap();
+        assert!(send(MEMBER).is_ok());
+
+        // Without ViewChannels, nothing else applies
+        let mut view = Permissions::NONE;
+        view.add(Permission::ViewChannels);
+        svc.set_channel_override(
+            channel,
+            "member",
+            MEMBER,
+            "0",
+            &view.to_string_repr(),
+            ADMIN,
+        )
+        .unwrap();
+        assert_eq!(
+            svc.channel_permissions(channel, MEMBER).unwrap().bits(),
+            Permissions::NONE.bits()
+        );
+        assert!(svc
+            .channel_permissions(channel, OWNER)
+            .unwrap()
+            .has(Permission::SendMessages));
+    }
+
+    #[tokio::test]
+    async fn test_search_skips_private_channels() {
+        let (svc, created) = community().await;
+        let channel = &created.general_channel_id;
+        svc.send_message(channel, OWNER, "launch plans", None, None, None, None)
+            .unwrap();
+        let search = |did: &str| {
+            let visible = svc.visible_channels(did).unwrap();
+            svc.db()
+                .search_messages("launch", did, &[], &visible, 10)
+                .unwrap()
+                .len()
+        };
+        assert_eq!(search(MEMBER), 1);
+
+        // Hide the channel from everyone but admins
+        let view = {
+            let mut view = Permissions::NONE;
+            view.add(Permission::ViewChannels);
+            view.to_string_repr()
+        };
+        let roles = &created.role_ids;
+        svc.set_channel_override(channel, "role", &roles.member, "0", &view, OWNER)
+            .unwrap();
+        svc.set_channel_override(channel, "role", &roles.admin, &view, "0", OWNER)
+            .unwrap();
+        assert!(!svc.visible_channels(MEMBER).unwrap().contains(channel));
+        assert_eq!(search(MEMBER), 0);
+        assert_eq!(search(ADMIN), 1);
+        assert_eq!(search(OWNER), 1);
+        assert_eq!(search(OUTSIDER), 0);
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_withhold_permissions() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let channel = &created.general_channel_id;
+        let message = svc
+            .send_message(channel, MEMBER, "hi", None, None, None, None)
+            .unwrap();
+
+        svc.timeout_member(cid, MEMBER, None, "mute", 600, MOD)
+            .unwrap();
+        assert!(matches!(
+            svc.send_message(channel, MEMBER, "hi", None, None, None, None),
+            Err(Error::MemberTimedOut(_))
+        ));
+        assert!(matches!(
+            svc.add_reaction(&message.id, MEMBER, "👍", false),
+            Err(Error::MemberTimedOut(_))
+        ));
+        let permissions = svc.member_permissions(cid, MEMBER).unwrap();
+        assert!(!permissions.has(Permission::SendMessages));
+        assert!(permissions.has(Permission::UploadFiles));
+
+        svc.timeout_member(cid, MEMBER, None, "restrict", 600, MOD)
+            .unwrap();
+        assert!(!svc
+            .member_permissions(cid, MEMBER)
+            .unwrap()
+            .has(Permission::UploadFiles));
+
+        // Something they never had is still a plain denial
+        assert!(denied(
+            svc.create_invite(cid, MEMBER, None, None),
+            Permission::CreateInvites
+        ));
+    }
+
+    // ── Per-mutation guards ─────────────────────────────────────────────
+    //
+    // Each guarded mutation: who is allowed, who is denied, how channel
+    // overrides change that (community-wide guards ignore them), and
+    // whether a timeout withholds it.
+
+    #[tokio::test]
+    async fn test_set_slow_mode_guard() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let channel = &created.general_channel_id;
+        let manage = bits(&[Permission::ManageChannels]);
+        let slow_mode = |did: &str| svc.set_slow_mode(channel, 5, did);
+
+        assert!(slow_mode(OWNER).is_ok());
+        assert!(slow_mode(ADMIN).is_ok());
+        assert!(denied(slow_mode(MOD), Permission::ManageChannels));
+        assert!(matches!(slow_mode(OUTSIDER), Err(Error::NotMember)));
+
+        svc.set_channel_override(channel, "member", MEMBER, &manage, "0", OWNER)
+            .unwrap();
+        assert!(slow_mode(MEMBER).is_ok());
+        let admin = &created.role_ids.admin;
+        svc.set_channel_override(channel, "role", admin, "0", &manage, OWNER)
+            .unwrap();
+        assert!(denied(slow_mode(ADMIN), Permission::ManageChannels));
+
+        // A timeout only takes away speaking
+        svc.timeout_member(cid, MEMBER, None, "restrict", 600, MOD)
+            .unwrap();
+        assert!(slow_mode(MEMBER).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reorder_channels_guard() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let order = vec![created.general_channel_id.clone()];
+        let reorder = |did: &str| svc.reorder_channels(&created.space_id, &order, did);
+
+        assert!(reorder(OWNER).is_ok());
+        assert!(reorder(ADMIN).is_ok());
+        assert!(denied(reorder(MOD), Permission::ManageChannels));
+        assert!(matches!(reorder(OUTSIDER), Err(Error::NotMember)));
+
+        // Channel overrides don't grant community-wide permissions
+        let manage = bits(&[Permission::ManageChannels]);
+        svc.set_channel_override(
+            &created.general_channel_id,
+            "member",
+            MEMBER,
+            &manage,
+            "0",
+            OWNER,
+        )
+        .unwrap();
+        assert!(denied(reorder(MEMBER), Permission::ManageChannels));
+
+        svc.timeout_member(cid, ADMIN, None, "restrict", 600, OWNER)
+            .unwrap();
+        assert!(reorder(ADMIN).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_emoji_guard() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let mut count = 0;
+        let mut emoji = || {
+            count += 1;
+            svc.create_emoji(cid, &format!("e{}", count), "https://e.png", false, OWNER)
+                .unwrap()
+                .id
+        };
+
+        let first = emoji();
+        assert!(denied(
+            svc.delete_emoji(&first, MOD),
+            Permission::ManageEmoji
+        ));
+        assert!(denied(
+            svc.delete_emoji(&first, MEMBER),
+            Permission::ManageEmoji
+        ));
+        assert!(matches!(
+            svc.delete_emoji(&first, OUTSIDER),
+            Err(Error::NotMember)
+        ));
+        svc.delete_emoji(&first, ADMIN).unwrap();
+
+        // Channel overrides don't grant community-wide permissions
+        let manage = bits(&[Permission::ManageEmoji]);
+        svc.set_channel_override(
+            &created.general_channel_id,
+            "member",
+            MEMBER,
+            &manage,
+            "0",
+            OWNER,
+        )
+        .unwrap();
+        let second = emoji();
+        assert!(denied(
+            svc.delete_emoji(&second, MEMBER),
+            Permission::ManageEmoji
+        ));
+
+        svc.timeout_member(cid, ADMIN, None, "restrict", 600, OWNER)
+            .unwrap();
+        svc.delete_emoji(&second, ADMIN).unwrap();
+        svc.delete_emoji(&emoji(), OWNER).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pin_message_guard() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let channel = &created.general_channel_id;
+        let pin = |did: &str| {
+            let message = svc
+                .send_message(channel, OWNER, "pin me", None, None, None, None)
+                .unwrap();
+            svc.pin_message(channel, &message.id, did)
+        };
+
+        assert!(pin(ADMIN).is_ok());
+        assert!(pin(MOD).is_ok());
+        assert!(denied(pin(MEMBER), Permission::ManageMessages));
+        assert!(matches!(pin(OUTSIDER), Err(Error::NotMember)));
+
+        let manage = bits(&[Permission::ManageMessages]);
+        svc.set_channel_override(channel, "member", MEMBER, &manage, "0", OWNER)
+            .unwrap();
+        assert!(pin(MEMBER).is_ok());
+        let roles = &created.role_ids;
+        svc.set_channel_override(channel, "role", &roles.moderator, "0", &manage, OWNER)
+            .unwrap();
+        assert!(denied(pin(MOD), Permission::ManageMessages));
+
+        svc.timeout_member(cid, MEMBER, None, "restrict", 600, ADMIN)
+            .unwrap();
+        assert!(pin(MEMBER).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_create_thread_guard() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let channel = &created.general_channel_id;
+        let thread = |did: &str| {
+            let message = svc
+                .send_message(channel, OWNER, "topic", None, None, None, None)
+                .unwrap();
+            svc.create_thread(channel, &message.id, Some("thread"), did)
+        };
+
+        assert!(thread(MEMBER).is_ok());
+        assert!(matches!(thread(OUTSIDER), Err(Error::NotMember)));
+
+        let create = bits(&[Permission::CreateThreads]);
+        let roles = &created.role_ids;
+        svc.set_channel_override(channel, "role", &roles.member, "0", &create, OWNER)
+            .unwrap();
+        assert!(denied(thread(MEMBER), Permission::CreateThreads));
+        assert!(thread(OWNER).is_ok());
+        svc.set_channel_override(channel, "member", MEMBER, &create, "0", OWNER)
+            .unwrap();
+        assert!(thread(MEMBER).is_ok());
+
+        // Muting takes it away, even when an override granted it
+        svc.timeout_member(cid, MEMBER, None, "mute", 600, MOD)
+            .unwrap();
+        assert!(timed_out(thread(MEMBER)));
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_guard() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let channel = &created.general_channel_id;
+        let upload = |did: &str| svc.upload_file(channel, None, "a.txt", None, 1, None, "[]", did);
+
+        assert!(upload(MEMBER).is_ok());
+        assert!(matches!(upload(OUTSIDER), Err(Error::NotMember)));
+
+        let files = bits(&[Permission::UploadFiles]);
+        let roles = &created.role_ids;
+        svc.set_channel_override(channel, "role", &roles.member, "0", &files, OWNER)
+            .unwrap();
+        assert!(denied(upload(MEMBER), Permission::UploadFiles));
+        svc.set_channel_override(channel, "member", MEMBER, &files, "0", OWNER)
+            .unwrap();
+        assert!(upload(MEMBER).is_ok());
+
+        // A mute leaves uploads alone; a restriction takes them away
+        svc.timeout_member(cid, MEMBER, None, "mute", 600, MOD)
+            .unwrap();
+        assert!(upload(MEMBER).is_ok());
+        svc.timeout_member(cid, MEMBER, None, "restrict", 600, MOD)
+            .unwrap();
+        assert!(timed_out(upload(MEMBER)));
+    }
+
+    #[tokio::test]
+    async fn test_create_webhook_guard() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let channel = &created.general_channel_id;
+        let webhook = |did: &str| svc.create_webhook(channel, "hook", None, did);
+
+        assert!(webhook(OWNER).is_ok());
+        assert!(webhook(ADMIN).is_ok());
+        assert!(denied(webhook(MOD), Permission::ManageWebhooks));
+        assert!(matches!(webhook(OUTSIDER), Err(Error::NotMember)));
+
+        svc.timeout_member(cid, ADMIN, None, "restrict", 600, OWNER)
+            .unwrap();
+        assert!(webhook(ADMIN).is_ok());
+
+        let manage = bits(&[Permission::ManageWebhooks]);
+        svc.set_channel_override(channel, "member", MEMBER, &manage, "0", OWNER)
+            .unwrap();
+        assert!(webhook(MEMBER).is_ok());
+        let admin = &created.role_ids.admin;
+        svc.set_channel_override(channel, "role", admin, "0", &manage, OWNER)
+            .unwrap();
+        assert!(denied(webhook(ADMIN), Permission::ManageWebhooks));
+    }
+
+    #[tokio::test]
+    async fn test_update_branding_guard() {
+        let (svc, created) = community().await;
+        let cid = &created.community_id;
+        let brand =
+            |did: &str| svc.update_branding(cid, None, None, None, Some("#ff0000"), None, did);
+
+        assert!(brand(OWNER).is_ok());
+        assert!(brand(ADMIN).is_ok());
+        assert!(denied(brand(MOD), Permission::ManageBranding));
+        assert!(denied(brand(MEMBER), Permission::ManageBranding));
+        assert!(matches!(brand(OUTSIDER), Err(Error::NotMember)));
+
+        // Channel overrides don't grant community-wide permissions
+        let manage = bits(&[Permission::ManageBranding]);
+        svc.set_channel_override(
+            &created.general_channel_id,
+            "member",
+            MEMBER,
+            &manage,
+            "0",
+            OWNER,
+        )
+        .unwrap();
+        assert!(denied(brand(MEMBER), Permission::ManageBranding));
+
+        svc.timeout_member(cid, ADMIN, None, "restrict", 600, OWNER)
+            .unwrap();
+        assert!(brand(ADMIN).is_ok());
+    }
+}
@@ -3,6 +3,7 @@
 //! Category CRUD within spaces. Categories are user-named groupings
 //! that organize channels within a space.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::CommunityCategoryRecord;
@@ -17,6 +18,8 @@ impl super::CommunityService {
         position: i32,
         actor_did: &str,
     ) -> Result<CommunityCategoryRecord> {
+        self.require_permission(community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(community_id)?;
         let now = crate::time::now_timestamp();
         let category_id = generate_id();
 
@@ -69,6 +72,8 @@ impl super::CommunityService {
             .db()
             .get_community_category(category_id)?
             .ok_or(Error::CategoryNotFound)?;
+        self.require_permission(&category.community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&category.community_id)?;
 
         self.db()
             .update_community_category(category_id, name, now)?;
@@ -88,7 +93,19 @@ impl super::CommunityService {
     }
 
     /// Reorder categories in a space.
-    pub fn reorder_categories(&self, _space_id: &str, category_ids: &[String]) -> Result<()> {
+    pub fn reorder_categories(
+        &self,
+        space_id: &str,
+        category_ids: &[String],
+        actor_did: &str,
+    ) -> Result<()> {
+        let space = self
+            .db()
+            .get_community_space(space_id)?
+            .ok_or(Error::SpaceNotFound)?;
+        self.require_permission(&space.community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&space.community_id)?;
+
         let now = crate::time::now_timestamp();
         for (position, category_id) in category_ids.iter().enumerate() {
             self.db()
@@ -104,6 +121,8 @@ impl super::CommunityService {
             .db()
             .get_community_category(category_id)?
             .ok_or(Error::CategoryNotFound)?;
+        self.require_permission(&category.community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&category.community_id)?;
 
         let now = crate::time::now_timestamp();
         self.db().delete_community_category(category_id)?;
@@ -129,7 +148,9 @@ impl super::CommunityService {
         category_id: Option<&str>,
         actor_did: &str,
     ) -> Result<()> {
-        let channel = self.get_channel(channel_id)?;
+        let channel =
+            self.require_channel_permission(channel_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&channel.community_id)?;
         let now = crate::time::now_timestamp();
 
         self.db()
@@ -3,6 +3,7 @@
 //! Channel CRUD within spaces. Supports text, voice, files,
 //! announcement, bulletin, and welcome channel types.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::CommunityChannelRecord;
@@ -37,6 +38,8 @@ impl super::CommunityService {
                 channel_type
             )));
         }
+        self.require_permission(community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(community_id)?;
 
         let now = crate::time::now_timestamp();
         let channel_id = generate_id();
@@ -107,7 +110,9 @@ impl super::CommunityService {
         actor_did: &str,
     ) -> Result<()> {
         let now = crate::time::now_timestamp();
-        let channel = self.get_channel(channel_id)?;
+        let channel =
+            self.require_channel_permission(channel_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&channel.community_id)?;
 
         self.db()
             .update_community_channel(channel_id, name, topic, now)?;
@@ -127,7 +132,10 @@ impl super::CommunityService {
     }
 
     /// Set slow mode for a channel (seconds between messages per user, 0 = off).
-    pub fn set_slow_mode(&self, channel_id: &str, seconds: i32, _actor_did: &str) -> Result<()> {
+    pub fn set_slow_mode(&self, channel_id: &str, seconds: i32, actor_did: &str) -> Result<()> {
+        let channel =
+            self.require_channel_permission(channel_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&channel.community_id)?;
         let now = crate::time::now_timestamp();
         self.db().update_channel_slow_mode(channel_id, seconds, now)
     }
@@ -137,15 +145,18 @@ impl super::CommunityService {
         &self,
         channel_id: &str,
         enabled: bool,
-        _actor_did: &str,
+        actor_did: &str,
     ) -> Result<()> {
+        self.require_channel_permission(channel_id, actor_did, Permission::ManageChannels)?;
         let now = crate::time::now_timestamp();
         self.db().update_channel_e2ee(channel_id, enabled, now)
     }
 
     /// Delete a channel.
     pub fn delete_channel(&self, channel_id: &str, actor_did: &str) -> Result<()> {
-        let channel = self.get_channel(channel_id)?;
+        let channel =
+            self.require_channel_permission(channel_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&channel.community_id)?;
         let now = crate::time::now_timestamp();
 
         self.db().delete_community_channel(channel_id)?;
@@ -165,7 +176,19 @@ impl super::CommunityService {
     }
 
     /// Reorder channels within a space.
-    pub fn reorder_channels(&self, _space_id: &str, channel_ids: &[String]) -> Result<()> {
+    pub fn reorder_channels(
+        &self,
+        space_id: &str,
+        channel_ids: &[String],
+        actor_did: &str,
+    ) -> Result<()> {
+        let space = self
+            .db()
+            .get_community_space(space_id)?
+            .ok_or(Error::SpaceNotFound)?;
+        self.require_permission(&space.community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&space.community_id)?;
+
         let now = crate::time::now_timestamp();
         for (position, channel_id) in channel_ids.iter().enumerate() {
             self.db()
@@ -2,8 +2,9 @@
 //!
 //! Community branding, custom emoji, stickers, sticker packs, and vanity URLs.
 
+use super::permissions::Permission;
 use super::service::generate_id;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::storage::{CommunityEmojiRecord, CommunityStickerPackRecord, CommunityStickerRecord};
 
 impl super::CommunityService {
@@ -21,6 +22,7 @@ impl super::CommunityService {
         custom_css: Option<&str>,
         actor_did: &str,
     ) -> Result<()> {
+        self.require_permission(community_id, actor_did, Permission::ManageBranding)?;
         let now = crate::time::now_timestamp();
         self.db().update_community_branding(
             community_id,
@@ -53,6 +55,7 @@ impl super::CommunityService {
         vanity_url: &str,
         actor_did: &str,
     ) -> Result<()> {
+        self.require_permission(community_id, actor_did, Permission::ManageCommunity)?;
         let now = crate::time::now_timestamp();
         self.db()
             .update_community_vanity_url(community_id, vanity_url, now)?;
@@ -82,6 +85,7 @@ impl super::CommunityService {
         animated: bool,
         uploaded_by: &str,
     ) -> Result<CommunityEmojiRecord> {
+        self.require_permission(community_id, uploaded_by, Permission::ManageEmoji)?;
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
@@ -123,15 +127,25 @@ impl super::CommunityService {
     }
 
     /// Rename a custom emoji.
-    pub fn rename_emoji(&self, emoji_id: &str, new_name: &str) -> Result<()> {
+    pub fn rename_emoji(&self, emoji_id: &str, new_name: &str, actor_did: &str) -> Result<()> {
+        self.require_emoji_manager(emoji_id, actor_did)?;
         self.db().rename_community_emoji(emoji_id, new_name)
     }
 
     /// Delete a custom emoji.
-    pub fn delete_emoji(&self, emoji_id: &str, _actor_did: &str) -> Result<()> {
+    pub fn delete_emoji(&self, emoji_id: &str, actor_did: &str) -> Result<()> {
+        self.require_emoji_manager(emoji_id, actor_did)?;
         self.db().delete_community_emoji(emoji_id)
     }
 
+    fn require_emoji_manager(&self, emoji_id: &str, actor_did: &str) -> Result<()> {
+        let emoji = self
+            .db()
+            .get_community_emoji_by_id(emoji_id)?
+            .ok_or_else(|| Error::InvalidCommunityOperation("Emoji not found".to_string()))?;
+        self.require_permission(&emoji.community_id, actor_did, Permission::ManageEmoji)
+    }
+
     // ── Custom Stickers ─────────────────────────────────────────────────
 
     /// Create a custom sticker.
@@ -146,6 +160,7 @@ impl super::CommunityService {
         format: &str,
         uploaded_by: &str,
     ) -> Result<CommunityStickerRecord> {
+        self.require_permission(community_id, uploaded_by, Permission::ManageEmoji)?;
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
@@ -191,7 +206,12 @@ impl super::CommunityService {
     }
 
     /// Delete a custom sticker.
-    pub fn delete_sticker(&self, sticker_id: &str) -> Result<()> {
+    pub fn delete_sticker(&self, sticker_id: &str, actor_did: &str) -> Result<()> {
+        let sticker = self
+            .db()
+            .get_community_sticker(sticker_id)?
+            .ok_or_else(|| Error::InvalidCommunityOperation("Sticker not found".to_string()))?;
+        self.require_permission(&sticker.community_id, actor_did, Permission::ManageEmoji)?;
         self.db().delete_community_sticker(sticker_id)
     }
 
@@ -207,6 +227,7 @@ impl super::CommunityService {
         cover_sticker_id: Option<&str>,
         created_by: &str,
     ) -> Result<CommunityStickerPackRecord> {
+        self.require_permission(community_id, created_by, Permission::ManageEmoji)?;
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
@@ -248,12 +269,22 @@ impl super::CommunityService {
     }
 
     /// Delete a sticker pack.
-    pub fn delete_sticker_pack(&self, pack_id: &str) -> Result<()> {
+    pub fn delete_sticker_pack(&self, pack_id: &str, actor_did: &str) -> Result<()> {
+        self.require_sticker_pack_manager(pack_id, actor_did)?;
         self.db().delete_community_sticker_pack(pack_id)
     }
 
     /// Rename a sticker pack.
-    pub fn rename_sticker_pack(&self, pack_id: &str, new_name: &str) -> Result<()> {
+    pub fn rename_sticker_pack(&self, pack_id: &str, new_name: &str, actor_did: &str) -> Result<()> {
+        self.require_sticker_pack_manager(pack_id, actor_did)?;
         self.db().rename_community_sticker_pack(pack_id, new_name)
     }
+
+    fn require_sticker_pack_manager(&self, pack_id: &str, actor_did: &str) -> Result<()> {
+        let pack = self
+            .db()
+            .get_community_sticker_pack(pack_id)?
+            .ok_or_else(|| Error::InvalidCommunityOperation("Sticker pack not found".to_string()))?;
+        self.require_permission(&pack.community_id, actor_did, Permission::ManageEmoji)
+    }
 }
@@ -2,6 +2,7 @@
 //!
 //! File and folder CRUD for file-type channels.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::{CommunityFileFolderRecord, CommunityFileRecord};
@@ -27,11 +28,7 @@ impl super::CommunityService {
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
-        // Verify channel exists
-        let _channel = self
-            .db()
-            .get_community_channel(channel_id)?
-            .ok_or(Error::ChannelNotFound)?;
+        self.require_channel_permission(channel_id, uploaded_by, Permission::UploadFiles)?;
 
         // Auto-versioning: check for existing file with same name in same folder
         let version = self.db().store_community_file_versioned(
@@ -100,9 +97,12 @@ impl super::CommunityService {
         self.db().increment_file_download_count(id)
     }
 
-    /// Delete a file.
+    /// Delete a file (its uploader, or anyone with `ManageFiles`).
     pub fn delete_file(&self, id: &str, actor_did: &str) -> Result<()> {
         let file = self.get_file(id)?;
+        if file.uploaded_by != actor_did {
+            self.require_channel_permission(&file.channel_id, actor_did, Permission::ManageFiles)?;
+        }
         self.db().delete_community_file(id)?;
 
         // Audit log — we need the channel to get community_id
@@ -133,6 +133,7 @@ impl super::CommunityService {
         name: &str,
         created_by: &str,
     ) -> Result<CommunityFileFolderRecord> {
+        self.require_channel_permission(channel_id, created_by, Permission::ManageFiles)?;
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
@@ -166,7 +167,12 @@ impl super::CommunityService {
     }
 
     /// Delete a folder (cascades to subfolders and files).
-    pub fn delete_folder(&self, id: &str) -> Result<()> {
+    pub fn delete_folder(&self, id: &str, actor_did: &str) -> Result<()> {
+        let folder = self
+            .db()
+            .get_community_file_folder(id)?
+            .ok_or_else(|| Error::InvalidCommunityOperation("Folder not found".to_string()))?;
+        self.require_channel_permission(&folder.channel_id, actor_did, Permission::ManageFiles)?;
         self.db().delete_community_file_folder(id)
     }
 }
@@ -2,9 +2,10 @@
 //!
 //! Webhooks, notification settings, and advanced channel features.
 
+use super::permissions::{Permission, Permissions};
 use super::service::generate_id;
 use crate::error::{Error, Result};
-use crate::storage::{ChannelPermissionOverrideRecord, CommunityWebhookRecord};
+use crate::storage::{ChannelPermissionOverrideRecord, CommunityRoleRecord, CommunityWebhookRecord};
 
 impl super::CommunityService {
     // ── Webhooks ────────────────────────────────────────────────────────
@@ -17,6 +18,8 @@ impl super::CommunityService {
         avatar_url: Option<&str>,
         creator_did: &str,
     ) -> Result<CommunityWebhookRecord> {
+        let channel =
+            self.require_channel_permission(channel_id, creator_did, Permission::ManageWebhooks)?;
         let now = crate::time::now_timestamp();
         let id = generate_id();
         let token = generate_webhook_token();
@@ -31,19 +34,16 @@ impl super::CommunityService {
             now,
         )?;
 
-        // Audit log — need channel to get community
-        if let Some(channel) = self.db().get_community_channel(channel_id)? {
-            self.db().insert_audit_log(
-                &generate_id(),
-                &channel.community_id,
-                creator_did,
-                "webhook_create",
-                Some("webhook"),
-                Some(&id),
-                Some(&serde_json::json!({"name": name}).to_string()),
-                now,
-            )?;
-        }
+        self.db().insert_audit_log(
+            &generate_id(),
+            &channel.community_id,
+            creator_did,
+            "webhook_create",
+            Some("webhook"),
+            Some(&id),
+            Some(&serde_json::json!({"name": name}).to_string()),
+            now,
+        )?;
 
         Ok(CommunityWebhookRecord {
             id,
@@ -76,28 +76,34 @@ impl super::CommunityService {
         id: &str,
         name: Option<&str>,
         avatar_url: Option<&str>,
+        actor_did: &str,
     ) -> Result<()> {
+        let webhook = self.get_webhook(id)?;
+        self.require_channel_permission(&webhook.channel_id, actor_did, Permission::ManageWebhooks)?;
         self.db().update_community_webhook(id, name, avatar_url)
     }
 
     /// Delete a webhook.
     pub fn delete_webhook(&self, id: &str, actor_did: &str) -> Result<()> {
         let webhook = self.get_webhook(id)?;
+        let channel = self.require_channel_permission(
+            &webhook.channel_id,
+            actor_did,
+            Permission::ManageWebhooks,
+        )?;
         self.db().delete_community_webhook(id)?;
 
-        if let Some(channel) = self.db().get_community_channel(&webhook.channel_id)? {
-            let now = crate::time::now_timestamp();
-            self.db().insert_audit_log(
-                &generate_id(),
-                &channel.community_id,
-                actor_did,
-                "webhook_delete",
-                Some("webhook"),
-                Some(id),
-                None,
-                now,
-            )?;
-        }
+        let now = crate::time::now_timestamp();
+        self.db().insert_audit_log(
+            &generate_id(),
+            &channel.community_id,
+            actor_did,
+            "webhook_delete",
+            Some("webhook"),
+            Some(id),
+            None,
+            now,
+        )?;
 
         Ok(())
     }
@@ -105,6 +111,8 @@ impl super::CommunityService {
     // ── Channel Permission Overrides (Advanced Roles Phase 4) ───────────
 
     /// Set a permission override for a role or member on a channel.
+    ///
+    /// Only permissions the actor holds can be allowed or denied.
     pub fn set_channel_override(
         &self,
         channel_id: &str,
@@ -114,6 +122,12 @@ impl super::CommunityService {
         deny_bitfield: &str,
         actor_did: &str,
     ) -> Result<()> {
+        let channel =
+            self.require_channel_permission(channel_id, actor_did, Permission::ManageRoles)?;
+        let touched = Permissions::from_string(allow_bitfield)
+            .merge(&Permissions::from_string(deny_bitfield));
+        self.require_grantable(&channel.community_id, actor_did, &touched)?;
+
         let id = generate_id();
         self.db().set_channel_permission_override(
             &id,
@@ -124,25 +138,23 @@ impl super::CommunityService {
             deny_bitfield,
         )?;
 
-        if let Some(channel) = self.db().get_community_channel(channel_id)? {
-            let now = crate::time::now_timestamp();
-            self.db().insert_audit_log(
-                &generate_id(),
-                &channel.community_id,
-                actor_did,
-                "permission_override_set",
-                Some("channel"),
-                Some(channel_id),
-                Some(
-                    &serde_json::json!({
-                        "target_type": target_type,
-                        "target_id": target_id,
-                    })
-                    .to_string(),
-                ),
-                now,
-            )?;
-        }
+        let now = crate::time::now_timestamp();
+        self.db().insert_audit_log(
+            &generate_id(),
+            &channel.community_id,
+            actor_did,
+            "permission_override_set",
+            Some("channel"),
+            Some(channel_id),
+            Some(
+                &serde_json::json!({
+                    "target_type": target_type,
+                    "target_id": target_id,
+                })
+                .to_string(),
+            ),
+            now,
+        )?;
 
         Ok(())
     }
@@ -156,7 +168,14 @@ impl super::CommunityService {
     }
 
     /// Remove a permission override.
-    pub fn remove_channel_override(&self, override_id: &str) -> Result<()> {
+    pub fn remove_channel_override(&self, override_id: &str, actor_did: &str) -> Result<()> {
+        let existing = self
+            .db()
+            .get_channel_permission_override(override_id)?
+            .ok_or_else(|| {
+                Error::InvalidCommunityOperation("Permission override not found".to_string())
+            })?;
+        self.require_channel_permission(&existing.channel_id, actor_did, Permission::ManageRoles)?;
         self.db().remove_channel_permission_override(override_id)
     }
 
@@ -175,6 +194,15 @@ impl super::CommunityService {
         permissions_bitfield: &str,
         actor_did: &str,
     ) -> Result<crate::storage::CommunityRoleRecord> {
+        self.require_permission(community_id, actor_did, Permission::ManageRoles)?;
+        self.require_unsigned(community_id)?;
+        self.require_position_below(community_id, actor_did, position)?;
+        self.require_grantable(
+            community_id,
+            actor_did,
+            &Permissions::from_string(permissions_bitfield),
+        )?;
+
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
@@ -231,6 +259,11 @@ impl super::CommunityService {
         position: Option<i32>,
         actor_did: &str,
     ) -> Result<()> {
+        let role = self.managed_role(role_id, actor_did)?;
+        if let Some(position) = position {
+            self.require_position_below(&role.community_id, actor_did, position)?;
+        }
+
         let now = crate::time::now_timestamp();
         self.db().update_community_role(
             role_id,
@@ -241,7 +274,6 @@ impl super::CommunityService {
             position,
             now,
         )?;
-        let _ = actor_did; // for future audit
         Ok(())
     }
 
@@ -252,19 +284,42 @@ impl super::CommunityService {
         permissions_bitfield: &str,
         actor_did: &str,
     ) -> Result<()> {
+        let role = self.managed_role(role_id, actor_did)?;
+        self.require_grantable(
+            &role.community_id,
+            actor_did,
+            &Permissions::from_string(permissions_bitfield),
+        )?;
+
         let now = crate::time::now_timestamp();
         self.db()
             .update_community_role_permissions(role_id, permissions_bitfield, now)?;
-        let _ = actor_did;
         Ok(())
     }
 
     /// Delete a custom role (preset roles cannot be deleted).
     pub fn delete_role(&self, role_id: &str, actor_did: &str) -> Result<()> {
+        let role = self.managed_role(role_id, actor_did)?;
+        if role.is_preset {
+            return Err(Error::InvalidCommunityOperation(
+                "Preset roles can't be deleted".to_string(),
+            ));
+        }
         self.db().delete_community_role(role_id)?;
-        let _ = actor_did;
         Ok(())
     }
+
+    /// Look up a role `actor_did` may manage.
+    fn managed_role(&self, role_id: &str, actor_did: &str) -> Result<CommunityRoleRecord> {
+        let role = self
+            .db()
+            .get_community_role(role_id)?
+            .ok_or(Error::RoleNotFound)?;
+        self.require_permission(&role.community_id, actor_did, Permission::ManageRoles)?;
+        self.require_unsigned(&role.community_id)?;
+        self.require_role_below(actor_did, &role)?;
+        Ok(role)
+    }
 }
 
 /// Generate a random webhook token (32-char hex).
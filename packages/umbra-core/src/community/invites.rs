@@ -2,6 +2,7 @@
 //!
 //! Invite codes with optional expiration, max uses, and vanity URLs.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::CommunityInviteRecord;
@@ -15,6 +16,8 @@ impl super::CommunityService {
         max_uses: Option<i32>,
         expires_at: Option<i64>,
     ) -> Result<CommunityInviteRecord> {
+        self.require_permission(community_id, creator_did, Permission::CreateInvites)?;
+        self.require_unsigned(community_id)?;
         let now = crate::time::now_timestamp();
         let invite_id = generate_id();
         let code = generate_invite_code();
@@ -65,6 +68,7 @@ impl super::CommunityService {
             .db()
             .get_community_invite_by_code(code)?
             .ok_or(Error::InviteNotFound)?;
+        self.require_unsigned(&invite.community_id)?;
 
         let now = crate::time::now_timestamp();
 
@@ -96,12 +100,16 @@ impl super::CommunityService {
         self.db().get_community_invites(community_id)
     }
 
-    /// Delete an invite.
+    /// Delete an invite (its creator, or anyone with `ManageInvites`).
     pub fn delete_invite(&self, invite_id: &str, actor_did: &str) -> Result<()> {
         let invite = self
             .db()
             .get_community_invite(invite_id)?
             .ok_or(Error::InviteNotFound)?;
+        if invite.creator_did != actor_did {
+            self.require_permission(&invite.community_id, actor_did, Permission::ManageInvites)?;
+            self.require_unsigned(&invite.community_id)?;
+        }
 
         let now = crate::time::now_timestamp();
         self.db().delete_community_invite(invite_id)?;
@@ -127,6 +135,8 @@ impl super::CommunityService {
         vanity_code: &str,
         creator_did: &str,
     ) -> Result<CommunityInviteRecord> {
+        self.require_permission(community_id, creator_did, Permission::ManageCommunity)?;
+        self.require_unsigned(community_id)?;
         let now = crate::time::now_timestamp();
         let invite_id = generate_id();
 
@@ -0,0 +1,1132 @@
+//! # Channel Keyring
+//!
+//! Keys for end-to-end encrypted channels: generating them, handing them
+//! to the members who can read the channel, rotating them when someone
+//! who held one loses access, and decrypting history across key versions.
+//!
+//! ## Distribution
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                       CHANNEL KEYRING                                   │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  Member ──community_member_key──► everyone                             │
+//! │    (signed X25519 key the member takes channel keys on)                 │
+//! │                                                                         │
+//! │  Key manager (ManageChannels) syncs each encrypted channel:            │
+//! │    1. Readers = members with ViewChannels there                        │
+//! │    2. No key yet, or a holder of the latest key is no longer a         │
+//! │       reader (kicked, banned, left, lost ViewChannels) → new version   │
+//! │    3. Readers without the latest key get it, wrapped to their          │
+//! │       member key; with ReadMessageHistory, the older ones too          │
+//! │                                                                         │
+//! │  Key manager ──community_channel_keys──► reader                        │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! Each message records the `key_version` it was encrypted with, so
+//! history stays readable across rotations. Like the op log, the keyring
+//! never talks to the network: what other members need comes back as
+//! [`RelayMessage`]s.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::permissions::Permission;
+use crate::crypto::{hex_bytes, sign, verify, EncryptionKey, Nonce, Signature, NONCE_SIZE};
+use crate::error::{Error, Result};
+use crate::identity::{Did, Identity};
+use crate::network::relay_client::RelayMessage;
+use crate::storage::ChannelKeyRecord;
+
+/// Keyring envelope format version
+pub const KEYRING_VERSION: u32 = 1;
+
+/// Prefix for data signed over a member key announcement
+const MEMBER_KEY_DOMAIN: &[u8] = b"umbra-community-member-key-v1";
+
+/// One version of a channel's shared key
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct ChannelKey {
+    /// Version of the key; the first key of a channel is version 1
+    pub key_version: i32,
+    /// The raw AES-256 key
+    pub raw_key: [u8; 32],
+}
+
+/// Signed announcement of the key a member takes channel keys on
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberKeyAnnouncement {
+    /// Community ID
+    pub community_id: String,
+    /// The announcing member
+    pub member_did: String,
+    /// Their X25519 public key
+    #[serde(with = "hex_bytes")]
+    pub encryption_key: [u8; 32],
+    /// When it was announced (Unix timestamp)
+    pub announced_at: i64,
+    /// Ed25519 signature by the member's signing key (hex)
+    pub signature: String,
+}
+
+impl MemberKeyAnnouncement {
+    fn sign(identity: &Identity, community_id: &str, announced_at: i64) -> Result<Self> {
+        let mut announcement = Self {
+            community_id: community_id.to_string(),
+            member_did: identity.did_string(),
+            encryption_key: identity.keypair().encryption.public_bytes(),
+            announced_at,
+            signature: String::new(),
+        };
+        announcement.signature =
+            sign(&identity.keypair().signing, &announcement.sign_bytes()?).to_hex();
+        Ok(announcement)
+    }
+
+    /// Check the announcement is signed by the member it names.
+    pub fn verify(&self) -> Result<()> {
+        let public_key = Did::parse(&self.member_did)?.public_key()?;
+        verify(
+            &public_key,
+            &self.sign_bytes()?,
+            &Signature::from_hex(&self.signature)?,
+        )
+    }
+
+    fn sign_bytes(&self) -> Result<Vec<u8>> {
+        let data = (
+            &self.community_id,
+            &self.member_did,
+            &self.encryption_key,
+            self.announced_at,
+        );
+        let mut bytes = MEMBER_KEY_DOMAIN.to_vec();
+        bytes.extend(
+            bincode::serialize(&data).map_err(|e| Error::SerializationError(e.to_string()))?,
+        );
+        Ok(bytes)
+    }
+}
+
+/// One version of a channel key, encrypted for a member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WrappedChannelKey {
+    /// Key version
+    pub key_version: i32,
+    /// Encrypted key (hex encoded)
+    pub encrypted_key: String,
+    /// Nonce (hex encoded)
+    pub nonce: String,
+}
+
+/// Channel keys a key manager handed to one member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelKeyGrant {
+    /// Community ID
+    pub community_id: String,
+    /// Channel ID
+    pub channel_id: String,
+    /// Key manager who wrapped the keys
+    pub sender_did: String,
+    /// Member they're wrapped for
+    pub recipient_did: String,
+    /// The keys, oldest first
+    pub keys: Vec<WrappedChannelKey>,
+}
+
+/// What syncing a keyring did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct KeyringUpdate {
+    /// Channels that got a new key, with its version
+    pub rotated: BTreeMap<String, i32>,
+    /// Readers who haven't announced a member key yet, so got nothing
+    pub awaiting_key: BTreeSet<String>,
+    /// Member key and channel key envelopes for other members
+    pub relay_messages: Vec<RelayMessage>,
+}
+
+impl KeyringUpdate {
+    fn merge(&mut self, other: KeyringUpdate) {
+        self.rotated.extend(other.rotated);
+        self.awaiting_key.extend(other.awaiting_key);
+        self.relay_messages.extend(other.relay_messages);
+    }
+}
+
+/// A message encrypted with a channel key, ready for
+/// [`send_encrypted_message`](super::CommunityService::send_encrypted_message)
+#[derive(Debug, Clone)]
+pub struct EncryptedChannelMessage {
+    /// Ciphertext
+    pub content_encrypted: Vec<u8>,
+    /// Nonce (hex encoded)
+    pub nonce: String,
+    /// Version of the channel key used
+    pub key_version: i32,
+}
+
+/// A keyring envelope of `kind` carrying `payload`.
+fn envelope(kind: &str, payload: &impl Serialize) -> Result<String> {
+    serde_json::to_string(&serde_json::json!({
+        "envelope": kind,
+        "version": KEYRING_VERSION,
+        "payload": payload,
+    }))
+    .map_err(|e| Error::SerializationError(format!("Failed to serialize {}: {}", kind, e)))
+}
+
+fn storage_aad(channel_id: &str, key_version: i32) -> String {
+    format!("community-channel-key:{}:{}", channel_id, key_version)
+}
+
+fn transfer_aad(channel_id: &str, key_version: i32, recipient_did: &str) -> String {
+    format!(
+        "community-channel-key-transfer:{}:{}:{}",
+        channel_id, key_version, recipient_did
+    )
+}
+
+fn message_aad(channel_id: &str, sender_did: &str, key_version: i32) -> String {
+    format!(
+        "community-message:{}:{}:{}",
+        channel_id, sender_did, key_version
+    )
+}
+
+fn decode_hex(value: &str, what: &str) -> Result<Vec<u8>> {
+    hex::decode(value).map_err(|e| Error::InvalidKey(format!("Invalid {} hex: {}", what, e)))
+}
+
+fn decode_nonce(nonce_hex: &str) -> Result<Nonce> {
+    let bytes: [u8; NONCE_SIZE] = decode_hex(nonce_hex, "nonce")?
+        .try_into()
+        .map_err(|_| Error::InvalidKey(format!("Nonce must be {} bytes", NONCE_SIZE)))?;
+    Ok(Nonce::from_bytes(bytes))
+}
+
+fn raw_key(bytes: Vec<u8>) -> Result<[u8; 32]> {
+    bytes
+        .try_into()
+        .map_err(|_| Error::InvalidKey("Channel key must be 32 bytes".into()))
+}
+
+/// Key our stored channel keys are encrypted with, derived from our
+/// signing secret (which, unlike the encryption key, never rotates).
+fn wrapping_key(identity: &Identity) -> EncryptionKey {
+    use hkdf::Hkdf;
+    use sha2::Sha256;
+    let ikm = identity.keypair().signing.secret_bytes();
+    let hkdf = Hkdf::<Sha256>::new(None, &ikm);
+    let mut key = [0u8; 32];
+    hkdf.expand(b"umbra-community-channel-key-wrapping-v1", &mut key)
+        .expect("HKDF-SHA256 expansion should not fail for 32 bytes");
+    EncryptionKey::from_bytes(key)
+}
+
+impl super::CommunityService {
+    // ── Member keys ─────────────────────────────────────────────────────
+
+    /// Announce our encryption key to a community, so key managers can
+    /// hand us channel keys.
+    ///
+    /// Returns a `community_member_key` envelope for every other member.
+    pub fn announce_member_key(
+        &self,
+        identity: &Identity,
+        community_id: &str,
+    ) -> Result<Vec<RelayMessage>> {
+        let our_did = identity.did_string();
+        if self
+            .db()
+            .get_community_member(community_id, &our_did)?
+            .is_none()
+        {
+            return Err(Error::NotMember);
+        }
+
+        let announcement =
+            MemberKeyAnnouncement::sign(identity, community_id, crate::time::now_timestamp())?;
+        self.store_member_key(&announcement)?;
+
+        let payload = envelope("community_member_key", &announcement)?;
+        Ok(self
+            .db()
+            .get_community_members(community_id)?
+            .iter()
+            .filter(|m| m.member_did != our_did)
+            .map(|m| RelayMessage::new(&m.member_did, &payload))
+            .collect())
+    }
+
+    /// Take in a member's key announcement.
+    ///
+    /// It must be signed by the member it names, who must be in the
+    /// community; one older than what we have is ignored.
+    pub fn receive_member_key(&self, announcement: &MemberKeyAnnouncement) -> Result<()> {
+        announcement.verify()?;
+        if self
+            .db()
+            .get_community_member(&announcement.community_id, &announcement.member_did)?
+            .is_none()
+        {
+            return Err(Error::NotMember);
+        }
+        if let Some(current) = self
+            .db()
+            .get_community_member_key(&announcement.community_id, &announcement.member_did)?
+        {
+            if current.announced_at >= announcement.announced_at {
+                return Ok(());
+            }
+        }
+        self.store_member_key(announcement)
+    }
+
+    fn store_member_key(&self, announcement: &MemberKeyAnnouncement) -> Result<()> {
+        self.db().set_community_member_key(
+            &announcement.community_id,
+            &announcement.member_did,
+            &hex::encode(announcement.encryption_key),
+            announcement.announced_at,
+        )
+    }
+
+    fn member_key(&self, community_id: &str, member_did: &str) -> Result<Option<[u8; 32]>> {
+        self.db()
+            .get_community_member_key(community_id, member_did)?
+            .map(|record| raw_key(decode_hex(&record.encryption_key, "member key")?))
+            .transpose()
+    }
+
+    // ── Channel keys ────────────────────────────────────────────────────
+
+    /// One of a channel's keys: `key_version`, or the latest one.
+    pub fn channel_key(
+        &self,
+        identity: &Identity,
+        channel_id: &str,
+        key_version: Option<i32>,
+    ) -> Result<ChannelKey> {
+        let record = match key_version {
+            Some(version) => self
+                .db()
+                .get_channel_key(channel_id, version)?
+                .ok_or_else(|| {
+                    Error::InvalidKey(format!("Channel key version {} not found", version))
+                })?,
+            None => self
+                .db()
+                .get_latest_channel_key(channel_id)?
+                .ok_or_else(|| Error::InvalidKey("No channel key found".into()))?,
+        };
+        Self::unwrap_stored_key(identity, &record)
+    }
+
+    /// Store a channel key, encrypted with our wrapping key as nonce || ciphertext.
+    fn store_key(&self, identity: &Identity, channel_id: &str, key: &ChannelKey) -> Result<()> {
+        let aad = storage_aad(channel_id, key.key_version);
+        let (nonce, ciphertext) =
+            crate::crypto::encrypt(&wrapping_key(identity), &key.raw_key, aad.as_bytes())?;
+
+        let mut stored = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        stored.extend_from_slice(&nonce.0);
+        stored.extend_from_slice(&ciphertext);
+
+        self.db().store_channel_key(
+            channel_id,
+            key.key_version,
+            &stored,
+            crate::time::now_timestamp(),
+        )
+    }
+
+    fn unwrap_stored_key(identity: &Identity, record: &ChannelKeyRecord) -> Result<ChannelKey> {
+        let stored = &record.encrypted_key;
+        if stored.len() < NONCE_SIZE {
+            return Err(Error::InvalidKey("Invalid stored key format".into()));
+        }
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&stored[..NONCE_SIZE]);
+
+        let aad = storage_aad(&record.channel_id, record.key_version);
+        let raw = crate::crypto::decrypt(
+            &wrapping_key(identity),
+            &Nonce::from_bytes(nonce),
+            &stored[NONCE_SIZE..],
+            aad.as_bytes(),
+        )?;
+        Ok(ChannelKey {
+            key_version: record.key_version,
+            raw_key: raw_key(raw)?,
+        })
+    }
+
+    /// Generate and store the next version of a channel's key.
+    fn new_key(&self, identity: &Identity, channel_id: &str) -> Result<ChannelKey> {
+        use rand::RngCore;
+
+        let current = self.db().get_latest_channel_key(channel_id)?;
+        let mut key = ChannelKey {
+            key_version: current.map(|k| k.key_version + 1).unwrap_or(1),
+            raw_key: [0u8; 32],
+        };
+        rand::rngs::OsRng.fill_bytes(&mut key.raw_key);
+        self.store_key(identity, channel_id, &key)?;
+        self.db()
+            .add_channel_key_holder(channel_id, key.key_version, &identity.did_string())?;
+        Ok(key)
+    }
+
+    // ── Sync ────────────────────────────────────────────────────────────
+
+    /// Bring an encrypted channel's keyring up to date with who can read
+    /// it: rotate the key if a holder has lost access, and hand readers
+    /// the keys they're missing. Needs `ManageChannels` there.
+    pub fn sync_channel_keys(
+        &self,
+        identity: &Identity,
+        channel_id: &str,
+    ) -> Result<KeyringUpdate> {
+        self.update_channel_keys(identity, channel_id, false)
+    }
+
+    /// Rotate an encrypted channel's key now, and hand the new one to
+    /// everyone who can read the channel. Needs `ManageChannels` there.
+    pub fn rotate_channel_key(
+        &self,
+        identity: &Identity,
+        channel_id: &str,
+    ) -> Result<KeyringUpdate> {
+        self.update_channel_keys(identity, channel_id, true)
+    }
+
+    /// [`sync_channel_keys`](Self::sync_channel_keys) for every encrypted
+    /// channel of a community we manage; the others are left alone.
+    ///
+    /// Run after anything that changes who can read a channel: members
+    /// joining, leaving, kicked or banned, role and override changes.
+    pub fn sync_community_keys(
+        &self,
+        identity: &Identity,
+        community_id: &str,
+    ) -> Result<KeyringUpdate> {
+        let our_did = identity.did_string();
+        let mut update = KeyringUpdate::default();
+        for channel in self.db().get_community_channels(community_id)? {
+            if !channel.e2ee_enabled
+                || !self
+                    .channel_permissions(&channel.id, &our_did)?
+                    .has(Permission::ManageChannels)
+            {
+                continue;
+            }
+            update.merge(self.update_channel_keys(identity, &channel.id, false)?);
+        }
+        Ok(update)
+    }
+
+    fn update_channel_keys(
+        &self,
+        identity: &Identity,
+        channel_id: &str,
+        force_rotation: bool,
+    ) -> Result<KeyringUpdate> {
+        let our_did = identity.did_string();
+        let channel =
+            self.require_channel_permission(channel_id, &our_did, Permission::ManageChannels)?;
+        if !channel.e2ee_enabled {
+            return Err(Error::InvalidCommunityOperation(
+                "Channel isn't end-to-end encrypted".to_string(),
+            ));
+        }
+        let community_id = &channel.community_id;
+
+        // Readers, and whether they may read what came before they joined
+        let mut readers = BTreeMap::new();
+        for member in self.db().get_community_members(community_id)? {
+            let permissions = self.channel_permissions(channel_id, &member.member_did)?;
+            if permissions.has(Permission::ViewChannels) {
+                readers.insert(
+                    member.member_did,
+                    permissions.has(Permission::ReadMessageHistory),
+                );
+            }
+        }
+
+        let mut update = KeyringUpdate::default();
+        let latest = match self.db().get_latest_channel_key(channel_id)? {
+            Some(record) => {
+                let holders = self
+                    .db()
+                    .get_channel_key_holders(channel_id, record.key_version)?;
+                if force_rotation || holders.iter().any(|did| !readers.contains_key(did)) {
+                    None
+                } else {
+                    Some(record.key_version)
+                }
+            }
+            None => None,
+        };
+        let latest = match latest {
+            Some(version) => version,
+            None => {
+                let key = self.new_key(identity, channel_id)?;
+                update
+                    .rotated
+                    .insert(channel_id.to_string(), key.key_version);
+                key.key_version
+            }
+        };
+
+        let records = self.db().get_channel_keys(channel_id)?;
+        let mut holders = BTreeMap::new();
+        for record in &records {
+            let dids: BTreeSet<String> = self
+                .db()
+                .get_channel_key_holders(channel_id, record.key_version)?
+                .into_iter()
+                .collect();
+            holders.insert(record.key_version, dids);
+        }
+        let holds =
+            |did: &str, version: i32| holders.get(&version).is_some_and(|h| h.contains(did));
+
+        for (did, reads_history) in &readers {
+            if *did == our_did || holds(did, latest) {
+                continue;
+            }
+            let Some(member_key) = self.member_key(community_id, did)? else {
+                update.awaiting_key.insert(did.clone());
+                continue;
+            };
+
+            let mut keys = Vec::new();
+            for record in &records {
+                let entitled =
+                    record.key_version == latest || (*reads_history && record.key_version < latest);
+                if !entitled || holds(did, record.key_version) {
+                    continue;
+                }
+                let key = Self::unwrap_stored_key(identity, record)?;
+                keys.push(Self::wrap_for_member(
+                    identity,
+                    channel_id,
+                    did,
+                    &member_key,
+                    &key,
+                )?);
+            }
+            for key in &keys {
+                self.db()
+                    .add_channel_key_holder(channel_id, key.key_version, did)?;
+            }
+
+            let grant = ChannelKeyGrant {
+                community_id: community_id.clone(),
+                channel_id: channel_id.to_string(),
+                sender_did: our_did.clone(),
+                recipient_did: did.clone(),
+                keys,
+            };
+            update.relay_messages.push(RelayMessage::new(
+                did,
+                &envelope("community_channel_keys", &grant)?,
+            ));
+        }
+
+        Ok(update)
+    }
+
+    fn wrap_for_member(
+        identity: &Identity,
+        channel_id: &str,
+        member_did: &str,
+        member_key: &[u8; 32],
+        key: &ChannelKey,
+    ) -> Result<WrappedChannelKey> {
+        let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
+            &identity.keypair().encryption,
+            member_key,
+            channel_id.as_bytes(),
+            &key.raw_key,
+            transfer_aad(channel_id, key.key_version, member_did).as_bytes(),
+        )?;
+        Ok(WrappedChannelKey {
+            key_version: key.key_version,
+            encrypted_key: hex::encode(&ciphertext),
+            nonce: hex::encode(nonce.0),
+        })
+    }
+
+    /// Store the channel keys a key manager handed us.
+    ///
+    /// The grant must be for us, from someone with `ManageChannels` in the
+    /// channel. Returns the key versions that were new to us.
+    pub fn receive_channel_keys(
+        &self,
+        identity: &Identity,
+        grant: &ChannelKeyGrant,
+    ) -> Result<Vec<i32>> {
+        let our_did = identity.did_string();
+        if grant.recipient_did != our_did {
+            return Err(Error::InvalidCommunityOperation(
+                "Channel keys are for another member".to_string(),
+            ));
+        }
+        let channel = self.require_channel_permission(
+            &grant.channel_id,
+            &grant.sender_did,
+            Permission::ManageChannels,
+        )?;
+        if channel.community_id != grant.community_id {
+            return Err(Error::ChannelNotFound);
+        }
+        let sender_key = self
+            .member_key(&channel.community_id, &grant.sender_did)?
+            .ok_or_else(|| Error::InvalidKey("Sender hasn't announced a member key".into()))?;
+
+        let mut imported = Vec::new();
+        for wrapped in &grant.keys {
+            let raw = crate::crypto::decrypt_from_sender(
+                &identity.keypair().encryption,
+                &sender_key,
+                grant.channel_id.as_bytes(),
+                &decode_nonce(&wrapped.nonce)?,
+                &decode_hex(&wrapped.encrypted_key, "encrypted key")?,
+                transfer_aad(&grant.channel_id, wrapped.key_version, &our_did).as_bytes(),
+            )?;
+            let key = ChannelKey {
+                key_version: wrapped.key_version,
+                raw_key: raw_key(raw)?,
+            };
+
+            for did in [&grant.sender_did, &our_did] {
+                self.db()
+                    .add_channel_key_holder(&grant.channel_id, key.key_version, did)?;
+            }
+            if self
+                .db()
+                .get_channel_key(&grant.channel_id, key.key_version)?
+                .is_none()
+            {
+                self.store_key(identity, &grant.channel_id, &key)?;
+                imported.push(key.key_version);
+            }
+        }
+        Ok(imported)
+    }
+
+    // ── Messages ────────────────────────────────────────────────────────
+
+    /// Encrypt a message for a channel with its latest key.
+    pub fn encrypt_channel_message(
+        &self,
+        identity: &Identity,
+        channel_id: &str,
+        plaintext: &str,
+    ) -> Result<EncryptedChannelMessage> {
+        let key = self.channel_key(identity, channel_id, None)?;
+        let aad = message_aad(channel_id, &identity.did_string(), key.key_version);
+        let (nonce, ciphertext) = crate::crypto::encrypt(
+            &EncryptionKey::from_bytes(key.raw_key),
+            plaintext.as_bytes(),
+            aad.as_bytes(),
+        )?;
+        Ok(EncryptedChannelMessage {
+            content_encrypted: ciphertext,
+            nonce: hex::encode(nonce.0),
+            key_version: key.key_version,
+        })
+    }
+
+    /// Decrypt a stored channel message with the key version it was
+    /// encrypted with.
+    pub fn decrypt_channel_message(&self, identity: &Identity, message_id: &str) -> Result<String> {
+        let message = self.get_message(message_id)?;
+        let (Some(ciphertext), Some(nonce), Some(key_version)) = (
+            message.content_encrypted.as_ref(),
+            message.nonce.as_deref(),
+            message.key_version,
+        ) else {
+            return Err(Error::InvalidCommunityOperation(
+                "Message isn't end-to-end encrypted".to_string(),
+            ));
+        };
+
+        let key = self.channel_key(identity, &message.channel_id, Some(key_version))?;
+        let aad = message_aad(&message.channel_id, &message.sender_did, key_version);
+        let plaintext = crate::crypto::decrypt(
+            &EncryptionKey::from_bytes(key.raw_key),
+            &decode_nonce(nonce)?,
+            ciphertext,
+            aad.as_bytes(),
+        )?;
+        String::from_utf8(plaintext)
+            .map_err(|e| Error::DecryptionFailed(format!("Message isn't valid UTF-8: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::{CommunityOp, CommunityService, Permissions};
+    use crate::storage::Database;
+    use std::sync::Arc;
+
+    const INVITE: &str = "join-me";
+
+    /// One member's device: their identity and their replica.
+    struct Device {
+        identity: Identity,
+        svc: CommunityService,
+    }
+
+    impl Device {
+        async fn new(name: &str) -> Self {
+            Self {
+                identity: Identity::create(name.to_string()).unwrap().0,
+                svc: CommunityService::new(Arc::new(Database::open(None).await.unwrap())),
+            }
+        }
+
+        fn did(&self) -> String {
+            self.identity.did_string()
+        }
+
+        /// Take in every envelope addressed to us.
+        fn deliver(&self, messages: &[RelayMessage]) -> Vec<i32> {
+            let mut imported = Vec::new();
+            for message in messages.iter().filter(|m| m.to_did == self.did()) {
+                let envelope: serde_json::Value = serde_json::from_str(&message.payload).unwrap();
+                let payload = envelope["payload"].clone();
+                match envelope["envelope"].as_str().unwrap() {
+                    "community_member_key" => self
+                        .svc
+                        .receive_member_key(&serde_json::from_value(payload).unwrap())
+                        .unwrap(),
+                    "community_channel_keys" => imported.extend(
+                        self.svc
+                            .receive_channel_keys(
+                                &self.identity,
+                                &serde_json::from_value(payload).unwrap(),
+                            )
+                            .unwrap(),
+                    ),
+                    other => panic!("unexpected envelope {}", other),
+                }
+            }
+            imported
+        }
+
+        /// Encrypt a message here and store it on `to`'s replica.
+        fn post(&self, to: &Device, channel_id: &str, text: &str) -> String {
+            let encrypted = self
+                .svc
+                .encrypt_channel_message(&self.identity, channel_id, text)
+                .unwrap();
+            to.store(self, channel_id, &encrypted)
+        }
+
+        /// Store a message `from` encrypted on our replica.
+        fn store(
+            &self,
+            from: &Device,
+            channel_id: &str,
+            encrypted: &EncryptedChannelMessage,
+        ) -> String {
+            self.svc
+                .send_encrypted_message(
+                    channel_id,
+                    &from.did(),
+                    &encrypted.content_encrypted,
+                    &encrypted.nonce,
+                    encrypted.key_version,
+                    None,
+                    None,
+                )
+                .unwrap()
+        }
+
+        fn read(&self, message_id: &str) -> Result<String> {
+            self.svc.decrypt_channel_message(&self.identity, message_id)
+        }
+    }
+
+    /// Send `to` everything in the op log it lacks from `from`.
+    fn sync_log(from: &Device, to: &Device, community_id: &str) {
+        let heads = to.svc.op_heads(community_id).unwrap();
+        to.svc
+            .ingest_ops(from.svc.ops_since(community_id, &heads).unwrap())
+            .unwrap();
+    }
+
+    /// Alice's community with its general channel encrypted.
+    async fn community() -> (Device, String, String) {
+        let alice = Device::new("Alice").await;
+        let created = alice
+            .svc
+            .create_signed_community(&alice.identity, "Umbra", None, None)
+            .unwrap();
+        let cid = created.community_id;
+        alice
+            .svc
+            .submit_op(
+                &alice.identity,
+                &cid,
+                CommunityOp::CreateInvite {
+                    code: INVITE.to_string(),
+                    max_uses: None,
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+        let channel = created.general_channel_id;
+        alice
+            .svc
+            .set_channel_e2ee(&channel, true, &alice.did())
+            .unwrap();
+        alice
+            .svc
+            .announce_member_key(&alice.identity, &cid)
+            .unwrap();
+        (alice, cid, channel)
+    }
+
+    /// `name` joins Alice's community and swaps member keys with her.
+    async fn join(alice: &Device, cid: &str, channel: &str, name: &str) -> Device {
+        let device = Device::new(name).await;
+        sync_log(alice, &device, cid);
+        device
+            .svc
+            .submit_op(
+                &device.identity,
+                cid,
+                CommunityOp::Join {
+                    code: INVITE.to_string(),
+                    nickname: None,
+                },
+            )
+            .unwrap();
+        sync_log(&device, alice, cid);
+        device
+            .svc
+            .set_channel_e2ee(channel, true, &alice.did())
+            .unwrap();
+
+        alice.deliver(
+            &device
+                .svc
+                .announce_member_key(&device.identity, cid)
+                .unwrap(),
+        );
+        device.deliver(&alice.svc.announce_member_key(&alice.identity, cid).unwrap());
+        device
+    }
+
+    fn sync_keys(alice: &Device, channel: &str) -> KeyringUpdate {
+        alice
+            .svc
+            .sync_channel_keys(&alice.identity, channel)
+            .unwrap()
+    }
+
+    fn deny(alice: &Device, channel: &str, member: &Device, permission: Permission) {
+        let mut denied = Permissions::NONE;
+        denied.add(permission);
+        alice
+            .svc
+            .set_channel_override(
+                channel,
+                "member",
+                &member.did(),
+                "0",
+                &denied.to_string_repr(),
+                &alice.did(),
+            )
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_member_key_announcement() {
+        let (alice, cid, channel) = community().await;
+        let bob = join(&alice, &cid, &channel, "Bob").await;
+
+        let key = alice
+            .svc
+            .db()
+            .get_community_member_key(&cid, &bob.did())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            key.encryption_key,
+            hex::encode(bob.identity.keypair().encryption.public_bytes())
+        );
+
+        let announcement = MemberKeyAnnouncement::sign(&bob.identity, &cid, 1).unwrap();
+        announcement.verify().unwrap();
+        let mut tampered = announcement.clone();
+        tampered.encryption_key = [7u8; 32];
+        assert!(tampered.verify().is_err());
+        assert!(alice.svc.receive_member_key(&tampered).is_err());
+
+        // An older announcement doesn't replace the current key
+        let mut stale = MemberKeyAnnouncement::sign(&bob.identity, &cid, 1).unwrap();
+        stale.encryption_key = [7u8; 32];
+        stale.signature = sign(
+            &bob.identity.keypair().signing,
+            &stale.sign_bytes().unwrap(),
+        )
+        .to_hex();
+        alice.svc.receive_member_key(&stale).unwrap();
+        assert_eq!(
+            alice
+                .svc
+                .db()
+                .get_community_member_key(&cid, &bob.did())
+                .unwrap()
+                .unwrap()
+                .encryption_key,
+            key.encryption_key
+        );
+
+        // Only members can announce
+        let mallory = Device::new("Mallory").await;
+        let outsider = MemberKeyAnnouncement::sign(&mallory.identity, &cid, 1).unwrap();
+        assert!(matches!(
+            alice.svc.receive_member_key(&outsider),
+            Err(Error::NotMember)
+        ));
+        assert!(matches!(
+            mallory.svc.announce_member_key(&mallory.identity, &cid),
+            Err(Error::NotMember)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_channel_keys_reach_readers() {
+        let (alice, cid, channel) = community().await;
+        let bob = Device::new("Bob").await;
+        sync_log(&alice, &bob, &cid);
+        bob.svc
+            .submit_op(
+                &bob.identity,
+                &cid,
+                CommunityOp::Join {
+                    code: INVITE.to_string(),
+                    nickname: None,
+                },
+            )
+            .unwrap();
+        sync_log(&bob, &alice, &cid);
+        bob.svc
+            .set_channel_e2ee(&channel, true, &alice.did())
+            .unwrap();
+
+        // Bob hasn't announced a member key, so gets nothing yet
+        let update = sync_keys(&alice, &channel);
+        assert_eq!(update.rotated.get(&channel), Some(&1));
+        assert!(update.awaiting_key.contains(&bob.did()));
+        assert!(update.relay_messages.is_empty());
+
+        alice.deliver(&bob.svc.announce_member_key(&bob.identity, &cid).unwrap());
+        bob.deliver(
+            &alice
+                .svc
+                .announce_member_key(&alice.identity, &cid)
+                .unwrap(),
+        );
+        let update = sync_keys(&alice, &channel);
+        assert!(update.rotated.is_empty());
+        assert!(update.awaiting_key.is_empty());
+        assert_eq!(update.relay_messages.len(), 1);
+        assert_eq!(bob.deliver(&update.relay_messages), vec![1]);
+
+        // Nothing left to hand out
+        assert!(sync_keys(&alice, &channel).relay_messages.is_empty());
+
+        let from_alice = alice.post(&bob, &channel, "hello bob");
+        assert_eq!(bob.read(&from_alice).unwrap(), "hello bob");
+        let from_bob = bob.post(&alice, &channel, "hi alice");
+        assert_eq!(alice.read(&from_bob).unwrap(), "hi alice");
+
+        // Only key managers sync, and only for encrypted channels
+        assert!(matches!(
+            bob.svc.sync_channel_keys(&bob.identity, &channel),
+            Err(Error::PermissionDenied { .. })
+        ));
+        alice
+            .svc
+            .set_channel_e2ee(&channel, false, &alice.did())
+            .unwrap();
+        assert!(alice
+            .svc
+            .sync_channel_keys(&alice.identity, &channel)
+            .is_err());
+        assert!(alice
+            .svc
+            .sync_community_keys(&alice.identity, &cid)
+            .unwrap()
+            .relay_messages
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_grants_are_checked() {
+        let (alice, cid, channel) = community().await;
+        let bob = join(&alice, &cid, &channel, "Bob").await;
+        let carol = join(&alice, &cid, &channel, "Carol").await;
+        carol.deliver(&bob.svc.announce_member_key(&bob.identity, &cid).unwrap());
+
+        let update = sync_keys(&alice, &channel);
+        let grant: ChannelKeyGrant = {
+            let message = update
+                .relay_messages
+                .iter()
+                .find(|m| m.to_did == bob.did())
+                .unwrap();
+            let envelope: serde_json::Value = serde_json::from_str(&message.payload).unwrap();
+            serde_json::from_value(envelope["payload"].clone()).unwrap()
+        };
+
+        // Not addressed to Carol
+        assert!(carol
+            .svc
+            .receive_channel_keys(&carol.identity, &grant)
+            .is_err());
+
+        // Bob can't hand out keys
+        let mut forged = grant.clone();
+        forged.sender_did = bob.did();
+        forged.recipient_did = carol.did();
+        assert!(matches!(
+            carol.svc.receive_channel_keys(&carol.identity, &forged),
+            Err(Error::PermissionDenied { .. })
+        ));
+
+        // Keys wrapped for Bob don't open for anyone else
+        let mut redirected = grant.clone();
+        redirected.recipient_did = carol.did();
+        assert!(carol
+            .svc
+            .receive_channel_keys(&carol.identity, &redirected)
+            .is_err());
+
+        assert_eq!(
+            bob.svc.receive_channel_keys(&bob.identity, &grant).unwrap(),
+            vec![1]
+        );
+        // Taking the same grant twice imports nothing new
+        assert!(bob
+            .svc
+            .receive_channel_keys(&bob.identity, &grant)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_kick_rotates_key() {
+        let (alice, cid, channel) = community().await;
+        let bob = join(&alice, &cid, &channel, "Bob").await;
+        let carol = join(&alice, &cid, &channel, "Carol").await;
+        let update = sync_keys(&alice, &channel);
+        bob.deliver(&update.relay_messages);
+        carol.deliver(&update.relay_messages);
+        let before = alice.post(&bob, &channel, "before");
+
+        alice
+            .svc
+            .submit_op(
+                &alice.identity,
+                &cid,
+                CommunityOp::Kick {
+                    member_did: carol.did(),
+                },
+            )
+            .unwrap();
+        let update = alice
+            .svc
+            .sync_community_keys(&alice.identity, &cid)
+            .unwrap();
+        assert_eq!(update.rotated.get(&channel), Some(&2));
+        assert_eq!(update.relay_messages.len(), 1);
+        assert_eq!(update.relay_messages[0].to_did, bob.did());
+        assert_eq!(bob.deliver(&update.relay_messages), vec![2]);
+
+        // New messages use the new key, which Carol never got
+        let after = alice.post(&bob, &channel, "after");
+        assert_eq!(bob.read(&before).unwrap(), "before");
+        assert_eq!(bob.read(&after).unwrap(), "after");
+        let leaked = alice.post(&carol, &channel, "after");
+        assert!(carol.read(&leaked).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_losing_view_rotates_key() {
+        let (alice, cid, channel) = community().await;
+        let bob = join(&alice, &cid, &channel, "Bob").await;
+        let carol = join(&alice, &cid, &channel, "Carol").await;
+        let update = sync_keys(&alice, &channel);
+        bob.deliver(&update.relay_messages);
+        carol.deliver(&update.relay_messages);
+
+        // Losing history alone doesn't take away the current key
+        deny(&alice, &channel, &carol, Permission::ReadMessageHistory);
+        assert!(sync_keys(&alice, &channel).rotated.is_empty());
+
+        deny(&alice, &channel, &carol, Permission::ViewChannels);
+        let update = sync_keys(&alice, &channel);
+        assert_eq!(update.rotated.get(&channel), Some(&2));
+        assert_eq!(bob.deliver(&update.relay_messages), vec![2]);
+        assert!(carol.deliver(&update.relay_messages).is_empty());
+
+        // Forced rotations reach every reader
+        let update = alice
+            .svc
+            .rotate_channel_key(&alice.identity, &channel)
+            .unwrap();
+        assert_eq!(update.rotated.get(&channel), Some(&3));
+        assert_eq!(bob.deliver(&update.relay_messages), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_history_needs_read_message_history() {
+        let (alice, cid, channel) = community().await;
+        let bob = join(&alice, &cid, &channel, "Bob").await;
+        bob.deliver(&sync_keys(&alice, &channel).relay_messages);
+        let old = alice
+            .svc
+            .encrypt_channel_message(&alice.identity, &channel, "old news")
+            .unwrap();
+        assert_eq!(old.key_version, 1);
+        let update = alice
+            .svc
+            .rotate_channel_key(&alice.identity, &channel)
+            .unwrap();
+        bob.deliver(&update.relay_messages);
+
+        let carol = join(&alice, &cid, &channel, "Carol").await;
+        let dave = join(&alice, &cid, &channel, "Dave").await;
+        deny(&alice, &channel, &dave, Permission::ReadMessageHistory);
+        let update = sync_keys(&alice, &channel);
+        assert!(update.rotated.is_empty());
+        assert_eq!(update.relay_messages.len(), 2);
+        assert_eq!(carol.deliver(&update.relay_messages), vec![1, 2]);
+        assert_eq!(dave.deliver(&update.relay_messages), vec![2]);
+
+        // Carol reads history from before she joined; Dave only what's new
+        let old_for_carol = carol.store(&alice, &channel, &old);
+        let old_for_dave = dave.store(&alice, &channel, &old);
+        assert_eq!(carol.read(&old_for_carol).unwrap(), "old news");
+        assert!(dave.read(&old_for_dave).is_err());
+        let new_for_dave = alice.post(&dave, &channel, "news");
+        assert_eq!(dave.read(&new_for_dave).unwrap(), "news");
+    }
+}
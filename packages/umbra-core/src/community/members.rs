@@ -2,6 +2,7 @@
 //!
 //! Join, leave, kick, ban, and member profile operations.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::{CommunityBanRecord, CommunityMemberRecord, CommunityRoleRecord};
@@ -14,6 +15,8 @@ impl super::CommunityService {
         member_did: &str,
         nickname: Option<&str>,
     ) -> Result<()> {
+        self.require_unsigned(community_id)?;
+
         // Check if banned
         if self.db().is_community_banned(community_id, member_did)? {
             return Err(Error::BannedFromCommunity);
@@ -65,6 +68,7 @@ impl super::CommunityService {
     /// Remove self from a community (leave).
     pub fn leave_community(&self, community_id: &str, member_did: &str) -> Result<()> {
         let community = self.get_community(community_id)?;
+        self.require_unsigned(community_id)?;
         if community.owner_did == member_did {
             return Err(Error::InvalidCommunityOperation(
                 "Owner cannot leave. Transfer ownership first.".to_string(),
@@ -91,10 +95,9 @@ impl super::CommunityService {
 
     /// Kick a member from a community.
     pub fn kick_member(&self, community_id: &str, target_did: &str, actor_did: &str) -> Result<()> {
-        let community = self.get_community(community_id)?;
-        if community.owner_did == target_did {
-            return Err(Error::CannotModifyOwner);
-        }
+        self.require_permission(community_id, actor_did, Permission::KickMembers)?;
+        self.require_unsigned(community_id)?;
+        self.require_above(community_id, actor_did, target_did, Permission::KickMembers)?;
 
         let now = crate::time::now_timestamp();
         self.db()
@@ -124,10 +127,9 @@ impl super::CommunityService {
         device_fingerprint: Option<&str>,
         actor_did: &str,
     ) -> Result<()> {
-        let community = self.get_community(community_id)?;
-        if community.owner_did == target_did {
-            return Err(Error::CannotModifyOwner);
-        }
+        self.require_permission(community_id, actor_did, Permission::BanMembers)?;
+        self.require_unsigned(community_id)?;
+        self.require_above(community_id, actor_did, target_did, Permission::BanMembers)?;
 
         let now = crate::time::now_timestamp();
 
@@ -167,6 +169,8 @@ impl super::CommunityService {
         target_did: &str,
         actor_did: &str,
     ) -> Result<()> {
+        self.require_permission(community_id, actor_did, Permission::BanMembers)?;
+        self.require_unsigned(community_id)?;
         let now = crate::time::now_timestamp();
         self.db().remove_community_ban(community_id, target_did)?;
 
@@ -201,6 +205,9 @@ impl super::CommunityService {
     }
 
     /// Update a member's community profile (nickname, avatar, bio).
+    ///
+    /// Members edit their own profile; others can only change a member's
+    /// nickname, with `ManageNicknames` and a higher role.
     pub fn update_member_profile(
         &self,
         community_id: &str,
@@ -208,7 +215,28 @@ impl super::CommunityService {
         nickname: Option<&str>,
         avatar_url: Option<&str>,
         bio: Option<&str>,
+        actor_did: &str,
     ) -> Result<()> {
+        if actor_did == member_did {
+            if nickname.is_some() {
+                self.require_permission(community_id, actor_did, Permission::ChangeNickname)?;
+                self.require_unsigned(community_id)?;
+            }
+        } else {
+            if avatar_url.is_some() || bio.is_some() {
+                return Err(Error::InsufficientPermissions(
+                    "Can only change another member's nickname".to_string(),
+                ));
+            }
+            self.require_permission(community_id, actor_did, Permission::ManageNicknames)?;
+            self.require_above(
+                community_id,
+                actor_did,
+                member_did,
+                Permission::ManageNicknames,
+            )?;
+        }
+
         self.db().update_community_member_profile(
             community_id,
             member_did,
@@ -231,6 +259,7 @@ impl super::CommunityService {
         role_id: &str,
         actor_did: &str,
     ) -> Result<()> {
+        self.require_role_assignable(community_id, role_id, actor_did)?;
         let now = crate::time::now_timestamp();
         self.db()
             .assign_community_role(community_id, member_did, role_id, now, Some(actor_did))?;
@@ -257,6 +286,7 @@ impl super::CommunityService {
         role_id: &str,
         actor_did: &str,
     ) -> Result<()> {
+        self.require_role_assignable(community_id, role_id, actor_did)?;
         self.db()
             .unassign_community_role(community_id, member_did, role_id)?;
 
@@ -275,6 +305,23 @@ impl super::CommunityService {
         Ok(())
     }
 
+    /// Require that `actor_did` may give out or take away `role_id`.
+    fn require_role_assignable(
+        &self,
+        community_id: &str,
+        role_id: &str,
+        actor_did: &str,
+    ) -> Result<()> {
+        let role = self
+            .db()
+            .get_community_role(role_id)?
+            .filter(|role| role.community_id == community_id)
+            .ok_or(Error::RoleNotFound)?;
+        self.require_permission(community_id, actor_did, Permission::ManageRoles)?;
+        self.require_unsigned(community_id)?;
+        self.require_role_below(actor_did, &role)
+    }
+
     /// Get all roles for a community.
     pub fn get_roles(&self, community_id: &str) -> Result<Vec<CommunityRoleRecord>> {
         self.db().get_community_roles(community_id)
@@ -3,6 +3,7 @@
 //! Message CRUD, reactions, read receipts, pins, slow mode enforcement,
 //! channel type enforcement, and @mention parsing.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::{
@@ -59,9 +60,12 @@ impl super::CommunityService {
     /// Send a plaintext message to a channel (non-E2EE).
     ///
     /// Enforces:
-    /// - Channel type restrictions (voice-only, announcement, welcome)
-    /// - Mute timeouts
+    /// - `SendMessages` (`SendThreadMessages` in a thread), with channel
+    ///   overrides and mute timeouts
+    /// - Channel type restrictions (voice-only, announcement)
     /// - Slow mode cooldowns
+    ///
+    /// Log-backed communities take plaintext messages as signed ops.
     pub fn send_message(
         &self,
         channel_id: &str,
@@ -75,71 +79,8 @@ impl super::CommunityService {
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
-        // Get channel and enforce type restrictions
-        let channel = self
-            .db()
-            .get_community_channel(channel_id)?
-            .ok_or(Error::ChannelNotFound)?;
-
-        // Voice channels don't support text messages
-        if channel.channel_type == "voice" {
-            return Err(Error::ChannelTypeRestriction(
-                "Cannot send text messages in a voice channel".to_string(),
-            ));
-        }
-
-        // Announcement channels: restricted posting (only admins/mods)
-        // The frontend should check permissions; we enforce at service level by checking
-        // if sender is the community owner (more granular checks via permission system)
-        if channel.channel_type == "announcement" {
-            let community = self.get_community(&channel.community_id)?;
-            if community.owner_did != sender_did {
-                // Check if member has ManageChannels permission via roles
-                // For now, only community owner can post in announcement channels
-                // Full permission check would integrate with the permission bitfield system
-                let member_roles = self
-                    .db()
-                    .get_member_community_roles(&channel.community_id, sender_did)?;
-                let has_announce_perm = member_roles.iter().any(|r| {
-                    let bits: u64 = r.permissions_bitfield.parse().unwrap_or(0);
-                    // Administrator (bit 0) or ManageChannels (bit 4)
-                    bits & 1 != 0 || bits & (1 << 4) != 0
-                });
-                if !has_announce_perm {
-                    return Err(Error::ChannelTypeRestriction(
-                        "Only administrators and moderators can post in announcement channels"
-                            .to_string(),
-                    ));
-                }
-            }
-        }
-
-        // Check mute timeout
-        if self
-            .db()
-            .is_member_timed_out(&channel.community_id, sender_did, "mute", now)?
-        {
-            return Err(Error::MemberTimedOut(
-                "You are currently muted in this community".to_string(),
-            ));
-        }
-
-        // Check slow mode
-        if channel.slow_mode_seconds > 0 {
-            // Check last message time from this sender
-            let recent = self.db().get_community_messages(channel_id, 1, None)?;
-            if let Some(last) = recent.last() {
-                if last.sender_did == sender_did {
-                    let cooldown = channel.slow_mode_seconds as i64;
-                    if now - last.created_at < cooldown {
-                        return Err(Error::InvalidCommunityOperation(format!(
-                            "Slow mode: wait {} seconds between messages",
-                            channel.slow_mode_seconds
-                        )));
-                    }
-                }
-            }
-        }
+        self.require_unsigned(&self.get_channel(channel_id)?.community_id)?;
+        self.require_can_post(channel_id, sender_did, thread_id, now)?;
 
         self.db().store_community_message(
             &id,
@@ -185,7 +126,8 @@ impl super::CommunityService {
         })
     }
 
-    /// Send an encrypted message to a channel (E2EE).
+    /// Send an encrypted message to a channel (E2EE), with the same checks
+    /// as [`send_message`](Self::send_message).
     #[allow(clippy::too_many_arguments)]
     pub fn send_encrypted_message(
         &self,
@@ -200,6 +142,8 @@ impl super::CommunityService {
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
+        self.require_can_post(channel_id, sender_did, thread_id, now)?;
+
         self.db().store_community_message(
             &id,
             channel_id,
@@ -225,11 +169,74 @@ impl super::CommunityService {
         Ok(id)
     }
 
+    /// Require that a message isn't a log-backed community's plaintext
+    /// message, which only changes through signed ops. (E2EE messages
+    /// aren't in the log.)
+    fn require_unsigned_message(&self, msg: &CommunityMessageRecord) -> Result<()> {
+        if msg.is_e2ee {
+            return Ok(());
+        }
+        self.require_unsigned(&self.get_channel(&msg.channel_id)?.community_id)
+    }
+
+    /// Check `sender_did` may post in a channel now.
+    fn require_can_post(
+        &self,
+        channel_id: &str,
+        sender_did: &str,
+        thread_id: Option<&str>,
+        now: i64,
+    ) -> Result<()> {
+        let channel = self.get_channel(channel_id)?;
+
+        // Voice channels don't support text messages
+        if channel.channel_type == "voice" {
+            return Err(Error::ChannelTypeRestriction(
+                "Cannot send text messages in a voice channel".to_string(),
+            ));
+        }
+
+        let required = if thread_id.is_some() {
+            Permission::SendThreadMessages
+        } else {
+            Permission::SendMessages
+        };
+        self.require_channel_permission(channel_id, sender_did, required)?;
+
+        // Announcement channels: only those who manage channels can post
+        if channel.channel_type == "announcement" {
+            self.require_channel_permission(channel_id, sender_did, Permission::ManageChannels)?;
+        }
+
+        // Check slow mode
+        if channel.slow_mode_seconds > 0 {
+            // Check last message time from this sender
+            let recent = self.db().get_community_messages(channel_id, 1, None)?;
+            if let Some(last) = recent.last() {
+                if last.sender_did == sender_did {
+                    let cooldown = channel.slow_mode_seconds as i64;
+                    if now - last.created_at < cooldown {
+                        return Err(Error::InvalidCommunityOperation(format!(
+                            "Slow mode: wait {} seconds between messages",
+                            channel.slow_mode_seconds
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Store a received message from another member (via relay / bridge).
     ///
     /// Unlike `send_message`, this skips permission checks and slow-mode
     /// enforcement because the message was authored elsewhere. Uses
     /// INSERT OR IGNORE so duplicate IDs are silently skipped.
+    ///
+    /// Log-backed communities receive messages as signed ops instead,
+    /// which are checked on receipt (see
+    /// [`ingest_ops`](Self::ingest_ops)); unsigned ones are refused.
     pub fn store_received_message(
         &self,
         id: &str,
@@ -239,6 +246,7 @@ impl super::CommunityService {
         created_at: i64,
         metadata_json: Option<&str>,
     ) -> Result<()> {
+        self.require_unsigned(&self.get_channel(channel_id)?.community_id)?;
         self.db().store_community_message_if_not_exists(
             id,
             channel_id,
@@ -279,6 +287,7 @@ impl super::CommunityService {
     /// Edit a message.
     pub fn edit_message(&self, id: &str, new_content: &str, editor_did: &str) -> Result<()> {
         let msg = self.get_message(id)?;
+        self.require_unsigned_message(&msg)?;
         if msg.sender_did != editor_did {
             return Err(Error::InsufficientPermissions(
                 "Can only edit your own messages".to_string(),
@@ -289,8 +298,14 @@ impl super::CommunityService {
             .edit_community_message(id, Some(new_content), None, None, now)
     }
 
-    /// Delete a message for everyone (mod/admin or sender).
-    pub fn delete_message_for_everyone(&self, id: &str) -> Result<()> {
+    /// Delete a message for everyone (its sender, or anyone with
+    /// `ManageMessages` in the channel).
+    pub fn delete_message_for_everyone(&self, id: &str, actor_did: &str) -> Result<()> {
+        let msg = self.get_message(id)?;
+        self.require_unsigned_message(&msg)?;
+        if msg.sender_did != actor_did {
+            self.require_channel_permission(&msg.channel_id, actor_did, Permission::ManageMessages)?;
+        }
         self.db().delete_community_message_for_everyone(id)
     }
 
@@ -311,6 +326,8 @@ impl super::CommunityService {
         emoji: &str,
         is_custom: bool,
     ) -> Result<()> {
+        let msg = self.get_message(message_id)?;
+        self.require_channel_permission(&msg.channel_id, member_did, Permission::AddReactions)?;
         let now = crate::time::now_timestamp();
         self.db()
             .add_community_reaction(message_id, member_did, emoji, is_custom, now)
@@ -350,11 +367,10 @@ impl super::CommunityService {
 
     /// Pin a message in a channel (respects pin limit).
     pub fn pin_message(&self, channel_id: &str, message_id: &str, pinned_by: &str) -> Result<()> {
+        let channel =
+            self.require_channel_permission(channel_id, pinned_by, Permission::ManageMessages)?;
+
         // Check pin limit
-        let channel = self
-            .db()
-            .get_community_channel(channel_id)?
-            .ok_or(Error::ChannelNotFound)?;
         let count = self.db().get_community_pin_count(channel_id)?;
         if count >= channel.pin_limit {
             return Err(Error::InvalidCommunityOperation(format!(
@@ -369,7 +385,8 @@ impl super::CommunityService {
     }
 
     /// Unpin a message.
-    pub fn unpin_message(&self, channel_id: &str, message_id: &str) -> Result<()> {
+    pub fn unpin_message(&self, channel_id: &str, message_id: &str, actor_did: &str) -> Result<()> {
+        self.require_channel_permission(channel_id, actor_did, Permission::ManageMessages)?;
         self.db().unpin_community_message(channel_id, message_id)
     }
 
@@ -410,6 +427,7 @@ impl super::CommunityService {
         channel_id: &str,
         content: &str,
     ) -> Result<CommunityMessageRecord> {
+        self.require_unsigned(&self.get_channel(channel_id)?.community_id)?;
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
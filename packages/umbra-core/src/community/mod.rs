@@ -31,6 +31,7 @@
 //! └─────────────────────────────────────────────────────────────────────────┘
 //! ```
 
+mod authorization;
 mod boost_nodes;
 mod categories;
 mod channels;
@@ -38,10 +39,12 @@ mod customization;
 mod files;
 mod integrations;
 mod invites;
+mod keyring;
 mod member_experience;
 mod members;
 mod messaging;
 mod moderation;
+mod oplog;
 mod permissions;
 mod roles;
 mod seats;
@@ -49,7 +52,15 @@ mod service;
 mod spaces;
 mod threads;
 
+pub use keyring::{
+    ChannelKey, ChannelKeyGrant, EncryptedChannelMessage, KeyringUpdate, MemberKeyAnnouncement,
+    WrappedChannelKey, KEYRING_VERSION,
+};
 pub use messaging::{parse_mentions, MentionType};
+pub use oplog::{
+    community_id_for, op_envelope, CommunityOp, IngestReport, RejectedOp, SignedOp, SubmittedOp,
+    OP_VERSION,
+};
 pub use permissions::{Permission, Permissions};
 pub use roles::RolePreset;
 pub use seats::SeatInput;
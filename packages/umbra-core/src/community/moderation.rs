@@ -2,6 +2,7 @@
 //!
 //! Warnings, timeouts, AutoMod keyword filtering, and audit log extensions.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::CommunityWarningRecord;
@@ -22,15 +23,17 @@ impl super::CommunityService {
         warned_by: &str,
         expires_at: Option<i64>,
     ) -> Result<CommunityWarningRecord> {
+        self.require_permission(community_id, warned_by, Permission::TimeoutMembers)?;
+        self.require_above(
+            community_id,
+            warned_by,
+            member_did,
+            Permission::TimeoutMembers,
+        )?;
+
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
-        // Prevent warning the owner
-        let community = self.get_community(community_id)?;
-        if community.owner_did == member_did {
-            return Err(Error::CannotModifyOwner);
-        }
-
         self.db().create_community_warning(
             &id,
             community_id,
@@ -94,10 +97,24 @@ impl super::CommunityService {
 
     /// Delete a warning.
     pub fn delete_warning(&self, warning_id: &str, actor_did: &str) -> Result<()> {
+        let warning = self
+            .db()
+            .get_community_warning(warning_id)?
+            .ok_or_else(|| Error::InvalidCommunityOperation("Warning not found".to_string()))?;
+        self.require_permission(&warning.community_id, actor_did, Permission::TimeoutMembers)?;
+
         self.db().delete_community_warning(warning_id)?;
 
-        // We don't know community_id from just warning_id, so skip audit log here
-        let _ = actor_did; // acknowledged
+        self.db().insert_audit_log(
+            &generate_id(),
+            &warning.community_id,
+            actor_did,
+            "warning_delete",
+            Some("member"),
+            Some(&warning.member_did),
+            None,
+            crate::time::now_timestamp(),
+        )?;
         Ok(())
     }
 
@@ -181,10 +198,13 @@ impl super::CommunityService {
             ));
         }
 
-        let community = self.get_community(community_id)?;
-        if community.owner_did == member_did {
-            return Err(Error::CannotModifyOwner);
-        }
+        self.require_permission(community_id, issued_by, Permission::TimeoutMembers)?;
+        self.require_above(
+            community_id,
+            issued_by,
+            member_did,
+            Permission::TimeoutMembers,
+        )?;
 
         let now = crate::time::now_timestamp();
         let id = generate_id();
@@ -222,8 +242,24 @@ impl super::CommunityService {
 
     /// Remove a timeout early.
     pub fn remove_timeout(&self, timeout_id: &str, actor_did: &str) -> Result<()> {
+        let timeout = self
+            .db()
+            .get_community_timeout(timeout_id)?
+            .ok_or_else(|| Error::InvalidCommunityOperation("Timeout not found".to_string()))?;
+        self.require_permission(&timeout.community_id, actor_did, Permission::TimeoutMembers)?;
+
         self.db().remove_community_timeout(timeout_id)?;
-        let _ = actor_did; // acknowledged — would need timeout record for full audit log
+
+        self.db().insert_audit_log(
+            &generate_id(),
+            &timeout.community_id,
+            actor_did,
+            "member_timeout_remove",
+            Some("member"),
+            Some(&timeout.member_did),
+            None,
+            crate::time::now_timestamp(),
+        )?;
         Ok(())
     }
 
@@ -0,0 +1,311 @@
+//! # Materialization
+//!
+//! Bringing a community's SQLite rows from one replayed state to the
+//! next. Only what changed is written; children are removed before their
+//! parents and created after them.
+
+use super::state::{CommunityState, MessageState};
+use crate::error::Result;
+use crate::storage::Database;
+
+/// Write the difference between `prev` and `next` for `community_id`.
+pub(crate) fn materialize(
+    db: &Database,
+    community_id: &str,
+    prev: &CommunityState,
+    next: &CommunityState,
+) -> Result<()> {
+    if next.genesis_id.is_none() {
+        return Ok(());
+    }
+    let now = crate::time::now_timestamp();
+
+    // ── Removals, children first ─────────────────────────────────────────
+    for (id, old) in &prev.messages {
+        match next.messages.get(id) {
+            None => db.delete_community_message(id)?,
+            // A delete that no longer holds: bring the message back
+            Some(new) if old.deleted && !new.deleted => {
+                db.delete_community_message(id)?;
+                write_message(db, id, new)?;
+            }
+            _ => {}
+        }
+    }
+    for id in prev
+        .invites
+        .keys()
+        .filter(|id| !next.invites.contains_key(*id))
+    {
+        db.delete_community_invite(id)?;
+    }
+    for did in prev.bans.keys().filter(|did| !next.bans.contains_key(*did)) {
+        db.remove_community_ban(community_id, did)?;
+    }
+    for (did, old) in &prev.members {
+        match next.members.get(did) {
+            None => db.remove_community_member(community_id, did)?,
+            Some(new) => {
+                for role_id in old.roles.difference(&new.roles) {
+                    db.unassign_community_role(community_id, did, role_id)?;
+                }
+            }
+        }
+    }
+    for id in prev.roles.keys().filter(|id| !next.roles.contains_key(*id)) {
+        db.delete_community_role(id)?;
+    }
+    for id in prev
+        .channels
+        .keys()
+        .filter(|id| !next.channels.contains_key(*id))
+    {
+        db.delete_community_channel(id)?;
+    }
+    for id in prev
+        .categories
+        .keys()
+        .filter(|id| !next.categories.contains_key(*id))
+    {
+        db.delete_community_category(id)?;
+    }
+    for id in prev
+        .spaces
+        .keys()
+        .filter(|id| !next.spaces.contains_key(*id))
+    {
+        db.delete_community_space(id)?;
+    }
+
+    // ── The community itself ─────────────────────────────────────────────
+    if db.get_community(community_id)?.is_none() {
+        db.create_community_record(
+            community_id,
+            &next.name,
+            next.description.as_deref(),
+            &next.owner_did,
+            None,
+            next.created_at,
+        )?;
+    } else if prev.name != next.name
+        || prev.description != next.description
+        || prev.genesis_id != next.genesis_id
+    {
+        db.update_community(
+            community_id,
+            Some(&next.name),
+            next.description.as_deref(),
+            next.updated_at,
+        )?;
+    }
+    if prev.owner_did != next.owner_did {
+        db.update_community_owner(community_id, &next.owner_did, next.updated_at)?;
+    }
+
+    // ── Creations and updates, parents first ─────────────────────────────
+    for (id, space) in &next.spaces {
+        if !prev.spaces.contains_key(id) {
+            db.create_community_space(
+                id,
+                community_id,
+                &space.name,
+                space.position,
+                space.created_at,
+            )?;
+        }
+    }
+    for (id, category) in &next.categories {
+        if !prev.categories.contains_key(id) {
+            db.create_community_category(
+                id,
+                community_id,
+                &category.space_id,
+                &category.name,
+                category.position,
+                category.created_at,
+            )?;
+        }
+    }
+    for (id, channel) in &next.channels {
+        match prev.channels.get(id) {
+            None => {
+                db.create_community_channel(
+                    id,
+                    community_id,
+                    &channel.space_id,
+                    channel.category_id.as_deref(),
+                    &channel.name,
+                    &channel.channel_type,
+                    channel.topic.as_deref(),
+                    channel.position,
+                    channel.created_at,
+                )?;
+                if channel.slow_mode_seconds > 0 {
+                    db.update_channel_slow_mode(id, channel.slow_mode_seconds as i32, now)?;
+                }
+            }
+            Some(old) => {
+                if old.name != channel.name || old.topic != channel.topic {
+                    db.update_community_channel(
+                        id,
+                        Some(&channel.name),
+                        channel.topic.as_deref(),
+                        now,
+                    )?;
+                }
+                if old.slow_mode_seconds != channel.slow_mode_seconds {
+                    db.update_channel_slow_mode(id, channel.slow_mode_seconds as i32, now)?;
+                }
+                if old.category_id != channel.category_id {
+                    db.update_channel_category(id, channel.category_id.as_deref(), now)?;
+                }
+            }
+        }
+    }
+    for (id, role) in &next.roles {
+        match prev.roles.get(id) {
+            None => db.create_community_role(
+                id,
+                community_id,
+                &role.name,
+                role.color.as_deref(),
+                role.position,
+                role.hoisted,
+                false,
+                role.is_preset,
+                &role.permissions.to_string_repr(),
+                role.created_at,
+            )?,
+            Some(old) => {
+                if old.name != role.name || old.color != role.color || old.position != role.position
+                {
+                    db.update_community_role(
+                        id,
+                        Some(&role.name),
+                        role.color.as_deref(),
+                        None,
+                        None,
+                        Some(role.position),
+                        now,
+                    )?;
+                }
+                if old.permissions != role.permissions {
+                    db.update_community_role_permissions(
+                        id,
+                        &role.permissions.to_string_repr(),
+                        now,
+                    )?;
+                }
+            }
+        }
+    }
+    for (did, member) in &next.members {
+        let old_roles = match prev.members.get(did) {
+            Some(old) => Some(&old.roles),
+            None => {
+                db.add_community_member(
+                    community_id,
+                    did,
+                    member.joined_at,
+                    member.nickname.as_deref(),
+                )?;
+                None
+            }
+        };
+        for role_id in &member.roles {
+            if old_roles.map_or(true, |roles| !roles.contains(role_id)) {
+                db.assign_community_role(community_id, did, role_id, member.joined_at, None)?;
+            }
+        }
+    }
+    for (did, ban) in &next.bans {
+        match prev.bans.get(did) {
+            Some(old) if old == ban => continue,
+            Some(_) => db.remove_community_ban(community_id, did)?,
+            None => {}
+        }
+        db.create_community_ban(
+            community_id,
+            did,
+            ban.reason.as_deref(),
+            &ban.banned_by,
+            None,
+            ban.expires_at,
+            ban.created_at,
+        )?;
+    }
+    for (id, invite) in &next.invites {
+        let written_uses = match prev.invites.get(id) {
+            Some(old) if old.uses <= invite.uses => old.uses,
+            old => {
+                // Use counts only go up, so fewer uses means starting over
+                if old.is_some() {
+                    db.delete_community_invite(id)?;
+                }
+                db.create_community_invite(
+                    id,
+                    community_id,
+                    &invite.code,
+                    false,
+                    &invite.creator_did,
+                    invite.max_uses.map(|m| m as i32),
+                    invite.expires_at,
+                    invite.created_at,
+                )?;
+                0
+            }
+        };
+        for _ in written_uses..invite.uses {
+            db.increment_invite_use_count(id)?;
+        }
+    }
+    for (id, message) in &next.messages {
+        match prev.messages.get(id) {
+            None => write_message(db, id, message)?,
+            // Restored with the removals
+            Some(old) if old.deleted && !message.deleted => {}
+            Some(old) => {
+                if !old.deleted && message.deleted {
+                    db.delete_community_message_for_everyone(id)?;
+                } else if old.content != message.content || old.edited_at != message.edited_at {
+                    db.edit_community_message(
+                        id,
+                        Some(&message.content),
+                        None,
+                        None,
+                        message.edited_at.unwrap_or(message.created_at),
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Insert a message row as the log has it.
+fn write_message(db: &Database, id: &str, message: &MessageState) -> Result<()> {
+    db.store_community_message_if_not_exists(
+        id,
+        &message.channel_id,
+        &message.sender_did,
+        None,
+        Some(&message.content),
+        None,
+        None,
+        false,
+        message.reply_to_id.as_deref(),
+        None,
+        false,
+        false,
+        None,
+        message.created_at,
+        None,
+    )?;
+    if message.deleted {
+        db.delete_community_message_for_everyone(id)?;
+    } else if let Some(edited_at) = message.edited_at {
+        db.edit_community_message(id, Some(&message.content), None, None, edited_at)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,1085 @@
+//! # Community Op Log
+//!
+//! Communities replicated between members as a signed operation log.
+//!
+//! Every change is a [`SignedOp`]: a [`CommunityOp`] signed with its
+//! author's Ed25519 key and linked to the log heads the author had seen,
+//! so a community's ops form a DAG rooted at its genesis op. Replicas
+//! exchange ops (see [`CommunityService::ops_since`]) and each one orders
+//! what it has by (lamport, id) and replays it, checking every op against
+//! the roles and permissions at that point in the log. Replicas with the
+//! same ops therefore agree on the community, whichever order the ops
+//! arrived in; an op that isn't allowed where it falls is kept in the log
+//! but has no effect.
+//!
+//! An op happens at its author's signed timestamp, or at its latest
+//! parent's time if that's later, so replicas time ops alike no matter
+//! when they received them. Ops dated ahead of our clock wait until their
+//! time comes, so claiming a later time can't dodge slow mode or outlast
+//! a ban.
+//!
+//! Ops are only taken for communities we already have a log for (or whose
+//! genesis op comes with them), and ops by authors who aren't members
+//! where they fall in the log are dropped rather than stored.
+//!
+//! The replay is kept between calls, and new ops that sort after
+//! everything we have are applied on top of it; only ops that land in
+//! the middle of the log replay it from the start. The replayed state is
+//! written to the usual community tables, so the rest of the community
+//! API reads log-backed communities like any other. Those tables only
+//! change through the log: the unsigned `CommunityService` mutators
+//! refuse log-backed communities.
+
+mod materialize;
+mod ops;
+mod state;
+
+pub use ops::{community_id_for, CommunityOp, SignedOp, OP_VERSION};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use self::materialize::materialize;
+use self::state::{preset_role_id, replay, CommunityState, Replay};
+use super::roles::PresetRoleIds;
+use super::service::CommunityCreateResult;
+use crate::error::{Error, Result};
+use crate::identity::Identity;
+use crate::network::relay_client::RelayMessage;
+
+/// How far ahead of our clock (in seconds) a received op may be dated
+pub(crate) const MAX_CLOCK_SKEW: i64 = 30;
+
+/// An op we made
+#[derive(Debug, Clone, Serialize)]
+pub struct SubmittedOp {
+    /// The signed op
+    pub op: SignedOp,
+    /// The `community_op` envelope for every other member
+    pub relay_messages: Vec<RelayMessage>,
+}
+
+/// An op that was refused or that had no effect
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedOp {
+    /// Op ID
+    pub id: String,
+    /// Why it was rejected
+    pub reason: String,
+}
+
+/// What became of ops we received
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestReport {
+    /// New ops that took effect
+    pub applied: Vec<String>,
+    /// Ops that were badly signed (and dropped), or not allowed where they
+    /// fall in the log (and kept, without effect)
+    pub rejected: Vec<RejectedOp>,
+    /// New ops waiting for ancestors we don't have
+    pub pending: Vec<String>,
+    /// Ancestors to ask the sender for
+    pub missing: Vec<String>,
+}
+
+/// A `community_op` envelope carrying `ops`.
+pub fn op_envelope(community_id: &str, ops: &[SignedOp]) -> Result<String> {
+    serde_json::to_string(&serde_json::json!({
+        "envelope": "community_op",
+        "version": OP_VERSION,
+        "payload": {
+            "communityId": community_id,
+            "ops": ops,
+        },
+    }))
+    .map_err(|e| Error::SerializationError(format!("Failed to serialize ops: {}", e)))
+}
+
+/// Replayed logs, kept between calls
+#[derive(Default)]
+pub(crate) struct ReplayCache(Mutex<HashMap<String, CachedReplay>>);
+
+/// A community's replayed log
+struct CachedReplay {
+    /// How many of its ops were stored when it was replayed
+    stored: usize,
+    replay: Replay,
+}
+
+/// Sign `op` on top of `tip` and apply it there.
+fn append(
+    identity: &Identity,
+    community_id: &str,
+    tip: &mut Replay,
+    op: CommunityOp,
+) -> Result<SignedOp> {
+    let lamport = if tip.heads.is_empty() {
+        0
+    } else {
+        tip.lamport + 1
+    };
+    let signed = SignedOp::new(
+        identity,
+        community_id,
+        tip.heads.clone(),
+        lamport,
+        crate::time::now_timestamp(),
+        op,
+    )?;
+    let now = tip.time_of(&signed);
+    tip.state.apply(&signed, now)?;
+    tip.record(&signed, now);
+    Ok(signed)
+}
+
+/// The `community_op` envelope for everyone but us who was or is a member.
+fn relay_ops(
+    community_id: &str,
+    our_did: &str,
+    prev: &CommunityState,
+    next: &CommunityState,
+    ops: &[SignedOp],
+) -> Result<Vec<RelayMessage>> {
+    let envelope = op_envelope(community_id, ops)?;
+    let recipients: BTreeSet<&String> = prev.members.keys().chain(next.members.keys()).collect();
+    Ok(recipients
+        .into_iter()
+        .filter(|did| did.as_str() != our_did)
+        .map(|did| RelayMessage::new(did, &envelope))
+        .collect())
+}
+
+impl super::CommunityService {
+    fn load_ops(&self, community_id: &str) -> Result<Vec<SignedOp>> {
+        self.db()
+            .get_community_ops(community_id)?
+            .iter()
+            .map(|json| {
+                serde_json::from_str(json)
+                    .map_err(|e| Error::DeserializationError(format!("Invalid stored op: {}", e)))
+            })
+            .collect()
+    }
+
+    fn store_op(&self, op: &SignedOp, received_at: i64) -> Result<bool> {
+        let json = serde_json::to_string(op)
+            .map_err(|e| Error::SerializationError(format!("Failed to serialize op: {}", e)))?;
+        self.db().store_community_op(
+            &op.id,
+            &op.community_id,
+            &op.author_did,
+            op.lamport,
+            &json,
+            received_at,
+        )
+    }
+
+    /// Take a community's replayed log out of the cache, replaying it
+    /// again if the cached one is missing or stale. Put it back once
+    /// it's been brought up to date; on error it's simply dropped.
+    fn take_replay(
+        &self,
+        cache: &mut HashMap<String, CachedReplay>,
+        community_id: &str,
+    ) -> Result<CachedReplay> {
+        let stored = self.db().count_community_ops(community_id)?;
+        match cache.remove(community_id) {
+            Some(cached) if cached.stored == stored => Ok(cached),
+            _ => Ok(CachedReplay {
+                stored,
+                replay: replay(self.load_ops(community_id)?),
+            }),
+        }
+    }
+
+    /// Whether a community is log-backed.
+    pub(crate) fn is_log_backed(&self, community_id: &str) -> Result<bool> {
+        Ok(self.db().count_community_ops(community_id)? > 0)
+    }
+
+    /// Create a log-backed community, with the same default space,
+    /// category, channels and preset roles as
+    /// [`create_community`](Self::create_community).
+    pub fn create_signed_community(
+        &self,
+        identity: &Identity,
+        name: &str,
+        description: Option<&str>,
+        nickname: Option<&str>,
+    ) -> Result<CommunityCreateResult> {
+        let nonce = uuid::Uuid::new_v4().to_string();
+        let community_id = community_id_for(&identity.did_string(), &nonce);
+        let mut tip = Replay::default();
+
+        let genesis = append(
+            identity,
+            &community_id,
+            &mut tip,
+            CommunityOp::CreateCommunity {
+                name: name.to_string(),
+                description: description.map(String::from),
+                nickname: nickname.map(String::from),
+                nonce,
+            },
+        )?;
+        let space = append(
+            identity,
+            &community_id,
+            &mut tip,
+            CommunityOp::CreateSpace {
+                name: "General".to_string(),
+                position: 0,
+            },
+        )?;
+        let category = append(
+            identity,
+            &community_id,
+            &mut tip,
+            CommunityOp::CreateCategory {
+                space_id: space.id.clone(),
+                name: "General".to_string(),
+                position: 0,
+            },
+        )?;
+        let mut channels = Vec::new();
+        for (position, (name, channel_type)) in [("welcome", "welcome"), ("general", "text")]
+            .into_iter()
+            .enumerate()
+        {
+            channels.push(append(
+                identity,
+                &community_id,
+                &mut tip,
+                CommunityOp::CreateChannel {
+                    space_id: space.id.clone(),
+                    category_id: Some(category.id.clone()),
+                    name: name.to_string(),
+                    channel_type: channel_type.to_string(),
+                    topic: None,
+                    position: position as i32,
+                },
+            )?);
+        }
+
+        let ops = [&genesis, &space, &category, &channels[0], &channels[1]];
+        for op in ops {
+            self.store_op(op, op.timestamp)?;
+        }
+        materialize(
+            self.db(),
+            &community_id,
+            &CommunityState::default(),
+            &tip.state,
+        )?;
+        self.replays().0.lock().insert(
+            community_id.clone(),
+            CachedReplay {
+                stored: ops.len(),
+                replay: tip,
+            },
+        );
+
+        Ok(CommunityCreateResult {
+            community_id,
+            space_id: space.id,
+            welcome_channel_id: channels[0].id.clone(),
+            general_channel_id: channels[1].id.clone(),
+            role_ids: PresetRoleIds {
+                owner: preset_role_id(&genesis.id, "owner"),
+                admin: preset_role_id(&genesis.id, "admin"),
+                moderator: preset_role_id(&genesis.id, "moderator"),
+                member: preset_role_id(&genesis.id, "member"),
+            },
+        })
+    }
+
+    /// Make a change to a log-backed community.
+    ///
+    /// The op follows every head of our log. It's checked like one we
+    /// received, and not stored if it isn't allowed.
+    pub fn submit_op(
+        &self,
+        identity: &Identity,
+        community_id: &str,
+        op: CommunityOp,
+    ) -> Result<SubmittedOp> {
+        let mut cache = self.replays().0.lock();
+        let mut cached = self.take_replay(&mut cache, community_id)?;
+        let tip = &mut cached.replay;
+        if tip.state.genesis_id.is_none() {
+            return Err(Error::CommunityNotFound);
+        }
+        let prev = tip.state.clone();
+        let signed = append(identity, community_id, tip, op)?;
+
+        if self.store_op(&signed, signed.timestamp)? {
+            cached.stored += 1;
+        }
+        materialize(self.db(), community_id, &prev, &cached.replay.state)?;
+
+        let relay_messages = relay_ops(
+            community_id,
+            &signed.author_did,
+            &prev,
+            &cached.replay.state,
+            std::slice::from_ref(&signed),
+        )?;
+        cache.insert(community_id.to_string(), cached);
+        Ok(SubmittedOp {
+            op: signed,
+            relay_messages,
+        })
+    }
+
+    /// Take in ops from another member.
+    ///
+    /// Ops that aren't validly signed, that belong to a community we have
+    /// no log for, or whose author isn't a member where they fall in the
+    /// log, are dropped. So are ops dated more than `MAX_CLOCK_SKEW`
+    /// ahead of us; they're taken once that time comes. The rest are
+    /// stored and applied, and the community tables updated to match.
+    pub fn ingest_ops(&self, ops: Vec<SignedOp>) -> Result<IngestReport> {
+        let mut report = IngestReport::default();
+        let mut by_community: BTreeMap<String, Vec<SignedOp>> = BTreeMap::new();
+        let latest = crate::time::now_timestamp() + MAX_CLOCK_SKEW;
+        for op in ops {
+            let checked = op.verify().and_then(|()| {
+                if op.timestamp > latest {
+                    return Err(Error::InvalidCommunityOperation(
+                        "Op is dated in the future".to_string(),
+                    ));
+                }
+                Ok(())
+            });
+            match checked {
+                Ok(()) => by_community
+                    .entry(op.community_id.clone())
+                    .or_default()
+                    .push(op),
+                Err(e) => report.rejected.push(RejectedOp {
+                    id: op.id,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        let mut cache = self.replays().0.lock();
+        for (community_id, ops) in by_community {
+            let cached = self.take_replay(&mut cache, &community_id)?;
+            let mut seen = HashSet::new();
+            let mut ops: Vec<SignedOp> = ops
+                .into_iter()
+                .filter(|op| !cached.replay.knows(&op.id) && seen.insert(op.id.clone()))
+                .collect();
+            ops.sort_by(|a, b| (a.lamport, &a.id).cmp(&(b.lamport, &b.id)));
+
+            let has_genesis = ops
+                .iter()
+                .any(|op| matches!(op.op, CommunityOp::CreateCommunity { .. }));
+            if cached.stored == 0 && !has_genesis {
+                report.rejected.extend(ops.into_iter().map(|op| RejectedOp {
+                    id: op.id,
+                    reason: Error::CommunityNotFound.to_string(),
+                }));
+                continue;
+            }
+            if ops.is_empty() {
+                cache.insert(community_id, cached);
+                continue;
+            }
+
+            // Apply on top of the replay while the ops come after it;
+            // otherwise replay the log with them in place
+            let received_at = crate::time::now_timestamp();
+            let prev = cached.replay.state.clone();
+            let mut next = cached.replay;
+            for op in &ops {
+                if !next.extends(op) {
+                    let mut all = self.load_ops(&community_id)?;
+                    all.extend(ops.iter().cloned());
+                    next = replay(all);
+                    break;
+                }
+                next.place(op);
+            }
+
+            let mut stored = cached.stored;
+            for op in &ops {
+                if !next.outsiders.contains(&op.id) && self.store_op(op, received_at)? {
+                    stored += 1;
+                }
+            }
+            materialize(self.db(), &community_id, &prev, &next.state)?;
+
+            for op in ops {
+                if let Some(reason) = next.rejected.get(&op.id) {
+                    report.rejected.push(RejectedOp {
+                        id: op.id,
+                        reason: reason.clone(),
+                    });
+                } else if next.placed(&op.id) {
+                    report.applied.push(op.id);
+                } else {
+                    report.pending.push(op.id);
+                }
+            }
+            report.missing.extend(next.missing.iter().cloned());
+            cache.insert(
+                community_id,
+                CachedReplay {
+                    stored,
+                    replay: next,
+                },
+            );
+        }
+
+        Ok(report)
+    }
+
+    /// The heads of a community's log: the ops nothing we have follows.
+    ///
+    /// A member sends these to another to ask for what they're missing.
+    pub fn op_heads(&self, community_id: &str) -> Result<Vec<String>> {
+        let mut cache = self.replays().0.lock();
+        let cached = self.take_replay(&mut cache, community_id)?;
+        let heads = cached.replay.heads.clone();
+        cache.insert(community_id.to_string(), cached);
+        Ok(heads)
+    }
+
+    /// Every op of a community that isn't an ancestor of `heads` (or one
+    /// of them), oldest first: what a member with those heads lacks.
+    pub fn ops_since(&self, community_id: &str, heads: &[String]) -> Result<Vec<SignedOp>> {
+        let ops = self.load_ops(community_id)?;
+        let parents: HashMap<&str, &[String]> = ops
+            .iter()
+            .map(|op| (op.id.as_str(), op.parents.as_slice()))
+            .collect();
+
+        let mut known: HashSet<&str> = HashSet::new();
+        let mut stack: Vec<&str> = heads.iter().map(String::as_str).collect();
+        while let Some(id) = stack.pop() {
+            if let Some(op_parents) = parents.get(id) {
+                if known.insert(id) {
+                    stack.extend(op_parents.iter().map(String::as_str));
+                }
+            }
+        }
+
+        Ok(ops
+            .iter()
+            .filter(|op| !known.contains(op.id.as_str()))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::{CommunityService, Permissions};
+    use crate::storage::Database;
+    use std::sync::Arc;
+
+    async fn replica() -> CommunityService {
+        CommunityService::new(Arc::new(Database::open(None).await.unwrap()))
+    }
+
+    fn identity(name: &str) -> Identity {
+        Identity::create(name.to_string()).unwrap().0
+    }
+
+    /// Send `to` everything it lacks from `from`.
+    fn sync(from: &CommunityService, to: &CommunityService, community_id: &str) -> IngestReport {
+        let heads = to.op_heads(community_id).unwrap();
+        to.ingest_ops(from.ops_since(community_id, &heads).unwrap())
+            .unwrap()
+    }
+
+    /// Sign `op` as `identity` on top of `svc`'s log, claiming `timestamp`,
+    /// without checking or storing it.
+    fn sign_on(
+        svc: &CommunityService,
+        identity: &Identity,
+        community_id: &str,
+        timestamp: i64,
+        op: CommunityOp,
+    ) -> SignedOp {
+        let lamport = svc
+            .ops_since(community_id, &[])
+            .unwrap()
+            .iter()
+            .map(|op| op.lamport)
+            .max()
+            .unwrap();
+        SignedOp::new(
+            identity,
+            community_id,
+            svc.op_heads(community_id).unwrap(),
+            lamport + 1,
+            timestamp,
+            op,
+        )
+        .unwrap()
+    }
+
+    /// Alice's community, with Bob joined on both replicas.
+    async fn community_with_bob() -> (
+        CommunityService,
+        Identity,
+        CommunityService,
+        Identity,
+        CommunityCreateResult,
+    ) {
+        let (alice, bob) = (identity("Alice"), identity("Bob"));
+        let (alice_svc, bob_svc) = (replica().await, replica().await);
+        let created = alice_svc
+            .create_signed_community(&alice, "Umbra", Some("desc"), None)
+            .unwrap();
+        let cid = &created.community_id;
+        alice_svc
+            .submit_op(
+                &alice,
+                cid,
+                CommunityOp::CreateInvite {
+                    code: "join-me".to_string(),
+                    max_uses: Some(1),
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+
+        sync(&alice_svc, &bob_svc, cid);
+        let joined = bob_svc
+            .submit_op(
+                &bob,
+                cid,
+                CommunityOp::Join {
+                    code: "join-me".to_string(),
+                    nickname: Some("bob".to_string()),
+                },
+            )
+            .unwrap();
+        assert_eq!(joined.relay_messages.len(), 1);
+        assert_eq!(joined.relay_messages[0].to_did, alice.did_string());
+        let report = sync(&bob_svc, &alice_svc, cid);
+        assert_eq!(report.applied, vec![joined.op.id]);
+
+        (alice_svc, alice, bob_svc, bob, created)
+    }
+
+    #[test]
+    fn test_signed_op_verification() {
+        let alice = identity("Alice");
+        let op = SignedOp::new(
+            &alice,
+            "c1",
+            vec!["b".to_string(), "a".to_string()],
+            5,
+            1000,
+            CommunityOp::Leave,
+        )
+        .unwrap();
+        assert_eq!(op.parents, vec!["a".to_string(), "b".to_string()]);
+        op.verify().unwrap();
+
+        let mut tampered = op.clone();
+        tampered.op = CommunityOp::Kick {
+            member_did: "did:key:z6MkBob".to_string(),
+        };
+        assert!(tampered.verify().is_err());
+
+        // Re-deriving the ID doesn't help without the author's key
+        let mallory = SignedOp::new(
+            &identity("Mallory"),
+            "c1",
+            op.parents.clone(),
+            5,
+            1000,
+            tampered.op,
+        )
+        .unwrap();
+        let mut forged = mallory.clone();
+        forged.author_did = alice.did_string();
+        assert!(forged.verify().is_err());
+
+        let mut orphan = op.clone();
+        orphan.parents.clear();
+        assert!(orphan.verify().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_signed_community() {
+        let alice = identity("Alice");
+        let svc = replica().await;
+        let created = svc
+            .create_signed_community(&alice, "Umbra", Some("desc"), Some("ali"))
+            .unwrap();
+        let cid = &created.community_id;
+
+        let community = svc.get_community(cid).unwrap();
+        assert_eq!(community.name, "Umbra");
+        assert_eq!(community.owner_did, alice.did_string());
+        assert_eq!(svc.db().get_community_channels(cid).unwrap().len(), 2);
+        assert_eq!(svc.db().get_community_roles(cid).unwrap().len(), 4);
+        let roles = svc
+            .db()
+            .get_member_community_roles(cid, &alice.did_string())
+            .unwrap();
+        assert_eq!(roles.len(), 1);
+        assert_eq!(roles[0].id, created.role_ids.owner);
+
+        // A second replica builds the same community from the log
+        let other = replica().await;
+        let report = sync(&svc, &other, cid);
+        assert_eq!(report.applied.len(), 5);
+        assert_eq!(other.get_community(cid).unwrap().name, "Umbra");
+        assert_eq!(other.op_heads(cid).unwrap(), svc.op_heads(cid).unwrap());
+        assert!(svc
+            .ops_since(cid, &svc.op_heads(cid).unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_forged_ops_are_rejected() {
+        let (alice_svc, _alice, bob_svc, bob, created) = community_with_bob().await;
+        let cid = &created.community_id;
+
+        // Bob's own replica refuses to make ops he isn't allowed to
+        assert!(bob_svc
+            .submit_op(
+                &bob,
+                cid,
+                CommunityOp::AssignRole {
+                    member_did: bob.did_string(),
+                    role_id: created.role_ids.admin.clone(),
+                },
+            )
+            .is_err());
+
+        // A tampered replica signs them anyway; Alice's replica ignores them
+        let grant = sign_on(
+            &bob_svc,
+            &bob,
+            cid,
+            crate::time::now_timestamp(),
+            CommunityOp::AssignRole {
+                member_did: bob.did_string(),
+                role_id: created.role_ids.admin.clone(),
+            },
+        );
+        let channel = SignedOp::new(
+            &bob,
+            cid,
+            vec![grant.id.clone()],
+            grant.lamport + 1,
+            crate::time::now_timestamp(),
+            CommunityOp::CreateChannel {
+                space_id: created.space_id.clone(),
+                category_id: None,
+                name: "bobs-channel".to_string(),
+                channel_type: "text".to_string(),
+                topic: None,
+                position: 9,
+            },
+        )
+        .unwrap();
+
+        let report = alice_svc
+            .ingest_ops(vec![grant.clone(), channel.clone()])
+            .unwrap();
+        assert!(report.applied.is_empty());
+        assert_eq!(report.rejected.len(), 2);
+        assert_eq!(alice_svc.db().get_community_channels(cid).unwrap().len(), 2);
+        let bob_roles = alice_svc
+            .db()
+            .get_member_community_roles(cid, &bob.did_string())
+            .unwrap();
+        assert_eq!(bob_roles.len(), 1);
+        assert_eq!(bob_roles[0].id, created.role_ids.member);
+
+        // An op whose signature doesn't check out isn't even stored
+        let mut forged = channel.clone();
+        forged.signature = grant.signature.clone();
+        let report = alice_svc.ingest_ops(vec![forged]).unwrap();
+        assert_eq!(report.rejected.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_demotion_is_deterministic() {
+        let (alice_svc, alice, bob_svc, bob, created) = community_with_bob().await;
+        let cid = &created.community_id;
+
+        let promote = alice_svc
+            .submit_op(
+                &alice,
+                cid,
+                CommunityOp::AssignRole {
+                    member_did: bob.did_string(),
+                    role_id: created.role_ids.admin.clone(),
+                },
+            )
+            .unwrap();
+        sync(&alice_svc, &bob_svc, cid);
+        assert!(bob_svc
+            .db()
+            .get_member_community_roles(cid, &bob.did_string())
+            .unwrap()
+            .iter()
+            .any(|r| Permissions::from_string(&r.permissions_bitfield)
+                .has(crate::community::Permission::ManageChannels)));
+
+        // Concurrently, Alice demotes Bob and Bob creates a channel
+        let demote = alice_svc
+            .submit_op(
+                &alice,
+                cid,
+                CommunityOp::UnassignRole {
+                    member_did: bob.did_string(),
+                    role_id: created.role_ids.admin.clone(),
+                },
+            )
+            .unwrap();
+        let channel = bob_svc
+            .submit_op(
+                &bob,
+                cid,
+                CommunityOp::CreateChannel {
+                    space_id: created.space_id.clone(),
+                    category_id: None,
+                    name: "race".to_string(),
+                    channel_type: "text".to_string(),
+                    topic: None,
+                    position: 2,
+                },
+            )
+            .unwrap();
+        assert_eq!(demote.op.parents, channel.op.parents);
+        assert_eq!(demote.op.parents, vec![promote.op.id]);
+
+        sync(&alice_svc, &bob_svc, cid);
+        sync(&bob_svc, &alice_svc, cid);
+
+        // The op with the lower ID goes first: if it's the demotion, the
+        // channel was made without permission
+        let channel_survives = channel.op.id < demote.op.id;
+        for svc in [&alice_svc, &bob_svc] {
+            let exists = svc
+                .db()
+                .get_community_channel(&channel.op.id)
+                .unwrap()
+                .is_some();
+            assert_eq!(exists, channel_survives);
+            assert_eq!(
+                svc.db()
+                    .get_member_community_roles(cid, &bob.did_string())
+                    .unwrap()
+                    .len(),
+                1
+            );
+        }
+        assert_eq!(
+            alice_svc.op_heads(cid).unwrap(),
+            bob_svc.op_heads(cid).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_ops_wait_for_parents() {
+        let (alice_svc, alice, bob_svc, bob, created) = community_with_bob().await;
+        let cid = &created.community_id;
+
+        let first = bob_svc
+            .submit_op(
+                &bob,
+                cid,
+                CommunityOp::SendMessage {
+                    channel_id: created.general_channel_id.clone(),
+                    content: "hello".to_string(),
+                    reply_to_id: None,
+                },
+            )
+            .unwrap();
+        let second = bob_svc
+            .submit_op(
+                &bob,
+                cid,
+                CommunityOp::EditMessage {
+                    message_id: first.op.id.clone(),
+                    content: "hello!".to_string(),
+                },
+            )
+            .unwrap();
+
+        let report = alice_svc.ingest_ops(vec![second.op.clone()]).unwrap();
+        assert_eq!(report.pending, vec![second.op.id.clone()]);
+        assert_eq!(report.missing, vec![first.op.id.clone()]);
+        assert!(alice_svc
+            .db()
+            .get_community_messages(&created.general_channel_id, 10, None)
+            .unwrap()
+            .is_empty());
+
+        let report = alice_svc.ingest_ops(vec![first.op.clone()]).unwrap();
+        assert_eq!(report.applied, vec![first.op.id.clone()]);
+        let messages = alice_svc
+            .db()
+            .get_community_messages(&created.general_channel_id, 10, None)
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content_plaintext.as_deref(), Some("hello!"));
+        assert!(messages[0].edited_at.is_some());
+
+        // Alice can delete it as owner; Bob learns of it on sync
+        alice_svc
+            .submit_op(
+                &alice,
+                cid,
+                CommunityOp::DeleteMessage {
+                    message_id: first.op.id.clone(),
+                },
+            )
+            .unwrap();
+        sync(&alice_svc, &bob_svc, cid);
+        let message = bob_svc
+            .db()
+            .get_community_message(&first.op.id)
+            .unwrap()
+            .unwrap();
+        assert!(message.deleted_for_everyone);
+    }
+
+    #[tokio::test]
+    async fn test_ops_from_outsiders_are_dropped() {
+        let (alice_svc, _alice, _bob_svc, _bob, created) = community_with_bob().await;
+        let cid = &created.community_id;
+        let mallory = identity("Mallory");
+
+        let op = sign_on(
+            &alice_svc,
+            &mallory,
+            cid,
+            crate::time::now_timestamp(),
+            CommunityOp::CreateChannel {
+                space_id: created.space_id.clone(),
+                category_id: None,
+                name: "mallorys-channel".to_string(),
+                channel_type: "text".to_string(),
+                topic: None,
+                position: 9,
+            },
+        );
+        let heads = alice_svc.op_heads(cid).unwrap();
+        let report = alice_svc.ingest_ops(vec![op.clone()]).unwrap();
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].id, op.id);
+        assert!(alice_svc.ops_since(cid, &heads).unwrap().is_empty());
+        assert_eq!(alice_svc.op_heads(cid).unwrap(), heads);
+
+        // Nor are ops taken for a community we have no log for
+        let stranger = replica().await;
+        let report = stranger.ingest_ops(vec![op]).unwrap();
+        assert_eq!(report.rejected.len(), 1);
+        assert!(stranger.ops_since(cid, &[]).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_future_dated_ops_dont_dodge_slow_mode() {
+        let (alice_svc, alice, bob_svc, bob, created) = community_with_bob().await;
+        let cid = &created.community_id;
+        let channel_id = &created.general_channel_id;
+
+        alice_svc
+            .submit_op(
+                &alice,
+                cid,
+                CommunityOp::SetSlowMode {
+                    channel_id: channel_id.clone(),
+                    seconds: 60,
+                },
+            )
+            .unwrap();
+        sync(&alice_svc, &bob_svc, cid);
+        let send = |content: &str| CommunityOp::SendMessage {
+            channel_id: channel_id.clone(),
+            content: content.to_string(),
+            reply_to_id: None,
+        };
+        bob_svc.submit_op(&bob, cid, send("first")).unwrap();
+        assert!(bob_svc.submit_op(&bob, cid, send("too soon")).is_err());
+        sync(&bob_svc, &alice_svc, cid);
+
+        // Claiming to be an hour ahead doesn't help: it waits for that hour
+        let later = sign_on(
+            &bob_svc,
+            &bob,
+            cid,
+            crate::time::now_timestamp() + 3600,
+            send("from the future"),
+        );
+        let report = alice_svc.ingest_ops(vec![later.clone()]).unwrap();
+        assert_eq!(report.rejected.len(), 1);
+        assert!(report.rejected[0].reason.contains("future"));
+        assert!(!alice_svc.ops_since(cid, &[]).unwrap().contains(&later));
+
+        // Within the allowed skew it's taken, and slow mode still applies
+        let soon = sign_on(
+            &bob_svc,
+            &bob,
+            cid,
+            crate::time::now_timestamp() + MAX_CLOCK_SKEW / 2,
+            send("a little early"),
+        );
+        let report = alice_svc.ingest_ops(vec![soon]).unwrap();
+        assert_eq!(report.rejected.len(), 1);
+        assert!(report.rejected[0].reason.contains("Slow mode"));
+        assert_eq!(
+            alice_svc
+                .db()
+                .get_community_messages(channel_id, 10, None)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_ignores_when_ops_arrived() {
+        let (alice_svc, alice, bob_svc, bob, created) = community_with_bob().await;
+        let cid = &created.community_id;
+        let channel_id = &created.general_channel_id;
+
+        alice_svc
+            .submit_op(
+                &alice,
+                cid,
+                CommunityOp::SetSlowMode {
+                    channel_id: channel_id.clone(),
+                    seconds: 60,
+                },
+            )
+            .unwrap();
+        sync(&alice_svc, &bob_svc, cid);
+        let send = |content: &str| CommunityOp::SendMessage {
+            channel_id: channel_id.clone(),
+            content: content.to_string(),
+            reply_to_id: None,
+        };
+        let now = crate::time::now_timestamp();
+        let first = sign_on(&bob_svc, &bob, cid, now, send("first"));
+        let follow = |parent: &SignedOp, timestamp: i64, content: &str| {
+            SignedOp::new(
+                &bob,
+                cid,
+                vec![parent.id.clone()],
+                parent.lamport + 1,
+                timestamp,
+                send(content),
+            )
+            .unwrap()
+        };
+        let second = follow(&first, now + 120, "second");
+        let too_soon = follow(&second, now + 130, "too soon");
+
+        // One replica got the ops long after they were made, the other
+        // before their claimed times; both replay them alike
+        let mut outcomes = Vec::new();
+        for received_at in [now, now + 86_400] {
+            let svc = replica().await;
+            sync(&alice_svc, &svc, cid);
+            for op in [&first, &second, &too_soon] {
+                assert!(svc.store_op(op, received_at).unwrap());
+            }
+            let replayed = replay(svc.load_ops(cid).unwrap());
+            outcomes.push(replayed.rejected.into_keys().collect::<Vec<_>>());
+        }
+        assert_eq!(outcomes[0], outcomes[1]);
+        assert_eq!(outcomes[0], vec![too_soon.id]);
+    }
+
+    #[tokio::test]
+    async fn test_log_backed_communities_refuse_unsigned_changes() {
+        let (alice_svc, alice, _bob_svc, bob, created) = community_with_bob().await;
+        let cid = &created.community_id;
+        let alice_did = alice.did_string();
+
+        assert!(matches!(
+            alice_svc.create_channel(
+                cid,
+                &created.space_id,
+                "x",
+                "text",
+                None,
+                5,
+                &alice_did,
+                None
+            ),
+            Err(Error::InvalidCommunityOperation(_))
+        ));
+        assert!(matches!(
+            alice_svc.kick_member(cid, &bob.did_string(), &alice_did),
+            Err(Error::InvalidCommunityOperation(_))
+        ));
+        assert!(matches!(
+            alice_svc.store_received_message(
+                "forged",
+                &created.general_channel_id,
+                &bob.did_string(),
+                "hi",
+                0,
+                None,
+            ),
+            Err(Error::InvalidCommunityOperation(_))
+        ));
+        assert_eq!(alice_svc.db().get_community_channels(cid).unwrap().len(), 2);
+        assert!(alice_svc
+            .db()
+            .get_community_member(cid, &bob.did_string())
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_replay_cache_follows_other_writers() {
+        let alice = identity("Alice");
+        let db = Arc::new(Database::open(None).await.unwrap());
+        let (first, second) = (
+            CommunityService::new(db.clone()),
+            CommunityService::new(db.clone()),
+        );
+        let created = first
+            .create_signed_community(&alice, "Umbra", None, None)
+            .unwrap();
+        let cid = &created.community_id;
+        assert_eq!(first.op_heads(cid).unwrap(), second.op_heads(cid).unwrap());
+
+        // Another service on the same database appends; our cached replay
+        // is stale and gets replayed again
+        let renamed = second
+            .submit_op(
+                &alice,
+                cid,
+                CommunityOp::UpdateCommunity {
+                    name: Some("Renamed".to_string()),
+                    description: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(first.op_heads(cid).unwrap(), vec![renamed.op.id.clone()]);
+        let next = first
+            .submit_op(
+                &alice,
+                cid,
+                CommunityOp::UpdateCommunity {
+                    name: Some("Again".to_string()),
+                    description: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(next.op.parents, vec![renamed.op.id]);
+        assert_eq!(second.get_community(cid).unwrap().name, "Again");
+    }
+}
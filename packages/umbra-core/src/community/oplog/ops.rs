@@ -0,0 +1,389 @@
+//! # Signed Operations
+//!
+//! The operations a community log is made of, and how they're
+//! identified, signed, and verified.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{sign, verify, Signature};
+use crate::error::{Error, Result};
+use crate::identity::{Did, Identity};
+
+/// Operation format version
+pub const OP_VERSION: u32 = 1;
+
+/// Domain separator for operation IDs and signatures
+const OP_DOMAIN: &[u8] = b"umbra-community-op-v1";
+
+/// Domain separator for IDs derived from other IDs
+const ID_DOMAIN: &[u8] = b"umbra-community-v1";
+
+/// A change to a community.
+///
+/// Entities an operation creates (spaces, channels, roles, invites,
+/// messages) take the ID of the operation that created them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommunityOp {
+    // ── Community ────────────────────────────────────────────────────────
+    /// Create the community (the genesis operation), with the author as
+    /// owner and the preset roles
+    CreateCommunity {
+        /// Community name
+        name: String,
+        /// Community description
+        description: Option<String>,
+        /// Owner's nickname
+        nickname: Option<String>,
+        /// Random nonce the community ID is derived from
+        nonce: String,
+    },
+    /// Rename the community or change its description
+    UpdateCommunity {
+        /// New name
+        name: Option<String>,
+        /// New description
+        description: Option<String>,
+    },
+    /// Hand the community to another member (owner only)
+    TransferOwnership {
+        /// The new owner
+        new_owner_did: String,
+    },
+
+    // ── Structure ────────────────────────────────────────────────────────
+    /// Create a space
+    CreateSpace {
+        /// Space name
+        name: String,
+        /// Sort position
+        position: i32,
+    },
+    /// Delete a space with its categories and channels
+    DeleteSpace {
+        /// Space ID
+        space_id: String,
+    },
+    /// Create a category in a space
+    CreateCategory {
+        /// Space ID
+        space_id: String,
+        /// Category name
+        name: String,
+        /// Sort position
+        position: i32,
+    },
+    /// Delete a category; its channels are kept, uncategorized
+    DeleteCategory {
+        /// Category ID
+        category_id: String,
+    },
+    /// Create a channel
+    CreateChannel {
+        /// Space ID
+        space_id: String,
+        /// Category ID
+        category_id: Option<String>,
+        /// Channel name
+        name: String,
+        /// Channel type (`text`, `voice`, `announcement`, ...)
+        channel_type: String,
+        /// Channel topic
+        topic: Option<String>,
+        /// Sort position
+        position: i32,
+    },
+    /// Rename a channel or change its topic
+    UpdateChannel {
+        /// Channel ID
+        channel_id: String,
+        /// New name
+        name: Option<String>,
+        /// New topic
+        topic: Option<String>,
+    },
+    /// Set a channel's slow mode (0 turns it off)
+    SetSlowMode {
+        /// Channel ID
+        channel_id: String,
+        /// Seconds between a member's messages
+        seconds: u32,
+    },
+    /// Delete a channel and its messages
+    DeleteChannel {
+        /// Channel ID
+        channel_id: String,
+    },
+
+    // ── Roles ────────────────────────────────────────────────────────────
+    /// Create a custom role
+    CreateRole {
+        /// Role name
+        name: String,
+        /// Role color
+        color: Option<String>,
+        /// Position (higher = more authority)
+        position: i32,
+        /// Permission bitfield (decimal)
+        permissions: String,
+    },
+    /// Change a role
+    UpdateRole {
+        /// Role ID
+        role_id: String,
+        /// New name
+        name: Option<String>,
+        /// New color
+        color: Option<String>,
+        /// New position
+        position: Option<i32>,
+        /// New permission bitfield (decimal)
+        permissions: Option<String>,
+    },
+    /// Delete a custom role
+    DeleteRole {
+        /// Role ID
+        role_id: String,
+    },
+    /// Give a member a role
+    AssignRole {
+        /// Member DID
+        member_did: String,
+        /// Role ID
+        role_id: String,
+    },
+    /// Take a role from a member
+    UnassignRole {
+        /// Member DID
+        member_did: String,
+        /// Role ID
+        role_id: String,
+    },
+
+    // ── Members ──────────────────────────────────────────────────────────
+    /// Create an invite
+    CreateInvite {
+        /// Invite code
+        code: String,
+        /// Maximum number of joins
+        max_uses: Option<u32>,
+        /// Expiry (Unix seconds)
+        expires_at: Option<i64>,
+    },
+    /// Revoke an invite
+    RevokeInvite {
+        /// Invite ID
+        invite_id: String,
+    },
+    /// Join the community with an invite
+    Join {
+        /// Invite code
+        code: String,
+        /// Nickname
+        nickname: Option<String>,
+    },
+    /// Leave the community
+    Leave,
+    /// Remove a member
+    Kick {
+        /// Member DID
+        member_did: String,
+    },
+    /// Remove a member and keep them from rejoining
+    Ban {
+        /// DID to ban
+        member_did: String,
+        /// Reason
+        reason: Option<String>,
+        /// Expiry (Unix seconds); permanent if `None`
+        expires_at: Option<i64>,
+    },
+    /// Lift a ban
+    Unban {
+        /// Banned DID
+        member_did: String,
+    },
+
+    // ── Messages ─────────────────────────────────────────────────────────
+    /// Post a message
+    SendMessage {
+        /// Channel ID
+        channel_id: String,
+        /// Message text
+        content: String,
+        /// Message replied to
+        reply_to_id: Option<String>,
+    },
+    /// Edit one of our messages
+    EditMessage {
+        /// Message ID
+        message_id: String,
+        /// New text
+        content: String,
+    },
+    /// Delete a message for everyone
+    DeleteMessage {
+        /// Message ID
+        message_id: String,
+    },
+}
+
+/// A community operation signed by its author.
+///
+/// `parents` are the log heads the author had seen, which orders the
+/// operation after them; `lamport` is one more than the highest parent's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedOp {
+    /// Operation format version
+    pub version: u32,
+    /// Operation ID: hex SHA-256 of everything but the signature
+    pub id: String,
+    /// Community ID
+    pub community_id: String,
+    /// Author DID
+    pub author_did: String,
+    /// IDs of the operations this one follows (sorted)
+    pub parents: Vec<String>,
+    /// Lamport clock
+    pub lamport: u64,
+    /// Creation time claimed by the author (Unix seconds)
+    pub timestamp: i64,
+    /// The change
+    pub op: CommunityOp,
+    /// Ed25519 signature by the author (hex encoded)
+    pub signature: String,
+}
+
+/// The signed part of an operation
+#[derive(Serialize)]
+struct UnsignedOp<'a> {
+    version: u32,
+    community_id: &'a str,
+    author_did: &'a str,
+    parents: &'a [String],
+    lamport: u64,
+    timestamp: i64,
+    op: &'a CommunityOp,
+}
+
+impl SignedOp {
+    /// Create and sign an operation.
+    pub fn new(
+        identity: &Identity,
+        community_id: &str,
+        mut parents: Vec<String>,
+        lamport: u64,
+        timestamp: i64,
+        op: CommunityOp,
+    ) -> Result<Self> {
+        parents.sort();
+        parents.dedup();
+        let author_did = identity.did_string();
+        let bytes = signed_bytes(&UnsignedOp {
+            version: OP_VERSION,
+            community_id,
+            author_did: &author_did,
+            parents: &parents,
+            lamport,
+            timestamp,
+            op: &op,
+        })?;
+
+        Ok(Self {
+            version: OP_VERSION,
+            id: hex::encode(Sha256::digest(&bytes)),
+            community_id: community_id.to_string(),
+            author_did,
+            parents,
+            lamport,
+            timestamp,
+            op,
+            signature: sign(&identity.keypair().signing, &bytes).to_hex(),
+        })
+    }
+
+    /// Whether this is a community's genesis operation.
+    pub fn is_genesis(&self) -> bool {
+        matches!(self.op, CommunityOp::CreateCommunity { .. })
+    }
+
+    /// Check the operation is well formed and signed by its author.
+    ///
+    /// Whether the author was allowed to make it depends on the log
+    /// before it, and is checked when the log is replayed.
+    pub fn verify(&self) -> Result<()> {
+        if self.version != OP_VERSION {
+            return Err(Error::InvalidCommunityOperation(format!(
+                "Unsupported op version {}",
+                self.version
+            )));
+        }
+        if self.parents.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(Error::InvalidCommunityOperation(
+                "Op parents must be sorted and unique".to_string(),
+            ));
+        }
+
+        let bytes = signed_bytes(&UnsignedOp {
+            version: self.version,
+            community_id: &self.community_id,
+            author_did: &self.author_did,
+            parents: &self.parents,
+            lamport: self.lamport,
+            timestamp: self.timestamp,
+            op: &self.op,
+        })?;
+        if hex::encode(Sha256::digest(&bytes)) != self.id {
+            return Err(Error::InvalidCommunityOperation(
+                "Op ID doesn't match its contents".to_string(),
+            ));
+        }
+        let public_key = Did::parse(&self.author_did)?.public_key()?;
+        verify(&public_key, &bytes, &Signature::from_hex(&self.signature)?)?;
+
+        match &self.op {
+            CommunityOp::CreateCommunity { nonce, .. } => {
+                if !self.parents.is_empty() || self.lamport != 0 {
+                    return Err(Error::InvalidCommunityOperation(
+                        "A genesis op can't have parents".to_string(),
+                    ));
+                }
+                if self.community_id != community_id_for(&self.author_did, nonce) {
+                    return Err(Error::InvalidCommunityOperation(
+                        "Community ID doesn't match its genesis op".to_string(),
+                    ));
+                }
+            }
+            _ if self.parents.is_empty() => {
+                return Err(Error::InvalidCommunityOperation(
+                    "Only the genesis op can have no parents".to_string(),
+                ));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn signed_bytes(unsigned: &UnsignedOp<'_>) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(unsigned)
+        .map_err(|e| Error::SerializationError(format!("Failed to serialize op: {}", e)))?;
+    Ok([OP_DOMAIN, &json].concat())
+}
+
+/// Derive an ID from `seed` and a label.
+pub(crate) fn derive_id(seed: &str, label: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(ID_DOMAIN);
+    hasher.update(seed.as_bytes());
+    hasher.update(b"|");
+    hasher.update(label.as_bytes());
+    hex::encode(hasher.finalize())[..32].to_string()
+}
+
+/// The ID of the community created by `author_did`'s genesis op with `nonce`.
+pub fn community_id_for(author_did: &str, nonce: &str) -> String {
+    derive_id(author_did, nonce)
+}
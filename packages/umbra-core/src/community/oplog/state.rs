@@ -0,0 +1,819 @@
+//! # Log Replay
+//!
+//! Folding a community's operations, in order, into its state. Every
+//! operation is checked against the state of the log before it, with the
+//! same authorization rules as the rest of the community API, so an
+//! operation is valid or not the same way on every replica.
+//!
+//! ## Time
+//!
+//! Slow mode, bans and invites expire by the clock, and an op's author
+//! picks its timestamp. Ops are therefore timed at their author's
+//! timestamp, but never later than when we received them nor earlier
+//! than their parents. Honest ops are timed the same everywhere; an op
+//! dated into the future to dodge slow mode or outlast a ban is pulled
+//! back to when it actually arrived.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use super::ops::{derive_id, CommunityOp, SignedOp};
+use crate::community::authorization::{Access, Rank};
+use crate::community::channels::CHANNEL_TYPES;
+use crate::community::{Permission, Permissions, RolePreset};
+use crate::error::{Error, Result};
+
+/// Presets created with a community, with the labels their IDs are
+/// derived from
+const PRESETS: [(RolePreset, &str); 4] = [
+    (RolePreset::Owner, "owner"),
+    (RolePreset::Admin, "admin"),
+    (RolePreset::Moderator, "moderator"),
+    (RolePreset::Member, "member"),
+];
+
+/// ID of a preset role of the community created by `genesis_id`.
+pub(crate) fn preset_role_id(genesis_id: &str, label: &str) -> String {
+    derive_id(genesis_id, label)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SpaceState {
+    pub name: String,
+    pub position: i32,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CategoryState {
+    pub space_id: String,
+    pub name: String,
+    pub position: i32,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChannelState {
+    pub space_id: String,
+    pub category_id: Option<String>,
+    pub name: String,
+    pub channel_type: String,
+    pub topic: Option<String>,
+    pub position: i32,
+    pub slow_mode_seconds: u32,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RoleState {
+    pub name: String,
+    pub color: Option<String>,
+    pub position: i32,
+    pub permissions: Permissions,
+    pub hoisted: bool,
+    pub is_preset: bool,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MemberState {
+    pub nickname: Option<String>,
+    pub joined_at: i64,
+    pub roles: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BanState {
+    pub reason: Option<String>,
+    pub banned_by: String,
+    pub expires_at: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InviteState {
+    pub code: String,
+    pub creator_did: String,
+    pub max_uses: Option<u32>,
+    pub expires_at: Option<i64>,
+    pub uses: u32,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MessageState {
+    pub channel_id: String,
+    pub sender_did: String,
+    pub content: String,
+    pub reply_to_id: Option<String>,
+    pub created_at: i64,
+    pub edited_at: Option<i64>,
+    pub deleted: bool,
+}
+
+/// A community as its log describes it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CommunityState {
+    /// ID of the accepted genesis op; `None` until there is one
+    pub genesis_id: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub owner_did: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub spaces: BTreeMap<String, SpaceState>,
+    pub categories: BTreeMap<String, CategoryState>,
+    pub channels: BTreeMap<String, ChannelState>,
+    pub roles: BTreeMap<String, RoleState>,
+    pub members: BTreeMap<String, MemberState>,
+    pub bans: BTreeMap<String, BanState>,
+    pub invites: BTreeMap<String, InviteState>,
+    pub messages: BTreeMap<String, MessageState>,
+    /// Time of each member's last message, by (channel, member)
+    last_posts: HashMap<(String, String), i64>,
+}
+
+impl CommunityState {
+    /// The roles a member holds.
+    fn member_roles<'a>(&'a self, did: &str) -> impl Iterator<Item = &'a RoleState> + 'a {
+        self.members
+            .get(did)
+            .into_iter()
+            .flat_map(|m| m.roles.iter())
+            .filter_map(|id| self.roles.get(id))
+    }
+
+    /// What a member may do.
+    fn access(&self, did: &str) -> Access {
+        Access::from_roles(
+            did == self.owner_did,
+            self.member_roles(did).map(|r| r.permissions),
+        )
+    }
+
+    /// Where a member stands in the role hierarchy.
+    fn rank(&self, did: &str) -> Rank {
+        if did == self.owner_did {
+            return Rank::Owner;
+        }
+        Rank::of(self.member_roles(did).map(|r| r.position))
+    }
+
+    /// Whether `op`'s author may make changes here at all: only members
+    /// can, except to create the community or join it.
+    pub fn admits(&self, op: &SignedOp) -> bool {
+        matches!(
+            op.op,
+            CommunityOp::CreateCommunity { .. } | CommunityOp::Join { .. }
+        ) || self.members.contains_key(&op.author_did)
+    }
+
+    fn require_member(&self, did: &str) -> Result<&MemberState> {
+        self.members.get(did).ok_or(Error::NotMember)
+    }
+
+    fn is_banned(&self, did: &str, at: i64) -> bool {
+        self.bans
+            .get(did)
+            .is_some_and(|b| b.expires_at.map_or(true, |exp| exp > at))
+    }
+
+    fn role(&self, role_id: &str) -> Result<&RoleState> {
+        self.roles.get(role_id).ok_or(Error::RoleNotFound)
+    }
+
+    fn channel(&self, channel_id: &str) -> Result<&ChannelState> {
+        self.channels.get(channel_id).ok_or(Error::ChannelNotFound)
+    }
+
+    fn live_message(&self, message_id: &str) -> Result<&MessageState> {
+        self.messages
+            .get(message_id)
+            .filter(|m| !m.deleted)
+            .ok_or(Error::MessageNotFound)
+    }
+
+    fn owner_role_id(&self) -> Option<String> {
+        self.genesis_id
+            .as_deref()
+            .map(|id| preset_role_id(id, "owner"))
+    }
+
+    fn remove_channel(&mut self, channel_id: &str) {
+        self.channels.remove(channel_id);
+        self.messages.retain(|_, m| m.channel_id != channel_id);
+        self.last_posts.retain(|(c, _), _| c != channel_id);
+    }
+
+    /// Check `op` against the current state and apply it as of `now`.
+    ///
+    /// A rejected op leaves the state untouched.
+    pub fn apply(&mut self, op: &SignedOp, now: i64) -> Result<()> {
+        let author = op.author_did.as_str();
+
+        let genesis_id = match (&self.genesis_id, &op.op) {
+            (Some(_), CommunityOp::CreateCommunity { .. }) => {
+                return Err(Error::InvalidCommunityOperation(
+                    "Community already created".to_string(),
+                ))
+            }
+            (None, CommunityOp::CreateCommunity { .. }) => op.id.clone(),
+            (None, _) => {
+                return Err(Error::InvalidCommunityOperation(
+                    "Community not created yet".to_string(),
+                ))
+            }
+            (Some(id), _) => id.clone(),
+        };
+        if !self.admits(op) {
+            return Err(Error::NotMember);
+        }
+
+        match &op.op {
+            CommunityOp::CreateCommunity {
+                name,
+                description,
+                nickname,
+                ..
+            } => {
+                self.genesis_id = Some(genesis_id.clone());
+                self.name = name.clone();
+                self.description = description.clone();
+                self.owner_did = author.to_string();
+                self.created_at = now;
+                self.updated_at = now;
+                for (preset, label) in PRESETS {
+                    self.roles.insert(
+                        preset_role_id(&genesis_id, label),
+                        RoleState {
+                            name: preset.name().to_string(),
+                            color: Some(preset.color().to_string()),
+                            position: preset.position(),
+                            permissions: preset.permissions(),
+                            hoisted: preset.hoisted(),
+                            is_preset: true,
+                            created_at: now,
+                        },
+                    );
+                }
+                self.members.insert(
+                    author.to_string(),
+                    MemberState {
+                        nickname: nickname.clone(),
+                        joined_at: now,
+                        roles: BTreeSet::from([preset_role_id(&genesis_id, "owner")]),
+                    },
+                );
+            }
+
+            CommunityOp::UpdateCommunity { name, description } => {
+                self.access(author).check(Permission::ManageCommunity)?;
+                if let Some(name) = name {
+                    self.name = name.clone();
+                }
+                if let Some(description) = description {
+                    self.description = Some(description.clone());
+                }
+                self.updated_at = now;
+            }
+
+            CommunityOp::TransferOwnership { new_owner_did } => {
+                if author != self.owner_did {
+                    return Err(Error::InsufficientPermissions(
+                        "Only the owner can transfer ownership".to_string(),
+                    ));
+                }
+                self.require_member(new_owner_did)?;
+                let owner_role = preset_role_id(&genesis_id, "owner");
+                if let Some(m) = self.members.get_mut(author) {
+                    m.roles.remove(&owner_role);
+                }
+                if let Some(m) = self.members.get_mut(new_owner_did) {
+                    m.roles.insert(owner_role);
+                }
+                self.owner_did = new_owner_did.clone();
+                self.updated_at = now;
+            }
+
+            CommunityOp::CreateSpace { name, position } => {
+                self.access(author).check(Permission::ManageChannels)?;
+                self.spaces.insert(
+                    op.id.clone(),
+                    SpaceState {
+                        name: name.clone(),
+                        position: *position,
+                        created_at: now,
+                    },
+                );
+            }
+
+            CommunityOp::DeleteSpace { space_id } => {
+                self.access(author).check(Permission::ManageChannels)?;
+                if self.spaces.remove(space_id).is_none() {
+                    return Err(Error::SpaceNotFound);
+                }
+                self.categories.retain(|_, c| &c.space_id != space_id);
+                let channels: Vec<String> = self
+                    .channels
+                    .iter()
+                    .filter(|(_, c)| &c.space_id == space_id)
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                for id in channels {
+                    self.remove_channel(&id);
+                }
+            }
+
+            CommunityOp::CreateCategory {
+                space_id,
+                name,
+                position,
+            } => {
+                self.access(author).check(Permission::ManageChannels)?;
+                if !self.spaces.contains_key(space_id) {
+                    return Err(Error::SpaceNotFound);
+                }
+                self.categories.insert(
+                    op.id.clone(),
+                    CategoryState {
+                        space_id: space_id.clone(),
+                        name: name.clone(),
+                        position: *position,
+                        created_at: now,
+                    },
+                );
+            }
+
+            CommunityOp::DeleteCategory { category_id } => {
+                self.access(author).check(Permission::ManageChannels)?;
+                if self.categories.remove(category_id).is_none() {
+                    return Err(Error::CategoryNotFound);
+                }
+                for channel in self.channels.values_mut() {
+                    if channel.category_id.as_ref() == Some(category_id) {
+                        channel.category_id = None;
+                    }
+                }
+            }
+
+            CommunityOp::CreateChannel {
+                space_id,
+                category_id,
+                name,
+                channel_type,
+                topic,
+                position,
+            } => {
+                self.access(author).check(Permission::ManageChannels)?;
+                if !CHANNEL_TYPES.contains(&channel_type.as_str()) {
+                    return Err(Error::InvalidCommunityOperation(format!(
+                        "Invalid channel type: {}",
+                        channel_type
+                    )));
+                }
+                if !self.spaces.contains_key(space_id) {
+                    return Err(Error::SpaceNotFound);
+                }
+                if let Some(category_id) = category_id {
+                    if self.categories.get(category_id).map(|c| &c.space_id) != Some(space_id) {
+                        return Err(Error::CategoryNotFound);
+                    }
+                }
+                self.channels.insert(
+                    op.id.clone(),
+                    ChannelState {
+                        space_id: space_id.clone(),
+                        category_id: category_id.clone(),
+                        name: name.clone(),
+                        channel_type: channel_type.clone(),
+                        topic: topic.clone(),
+                        position: *position,
+                        slow_mode_seconds: 0,
+                        created_at: now,
+                    },
+                );
+            }
+
+            CommunityOp::UpdateChannel {
+                channel_id,
+                name,
+                topic,
+            } => {
+                self.access(author).check(Permission::ManageChannels)?;
+                self.channel(channel_id)?;
+                let channel = self.channels.get_mut(channel_id).expect("checked above");
+                if let Some(name) = name {
+                    channel.name = name.clone();
+                }
+                if let Some(topic) = topic {
+                    channel.topic = Some(topic.clone());
+                }
+            }
+
+            CommunityOp::SetSlowMode {
+                channel_id,
+                seconds,
+            } => {
+                self.access(author).check(Permission::ManageChannels)?;
+                self.channel(channel_id)?;
+                let channel = self.channels.get_mut(channel_id).expect("checked above");
+                channel.slow_mode_seconds = *seconds;
+            }
+
+            CommunityOp::DeleteChannel { channel_id } => {
+                self.access(author).check(Permission::ManageChannels)?;
+                self.channel(channel_id)?;
+                self.remove_channel(channel_id);
+            }
+
+            CommunityOp::CreateRole {
+                name,
+                color,
+                position,
+                permissions,
+            } => {
+                self.access(author).check(Permission::ManageRoles)?;
+                self.rank(author).check_position_below(*position)?;
+                let permissions = Permissions::from_string(permissions);
+                self.access(author).check_grantable(&permissions)?;
+                self.roles.insert(
+                    op.id.clone(),
+                    RoleState {
+                        name: name.clone(),
+                        color: color.clone(),
+                        position: *position,
+                        permissions,
+                        hoisted: false,
+                        is_preset: false,
+                        created_at: now,
+                    },
+                );
+            }
+
+            CommunityOp::UpdateRole {
+                role_id,
+                name,
+                color,
+                position,
+                permissions,
+            } => {
+                self.access(author).check(Permission::ManageRoles)?;
+                if Some(role_id) == self.owner_role_id().as_ref() {
+                    return Err(Error::CannotModifyOwner);
+                }
+                let rank = self.rank(author);
+                rank.check_position_below(self.role(role_id)?.position)?;
+                if let Some(position) = position {
+                    rank.check_position_below(*position)?;
+                }
+                let permissions = permissions.as_deref().map(Permissions::from_string);
+                if let Some(permissions) = &permissions {
+                    self.access(author).check_grantable(permissions)?;
+                }
+                let role = self.roles.get_mut(role_id).expect("checked above");
+                if let Some(name) = name {
+                    role.name = name.clone();
+                }
+                if let Some(color) = color {
+                    role.color = Some(color.clone());
+                }
+                if let Some(position) = position {
+                    role.position = *position;
+                }
+                if let Some(permissions) = permissions {
+                    role.permissions = permissions;
+                }
+            }
+
+            CommunityOp::DeleteRole { role_id } => {
+                self.access(author).check(Permission::ManageRoles)?;
+                let role = self.role(role_id)?;
+                if role.is_preset {
+                    return Err(Error::InvalidCommunityOperation(
+                        "Preset roles can't be deleted".to_string(),
+                    ));
+                }
+                self.rank(author).check_position_below(role.position)?;
+                self.roles.remove(role_id);
+                for member in self.members.values_mut() {
+                    member.roles.remove(role_id);
+                }
+            }
+
+            CommunityOp::AssignRole {
+                member_did,
+                role_id,
+            }
+            | CommunityOp::UnassignRole {
+                member_did,
+                role_id,
+            } => {
+                self.access(author).check(Permission::ManageRoles)?;
+                if Some(role_id) == self.owner_role_id().as_ref() {
+                    return Err(Error::CannotModifyOwner);
+                }
+                self.rank(author)
+                    .check_position_below(self.role(role_id)?.position)?;
+                self.require_member(member_did)?;
+                let member = self.members.get_mut(member_did).expect("checked above");
+                if matches!(op.op, CommunityOp::AssignRole { .. }) {
+                    member.roles.insert(role_id.clone());
+                } else {
+                    member.roles.remove(role_id);
+                }
+            }
+
+            CommunityOp::CreateInvite {
+                code,
+                max_uses,
+                expires_at,
+            } => {
+                self.access(author).check(Permission::CreateInvites)?;
+                if self.invites.values().any(|i| &i.code == code) {
+                    return Err(Error::InvalidCommunityOperation(
+                        "Invite code already in use".to_string(),
+                    ));
+                }
+                self.invites.insert(
+                    op.id.clone(),
+                    InviteState {
+                        code: code.clone(),
+                        creator_did: author.to_string(),
+                        max_uses: *max_uses,
+                        expires_at: *expires_at,
+                        uses: 0,
+                        created_at: now,
+                    },
+                );
+            }
+
+            CommunityOp::RevokeInvite { invite_id } => {
+                let invite = self.invites.get(invite_id).ok_or(Error::InviteNotFound)?;
+                if invite.creator_did != author {
+                    self.access(author).check(Permission::ManageInvites)?;
+                }
+                self.invites.remove(invite_id);
+            }
+
+            CommunityOp::Join { code, nickname } => {
+                if self.members.contains_key(author) {
+                    return Err(Error::AlreadyMember);
+                }
+                if self.is_banned(author, now) {
+                    return Err(Error::BannedFromCommunity);
+                }
+                let invite = self
+                    .invites
+                    .values_mut()
+                    .find(|i| &i.code == code)
+                    .ok_or(Error::InviteNotFound)?;
+                if invite.expires_at.is_some_and(|exp| exp <= now) {
+                    return Err(Error::InviteExpired);
+                }
+                if invite.max_uses.is_some_and(|max| invite.uses >= max) {
+                    return Err(Error::InviteMaxUsesReached);
+                }
+                invite.uses += 1;
+                self.members.insert(
+                    author.to_string(),
+                    MemberState {
+                        nickname: nickname.clone(),
+                        joined_at: now,
+                        roles: BTreeSet::from([preset_role_id(&genesis_id, "member")]),
+                    },
+                );
+            }
+
+            CommunityOp::Leave => {
+                if author == self.owner_did {
+                    return Err(Error::InvalidCommunityOperation(
+                        "Owner cannot leave. Transfer ownership first.".to_string(),
+                    ));
+                }
+                self.members.remove(author);
+            }
+
+            CommunityOp::Kick { member_did } => {
+                self.access(author).check(Permission::KickMembers)?;
+                self.require_member(member_did)?;
+                self.rank(author)
+                    .check_above(self.rank(member_did), Permission::KickMembers)?;
+                self.members.remove(member_did);
+            }
+
+            CommunityOp::Ban {
+                member_did,
+                reason,
+                expires_at,
+            } => {
+                self.access(author).check(Permission::BanMembers)?;
+                self.rank(author)
+                    .check_above(self.rank(member_did), Permission::BanMembers)?;
+                self.members.remove(member_did);
+                self.bans.insert(
+                    member_did.clone(),
+                    BanState {
+                        reason: reason.clone(),
+                        banned_by: author.to_string(),
+                        expires_at: *expires_at,
+                        created_at: now,
+                    },
+                );
+            }
+
+            CommunityOp::Unban { member_did } => {
+                self.access(author).check(Permission::BanMembers)?;
+                if self.bans.remove(member_did).is_none() {
+                    return Err(Error::InvalidCommunityOperation(
+                        "Member isn't banned".to_string(),
+                    ));
+                }
+            }
+
+            CommunityOp::SendMessage {
+                channel_id,
+                content,
+                reply_to_id,
+            } => {
+                let channel = self.channel(channel_id)?;
+                if channel.channel_type == "voice" {
+                    return Err(Error::ChannelTypeRestriction(
+                        "Cannot send text messages in a voice channel".to_string(),
+                    ));
+                }
+                if channel.channel_type == "announcement" {
+                    self.access(author).check(Permission::ManageChannels)?;
+                }
+                self.access(author).check(Permission::SendMessages)?;
+                let key = (channel_id.clone(), author.to_string());
+                if channel.slow_mode_seconds > 0 {
+                    if let Some(last) = self.last_posts.get(&key) {
+                        if now - last < channel.slow_mode_seconds as i64 {
+                            return Err(Error::InvalidCommunityOperation(format!(
+                                "Slow mode: wait {} seconds between messages",
+                                channel.slow_mode_seconds
+                            )));
+                        }
+                    }
+                }
+                self.last_posts.insert(key, now);
+                self.messages.insert(
+                    op.id.clone(),
+                    MessageState {
+                        channel_id: channel_id.clone(),
+                        sender_did: author.to_string(),
+                        content: content.clone(),
+                        reply_to_id: reply_to_id.clone(),
+                        created_at: now,
+                        edited_at: None,
+                        deleted: false,
+                    },
+                );
+            }
+
+            CommunityOp::EditMessage {
+                message_id,
+                content,
+            } => {
+                if self.live_message(message_id)?.sender_did != author {
+                    return Err(Error::InsufficientPermissions(
+                        "Only the author can edit a message".to_string(),
+                    ));
+                }
+                let message = self.messages.get_mut(message_id).expect("checked above");
+                message.content = content.clone();
+                message.edited_at = Some(now);
+            }
+
+            CommunityOp::DeleteMessage { message_id } => {
+                if self.live_message(message_id)?.sender_did != author {
+                    self.access(author).check(Permission::ManageMessages)?;
+                }
+                let message = self.messages.get_mut(message_id).expect("checked above");
+                message.deleted = true;
+                message.content.clear();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The result of replaying a community's log, which later ops can be
+/// placed on top of
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Replay {
+    /// The community after every valid op
+    pub state: CommunityState,
+    /// Ops that were invalid where they fell in the log, with the reason
+    pub rejected: BTreeMap<String, String>,
+    /// Ops by authors who weren't members where they fall. They aren't
+    /// placed, and ops following them wait.
+    pub outsiders: BTreeSet<String>,
+    /// Ops waiting for an ancestor we don't have
+    pub pending: BTreeSet<String>,
+    /// Parents referenced by ops that we don't have
+    pub missing: BTreeSet<String>,
+    /// Ops nothing we have follows yet, which new ops take as parents
+    pub heads: Vec<String>,
+    /// Lamport clock of the heads
+    pub lamport: u64,
+    /// Lamport clock and time of every placed op
+    placed: HashMap<String, (u64, i64)>,
+    /// (lamport, id) of the last placed op
+    last: Option<(u64, String)>,
+}
+
+impl Replay {
+    /// Whether `id` was placed (applied or not).
+    pub fn placed(&self, id: &str) -> bool {
+        self.placed.contains_key(id)
+    }
+
+    /// Whether we've seen `id` at all.
+    pub fn knows(&self, id: &str) -> bool {
+        self.placed(id) || self.pending.contains(id) || self.outsiders.contains(id)
+    }
+
+    /// Whether `op` can be placed on top of what we have: nothing is
+    /// waiting, its parents are placed, and it sorts after the last op.
+    pub fn extends(&self, op: &SignedOp) -> bool {
+        self.pending.is_empty()
+            && op.parents.iter().all(|p| self.placed(p))
+            && self
+                .last
+                .as_ref()
+                .map_or(true, |(lamport, id)| (op.lamport, &op.id) > (*lamport, id))
+    }
+
+    /// When `op` happened: its author's timestamp, but no earlier than its
+    /// parents. Only signed log data goes in, so every replica times an op
+    /// the same way. Its parents must all be placed.
+    pub fn time_of(&self, op: &SignedOp) -> i64 {
+        let floor = op.parents.iter().map(|p| self.placed[p].1).max();
+        floor.map_or(op.timestamp, |floor| op.timestamp.max(floor))
+    }
+
+    /// Place `op` after everything placed so far. Its parents must all be
+    /// placed.
+    pub fn place(&mut self, op: &SignedOp) {
+        if !self.state.admits(op) {
+            self.outsiders.insert(op.id.clone());
+            self.rejected
+                .insert(op.id.clone(), Error::NotMember.to_string());
+            return;
+        }
+
+        let expected = op
+            .parents
+            .iter()
+            .map(|p| self.placed[p].0 + 1)
+            .max()
+            .unwrap_or(0);
+        let now = self.time_of(op);
+        let result = if op.lamport != expected {
+            Err(Error::InvalidCommunityOperation(
+                "Lamport clock doesn't follow its parents".to_string(),
+            ))
+        } else {
+            self.state.apply(op, now)
+        };
+        if let Err(e) = result {
+            self.rejected.insert(op.id.clone(), e.to_string());
+        }
+        self.record(op, now);
+    }
+
+    /// Record `op`, timed at `now`, as the latest placed op.
+    pub fn record(&mut self, op: &SignedOp, now: i64) {
+        self.placed.insert(op.id.clone(), (op.lamport, now));
+        self.heads.retain(|h| !op.parents.contains(h));
+        self.heads.push(op.id.clone());
+        self.heads.sort();
+        self.lamport = self.lamport.max(op.lamport);
+        self.last = Some((op.lamport, op.id.clone()));
+    }
+}
+
+/// Order and replay a community's ops.
+///
+/// Ops are taken in (lamport, id) order, which puts every op after its
+/// parents and breaks ties between concurrent ops the same way everywhere.
+pub(crate) fn replay(mut ops: Vec<SignedOp>) -> Replay {
+    ops.sort_by(|a, b| (a.lamport, &a.id).cmp(&(b.lamport, &b.id)));
+    let stored: HashSet<&str> = ops.iter().map(|op| op.id.as_str()).collect();
+
+    let mut replay = Replay::default();
+    for op in &ops {
+        if op.parents.iter().all(|p| replay.placed(p)) {
+            replay.place(op);
+            continue;
+        }
+        replay.pending.insert(op.id.clone());
+        for parent in &op.parents {
+            if !stored.contains(parent.as_str()) {
+                replay.missing.insert(parent.clone());
+            }
+        }
+    }
+    replay
+}
@@ -7,6 +7,7 @@
 //!
 //! Platform-agnostic: works with Discord, GitHub, Steam, etc.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::CommunitySeatRecord;
@@ -113,6 +114,7 @@ impl super::CommunityService {
             .db()
             .get_community_seat(seat_id)?
             .ok_or_else(|| Error::InvalidCommunityOperation("Seat not found".to_string()))?;
+        self.require_permission(&seat.community_id, actor_did, Permission::ManageCommunity)?;
 
         let now = crate::time::now_timestamp();
         self.db().delete_community_seat(seat_id)?;
@@ -140,7 +142,13 @@ impl super::CommunityService {
     /// Create seats in batch (for import).
     ///
     /// Takes a list of seat data and creates them all. Returns the count of seats created.
-    pub fn create_seats_batch(&self, community_id: &str, seats: Vec<SeatInput>) -> Result<usize> {
+    pub fn create_seats_batch(
+        &self,
+        community_id: &str,
+        seats: Vec<SeatInput>,
+        actor_did: &str,
+    ) -> Result<usize> {
+        self.require_permission(community_id, actor_did, Permission::ManageCommunity)?;
         let now = crate::time::now_timestamp();
 
         let records: Vec<CommunitySeatRecord> = seats
@@ -2,6 +2,8 @@
 //!
 //! Core service struct for community CRUD operations.
 
+use super::oplog::ReplayCache;
+use super::permissions::Permission;
 use crate::error::{Error, Result};
 use crate::storage::Database;
 use std::sync::Arc;
@@ -9,15 +11,21 @@ use std::sync::Arc;
 /// The main community service — coordinates all community operations.
 ///
 /// Holds a reference to the database and provides high-level methods
-/// for creating, updating, and querying communities.
+/// for creating, updating, and querying communities. Clones share the
+/// replayed op logs of log-backed communities.
+#[derive(Clone)]
 pub struct CommunityService {
     db: Arc<Database>,
+    replays: Arc<ReplayCache>,
 }
 
 impl CommunityService {
     /// Create a new community service backed by the given database.
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            replays: Arc::new(ReplayCache::default()),
+        }
     }
 
     /// Get a reference to the underlying database.
@@ -25,6 +33,11 @@ impl CommunityService {
         &self.db
     }
 
+    /// The replayed op logs of log-backed communities.
+    pub(crate) fn replays(&self) -> &ReplayCache {
+        &self.replays
+    }
+
     /// Create a new community with default spaces and roles.
     ///
     /// This is the primary entry point for community creation. It:
@@ -143,6 +156,8 @@ impl CommunityService {
         description: Option<&str>,
         actor_did: &str,
     ) -> Result<()> {
+        self.require_permission(id, actor_did, Permission::ManageCommunity)?;
+        self.require_unsigned(id)?;
         let now = crate::time::now_timestamp();
 
         self.db().update_community(id, name, description, now)?;
@@ -169,6 +184,7 @@ impl CommunityService {
                 "Only the owner can delete a community".to_string(),
             ));
         }
+        self.require_unsigned(id)?;
 
         self.db().delete_community(id)?;
         Ok(())
@@ -187,6 +203,7 @@ impl CommunityService {
                 "Only the owner can transfer ownership".to_string(),
             ));
         }
+        self.require_unsigned(community_id)?;
 
         let now = crate::time::now_timestamp();
         self.db()
@@ -3,6 +3,7 @@
 //! Spaces are one level of organization within a community.
 //! Each space contains channels and has a position for ordering.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::CommunitySpaceRecord;
@@ -16,6 +17,8 @@ impl super::CommunityService {
         position: i32,
         actor_did: &str,
     ) -> Result<CommunitySpaceRecord> {
+        self.require_permission(community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(community_id)?;
         let now = crate::time::now_timestamp();
         let space_id = generate_id();
 
@@ -55,6 +58,8 @@ impl super::CommunityService {
             .db()
             .get_community_space(space_id)?
             .ok_or(Error::SpaceNotFound)?;
+        self.require_permission(&space.community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&space.community_id)?;
 
         self.db().update_community_space(space_id, name, now)?;
 
@@ -77,8 +82,10 @@ impl super::CommunityService {
         &self,
         community_id: &str,
         space_ids: &[String],
-        _actor_did: &str,
+        actor_did: &str,
     ) -> Result<()> {
+        self.require_permission(community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(community_id)?;
         let now = crate::time::now_timestamp();
         for (position, space_id) in space_ids.iter().enumerate() {
             self.db()
@@ -86,7 +93,6 @@ impl super::CommunityService {
         }
 
         // Audit log for reorder is optional (too noisy)
-        let _ = community_id;
         Ok(())
     }
 
@@ -96,6 +102,8 @@ impl super::CommunityService {
             .db()
             .get_community_space(space_id)?
             .ok_or(Error::SpaceNotFound)?;
+        self.require_permission(&space.community_id, actor_did, Permission::ManageChannels)?;
+        self.require_unsigned(&space.community_id)?;
 
         let now = crate::time::now_timestamp();
         self.db().delete_community_space(space_id)?;
@@ -3,6 +3,7 @@
 //! Threaded conversations, thread follow/unfollow, message search,
 //! and advanced search with filters.
 
+use super::permissions::Permission;
 use super::service::generate_id;
 use crate::error::{Error, Result};
 use crate::storage::{
@@ -20,6 +21,7 @@ impl super::CommunityService {
         name: Option<&str>,
         created_by: &str,
     ) -> Result<CommunityThreadRecord> {
+        self.require_channel_permission(channel_id, created_by, Permission::CreateThreads)?;
         let now = crate::time::now_timestamp();
         let id = generate_id();
 
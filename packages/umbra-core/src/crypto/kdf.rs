@@ -128,7 +128,6 @@ pub mod domain {
     pub const MESSAGE_ENCRYPTION: &[u8] = b"umbra-message-encryption-v1";
 
     /// Domain for storage encryption key derivation
-    #[allow(dead_code)]
     pub const STORAGE_ENCRYPTION: &[u8] = b"umbra-storage-encryption-v1";
 
     /// Domain for per-file encryption key derivation
@@ -145,6 +144,33 @@ pub mod domain {
 
     /// Domain for account sync encryption key derivation
     pub const ACCOUNT_SYNC: &[u8] = b"umbra-account-sync-v1";
+
+    /// Domain for the X3DH session-establishment secret
+    pub const X3DH: &[u8] = b"umbra-x3dh-v1";
+
+    /// Domain for Double Ratchet root key steps
+    pub const RATCHET_ROOT: &[u8] = b"umbra-ratchet-root-v1";
+
+    /// Prefix for data signed over a prekey
+    pub const SIGNED_PREKEY: &[u8] = b"umbra-signed-prekey-v1";
+
+    /// Domain for per-device message key wrapping (multi-device fan-out)
+    pub const DEVICE_FANOUT: &[u8] = b"umbra-device-fanout-v1";
+
+    /// Domain for sealed-sender envelope encryption
+    pub const SEALED_SENDER: &[u8] = b"umbra-sealed-sender-v1";
+
+    /// Domain for sealed-sender delivery access keys
+    pub const SEALED_ACCESS: &[u8] = b"umbra-sealed-access-v1";
+
+    /// Domain for the MLS key schedule and tree secrets
+    pub const MLS: &[u8] = b"umbra-mls-v1";
+
+    /// Domain for sealing MLS secrets to a member's key
+    pub const MLS_HPKE: &[u8] = b"umbra-mls-hpke-v1";
+
+    /// Prefix for data signed by MLS group members
+    pub const MLS_SIGNATURE: &[u8] = b"umbra-mls-signature-v1";
 }
 
 /// Keys derived from a master seed
@@ -221,15 +247,21 @@ pub fn derive_shared_secret(dh_output: &[u8; 32], conversation_id: &[u8]) -> Res
 /// ## Security
 ///
 /// The storage key is derived from both signing and encryption keys,
-/// ensuring it's tied to the user's identity.
-#[allow(dead_code)]
-pub fn derive_storage_key(signing_key: &[u8; 32], encryption_key: &[u8; 32]) -> Result<[u8; 32]> {
+/// ensuring it's tied to the user's identity. `host_secret` (the HKDF
+/// salt) binds it to a secret of the device as well, such as the key of a
+/// passphrase vault, so changing that secret changes the storage key.
+/// It's empty where the platform keystore already guards the database.
+pub fn derive_storage_key(
+    signing_key: &[u8; 32],
+    encryption_key: &[u8; 32],
+    host_secret: &[u8],
+) -> Result<[u8; 32]> {
     // Combine both keys as input key material
     let mut combined = [0u8; 64];
     combined[..32].copy_from_slice(signing_key);
     combined[32..].copy_from_slice(encryption_key);
 
-    let hkdf = Hkdf::<Sha256>::new(None, &combined);
+    let hkdf = Hkdf::<Sha256>::new(Some(host_secret), &combined);
 
     let mut storage_key = [0u8; 32];
     hkdf.expand(domain::STORAGE_ENCRYPTION, &mut storage_key)
@@ -307,6 +339,52 @@ pub fn derive_file_key(shared_secret: &[u8; 32], file_id: &[u8]) -> Result<[u8;
     Ok(key)
 }
 
+/// Derive the key that wraps a message content key for one device.
+///
+/// Uses HKDF-SHA256 with the ephemeral-to-device ECDH output as input key
+/// material and the message ID as salt, so every (message, device) pair
+/// gets an independent wrapping key.
+pub fn derive_device_wrap_key(dh_output: &[u8; 32], message_id: &[u8]) -> Result<[u8; 32]> {
+    let hkdf = Hkdf::<Sha256>::new(Some(message_id), dh_output);
+
+    let mut key = [0u8; 32];
+    hkdf.expand(domain::DEVICE_FANOUT, &mut key)
+        .map_err(|_| Error::KeyDerivationFailed("Failed to derive device wrap key".into()))?;
+
+    Ok(key)
+}
+
+/// Derive the key that encrypts a sealed-sender envelope.
+///
+/// Uses HKDF-SHA256 with the ephemeral-to-recipient ECDH output as input key
+/// material and `ephemeral_public || recipient_public` as salt, binding the
+/// key to both ends of the exchange.
+pub fn derive_sealed_sender_key(dh_output: &[u8; 32], salt: &[u8]) -> Result<[u8; 32]> {
+    let hkdf = Hkdf::<Sha256>::new(Some(salt), dh_output);
+
+    let mut key = [0u8; 32];
+    hkdf.expand(domain::SEALED_SENDER, &mut key)
+        .map_err(|_| Error::KeyDerivationFailed("Failed to derive sealed sender key".into()))?;
+
+    Ok(key)
+}
+
+/// Derive the sealed-sender access key for messages to `recipient_did`.
+///
+/// Uses HKDF-SHA256 with the static ECDH output between two friends as
+/// input key material and the recipient's DID as salt. Both friends derive
+/// the same key; the recipient registers its hash with their relay, and the
+/// sender presents it as a delivery credential.
+pub fn derive_sealed_access_key(dh_output: &[u8; 32], recipient_did: &[u8]) -> Result<[u8; 32]> {
+    let hkdf = Hkdf::<Sha256>::new(Some(recipient_did), dh_output);
+
+    let mut key = [0u8; 32];
+    hkdf.expand(domain::SEALED_ACCESS, &mut key)
+        .map_err(|_| Error::KeyDerivationFailed("Failed to derive sealed access key".into()))?;
+
+    Ok(key)
+}
+
 /// Derive a unique encryption key for a community channel file.
 ///
 /// Uses HKDF-SHA256 with the channel's group key as input key material,
@@ -503,10 +581,13 @@ mod tests {
         let signing = [1u8; 32];
         let encryption = [2u8; 32];
 
-        let key1 = derive_storage_key(&signing, &encryption).unwrap();
-        let key2 = derive_storage_key(&signing, &encryption).unwrap();
-
+        let key1 = derive_storage_key(&signing, &encryption, b"").unwrap();
+        let key2 = derive_storage_key(&signing, &encryption, b"").unwrap();
         assert_eq!(key1, key2);
+
+        // A different host secret gives a different key
+        let key3 = derive_storage_key(&signing, &encryption, &[3u8; 32]).unwrap();
+        assert_ne!(key1, key3);
     }
 
     #[test]
@@ -292,7 +292,7 @@ impl PublicKey {
 }
 
 /// Serde helper for serializing byte arrays as hex
-mod hex_bytes {
+pub(crate) mod hex_bytes {
     use serde::{Deserialize, Deserializer, Serializer};
 
     pub fn serialize<S>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error>
@@ -0,0 +1,304 @@
+//! MLS wire types
+//!
+//! Everything here travels between members (through the relay) as bincode
+//! bytes; see [`MlsMessage`](super::MlsMessage) for encoding.
+
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::schedule::HpkeCiphertext;
+use super::tree::{LeafNode, RatchetTree};
+use super::MlsMessage;
+use crate::crypto::encryption::NONCE_SIZE;
+use crate::crypto::kdf::domain;
+use crate::crypto::keys::{hex_bytes, EncryptionKeyPair, SigningKeyPair};
+use crate::crypto::signing::{sign, verify, Signature};
+use crate::error::Result;
+
+/// Bytes covered by an MLS signature: a domain prefix, a label naming the
+/// kind of content, then the content itself
+pub(crate) fn to_be_signed(label: &str, parts: &[&[u8]]) -> Vec<u8> {
+    let len = parts.iter().map(|p| p.len()).sum::<usize>();
+    let mut data = Vec::with_capacity(domain::MLS_SIGNATURE.len() + label.len() + 1 + len);
+    data.extend_from_slice(domain::MLS_SIGNATURE);
+    data.extend_from_slice(label.as_bytes());
+    data.push(0);
+    for part in parts {
+        data.extend_from_slice(part);
+    }
+    data
+}
+
+// ============================================================================
+// KEY PACKAGES
+// ============================================================================
+
+/// A prospective member's signed keys, published so they can be added to a group
+///
+/// The private halves stay with the owner in [`KeyPackageSecrets`]. A key
+/// package should be used for one group add only.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyPackage {
+    /// The leaf the member will occupy
+    pub leaf: LeafNode,
+    /// X25519 key the Welcome's group secrets are encrypted to
+    #[serde(with = "hex_bytes")]
+    pub init_key: [u8; 32],
+    /// Signature by the leaf's signing key (hex encoded)
+    pub signature: String,
+}
+
+/// Private keys of a [`KeyPackage`]
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct KeyPackageSecrets {
+    pub(crate) init_secret: [u8; 32],
+    pub(crate) leaf_secret: [u8; 32],
+}
+
+impl KeyPackage {
+    /// Generate a key package for `did`, signed with its identity key
+    pub fn generate(signing: &SigningKeyPair, did: &str) -> (Self, KeyPackageSecrets) {
+        let init = EncryptionKeyPair::generate();
+        let leaf_keys = EncryptionKeyPair::generate();
+        let leaf = LeafNode::new(signing, did, leaf_keys.public_bytes());
+        let init_key = init.public_bytes();
+        let data = to_be_signed("key_package", &[leaf.signature.as_bytes(), &init_key]);
+
+        let package = Self {
+            leaf,
+            init_key,
+            signature: sign(signing, &data).to_hex(),
+        };
+        let secrets = KeyPackageSecrets {
+            init_secret: init.secret_bytes(),
+            leaf_secret: leaf_keys.secret_bytes(),
+        };
+        (package, secrets)
+    }
+
+    /// Check both signatures and that the DID matches the signing key
+    pub fn verify(&self) -> Result<()> {
+        self.leaf.verify()?;
+        let signature = Signature::from_hex(&self.signature)?;
+        let data = to_be_signed(
+            "key_package",
+            &[self.leaf.signature.as_bytes(), &self.init_key],
+        );
+        verify(&self.leaf.signature_key, &data, &signature)
+    }
+
+    /// Hash identifying this key package in a Welcome
+    pub fn reference(&self) -> Result<[u8; 32]> {
+        Ok(super::schedule::hash(&[&self.to_bytes()?]))
+    }
+}
+
+impl MlsMessage for KeyPackage {}
+
+impl KeyPackageSecrets {
+    /// Serialize for persistence
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        super::encode(self)
+    }
+
+    /// Restore persisted secrets
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        super::decode(bytes)
+    }
+}
+
+// ============================================================================
+// PROPOSALS
+// ============================================================================
+
+/// A proposed membership change, applied by the next commit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Proposal {
+    /// Add the owner of a key package
+    Add(KeyPackage),
+    /// Remove the member at a leaf
+    Remove {
+        /// Leaf index of the member
+        leaf: u32,
+    },
+    /// Replace a member's leaf with fresh keys (post-compromise security
+    /// for members who don't commit themselves)
+    Update {
+        /// Leaf index of the member, who must be the proposer
+        leaf: u32,
+        /// The replacement leaf
+        leaf_node: LeafNode,
+    },
+}
+
+/// A proposal sent on its own, for whoever commits next to include
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MlsProposal {
+    /// Group the proposal is for
+    pub group_id: String,
+    /// Epoch the proposal was made in
+    pub epoch: u64,
+    /// Leaf index of the proposer
+    pub sender: u32,
+    /// The proposed change
+    pub proposal: Proposal,
+    /// Signature by the proposer over the fields above (hex encoded)
+    pub signature: String,
+}
+
+impl MlsMessage for MlsProposal {}
+
+// ============================================================================
+// COMMITS
+// ============================================================================
+
+/// The committer's new path: fresh leaf and parent keys, each parent's path
+/// secret encrypted to the resolution of the matching copath node
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UpdatePath {
+    /// The committer's new leaf
+    pub leaf: LeafNode,
+    /// New parent nodes, from the leaf's parent up to the root
+    pub nodes: Vec<UpdatePathNode>,
+}
+
+/// One parent node of an [`UpdatePath`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UpdatePathNode {
+    /// The node's new public key
+    #[serde(with = "hex_bytes")]
+    pub encryption_key: [u8; 32],
+    /// The node's path secret, once per node in the copath resolution
+    /// (minus leaves added by this commit, which get it in the Welcome)
+    pub encrypted_path_secret: Vec<HpkeCiphertext>,
+}
+
+/// Moves the group to the next epoch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Commit {
+    /// Group the commit is for
+    pub group_id: String,
+    /// Epoch the commit was created in
+    pub epoch: u64,
+    /// Leaf index of the committer
+    pub sender: u32,
+    /// Proposals applied by the commit, in order
+    pub proposals: Vec<Proposal>,
+    /// The committer's fresh keys
+    pub path: UpdatePath,
+    /// Signature by the committer over the fields above and the group
+    /// context (hex encoded)
+    pub signature: String,
+    /// MAC under the new epoch's confirmation key over the new transcript
+    #[serde(with = "hex_bytes")]
+    pub confirmation_tag: [u8; 32],
+}
+
+impl MlsMessage for Commit {}
+
+impl Commit {
+    /// The part of the commit covered by its signature and the transcript
+    pub(crate) fn content_bytes(&self) -> Result<Vec<u8>> {
+        super::encode(&(
+            &self.group_id,
+            self.epoch,
+            self.sender,
+            &self.proposals,
+            &self.path,
+        ))
+    }
+}
+
+// ============================================================================
+// WELCOME
+// ============================================================================
+
+/// Brings members added by a commit into the new epoch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Welcome {
+    /// Group secrets, one entry per new member
+    pub secrets: Vec<EncryptedGroupSecrets>,
+    /// Nonce of `encrypted_group_info`
+    pub nonce: [u8; NONCE_SIZE],
+    /// [`GroupInfo`] encrypted under the welcome key
+    pub encrypted_group_info: Vec<u8>,
+}
+
+impl MlsMessage for Welcome {}
+
+/// [`GroupSecrets`] sealed to a key package's init key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EncryptedGroupSecrets {
+    /// [`KeyPackage::reference`] of the recipient's key package
+    #[serde(with = "hex_bytes")]
+    pub key_package_ref: [u8; 32],
+    /// The encoded [`GroupSecrets`]
+    pub encrypted: HpkeCiphertext,
+}
+
+/// What a new member needs besides the public group info
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct GroupSecrets {
+    pub joiner_secret: [u8; 32],
+    /// Path secret of the lowest node shared with the committer
+    pub path_secret: Option<[u8; 32]>,
+}
+
+/// The new epoch's public state, signed by the committer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct GroupInfo {
+    pub group_id: String,
+    pub epoch: u64,
+    pub tree: RatchetTree,
+    pub transcript_hash: [u8; 32],
+    pub confirmation_tag: [u8; 32],
+    pub signer: u32,
+    pub signature: String,
+}
+
+impl GroupInfo {
+    pub(crate) fn content_bytes(&self) -> Result<Vec<u8>> {
+        super::encode(&(
+            &self.group_id,
+            self.epoch,
+            &self.tree,
+            self.transcript_hash,
+            self.confirmation_tag,
+            self.signer,
+        ))
+    }
+}
+
+// ============================================================================
+// APPLICATION MESSAGES
+// ============================================================================
+
+/// An encrypted application message
+///
+/// The sender's leaf and generation travel in the clear (authenticated as
+/// AAD), like a ratchet header, so the recipient can find the key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MlsCiphertext {
+    /// Group the message was sent to
+    pub group_id: String,
+    /// Epoch whose keys encrypted the message
+    pub epoch: u64,
+    /// Leaf index of the sender
+    pub sender: u32,
+    /// Position in the sender's chain
+    pub generation: u32,
+    /// AES-GCM nonce
+    pub nonce: [u8; NONCE_SIZE],
+    /// Encrypted, signed content
+    pub ciphertext: Vec<u8>,
+}
+
+impl MlsMessage for MlsCiphertext {}
+
+/// Plaintext inside an [`MlsCiphertext`]: the content and the sender's
+/// signature, so members can't impersonate each other
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ApplicationContent {
+    pub content: Vec<u8>,
+    pub signature: String,
+}
@@ -0,0 +1,1428 @@
+//! # MLS Group State
+//!
+//! TreeKEM group key agreement modelled on MLS (RFC 9420), used for group
+//! conversations. Every membership change is a signed **commit** that moves
+//! the group to a new **epoch** with fresh secrets; members added by a
+//! commit receive a **Welcome**.
+//!
+//! ## Ratchet Tree
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                      TREEKEM COMMIT                                     │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │              root ◄── path secret ps[2]                                 │
+//! │            ┌───┴───┐                                                    │
+//! │     ps[1]─►P       Q ◄── encrypted ps[2] to Q (or Q's resolution)       │
+//! │          ┌─┴─┐   ┌─┴─┐                                                  │
+//! │          A   B   C   D                                                  │
+//! │          │   ▲                                                          │
+//! │          │   └── encrypted ps[1] to B                                   │
+//! │          └── committer: ps[0] random, ps[i+1] = KDF(ps[i])              │
+//! │                                                                         │
+//! │  Each node's key pair is derived from its path secret. A commit sends   │
+//! │  one ciphertext per level (more only where the tree has blanks), so     │
+//! │  adds and removes cost O(log n). The root's next secret is the          │
+//! │  commit secret that feeds the key schedule (see `schedule`).            │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! ## Message Flow
+//!
+//! ```text
+//!   New member ──KeyPackage──► Committer
+//!   Committer  ──Commit──────► existing members   (process_commit)
+//!   Committer  ──Welcome─────► new member         (join)
+//!   Any member ──MlsCiphertext► everyone          (encrypt / decrypt)
+//! ```
+//!
+//! Commits are applied by their sender as soon as they're created, so only
+//! one member should commit at a time (in Umbra, the group admin); a commit
+//! for an epoch the group has already left is rejected.
+//!
+//! ## Security Properties
+//!
+//! | Property | Provided by |
+//! |----------|-------------|
+//! | Forward secrecy | Per-sender chains delete used keys; old epochs are dropped after `MAX_PAST_EPOCHS` |
+//! | Post-compromise security | Every commit replaces the committer's whole path with fresh keys; `propose_update` does the same for other members |
+//! | Removed members are locked out | A removal blanks the member's path, and the commit re-keys every node they knew |
+//! | No member is left on a stale key | The commit's path covers every leaf; anyone it can't reach fails to process it instead of staying behind |
+//! | Agreement | Epoch secrets depend on the tree hash and the transcript; the confirmation tag proves both sides derived the same ones |
+//! | Authenticity | Leaves, commits, proposals and application messages are signed with the member's DID key |
+//! | Atomic state updates | `process_commit` and `decrypt` only change state once everything verified |
+//!
+//! Simplifications next to RFC 9420: a single cipher suite, no parent hashes
+//! (leaves and commits are signed instead), proposals are carried by value
+//! in the commit, and handshake messages aren't encrypted.
+
+mod messages;
+mod schedule;
+mod tree;
+
+pub use messages::{
+    Commit, EncryptedGroupSecrets, KeyPackage, KeyPackageSecrets, MlsCiphertext, MlsProposal,
+    Proposal, UpdatePath, UpdatePathNode, Welcome,
+};
+pub use schedule::HpkeCiphertext;
+pub use tree::{LeafNode, RatchetTree};
+
+use bincode::Options;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::crypto::encryption::{decrypt, encrypt, EncryptionKey, Nonce};
+use crate::crypto::keys::{EncryptionKeyPair, SigningKeyPair};
+use crate::crypto::signing::{sign, verify, Signature};
+use crate::error::{Error, Result};
+use messages::{to_be_signed, ApplicationContent, GroupInfo, GroupSecrets};
+use schedule::{EpochSecrets, SenderChain};
+use tree::{copath, direct_path, in_subtree, leaf_node};
+
+/// Number of previous epochs whose application keys are kept, so messages
+/// sent just before a commit can still be read
+pub const MAX_PAST_EPOCHS: usize = 2;
+
+/// Upper bound on the size of a decoded MLS message or state
+const MAX_ENCODED_SIZE: u64 = 16 * 1024 * 1024;
+
+// ============================================================================
+// ENCODING
+// ============================================================================
+
+/// Binary encoding shared by MLS wire messages
+pub trait MlsMessage: Serialize + DeserializeOwned {
+    /// Encode for sending
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        encode(self)
+    }
+
+    /// Decode a received message
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        decode(bytes)
+    }
+}
+
+fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    bincode::serialize(value).map_err(|e| Error::SerializationError(e.to_string()))
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_ENCODED_SIZE)
+        .deserialize(bytes)
+        .map_err(|e| Error::DeserializationError(format!("Invalid MLS data: {}", e)))
+}
+
+fn random_secret() -> [u8; 32] {
+    let mut secret = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+fn to_array(bytes: &[u8]) -> Result<[u8; 32]> {
+    bytes
+        .try_into()
+        .map_err(|_| Error::DecryptionFailed("MLS secret is not 32 bytes".into()))
+}
+
+fn context_bytes(
+    group_id: &str,
+    epoch: u64,
+    tree_hash: &[u8; 32],
+    transcript_hash: &[u8; 32],
+) -> Result<Vec<u8>> {
+    encode(&(group_id, epoch, tree_hash, transcript_hash))
+}
+
+fn path_info(group_id: &str, epoch: u64, node: u32) -> Vec<u8> {
+    let mut info = Vec::with_capacity(group_id.len() + 12);
+    info.extend_from_slice(group_id.as_bytes());
+    info.extend_from_slice(&epoch.to_be_bytes());
+    info.extend_from_slice(&node.to_be_bytes());
+    info
+}
+
+fn application_aad(message: &MlsCiphertext, aad: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(message.group_id.len() + 16 + aad.len());
+    out.extend_from_slice(message.group_id.as_bytes());
+    out.extend_from_slice(&message.epoch.to_be_bytes());
+    out.extend_from_slice(&message.sender.to_be_bytes());
+    out.extend_from_slice(&message.generation.to_be_bytes());
+    out.extend_from_slice(aad);
+    out
+}
+
+// ============================================================================
+// GROUP STATE
+// ============================================================================
+
+/// Private key of a parent node on our direct path
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct NodeSecret {
+    node: u32,
+    secret: [u8; 32],
+}
+
+/// Application keys of an epoch we've moved past
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct PastEpoch {
+    epoch: u64,
+    #[zeroize(skip)]
+    tree: RatchetTree,
+    senders: Vec<SenderChain>,
+}
+
+/// Result of [`MlsGroup::commit`]
+#[derive(Debug, Clone)]
+pub struct CommitOutput {
+    /// Send to every other member of the previous epoch
+    pub commit: Commit,
+    /// Send to the added members, if any
+    pub welcome: Option<Welcome>,
+    /// DIDs added by the commit
+    pub added: Vec<String>,
+    /// DIDs removed by the commit
+    pub removed: Vec<String>,
+}
+
+/// Result of [`MlsGroup::process_commit`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitOutcome {
+    /// The group moved to the next epoch
+    Applied {
+        /// DID of the committer
+        committer: String,
+        /// DIDs added by the commit
+        added: Vec<String>,
+        /// DIDs removed by the commit
+        removed: Vec<String>,
+    },
+    /// The commit removed us; the group state should be deleted
+    Removed {
+        /// DID of the committer
+        committer: String,
+    },
+}
+
+/// Proposals applied to a copy of the tree
+struct AppliedProposals {
+    /// (leaf index, DID) of each added member, with its key package
+    added: Vec<(u32, KeyPackage)>,
+    /// (leaf index, DID) of each removed member
+    removed: Vec<(u32, String)>,
+}
+
+impl AppliedProposals {
+    fn added_nodes(&self) -> Vec<u32> {
+        self.added
+            .iter()
+            .map(|(leaf, _)| leaf_node(*leaf))
+            .collect()
+    }
+
+    fn added_dids(&self) -> Vec<String> {
+        self.added
+            .iter()
+            .map(|(_, kp)| kp.leaf.did.clone())
+            .collect()
+    }
+
+    fn removed_dids(&self) -> Vec<String> {
+        self.removed.iter().map(|(_, did)| did.clone()).collect()
+    }
+}
+
+/// One member's view of an MLS group
+///
+/// Serialize with [`to_bytes`](Self::to_bytes) for persistence. All secret
+/// material is zeroized on drop.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct MlsGroup {
+    group_id: String,
+    epoch: u64,
+    #[zeroize(skip)]
+    tree: RatchetTree,
+    own_leaf: u32,
+    /// Private key of our leaf
+    leaf_secret: [u8; 32],
+    /// Private keys of parent nodes on our direct path
+    node_secrets: Vec<NodeSecret>,
+    transcript_hash: [u8; 32],
+    interim_transcript_hash: [u8; 32],
+    init_secret: [u8; 32],
+    /// Application message chains of the current epoch, one per member
+    senders: Vec<SenderChain>,
+    past_epochs: Vec<PastEpoch>,
+    /// Proposals received this epoch, for our next commit
+    #[zeroize(skip)]
+    pending_proposals: Vec<Proposal>,
+    /// Leaf keys of our own update proposals that haven't been committed
+    pending_leaf_secrets: Vec<[u8; 32]>,
+}
+
+impl std::fmt::Debug for MlsGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MlsGroup")
+            .field("group_id", &self.group_id)
+            .field("epoch", &self.epoch)
+            .field("own_leaf", &self.own_leaf)
+            .field("members", &self.tree.members().count())
+            .field("pending_proposals", &self.pending_proposals.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl MlsGroup {
+    /// Create a group with ourselves as its only member
+    pub fn create(group_id: &str, signing: &SigningKeyPair, did: &str) -> Result<Self> {
+        let leaf_keys = EncryptionKeyPair::generate();
+        let tree = RatchetTree::new(LeafNode::new(signing, did, leaf_keys.public_bytes()));
+
+        let mut group = Self {
+            group_id: group_id.to_string(),
+            epoch: 0,
+            tree,
+            own_leaf: 0,
+            leaf_secret: leaf_keys.secret_bytes(),
+            node_secrets: Vec::new(),
+            transcript_hash: [0u8; 32],
+            interim_transcript_hash: [0u8; 32],
+            init_secret: [0u8; 32],
+            senders: Vec::new(),
+            past_epochs: Vec::new(),
+            pending_proposals: Vec::new(),
+            pending_leaf_secrets: Vec::new(),
+        };
+
+        let mut joiner = random_secret();
+        let secrets = EpochSecrets::derive(&joiner, &group.context()?)?;
+        joiner.zeroize();
+        let tag = schedule::confirmation_tag(&secrets.confirmation_key, &group.transcript_hash);
+        group.start_epoch(&secrets, &tag)?;
+        Ok(group)
+    }
+
+    /// Join a group from a Welcome addressed to one of our key packages
+    pub fn join(
+        welcome: &Welcome,
+        key_package: &KeyPackage,
+        secrets: &KeyPackageSecrets,
+    ) -> Result<Self> {
+        let reference = key_package.reference()?;
+        let entry = welcome
+            .secrets
+            .iter()
+            .find(|s| s.key_package_ref == reference)
+            .ok_or_else(|| {
+                Error::DecryptionFailed("Welcome is not addressed to this key package".into())
+            })?;
+
+        let init = EncryptionKeyPair::from_bytes(&secrets.init_secret);
+        let group_secrets: GroupSecrets =
+            decode(&schedule::open(&init, &reference, &entry.encrypted)?)?;
+
+        let info_key = schedule::welcome_key(&group_secrets.joiner_secret)?;
+        let info: GroupInfo = decode(&decrypt(
+            &info_key,
+            &Nonce(welcome.nonce),
+            &welcome.encrypted_group_info,
+            &[],
+        )?)?;
+
+        // The committer vouches for the tree, and every leaf for itself
+        let signer = info
+            .tree
+            .leaf(info.signer)
+            .ok_or_else(|| Error::ProtocolError("Welcome signer is not a group member".into()))?;
+        let signature = Signature::from_hex(&info.signature)?;
+        verify(
+            &signer.signature_key,
+            &to_be_signed("group_info", &[&info.content_bytes()?]),
+            &signature,
+        )?;
+        for (_, leaf) in info.tree.members() {
+            leaf.verify()?;
+        }
+
+        let own_leaf = info
+            .tree
+            .members()
+            .find(|(_, leaf)| **leaf == key_package.leaf)
+            .map(|(i, _)| i)
+            .ok_or_else(|| Error::ProtocolError("Welcome tree doesn't contain us".into()))?;
+
+        let mut group = Self {
+            group_id: info.group_id.clone(),
+            epoch: info.epoch,
+            tree: info.tree.clone(),
+            own_leaf,
+            leaf_secret: secrets.leaf_secret,
+            node_secrets: Vec::new(),
+            transcript_hash: info.transcript_hash,
+            interim_transcript_hash: [0u8; 32],
+            init_secret: [0u8; 32],
+            senders: Vec::new(),
+            past_epochs: Vec::new(),
+            pending_proposals: Vec::new(),
+            pending_leaf_secrets: Vec::new(),
+        };
+
+        let epoch_secrets = EpochSecrets::derive(&group_secrets.joiner_secret, &group.context()?)?;
+        schedule::verify_confirmation_tag(
+            &epoch_secrets.confirmation_key,
+            &info.transcript_hash,
+            &info.confirmation_tag,
+        )?;
+
+        // Keys from the lowest node we share with the committer up to the root
+        if let Some(path_secret) = group_secrets.path_secret {
+            let leaves = group.tree.leaf_count();
+            let signer_path = direct_path(leaf_node(info.signer), leaves);
+            let own_path = direct_path(leaf_node(own_leaf), leaves);
+            let start = own_path
+                .iter()
+                .position(|x| signer_path.contains(x))
+                .ok_or_else(|| Error::ProtocolError("No common ancestor with signer".into()))?;
+
+            let mut secret = path_secret;
+            for &node in &own_path[start..] {
+                let keys = schedule::node_keypair(&secret)?;
+                if group.tree.public_key(node) != Some(keys.public_bytes()) {
+                    return Err(Error::ProtocolError(
+                        "Welcome path secret doesn't match the tree".into(),
+                    ));
+                }
+                group.node_secrets.push(NodeSecret {
+                    node,
+                    secret: keys.secret_bytes(),
+                });
+                secret = schedule::next_path_secret(&secret)?;
+            }
+            secret.zeroize();
+        }
+
+        group.start_epoch(&epoch_secrets, &info.confirmation_tag)?;
+        Ok(group)
+    }
+
+    // ========================================================================
+    // ACCESSORS
+    // ========================================================================
+
+    /// The group's ID
+    pub fn group_id(&self) -> &str {
+        &self.group_id
+    }
+
+    /// The current epoch, incremented by every commit
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Our own DID
+    pub fn own_did(&self) -> &str {
+        self.tree
+            .leaf(self.own_leaf)
+            .map(|leaf| leaf.did.as_str())
+            .unwrap_or_default()
+    }
+
+    /// DIDs of the current members
+    pub fn members(&self) -> Vec<String> {
+        self.tree
+            .members()
+            .map(|(_, leaf)| leaf.did.clone())
+            .collect()
+    }
+
+    /// Whether `did` is a current member
+    pub fn contains(&self, did: &str) -> bool {
+        self.tree.find(did).is_some()
+    }
+
+    /// Serialize for persistence
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        encode(self)
+    }
+
+    /// Restore a persisted group
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        decode(bytes)
+    }
+
+    fn context(&self) -> Result<Vec<u8>> {
+        context_bytes(
+            &self.group_id,
+            self.epoch,
+            &self.tree.tree_hash()?,
+            &self.transcript_hash,
+        )
+    }
+
+    fn leaf_index(&self, did: &str) -> Result<u32> {
+        self.tree
+            .find(did)
+            .ok_or_else(|| Error::ProtocolError(format!("{} is not a group member", did)))
+    }
+
+    // ========================================================================
+    // PROPOSALS
+    // ========================================================================
+
+    /// Propose replacing our leaf keys, for the next committer to include
+    pub fn propose_update(&mut self, signing: &SigningKeyPair) -> Result<MlsProposal> {
+        let leaf_keys = EncryptionKeyPair::generate();
+        let leaf_node = LeafNode::new(signing, self.own_did(), leaf_keys.public_bytes());
+        let proposal = self.sign_proposal(
+            signing,
+            Proposal::Update {
+                leaf: self.own_leaf,
+                leaf_node,
+            },
+        )?;
+        self.pending_leaf_secrets.push(leaf_keys.secret_bytes());
+        Ok(proposal)
+    }
+
+    /// Propose removing a member (or ourselves, to leave)
+    pub fn propose_remove(&self, signing: &SigningKeyPair, did: &str) -> Result<MlsProposal> {
+        let leaf = self.leaf_index(did)?;
+        self.sign_proposal(signing, Proposal::Remove { leaf })
+    }
+
+    fn sign_proposal(&self, signing: &SigningKeyPair, proposal: Proposal) -> Result<MlsProposal> {
+        let mut message = MlsProposal {
+            group_id: self.group_id.clone(),
+            epoch: self.epoch,
+            sender: self.own_leaf,
+            proposal,
+            signature: String::new(),
+        };
+        let data = self.proposal_data(&message)?;
+        message.signature = sign(signing, &data).to_hex();
+        Ok(message)
+    }
+
+    fn proposal_data(&self, message: &MlsProposal) -> Result<Vec<u8>> {
+        let content = encode(&(
+            &message.group_id,
+            message.epoch,
+            message.sender,
+            &message.proposal,
+        ))?;
+        Ok(to_be_signed("proposal", &[&self.context()?, &content]))
+    }
+
+    /// Verify a member's proposal and keep it for our next commit
+    pub fn receive_proposal(&mut self, message: &MlsProposal) -> Result<()> {
+        self.check_epoch(&message.group_id, message.epoch)?;
+        let sender = self
+            .tree
+            .leaf(message.sender)
+            .ok_or_else(|| Error::ProtocolError("Proposal sender is not a group member".into()))?;
+        let signature = Signature::from_hex(&message.signature)?;
+        verify(
+            &sender.signature_key,
+            &self.proposal_data(message)?,
+            &signature,
+        )?;
+
+        // Members may only update their own leaf
+        if let Proposal::Update { leaf, .. } = &message.proposal {
+            if *leaf != message.sender {
+                return Err(Error::ProtocolError(
+                    "Update proposal for another member's leaf".into(),
+                ));
+            }
+        }
+
+        self.pending_proposals.push(message.proposal.clone());
+        Ok(())
+    }
+
+    fn check_epoch(&self, group_id: &str, epoch: u64) -> Result<()> {
+        if group_id != self.group_id {
+            return Err(Error::ProtocolError(format!(
+                "Message for group {} processed by {}",
+                group_id, self.group_id
+            )));
+        }
+        if epoch != self.epoch {
+            return Err(Error::ProtocolError(format!(
+                "Message for epoch {} but the group is at epoch {}",
+                epoch, self.epoch
+            )));
+        }
+        Ok(())
+    }
+
+    /// Apply proposals to a tree: updates, then removes, then adds
+    fn apply_proposals(
+        tree: &mut RatchetTree,
+        committer: u32,
+        proposals: &[Proposal],
+    ) -> Result<AppliedProposals> {
+        let mut applied = AppliedProposals {
+            added: Vec::new(),
+            removed: Vec::new(),
+        };
+
+        for proposal in proposals {
+            if let Proposal::Update { leaf, leaf_node } = proposal {
+                let current = tree.leaf(*leaf).ok_or_else(|| {
+                    Error::ProtocolError(format!("Update for blank leaf {}", leaf))
+                })?;
+                if *leaf == committer || leaf_node.did != current.did {
+                    return Err(Error::ProtocolError("Invalid update proposal".into()));
+                }
+                leaf_node.verify()?;
+                tree.update_leaf(*leaf, leaf_node.clone());
+            }
+        }
+
+        for proposal in proposals {
+            if let Proposal::Remove { leaf } = proposal {
+                if *leaf == committer {
+                    return Err(Error::ProtocolError(
+                        "A committer can't remove itself".into(),
+                    ));
+                }
+                let did = tree.leaf(*leaf).map(|l| l.did.clone()).ok_or_else(|| {
+                    Error::ProtocolError(format!("Remove of blank leaf {}", leaf))
+                })?;
+                tree.remove_leaf(*leaf);
+                applied.removed.push((*leaf, did));
+            }
+        }
+
+        for proposal in proposals {
+            if let Proposal::Add(key_package) = proposal {
+                key_package.verify()?;
+                if tree.find(&key_package.leaf.did).is_some() {
+                    return Err(Error::ProtocolError(format!(
+                        "{} is already a group member",
+                        key_package.leaf.did
+                    )));
+                }
+                let leaf = tree.add_leaf(key_package.leaf.clone());
+                applied.added.push((leaf, key_package.clone()));
+            }
+        }
+
+        Ok(applied)
+    }
+
+    // ========================================================================
+    // COMMITS
+    // ========================================================================
+
+    /// Add members from their key packages
+    pub fn add_members(
+        &mut self,
+        signing: &SigningKeyPair,
+        key_packages: Vec<KeyPackage>,
+    ) -> Result<CommitOutput> {
+        self.commit(
+            signing,
+            key_packages.into_iter().map(Proposal::Add).collect(),
+        )
+    }
+
+    /// Remove members by DID
+    pub fn remove_members(
+        &mut self,
+        signing: &SigningKeyPair,
+        dids: &[&str],
+    ) -> Result<CommitOutput> {
+        let proposals = dids
+            .iter()
+            .map(|did| {
+                Ok(Proposal::Remove {
+                    leaf: self.leaf_index(did)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.commit(signing, proposals)
+    }
+
+    /// Commit `proposals` and any received ones, re-keying our whole path
+    ///
+    /// A commit with no proposals just refreshes our keys. The group moves
+    /// to the next epoch right away.
+    pub fn commit(
+        &mut self,
+        signing: &SigningKeyPair,
+        proposals: Vec<Proposal>,
+    ) -> Result<CommitOutput> {
+        let mut all_proposals = self.pending_proposals.clone();
+        all_proposals.extend(proposals);
+
+        let mut tree = self.tree.clone();
+        let applied = Self::apply_proposals(&mut tree, self.own_leaf, &all_proposals)?;
+        let new_epoch = self.epoch + 1;
+
+        // Fresh keys for our leaf and every node above it
+        let own_did = self.own_did().to_string();
+        let leaf_keys = EncryptionKeyPair::generate();
+        let leaf = LeafNode::new(signing, &own_did, leaf_keys.public_bytes());
+
+        let leaves = tree.leaf_count();
+        let own_node = leaf_node(self.own_leaf);
+        let path = direct_path(own_node, leaves);
+        let copath = copath(own_node, leaves);
+        let added_nodes = applied.added_nodes();
+
+        let mut path_secrets = Vec::with_capacity(path.len());
+        let mut node_secrets = Vec::with_capacity(path.len());
+        let mut nodes = Vec::with_capacity(path.len());
+        let mut secret = random_secret();
+        for (&node, &sibling) in path.iter().zip(&copath) {
+            let keys = schedule::node_keypair(&secret)?;
+            let info = path_info(&self.group_id, new_epoch, node);
+            let encrypted_path_secret = tree
+                .resolution(sibling)
+                .into_iter()
+                .filter(|x| !added_nodes.contains(x))
+                .map(|x| {
+                    let public_key = tree.public_key(x).ok_or_else(|| {
+                        Error::EncryptionFailed(format!("Blank node {} in resolution", x))
+                    })?;
+                    schedule::seal(&public_key, &info, &secret)
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            nodes.push(UpdatePathNode {
+                encryption_key: keys.public_bytes(),
+                encrypted_path_secret,
+            });
+            node_secrets.push(NodeSecret {
+                node,
+                secret: keys.secret_bytes(),
+            });
+            path_secrets.push(secret);
+            secret = schedule::next_path_secret(&secret)?;
+        }
+        let mut commit_secret = secret;
+
+        tree.set_leaf(self.own_leaf, leaf.clone());
+        for (&node, update) in path.iter().zip(&nodes) {
+            tree.set_parent(node, update.encryption_key);
+        }
+
+        let mut commit = Commit {
+            group_id: self.group_id.clone(),
+            epoch: self.epoch,
+            sender: self.own_leaf,
+            proposals: all_proposals,
+            path: UpdatePath { leaf, nodes },
+            signature: String::new(),
+            confirmation_tag: [0u8; 32],
+        };
+        let content = commit.content_bytes()?;
+        commit.signature = sign(
+            signing,
+            &to_be_signed("commit", &[&self.context()?, &content]),
+        )
+        .to_hex();
+
+        // Key schedule for the new epoch
+        let transcript_hash = schedule::hash(&[
+            &self.interim_transcript_hash,
+            &content,
+            commit.signature.as_bytes(),
+        ]);
+        let context = context_bytes(
+            &self.group_id,
+            new_epoch,
+            &tree.tree_hash()?,
+            &transcript_hash,
+        )?;
+        let mut joiner = schedule::joiner_secret(&self.init_secret, &commit_secret, &context)?;
+        commit_secret.zeroize();
+        let epoch_secrets = EpochSecrets::derive(&joiner, &context)?;
+        commit.confirmation_tag =
+            schedule::confirmation_tag(&epoch_secrets.confirmation_key, &transcript_hash);
+
+        let welcome = if applied.added.is_empty() {
+            None
+        } else {
+            Some(self.welcome(
+                signing,
+                &applied,
+                &tree,
+                new_epoch,
+                &transcript_hash,
+                &commit.confirmation_tag,
+                &joiner,
+                &copath,
+                &path_secrets,
+            )?)
+        };
+        joiner.zeroize();
+        for secret in path_secrets.iter_mut() {
+            secret.zeroize();
+        }
+
+        self.archive_epoch();
+        self.tree = tree;
+        self.epoch = new_epoch;
+        self.transcript_hash = transcript_hash;
+        self.leaf_secret = leaf_keys.secret_bytes();
+        self.node_secrets = node_secrets;
+        self.pending_proposals.clear();
+        self.pending_leaf_secrets.clear();
+        self.start_epoch(&epoch_secrets, &commit.confirmation_tag)?;
+
+        Ok(CommitOutput {
+            commit,
+            welcome,
+            added: applied.added_dids(),
+            removed: applied.removed_dids(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn welcome(
+        &self,
+        signing: &SigningKeyPair,
+        applied: &AppliedProposals,
+        tree: &RatchetTree,
+        epoch: u64,
+        transcript_hash: &[u8; 32],
+        confirmation_tag: &[u8; 32],
+        joiner_secret: &[u8; 32],
+        copath: &[u32],
+        path_secrets: &[[u8; 32]],
+    ) -> Result<Welcome> {
+        let mut info = GroupInfo {
+            group_id: self.group_id.clone(),
+            epoch,
+            tree: tree.clone(),
+            transcript_hash: *transcript_hash,
+            confirmation_tag: *confirmation_tag,
+            signer: self.own_leaf,
+            signature: String::new(),
+        };
+        info.signature = sign(
+            signing,
+            &to_be_signed("group_info", &[&info.content_bytes()?]),
+        )
+        .to_hex();
+
+        let (nonce, encrypted_group_info) =
+            encrypt(&schedule::welcome_key(joiner_secret)?, &encode(&info)?, &[])?;
+
+        let mut secrets = Vec::with_capacity(applied.added.len());
+        for (leaf, key_package) in &applied.added {
+            // The new member sits below exactly one copath node; the path
+            // secret of the node above it is the lowest one we share
+            let path_secret = copath
+                .iter()
+                .position(|&x| in_subtree(x, leaf_node(*leaf)))
+                .map(|i| path_secrets[i]);
+            let group_secrets = GroupSecrets {
+                joiner_secret: *joiner_secret,
+                path_secret,
+            };
+            let reference = key_package.reference()?;
+            secrets.push(EncryptedGroupSecrets {
+                key_package_ref: reference,
+                encrypted: schedule::seal(
+                    &key_package.init_key,
+                    &reference,
+                    &encode(&group_secrets)?,
+                )?,
+            });
+        }
+
+        Ok(Welcome {
+            secrets,
+            nonce: nonce.0,
+            encrypted_group_info,
+        })
+    }
+
+    /// Verify and apply a commit from another member
+    ///
+    /// The group is only modified if the whole commit checks out. If it
+    /// removed us, [`CommitOutcome::Removed`] is returned and the state
+    /// should be discarded.
+    pub fn process_commit(&mut self, commit: &Commit) -> Result<CommitOutcome> {
+        self.check_epoch(&commit.group_id, commit.epoch)?;
+        if commit.sender == self.own_leaf {
+            return Err(Error::ProtocolError("Commit claims to be from us".into()));
+        }
+        let committer = self
+            .tree
+            .leaf(commit.sender)
+            .ok_or_else(|| Error::ProtocolError("Committer is not a group member".into()))?
+            .clone();
+
+        let content = commit.content_bytes()?;
+        let signature = Signature::from_hex(&commit.signature)?;
+        verify(
+            &committer.signature_key,
+            &to_be_signed("commit", &[&self.context()?, &content]),
+            &signature,
+        )?;
+
+        let mut tree = self.tree.clone();
+        let applied = Self::apply_proposals(&mut tree, commit.sender, &commit.proposals)?;
+        if applied
+            .removed
+            .iter()
+            .any(|(leaf, _)| *leaf == self.own_leaf)
+        {
+            return Ok(CommitOutcome::Removed {
+                committer: committer.did,
+            });
+        }
+
+        commit.path.leaf.verify()?;
+        if commit.path.leaf.did != committer.did {
+            return Err(Error::ProtocolError(
+                "Commit path leaf belongs to someone else".into(),
+            ));
+        }
+
+        let leaves = tree.leaf_count();
+        let sender_node = leaf_node(commit.sender);
+        let path = direct_path(sender_node, leaves);
+        let copath = copath(sender_node, leaves);
+        if commit.path.nodes.len() != path.len() {
+            return Err(Error::ProtocolError(
+                "Commit path has the wrong length".into(),
+            ));
+        }
+
+        // Decrypt the path secret of the lowest node we share with the
+        // committer, using whichever key of ours is in its resolution
+        let own_node = leaf_node(self.own_leaf);
+        let level = copath
+            .iter()
+            .position(|&x| in_subtree(x, own_node))
+            .ok_or_else(|| Error::ProtocolError("We are not below the commit path".into()))?;
+        let added_nodes = applied.added_nodes();
+        let resolution: Vec<u32> = tree
+            .resolution(copath[level])
+            .into_iter()
+            .filter(|x| !added_nodes.contains(x))
+            .collect();
+        let ciphertexts = &commit.path.nodes[level].encrypted_path_secret;
+        if ciphertexts.len() != resolution.len() {
+            return Err(Error::ProtocolError(
+                "Commit path doesn't cover the whole group".into(),
+            ));
+        }
+        let (keys, ciphertext) = resolution
+            .iter()
+            .zip(ciphertexts)
+            .find_map(|(&x, ct)| self.private_key(&tree, x).map(|keys| (keys, ct)))
+            .ok_or_else(|| Error::DecryptionFailed("No key to decrypt the commit path".into()))?;
+        let info = path_info(&self.group_id, commit.epoch + 1, path[level]);
+        let mut secret = to_array(&schedule::open(&keys, &info, ciphertext)?)?;
+
+        let mut node_secrets = Vec::new();
+        for (&node, update) in path.iter().zip(&commit.path.nodes).skip(level) {
+            let keys = schedule::node_keypair(&secret)?;
+            if keys.public_bytes() != update.encryption_key {
+                return Err(Error::ProtocolError(
+                    "Commit path keys don't match its path secrets".into(),
+                ));
+            }
+            node_secrets.push(NodeSecret {
+                node,
+                secret: keys.secret_bytes(),
+            });
+            secret = schedule::next_path_secret(&secret)?;
+        }
+        let mut commit_secret = secret;
+
+        tree.set_leaf(commit.sender, commit.path.leaf.clone());
+        for (&node, update) in path.iter().zip(&commit.path.nodes) {
+            tree.set_parent(node, update.encryption_key);
+        }
+
+        let transcript_hash = schedule::hash(&[
+            &self.interim_transcript_hash,
+            &content,
+            commit.signature.as_bytes(),
+        ]);
+        let new_epoch = self.epoch + 1;
+        let context = context_bytes(
+            &self.group_id,
+            new_epoch,
+            &tree.tree_hash()?,
+            &transcript_hash,
+        )?;
+        let mut joiner = schedule::joiner_secret(&self.init_secret, &commit_secret, &context)?;
+        commit_secret.zeroize();
+        let epoch_secrets = EpochSecrets::derive(&joiner, &context);
+        joiner.zeroize();
+        let epoch_secrets = epoch_secrets?;
+        schedule::verify_confirmation_tag(
+            &epoch_secrets.confirmation_key,
+            &transcript_hash,
+            &commit.confirmation_tag,
+        )?;
+
+        // Everything verified: move to the new epoch
+        let own_leaf_key = tree.public_key(own_node);
+        if own_leaf_key != self.tree.public_key(own_node) {
+            // A committed update proposal of ours replaced our leaf
+            let secret = self
+                .pending_leaf_secrets
+                .iter()
+                .find(|s| Some(EncryptionKeyPair::from_bytes(s).public_bytes()) == own_leaf_key)
+                .copied()
+                .ok_or_else(|| {
+                    Error::ProtocolError("Commit replaced our leaf with unknown keys".into())
+                })?;
+            self.leaf_secret = secret;
+        }
+        self.node_secrets
+            .retain(|s| !node_secrets.iter().any(|n| n.node == s.node));
+        self.node_secrets.extend(node_secrets);
+        self.archive_epoch();
+        self.tree = tree;
+        self.epoch = new_epoch;
+        self.transcript_hash = transcript_hash;
+        self.prune_node_secrets();
+        self.pending_proposals.clear();
+        self.pending_leaf_secrets.clear();
+        self.start_epoch(&epoch_secrets, &commit.confirmation_tag)?;
+
+        Ok(CommitOutcome::Applied {
+            committer: committer.did,
+            added: applied.added_dids(),
+            removed: applied.removed_dids(),
+        })
+    }
+
+    /// Our private key for node `x` of `tree`, if we hold it
+    fn private_key(&self, tree: &RatchetTree, x: u32) -> Option<EncryptionKeyPair> {
+        let public_key = tree.public_key(x)?;
+        let candidates: Vec<&[u8; 32]> = if x == leaf_node(self.own_leaf) {
+            std::iter::once(&self.leaf_secret)
+                .chain(&self.pending_leaf_secrets)
+                .collect()
+        } else {
+            self.node_secrets
+                .iter()
+                .filter(|s| s.node == x)
+                .map(|s| &s.secret)
+                .collect()
+        };
+        candidates
+            .into_iter()
+            .map(EncryptionKeyPair::from_bytes)
+            .find(|keys| keys.public_bytes() == public_key)
+    }
+
+    /// Forget node keys that are no longer on our path or were replaced
+    fn prune_node_secrets(&mut self) {
+        let path = direct_path(leaf_node(self.own_leaf), self.tree.leaf_count());
+        let tree = &self.tree;
+        self.node_secrets.retain(|s| {
+            path.contains(&s.node)
+                && tree.public_key(s.node)
+                    == Some(EncryptionKeyPair::from_bytes(&s.secret).public_bytes())
+        });
+    }
+
+    /// Keep the current epoch's chains for late messages before leaving it
+    fn archive_epoch(&mut self) {
+        self.past_epochs.push(PastEpoch {
+            epoch: self.epoch,
+            tree: self.tree.clone(),
+            senders: std::mem::take(&mut self.senders),
+        });
+        if self.past_epochs.len() > MAX_PAST_EPOCHS {
+            self.past_epochs.remove(0);
+        }
+    }
+
+    /// Set up the application chains of the epoch we just entered
+    fn start_epoch(&mut self, secrets: &EpochSecrets, confirmation_tag: &[u8; 32]) -> Result<()> {
+        self.senders = self
+            .tree
+            .members()
+            .map(|(leaf, _)| SenderChain::new(&secrets.encryption, leaf))
+            .collect::<Result<Vec<_>>>()?;
+        self.init_secret = secrets.init;
+        self.interim_transcript_hash = schedule::hash(&[&self.transcript_hash, confirmation_tag]);
+        Ok(())
+    }
+
+    // ========================================================================
+    // APPLICATION MESSAGES
+    // ========================================================================
+
+    /// Encrypt and sign an application message with the next key of our chain
+    pub fn encrypt(
+        &mut self,
+        signing: &SigningKeyPair,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<MlsCiphertext> {
+        let own_leaf = self.own_leaf;
+        let chain = self
+            .senders
+            .iter_mut()
+            .find(|c| c.leaf == own_leaf)
+            .ok_or_else(|| Error::EncryptionFailed("No sending chain for our leaf".into()))?;
+        let (generation, mut key) = chain.next()?;
+
+        let mut message = MlsCiphertext {
+            group_id: self.group_id.clone(),
+            epoch: self.epoch,
+            sender: own_leaf,
+            generation,
+            nonce: [0u8; 12],
+            ciphertext: Vec::new(),
+        };
+        let signature = sign(
+            signing,
+            &to_be_signed("application", &[&application_aad(&message, aad), plaintext]),
+        );
+        let content = encode(&ApplicationContent {
+            content: plaintext.to_vec(),
+            signature: signature.to_hex(),
+        })?;
+
+        let result = encrypt(
+            &EncryptionKey::from_bytes(key),
+            &content,
+            &application_aad(&message, aad),
+        );
+        key.zeroize();
+        let (nonce, ciphertext) = result?;
+        message.nonce = nonce.0;
+        message.ciphertext = ciphertext;
+        Ok(message)
+    }
+
+    /// Decrypt an application message from the current or a recent epoch
+    ///
+    /// Returns the sender's DID and the plaintext. The message key is
+    /// deleted once used, so each message can only be decrypted once.
+    pub fn decrypt(&mut self, message: &MlsCiphertext, aad: &[u8]) -> Result<(String, Vec<u8>)> {
+        if message.group_id != self.group_id {
+            return Err(Error::DecryptionFailed(
+                "Message is for another group".into(),
+            ));
+        }
+
+        let (tree, senders) = if message.epoch == self.epoch {
+            (&self.tree, &mut self.senders)
+        } else {
+            let past = self
+                .past_epochs
+                .iter_mut()
+                .find(|p| p.epoch == message.epoch)
+                .ok_or_else(|| {
+                    Error::DecryptionFailed(format!(
+                        "No keys for epoch {} (group is at {})",
+                        message.epoch, self.epoch
+                    ))
+                })?;
+            (&past.tree, &mut past.senders)
+        };
+
+        let sender = tree
+            .leaf(message.sender)
+            .ok_or_else(|| Error::DecryptionFailed("Sender is not a group member".into()))?;
+        let chain = senders
+            .iter_mut()
+            .find(|c| c.leaf == message.sender)
+            .ok_or_else(|| Error::DecryptionFailed("No chain for sender".into()))?;
+
+        // Work on a copy so a forged message can't burn a real key
+        let mut next = chain.clone();
+        let mut key = next.key_for(message.generation)?;
+        let full_aad = application_aad(message, aad);
+        let result = decrypt(
+            &EncryptionKey::from_bytes(key),
+            &Nonce(message.nonce),
+            &message.ciphertext,
+            &full_aad,
+        );
+        key.zeroize();
+        let content: ApplicationContent = decode(&result?)?;
+
+        let signature = Signature::from_hex(&content.signature)?;
+        verify(
+            &sender.signature_key,
+            &to_be_signed("application", &[&full_aad, &content.content]),
+            &signature,
+        )?;
+
+        *chain = next;
+        Ok((sender.did.clone(), content.content))
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::identity::Did;
+
+    struct Member {
+        keys: KeyPair,
+        did: String,
+    }
+
+    impl Member {
+        fn new() -> Self {
+            let keys = KeyPair::generate();
+            let did = Did::from_public_key(&keys.signing.public_bytes())
+                .as_str()
+                .to_string();
+            Self { keys, did }
+        }
+
+        fn key_package(&self) -> (KeyPackage, KeyPackageSecrets) {
+            KeyPackage::generate(&self.keys.signing, &self.did)
+        }
+    }
+
+    /// A group of `n` members, each holding their own state
+    fn group(n: usize) -> (Vec<Member>, Vec<MlsGroup>) {
+        let members: Vec<Member> = (0..n).map(|_| Member::new()).collect();
+        let mut states =
+            vec![MlsGroup::create("group-1", &members[0].keys.signing, &members[0].did).unwrap()];
+
+        for member in &members[1..] {
+            let (kp, secrets) = member.key_package();
+            let output = states[0]
+                .add_members(&members[0].keys.signing, vec![kp.clone()])
+                .unwrap();
+            for state in states.iter_mut().skip(1) {
+                state.process_commit(&output.commit).unwrap();
+            }
+            states.push(MlsGroup::join(output.welcome.as_ref().unwrap(), &kp, &secrets).unwrap());
+        }
+        (members, states)
+    }
+
+    fn assert_all_can_read(members: &[Member], states: &mut [MlsGroup]) {
+        for i in 0..states.len() {
+            let text = format!("hello from {}", i);
+            let message = states[i]
+                .encrypt(&members[i].keys.signing, text.as_bytes(), b"aad")
+                .unwrap();
+            for (j, state) in states.iter_mut().enumerate() {
+                if i != j {
+                    let (sender, plaintext) = state.decrypt(&message, b"aad").unwrap();
+                    assert_eq!(sender, members[i].did);
+                    assert_eq!(plaintext, text.as_bytes());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_create_add_and_message() {
+        let (members, mut states) = group(5);
+        for state in &states {
+            assert_eq!(state.epoch(), 4);
+            assert_eq!(state.members().len(), 5);
+        }
+        assert_all_can_read(&members, &mut states);
+    }
+
+    #[test]
+    fn test_commit_size_is_logarithmic() {
+        let (members, mut states) = group(16);
+
+        // Members added one by one leave the parents off the admin's path
+        // blank; once everyone has committed, the tree is full
+        for i in 1..states.len() {
+            let output = states[i]
+                .commit(&members[i].keys.signing, Vec::new())
+                .unwrap();
+            for (j, state) in states.iter_mut().enumerate() {
+                if j != i {
+                    state.process_commit(&output.commit).unwrap();
+                }
+            }
+        }
+        let output = states[0]
+            .commit(&members[0].keys.signing, Vec::new())
+            .unwrap();
+
+        // 16 leaves: 4 levels, one ciphertext each
+        assert_eq!(output.commit.path.nodes.len(), 4);
+        let ciphertexts: usize = output
+            .commit
+            .path
+            .nodes
+            .iter()
+            .map(|n| n.encrypted_path_secret.len())
+            .sum();
+        assert_eq!(ciphertexts, 4);
+    }
+
+    #[test]
+    fn test_removed_member_is_locked_out() {
+        let (members, mut states) = group(4);
+        let output = states[0]
+            .remove_members(&members[0].keys.signing, &[&members[2].did])
+            .unwrap();
+        assert_eq!(output.removed, vec![members[2].did.clone()]);
+
+        let outcome = states[2].process_commit(&output.commit).unwrap();
+        assert!(matches!(outcome, CommitOutcome::Removed { .. }));
+        for i in [1, 3] {
+            assert!(matches!(
+                states[i].process_commit(&output.commit).unwrap(),
+                CommitOutcome::Applied { .. }
+            ));
+        }
+
+        let message = states[0]
+            .encrypt(&members[0].keys.signing, b"secret", b"")
+            .unwrap();
+        assert!(states[2].decrypt(&message, b"").is_err());
+        assert_eq!(states[3].decrypt(&message, b"").unwrap().1, b"secret");
+
+        let mut members = members;
+        members.remove(2);
+        states.remove(2);
+        assert_all_can_read(&members, &mut states);
+    }
+
+    #[test]
+    fn test_update_proposal_heals_a_member() {
+        let (members, mut states) = group(3);
+        let before = states[2].leaf_secret;
+
+        let proposal = states[2].propose_update(&members[2].keys.signing).unwrap();
+        states[0].receive_proposal(&proposal).unwrap();
+        let output = states[0]
+            .commit(&members[0].keys.signing, Vec::new())
+            .unwrap();
+        states[1].process_commit(&output.commit).unwrap();
+        states[2].process_commit(&output.commit).unwrap();
+
+        assert_ne!(states[2].leaf_secret, before);
+        assert_all_can_read(&members, &mut states);
+    }
+
+    #[test]
+    fn test_tampered_commit_is_rejected_without_state_change() {
+        let (members, mut states) = group(3);
+        let output = states[0]
+            .commit(&members[0].keys.signing, Vec::new())
+            .unwrap();
+
+        let mut forged = output.commit.clone();
+        forged.confirmation_tag[0] ^= 1;
+        assert!(states[1].process_commit(&forged).is_err());
+
+        let mut forged = output.commit.clone();
+        forged.proposals.push(Proposal::Remove { leaf: 2 });
+        assert!(states[1].process_commit(&forged).is_err());
+
+        // The real commit still applies, and replays are rejected
+        assert_eq!(states[1].epoch(), 2);
+        states[1].process_commit(&output.commit).unwrap();
+        assert_eq!(states[1].epoch(), 3);
+        assert!(states[1].process_commit(&output.commit).is_err());
+    }
+
+    #[test]
+    fn test_messages_from_previous_epoch_still_decrypt() {
+        let (members, mut states) = group(2);
+        let late = states[1]
+            .encrypt(&members[1].keys.signing, b"sent before the commit", b"")
+            .unwrap();
+
+        let output = states[0]
+            .commit(&members[0].keys.signing, Vec::new())
+            .unwrap();
+        states[1].process_commit(&output.commit).unwrap();
+
+        assert_eq!(
+            states[0].decrypt(&late, b"").unwrap().1,
+            b"sent before the commit"
+        );
+        // But only once
+        assert!(states[0].decrypt(&late, b"").is_err());
+    }
+
+    #[test]
+    fn test_forged_application_message_is_rejected() {
+        let (members, mut states) = group(3);
+        let message = states[0]
+            .encrypt(&members[0].keys.signing, b"hi", b"aad")
+            .unwrap();
+
+        // Wrong AAD, or a member claiming to be someone else
+        assert!(states[1].decrypt(&message, b"other").is_err());
+        let mut forged = message.clone();
+        forged.sender = 2;
+        assert!(states[1].decrypt(&forged, b"aad").is_err());
+
+        // Failed attempts don't use up the key
+        assert_eq!(states[1].decrypt(&message, b"aad").unwrap().1, b"hi");
+    }
+
+    #[test]
+    fn test_welcome_for_other_key_package_is_rejected() {
+        let (members, mut states) = group(1);
+        let newcomer = Member::new();
+        let (kp, _) = newcomer.key_package();
+        let output = states[0]
+            .add_members(&members[0].keys.signing, vec![kp])
+            .unwrap();
+
+        let (other_kp, other_secrets) = newcomer.key_package();
+        assert!(
+            MlsGroup::join(output.welcome.as_ref().unwrap(), &other_kp, &other_secrets).is_err()
+        );
+    }
+
+    #[test]
+    fn test_add_existing_member_is_rejected() {
+        let (members, mut states) = group(2);
+        let (kp, _) = members[1].key_package();
+        assert!(states[0]
+            .add_members(&members[0].keys.signing, vec![kp])
+            .is_err());
+        assert_eq!(states[0].epoch(), 1);
+    }
+
+    #[test]
+    fn test_state_and_message_serialization() {
+        let (members, mut states) = group(3);
+        let output = states[0]
+            .commit(&members[0].keys.signing, Vec::new())
+            .unwrap();
+
+        let commit = Commit::from_bytes(&output.commit.to_bytes().unwrap()).unwrap();
+        let mut restored = MlsGroup::from_bytes(&states[1].to_bytes().unwrap()).unwrap();
+        restored.process_commit(&commit).unwrap();
+        assert_eq!(restored.epoch(), states[0].epoch());
+
+        let message = states[0]
+            .encrypt(&members[0].keys.signing, b"after restore", b"")
+            .unwrap();
+        let message = MlsCiphertext::from_bytes(&message.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored.decrypt(&message, b"").unwrap().1, b"after restore");
+
+        assert!(MlsGroup::from_bytes(b"garbage").is_err());
+    }
+}
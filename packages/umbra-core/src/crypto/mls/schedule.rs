@@ -0,0 +1,358 @@
+//! Key derivation for MLS groups
+//!
+//! ```text
+//!   init_secret[n-1]     commit_secret (root of the committer's path)
+//!          │                  │
+//!          └──► HKDF-Extract ◄┘
+//!                    │
+//!                    ▼ Expand("joiner", context[n])
+//!              joiner_secret ──────────────► sent to new members (Welcome)
+//!                    │
+//!                    ▼ Expand("epoch", context[n])
+//!              epoch_secret
+//!                    │
+//!        ┌───────────┼──────────────┐
+//!        ▼           ▼              ▼
+//!   encryption   confirmation     init_secret[n]
+//!   (per-sender  (HMAC over the   (next epoch)
+//!    chains)      transcript)
+//! ```
+//!
+//! The Welcome's GroupInfo is encrypted under a key expanded from the
+//! joiner secret, which is all a new member receives.
+//!
+//! `context[n]` binds the group ID, epoch, tree hash and transcript hash, so
+//! members only agree on an epoch's secrets if they agree on everything
+//! that led to it.
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::crypto::encryption::{decrypt, encrypt, EncryptionKey, Nonce, NONCE_SIZE};
+use crate::crypto::kdf::domain;
+use crate::crypto::keys::{hex_bytes, EncryptionKeyPair};
+use crate::error::{Error, Result};
+
+/// Maximum number of message keys a receiver will skip ahead in one sender chain
+pub const MAX_GENERATION_GAP: u32 = 1000;
+
+/// Maximum number of skipped message keys retained per sender
+const MAX_SKIPPED_KEYS: usize = 1000;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// ============================================================================
+// KDF HELPERS
+// ============================================================================
+
+/// HKDF-Expand `secret` under a label and context
+pub(crate) fn expand(secret: &[u8; 32], label: &str, context: &[u8]) -> Result<[u8; 32]> {
+    let hkdf = Hkdf::<Sha256>::from_prk(secret)
+        .map_err(|_| Error::KeyDerivationFailed("Invalid MLS secret".into()))?;
+    let mut info = Vec::with_capacity(domain::MLS.len() + label.len() + 1 + context.len());
+    info.extend_from_slice(domain::MLS);
+    info.extend_from_slice(label.as_bytes());
+    info.push(0);
+    info.extend_from_slice(context);
+
+    let mut out = [0u8; 32];
+    hkdf.expand(&info, &mut out)
+        .map_err(|_| Error::KeyDerivationFailed(format!("Failed to derive MLS {}", label)))?;
+    Ok(out)
+}
+
+/// HKDF-Extract
+pub(crate) fn extract(salt: &[u8; 32], ikm: &[u8; 32]) -> [u8; 32] {
+    let (prk, _) = Hkdf::<Sha256>::extract(Some(salt), ikm);
+    prk.into()
+}
+
+/// The next path secret up the tree
+pub(crate) fn next_path_secret(path_secret: &[u8; 32]) -> Result<[u8; 32]> {
+    expand(path_secret, "path", &[])
+}
+
+/// The node key pair a path secret stands for
+pub(crate) fn node_keypair(path_secret: &[u8; 32]) -> Result<EncryptionKeyPair> {
+    Ok(EncryptionKeyPair::from_bytes(&expand(
+        path_secret,
+        "node",
+        &[],
+    )?))
+}
+
+/// SHA-256 over the concatenation of `parts`
+pub(crate) fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// HMAC-SHA256 tag proving knowledge of the epoch's confirmation key
+pub(crate) fn confirmation_tag(confirmation_key: &[u8; 32], transcript: &[u8; 32]) -> [u8; 32] {
+    let mut mac =
+        <HmacSha256 as Mac>::new_from_slice(confirmation_key).expect("HMAC accepts any key length");
+    mac.update(transcript);
+    mac.finalize().into_bytes().into()
+}
+
+/// Check a confirmation tag in constant time
+pub(crate) fn verify_confirmation_tag(
+    confirmation_key: &[u8; 32],
+    transcript: &[u8; 32],
+    tag: &[u8; 32],
+) -> Result<()> {
+    let mut mac =
+        <HmacSha256 as Mac>::new_from_slice(confirmation_key).expect("HMAC accepts any key length");
+    mac.update(transcript);
+    mac.verify_slice(tag)
+        .map_err(|_| Error::DecryptionFailed("MLS confirmation tag mismatch".into()))
+}
+
+// ============================================================================
+// PUBLIC-KEY ENCRYPTION
+// ============================================================================
+
+/// A message sealed to an X25519 public key (HPKE base mode, simplified)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HpkeCiphertext {
+    /// Sender's ephemeral X25519 public key
+    #[serde(with = "hex_bytes")]
+    pub kem_output: [u8; 32],
+    /// AES-GCM nonce
+    pub nonce: [u8; NONCE_SIZE],
+    /// AES-GCM ciphertext and tag
+    pub ciphertext: Vec<u8>,
+}
+
+/// Seal `plaintext` so only the holder of `public_key`'s private key can open it
+pub(crate) fn seal(public_key: &[u8; 32], info: &[u8], plaintext: &[u8]) -> Result<HpkeCiphertext> {
+    let ephemeral = EncryptionKeyPair::generate();
+    let kem_output = ephemeral.public_bytes();
+    let key = hpke_key(
+        &ephemeral.diffie_hellman(public_key),
+        &kem_output,
+        public_key,
+        info,
+    )?;
+    let (nonce, ciphertext) = encrypt(&key, plaintext, info)?;
+    Ok(HpkeCiphertext {
+        kem_output,
+        nonce: nonce.0,
+        ciphertext,
+    })
+}
+
+/// Open an [`HpkeCiphertext`] with our private key
+pub(crate) fn open(
+    keypair: &EncryptionKeyPair,
+    info: &[u8],
+    sealed: &HpkeCiphertext,
+) -> Result<Vec<u8>> {
+    let key = hpke_key(
+        &keypair.diffie_hellman(&sealed.kem_output),
+        &sealed.kem_output,
+        &keypair.public_bytes(),
+        info,
+    )?;
+    decrypt(&key, &Nonce(sealed.nonce), &sealed.ciphertext, info)
+}
+
+fn hpke_key(
+    dh_output: &[u8; 32],
+    kem_output: &[u8; 32],
+    recipient: &[u8; 32],
+    info: &[u8],
+) -> Result<EncryptionKey> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(kem_output);
+    salt[32..].copy_from_slice(recipient);
+    let hkdf = Hkdf::<Sha256>::new(Some(&salt), dh_output);
+
+    let mut label = Vec::with_capacity(domain::MLS_HPKE.len() + info.len());
+    label.extend_from_slice(domain::MLS_HPKE);
+    label.extend_from_slice(info);
+
+    let mut key = [0u8; 32];
+    hkdf.expand(&label, &mut key)
+        .map_err(|_| Error::KeyDerivationFailed("Failed to derive HPKE key".into()))?;
+    Ok(EncryptionKey::from_bytes(key))
+}
+
+// ============================================================================
+// EPOCH SECRETS
+// ============================================================================
+
+/// Secrets of one epoch, derived from its joiner secret
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct EpochSecrets {
+    pub encryption: [u8; 32],
+    pub confirmation_key: [u8; 32],
+    pub init: [u8; 32],
+}
+
+/// `joiner_secret` for the epoch that `commit_secret` starts
+pub(crate) fn joiner_secret(
+    init_secret: &[u8; 32],
+    commit_secret: &[u8; 32],
+    context: &[u8],
+) -> Result<[u8; 32]> {
+    let mut prk = extract(init_secret, commit_secret);
+    let joiner = expand(&prk, "joiner", context);
+    prk.zeroize();
+    joiner
+}
+
+impl EpochSecrets {
+    pub(crate) fn derive(joiner_secret: &[u8; 32], context: &[u8]) -> Result<Self> {
+        let mut epoch = expand(joiner_secret, "epoch", context)?;
+        let secrets = Self {
+            encryption: expand(&epoch, "encryption", &[])?,
+            confirmation_key: expand(&epoch, "confirm", &[])?,
+            init: expand(&epoch, "init", &[])?,
+        };
+        epoch.zeroize();
+        Ok(secrets)
+    }
+}
+
+/// Key for the GroupInfo in a Welcome, derived from the joiner secret alone
+pub(crate) fn welcome_key(joiner_secret: &[u8; 32]) -> Result<EncryptionKey> {
+    Ok(EncryptionKey::from_bytes(expand(
+        joiner_secret,
+        "welcome",
+        &[],
+    )?))
+}
+
+// ============================================================================
+// SENDER CHAINS
+// ============================================================================
+
+/// A message key cached for a message that hasn't arrived yet
+#[derive(Clone, Serialize, Deserialize, Zeroize)]
+struct SkippedKey {
+    generation: u32,
+    key: [u8; 32],
+}
+
+/// Symmetric ratchet for one member's application messages in one epoch
+///
+/// Each message key is used once and forgotten, so a later compromise
+/// doesn't expose earlier messages of the epoch.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct SenderChain {
+    pub leaf: u32,
+    generation: u32,
+    chain: [u8; 32],
+    skipped: Vec<SkippedKey>,
+}
+
+impl SenderChain {
+    /// Start the chain of `leaf` from the epoch's encryption secret
+    pub(crate) fn new(encryption_secret: &[u8; 32], leaf: u32) -> Result<Self> {
+        Ok(Self {
+            leaf,
+            generation: 0,
+            chain: expand(encryption_secret, "sender", &leaf.to_be_bytes())?,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Next key for sending: (generation, key)
+    pub(crate) fn next(&mut self) -> Result<(u32, [u8; 32])> {
+        let generation = self.generation;
+        let key = self.step()?;
+        Ok((generation, key))
+    }
+
+    /// The key for a received message's generation
+    ///
+    /// Keys for skipped generations are cached (up to
+    /// [`MAX_GENERATION_GAP`] ahead); a key that was already used is gone.
+    pub(crate) fn key_for(&mut self, generation: u32) -> Result<[u8; 32]> {
+        if let Some(pos) = self.skipped.iter().position(|k| k.generation == generation) {
+            let mut skipped = self.skipped.remove(pos);
+            let key = skipped.key;
+            skipped.zeroize();
+            return Ok(key);
+        }
+        if generation < self.generation {
+            return Err(Error::DecryptionFailed(format!(
+                "MLS message key for generation {} was already used",
+                generation
+            )));
+        }
+        if generation - self.generation > MAX_GENERATION_GAP {
+            return Err(Error::DecryptionFailed(format!(
+                "Too many skipped messages ({} > {})",
+                generation - self.generation,
+                MAX_GENERATION_GAP
+            )));
+        }
+
+        while self.generation < generation {
+            let skipped_generation = self.generation;
+            let key = self.step()?;
+            self.skipped.push(SkippedKey {
+                generation: skipped_generation,
+                key,
+            });
+        }
+        if self.skipped.len() > MAX_SKIPPED_KEYS {
+            let excess = self.skipped.len() - MAX_SKIPPED_KEYS;
+            for mut key in self.skipped.drain(..excess) {
+                key.zeroize();
+            }
+        }
+        self.step()
+    }
+
+    fn step(&mut self) -> Result<[u8; 32]> {
+        let context = self.generation.to_be_bytes();
+        let key = expand(&self.chain, "key", &context)?;
+        self.chain = expand(&self.chain, "next", &context)?;
+        self.generation += 1;
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let recipient = EncryptionKeyPair::generate();
+        let sealed = seal(&recipient.public_bytes(), b"info", b"path secret").unwrap();
+        assert_eq!(open(&recipient, b"info", &sealed).unwrap(), b"path secret");
+
+        // Bound to the info string and the recipient
+        assert!(open(&recipient, b"other", &sealed).is_err());
+        assert!(open(&EncryptionKeyPair::generate(), b"info", &sealed).is_err());
+    }
+
+    #[test]
+    fn test_sender_chain_out_of_order() {
+        let mut sender = SenderChain::new(&[1u8; 32], 3).unwrap();
+        let mut receiver = sender.clone();
+
+        let keys: Vec<_> = (0..4).map(|_| sender.next().unwrap()).collect();
+        assert_eq!(keys[0].0, 0);
+        assert_eq!(keys[3].0, 3);
+
+        assert_eq!(receiver.key_for(2).unwrap(), keys[2].1);
+        assert_eq!(receiver.key_for(0).unwrap(), keys[0].1);
+        assert_eq!(receiver.key_for(3).unwrap(), keys[3].1);
+        assert_eq!(receiver.key_for(1).unwrap(), keys[1].1);
+
+        // Each key works once
+        assert!(receiver.key_for(2).is_err());
+        assert!(receiver.key_for(MAX_GENERATION_GAP + 10).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+//! Ratchet tree
+//!
+//! The public half of a TreeKEM group: a left-balanced binary tree stored as
+//! an array (RFC 9420 appendix C). Leaves sit at even indices and hold a
+//! member's identity and keys; parent nodes hold an X25519 key shared by
+//! every member below them.
+//!
+//! ```text
+//!                     3                    leaf i  →  node 2i
+//!                ┌────┴────┐               level(x) = trailing ones of x
+//!                1         5
+//!              ┌─┴─┐     ┌─┴─┐
+//!              0   2     4   6             leaves: A=0 B=2 C=4 D=6
+//!              A   B     C   D
+//! ```
+//!
+//! The leaf count is always a power of two: the tree doubles when it is
+//! full and halves again once its right half is empty, so node indices of
+//! existing members never move.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::messages::to_be_signed;
+use crate::crypto::keys::{hex_bytes, SigningKeyPair};
+use crate::crypto::signing::{sign, verify, Signature};
+use crate::error::{Error, Result};
+use crate::identity::Did;
+
+// ============================================================================
+// TREE MATH
+// ============================================================================
+
+/// Level of a node: 0 for leaves, 1 for their parents, and so on
+pub(crate) fn level(x: u32) -> u32 {
+    x.trailing_ones()
+}
+
+/// Node index of a leaf
+pub(crate) fn leaf_node(leaf: u32) -> u32 {
+    leaf * 2
+}
+
+/// Root of a tree with `leaves` leaves (a power of two)
+pub(crate) fn root(leaves: u32) -> u32 {
+    leaves - 1
+}
+
+fn left(x: u32) -> u32 {
+    x ^ (1 << (level(x) - 1))
+}
+
+fn right(x: u32) -> u32 {
+    x ^ (3 << (level(x) - 1))
+}
+
+fn parent(x: u32) -> u32 {
+    let k = level(x);
+    let b = (x >> (k + 1)) & 1;
+    (x | (1 << k)) ^ (b << (k + 1))
+}
+
+fn sibling(x: u32) -> u32 {
+    let p = parent(x);
+    if x < p {
+        right(p)
+    } else {
+        left(p)
+    }
+}
+
+/// Ancestors of `x`, from its parent up to and including the root
+pub(crate) fn direct_path(x: u32, leaves: u32) -> Vec<u32> {
+    let r = root(leaves);
+    let mut path = Vec::new();
+    let mut node = x;
+    while node != r {
+        node = parent(node);
+        path.push(node);
+    }
+    path
+}
+
+/// Siblings of `x` and of each of its ancestors below the root
+pub(crate) fn copath(x: u32, leaves: u32) -> Vec<u32> {
+    let r = root(leaves);
+    let mut path = Vec::new();
+    let mut node = x;
+    while node != r {
+        path.push(sibling(node));
+        node = parent(node);
+    }
+    path
+}
+
+/// Whether `descendant` is `node` or lies below it
+pub(crate) fn in_subtree(node: u32, descendant: u32) -> bool {
+    let half = (1u32 << level(node)) - 1;
+    descendant + half >= node && descendant <= node + half
+}
+
+// ============================================================================
+// NODES
+// ============================================================================
+
+/// A member's leaf: their DID, signing key and current TreeKEM key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LeafNode {
+    /// Member DID; must be the `did:key` of `signature_key`
+    pub did: String,
+    /// Ed25519 identity key that signs the member's handshake messages
+    #[serde(with = "hex_bytes")]
+    pub signature_key: [u8; 32],
+    /// X25519 key path secrets are encrypted to
+    #[serde(with = "hex_bytes")]
+    pub encryption_key: [u8; 32],
+    /// Signature over the fields above (hex encoded)
+    pub signature: String,
+}
+
+impl LeafNode {
+    /// Create a leaf signed with the member's identity key
+    pub fn new(signing: &SigningKeyPair, did: &str, encryption_key: [u8; 32]) -> Self {
+        let signature_key = signing.public_bytes();
+        let data = leaf_data(did, &signature_key, &encryption_key);
+        Self {
+            did: did.to_string(),
+            signature_key,
+            encryption_key,
+            signature: sign(signing, &data).to_hex(),
+        }
+    }
+
+    /// Check the signature and that the DID belongs to the signing key
+    pub fn verify(&self) -> Result<()> {
+        if Did::from_public_key(&self.signature_key).as_str() != self.did {
+            return Err(Error::InvalidKey(format!(
+                "Leaf signing key does not match {}",
+                self.did
+            )));
+        }
+        let signature = Signature::from_hex(&self.signature)?;
+        let data = leaf_data(&self.did, &self.signature_key, &self.encryption_key);
+        verify(&self.signature_key, &data, &signature)
+    }
+}
+
+fn leaf_data(did: &str, signature_key: &[u8; 32], encryption_key: &[u8; 32]) -> Vec<u8> {
+    to_be_signed("leaf", &[did.as_bytes(), signature_key, encryption_key])
+}
+
+/// An intermediate node's key, known to the members below it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ParentNode {
+    #[serde(with = "hex_bytes")]
+    pub encryption_key: [u8; 32],
+    /// Leaves added below this node since it was last set; they don't know
+    /// its private key yet
+    pub unmerged_leaves: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+enum Node {
+    Leaf(LeafNode),
+    Parent(ParentNode),
+}
+
+// ============================================================================
+// RATCHET TREE
+// ============================================================================
+
+/// Public state of the group's ratchet tree
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RatchetTree {
+    nodes: Vec<Option<Node>>,
+}
+
+impl RatchetTree {
+    /// A one-member tree
+    pub fn new(leaf: LeafNode) -> Self {
+        Self {
+            nodes: vec![Some(Node::Leaf(leaf))],
+        }
+    }
+
+    /// Number of leaf slots (including blank ones)
+    pub fn leaf_count(&self) -> u32 {
+        (self.nodes.len() as u32).div_ceil(2)
+    }
+
+    /// The leaf at `leaf`, if it isn't blank
+    pub fn leaf(&self, leaf: u32) -> Option<&LeafNode> {
+        match self.nodes.get(leaf_node(leaf) as usize) {
+            Some(Some(Node::Leaf(node))) => Some(node),
+            _ => None,
+        }
+    }
+
+    /// Current members as (leaf index, leaf)
+    pub fn members(&self) -> impl Iterator<Item = (u32, &LeafNode)> {
+        (0..self.leaf_count()).filter_map(|i| self.leaf(i).map(|leaf| (i, leaf)))
+    }
+
+    /// Leaf index of a member
+    pub fn find(&self, did: &str) -> Option<u32> {
+        self.members()
+            .find(|(_, leaf)| leaf.did == did)
+            .map(|(i, _)| i)
+    }
+
+    /// Public key of a non-blank node
+    pub(crate) fn public_key(&self, x: u32) -> Option<[u8; 32]> {
+        match self.nodes.get(x as usize)? {
+            Some(Node::Leaf(leaf)) => Some(leaf.encryption_key),
+            Some(Node::Parent(node)) => Some(node.encryption_key),
+            None => None,
+        }
+    }
+
+    /// Put a new member in the leftmost blank leaf, growing the tree if
+    /// it is full. Returns the new leaf index.
+    pub(crate) fn add_leaf(&mut self, leaf: LeafNode) -> u32 {
+        let index = match (0..self.leaf_count()).find(|&i| self.leaf(i).is_none()) {
+            Some(i) => i,
+            None => {
+                let leaves = self.leaf_count();
+                self.nodes.resize(leaves as usize * 4 - 1, None);
+                leaves
+            }
+        };
+
+        let x = leaf_node(index);
+        self.nodes[x as usize] = Some(Node::Leaf(leaf));
+        for p in direct_path(x, self.leaf_count()) {
+            if let Some(Node::Parent(node)) = &mut self.nodes[p as usize] {
+                node.unmerged_leaves.push(index);
+            }
+        }
+        index
+    }
+
+    /// Replace a member's leaf and blank its direct path
+    pub(crate) fn update_leaf(&mut self, leaf: u32, node: LeafNode) {
+        self.blank_path(leaf);
+        self.nodes[leaf_node(leaf) as usize] = Some(Node::Leaf(node));
+    }
+
+    /// Remove a member, blanking their leaf and direct path, and shrink the
+    /// tree while its right half is empty
+    pub(crate) fn remove_leaf(&mut self, leaf: u32) {
+        self.blank_path(leaf);
+        self.nodes[leaf_node(leaf) as usize] = None;
+
+        while self.leaf_count() > 1 {
+            let half = self.leaf_count() as usize;
+            if self.nodes[half..].iter().any(Option::is_some) {
+                break;
+            }
+            self.nodes.truncate(half - 1);
+        }
+    }
+
+    fn blank_path(&mut self, leaf: u32) {
+        for p in direct_path(leaf_node(leaf), self.leaf_count()) {
+            self.nodes[p as usize] = None;
+        }
+    }
+
+    /// Set a parent node's key after a path update
+    pub(crate) fn set_parent(&mut self, x: u32, encryption_key: [u8; 32]) {
+        self.nodes[x as usize] = Some(Node::Parent(ParentNode {
+            encryption_key,
+            unmerged_leaves: Vec::new(),
+        }));
+    }
+
+    /// Set a member's leaf after a path update
+    pub(crate) fn set_leaf(&mut self, leaf: u32, node: LeafNode) {
+        self.nodes[leaf_node(leaf) as usize] = Some(Node::Leaf(node));
+    }
+
+    /// The smallest set of non-blank nodes covering every leaf below `x`
+    ///
+    /// Encrypting to each node of the resolution reaches every member in
+    /// the subtree; unmerged leaves are listed explicitly because they
+    /// don't hold the node's private key.
+    pub(crate) fn resolution(&self, x: u32) -> Vec<u32> {
+        match &self.nodes[x as usize] {
+            Some(Node::Leaf(_)) => vec![x],
+            Some(Node::Parent(node)) => {
+                let mut res = vec![x];
+                res.extend(node.unmerged_leaves.iter().map(|&l| leaf_node(l)));
+                res
+            }
+            None if level(x) == 0 => Vec::new(),
+            None => {
+                let mut res = self.resolution(left(x));
+                res.extend(self.resolution(right(x)));
+                res
+            }
+        }
+    }
+
+    /// Hash of the whole public tree, bound into every epoch's key schedule
+    pub fn tree_hash(&self) -> Result<[u8; 32]> {
+        let bytes =
+            bincode::serialize(self).map_err(|e| Error::SerializationError(e.to_string()))?;
+        Ok(Sha256::digest(bytes).into())
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+
+    fn leaf() -> LeafNode {
+        let keys = KeyPair::generate();
+        let did = Did::from_public_key(&keys.signing.public_bytes());
+        LeafNode::new(&keys.signing, did.as_str(), [7u8; 32])
+    }
+
+    #[test]
+    fn test_tree_math() {
+        // 4 leaves: nodes 0..=6, root 3
+        assert_eq!(root(4), 3);
+        assert_eq!(direct_path(0, 4), vec![1, 3]);
+        assert_eq!(copath(0, 4), vec![2, 5]);
+        assert_eq!(direct_path(6, 4), vec![5, 3]);
+        assert_eq!(copath(6, 4), vec![4, 1]);
+        assert!(direct_path(0, 1).is_empty());
+
+        assert!(in_subtree(5, 4));
+        assert!(in_subtree(5, 6));
+        assert!(!in_subtree(5, 2));
+        assert!(in_subtree(3, 0));
+    }
+
+    #[test]
+    fn test_add_and_remove_resize_the_tree() {
+        let mut tree = RatchetTree::new(leaf());
+        assert_eq!(tree.leaf_count(), 1);
+
+        assert_eq!(tree.add_leaf(leaf()), 1);
+        assert_eq!(tree.add_leaf(leaf()), 2);
+        assert_eq!(tree.leaf_count(), 4);
+
+        // Removing leaf 1 leaves a hole that the next add fills
+        tree.remove_leaf(1);
+        assert_eq!(tree.leaf_count(), 4);
+        assert_eq!(tree.add_leaf(leaf()), 1);
+
+        // Emptying the right half halves the tree
+        tree.remove_leaf(2);
+        assert_eq!(tree.leaf_count(), 2);
+        assert_eq!(tree.members().count(), 2);
+    }
+
+    #[test]
+    fn test_resolution_skips_blanks_and_lists_unmerged_leaves() {
+        let mut tree = RatchetTree::new(leaf());
+        tree.add_leaf(leaf());
+        tree.add_leaf(leaf());
+        tree.add_leaf(leaf());
+
+        // All parents blank: the root resolves to every leaf
+        assert_eq!(tree.resolution(3), vec![0, 2, 4, 6]);
+
+        tree.set_parent(5, [1u8; 32]);
+        assert_eq!(tree.resolution(3), vec![0, 2, 5]);
+
+        // A leaf added under a keyed parent is unmerged there
+        tree.remove_leaf(3);
+        tree.set_parent(5, [1u8; 32]);
+        tree.add_leaf(leaf());
+        assert_eq!(tree.resolution(5), vec![5, 6]);
+    }
+
+    #[test]
+    fn test_leaf_signature() {
+        let node = leaf();
+        assert!(node.verify().is_ok());
+
+        let mut tampered = node.clone();
+        tampered.encryption_key = [8u8; 32];
+        assert!(tampered.verify().is_err());
+
+        // A leaf can't claim someone else's DID
+        let mut impostor = leaf();
+        impostor.did = node.did.clone();
+        assert!(impostor.verify().is_err());
+    }
+}
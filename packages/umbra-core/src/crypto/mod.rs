@@ -86,6 +86,10 @@
 //! | X25519 | Key Exchange | Fast ECDH, same curve as Ed25519 |
 //! | AES-256-GCM | Encryption | Hardware acceleration, AEAD |
 //! | HKDF-SHA256 | Key Derivation | Industry standard, well-analyzed |
+//! | scrypt | Passphrase Derivation | Memory-hard, resists GPU guessing |
+//! | Double Ratchet | DM Sessions | Forward secrecy, post-compromise security |
+//! | TreeKEM (MLS) | Group Sessions | O(log n) membership changes, forward secrecy, post-compromise security |
+//! | Iterated SHA-512 | Safety Numbers | Slow to brute-force a matching key |
 //! | BIP39 | Recovery Phrase | User-friendly backup, standard |
 //!
 //! ## Security Considerations
@@ -98,6 +102,10 @@
 mod encryption;
 mod kdf;
 mod keys;
+mod mls;
+mod ratchet;
+mod safety_number;
+mod scrypt;
 mod signing;
 
 pub use encryption::{
@@ -105,11 +113,23 @@ pub use encryption::{
     EncryptedChunkInfo, EncryptionKey, Nonce, SharedSecret, NONCE_SIZE,
 };
 pub use kdf::{
-    compute_key_fingerprint, derive_backup_key, derive_channel_file_key, derive_file_key,
-    derive_keys_from_seed, derive_shared_secret, derive_sync_key, verify_key_fingerprint,
-    DerivedKeys,
+    compute_key_fingerprint, derive_backup_key, derive_channel_file_key, derive_device_wrap_key,
+    derive_file_key, derive_keys_from_seed, derive_sealed_access_key, derive_sealed_sender_key,
+    derive_shared_secret, derive_storage_key, derive_sync_key, verify_key_fingerprint, DerivedKeys,
 };
+pub(crate) use keys::hex_bytes;
 pub use keys::{EncryptionKeyPair, KeyPair, PublicKey, SigningKeyPair};
+pub use mls::{
+    Commit, CommitOutcome, CommitOutput, EncryptedGroupSecrets, HpkeCiphertext, KeyPackage,
+    KeyPackageSecrets, LeafNode, MlsCiphertext, MlsGroup, MlsMessage, MlsProposal, Proposal,
+    RatchetTree, UpdatePath, UpdatePathNode, Welcome,
+};
+pub use ratchet::{
+    sign_prekey, OneTimePrekey, PrekeyBundle, RatchetHeader, RatchetMessage, RatchetSession,
+    X3dhInit, MAX_SKIP,
+};
+pub use safety_number::{SafetyNumber, SAFETY_NUMBER_DIGITS};
+pub use scrypt::{scrypt, ScryptParams};
 pub use signing::{sign, verify, Signature, SIGNATURE_SIZE};
 
 /// Size of encryption keys in bytes (256 bits)
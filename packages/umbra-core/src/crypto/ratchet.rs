@@ -0,0 +1,836 @@
+//! # Double Ratchet Sessions
+//!
+//! Signal-style session layer for direct messages: an X3DH-like handshake
+//! bootstraps a shared root key, and the Double Ratchet derives a fresh
+//! AES-256-GCM key for every message.
+//!
+//! ## Session Establishment (X3DH)
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                      X3DH KEY AGREEMENT                                 │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  Bob publishes a PrekeyBundle alongside his PublicIdentity:            │
+//! │    IK_B   identity X25519 key (the existing encryption key)            │
+//! │    SPK_B  signed prekey (signed with Bob's Ed25519 key)                │
+//! │    OPK_B  optional one-time prekey (consumed on first use)             │
+//! │                                                                         │
+//! │  Alice generates an ephemeral key EK_A and computes:                   │
+//! │    DH1 = DH(IK_A,  SPK_B)                                              │
+//! │    DH2 = DH(EK_A,  IK_B)                                               │
+//! │    DH3 = DH(EK_A,  SPK_B)                                              │
+//! │    DH4 = DH(EK_A,  OPK_B)          (only if a one-time prekey exists) │
+//! │                                                                         │
+//! │    SK  = HKDF(0xFF×32 || DH1 || DH2 || DH3 [|| DH4],                   │
+//! │              info = "umbra-x3dh-v1")                                   │
+//! │                                                                         │
+//! │  Alice sends EK_A + prekey IDs (X3dhInit) with her first messages.     │
+//! │  Bob repeats the DHs with his private halves and derives the same SK.  │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! ## Double Ratchet
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                      RATCHET STEPS                                      │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  DH ratchet (new ratchet key seen in a header):                        │
+//! │    (RK, CK_recv) = HKDF(salt = RK, ikm = DH(our_ratchet, their_new))   │
+//! │    generate our_ratchet'                                               │
+//! │    (RK, CK_send) = HKDF(salt = RK, ikm = DH(our_ratchet', their_new))  │
+//! │                                                                         │
+//! │  Symmetric ratchet (every message):                                    │
+//! │    MK  = HMAC-SHA256(CK, 0x01)     → AES-256-GCM message key           │
+//! │    CK' = HMAC-SHA256(CK, 0x02)     → next chain key                    │
+//! │                                                                         │
+//! │  Out-of-order delivery: message keys for skipped message numbers are   │
+//! │  cached (bounded by MAX_SKIP) and consumed when the late message       │
+//! │  arrives.                                                              │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! ## Security Properties
+//!
+//! | Property | Provided by |
+//! |----------|-------------|
+//! | Forward secrecy | Chain keys are one-way; used message keys are deleted |
+//! | Post-compromise security | Every DH ratchet step mixes in fresh randomness |
+//! | Prekey authenticity | SPK is signed with the identity's Ed25519 key |
+//! | Atomic state updates | `decrypt` only commits state on successful authentication |
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::encryption::{decrypt, encrypt, EncryptionKey, Nonce};
+use super::kdf::domain;
+use super::keys::{hex_bytes, EncryptionKeyPair, SigningKeyPair};
+use super::signing::{sign, verify, Signature};
+use crate::error::{Error, Result};
+
+/// Maximum number of message keys that may be skipped in a single chain.
+///
+/// Bounds the work (and cache growth) an attacker can force by sending a
+/// header with a huge message number.
+pub const MAX_SKIP: u32 = 1000;
+
+/// Maximum number of skipped message keys retained per session.
+const MAX_SKIPPED_KEYS: usize = 2000;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// ============================================================================
+// PREKEY BUNDLE
+// ============================================================================
+
+/// A one-time prekey published inside a [`PrekeyBundle`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OneTimePrekey {
+    /// Local identifier of the prekey
+    pub id: u32,
+    /// X25519 public key
+    #[serde(with = "hex_bytes")]
+    pub public_key: [u8; 32],
+}
+
+/// Public prekey material needed to start a ratchet session with someone
+///
+/// Published alongside a `PublicIdentity`. The signed prekey is signed
+/// with the owner's Ed25519 identity key so a relay or MITM cannot swap it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PrekeyBundle {
+    /// The owner's long-term X25519 identity key
+    #[serde(with = "hex_bytes")]
+    pub identity_key: [u8; 32],
+    /// Identifier of the signed prekey
+    pub signed_prekey_id: u32,
+    /// Medium-term X25519 signed prekey
+    #[serde(with = "hex_bytes")]
+    pub signed_prekey: [u8; 32],
+    /// Ed25519 signature over the signed prekey (hex encoded)
+    pub signed_prekey_signature: String,
+    /// Optional one-time prekey
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub one_time_prekey: Option<OneTimePrekey>,
+}
+
+impl PrekeyBundle {
+    /// Verify the signed prekey against the owner's Ed25519 public key
+    pub fn verify(&self, signing_public_key: &[u8; 32]) -> Result<()> {
+        let signature = Signature::from_hex(&self.signed_prekey_signature)?;
+        let data = signed_prekey_data(self.signed_prekey_id, &self.signed_prekey);
+        verify(signing_public_key, &data, &signature)
+    }
+}
+
+/// Sign a prekey with an identity signing key
+///
+/// Returns the hex-encoded signature placed in [`PrekeyBundle::signed_prekey_signature`].
+pub fn sign_prekey(signing: &SigningKeyPair, prekey_id: u32, prekey_public: &[u8; 32]) -> String {
+    sign(signing, &signed_prekey_data(prekey_id, prekey_public)).to_hex()
+}
+
+fn signed_prekey_data(prekey_id: u32, prekey_public: &[u8; 32]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(domain::SIGNED_PREKEY.len() + 4 + 32);
+    data.extend_from_slice(domain::SIGNED_PREKEY);
+    data.extend_from_slice(&prekey_id.to_be_bytes());
+    data.extend_from_slice(prekey_public);
+    data
+}
+
+/// Handshake data sent by the initiator until the responder replies
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Zeroize)]
+pub struct X3dhInit {
+    /// Initiator's ephemeral X25519 public key
+    #[serde(with = "hex_bytes")]
+    pub ephemeral_key: [u8; 32],
+    /// Which of the responder's signed prekeys was used
+    pub signed_prekey_id: u32,
+    /// Which one-time prekey was used, if any
+    ///
+    /// Always serialized: sessions are persisted with bincode, which can't
+    /// read back a skipped field.
+    #[serde(default)]
+    pub one_time_prekey_id: Option<u32>,
+}
+
+/// Run X3DH as the initiator against a verified bundle
+///
+/// Returns the shared secret and the handshake data to send.
+fn x3dh_initiate(
+    our_identity: &EncryptionKeyPair,
+    bundle: &PrekeyBundle,
+) -> Result<([u8; 32], X3dhInit)> {
+    let ephemeral = EncryptionKeyPair::generate();
+
+    let mut ikm = vec![0xFFu8; 32];
+    ikm.extend_from_slice(&our_identity.diffie_hellman(&bundle.signed_prekey));
+    ikm.extend_from_slice(&ephemeral.diffie_hellman(&bundle.identity_key));
+    ikm.extend_from_slice(&ephemeral.diffie_hellman(&bundle.signed_prekey));
+    if let Some(otk) = &bundle.one_time_prekey {
+        ikm.extend_from_slice(&ephemeral.diffie_hellman(&otk.public_key));
+    }
+
+    let secret = x3dh_kdf(&ikm);
+    ikm.zeroize();
+
+    Ok((
+        secret?,
+        X3dhInit {
+            ephemeral_key: ephemeral.public_bytes(),
+            signed_prekey_id: bundle.signed_prekey_id,
+            one_time_prekey_id: bundle.one_time_prekey.as_ref().map(|k| k.id),
+        },
+    ))
+}
+
+/// Run X3DH as the responder using our private prekeys
+fn x3dh_respond(
+    our_identity: &EncryptionKeyPair,
+    signed_prekey: &EncryptionKeyPair,
+    one_time_prekey: Option<&EncryptionKeyPair>,
+    their_identity_key: &[u8; 32],
+    init: &X3dhInit,
+) -> Result<[u8; 32]> {
+    if init.one_time_prekey_id.is_some() != one_time_prekey.is_some() {
+        return Err(Error::KeyExchangeFailed(
+            "One-time prekey mismatch in X3DH handshake".into(),
+        ));
+    }
+
+    let mut ikm = vec![0xFFu8; 32];
+    ikm.extend_from_slice(&signed_prekey.diffie_hellman(their_identity_key));
+    ikm.extend_from_slice(&our_identity.diffie_hellman(&init.ephemeral_key));
+    ikm.extend_from_slice(&signed_prekey.diffie_hellman(&init.ephemeral_key));
+    if let Some(otk) = one_time_prekey {
+        ikm.extend_from_slice(&otk.diffie_hellman(&init.ephemeral_key));
+    }
+
+    let secret = x3dh_kdf(&ikm);
+    ikm.zeroize();
+    secret
+}
+
+fn x3dh_kdf(ikm: &[u8]) -> Result<[u8; 32]> {
+    let hkdf = Hkdf::<Sha256>::new(Some(&[0u8; 32]), ikm);
+    let mut out = [0u8; 32];
+    hkdf.expand(domain::X3DH, &mut out)
+        .map_err(|_| Error::KeyDerivationFailed("Failed to derive X3DH secret".into()))?;
+    Ok(out)
+}
+
+// ============================================================================
+// RATCHET
+// ============================================================================
+
+/// Per-message ratchet header (sent in the clear, authenticated as AAD)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RatchetHeader {
+    /// Sender's current ratchet public key
+    #[serde(with = "hex_bytes")]
+    pub ratchet_key: [u8; 32],
+    /// Number of messages in the sender's previous sending chain
+    pub previous_chain_length: u32,
+    /// Message number within the current sending chain
+    pub message_number: u32,
+}
+
+impl RatchetHeader {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(40);
+        out.extend_from_slice(&self.ratchet_key);
+        out.extend_from_slice(&self.previous_chain_length.to_be_bytes());
+        out.extend_from_slice(&self.message_number.to_be_bytes());
+        out
+    }
+}
+
+/// Output of [`RatchetSession::encrypt`]
+#[derive(Debug, Clone)]
+pub struct RatchetMessage {
+    /// Header to transmit with the ciphertext
+    pub header: RatchetHeader,
+    /// AES-GCM nonce
+    pub nonce: Nonce,
+    /// Ciphertext with authentication tag
+    pub ciphertext: Vec<u8>,
+}
+
+/// A cached key for a message that hasn't arrived yet
+#[derive(Clone, Serialize, Deserialize, Zeroize)]
+struct SkippedKey {
+    ratchet_key: [u8; 32],
+    message_number: u32,
+    message_key: [u8; 32],
+}
+
+/// Double Ratchet state for one peer
+///
+/// Serialize with [`to_bytes`](Self::to_bytes) for persistence. All secret
+/// material is zeroized on drop.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct RatchetSession {
+    /// Our current ratchet private key
+    ratchet_secret: [u8; 32],
+    /// Their current ratchet public key
+    remote_ratchet_key: Option<[u8; 32]>,
+    root_key: [u8; 32],
+    sending_chain: Option<[u8; 32]>,
+    receiving_chain: Option<[u8; 32]>,
+    sending_count: u32,
+    receiving_count: u32,
+    previous_sending_count: u32,
+    skipped: Vec<SkippedKey>,
+    /// Handshake data, attached to outgoing messages until the peer replies
+    pending_init: Option<X3dhInit>,
+}
+
+impl std::fmt::Debug for RatchetSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RatchetSession")
+            .field("sending_count", &self.sending_count)
+            .field("receiving_count", &self.receiving_count)
+            .field("skipped", &self.skipped.len())
+            .field("pending_init", &self.pending_init.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RatchetSession {
+    /// Start a session as the initiator from the peer's prekey bundle
+    ///
+    /// The bundle must be signed by `their_signing_key`, and its identity
+    /// key must be the peer's known X25519 key.
+    pub fn initiate(
+        our_identity: &EncryptionKeyPair,
+        bundle: &PrekeyBundle,
+        their_signing_key: &[u8; 32],
+    ) -> Result<Self> {
+        bundle.verify(their_signing_key)?;
+
+        let (shared_secret, init) = x3dh_initiate(our_identity, bundle)?;
+
+        let ratchet = EncryptionKeyPair::generate();
+        let dh_out = ratchet.diffie_hellman(&bundle.signed_prekey);
+        let (root_key, sending_chain) = kdf_root(&shared_secret, &dh_out)?;
+
+        Ok(Self {
+            ratchet_secret: ratchet.secret_bytes(),
+            remote_ratchet_key: Some(bundle.signed_prekey),
+            root_key,
+            sending_chain: Some(sending_chain),
+            receiving_chain: None,
+            sending_count: 0,
+            receiving_count: 0,
+            previous_sending_count: 0,
+            skipped: Vec::new(),
+            pending_init: Some(init),
+        })
+    }
+
+    /// Accept a session started by a peer
+    ///
+    /// `signed_prekey` and `one_time_prekey` are the private halves of the
+    /// prekeys referenced by `init`.
+    pub fn respond(
+        our_identity: &EncryptionKeyPair,
+        signed_prekey: &EncryptionKeyPair,
+        one_time_prekey: Option<&EncryptionKeyPair>,
+        their_identity_key: &[u8; 32],
+        init: &X3dhInit,
+    ) -> Result<Self> {
+        let shared_secret = x3dh_respond(
+            our_identity,
+            signed_prekey,
+            one_time_prekey,
+            their_identity_key,
+            init,
+        )?;
+
+        Ok(Self {
+            ratchet_secret: signed_prekey.secret_bytes(),
+            remote_ratchet_key: None,
+            root_key: shared_secret,
+            sending_chain: None,
+            receiving_chain: None,
+            sending_count: 0,
+            receiving_count: 0,
+            previous_sending_count: 0,
+            skipped: Vec::new(),
+            pending_init: None,
+        })
+    }
+
+    /// Handshake data to attach to outgoing messages, if the peer hasn't replied yet
+    pub fn pending_init(&self) -> Option<&X3dhInit> {
+        self.pending_init.as_ref()
+    }
+
+    /// Encrypt a message, advancing the sending chain
+    pub fn encrypt(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<RatchetMessage> {
+        let chain = self.sending_chain.ok_or_else(|| {
+            Error::EncryptionFailed("Ratchet session has no sending chain yet".into())
+        })?;
+
+        let (next_chain, message_key) = kdf_chain(&chain)?;
+        let header = RatchetHeader {
+            ratchet_key: EncryptionKeyPair::from_bytes(&self.ratchet_secret).public_bytes(),
+            previous_chain_length: self.previous_sending_count,
+            message_number: self.sending_count,
+        };
+
+        let full_aad = header_aad(aad, &header);
+        let (nonce, ciphertext) = encrypt(
+            &EncryptionKey::from_bytes(message_key),
+            plaintext,
+            &full_aad,
+        )?;
+
+        self.sending_chain = Some(next_chain);
+        self.sending_count += 1;
+
+        Ok(RatchetMessage {
+            header,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt a message, advancing the receiving side of the ratchet
+    ///
+    /// The session is only modified if decryption succeeds, so a forged or
+    /// corrupted message cannot desynchronize it.
+    pub fn decrypt(
+        &mut self,
+        header: &RatchetHeader,
+        nonce: &Nonce,
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>> {
+        let full_aad = header_aad(aad, header);
+
+        // Late message from an earlier chain position
+        if let Some(pos) = self.skipped.iter().position(|k| {
+            k.ratchet_key == header.ratchet_key && k.message_number == header.message_number
+        }) {
+            let key = EncryptionKey::from_bytes(self.skipped[pos].message_key);
+            let plaintext = decrypt(&key, nonce, ciphertext, &full_aad)?;
+            self.skipped.remove(pos).zeroize();
+            self.pending_init = None;
+            return Ok(plaintext);
+        }
+
+        let mut next = self.clone();
+
+        if next.remote_ratchet_key != Some(header.ratchet_key) {
+            next.skip_message_keys(header.previous_chain_length)?;
+            next.dh_ratchet(&header.ratchet_key)?;
+        }
+        next.skip_message_keys(header.message_number)?;
+
+        let chain = next.receiving_chain.ok_or_else(|| {
+            Error::DecryptionFailed("Ratchet session has no receiving chain".into())
+        })?;
+        let (next_chain, message_key) = kdf_chain(&chain)?;
+        let plaintext = decrypt(
+            &EncryptionKey::from_bytes(message_key),
+            nonce,
+            ciphertext,
+            &full_aad,
+        )?;
+
+        next.receiving_chain = Some(next_chain);
+        next.receiving_count += 1;
+        // Any authenticated reply proves the peer completed the handshake
+        next.pending_init = None;
+
+        *self = next;
+        Ok(plaintext)
+    }
+
+    /// Serialize for persistence
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self).map_err(|e| Error::SerializationError(e.to_string()))
+    }
+
+    /// Restore a persisted session
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes)
+            .map_err(|e| Error::DeserializationError(format!("Invalid ratchet session: {}", e)))
+    }
+
+    fn skip_message_keys(&mut self, until: u32) -> Result<()> {
+        let Some(mut chain) = self.receiving_chain else {
+            return Ok(());
+        };
+
+        if until > self.receiving_count.saturating_add(MAX_SKIP) {
+            return Err(Error::DecryptionFailed(format!(
+                "Too many skipped messages ({} > {})",
+                until - self.receiving_count,
+                MAX_SKIP
+            )));
+        }
+
+        let remote = self.remote_ratchet_key.unwrap_or_default();
+        while self.receiving_count < until {
+            let (next_chain, message_key) = kdf_chain(&chain)?;
+            self.skipped.push(SkippedKey {
+                ratchet_key: remote,
+                message_number: self.receiving_count,
+                message_key,
+            });
+            chain = next_chain;
+            self.receiving_count += 1;
+        }
+        self.receiving_chain = Some(chain);
+
+        // Drop the oldest cached keys once the cache is full
+        if self.skipped.len() > MAX_SKIPPED_KEYS {
+            let excess = self.skipped.len() - MAX_SKIPPED_KEYS;
+            for mut key in self.skipped.drain(..excess) {
+                key.zeroize();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn dh_ratchet(&mut self, their_ratchet_key: &[u8; 32]) -> Result<()> {
+        self.previous_sending_count = self.sending_count;
+        self.sending_count = 0;
+        self.receiving_count = 0;
+        self.remote_ratchet_key = Some(*their_ratchet_key);
+
+        let ours = EncryptionKeyPair::from_bytes(&self.ratchet_secret);
+        let (root_key, receiving_chain) =
+            kdf_root(&self.root_key, &ours.diffie_hellman(their_ratchet_key))?;
+
+        let new_ratchet = EncryptionKeyPair::generate();
+        let (root_key, sending_chain) =
+            kdf_root(&root_key, &new_ratchet.diffie_hellman(their_ratchet_key))?;
+
+        self.ratchet_secret = new_ratchet.secret_bytes();
+        self.root_key = root_key;
+        self.receiving_chain = Some(receiving_chain);
+        self.sending_chain = Some(sending_chain);
+        Ok(())
+    }
+}
+
+/// Root KDF: (root_key, dh_output) → (new root key, chain key)
+fn kdf_root(root_key: &[u8; 32], dh_output: &[u8; 32]) -> Result<([u8; 32], [u8; 32])> {
+    let hkdf = Hkdf::<Sha256>::new(Some(root_key), dh_output);
+    let mut okm = [0u8; 64];
+    hkdf.expand(domain::RATCHET_ROOT, &mut okm)
+        .map_err(|_| Error::KeyDerivationFailed("Failed to derive ratchet root key".into()))?;
+
+    let mut root = [0u8; 32];
+    let mut chain = [0u8; 32];
+    root.copy_from_slice(&okm[..32]);
+    chain.copy_from_slice(&okm[32..]);
+    okm.zeroize();
+    Ok((root, chain))
+}
+
+/// Chain KDF: chain_key → (next chain key, message key)
+fn kdf_chain(chain_key: &[u8; 32]) -> Result<([u8; 32], [u8; 32])> {
+    let derive = |constant: u8| -> Result<[u8; 32]> {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(chain_key)
+            .map_err(|e| Error::KeyDerivationFailed(format!("Invalid chain key: {}", e)))?;
+        mac.update(&[constant]);
+        Ok(mac.finalize().into_bytes().into())
+    };
+
+    let message_key = derive(0x01)?;
+    let next_chain = derive(0x02)?;
+    Ok((next_chain, message_key))
+}
+
+fn header_aad(aad: &[u8], header: &RatchetHeader) -> Vec<u8> {
+    let mut out = Vec::with_capacity(aad.len() + 40);
+    out.extend_from_slice(aad);
+    out.extend_from_slice(&header.to_bytes());
+    out
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+
+    struct Peer {
+        keys: KeyPair,
+        signed_prekey: EncryptionKeyPair,
+        one_time_prekey: EncryptionKeyPair,
+    }
+
+    impl Peer {
+        fn new() -> Self {
+            Self {
+                keys: KeyPair::generate(),
+                signed_prekey: EncryptionKeyPair::generate(),
+                one_time_prekey: EncryptionKeyPair::generate(),
+            }
+        }
+
+        fn bundle(&self) -> PrekeyBundle {
+            let spk = self.signed_prekey.public_bytes();
+            PrekeyBundle {
+                identity_key: self.keys.encryption.public_bytes(),
+                signed_prekey_id: 1,
+                signed_prekey: spk,
+                signed_prekey_signature: sign_prekey(&self.keys.signing, 1, &spk),
+                one_time_prekey: Some(OneTimePrekey {
+                    id: 7,
+                    public_key: self.one_time_prekey.public_bytes(),
+                }),
+            }
+        }
+    }
+
+    fn establish() -> (RatchetSession, RatchetSession) {
+        let alice = Peer::new();
+        let bob = Peer::new();
+
+        let mut alice_session = RatchetSession::initiate(
+            &alice.keys.encryption,
+            &bob.bundle(),
+            &bob.keys.signing.public_bytes(),
+        )
+        .unwrap();
+
+        let init = alice_session.pending_init().unwrap().clone();
+        assert_eq!(init.one_time_prekey_id, Some(7));
+
+        let mut bob_session = RatchetSession::respond(
+            &bob.keys.encryption,
+            &bob.signed_prekey,
+            Some(&bob.one_time_prekey),
+            &alice.keys.encryption.public_bytes(),
+            &init,
+        )
+        .unwrap();
+
+        // First message completes the handshake on Bob's side
+        let msg = alice_session.encrypt(b"hello", b"aad").unwrap();
+        let pt = bob_session
+            .decrypt(&msg.header, &msg.nonce, &msg.ciphertext, b"aad")
+            .unwrap();
+        assert_eq!(pt, b"hello");
+
+        (alice_session, bob_session)
+    }
+
+    #[test]
+    fn test_bundle_signature_verification() {
+        let bob = Peer::new();
+        let bundle = bob.bundle();
+        assert!(bundle.verify(&bob.keys.signing.public_bytes()).is_ok());
+
+        let mut forged = bundle.clone();
+        forged.signed_prekey = EncryptionKeyPair::generate().public_bytes();
+        assert!(forged.verify(&bob.keys.signing.public_bytes()).is_err());
+
+        let mallory = KeyPair::generate();
+        let result = RatchetSession::initiate(
+            &mallory.encryption,
+            &forged,
+            &bob.keys.signing.public_bytes(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_round_trip_both_directions() {
+        let (mut alice, mut bob) = establish();
+
+        for i in 0..3 {
+            let text = format!("bob → alice {}", i);
+            let msg = bob.encrypt(text.as_bytes(), b"").unwrap();
+            let pt = alice
+                .decrypt(&msg.header, &msg.nonce, &msg.ciphertext, b"")
+                .unwrap();
+            assert_eq!(pt, text.as_bytes());
+        }
+        assert!(alice.pending_init().is_none());
+
+        let msg = alice.encrypt(b"alice again", b"").unwrap();
+        let pt = bob
+            .decrypt(&msg.header, &msg.nonce, &msg.ciphertext, b"")
+            .unwrap();
+        assert_eq!(pt, b"alice again");
+    }
+
+    #[test]
+    fn test_every_message_uses_a_new_key() {
+        let (mut alice, _bob) = establish();
+
+        let m1 = alice.encrypt(b"same", b"").unwrap();
+        let m2 = alice.encrypt(b"same", b"").unwrap();
+        assert_ne!(m1.header.message_number, m2.header.message_number);
+        assert_ne!(m1.ciphertext, m2.ciphertext);
+    }
+
+    #[test]
+    fn test_out_of_order_delivery() {
+        let (mut alice, mut bob) = establish();
+
+        let m1 = alice.encrypt(b"one", b"").unwrap();
+        let m2 = alice.encrypt(b"two", b"").unwrap();
+        let m3 = alice.encrypt(b"three", b"").unwrap();
+
+        assert_eq!(
+            bob.decrypt(&m3.header, &m3.nonce, &m3.ciphertext, b"")
+                .unwrap(),
+            b"three"
+        );
+        assert_eq!(
+            bob.decrypt(&m1.header, &m1.nonce, &m1.ciphertext, b"")
+                .unwrap(),
+            b"one"
+        );
+        assert_eq!(
+            bob.decrypt(&m2.header, &m2.nonce, &m2.ciphertext, b"")
+                .unwrap(),
+            b"two"
+        );
+
+        // Replaying a consumed message key fails
+        assert!(bob
+            .decrypt(&m2.header, &m2.nonce, &m2.ciphertext, b"")
+            .is_err());
+    }
+
+    #[test]
+    fn test_out_of_order_across_dh_ratchet() {
+        let (mut alice, mut bob) = establish();
+
+        let late = alice.encrypt(b"late", b"").unwrap();
+
+        // Bob replies, then Alice sends in a new chain
+        let reply = bob.encrypt(b"reply", b"").unwrap();
+        alice
+            .decrypt(&reply.header, &reply.nonce, &reply.ciphertext, b"")
+            .unwrap();
+        let fresh = alice.encrypt(b"fresh", b"").unwrap();
+        assert_ne!(fresh.header.ratchet_key, late.header.ratchet_key);
+
+        assert_eq!(
+            bob.decrypt(&fresh.header, &fresh.nonce, &fresh.ciphertext, b"")
+                .unwrap(),
+            b"fresh"
+        );
+        assert_eq!(
+            bob.decrypt(&late.header, &late.nonce, &late.ciphertext, b"")
+                .unwrap(),
+            b"late"
+        );
+    }
+
+    #[test]
+    fn test_failed_decrypt_does_not_advance_state() {
+        let (mut alice, mut bob) = establish();
+
+        let mut msg = alice.encrypt(b"payload", b"aad").unwrap();
+        msg.ciphertext[0] ^= 0xFF;
+        assert!(bob
+            .decrypt(&msg.header, &msg.nonce, &msg.ciphertext, b"aad")
+            .is_err());
+
+        let ok = alice.encrypt(b"next", b"aad").unwrap();
+        assert_eq!(
+            bob.decrypt(&ok.header, &ok.nonce, &ok.ciphertext, b"aad")
+                .unwrap(),
+            b"next"
+        );
+    }
+
+    #[test]
+    fn test_header_is_authenticated() {
+        let (mut alice, mut bob) = establish();
+
+        let mut msg = alice.encrypt(b"payload", b"").unwrap();
+        msg.header.previous_chain_length += 1;
+        assert!(bob
+            .decrypt(&msg.header, &msg.nonce, &msg.ciphertext, b"")
+            .is_err());
+    }
+
+    #[test]
+    fn test_skip_limit() {
+        let (mut alice, mut bob) = establish();
+
+        let mut msg = alice.encrypt(b"payload", b"").unwrap();
+        msg.header.message_number = MAX_SKIP + 10;
+        assert!(bob
+            .decrypt(&msg.header, &msg.nonce, &msg.ciphertext, b"")
+            .is_err());
+    }
+
+    #[test]
+    fn test_session_serialization_round_trip() {
+        let (mut alice, bob) = establish();
+
+        let mut restored = RatchetSession::from_bytes(&bob.to_bytes().unwrap()).unwrap();
+        let msg = alice.encrypt(b"after restore", b"").unwrap();
+        assert_eq!(
+            restored
+                .decrypt(&msg.header, &msg.nonce, &msg.ciphertext, b"")
+                .unwrap(),
+            b"after restore"
+        );
+    }
+
+    #[test]
+    fn test_session_without_one_time_prekey_round_trip() {
+        let alice = Peer::new();
+        let bob = Peer::new();
+        let mut bundle = bob.bundle();
+        bundle.one_time_prekey = None;
+
+        let session = RatchetSession::initiate(
+            &alice.keys.encryption,
+            &bundle,
+            &bob.keys.signing.public_bytes(),
+        )
+        .unwrap();
+        assert_eq!(session.pending_init().unwrap().one_time_prekey_id, None);
+
+        let restored = RatchetSession::from_bytes(&session.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored.pending_init(), session.pending_init());
+    }
+
+    #[test]
+    fn test_respond_requires_matching_one_time_prekey() {
+        let alice = Peer::new();
+        let bob = Peer::new();
+
+        let session = RatchetSession::initiate(
+            &alice.keys.encryption,
+            &bob.bundle(),
+            &bob.keys.signing.public_bytes(),
+        )
+        .unwrap();
+
+        let result = RatchetSession::respond(
+            &bob.keys.encryption,
+            &bob.signed_prekey,
+            None,
+            &alice.keys.encryption.public_bytes(),
+            session.pending_init().unwrap(),
+        );
+        assert!(result.is_err());
+    }
+}
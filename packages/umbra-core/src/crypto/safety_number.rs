@@ -0,0 +1,234 @@
+//! # Safety Numbers
+//!
+//! Out-of-band verification of a friend's keys, in the style of Signal.
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                        SAFETY NUMBER                                    │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  fingerprint(did, keys)                                                │
+//! │    h = SHA-512(version ‖ signing ‖ encryption ‖ did)                   │
+//! │    repeat 5200×: h = SHA-512(h ‖ signing ‖ encryption)                 │
+//! │    → first 30 bytes as six 5-digit groups                              │
+//! │                                                                         │
+//! │  Alice: 30 digits ─┐                                                   │
+//! │                    ├─► sorted by DID ─► 60 digits, same on both sides  │
+//! │  Bob:   30 digits ─┘                                                   │
+//! │                                                                         │
+//! │  QR payload: base64(version ‖ local fingerprint ‖ remote fingerprint)  │
+//! │  Scanning a friend's code checks their "local" is our "remote" and     │
+//! │  vice versa.                                                           │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! Both the signing and encryption keys are covered, so a key rotation or a
+//! key swapped in transit changes the number.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use sha2::{Digest, Sha512};
+
+use super::PublicKey;
+use crate::error::{Error, Result};
+
+/// Version of the fingerprint format and QR payload
+const SAFETY_NUMBER_VERSION: u8 = 0;
+
+/// Hash iterations, to make finding a key with a matching number expensive
+const FINGERPRINT_ITERATIONS: usize = 5200;
+
+/// Fingerprint bytes kept per party
+const FINGERPRINT_SIZE: usize = 32;
+
+/// 5-digit groups per party
+const GROUPS_PER_PARTY: usize = 6;
+
+/// Length of a safety number in digits
+pub const SAFETY_NUMBER_DIGITS: usize = GROUPS_PER_PARTY * 5 * 2;
+
+/// Safety number for a pair of identities
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafetyNumber {
+    local_fingerprint: [u8; FINGERPRINT_SIZE],
+    remote_fingerprint: [u8; FINGERPRINT_SIZE],
+    digits: String,
+}
+
+impl SafetyNumber {
+    /// Compute the safety number between our keys and a friend's
+    pub fn compute(
+        local_did: &str,
+        local_key: &PublicKey,
+        remote_did: &str,
+        remote_key: &PublicKey,
+    ) -> Self {
+        let local_fingerprint = fingerprint(local_did, local_key);
+        let remote_fingerprint = fingerprint(remote_did, remote_key);
+
+        let (first, second) = if local_did <= remote_did {
+            (&local_fingerprint, &remote_fingerprint)
+        } else {
+            (&remote_fingerprint, &local_fingerprint)
+        };
+        let digits = format!(
+            "{}{}",
+            fingerprint_digits(first),
+            fingerprint_digits(second)
+        );
+
+        Self {
+            local_fingerprint,
+            remote_fingerprint,
+            digits,
+        }
+    }
+
+    /// The 60-digit safety number
+    pub fn digits(&self) -> &str {
+        &self.digits
+    }
+
+    /// The safety number in 5-digit groups, for display
+    pub fn display(&self) -> String {
+        self.digits
+            .as_bytes()
+            .chunks(5)
+            .map(|group| std::str::from_utf8(group).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Payload to show as a QR code for the friend to scan
+    pub fn qr_payload(&self) -> String {
+        let mut bytes = Vec::with_capacity(1 + 2 * FINGERPRINT_SIZE);
+        bytes.push(SAFETY_NUMBER_VERSION);
+        bytes.extend_from_slice(&self.local_fingerprint);
+        bytes.extend_from_slice(&self.remote_fingerprint);
+        BASE64.encode(bytes)
+    }
+
+    /// Check a QR payload scanned from the friend's screen
+    ///
+    /// Returns false if the keys do not match (a key changed, or someone
+    /// is in the middle).
+    pub fn verify_qr_payload(&self, payload: &str) -> Result<bool> {
+        let bytes = BASE64
+            .decode(payload.trim())
+            .map_err(|e| Error::InvalidKey(format!("Invalid safety number payload: {}", e)))?;
+        if bytes.len() != 1 + 2 * FINGERPRINT_SIZE {
+            return Err(Error::InvalidKey(
+                "Invalid safety number payload length".into(),
+            ));
+        }
+        if bytes[0] != SAFETY_NUMBER_VERSION {
+            return Err(Error::ProtocolError(format!(
+                "Unsupported safety number version: {}",
+                bytes[0]
+            )));
+        }
+
+        // Their local fingerprint is our remote one, and vice versa
+        let (theirs, ours) = bytes[1..].split_at(FINGERPRINT_SIZE);
+        Ok(theirs == self.remote_fingerprint && ours == self.local_fingerprint)
+    }
+}
+
+fn fingerprint(did: &str, key: &PublicKey) -> [u8; FINGERPRINT_SIZE] {
+    let mut hasher = Sha512::new();
+    hasher.update([0, SAFETY_NUMBER_VERSION]);
+    hasher.update(key.signing);
+    hasher.update(key.encryption);
+    hasher.update(did.as_bytes());
+    let mut hash = hasher.finalize();
+
+    for _ in 0..FINGERPRINT_ITERATIONS {
+        let mut hasher = Sha512::new();
+        hasher.update(hash);
+        hasher.update(key.signing);
+        hasher.update(key.encryption);
+        hash = hasher.finalize();
+    }
+
+    let mut fingerprint = [0u8; FINGERPRINT_SIZE];
+    fingerprint.copy_from_slice(&hash[..FINGERPRINT_SIZE]);
+    fingerprint
+}
+
+fn fingerprint_digits(fingerprint: &[u8; FINGERPRINT_SIZE]) -> String {
+    fingerprint
+        .chunks(5)
+        .take(GROUPS_PER_PARTY)
+        .map(|chunk| {
+            let value = chunk.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+            format!("{:05}", value % 100_000)
+        })
+        .collect()
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+
+    fn party(seed: u8) -> (String, PublicKey) {
+        let keypair = KeyPair::from_seed(&[seed; 32]).unwrap();
+        (format!("did:key:test{}", seed), keypair.public_keys())
+    }
+
+    #[test]
+    fn test_safety_number_matches_on_both_sides() {
+        let (alice_did, alice_key) = party(1);
+        let (bob_did, bob_key) = party(2);
+
+        let alice_view = SafetyNumber::compute(&alice_did, &alice_key, &bob_did, &bob_key);
+        let bob_view = SafetyNumber::compute(&bob_did, &bob_key, &alice_did, &alice_key);
+
+        assert_eq!(alice_view.digits(), bob_view.digits());
+        assert_eq!(alice_view.digits().len(), SAFETY_NUMBER_DIGITS);
+        assert!(alice_view.digits().chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(alice_view.display().split(' ').count(), 12);
+    }
+
+    #[test]
+    fn test_safety_number_changes_with_keys() {
+        let (alice_did, alice_key) = party(1);
+        let (bob_did, bob_key) = party(2);
+        let before = SafetyNumber::compute(&alice_did, &alice_key, &bob_did, &bob_key);
+
+        let mut rotated = bob_key.clone();
+        rotated.encryption = party(3).1.encryption;
+        let after = SafetyNumber::compute(&alice_did, &alice_key, &bob_did, &rotated);
+
+        assert_ne!(before.digits(), after.digits());
+    }
+
+    #[test]
+    fn test_qr_payload_verification() {
+        let (alice_did, alice_key) = party(1);
+        let (bob_did, bob_key) = party(2);
+        let (_, mallory_key) = party(3);
+
+        let alice_view = SafetyNumber::compute(&alice_did, &alice_key, &bob_did, &bob_key);
+        let bob_view = SafetyNumber::compute(&bob_did, &bob_key, &alice_did, &alice_key);
+        assert!(alice_view
+            .verify_qr_payload(&bob_view.qr_payload())
+            .unwrap());
+        assert!(bob_view
+            .verify_qr_payload(&alice_view.qr_payload())
+            .unwrap());
+
+        // Our own code, or one made against a swapped key, does not match
+        assert!(!alice_view
+            .verify_qr_payload(&alice_view.qr_payload())
+            .unwrap());
+        let swapped = SafetyNumber::compute(&bob_did, &bob_key, &alice_did, &mallory_key);
+        assert!(!alice_view.verify_qr_payload(&swapped.qr_payload()).unwrap());
+
+        assert!(alice_view.verify_qr_payload("not base64!").is_err());
+    }
+}
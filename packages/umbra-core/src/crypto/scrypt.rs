@@ -0,0 +1,267 @@
+//! # Passphrase Key Derivation (scrypt)
+//!
+//! Memory-hard key derivation for secrets that start life as something a
+//! human typed (vault passphrases), as opposed to the high-entropy seeds
+//! handled by HKDF in `kdf.rs`.
+//!
+//! ## Construction (RFC 7914)
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                          scrypt(P, S, N, r, p)                          │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  B = PBKDF2-HMAC-SHA256(P, S, 1, p × 128r)                              │
+//! │                                                                         │
+//! │  for each 128r-byte block Bᵢ:                                           │
+//! │      Bᵢ = ROMix(Bᵢ, N)          ← N × 128r bytes of scratch memory      │
+//! │                                    (BlockMix over Salsa20/8)            │
+//! │                                                                         │
+//! │  DK = PBKDF2-HMAC-SHA256(P, B, 1, dkLen)                                │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! Checked against the RFC 7914 §12 test vectors.
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::error::{Error, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Cost parameters for [`scrypt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScryptParams {
+    /// log₂ of the CPU/memory cost `N`
+    pub log_n: u8,
+    /// Block size `r`
+    pub r: u32,
+    /// Parallelization `p`
+    pub p: u32,
+}
+
+impl ScryptParams {
+    /// Interactive-login cost: N = 2¹⁷, r = 8, p = 1 (128 MiB, ~0.3s).
+    pub const INTERACTIVE: Self = Self {
+        log_n: 17,
+        r: 8,
+        p: 1,
+    };
+
+    /// Validate the parameters against RFC 7914 limits and a sane memory cap.
+    pub fn validate(&self) -> Result<()> {
+        if self.log_n == 0 || self.log_n >= 32 || self.r == 0 || self.p == 0 {
+            return Err(Error::KeyDerivationFailed(
+                "Invalid scrypt parameters".into(),
+            ));
+        }
+        // p × r must stay below 2³⁰, and refuse more than 1 GiB of scratch
+        let pr = self.p as u64 * self.r as u64;
+        let memory = 128u64 * self.r as u64 * (1u64 << self.log_n);
+        if pr >= 1 << 30 || memory > 1 << 30 {
+            return Err(Error::KeyDerivationFailed(
+                "scrypt parameters exceed resource limits".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ScryptParams {
+    fn default() -> Self {
+        Self::INTERACTIVE
+    }
+}
+
+/// Derive `output.len()` bytes from a passphrase with scrypt.
+pub fn scrypt(
+    passphrase: &[u8],
+    salt: &[u8],
+    params: &ScryptParams,
+    output: &mut [u8],
+) -> Result<()> {
+    params.validate()?;
+    if output.is_empty() {
+        return Err(Error::KeyDerivationFailed("Empty scrypt output".into()));
+    }
+
+    let r = params.r as usize;
+    let n = 1usize << params.log_n;
+    let block_len = 128 * r;
+
+    let mut b = vec![0u8; block_len * params.p as usize];
+    pbkdf2_sha256(passphrase, salt, &mut b)?;
+
+    let mut x = vec![0u32; 32 * r];
+    let mut v = vec![0u32; 32 * r * n];
+    let mut scratch = vec![0u32; 32 * r];
+    for chunk in b.chunks_mut(block_len) {
+        ro_mix(chunk, &mut x, &mut v, &mut scratch, n);
+    }
+
+    let result = pbkdf2_sha256(passphrase, &b, output);
+    b.zeroize();
+    x.zeroize();
+    v.zeroize();
+    scratch.zeroize();
+    result
+}
+
+/// PBKDF2-HMAC-SHA256 with a single iteration, as scrypt uses it.
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], output: &mut [u8]) -> Result<()> {
+    let prf = HmacSha256::new_from_slice(password)
+        .map_err(|_| Error::KeyDerivationFailed("Invalid HMAC key".into()))?;
+
+    for (i, chunk) in output.chunks_mut(32).enumerate() {
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&(i as u32 + 1).to_be_bytes());
+        let block = mac.finalize().into_bytes();
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    Ok(())
+}
+
+/// scryptROMix: fill `v` with N successive BlockMix states, then walk it
+/// data-dependently.
+fn ro_mix(block: &mut [u8], x: &mut [u32], v: &mut [u32], scratch: &mut [u32], n: usize) {
+    let words = x.len();
+
+    for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    for i in 0..n {
+        v[i * words..(i + 1) * words].copy_from_slice(x);
+        block_mix(x, scratch);
+    }
+
+    for _ in 0..n {
+        // Integerify: first word of the last 64-byte sub-block, mod N
+        let j = x[words - 16] as usize & (n - 1);
+        for (a, b) in x.iter_mut().zip(&v[j * words..(j + 1) * words]) {
+            *a ^= b;
+        }
+        block_mix(x, scratch);
+    }
+
+    for (bytes, word) in block.chunks_exact_mut(4).zip(x.iter()) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+}
+
+/// scryptBlockMix over 2r 64-byte sub-blocks. Even outputs go to the first
+/// half of `b`, odd outputs to the second half.
+fn block_mix(b: &mut [u32], scratch: &mut [u32]) {
+    let sub_blocks = b.len() / 16;
+    let half = sub_blocks / 2;
+
+    let mut t = [0u32; 16];
+    t.copy_from_slice(&b[(sub_blocks - 1) * 16..]);
+
+    for i in 0..sub_blocks {
+        for (a, w) in t.iter_mut().zip(&b[i * 16..(i + 1) * 16]) {
+            *a ^= w;
+        }
+        salsa20_8(&mut t);
+        let dest = if i % 2 == 0 { i / 2 } else { half + i / 2 };
+        scratch[dest * 16..(dest + 1) * 16].copy_from_slice(&t);
+    }
+
+    b.copy_from_slice(scratch);
+}
+
+/// The Salsa20/8 core.
+fn salsa20_8(block: &mut [u32; 16]) {
+    let mut x = *block;
+
+    macro_rules! quarter {
+        ($a:expr, $b:expr, $c:expr, $d:expr) => {
+            x[$b] ^= x[$a].wrapping_add(x[$d]).rotate_left(7);
+            x[$c] ^= x[$b].wrapping_add(x[$a]).rotate_left(9);
+            x[$d] ^= x[$c].wrapping_add(x[$b]).rotate_left(13);
+            x[$a] ^= x[$d].wrapping_add(x[$c]).rotate_left(18);
+        };
+    }
+
+    for _ in 0..4 {
+        // Column round
+        quarter!(0, 4, 8, 12);
+        quarter!(5, 9, 13, 1);
+        quarter!(10, 14, 2, 6);
+        quarter!(15, 3, 7, 11);
+        // Row round
+        quarter!(0, 1, 2, 3);
+        quarter!(5, 6, 7, 4);
+        quarter!(10, 11, 8, 9);
+        quarter!(15, 12, 13, 14);
+    }
+
+    for (out, mixed) in block.iter_mut().zip(x.iter()) {
+        *out = out.wrapping_add(*mixed);
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc7914_vector_empty() {
+        let params = ScryptParams {
+            log_n: 4,
+            r: 1,
+            p: 1,
+        };
+        let mut out = [0u8; 64];
+        scrypt(b"", b"", &params, &mut out).unwrap();
+        assert_eq!(
+            hex::encode(out),
+            "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+             fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"
+        );
+    }
+
+    #[test]
+    fn test_rfc7914_vector_password() {
+        let params = ScryptParams {
+            log_n: 10,
+            r: 8,
+            p: 16,
+        };
+        let mut out = [0u8; 64];
+        scrypt(b"password", b"NaCl", &params, &mut out).unwrap();
+        assert_eq!(
+            hex::encode(out),
+            "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162\
+             2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"
+        );
+    }
+
+    #[test]
+    fn test_invalid_params_rejected() {
+        let mut out = [0u8; 32];
+        let zero_r = ScryptParams {
+            log_n: 4,
+            r: 0,
+            p: 1,
+        };
+        assert!(scrypt(b"pw", b"salt", &zero_r, &mut out).is_err());
+
+        let huge = ScryptParams {
+            log_n: 24,
+            r: 8,
+            p: 1,
+        };
+        assert!(huge.validate().is_err());
+        assert!(ScryptParams::INTERACTIVE.validate().is_ok());
+    }
+}
@@ -57,7 +57,9 @@
 //! │      ├── ConversationNotFound  - Conversation doesn't exist            │
 //! │      ├── MessageNotFound       - Message doesn't exist                 │
 //! │      ├── RecipientOffline      - Recipient is offline                  │
-//! │      └── DeliveryFailed        - Message delivery failed               │
+//! │      ├── DeliveryFailed        - Message delivery failed               │
+//! │      ├── GroupNotFound         - Group doesn't exist                   │
+//! │      └── NotGroupMember        - Not a member of the group             │
 //! │                                                                         │
 //! └─────────────────────────────────────────────────────────────────────────┘
 //! ```
@@ -278,6 +280,14 @@ pub enum Error {
     #[error("Invalid message content: {0}")]
     InvalidMessageContent(String),
 
+    /// Group not found
+    #[error("Group not found.")]
+    GroupNotFound,
+
+    /// Not a member of this group
+    #[error("Not a member of this group.")]
+    NotGroupMember,
+
     // ========================================================================
     // Community Errors (800-899)
     // ========================================================================
@@ -345,6 +355,13 @@ pub enum Error {
     #[error("Channel type restriction: {0}")]
     ChannelTypeRestriction(String),
 
+    /// The actor lacks a permission the action requires
+    #[error("Missing permission: {required:?}")]
+    PermissionDenied {
+        /// The permission that was required
+        required: crate::community::Permission,
+    },
+
     // ========================================================================
     // Internal Errors (900-999)
     // ========================================================================
@@ -433,6 +450,8 @@ impl Error {
             Error::RecipientOffline => 702,
             Error::DeliveryFailed(_) => 703,
             Error::InvalidMessageContent(_) => 704,
+            Error::GroupNotFound => 705,
+            Error::NotGroupMember => 706,
 
             // Community (800-899)
             Error::CommunityNotFound => 800,
@@ -451,6 +470,7 @@ impl Error {
             Error::MemberTimedOut(_) => 813,
             Error::CategoryNotFound => 815,
             Error::ChannelTypeRestriction(_) => 814,
+            Error::PermissionDenied { .. } => 816,
 
             // Internal (900-999)
             Error::Internal(_) => 900,
@@ -561,6 +581,7 @@ mod tests {
         assert_eq!(Error::NotConnected.code(), 500);
         assert_eq!(Error::AlreadyFriends.code(), 600);
         assert_eq!(Error::ConversationNotFound.code(), 700);
+        assert_eq!(Error::GroupNotFound.code(), 705);
         assert_eq!(Error::Internal("test".into()).code(), 900);
     }
 
@@ -12,6 +12,7 @@ use super::state::{get_runtime, get_state, init_state, FfiState};
 use super::types::*;
 use crate::discovery::ConnectionInfo;
 use crate::friends::FriendsService;
+use crate::groups::GroupService;
 use crate::identity::Identity;
 use crate::identity::RecoveryPhrase;
 use crate::messaging::MessagingService;
@@ -70,6 +71,21 @@ fn get_or_create_messaging_service(state: &FfiState) -> Result<Arc<MessagingServ
     )))
 }
 
+/// Create a GroupService from the current state.
+fn create_group_service(state: &FfiState) -> Result<GroupService, FfiResult> {
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| FfiResult::err(200, "No identity loaded".to_string()))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| FfiResult::err(400, "Database not initialized".to_string()))?;
+    let cloned = Identity::clone_for_service(identity)
+        .map_err(|e| FfiResult::err(200, format!("Failed to clone identity: {}", e)))?;
+    Ok(GroupService::new(Arc::new(cloned), database.clone()))
+}
+
 // ============================================================================
 // INITIALIZATION
 // ============================================================================
@@ -121,6 +137,10 @@ pub unsafe extern "C" fn umbra_init(storage_path: *const c_char) -> FfiResult {
 ///
 /// Opens (or creates) an SQLite database at `<storage_path>/umbra.db`.
 /// Must be called after `umbra_init()` and before any data-access methods.
+/// With a persistent SecureStore the database is encrypted under the key
+/// cached by the last identity (see `secure_database`). Until an identity
+/// has keyed it, or when encryption is unavailable, it opens plaintext and
+/// `database_status` says why.
 ///
 /// # Returns
 /// FfiResult with success/error status
@@ -143,16 +163,63 @@ pub extern "C" fn umbra_init_database() -> FfiResult {
             }
         }
 
-        let db_path = {
+        // Encrypt at rest only when the key can outlive this process
+        // (Keychain, or a file vault unlocked via `secure_store_unlock`)
+        let (db_path, encryption_key) = {
             let st = state.read();
-            format!("{}/umbra.db", st.storage_path)
+            let store = st.secure_store.as_ref().filter(|s| s.is_persistent());
+            let key = if !crate::storage::Database::encryption_available() {
+                tracing::error!("SQLCipher unavailable; database will NOT be encrypted");
+                None
+            } else if let Some(store) = store {
+                match crate::storage::load_storage_key(store) {
+                    Ok(Some(key)) => Some(*key),
+                    Ok(None) => {
+                        tracing::info!("Database will be encrypted once an identity is loaded");
+                        None
+                    }
+                    Err(e) => return FfiResult::err(e.code(), e.to_string()),
+                }
+            } else {
+                tracing::error!("No persistent SecureStore; database will NOT be encrypted");
+                None
+            };
+            (format!("{}/umbra.db", st.storage_path), key)
         };
 
-        match crate::storage::Database::open(Some(&db_path)).await {
+        let config = crate::storage::DatabaseConfig {
+            path: Some(db_path.clone()),
+            encryption_key,
+        };
+        match crate::storage::Database::open_with_config(config).await {
             Ok(database) => {
+                let database = Arc::new(database);
+                let sweeper = tokio::spawn(crate::messaging::disappearing::run_sweeper(
+                    database.clone(),
+                    crate::messaging::disappearing::SWEEP_INTERVAL,
+                    |sweep| {
+                        super::dispatcher::emit_event(
+                            "message",
+                            &serde_json::json!({
+                                "type": "messagesExpired",
+                                "messages": sweep.messages,
+                                "files": sweep.files,
+                            }),
+                        )
+                    },
+                ));
+
                 let mut st = state.write();
-                st.database = Some(Arc::new(database));
+                st.community = Some(Arc::new(crate::community::CommunityService::new(
+                    database.clone(),
+                )));
+                st.database = Some(database);
+                st.sweeper = Some(sweeper);
                 tracing::info!("Database initialized at: {}", db_path);
+                // Identity loaded first (or switched since the key was cached)
+                if let Err(e) = super::dispatch_secure_store::secure_database(&st) {
+                    tracing::error!("Failed to key database: {}", e);
+                }
                 FfiResult::ok_empty()
             }
             Err(e) => {
@@ -225,7 +292,12 @@ pub unsafe extern "C" fn umbra_identity_create(display_name: *const c_char) -> F
             let did = identity.did_string();
             // Use .phrase() to get the space-separated recovery phrase string
             let phrase_str = recovery_phrase.phrase();
-            state.write().identity = Some(identity);
+            let mut state = state.write();
+            state.identity = Some(identity);
+            // Don't lose the phrase over this; `database_status` reports it
+            if let Err(e) = super::dispatch_secure_store::secure_database(&state) {
+                tracing::error!("Failed to key database: {}", e);
+            }
 
             let json = serde_json::json!({
                 "did": did,
@@ -269,9 +341,21 @@ pub unsafe extern "C" fn umbra_identity_restore(
     };
 
     match Identity::from_recovery_phrase(&recovery, name) {
-        Ok(identity) => {
+        Ok(mut identity) => {
             let did = identity.did_string();
-            state.write().identity = Some(identity);
+            let mut state = state.write();
+            // A rotated encryption key can't be derived from the phrase
+            if let Some(store) = &state.secure_store {
+                if let Err(e) =
+                    crate::storage::restore_rotated_encryption_key(store, &mut identity)
+                {
+                    return FfiResult::err(e.code(), e.to_string());
+                }
+            }
+            state.identity = Some(identity);
+            if let Err(e) = super::dispatch_secure_store::secure_database(&state) {
+                return FfiResult::err(e.code(), e.to_string());
+            }
             FfiResult::ok(did)
         }
         Err(e) => FfiResult::err(e.code(), e.to_string()),
@@ -890,6 +974,10 @@ pub unsafe extern "C" fn umbra_messaging_send_text(
         added_at: friend_record.created_at,
         online: false,
         last_seen: None,
+        previous_encryption_public_key: None,
+        key_rotated_at: friend_record.key_rotated_at,
+        verified: friend_record.verified,
+        verified_at: friend_record.verified_at,
     };
 
     match messaging.send_text(&friend, &message) {
@@ -990,6 +1078,269 @@ pub unsafe extern "C" fn umbra_messaging_get_messages(
     }
 }
 
+// ============================================================================
+// GROUPS
+// ============================================================================
+
+/// Create a group
+///
+/// # Safety
+/// `name` and `description` (if not null) must be valid null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn umbra_groups_create(
+    name: *const c_char,
+    description: *const c_char,
+) -> FfiResult {
+    let name = match cstr_to_string(name) {
+        Some(n) => n,
+        None => return FfiResult::err(704, "Invalid group name".to_string()),
+    };
+
+    let description = cstr_to_string(description);
+
+    let state = match get_state() {
+        Ok(s) => s,
+        Err(e) => return FfiResult::err(e.code(), e.to_string()),
+    };
+
+    let state = state.read();
+
+    let groups = match create_group_service(&state) {
+        Ok(g) => g,
+        Err(e) => return e,
+    };
+
+    match groups.create_group(&name, description.as_deref()) {
+        Ok(group) => {
+            let json = serde_json::json!({
+                "group_id": group.id,
+                "conversation_id": crate::groups::conversation_id(&group.id),
+                "name": group.name,
+                "created_by": group.created_by,
+                "created_at": group.created_at,
+            });
+            FfiResult::ok(json.to_string())
+        }
+        Err(e) => FfiResult::err(e.code(), e.to_string()),
+    }
+}
+
+/// Get list of groups
+#[no_mangle]
+pub extern "C" fn umbra_groups_list() -> FfiResult {
+    let state = match get_state() {
+        Ok(s) => s,
+        Err(e) => return FfiResult::err(e.code(), e.to_string()),
+    };
+
+    let state = state.read();
+
+    let groups = match create_group_service(&state) {
+        Ok(g) => g,
+        Err(e) => return e,
+    };
+
+    match groups.list_groups() {
+        Ok(list) => {
+            let groups_json: Vec<serde_json::Value> = list
+                .iter()
+                .map(|g| {
+                    serde_json::json!({
+                        "id": g.id,
+                        "name": g.name,
+                        "description": g.description,
+                        "created_by": g.created_by,
+                        "created_at": g.created_at,
+                        "updated_at": g.updated_at,
+                    })
+                })
+                .collect();
+
+            FfiResult::ok(serde_json::to_string(&groups_json).unwrap_or_default())
+        }
+        Err(e) => FfiResult::err(e.code(), e.to_string()),
+    }
+}
+
+/// Get the members of a group
+///
+/// # Safety
+/// `group_id` must be a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn umbra_groups_get_members(group_id: *const c_char) -> FfiResult {
+    let group_id = match cstr_to_string(group_id) {
+        Some(g) => g,
+        None => return FfiResult::err(705, "Invalid group ID".to_string()),
+    };
+
+    let state = match get_state() {
+        Ok(s) => s,
+        Err(e) => return FfiResult::err(e.code(), e.to_string()),
+    };
+
+    let state = state.read();
+
+    let groups = match create_group_service(&state) {
+        Ok(g) => g,
+        Err(e) => return e,
+    };
+
+    match groups.members(&group_id) {
+        Ok(members) => {
+            let members_json: Vec<serde_json::Value> = members
+                .iter()
+                .map(|m| {
+                    serde_json::json!({
+                        "member_did": m.member_did,
+                        "display_name": m.display_name,
+                        "role": m.role,
+                        "joined_at": m.joined_at,
+                    })
+                })
+                .collect();
+
+            FfiResult::ok(serde_json::to_string(&members_json).unwrap_or_default())
+        }
+        Err(e) => FfiResult::err(e.code(), e.to_string()),
+    }
+}
+
+/// Invite a friend to a group
+///
+/// Returns the relay messages the caller must deliver.
+///
+/// # Safety
+/// `group_id` and `did` must be valid null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn umbra_groups_invite_member(
+    group_id: *const c_char,
+    did: *const c_char,
+) -> FfiResult {
+    let group_id = match cstr_to_string(group_id) {
+        Some(g) => g,
+        None => return FfiResult::err(705, "Invalid group ID".to_string()),
+    };
+
+    let did_str = match cstr_to_string(did) {
+        Some(d) => d,
+        None => return FfiResult::err(204, "Invalid DID".to_string()),
+    };
+
+    let state = match get_state() {
+        Ok(s) => s,
+        Err(e) => return FfiResult::err(e.code(), e.to_string()),
+    };
+
+    let state = state.read();
+
+    let groups = match create_group_service(&state) {
+        Ok(g) => g,
+        Err(e) => return e,
+    };
+
+    match groups.invite_member(&group_id, &did_str) {
+        Ok(invite) => {
+            let json = serde_json::json!({
+                "invite_id": invite.invite_id,
+                "relay_messages": invite.relay_messages,
+            });
+            FfiResult::ok(json.to_string())
+        }
+        Err(e) => FfiResult::err(e.code(), e.to_string()),
+    }
+}
+
+/// Accept a group invite
+///
+/// Returns the relay message telling the inviter.
+///
+/// # Safety
+/// `invite_id` must be a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn umbra_groups_accept_invite(invite_id: *const c_char) -> FfiResult {
+    let id = match cstr_to_string(invite_id) {
+        Some(i) => i,
+        None => return FfiResult::err(808, "Invalid invite ID".to_string()),
+    };
+
+    let state = match get_state() {
+        Ok(s) => s,
+        Err(e) => return FfiResult::err(e.code(), e.to_string()),
+    };
+
+    let state = state.read();
+
+    let groups = match create_group_service(&state) {
+        Ok(g) => g,
+        Err(e) => return e,
+    };
+
+    let group = match groups.accept_invite(&id) {
+        Ok(g) => g,
+        Err(e) => return FfiResult::err(e.code(), e.to_string()),
+    };
+
+    match groups.invite_accept_envelope(&id) {
+        Ok(relay_message) => {
+            let json = serde_json::json!({
+                "group_id": group.id,
+                "conversation_id": crate::groups::conversation_id(&group.id),
+                "relay_messages": [relay_message],
+            });
+            FfiResult::ok(json.to_string())
+        }
+        Err(e) => FfiResult::err(e.code(), e.to_string()),
+    }
+}
+
+/// Send a message to a group
+///
+/// Returns the relay messages the caller must deliver.
+///
+/// # Safety
+/// `group_id` and `text` must be valid null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn umbra_groups_send_message(
+    group_id: *const c_char,
+    text: *const c_char,
+) -> FfiResult {
+    let group_id = match cstr_to_string(group_id) {
+        Some(g) => g,
+        None => return FfiResult::err(705, "Invalid group ID".to_string()),
+    };
+
+    let message = match cstr_to_string(text) {
+        Some(t) => t,
+        None => return FfiResult::err(704, "Invalid message".to_string()),
+    };
+
+    let state = match get_state() {
+        Ok(s) => s,
+        Err(e) => return FfiResult::err(e.code(), e.to_string()),
+    };
+
+    let state = state.read();
+
+    let groups = match create_group_service(&state) {
+        Ok(g) => g,
+        Err(e) => return e,
+    };
+
+    let conv_id = crate::groups::conversation_id(&group_id);
+    match groups.send_message(&group_id, &conv_id, &message) {
+        Ok(sent) => {
+            let json = serde_json::json!({
+                "id": sent.id,
+                "conversation_id": sent.conversation_id,
+                "timestamp": sent.timestamp,
+                "relay_messages": sent.relay_messages,
+            });
+            FfiResult::ok(json.to_string())
+        }
+        Err(e) => FfiResult::err(e.code(), e.to_string()),
+    }
+}
+
 // ============================================================================
 // GENERIC DISPATCHER
 // ============================================================================
@@ -1,5 +1,6 @@
 //! Community core + spaces + categories + channels + members + roles + invites dispatch handlers.
 
+use super::dispatch_community_keys::{ok_rekeyed, rekey_channel, rekey_community};
 use super::dispatcher::{
     community_service, emit_event, err, json_parse, ok_json, ok_success, require_str, DResult,
 };
@@ -262,6 +263,7 @@ pub fn community_category_update(args: &str) -> DResult {
 pub fn community_category_reorder(args: &str) -> DResult {
     let data = json_parse(args)?;
     let space_id = require_str(&data, "space_id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let category_ids: Vec<String> = data["category_ids"]
         .as_array()
         .map(|arr| {
@@ -271,7 +273,7 @@ pub fn community_category_reorder(args: &str) -> DResult {
         })
         .unwrap_or_default();
     let svc = community_service()?;
-    svc.reorder_categories(space_id, &category_ids)
+    svc.reorder_categories(space_id, &category_ids, actor_did)
         .map_err(|e| err(e.code(), e))?;
     ok_success()
 }
@@ -396,7 +398,7 @@ pub fn community_channel_set_e2ee(args: &str) -> DResult {
     let svc = community_service()?;
     svc.set_channel_e2ee(channel_id, enabled, actor_did)
         .map_err(|e| err(e.code(), e))?;
-    ok_success()
+    ok_rekeyed(rekey_channel(channel_id))
 }
 
 pub fn community_channel_delete(args: &str) -> DResult {
@@ -416,6 +418,7 @@ pub fn community_channel_delete(args: &str) -> DResult {
 pub fn community_channel_reorder(args: &str) -> DResult {
     let data = json_parse(args)?;
     let space_id = require_str(&data, "space_id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let channel_ids: Vec<String> = data["channel_ids"]
         .as_array()
         .map(|arr| {
@@ -425,7 +428,7 @@ pub fn community_channel_reorder(args: &str) -> DResult {
         })
         .unwrap_or_default();
     let svc = community_service()?;
-    svc.reorder_channels(space_id, &channel_ids)
+    svc.reorder_channels(space_id, &channel_ids, actor_did)
         .map_err(|e| err(e.code(), e))?;
     ok_success()
 }
@@ -473,7 +476,7 @@ pub fn community_kick(args: &str) -> DResult {
         "community",
         &serde_json::json!({"type": "memberKicked", "community_id": community_id, "target_did": target_did}),
     );
-    ok_success()
+    ok_rekeyed(rekey_community(community_id))
 }
 
 pub fn community_ban(args: &str) -> DResult {
@@ -498,7 +501,7 @@ pub fn community_ban(args: &str) -> DResult {
         "community",
         &serde_json::json!({"type": "memberBanned", "community_id": community_id, "target_did": target_did}),
     );
-    ok_success()
+    ok_rekeyed(rekey_community(community_id))
 }
 
 pub fn community_unban(args: &str) -> DResult {
@@ -545,8 +548,9 @@ pub fn community_member_update_profile(args: &str) -> DResult {
     let nickname = data["nickname"].as_str();
     let avatar_url = data["avatar_url"].as_str();
     let bio = data["bio"].as_str();
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.update_member_profile(community_id, member_did, nickname, avatar_url, bio)
+    svc.update_member_profile(community_id, member_did, nickname, avatar_url, bio, actor_did)
         .map_err(|e| err(e.code(), e))?;
     ok_success()
 }
@@ -622,7 +626,7 @@ pub fn community_role_unassign(args: &str) -> DResult {
         "community",
         &serde_json::json!({"type": "roleUnassigned", "community_id": community_id, "member_did": member_did, "role_id": role_id}),
     );
-    ok_success()
+    ok_rekeyed(rekey_community(community_id))
 }
 
 pub fn community_custom_role_create(args: &str) -> DResult {
@@ -690,7 +694,11 @@ pub fn community_role_update_permissions(args: &str) -> DResult {
     let svc = community_service()?;
     svc.update_role_permissions(role_id, permissions_bitfield, actor_did)
         .map_err(|e| err(e.code(), e))?;
-    ok_success()
+    let relay_messages = match svc.db().get_community_role(role_id) {
+        Ok(Some(role)) => rekey_community(&role.community_id),
+        _ => Vec::new(),
+    };
+    ok_rekeyed(relay_messages)
 }
 
 pub fn community_role_delete(args: &str) -> DResult {
@@ -698,13 +706,19 @@ pub fn community_role_delete(args: &str) -> DResult {
     let role_id = require_str(&data, "role_id")?;
     let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
+    let community_id = svc
+        .db()
+        .get_community_role(role_id)
+        .ok()
+        .flatten()
+        .map(|role| role.community_id);
     svc.delete_role(role_id, actor_did)
         .map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
         &serde_json::json!({"type": "roleDeleted", "role_id": role_id}),
     );
-    ok_success()
+    ok_rekeyed(community_id.map(|id| rekey_community(&id)).unwrap_or_default())
 }
 
 // ── Community — Invites ─────────────────────────────────────────────────────
@@ -3,6 +3,7 @@
 //! search, warnings, webhooks, channel overrides, boost nodes, timeouts,
 //! thread follow, member status, notification settings, mentions, vanity URL.
 
+use super::dispatch_community_keys::{ok_rekeyed, rekey_channel};
 use super::dispatcher::{
     boost_node_json, community_file_json, community_folder_json, community_msg_json,
     community_service, emit_event, err, json_parse, member_status_json,
@@ -72,8 +73,9 @@ pub fn community_emoji_rename(args: &str) -> DResult {
     let data = json_parse(args)?;
     let emoji_id = require_str(&data, "emoji_id")?;
     let new_name = require_str(&data, "new_name")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.rename_emoji(emoji_id, new_name)
+    svc.rename_emoji(emoji_id, new_name, actor_did)
         .map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
@@ -143,8 +145,9 @@ pub fn community_sticker_list(args: &str) -> DResult {
 pub fn community_sticker_delete(args: &str) -> DResult {
     let data = json_parse(args)?;
     let sticker_id = require_str(&data, "sticker_id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.delete_sticker(sticker_id)
+    svc.delete_sticker(sticker_id, actor_did)
         .map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
@@ -208,8 +211,9 @@ pub fn community_sticker_pack_list(args: &str) -> DResult {
 pub fn community_sticker_pack_delete(args: &str) -> DResult {
     let data = json_parse(args)?;
     let pack_id = require_str(&data, "pack_id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.delete_sticker_pack(pack_id)
+    svc.delete_sticker_pack(pack_id, actor_did)
         .map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
@@ -222,8 +226,9 @@ pub fn community_sticker_pack_rename(args: &str) -> DResult {
     let data = json_parse(args)?;
     let pack_id = require_str(&data, "pack_id")?;
     let new_name = require_str(&data, "new_name")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.rename_sticker_pack(pack_id, new_name)
+    svc.rename_sticker_pack(pack_id, new_name, actor_did)
         .map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
@@ -340,8 +345,9 @@ pub fn community_get_folders(args: &str) -> DResult {
 pub fn community_delete_folder(args: &str) -> DResult {
     let data = json_parse(args)?;
     let id = require_str(&data, "id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.delete_folder(id).map_err(|e| err(e.code(), e))?;
+    svc.delete_folder(id, actor_did).map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
         &serde_json::json!({"type": "folderDeleted", "folder_id": id}),
@@ -420,6 +426,7 @@ pub fn community_seat_delete(args: &str) -> DResult {
 pub fn community_seat_create_batch(args: &str) -> DResult {
     let data = json_parse(args)?;
     let community_id = require_str(&data, "community_id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let seats_arr = data["seats"]
         .as_array()
         .ok_or_else(|| err(2, "Missing seats array"))?;
@@ -445,7 +452,7 @@ pub fn community_seat_create_batch(args: &str) -> DResult {
 
     let svc = community_service()?;
     let count = svc
-        .create_seats_batch(community_id, seats)
+        .create_seats_batch(community_id, seats, actor_did)
         .map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
@@ -736,8 +743,9 @@ pub fn community_webhook_update(args: &str) -> DResult {
     let webhook_id = require_str(&data, "webhook_id")?;
     let name = data["name"].as_str();
     let avatar_url = data["avatar_url"].as_str();
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.update_webhook(webhook_id, name, avatar_url)
+    svc.update_webhook(webhook_id, name, avatar_url, actor_did)
         .map_err(|e| err(e.code(), e))?;
     ok_success()
 }
@@ -769,7 +777,7 @@ pub fn community_channel_override_set(args: &str) -> DResult {
     let svc = community_service()?;
     svc.set_channel_override(channel_id, target_type, target_id, allow_bitfield, deny_bitfield, actor_did)
         .map_err(|e| err(e.code(), e))?;
-    ok_success()
+    ok_rekeyed(rekey_channel(channel_id))
 }
 
 pub fn community_channel_override_list(args: &str) -> DResult {
@@ -793,10 +801,17 @@ pub fn community_channel_override_list(args: &str) -> DResult {
 pub fn community_channel_override_remove(args: &str) -> DResult {
     let data = json_parse(args)?;
     let override_id = require_str(&data, "override_id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.remove_channel_override(override_id)
+    let channel_id = svc
+        .db()
+        .get_channel_permission_override(override_id)
+        .ok()
+        .flatten()
+        .map(|o| o.channel_id);
+    svc.remove_channel_override(override_id, actor_did)
         .map_err(|e| err(e.code(), e))?;
-    ok_success()
+    ok_rekeyed(channel_id.map(|id| rekey_channel(&id)).unwrap_or_default())
 }
 
 // ── Boost Nodes ─────────────────────────────────────────────────────────────
@@ -0,0 +1,143 @@
+//! Community channel keyring dispatch handlers — member keys, channel key
+//! sync and rotation, and message encryption.
+//!
+//! `community_member_key` and `community_channel_keys` envelopes for other
+//! members come back as `relay_messages` for the caller to send.
+
+use super::dispatcher::{
+    community_service, emit_event, err, json_parse, ok_json, require_str, service_identity, DResult,
+};
+use crate::community::{ChannelKeyGrant, MemberKeyAnnouncement};
+use crate::network::relay_client::RelayMessage;
+
+/// Sync the keyrings of a community's encrypted channels after a change to
+/// who can read them.
+///
+/// Best effort: the change itself already went through, so a failure here
+/// is logged and the next sync picks it up.
+pub(super) fn rekey_community(community_id: &str) -> Vec<RelayMessage> {
+    let (Ok(identity), Ok(svc)) = (service_identity(), community_service()) else {
+        return Vec::new();
+    };
+    match svc.sync_community_keys(&identity, community_id) {
+        Ok(update) => update.relay_messages,
+        Err(e) => {
+            tracing::warn!("Channel key sync for {} failed: {}", community_id, e);
+            Vec::new()
+        }
+    }
+}
+
+/// [`rekey_community`] for the community a channel belongs to.
+pub(super) fn rekey_channel(channel_id: &str) -> Vec<RelayMessage> {
+    let Ok(svc) = community_service() else {
+        return Vec::new();
+    };
+    match svc.get_channel(channel_id) {
+        Ok(channel) => rekey_community(&channel.community_id),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Success response carrying the key envelopes a rekey produced.
+pub(super) fn ok_rekeyed(relay_messages: Vec<RelayMessage>) -> DResult {
+    ok_json(serde_json::json!({"success": true, "relay_messages": relay_messages}))
+}
+
+pub fn community_keys_announce(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let community_id = require_str(&data, "community_id")?;
+    let identity = service_identity()?;
+    let svc = community_service()?;
+    let relay_messages = svc
+        .announce_member_key(&identity, community_id)
+        .map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({ "relay_messages": relay_messages }))
+}
+
+pub fn community_keys_receive_member_key(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let announcement: MemberKeyAnnouncement = serde_json::from_value(data["announcement"].clone())
+        .map_err(|e| err(2, format!("Invalid announcement: {}", e)))?;
+    let svc = community_service()?;
+    svc.receive_member_key(&announcement)
+        .map_err(|e| err(e.code(), e))?;
+
+    // A new member key may be all a reader was waiting on
+    let relay_messages = rekey_community(&announcement.community_id);
+    ok_json(serde_json::json!({ "relay_messages": relay_messages }))
+}
+
+pub fn community_keys_sync(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let identity = service_identity()?;
+    let svc = community_service()?;
+    let update = match data["channel_id"].as_str() {
+        Some(channel_id) => svc.sync_channel_keys(&identity, channel_id),
+        None => svc.sync_community_keys(&identity, require_str(&data, "community_id")?),
+    }
+    .map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!(update))
+}
+
+pub fn community_keys_rotate(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let channel_id = require_str(&data, "channel_id")?;
+    let identity = service_identity()?;
+    let svc = community_service()?;
+    let update = svc
+        .rotate_channel_key(&identity, channel_id)
+        .map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!(update))
+}
+
+pub fn community_keys_receive(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let grant: ChannelKeyGrant = serde_json::from_value(data["grant"].clone())
+        .map_err(|e| err(2, format!("Invalid grant: {}", e)))?;
+    let identity = service_identity()?;
+    let svc = community_service()?;
+    let imported = svc
+        .receive_channel_keys(&identity, &grant)
+        .map_err(|e| err(e.code(), e))?;
+    if !imported.is_empty() {
+        emit_event(
+            "community",
+            &serde_json::json!({
+                "type": "channelKeysReceived",
+                "channel_id": grant.channel_id,
+                "key_versions": imported,
+            }),
+        );
+    }
+    ok_json(serde_json::json!({ "imported": imported }))
+}
+
+pub fn community_keys_encrypt(args: &str) -> DResult {
+    use base64::Engine as _;
+    let data = json_parse(args)?;
+    let channel_id = require_str(&data, "channel_id")?;
+    let content = require_str(&data, "content")?;
+    let identity = service_identity()?;
+    let svc = community_service()?;
+    let encrypted = svc
+        .encrypt_channel_message(&identity, channel_id, content)
+        .map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({
+        "content_encrypted_b64":
+            base64::engine::general_purpose::STANDARD.encode(&encrypted.content_encrypted),
+        "nonce": encrypted.nonce,
+        "key_version": encrypted.key_version,
+    }))
+}
+
+pub fn community_keys_decrypt(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let message_id = require_str(&data, "message_id")?;
+    let identity = service_identity()?;
+    let svc = community_service()?;
+    let content = svc
+        .decrypt_channel_message(&identity, message_id)
+        .map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({ "content": content }))
+}
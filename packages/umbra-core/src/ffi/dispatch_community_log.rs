@@ -0,0 +1,113 @@
+//! Community op log dispatch handlers — create, submit, ingest, sync.
+//!
+//! Ops travel as JSON [`SignedOp`]s; `community_op` envelopes for other
+//! members come back as `relay_messages` for the caller to send.
+
+use super::dispatch_community_keys::rekey_community;
+use super::dispatcher::{
+    community_service, emit_event, err, json_parse, ok_json, require_str, service_identity, DResult,
+};
+use crate::community::{CommunityOp, SignedOp};
+
+pub fn community_log_create(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let name = require_str(&data, "name")?;
+    let description = data["description"].as_str();
+    let nickname = data["nickname"].as_str();
+
+    let identity = service_identity()?;
+    let svc = community_service()?;
+    let result = svc
+        .create_signed_community(&identity, name, description, nickname)
+        .map_err(|e| err(e.code(), e))?;
+
+    emit_event(
+        "community",
+        &serde_json::json!({"type": "communityCreated", "community_id": result.community_id}),
+    );
+
+    ok_json(serde_json::json!({
+        "community_id": result.community_id,
+        "space_id": result.space_id,
+        "welcome_channel_id": result.welcome_channel_id,
+        "general_channel_id": result.general_channel_id,
+        "role_ids": {
+            "owner": result.role_ids.owner,
+            "admin": result.role_ids.admin,
+            "moderator": result.role_ids.moderator,
+            "member": result.role_ids.member,
+        }
+    }))
+}
+
+pub fn community_log_submit(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let community_id = require_str(&data, "community_id")?;
+    let op: CommunityOp = serde_json::from_value(data["op"].clone())
+        .map_err(|e| err(2, format!("Invalid op: {}", e)))?;
+
+    let identity = service_identity()?;
+    let svc = community_service()?;
+    let mut submitted = svc
+        .submit_op(&identity, community_id, op)
+        .map_err(|e| err(e.code(), e))?;
+
+    emit_event(
+        "community",
+        &serde_json::json!({"type": "communityLogUpdated", "community_id": community_id}),
+    );
+
+    submitted
+        .relay_messages
+        .extend(rekey_community(community_id));
+    ok_json(serde_json::json!({
+        "op": submitted.op,
+        "relay_messages": submitted.relay_messages,
+    }))
+}
+
+pub fn community_log_ingest(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let ops: Vec<SignedOp> = serde_json::from_value(data["ops"].clone())
+        .map_err(|e| err(2, format!("Invalid ops: {}", e)))?;
+
+    let community_ids: std::collections::BTreeSet<String> =
+        ops.iter().map(|op| op.community_id.clone()).collect();
+    let svc = community_service()?;
+    let report = svc.ingest_ops(ops).map_err(|e| err(e.code(), e))?;
+
+    // Applied ops can change who may read an encrypted channel
+    let mut relay_messages = Vec::new();
+    if !report.applied.is_empty() {
+        for community_id in community_ids {
+            emit_event(
+                "community",
+                &serde_json::json!({"type": "communityLogUpdated", "community_id": community_id}),
+            );
+            relay_messages.extend(rekey_community(&community_id));
+        }
+    }
+
+    let mut result = serde_json::json!(report);
+    result["relay_messages"] = serde_json::json!(relay_messages);
+    ok_json(result)
+}
+
+pub fn community_log_heads(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let community_id = require_str(&data, "community_id")?;
+    let svc = community_service()?;
+    let heads = svc.op_heads(community_id).map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!(heads))
+}
+
+pub fn community_log_ops_since(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let community_id = require_str(&data, "community_id")?;
+    let heads: Vec<String> = serde_json::from_value(data["heads"].clone()).unwrap_or_default();
+    let svc = community_service()?;
+    let ops = svc
+        .ops_since(community_id, &heads)
+        .map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!(ops))
+}
@@ -102,8 +102,9 @@ pub fn community_message_edit(args: &str) -> DResult {
 pub fn community_message_delete(args: &str) -> DResult {
     let data = json_parse(args)?;
     let id = require_str(&data, "id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.delete_message_for_everyone(id)
+    svc.delete_message_for_everyone(id, actor_did)
         .map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
@@ -185,8 +186,9 @@ pub fn community_unpin_message(args: &str) -> DResult {
     let data = json_parse(args)?;
     let channel_id = require_str(&data, "channel_id")?;
     let message_id = require_str(&data, "message_id")?;
+    let actor_did = require_str(&data, "actor_did")?;
     let svc = community_service()?;
-    svc.unpin_message(channel_id, message_id)
+    svc.unpin_message(channel_id, message_id, actor_did)
         .map_err(|e| err(e.code(), e))?;
     emit_event(
         "community",
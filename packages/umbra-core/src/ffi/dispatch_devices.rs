@@ -0,0 +1,161 @@
+//! Multi-device dispatch handlers.
+//!
+//! Linking flow: the new device calls `device_link_request` and hands its
+//! public keys to an existing device (QR code or sync), which calls
+//! `device_link`. The returned signed list is given back to the new device
+//! via `device_install`. Peers' lists arrive with their profile and are
+//! stored with `device_import_list`.
+
+use super::dispatcher::{err, json_parse, ok_json, require_str, DResult};
+use super::state::get_state;
+use crate::crypto::PublicKey;
+use crate::identity::DeviceList;
+use crate::messaging::devices;
+
+fn parse_key(data: &serde_json::Value, field: &str) -> Result<[u8; 32], (i32, String)> {
+    hex::decode(require_str(data, field)?)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| err(304, format!("Invalid {}", field)))
+}
+
+fn parse_list(data: &serde_json::Value) -> Result<DeviceList, (i32, String)> {
+    serde_json::from_value(data["list"].clone())
+        .map_err(|e| err(2, format!("Invalid device list: {}", e)))
+}
+
+fn list_json(list: &DeviceList) -> DResult {
+    ok_json(serde_json::to_value(list).unwrap_or_default())
+}
+
+/// This device's ID and public keys, to be certified by an existing device.
+///
+/// Returns: `{ device_id, signing_key, encryption_key }`
+pub fn device_link_request() -> DResult {
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let device = devices::local_device(db).map_err(|e| err(e.code(), e))?;
+    let keys = device.public_keys();
+    ok_json(serde_json::json!({
+        "device_id": device.device_id(),
+        "signing_key": hex::encode(keys.signing),
+        "encryption_key": hex::encode(keys.encryption),
+    }))
+}
+
+/// Certify another device and add it to our device list.
+///
+/// Args: `{ "signing_key": "hex", "encryption_key": "hex", "name": "Laptop" }`
+/// Returns: the new signed device list
+pub fn device_link(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let signing = parse_key(&data, "signing_key")?;
+    let encryption = parse_key(&data, "encryption_key")?;
+    let name = require_str(&data, "name")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let list = devices::link_device(
+        identity,
+        db,
+        PublicKey::from_bytes(signing, encryption),
+        name,
+    )
+    .map_err(|e| err(e.code(), e))?;
+    list_json(&list)
+}
+
+/// Revoke a device. Its ID can never be linked again.
+///
+/// Args: `{ "device_id": "..." }`
+/// Returns: the new signed device list
+pub fn device_revoke(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let device_id = require_str(&data, "device_id")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let list = devices::revoke_device(identity, db, device_id).map_err(|e| err(e.code(), e))?;
+    list_json(&list)
+}
+
+/// Our signed device list (created with this device on first call).
+pub fn device_list() -> DResult {
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let list = devices::own_device_list(identity, db).map_err(|e| err(e.code(), e))?;
+    list_json(&list)
+}
+
+/// Install our signed device list on a newly linked device.
+///
+/// Args: `{ "list": { ...DeviceList } }`
+pub fn device_install(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let list = parse_list(&data)?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    devices::install_device_list(identity, db, &list).map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({ "success": true, "version": list.version }))
+}
+
+/// Store a peer's device list from their profile.
+///
+/// Args: `{ "list": { ...DeviceList } }`
+/// Returns: `{ "updated": bool }` (false if we already hold a newer version)
+pub fn device_import_list(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let list = parse_list(&data)?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let updated = devices::import_device_list(db, &list).map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({ "updated": updated }))
+}
@@ -4,7 +4,7 @@ use super::dispatcher::{
     deterministic_conversation_id, err, json_parse, ok_json, ok_success, require_str, DResult,
 };
 use super::state::get_state;
-use crate::friends::FriendRequest;
+use crate::friends::{Friend, FriendRequest};
 use crate::storage::FriendRequestRecord;
 
 pub fn friends_send_request(args: &str) -> DResult {
@@ -336,6 +336,7 @@ pub fn friends_list() -> DResult {
                 "did": f.did, "display_name": f.display_name,
                 "signing_key": f.signing_key, "encryption_key": f.encryption_key,
                 "status": f.status, "avatar": f.avatar, "created_at": f.created_at,
+                "verified": f.verified, "verified_at": f.verified_at,
             })
         })
         .collect();
@@ -437,11 +438,22 @@ pub fn friends_get_blocked() -> DResult {
     Ok(serde_json::to_string(&arr).unwrap_or_default())
 }
 
+/// Apply a friend's encryption key rotation.
+///
+/// Args: `{ "from_did", "rotation": { ...KeyRotation } }`. The signed
+/// timestamp must be newer than the last applied rotation, so a replayed or
+/// stale announcement can't roll the key back. The old key stays usable for
+/// the grace window.
 pub fn friends_update_encryption_key(args: &str) -> DResult {
     let data = json_parse(args)?;
     let from_did = require_str(&data, "from_did")?;
-    let new_key_hex = require_str(&data, "new_encryption_key")?;
-    let signature_hex = require_str(&data, "signature")?;
+    let rotation: crate::identity::KeyRotation = serde_json::from_value(
+        data.get("rotation").cloned().ok_or_else(|| err(2, "Missing rotation"))?,
+    )
+    .map_err(|e| err(2, format!("Invalid rotation: {}", e)))?;
+    if rotation.did != from_did {
+        return Err(err(303, "Rotation is for another identity"));
+    }
 
     let state = get_state().map_err(|e| err(100, e))?;
     let state = state.read();
@@ -456,28 +468,126 @@ pub fn friends_update_encryption_key(args: &str) -> DResult {
         .map_err(|e| err(e.code(), e))?
         .ok_or_else(|| err(601, "Friend not found"))?;
 
-    // Decode and validate new key
-    let new_key_bytes = hex::decode(new_key_hex).map_err(|e| err(304, format!("Invalid key: {}", e)))?;
-    if new_key_bytes.len() != 32 {
-        return Err(err(304, "Key must be 32 bytes"));
+    let signing_key: [u8; 32] = hex::decode(&friend.signing_key)
+        .ok()
+        .and_then(|k| k.try_into().ok())
+        .ok_or_else(|| err(304, "Invalid signing key"))?;
+    let updated = crate::friends::apply_key_rotation(db, &signing_key, &rotation)
+        .map_err(|e| err(e.code(), e))?;
+    if updated && friend.verified {
+        emit_verified_key_changed(from_did, &hex::encode(rotation.new_encryption_key));
     }
 
-    // Verify signature using friend's (unchanged) signing key
-    let sk_bytes = hex::decode(&friend.signing_key).map_err(|e| err(304, format!("Invalid signing key: {}", e)))?;
-    let mut sk = [0u8; 32];
-    sk.copy_from_slice(&sk_bytes);
+    ok_json(serde_json::json!({"updated": updated}))
+}
 
-    let sig_bytes = hex::decode(signature_hex).map_err(|e| err(304, format!("Invalid signature: {}", e)))?;
-    let signature = crate::crypto::Signature::from_slice(&sig_bytes)
-        .map_err(|_| err(303, "Invalid signature format"))?;
-    crate::crypto::verify(&sk, &new_key_bytes, &signature)
-        .map_err(|_| err(303, "Signature verification failed"))?;
+/// A verified friend's keys changed, so their verification was cleared.
+/// The UI should warn and ask the user to compare safety numbers again.
+fn emit_verified_key_changed(did: &str, new_encryption_key: &str) {
+    super::dispatcher::emit_event(
+        "friend",
+        &serde_json::json!({
+            "type": "verifiedKeyChanged",
+            "did": did,
+            "newEncryptionKey": new_encryption_key,
+        }),
+    );
+}
+
+fn load_friend(db: &crate::storage::Database, did: &str) -> Result<Friend, (i32, String)> {
+    let record = db
+        .get_friend(did)
+        .map_err(|e| err(e.code(), e))?
+        .ok_or_else(|| err(601, "Friend not found"))?;
+    Friend::from_record(record).map_err(|e| err(e.code(), e))
+}
+
+/// Safety number for out-of-band verification of a friend.
+///
+/// Args: `{ "did" }`
+/// Returns: `{ safety_number, display, qr_payload, verified, verified_at }`
+pub fn friends_safety_number(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let did = require_str(&data, "did")?;
 
-    // Update friend's encryption key
-    let mut new_enc = [0u8; 32];
-    new_enc.copy_from_slice(&new_key_bytes);
-    db.update_friend_encryption_key(from_did, &new_enc)
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let friend = load_friend(db, did)?;
+    let number = friend.safety_number(identity);
+    ok_json(serde_json::json!({
+        "safety_number": number.digits(),
+        "display": number.display(),
+        "qr_payload": number.qr_payload(),
+        "verified": friend.verified,
+        "verified_at": friend.verified_at,
+    }))
+}
+
+/// Mark a friend verified.
+///
+/// Args: `{ "did", "qr_payload"? }`. With a scanned `qr_payload` it must
+/// match our safety number; without one the user compared the digits.
+pub fn friends_verify(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let did = require_str(&data, "did")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let friend = load_friend(db, did)?;
+    if let Some(payload) = data["qr_payload"].as_str() {
+        let matches = friend
+            .safety_number(identity)
+            .verify_qr_payload(payload)
+            .map_err(|e| err(e.code(), e))?;
+        if !matches {
+            return Err(err(303, "Safety number does not match"));
+        }
+    }
+    db.set_friend_verified(did, true)
         .map_err(|e| err(e.code(), e))?;
 
-    ok_json(serde_json::json!({"updated": true}))
+    super::dispatcher::emit_event(
+        "friend",
+        &serde_json::json!({"type": "friendVerified", "did": did}),
+    );
+
+    ok_json(serde_json::json!({ "verified": true }))
+}
+
+/// Clear a friend's verification.
+///
+/// Args: `{ "did" }`
+pub fn friends_unverify(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let did = require_str(&data, "did")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let updated = db
+        .set_friend_verified(did, false)
+        .map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({ "updated": updated }))
 }
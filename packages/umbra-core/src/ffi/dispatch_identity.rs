@@ -1,9 +1,11 @@
 //! Identity dispatch handlers.
 
+use super::dispatch_secure_store::secure_database;
 use super::dispatcher::{err, json_parse, ok_json, ok_success, require_str, DResult};
 use super::state::get_state;
 use base64::Engine as _;
 use crate::identity::{Identity, ProfileUpdate, RecoveryPhrase};
+use crate::storage::{restore_rotated_encryption_key, store_rotated_encryption_key};
 use sha2::Digest;
 
 pub fn identity_create(args: &str) -> DResult {
@@ -24,6 +26,10 @@ pub fn identity_create(args: &str) -> DResult {
             let mut s = state.write();
             s.identity = Some(identity);
             s.backup_seed = Some(seed);
+            // Don't lose the phrase over this; `database_status` reports it
+            if let Err(e) = secure_database(&s) {
+                tracing::error!("Failed to key database: {}", e);
+            }
 
             ok_json(serde_json::json!({ "did": did, "recovery_phrase": phrase }))
         }
@@ -46,11 +52,17 @@ pub fn identity_restore(args: &str) -> DResult {
         .map_err(|e| err(203, format!("Seed derivation failed: {}", e)))?;
 
     match Identity::from_recovery_phrase(&recovery, name.to_string()) {
-        Ok(identity) => {
+        Ok(mut identity) => {
             let did = identity.did_string();
             let mut s = state.write();
+            // A rotated encryption key can't be derived from the phrase
+            if let Some(store) = &s.secure_store {
+                restore_rotated_encryption_key(store, &mut identity)
+                    .map_err(|e| err(e.code(), e))?;
+            }
             s.identity = Some(identity);
             s.backup_seed = Some(seed);
+            secure_database(&s).map_err(|e| err(e.code(), e))?;
             Ok(did)
         }
         Err(e) => Err(err(e.code(), e)),
@@ -74,11 +86,20 @@ pub fn identity_get_profile() -> DResult {
         .as_ref()
         .ok_or_else(|| err(200, "No identity loaded"))?;
     let p = id.profile();
+    // The signed device list is published with the profile once the
+    // database is open
+    let device_list = state
+        .database
+        .as_ref()
+        .map(|db| crate::messaging::devices::own_device_list(id, db))
+        .transpose()
+        .map_err(|e| err(e.code(), e))?;
     ok_json(serde_json::json!({
         "did": id.did_string(), "display_name": p.display_name,
         "status": p.status, "avatar": p.avatar,
         "signing_key": hex::encode(&id.keypair().signing.public_bytes()),
         "encryption_key": hex::encode(&id.keypair().encryption.public_bytes()),
+        "device_list": device_list,
     }))
 }
 
@@ -122,7 +143,9 @@ pub fn identity_update_profile(args: &str) -> DResult {
 
 pub fn identity_rotate_encryption_key() -> DResult {
     let state = get_state().map_err(|e| err(100, e))?;
-    let mut state = state.write();
+    let mut guard = state.write();
+    // Reborrow so identity and secure_store can be borrowed separately
+    let state = &mut *guard;
 
     // Clone the database Arc before taking a mutable borrow on identity
     let database = state
@@ -137,14 +160,19 @@ pub fn identity_rotate_encryption_key() -> DResult {
     let our_did = identity.did_string();
 
     // Generate new encryption key (random, not mnemonic-derived)
-    let new_pub_key = identity
+    let rotation = identity
         .rotate_encryption_key()
         .map_err(|e| err(300, format!("Key rotation failed: {}", e)))?;
-    let new_pub_hex = hex::encode(new_pub_key);
-
-    // Sign the new public key so recipients can verify authenticity
-    let signature = crate::crypto::sign(&identity.keypair().signing, &new_pub_key);
-    let signature_hex = hex::encode(signature.as_bytes());
+    let new_pub_hex = hex::encode(rotation.new_encryption_key);
+
+    // The new key can't be re-derived from the phrase, so persist it
+    if let Some(store) = &state.secure_store {
+        store_rotated_encryption_key(store, identity).map_err(|e| err(e.code(), e))?;
+        // The database key is derived from the encryption key
+        secure_database(state).map_err(|e| err(e.code(), e))?;
+    } else {
+        tracing::warn!("No SecureStore; rotated encryption key will not survive a restore");
+    }
 
     // Build relay envelopes for all friends
     let friends = database
@@ -161,8 +189,8 @@ pub fn identity_rotate_encryption_key() -> DResult {
             "payload": {
                 "fromDid": our_did,
                 "newEncryptionKey": new_pub_hex,
-                "signature": signature_hex,
                 "timestamp": timestamp,
+                "rotation": rotation,
             }
         });
         relay_messages.push(serde_json::json!({
@@ -1,9 +1,18 @@
 //! Messaging (DM) dispatch handlers.
 
+use std::sync::Arc;
+
 use super::dispatcher::{
     deterministic_conversation_id, err, json_parse, ok_json, ok_success, require_str, DResult,
 };
-use super::state::get_state;
+use super::state::{get_state, FfiState};
+use crate::crypto::PrekeyBundle;
+use crate::friends::Friend;
+use crate::messaging::disappearing::{self, DisappearingTimer};
+use crate::messaging::sealed::{self, SealedEnvelope};
+use crate::messaging::{Message, MessageContent, MessageEnvelope, MessagingService};
+use crate::network::relay_client::RelayClientMessage;
+use crate::storage::Database;
 use base64::Engine as _;
 
 /// Convert hex-encoded ciphertext to base64, matching the WASM path.
@@ -15,6 +24,49 @@ fn hex_to_base64_safe(hex_str: &str) -> String {
     }
 }
 
+/// The messaging service for the loaded identity.
+fn messaging_service(state: &FfiState) -> Result<Arc<MessagingService>, (i32, String)> {
+    if let Some(messaging) = &state.messaging {
+        return Ok(messaging.clone());
+    }
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+    let identity = identity.clone_for_service().map_err(|e| err(e.code(), e))?;
+    Ok(Arc::new(MessagingService::new(
+        Arc::new(identity),
+        db.clone(),
+    )))
+}
+
+fn load_friend(db: &Database, did: &str) -> Result<Friend, (i32, String)> {
+    let record = db
+        .get_friend(did)
+        .map_err(|e| err(e.code(), e))?
+        .ok_or_else(|| err(503, "Friend not found"))?;
+    Friend::from_record(record).map_err(|e| err(e.code(), e))
+}
+
+/// A version 2 relay envelope carrying a signed `MessageEnvelope`, plus any
+/// `extra` payload fields (e.g. `threadId`).
+fn envelope_payload(
+    kind: &str,
+    envelope: &MessageEnvelope,
+    mut extra: serde_json::Value,
+) -> serde_json::Value {
+    extra["messageEnvelope"] = serde_json::json!(envelope);
+    serde_json::json!({ "envelope": kind, "version": 2, "payload": extra })
+}
+
+fn relay_message(to_did: &str, envelope: &serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ "to_did": to_did, "payload": envelope.to_string() })
+}
+
 pub fn messaging_get_conversations() -> DResult {
     let state = get_state().map_err(|e| err(100, e))?;
     let state = state.read();
@@ -30,6 +82,7 @@ pub fn messaging_get_conversations() -> DResult {
                 "id": c.id, "friend_did": c.friend_did, "conv_type": c.conv_type,
                 "group_id": c.group_id, "created_at": c.created_at,
                 "last_message_at": c.last_message_at, "unread_count": c.unread_count,
+                "disappearing_timer": c.disappearing_timer,
             })
         })
         .collect();
@@ -84,6 +137,7 @@ pub fn messaging_get_messages(args: &str) -> DResult {
                 "content_encrypted": hex_to_base64_safe(&m.content_encrypted),
                 "nonce": m.nonce, "timestamp": m.timestamp,
                 "delivered": m.delivered, "read": m.read,
+                "expires_at": m.expires_at,
             })
         })
         .collect();
@@ -105,9 +159,7 @@ pub fn messaging_send(args: &str) -> DResult {
         .as_ref()
         .ok_or_else(|| err(400, "Database not initialized"))?;
 
-    let msg_id = uuid::Uuid::new_v4().to_string();
     let sender_did = identity.did_string();
-    let timestamp = crate::time::now_timestamp_millis();
 
     let conv = db
         .get_conversation(conv_id)
@@ -117,50 +169,47 @@ pub fn messaging_send(args: &str) -> DResult {
         .friend_did
         .as_deref()
         .ok_or_else(|| err(700, "No friend_did on conversation"))?;
+    let friend = load_friend(db, friend_did)?;
 
-    let friend_rec = db
-        .get_friend(friend_did)
-        .map_err(|e| err(e.code(), e))?
-        .ok_or_else(|| err(503, "Friend not found"))?;
-    let friend_enc_bytes = hex::decode(&friend_rec.encryption_key)
-        .map_err(|e| err(704, format!("Invalid key: {}", e)))?;
-    let mut friend_enc_key = [0u8; 32];
-    if friend_enc_bytes.len() != 32 {
-        return Err(err(704, "Key must be 32 bytes"));
-    }
-    friend_enc_key.copy_from_slice(&friend_enc_bytes);
-
-    let aad = format!("{}{}{}", sender_did, friend_did, timestamp);
-    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
-        &identity.keypair().encryption,
-        &friend_enc_key,
-        conv_id.as_bytes(),
-        content.as_bytes(),
-        aad.as_bytes(),
-    )
-    .map_err(|e| err(704, format!("Encryption failed: {}", e)))?;
+    let mut message = Message::new(
+        conv_id.to_string(),
+        sender_did.clone(),
+        friend_did.to_string(),
+        MessageContent::text(content),
+    );
+    message.expires_in = conv.disappearing_timer;
+    let (msg_id, timestamp) = (message.id.clone(), message.timestamp);
+    let outgoing = messaging_service(&state)?
+        .encrypt_text(&message, &friend)
+        .map_err(|e| err(704, format!("Encryption failed: {}", e)))?;
 
     db.store_message(
         &msg_id,
         conv_id,
         &sender_did,
-        &ciphertext,
-        &nonce.0,
+        &outgoing.ciphertext,
+        &outgoing.nonce.0,
         timestamp,
     )
     .map_err(|e| err(e.code(), e))?;
-
-    let ct_b64 = base64::engine::general_purpose::STANDARD.encode(&ciphertext);
-    let nonce_hex = hex::encode(&nonce.0);
-
-    let relay_envelope = serde_json::json!({
-        "envelope": "chat_message", "version": 1,
-        "payload": {
-            "messageId": msg_id, "conversationId": conv_id,
-            "senderDid": sender_did, "contentEncrypted": ct_b64,
-            "nonce": nonce_hex, "timestamp": timestamp,
-        }
-    });
+    let _ = db.index_message(&msg_id, content);
+
+    let ct_b64 = base64::engine::general_purpose::STANDARD.encode(&outgoing.ciphertext);
+    let nonce_hex = hex::encode(outgoing.nonce.0);
+
+    let relay_envelope = match &outgoing.envelope {
+        Some(envelope) => envelope_payload("chat_message", envelope, serde_json::json!({})),
+        // Legacy fallback: older peers can only read the static-key payload
+        None => serde_json::json!({
+            "envelope": "chat_message", "version": 1,
+            "payload": {
+                "messageId": msg_id, "conversationId": conv_id,
+                "senderDid": sender_did, "contentEncrypted": ct_b64,
+                "nonce": nonce_hex, "timestamp": timestamp,
+                "expiresIn": conv.disappearing_timer,
+            }
+        }),
+    };
 
     super::dispatcher::emit_event(
         "message",
@@ -181,7 +230,7 @@ pub fn messaging_send(args: &str) -> DResult {
         "friend_did": friend_did, "timestamp": timestamp,
         "delivered": false, "read": false,
         "content_encrypted": ct_b64, "nonce": nonce_hex,
-        "relay_messages": [{ "to_did": friend_did, "payload": relay_envelope.to_string() }],
+        "relay_messages": [relay_message(friend_did, &relay_envelope)],
     }))
 }
 
@@ -281,6 +330,12 @@ pub fn messaging_decrypt(args: &str) -> DResult {
     .map_err(|e| err(704, format!("KEY_MISMATCH: Decryption failed: {}", e)))?;
 
     let text = String::from_utf8_lossy(&plaintext).to_string();
+
+    // Stored DMs are ciphertext only, so this is where history gets indexed
+    if let Ok(Some(msg_id)) = db.find_message_id(conv_id, sender_did, timestamp) {
+        let _ = db.index_message(&msg_id, &text);
+    }
+
     ok_json(serde_json::json!(text))
 }
 
@@ -301,19 +356,24 @@ pub fn messaging_store_incoming(args: &str) -> DResult {
     let timestamp = data["timestamp"]
         .as_i64()
         .ok_or_else(|| err(2, "Missing timestamp"))?;
+    // The sender's disappearing timer, from the envelope's `expiresIn`
+    let expires_at = data["expires_in"]
+        .as_i64()
+        .map(|secs| timestamp + secs * 1000);
 
     let ciphertext = base64::engine::general_purpose::STANDARD
         .decode(ct_b64)
         .map_err(|e| err(704, format!("Invalid base64: {}", e)))?;
     let nonce_bytes = hex::decode(nonce_hex).map_err(|e| err(704, e))?;
 
-    db.store_message(
+    db.store_message_with_expiry(
         msg_id,
         conv_id,
         sender_did,
         &ciphertext,
         &nonce_bytes,
         timestamp,
+        expires_at,
     )
     .map_err(|e| err(e.code(), e))?;
 
@@ -405,41 +465,46 @@ pub fn messaging_edit(args: &str) -> DResult {
         .friend_did
         .as_deref()
         .ok_or_else(|| err(700, "No friend_did"))?;
+    let friend = load_friend(db, friend_did)?;
+
+    // The edit reuses the message ID, which the envelope signature covers,
+    // so the recipient can tell it from a new message
+    let mut edit = Message::new(
+        message.conversation_id.clone(),
+        our_did,
+        friend_did.to_string(),
+        MessageContent::text(new_text),
+    );
+    edit.id = msg_id.to_string();
+    let edited_at = edit.timestamp;
+    let outgoing = messaging_service(&state)?
+        .encrypt_text(&edit, &friend)
+        .map_err(|e| err(704, format!("Encryption failed: {}", e)))?;
 
-    let friend_rec = db
-        .get_friend(friend_did)
-        .map_err(|e| err(e.code(), e))?
-        .ok_or_else(|| err(503, "Friend not found"))?;
-    let fek_bytes = hex::decode(&friend_rec.encryption_key).map_err(|e| err(704, e))?;
-    let mut fek = [0u8; 32];
-    if fek_bytes.len() != 32 {
-        return Err(err(704, "Key must be 32 bytes"));
-    }
-    fek.copy_from_slice(&fek_bytes);
-
-    let edited_at = crate::time::now_timestamp_millis();
-    let aad = format!("{}{}{}", our_did, friend_did, edited_at);
-    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
-        &identity.keypair().encryption,
-        &fek,
-        message.conversation_id.as_bytes(),
-        new_text.as_bytes(),
-        aad.as_bytes(),
-    )
-    .map_err(|e| err(704, format!("Encryption failed: {}", e)))?;
-
-    db.edit_message(msg_id, &ciphertext, &nonce.0, edited_at)
+    db.edit_message(msg_id, &outgoing.ciphertext, &outgoing.nonce.0, edited_at)
         .map_err(|e| err(e.code(), e))?;
+    let _ = db.index_message(msg_id, new_text);
 
     super::dispatcher::emit_event(
         "message",
         &serde_json::json!({"type": "messageEdited", "message_id": msg_id, "edited_at": edited_at}),
     );
 
+    // Older peers get no relayed edit from here, as before
+    let relay_messages: Vec<serde_json::Value> = outgoing
+        .envelope
+        .iter()
+        .map(|envelope| {
+            let payload = envelope_payload("message_edit", envelope, serde_json::json!({}));
+            relay_message(friend_did, &payload)
+        })
+        .collect();
+
     ok_json(serde_json::json!({
         "message_id": msg_id, "edited_at": edited_at,
-        "content_encrypted": base64::engine::general_purpose::STANDARD.encode(&ciphertext),
-        "nonce": hex::encode(&nonce.0),
+        "content_encrypted": base64::engine::general_purpose::STANDARD.encode(&outgoing.ciphertext),
+        "nonce": hex::encode(outgoing.nonce.0),
+        "relay_messages": relay_messages,
     }))
 }
 
@@ -641,9 +706,7 @@ pub fn messaging_reply_thread(args: &str) -> DResult {
         .as_ref()
         .ok_or_else(|| err(400, "Database not initialized"))?;
 
-    let msg_id = uuid::Uuid::new_v4().to_string();
     let sender_did = identity.did_string();
-    let timestamp = crate::time::now_timestamp_millis();
 
     let conv = db
         .get_conversation(conv_id)
@@ -653,56 +716,56 @@ pub fn messaging_reply_thread(args: &str) -> DResult {
         .friend_did
         .as_deref()
         .ok_or_else(|| err(700, "No friend_did"))?;
+    let friend = load_friend(db, friend_did)?;
 
-    let friend_rec = db
-        .get_friend(friend_did)
-        .map_err(|e| err(e.code(), e))?
-        .ok_or_else(|| err(503, "Friend not found"))?;
-    let fek_bytes = hex::decode(&friend_rec.encryption_key).map_err(|e| err(704, e))?;
-    let mut fek = [0u8; 32];
-    if fek_bytes.len() != 32 {
-        return Err(err(704, "Key must be 32 bytes"));
-    }
-    fek.copy_from_slice(&fek_bytes);
-
-    let aad = format!("{}{}{}", sender_did, friend_did, timestamp);
-    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
-        &identity.keypair().encryption,
-        &fek,
-        conv_id.as_bytes(),
-        content.as_bytes(),
-        aad.as_bytes(),
-    )
-    .map_err(|e| err(704, e))?;
+    let message = Message::new(
+        conv_id.to_string(),
+        sender_did.clone(),
+        friend_did.to_string(),
+        MessageContent::text(content),
+    );
+    let (msg_id, timestamp) = (message.id.clone(), message.timestamp);
+    let outgoing = messaging_service(&state)?
+        .encrypt_text(&message, &friend)
+        .map_err(|e| err(704, e))?;
 
     db.store_message(
         &msg_id,
         conv_id,
         &sender_did,
-        &ciphertext,
-        &nonce.0,
+        &outgoing.ciphertext,
+        &outgoing.nonce.0,
         timestamp,
     )
     .map_err(|e| err(e.code(), e))?;
     db.set_message_reply(&msg_id, thread_id)
         .map_err(|e| err(e.code(), e))?;
-
-    let ct_b64 = base64::engine::general_purpose::STANDARD.encode(&ciphertext);
-    let nonce_hex = hex::encode(&nonce.0);
-    let relay_envelope = serde_json::json!({
-        "envelope": "chat_message", "version": 1,
-        "payload": {
-            "messageId": msg_id, "conversationId": conv_id,
-            "senderDid": sender_did, "contentEncrypted": ct_b64,
-            "nonce": nonce_hex, "timestamp": timestamp, "threadId": thread_id,
-        }
-    });
+    let _ = db.index_message(&msg_id, content);
+
+    let ct_b64 = base64::engine::general_purpose::STANDARD.encode(&outgoing.ciphertext);
+    let nonce_hex = hex::encode(outgoing.nonce.0);
+    let relay_envelope = match &outgoing.envelope {
+        Some(envelope) => envelope_payload(
+            "chat_message",
+            envelope,
+            serde_json::json!({ "threadId": thread_id }),
+        ),
+        // Legacy fallback: older peers can only read the static-key payload
+        None => serde_json::json!({
+            "envelope": "chat_message", "version": 1,
+            "payload": {
+                "messageId": msg_id, "conversationId": conv_id,
+                "senderDid": sender_did, "contentEncrypted": ct_b64,
+                "nonce": nonce_hex, "timestamp": timestamp, "threadId": thread_id,
+            }
+        }),
+    };
 
     ok_json(serde_json::json!({
         "id": msg_id, "conversation_id": conv_id, "sender_did": sender_did,
         "friend_did": friend_did, "timestamp": timestamp, "thread_id": thread_id,
         "content_encrypted": ct_b64, "nonce": nonce_hex,
-        "relay_messages": [{ "to_did": friend_did, "payload": relay_envelope.to_string() }],
+        "relay_messages": [relay_message(friend_did, &relay_envelope)],
     }))
 }
 
@@ -763,3 +826,425 @@ pub fn messaging_update_status(args: &str) -> DResult {
 
     ok_success()
 }
+
+/// Change a DM conversation's disappearing-message timer.
+///
+/// Args: `{ "conversation_id", "duration_secs" }` (null or 0 turns it off)
+/// Returns: `{ timer, relay_messages }`
+pub fn messaging_set_disappearing_timer(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let conv_id = require_str(&data, "conversation_id")?;
+    let duration = data["duration_secs"]
+        .as_u64()
+        .filter(|secs| *secs > 0)
+        .map(|secs| u32::try_from(secs).map_err(|_| err(2, "duration_secs is too large")))
+        .transpose()?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let conv = db
+        .get_conversation(conv_id)
+        .map_err(|e| err(e.code(), e))?
+        .ok_or_else(|| err(700, "Conversation not found"))?;
+    let friend_did = conv
+        .friend_did
+        .as_deref()
+        .ok_or_else(|| err(700, "No friend_did on conversation"))?;
+
+    let timer =
+        DisappearingTimer::new(identity, conv_id, duration).map_err(|e| err(e.code(), e))?;
+    disappearing::apply_timer(db, &timer).map_err(|e| err(e.code(), e))?;
+    emit_timer_changed(&timer);
+
+    let relay_envelope = serde_json::json!({
+        "envelope": "disappearing_timer", "version": 1,
+        "payload": timer,
+    });
+    ok_json(serde_json::json!({
+        "timer": timer,
+        "relay_messages": [{ "to_did": friend_did, "payload": relay_envelope.to_string() }],
+    }))
+}
+
+/// Apply a disappearing-timer change received from a friend.
+///
+/// Args: the `payload` of a `disappearing_timer` envelope
+/// Returns: `{ applied }` (false if a newer setting was already stored)
+pub fn messaging_receive_disappearing_timer(args: &str) -> DResult {
+    let timer: DisappearingTimer =
+        serde_json::from_str(args).map_err(|e| err(2, format!("Invalid timer: {}", e)))?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    // Only the friend on the other end of the conversation may change it
+    let conv = db
+        .get_conversation(&timer.conversation_id)
+        .map_err(|e| err(e.code(), e))?
+        .ok_or_else(|| err(700, "Conversation not found"))?;
+    if conv.friend_did.as_deref() != Some(timer.set_by.as_str()) {
+        return Err(err(303, "Timer was not set by this conversation's friend"));
+    }
+    let friend = db
+        .get_friend(&timer.set_by)
+        .map_err(|e| err(e.code(), e))?
+        .ok_or_else(|| err(601, "Friend not found"))?;
+    let friend = crate::friends::Friend::from_record(friend).map_err(|e| err(e.code(), e))?;
+    timer
+        .verify(&friend.signing_public_key)
+        .map_err(|e| err(e.code(), e))?;
+
+    let applied = disappearing::apply_timer(db, &timer).map_err(|e| err(e.code(), e))?;
+    if applied {
+        emit_timer_changed(&timer);
+    }
+    ok_json(serde_json::json!({ "applied": applied }))
+}
+
+/// Delete expired disappearing messages now, rather than waiting for the
+/// background sweeper.
+///
+/// Returns: `{ messages, files }` deleted
+pub fn messaging_sweep_expired() -> DResult {
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let sweep = disappearing::sweep_expired(db).map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({ "messages": sweep.messages, "files": sweep.files }))
+}
+
+fn emit_timer_changed(timer: &DisappearingTimer) {
+    super::dispatcher::emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "disappearingTimerChanged",
+            "conversation_id": timer.conversation_id,
+            "duration_secs": timer.duration(),
+            "set_by": timer.set_by,
+            "set_at": timer.set_at,
+        }),
+    );
+}
+
+// ── Sealed sender ───────────────────────────────────────────────────────
+
+/// Seal a relay payload for a friend, so the relay can't see it is from us.
+///
+/// Args: `{ to_did, payload }`
+/// Returns: `{ relay_message }`, a `send_sealed` message. Send it on a
+/// relay connection that hasn't registered our DID.
+pub fn messaging_seal(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let to_did = require_str(&data, "to_did")?;
+    let payload = require_str(&data, "payload")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let friend = db
+        .get_friend(to_did)
+        .map_err(|e| err(e.code(), e))?
+        .ok_or_else(|| err(601, "Friend not found"))?;
+    let friend = Friend::from_record(friend).map_err(|e| err(e.code(), e))?;
+
+    let sealed = SealedEnvelope::seal(identity, &friend, payload).map_err(|e| err(e.code(), e))?;
+    let msg = RelayClientMessage::SendSealed {
+        to_did: to_did.to_string(),
+        access_key: sealed::access_key_for(identity, &friend).map_err(|e| err(e.code(), e))?,
+        payload: sealed.to_json().map_err(|e| err(e.code(), e))?,
+    };
+    ok_json(serde_json::json!({
+        "relay_message": serde_json::to_string(&msg).map_err(|e| err(700, e))?,
+    }))
+}
+
+/// Open a sealed-sender payload and verify it against the sending friend.
+///
+/// Args: `{ payload }`, from a `sealed_message` (or an offline message with
+/// an empty `from_did`)
+/// Returns: `{ sender_did, payload, timestamp }`
+pub fn messaging_open_sealed(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let payload = require_str(&data, "payload")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let content = SealedEnvelope::from_json(payload)
+        .and_then(|sealed| sealed.open(identity))
+        .map_err(|e| err(e.code(), e))?;
+    let friend = db
+        .get_friend(&content.sender_did)
+        .map_err(|e| err(e.code(), e))?
+        .ok_or_else(|| err(601, "Sealed message is not from a friend"))?;
+    let friend = Friend::from_record(friend).map_err(|e| err(e.code(), e))?;
+    content.verify(&friend).map_err(|e| err(e.code(), e))?;
+
+    ok_json(serde_json::json!({
+        "sender_did": content.sender_did,
+        "payload": content.payload,
+        "timestamp": content.timestamp,
+    }))
+}
+
+/// Build the relay message registering our friends' sealed-sender access
+/// keys. Send it after authenticating, and again when friends change.
+///
+/// Returns: `{ relay_message, count }`
+pub fn messaging_sealed_access() -> DResult {
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let friends = db
+        .get_all_friends()
+        .map_err(|e| err(e.code(), e))?
+        .into_iter()
+        .map(Friend::from_record)
+        .collect::<crate::error::Result<Vec<_>>>()
+        .map_err(|e| err(e.code(), e))?;
+    let access_hashes = sealed::access_hashes(identity, &friends).map_err(|e| err(e.code(), e))?;
+
+    let count = access_hashes.len();
+    let msg = RelayClientMessage::RegisterSealedAccess { access_hashes };
+    ok_json(serde_json::json!({
+        "relay_message": serde_json::to_string(&msg).map_err(|e| err(700, e))?,
+        "count": count,
+    }))
+}
+
+// ── Ratchet sessions ────────────────────────────────────────────────────
+
+/// Receive a version 2 `chat_message` or `message_edit` relay payload.
+///
+/// Args: `{ envelope, thread_id? }`, the payload's `messageEnvelope` and
+/// `threadId`
+/// Returns: `{ message_id, conversation_id, edited }`. The stored copy is
+/// read back with `messaging_decrypt`, like any other DM.
+pub fn messaging_receive_envelope(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let envelope: MessageEnvelope = serde_json::from_value(data["envelope"].clone())
+        .map_err(|e| err(2, format!("Invalid envelope: {}", e)))?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let friend = load_friend(db, &envelope.sender_did)?;
+    let svc = messaging_service(&state)?;
+    let message = svc
+        .open(&envelope, &friend)
+        .map_err(|e| err(704, format!("Decryption failed: {}", e)))?;
+    let MessageContent::Text(text) = &message.content else {
+        return Err(err(704, "Unsupported envelope content"));
+    };
+    let conv_id = deterministic_conversation_id(&identity.did_string(), &friend.did);
+    if message.conversation_id != conv_id {
+        return Err(err(700, "Envelope is for another conversation"));
+    }
+
+    let (nonce, ciphertext) = svc
+        .encrypt_for_storage(
+            &friend,
+            &conv_id,
+            &message.sender_did,
+            message.timestamp,
+            text,
+        )
+        .map_err(|e| err(e.code(), e))?;
+
+    // An edit comes back under the ID of the message it replaces
+    let edited = match db.get_message(&message.id).map_err(|e| err(e.code(), e))? {
+        Some(existing)
+            if existing.sender_did == message.sender_did && existing.conversation_id == conv_id =>
+        {
+            db.edit_message(&message.id, &ciphertext, &nonce.0, message.timestamp)
+                .map_err(|e| err(e.code(), e))?;
+            true
+        }
+        Some(_) => return Err(err(704, "Message ID already in use")),
+        None => {
+            db.store_message_with_expiry(
+                &message.id,
+                &conv_id,
+                &message.sender_did,
+                &ciphertext,
+                &nonce.0,
+                message.timestamp,
+                message
+                    .expires_in
+                    .map(|secs| message.timestamp + secs as i64 * 1000),
+            )
+            .map_err(|e| err(e.code(), e))?;
+            if let Some(thread_id) = data["thread_id"].as_str() {
+                db.set_message_reply(&message.id, thread_id)
+                    .map_err(|e| err(e.code(), e))?;
+            }
+            false
+        }
+    };
+    let _ = db.index_message(&message.id, text);
+
+    let event = if edited {
+        serde_json::json!({
+            "type": "messageEdited", "message_id": message.id,
+            "edited_at": message.timestamp,
+        })
+    } else {
+        serde_json::json!({
+            "type": "messageReceived",
+            "message": {
+                "id": message.id, "conversation_id": conv_id,
+                "sender_did": message.sender_did,
+                "content_encrypted": base64::engine::general_purpose::STANDARD.encode(&ciphertext),
+                "nonce": hex::encode(nonce.0),
+                "status": "delivered",
+                "timestamp": message.timestamp,
+                "delivered": true, "read": false,
+            }
+        })
+    };
+    super::dispatcher::emit_event("message", &event);
+
+    ok_json(serde_json::json!({
+        "message_id": message.id, "conversation_id": conv_id, "edited": edited,
+    }))
+}
+
+/// Give a friend a prekey bundle, so they can open a ratchet session with
+/// us. Each call uses up a new one-time prekey.
+///
+/// Args: `{ to_did }`
+/// Returns: `{ relay_messages }`, a `prekey_bundle` envelope
+pub fn messaging_publish_prekeys(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let to_did = require_str(&data, "to_did")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    load_friend(db, to_did)?;
+    let bundle = messaging_service(&state)?
+        .prekey_bundle()
+        .map_err(|e| err(e.code(), e))?;
+
+    let envelope = serde_json::json!({
+        "envelope": "prekey_bundle", "version": 1,
+        "payload": { "senderDid": identity.did_string(), "bundle": bundle }
+    });
+    ok_json(serde_json::json!({ "relay_messages": [relay_message(to_did, &envelope)] }))
+}
+
+/// Ask a friend for a prekey bundle; they answer with
+/// `messaging_publish_prekeys`.
+///
+/// Args: `{ to_did }`
+/// Returns: `{ relay_messages }`, a `prekey_request` envelope
+pub fn messaging_request_prekeys(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let to_did = require_str(&data, "to_did")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    load_friend(db, to_did)?;
+    let envelope = serde_json::json!({
+        "envelope": "prekey_request", "version": 1,
+        "payload": { "senderDid": identity.did_string() }
+    });
+    ok_json(serde_json::json!({ "relay_messages": [relay_message(to_did, &envelope)] }))
+}
+
+/// Open a ratchet session from a friend's `prekey_bundle`. Messages to them
+/// use `MessageEnvelope`s from then on.
+///
+/// A bundle is ignored if we already have a session with the friend, so a
+/// replayed one can't reset it.
+///
+/// Args: `{ sender_did, bundle }`
+/// Returns: `{ established }`
+pub fn messaging_receive_prekeys(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let sender_did = require_str(&data, "sender_did")?;
+    let bundle: PrekeyBundle = serde_json::from_value(data["bundle"].clone())
+        .map_err(|e| err(2, format!("Invalid bundle: {}", e)))?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let friend = load_friend(db, sender_did)?;
+    let svc = messaging_service(&state)?;
+    if svc.has_session(&friend.did).map_err(|e| err(e.code(), e))? {
+        return ok_json(serde_json::json!({ "established": false }));
+    }
+    svc.establish_session(&friend, &bundle)
+        .map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({ "established": true }))
+}
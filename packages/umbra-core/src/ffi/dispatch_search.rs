@@ -0,0 +1,54 @@
+//! Unified message search dispatch handler.
+//!
+//! Searches DMs, groups, and community channels the current identity can
+//! read, using the FTS5 index maintained by the storage layer.
+
+use super::dispatcher::{err, json_parse, require_str, DResult};
+use super::state::get_state;
+use crate::community::CommunityService;
+use crate::storage::SearchSource;
+
+/// Ranked full-text search across all readable messages.
+///
+/// Args: `{ "query": "...", "sources": ["dm", "group", "community"], "limit": 50 }`
+/// (`sources` defaults to all three). The query supports `"exact phrases"`
+/// and `prefix*` terms.
+///
+/// Returns: `[{ message_id, source, scope_id, community_id, sender_did,
+/// created_at, snippet, rank }]`, best match first. Matched terms in
+/// `snippet` are wrapped in `<mark>…</mark>`.
+pub fn search(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let query = require_str(&data, "query")?;
+    let limit = data["limit"].as_u64().unwrap_or(50) as usize;
+
+    let mut sources = Vec::new();
+    if let Some(arr) = data["sources"].as_array() {
+        for value in arr {
+            let name = value.as_str().unwrap_or_default();
+            let source = SearchSource::parse(name)
+                .ok_or_else(|| err(2, format!("Unknown search source: {}", name)))?;
+            sources.push(source);
+        }
+    }
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+
+    let did = identity.did_string();
+    let visible_channels = CommunityService::new(db.clone())
+        .visible_channels(&did)
+        .map_err(|e| err(e.code(), e))?;
+    let hits = db
+        .search_messages(query, &did, &sources, &visible_channels, limit)
+        .map_err(|e| err(e.code(), e))?;
+    Ok(serde_json::to_string(&hits).unwrap_or_default())
+}
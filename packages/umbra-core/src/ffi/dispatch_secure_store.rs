@@ -3,9 +3,44 @@
 //! Exposes the Rust SecureStore (iOS Keychain, Android Keystore) through the
 //! generic dispatcher so that TypeScript can persist sensitive data (identity,
 //! recovery phrase, PIN) without needing additional Swift/Kotlin code.
+//!
+//! Hosts without a platform keystore (CLI, Tauri on Linux) call
+//! `secure_store_unlock` first to back the store with an encrypted file vault.
+//!
+//! The database key is derived from the loaded identity and the store's
+//! secret (see `derive_database_key`), and cached in the store so the
+//! database can be opened before the identity is.
 
 use super::dispatcher::{err, json_parse, ok_json, require_str, DResult};
-use super::state::get_state;
+use super::state::{get_state, FfiState};
+use crate::storage::{
+    derive_database_key, load_storage_key, store_storage_key, Database, FileVault, SecureStore,
+    VAULT_FILE_NAME,
+};
+
+/// Back the SecureStore with a passphrase-encrypted file vault.
+///
+/// Opens (or creates) `<storage_path>/secure_store.vault` and replaces the
+/// default backend, so values stored afterwards survive restarts. Values
+/// stored in the previous backend are not carried over. Call this before
+/// `umbra_init_database` so the database is opened encrypted.
+///
+/// Args: `{ "passphrase": "..." }`
+/// Returns: `{ "path": "..." }`
+pub fn secure_store_unlock(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let passphrase = require_str(&data, "passphrase")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let path = std::path::Path::new(&state.read().storage_path).join(VAULT_FILE_NAME);
+
+    // Derive the vault key without holding the state lock
+    let vault = FileVault::open(&path, passphrase).map_err(|e| err(e.code(), e))?;
+    state.write().secure_store = Some(SecureStore::with_backend(vault));
+
+    tracing::info!("SecureStore unlocked from {}", path.display());
+    ok_json(serde_json::json!({ "path": path.to_string_lossy() }))
+}
 
 /// Store a UTF-8 string value in secure storage.
 ///
@@ -98,3 +133,127 @@ pub fn secure_exists(args: &str) -> DResult {
 
     ok_json(serde_json::json!({ "exists": exists }))
 }
+
+/// Why the database is not encrypted, or `None` if it is
+fn plaintext_reason(state: &FfiState) -> Option<&'static str> {
+    let persistent = state
+        .secure_store
+        .as_ref()
+        .is_some_and(|s| s.is_persistent());
+    match &state.database {
+        Some(db) if db.is_encrypted() => None,
+        _ if !Database::encryption_available() => Some("encryption_unavailable"),
+        _ if !persistent => Some("no_persistent_secure_store"),
+        _ if state.identity.is_none() => Some("no_identity"),
+        _ => Some("not_keyed"),
+    }
+}
+
+/// Key the database to the loaded identity.
+///
+/// Encrypts a plaintext database in place, or rekeys an encrypted one whose
+/// cached key no longer matches (new identity, rotated key, new vault
+/// passphrase). The new key is cached before the database is touched, so an
+/// interrupted migration is finished by the next `umbra_init_database`.
+///
+/// Leaves the database plaintext, with an error logged, when the key can't
+/// be kept (no persistent SecureStore) or the platform has no SQLCipher.
+/// `database_status` reports that state to the UI.
+pub(crate) fn secure_database(state: &FfiState) -> crate::Result<()> {
+    let (Some(db), Some(identity)) = (&state.database, &state.identity) else {
+        return Ok(());
+    };
+    let store = match state.secure_store.as_ref().filter(|s| s.is_persistent()) {
+        Some(store) if Database::encryption_available() => store,
+        _ => {
+            if let Some(reason) = plaintext_reason(state) {
+                tracing::error!("Database is NOT encrypted at rest ({})", reason);
+            }
+            return Ok(());
+        }
+    };
+
+    let key = derive_database_key(store, identity)?;
+    let old_key = load_storage_key(store)?;
+    if db.is_encrypted() && old_key.as_deref() == Some(&*key) {
+        return Ok(());
+    }
+
+    store_storage_key(store, &key)?;
+    let keyed = if db.is_encrypted() {
+        db.rekey(&key)
+    } else {
+        db.encrypt(&key)
+    };
+    if let Err(e) = keyed {
+        // Keep the cached key matching the file
+        if let Some(old_key) = old_key.filter(|_| db.is_encrypted()) {
+            let _ = store_storage_key(store, &old_key);
+        }
+        return Err(e);
+    }
+
+    tracing::info!("Database keyed to the loaded identity");
+    Ok(())
+}
+
+/// Re-derive the database key and rekey the database if it changed.
+///
+/// Hosts call this after anything that changes the key inputs outside of
+/// the identity and passphrase handlers, which rekey on their own.
+///
+/// Returns: `{ "success": true }`
+pub fn database_rekey(_args: &str) -> DResult {
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    if state.database.is_none() {
+        return Err(err(400, "Database not initialized"));
+    }
+    if state.identity.is_none() {
+        return Err(err(200, "No identity loaded"));
+    }
+
+    secure_database(&state).map_err(|e| err(e.code(), e))?;
+    ok_json(serde_json::json!({ "success": true }))
+}
+
+/// Change the passphrase of the file vault backing the SecureStore.
+///
+/// The database key is bound to the vault key, so the database is rekeyed
+/// under the new passphrase as part of the change.
+///
+/// Args: `{ "passphrase": "..." }`
+/// Returns: `{ "success": true }`
+pub fn secure_store_change_passphrase(args: &str) -> DResult {
+    let data = json_parse(args)?;
+    let passphrase = require_str(&data, "passphrase")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let store = state
+        .secure_store
+        .as_ref()
+        .ok_or_else(|| err(400, "SecureStore not initialized"))?;
+
+    store
+        .change_passphrase(passphrase)
+        .map_err(|e| err(e.code(), e))?;
+    secure_database(&state).map_err(|e| err(e.code(), e))?;
+
+    ok_json(serde_json::json!({ "success": true }))
+}
+
+/// Report whether the database is encrypted at rest.
+///
+/// Returns: `{ "encrypted": bool, "reason": null | "encryption_unavailable"
+/// | "no_persistent_secure_store" | "no_identity" | "not_keyed" }`
+pub fn database_status(_args: &str) -> DResult {
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    if state.database.is_none() {
+        return Err(err(400, "Database not initialized"));
+    }
+
+    let reason = plaintext_reason(&state);
+    ok_json(serde_json::json!({ "encrypted": reason.is_none(), "reason": reason }))
+}
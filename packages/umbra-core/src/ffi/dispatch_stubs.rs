@@ -170,6 +170,75 @@ pub fn relay_send(args: &str) -> DResult {
     }))
 }
 
+pub fn relay_authenticate(args: &str) -> DResult {
+    use super::dispatcher::{json_parse, ok_json, require_str};
+    use super::state::get_state;
+    use crate::network::relay_client::RelayClientMessage;
+
+    let data = json_parse(args)?;
+    let nonce = require_str(&data, "nonce")?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+
+    let auth_msg = RelayClientMessage::authenticate(&identity.keypair().signing, nonce);
+    let auth_msg = serde_json::to_string(&auth_msg).map_err(|e| err(700, e))?;
+
+    ok_json(serde_json::json!({
+        "did": identity.did_string(),
+        "relay_message": auth_msg,
+    }))
+}
+
+/// Sign a community invite for publishing to the relay.
+///
+/// `invite` is a `RelayInvite`; the returned `relay_message` is a
+/// `publish_invite` signed by the identity key.
+pub fn relay_publish_invite(args: &str) -> DResult {
+    use super::dispatcher::{json_parse, ok_json};
+    use super::state::get_state;
+    use crate::network::relay_client::{RelayClientMessage, RelayInvite};
+
+    let data = json_parse(args)?;
+    let invite: RelayInvite = serde_json::from_value(data["invite"].clone())
+        .map_err(|e| err(2, format!("Invalid invite: {}", e)))?;
+
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+
+    let did = identity.did_string();
+    let msg = RelayClientMessage::publish_invite(&identity.keypair().signing, &did, invite);
+    let msg = serde_json::to_string(&msg).map_err(|e| err(700, e))?;
+
+    ok_json(serde_json::json!({
+        "did": did,
+        "relay_message": msg,
+    }))
+}
+
+/// Rank candidate relays for connecting, best first.
+///
+/// `relays` are directory entries (`GET /relays`) with the `latency_ms`
+/// the host measured to each (`null` if unreachable).
+pub fn relay_rank(args: &str) -> DResult {
+    use super::dispatcher::{json_parse, ok_json};
+    use crate::network::relay_client::{rank_relays, RelayCandidate};
+
+    let data = json_parse(args)?;
+    let candidates: Vec<RelayCandidate> = serde_json::from_value(data["relays"].clone())
+        .map_err(|e| err(2, format!("Invalid relays: {}", e)))?;
+
+    ok_json(serde_json::json!({ "relays": rank_relays(candidates) }))
+}
+
 // ── Crypto ──────────────────────────────────────────────────────────────────
 // Sign/verify/encrypt/decrypt operations — the crypto primitives exist in
 // crate::crypto, but the dispatcher needs identity state access.
@@ -8,6 +8,8 @@
 //!   - `dispatch_friends`   — friends_* methods
 //!   - `dispatch_messaging` — messaging_* methods (DM)
 //!   - `dispatch_groups`    — groups_* methods (CRUD, encryption, invitations)
+//!   - `dispatch_community_log` — community_log_* methods (signed op log)
+//!   - `dispatch_community_keys` — community_keys_* methods (channel keyring)
 //!   - `dispatch_stubs`     — 501 placeholders (network, crypto, transfers, etc.)
 //!
 //! Returns `Ok(json_string)` on success, `Err((error_code, message))` on failure.
@@ -17,6 +19,8 @@ use sha2::{Digest, Sha256};
 
 use super::state::get_state;
 use crate::community::CommunityService;
+use crate::groups::GroupService;
+use crate::identity::Identity;
 
 pub type DResult = Result<String, (i32, String)>;
 
@@ -57,6 +61,9 @@ pub fn deterministic_conversation_id(did_a: &str, did_b: &str) -> String {
 pub fn community_service() -> Result<CommunityService, (i32, String)> {
     let state = get_state().map_err(|e| err(100, e))?;
     let state = state.read();
+    if let Some(community) = &state.community {
+        return Ok(CommunityService::clone(community));
+    }
     let db = state
         .database
         .as_ref()
@@ -64,6 +71,28 @@ pub fn community_service() -> Result<CommunityService, (i32, String)> {
     Ok(CommunityService::new(db.clone()))
 }
 
+/// A copy of the loaded identity, for services that sign on its behalf.
+pub fn service_identity() -> Result<Identity, (i32, String)> {
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| err(200, "No identity loaded"))?;
+    identity.clone_for_service().map_err(|e| err(e.code(), e))
+}
+
+pub fn group_service() -> Result<GroupService, (i32, String)> {
+    let identity = service_identity()?;
+    let state = get_state().map_err(|e| err(100, e))?;
+    let state = state.read();
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| err(400, "Database not initialized"))?;
+    Ok(GroupService::new(std::sync::Arc::new(identity), db.clone()))
+}
+
 pub fn dm_file_service() -> Result<crate::messaging::files::DmFileService, (i32, String)> {
     let state = get_state().map_err(|e| err(100, e))?;
     let state = state.read();
@@ -222,12 +251,16 @@ pub fn notification_setting_json(
 
 use super::dispatch_community;
 use super::dispatch_community_ext;
+use super::dispatch_community_keys;
+use super::dispatch_community_log;
 use super::dispatch_community_msg;
+use super::dispatch_devices;
 use super::dispatch_dm_files;
 use super::dispatch_friends;
 use super::dispatch_groups;
 use super::dispatch_identity;
 use super::dispatch_messaging;
+use super::dispatch_search;
 use super::dispatch_secure_store;
 use super::dispatch_stubs;
 
@@ -257,6 +290,9 @@ pub fn dispatch(method: &str, args: &str) -> DResult {
         "friends_unblock" => dispatch_friends::friends_unblock(args),
         "friends_get_blocked" => dispatch_friends::friends_get_blocked(),
         "friends_update_encryption_key" => dispatch_friends::friends_update_encryption_key(args),
+        "friends_safety_number" => dispatch_friends::friends_safety_number(args),
+        "friends_verify" => dispatch_friends::friends_verify(args),
+        "friends_unverify" => dispatch_friends::friends_unverify(args),
 
         // ── Messaging (DM) ──────────────────────────────────────────
         "messaging_get_conversations" => dispatch_messaging::messaging_get_conversations(),
@@ -285,6 +321,23 @@ pub fn dispatch(method: &str, args: &str) -> DResult {
         "messaging_reply_thread" => dispatch_messaging::messaging_reply_thread(args),
         "messaging_get_pinned" => dispatch_messaging::messaging_get_pinned(args),
         "messaging_update_status" => dispatch_messaging::messaging_update_status(args),
+        "messaging_set_disappearing_timer" => {
+            dispatch_messaging::messaging_set_disappearing_timer(args)
+        }
+        "messaging_receive_disappearing_timer" => {
+            dispatch_messaging::messaging_receive_disappearing_timer(args)
+        }
+        "messaging_sweep_expired" => dispatch_messaging::messaging_sweep_expired(),
+        "messaging_seal" => dispatch_messaging::messaging_seal(args),
+        "messaging_open_sealed" => dispatch_messaging::messaging_open_sealed(args),
+        "messaging_sealed_access" => dispatch_messaging::messaging_sealed_access(),
+        "messaging_receive_envelope" => dispatch_messaging::messaging_receive_envelope(args),
+        "messaging_publish_prekeys" => dispatch_messaging::messaging_publish_prekeys(args),
+        "messaging_request_prekeys" => dispatch_messaging::messaging_request_prekeys(args),
+        "messaging_receive_prekeys" => dispatch_messaging::messaging_receive_prekeys(args),
+
+        // ── Search ──────────────────────────────────────────────────
+        "search" => dispatch_search::search(args),
 
         // ── Groups ─────────────────────────────────────────────────
         "groups_create" => dispatch_groups::groups_create(args),
@@ -318,6 +371,10 @@ pub fn dispatch(method: &str, args: &str) -> DResult {
         "groups_remove_member_with_rotation" => {
             dispatch_groups::groups_remove_member_with_rotation(args)
         }
+        "groups_mls_add_member" => dispatch_groups::groups_mls_add_member(args),
+        "groups_mls_process_welcome" => dispatch_groups::groups_mls_process_welcome(args),
+        "groups_mls_process_commit" => dispatch_groups::groups_mls_process_commit(args),
+        "groups_mls_update" => dispatch_groups::groups_mls_update(args),
 
         // ── Network ─────────────────────────────────────────────────
         "network_status" => dispatch_stubs::network_status(),
@@ -327,6 +384,9 @@ pub fn dispatch(method: &str, args: &str) -> DResult {
         "relay_create_session" => dispatch_stubs::relay_create_session(args),
         "relay_accept_session" => dispatch_stubs::relay_accept_session(args),
         "relay_send" => dispatch_stubs::relay_send(args),
+        "relay_authenticate" => dispatch_stubs::relay_authenticate(args),
+        "relay_publish_invite" => dispatch_stubs::relay_publish_invite(args),
+        "relay_rank" => dispatch_stubs::relay_rank(args),
 
         // ── Crypto ──────────────────────────────────────────────────
         "crypto_sign" => dispatch_stubs::crypto_sign(args),
@@ -450,6 +510,24 @@ pub fn dispatch(method: &str, args: &str) -> DResult {
         "community_invite_delete" => dispatch_community::community_invite_delete(args),
         "community_invite_set_vanity" => dispatch_community::community_invite_set_vanity(args),
 
+        // ── Community — Op Log ──────────────────────────────────────
+        "community_log_create" => dispatch_community_log::community_log_create(args),
+        "community_log_submit" => dispatch_community_log::community_log_submit(args),
+        "community_log_ingest" => dispatch_community_log::community_log_ingest(args),
+        "community_log_heads" => dispatch_community_log::community_log_heads(args),
+        "community_log_ops_since" => dispatch_community_log::community_log_ops_since(args),
+
+        // ── Community — Channel Keyring ─────────────────────────────
+        "community_keys_announce" => dispatch_community_keys::community_keys_announce(args),
+        "community_keys_receive_member_key" => {
+            dispatch_community_keys::community_keys_receive_member_key(args)
+        }
+        "community_keys_sync" => dispatch_community_keys::community_keys_sync(args),
+        "community_keys_rotate" => dispatch_community_keys::community_keys_rotate(args),
+        "community_keys_receive" => dispatch_community_keys::community_keys_receive(args),
+        "community_keys_encrypt" => dispatch_community_keys::community_keys_encrypt(args),
+        "community_keys_decrypt" => dispatch_community_keys::community_keys_decrypt(args),
+
         // ── Community — Messages ────────────────────────────────────
         "community_message_send" => dispatch_community_msg::community_message_send(args),
         "community_message_store_received" => {
@@ -672,10 +750,24 @@ pub fn dispatch(method: &str, args: &str) -> DResult {
         "dht_stop_providing" => dispatch_stubs::dht_stop_providing(args),
 
         // ── Secure Store ───────────────────────────────────────────
+        "secure_store_unlock" => dispatch_secure_store::secure_store_unlock(args),
         "secure_store" => dispatch_secure_store::secure_store(args),
         "secure_retrieve" => dispatch_secure_store::secure_retrieve(args),
         "secure_delete" => dispatch_secure_store::secure_delete(args),
         "secure_exists" => dispatch_secure_store::secure_exists(args),
+        "secure_store_change_passphrase" => {
+            dispatch_secure_store::secure_store_change_passphrase(args)
+        }
+        "database_rekey" => dispatch_secure_store::database_rekey(args),
+        "database_status" => dispatch_secure_store::database_status(args),
+
+        // ── Devices ────────────────────────────────────────────────
+        "device_link_request" => dispatch_devices::device_link_request(),
+        "device_link" => dispatch_devices::device_link(args),
+        "device_revoke" => dispatch_devices::device_revoke(args),
+        "device_list" => dispatch_devices::device_list(),
+        "device_install" => dispatch_devices::device_install(args),
+        "device_import_list" => dispatch_devices::device_import_list(args),
 
         // ── Discovery ─────────────────────────────────────────────
         "discovery_get_connection_info" => dispatch_stubs::discovery_get_connection_info(),
@@ -66,12 +66,24 @@ mod dispatch_community_msg;
 #[cfg(feature = "ffi")]
 mod dispatch_community_ext;
 
+#[cfg(feature = "ffi")]
+mod dispatch_community_log;
+
+#[cfg(feature = "ffi")]
+mod dispatch_community_keys;
+
 #[cfg(feature = "ffi")]
 mod dispatch_dm_files;
 
+#[cfg(feature = "ffi")]
+mod dispatch_search;
+
 #[cfg(feature = "ffi")]
 mod dispatch_secure_store;
 
+#[cfg(feature = "ffi")]
+mod dispatch_devices;
+
 #[cfg(feature = "ffi")]
 mod dispatch_groups;
 
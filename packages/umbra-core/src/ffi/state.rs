@@ -47,6 +47,8 @@ pub struct FfiState {
     pub storage_path: String,
     /// Master seed retained for backup key derivation.
     pub backup_seed: Option<[u8; 32]>,
+    /// Background task deleting expired disappearing messages.
+    pub sweeper: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl FfiState {
@@ -64,6 +66,7 @@ impl FfiState {
             event_callback: None,
             storage_path,
             backup_seed: None,
+            sweeper: None,
         }
     }
 }
@@ -48,6 +48,8 @@ use crate::community::CommunityService;
 use crate::discovery::ConnectionInfo;
 use crate::friends::FriendRequest;
 use crate::identity::{Identity, ProfileUpdate, RecoveryPhrase};
+use crate::messaging::disappearing::{self, DisappearingTimer};
+use crate::messaging::sealed::{self, SealedEnvelope};
 use crate::network::NetworkService;
 use crate::storage::{Database, FriendRequestRecord};
 use base64::Engine as _;
@@ -84,6 +86,9 @@ struct WasmState {
     identity: Option<Identity>,
     network: Option<Arc<NetworkService>>,
     database: Option<Arc<Database>>,
+    /// Community service shared between calls, so log-backed
+    /// communities aren't replayed on every one.
+    community: Option<CommunityService>,
     /// Master seed retained for backup key derivation.
     /// Set during identity create/restore, cleared on shutdown.
     backup_seed: Option<[u8; 32]>,
@@ -99,6 +104,7 @@ impl WasmState {
             identity: None,
             network: None,
             database: None,
+            community: None,
             backup_seed: None,
             #[cfg(target_arch = "wasm32")]
             connection_injector: None,
@@ -148,7 +154,9 @@ pub fn umbra_wasm_init_database() -> Promise {
             .map_err(|e| JsValue::from_str(&format!("Failed to open database: {}", e)))?;
 
         let db = Arc::new(database);
-        state.write().database = Some(db);
+        let mut state = state.write();
+        state.community = Some(CommunityService::new(db.clone()));
+        state.database = Some(db);
 
         Ok(JsValue::TRUE)
     })
@@ -324,7 +332,7 @@ pub fn umbra_wasm_identity_rotate_encryption_key() -> Result<JsValue, JsValue> {
     let mut state = state.write();
 
     // First, do the mutable identity operations
-    let (our_did, _new_pub_key, new_pub_hex, signature_hex) = {
+    let (our_did, rotation, new_pub_hex) = {
         let identity = state
             .identity
             .as_mut()
@@ -333,16 +341,12 @@ pub fn umbra_wasm_identity_rotate_encryption_key() -> Result<JsValue, JsValue> {
         let our_did = identity.did_string();
 
         // Generate new encryption key (random, not mnemonic-derived)
-        let new_pub_key = identity
+        let rotation = identity
             .rotate_encryption_key()
             .map_err(|e| JsValue::from_str(&format!("Key rotation failed: {}", e)))?;
-        let new_pub_hex = hex::encode(new_pub_key);
+        let new_pub_hex = hex::encode(rotation.new_encryption_key);
 
-        // Sign the new public key so recipients can verify authenticity
-        let signature = crate::crypto::sign(&identity.keypair().signing, &new_pub_key);
-        let signature_hex = hex::encode(signature.as_bytes());
-
-        (our_did, new_pub_key, new_pub_hex, signature_hex)
+        (our_did, rotation, new_pub_hex)
     };
 
     // Now get immutable database reference (mutable borrow on identity is released)
@@ -366,8 +370,8 @@ pub fn umbra_wasm_identity_rotate_encryption_key() -> Result<JsValue, JsValue> {
             "payload": {
                 "fromDid": our_did,
                 "newEncryptionKey": new_pub_hex,
-                "signature": signature_hex,
                 "timestamp": timestamp,
+                "rotation": rotation,
             }
         });
         relay_messages.push(serde_json::json!({
@@ -928,10 +932,12 @@ pub fn umbra_wasm_friends_build_accept_ack(json: &str) -> Result<JsValue, JsValu
 
 /// Update a friend's encryption key after receiving a key_rotation envelope.
 ///
-/// Verifies the signature using the friend's (unchanged) Ed25519 signing key,
-/// then updates the X25519 encryption key in the database.
+/// Verifies the signed rotation using the friend's (unchanged) Ed25519 signing
+/// key, then updates the X25519 encryption key in the database. A rotation no
+/// newer than the last one applied is ignored, so replays can't roll back.
 ///
-/// Input JSON: `{ "from_did": "...", "new_encryption_key": "hex", "signature": "hex" }`
+/// Input JSON: `{ "from_did": "...", "rotation": { ...KeyRotation } }`.
+/// The old key stays usable for the rotation grace window.
 #[wasm_bindgen]
 pub fn umbra_wasm_friends_update_encryption_key(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
@@ -940,12 +946,15 @@ pub fn umbra_wasm_friends_update_encryption_key(json: &str) -> Result<JsValue, J
     let from_did = data["from_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing from_did"))?;
-    let new_enc_key_hex = data["new_encryption_key"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing new_encryption_key"))?;
-    let signature_hex = data["signature"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing signature"))?;
+    let rotation: crate::identity::KeyRotation = serde_json::from_value(
+        data.get("rotation")
+            .cloned()
+            .ok_or_else(|| JsValue::from_str("Missing rotation"))?,
+    )
+    .map_err(|e| JsValue::from_str(&format!("Invalid rotation: {}", e)))?;
+    if rotation.did != from_did {
+        return Err(JsValue::from_str("Rotation is for another identity"));
+    }
 
     let state = get_state()?;
     let state = state.read();
@@ -960,48 +969,170 @@ pub fn umbra_wasm_friends_update_encryption_key(json: &str) -> Result<JsValue, J
         .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
         .ok_or_else(|| JsValue::from_str("Friend not found"))?;
 
-    // Decode the new encryption key
-    let new_key_bytes = hex::decode(new_enc_key_hex)
-        .map_err(|e| JsValue::from_str(&format!("Invalid key hex: {}", e)))?;
-    if new_key_bytes.len() != 32 {
-        return Err(JsValue::from_str("New encryption key must be 32 bytes"));
+    let signing_key: [u8; 32] = hex::decode(&friend.signing_key)
+        .ok()
+        .and_then(|k| k.try_into().ok())
+        .ok_or_else(|| JsValue::from_str("Invalid signing key"))?;
+    let updated = crate::friends::apply_key_rotation(database, &signing_key, &rotation)
+        .map_err(|e| JsValue::from_str(&format!("Key rotation rejected: {}", e)))?;
+
+    if updated {
+        let new_key_hex = hex::encode(rotation.new_encryption_key);
+        emit_event(
+            "friend",
+            &serde_json::json!({
+                "type": "friendKeyRotated",
+                "did": from_did,
+                "newEncryptionKey": new_key_hex,
+            }),
+        );
+        if friend.verified {
+            emit_verified_key_changed(from_did, &new_key_hex);
+        }
     }
 
-    // Verify the signature using the friend's (unchanged) signing key
-    let signing_key_bytes = hex::decode(&friend.signing_key)
-        .map_err(|e| JsValue::from_str(&format!("Invalid signing key: {}", e)))?;
-    let mut sk = [0u8; 32];
-    sk.copy_from_slice(&signing_key_bytes);
+    Ok(JsValue::from_str(
+        &serde_json::json!({"updated": updated}).to_string(),
+    ))
+}
+
+/// A verified friend's keys changed, so their verification was cleared.
+/// The UI should warn and ask the user to compare safety numbers again.
+fn emit_verified_key_changed(did: &str, new_encryption_key: &str) {
+    emit_event(
+        "friend",
+        &serde_json::json!({
+            "type": "verifiedKeyChanged",
+            "did": did,
+            "newEncryptionKey": new_encryption_key,
+        }),
+    );
+}
+
+/// Safety number for out-of-band verification of a friend
+///
+/// Returns JSON: { "safety_number", "display", "qr_payload", "verified", "verified_at" }
+#[wasm_bindgen]
+pub fn umbra_wasm_friends_safety_number(did: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let friend = load_friend(database, did)?;
+    let number = friend.safety_number(identity);
+    let result = serde_json::json!({
+        "safety_number": number.digits(),
+        "display": number.display(),
+        "qr_payload": number.qr_payload(),
+        "verified": friend.verified,
+        "verified_at": friend.verified_at,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Mark a friend verified
+///
+/// Takes JSON: { "did": "...", "qr_payload": "..." (optional) }
+/// With a scanned `qr_payload` it must match our safety number; without one
+/// the user compared the digits by hand.
+#[wasm_bindgen]
+pub fn umbra_wasm_friends_verify(json: &str) -> Result<(), JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+    let did = data["did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing did"))?;
 
-    let sig_bytes = hex::decode(signature_hex)
-        .map_err(|e| JsValue::from_str(&format!("Invalid signature hex: {}", e)))?;
-    let signature = crate::crypto::Signature::from_slice(&sig_bytes)
-        .map_err(|_| JsValue::from_str("Invalid signature format"))?;
+    let state = get_state()?;
+    let state = state.read();
 
-    crate::crypto::verify(&sk, &new_key_bytes, &signature)
-        .map_err(|_| JsValue::from_str("Signature verification failed — key rotation rejected"))?;
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    // Update the friend's encryption key in the database
-    let mut new_enc = [0u8; 32];
-    new_enc.copy_from_slice(&new_key_bytes);
+    let friend = load_friend(database, did)?;
+    if let Some(payload) = data["qr_payload"].as_str() {
+        let matches = friend
+            .safety_number(identity)
+            .verify_qr_payload(payload)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        if !matches {
+            return Err(JsValue::from_str("Safety number does not match"));
+        }
+    }
     database
-        .update_friend_encryption_key(from_did, &new_enc)
-        .map_err(|e| JsValue::from_str(&format!("Failed to update key: {}", e)))?;
+        .set_friend_verified(did, true)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "friend",
-        &serde_json::json!({
-            "type": "friendKeyRotated",
-            "did": from_did,
-            "newEncryptionKey": new_enc_key_hex,
-        }),
+        &serde_json::json!({"type": "friendVerified", "did": did}),
     );
+    Ok(())
+}
 
-    Ok(JsValue::from_str(
-        &serde_json::json!({"updated": true}).to_string(),
+/// Clear a friend's verification
+#[wasm_bindgen]
+pub fn umbra_wasm_friends_unverify(did: &str) -> Result<bool, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    database
+        .set_friend_verified(did, false)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn load_friend(database: &Database, did: &str) -> Result<crate::friends::Friend, JsValue> {
+    let record = database
+        .get_friend(did)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Friend not found"))?;
+    crate::friends::Friend::from_record(record).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// The messaging service for the loaded identity.
+fn messaging_service(
+    identity: &Identity,
+    database: &Arc<Database>,
+) -> Result<crate::messaging::MessagingService, JsValue> {
+    let identity = identity
+        .clone_for_service()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(crate::messaging::MessagingService::new(
+        Arc::new(identity),
+        database.clone(),
     ))
 }
 
+/// A version 2 relay envelope carrying a signed `MessageEnvelope`, plus any
+/// `extra` payload fields (e.g. `threadId`).
+fn dm_envelope_payload(
+    kind: &str,
+    envelope: &crate::messaging::MessageEnvelope,
+    mut extra: serde_json::Value,
+) -> serde_json::Value {
+    extra["messageEnvelope"] = serde_json::json!(envelope);
+    serde_json::json!({ "envelope": kind, "version": 2, "payload": extra })
+}
+
 /// Reject a friend request
 ///
 /// Updates the request status to "rejected" in the database.
@@ -1025,7 +1156,8 @@ pub fn umbra_wasm_friends_reject_request(request_id: &str) -> Result<(), JsValue
 /// Get list of friends as JSON array
 ///
 /// Each friend: { "did": "...", "display_name": "...", "status": "...", "signing_key": "...",
-///                "encryption_key": "...", "created_at": ..., "updated_at": ... }
+///                "encryption_key": "...", "created_at": ..., "updated_at": ...,
+///                "verified": bool, "verified_at": ... }
 #[wasm_bindgen]
 pub fn umbra_wasm_friends_list() -> Result<JsValue, JsValue> {
     let state = get_state()?;
@@ -1050,6 +1182,8 @@ pub fn umbra_wasm_friends_list() -> Result<JsValue, JsValue> {
                         "encryption_key": f.encryption_key,
                         "created_at": f.created_at,
                         "updated_at": f.updated_at,
+                        "verified": f.verified,
+                        "verified_at": f.verified_at,
                     })
                 })
                 .collect();
@@ -1215,6 +1349,7 @@ pub fn umbra_wasm_messaging_get_conversations() -> Result<JsValue, JsValue> {
                         "created_at": c.created_at,
                         "last_message_at": c.last_message_at,
                         "unread_count": c.unread_count,
+                        "disappearing_timer": c.disappearing_timer,
                     })
                 })
                 .collect();
@@ -1304,6 +1439,7 @@ pub fn umbra_wasm_messaging_get_messages(
                         "timestamp": m.timestamp,
                         "delivered": m.delivered,
                         "read": m.read,
+                        "expires_at": m.expires_at,
                         "threadReplyCount": reply_count,
                     })
                 })
@@ -1351,30 +1487,23 @@ pub fn umbra_wasm_messaging_send(conversation_id: &str, content: &str) -> Result
         JsValue::from_str("Cannot send message: conversation has no friend_did (group?)")
     })?;
 
-    // Look up the friend's encryption key
-    let friend_record = database
-        .get_friend(friend_did)
-        .map_err(|e| JsValue::from_str(&format!("Failed to get friend: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Friend not found — cannot encrypt message"))?;
-
-    let friend_encryption_key_bytes = hex::decode(&friend_record.encryption_key)
-        .map_err(|e| JsValue::from_str(&format!("Invalid friend encryption key: {}", e)))?;
-    let mut friend_enc_key = [0u8; 32];
-    if friend_encryption_key_bytes.len() != 32 {
-        return Err(JsValue::from_str("Friend encryption key must be 32 bytes"));
-    }
-    friend_enc_key.copy_from_slice(&friend_encryption_key_bytes);
+    let friend = load_friend(database, friend_did)?;
 
-    // Encrypt the content using X25519 ECDH + AES-256-GCM
-    let aad = format!("{}{}{}", sender_did, friend_did, timestamp);
-    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
-        &identity.keypair().encryption,
-        &friend_enc_key,
-        conversation_id.as_bytes(),
-        content.as_bytes(),
-        aad.as_bytes(),
-    )
-    .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+    // Ratcheted envelope for the wire if the friend can read one; the
+    // stored copy is under the static conversation key either way
+    let mut message = crate::messaging::Message::new(
+        conversation_id.to_string(),
+        sender_did.clone(),
+        friend_did.to_string(),
+        crate::messaging::MessageContent::text(content),
+    );
+    message.id = message_id.clone();
+    message.timestamp = timestamp;
+    message.expires_in = conversation.disappearing_timer;
+    let outgoing = messaging_service(identity, database)?
+        .encrypt_text(&message, &friend)
+        .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+    let (nonce, ciphertext) = (outgoing.nonce, outgoing.ciphertext.clone());
 
     // Store encrypted message locally
     database
@@ -1387,6 +1516,7 @@ pub fn umbra_wasm_messaging_send(conversation_id: &str, content: &str) -> Result
             timestamp,
         )
         .map_err(|e| JsValue::from_str(&format!("Failed to store message: {}", e)))?;
+    let _ = database.index_message(&message_id, content);
 
     // Send over the network if connected
     if let Some(ref network) = state.network {
@@ -1439,18 +1569,23 @@ pub fn umbra_wasm_messaging_send(conversation_id: &str, content: &str) -> Result
     let nonce_hex = hex::encode(&nonce.0);
 
     // Build relay envelope so TS only has to call ws.send()
-    let relay_envelope = serde_json::json!({
-        "envelope": "chat_message",
-        "version": 1,
-        "payload": {
-            "messageId": message_id,
-            "conversationId": conversation_id,
-            "senderDid": sender_did,
-            "contentEncrypted": ciphertext_b64,
-            "nonce": nonce_hex,
-            "timestamp": timestamp,
-        }
-    });
+    let relay_envelope = match &outgoing.envelope {
+        Some(envelope) => dm_envelope_payload("chat_message", envelope, serde_json::json!({})),
+        // Legacy fallback: older peers can only read the static-key payload
+        None => serde_json::json!({
+            "envelope": "chat_message",
+            "version": 1,
+            "payload": {
+                "messageId": message_id,
+                "conversationId": conversation_id,
+                "senderDid": sender_did,
+                "contentEncrypted": ciphertext_b64,
+                "nonce": nonce_hex,
+                "timestamp": timestamp,
+                "expiresIn": conversation.disappearing_timer,
+            }
+        }),
+    };
 
     let json = serde_json::json!({
         "id": message_id,
@@ -1676,6 +1811,12 @@ pub fn umbra_wasm_messaging_decrypt(
     ) {
         Ok(plaintext) => {
             let text = String::from_utf8_lossy(&plaintext).to_string();
+
+            // Stored DMs are ciphertext only, so this is where history gets indexed
+            if let Ok(Some(id)) = database.find_message_id(conversation_id, sender_did, timestamp) {
+                let _ = database.index_message(&id, &text);
+            }
+
             let json = serde_json::json!(text);
             Ok(JsValue::from_str(&json.to_string()))
         }
@@ -1722,6 +1863,10 @@ pub fn umbra_wasm_messaging_store_incoming(json: &str) -> Result<(), JsValue> {
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing nonce"))?;
     let timestamp = data["timestamp"].as_i64().unwrap_or(0);
+    // The sender's disappearing timer, from the envelope's `expiresIn`
+    let expires_at = data["expires_in"]
+        .as_i64()
+        .map(|secs| timestamp + secs * 1000);
 
     // Validate sender is a known friend
     let _friend = database
@@ -1772,13 +1917,14 @@ pub fn umbra_wasm_messaging_store_incoming(json: &str) -> Result<(), JsValue> {
             .map_err(|e| JsValue::from_str(&format!("Failed to store thread reply: {}", e)))?;
     } else {
         database
-            .store_message(
+            .store_message_with_expiry(
                 message_id,
                 &actual_conversation_id,
                 sender_did,
                 &ciphertext,
                 &nonce_bytes,
                 timestamp,
+                expires_at,
             )
             .map_err(|e| JsValue::from_str(&format!("Failed to store message: {}", e)))?;
     }
@@ -1814,19 +1960,25 @@ pub fn umbra_wasm_messaging_store_incoming(json: &str) -> Result<(), JsValue> {
     Ok(())
 }
 
-// ============================================================================
-// MESSAGING FEATURES (edit, delete, pin, react, thread, forward)
-// ============================================================================
-
-/// Edit a message's content.
+/// Change a DM conversation's disappearing-message timer.
 ///
-/// Re-encrypts the new text and updates the message in the database.
-/// Only the original sender can edit their own messages.
-///
-/// Takes JSON: { "message_id": "...", "new_text": "..." }
-/// Returns JSON: { "message_id", "edited_at", "content_encrypted", "nonce" }
+/// Takes JSON: { "conversation_id", "duration_secs" } (null or 0 turns it off)
+/// Returns JSON: { "timer", "relay_messages" }
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_edit(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_set_disappearing_timer(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let conversation_id = data["conversation_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
+    let duration = data["duration_secs"]
+        .as_u64()
+        .filter(|secs| *secs > 0)
+        .map(|secs| {
+            u32::try_from(secs).map_err(|_| JsValue::from_str("duration_secs is too large"))
+        })
+        .transpose()?;
+
     let state = get_state()?;
     let state = state.read();
 
@@ -1839,150 +1991,136 @@ pub fn umbra_wasm_messaging_edit(json: &str) -> Result<JsValue, JsValue> {
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let message_id = data["message_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-    let new_text = data["new_text"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing new_text"))?;
-
-    // Look up the message to verify ownership
-    let message = database
-        .get_message(message_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Message not found"))?;
-
-    let our_did = identity.did_string();
-    if message.sender_did != our_did {
-        return Err(JsValue::from_str("Cannot edit: you are not the sender"));
-    }
-
-    // Look up friend encryption key for re-encryption
     let conversation = database
-        .get_conversation(&message.conversation_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .get_conversation(conversation_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
         .ok_or_else(|| JsValue::from_str("Conversation not found"))?;
-
     let friend_did = conversation
         .friend_did
         .as_deref()
-        .ok_or_else(|| JsValue::from_str("Cannot edit in group conversation yet"))?;
+        .ok_or_else(|| JsValue::from_str("Conversation has no friend_did"))?;
 
-    let friend_record = database
-        .get_friend(friend_did)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Friend not found"))?;
+    let timer = DisappearingTimer::new(identity, conversation_id, duration)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    disappearing::apply_timer(database, &timer).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    emit_timer_changed(&timer);
 
-    let friend_enc_bytes = hex::decode(&friend_record.encryption_key)
-        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
-    let mut friend_enc_key = [0u8; 32];
-    if friend_enc_bytes.len() != 32 {
-        return Err(JsValue::from_str("Key must be 32 bytes"));
-    }
-    friend_enc_key.copy_from_slice(&friend_enc_bytes);
+    let relay_envelope = serde_json::json!({
+        "envelope": "disappearing_timer",
+        "version": 1,
+        "payload": timer,
+    });
+    let json = serde_json::json!({
+        "timer": timer,
+        "relay_messages": [{
+            "to_did": friend_did,
+            "payload": relay_envelope.to_string(),
+        }],
+    });
+    Ok(JsValue::from_str(&json.to_string()))
+}
 
-    let edited_at = crate::time::now_timestamp_millis();
-    let aad = format!("{}{}{}", our_did, friend_did, edited_at);
+/// Apply a disappearing-timer change received from a friend.
+///
+/// Takes the `payload` of a `disappearing_timer` envelope. Returns false if
+/// a newer setting was already stored.
+#[wasm_bindgen]
+pub fn umbra_wasm_messaging_receive_disappearing_timer(json: &str) -> Result<bool, JsValue> {
+    let timer: DisappearingTimer =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
-        &identity.keypair().encryption,
-        &friend_enc_key,
-        message.conversation_id.as_bytes(),
-        new_text.as_bytes(),
-        aad.as_bytes(),
-    )
-    .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+    let state = get_state()?;
+    let state = state.read();
 
-    database
-        .edit_message(message_id, &ciphertext, &nonce.0, edited_at)
-        .map_err(|e| JsValue::from_str(&format!("Failed to edit: {}", e)))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    emit_event(
-        "message",
-        &serde_json::json!({
-            "type": "messageEdited",
-            "messageId": message_id,
-            "newText": new_text,
-            "editedAt": edited_at,
-        }),
-    );
+    // Only the friend on the other end of the conversation may change it
+    let conversation = database
+        .get_conversation(&timer.conversation_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
+        .ok_or_else(|| JsValue::from_str("Conversation not found"))?;
+    if conversation.friend_did.as_deref() != Some(timer.set_by.as_str()) {
+        return Err(JsValue::from_str(
+            "Timer was not set by this conversation's friend",
+        ));
+    }
+    let friend = load_friend(database, &timer.set_by)?;
+    timer
+        .verify(&friend.signing_public_key)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let result = serde_json::json!({
-        "message_id": message_id,
-        "edited_at": edited_at,
-        "content_encrypted": base64::engine::general_purpose::STANDARD.encode(&ciphertext),
-        "nonce": hex::encode(&nonce.0),
-    });
-    Ok(JsValue::from_str(&result.to_string()))
+    let applied = disappearing::apply_timer(database, &timer)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    if applied {
+        emit_timer_changed(&timer);
+    }
+    Ok(applied)
 }
 
-/// Soft-delete a message.
+/// Delete expired disappearing messages.
 ///
-/// Only the original sender can delete their own messages.
-///
-/// Takes JSON: { "message_id": "..." }
-/// Returns JSON: { "message_id", "deleted_at" }
+/// There is no background task on the web; call this from a JS timer
+/// (every 30 seconds matches the native sweeper).
+/// Returns JSON: { "messages", "files" } deleted
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_delete(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_sweep_expired() -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let identity = state
-        .identity
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let message_id = data["message_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-
-    let message = database
-        .get_message(message_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Message not found"))?;
-
-    let our_did = identity.did_string();
-    if message.sender_did != our_did {
-        return Err(JsValue::from_str("Cannot delete: you are not the sender"));
+    let sweep =
+        disappearing::sweep_expired(database).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    if sweep.messages > 0 || sweep.files > 0 {
+        emit_event(
+            "message",
+            &serde_json::json!({
+                "type": "messagesExpired",
+                "messages": sweep.messages,
+                "files": sweep.files,
+            }),
+        );
     }
 
-    let deleted_at = crate::time::now_timestamp_millis();
-    database
-        .delete_message(message_id, deleted_at)
-        .map_err(|e| JsValue::from_str(&format!("Failed to delete: {}", e)))?;
+    let json = serde_json::json!({ "messages": sweep.messages, "files": sweep.files });
+    Ok(JsValue::from_str(&json.to_string()))
+}
 
+fn emit_timer_changed(timer: &DisappearingTimer) {
     emit_event(
         "message",
         &serde_json::json!({
-            "type": "messageDeleted",
-            "messageId": message_id,
-            "deletedAt": deleted_at,
+            "type": "disappearingTimerChanged",
+            "conversation_id": timer.conversation_id,
+            "duration_secs": timer.duration(),
+            "set_by": timer.set_by,
+            "set_at": timer.set_at,
         }),
     );
-
-    let result = serde_json::json!({
-        "message_id": message_id,
-        "deleted_at": deleted_at,
-    });
-    Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Pin a message in a conversation.
+/// Seal a relay payload for a friend, so the relay can't see it is from us.
 ///
-/// Takes JSON: { "message_id": "..." }
-/// Returns JSON: { "message_id", "pinned_by", "pinned_at" }
+/// Takes JSON: { "to_did", "payload" }
+/// Returns JSON: { "relay_message" }, a `send_sealed` message to send on a
+/// relay connection that hasn't registered our DID.
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_pin(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_seal(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+    let to_did = data["to_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing to_did"))?;
+    let payload = data["payload"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing payload"))?;
+
     let state = get_state()?;
     let state = state.read();
 
@@ -1995,48 +2133,36 @@ pub fn umbra_wasm_messaging_pin(json: &str) -> Result<JsValue, JsValue> {
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let message_id = data["message_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-
-    let our_did = identity.did_string();
-    let pinned_at = crate::time::now_timestamp_millis();
-
-    database
-        .pin_message(message_id, &our_did, pinned_at)
-        .map_err(|e| JsValue::from_str(&format!("Failed to pin: {}", e)))?;
-
-    emit_event(
-        "message",
-        &serde_json::json!({
-            "type": "messagePinned",
-            "messageId": message_id,
-            "pinnedBy": our_did,
-            "pinnedAt": pinned_at,
-        }),
-    );
+    let friend = load_friend(database, to_did)?;
+    let sealed = SealedEnvelope::seal(identity, &friend, payload)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let msg = crate::network::relay_client::RelayClientMessage::SendSealed {
+        to_did: to_did.to_string(),
+        access_key: sealed::access_key_for(identity, &friend)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?,
+        payload: sealed
+            .to_json()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?,
+    };
 
-    let result = serde_json::json!({
-        "message_id": message_id,
-        "pinned_by": our_did,
-        "pinned_at": pinned_at,
+    let json = serde_json::json!({
+        "relay_message": serde_json::to_string(&msg)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?,
     });
-    Ok(JsValue::from_str(&result.to_string()))
+    Ok(JsValue::from_str(&json.to_string()))
 }
 
-/// Unpin a message.
+/// Open a sealed-sender payload and verify it against the sending friend.
 ///
-/// Takes JSON: { "message_id": "..." }
-/// Returns JSON: { "message_id" }
+/// Takes the `payload` of a `sealed_message` (or of an offline message with
+/// an empty `from_did`).
+/// Returns JSON: { "sender_did", "payload", "timestamp" }
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_unpin(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_open_sealed(payload: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let _identity = state
+    let identity = state
         .identity
         .as_ref()
         .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
@@ -2045,35 +2171,28 @@ pub fn umbra_wasm_messaging_unpin(json: &str) -> Result<JsValue, JsValue> {
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let message_id = data["message_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-
-    database
-        .unpin_message(message_id)
-        .map_err(|e| JsValue::from_str(&format!("Failed to unpin: {}", e)))?;
-
-    emit_event(
-        "message",
-        &serde_json::json!({
-            "type": "messageUnpinned",
-            "messageId": message_id,
-        }),
-    );
+    let content = SealedEnvelope::from_json(payload)
+        .and_then(|sealed| sealed.open(identity))
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let friend = load_friend(database, &content.sender_did)?;
+    content
+        .verify(&friend)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let result = serde_json::json!({ "message_id": message_id });
-    Ok(JsValue::from_str(&result.to_string()))
+    let json = serde_json::json!({
+        "sender_did": content.sender_did,
+        "payload": content.payload,
+        "timestamp": content.timestamp,
+    });
+    Ok(JsValue::from_str(&json.to_string()))
 }
 
-/// Add a reaction (emoji) to a message.
+/// Build the relay message registering our friends' sealed-sender access
+/// keys. Send it after authenticating, and again when friends change.
 ///
-/// Takes JSON: { "message_id": "...", "emoji": "..." }
-/// Returns JSON: { "reactions": [...] } — all reactions for the message
+/// Returns JSON: { "relay_message", "count" }
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_add_reaction(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_sealed_access() -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -2086,62 +2205,141 @@ pub fn umbra_wasm_messaging_add_reaction(json: &str) -> Result<JsValue, JsValue>
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
+    let friends = database
+        .get_all_friends()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
+        .into_iter()
+        .map(crate::friends::Friend::from_record)
+        .collect::<crate::error::Result<Vec<_>>>()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let access_hashes =
+        sealed::access_hashes(identity, &friends).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let count = access_hashes.len();
+    let msg =
+        crate::network::relay_client::RelayClientMessage::RegisterSealedAccess { access_hashes };
+    let json = serde_json::json!({
+        "relay_message": serde_json::to_string(&msg)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?,
+        "count": count,
+    });
+    Ok(JsValue::from_str(&json.to_string()))
+}
+
+// ============================================================================
+// RATCHET SESSIONS
+// ============================================================================
+
+/// Receive a version 2 `chat_message` or `message_edit` relay payload.
+///
+/// The envelope is decrypted (advancing or accepting the ratchet session)
+/// and stored under the static conversation key, so
+/// `umbra_wasm_messaging_decrypt` reads it like any other DM. New messages
+/// are stored as by `umbra_wasm_messaging_store_incoming`.
+///
+/// Takes JSON: { "envelope": <messageEnvelope>, "thread_id"?: "..." }
+/// Returns JSON: { "message_id", "conversation_id", "edited" }
+#[wasm_bindgen]
+pub fn umbra_wasm_messaging_receive_envelope(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let envelope: crate::messaging::MessageEnvelope =
+        serde_json::from_value(data["envelope"].clone())
+            .map_err(|e| JsValue::from_str(&format!("Invalid envelope: {}", e)))?;
 
-    let message_id = data["message_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-    let emoji = data["emoji"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing emoji"))?;
-
-    let our_did = identity.did_string();
-    let reaction_id = uuid::Uuid::new_v4().to_string();
-    let now = crate::time::now_timestamp_millis();
+    let state = get_state()?;
+    let guard = state.read();
 
-    database
-        .add_reaction(&reaction_id, message_id, &our_did, emoji, now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to add reaction: {}", e)))?;
+    let identity = guard
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = guard
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    // Return all reactions for the message
-    let reactions = database
-        .get_reactions(message_id)
-        .map_err(|e| JsValue::from_str(&format!("Failed to get reactions: {}", e)))?;
+    let friend = load_friend(database, &envelope.sender_did)?;
+    let service = messaging_service(identity, database)?;
+    let message = service
+        .open(&envelope, &friend)
+        .map_err(|e| JsValue::from_str(&format!("Decryption failed: {}", e)))?;
+    let crate::messaging::MessageContent::Text(text) = &message.content else {
+        return Err(JsValue::from_str("Unsupported envelope content"));
+    };
+    let conversation_id = deterministic_conversation_id(&identity.did_string(), &friend.did);
+    if message.conversation_id != conversation_id {
+        return Err(JsValue::from_str("Envelope is for another conversation"));
+    }
 
-    let reactions_json: Vec<serde_json::Value> = reactions
-        .iter()
-        .map(|r| {
-            serde_json::json!({
-                "id": r.id,
-                "message_id": r.message_id,
-                "user_did": r.user_did,
-                "emoji": r.emoji,
-                "created_at": r.created_at,
-            })
-        })
-        .collect();
+    let (nonce, ciphertext) = service
+        .encrypt_for_storage(
+            &friend,
+            &conversation_id,
+            &message.sender_did,
+            message.timestamp,
+            text,
+        )
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "message",
-        &serde_json::json!({
-            "type": "reactionAdded",
-            "messageId": message_id,
-            "emoji": emoji,
-            "userDid": our_did,
-        }),
-    );
+    // An edit comes back under the ID of the message it replaces
+    let existing = database
+        .get_message(&message.id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let edited = match existing {
+        Some(existing)
+            if existing.sender_did == message.sender_did
+                && existing.conversation_id == conversation_id =>
+        {
+            database
+                .edit_message(&message.id, &ciphertext, &nonce.0, message.timestamp)
+                .map_err(|e| JsValue::from_str(&format!("Failed to edit: {}", e)))?;
+            let _ = database.index_message(&message.id, text);
+            emit_event(
+                "message",
+                &serde_json::json!({
+                    "type": "messageEdited",
+                    "messageId": message.id,
+                    "newText": text,
+                    "editedAt": message.timestamp,
+                }),
+            );
+            true
+        }
+        Some(_) => return Err(JsValue::from_str("Message ID already in use")),
+        None => false,
+    };
 
-    let result = serde_json::json!({ "reactions": reactions_json });
+    let result = serde_json::json!({
+        "message_id": message.id,
+        "conversation_id": conversation_id,
+        "edited": edited,
+    });
+    if !edited {
+        let incoming = serde_json::json!({
+            "message_id": message.id,
+            "conversation_id": conversation_id,
+            "sender_did": message.sender_did,
+            "content_encrypted": base64::engine::general_purpose::STANDARD.encode(&ciphertext),
+            "nonce": hex::encode(nonce.0),
+            "timestamp": message.timestamp,
+            "expires_in": message.expires_in,
+            "thread_id": data["thread_id"].as_str(),
+        });
+        // store_incoming takes the state lock itself
+        drop(guard);
+        umbra_wasm_messaging_store_incoming(&incoming.to_string())?;
+    }
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Remove a reaction from a message.
+/// Give a friend a prekey bundle, so they can open a ratchet session with
+/// us. Each call uses up a new one-time prekey.
 ///
-/// Takes JSON: { "message_id": "...", "emoji": "..." }
-/// Returns JSON: { "reactions": [...] } — remaining reactions
+/// Takes JSON: { "to_did": "..." }
+/// Returns JSON: { "relay_messages" }, a `prekey_bundle` envelope
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_remove_reaction(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_publish_prekeys(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -2156,59 +2354,33 @@ pub fn umbra_wasm_messaging_remove_reaction(json: &str) -> Result<JsValue, JsVal
 
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let message_id = data["message_id"]
+    let to_did = data["to_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-    let emoji = data["emoji"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing emoji"))?;
-
-    let our_did = identity.did_string();
-
-    database
-        .remove_reaction(message_id, &our_did, emoji)
-        .map_err(|e| JsValue::from_str(&format!("Failed to remove reaction: {}", e)))?;
-
-    let reactions = database
-        .get_reactions(message_id)
-        .map_err(|e| JsValue::from_str(&format!("Failed to get reactions: {}", e)))?;
-
-    let reactions_json: Vec<serde_json::Value> = reactions
-        .iter()
-        .map(|r| {
-            serde_json::json!({
-                "id": r.id,
-                "message_id": r.message_id,
-                "user_did": r.user_did,
-                "emoji": r.emoji,
-                "created_at": r.created_at,
-            })
-        })
-        .collect();
+        .ok_or_else(|| JsValue::from_str("Missing to_did"))?;
 
-    emit_event(
-        "message",
-        &serde_json::json!({
-            "type": "reactionRemoved",
-            "messageId": message_id,
-            "emoji": emoji,
-            "userDid": our_did,
-        }),
-    );
+    load_friend(database, to_did)?;
+    let bundle = messaging_service(identity, database)?
+        .prekey_bundle()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let result = serde_json::json!({ "reactions": reactions_json });
-    Ok(JsValue::from_str(&result.to_string()))
+    let envelope = serde_json::json!({
+        "envelope": "prekey_bundle",
+        "version": 1,
+        "payload": { "senderDid": identity.did_string(), "bundle": bundle },
+    });
+    let json = serde_json::json!({
+        "relay_messages": [{ "to_did": to_did, "payload": envelope.to_string() }],
+    });
+    Ok(JsValue::from_str(&json.to_string()))
 }
 
-/// Forward a message to another conversation.
-///
-/// Copies the message content (re-encrypted) to the target conversation.
+/// Ask a friend for a prekey bundle; they answer with
+/// `umbra_wasm_messaging_publish_prekeys`.
 ///
-/// Takes JSON: { "message_id": "...", "target_conversation_id": "..." }
-/// Returns JSON: { "new_message_id", "target_conversation_id" }
+/// Takes JSON: { "to_did": "..." }
+/// Returns JSON: { "relay_messages" }, a `prekey_request` envelope
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_forward(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_request_prekeys(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -2223,162 +2395,39 @@ pub fn umbra_wasm_messaging_forward(json: &str) -> Result<JsValue, JsValue> {
 
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let message_id = data["message_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-    let target_conversation_id = data["target_conversation_id"]
+    let to_did = data["to_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing target_conversation_id"))?;
+        .ok_or_else(|| JsValue::from_str("Missing to_did"))?;
 
-    // Look up original message to get its content
-    let original = database
-        .get_message(message_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Original message not found"))?;
-
-    // Decrypt the original message first
-    let original_conv = database
-        .get_conversation(&original.conversation_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Original conversation not found"))?;
-
-    let orig_friend_did = original_conv
-        .friend_did
-        .as_deref()
-        .ok_or_else(|| JsValue::from_str("Cannot forward from group conversation yet"))?;
-
-    let orig_friend = database
-        .get_friend(orig_friend_did)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Friend not found for original conversation"))?;
-
-    let orig_enc_bytes = hex::decode(&orig_friend.encryption_key)
-        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
-    let mut orig_enc_key = [0u8; 32];
-    if orig_enc_bytes.len() != 32 {
-        return Err(JsValue::from_str("Key must be 32 bytes"));
-    }
-    orig_enc_key.copy_from_slice(&orig_enc_bytes);
-
-    let orig_ct = hex::decode(&original.content_encrypted)
-        .map_err(|e| JsValue::from_str(&format!("Invalid ciphertext hex: {}", e)))?;
-    let orig_nonce_bytes = hex::decode(&original.nonce)
-        .map_err(|e| JsValue::from_str(&format!("Invalid nonce hex: {}", e)))?;
-    if orig_nonce_bytes.len() != 12 {
-        return Err(JsValue::from_str("Original nonce must be 12 bytes"));
-    }
-    let mut orig_nonce_arr = [0u8; 12];
-    orig_nonce_arr.copy_from_slice(&orig_nonce_bytes);
-    let orig_nonce = crate::crypto::Nonce(orig_nonce_arr);
-
-    let our_did = identity.did_string();
-    let orig_sender = &original.sender_did;
-    let orig_aad = format!(
-        "{}{}{}",
-        orig_sender,
-        if orig_sender == &our_did {
-            orig_friend_did
-        } else {
-            &our_did
-        },
-        original.timestamp
-    );
-
-    let plaintext = crate::crypto::decrypt_from_sender(
-        &identity.keypair().encryption,
-        &orig_enc_key,
-        original.conversation_id.as_bytes(),
-        &orig_nonce,
-        &orig_ct,
-        orig_aad.as_bytes(),
-    )
-    .map_err(|e| JsValue::from_str(&format!("Failed to decrypt original: {}", e)))?;
-
-    // Re-encrypt for target conversation
-    let target_conv = database
-        .get_conversation(target_conversation_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Target conversation not found"))?;
-
-    let target_friend_did = target_conv
-        .friend_did
-        .as_deref()
-        .ok_or_else(|| JsValue::from_str("Cannot forward to group conversation yet"))?;
-
-    let target_friend = database
-        .get_friend(target_friend_did)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Friend not found for target conversation"))?;
-
-    let target_enc_bytes = hex::decode(&target_friend.encryption_key)
-        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
-    let mut target_enc_key = [0u8; 32];
-    if target_enc_bytes.len() != 32 {
-        return Err(JsValue::from_str("Key must be 32 bytes"));
-    }
-    target_enc_key.copy_from_slice(&target_enc_bytes);
-
-    let new_msg_id = uuid::Uuid::new_v4().to_string();
-    let timestamp = crate::time::now_timestamp_millis();
-    let aad = format!("{}{}{}", our_did, target_friend_did, timestamp);
-
-    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
-        &identity.keypair().encryption,
-        &target_enc_key,
-        target_conversation_id.as_bytes(),
-        &plaintext,
-        aad.as_bytes(),
-    )
-    .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
-
-    database
-        .store_message_extended(
-            &new_msg_id,
-            target_conversation_id,
-            &our_did,
-            &ciphertext,
-            &nonce.0,
-            timestamp,
-            None,
-            None,
-            Some(message_id),
-        )
-        .map_err(|e| JsValue::from_str(&format!("Failed to store forwarded: {}", e)))?;
-
-    emit_event(
-        "message",
-        &serde_json::json!({
-            "type": "messageSent",
-            "message": {
-                "id": new_msg_id,
-                "conversation_id": target_conversation_id,
-                "sender_did": our_did,
-                "status": "sent",
-                "timestamp": timestamp,
-                "forwarded_from": message_id,
-            }
-        }),
-    );
-
-    let result = serde_json::json!({
-        "new_message_id": new_msg_id,
-        "target_conversation_id": target_conversation_id,
+    load_friend(database, to_did)?;
+    let envelope = serde_json::json!({
+        "envelope": "prekey_request",
+        "version": 1,
+        "payload": { "senderDid": identity.did_string() },
     });
-    Ok(JsValue::from_str(&result.to_string()))
+    let json = serde_json::json!({
+        "relay_messages": [{ "to_did": to_did, "payload": envelope.to_string() }],
+    });
+    Ok(JsValue::from_str(&json.to_string()))
 }
 
-/// Get thread replies for a message.
+/// Open a ratchet session from a friend's `prekey_bundle`. Messages to them
+/// use `MessageEnvelope`s from then on.
 ///
-/// Returns all messages in the thread (parent + replies).
+/// A bundle is ignored if we already have a session with the friend, so a
+/// replayed one can't reset it.
 ///
-/// Takes JSON: { "parent_id": "..." }
-/// Returns JSON: [message, message, ...]
+/// Takes JSON: { "sender_did": "...", "bundle": {...} }
+/// Returns JSON: { "established" }
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_get_thread(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_receive_prekeys(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
@@ -2386,52 +2435,40 @@ pub fn umbra_wasm_messaging_get_thread(json: &str) -> Result<JsValue, JsValue> {
 
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let parent_id = data["parent_id"]
+    let sender_did = data["sender_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing parent_id"))?;
-
-    let messages = database
-        .get_thread_messages(parent_id)
-        .map_err(|e| JsValue::from_str(&format!("Failed to get thread: {}", e)))?;
-
-    let messages_json: Vec<serde_json::Value> = messages
-        .iter()
-        .map(|m| {
-            serde_json::json!({
-                "id": m.id,
-                "conversation_id": m.conversation_id,
-                "sender_did": m.sender_did,
-                "content_encrypted": hex_to_base64_safe(&m.content_encrypted),
-                "nonce": m.nonce,
-                "timestamp": m.timestamp,
-                "delivered": m.delivered,
-                "read": m.read,
-                "edited": m.edited,
-                "edited_at": m.edited_at,
-                "deleted": m.deleted,
-                "deleted_at": m.deleted_at,
-                "pinned": m.pinned,
-                "reply_to_id": m.reply_to_id,
-                "thread_id": m.thread_id,
-                "forwarded_from": m.forwarded_from,
-            })
-        })
-        .collect();
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
+    let bundle: crate::crypto::PrekeyBundle = serde_json::from_value(data["bundle"].clone())
+        .map_err(|e| JsValue::from_str(&format!("Invalid bundle: {}", e)))?;
 
+    let friend = load_friend(database, sender_did)?;
+    let service = messaging_service(identity, database)?;
+    let established = !service
+        .has_session(&friend.did)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    if established {
+        service
+            .establish_session(&friend, &bundle)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    }
     Ok(JsValue::from_str(
-        &serde_json::to_string(&messages_json).unwrap_or_default(),
+        &serde_json::json!({ "established": established }).to_string(),
     ))
 }
 
-/// Send a reply in a thread.
+// ============================================================================
+// MESSAGING FEATURES (edit, delete, pin, react, thread, forward)
+// ============================================================================
+
+/// Edit a message's content.
 ///
-/// Creates a new message with thread_id pointing to the parent.
+/// Re-encrypts the new text and updates the message in the database.
+/// Only the original sender can edit their own messages.
 ///
-/// Takes JSON: { "parent_id": "...", "text": "..." }
-/// Returns JSON: same as send_message, with thread_id set
+/// Takes JSON: { "message_id": "...", "new_text": "..." }
+/// Returns JSON: { "message_id", "edited_at", "content_encrypted", "nonce" }
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_reply_thread(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_edit(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -2447,134 +2484,102 @@ pub fn umbra_wasm_messaging_reply_thread(json: &str) -> Result<JsValue, JsValue>
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let parent_id = data["parent_id"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing parent_id"))?;
-    let text = data["text"]
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+    let new_text = data["new_text"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing text"))?;
+        .ok_or_else(|| JsValue::from_str("Missing new_text"))?;
 
-    // Look up parent message to get conversation_id
-    let parent = database
-        .get_message(parent_id)
+    // Look up the message to verify ownership
+    let message = database
+        .get_message(message_id)
         .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Parent message not found"))?;
+        .ok_or_else(|| JsValue::from_str("Message not found"))?;
 
-    let conversation_id = &parent.conversation_id;
+    let our_did = identity.did_string();
+    if message.sender_did != our_did {
+        return Err(JsValue::from_str("Cannot edit: you are not the sender"));
+    }
 
-    // Look up conversation + friend for encryption
+    // Look up friend encryption key for re-encryption
     let conversation = database
-        .get_conversation(conversation_id)
+        .get_conversation(&message.conversation_id)
         .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
         .ok_or_else(|| JsValue::from_str("Conversation not found"))?;
 
     let friend_did = conversation
         .friend_did
         .as_deref()
-        .ok_or_else(|| JsValue::from_str("Cannot thread in group conversation yet"))?;
+        .ok_or_else(|| JsValue::from_str("Cannot edit in group conversation yet"))?;
 
-    let friend_record = database
-        .get_friend(friend_did)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Friend not found"))?;
+    let friend = load_friend(database, friend_did)?;
 
-    let friend_enc_bytes = hex::decode(&friend_record.encryption_key)
-        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
-    let mut friend_enc_key = [0u8; 32];
-    if friend_enc_bytes.len() != 32 {
-        return Err(JsValue::from_str("Key must be 32 bytes"));
-    }
-    friend_enc_key.copy_from_slice(&friend_enc_bytes);
+    // The edit reuses the message ID, which the envelope signature covers,
+    // so the recipient can tell it from a new message
+    let mut edit = crate::messaging::Message::new(
+        message.conversation_id.clone(),
+        our_did,
+        friend_did.to_string(),
+        crate::messaging::MessageContent::text(new_text),
+    );
+    edit.id = message_id.to_string();
+    let edited_at = edit.timestamp;
+    let outgoing = messaging_service(identity, database)?
+        .encrypt_text(&edit, &friend)
+        .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+    let (nonce, ciphertext) = (outgoing.nonce, outgoing.ciphertext.clone());
 
-    let our_did = identity.did_string();
-    let message_id = uuid::Uuid::new_v4().to_string();
-    let timestamp = crate::time::now_timestamp_millis();
-    let aad = format!("{}{}{}", our_did, friend_did, timestamp);
+    database
+        .edit_message(message_id, &ciphertext, &nonce.0, edited_at)
+        .map_err(|e| JsValue::from_str(&format!("Failed to edit: {}", e)))?;
+    let _ = database.index_message(message_id, new_text);
 
-    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
-        &identity.keypair().encryption,
-        &friend_enc_key,
-        conversation_id.as_bytes(),
-        text.as_bytes(),
-        aad.as_bytes(),
-    )
-    .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "messageEdited",
+            "messageId": message_id,
+            "newText": new_text,
+            "editedAt": edited_at,
+        }),
+    );
 
-    database
-        .store_message_extended(
-            &message_id,
-            conversation_id,
-            &our_did,
-            &ciphertext,
-            &nonce.0,
-            timestamp,
-            Some(parent_id),
-            Some(parent_id),
-            None,
-        )
-        .map_err(|e| JsValue::from_str(&format!("Failed to store reply: {}", e)))?;
-
-    emit_event(
-        "message",
-        &serde_json::json!({
-            "type": "messageSent",
-            "message": {
-                "id": message_id,
-                "conversation_id": conversation_id,
-                "sender_did": our_did,
-                "content": { "type": "text", "text": text },
-                "status": "sent",
-                "timestamp": timestamp,
-                "thread_id": parent_id,
-                "reply_to_id": parent_id,
-            }
-        }),
-    );
-
-    let ct_b64 = base64::engine::general_purpose::STANDARD.encode(&ciphertext);
-    let nonce_hex = hex::encode(&nonce.0);
-
-    // Build relay envelope so TS only has to call ws.send()
-    let relay_envelope = serde_json::json!({
-        "envelope": "chat_message",
-        "version": 1,
-        "payload": {
-            "messageId": message_id,
-            "conversationId": conversation_id,
-            "senderDid": our_did,
-            "contentEncrypted": ct_b64,
-            "nonce": nonce_hex,
-            "timestamp": timestamp,
-            "threadId": parent_id,
-        }
-    });
+    // Older peers get no relayed edit from here, as before
+    let relay_messages: Vec<serde_json::Value> = outgoing
+        .envelope
+        .iter()
+        .map(|envelope| {
+            let payload = dm_envelope_payload("message_edit", envelope, serde_json::json!({}));
+            serde_json::json!({ "to_did": friend_did, "payload": payload.to_string() })
+        })
+        .collect();
 
     let result = serde_json::json!({
-        "id": message_id,
-        "conversation_id": conversation_id,
-        "sender_did": our_did,
-        "friend_did": friend_did,
-        "timestamp": timestamp,
-        "thread_id": parent_id,
-        "content_encrypted": ct_b64,
-        "nonce": nonce_hex,
-        "relay_messages": [{
-            "to_did": friend_did,
-            "payload": relay_envelope.to_string(),
-        }],
+        "message_id": message_id,
+        "edited_at": edited_at,
+        "content_encrypted": base64::engine::general_purpose::STANDARD.encode(&ciphertext),
+        "nonce": hex::encode(&nonce.0),
+        "relay_messages": relay_messages,
     });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Get all pinned messages in a conversation.
+/// Soft-delete a message.
 ///
-/// Takes JSON: { "conversation_id": "..." }
-/// Returns JSON: [message, ...]
+/// Only the original sender can delete their own messages.
+///
+/// Takes JSON: { "message_id": "..." }
+/// Returns JSON: { "message_id", "deleted_at" }
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_get_pinned(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_delete(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
@@ -2583,48 +2588,47 @@ pub fn umbra_wasm_messaging_get_pinned(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let conversation_id = data["conversation_id"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
 
-    let messages = database
-        .get_pinned_messages(conversation_id)
-        .map_err(|e| JsValue::from_str(&format!("Failed to get pinned: {}", e)))?;
+    let message = database
+        .get_message(message_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Message not found"))?;
 
-    let messages_json: Vec<serde_json::Value> = messages
-        .iter()
-        .map(|m| {
-            serde_json::json!({
-                "id": m.id,
-                "conversation_id": m.conversation_id,
-                "sender_did": m.sender_did,
-                "content_encrypted": hex_to_base64_safe(&m.content_encrypted),
-                "nonce": m.nonce,
-                "timestamp": m.timestamp,
-                "pinned": true,
-                "pinned_by": m.pinned_by,
-                "pinned_at": m.pinned_at,
-                "edited": m.edited,
-                "deleted": m.deleted,
-            })
-        })
-        .collect();
+    let our_did = identity.did_string();
+    if message.sender_did != our_did {
+        return Err(JsValue::from_str("Cannot delete: you are not the sender"));
+    }
 
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&messages_json).unwrap_or_default(),
-    ))
-}
+    let deleted_at = crate::time::now_timestamp_millis();
+    database
+        .delete_message(message_id, deleted_at)
+        .map_err(|e| JsValue::from_str(&format!("Failed to delete: {}", e)))?;
 
-// ============================================================================
-// GROUP OPERATIONS
-// ============================================================================
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "messageDeleted",
+            "messageId": message_id,
+            "deletedAt": deleted_at,
+        }),
+    );
 
-/// Create a new group.
+    let result = serde_json::json!({
+        "message_id": message_id,
+        "deleted_at": deleted_at,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Pin a message in a conversation.
 ///
-/// Takes JSON: { "name": "...", "description": "..." (optional) }
-/// Returns JSON: { "group_id", "conversation_id", "name" }
+/// Takes JSON: { "message_id": "..." }
+/// Returns JSON: { "message_id", "pinned_by", "pinned_at" }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_create(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_pin(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -2640,138 +2644,89 @@ pub fn umbra_wasm_groups_create(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let name = data["name"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing name"))?;
-    let description = data["description"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
 
     let our_did = identity.did_string();
-    let group_id = uuid::Uuid::new_v4().to_string();
-    let conv_id = format!("group-{}", group_id);
-    let now = crate::time::now_timestamp_millis();
-
-    // Create the group
-    database
-        .create_group(&group_id, name, description, &our_did, now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to create group: {}", e)))?;
-
-    // Add self as admin
-    let display_name = identity.profile().display_name.clone();
-    database
-        .add_group_member(&group_id, &our_did, Some(&display_name), "admin", now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to add self to group: {}", e)))?;
+    let pinned_at = crate::time::now_timestamp_millis();
 
-    // Create group conversation
     database
-        .create_group_conversation(&conv_id, &group_id)
-        .map_err(|e| JsValue::from_str(&format!("Failed to create group conversation: {}", e)))?;
+        .pin_message(message_id, &our_did, pinned_at)
+        .map_err(|e| JsValue::from_str(&format!("Failed to pin: {}", e)))?;
 
-    // Generate and store group encryption key
-    let group_key: [u8; 32] = {
-        let mut key = [0u8; 32];
-        getrandom::getrandom(&mut key)
-            .map_err(|e| JsValue::from_str(&format!("Failed to generate group key: {}", e)))?;
-        key
-    };
-    let wrapping_key = derive_key_wrapping_key(identity);
-    let wrap_enc_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
-    let aad = format!("group-key:{}:1", group_id);
-    let (nonce, ciphertext) = crate::crypto::encrypt(&wrap_enc_key, &group_key, aad.as_bytes())
-        .map_err(|e| JsValue::from_str(&format!("Failed to encrypt group key: {}", e)))?;
-    let mut stored_key = Vec::with_capacity(12 + ciphertext.len());
-    stored_key.extend_from_slice(&nonce.0);
-    stored_key.extend_from_slice(&ciphertext);
-    database
-        .store_group_key(&group_id, 1, &stored_key, now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "messagePinned",
+            "messageId": message_id,
+            "pinnedBy": our_did,
+            "pinnedAt": pinned_at,
+        }),
+    );
 
     let result = serde_json::json!({
-        "group_id": group_id,
-        "conversation_id": conv_id,
-        "name": name,
-        "created_by": our_did,
-        "created_at": now,
+        "message_id": message_id,
+        "pinned_by": our_did,
+        "pinned_at": pinned_at,
     });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Get group info by ID.
+/// Unpin a message.
 ///
-/// Takes: group_id as string
-/// Returns JSON: { group fields }
+/// Takes JSON: { "message_id": "..." }
+/// Returns JSON: { "message_id" }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_get(group_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_unpin(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
+    let _identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let group = database
-        .get_group(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Group not found"))?;
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let result = serde_json::json!({
-        "id": group.id,
-        "name": group.name,
-        "description": group.description,
-        "avatar": group.avatar,
-        "created_by": group.created_by,
-        "created_at": group.created_at,
-        "updated_at": group.updated_at,
-    });
+    let message_id = data["message_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+
+    database
+        .unpin_message(message_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to unpin: {}", e)))?;
+
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "messageUnpinned",
+            "messageId": message_id,
+        }),
+    );
+
+    let result = serde_json::json!({ "message_id": message_id });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// List all groups.
+/// Add a reaction (emoji) to a message.
 ///
-/// Returns JSON: [group, ...]
+/// Takes JSON: { "message_id": "...", "emoji": "..." }
+/// Returns JSON: { "reactions": [...] } — all reactions for the message
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_list() -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_add_reaction(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let database = state
-        .database
+    let identity = state
+        .identity
         .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-
-    let groups = database
-        .get_all_groups()
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
-
-    let groups_json: Vec<serde_json::Value> = groups
-        .iter()
-        .map(|g| {
-            serde_json::json!({
-                "id": g.id,
-                "name": g.name,
-                "description": g.description,
-                "avatar": g.avatar,
-                "created_by": g.created_by,
-                "created_at": g.created_at,
-                "updated_at": g.updated_at,
-            })
-        })
-        .collect();
-
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&groups_json).unwrap_or_default(),
-    ))
-}
-
-/// Update a group's name/description.
-///
-/// Takes JSON: { "group_id": "...", "name": "...", "description": "..." }
-/// Returns JSON: { "group_id", "updated_at" }
-#[wasm_bindgen]
-pub fn umbra_wasm_groups_update(json: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
@@ -2780,74 +2735,66 @@ pub fn umbra_wasm_groups_update(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let group_id = data["group_id"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let name = data["name"]
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+    let emoji = data["emoji"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing name"))?;
-    let description = data["description"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing emoji"))?;
+
+    let our_did = identity.did_string();
+    let reaction_id = uuid::Uuid::new_v4().to_string();
     let now = crate::time::now_timestamp_millis();
 
     database
-        .update_group(group_id, name, description, now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to update group: {}", e)))?;
-
-    let result = serde_json::json!({
-        "group_id": group_id,
-        "updated_at": now,
-    });
-    Ok(JsValue::from_str(&result.to_string()))
-}
-
-/// Delete a group (admin only).
-///
-/// Takes: group_id as string
-/// Returns JSON: { "group_id" }
-#[wasm_bindgen]
-pub fn umbra_wasm_groups_delete(group_id: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
-    let identity = state
-        .identity
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+        .add_reaction(&reaction_id, message_id, &our_did, emoji, now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to add reaction: {}", e)))?;
 
-    // Verify admin
-    let our_did = identity.did_string();
-    let members = database
-        .get_group_members(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    // Return all reactions for the message
+    let reactions = database
+        .get_reactions(message_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to get reactions: {}", e)))?;
 
-    let is_admin = members
+    let reactions_json: Vec<serde_json::Value> = reactions
         .iter()
-        .any(|m| m.member_did == our_did && m.role == "admin");
-    if !is_admin {
-        return Err(JsValue::from_str("Only admins can delete groups"));
-    }
+        .map(|r| {
+            serde_json::json!({
+                "id": r.id,
+                "message_id": r.message_id,
+                "user_did": r.user_did,
+                "emoji": r.emoji,
+                "created_at": r.created_at,
+            })
+        })
+        .collect();
 
-    database
-        .delete_group(group_id)
-        .map_err(|e| JsValue::from_str(&format!("Failed to delete group: {}", e)))?;
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "reactionAdded",
+            "messageId": message_id,
+            "emoji": emoji,
+            "userDid": our_did,
+        }),
+    );
 
-    let result = serde_json::json!({ "group_id": group_id });
+    let result = serde_json::json!({ "reactions": reactions_json });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Add a member to a group.
+/// Remove a reaction from a message.
 ///
-/// Takes JSON: { "group_id": "...", "did": "...", "display_name": "..." (optional) }
-/// Returns JSON: { "group_id", "member_did" }
+/// Takes JSON: { "message_id": "...", "emoji": "..." }
+/// Returns JSON: { "reactions": [...] } — remaining reactions
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_add_member(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_remove_reaction(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
@@ -2856,35 +2803,65 @@ pub fn umbra_wasm_groups_add_member(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let group_id = data["group_id"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let did = data["did"]
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+    let emoji = data["emoji"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing did"))?;
-    let display_name = data["display_name"].as_str();
-    let now = crate::time::now_timestamp_millis();
+        .ok_or_else(|| JsValue::from_str("Missing emoji"))?;
+
+    let our_did = identity.did_string();
 
     database
-        .add_group_member(group_id, did, display_name, "member", now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to add member: {}", e)))?;
+        .remove_reaction(message_id, &our_did, emoji)
+        .map_err(|e| JsValue::from_str(&format!("Failed to remove reaction: {}", e)))?;
 
-    let result = serde_json::json!({
-        "group_id": group_id,
-        "member_did": did,
-    });
+    let reactions = database
+        .get_reactions(message_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to get reactions: {}", e)))?;
+
+    let reactions_json: Vec<serde_json::Value> = reactions
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "id": r.id,
+                "message_id": r.message_id,
+                "user_did": r.user_did,
+                "emoji": r.emoji,
+                "created_at": r.created_at,
+            })
+        })
+        .collect();
+
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "reactionRemoved",
+            "messageId": message_id,
+            "emoji": emoji,
+            "userDid": our_did,
+        }),
+    );
+
+    let result = serde_json::json!({ "reactions": reactions_json });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Remove a member from a group.
+/// Forward a message to another conversation.
 ///
-/// Takes JSON: { "group_id": "...", "did": "..." }
-/// Returns JSON: { "group_id", "member_did" }
+/// Copies the message content (re-encrypted) to the target conversation.
+///
+/// Takes JSON: { "message_id": "...", "target_conversation_id": "..." }
+/// Returns JSON: { "new_message_id", "target_conversation_id" }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_remove_member(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_forward(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
@@ -2893,181 +2870,214 @@ pub fn umbra_wasm_groups_remove_member(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let group_id = data["group_id"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let did = data["did"]
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+    let target_conversation_id = data["target_conversation_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing target_conversation_id"))?;
 
-    database
-        .remove_group_member(group_id, did)
-        .map_err(|e| JsValue::from_str(&format!("Failed to remove member: {}", e)))?;
+    // Look up original message to get its content
+    let original = database
+        .get_message(message_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Original message not found"))?;
 
-    let result = serde_json::json!({
-        "group_id": group_id,
-        "member_did": did,
-    });
-    Ok(JsValue::from_str(&result.to_string()))
-}
+    // Decrypt the original message first
+    let original_conv = database
+        .get_conversation(&original.conversation_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Original conversation not found"))?;
 
-/// Get all members of a group.
-///
-/// Takes: group_id as string
-/// Returns JSON: [member, ...]
-#[wasm_bindgen]
-pub fn umbra_wasm_groups_get_members(group_id: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
+    let orig_friend_did = original_conv
+        .friend_did
+        .as_deref()
+        .ok_or_else(|| JsValue::from_str("Cannot forward from group conversation yet"))?;
 
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    let orig_friend = database
+        .get_friend(orig_friend_did)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Friend not found for original conversation"))?;
 
-    let members = database
-        .get_group_members(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let orig_enc_bytes = hex::decode(&orig_friend.encryption_key)
+        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
+    let mut orig_enc_key = [0u8; 32];
+    if orig_enc_bytes.len() != 32 {
+        return Err(JsValue::from_str("Key must be 32 bytes"));
+    }
+    orig_enc_key.copy_from_slice(&orig_enc_bytes);
 
-    let members_json: Vec<serde_json::Value> = members
-        .iter()
-        .map(|m| {
-            serde_json::json!({
-                "group_id": m.group_id,
-                "member_did": m.member_did,
-                "display_name": m.display_name,
-                "role": m.role,
-                "joined_at": m.joined_at,
-            })
-        })
-        .collect();
+    let orig_ct = hex::decode(&original.content_encrypted)
+        .map_err(|e| JsValue::from_str(&format!("Invalid ciphertext hex: {}", e)))?;
+    let orig_nonce_bytes = hex::decode(&original.nonce)
+        .map_err(|e| JsValue::from_str(&format!("Invalid nonce hex: {}", e)))?;
+    if orig_nonce_bytes.len() != 12 {
+        return Err(JsValue::from_str("Original nonce must be 12 bytes"));
+    }
+    let mut orig_nonce_arr = [0u8; 12];
+    orig_nonce_arr.copy_from_slice(&orig_nonce_bytes);
+    let orig_nonce = crate::crypto::Nonce(orig_nonce_arr);
 
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&members_json).unwrap_or_default(),
-    ))
-}
+    let our_did = identity.did_string();
+    let orig_sender = &original.sender_did;
+    let orig_aad = format!(
+        "{}{}{}",
+        orig_sender,
+        if orig_sender == &our_did {
+            orig_friend_did
+        } else {
+            &our_did
+        },
+        original.timestamp
+    );
 
-// ============================================================================
-// GROUP ENCRYPTION
-// ============================================================================
+    let plaintext = crate::crypto::decrypt_from_sender(
+        &identity.keypair().encryption,
+        &orig_enc_key,
+        original.conversation_id.as_bytes(),
+        &orig_nonce,
+        &orig_ct,
+        orig_aad.as_bytes(),
+    )
+    .map_err(|e| JsValue::from_str(&format!("Failed to decrypt original: {}", e)))?;
 
-/// Generate a shared group key for a newly created group.
-///
-/// Creates a random 256-bit AES key, encrypts it with the creator's
-/// key-wrapping key (derived from identity via HKDF), and stores it
-/// in the group_keys table.
-///
-/// Takes: group_id as string
-/// Returns JSON: { "group_id", "key_version", "raw_key_hex" }
-///
-/// The raw_key_hex is returned so it can be encrypted per-member
-/// for relay distribution (ECDH with each invitee).
-#[wasm_bindgen]
-pub fn umbra_wasm_groups_generate_key(group_id: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
+    // Re-encrypt for target conversation
+    let target_conv = database
+        .get_conversation(target_conversation_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Target conversation not found"))?;
 
-    let identity = state
-        .identity
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    let target_friend_did = target_conv
+        .friend_did
+        .as_deref()
+        .ok_or_else(|| JsValue::from_str("Cannot forward to group conversation yet"))?;
 
-    // Generate random 256-bit key
-    let mut raw_key = [0u8; 32];
-    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut raw_key);
+    let target_friend = database
+        .get_friend(target_friend_did)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Friend not found for target conversation"))?;
 
-    // Encrypt with own key-wrapping key (HKDF from identity)
-    let wrapping_key = derive_key_wrapping_key(identity);
-    let encryption_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
-    let aad = format!("group-key:{}", group_id);
-    let (nonce, encrypted) = crate::crypto::encrypt(&encryption_key, &raw_key, aad.as_bytes())
-        .map_err(|e| JsValue::from_str(&format!("Failed to encrypt group key: {}", e)))?;
+    let target_enc_bytes = hex::decode(&target_friend.encryption_key)
+        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
+    let mut target_enc_key = [0u8; 32];
+    if target_enc_bytes.len() != 32 {
+        return Err(JsValue::from_str("Key must be 32 bytes"));
+    }
+    target_enc_key.copy_from_slice(&target_enc_bytes);
 
-    // Store: nonce (12 bytes) + encrypted key
-    let mut stored = Vec::with_capacity(12 + encrypted.len());
-    stored.extend_from_slice(&nonce.0);
-    stored.extend_from_slice(&encrypted);
+    let new_msg_id = uuid::Uuid::new_v4().to_string();
+    let timestamp = crate::time::now_timestamp_millis();
+    let aad = format!("{}{}{}", our_did, target_friend_did, timestamp);
+
+    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
+        &identity.keypair().encryption,
+        &target_enc_key,
+        target_conversation_id.as_bytes(),
+        &plaintext,
+        aad.as_bytes(),
+    )
+    .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
 
-    let now = crate::time::now_timestamp_millis();
     database
-        .store_group_key(group_id, 1, &stored, now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
+        .store_message_extended(
+            &new_msg_id,
+            target_conversation_id,
+            &our_did,
+            &ciphertext,
+            &nonce.0,
+            timestamp,
+            None,
+            None,
+            Some(message_id),
+        )
+        .map_err(|e| JsValue::from_str(&format!("Failed to store forwarded: {}", e)))?;
+
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "messageSent",
+            "message": {
+                "id": new_msg_id,
+                "conversation_id": target_conversation_id,
+                "sender_did": our_did,
+                "status": "sent",
+                "timestamp": timestamp,
+                "forwarded_from": message_id,
+            }
+        }),
+    );
 
     let result = serde_json::json!({
-        "group_id": group_id,
-        "key_version": 1,
-        "raw_key_hex": hex::encode(&raw_key),
+        "new_message_id": new_msg_id,
+        "target_conversation_id": target_conversation_id,
     });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Rotate the group key (e.g., after removing a member).
+/// Get thread replies for a message.
 ///
-/// Generates a new AES-256 key, increments the key version, stores it.
+/// Returns all messages in the thread (parent + replies).
 ///
-/// Takes: group_id as string
-/// Returns JSON: { "group_id", "key_version", "raw_key_hex" }
+/// Takes JSON: { "parent_id": "..." }
+/// Returns JSON: [message, message, ...]
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_rotate_key(group_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_get_thread(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let identity = state
-        .identity
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    // Get current key version
-    let current = database
-        .get_latest_group_key(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
-    let new_version = current.map(|k| k.key_version + 1).unwrap_or(1);
-
-    // Generate new random key
-    let mut raw_key = [0u8; 32];
-    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut raw_key);
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    // Encrypt with own wrapping key
-    let wrapping_key = derive_key_wrapping_key(identity);
-    let encryption_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
-    let aad = format!("group-key:{}:{}", group_id, new_version);
-    let (nonce, encrypted) = crate::crypto::encrypt(&encryption_key, &raw_key, aad.as_bytes())
-        .map_err(|e| JsValue::from_str(&format!("Failed to encrypt group key: {}", e)))?;
+    let parent_id = data["parent_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing parent_id"))?;
 
-    let mut stored = Vec::with_capacity(12 + encrypted.len());
-    stored.extend_from_slice(&nonce.0);
-    stored.extend_from_slice(&encrypted);
+    let messages = database
+        .get_thread_messages(parent_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to get thread: {}", e)))?;
 
-    let now = crate::time::now_timestamp_millis();
-    database
-        .store_group_key(group_id, new_version, &stored, now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
+    let messages_json: Vec<serde_json::Value> = messages
+        .iter()
+        .map(|m| {
+            serde_json::json!({
+                "id": m.id,
+                "conversation_id": m.conversation_id,
+                "sender_did": m.sender_did,
+                "content_encrypted": hex_to_base64_safe(&m.content_encrypted),
+                "nonce": m.nonce,
+                "timestamp": m.timestamp,
+                "delivered": m.delivered,
+                "read": m.read,
+                "edited": m.edited,
+                "edited_at": m.edited_at,
+                "deleted": m.deleted,
+                "deleted_at": m.deleted_at,
+                "pinned": m.pinned,
+                "reply_to_id": m.reply_to_id,
+                "thread_id": m.thread_id,
+                "forwarded_from": m.forwarded_from,
+            })
+        })
+        .collect();
 
-    let result = serde_json::json!({
-        "group_id": group_id,
-        "key_version": new_version,
-        "raw_key_hex": hex::encode(&raw_key),
-    });
-    Ok(JsValue::from_str(&result.to_string()))
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&messages_json).unwrap_or_default(),
+    ))
 }
 
-/// Import a group key received from another member (via invite or key rotation).
+/// Send a reply in a thread.
 ///
-/// The key arrives encrypted via ECDH with the sender.
-/// We decrypt it using our X25519 key, then re-encrypt with our wrapping key.
+/// Creates a new message with thread_id pointing to the parent.
 ///
-/// Takes JSON: { "group_id", "key_version", "encrypted_key_hex", "nonce_hex", "sender_did" }
-/// Returns JSON: { "group_id", "key_version" }
+/// Takes JSON: { "parent_id": "...", "text": "..." }
+/// Returns JSON: same as send_message, with thread_id set
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_import_key(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_reply_thread(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -3083,94 +3093,131 @@ pub fn umbra_wasm_groups_import_key(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let group_id = data["group_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let key_version = data["key_version"].as_i64().unwrap_or(1) as i32;
-    let encrypted_key_hex = data["encrypted_key_hex"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing encrypted_key_hex"))?;
-    let nonce_hex = data["nonce_hex"]
+    let parent_id = data["parent_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing nonce_hex"))?;
-    let sender_did = data["sender_did"]
+        .ok_or_else(|| JsValue::from_str("Missing parent_id"))?;
+    let text = data["text"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing text"))?;
 
-    // Look up sender's encryption key
-    let sender = database
-        .get_friend(sender_did)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Sender not found in friends"))?;
+    // Look up parent message to get conversation_id
+    let parent = database
+        .get_message(parent_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Parent message not found"))?;
 
-    let sender_enc_bytes = hex::decode(&sender.encryption_key)
-        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
-    let mut sender_enc_key = [0u8; 32];
-    if sender_enc_bytes.len() != 32 {
-        return Err(JsValue::from_str("Key must be 32 bytes"));
-    }
-    sender_enc_key.copy_from_slice(&sender_enc_bytes);
+    let conversation_id = &parent.conversation_id;
 
-    // Decrypt the group key using ECDH
-    let ciphertext = hex::decode(encrypted_key_hex)
-        .map_err(|e| JsValue::from_str(&format!("Invalid hex: {}", e)))?;
-    let nonce_bytes =
-        hex::decode(nonce_hex).map_err(|e| JsValue::from_str(&format!("Invalid nonce: {}", e)))?;
-    if nonce_bytes.len() != 12 {
-        return Err(JsValue::from_str("Nonce must be 12 bytes"));
-    }
-    let mut nonce_arr = [0u8; 12];
-    nonce_arr.copy_from_slice(&nonce_bytes);
-    let nonce = crate::crypto::Nonce(nonce_arr);
+    // Look up conversation + friend for encryption
+    let conversation = database
+        .get_conversation(conversation_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Conversation not found"))?;
 
-    let aad = format!("group-key-transfer:{}:{}", group_id, key_version);
-    let raw_key = crate::crypto::decrypt_from_sender(
-        &identity.keypair().encryption,
-        &sender_enc_key,
-        group_id.as_bytes(),
-        &nonce,
-        &ciphertext,
-        aad.as_bytes(),
-    )
-    .map_err(|e| JsValue::from_str(&format!("Failed to decrypt group key: {}", e)))?;
+    let friend_did = conversation
+        .friend_did
+        .as_deref()
+        .ok_or_else(|| JsValue::from_str("Cannot thread in group conversation yet"))?;
 
-    // Re-encrypt with our own wrapping key
-    let wrapping_key = derive_key_wrapping_key(identity);
-    let wrap_enc_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
-    let wrap_aad = format!("group-key:{}:{}", group_id, key_version);
-    let (wrap_nonce, wrap_ct) =
-        crate::crypto::encrypt(&wrap_enc_key, &raw_key, wrap_aad.as_bytes())
-            .map_err(|e| JsValue::from_str(&format!("Failed to re-encrypt group key: {}", e)))?;
+    let friend = load_friend(database, friend_did)?;
 
-    let mut stored = Vec::with_capacity(12 + wrap_ct.len());
-    stored.extend_from_slice(&wrap_nonce.0);
-    stored.extend_from_slice(&wrap_ct);
+    let our_did = identity.did_string();
+    let message = crate::messaging::Message::new(
+        conversation_id.clone(),
+        our_did.clone(),
+        friend_did.to_string(),
+        crate::messaging::MessageContent::text(text),
+    );
+    let (message_id, timestamp) = (message.id.clone(), message.timestamp);
+    let outgoing = messaging_service(identity, database)?
+        .encrypt_text(&message, &friend)
+        .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+    let (nonce, ciphertext) = (outgoing.nonce, outgoing.ciphertext.clone());
 
-    let now = crate::time::now_timestamp_millis();
     database
-        .store_group_key(group_id, key_version, &stored, now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
+        .store_message_extended(
+            &message_id,
+            conversation_id,
+            &our_did,
+            &ciphertext,
+            &nonce.0,
+            timestamp,
+            Some(parent_id),
+            Some(parent_id),
+            None,
+        )
+        .map_err(|e| JsValue::from_str(&format!("Failed to store reply: {}", e)))?;
+    let _ = database.index_message(&message_id, text);
+
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "messageSent",
+            "message": {
+                "id": message_id,
+                "conversation_id": conversation_id,
+                "sender_did": our_did,
+                "content": { "type": "text", "text": text },
+                "status": "sent",
+                "timestamp": timestamp,
+                "thread_id": parent_id,
+                "reply_to_id": parent_id,
+            }
+        }),
+    );
+
+    let ct_b64 = base64::engine::general_purpose::STANDARD.encode(&ciphertext);
+    let nonce_hex = hex::encode(&nonce.0);
+
+    // Build relay envelope so TS only has to call ws.send()
+    let relay_envelope = match &outgoing.envelope {
+        Some(envelope) => dm_envelope_payload(
+            "chat_message",
+            envelope,
+            serde_json::json!({ "threadId": parent_id }),
+        ),
+        // Legacy fallback: older peers can only read the static-key payload
+        None => serde_json::json!({
+            "envelope": "chat_message",
+            "version": 1,
+            "payload": {
+                "messageId": message_id,
+                "conversationId": conversation_id,
+                "senderDid": our_did,
+                "contentEncrypted": ct_b64,
+                "nonce": nonce_hex,
+                "timestamp": timestamp,
+                "threadId": parent_id,
+            }
+        }),
+    };
 
     let result = serde_json::json!({
-        "group_id": group_id,
-        "key_version": key_version,
+        "id": message_id,
+        "conversation_id": conversation_id,
+        "sender_did": our_did,
+        "friend_did": friend_did,
+        "timestamp": timestamp,
+        "thread_id": parent_id,
+        "content_encrypted": ct_b64,
+        "nonce": nonce_hex,
+        "relay_messages": [{
+            "to_did": friend_did,
+            "payload": relay_envelope.to_string(),
+        }],
     });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Encrypt a message with the group's shared key.
+/// Get all pinned messages in a conversation.
 ///
-/// Takes JSON: { "group_id", "plaintext" }
-/// Returns JSON: { "ciphertext_hex", "nonce_hex", "key_version" }
+/// Takes JSON: { "conversation_id": "..." }
+/// Returns JSON: [message, ...]
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_encrypt_message(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_messaging_get_pinned(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let identity = state
-        .identity
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
@@ -3179,46 +3226,48 @@ pub fn umbra_wasm_groups_encrypt_message(json: &str) -> Result<JsValue, JsValue>
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let group_id = data["group_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let plaintext = data["plaintext"]
+    let conversation_id = data["conversation_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing plaintext"))?;
-
-    // Get latest group key
-    let key_record = database
-        .get_latest_group_key(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("No group key found"))?;
+        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
 
-    // Decrypt the stored key with our wrapping key
-    let raw_key = decrypt_stored_group_key(identity, &key_record, group_id)?;
+    let messages = database
+        .get_pinned_messages(conversation_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to get pinned: {}", e)))?;
 
-    // Encrypt the message
-    let enc_key = crate::crypto::EncryptionKey::from_bytes(raw_key);
-    let sender_did = identity.did_string();
-    let timestamp = crate::time::now_timestamp_millis();
-    let aad = format!("group-msg:{}:{}:{}", group_id, sender_did, timestamp);
-    let (nonce, ciphertext) =
-        crate::crypto::encrypt(&enc_key, plaintext.as_bytes(), aad.as_bytes())
-            .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+    let messages_json: Vec<serde_json::Value> = messages
+        .iter()
+        .map(|m| {
+            serde_json::json!({
+                "id": m.id,
+                "conversation_id": m.conversation_id,
+                "sender_did": m.sender_did,
+                "content_encrypted": hex_to_base64_safe(&m.content_encrypted),
+                "nonce": m.nonce,
+                "timestamp": m.timestamp,
+                "pinned": true,
+                "pinned_by": m.pinned_by,
+                "pinned_at": m.pinned_at,
+                "edited": m.edited,
+                "deleted": m.deleted,
+            })
+        })
+        .collect();
 
-    let result = serde_json::json!({
-        "ciphertext_hex": hex::encode(&ciphertext),
-        "nonce_hex": hex::encode(&nonce.0),
-        "key_version": key_record.key_version,
-        "timestamp": timestamp,
-    });
-    Ok(JsValue::from_str(&result.to_string()))
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&messages_json).unwrap_or_default(),
+    ))
 }
 
-/// Decrypt a group message with the specified key version.
+// ============================================================================
+// GROUP OPERATIONS
+// ============================================================================
+
+/// Create a new group.
 ///
-/// Takes JSON: { "group_id", "ciphertext_hex", "nonce_hex", "key_version", "sender_did", "timestamp" }
-/// Returns JSON: the decrypted plaintext string
+/// Takes JSON: { "name": "...", "description": "..." (optional) }
+/// Returns JSON: { "group_id", "conversation_id", "name" }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_decrypt_message(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_create(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -3234,132 +3283,102 @@ pub fn umbra_wasm_groups_decrypt_message(json: &str) -> Result<JsValue, JsValue>
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let group_id = data["group_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let ciphertext_hex = data["ciphertext_hex"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing ciphertext_hex"))?;
-    let nonce_hex = data["nonce_hex"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing nonce_hex"))?;
-    let key_version = data["key_version"].as_i64().unwrap_or(1) as i32;
-    let sender_did = data["sender_did"]
+    let name = data["name"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
-    let timestamp = data["timestamp"].as_i64().unwrap_or(0);
-
-    // Get the specified key version
-    let key_record = database
-        .get_group_key(group_id, key_version)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| {
-            JsValue::from_str(&format!("Group key version {} not found", key_version))
-        })?;
+        .ok_or_else(|| JsValue::from_str("Missing name"))?;
+    let description = data["description"].as_str();
 
-    // Decrypt the stored key
-    let raw_key = decrypt_stored_group_key(identity, &key_record, group_id)?;
+    let our_did = identity.did_string();
+    let group_id = uuid::Uuid::new_v4().to_string();
+    let conv_id = format!("group-{}", group_id);
+    let now = crate::time::now_timestamp_millis();
 
-    // Decrypt the message
-    let ciphertext = hex::decode(ciphertext_hex)
-        .map_err(|e| JsValue::from_str(&format!("Invalid hex: {}", e)))?;
-    let nonce_bytes =
-        hex::decode(nonce_hex).map_err(|e| JsValue::from_str(&format!("Invalid nonce: {}", e)))?;
-    if nonce_bytes.len() != 12 {
-        return Err(JsValue::from_str("Nonce must be 12 bytes"));
-    }
-    let mut nonce_arr = [0u8; 12];
-    nonce_arr.copy_from_slice(&nonce_bytes);
-    let nonce = crate::crypto::Nonce(nonce_arr);
+    // Create the group
+    database
+        .create_group(&group_id, name, description, &our_did, now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to create group: {}", e)))?;
 
-    let enc_key = crate::crypto::EncryptionKey::from_bytes(raw_key);
-    let aad = format!("group-msg:{}:{}:{}", group_id, sender_did, timestamp);
-    let plaintext = crate::crypto::decrypt(&enc_key, &nonce, &ciphertext, aad.as_bytes())
-        .map_err(|e| JsValue::from_str(&format!("Decryption failed: {}", e)))?;
+    // Add self as admin
+    let display_name = identity.profile().display_name.clone();
+    database
+        .add_group_member(&group_id, &our_did, Some(&display_name), "admin", now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to add self to group: {}", e)))?;
 
-    let text = String::from_utf8_lossy(&plaintext).to_string();
-    Ok(JsValue::from_str(&serde_json::json!(text).to_string()))
-}
+    // Create group conversation
+    database
+        .create_group_conversation(&conv_id, &group_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to create group conversation: {}", e)))?;
 
-/// Encrypt a group key for a specific member (for invite or key rotation).
-///
-/// Uses ECDH with the member's public key to encrypt the raw group key.
+    // Generate and store group encryption key
+    let group_key: [u8; 32] = {
+        let mut key = [0u8; 32];
+        getrandom::getrandom(&mut key)
+            .map_err(|e| JsValue::from_str(&format!("Failed to generate group key: {}", e)))?;
+        key
+    };
+    let wrapping_key = derive_key_wrapping_key(identity);
+    let wrap_enc_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
+    let aad = format!("group-key:{}:1", group_id);
+    let (nonce, ciphertext) = crate::crypto::encrypt(&wrap_enc_key, &group_key, aad.as_bytes())
+        .map_err(|e| JsValue::from_str(&format!("Failed to encrypt group key: {}", e)))?;
+    let mut stored_key = Vec::with_capacity(12 + ciphertext.len());
+    stored_key.extend_from_slice(&nonce.0);
+    stored_key.extend_from_slice(&ciphertext);
+    database
+        .store_group_key(&group_id, 1, &stored_key, now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
+
+    // New groups encrypt messages with MLS; the shared key is only used by
+    // groups created before MLS
+    crate::messaging::mls::create_session(identity, database, &group_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to create MLS group: {}", e)))?;
+
+    let result = serde_json::json!({
+        "group_id": group_id,
+        "conversation_id": conv_id,
+        "name": name,
+        "created_by": our_did,
+        "created_at": now,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Get group info by ID.
 ///
-/// Takes JSON: { "group_id", "raw_key_hex", "key_version", "member_did" }
-/// Returns JSON: { "encrypted_key_hex", "nonce_hex" }
+/// Takes: group_id as string
+/// Returns JSON: { group fields }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_encrypt_key_for_member(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_get(group_id: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let identity = state
-        .identity
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let group_id = data["group_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let raw_key_hex = data["raw_key_hex"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing raw_key_hex"))?;
-    let key_version = data["key_version"].as_i64().unwrap_or(1) as i32;
-    let member_did = data["member_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-
-    let raw_key = hex::decode(raw_key_hex)
-        .map_err(|e| JsValue::from_str(&format!("Invalid key hex: {}", e)))?;
-
-    // Look up member's encryption key
-    let member = database
-        .get_friend(member_did)
+    let group = database
+        .get_group(group_id)
         .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Member not found in friends"))?;
-
-    let member_enc_bytes = hex::decode(&member.encryption_key)
-        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
-    let mut member_enc_key = [0u8; 32];
-    if member_enc_bytes.len() != 32 {
-        return Err(JsValue::from_str("Key must be 32 bytes"));
-    }
-    member_enc_key.copy_from_slice(&member_enc_bytes);
-
-    // Encrypt group key using ECDH with member
-    let aad = format!("group-key-transfer:{}:{}", group_id, key_version);
-    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
-        &identity.keypair().encryption,
-        &member_enc_key,
-        group_id.as_bytes(),
-        &raw_key,
-        aad.as_bytes(),
-    )
-    .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+        .ok_or_else(|| JsValue::from_str("Group not found"))?;
 
     let result = serde_json::json!({
-        "encrypted_key_hex": hex::encode(&ciphertext),
-        "nonce_hex": hex::encode(&nonce.0),
+        "id": group.id,
+        "name": group.name,
+        "description": group.description,
+        "avatar": group.avatar,
+        "created_by": group.created_by,
+        "created_at": group.created_at,
+        "updated_at": group.updated_at,
     });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-// ============================================================================
-// GROUP INVITATIONS
-// ============================================================================
-
-/// Store a received group invite.
+/// List all groups.
 ///
-/// Takes JSON: { "id", "group_id", "group_name", "description", "inviter_did",
-///               "inviter_name", "encrypted_group_key", "nonce", "members_json" }
+/// Returns JSON: [group, ...]
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_store_invite(json: &str) -> Result<(), JsValue> {
+pub fn umbra_wasm_groups_list() -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -3368,57 +3387,36 @@ pub fn umbra_wasm_groups_store_invite(json: &str) -> Result<(), JsValue> {
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let invite = crate::storage::GroupInviteRecord {
-        id: data["id"]
-            .as_str()
-            .unwrap_or(&uuid::Uuid::new_v4().to_string())
-            .to_string(),
-        group_id: data["group_id"].as_str().unwrap_or("").to_string(),
-        group_name: data["group_name"].as_str().unwrap_or("").to_string(),
-        description: data["description"].as_str().map(|s| s.to_string()),
-        inviter_did: data["inviter_did"].as_str().unwrap_or("").to_string(),
-        inviter_name: data["inviter_name"].as_str().unwrap_or("").to_string(),
-        encrypted_group_key: data["encrypted_group_key"]
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        nonce: data["nonce"].as_str().unwrap_or("").to_string(),
-        members_json: data["members_json"].as_str().unwrap_or("[]").to_string(),
-        status: "pending".to_string(),
-        created_at: data["created_at"]
-            .as_i64()
-            .unwrap_or_else(|| crate::time::now_timestamp_millis()),
-    };
-
-    database
-        .store_group_invite(&invite)
-        .map_err(|e| JsValue::from_str(&format!("Failed to store invite: {}", e)))?;
+    let groups = database
+        .get_all_groups()
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
 
-    emit_event(
-        "group",
-        &serde_json::json!({
-            "type": "groupInviteReceived",
-            "invite": {
-                "id": invite.id,
-                "group_id": invite.group_id,
-                "group_name": invite.group_name,
-                "inviter_did": invite.inviter_did,
-                "inviter_name": invite.inviter_name,
-            }
-        }),
-    );
+    let groups_json: Vec<serde_json::Value> = groups
+        .iter()
+        .map(|g| {
+            serde_json::json!({
+                "id": g.id,
+                "name": g.name,
+                "description": g.description,
+                "avatar": g.avatar,
+                "created_by": g.created_by,
+                "created_at": g.created_at,
+                "updated_at": g.updated_at,
+            })
+        })
+        .collect();
 
-    Ok(())
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&groups_json).unwrap_or_default(),
+    ))
 }
 
-/// Get pending group invites.
+/// Update a group's name/description.
 ///
-/// Returns JSON: [invite, ...]
+/// Takes JSON: { "group_id": "...", "name": "...", "description": "..." }
+/// Returns JSON: { "group_id", "updated_at" }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_get_pending_invites() -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_update(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -3427,42 +3425,35 @@ pub fn umbra_wasm_groups_get_pending_invites() -> Result<JsValue, JsValue> {
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let invites = database
-        .get_pending_group_invites()
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let invites_json: Vec<serde_json::Value> = invites
-        .iter()
-        .map(|i| {
-            serde_json::json!({
-                "id": i.id,
-                "group_id": i.group_id,
-                "group_name": i.group_name,
-                "description": i.description,
-                "inviter_did": i.inviter_did,
-                "inviter_name": i.inviter_name,
-                "encrypted_group_key": i.encrypted_group_key,
-                "nonce": i.nonce,
-                "members_json": i.members_json,
-                "status": i.status,
-                "created_at": i.created_at,
-            })
-        })
-        .collect();
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let name = data["name"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing name"))?;
+    let description = data["description"].as_str();
+    let now = crate::time::now_timestamp_millis();
 
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&invites_json).unwrap_or_default(),
-    ))
+    database
+        .update_group(group_id, name, description, now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to update group: {}", e)))?;
+
+    let result = serde_json::json!({
+        "group_id": group_id,
+        "updated_at": now,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Accept a group invite.
-///
-/// Imports the group key, creates local group + conversation, adds self as member.
+/// Delete a group (admin only).
 ///
-/// Takes: invite_id as string
-/// Returns JSON: { "group_id", "conversation_id" }
+/// Takes: group_id as string
+/// Returns JSON: { "group_id" }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_accept_invite(invite_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_delete(group_id: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -3475,91 +3466,35 @@ pub fn umbra_wasm_groups_accept_invite(invite_id: &str) -> Result<JsValue, JsVal
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let invite = database
-        .get_group_invite(invite_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Invite not found"))?;
-
+    // Verify admin
     let our_did = identity.did_string();
-    let now = crate::time::now_timestamp_millis();
+    let members = database
+        .get_group_members(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
 
-    // Import the group key (decrypt ECDH → re-encrypt with wrapping key)
-    let import_json = serde_json::json!({
-        "group_id": invite.group_id,
-        "key_version": 1,
-        "encrypted_key_hex": invite.encrypted_group_key,
-        "nonce_hex": invite.nonce,
-        "sender_did": invite.inviter_did,
-    });
-    umbra_wasm_groups_import_key(&import_json.to_string())?;
+    let is_admin = members
+        .iter()
+        .any(|m| m.member_did == our_did && m.role == "admin");
+    if !is_admin {
+        return Err(JsValue::from_str("Only admins can delete groups"));
+    }
 
-    // Create the group locally
     database
-        .create_group(
-            &invite.group_id,
-            &invite.group_name,
-            invite.description.as_deref(),
-            &invite.inviter_did,
-            now,
-        )
-        .map_err(|e| JsValue::from_str(&format!("Failed to create group: {}", e)))?;
-
-    // Add self as member
-    let display_name = identity.profile().display_name.clone();
-    database
-        .add_group_member(
-            &invite.group_id,
-            &our_did,
-            Some(&display_name),
-            "member",
-            now,
-        )
-        .map_err(|e| JsValue::from_str(&format!("Failed to add self: {}", e)))?;
-
-    // Add existing members from the invite
-    if let Ok(members) = serde_json::from_str::<Vec<serde_json::Value>>(&invite.members_json) {
-        for m in &members {
-            let did = m["did"].as_str().unwrap_or("");
-            let name = m["display_name"].as_str();
-            let role = m["role"].as_str().unwrap_or("member");
-            if !did.is_empty() {
-                let _ = database.add_group_member(&invite.group_id, did, name, role, now);
-            }
-        }
-    }
-
-    // Create group conversation
-    let conv_id = format!("group-{}", invite.group_id);
-    database
-        .create_group_conversation(&conv_id, &invite.group_id)
-        .map_err(|e| JsValue::from_str(&format!("Failed to create conversation: {}", e)))?;
-
-    // Update invite status
-    database
-        .update_group_invite_status(invite_id, "accepted")
-        .map_err(|e| JsValue::from_str(&format!("Failed to update invite: {}", e)))?;
-
-    emit_event(
-        "group",
-        &serde_json::json!({
-            "type": "groupInviteAccepted",
-            "group_id": invite.group_id,
-            "conversation_id": conv_id,
-        }),
-    );
+        .delete_group(group_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to delete group: {}", e)))?;
+    crate::messaging::mls::delete_session(database, group_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to delete MLS group: {}", e)))?;
 
-    let result = serde_json::json!({
-        "group_id": invite.group_id,
-        "conversation_id": conv_id,
-    });
+    let result = serde_json::json!({ "group_id": group_id });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Decline a group invite.
+/// Add a member to a group.
 ///
-/// Takes: invite_id as string
+/// Takes JSON: { "group_id": "...", "did": "...", "display_name": "..." (optional) }
+/// Returns JSON: { "group_id", "member_did" }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_decline_invite(invite_id: &str) -> Result<(), JsValue> {
+pub fn umbra_wasm_groups_add_member(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -3568,22 +3503,35 @@ pub fn umbra_wasm_groups_decline_invite(invite_id: &str) -> Result<(), JsValue>
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let did = data["did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing did"))?;
+    let display_name = data["display_name"].as_str();
+    let now = crate::time::now_timestamp_millis();
+
     database
-        .update_group_invite_status(invite_id, "declined")
-        .map_err(|e| JsValue::from_str(&format!("Failed to update invite: {}", e)))?;
+        .add_group_member(group_id, did, display_name, "member", now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to add member: {}", e)))?;
 
-    Ok(())
+    let result = serde_json::json!({
+        "group_id": group_id,
+        "member_did": did,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
 }
 
-// ============================================================================
-// MESSAGE DELIVERY STATUS
-// ============================================================================
-
-/// Update a specific message's delivery status.
+/// Remove a member from a group.
 ///
-/// Takes JSON: { "message_id", "status" } where status is "delivered" or "read"
+/// Takes JSON: { "group_id": "...", "did": "..." }
+/// Returns JSON: { "group_id", "member_did" }
 #[wasm_bindgen]
-pub fn umbra_wasm_messaging_update_status(json: &str) -> Result<(), JsValue> {
+pub fn umbra_wasm_groups_remove_member(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -3595,60 +3543,30 @@ pub fn umbra_wasm_messaging_update_status(json: &str) -> Result<(), JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let message_id = data["message_id"]
+    let group_id = data["group_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-    let status = data["status"]
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let did = data["did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing status"))?;
-
-    match status {
-        "sent" => {
-            database
-                .mark_message_sent(message_id)
-                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
-        }
-        "delivered" => {
-            database
-                .mark_message_delivered(message_id)
-                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
-        }
-        "read" => {
-            database
-                .mark_message_read(message_id)
-                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
-        }
-        _ => {
-            return Err(JsValue::from_str(&format!("Invalid status: {}", status)));
-        }
-    }
+        .ok_or_else(|| JsValue::from_str("Missing did"))?;
 
-    emit_event(
-        "message",
-        &serde_json::json!({
-            "type": "messageStatusUpdated",
-            "messageId": message_id,
-            "status": status,
-        }),
-    );
+    database
+        .remove_group_member(group_id, did)
+        .map_err(|e| JsValue::from_str(&format!("Failed to remove member: {}", e)))?;
 
-    Ok(())
+    let result = serde_json::json!({
+        "group_id": group_id,
+        "member_did": did,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
 }
 
-// ============================================================================
-// RELAY ENVELOPE BUILDERS (pure data — no DB/crypto)
-// ============================================================================
-
-/// Build a community_event relay envelope batch for all members except the sender.
-///
-/// Takes JSON: { "community_id", "event" (any JSON), "sender_did", "canonical_community_id"? }
-/// Returns JSON: [{ "to_did", "payload": "<stringified envelope>" }, ...]
+/// Get all members of a group.
 ///
-/// `canonical_community_id` is the origin/owner's community ID used in the envelope payload
-/// so that receivers can resolve it via `findCommunityByOrigin()`. If not provided, falls back
-/// to `community_id` (which is the local ID used for member lookup).
+/// Takes: group_id as string
+/// Returns JSON: [member, ...]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_build_event_relay_batch(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_get_members(group_id: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -3657,634 +3575,588 @@ pub fn umbra_wasm_community_build_event_relay_batch(json: &str) -> Result<JsValu
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let community_id = data["community_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    // Use canonical_community_id for the envelope payload (so receivers can resolve via origin mapping).
-    // Falls back to community_id for the owner (who IS the origin, so their local ID is canonical).
-    let canonical_community_id = data["canonical_community_id"]
-        .as_str()
-        .unwrap_or(community_id);
-    let event = &data["event"];
-    let sender_did = data["sender_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
-
-    let timestamp = crate::time::now_timestamp_millis();
-
-    let envelope = serde_json::json!({
-        "envelope": "community_event",
-        "version": 1,
-        "payload": {
-            "communityId": canonical_community_id,
-            "event": event,
-            "senderDid": sender_did,
-            "timestamp": timestamp,
-        }
-    });
-    let envelope_str = envelope.to_string();
-
-    // Get community members from database
     let members = database
-        .get_community_members(community_id)
+        .get_group_members(group_id)
         .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
 
-    let relay_messages: Vec<serde_json::Value> = members
+    let members_json: Vec<serde_json::Value> = members
         .iter()
-        .filter(|m| m.member_did != sender_did)
         .map(|m| {
             serde_json::json!({
-                "to_did": m.member_did,
-                "payload": envelope_str,
+                "group_id": m.group_id,
+                "member_did": m.member_did,
+                "display_name": m.display_name,
+                "role": m.role,
+                "joined_at": m.joined_at,
             })
         })
         .collect();
 
     Ok(JsValue::from_str(
-        &serde_json::to_string(&relay_messages).unwrap_or_default(),
+        &serde_json::to_string(&members_json).unwrap_or_default(),
     ))
 }
 
-/// Build a dm_file_event relay envelope.
+// ============================================================================
+// GROUP ENCRYPTION
+// ============================================================================
+
+/// Generate a shared group key for a newly created group.
 ///
-/// Pure data construction — no DB or crypto.
-/// Takes JSON: { "conversation_id", "sender_did", "event" (any JSON) }
-/// Returns JSON: { "payload": "<stringified envelope>" }
+/// Creates a random 256-bit AES key, encrypts it with the creator's
+/// key-wrapping key (derived from identity via HKDF), and stores it
+/// in the group_keys table.
+///
+/// Takes: group_id as string
+/// Returns JSON: { "group_id", "key_version", "raw_key_hex" }
+///
+/// The raw_key_hex is returned so it can be encrypted per-member
+/// for relay distribution (ECDH with each invitee).
 #[wasm_bindgen]
-pub fn umbra_wasm_build_dm_file_event_envelope(json: &str) -> Result<JsValue, JsValue> {
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+pub fn umbra_wasm_groups_generate_key(group_id: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
 
-    let conversation_id = data["conversation_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
-    let sender_did = data["sender_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
-    let event = &data["event"];
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let timestamp = crate::time::now_timestamp_millis();
+    // Generate random 256-bit key
+    let mut raw_key = [0u8; 32];
+    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut raw_key);
 
-    let envelope = serde_json::json!({
-        "envelope": "dm_file_event",
-        "version": 1,
-        "payload": {
-            "conversationId": conversation_id,
-            "senderDid": sender_did,
-            "timestamp": timestamp,
-            "event": event,
-        }
-    });
-
-    let result = serde_json::json!({
-        "payload": envelope.to_string(),
-    });
-    Ok(JsValue::from_str(&result.to_string()))
-}
-
-/// Build an account_metadata relay envelope.
-///
-/// Pure data construction — no DB or crypto.
-/// Takes JSON: { "sender_did", "key", "value" }
-/// Returns JSON: { "to_did" (= sender_did), "payload": "<stringified envelope>" }
-#[wasm_bindgen]
-pub fn umbra_wasm_build_metadata_envelope(json: &str) -> Result<JsValue, JsValue> {
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let sender_did = data["sender_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
-    let key = data["key"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing key"))?;
-    let value = data["value"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing value"))?;
+    // Encrypt with own key-wrapping key (HKDF from identity)
+    let wrapping_key = derive_key_wrapping_key(identity);
+    let encryption_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
+    let aad = format!("group-key:{}", group_id);
+    let (nonce, encrypted) = crate::crypto::encrypt(&encryption_key, &raw_key, aad.as_bytes())
+        .map_err(|e| JsValue::from_str(&format!("Failed to encrypt group key: {}", e)))?;
 
-    let timestamp = crate::time::now_timestamp_millis();
+    // Store: nonce (12 bytes) + encrypted key
+    let mut stored = Vec::with_capacity(12 + encrypted.len());
+    stored.extend_from_slice(&nonce.0);
+    stored.extend_from_slice(&encrypted);
 
-    let envelope = serde_json::json!({
-        "envelope": "account_metadata",
-        "version": 1,
-        "payload": {
-            "senderDid": sender_did,
-            "key": key,
-            "value": value,
-            "timestamp": timestamp,
-        }
-    });
+    let now = crate::time::now_timestamp_millis();
+    database
+        .store_group_key(group_id, 1, &stored, now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
 
     let result = serde_json::json!({
-        "to_did": sender_did,
-        "payload": envelope.to_string(),
+        "group_id": group_id,
+        "key_version": 1,
+        "raw_key_hex": hex::encode(&raw_key),
     });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-// ============================================================================
-// ACCOUNT BACKUP
-// ============================================================================
-
-/// Create an encrypted account backup.
+/// Rotate the group key (e.g., after removing a member).
 ///
-/// Exports the database, compresses, encrypts with a key derived from the
-/// master seed, chunks into 64KB pieces, and builds relay envelopes to send
-/// to the user's own DID.
+/// Generates a new AES-256 key, increments the key version, stores it.
 ///
-/// Returns JSON: { "relayMessages": [{ "to_did", "payload" }], "chunkCount": N, "totalSize": N }
+/// Takes: group_id as string
+/// Returns JSON: { "group_id", "key_version", "raw_key_hex" }
 #[wasm_bindgen]
-pub fn umbra_wasm_account_create_backup(_json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_rotate_key(group_id: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
-    let state_r = state.read();
+    let state = state.read();
 
-    let identity = state_r
+    let identity = state
         .identity
         .as_ref()
         .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
-    let database = state_r
+    let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-    let seed = state_r
-        .backup_seed
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Backup seed not available — identity was not created or restored in this session"))?;
-
-    // 1. Derive backup encryption key
-    let backup_key = crate::crypto::derive_backup_key(seed)
-        .map_err(|e| JsValue::from_str(&format!("Key derivation failed: {}", e)))?;
-
-    // 2. Export database to JSON
-    let export_bytes = database
-        .export_database()
-        .map_err(|e| JsValue::from_str(&format!("Database export failed: {}", e)))?;
-
-    // 3. Compress with flate2-style deflate via miniz_oxide
-    let compressed = miniz_oxide::deflate::compress_to_vec(&export_bytes, 6);
-
-    // 4. Encrypt with AES-256-GCM
-    let enc_key = crate::crypto::EncryptionKey::from_bytes(backup_key);
-    let (nonce, encrypted) = crate::crypto::encrypt(&enc_key, &compressed, b"umbra-backup")
-        .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
-
-    let total_size = encrypted.len();
 
-    // 5. Chunk into 64KB pieces
-    const CHUNK_SIZE: usize = 64 * 1024;
-    let chunks: Vec<&[u8]> = encrypted.chunks(CHUNK_SIZE).collect();
-    let total_chunks = chunks.len();
-    let own_did = identity.did_string();
-    let timestamp = crate::time::now_timestamp_millis();
-    let backup_id = format!("backup-{}", timestamp);
+    // Get current key version
+    let current = database
+        .get_latest_group_key(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let new_version = current.map(|k| k.key_version + 1).unwrap_or(1);
 
-    // 6. Build relay envelopes
-    let mut relay_messages = Vec::new();
+    // Generate new random key
+    let mut raw_key = [0u8; 32];
+    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut raw_key);
 
-    // Manifest envelope
-    let manifest_envelope = serde_json::json!({
-        "envelope": "account_backup_manifest",
-        "version": 1,
-        "payload": {
-            "backupId": backup_id,
-            "senderDid": own_did,
-            "totalChunks": total_chunks,
-            "totalSize": total_size,
-            "nonce": hex::encode(nonce.as_bytes()),
-            "timestamp": timestamp,
-        }
-    });
-    relay_messages.push(serde_json::json!({
-        "to_did": own_did,
-        "payload": manifest_envelope.to_string(),
-    }));
+    // Encrypt with own wrapping key
+    let wrapping_key = derive_key_wrapping_key(identity);
+    let encryption_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
+    let aad = format!("group-key:{}:{}", group_id, new_version);
+    let (nonce, encrypted) = crate::crypto::encrypt(&encryption_key, &raw_key, aad.as_bytes())
+        .map_err(|e| JsValue::from_str(&format!("Failed to encrypt group key: {}", e)))?;
 
-    // Chunk envelopes
-    for (i, chunk) in chunks.iter().enumerate() {
-        let chunk_hash = {
-            let digest = Sha256::digest(chunk);
-            hex::encode(digest)
-        };
+    let mut stored = Vec::with_capacity(12 + encrypted.len());
+    stored.extend_from_slice(&nonce.0);
+    stored.extend_from_slice(&encrypted);
 
-        let chunk_envelope = serde_json::json!({
-            "envelope": "account_backup_chunk",
-            "version": 1,
-            "payload": {
-                "backupId": backup_id,
-                "chunkIndex": i,
-                "totalChunks": total_chunks,
-                "data": base64::engine::general_purpose::STANDARD.encode(chunk),
-                "hash": chunk_hash,
-            }
-        });
-        relay_messages.push(serde_json::json!({
-            "to_did": own_did,
-            "payload": chunk_envelope.to_string(),
-        }));
-    }
+    let now = crate::time::now_timestamp_millis();
+    database
+        .store_group_key(group_id, new_version, &stored, now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
 
     let result = serde_json::json!({
-        "relayMessages": relay_messages,
-        "chunkCount": total_chunks,
-        "totalSize": total_size,
+        "group_id": group_id,
+        "key_version": new_version,
+        "raw_key_hex": hex::encode(&raw_key),
     });
-
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Restore an account from an encrypted backup.
+/// Import a group key received from another member (via invite or key rotation).
 ///
-/// Takes ordered encrypted chunk data (base64), reassembles, decrypts,
-/// decompresses, and imports into the database.
+/// The key arrives encrypted via ECDH with the sender.
+/// We decrypt it using our X25519 key, then re-encrypt with our wrapping key.
 ///
-/// Takes JSON: { "chunks": ["base64...", ...], "nonce": "hex..." }
-/// Returns JSON: { "imported": { "settings": N, "friends": N, ... } }
+/// Takes JSON: { "group_id", "key_version", "encrypted_key_hex", "nonce_hex", "sender_did" }
+/// Returns JSON: { "group_id", "key_version" }
 #[wasm_bindgen]
-pub fn umbra_wasm_account_restore_backup(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_import_key(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
-    let state_r = state.read();
+    let state = state.read();
 
-    let database = state_r
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-    let seed = state_r
-        .backup_seed
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Backup seed not available — identity was not created or restored in this session"))?;
 
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let chunks = data["chunks"]
-        .as_array()
-        .ok_or_else(|| JsValue::from_str("Missing chunks array"))?;
-    let nonce_hex = data["nonce"]
+    let group_id = data["group_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing nonce"))?;
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let key_version = data["key_version"].as_i64().unwrap_or(1) as i32;
+    let encrypted_key_hex = data["encrypted_key_hex"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing encrypted_key_hex"))?;
+    let nonce_hex = data["nonce_hex"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing nonce_hex"))?;
+    let sender_did = data["sender_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
 
-    // 1. Derive backup encryption key
-    let backup_key = crate::crypto::derive_backup_key(seed)
-        .map_err(|e| JsValue::from_str(&format!("Key derivation failed: {}", e)))?;
+    // Look up sender's encryption key
+    let sender = database
+        .get_friend(sender_did)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Sender not found in friends"))?;
 
-    // 2. Reassemble chunks
-    let mut encrypted = Vec::new();
-    for chunk_val in chunks {
-        let chunk_b64 = chunk_val
-            .as_str()
-            .ok_or_else(|| JsValue::from_str("Chunk must be a string"))?;
-        let chunk_bytes = base64::engine::general_purpose::STANDARD
-            .decode(chunk_b64)
-            .map_err(|e| JsValue::from_str(&format!("Invalid base64 chunk: {}", e)))?;
-        encrypted.extend_from_slice(&chunk_bytes);
+    let sender_enc_bytes = hex::decode(&sender.encryption_key)
+        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
+    let mut sender_enc_key = [0u8; 32];
+    if sender_enc_bytes.len() != 32 {
+        return Err(JsValue::from_str("Key must be 32 bytes"));
     }
+    sender_enc_key.copy_from_slice(&sender_enc_bytes);
 
-    // 3. Decrypt with AES-256-GCM
-    let enc_key = crate::crypto::EncryptionKey::from_bytes(backup_key);
-    let nonce_bytes = hex::decode(nonce_hex)
-        .map_err(|e| JsValue::from_str(&format!("Invalid nonce hex: {}", e)))?;
+    // Decrypt the group key using ECDH
+    let ciphertext = hex::decode(encrypted_key_hex)
+        .map_err(|e| JsValue::from_str(&format!("Invalid hex: {}", e)))?;
+    let nonce_bytes =
+        hex::decode(nonce_hex).map_err(|e| JsValue::from_str(&format!("Invalid nonce: {}", e)))?;
     if nonce_bytes.len() != 12 {
         return Err(JsValue::from_str("Nonce must be 12 bytes"));
     }
     let mut nonce_arr = [0u8; 12];
     nonce_arr.copy_from_slice(&nonce_bytes);
-    let nonce = crate::crypto::Nonce::from_bytes(nonce_arr);
-    let compressed = crate::crypto::decrypt(&enc_key, &nonce, &encrypted, b"umbra-backup")
-        .map_err(|e| JsValue::from_str(&format!("Decryption failed: {}", e)))?;
+    let nonce = crate::crypto::Nonce(nonce_arr);
 
-    // 4. Decompress
-    let decompressed = miniz_oxide::inflate::decompress_to_vec(&compressed)
-        .map_err(|e| JsValue::from_str(&format!("Decompression failed: {:?}", e)))?;
+    let aad = format!("group-key-transfer:{}:{}", group_id, key_version);
+    let raw_key = crate::crypto::decrypt_from_sender(
+        &identity.keypair().encryption,
+        &sender_enc_key,
+        group_id.as_bytes(),
+        &nonce,
+        &ciphertext,
+        aad.as_bytes(),
+    )
+    .map_err(|e| JsValue::from_str(&format!("Failed to decrypt group key: {}", e)))?;
 
-    // 5. Import into database
-    let stats = database
-        .import_database(&decompressed)
-        .map_err(|e| JsValue::from_str(&format!("Database import failed: {}", e)))?;
+    // Re-encrypt with our own wrapping key
+    let wrapping_key = derive_key_wrapping_key(identity);
+    let wrap_enc_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
+    let wrap_aad = format!("group-key:{}:{}", group_id, key_version);
+    let (wrap_nonce, wrap_ct) =
+        crate::crypto::encrypt(&wrap_enc_key, &raw_key, wrap_aad.as_bytes())
+            .map_err(|e| JsValue::from_str(&format!("Failed to re-encrypt group key: {}", e)))?;
+
+    let mut stored = Vec::with_capacity(12 + wrap_ct.len());
+    stored.extend_from_slice(&wrap_nonce.0);
+    stored.extend_from_slice(&wrap_ct);
+
+    let now = crate::time::now_timestamp_millis();
+    database
+        .store_group_key(group_id, key_version, &stored, now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
 
     let result = serde_json::json!({
-        "imported": {
-            "settings": stats.settings,
-            "friends": stats.friends,
-            "conversations": stats.conversations,
-            "groups": stats.groups,
-            "blocked_users": stats.blocked_users,
-        }
+        "group_id": group_id,
+        "key_version": key_version,
     });
-
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-// ============================================================================
-// ACCOUNT SYNC — Encrypted blob creation, parsing, and challenge signing
-// ============================================================================
-
-/// Create an encrypted sync blob from the current database state.
-///
-/// Collects preferences, friends, groups, and blocked users, serialises to
-/// CBOR, compresses, and encrypts with AES-256-GCM using a key derived from
-/// the recovery seed.
+/// Encrypt a message with the group's shared key.
 ///
-/// Takes JSON: { "section_versions"?: { "preferences": N, "friends": N, ... } }
-/// Returns JSON: { "blob": "base64...", "sections": { "name": version } }
+/// Takes JSON: { "group_id", "plaintext" }
+/// Returns JSON: { "ciphertext_hex", "nonce_hex", "key_version" }
 #[wasm_bindgen]
-pub fn umbra_wasm_sync_create_blob(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_encrypt_message(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
-    let state_r = state.read();
+    let state = state.read();
 
-    let database = state_r
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-    let seed = state_r
-        .backup_seed
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Seed not available — identity was not created or restored in this session"))?;
 
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    // Parse optional section versions
-    let section_versions: Option<std::collections::HashMap<String, u64>> =
-        data.get("section_versions")
-            .and_then(|sv| serde_json::from_value(sv.clone()).ok());
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let plaintext = data["plaintext"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing plaintext"))?;
 
-    // 1. Create sync payload from database
-    let payload = crate::sync::create_sync_blob(database, section_versions.as_ref())
-        .map_err(|e| JsValue::from_str(&format!("Sync blob creation failed: {}", e)))?;
+    if crate::messaging::mls::has_session(database, group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+    {
+        let timestamp = crate::time::now_timestamp_millis();
+        let aad = crate::messaging::mls::message_aad(group_id, &identity.did_string(), timestamp);
+        let ciphertext_hex = crate::messaging::mls::encrypt_message(
+            identity,
+            database,
+            group_id,
+            plaintext.as_bytes(),
+            &aad,
+        )
+        .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+        let result = serde_json::json!({
+            "ciphertext_hex": ciphertext_hex,
+            "nonce_hex": "",
+            "key_version": crate::messaging::mls::MLS_KEY_VERSION,
+            "timestamp": timestamp,
+        });
+        return Ok(JsValue::from_str(&result.to_string()));
+    }
 
-    // Collect section versions for response
-    let sections: serde_json::Value = payload
-        .sections
-        .iter()
-        .map(|(name, section)| (name.clone(), serde_json::json!(section.v)))
-        .collect::<serde_json::Map<String, serde_json::Value>>()
-        .into();
+    // Get latest group key
+    let key_record = database
+        .get_latest_group_key(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("No group key found"))?;
 
-    // 2. Encrypt the payload
-    let blob = crate::sync::encrypt_sync_blob(&payload, seed)
-        .map_err(|e| JsValue::from_str(&format!("Sync blob encryption failed: {}", e)))?;
+    // Decrypt the stored key with our wrapping key
+    let raw_key = decrypt_stored_group_key(identity, &key_record, group_id)?;
+
+    // Encrypt the message
+    let enc_key = crate::crypto::EncryptionKey::from_bytes(raw_key);
+    let sender_did = identity.did_string();
+    let timestamp = crate::time::now_timestamp_millis();
+    let aad = format!("group-msg:{}:{}:{}", group_id, sender_did, timestamp);
+    let (nonce, ciphertext) =
+        crate::crypto::encrypt(&enc_key, plaintext.as_bytes(), aad.as_bytes())
+            .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
 
     let result = serde_json::json!({
-        "blob": base64::engine::general_purpose::STANDARD.encode(&blob),
-        "sections": sections,
-        "size": blob.len(),
+        "ciphertext_hex": hex::encode(&ciphertext),
+        "nonce_hex": hex::encode(&nonce.0),
+        "key_version": key_record.key_version,
+        "timestamp": timestamp,
     });
-
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Parse a sync blob and return a summary without applying it.
-///
-/// Decrypts and inspects the blob to show what data it contains.
+/// Decrypt a group message with the specified key version.
 ///
-/// Takes JSON: { "blob": "base64..." }
-/// Returns JSON: { "v": 1, "updated_at": N, "sections": { "friends": { "v": N, "count": N } } }
+/// Takes JSON: { "group_id", "ciphertext_hex", "nonce_hex", "key_version", "sender_did", "timestamp" }
+/// Returns JSON: the decrypted plaintext string
 #[wasm_bindgen]
-pub fn umbra_wasm_sync_parse_blob(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_decrypt_message(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
-    let state_r = state.read();
+    let state = state.read();
 
-    let seed = state_r
-        .backup_seed
+    let identity = state
+        .identity
         .as_ref()
-        .ok_or_else(|| JsValue::from_str("Seed not available"))?;
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let blob_b64 = data["blob"]
+    let group_id = data["group_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing blob field"))?;
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let ciphertext_hex = data["ciphertext_hex"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing ciphertext_hex"))?;
+    let nonce_hex = data["nonce_hex"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing nonce_hex"))?;
+    let key_version = data["key_version"].as_i64().unwrap_or(1) as i32;
+    let sender_did = data["sender_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
+    let timestamp = data["timestamp"].as_i64().unwrap_or(0);
 
-    let blob = base64::engine::general_purpose::STANDARD
-        .decode(blob_b64)
-        .map_err(|e| JsValue::from_str(&format!("Invalid base64: {}", e)))?;
+    if key_version == crate::messaging::mls::MLS_KEY_VERSION {
+        let aad = crate::messaging::mls::message_aad(group_id, sender_did, timestamp);
+        let (mls_sender, plaintext) =
+            crate::messaging::mls::decrypt_message(database, group_id, ciphertext_hex, &aad)
+                .map_err(|e| JsValue::from_str(&format!("Decryption failed: {}", e)))?;
+        if mls_sender != sender_did {
+            return Err(JsValue::from_str(
+                "Message was signed by a different group member",
+            ));
+        }
+        let text = String::from_utf8_lossy(&plaintext).to_string();
+        if let Ok(Some(id)) = database.find_message_id(group_id, sender_did, timestamp) {
+            let _ = database.index_message(&id, &text);
+        }
+        return Ok(JsValue::from_str(&serde_json::json!(text).to_string()));
+    }
 
-    let summary = crate::sync::parse_sync_blob_summary(&blob, seed)
-        .map_err(|e| JsValue::from_str(&format!("Sync blob parse failed: {}", e)))?;
+    // Get the specified key version
+    let key_record = database
+        .get_group_key(group_id, key_version)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| {
+            JsValue::from_str(&format!("Group key version {} not found", key_version))
+        })?;
 
-    let result = serde_json::to_value(&summary)
-        .map_err(|e| JsValue::from_str(&format!("Serialization failed: {}", e)))?;
+    // Decrypt the stored key
+    let raw_key = decrypt_stored_group_key(identity, &key_record, group_id)?;
 
-    Ok(JsValue::from_str(&result.to_string()))
+    // Decrypt the message
+    let ciphertext = hex::decode(ciphertext_hex)
+        .map_err(|e| JsValue::from_str(&format!("Invalid hex: {}", e)))?;
+    let nonce_bytes =
+        hex::decode(nonce_hex).map_err(|e| JsValue::from_str(&format!("Invalid nonce: {}", e)))?;
+    if nonce_bytes.len() != 12 {
+        return Err(JsValue::from_str("Nonce must be 12 bytes"));
+    }
+    let mut nonce_arr = [0u8; 12];
+    nonce_arr.copy_from_slice(&nonce_bytes);
+    let nonce = crate::crypto::Nonce(nonce_arr);
+
+    let enc_key = crate::crypto::EncryptionKey::from_bytes(raw_key);
+    let aad = format!("group-msg:{}:{}:{}", group_id, sender_did, timestamp);
+    let plaintext = crate::crypto::decrypt(&enc_key, &nonce, &ciphertext, aad.as_bytes())
+        .map_err(|e| JsValue::from_str(&format!("Decryption failed: {}", e)))?;
+
+    let text = String::from_utf8_lossy(&plaintext).to_string();
+
+    if let Ok(Some(id)) = database.find_message_id(group_id, sender_did, timestamp) {
+        let _ = database.index_message(&id, &text);
+    }
+
+    Ok(JsValue::from_str(&serde_json::json!(text).to_string()))
 }
 
-/// Apply a sync blob — decrypt and import its contents into the database.
+/// Encrypt a group key for a specific member (for invite or key rotation).
 ///
-/// Takes JSON: { "blob": "base64..." }
-/// Returns JSON: { "imported": { "settings": N, "friends": N, "groups": N, "blocked_users": N } }
+/// Uses ECDH with the member's public key to encrypt the raw group key.
+///
+/// Takes JSON: { "group_id", "raw_key_hex", "key_version", "member_did" }
+/// Returns JSON: { "encrypted_key_hex", "nonce_hex" }
 #[wasm_bindgen]
-pub fn umbra_wasm_sync_apply_blob(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_encrypt_key_for_member(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
-    let state_r = state.read();
+    let state = state.read();
 
-    let database = state_r
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-    let seed = state_r
-        .backup_seed
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Seed not available"))?;
 
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let blob_b64 = data["blob"]
+    let group_id = data["group_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing blob field"))?;
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let raw_key_hex = data["raw_key_hex"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing raw_key_hex"))?;
+    let key_version = data["key_version"].as_i64().unwrap_or(1) as i32;
+    let member_did = data["member_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
 
-    let blob = base64::engine::general_purpose::STANDARD
-        .decode(blob_b64)
-        .map_err(|e| JsValue::from_str(&format!("Invalid base64: {}", e)))?;
+    let raw_key = hex::decode(raw_key_hex)
+        .map_err(|e| JsValue::from_str(&format!("Invalid key hex: {}", e)))?;
 
-    // 1. Decrypt the sync blob
-    let payload = crate::sync::decrypt_sync_blob(&blob, seed)
-        .map_err(|e| JsValue::from_str(&format!("Sync blob decryption failed: {}", e)))?;
+    // Look up member's encryption key
+    let member = database
+        .get_friend(member_did)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Member not found in friends"))?;
 
-    // 2. Reconstruct a backup-compatible JSON for import_database()
-    let import_json = serde_json::json!({
-        "version": 1,
-        "exported_at": payload.updated_at,
-        "settings": payload.sections.get("preferences").map(|s| &s.data).unwrap_or(&serde_json::json!([])),
-        "friends": payload.sections.get("friends").map(|s| &s.data).unwrap_or(&serde_json::json!([])),
-        "groups": payload.sections.get("groups").map(|s| &s.data).unwrap_or(&serde_json::json!([])),
-        "blocked_users": payload.sections.get("blocked").map(|s| &s.data).unwrap_or(&serde_json::json!([])),
-        "conversations": [],
-    });
-
-    let import_bytes = serde_json::to_vec(&import_json)
-        .map_err(|e| JsValue::from_str(&format!("Serialization failed: {}", e)))?;
+    let member_enc_bytes = hex::decode(&member.encryption_key)
+        .map_err(|e| JsValue::from_str(&format!("Invalid key: {}", e)))?;
+    let mut member_enc_key = [0u8; 32];
+    if member_enc_bytes.len() != 32 {
+        return Err(JsValue::from_str("Key must be 32 bytes"));
+    }
+    member_enc_key.copy_from_slice(&member_enc_bytes);
 
-    // 3. Import into database using existing import_database()
-    let stats = database
-        .import_database(&import_bytes)
-        .map_err(|e| JsValue::from_str(&format!("Database import failed: {}", e)))?;
+    // Encrypt group key using ECDH with member
+    let aad = format!("group-key-transfer:{}:{}", group_id, key_version);
+    let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
+        &identity.keypair().encryption,
+        &member_enc_key,
+        group_id.as_bytes(),
+        &raw_key,
+        aad.as_bytes(),
+    )
+    .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
 
     let result = serde_json::json!({
-        "imported": {
-            "settings": stats.settings,
-            "friends": stats.friends,
-            "groups": stats.groups,
-            "blocked_users": stats.blocked_users,
-        }
+        "encrypted_key_hex": hex::encode(&ciphertext),
+        "nonce_hex": hex::encode(&nonce.0),
     });
-
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Sign a sync auth challenge nonce with the identity's Ed25519 key.
-///
-/// Used for the relay's challenge-response auth flow.
+// ============================================================================
+// GROUP INVITATIONS
+// ============================================================================
+
+/// Store a received group invite.
 ///
-/// Takes JSON: { "nonce": "uuid-string" }
-/// Returns JSON: { "signature": "base64...", "public_key": "base64..." }
+/// Takes JSON: { "id", "group_id", "group_name", "description", "inviter_did",
+///               "inviter_name", "encrypted_group_key", "nonce", "members_json" }
 #[wasm_bindgen]
-pub fn umbra_wasm_sync_sign_challenge(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_store_invite(json: &str) -> Result<(), JsValue> {
     let state = get_state()?;
-    let state_r = state.read();
+    let state = state.read();
 
-    let identity = state_r
-        .identity
+    let database = state
+        .database
         .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let nonce = data["nonce"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing nonce field"))?;
-
-    // Sign the nonce with Ed25519
-    let signature = crate::crypto::sign(&identity.keypair().signing, nonce.as_bytes());
-    let public_key = identity.keypair().signing.public_bytes();
+    let invite = crate::storage::GroupInviteRecord {
+        id: data["id"]
+            .as_str()
+            .unwrap_or(&uuid::Uuid::new_v4().to_string())
+            .to_string(),
+        group_id: data["group_id"].as_str().unwrap_or("").to_string(),
+        group_name: data["group_name"].as_str().unwrap_or("").to_string(),
+        description: data["description"].as_str().map(|s| s.to_string()),
+        inviter_did: data["inviter_did"].as_str().unwrap_or("").to_string(),
+        inviter_name: data["inviter_name"].as_str().unwrap_or("").to_string(),
+        encrypted_group_key: data["encrypted_group_key"]
+            .as_str()
+            .unwrap_or("")
+            .to_string(),
+        nonce: data["nonce"].as_str().unwrap_or("").to_string(),
+        members_json: data["members_json"].as_str().unwrap_or("[]").to_string(),
+        status: "pending".to_string(),
+        created_at: data["created_at"]
+            .as_i64()
+            .unwrap_or_else(|| crate::time::now_timestamp_millis()),
+    };
 
-    let b64 = base64::engine::general_purpose::STANDARD;
+    database
+        .store_group_invite(&invite)
+        .map_err(|e| JsValue::from_str(&format!("Failed to store invite: {}", e)))?;
 
-    let result = serde_json::json!({
-        "signature": base64::Engine::encode(&b64, signature.as_bytes()),
-        "public_key": base64::Engine::encode(&b64, &public_key),
-    });
+    emit_event(
+        "group",
+        &serde_json::json!({
+            "type": "groupInviteReceived",
+            "invite": {
+                "id": invite.id,
+                "group_id": invite.group_id,
+                "group_name": invite.group_name,
+                "inviter_did": invite.inviter_did,
+                "inviter_name": invite.inviter_name,
+            }
+        }),
+    );
 
-    Ok(JsValue::from_str(&result.to_string()))
+    Ok(())
 }
 
-// ============================================================================
-// GROUP RELAY ENVELOPE BUILDERS (orchestrate DB + crypto + envelope)
-// ============================================================================
-
-/// Send a group invitation: encrypt group key for invitee, build `group_invite` envelope.
-///
-/// Orchestrates: get group info, get profile, encrypt key for member, get members list,
-/// generate UUID, and build the relay envelope.
+/// Get pending group invites.
 ///
-/// Takes JSON: { "group_id", "member_did" }
-/// Returns JSON: { "relay_messages": [{ "to_did", "payload" }] }
+/// Returns JSON: [invite, ...]
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_send_invite(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_get_pending_invites() -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let identity = state
-        .identity
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let group_id = data["group_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let member_did = data["member_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-
-    let our_did = identity.did_string();
-    let display_name = identity.profile().display_name.clone();
-    let now = crate::time::now_timestamp_millis();
-    let invite_id = uuid::Uuid::new_v4().to_string();
-
-    // Get group info
-    let group = database
-        .get_group(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Group not found"))?;
-
-    // Get latest key and decrypt it
-    let key_record = database
-        .get_latest_group_key(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("No group key found"))?;
-    let raw_key = decrypt_stored_group_key(identity, &key_record, group_id)?;
-
-    // Encrypt group key for the invitee via ECDH
-    let (enc_key_hex, nonce_hex) = encrypt_group_key_for_member_internal(
-        identity,
-        database,
-        group_id,
-        member_did,
-        &raw_key,
-        key_record.key_version,
-    )?;
-
-    // Get members list
-    let members = database
-        .get_group_members(group_id)
+    let invites = database
+        .get_pending_group_invites()
         .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
-    let members_json_arr: Vec<serde_json::Value> = members
+
+    let invites_json: Vec<serde_json::Value> = invites
         .iter()
-        .map(|m| {
+        .map(|i| {
             serde_json::json!({
-                "did": m.member_did,
-                "display_name": m.display_name,
-                "role": m.role,
+                "id": i.id,
+                "group_id": i.group_id,
+                "group_name": i.group_name,
+                "description": i.description,
+                "inviter_did": i.inviter_did,
+                "inviter_name": i.inviter_name,
+                "encrypted_group_key": i.encrypted_group_key,
+                "nonce": i.nonce,
+                "members_json": i.members_json,
+                "status": i.status,
+                "created_at": i.created_at,
             })
         })
         .collect();
-    let members_json_str = serde_json::to_string(&members_json_arr).unwrap_or_default();
-
-    // Build relay envelope
-    let envelope = serde_json::json!({
-        "envelope": "group_invite",
-        "version": 1,
-        "payload": {
-            "inviteId": invite_id,
-            "groupId": group_id,
-            "groupName": group.name,
-            "description": group.description,
-            "inviterDid": our_did,
-            "inviterName": display_name,
-            "encryptedGroupKey": enc_key_hex,
-            "nonce": nonce_hex,
-            "membersJson": members_json_str,
-            "timestamp": now,
-        }
-    });
 
-    let result = serde_json::json!({
-        "relay_messages": [{
-            "to_did": member_did,
-            "payload": envelope.to_string(),
-        }],
-    });
-    Ok(JsValue::from_str(&result.to_string()))
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&invites_json).unwrap_or_default(),
+    ))
 }
 
-/// Build a `group_invite_accept` relay envelope.
+/// Accept a group invite.
 ///
-/// Takes JSON: { "invite_id", "group_id" }
-/// Returns JSON: { "relay_messages": [{ "to_did", "payload" }] }
+/// Imports the group key, creates local group + conversation, adds self as member.
+///
+/// Takes: invite_id as string
+/// Returns JSON: { "group_id", "conversation_id" }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_build_invite_accept_envelope(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_accept_invite(invite_id: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -4297,60 +4169,118 @@ pub fn umbra_wasm_groups_build_invite_accept_envelope(json: &str) -> Result<JsVa
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let invite_id = data["invite_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing invite_id"))?;
-    let group_id = data["group_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-
-    let our_did = identity.did_string();
-    let display_name = identity.profile().display_name.clone();
-    let now = crate::time::now_timestamp_millis();
-
-    // Get the invite to find the inviter
     let invite = database
         .get_group_invite(invite_id)
         .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
         .ok_or_else(|| JsValue::from_str("Invite not found"))?;
 
-    let envelope = serde_json::json!({
-        "envelope": "group_invite_accept",
-        "version": 1,
-        "payload": {
-            "inviteId": invite_id,
-            "groupId": group_id,
-            "fromDid": our_did,
-            "fromDisplayName": display_name,
-            "timestamp": now,
-        }
+    let our_did = identity.did_string();
+    let now = crate::time::now_timestamp_millis();
+
+    // Import the group key (decrypt ECDH → re-encrypt with wrapping key)
+    let import_json = serde_json::json!({
+        "group_id": invite.group_id,
+        "key_version": 1,
+        "encrypted_key_hex": invite.encrypted_group_key,
+        "nonce_hex": invite.nonce,
+        "sender_did": invite.inviter_did,
     });
+    umbra_wasm_groups_import_key(&import_json.to_string())?;
+
+    // Create the group locally
+    database
+        .create_group(
+            &invite.group_id,
+            &invite.group_name,
+            invite.description.as_deref(),
+            &invite.inviter_did,
+            now,
+        )
+        .map_err(|e| JsValue::from_str(&format!("Failed to create group: {}", e)))?;
+
+    // Add self as member
+    let display_name = identity.profile().display_name.clone();
+    database
+        .add_group_member(
+            &invite.group_id,
+            &our_did,
+            Some(&display_name),
+            "member",
+            now,
+        )
+        .map_err(|e| JsValue::from_str(&format!("Failed to add self: {}", e)))?;
+
+    // Add existing members from the invite
+    if let Ok(members) = serde_json::from_str::<Vec<serde_json::Value>>(&invite.members_json) {
+        for m in &members {
+            let did = m["did"].as_str().unwrap_or("");
+            let name = m["display_name"].as_str();
+            let role = m["role"].as_str().unwrap_or("member");
+            if !did.is_empty() {
+                let _ = database.add_group_member(&invite.group_id, did, name, role, now);
+            }
+        }
+    }
+
+    // Create group conversation
+    let conv_id = format!("group-{}", invite.group_id);
+    database
+        .create_group_conversation(&conv_id, &invite.group_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to create conversation: {}", e)))?;
+
+    // Update invite status
+    database
+        .update_group_invite_status(invite_id, "accepted")
+        .map_err(|e| JsValue::from_str(&format!("Failed to update invite: {}", e)))?;
+
+    emit_event(
+        "group",
+        &serde_json::json!({
+            "type": "groupInviteAccepted",
+            "group_id": invite.group_id,
+            "conversation_id": conv_id,
+        }),
+    );
 
     let result = serde_json::json!({
-        "relay_messages": [{
-            "to_did": invite.inviter_did,
-            "payload": envelope.to_string(),
-        }],
+        "group_id": invite.group_id,
+        "conversation_id": conv_id,
     });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Build a `group_invite_decline` relay envelope.
+/// Decline a group invite.
 ///
-/// Takes JSON: { "invite_id" }
-/// Returns JSON: { "relay_messages": [{ "to_did", "payload" }] }
+/// Takes: invite_id as string
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_build_invite_decline_envelope(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_decline_invite(invite_id: &str) -> Result<(), JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let identity = state
-        .identity
+    let database = state
+        .database
         .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    database
+        .update_group_invite_status(invite_id, "declined")
+        .map_err(|e| JsValue::from_str(&format!("Failed to update invite: {}", e)))?;
+
+    Ok(())
+}
+
+// ============================================================================
+// MESSAGE DELIVERY STATUS
+// ============================================================================
+
+/// Update a specific message's delivery status.
+///
+/// Takes JSON: { "message_id", "status" } where status is "delivered" or "read"
+#[wasm_bindgen]
+pub fn umbra_wasm_messaging_update_status(json: &str) -> Result<(), JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
     let database = state
         .database
         .as_ref()
@@ -4359,55 +4289,63 @@ pub fn umbra_wasm_groups_build_invite_decline_envelope(json: &str) -> Result<JsV
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let invite_id = data["invite_id"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing invite_id"))?;
-
-    let our_did = identity.did_string();
-    let display_name = identity.profile().display_name.clone();
-    let now = crate::time::now_timestamp_millis();
-
-    // Get the invite to find the inviter
-    let invite = database
-        .get_group_invite(invite_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("Invite not found"))?;
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+    let status = data["status"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing status"))?;
 
-    let envelope = serde_json::json!({
-        "envelope": "group_invite_decline",
-        "version": 1,
-        "payload": {
-            "inviteId": invite_id,
-            "groupId": invite.group_id,
-            "fromDid": our_did,
-            "fromDisplayName": display_name,
-            "timestamp": now,
+    match status {
+        "sent" => {
+            database
+                .mark_message_sent(message_id)
+                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
         }
-    });
+        "delivered" => {
+            database
+                .mark_message_delivered(message_id)
+                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+        }
+        "read" => {
+            database
+                .mark_message_read(message_id)
+                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+        }
+        _ => {
+            return Err(JsValue::from_str(&format!("Invalid status: {}", status)));
+        }
+    }
 
-    let result = serde_json::json!({
-        "relay_messages": [{
-            "to_did": invite.inviter_did,
-            "payload": envelope.to_string(),
-        }],
-    });
-    Ok(JsValue::from_str(&result.to_string()))
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "messageStatusUpdated",
+            "messageId": message_id,
+            "status": status,
+        }),
+    );
+
+    Ok(())
 }
 
-/// Send a group message: encrypt, store locally, build `group_message` envelopes for all members.
+// ============================================================================
+// RELAY ENVELOPE BUILDERS (pure data — no DB/crypto)
+// ============================================================================
+
+/// Build a community_event relay envelope batch for all members except the sender.
 ///
-/// Takes JSON: { "group_id", "conversation_id", "text" }
-/// Returns JSON: { "message": { id, conversationId, senderDid, timestamp },
-///                  "relay_messages": [{ "to_did", "payload" }, ...] }
+/// Takes JSON: { "community_id", "event" (any JSON), "sender_did", "canonical_community_id"? }
+/// Returns JSON: [{ "to_did", "payload": "<stringified envelope>" }, ...]
+///
+/// `canonical_community_id` is the origin/owner's community ID used in the envelope payload
+/// so that receivers can resolve it via `findCommunityByOrigin()`. If not provided, falls back
+/// to `community_id` (which is the local ID used for member lookup).
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_send_message(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_build_event_relay_batch(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
-    let identity = state
-        .identity
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
     let database = state
         .database
         .as_ref()
@@ -4416,65 +4354,862 @@ pub fn umbra_wasm_groups_send_message(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let group_id = data["group_id"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let conversation_id = data["conversation_id"]
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    // Use canonical_community_id for the envelope payload (so receivers can resolve via origin mapping).
+    // Falls back to community_id for the owner (who IS the origin, so their local ID is canonical).
+    let canonical_community_id = data["canonical_community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
-    let text = data["text"]
+        .unwrap_or(community_id);
+    let event = &data["event"];
+    let sender_did = data["sender_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing text"))?;
-
-    let our_did = identity.did_string();
-    let display_name = identity.profile().display_name.clone();
-    let message_id = uuid::Uuid::new_v4().to_string();
-    let now = crate::time::now_timestamp_millis();
-
-    // Encrypt with group key
-    let key_record = database
-        .get_latest_group_key(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
-        .ok_or_else(|| JsValue::from_str("No group key found"))?;
-    let raw_key = decrypt_stored_group_key(identity, &key_record, group_id)?;
-    let enc_key = crate::crypto::EncryptionKey::from_bytes(raw_key);
-    let aad = format!("group-msg:{}:{}:{}", group_id, our_did, now);
-    let (nonce, ciphertext) = crate::crypto::encrypt(&enc_key, text.as_bytes(), aad.as_bytes())
-        .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
-
-    let ciphertext_hex = hex::encode(&ciphertext);
-    let nonce_hex = hex::encode(&nonce.0);
-
-    // Store locally
-    database
-        .store_message(
-            &message_id,
-            conversation_id,
-            &our_did,
-            &ciphertext,
-            &nonce.0,
-            now,
-        )
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
 
-    // Build relay envelopes for all members except self
-    let members = database
-        .get_group_members(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let timestamp = crate::time::now_timestamp_millis();
 
     let envelope = serde_json::json!({
-        "envelope": "group_message",
+        "envelope": "community_event",
         "version": 1,
         "payload": {
-            "messageId": message_id,
-            "groupId": group_id,
-            "conversationId": conversation_id,
-            "senderDid": our_did,
-            "senderName": display_name,
-            "ciphertext": ciphertext_hex,
-            "nonce": nonce_hex,
-            "keyVersion": key_record.key_version,
-            "timestamp": now,
+            "communityId": canonical_community_id,
+            "event": event,
+            "senderDid": sender_did,
+            "timestamp": timestamp,
+        }
+    });
+    let envelope_str = envelope.to_string();
+
+    // Get community members from database
+    let members = database
+        .get_community_members(community_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+
+    let relay_messages: Vec<serde_json::Value> = members
+        .iter()
+        .filter(|m| m.member_did != sender_did)
+        .map(|m| {
+            serde_json::json!({
+                "to_did": m.member_did,
+                "payload": envelope_str,
+            })
+        })
+        .collect();
+
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&relay_messages).unwrap_or_default(),
+    ))
+}
+
+/// Build a dm_file_event relay envelope.
+///
+/// Pure data construction — no DB or crypto.
+/// Takes JSON: { "conversation_id", "sender_did", "event" (any JSON) }
+/// Returns JSON: { "payload": "<stringified envelope>" }
+#[wasm_bindgen]
+pub fn umbra_wasm_build_dm_file_event_envelope(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let conversation_id = data["conversation_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
+    let sender_did = data["sender_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
+    let event = &data["event"];
+
+    let timestamp = crate::time::now_timestamp_millis();
+
+    let envelope = serde_json::json!({
+        "envelope": "dm_file_event",
+        "version": 1,
+        "payload": {
+            "conversationId": conversation_id,
+            "senderDid": sender_did,
+            "timestamp": timestamp,
+            "event": event,
+        }
+    });
+
+    let result = serde_json::json!({
+        "payload": envelope.to_string(),
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Build an account_metadata relay envelope.
+///
+/// Pure data construction — no DB or crypto.
+/// Takes JSON: { "sender_did", "key", "value" }
+/// Returns JSON: { "to_did" (= sender_did), "payload": "<stringified envelope>" }
+#[wasm_bindgen]
+pub fn umbra_wasm_build_metadata_envelope(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let sender_did = data["sender_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
+    let key = data["key"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing key"))?;
+    let value = data["value"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing value"))?;
+
+    let timestamp = crate::time::now_timestamp_millis();
+
+    let envelope = serde_json::json!({
+        "envelope": "account_metadata",
+        "version": 1,
+        "payload": {
+            "senderDid": sender_did,
+            "key": key,
+            "value": value,
+            "timestamp": timestamp,
+        }
+    });
+
+    let result = serde_json::json!({
+        "to_did": sender_did,
+        "payload": envelope.to_string(),
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+// ============================================================================
+// ACCOUNT BACKUP
+// ============================================================================
+
+/// Create an encrypted account backup.
+///
+/// Exports the database, compresses, encrypts with a key derived from the
+/// master seed, chunks into 64KB pieces, and builds relay envelopes to send
+/// to the user's own DID.
+///
+/// Returns JSON: { "relayMessages": [{ "to_did", "payload" }], "chunkCount": N, "totalSize": N }
+#[wasm_bindgen]
+pub fn umbra_wasm_account_create_backup(_json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state_r = state.read();
+
+    let identity = state_r
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state_r
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    let seed = state_r
+        .backup_seed
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Backup seed not available — identity was not created or restored in this session"))?;
+
+    // 1. Derive backup encryption key
+    let backup_key = crate::crypto::derive_backup_key(seed)
+        .map_err(|e| JsValue::from_str(&format!("Key derivation failed: {}", e)))?;
+
+    // 2. Export database to JSON
+    let export_bytes = database
+        .export_database()
+        .map_err(|e| JsValue::from_str(&format!("Database export failed: {}", e)))?;
+
+    // 3. Compress with flate2-style deflate via miniz_oxide
+    let compressed = miniz_oxide::deflate::compress_to_vec(&export_bytes, 6);
+
+    // 4. Encrypt with AES-256-GCM
+    let enc_key = crate::crypto::EncryptionKey::from_bytes(backup_key);
+    let (nonce, encrypted) = crate::crypto::encrypt(&enc_key, &compressed, b"umbra-backup")
+        .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+
+    let total_size = encrypted.len();
+
+    // 5. Chunk into 64KB pieces
+    const CHUNK_SIZE: usize = 64 * 1024;
+    let chunks: Vec<&[u8]> = encrypted.chunks(CHUNK_SIZE).collect();
+    let total_chunks = chunks.len();
+    let own_did = identity.did_string();
+    let timestamp = crate::time::now_timestamp_millis();
+    let backup_id = format!("backup-{}", timestamp);
+
+    // 6. Build relay envelopes
+    let mut relay_messages = Vec::new();
+
+    // Manifest envelope
+    let manifest_envelope = serde_json::json!({
+        "envelope": "account_backup_manifest",
+        "version": 1,
+        "payload": {
+            "backupId": backup_id,
+            "senderDid": own_did,
+            "totalChunks": total_chunks,
+            "totalSize": total_size,
+            "nonce": hex::encode(nonce.as_bytes()),
+            "timestamp": timestamp,
+        }
+    });
+    relay_messages.push(serde_json::json!({
+        "to_did": own_did,
+        "payload": manifest_envelope.to_string(),
+    }));
+
+    // Chunk envelopes
+    for (i, chunk) in chunks.iter().enumerate() {
+        let chunk_hash = {
+            let digest = Sha256::digest(chunk);
+            hex::encode(digest)
+        };
+
+        let chunk_envelope = serde_json::json!({
+            "envelope": "account_backup_chunk",
+            "version": 1,
+            "payload": {
+                "backupId": backup_id,
+                "chunkIndex": i,
+                "totalChunks": total_chunks,
+                "data": base64::engine::general_purpose::STANDARD.encode(chunk),
+                "hash": chunk_hash,
+            }
+        });
+        relay_messages.push(serde_json::json!({
+            "to_did": own_did,
+            "payload": chunk_envelope.to_string(),
+        }));
+    }
+
+    let result = serde_json::json!({
+        "relayMessages": relay_messages,
+        "chunkCount": total_chunks,
+        "totalSize": total_size,
+    });
+
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Restore an account from an encrypted backup.
+///
+/// Takes ordered encrypted chunk data (base64), reassembles, decrypts,
+/// decompresses, and imports into the database.
+///
+/// Takes JSON: { "chunks": ["base64...", ...], "nonce": "hex..." }
+/// Returns JSON: { "imported": { "settings": N, "friends": N, ... } }
+#[wasm_bindgen]
+pub fn umbra_wasm_account_restore_backup(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state_r = state.read();
+
+    let database = state_r
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    let seed = state_r
+        .backup_seed
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Backup seed not available — identity was not created or restored in this session"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let chunks = data["chunks"]
+        .as_array()
+        .ok_or_else(|| JsValue::from_str("Missing chunks array"))?;
+    let nonce_hex = data["nonce"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing nonce"))?;
+
+    // 1. Derive backup encryption key
+    let backup_key = crate::crypto::derive_backup_key(seed)
+        .map_err(|e| JsValue::from_str(&format!("Key derivation failed: {}", e)))?;
+
+    // 2. Reassemble chunks
+    let mut encrypted = Vec::new();
+    for chunk_val in chunks {
+        let chunk_b64 = chunk_val
+            .as_str()
+            .ok_or_else(|| JsValue::from_str("Chunk must be a string"))?;
+        let chunk_bytes = base64::engine::general_purpose::STANDARD
+            .decode(chunk_b64)
+            .map_err(|e| JsValue::from_str(&format!("Invalid base64 chunk: {}", e)))?;
+        encrypted.extend_from_slice(&chunk_bytes);
+    }
+
+    // 3. Decrypt with AES-256-GCM
+    let enc_key = crate::crypto::EncryptionKey::from_bytes(backup_key);
+    let nonce_bytes = hex::decode(nonce_hex)
+        .map_err(|e| JsValue::from_str(&format!("Invalid nonce hex: {}", e)))?;
+    if nonce_bytes.len() != 12 {
+        return Err(JsValue::from_str("Nonce must be 12 bytes"));
+    }
+    let mut nonce_arr = [0u8; 12];
+    nonce_arr.copy_from_slice(&nonce_bytes);
+    let nonce = crate::crypto::Nonce::from_bytes(nonce_arr);
+    let compressed = crate::crypto::decrypt(&enc_key, &nonce, &encrypted, b"umbra-backup")
+        .map_err(|e| JsValue::from_str(&format!("Decryption failed: {}", e)))?;
+
+    // 4. Decompress
+    let decompressed = miniz_oxide::inflate::decompress_to_vec(&compressed)
+        .map_err(|e| JsValue::from_str(&format!("Decompression failed: {:?}", e)))?;
+
+    // 5. Import into database
+    let stats = database
+        .import_database(&decompressed)
+        .map_err(|e| JsValue::from_str(&format!("Database import failed: {}", e)))?;
+
+    let result = serde_json::json!({
+        "imported": {
+            "settings": stats.settings,
+            "friends": stats.friends,
+            "conversations": stats.conversations,
+            "groups": stats.groups,
+            "blocked_users": stats.blocked_users,
+        }
+    });
+
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+// ============================================================================
+// ACCOUNT SYNC — Encrypted blob creation, parsing, and challenge signing
+// ============================================================================
+
+/// Create an encrypted sync blob from the current database state.
+///
+/// Collects preferences, friends, groups, and blocked users, serialises to
+/// CBOR, compresses, and encrypts with AES-256-GCM using a key derived from
+/// the recovery seed.
+///
+/// Takes JSON: { "section_versions"?: { "preferences": N, "friends": N, ... } }
+/// Returns JSON: { "blob": "base64...", "sections": { "name": version } }
+#[wasm_bindgen]
+pub fn umbra_wasm_sync_create_blob(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state_r = state.read();
+
+    let database = state_r
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    let seed = state_r
+        .backup_seed
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Seed not available — identity was not created or restored in this session"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    // Parse optional section versions
+    let section_versions: Option<std::collections::HashMap<String, u64>> =
+        data.get("section_versions")
+            .and_then(|sv| serde_json::from_value(sv.clone()).ok());
+
+    // 1. Create sync payload from database
+    let payload = crate::sync::create_sync_blob(database, section_versions.as_ref())
+        .map_err(|e| JsValue::from_str(&format!("Sync blob creation failed: {}", e)))?;
+
+    // Collect section versions for response
+    let sections: serde_json::Value = payload
+        .sections
+        .iter()
+        .map(|(name, section)| (name.clone(), serde_json::json!(section.v)))
+        .collect::<serde_json::Map<String, serde_json::Value>>()
+        .into();
+
+    // 2. Encrypt the payload
+    let blob = crate::sync::encrypt_sync_blob(&payload, seed)
+        .map_err(|e| JsValue::from_str(&format!("Sync blob encryption failed: {}", e)))?;
+
+    let result = serde_json::json!({
+        "blob": base64::engine::general_purpose::STANDARD.encode(&blob),
+        "sections": sections,
+        "size": blob.len(),
+    });
+
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Parse a sync blob and return a summary without applying it.
+///
+/// Decrypts and inspects the blob to show what data it contains.
+///
+/// Takes JSON: { "blob": "base64..." }
+/// Returns JSON: { "v": 1, "updated_at": N, "sections": { "friends": { "v": N, "count": N } } }
+#[wasm_bindgen]
+pub fn umbra_wasm_sync_parse_blob(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state_r = state.read();
+
+    let seed = state_r
+        .backup_seed
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Seed not available"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let blob_b64 = data["blob"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing blob field"))?;
+
+    let blob = base64::engine::general_purpose::STANDARD
+        .decode(blob_b64)
+        .map_err(|e| JsValue::from_str(&format!("Invalid base64: {}", e)))?;
+
+    let summary = crate::sync::parse_sync_blob_summary(&blob, seed)
+        .map_err(|e| JsValue::from_str(&format!("Sync blob parse failed: {}", e)))?;
+
+    let result = serde_json::to_value(&summary)
+        .map_err(|e| JsValue::from_str(&format!("Serialization failed: {}", e)))?;
+
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Apply a sync blob — decrypt and import its contents into the database.
+///
+/// Takes JSON: { "blob": "base64..." }
+/// Returns JSON: { "imported": { "settings": N, "friends": N, "groups": N, "blocked_users": N } }
+#[wasm_bindgen]
+pub fn umbra_wasm_sync_apply_blob(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state_r = state.read();
+
+    let database = state_r
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    let seed = state_r
+        .backup_seed
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Seed not available"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let blob_b64 = data["blob"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing blob field"))?;
+
+    let blob = base64::engine::general_purpose::STANDARD
+        .decode(blob_b64)
+        .map_err(|e| JsValue::from_str(&format!("Invalid base64: {}", e)))?;
+
+    // 1. Decrypt the sync blob
+    let payload = crate::sync::decrypt_sync_blob(&blob, seed)
+        .map_err(|e| JsValue::from_str(&format!("Sync blob decryption failed: {}", e)))?;
+
+    // 2. Reconstruct a backup-compatible JSON for import_database()
+    let import_json = serde_json::json!({
+        "version": 1,
+        "exported_at": payload.updated_at,
+        "settings": payload.sections.get("preferences").map(|s| &s.data).unwrap_or(&serde_json::json!([])),
+        "friends": payload.sections.get("friends").map(|s| &s.data).unwrap_or(&serde_json::json!([])),
+        "groups": payload.sections.get("groups").map(|s| &s.data).unwrap_or(&serde_json::json!([])),
+        "blocked_users": payload.sections.get("blocked").map(|s| &s.data).unwrap_or(&serde_json::json!([])),
+        "conversations": [],
+    });
+
+    let import_bytes = serde_json::to_vec(&import_json)
+        .map_err(|e| JsValue::from_str(&format!("Serialization failed: {}", e)))?;
+
+    // 3. Import into database using existing import_database()
+    let stats = database
+        .import_database(&import_bytes)
+        .map_err(|e| JsValue::from_str(&format!("Database import failed: {}", e)))?;
+
+    let result = serde_json::json!({
+        "imported": {
+            "settings": stats.settings,
+            "friends": stats.friends,
+            "groups": stats.groups,
+            "blocked_users": stats.blocked_users,
+        }
+    });
+
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Sign a sync auth challenge nonce with the identity's Ed25519 key.
+///
+/// Used for the relay's challenge-response auth flow.
+///
+/// Takes JSON: { "nonce": "uuid-string" }
+/// Returns JSON: { "signature": "base64...", "public_key": "base64..." }
+#[wasm_bindgen]
+pub fn umbra_wasm_sync_sign_challenge(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state_r = state.read();
+
+    let identity = state_r
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let nonce = data["nonce"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing nonce field"))?;
+
+    // Sign the nonce with Ed25519
+    let signature = crate::crypto::sign(&identity.keypair().signing, nonce.as_bytes());
+    let public_key = identity.keypair().signing.public_bytes();
+
+    let b64 = base64::engine::general_purpose::STANDARD;
+
+    let result = serde_json::json!({
+        "signature": base64::Engine::encode(&b64, signature.as_bytes()),
+        "public_key": base64::Engine::encode(&b64, &public_key),
+    });
+
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+// ============================================================================
+// GROUP RELAY ENVELOPE BUILDERS (orchestrate DB + crypto + envelope)
+// ============================================================================
+
+/// Send a group invitation: encrypt group key for invitee, build `group_invite` envelope.
+///
+/// Orchestrates: get group info, get profile, encrypt key for member, get members list,
+/// generate UUID, and build the relay envelope.
+///
+/// Takes JSON: { "group_id", "member_did" }
+/// Returns JSON: { "relay_messages": [{ "to_did", "payload" }] }
+#[wasm_bindgen]
+pub fn umbra_wasm_groups_send_invite(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let member_did = data["member_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
+
+    let our_did = identity.did_string();
+    let display_name = identity.profile().display_name.clone();
+    let now = crate::time::now_timestamp_millis();
+    let invite_id = uuid::Uuid::new_v4().to_string();
+
+    // Get group info
+    let group = database
+        .get_group(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Group not found"))?;
+
+    // Get latest key and decrypt it
+    let key_record = database
+        .get_latest_group_key(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("No group key found"))?;
+    let raw_key = decrypt_stored_group_key(identity, &key_record, group_id)?;
+
+    // Encrypt group key for the invitee via ECDH
+    let (enc_key_hex, nonce_hex) = encrypt_group_key_for_member_internal(
+        identity,
+        database,
+        group_id,
+        member_did,
+        &raw_key,
+        key_record.key_version,
+    )?;
+
+    // Get members list
+    let members = database
+        .get_group_members(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let members_json_arr: Vec<serde_json::Value> = members
+        .iter()
+        .map(|m| {
+            serde_json::json!({
+                "did": m.member_did,
+                "display_name": m.display_name,
+                "role": m.role,
+            })
+        })
+        .collect();
+    let members_json_str = serde_json::to_string(&members_json_arr).unwrap_or_default();
+
+    // Build relay envelope
+    let envelope = serde_json::json!({
+        "envelope": "group_invite",
+        "version": 1,
+        "payload": {
+            "inviteId": invite_id,
+            "groupId": group_id,
+            "groupName": group.name,
+            "description": group.description,
+            "inviterDid": our_did,
+            "inviterName": display_name,
+            "encryptedGroupKey": enc_key_hex,
+            "nonce": nonce_hex,
+            "membersJson": members_json_str,
+            "timestamp": now,
+        }
+    });
+
+    let result = serde_json::json!({
+        "relay_messages": [{
+            "to_did": member_did,
+            "payload": envelope.to_string(),
+        }],
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Build a `group_invite_accept` relay envelope.
+///
+/// Takes JSON: { "invite_id", "group_id" }
+/// Returns JSON: { "relay_messages": [{ "to_did", "payload" }] }
+#[wasm_bindgen]
+pub fn umbra_wasm_groups_build_invite_accept_envelope(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let invite_id = data["invite_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing invite_id"))?;
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+
+    let our_did = identity.did_string();
+    let display_name = identity.profile().display_name.clone();
+    let now = crate::time::now_timestamp_millis();
+
+    // Get the invite to find the inviter
+    let invite = database
+        .get_group_invite(invite_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Invite not found"))?;
+
+    // The inviter adds us to the MLS group with this key package
+    let key_package = crate::messaging::mls::new_key_package(identity, database)
+        .map_err(|e| JsValue::from_str(&format!("Failed to create key package: {}", e)))?;
+
+    let envelope = serde_json::json!({
+        "envelope": "group_invite_accept",
+        "version": 1,
+        "payload": {
+            "inviteId": invite_id,
+            "groupId": group_id,
+            "fromDid": our_did,
+            "fromDisplayName": display_name,
+            "keyPackage": key_package,
+            "timestamp": now,
+        }
+    });
+
+    let result = serde_json::json!({
+        "relay_messages": [{
+            "to_did": invite.inviter_did,
+            "payload": envelope.to_string(),
+        }],
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Build a `group_invite_decline` relay envelope.
+///
+/// Takes JSON: { "invite_id" }
+/// Returns JSON: { "relay_messages": [{ "to_did", "payload" }] }
+#[wasm_bindgen]
+pub fn umbra_wasm_groups_build_invite_decline_envelope(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let invite_id = data["invite_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing invite_id"))?;
+
+    let our_did = identity.did_string();
+    let display_name = identity.profile().display_name.clone();
+    let now = crate::time::now_timestamp_millis();
+
+    // Get the invite to find the inviter
+    let invite = database
+        .get_group_invite(invite_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        .ok_or_else(|| JsValue::from_str("Invite not found"))?;
+
+    let envelope = serde_json::json!({
+        "envelope": "group_invite_decline",
+        "version": 1,
+        "payload": {
+            "inviteId": invite_id,
+            "groupId": invite.group_id,
+            "fromDid": our_did,
+            "fromDisplayName": display_name,
+            "timestamp": now,
+        }
+    });
+
+    let result = serde_json::json!({
+        "relay_messages": [{
+            "to_did": invite.inviter_did,
+            "payload": envelope.to_string(),
+        }],
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Send a group message: encrypt, store locally, build `group_message` envelopes for all members.
+///
+/// Takes JSON: { "group_id", "conversation_id", "text" }
+/// Returns JSON: { "message": { id, conversationId, senderDid, timestamp },
+///                  "relay_messages": [{ "to_did", "payload" }, ...] }
+#[wasm_bindgen]
+pub fn umbra_wasm_groups_send_message(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let conversation_id = data["conversation_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
+    let text = data["text"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing text"))?;
+
+    let our_did = identity.did_string();
+    let display_name = identity.profile().display_name.clone();
+    let message_id = uuid::Uuid::new_v4().to_string();
+    let now = crate::time::now_timestamp_millis();
+
+    let (ciphertext_hex, nonce_hex, key_version) =
+        if crate::messaging::mls::has_session(database, group_id)
+            .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+        {
+            // MLS message keys are single use, so we keep the plaintext of our
+            // own message, as for ratcheted DMs
+            let aad = crate::messaging::mls::message_aad(group_id, &our_did, now);
+            let ciphertext_hex = crate::messaging::mls::encrypt_message(
+                identity,
+                database,
+                group_id,
+                text.as_bytes(),
+                &aad,
+            )
+            .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+            database
+                .store_message(
+                    &message_id,
+                    conversation_id,
+                    &our_did,
+                    text.as_bytes(),
+                    &[0u8; 12],
+                    now,
+                )
+                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+            (
+                ciphertext_hex,
+                String::new(),
+                crate::messaging::mls::MLS_KEY_VERSION,
+            )
+        } else {
+            // Encrypt with group key
+            let key_record = database
+                .get_latest_group_key(group_id)
+                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+                .ok_or_else(|| JsValue::from_str("No group key found"))?;
+            let raw_key = decrypt_stored_group_key(identity, &key_record, group_id)?;
+            let enc_key = crate::crypto::EncryptionKey::from_bytes(raw_key);
+            let aad = format!("group-msg:{}:{}:{}", group_id, our_did, now);
+            let (nonce, ciphertext) =
+                crate::crypto::encrypt(&enc_key, text.as_bytes(), aad.as_bytes())
+                    .map_err(|e| JsValue::from_str(&format!("Encryption failed: {}", e)))?;
+
+            // Store locally
+            database
+                .store_message(
+                    &message_id,
+                    conversation_id,
+                    &our_did,
+                    &ciphertext,
+                    &nonce.0,
+                    now,
+                )
+                .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+            (
+                hex::encode(&ciphertext),
+                hex::encode(nonce.0),
+                key_record.key_version,
+            )
+        };
+    let _ = database.index_message(&message_id, text);
+
+    // Build relay envelopes for all members except self
+    let members = database
+        .get_group_members(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+
+    let envelope = serde_json::json!({
+        "envelope": "group_message",
+        "version": 1,
+        "payload": {
+            "messageId": message_id,
+            "groupId": group_id,
+            "conversationId": conversation_id,
+            "senderDid": our_did,
+            "senderName": display_name,
+            "ciphertext": ciphertext_hex,
+            "nonce": nonce_hex,
+            "keyVersion": key_version,
+            "timestamp": now,
         }
     });
     let envelope_str = envelope.to_string();
@@ -4490,42 +5225,351 @@ pub fn umbra_wasm_groups_send_message(json: &str) -> Result<JsValue, JsValue> {
         })
         .collect();
 
-    // Emit event
+    // Emit event
+    emit_event(
+        "message",
+        &serde_json::json!({
+            "type": "messageSent",
+            "conversationId": conversation_id,
+            "message": {
+                "id": message_id,
+                "conversationId": conversation_id,
+                "senderDid": our_did,
+                "content": { "type": "text", "text": text },
+                "timestamp": now,
+                "status": "sent",
+            }
+        }),
+    );
+
+    let result = serde_json::json!({
+        "message": {
+            "id": message_id,
+            "conversationId": conversation_id,
+            "senderDid": our_did,
+            "timestamp": now,
+        },
+        "relay_messages": relay_messages,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Remove a member with key rotation: remove, rotate key, encrypt new key per remaining member,
+/// build `group_key_rotation` + `group_member_removed` envelopes.
+///
+/// Takes JSON: { "group_id", "member_did" }
+/// Returns JSON: { "key_version": number, "relay_messages": [{ "to_did", "payload" }, ...] }
+#[wasm_bindgen]
+pub fn umbra_wasm_groups_remove_member_with_rotation(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let member_did = data["member_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
+
+    let our_did = identity.did_string();
+    let now = crate::time::now_timestamp_millis();
+
+    // 1. Remove member
+    database
+        .remove_group_member(group_id, member_did)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+
+    if crate::messaging::mls::has_session(database, group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?
+    {
+        return remove_mls_group_member(identity, database, group_id, member_did);
+    }
+
+    // 2. Rotate key (generate new random key, store encrypted)
+    let current = database
+        .get_latest_group_key(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let new_version = current.map(|k| k.key_version + 1).unwrap_or(1);
+
+    let mut new_raw_key = [0u8; 32];
+    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut new_raw_key);
+
+    let wrapping_key = derive_key_wrapping_key(identity);
+    let wrap_enc_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
+    let aad = format!("group-key:{}:{}", group_id, new_version);
+    let (wrap_nonce, encrypted_key) =
+        crate::crypto::encrypt(&wrap_enc_key, &new_raw_key, aad.as_bytes())
+            .map_err(|e| JsValue::from_str(&format!("Failed to encrypt group key: {}", e)))?;
+
+    let mut stored = Vec::with_capacity(12 + encrypted_key.len());
+    stored.extend_from_slice(&wrap_nonce.0);
+    stored.extend_from_slice(&encrypted_key);
+    database
+        .store_group_key(group_id, new_version, &stored, now)
+        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
+
+    // 3. Get remaining members and build envelopes
+    let members = database
+        .get_group_members(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+
+    let mut relay_messages: Vec<serde_json::Value> = Vec::new();
+    // Members we couldn't wrap the new key for, reported to the caller
+    let mut unreachable_members: Vec<String> = Vec::new();
+
+    // Build group_member_removed envelope (same for all)
+    let remove_envelope = serde_json::json!({
+        "envelope": "group_member_removed",
+        "version": 1,
+        "payload": {
+            "groupId": group_id,
+            "removedDid": member_did,
+            "removedBy": our_did,
+            "timestamp": now,
+        }
+    });
+    let remove_envelope_str = remove_envelope.to_string();
+
+    for m in &members {
+        if m.member_did == our_did {
+            continue;
+        }
+
+        // Encrypt new key for this member via ECDH
+        match encrypt_group_key_for_member_internal(
+            identity,
+            database,
+            group_id,
+            &m.member_did,
+            &new_raw_key,
+            new_version,
+        ) {
+            Ok((enc_key_hex, enc_nonce_hex)) => {
+                // Key rotation envelope (per-member unique ECDH payload)
+                let key_envelope = serde_json::json!({
+                    "envelope": "group_key_rotation",
+                    "version": 1,
+                    "payload": {
+                        "groupId": group_id,
+                        "encryptedKey": enc_key_hex,
+                        "nonce": enc_nonce_hex,
+                        "senderDid": our_did,
+                        "keyVersion": new_version,
+                        "timestamp": now,
+                    }
+                });
+                relay_messages.push(serde_json::json!({
+                    "to_did": m.member_did,
+                    "payload": key_envelope.to_string(),
+                }));
+            }
+            Err(e) => {
+                // Log but don't fail the whole operation
+                web_sys::console::warn_1(&JsValue::from_str(&format!(
+                    "[groups] Failed to encrypt rotated key for {}: {:?}",
+                    m.member_did, e
+                )));
+                unreachable_members.push(m.member_did.clone());
+            }
+        }
+
+        // Member removed envelope
+        relay_messages.push(serde_json::json!({
+            "to_did": m.member_did,
+            "payload": remove_envelope_str,
+        }));
+    }
+
+    emit_event(
+        "group",
+        &serde_json::json!({
+            "type": "groupMemberRemoved",
+            "group_id": group_id,
+            "member_did": member_did,
+            "key_version": new_version,
+            "unreachable_members": unreachable_members,
+        }),
+    );
+
+    let result = serde_json::json!({
+        "key_version": new_version,
+        "unreachable_members": unreachable_members,
+        "relay_messages": relay_messages,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Add a member to a group's MLS session from their key package.
+///
+/// Takes JSON: { "group_id", "member_did", "key_package" }
+/// Returns JSON: { "group_id", "epoch", "relay_messages": [{ "to_did", "payload" }, ...] }
+#[wasm_bindgen]
+pub fn umbra_wasm_groups_mls_add_member(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let member_did = data["member_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
+    let key_package = data["key_package"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing key_package"))?;
+
+    let change =
+        crate::messaging::mls::add_member(identity, database, group_id, member_did, key_package)
+            .map_err(|e| JsValue::from_str(&format!("Failed to add member to MLS group: {}", e)))?;
+    let relay_messages =
+        mls_change_relay_messages(database, group_id, &identity.did_string(), &change)?;
+
+    emit_event(
+        "group",
+        &serde_json::json!({
+            "type": "mlsEpochChanged",
+            "group_id": group_id,
+            "epoch": change.epoch,
+        }),
+    );
+
+    let result = serde_json::json!({
+        "group_id": group_id,
+        "epoch": change.epoch,
+        "relay_messages": relay_messages,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Join a group's MLS session from a `group_mls_welcome` envelope.
+///
+/// Takes JSON: { "welcome" }
+/// Returns JSON: { "group_id", "epoch", "members" }
+#[wasm_bindgen]
+pub fn umbra_wasm_groups_mls_process_welcome(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let welcome = data["welcome"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing welcome"))?;
+
+    let group = crate::messaging::mls::process_welcome(database, welcome)
+        .map_err(|e| JsValue::from_str(&format!("Failed to join MLS group: {}", e)))?;
+
+    emit_event(
+        "group",
+        &serde_json::json!({
+            "type": "mlsEpochChanged",
+            "group_id": group.group_id(),
+            "epoch": group.epoch(),
+        }),
+    );
+
+    let result = serde_json::json!({
+        "group_id": group.group_id(),
+        "epoch": group.epoch(),
+        "members": group.members(),
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
+
+/// Apply a `group_mls_commit` from the group admin.
+///
+/// Takes JSON: { "group_id", "commit" }
+/// Returns JSON: { "group_id", "status": "applied" | "removed", "committer", "added"?, "removed"? }
+#[wasm_bindgen]
+pub fn umbra_wasm_groups_mls_process_commit(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let group_id = data["group_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
+    let commit = data["commit"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing commit"))?;
+
+    let outcome = crate::messaging::mls::process_commit(database, group_id, commit)
+        .map_err(|e| JsValue::from_str(&format!("Failed to process MLS commit: {}", e)))?;
+
+    let result = match outcome {
+        crate::crypto::CommitOutcome::Applied {
+            committer,
+            added,
+            removed,
+        } => serde_json::json!({
+            "group_id": group_id,
+            "status": "applied",
+            "committer": committer,
+            "added": added,
+            "removed": removed,
+        }),
+        crate::crypto::CommitOutcome::Removed { committer } => serde_json::json!({
+            "group_id": group_id,
+            "status": "removed",
+            "committer": committer,
+        }),
+    };
+
     emit_event(
-        "message",
+        "group",
         &serde_json::json!({
-            "type": "messageSent",
-            "conversationId": conversation_id,
-            "message": {
-                "id": message_id,
-                "conversationId": conversation_id,
-                "senderDid": our_did,
-                "content": { "type": "text", "text": text },
-                "timestamp": now,
-                "status": "sent",
-            }
+            "type": "mlsCommitProcessed",
+            "group_id": group_id,
+            "status": result["status"],
         }),
     );
 
-    let result = serde_json::json!({
-        "message": {
-            "id": message_id,
-            "conversationId": conversation_id,
-            "senderDid": our_did,
-            "timestamp": now,
-        },
-        "relay_messages": relay_messages,
-    });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-/// Remove a member with key rotation: remove, rotate key, encrypt new key per remaining member,
-/// build `group_key_rotation` + `group_member_removed` envelopes.
+/// Refresh our keys in a group's MLS session (post-compromise security).
 ///
-/// Takes JSON: { "group_id", "member_did" }
-/// Returns JSON: { "key_version": number, "relay_messages": [{ "to_did", "payload" }, ...] }
+/// Takes JSON: { "group_id" }
+/// Returns JSON: { "group_id", "epoch", "relay_messages": [{ "to_did", "payload" }, ...] }
 #[wasm_bindgen]
-pub fn umbra_wasm_groups_remove_member_with_rotation(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_groups_mls_update(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -4544,49 +5588,101 @@ pub fn umbra_wasm_groups_remove_member_with_rotation(json: &str) -> Result<JsVal
     let group_id = data["group_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing group_id"))?;
-    let member_did = data["member_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-
-    let our_did = identity.did_string();
-    let now = crate::time::now_timestamp_millis();
-
-    // 1. Remove member
-    database
-        .remove_group_member(group_id, member_did)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
 
-    // 2. Rotate key (generate new random key, store encrypted)
-    let current = database
-        .get_latest_group_key(group_id)
-        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
-    let new_version = current.map(|k| k.key_version + 1).unwrap_or(1);
+    let change = crate::messaging::mls::update(identity, database, group_id)
+        .map_err(|e| JsValue::from_str(&format!("Failed to update MLS keys: {}", e)))?;
+    let relay_messages =
+        mls_change_relay_messages(database, group_id, &identity.did_string(), &change)?;
 
-    let mut new_raw_key = [0u8; 32];
-    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut new_raw_key);
+    let result = serde_json::json!({
+        "group_id": group_id,
+        "epoch": change.epoch,
+        "relay_messages": relay_messages,
+    });
+    Ok(JsValue::from_str(&result.to_string()))
+}
 
-    let wrapping_key = derive_key_wrapping_key(identity);
-    let wrap_enc_key = crate::crypto::EncryptionKey::from_bytes(wrapping_key);
-    let aad = format!("group-key:{}:{}", group_id, new_version);
-    let (wrap_nonce, encrypted_key) =
-        crate::crypto::encrypt(&wrap_enc_key, &new_raw_key, aad.as_bytes())
-            .map_err(|e| JsValue::from_str(&format!("Failed to encrypt group key: {}", e)))?;
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
 
-    let mut stored = Vec::with_capacity(12 + encrypted_key.len());
-    stored.extend_from_slice(&wrap_nonce.0);
-    stored.extend_from_slice(&encrypted_key);
-    database
-        .store_group_key(group_id, new_version, &stored, now)
-        .map_err(|e| JsValue::from_str(&format!("Failed to store group key: {}", e)))?;
+/// Build a `group_mls_commit` relay envelope.
+fn mls_commit_envelope(group_id: &str, sender_did: &str, commit: &str) -> String {
+    serde_json::json!({
+        "envelope": "group_mls_commit",
+        "version": 1,
+        "payload": {
+            "groupId": group_id,
+            "senderDid": sender_did,
+            "commit": commit,
+            "timestamp": crate::time::now_timestamp_millis(),
+        }
+    })
+    .to_string()
+}
 
-    // 3. Get remaining members and build envelopes
+/// Relay messages for an MLS commit: the commit to every other member of the
+/// previous epoch, and the Welcome (if any) to the added members.
+fn mls_change_relay_messages(
+    database: &crate::storage::Database,
+    group_id: &str,
+    our_did: &str,
+    change: &crate::messaging::mls::MembershipChange,
+) -> Result<Vec<serde_json::Value>, JsValue> {
     let members = database
         .get_group_members(group_id)
         .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let commit_envelope = mls_commit_envelope(group_id, our_did, &change.commit);
 
-    let mut relay_messages: Vec<serde_json::Value> = Vec::new();
+    let mut relay_messages: Vec<serde_json::Value> = members
+        .iter()
+        .filter(|m| m.member_did != our_did && !change.added.contains(&m.member_did))
+        .map(|m| {
+            serde_json::json!({
+                "to_did": m.member_did,
+                "payload": commit_envelope,
+            })
+        })
+        .collect();
 
-    // Build group_member_removed envelope (same for all)
+    if let Some(welcome) = &change.welcome {
+        let welcome_envelope = serde_json::json!({
+            "envelope": "group_mls_welcome",
+            "version": 1,
+            "payload": {
+                "groupId": group_id,
+                "senderDid": our_did,
+                "welcome": welcome,
+                "timestamp": crate::time::now_timestamp_millis(),
+            }
+        })
+        .to_string();
+        for did in &change.added {
+            relay_messages.push(serde_json::json!({
+                "to_did": did,
+                "payload": welcome_envelope,
+            }));
+        }
+    }
+
+    Ok(relay_messages)
+}
+
+/// MLS half of `umbra_wasm_groups_remove_member_with_rotation`: one commit
+/// re-keys the group, sent to the remaining members and to the removed
+/// member, so they learn they've been removed.
+fn remove_mls_group_member(
+    identity: &crate::identity::Identity,
+    database: &crate::storage::Database,
+    group_id: &str,
+    member_did: &str,
+) -> Result<JsValue, JsValue> {
+    let our_did = identity.did_string();
+    let change = crate::messaging::mls::remove_member(identity, database, group_id, member_did)
+        .map_err(|e| {
+            JsValue::from_str(&format!("Failed to remove member from MLS group: {}", e))
+        })?;
+    let commit_envelope = mls_commit_envelope(group_id, &our_did, &change.commit);
     let remove_envelope = serde_json::json!({
         "envelope": "group_member_removed",
         "version": 1,
@@ -4594,57 +5690,26 @@ pub fn umbra_wasm_groups_remove_member_with_rotation(json: &str) -> Result<JsVal
             "groupId": group_id,
             "removedDid": member_did,
             "removedBy": our_did,
-            "timestamp": now,
-        }
-    });
-    let remove_envelope_str = remove_envelope.to_string();
-
-    for m in &members {
-        if m.member_did == our_did {
-            continue;
-        }
-
-        // Encrypt new key for this member via ECDH
-        match encrypt_group_key_for_member_internal(
-            identity,
-            database,
-            group_id,
-            &m.member_did,
-            &new_raw_key,
-            new_version,
-        ) {
-            Ok((enc_key_hex, enc_nonce_hex)) => {
-                // Key rotation envelope (per-member unique ECDH payload)
-                let key_envelope = serde_json::json!({
-                    "envelope": "group_key_rotation",
-                    "version": 1,
-                    "payload": {
-                        "groupId": group_id,
-                        "encryptedKey": enc_key_hex,
-                        "nonce": enc_nonce_hex,
-                        "senderDid": our_did,
-                        "keyVersion": new_version,
-                        "timestamp": now,
-                    }
-                });
-                relay_messages.push(serde_json::json!({
-                    "to_did": m.member_did,
-                    "payload": key_envelope.to_string(),
-                }));
-            }
-            Err(e) => {
-                // Log but don't fail the whole operation
-                web_sys::console::warn_1(&JsValue::from_str(&format!(
-                    "[groups] Failed to encrypt rotated key for {}: {:?}",
-                    m.member_did, e
-                )));
-            }
+            "timestamp": crate::time::now_timestamp_millis(),
         }
+    })
+    .to_string();
 
-        // Member removed envelope
+    let members = database
+        .get_group_members(group_id)
+        .map_err(|e| JsValue::from_str(&format!("DB error: {}", e)))?;
+    let mut relay_messages = vec![serde_json::json!({
+        "to_did": member_did,
+        "payload": commit_envelope,
+    })];
+    for m in members.iter().filter(|m| m.member_did != our_did) {
         relay_messages.push(serde_json::json!({
             "to_did": m.member_did,
-            "payload": remove_envelope_str,
+            "payload": commit_envelope,
+        }));
+        relay_messages.push(serde_json::json!({
+            "to_did": m.member_did,
+            "payload": remove_envelope,
         }));
     }
 
@@ -4654,21 +5719,19 @@ pub fn umbra_wasm_groups_remove_member_with_rotation(json: &str) -> Result<JsVal
             "type": "groupMemberRemoved",
             "group_id": group_id,
             "member_did": member_did,
-            "key_version": new_version,
+            "epoch": change.epoch,
         }),
     );
 
     let result = serde_json::json!({
-        "key_version": new_version,
+        "key_version": crate::messaging::mls::MLS_KEY_VERSION,
+        "epoch": change.epoch,
+        "unreachable_members": Vec::<String>::new(),
         "relay_messages": relay_messages,
     });
     Ok(JsValue::from_str(&result.to_string()))
 }
 
-// ============================================================================
-// HELPER FUNCTIONS
-// ============================================================================
-
 /// Encrypt a group key for a specific member using ECDH (internal helper).
 ///
 /// Looks up the member's encryption public key from friends table and encrypts
@@ -5011,6 +6074,7 @@ fn handle_inbound_message(
                     &nonce_arr,
                     msg.timestamp,
                 );
+                let _ = database.index_message(&msg.message_id, &content);
 
                 // Emit JS event for real-time UI update
                 emit_event(
@@ -5173,6 +6237,19 @@ pub fn umbra_wasm_network_stop() -> Promise {
     })
 }
 
+/// Set the STUN/TURN servers from the relay's `registered` message
+///
+/// Takes the message's `ice_servers` array as JSON. Offers and answers
+/// created afterwards use them alongside the public STUN servers.
+#[wasm_bindgen]
+pub fn umbra_wasm_network_set_ice_servers(ice_servers_json: &str) -> Result<JsValue, JsValue> {
+    let servers: Vec<crate::network::relay_client::RelayIceServer> =
+        serde_json::from_str(ice_servers_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid ICE servers: {}", e)))?;
+    crate::network::webrtc_transport::set_relay_ice_servers(servers);
+    Ok(JsValue::TRUE)
+}
+
 /// Create a WebRTC offer for signaling (step 1 of connection)
 ///
 /// Returns JSON string with SDP offer, ICE candidates, and our DID + PeerId.
@@ -5613,25 +6690,101 @@ pub fn umbra_wasm_relay_accept_session(session_id: &str, offer_payload: &str) ->
         });
 
         let result = serde_json::json!({
-            "session_id": session_id,
-            "answer_payload": answer_json,
-            "join_session_message": join_session_msg.to_string(),
-            "did": did,
-            "peer_id": peer_id,
+            "session_id": session_id,
+            "answer_payload": answer_json,
+            "join_session_message": join_session_msg.to_string(),
+            "did": did,
+            "peer_id": peer_id,
+        });
+
+        Ok(JsValue::from_str(&result.to_string()))
+    })
+}
+
+/// Send a message through the relay (for offline delivery).
+///
+/// Returns the relay message for the JS layer to send via WebSocket.
+#[wasm_bindgen]
+pub fn umbra_wasm_relay_send(to_did: &str, payload: &str) -> Promise {
+    let to_did = to_did.to_string();
+    let payload = payload.to_string();
+    future_to_promise(async move {
+        let state = get_state()?;
+        let state_r = state.read();
+
+        let identity = state_r
+            .identity
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+
+        let did = identity.did_string();
+        drop(state_r);
+
+        let send_msg = serde_json::json!({
+            "type": "send",
+            "to_did": to_did,
+            "payload": payload,
+        });
+
+        let result = serde_json::json!({
+            "sent": true,
+            "to_did": to_did,
+            "from_did": did,
+            "relay_message": send_msg.to_string(),
+        });
+
+        Ok(JsValue::from_str(&result.to_string()))
+    })
+}
+
+/// Answer a relay registration challenge.
+///
+/// Signs the nonce from the relay's `auth_challenge` with the identity's
+/// Ed25519 key and returns the authenticate message for the JS layer to
+/// send via WebSocket.
+///
+/// Returns JSON: { "did": "...", "relay_message": "..." }
+#[wasm_bindgen]
+pub fn umbra_wasm_relay_authenticate(nonce: &str) -> Promise {
+    let nonce = nonce.to_string();
+    future_to_promise(async move {
+        let state = get_state()?;
+        let state_r = state.read();
+
+        let identity = state_r
+            .identity
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+
+        let auth_msg = crate::network::relay_client::RelayClientMessage::authenticate(
+            &identity.keypair().signing,
+            &nonce,
+        );
+
+        let result = serde_json::json!({
+            "did": identity.did_string(),
+            "relay_message": serde_json::to_string(&auth_msg)
+                .map_err(|e| JsValue::from_str(&e.to_string()))?,
         });
 
         Ok(JsValue::from_str(&result.to_string()))
     })
 }
 
-/// Send a message through the relay (for offline delivery).
+/// Sign a community invite for publishing to the relay.
 ///
-/// Returns the relay message for the JS layer to send via WebSocket.
+/// Takes a JSON `RelayInvite` and returns the `publish_invite` message,
+/// signed with the identity's Ed25519 key, for the JS layer to send via
+/// WebSocket.
+///
+/// Returns JSON: { "did": "...", "relay_message": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_relay_send(to_did: &str, payload: &str) -> Promise {
-    let to_did = to_did.to_string();
-    let payload = payload.to_string();
+pub fn umbra_wasm_relay_publish_invite(invite_json: &str) -> Promise {
+    let invite_json = invite_json.to_string();
     future_to_promise(async move {
+        let invite: crate::network::relay_client::RelayInvite = serde_json::from_str(&invite_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid invite: {}", e)))?;
+
         let state = get_state()?;
         let state_r = state.read();
 
@@ -5641,25 +6794,37 @@ pub fn umbra_wasm_relay_send(to_did: &str, payload: &str) -> Promise {
             .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
 
         let did = identity.did_string();
-        drop(state_r);
-
-        let send_msg = serde_json::json!({
-            "type": "send",
-            "to_did": to_did,
-            "payload": payload,
-        });
+        let msg = crate::network::relay_client::RelayClientMessage::publish_invite(
+            &identity.keypair().signing,
+            &did,
+            invite,
+        );
 
         let result = serde_json::json!({
-            "sent": true,
-            "to_did": to_did,
-            "from_did": did,
-            "relay_message": send_msg.to_string(),
+            "did": did,
+            "relay_message": serde_json::to_string(&msg)
+                .map_err(|e| JsValue::from_str(&e.to_string()))?,
         });
 
         Ok(JsValue::from_str(&result.to_string()))
     })
 }
 
+/// Rank candidate relays for connecting, best first.
+///
+/// Takes a JSON array of relay directory entries (from `GET /relays`), each
+/// with the `latency_ms` measured by the JS layer (`null` if unreachable).
+///
+/// Returns JSON: { "relays": [...] }
+#[wasm_bindgen]
+pub fn umbra_wasm_relay_rank(relays_json: &str) -> Result<JsValue, JsValue> {
+    let candidates: Vec<crate::network::relay_client::RelayCandidate> =
+        serde_json::from_str(relays_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid relays: {}", e)))?;
+    let ranked = crate::network::relay_client::rank_relays(candidates);
+    Ok(JsValue::from_str(&serde_json::json!({ "relays": ranked }).to_string()))
+}
+
 /// Fetch offline messages from the relay.
 ///
 /// Returns the fetch_offline message for the JS layer to send via WebSocket.
@@ -5676,6 +6841,28 @@ pub fn umbra_wasm_relay_fetch_offline() -> Promise {
     })
 }
 
+/// Acknowledge offline messages so the relay can delete them.
+///
+/// Takes a JSON array of offline message IDs and returns the ack_offline
+/// message for the JS layer to send via WebSocket.
+#[wasm_bindgen]
+pub fn umbra_wasm_relay_ack_offline(ids_json: &str) -> Promise {
+    let ids_json = ids_json.to_string();
+    future_to_promise(async move {
+        let _state = get_state()?;
+
+        let ids: Vec<String> = serde_json::from_str(&ids_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+        let msg = serde_json::json!({
+            "type": "ack_offline",
+            "ids": ids,
+        });
+
+        Ok(JsValue::from_str(&msg.to_string()))
+    })
+}
+
 // ============================================================================
 // CRYPTO UTILITIES (for web)
 // ============================================================================
@@ -5989,116 +7176,421 @@ pub fn umbra_wasm_plugin_kv_list(plugin_id: &str, prefix: &str) -> Result<JsValu
 
 /// Save a plugin bundle to local storage
 #[wasm_bindgen]
-pub fn umbra_wasm_plugin_bundle_save(
-    plugin_id: &str,
-    manifest: &str,
-    bundle: &str,
-) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_plugin_bundle_save(
+    plugin_id: &str,
+    manifest: &str,
+    bundle: &str,
+) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    database
+        .plugin_bundle_save(plugin_id, manifest, bundle)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let json = serde_json::json!({ "ok": true, "plugin_id": plugin_id });
+    Ok(JsValue::from_str(&json.to_string()))
+}
+
+/// Load a plugin bundle from local storage
+#[wasm_bindgen]
+pub fn umbra_wasm_plugin_bundle_load(plugin_id: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    match database
+        .plugin_bundle_load(plugin_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
+    {
+        Some(record) => {
+            let json = serde_json::json!({
+                "plugin_id": record.plugin_id,
+                "manifest": record.manifest,
+                "bundle": record.bundle,
+                "installed_at": record.installed_at,
+            });
+            Ok(JsValue::from_str(&json.to_string()))
+        }
+        None => {
+            let json = serde_json::json!({ "error": "not_found" });
+            Ok(JsValue::from_str(&json.to_string()))
+        }
+    }
+}
+
+/// Delete a plugin bundle from local storage
+#[wasm_bindgen]
+pub fn umbra_wasm_plugin_bundle_delete(plugin_id: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let deleted = database
+        .plugin_bundle_delete(plugin_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let json = serde_json::json!({ "deleted": deleted });
+    Ok(JsValue::from_str(&json.to_string()))
+}
+
+/// List all installed plugin bundles (manifests only)
+#[wasm_bindgen]
+pub fn umbra_wasm_plugin_bundle_list() -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let bundles = database
+        .plugin_bundle_list()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let plugins: Vec<serde_json::Value> = bundles
+        .iter()
+        .map(|b| {
+            serde_json::json!({
+                "plugin_id": b.plugin_id,
+                "manifest": b.manifest,
+                "installed_at": b.installed_at,
+            })
+        })
+        .collect();
+
+    let json = serde_json::json!({ "plugins": plugins });
+    Ok(JsValue::from_str(&json.to_string()))
+}
+
+// ============================================================================
+// CALL HISTORY
+// ============================================================================
+
+/// Store a new call record.
+///
+/// Takes JSON: { "id": "...", "conversation_id": "...", "call_type": "voice|video",
+///               "direction": "incoming|outgoing", "participants": "[\"did1\",\"did2\"]" }
+///
+/// Creates a record with started_at = now (ms), status = "active".
+/// Returns JSON: { "id": "...", "started_at": ... }
+#[wasm_bindgen]
+pub fn umbra_wasm_calls_store(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let id = data["id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+    let conversation_id = data["conversation_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
+    let call_type = data["call_type"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing call_type"))?;
+    let direction = data["direction"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing direction"))?;
+    let participants = data["participants"].as_str().unwrap_or("[]");
+
+    let started_at = crate::time::now_timestamp_millis();
+
+    database
+        .store_call_record(
+            id,
+            conversation_id,
+            call_type,
+            direction,
+            "active",
+            participants,
+            started_at,
+        )
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let result = serde_json::json!({
+        "id": id,
+        "started_at": started_at,
+    });
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&result).unwrap_or_default(),
+    ))
+}
+
+/// End a call record.
+///
+/// Takes JSON: { "id": "...", "status": "completed|missed|declined|cancelled" }
+///
+/// Calculates duration_ms from started_at to now.
+/// Returns JSON: { "id": "...", "ended_at": ..., "duration_ms": ... }
+#[wasm_bindgen]
+pub fn umbra_wasm_calls_end(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let id = data["id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+    let status = data["status"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing status"))?;
+
+    // Get the call record to compute duration
+    let calls = database
+        .get_all_call_history(1000, 0)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let call = calls
+        .iter()
+        .find(|c| c.id == id)
+        .ok_or_else(|| JsValue::from_str("Call record not found"))?;
+
+    let ended_at = crate::time::now_timestamp_millis();
+    let duration_ms = ended_at - call.started_at;
+
+    database
+        .end_call_record(id, status, ended_at, duration_ms)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let result = serde_json::json!({
+        "id": id,
+        "ended_at": ended_at,
+        "duration_ms": duration_ms,
+    });
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&result).unwrap_or_default(),
+    ))
+}
+
+/// Get call history for a specific conversation.
+///
+/// Takes JSON: { "conversation_id": "...", "limit": 50, "offset": 0 }
+/// Returns JSON array of call records.
+#[wasm_bindgen]
+pub fn umbra_wasm_calls_get_history(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
+
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    database
-        .plugin_bundle_save(plugin_id, manifest, bundle)
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let conversation_id = data["conversation_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
+    let limit = data["limit"].as_i64().unwrap_or(50) as usize;
+    let offset = data["offset"].as_i64().unwrap_or(0) as usize;
+
+    let records = database
+        .get_call_history(conversation_id, limit, offset)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let json = serde_json::json!({ "ok": true, "plugin_id": plugin_id });
-    Ok(JsValue::from_str(&json.to_string()))
+    let calls_json: Vec<serde_json::Value> = records
+        .iter()
+        .map(|c| {
+            serde_json::json!({
+                "id": c.id,
+                "conversation_id": c.conversation_id,
+                "call_type": c.call_type,
+                "direction": c.direction,
+                "status": c.status,
+                "participants": c.participants,
+                "started_at": c.started_at,
+                "ended_at": c.ended_at,
+                "duration_ms": c.duration_ms,
+                "created_at": c.created_at,
+            })
+        })
+        .collect();
+
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&calls_json).unwrap_or_default(),
+    ))
 }
 
-/// Load a plugin bundle from local storage
+/// Get all call history across all conversations.
+///
+/// Takes JSON: { "limit": 50, "offset": 0 }
+/// Returns JSON array of all call records.
 #[wasm_bindgen]
-pub fn umbra_wasm_plugin_bundle_load(plugin_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_calls_get_all_history(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
+
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    match database
-        .plugin_bundle_load(plugin_id)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?
-    {
-        Some(record) => {
-            let json = serde_json::json!({
-                "plugin_id": record.plugin_id,
-                "manifest": record.manifest,
-                "bundle": record.bundle,
-                "installed_at": record.installed_at,
-            });
-            Ok(JsValue::from_str(&json.to_string()))
-        }
-        None => {
-            let json = serde_json::json!({ "error": "not_found" });
-            Ok(JsValue::from_str(&json.to_string()))
-        }
-    }
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let limit = data["limit"].as_i64().unwrap_or(50) as usize;
+    let offset = data["offset"].as_i64().unwrap_or(0) as usize;
+
+    let records = database
+        .get_all_call_history(limit, offset)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let calls_json: Vec<serde_json::Value> = records
+        .iter()
+        .map(|c| {
+            serde_json::json!({
+                "id": c.id,
+                "conversation_id": c.conversation_id,
+                "call_type": c.call_type,
+                "direction": c.direction,
+                "status": c.status,
+                "participants": c.participants,
+                "started_at": c.started_at,
+                "ended_at": c.ended_at,
+                "duration_ms": c.duration_ms,
+                "created_at": c.created_at,
+            })
+        })
+        .collect();
+
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&calls_json).unwrap_or_default(),
+    ))
 }
 
-/// Delete a plugin bundle from local storage
+// ============================================================================
+// NOTIFICATIONS
+// ============================================================================
+
+/// Create a new notification.
+///
+/// Takes JSON: { "id": "...", "type": "friend_request_received|call_missed|...",
+///               "title": "...", "description": "...", "related_did": "...",
+///               "related_id": "...", "avatar": "..." }
+///
+/// Returns JSON: { "id": "...", "created_at": ... }
 #[wasm_bindgen]
-pub fn umbra_wasm_plugin_bundle_delete(plugin_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_notifications_create(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
+
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let deleted = database
-        .plugin_bundle_delete(plugin_id)
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let id = data["id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+    let notification_type = data["type"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing type"))?;
+    let title = data["title"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing title"))?;
+    let description = data["description"].as_str();
+    let related_did = data["related_did"].as_str();
+    let related_id = data["related_id"].as_str();
+    let avatar = data["avatar"].as_str();
+
+    let record = database
+        .create_notification(id, notification_type, title, description, related_did, related_id, avatar)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let json = serde_json::json!({ "deleted": deleted });
-    Ok(JsValue::from_str(&json.to_string()))
+    let result = serde_json::json!({
+        "id": record.id,
+        "created_at": record.created_at,
+    });
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&result).unwrap_or_default(),
+    ))
 }
 
-/// List all installed plugin bundles (manifests only)
+/// Get notifications with optional filters.
+///
+/// Takes JSON: { "type": "...", "read": true|false, "limit": 100, "offset": 0 }
+/// All fields optional. Returns JSON array of notification records.
 #[wasm_bindgen]
-pub fn umbra_wasm_plugin_bundle_list() -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_notifications_get(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
+
     let database = state
         .database
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let bundles = database
-        .plugin_bundle_list()
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let notification_type = data["type"].as_str();
+    let read = data["read"].as_bool();
+    let limit = data["limit"].as_i64().unwrap_or(100) as usize;
+    let offset = data["offset"].as_i64().unwrap_or(0) as usize;
+
+    let records = database
+        .get_notifications(notification_type, read, limit, offset)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let plugins: Vec<serde_json::Value> = bundles
+    let notifications_json: Vec<serde_json::Value> = records
         .iter()
-        .map(|b| {
+        .map(|n| {
             serde_json::json!({
-                "plugin_id": b.plugin_id,
-                "manifest": b.manifest,
-                "installed_at": b.installed_at,
+                "id": n.id,
+                "type": n.notification_type,
+                "title": n.title,
+                "description": n.description,
+                "related_did": n.related_did,
+                "related_id": n.related_id,
+                "avatar": n.avatar,
+                "read": n.read,
+                "dismissed": n.dismissed,
+                "action_taken": n.action_taken,
+                "created_at": n.created_at,
+                "updated_at": n.updated_at,
             })
         })
         .collect();
 
-    let json = serde_json::json!({ "plugins": plugins });
-    Ok(JsValue::from_str(&json.to_string()))
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&notifications_json).unwrap_or_default(),
+    ))
 }
 
-// ============================================================================
-// CALL HISTORY
-// ============================================================================
-
-/// Store a new call record.
-///
-/// Takes JSON: { "id": "...", "conversation_id": "...", "call_type": "voice|video",
-///               "direction": "incoming|outgoing", "participants": "[\"did1\",\"did2\"]" }
+/// Mark a single notification as read.
 ///
-/// Creates a record with started_at = now (ms), status = "active".
-/// Returns JSON: { "id": "...", "started_at": ... }
+/// Takes JSON: { "id": "..." }
+/// Returns JSON: { "success": true }
 #[wasm_bindgen]
-pub fn umbra_wasm_calls_store(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_notifications_mark_read(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -6113,48 +7605,52 @@ pub fn umbra_wasm_calls_store(json: &str) -> Result<JsValue, JsValue> {
     let id = data["id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing id"))?;
-    let conversation_id = data["conversation_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
-    let call_type = data["call_type"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing call_type"))?;
-    let direction = data["direction"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing direction"))?;
-    let participants = data["participants"].as_str().unwrap_or("[]");
-
-    let started_at = crate::time::now_timestamp_millis();
 
     database
-        .store_call_record(
-            id,
-            conversation_id,
-            call_type,
-            direction,
-            "active",
-            participants,
-            started_at,
-        )
+        .mark_notification_read(id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(JsValue::from_str(r#"{"success":true}"#))
+}
+
+/// Mark all notifications as read, optionally filtered by type.
+///
+/// Takes JSON: { "type": "..." } (type is optional)
+/// Returns JSON: { "success": true, "count": N }
+#[wasm_bindgen]
+pub fn umbra_wasm_notifications_mark_all_read(json: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let notification_type = data["type"].as_str();
+
+    let count = database
+        .mark_all_notifications_read(notification_type)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     let result = serde_json::json!({
-        "id": id,
-        "started_at": started_at,
+        "success": true,
+        "count": count,
     });
     Ok(JsValue::from_str(
         &serde_json::to_string(&result).unwrap_or_default(),
     ))
 }
 
-/// End a call record.
-///
-/// Takes JSON: { "id": "...", "status": "completed|missed|declined|cancelled" }
+/// Dismiss (soft-delete) a notification.
 ///
-/// Calculates duration_ms from started_at to now.
-/// Returns JSON: { "id": "...", "ended_at": ..., "duration_ms": ... }
+/// Takes JSON: { "id": "..." }
+/// Returns JSON: { "success": true }
 #[wasm_bindgen]
-pub fn umbra_wasm_calls_end(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_notifications_dismiss(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -6169,42 +7665,20 @@ pub fn umbra_wasm_calls_end(json: &str) -> Result<JsValue, JsValue> {
     let id = data["id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing id"))?;
-    let status = data["status"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing status"))?;
-
-    // Get the call record to compute duration
-    let calls = database
-        .get_all_call_history(1000, 0)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-    let call = calls
-        .iter()
-        .find(|c| c.id == id)
-        .ok_or_else(|| JsValue::from_str("Call record not found"))?;
-
-    let ended_at = crate::time::now_timestamp_millis();
-    let duration_ms = ended_at - call.started_at;
 
     database
-        .end_call_record(id, status, ended_at, duration_ms)
+        .dismiss_notification(id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let result = serde_json::json!({
-        "id": id,
-        "ended_at": ended_at,
-        "duration_ms": duration_ms,
-    });
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&result).unwrap_or_default(),
-    ))
+    Ok(JsValue::from_str(r#"{"success":true}"#))
 }
 
-/// Get call history for a specific conversation.
+/// Get unread notification counts by category.
 ///
-/// Takes JSON: { "conversation_id": "...", "limit": 50, "offset": 0 }
-/// Returns JSON array of call records.
+/// Takes JSON: {} (empty object)
+/// Returns JSON: { "all": N, "social": N, "calls": N, "mentions": N, "system": N }
 #[wasm_bindgen]
-pub fn umbra_wasm_calls_get_history(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_notifications_unread_counts(json: &str) -> Result<JsValue, JsValue> {
     let state = get_state()?;
     let state = state.read();
 
@@ -6213,445 +7687,582 @@ pub fn umbra_wasm_calls_get_history(json: &str) -> Result<JsValue, JsValue> {
         .as_ref()
         .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
 
-    let data: serde_json::Value =
+    // Parse JSON even if empty, to follow the convention
+    let _data: serde_json::Value =
         serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let conversation_id = data["conversation_id"]
+    let counts = database
+        .get_notification_unread_counts()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&counts).unwrap_or_default(),
+    ))
+}
+
+// ============================================================================
+// COMMUNITIES
+// ============================================================================
+
+/// Helper: create a CommunityService from the current state.
+fn community_service() -> Result<CommunityService, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+    if let Some(community) = &state.community {
+        return Ok(community.clone());
+    }
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    Ok(CommunityService::new(database.clone()))
+}
+
+/// Create a new community.
+///
+/// Takes JSON: { "name": "...", "description"?: "...", "owner_did": "...", "owner_nickname"?: "..." }
+/// Returns JSON: { "community_id", "space_id", "welcome_channel_id", "general_channel_id", "role_ids": { ... } }
+#[wasm_bindgen]
+pub fn umbra_wasm_community_create(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let name = data["name"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing conversation_id"))?;
-    let limit = data["limit"].as_i64().unwrap_or(50) as usize;
-    let offset = data["offset"].as_i64().unwrap_or(0) as usize;
+        .ok_or_else(|| JsValue::from_str("Missing name"))?;
+    let description = data["description"].as_str();
+    let owner_did = data["owner_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing owner_did"))?;
+    let owner_nickname = data["owner_nickname"].as_str();
+    let origin_community_id = data["origin_community_id"].as_str();
 
-    let records = database
-        .get_call_history(conversation_id, limit, offset)
+    let svc = community_service()?;
+    let result = svc
+        .create_community(name, description, owner_did, owner_nickname, origin_community_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let calls_json: Vec<serde_json::Value> = records
+    let json_result = serde_json::json!({
+        "community_id": result.community_id,
+        "space_id": result.space_id,
+        "welcome_channel_id": result.welcome_channel_id,
+        "general_channel_id": result.general_channel_id,
+        "role_ids": {
+            "owner": result.role_ids.owner,
+            "admin": result.role_ids.admin,
+            "moderator": result.role_ids.moderator,
+            "member": result.role_ids.member,
+        },
+    });
+
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "communityCreated",
+            "community_id": result.community_id,
+            "name": name,
+        }),
+    );
+
+    Ok(JsValue::from_str(&json_result.to_string()))
+}
+
+/// Find a community by its origin (remote) community ID.
+///
+/// Returns JSON string of the local community ID, or null if not found.
+#[wasm_bindgen]
+pub fn umbra_wasm_community_find_by_origin(origin_id: &str) -> Result<JsValue, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+    let db = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    let result = db
+        .find_community_by_origin(origin_id)
+        .map_err(|e: crate::error::Error| JsValue::from_str(&e.to_string()))?;
+    match result {
+        Some(id) => Ok(JsValue::from_str(&serde_json::json!(id).to_string())),
+        None => Ok(JsValue::from_str("null")),
+    }
+}
+
+/// Get a community by ID.
+///
+/// Returns JSON: Community object
+#[wasm_bindgen]
+pub fn umbra_wasm_community_get(community_id: &str) -> Result<JsValue, JsValue> {
+    let svc = community_service()?;
+    let c = svc
+        .get_community(community_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let json = serde_json::json!({
+        "id": c.id,
+        "name": c.name,
+        "description": c.description,
+        "icon_url": c.icon_url,
+        "banner_url": c.banner_url,
+        "splash_url": c.splash_url,
+        "accent_color": c.accent_color,
+        "custom_css": c.custom_css,
+        "owner_did": c.owner_did,
+        "vanity_url": c.vanity_url,
+        "origin_community_id": c.origin_community_id,
+        "created_at": c.created_at,
+        "updated_at": c.updated_at,
+    });
+    Ok(JsValue::from_str(&json.to_string()))
+}
+
+/// Get all communities the user is a member of.
+///
+/// Returns JSON: Community[]
+#[wasm_bindgen]
+pub fn umbra_wasm_community_get_mine(member_did: &str) -> Result<JsValue, JsValue> {
+    let svc = community_service()?;
+    let communities = svc
+        .get_my_communities(member_did)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let arr: Vec<serde_json::Value> = communities
         .iter()
         .map(|c| {
             serde_json::json!({
                 "id": c.id,
-                "conversation_id": c.conversation_id,
-                "call_type": c.call_type,
-                "direction": c.direction,
-                "status": c.status,
-                "participants": c.participants,
-                "started_at": c.started_at,
-                "ended_at": c.ended_at,
-                "duration_ms": c.duration_ms,
+                "name": c.name,
+                "description": c.description,
+                "icon_url": c.icon_url,
+                "banner_url": c.banner_url,
+                "splash_url": c.splash_url,
+                "accent_color": c.accent_color,
+                "custom_css": c.custom_css,
+                "owner_did": c.owner_did,
+                "vanity_url": c.vanity_url,
+                "origin_community_id": c.origin_community_id,
                 "created_at": c.created_at,
+                "updated_at": c.updated_at,
             })
         })
         .collect();
 
     Ok(JsValue::from_str(
-        &serde_json::to_string(&calls_json).unwrap_or_default(),
+        &serde_json::to_string(&arr).unwrap_or_default(),
+    ))
+}
+
+/// Update a community's name and/or description.
+///
+/// Takes JSON: { "id": "...", "name": "...", "description": "...", "actor_did": "..." }
+#[wasm_bindgen]
+pub fn umbra_wasm_community_update(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let id = data["id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+    let name = data["name"].as_str();
+    let description = data["description"].as_str();
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+
+    let svc = community_service()?;
+    svc.update_community(id, name, description, actor_did)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "communityUpdated",
+            "community_id": id,
+        }),
+    );
+
+    Ok(JsValue::from_str(
+        &serde_json::json!({"success": true}).to_string(),
+    ))
+}
+
+/// Delete a community (owner only).
+///
+/// Takes JSON: { "id": "...", "actor_did": "..." }
+#[wasm_bindgen]
+pub fn umbra_wasm_community_delete(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let id = data["id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+
+    let svc = community_service()?;
+    svc.delete_community(id, actor_did)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "communityDeleted",
+            "community_id": id,
+        }),
+    );
+
+    Ok(JsValue::from_str(
+        &serde_json::json!({"success": true}).to_string(),
     ))
 }
 
-/// Get all call history across all conversations.
+/// Transfer community ownership.
 ///
-/// Takes JSON: { "limit": 50, "offset": 0 }
-/// Returns JSON array of all call records.
+/// Takes JSON: { "community_id": "...", "current_owner_did": "...", "new_owner_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_calls_get_all_history(json: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+pub fn umbra_wasm_community_transfer_ownership(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let limit = data["limit"].as_i64().unwrap_or(50) as usize;
-    let offset = data["offset"].as_i64().unwrap_or(0) as usize;
+    let community_id = data["community_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let current_owner_did = data["current_owner_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing current_owner_did"))?;
+    let new_owner_did = data["new_owner_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing new_owner_did"))?;
 
-    let records = database
-        .get_all_call_history(limit, offset)
+    let svc = community_service()?;
+    svc.transfer_ownership(community_id, current_owner_did, new_owner_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let calls_json: Vec<serde_json::Value> = records
-        .iter()
-        .map(|c| {
-            serde_json::json!({
-                "id": c.id,
-                "conversation_id": c.conversation_id,
-                "call_type": c.call_type,
-                "direction": c.direction,
-                "status": c.status,
-                "participants": c.participants,
-                "started_at": c.started_at,
-                "ended_at": c.ended_at,
-                "duration_ms": c.duration_ms,
-                "created_at": c.created_at,
-            })
-        })
-        .collect();
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "ownershipTransferred",
+            "community_id": community_id,
+            "new_owner_did": new_owner_did,
+        }),
+    );
 
     Ok(JsValue::from_str(
-        &serde_json::to_string(&calls_json).unwrap_or_default(),
+        &serde_json::json!({"success": true}).to_string(),
     ))
 }
 
 // ============================================================================
-// NOTIFICATIONS
+// COMMUNITY — SPACES
 // ============================================================================
 
-/// Create a new notification.
-///
-/// Takes JSON: { "id": "...", "type": "friend_request_received|call_missed|...",
-///               "title": "...", "description": "...", "related_did": "...",
-///               "related_id": "...", "avatar": "..." }
+/// Create a new space in a community.
 ///
-/// Returns JSON: { "id": "...", "created_at": ... }
+/// Takes JSON: { "community_id": "...", "name": "...", "position": 0, "actor_did": "..." }
+/// Returns JSON: CommunitySpace object
 #[wasm_bindgen]
-pub fn umbra_wasm_notifications_create(json: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+pub fn umbra_wasm_community_space_create(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let id = data["id"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing id"))?;
-    let notification_type = data["type"]
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let name = data["name"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing type"))?;
-    let title = data["title"]
+        .ok_or_else(|| JsValue::from_str("Missing name"))?;
+    let position = data["position"].as_i64().unwrap_or(0) as i32;
+    let actor_did = data["actor_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing title"))?;
-    let description = data["description"].as_str();
-    let related_did = data["related_did"].as_str();
-    let related_id = data["related_id"].as_str();
-    let avatar = data["avatar"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
-    let record = database
-        .create_notification(id, notification_type, title, description, related_did, related_id, avatar)
+    let svc = community_service()?;
+    let space = svc
+        .create_space(community_id, name, position, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let result = serde_json::json!({
-        "id": record.id,
-        "created_at": record.created_at,
+    let json_result = serde_json::json!({
+        "id": space.id,
+        "community_id": space.community_id,
+        "name": space.name,
+        "position": space.position,
+        "created_at": space.created_at,
+        "updated_at": space.updated_at,
     });
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&result).unwrap_or_default(),
-    ))
+
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "spaceCreated",
+            "community_id": community_id,
+            "space_id": space.id,
+        }),
+    );
+
+    Ok(JsValue::from_str(&json_result.to_string()))
 }
 
-/// Get notifications with optional filters.
+/// Get all spaces in a community.
 ///
-/// Takes JSON: { "type": "...", "read": true|false, "limit": 100, "offset": 0 }
-/// All fields optional. Returns JSON array of notification records.
+/// Returns JSON: CommunitySpace[]
 #[wasm_bindgen]
-pub fn umbra_wasm_notifications_get(json: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let notification_type = data["type"].as_str();
-    let read = data["read"].as_bool();
-    let limit = data["limit"].as_i64().unwrap_or(100) as usize;
-    let offset = data["offset"].as_i64().unwrap_or(0) as usize;
-
-    let records = database
-        .get_notifications(notification_type, read, limit, offset)
+pub fn umbra_wasm_community_space_list(community_id: &str) -> Result<JsValue, JsValue> {
+    let svc = community_service()?;
+    let spaces = svc
+        .get_spaces(community_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let notifications_json: Vec<serde_json::Value> = records
+    let arr: Vec<serde_json::Value> = spaces
         .iter()
-        .map(|n| {
+        .map(|s| {
             serde_json::json!({
-                "id": n.id,
-                "type": n.notification_type,
-                "title": n.title,
-                "description": n.description,
-                "related_did": n.related_did,
-                "related_id": n.related_id,
-                "avatar": n.avatar,
-                "read": n.read,
-                "dismissed": n.dismissed,
-                "action_taken": n.action_taken,
-                "created_at": n.created_at,
-                "updated_at": n.updated_at,
+                "id": s.id,
+                "community_id": s.community_id,
+                "name": s.name,
+                "position": s.position,
+                "created_at": s.created_at,
+                "updated_at": s.updated_at,
             })
         })
         .collect();
 
     Ok(JsValue::from_str(
-        &serde_json::to_string(&notifications_json).unwrap_or_default(),
+        &serde_json::to_string(&arr).unwrap_or_default(),
     ))
 }
 
-/// Mark a single notification as read.
+/// Update a space's name.
 ///
-/// Takes JSON: { "id": "..." }
-/// Returns JSON: { "success": true }
+/// Takes JSON: { "space_id": "...", "name": "...", "actor_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_notifications_mark_read(json: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+pub fn umbra_wasm_community_space_update(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let id = data["id"]
+    let space_id = data["space_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing id"))?;
-
-    database
-        .mark_notification_read(id)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    Ok(JsValue::from_str(r#"{"success":true}"#))
-}
-
-/// Mark all notifications as read, optionally filtered by type.
-///
-/// Takes JSON: { "type": "..." } (type is optional)
-/// Returns JSON: { "success": true, "count": N }
-#[wasm_bindgen]
-pub fn umbra_wasm_notifications_mark_all_read(json: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let notification_type = data["type"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing space_id"))?;
+    let name = data["name"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing name"))?;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
-    let count = database
-        .mark_all_notifications_read(notification_type)
+    let svc = community_service()?;
+    svc.update_space(space_id, name, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let result = serde_json::json!({
-        "success": true,
-        "count": count,
-    });
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "spaceUpdated",
+            "space_id": space_id,
+        }),
+    );
+
     Ok(JsValue::from_str(
-        &serde_json::to_string(&result).unwrap_or_default(),
+        &serde_json::json!({"success": true}).to_string(),
     ))
 }
 
-/// Dismiss (soft-delete) a notification.
+/// Reorder spaces in a community.
 ///
-/// Takes JSON: { "id": "..." }
-/// Returns JSON: { "success": true }
+/// Takes JSON: { "community_id": "...", "space_ids": ["id1", "id2", ...] }
 #[wasm_bindgen]
-pub fn umbra_wasm_notifications_dismiss(json: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-
-    let data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+pub fn umbra_wasm_community_space_reorder(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let id = data["id"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let space_ids: Vec<String> = data["space_ids"]
+        .as_array()
+        .ok_or_else(|| JsValue::from_str("Missing space_ids array"))?
+        .iter()
+        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+        .collect();
 
-    database
-        .dismiss_notification(id)
+    let svc = community_service()?;
+    svc.reorder_spaces(community_id, &space_ids, "system")
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    Ok(JsValue::from_str(r#"{"success":true}"#))
+    Ok(JsValue::from_str(
+        &serde_json::json!({"success": true}).to_string(),
+    ))
 }
 
-/// Get unread notification counts by category.
+/// Delete a space and all its channels.
 ///
-/// Takes JSON: {} (empty object)
-/// Returns JSON: { "all": N, "social": N, "calls": N, "mentions": N, "system": N }
+/// Takes JSON: { "space_id": "...", "actor_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_notifications_unread_counts(json: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+pub fn umbra_wasm_community_space_delete(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    // Parse JSON even if empty, to follow the convention
-    let _data: serde_json::Value =
-        serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let space_id = data["space_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing space_id"))?;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
-    let counts = database
-        .get_notification_unread_counts()
+    let svc = community_service()?;
+    svc.delete_space(space_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "spaceDeleted",
+            "space_id": space_id,
+        }),
+    );
+
     Ok(JsValue::from_str(
-        &serde_json::to_string(&counts).unwrap_or_default(),
+        &serde_json::json!({"success": true}).to_string(),
     ))
 }
 
 // ============================================================================
-// COMMUNITIES
+// COMMUNITY — CHANNELS
 // ============================================================================
 
-/// Helper: create a CommunityService from the current state.
-fn community_service() -> Result<CommunityService, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-    Ok(CommunityService::new(database.clone()))
-}
-
-/// Create a new community.
+/// Create a new channel in a space.
 ///
-/// Takes JSON: { "name": "...", "description"?: "...", "owner_did": "...", "owner_nickname"?: "..." }
-/// Returns JSON: { "community_id", "space_id", "welcome_channel_id", "general_channel_id", "role_ids": { ... } }
+/// Takes JSON: { "community_id", "space_id", "name", "channel_type", "topic"?, "position", "actor_did" }
+/// Returns JSON: CommunityChannel object
 #[wasm_bindgen]
-pub fn umbra_wasm_community_create(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_channel_create(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
+    let community_id = data["community_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let space_id = data["space_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing space_id"))?;
     let name = data["name"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing name"))?;
-    let description = data["description"].as_str();
-    let owner_did = data["owner_did"]
+    let channel_type = data["channel_type"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing owner_did"))?;
-    let owner_nickname = data["owner_nickname"].as_str();
-    let origin_community_id = data["origin_community_id"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing channel_type"))?;
+    let topic = data["topic"].as_str();
+    let position = data["position"].as_i64().unwrap_or(0) as i32;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+    let category_id = data["category_id"].as_str();
 
     let svc = community_service()?;
-    let result = svc
-        .create_community(name, description, owner_did, owner_nickname, origin_community_id)
+    let ch = svc
+        .create_channel(
+            community_id,
+            space_id,
+            name,
+            channel_type,
+            topic,
+            position,
+            actor_did,
+            category_id,
+        )
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     let json_result = serde_json::json!({
-        "community_id": result.community_id,
-        "space_id": result.space_id,
-        "welcome_channel_id": result.welcome_channel_id,
-        "general_channel_id": result.general_channel_id,
-        "role_ids": {
-            "owner": result.role_ids.owner,
-            "admin": result.role_ids.admin,
-            "moderator": result.role_ids.moderator,
-            "member": result.role_ids.member,
-        },
+        "id": ch.id,
+        "community_id": ch.community_id,
+        "space_id": ch.space_id,
+        "category_id": ch.category_id,
+        "name": ch.name,
+        "channel_type": ch.channel_type,
+        "topic": ch.topic,
+        "position": ch.position,
+        "slow_mode_seconds": ch.slow_mode_seconds,
+        "e2ee_enabled": ch.e2ee_enabled,
+        "pin_limit": ch.pin_limit,
+        "created_at": ch.created_at,
+        "updated_at": ch.updated_at,
     });
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "communityCreated",
-            "community_id": result.community_id,
-            "name": name,
+            "type": "channelCreated",
+            "community_id": community_id,
+            "channel_id": ch.id,
         }),
     );
 
     Ok(JsValue::from_str(&json_result.to_string()))
 }
 
-/// Find a community by its origin (remote) community ID.
-///
-/// Returns JSON string of the local community ID, or null if not found.
-#[wasm_bindgen]
-pub fn umbra_wasm_community_find_by_origin(origin_id: &str) -> Result<JsValue, JsValue> {
-    let state = get_state()?;
-    let state = state.read();
-    let db = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
-    let result = db
-        .find_community_by_origin(origin_id)
-        .map_err(|e: crate::error::Error| JsValue::from_str(&e.to_string()))?;
-    match result {
-        Some(id) => Ok(JsValue::from_str(&serde_json::json!(id).to_string())),
-        None => Ok(JsValue::from_str("null")),
-    }
-}
-
-/// Get a community by ID.
+/// Get all channels in a space.
 ///
-/// Returns JSON: Community object
+/// Returns JSON: CommunityChannel[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_get(community_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_channel_list(space_id: &str) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    let c = svc
-        .get_community(community_id)
+    let channels = svc
+        .get_channels(space_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let json = serde_json::json!({
-        "id": c.id,
-        "name": c.name,
-        "description": c.description,
-        "icon_url": c.icon_url,
-        "banner_url": c.banner_url,
-        "splash_url": c.splash_url,
-        "accent_color": c.accent_color,
-        "custom_css": c.custom_css,
-        "owner_did": c.owner_did,
-        "vanity_url": c.vanity_url,
-        "origin_community_id": c.origin_community_id,
-        "created_at": c.created_at,
-        "updated_at": c.updated_at,
-    });
-    Ok(JsValue::from_str(&json.to_string()))
+    let arr: Vec<serde_json::Value> = channels
+        .iter()
+        .map(|ch| {
+            serde_json::json!({
+                "id": ch.id,
+                "community_id": ch.community_id,
+                "space_id": ch.space_id,
+                "category_id": ch.category_id,
+                "name": ch.name,
+                "channel_type": ch.channel_type,
+                "topic": ch.topic,
+                "position": ch.position,
+                "slow_mode_seconds": ch.slow_mode_seconds,
+                "e2ee_enabled": ch.e2ee_enabled,
+                "pin_limit": ch.pin_limit,
+                "created_at": ch.created_at,
+                "updated_at": ch.updated_at,
+            })
+        })
+        .collect();
+
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&arr).unwrap_or_default(),
+    ))
 }
 
-/// Get all communities the user is a member of.
+/// Get all channels in a community (across all spaces).
 ///
-/// Returns JSON: Community[]
+/// Returns JSON: CommunityChannel[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_get_mine(member_did: &str) -> Result<JsValue, JsValue> {
-    let svc = community_service()?;
-    let communities = svc
-        .get_my_communities(member_did)
+pub fn umbra_wasm_community_channel_list_all(community_id: &str) -> Result<JsValue, JsValue> {
+    let svc = community_service()?;
+    let channels = svc
+        .get_all_channels(community_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let arr: Vec<serde_json::Value> = communities
+    let arr: Vec<serde_json::Value> = channels
         .iter()
-        .map(|c| {
+        .map(|ch| {
             serde_json::json!({
-                "id": c.id,
-                "name": c.name,
-                "description": c.description,
-                "icon_url": c.icon_url,
-                "banner_url": c.banner_url,
-                "splash_url": c.splash_url,
-                "accent_color": c.accent_color,
-                "custom_css": c.custom_css,
-                "owner_did": c.owner_did,
-                "vanity_url": c.vanity_url,
-                "origin_community_id": c.origin_community_id,
-                "created_at": c.created_at,
-                "updated_at": c.updated_at,
+                "id": ch.id,
+                "community_id": ch.community_id,
+                "space_id": ch.space_id,
+                "category_id": ch.category_id,
+                "name": ch.name,
+                "channel_type": ch.channel_type,
+                "topic": ch.topic,
+                "position": ch.position,
+                "slow_mode_seconds": ch.slow_mode_seconds,
+                "e2ee_enabled": ch.e2ee_enabled,
+                "pin_limit": ch.pin_limit,
+                "created_at": ch.created_at,
+                "updated_at": ch.updated_at,
             })
         })
         .collect();
@@ -6661,32 +8272,60 @@ pub fn umbra_wasm_community_get_mine(member_did: &str) -> Result<JsValue, JsValu
     ))
 }
 
-/// Update a community's name and/or description.
+/// Get a single channel by ID.
 ///
-/// Takes JSON: { "id": "...", "name": "...", "description": "...", "actor_did": "..." }
+/// Returns JSON: CommunityChannel object
 #[wasm_bindgen]
-pub fn umbra_wasm_community_update(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_channel_get(channel_id: &str) -> Result<JsValue, JsValue> {
+    let svc = community_service()?;
+    let ch = svc
+        .get_channel(channel_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let json = serde_json::json!({
+        "id": ch.id,
+        "community_id": ch.community_id,
+        "space_id": ch.space_id,
+        "category_id": ch.category_id,
+        "name": ch.name,
+        "channel_type": ch.channel_type,
+        "topic": ch.topic,
+        "position": ch.position,
+        "slow_mode_seconds": ch.slow_mode_seconds,
+        "e2ee_enabled": ch.e2ee_enabled,
+        "pin_limit": ch.pin_limit,
+        "created_at": ch.created_at,
+        "updated_at": ch.updated_at,
+    });
+    Ok(JsValue::from_str(&json.to_string()))
+}
+
+/// Update a channel's name and/or topic.
+///
+/// Takes JSON: { "channel_id": "...", "name"?: "...", "topic"?: "...", "actor_did": "..." }
+#[wasm_bindgen]
+pub fn umbra_wasm_community_channel_update(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let id = data["id"]
+    let channel_id = data["channel_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
     let name = data["name"].as_str();
-    let description = data["description"].as_str();
+    let topic = data["topic"].as_str();
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.update_community(id, name, description, actor_did)
+    svc.update_channel(channel_id, name, topic, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "communityUpdated",
-            "community_id": id,
+            "type": "channelUpdated",
+            "channel_id": channel_id,
         }),
     );
 
@@ -6695,180 +8334,153 @@ pub fn umbra_wasm_community_update(json: &str) -> Result<JsValue, JsValue> {
     ))
 }
 
-/// Delete a community (owner only).
+/// Set slow mode for a channel.
 ///
-/// Takes JSON: { "id": "...", "actor_did": "..." }
+/// Takes JSON: { "channel_id": "...", "seconds": 0, "actor_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_delete(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_channel_set_slow_mode(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let id = data["id"]
+    let channel_id = data["channel_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
+    let seconds = data["seconds"]
+        .as_i64()
+        .ok_or_else(|| JsValue::from_str("Missing seconds"))? as i32;
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.delete_community(id, actor_did)
+    svc.set_slow_mode(channel_id, seconds, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "communityDeleted",
-            "community_id": id,
-        }),
-    );
-
     Ok(JsValue::from_str(
         &serde_json::json!({"success": true}).to_string(),
     ))
 }
 
-/// Transfer community ownership.
+/// Toggle E2EE for a channel.
 ///
-/// Takes JSON: { "community_id": "...", "current_owner_did": "...", "new_owner_did": "..." }
+/// Takes JSON: { "channel_id": "...", "enabled": true, "actor_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_transfer_ownership(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_channel_set_e2ee(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let community_id = data["community_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let current_owner_did = data["current_owner_did"]
+    let channel_id = data["channel_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing current_owner_did"))?;
-    let new_owner_did = data["new_owner_did"]
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
+    let enabled = data["enabled"]
+        .as_bool()
+        .ok_or_else(|| JsValue::from_str("Missing enabled"))?;
+    let actor_did = data["actor_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing new_owner_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.transfer_ownership(community_id, current_owner_did, new_owner_did)
+    svc.set_channel_e2ee(channel_id, enabled, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "ownershipTransferred",
-            "community_id": community_id,
-            "new_owner_did": new_owner_did,
-        }),
-    );
-
+    let relay_messages = community_rekey_channel(channel_id);
     Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
+        &serde_json::json!({"success": true, "relay_messages": relay_messages}).to_string(),
     ))
 }
 
-// ============================================================================
-// COMMUNITY — SPACES
-// ============================================================================
-
-/// Create a new space in a community.
+/// Delete a channel.
 ///
-/// Takes JSON: { "community_id": "...", "name": "...", "position": 0, "actor_did": "..." }
-/// Returns JSON: CommunitySpace object
+/// Takes JSON: { "channel_id": "...", "actor_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_space_create(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_channel_delete(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let community_id = data["community_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let name = data["name"]
+    let channel_id = data["channel_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing name"))?;
-    let position = data["position"].as_i64().unwrap_or(0) as i32;
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    let space = svc
-        .create_space(community_id, name, position, actor_did)
+    svc.delete_channel(channel_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let json_result = serde_json::json!({
-        "id": space.id,
-        "community_id": space.community_id,
-        "name": space.name,
-        "position": space.position,
-        "created_at": space.created_at,
-        "updated_at": space.updated_at,
-    });
-
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "spaceCreated",
-            "community_id": community_id,
-            "space_id": space.id,
+            "type": "channelDeleted",
+            "channel_id": channel_id,
         }),
     );
 
-    Ok(JsValue::from_str(&json_result.to_string()))
+    Ok(JsValue::from_str(
+        &serde_json::json!({"success": true}).to_string(),
+    ))
 }
 
-/// Get all spaces in a community.
+/// Reorder channels within a space.
 ///
-/// Returns JSON: CommunitySpace[]
+/// Takes JSON: { "space_id": "...", "channel_ids": ["id1", "id2", ...] }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_space_list(community_id: &str) -> Result<JsValue, JsValue> {
-    let svc = community_service()?;
-    let spaces = svc
-        .get_spaces(community_id)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+pub fn umbra_wasm_community_channel_reorder(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let arr: Vec<serde_json::Value> = spaces
+    let space_id = data["space_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing space_id"))?;
+    let channel_ids: Vec<String> = data["channel_ids"]
+        .as_array()
+        .ok_or_else(|| JsValue::from_str("Missing channel_ids array"))?
         .iter()
-        .map(|s| {
-            serde_json::json!({
-                "id": s.id,
-                "community_id": s.community_id,
-                "name": s.name,
-                "position": s.position,
-                "created_at": s.created_at,
-                "updated_at": s.updated_at,
-            })
-        })
+        .filter_map(|v| v.as_str().map(|s| s.to_string()))
         .collect();
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+
+    let svc = community_service()?;
+    svc.reorder_channels(space_id, &channel_ids, actor_did)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     Ok(JsValue::from_str(
-        &serde_json::to_string(&arr).unwrap_or_default(),
+        &serde_json::json!({"success": true}).to_string(),
     ))
 }
 
-/// Update a space's name.
+// ============================================================================
+// COMMUNITY — MEMBERS
+// ============================================================================
+
+/// Join a community.
 ///
-/// Takes JSON: { "space_id": "...", "name": "...", "actor_did": "..." }
+/// Takes JSON: { "community_id": "...", "member_did": "...", "nickname"?: "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_space_update(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_join(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let space_id = data["space_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing space_id"))?;
-    let name = data["name"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing name"))?;
-    let actor_did = data["actor_did"]
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let member_did = data["member_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
+    let nickname = data["nickname"].as_str();
 
     let svc = community_service()?;
-    svc.update_space(space_id, name, actor_did)
+    svc.join_community(community_id, member_did, nickname)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "spaceUpdated",
-            "space_id": space_id,
+            "type": "memberJoined",
+            "community_id": community_id,
+            "member_did": member_did,
         }),
     );
 
@@ -6877,265 +8489,151 @@ pub fn umbra_wasm_community_space_update(json: &str) -> Result<JsValue, JsValue>
     ))
 }
 
-/// Reorder spaces in a community.
+/// Leave a community.
 ///
-/// Takes JSON: { "community_id": "...", "space_ids": ["id1", "id2", ...] }
+/// Takes JSON: { "community_id": "...", "member_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_space_reorder(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_leave(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
     let community_id = data["community_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let space_ids: Vec<String> = data["space_ids"]
-        .as_array()
-        .ok_or_else(|| JsValue::from_str("Missing space_ids array"))?
-        .iter()
-        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-        .collect();
+    let member_did = data["member_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
 
     let svc = community_service()?;
-    svc.reorder_spaces(community_id, &space_ids, "system")
+    svc.leave_community(community_id, member_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "memberLeft",
+            "community_id": community_id,
+            "member_did": member_did,
+        }),
+    );
+
     Ok(JsValue::from_str(
         &serde_json::json!({"success": true}).to_string(),
     ))
 }
 
-/// Delete a space and all its channels.
+/// Kick a member from a community.
 ///
-/// Takes JSON: { "space_id": "...", "actor_did": "..." }
+/// Takes JSON: { "community_id": "...", "target_did": "...", "actor_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_space_delete(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_kick(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let space_id = data["space_id"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing space_id"))?;
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let target_did = data["target_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing target_did"))?;
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.delete_space(space_id, actor_did)
+    svc.kick_member(community_id, target_did, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "spaceDeleted",
-            "space_id": space_id,
+            "type": "memberKicked",
+            "community_id": community_id,
+            "target_did": target_did,
         }),
     );
 
+    let relay_messages = community_rekey(community_id);
     Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
+        &serde_json::json!({"success": true, "relay_messages": relay_messages}).to_string(),
     ))
 }
 
-// ============================================================================
-// COMMUNITY — CHANNELS
-// ============================================================================
-
-/// Create a new channel in a space.
+/// Ban a member from a community.
 ///
-/// Takes JSON: { "community_id", "space_id", "name", "channel_type", "topic"?, "position", "actor_did" }
-/// Returns JSON: CommunityChannel object
+/// Takes JSON: { "community_id", "target_did", "reason"?, "expires_at"?, "device_fingerprint"?, "actor_did" }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_channel_create(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_ban(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
     let community_id = data["community_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let space_id = data["space_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing space_id"))?;
-    let name = data["name"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing name"))?;
-    let channel_type = data["channel_type"]
+    let target_did = data["target_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_type"))?;
-    let topic = data["topic"].as_str();
-    let position = data["position"].as_i64().unwrap_or(0) as i32;
+        .ok_or_else(|| JsValue::from_str("Missing target_did"))?;
+    let reason = data["reason"].as_str();
+    let expires_at = data["expires_at"].as_i64();
+    let device_fingerprint = data["device_fingerprint"].as_str();
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
-    let category_id = data["category_id"].as_str();
 
     let svc = community_service()?;
-    let ch = svc
-        .create_channel(
-            community_id,
-            space_id,
-            name,
-            channel_type,
-            topic,
-            position,
-            actor_did,
-            category_id,
-        )
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let json_result = serde_json::json!({
-        "id": ch.id,
-        "community_id": ch.community_id,
-        "space_id": ch.space_id,
-        "category_id": ch.category_id,
-        "name": ch.name,
-        "channel_type": ch.channel_type,
-        "topic": ch.topic,
-        "position": ch.position,
-        "slow_mode_seconds": ch.slow_mode_seconds,
-        "e2ee_enabled": ch.e2ee_enabled,
-        "pin_limit": ch.pin_limit,
-        "created_at": ch.created_at,
-        "updated_at": ch.updated_at,
-    });
+    svc.ban_member(
+        community_id,
+        target_did,
+        reason,
+        expires_at,
+        device_fingerprint,
+        actor_did,
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "channelCreated",
+            "type": "memberBanned",
             "community_id": community_id,
-            "channel_id": ch.id,
+            "target_did": target_did,
         }),
     );
 
-    Ok(JsValue::from_str(&json_result.to_string()))
-}
-
-/// Get all channels in a space.
-///
-/// Returns JSON: CommunityChannel[]
-#[wasm_bindgen]
-pub fn umbra_wasm_community_channel_list(space_id: &str) -> Result<JsValue, JsValue> {
-    let svc = community_service()?;
-    let channels = svc
-        .get_channels(space_id)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let arr: Vec<serde_json::Value> = channels
-        .iter()
-        .map(|ch| {
-            serde_json::json!({
-                "id": ch.id,
-                "community_id": ch.community_id,
-                "space_id": ch.space_id,
-                "category_id": ch.category_id,
-                "name": ch.name,
-                "channel_type": ch.channel_type,
-                "topic": ch.topic,
-                "position": ch.position,
-                "slow_mode_seconds": ch.slow_mode_seconds,
-                "e2ee_enabled": ch.e2ee_enabled,
-                "pin_limit": ch.pin_limit,
-                "created_at": ch.created_at,
-                "updated_at": ch.updated_at,
-            })
-        })
-        .collect();
-
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&arr).unwrap_or_default(),
-    ))
-}
-
-/// Get all channels in a community (across all spaces).
-///
-/// Returns JSON: CommunityChannel[]
-#[wasm_bindgen]
-pub fn umbra_wasm_community_channel_list_all(community_id: &str) -> Result<JsValue, JsValue> {
-    let svc = community_service()?;
-    let channels = svc
-        .get_all_channels(community_id)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let arr: Vec<serde_json::Value> = channels
-        .iter()
-        .map(|ch| {
-            serde_json::json!({
-                "id": ch.id,
-                "community_id": ch.community_id,
-                "space_id": ch.space_id,
-                "category_id": ch.category_id,
-                "name": ch.name,
-                "channel_type": ch.channel_type,
-                "topic": ch.topic,
-                "position": ch.position,
-                "slow_mode_seconds": ch.slow_mode_seconds,
-                "e2ee_enabled": ch.e2ee_enabled,
-                "pin_limit": ch.pin_limit,
-                "created_at": ch.created_at,
-                "updated_at": ch.updated_at,
-            })
-        })
-        .collect();
-
+    let relay_messages = community_rekey(community_id);
     Ok(JsValue::from_str(
-        &serde_json::to_string(&arr).unwrap_or_default(),
+        &serde_json::json!({"success": true, "relay_messages": relay_messages}).to_string(),
     ))
 }
 
-/// Get a single channel by ID.
-///
-/// Returns JSON: CommunityChannel object
-#[wasm_bindgen]
-pub fn umbra_wasm_community_channel_get(channel_id: &str) -> Result<JsValue, JsValue> {
-    let svc = community_service()?;
-    let ch = svc
-        .get_channel(channel_id)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let json = serde_json::json!({
-        "id": ch.id,
-        "community_id": ch.community_id,
-        "space_id": ch.space_id,
-        "category_id": ch.category_id,
-        "name": ch.name,
-        "channel_type": ch.channel_type,
-        "topic": ch.topic,
-        "position": ch.position,
-        "slow_mode_seconds": ch.slow_mode_seconds,
-        "e2ee_enabled": ch.e2ee_enabled,
-        "pin_limit": ch.pin_limit,
-        "created_at": ch.created_at,
-        "updated_at": ch.updated_at,
-    });
-    Ok(JsValue::from_str(&json.to_string()))
-}
-
-/// Update a channel's name and/or topic.
+/// Unban a member.
 ///
-/// Takes JSON: { "channel_id": "...", "name"?: "...", "topic"?: "...", "actor_did": "..." }
+/// Takes JSON: { "community_id": "...", "target_did": "...", "actor_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_channel_update(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_unban(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let channel_id = data["channel_id"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
-    let name = data["name"].as_str();
-    let topic = data["topic"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let target_did = data["target_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing target_did"))?;
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.update_channel(channel_id, name, topic, actor_did)
+    svc.unban_member(community_id, target_did, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "channelUpdated",
-            "channel_id": channel_id,
+            "type": "memberUnbanned",
+            "community_id": community_id,
+            "target_did": target_did,
         }),
     );
 
@@ -7144,218 +8642,230 @@ pub fn umbra_wasm_community_channel_update(json: &str) -> Result<JsValue, JsValu
     ))
 }
 
-/// Set slow mode for a channel.
+/// Get all members of a community.
 ///
-/// Takes JSON: { "channel_id": "...", "seconds": 0, "actor_did": "..." }
+/// Returns JSON: CommunityMember[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_channel_set_slow_mode(json: &str) -> Result<JsValue, JsValue> {
-    let data: serde_json::Value = serde_json::from_str(json)
-        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
-
-    let channel_id = data["channel_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
-    let seconds = data["seconds"]
-        .as_i64()
-        .ok_or_else(|| JsValue::from_str("Missing seconds"))? as i32;
-    let actor_did = data["actor_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
-
+pub fn umbra_wasm_community_member_list(community_id: &str) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    svc.set_slow_mode(channel_id, seconds, actor_did)
+    let members = svc
+        .get_members(community_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
+    let arr: Vec<serde_json::Value> = members
+        .iter()
+        .map(|m| {
+            serde_json::json!({
+                "community_id": m.community_id,
+                "member_did": m.member_did,
+                "nickname": m.nickname,
+                "avatar_url": m.avatar_url,
+                "bio": m.bio,
+                "joined_at": m.joined_at,
+            })
+        })
+        .collect();
+
     Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
+        &serde_json::to_string(&arr).unwrap_or_default(),
     ))
 }
 
-/// Toggle E2EE for a channel.
+/// Get a single member record.
 ///
-/// Takes JSON: { "channel_id": "...", "enabled": true, "actor_did": "..." }
+/// Returns JSON: CommunityMember object
 #[wasm_bindgen]
-pub fn umbra_wasm_community_channel_set_e2ee(json: &str) -> Result<JsValue, JsValue> {
-    let data: serde_json::Value = serde_json::from_str(json)
-        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
-
-    let channel_id = data["channel_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
-    let enabled = data["enabled"]
-        .as_bool()
-        .ok_or_else(|| JsValue::from_str("Missing enabled"))?;
-    let actor_did = data["actor_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
-
+pub fn umbra_wasm_community_member_get(
+    community_id: &str,
+    member_did: &str,
+) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    svc.set_channel_e2ee(channel_id, enabled, actor_did)
+    let m = svc
+        .get_member(community_id, member_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
-    ))
+    let json = serde_json::json!({
+        "community_id": m.community_id,
+        "member_did": m.member_did,
+        "nickname": m.nickname,
+        "avatar_url": m.avatar_url,
+        "bio": m.bio,
+        "joined_at": m.joined_at,
+    });
+    Ok(JsValue::from_str(&json.to_string()))
 }
 
-/// Delete a channel.
+/// Update a member's community profile.
 ///
-/// Takes JSON: { "channel_id": "...", "actor_did": "..." }
+/// Takes JSON: { "community_id", "member_did", "nickname"?, "avatar_url"?, "bio"? }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_channel_delete(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_member_update_profile(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let channel_id = data["channel_id"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let member_did = data["member_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
+    let nickname = data["nickname"].as_str();
+    let avatar_url = data["avatar_url"].as_str();
+    let bio = data["bio"].as_str();
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.delete_channel(channel_id, actor_did)
+    svc.update_member_profile(community_id, member_did, nickname, avatar_url, bio, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "channelDeleted",
-            "channel_id": channel_id,
-        }),
-    );
-
     Ok(JsValue::from_str(
         &serde_json::json!({"success": true}).to_string(),
     ))
 }
 
-/// Reorder channels within a space.
+/// Get all bans for a community.
 ///
-/// Takes JSON: { "space_id": "...", "channel_ids": ["id1", "id2", ...] }
+/// Returns JSON: CommunityBan[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_channel_reorder(json: &str) -> Result<JsValue, JsValue> {
-    let data: serde_json::Value = serde_json::from_str(json)
-        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+pub fn umbra_wasm_community_ban_list(community_id: &str) -> Result<JsValue, JsValue> {
+    let svc = community_service()?;
+    let bans = svc
+        .get_bans(community_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let space_id = data["space_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing space_id"))?;
-    let channel_ids: Vec<String> = data["channel_ids"]
-        .as_array()
-        .ok_or_else(|| JsValue::from_str("Missing channel_ids array"))?
+    let arr: Vec<serde_json::Value> = bans
         .iter()
-        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+        .map(|b| {
+            serde_json::json!({
+                "community_id": b.community_id,
+                "banned_did": b.banned_did,
+                "reason": b.reason,
+                "banned_by": b.banned_by,
+                "device_fingerprint": b.device_fingerprint,
+                "expires_at": b.expires_at,
+                "created_at": b.created_at,
+            })
+        })
         .collect();
 
-    let svc = community_service()?;
-    svc.reorder_channels(space_id, &channel_ids)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
     Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
+        &serde_json::to_string(&arr).unwrap_or_default(),
     ))
 }
 
 // ============================================================================
-// COMMUNITY — MEMBERS
+// COMMUNITY — ROLES
 // ============================================================================
 
-/// Join a community.
+/// Get all roles for a community.
 ///
-/// Takes JSON: { "community_id": "...", "member_did": "...", "nickname"?: "..." }
+/// Returns JSON: CommunityRole[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_join(json: &str) -> Result<JsValue, JsValue> {
-    let data: serde_json::Value = serde_json::from_str(json)
-        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
-
-    let community_id = data["community_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let member_did = data["member_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-    let nickname = data["nickname"].as_str();
-
+pub fn umbra_wasm_community_role_list(community_id: &str) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    svc.join_community(community_id, member_did, nickname)
+    let roles = svc
+        .get_roles(community_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "memberJoined",
-            "community_id": community_id,
-            "member_did": member_did,
-        }),
-    );
+    let arr: Vec<serde_json::Value> = roles
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "id": r.id,
+                "community_id": r.community_id,
+                "name": r.name,
+                "color": r.color,
+                "icon": r.icon,
+                "badge": r.badge,
+                "position": r.position,
+                "hoisted": r.hoisted,
+                "mentionable": r.mentionable,
+                "is_preset": r.is_preset,
+                "permissions_bitfield": r.permissions_bitfield,
+                "created_at": r.created_at,
+                "updated_at": r.updated_at,
+            })
+        })
+        .collect();
 
     Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
+        &serde_json::to_string(&arr).unwrap_or_default(),
     ))
 }
 
-/// Leave a community.
+/// Get roles assigned to a specific member.
 ///
-/// Takes JSON: { "community_id": "...", "member_did": "..." }
+/// Returns JSON: CommunityRole[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_leave(json: &str) -> Result<JsValue, JsValue> {
-    let data: serde_json::Value = serde_json::from_str(json)
-        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
-
-    let community_id = data["community_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let member_did = data["member_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-
+pub fn umbra_wasm_community_member_roles(
+    community_id: &str,
+    member_did: &str,
+) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    svc.leave_community(community_id, member_did)
+    let roles = svc
+        .get_member_roles(community_id, member_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "memberLeft",
-            "community_id": community_id,
-            "member_did": member_did,
-        }),
-    );
+    let arr: Vec<serde_json::Value> = roles
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "id": r.id,
+                "community_id": r.community_id,
+                "name": r.name,
+                "color": r.color,
+                "icon": r.icon,
+                "badge": r.badge,
+                "position": r.position,
+                "hoisted": r.hoisted,
+                "mentionable": r.mentionable,
+                "is_preset": r.is_preset,
+                "permissions_bitfield": r.permissions_bitfield,
+                "created_at": r.created_at,
+                "updated_at": r.updated_at,
+            })
+        })
+        .collect();
 
     Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
+        &serde_json::to_string(&arr).unwrap_or_default(),
     ))
 }
 
-/// Kick a member from a community.
+/// Assign a role to a member.
 ///
-/// Takes JSON: { "community_id": "...", "target_did": "...", "actor_did": "..." }
+/// Takes JSON: { "community_id", "member_did", "role_id", "actor_did" }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_kick(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_role_assign(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
     let community_id = data["community_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let target_did = data["target_did"]
+    let member_did = data["member_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing target_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
+    let role_id = data["role_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing role_id"))?;
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.kick_member(community_id, target_did, actor_did)
+    svc.assign_role(community_id, member_did, role_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "memberKicked",
+            "type": "roleAssigned",
             "community_id": community_id,
-            "target_did": target_did,
+            "member_did": member_did,
+            "role_id": role_id,
         }),
     );
 
@@ -7364,108 +8874,148 @@ pub fn umbra_wasm_community_kick(json: &str) -> Result<JsValue, JsValue> {
     ))
 }
 
-/// Ban a member from a community.
+/// Unassign a role from a member.
 ///
-/// Takes JSON: { "community_id", "target_did", "reason"?, "expires_at"?, "device_fingerprint"?, "actor_did" }
+/// Takes JSON: { "community_id", "member_did", "role_id", "actor_did" }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_ban(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_role_unassign(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
     let community_id = data["community_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let target_did = data["target_did"]
+    let member_did = data["member_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing target_did"))?;
-    let reason = data["reason"].as_str();
-    let expires_at = data["expires_at"].as_i64();
-    let device_fingerprint = data["device_fingerprint"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
+    let role_id = data["role_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing role_id"))?;
     let actor_did = data["actor_did"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.ban_member(
-        community_id,
-        target_did,
-        reason,
-        expires_at,
-        device_fingerprint,
-        actor_did,
-    )
-    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    svc.unassign_role(community_id, member_did, role_id, actor_did)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "memberBanned",
+            "type": "roleUnassigned",
             "community_id": community_id,
-            "target_did": target_did,
+            "member_did": member_did,
+            "role_id": role_id,
         }),
     );
 
+    let relay_messages = community_rekey(community_id);
     Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
+        &serde_json::json!({"success": true, "relay_messages": relay_messages}).to_string(),
     ))
 }
 
-/// Unban a member.
+// ============================================================================
+// COMMUNITY — INVITES
+// ============================================================================
+
+/// Create an invite link for a community.
 ///
-/// Takes JSON: { "community_id": "...", "target_did": "...", "actor_did": "..." }
+/// Takes JSON: { "community_id", "creator_did", "max_uses"?, "expires_at"? }
+/// Returns JSON: CommunityInvite object
 #[wasm_bindgen]
-pub fn umbra_wasm_community_unban(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_invite_create(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
     let community_id = data["community_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let target_did = data["target_did"]
+    let creator_did = data["creator_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing target_did"))?;
-    let actor_did = data["actor_did"]
+        .ok_or_else(|| JsValue::from_str("Missing creator_did"))?;
+    let max_uses = data["max_uses"].as_i64().map(|v| v as i32);
+    let expires_at = data["expires_at"].as_i64();
+
+    let svc = community_service()?;
+    let invite = svc
+        .create_invite(community_id, creator_did, max_uses, expires_at)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let json_result = serde_json::json!({
+        "id": invite.id,
+        "community_id": invite.community_id,
+        "code": invite.code,
+        "vanity": invite.vanity,
+        "creator_did": invite.creator_did,
+        "max_uses": invite.max_uses,
+        "use_count": invite.use_count,
+        "expires_at": invite.expires_at,
+        "created_at": invite.created_at,
+    });
+    Ok(JsValue::from_str(&json_result.to_string()))
+}
+
+/// Use an invite code to join a community.
+///
+/// Takes JSON: { "code": "...", "member_did": "..." }
+/// Returns JSON: { "community_id": "..." }
+#[wasm_bindgen]
+pub fn umbra_wasm_community_invite_use(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let code = data["code"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing code"))?;
+    let member_did = data["member_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
+    let nickname = data["nickname"].as_str();
 
     let svc = community_service()?;
-    svc.unban_member(community_id, target_did, actor_did)
+    let community_id = svc
+        .use_invite(code, member_did, nickname)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "memberUnbanned",
+            "type": "memberJoined",
             "community_id": community_id,
-            "target_did": target_did,
+            "member_did": member_did,
         }),
     );
 
-    Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
-    ))
+    let json_result = serde_json::json!({
+        "community_id": community_id,
+    });
+    Ok(JsValue::from_str(&json_result.to_string()))
 }
 
-/// Get all members of a community.
+/// Get all invites for a community.
 ///
-/// Returns JSON: CommunityMember[]
+/// Returns JSON: CommunityInvite[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_member_list(community_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_invite_list(community_id: &str) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    let members = svc
-        .get_members(community_id)
+    let invites = svc
+        .get_invites(community_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let arr: Vec<serde_json::Value> = members
+    let arr: Vec<serde_json::Value> = invites
         .iter()
-        .map(|m| {
+        .map(|i| {
             serde_json::json!({
-                "community_id": m.community_id,
-                "member_did": m.member_did,
-                "nickname": m.nickname,
-                "avatar_url": m.avatar_url,
-                "bio": m.bio,
-                "joined_at": m.joined_at,
+                "id": i.id,
+                "community_id": i.community_id,
+                "code": i.code,
+                "vanity": i.vanity,
+                "creator_did": i.creator_did,
+                "max_uses": i.max_uses,
+                "use_count": i.use_count,
+                "expires_at": i.expires_at,
+                "created_at": i.created_at,
             })
         })
         .collect();
@@ -7475,50 +9025,23 @@ pub fn umbra_wasm_community_member_list(community_id: &str) -> Result<JsValue, J
     ))
 }
 
-/// Get a single member record.
-///
-/// Returns JSON: CommunityMember object
-#[wasm_bindgen]
-pub fn umbra_wasm_community_member_get(
-    community_id: &str,
-    member_did: &str,
-) -> Result<JsValue, JsValue> {
-    let svc = community_service()?;
-    let m = svc
-        .get_member(community_id, member_did)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let json = serde_json::json!({
-        "community_id": m.community_id,
-        "member_did": m.member_did,
-        "nickname": m.nickname,
-        "avatar_url": m.avatar_url,
-        "bio": m.bio,
-        "joined_at": m.joined_at,
-    });
-    Ok(JsValue::from_str(&json.to_string()))
-}
-
-/// Update a member's community profile.
+/// Delete an invite.
 ///
-/// Takes JSON: { "community_id", "member_did", "nickname"?, "avatar_url"?, "bio"? }
+/// Takes JSON: { "invite_id": "...", "actor_did": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_member_update_profile(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_invite_delete(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let community_id = data["community_id"]
+    let invite_id = data["invite_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let member_did = data["member_did"]
+        .ok_or_else(|| JsValue::from_str("Missing invite_id"))?;
+    let actor_did = data["actor_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-    let nickname = data["nickname"].as_str();
-    let avatar_url = data["avatar_url"].as_str();
-    let bio = data["bio"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.update_member_profile(community_id, member_did, nickname, avatar_url, bio)
+    svc.delete_invite(invite_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     Ok(JsValue::from_str(
@@ -7526,106 +9049,87 @@ pub fn umbra_wasm_community_member_update_profile(json: &str) -> Result<JsValue,
     ))
 }
 
-/// Get all bans for a community.
+/// Set a vanity invite code for a community.
 ///
-/// Returns JSON: CommunityBan[]
+/// Takes JSON: { "community_id": "...", "vanity_code": "...", "creator_did": "..." }
+/// Returns JSON: CommunityInvite object
 #[wasm_bindgen]
-pub fn umbra_wasm_community_ban_list(community_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_invite_set_vanity(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let community_id = data["community_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let vanity_code = data["vanity_code"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing vanity_code"))?;
+    let creator_did = data["creator_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing creator_did"))?;
+
     let svc = community_service()?;
-    let bans = svc
-        .get_bans(community_id)
+    let invite = svc
+        .set_vanity_invite(community_id, vanity_code, creator_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let arr: Vec<serde_json::Value> = bans
-        .iter()
-        .map(|b| {
-            serde_json::json!({
-                "community_id": b.community_id,
-                "banned_did": b.banned_did,
-                "reason": b.reason,
-                "banned_by": b.banned_by,
-                "device_fingerprint": b.device_fingerprint,
-                "expires_at": b.expires_at,
-                "created_at": b.created_at,
-            })
-        })
-        .collect();
-
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&arr).unwrap_or_default(),
-    ))
+    let json_result = serde_json::json!({
+        "id": invite.id,
+        "community_id": invite.community_id,
+        "code": invite.code,
+        "vanity": invite.vanity,
+        "creator_did": invite.creator_did,
+        "max_uses": invite.max_uses,
+        "use_count": invite.use_count,
+        "expires_at": invite.expires_at,
+        "created_at": invite.created_at,
+    });
+    Ok(JsValue::from_str(&json_result.to_string()))
 }
 
 // ============================================================================
-// COMMUNITY — ROLES
+// COMMUNITY — AUDIT LOG
 // ============================================================================
 
-/// Get all roles for a community.
+/// Get audit log entries for a community.
 ///
-/// Returns JSON: CommunityRole[]
+/// Takes JSON: { "community_id": "...", "limit": 50, "offset": 0 }
+/// Returns JSON: AuditLogEntry[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_role_list(community_id: &str) -> Result<JsValue, JsValue> {
-    let svc = community_service()?;
-    let roles = svc
-        .get_roles(community_id)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-    let arr: Vec<serde_json::Value> = roles
-        .iter()
-        .map(|r| {
-            serde_json::json!({
-                "id": r.id,
-                "community_id": r.community_id,
-                "name": r.name,
-                "color": r.color,
-                "icon": r.icon,
-                "badge": r.badge,
-                "position": r.position,
-                "hoisted": r.hoisted,
-                "mentionable": r.mentionable,
-                "is_preset": r.is_preset,
-                "permissions_bitfield": r.permissions_bitfield,
-                "created_at": r.created_at,
-                "updated_at": r.updated_at,
-            })
-        })
-        .collect();
+pub fn umbra_wasm_community_audit_log(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&arr).unwrap_or_default(),
-    ))
-}
+    let community_id = data["community_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let limit = data["limit"].as_i64().unwrap_or(50) as usize;
+    let offset = data["offset"].as_i64().unwrap_or(0) as usize;
 
-/// Get roles assigned to a specific member.
-///
-/// Returns JSON: CommunityRole[]
-#[wasm_bindgen]
-pub fn umbra_wasm_community_member_roles(
-    community_id: &str,
-    member_did: &str,
-) -> Result<JsValue, JsValue> {
-    let svc = community_service()?;
-    let roles = svc
-        .get_member_roles(community_id, member_did)
+    let state = get_state()?;
+    let state = state.read();
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let entries = database
+        .get_audit_log(community_id, limit, offset)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let arr: Vec<serde_json::Value> = roles
+    let arr: Vec<serde_json::Value> = entries
         .iter()
-        .map(|r| {
+        .map(|e| {
             serde_json::json!({
-                "id": r.id,
-                "community_id": r.community_id,
-                "name": r.name,
-                "color": r.color,
-                "icon": r.icon,
-                "badge": r.badge,
-                "position": r.position,
-                "hoisted": r.hoisted,
-                "mentionable": r.mentionable,
-                "is_preset": r.is_preset,
-                "permissions_bitfield": r.permissions_bitfield,
-                "created_at": r.created_at,
-                "updated_at": r.updated_at,
+                "id": e.id,
+                "community_id": e.community_id,
+                "actor_did": e.actor_did,
+                "action_type": e.action_type,
+                "target_type": e.target_type,
+                "target_id": e.target_id,
+                "metadata_json": e.metadata_json,
+                "content_detail": e.content_detail,
+                "created_at": e.created_at,
             })
         })
         .collect();
@@ -7635,585 +9139,659 @@ pub fn umbra_wasm_community_member_roles(
     ))
 }
 
-/// Assign a role to a member.
+// ============================================================================
+// COMMUNITY — MESSAGING (Phase 2)
+// ============================================================================
+
+/// Send a plaintext message to a community channel.
 ///
-/// Takes JSON: { "community_id", "member_did", "role_id", "actor_did" }
+/// Takes JSON: { "channel_id": "...", "sender_did": "...", "content": "...",
+///               "reply_to_id": null, "thread_id": null, "content_warning": null }
+/// Returns JSON: CommunityMessage object
 #[wasm_bindgen]
-pub fn umbra_wasm_community_role_assign(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_message_send(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let community_id = data["community_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let member_did = data["member_did"]
+    let channel_id = data["channel_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-    let role_id = data["role_id"]
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
+    let sender_did = data["sender_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing role_id"))?;
-    let actor_did = data["actor_did"]
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
+    let content = data["content"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing content"))?;
+    let reply_to_id = data["reply_to_id"].as_str();
+    let thread_id = data["thread_id"].as_str();
+    let content_warning = data["content_warning"].as_str();
+    let metadata_json = data["metadata_json"].as_str();
 
     let svc = community_service()?;
-    svc.assign_role(community_id, member_did, role_id, actor_did)
+    let msg = svc
+        .send_message(
+            channel_id,
+            sender_did,
+            content,
+            reply_to_id,
+            thread_id,
+            content_warning,
+            metadata_json,
+        )
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
+    let json_result = message_to_json(&msg);
+
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "roleAssigned",
-            "community_id": community_id,
-            "member_did": member_did,
-            "role_id": role_id,
+            "type": "communityMessageSent",
+            "channel_id": channel_id,
+            "message_id": msg.id,
+            "sender_did": sender_did,
         }),
     );
 
-    Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
-    ))
+    Ok(JsValue::from_str(&json_result.to_string()))
 }
 
-/// Unassign a role from a member.
+/// Send an encrypted message to a community channel (E2EE).
 ///
-/// Takes JSON: { "community_id", "member_did", "role_id", "actor_did" }
+/// Takes JSON: { "channel_id": "...", "sender_did": "...", "content_encrypted_b64": "...",
+///               "nonce": "...", "key_version": 1, "reply_to_id": null, "thread_id": null }
+/// Returns JSON: { "message_id": "..." }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_role_unassign(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_message_send_encrypted(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let community_id = data["community_id"]
+    let channel_id = data["channel_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let member_did = data["member_did"]
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
+    let sender_did = data["sender_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-    let role_id = data["role_id"]
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
+    let content_b64 = data["content_encrypted_b64"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing role_id"))?;
-    let actor_did = data["actor_did"]
+        .ok_or_else(|| JsValue::from_str("Missing content_encrypted_b64"))?;
+    let nonce = data["nonce"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing nonce"))?;
+    let key_version = data["key_version"]
+        .as_i64()
+        .ok_or_else(|| JsValue::from_str("Missing key_version"))? as i32;
+    let reply_to_id = data["reply_to_id"].as_str();
+    let thread_id = data["thread_id"].as_str();
+
+    let encrypted_bytes = base64::engine::general_purpose::STANDARD
+        .decode(content_b64)
+        .map_err(|e| JsValue::from_str(&format!("Invalid base64: {}", e)))?;
 
     let svc = community_service()?;
-    svc.unassign_role(community_id, member_did, role_id, actor_did)
+    let id = svc
+        .send_encrypted_message(
+            channel_id,
+            sender_did,
+            &encrypted_bytes,
+            nonce,
+            key_version,
+            reply_to_id,
+            thread_id,
+        )
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "roleUnassigned",
-            "community_id": community_id,
-            "member_did": member_did,
-            "role_id": role_id,
+            "type": "communityMessageSent",
+            "channel_id": channel_id,
+            "message_id": id,
+            "sender_did": sender_did,
+            "is_e2ee": true,
         }),
     );
 
     Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
+        &serde_json::json!({"message_id": id}).to_string(),
     ))
 }
 
-// ============================================================================
-// COMMUNITY — INVITES
-// ============================================================================
-
-/// Create an invite link for a community.
+/// Store a message received from another member via relay / bridge.
 ///
-/// Takes JSON: { "community_id", "creator_did", "max_uses"?, "expires_at"? }
-/// Returns JSON: CommunityInvite object
+/// Skips permission checks and uses INSERT OR IGNORE so duplicate IDs
+/// are silently skipped. Returns `{ "stored": true }`.
+///
+/// Takes JSON: { "id": "...", "channel_id": "...", "sender_did": "...",
+///               "content": "...", "created_at": 1234567890 }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_invite_create(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_message_store_received(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let community_id = data["community_id"]
+    let id = data["id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let creator_did = data["creator_did"]
+        .ok_or_else(|| JsValue::from_str("Missing id"))?;
+    let channel_id = data["channel_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing creator_did"))?;
-    let max_uses = data["max_uses"].as_i64().map(|v| v as i32);
-    let expires_at = data["expires_at"].as_i64();
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
+    let sender_did = data["sender_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
+    let content = data["content"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing content"))?;
+    let created_at = data["created_at"]
+        .as_i64()
+        .ok_or_else(|| JsValue::from_str("Missing created_at"))?;
+    let metadata_json = data["metadata_json"].as_str();
 
     let svc = community_service()?;
-    let invite = svc
-        .create_invite(community_id, creator_did, max_uses, expires_at)
+    svc.store_received_message(
+        id,
+        channel_id,
+        sender_did,
+        content,
+        created_at,
+        metadata_json,
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(JsValue::from_str(
+        &serde_json::json!({"stored": true}).to_string(),
+    ))
+}
+
+/// Get messages for a community channel (paginated).
+///
+/// Takes JSON: { "channel_id": "...", "limit": 50, "before_timestamp": null }
+/// Returns JSON: CommunityMessage[]
+#[wasm_bindgen]
+pub fn umbra_wasm_community_message_list(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let channel_id = data["channel_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
+    let limit = data["limit"].as_i64().unwrap_or(50) as usize;
+    let before_timestamp = data["before_timestamp"].as_i64();
+
+    let svc = community_service()?;
+    let msgs = svc
+        .get_messages(channel_id, limit, before_timestamp)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let json_result = serde_json::json!({
-        "id": invite.id,
-        "community_id": invite.community_id,
-        "code": invite.code,
-        "vanity": invite.vanity,
-        "creator_did": invite.creator_did,
-        "max_uses": invite.max_uses,
-        "use_count": invite.use_count,
-        "expires_at": invite.expires_at,
-        "created_at": invite.created_at,
-    });
-    Ok(JsValue::from_str(&json_result.to_string()))
+    let arr: Vec<serde_json::Value> = msgs.iter().map(|m| message_to_json(m)).collect();
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&arr).unwrap_or_default(),
+    ))
 }
 
-/// Use an invite code to join a community.
+/// Get a single message by ID.
 ///
-/// Takes JSON: { "code": "...", "member_did": "..." }
-/// Returns JSON: { "community_id": "..." }
+/// Returns JSON: CommunityMessage object
 #[wasm_bindgen]
-pub fn umbra_wasm_community_invite_use(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_message_get(message_id: &str) -> Result<JsValue, JsValue> {
+    let svc = community_service()?;
+    let msg = svc
+        .get_message(message_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(JsValue::from_str(&message_to_json(&msg).to_string()))
+}
+
+/// Edit a message.
+///
+/// Takes JSON: { "message_id": "...", "new_content": "...", "editor_did": "..." }
+/// Returns JSON: { "success": true }
+#[wasm_bindgen]
+pub fn umbra_wasm_community_message_edit(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let code = data["code"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing code"))?;
-    let member_did = data["member_did"]
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+    let new_content = data["new_content"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing new_content"))?;
+    let editor_did = data["editor_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
-    let nickname = data["nickname"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing editor_did"))?;
 
     let svc = community_service()?;
-    let community_id = svc
-        .use_invite(code, member_did, nickname)
+    svc.edit_message(message_id, new_content, editor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
         "community",
         &serde_json::json!({
-            "type": "memberJoined",
-            "community_id": community_id,
-            "member_did": member_did,
+            "type": "communityMessageEdited",
+            "message_id": message_id,
         }),
     );
 
-    let json_result = serde_json::json!({
-        "community_id": community_id,
-    });
-    Ok(JsValue::from_str(&json_result.to_string()))
+    Ok(JsValue::from_str("{\"success\":true}"))
 }
 
-/// Get all invites for a community.
+/// Delete a message for everyone.
 ///
-/// Returns JSON: CommunityInvite[]
+/// Returns JSON: { "success": true }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_invite_list(community_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_message_delete(
+    message_id: &str,
+    actor_did: &str,
+) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    let invites = svc
-        .get_invites(community_id)
+    svc.delete_message_for_everyone(message_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let arr: Vec<serde_json::Value> = invites
-        .iter()
-        .map(|i| {
-            serde_json::json!({
-                "id": i.id,
-                "community_id": i.community_id,
-                "code": i.code,
-                "vanity": i.vanity,
-                "creator_did": i.creator_did,
-                "max_uses": i.max_uses,
-                "use_count": i.use_count,
-                "expires_at": i.expires_at,
-                "created_at": i.created_at,
-            })
-        })
-        .collect();
+    emit_event(
+        "community",
+        &serde_json::json!({
+            "type": "communityMessageDeleted",
+            "message_id": message_id,
+        }),
+    );
 
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&arr).unwrap_or_default(),
-    ))
+    Ok(JsValue::from_str("{\"success\":true}"))
 }
 
-/// Delete an invite.
+/// Delete a message for the current user only.
 ///
-/// Takes JSON: { "invite_id": "...", "actor_did": "..." }
+/// Takes JSON: { "message_id": "...", "member_did": "..." }
+/// Returns JSON: { "success": true }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_invite_delete(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_message_delete_for_me(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let invite_id = data["invite_id"]
+    let message_id = data["message_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing invite_id"))?;
-    let actor_did = data["actor_did"]
+        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+    let member_did = data["member_did"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
 
     let svc = community_service()?;
-    svc.delete_invite(invite_id, actor_did)
+    svc.delete_message_for_me(message_id, member_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    Ok(JsValue::from_str(
-        &serde_json::json!({"success": true}).to_string(),
-    ))
+    Ok(JsValue::from_str("{\"success\":true}"))
 }
 
-/// Set a vanity invite code for a community.
+// ============================================================================
+// COMMUNITY — OP LOG
+// ============================================================================
+
+/// A copy of the loaded identity, for signing community ops.
+fn community_log_identity() -> Result<Identity, JsValue> {
+    let state = get_state()?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    identity
+        .clone_for_service()
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Create a community backed by a signed op log, owned by us.
 ///
-/// Takes JSON: { "community_id": "...", "vanity_code": "...", "creator_did": "..." }
-/// Returns JSON: CommunityInvite object
+/// Takes JSON: { "name": "...", "description"?: "...", "nickname"?: "..." }
+/// Returns JSON: { "community_id", "space_id", "welcome_channel_id", "general_channel_id", "role_ids": { ... } }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_invite_set_vanity(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_log_create(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let community_id = data["community_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let vanity_code = data["vanity_code"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing vanity_code"))?;
-    let creator_did = data["creator_did"]
+    let name = data["name"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing creator_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing name"))?;
+    let description = data["description"].as_str();
+    let nickname = data["nickname"].as_str();
 
+    let identity = community_log_identity()?;
     let svc = community_service()?;
-    let invite = svc
-        .set_vanity_invite(community_id, vanity_code, creator_did)
+    let result = svc
+        .create_signed_community(&identity, name, description, nickname)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     let json_result = serde_json::json!({
-        "id": invite.id,
-        "community_id": invite.community_id,
-        "code": invite.code,
-        "vanity": invite.vanity,
-        "creator_did": invite.creator_did,
-        "max_uses": invite.max_uses,
-        "use_count": invite.use_count,
-        "expires_at": invite.expires_at,
-        "created_at": invite.created_at,
+        "community_id": result.community_id,
+        "space_id": result.space_id,
+        "welcome_channel_id": result.welcome_channel_id,
+        "general_channel_id": result.general_channel_id,
+        "role_ids": {
+            "owner": result.role_ids.owner,
+            "admin": result.role_ids.admin,
+            "moderator": result.role_ids.moderator,
+            "member": result.role_ids.member,
+        }
     });
     Ok(JsValue::from_str(&json_result.to_string()))
 }
 
-// ============================================================================
-// COMMUNITY — AUDIT LOG
-// ============================================================================
-
-/// Get audit log entries for a community.
+/// Sign and apply a change to a log-backed community.
 ///
-/// Takes JSON: { "community_id": "...", "limit": 50, "offset": 0 }
-/// Returns JSON: AuditLogEntry[]
+/// Takes JSON: { "community_id": "...", "op": { "type": "send_message", ... } }
+/// Returns JSON: { "op": SignedOp, "relay_messages": [{ "to_did", "payload" }] }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_audit_log(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_log_submit(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
     let community_id = data["community_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
-    let limit = data["limit"].as_i64().unwrap_or(50) as usize;
-    let offset = data["offset"].as_i64().unwrap_or(0) as usize;
-
-    let state = get_state()?;
-    let state = state.read();
-    let database = state
-        .database
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+    let op: crate::community::CommunityOp = serde_json::from_value(data["op"].clone())
+        .map_err(|e| JsValue::from_str(&format!("Invalid op: {}", e)))?;
 
-    let entries = database
-        .get_audit_log(community_id, limit, offset)
+    let identity = community_log_identity()?;
+    let svc = community_service()?;
+    let mut submitted = svc
+        .submit_op(&identity, community_id, op)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let arr: Vec<serde_json::Value> = entries
-        .iter()
-        .map(|e| {
-            serde_json::json!({
-                "id": e.id,
-                "community_id": e.community_id,
-                "actor_did": e.actor_did,
-                "action_type": e.action_type,
-                "target_type": e.target_type,
-                "target_id": e.target_id,
-                "metadata_json": e.metadata_json,
-                "content_detail": e.content_detail,
-                "created_at": e.created_at,
-            })
-        })
-        .collect();
-
-    Ok(JsValue::from_str(
-        &serde_json::to_string(&arr).unwrap_or_default(),
-    ))
+    submitted
+        .relay_messages
+        .extend(community_rekey(community_id));
+    let json_result = serde_json::json!({
+        "op": submitted.op,
+        "relay_messages": submitted.relay_messages,
+    });
+    Ok(JsValue::from_str(&json_result.to_string()))
 }
 
-// ============================================================================
-// COMMUNITY — MESSAGING (Phase 2)
-// ============================================================================
-
-/// Send a plaintext message to a community channel.
+/// Take in ops received in a `community_op` envelope or a sync.
 ///
-/// Takes JSON: { "channel_id": "...", "sender_did": "...", "content": "...",
-///               "reply_to_id": null, "thread_id": null, "content_warning": null }
-/// Returns JSON: CommunityMessage object
+/// Takes JSON: { "ops": SignedOp[] }
+/// Returns JSON: { "applied", "rejected": [{ "id", "reason" }], "pending", "missing", "relay_messages" }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_message_send(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_log_ingest(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let channel_id = data["channel_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
-    let sender_did = data["sender_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
-    let content = data["content"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing content"))?;
-    let reply_to_id = data["reply_to_id"].as_str();
-    let thread_id = data["thread_id"].as_str();
-    let content_warning = data["content_warning"].as_str();
-    let metadata_json = data["metadata_json"].as_str();
+    let ops: Vec<crate::community::SignedOp> = serde_json::from_value(data["ops"].clone())
+        .map_err(|e| JsValue::from_str(&format!("Invalid ops: {}", e)))?;
 
+    let community_ids: std::collections::BTreeSet<String> =
+        ops.iter().map(|op| op.community_id.clone()).collect();
     let svc = community_service()?;
-    let msg = svc
-        .send_message(
-            channel_id,
-            sender_did,
-            content,
-            reply_to_id,
-            thread_id,
-            content_warning,
-            metadata_json,
-        )
+    let report = svc
+        .ingest_ops(ops)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let json_result = message_to_json(&msg);
-
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "communityMessageSent",
-            "channel_id": channel_id,
-            "message_id": msg.id,
-            "sender_did": sender_did,
-        }),
-    );
+    // Applied ops can change who may read an encrypted channel
+    let mut relay_messages = Vec::new();
+    if !report.applied.is_empty() {
+        for community_id in &community_ids {
+            relay_messages.extend(community_rekey(community_id));
+        }
+    }
 
+    let mut json_result = serde_json::json!(report);
+    json_result["relay_messages"] = serde_json::json!(relay_messages);
     Ok(JsValue::from_str(&json_result.to_string()))
 }
 
-/// Send an encrypted message to a community channel (E2EE).
+/// Get the heads of a community's op log.
 ///
-/// Takes JSON: { "channel_id": "...", "sender_did": "...", "content_encrypted_b64": "...",
-///               "nonce": "...", "key_version": 1, "reply_to_id": null, "thread_id": null }
-/// Returns JSON: { "message_id": "..." }
+/// Takes JSON: { "community_id": "..." }
+/// Returns JSON: string[]
 #[wasm_bindgen]
-pub fn umbra_wasm_community_message_send_encrypted(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_log_heads(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let channel_id = data["channel_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
-    let sender_did = data["sender_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
-    let content_b64 = data["content_encrypted_b64"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing content_encrypted_b64"))?;
-    let nonce = data["nonce"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing nonce"))?;
-    let key_version = data["key_version"]
-        .as_i64()
-        .ok_or_else(|| JsValue::from_str("Missing key_version"))? as i32;
-    let reply_to_id = data["reply_to_id"].as_str();
-    let thread_id = data["thread_id"].as_str();
-
-    let encrypted_bytes = base64::engine::general_purpose::STANDARD
-        .decode(content_b64)
-        .map_err(|e| JsValue::from_str(&format!("Invalid base64: {}", e)))?;
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
 
     let svc = community_service()?;
-    let id = svc
-        .send_encrypted_message(
-            channel_id,
-            sender_did,
-            &encrypted_bytes,
-            nonce,
-            key_version,
-            reply_to_id,
-            thread_id,
-        )
+    let heads = svc
+        .op_heads(community_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(JsValue::from_str(&serde_json::json!(heads).to_string()))
+}
+
+/// Get the ops a member with the given heads is missing.
+///
+/// Takes JSON: { "community_id": "...", "heads": ["..."] }
+/// Returns JSON: SignedOp[]
+#[wasm_bindgen]
+pub fn umbra_wasm_community_log_ops_since(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let community_id = data["community_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let heads: Vec<String> = serde_json::from_value(data["heads"].clone()).unwrap_or_default();
+
+    let svc = community_service()?;
+    let ops = svc
+        .ops_since(community_id, &heads)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "communityMessageSent",
-            "channel_id": channel_id,
-            "message_id": id,
-            "sender_did": sender_did,
-            "is_e2ee": true,
-        }),
-    );
+    Ok(JsValue::from_str(&serde_json::json!(ops).to_string()))
+}
 
-    Ok(JsValue::from_str(
-        &serde_json::json!({"message_id": id}).to_string(),
-    ))
+// ============================================================================
+// COMMUNITY — CHANNEL KEYRING
+// ============================================================================
+
+/// Sync the keyrings of a community's encrypted channels after a change to
+/// who can read them. Best effort — failures are logged and the next sync
+/// picks them up.
+fn community_rekey(community_id: &str) -> Vec<crate::network::relay_client::RelayMessage> {
+    let (Ok(identity), Ok(svc)) = (community_log_identity(), community_service()) else {
+        return Vec::new();
+    };
+    match svc.sync_community_keys(&identity, community_id) {
+        Ok(update) => update.relay_messages,
+        Err(e) => {
+            tracing::warn!("Channel key sync for {} failed: {}", community_id, e);
+            Vec::new()
+        }
+    }
 }
 
-/// Store a message received from another member via relay / bridge.
-///
-/// Skips permission checks and uses INSERT OR IGNORE so duplicate IDs
-/// are silently skipped. Returns `{ "stored": true }`.
+/// [`community_rekey`] for the community a channel belongs to.
+fn community_rekey_channel(channel_id: &str) -> Vec<crate::network::relay_client::RelayMessage> {
+    let Ok(svc) = community_service() else {
+        return Vec::new();
+    };
+    match svc.get_channel(channel_id) {
+        Ok(channel) => community_rekey(&channel.community_id),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Announce our encryption key to the other members of a community.
 ///
-/// Takes JSON: { "id": "...", "channel_id": "...", "sender_did": "...",
-///               "content": "...", "created_at": 1234567890 }
+/// Takes JSON: { "community_id": "..." }
+/// Returns JSON: { "relay_messages": [{ "to_did", "payload" }] }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_message_store_received(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_keys_announce(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let id = data["id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing id"))?;
-    let channel_id = data["channel_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
-    let sender_did = data["sender_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing sender_did"))?;
-    let content = data["content"]
+    let community_id = data["community_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing content"))?;
-    let created_at = data["created_at"]
-        .as_i64()
-        .ok_or_else(|| JsValue::from_str("Missing created_at"))?;
-    let metadata_json = data["metadata_json"].as_str();
+        .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
 
+    let identity = community_log_identity()?;
     let svc = community_service()?;
-    svc.store_received_message(
-        id,
-        channel_id,
-        sender_did,
-        content,
-        created_at,
-        metadata_json,
-    )
-    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let relay_messages = svc
+        .announce_member_key(&identity, community_id)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     Ok(JsValue::from_str(
-        &serde_json::json!({"stored": true}).to_string(),
+        &serde_json::json!({ "relay_messages": relay_messages }).to_string(),
     ))
 }
 
-/// Get messages for a community channel (paginated).
+/// Store a member key received in a `community_member_key` envelope, then
+/// hand out any channel keys the member was waiting on.
 ///
-/// Takes JSON: { "channel_id": "...", "limit": 50, "before_timestamp": null }
-/// Returns JSON: CommunityMessage[]
+/// Takes JSON: { "announcement": MemberKeyAnnouncement }
+/// Returns JSON: { "relay_messages": [{ "to_did", "payload" }] }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_message_list(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_keys_receive_member_key(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let channel_id = data["channel_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
-    let limit = data["limit"].as_i64().unwrap_or(50) as usize;
-    let before_timestamp = data["before_timestamp"].as_i64();
+    let announcement: crate::community::MemberKeyAnnouncement =
+        serde_json::from_value(data["announcement"].clone())
+            .map_err(|e| JsValue::from_str(&format!("Invalid announcement: {}", e)))?;
 
     let svc = community_service()?;
-    let msgs = svc
-        .get_messages(channel_id, limit, before_timestamp)
+    svc.receive_member_key(&announcement)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let arr: Vec<serde_json::Value> = msgs.iter().map(|m| message_to_json(m)).collect();
+    let relay_messages = community_rekey(&announcement.community_id);
     Ok(JsValue::from_str(
-        &serde_json::to_string(&arr).unwrap_or_default(),
+        &serde_json::json!({ "relay_messages": relay_messages }).to_string(),
     ))
 }
 
-/// Get a single message by ID.
+/// Hand out channel keys to readers missing them, rotating if a key holder
+/// lost access. Covers one channel, or every encrypted channel we manage.
 ///
-/// Returns JSON: CommunityMessage object
+/// Takes JSON: { "channel_id": "..." } or { "community_id": "..." }
+/// Returns JSON: { "rotated": { channel_id: key_version }, "awaiting_key": ["did"], "relay_messages": [...] }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_message_get(message_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_keys_sync(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let identity = community_log_identity()?;
     let svc = community_service()?;
-    let msg = svc
-        .get_message(message_id)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-    Ok(JsValue::from_str(&message_to_json(&msg).to_string()))
+    let update = match data["channel_id"].as_str() {
+        Some(channel_id) => svc.sync_channel_keys(&identity, channel_id),
+        None => {
+            let community_id = data["community_id"]
+                .as_str()
+                .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+            svc.sync_community_keys(&identity, community_id)
+        }
+    }
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(JsValue::from_str(&serde_json::json!(update).to_string()))
 }
 
-/// Edit a message.
+/// Rotate a channel's key and hand the new one to its readers.
 ///
-/// Takes JSON: { "message_id": "...", "new_content": "...", "editor_did": "..." }
-/// Returns JSON: { "success": true }
+/// Takes JSON: { "channel_id": "..." }
+/// Returns JSON: { "rotated": { channel_id: key_version }, "awaiting_key": ["did"], "relay_messages": [...] }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_message_edit(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_keys_rotate(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
-    let message_id = data["message_id"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-    let new_content = data["new_content"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing new_content"))?;
-    let editor_did = data["editor_did"]
+    let channel_id = data["channel_id"]
         .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing editor_did"))?;
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
 
+    let identity = community_log_identity()?;
     let svc = community_service()?;
-    svc.edit_message(message_id, new_content, editor_did)
+    let update = svc
+        .rotate_channel_key(&identity, channel_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "communityMessageEdited",
-            "message_id": message_id,
-        }),
-    );
-
-    Ok(JsValue::from_str("{\"success\":true}"))
+    Ok(JsValue::from_str(&serde_json::json!(update).to_string()))
 }
 
-/// Delete a message for everyone.
+/// Import channel keys received in a `community_channel_keys` envelope.
 ///
-/// Returns JSON: { "success": true }
+/// Takes JSON: { "grant": ChannelKeyGrant }
+/// Returns JSON: { "imported": [key_version] }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_message_delete(message_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_keys_receive(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let grant: crate::community::ChannelKeyGrant = serde_json::from_value(data["grant"].clone())
+        .map_err(|e| JsValue::from_str(&format!("Invalid grant: {}", e)))?;
+
+    let identity = community_log_identity()?;
     let svc = community_service()?;
-    svc.delete_message_for_everyone(message_id)
+    let imported = svc
+        .receive_channel_keys(&identity, &grant)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    emit_event(
-        "community",
-        &serde_json::json!({
-            "type": "communityMessageDeleted",
-            "message_id": message_id,
-        }),
-    );
+    if !imported.is_empty() {
+        emit_event(
+            "community",
+            &serde_json::json!({
+                "type": "channelKeysReceived",
+                "channel_id": grant.channel_id,
+                "key_versions": imported,
+            }),
+        );
+    }
 
-    Ok(JsValue::from_str("{\"success\":true}"))
+    Ok(JsValue::from_str(
+        &serde_json::json!({ "imported": imported }).to_string(),
+    ))
 }
 
-/// Delete a message for the current user only.
+/// Encrypt a message for an encrypted channel with its latest key.
 ///
-/// Takes JSON: { "message_id": "...", "member_did": "..." }
-/// Returns JSON: { "success": true }
+/// Takes JSON: { "channel_id": "...", "content": "..." }
+/// Returns JSON: { "content_encrypted_b64", "nonce", "key_version" }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_message_delete_for_me(json: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_keys_encrypt(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let channel_id = data["channel_id"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing channel_id"))?;
+    let content = data["content"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing content"))?;
+
+    let identity = community_log_identity()?;
+    let svc = community_service()?;
+    let encrypted = svc
+        .encrypt_channel_message(&identity, channel_id, content)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let json_result = serde_json::json!({
+        "content_encrypted_b64":
+            base64::engine::general_purpose::STANDARD.encode(&encrypted.content_encrypted),
+        "nonce": encrypted.nonce,
+        "key_version": encrypted.key_version,
+    });
+    Ok(JsValue::from_str(&json_result.to_string()))
+}
+
+/// Decrypt a stored message from an encrypted channel.
+///
+/// Takes JSON: { "message_id": "..." }
+/// Returns JSON: { "content": "..." }
+#[wasm_bindgen]
+pub fn umbra_wasm_community_keys_decrypt(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
         .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
 
     let message_id = data["message_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
-    let member_did = data["member_did"]
-        .as_str()
-        .ok_or_else(|| JsValue::from_str("Missing member_did"))?;
 
+    let identity = community_log_identity()?;
     let svc = community_service()?;
-    svc.delete_message_for_me(message_id, member_did)
+    let content = svc
+        .decrypt_channel_message(&identity, message_id)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    Ok(JsValue::from_str("{\"success\":true}"))
+    Ok(JsValue::from_str(
+        &serde_json::json!({ "content": content }).to_string(),
+    ))
 }
 
 // ============================================================================
@@ -8433,9 +10011,12 @@ pub fn umbra_wasm_community_unpin_message(json: &str) -> Result<JsValue, JsValue
     let message_id = data["message_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing message_id"))?;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.unpin_message(channel_id, message_id)
+    svc.unpin_message(channel_id, message_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
@@ -8693,6 +10274,60 @@ pub fn umbra_wasm_community_search(json: &str) -> Result<JsValue, JsValue> {
     ))
 }
 
+// ============================================================================
+// MESSAGE SEARCH (DMs, groups, communities)
+// ============================================================================
+
+/// Ranked full-text search across every DM, group, and community channel
+/// the current identity can read.
+///
+/// Takes JSON: { "query": "...", "sources": ["dm", "group", "community"], "limit": 50 }
+/// Returns JSON: [{ message_id, source, scope_id, community_id, sender_did,
+///                  created_at, snippet, rank }]
+#[wasm_bindgen]
+pub fn umbra_wasm_search(json: &str) -> Result<JsValue, JsValue> {
+    let data: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid JSON: {}", e)))?;
+
+    let query = data["query"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing query"))?;
+    let limit = data["limit"].as_u64().unwrap_or(50) as usize;
+
+    let mut sources = Vec::new();
+    if let Some(arr) = data["sources"].as_array() {
+        for value in arr {
+            let name = value.as_str().unwrap_or_default();
+            let source = crate::storage::SearchSource::parse(name).ok_or_else(|| {
+                JsValue::from_str(&format!("Unknown search source: {}", name))
+            })?;
+            sources.push(source);
+        }
+    }
+
+    let state = get_state()?;
+    let state = state.read();
+    let identity = state
+        .identity
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No identity loaded"))?;
+    let database = state
+        .database
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Database not initialized"))?;
+
+    let did = identity.did_string();
+    let visible_channels = CommunityService::new(database.clone())
+        .visible_channels(&did)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let hits = database
+        .search_messages(query, &did, &sources, &visible_channels, limit)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&hits).unwrap_or_default(),
+    ))
+}
+
 // ============================================================================
 // COMMUNITY — MODERATION (Phase 5)
 // ============================================================================
@@ -9162,9 +10797,12 @@ pub fn umbra_wasm_community_folder_list(json: &str) -> Result<JsValue, JsValue>
 ///
 /// Returns JSON: { "success": true }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_folder_delete(folder_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_folder_delete(
+    folder_id: &str,
+    actor_did: &str,
+) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    svc.delete_folder(folder_id)
+    svc.delete_folder(folder_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
     Ok(JsValue::from_str("{\"success\":true}"))
 }
@@ -9367,9 +11005,12 @@ pub fn umbra_wasm_community_emoji_rename(json: &str) -> Result<JsValue, JsValue>
     let new_name = data["new_name"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing new_name"))?;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.rename_emoji(emoji_id, new_name)
+    svc.rename_emoji(emoji_id, new_name, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     Ok(JsValue::from_str("{\"success\":true}"))
@@ -9469,9 +11110,12 @@ pub fn umbra_wasm_community_sticker_list(community_id: &str) -> Result<JsValue,
 ///
 /// Returns JSON: { "success": true }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_sticker_delete(sticker_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_sticker_delete(
+    sticker_id: &str,
+    actor_did: &str,
+) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    svc.delete_sticker(sticker_id)
+    svc.delete_sticker(sticker_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
     Ok(JsValue::from_str("{\"success\":true}"))
 }
@@ -9560,9 +11204,12 @@ pub fn umbra_wasm_community_sticker_pack_list(community_id: &str) -> Result<JsVa
 ///
 /// Returns JSON: { "success": true }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_sticker_pack_delete(pack_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_sticker_pack_delete(
+    pack_id: &str,
+    actor_did: &str,
+) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    svc.delete_sticker_pack(pack_id)
+    svc.delete_sticker_pack(pack_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
     Ok(JsValue::from_str("{\"success\":true}"))
 }
@@ -9582,9 +11229,12 @@ pub fn umbra_wasm_community_sticker_pack_rename(json: &str) -> Result<JsValue, J
     let new_name = data["new_name"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing new_name"))?;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.rename_sticker_pack(pack_id, new_name)
+    svc.rename_sticker_pack(pack_id, new_name, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
     Ok(JsValue::from_str("{\"success\":true}"))
 }
@@ -9674,9 +11324,12 @@ pub fn umbra_wasm_community_webhook_update(json: &str) -> Result<JsValue, JsValu
         .ok_or_else(|| JsValue::from_str("Missing webhook_id"))?;
     let name = data["name"].as_str();
     let avatar_url = data["avatar_url"].as_str();
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.update_webhook(webhook_id, name, avatar_url)
+    svc.update_webhook(webhook_id, name, avatar_url, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     Ok(JsValue::from_str("{\"success\":true}"))
@@ -9721,7 +11374,7 @@ pub fn umbra_wasm_community_webhook_delete(json: &str) -> Result<JsValue, JsValu
 ///
 /// Takes JSON: { "channel_id": "...", "target_type": "role"|"member",
 ///               "target_id": "...", "allow_bitfield": "...", "deny_bitfield": "...", "actor_did": "..." }
-/// Returns JSON: { "success": true }
+/// Returns JSON: { "success": true, "relay_messages": [...] }
 #[wasm_bindgen]
 pub fn umbra_wasm_community_channel_override_set(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
@@ -9757,7 +11410,10 @@ pub fn umbra_wasm_community_channel_override_set(json: &str) -> Result<JsValue,
     )
     .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    Ok(JsValue::from_str("{\"success\":true}"))
+    let relay_messages = community_rekey_channel(channel_id);
+    Ok(JsValue::from_str(
+        &serde_json::json!({"success": true, "relay_messages": relay_messages}).to_string(),
+    ))
 }
 
 /// Get all permission overrides for a channel.
@@ -9791,13 +11447,27 @@ pub fn umbra_wasm_community_channel_override_list(channel_id: &str) -> Result<Js
 
 /// Remove a permission override.
 ///
-/// Returns JSON: { "success": true }
+/// Returns JSON: { "success": true, "relay_messages": [...] }
 #[wasm_bindgen]
-pub fn umbra_wasm_community_channel_override_remove(override_id: &str) -> Result<JsValue, JsValue> {
+pub fn umbra_wasm_community_channel_override_remove(
+    override_id: &str,
+    actor_did: &str,
+) -> Result<JsValue, JsValue> {
     let svc = community_service()?;
-    svc.remove_channel_override(override_id)
+    let channel_id = svc
+        .db()
+        .get_channel_permission_override(override_id)
+        .ok()
+        .flatten()
+        .map(|o| o.channel_id);
+    svc.remove_channel_override(override_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
-    Ok(JsValue::from_str("{\"success\":true}"))
+    let relay_messages = channel_id
+        .map(|id| community_rekey_channel(&id))
+        .unwrap_or_default();
+    Ok(JsValue::from_str(
+        &serde_json::json!({"success": true, "relay_messages": relay_messages}).to_string(),
+    ))
 }
 
 // ============================================================================
@@ -9922,7 +11592,7 @@ pub fn umbra_wasm_community_role_update(json: &str) -> Result<JsValue, JsValue>
 /// Update a role's permission bitfield.
 ///
 /// Takes JSON: { "role_id": "...", "permissions_bitfield": "...", "actor_did": "..." }
-/// Returns JSON: { "success": true }
+/// Returns JSON: { "success": true, "relay_messages": [...] }
 #[wasm_bindgen]
 pub fn umbra_wasm_community_role_update_permissions(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
@@ -9950,13 +11620,19 @@ pub fn umbra_wasm_community_role_update_permissions(json: &str) -> Result<JsValu
         }),
     );
 
-    Ok(JsValue::from_str("{\"success\":true}"))
+    let relay_messages = match svc.db().get_community_role(role_id) {
+        Ok(Some(role)) => community_rekey(&role.community_id),
+        _ => Vec::new(),
+    };
+    Ok(JsValue::from_str(
+        &serde_json::json!({"success": true, "relay_messages": relay_messages}).to_string(),
+    ))
 }
 
 /// Delete a custom role.
 ///
 /// Takes JSON: { "role_id": "...", "actor_did": "..." }
-/// Returns JSON: { "success": true }
+/// Returns JSON: { "success": true, "relay_messages": [...] }
 #[wasm_bindgen]
 pub fn umbra_wasm_community_role_delete(json: &str) -> Result<JsValue, JsValue> {
     let data: serde_json::Value = serde_json::from_str(json)
@@ -9970,6 +11646,12 @@ pub fn umbra_wasm_community_role_delete(json: &str) -> Result<JsValue, JsValue>
         .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
+    let community_id = svc
+        .db()
+        .get_community_role(role_id)
+        .ok()
+        .flatten()
+        .map(|role| role.community_id);
     svc.delete_role(role_id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
@@ -9981,7 +11663,12 @@ pub fn umbra_wasm_community_role_delete(json: &str) -> Result<JsValue, JsValue>
         }),
     );
 
-    Ok(JsValue::from_str("{\"success\":true}"))
+    let relay_messages = community_id
+        .map(|id| community_rekey(&id))
+        .unwrap_or_default();
+    Ok(JsValue::from_str(
+        &serde_json::json!({"success": true, "relay_messages": relay_messages}).to_string(),
+    ))
 }
 
 // ============================================================================
@@ -10150,9 +11837,12 @@ pub fn umbra_wasm_community_category_reorder(json: &str) -> Result<JsValue, JsVa
         .iter()
         .filter_map(|v| v.as_str().map(|s| s.to_string()))
         .collect();
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.reorder_categories(space_id, &category_ids)
+    svc.reorder_categories(space_id, &category_ids, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     Ok(JsValue::from_str("{\"success\":true}"))
@@ -11049,9 +12739,12 @@ pub fn umbra_wasm_community_delete_folder(json: &str) -> Result<JsValue, JsValue
     let id = data["id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing id"))?;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let svc = community_service()?;
-    svc.delete_folder(id)
+    svc.delete_folder(id, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
@@ -12853,6 +14546,9 @@ pub fn umbra_wasm_community_seat_create_batch(json: &str) -> Result<JsValue, JsV
     let community_id = data["community_id"]
         .as_str()
         .ok_or_else(|| JsValue::from_str("Missing community_id"))?;
+    let actor_did = data["actor_did"]
+        .as_str()
+        .ok_or_else(|| JsValue::from_str("Missing actor_did"))?;
 
     let seats_json = data["seats"]
         .as_array()
@@ -12879,7 +14575,7 @@ pub fn umbra_wasm_community_seat_create_batch(json: &str) -> Result<JsValue, JsV
 
     let svc = community_service()?;
     let created = svc
-        .create_seats_batch(community_id, seat_inputs)
+        .create_seats_batch(community_id, seat_inputs, actor_did)
         .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
     emit_event(
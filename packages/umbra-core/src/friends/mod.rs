@@ -91,6 +91,18 @@
 //! │  • Automatically reject requests from blocked users                   │
 //! │  • Prevent messages from reaching the user                            │
 //! │                                                                         │
+//! │  5. Key Rotation                                                       │
+//! │  ───────────────                                                        │
+//! │  A friend's X25519 key is only replaced by a KeyRotation signed with  │
+//! │  their (unchanged) Ed25519 key. The old key is kept for the grace     │
+//! │  window so messages already in flight still decrypt.                  │
+//! │                                                                         │
+//! │  6. Verification                                                       │
+//! │  ───────────────                                                        │
+//! │  Users compare safety numbers (or scan each other's QR code) out of   │
+//! │  band and mark the friend verified. Any key change clears the flag,   │
+//! │  so a rotated or swapped key has to be verified again.                │
+//! │                                                                         │
 //! └─────────────────────────────────────────────────────────────────────────┘
 //! ```
 
@@ -99,10 +111,10 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::crypto::{sign, verify, Signature};
+use crate::crypto::{sign, verify, PublicKey, SafetyNumber, Signature};
 use crate::error::{Error, Result};
-use crate::identity::{Identity, PublicIdentity};
-use crate::storage::{Database, FriendRequestRecord};
+use crate::identity::{within_grace_period, Identity, KeyRotation, PublicIdentity};
+use crate::storage::{Database, FriendRecord, FriendRequestRecord};
 
 /// Maximum age of a friend request before it's considered stale (7 days)
 pub const MAX_REQUEST_AGE_SECS: i64 = 7 * 24 * 60 * 60;
@@ -350,6 +362,18 @@ pub struct Friend {
     pub online: bool,
     /// Last seen timestamp
     pub last_seen: Option<i64>,
+    /// X25519 key replaced by their latest rotation
+    #[serde(default)]
+    pub previous_encryption_public_key: Option<[u8; 32]>,
+    /// When they last rotated their encryption key
+    #[serde(default)]
+    pub key_rotated_at: Option<i64>,
+    /// Whether we verified their safety number out of band
+    #[serde(default)]
+    pub verified: bool,
+    /// When we verified their safety number
+    #[serde(default)]
+    pub verified_at: Option<i64>,
 }
 
 impl Friend {
@@ -365,9 +389,73 @@ impl Friend {
             added_at: crate::time::now_timestamp(),
             online: false,
             last_seen: None,
+            previous_encryption_public_key: None,
+            key_rotated_at: None,
+            verified: false,
+            verified_at: None,
+        }
+    }
+
+    /// Create from a stored friend record
+    pub fn from_record(record: FriendRecord) -> Result<Self> {
+        let signing_key = hex::decode(&record.signing_key)
+            .map_err(|e| Error::DatabaseError(format!("Invalid signing key: {}", e)))?;
+        let encryption_key = hex::decode(&record.encryption_key)
+            .map_err(|e| Error::DatabaseError(format!("Invalid encryption key: {}", e)))?;
+
+        let signing_key: [u8; 32] = signing_key
+            .try_into()
+            .map_err(|_| Error::DatabaseError("Invalid signing key length".into()))?;
+        let encryption_key: [u8; 32] = encryption_key
+            .try_into()
+            .map_err(|_| Error::DatabaseError("Invalid encryption key length".into()))?;
+        let previous_encryption_key = record
+            .previous_encryption_key
+            .as_deref()
+            .and_then(|k| hex::decode(k).ok())
+            .and_then(|k| k.try_into().ok());
+
+        Ok(Self {
+            did: record.did,
+            display_name: record.display_name,
+            status: record.status,
+            avatar: None,
+            encryption_public_key: encryption_key,
+            signing_public_key: signing_key,
+            added_at: record.created_at,
+            online: false,
+            last_seen: None,
+            previous_encryption_public_key: previous_encryption_key,
+            key_rotated_at: record.key_rotated_at,
+            verified: record.verified,
+            verified_at: record.verified_at,
+        })
+    }
+
+    /// Their previous encryption key, while it is inside the rotation
+    /// grace window
+    pub fn previous_encryption_key(&self) -> Option<[u8; 32]> {
+        match (self.previous_encryption_public_key, self.key_rotated_at) {
+            (Some(key), Some(rotated_at)) if within_grace_period(rotated_at) => Some(key),
+            _ => None,
         }
     }
 
+    /// Their current public keys
+    pub fn public_keys(&self) -> PublicKey {
+        PublicKey::from_bytes(self.signing_public_key, self.encryption_public_key)
+    }
+
+    /// The safety number between our identity and this friend
+    pub fn safety_number(&self, identity: &Identity) -> SafetyNumber {
+        SafetyNumber::compute(
+            &identity.did_string(),
+            &identity.public_keys(),
+            &self.did,
+            &self.public_keys(),
+        )
+    }
+
     /// Verify a signature from this friend
     pub fn verify_signature(&self, message: &[u8], signature: &Signature) -> Result<()> {
         verify(&self.signing_public_key, message, signature)
@@ -466,29 +554,7 @@ impl FriendsService {
         let mut friends = Vec::new();
 
         for record in friend_records {
-            let signing_key = hex::decode(&record.signing_key)
-                .map_err(|e| Error::DatabaseError(format!("Invalid signing key: {}", e)))?;
-            let encryption_key = hex::decode(&record.encryption_key)
-                .map_err(|e| Error::DatabaseError(format!("Invalid encryption key: {}", e)))?;
-
-            let signing_key: [u8; 32] = signing_key
-                .try_into()
-                .map_err(|_| Error::DatabaseError("Invalid signing key length".into()))?;
-            let encryption_key: [u8; 32] = encryption_key
-                .try_into()
-                .map_err(|_| Error::DatabaseError("Invalid encryption key length".into()))?;
-
-            friends.push(Friend {
-                did: record.did,
-                display_name: record.display_name,
-                status: record.status,
-                avatar: None,
-                encryption_public_key: encryption_key,
-                signing_public_key: signing_key,
-                added_at: record.created_at,
-                online: false,
-                last_seen: None,
-            });
+            friends.push(Friend::from_record(record)?);
         }
 
         *self.friends_cache.write() = friends;
@@ -795,6 +861,80 @@ impl FriendsService {
         self.database.is_blocked(did)
     }
 
+    /// Apply a friend's signed encryption key rotation
+    ///
+    /// The rotation must verify against the friend's signing key. Their old
+    /// key stays usable for the grace window. Replays of the same or older
+    /// rotations are ignored. A key change clears the friend's verification.
+    pub fn handle_key_rotation(&self, rotation: &KeyRotation) -> Result<Friend> {
+        let mut friend = self.get_friend(&rotation.did).ok_or(Error::NotFriends)?;
+
+        if !apply_key_rotation(&self.database, &friend.signing_public_key, rotation)? {
+            return Ok(friend);
+        }
+
+        if friend.verified {
+            tracing::warn!(
+                "Verified friend {} changed their keys; verification cleared",
+                friend.did
+            );
+        }
+        friend.previous_encryption_public_key = Some(friend.encryption_public_key);
+        friend.encryption_public_key = rotation.new_encryption_key;
+        friend.key_rotated_at = Some(rotation.rotated_at);
+        friend.verified = false;
+        friend.verified_at = None;
+        self.update_cached_friend(&friend);
+
+        tracing::info!("Friend {} rotated their encryption key", friend.did);
+        Ok(friend)
+    }
+
+    /// Mark a friend verified after comparing safety numbers
+    ///
+    /// If the friend's QR payload was scanned, it must match our safety
+    /// number; without one the user confirmed the digits by hand.
+    pub fn verify_friend(&self, did: &str, qr_payload: Option<&str>) -> Result<Friend> {
+        let mut friend = self.get_friend(did).ok_or(Error::NotFriends)?;
+
+        if let Some(payload) = qr_payload {
+            if !friend
+                .safety_number(&self.identity)
+                .verify_qr_payload(payload)?
+            {
+                return Err(Error::VerificationFailed);
+            }
+        }
+
+        self.database.set_friend_verified(did, true)?;
+        friend.verified = true;
+        friend.verified_at = Some(crate::time::now_timestamp());
+        self.update_cached_friend(&friend);
+        Ok(friend)
+    }
+
+    /// Clear a friend's verification
+    pub fn unverify_friend(&self, did: &str) -> Result<Friend> {
+        let mut friend = self.get_friend(did).ok_or(Error::NotFriends)?;
+
+        self.database.set_friend_verified(did, false)?;
+        friend.verified = false;
+        friend.verified_at = None;
+        self.update_cached_friend(&friend);
+        Ok(friend)
+    }
+
+    fn update_cached_friend(&self, friend: &Friend) {
+        if let Some(cached) = self
+            .friends_cache
+            .write()
+            .iter_mut()
+            .find(|f| f.did == friend.did)
+        {
+            *cached = friend.clone();
+        }
+    }
+
     /// Get all friends
     pub fn get_friends(&self) -> Vec<Friend> {
         self.friends_cache.read().clone()
@@ -853,6 +993,23 @@ impl FriendsService {
     }
 }
 
+/// Verify a friend's key rotation and store the new key
+///
+/// Returns false if the rotation was already applied (or superseded).
+/// Shared by [`FriendsService::handle_key_rotation`] and the FFI layer.
+pub fn apply_key_rotation(
+    database: &Database,
+    signing_key: &[u8; 32],
+    rotation: &KeyRotation,
+) -> Result<bool> {
+    rotation.verify(signing_key)?;
+    database.rotate_friend_encryption_key(
+        &rotation.did,
+        &rotation.new_encryption_key,
+        rotation.rotated_at,
+    )
+}
+
 // ============================================================================
 // TESTS
 // ============================================================================
@@ -1089,4 +1246,107 @@ mod tests {
 
         assert!(!service.is_blocked(bad_did).unwrap());
     }
+
+    #[tokio::test]
+    async fn test_friends_service_key_rotation() {
+        let mut alice = create_test_identity("Alice");
+        let bob = Arc::new(create_test_identity("Bob"));
+        let mallory = create_test_identity("Mallory");
+        let database = Arc::new(Database::open(None).await.unwrap());
+
+        let old_key = alice.keypair().encryption.public_bytes();
+        database
+            .add_friend(
+                &alice.did_string(),
+                "Alice",
+                &alice.keypair().signing.public_bytes(),
+                &old_key,
+                None,
+            )
+            .unwrap();
+        let service = FriendsService::new(bob, database.clone());
+        service.load().unwrap();
+
+        let rotation = alice.rotate_encryption_key().unwrap();
+
+        // A rotation to a key Alice didn't sign is rejected
+        let mut forged = rotation.clone();
+        forged.new_encryption_key = mallory.keypair().encryption.public_bytes();
+        assert!(service.handle_key_rotation(&forged).is_err());
+
+        let friend = service.handle_key_rotation(&rotation).unwrap();
+        assert_eq!(friend.encryption_public_key, rotation.new_encryption_key);
+        assert_eq!(friend.previous_encryption_key(), Some(old_key));
+
+        // Persisted, and replays are harmless
+        let record = database.get_friend(&alice.did_string()).unwrap().unwrap();
+        assert_eq!(
+            record.encryption_key,
+            hex::encode(rotation.new_encryption_key)
+        );
+        assert_eq!(record.previous_encryption_key, Some(hex::encode(old_key)));
+        let again = service.handle_key_rotation(&rotation).unwrap();
+        assert_eq!(again.previous_encryption_key(), Some(old_key));
+    }
+
+    #[tokio::test]
+    async fn test_friends_service_verification() {
+        let mut alice = create_test_identity("Alice");
+        let bob = Arc::new(create_test_identity("Bob"));
+        let database = Arc::new(Database::open(None).await.unwrap());
+
+        database
+            .add_friend(
+                &alice.did_string(),
+                "Alice",
+                &alice.keypair().signing.public_bytes(),
+                &alice.keypair().encryption.public_bytes(),
+                None,
+            )
+            .unwrap();
+        let service = FriendsService::new(bob.clone(), database.clone());
+        service.load().unwrap();
+
+        // Alice's view of the safety number, as shown in her QR code
+        let bob_friend = Friend::from_public_identity(&bob.public_identity());
+        let alice_view = bob_friend.safety_number(&alice);
+        let friend = service.get_friend(&alice.did_string()).unwrap();
+        assert!(!friend.verified);
+        assert_eq!(friend.safety_number(&bob).digits(), alice_view.digits());
+
+        // A code made against someone else's keys is rejected
+        let mallory = create_test_identity("Mallory");
+        let wrong = bob_friend.safety_number(&mallory);
+        assert!(matches!(
+            service.verify_friend(&alice.did_string(), Some(&wrong.qr_payload())),
+            Err(Error::VerificationFailed)
+        ));
+
+        let friend = service
+            .verify_friend(&alice.did_string(), Some(&alice_view.qr_payload()))
+            .unwrap();
+        assert!(friend.verified);
+        assert!(friend.verified_at.is_some());
+        assert!(
+            database
+                .get_friend(&alice.did_string())
+                .unwrap()
+                .unwrap()
+                .verified
+        );
+
+        // A key change clears verification and changes the number
+        let rotation = alice.rotate_encryption_key().unwrap();
+        let friend = service.handle_key_rotation(&rotation).unwrap();
+        assert!(!friend.verified);
+        assert!(friend.verified_at.is_none());
+        assert_ne!(friend.safety_number(&bob).digits(), alice_view.digits());
+        assert!(
+            !database
+                .get_friend(&alice.did_string())
+                .unwrap()
+                .unwrap()
+                .verified
+        );
+    }
 }
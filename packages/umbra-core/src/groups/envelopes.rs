@@ -0,0 +1,657 @@
+//! # Group Envelopes
+//!
+//! Relay envelopes group members exchange, and handling the ones we receive.
+
+use serde::{Deserialize, Serialize};
+
+use super::{GroupMessage, GroupRole, WrappedGroupKey};
+use crate::crypto::CommitOutcome;
+use crate::error::{Error, Result};
+use crate::network::relay_client::RelayMessage;
+use crate::storage::GroupInviteRecord;
+
+/// Envelope format version
+const ENVELOPE_VERSION: u32 = 1;
+
+/// A member listed in an invite
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteMember {
+    /// Member DID
+    pub did: String,
+    /// Display name
+    pub display_name: Option<String>,
+    /// Role (`"admin"` or `"member"`)
+    pub role: String,
+}
+
+/// `group_invite`: an invitation, with the group key encrypted for the invitee
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupInvitePayload {
+    /// Invite ID
+    pub invite_id: String,
+    /// Group ID
+    pub group_id: String,
+    /// Group name
+    pub group_name: String,
+    /// Group description
+    pub description: Option<String>,
+    /// Inviter DID
+    pub inviter_did: String,
+    /// Inviter display name
+    pub inviter_name: String,
+    /// Group key encrypted for the invitee (hex encoded)
+    pub encrypted_group_key: String,
+    /// Nonce of `encrypted_group_key` (hex encoded)
+    pub nonce: String,
+    /// Version of the group key
+    pub key_version: i32,
+    /// Current members
+    pub members: Vec<InviteMember>,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+impl GroupInvitePayload {
+    /// The invite as we store it, pending
+    pub fn to_record(&self) -> Result<GroupInviteRecord> {
+        Ok(GroupInviteRecord {
+            id: self.invite_id.clone(),
+            group_id: self.group_id.clone(),
+            group_name: self.group_name.clone(),
+            description: self.description.clone(),
+            inviter_did: self.inviter_did.clone(),
+            inviter_name: self.inviter_name.clone(),
+            encrypted_group_key: self.encrypted_group_key.clone(),
+            nonce: self.nonce.clone(),
+            members_json: serde_json::to_string(&self.members)?,
+            status: "pending".to_string(),
+            created_at: self.timestamp,
+        })
+    }
+}
+
+/// `group_invite_accept`: the invitee joined
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupInviteAcceptPayload {
+    /// Invite ID
+    pub invite_id: String,
+    /// Group ID
+    pub group_id: String,
+    /// Invitee DID
+    pub accepter_did: String,
+    /// Invitee display name
+    pub accepter_name: String,
+    /// Key package for the group's MLS session (hex encoded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_package: Option<String>,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+/// `group_invite_decline`: the invitee declined
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupInviteDeclinePayload {
+    /// Invite ID
+    pub invite_id: String,
+    /// Group ID
+    pub group_id: String,
+    /// Invitee DID
+    pub decliner_did: String,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+/// `group_message`: an encrypted group message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMessagePayload {
+    /// Message ID
+    pub message_id: String,
+    /// Group ID
+    pub group_id: String,
+    /// Sender's conversation ID
+    pub conversation_id: String,
+    /// Sender DID
+    pub sender_did: String,
+    /// Ciphertext (hex encoded)
+    pub ciphertext_hex: String,
+    /// Nonce (hex encoded); empty for MLS messages
+    pub nonce_hex: String,
+    /// Key version the message was encrypted with
+    pub key_version: i32,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+/// `group_key_rotation`: a new shared key, encrypted for the recipient
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupKeyRotationPayload {
+    /// Group ID
+    pub group_id: String,
+    /// Version of the new key
+    pub key_version: i32,
+    /// The key encrypted for the recipient (hex encoded)
+    pub encrypted_key: String,
+    /// Nonce (hex encoded)
+    pub nonce: String,
+    /// Sender DID
+    pub sender_did: String,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+/// `group_member_removed`: an admin removed a member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMemberRemovedPayload {
+    /// Group ID
+    pub group_id: String,
+    /// DID of the removed member
+    pub removed_did: String,
+    /// DID of the admin who removed them
+    pub removed_by: String,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+/// `group_mls_commit`: moves the group's MLS session to a new epoch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMlsCommitPayload {
+    /// Group ID
+    pub group_id: String,
+    /// Committer DID
+    pub sender_did: String,
+    /// The commit (hex encoded)
+    pub commit: String,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+/// `group_mls_welcome`: brings an added member into the group's MLS session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMlsWelcomePayload {
+    /// Group ID
+    pub group_id: String,
+    /// Committer DID
+    pub sender_did: String,
+    /// The Welcome (hex encoded)
+    pub welcome: String,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+/// A group relay envelope: `{ "envelope": <type>, "version": 1, "payload": {...} }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "envelope", content = "payload")]
+pub enum GroupEnvelope {
+    /// `group_invite`
+    #[serde(rename = "group_invite")]
+    Invite(GroupInvitePayload),
+    /// `group_invite_accept`
+    #[serde(rename = "group_invite_accept")]
+    InviteAccept(GroupInviteAcceptPayload),
+    /// `group_invite_decline`
+    #[serde(rename = "group_invite_decline")]
+    InviteDecline(GroupInviteDeclinePayload),
+    /// `group_message`
+    #[serde(rename = "group_message")]
+    Message(GroupMessagePayload),
+    /// `group_key_rotation`
+    #[serde(rename = "group_key_rotation")]
+    KeyRotation(GroupKeyRotationPayload),
+    /// `group_member_removed`
+    #[serde(rename = "group_member_removed")]
+    MemberRemoved(GroupMemberRemovedPayload),
+    /// `group_mls_commit`
+    #[serde(rename = "group_mls_commit")]
+    MlsCommit(GroupMlsCommitPayload),
+    /// `group_mls_welcome`
+    #[serde(rename = "group_mls_welcome")]
+    MlsWelcome(GroupMlsWelcomePayload),
+}
+
+impl GroupEnvelope {
+    /// Serialize for relay delivery
+    pub fn to_payload(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        value["version"] = ENVELOPE_VERSION.into();
+        Ok(value.to_string())
+    }
+
+    /// Parse a relay payload; fails if it isn't a group envelope
+    pub fn from_payload(payload: &str) -> Result<Self> {
+        serde_json::from_str(payload).map_err(|e| Error::DeserializationError(e.to_string()))
+    }
+
+    /// DID the envelope claims to come from
+    pub fn sender_did(&self) -> &str {
+        match self {
+            GroupEnvelope::Invite(p) => &p.inviter_did,
+            GroupEnvelope::InviteAccept(p) => &p.accepter_did,
+            GroupEnvelope::InviteDecline(p) => &p.decliner_did,
+            GroupEnvelope::Message(p) => &p.sender_did,
+            GroupEnvelope::KeyRotation(p) => &p.sender_did,
+            GroupEnvelope::MemberRemoved(p) => &p.removed_by,
+            GroupEnvelope::MlsCommit(p) => &p.sender_did,
+            GroupEnvelope::MlsWelcome(p) => &p.sender_did,
+        }
+    }
+}
+
+/// What a received envelope changed
+#[derive(Debug, Clone)]
+pub enum GroupEvent {
+    /// We were invited to a group; the invite is stored as pending
+    InviteReceived(GroupInviteRecord),
+    /// Someone we invited joined
+    MemberJoined {
+        /// Group ID
+        group_id: String,
+        /// New member's DID
+        member_did: String,
+    },
+    /// Someone we invited declined
+    InviteDeclined {
+        /// Group ID
+        group_id: String,
+        /// Invitee DID
+        member_did: String,
+    },
+    /// A group message
+    Message(GroupMessage),
+    /// The group's shared key was rotated
+    KeyRotated {
+        /// Group ID
+        group_id: String,
+        /// New key version
+        key_version: i32,
+    },
+    /// A member was removed
+    MemberRemoved {
+        /// Group ID
+        group_id: String,
+        /// Removed member's DID
+        member_did: String,
+    },
+    /// The group's MLS session moved to a new epoch (or we joined it)
+    EpochChanged {
+        /// Group ID
+        group_id: String,
+        /// New epoch
+        epoch: u64,
+    },
+    /// We were removed; our copy of the group is gone
+    RemovedFromGroup {
+        /// Group ID
+        group_id: String,
+    },
+}
+
+/// Result of handling a received envelope
+#[derive(Debug, Clone)]
+pub struct ReceivedEnvelope {
+    /// What changed
+    pub event: GroupEvent,
+    /// Envelopes to send in response
+    pub relay_messages: Vec<RelayMessage>,
+}
+
+impl From<GroupEvent> for ReceivedEnvelope {
+    fn from(event: GroupEvent) -> Self {
+        Self {
+            event,
+            relay_messages: Vec::new(),
+        }
+    }
+}
+
+impl super::GroupService {
+    fn require_sender_admin(&self, group_id: &str, did: &str) -> Result<()> {
+        match self.member_role(group_id, did)? {
+            Some(GroupRole::Admin) => Ok(()),
+            _ => Err(Error::InsufficientPermissions(format!(
+                "{} is not an admin of this group",
+                did
+            ))),
+        }
+    }
+
+    /// Handle an envelope that arrived from `from_did` (the relay's
+    /// authenticated sender), which must match the DID the envelope claims.
+    ///
+    /// Removals and commits are only taken from group admins. An accept
+    /// from an invitee adds them to the group's MLS session, so the result
+    /// may carry envelopes to send.
+    pub fn receive(&self, from_did: &str, envelope: GroupEnvelope) -> Result<ReceivedEnvelope> {
+        if envelope.sender_did() != from_did {
+            return Err(Error::ProtocolError(format!(
+                "Envelope from {} claims to be from {}",
+                from_did,
+                envelope.sender_did()
+            )));
+        }
+
+        match envelope {
+            GroupEnvelope::Invite(payload) => {
+                let invite = payload.to_record()?;
+                self.store_invite(&invite)?;
+                Ok(GroupEvent::InviteReceived(invite).into())
+            }
+            GroupEnvelope::InviteAccept(payload) => {
+                self.get_group(&payload.group_id)?;
+                self.add_member(
+                    &payload.group_id,
+                    &payload.accepter_did,
+                    Some(&payload.accepter_name),
+                    GroupRole::Member,
+                )?;
+                let relay_messages = match &payload.key_package {
+                    Some(key_package) if self.uses_mls(&payload.group_id)? => {
+                        self.add_mls_member(&payload.group_id, &payload.accepter_did, key_package)?
+                            .relay_messages
+                    }
+                    _ => Vec::new(),
+                };
+                Ok(ReceivedEnvelope {
+                    event: GroupEvent::MemberJoined {
+                        group_id: payload.group_id,
+                        member_did: payload.accepter_did,
+                    },
+                    relay_messages,
+                })
+            }
+            GroupEnvelope::InviteDecline(payload) => Ok(GroupEvent::InviteDeclined {
+                group_id: payload.group_id,
+                member_did: payload.decliner_did,
+            }
+            .into()),
+            GroupEnvelope::Message(payload) => {
+                Ok(GroupEvent::Message(self.receive_message(&payload)?).into())
+            }
+            GroupEnvelope::KeyRotation(payload) => {
+                self.require_sender_admin(&payload.group_id, &payload.sender_did)?;
+                let wrapped = WrappedGroupKey {
+                    encrypted_key: payload.encrypted_key,
+                    nonce: payload.nonce,
+                };
+                self.import_key(
+                    &payload.group_id,
+                    payload.key_version,
+                    &wrapped,
+                    &payload.sender_did,
+                )?;
+                Ok(GroupEvent::KeyRotated {
+                    group_id: payload.group_id,
+                    key_version: payload.key_version,
+                }
+                .into())
+            }
+            GroupEnvelope::MemberRemoved(payload) => {
+                self.require_sender_admin(&payload.group_id, &payload.removed_by)?;
+                if payload.removed_did == self.our_did() {
+                    self.leave_group(&payload.group_id)?;
+                    return Ok(GroupEvent::RemovedFromGroup {
+                        group_id: payload.group_id,
+                    }
+                    .into());
+                }
+                self.remove_member(&payload.group_id, &payload.removed_did)?;
+                Ok(GroupEvent::MemberRemoved {
+                    group_id: payload.group_id,
+                    member_did: payload.removed_did,
+                }
+                .into())
+            }
+            GroupEnvelope::MlsCommit(payload) => {
+                self.require_sender_admin(&payload.group_id, &payload.sender_did)?;
+                match self.process_commit(&payload.group_id, &payload.commit)? {
+                    CommitOutcome::Applied { .. } => {
+                        let epoch = crate::messaging::mls::load(self.db(), &payload.group_id)?
+                            .map(|group| group.epoch())
+                            .unwrap_or_default();
+                        Ok(GroupEvent::EpochChanged {
+                            group_id: payload.group_id,
+                            epoch,
+                        }
+                        .into())
+                    }
+                    CommitOutcome::Removed { .. } => {
+                        self.leave_group(&payload.group_id)?;
+                        Ok(GroupEvent::RemovedFromGroup {
+                            group_id: payload.group_id,
+                        }
+                        .into())
+                    }
+                }
+            }
+            GroupEnvelope::MlsWelcome(payload) => {
+                let group = self.process_welcome(&payload.welcome)?;
+                if group.group_id() != payload.group_id {
+                    return Err(Error::ProtocolError(format!(
+                        "Welcome for group {} arrived as {}",
+                        group.group_id(),
+                        payload.group_id
+                    )));
+                }
+                Ok(GroupEvent::EpochChanged {
+                    group_id: payload.group_id,
+                    epoch: group.epoch(),
+                }
+                .into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groups::GroupService;
+    use crate::identity::Identity;
+    use crate::storage::Database;
+    use std::sync::Arc;
+
+    async fn service(name: &str) -> GroupService {
+        let (identity, _) = Identity::create(name.to_string()).unwrap();
+        let db = Database::open(None).await.unwrap();
+        GroupService::new(Arc::new(identity), Arc::new(db))
+    }
+
+    fn befriend(a: &GroupService, b: &GroupService) {
+        for (me, them) in [(a, b), (b, a)] {
+            let keypair = them.identity().keypair();
+            me.db()
+                .add_friend(
+                    &them.our_did(),
+                    &them.identity().profile().display_name,
+                    &keypair.signing.public_bytes(),
+                    &keypair.encryption.public_bytes(),
+                    None,
+                )
+                .unwrap();
+        }
+    }
+
+    /// Deliver every message addressed to `to`, returning the events
+    fn deliver(
+        from: &GroupService,
+        to: &GroupService,
+        messages: &[RelayMessage],
+    ) -> Vec<ReceivedEnvelope> {
+        messages
+            .iter()
+            .filter(|m| m.to_did == to.our_did())
+            .map(|m| {
+                let envelope = GroupEnvelope::from_payload(&m.payload).unwrap();
+                to.receive(&from.our_did(), envelope).unwrap()
+            })
+            .collect()
+    }
+
+    /// Invite `member` to `admin`'s group and bring them into its MLS session
+    fn join(admin: &GroupService, member: &GroupService, group_id: &str) {
+        let invite = admin.invite_member(group_id, &member.our_did()).unwrap();
+        deliver(admin, member, &invite.relay_messages);
+        member.accept_invite(&invite.invite_id).unwrap();
+        let accept = member.invite_accept_envelope(&invite.invite_id).unwrap();
+        let joined = deliver(member, admin, &[accept]);
+        deliver(admin, member, &joined[0].relay_messages);
+    }
+
+    #[tokio::test]
+    async fn test_invite_join_and_message() {
+        let alice = service("Alice").await;
+        let bob = service("Bob").await;
+        befriend(&alice, &bob);
+
+        let group = alice.create_group("Team", None).unwrap();
+        let invite = alice.invite_member(&group.id, &bob.our_did()).unwrap();
+
+        let received = deliver(&alice, &bob, &invite.relay_messages);
+        assert!(
+            matches!(&received[0].event, GroupEvent::InviteReceived(i) if i.group_id == group.id)
+        );
+        assert_eq!(bob.pending_invites().unwrap().len(), 1);
+
+        bob.accept_invite(&invite.invite_id).unwrap();
+        let accept = bob.invite_accept_envelope(&invite.invite_id).unwrap();
+        let received = deliver(&bob, &alice, &[accept]);
+        assert!(matches!(received[0].event, GroupEvent::MemberJoined { .. }));
+        assert_eq!(alice.members(&group.id).unwrap().len(), 2);
+
+        // Alice's commit brings Bob into the MLS session
+        let received = deliver(&alice, &bob, &received[0].relay_messages);
+        assert!(matches!(
+            received[0].event,
+            GroupEvent::EpochChanged { epoch: 1, .. }
+        ));
+
+        let sent = alice
+            .send_message(
+                &group.id,
+                &crate::groups::conversation_id(&group.id),
+                "hello",
+            )
+            .unwrap();
+        let received = deliver(&alice, &bob, &sent.relay_messages);
+        match &received[0].event {
+            GroupEvent::Message(message) => {
+                assert_eq!(message.text, "hello");
+                assert_eq!(message.sender_did, alice.our_did());
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // Removing Bob re-keys the group and drops his copy of it
+        let removal = alice
+            .remove_member_with_rotation(&group.id, &bob.our_did())
+            .unwrap();
+        assert_eq!(removal.epoch, Some(2));
+        let received = deliver(&alice, &bob, &removal.relay_messages);
+        assert!(matches!(
+            received[0].event,
+            GroupEvent::RemovedFromGroup { .. }
+        ));
+        assert!(bob.get_group(&group.id).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_receive_checks_sender() {
+        let alice = service("Alice").await;
+        let bob = service("Bob").await;
+        let mallory = service("Mallory").await;
+        befriend(&alice, &bob);
+        befriend(&alice, &mallory);
+
+        let group = alice.create_group("Team", None).unwrap();
+        let invite = alice.invite_member(&group.id, &bob.our_did()).unwrap();
+        let envelope = GroupEnvelope::from_payload(&invite.relay_messages[0].payload).unwrap();
+
+        // The relay says Mallory sent it, but it claims to be from Alice
+        assert!(bob.receive(&mallory.our_did(), envelope).is_err());
+
+        // Only admins can remove members
+        bob.receive(
+            &alice.our_did(),
+            GroupEnvelope::from_payload(&invite.relay_messages[0].payload).unwrap(),
+        )
+        .unwrap();
+        bob.accept_invite(&invite.invite_id).unwrap();
+        let removal = GroupEnvelope::MemberRemoved(GroupMemberRemovedPayload {
+            group_id: group.id.clone(),
+            removed_did: bob.our_did(),
+            removed_by: mallory.our_did(),
+            timestamp: 0,
+        });
+        assert!(bob.receive(&mallory.our_did(), removal).is_err());
+        assert!(bob.get_group(&group.id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_decline_invite() {
+        let alice = service("Alice").await;
+        let bob = service("Bob").await;
+        befriend(&alice, &bob);
+
+        let group = alice.create_group("Team", None).unwrap();
+        let invite = alice.invite_member(&group.id, &bob.our_did()).unwrap();
+        deliver(&alice, &bob, &invite.relay_messages);
+
+        bob.decline_invite(&invite.invite_id).unwrap();
+        assert!(bob.pending_invites().unwrap().is_empty());
+        assert!(matches!(
+            bob.get_group(&group.id),
+            Err(Error::GroupNotFound)
+        ));
+
+        let decline = bob.invite_decline_envelope(&invite.invite_id).unwrap();
+        let received = deliver(&bob, &alice, &[decline]);
+        assert!(matches!(
+            &received[0].event,
+            GroupEvent::InviteDeclined { member_did, .. } if *member_did == bob.our_did()
+        ));
+        assert_eq!(alice.members(&group.id).unwrap().len(), 1);
+
+        assert!(matches!(
+            bob.accept_invite("no-such-invite"),
+            Err(Error::InviteNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_key_update_moves_epoch() {
+        let alice = service("Alice").await;
+        let bob = service("Bob").await;
+        befriend(&alice, &bob);
+        let group = alice.create_group("Team", None).unwrap();
+        join(&alice, &bob, &group.id);
+
+        let update = alice.update_mls_keys(&group.id).unwrap();
+        assert_eq!(update.epoch, 2);
+        let received = deliver(&alice, &bob, &update.relay_messages);
+        assert!(matches!(
+            received[0].event,
+            GroupEvent::EpochChanged { epoch: 2, .. }
+        ));
+
+        // Both sides encrypt and decrypt in the new epoch
+        let conversation = crate::groups::conversation_id(&group.id);
+        let sent = bob.send_message(&group.id, &conversation, "hi").unwrap();
+        let received = deliver(&bob, &alice, &sent.relay_messages);
+        assert!(matches!(&received[0].event, GroupEvent::Message(m) if m.text == "hi"));
+
+        // A tampered envelope doesn't decrypt
+        let sent = alice.send_message(&group.id, &conversation, "hey").unwrap();
+        let mut payload = match GroupEnvelope::from_payload(&sent.relay_messages[0].payload) {
+            Ok(GroupEnvelope::Message(payload)) => payload,
+            other => panic!("unexpected envelope: {:?}", other),
+        };
+        let last = payload.ciphertext_hex.pop().unwrap();
+        payload
+            .ciphertext_hex
+            .push(if last == '0' { '1' } else { '0' });
+        assert!(bob
+            .receive(&alice.our_did(), GroupEnvelope::Message(payload))
+            .is_err());
+    }
+}
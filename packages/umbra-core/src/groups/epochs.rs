@@ -0,0 +1,109 @@
+//! # Key Epochs
+//!
+//! MLS epoch changes: adding members from their key packages, refreshing
+//! our keys, and applying what the admin commits.
+
+use super::{GroupEnvelope, GroupMlsCommitPayload, GroupMlsWelcomePayload, RelayMessage};
+use crate::crypto::{CommitOutcome, MlsGroup};
+use crate::error::Result;
+use crate::messaging::mls::{self, MembershipChange};
+
+/// Result of a commit we made
+#[derive(Debug, Clone)]
+pub struct EpochChange {
+    /// Epoch the group moved to
+    pub epoch: u64,
+    /// The commit for the other members, and the Welcome for added ones
+    pub relay_messages: Vec<RelayMessage>,
+}
+
+impl super::GroupService {
+    /// A `group_mls_commit` envelope for `commit` (hex encoded).
+    pub(crate) fn mls_commit_envelope(&self, group_id: &str, commit: &str) -> Result<String> {
+        GroupEnvelope::MlsCommit(GroupMlsCommitPayload {
+            group_id: group_id.to_string(),
+            sender_did: self.our_did(),
+            commit: commit.to_string(),
+            timestamp: crate::time::now_timestamp_millis(),
+        })
+        .to_payload()
+    }
+
+    /// The commit goes to every other member of the previous epoch, the
+    /// Welcome (if any) to the added members.
+    fn epoch_change(&self, group_id: &str, change: MembershipChange) -> Result<EpochChange> {
+        let our_did = self.our_did();
+        let commit_envelope = self.mls_commit_envelope(group_id, &change.commit)?;
+
+        let mut relay_messages: Vec<RelayMessage> = self
+            .members(group_id)?
+            .iter()
+            .filter(|m| m.member_did != our_did && !change.added.contains(&m.member_did))
+            .map(|m| RelayMessage::new(&m.member_did, &commit_envelope))
+            .collect();
+
+        if let Some(welcome) = change.welcome {
+            let welcome_envelope = GroupEnvelope::MlsWelcome(GroupMlsWelcomePayload {
+                group_id: group_id.to_string(),
+                sender_did: our_did,
+                welcome,
+                timestamp: crate::time::now_timestamp_millis(),
+            })
+            .to_payload()?;
+            for did in &change.added {
+                relay_messages.push(RelayMessage::new(did, &welcome_envelope));
+            }
+        }
+
+        Ok(EpochChange {
+            epoch: change.epoch,
+            relay_messages,
+        })
+    }
+
+    /// Whether the group's messages are encrypted with MLS.
+    pub fn uses_mls(&self, group_id: &str) -> Result<bool> {
+        mls::has_session(self.db(), group_id)
+    }
+
+    /// A key package for joining a group, sent back with an invite accept.
+    pub fn new_key_package(&self) -> Result<String> {
+        mls::new_key_package(self.identity(), self.db())
+    }
+
+    /// Add a member to the group's MLS session from the key package they
+    /// sent (hex encoded).
+    pub fn add_mls_member(
+        &self,
+        group_id: &str,
+        member_did: &str,
+        key_package: &str,
+    ) -> Result<EpochChange> {
+        let change = mls::add_member(
+            self.identity(),
+            self.db(),
+            group_id,
+            member_did,
+            key_package,
+        )?;
+        self.epoch_change(group_id, change)
+    }
+
+    /// Refresh our keys in the group (post-compromise security).
+    pub fn update_mls_keys(&self, group_id: &str) -> Result<EpochChange> {
+        let change = mls::update(self.identity(), self.db(), group_id)?;
+        self.epoch_change(group_id, change)
+    }
+
+    /// Join a group's MLS session from a Welcome (hex encoded).
+    pub fn process_welcome(&self, welcome: &str) -> Result<MlsGroup> {
+        mls::process_welcome(self.db(), welcome)
+    }
+
+    /// Apply a commit (hex encoded) from the group admin.
+    ///
+    /// If it removed us, the group's MLS state is deleted.
+    pub fn process_commit(&self, group_id: &str, commit: &str) -> Result<CommitOutcome> {
+        mls::process_commit(self.db(), group_id, commit)
+    }
+}
@@ -0,0 +1,163 @@
+//! # Invitations
+//!
+//! Sending, storing, accepting, and declining group invites.
+
+use super::{
+    conversation_id, GroupEnvelope, GroupInviteAcceptPayload, GroupInviteDeclinePayload,
+    GroupInvitePayload, GroupRole, InviteMember, RelayMessage, WrappedGroupKey,
+};
+use crate::error::{Error, Result};
+use crate::storage::{GroupInviteRecord, GroupRecord};
+
+/// An invite we sent
+#[derive(Debug, Clone)]
+pub struct SentInvite {
+    /// Invite ID
+    pub invite_id: String,
+    /// The `group_invite` envelope for the invitee
+    pub relay_messages: Vec<RelayMessage>,
+}
+
+impl super::GroupService {
+    fn invite(&self, invite_id: &str) -> Result<GroupInviteRecord> {
+        self.db()
+            .get_group_invite(invite_id)?
+            .ok_or(Error::InviteNotFound)
+    }
+
+    /// Invite a friend to a group.
+    ///
+    /// The invite carries the group's current shared key, encrypted for
+    /// them, and the member list.
+    pub fn invite_member(&self, group_id: &str, member_did: &str) -> Result<SentInvite> {
+        let group = self.get_group(group_id)?;
+        let key = self.key(group_id, None)?;
+        let wrapped = self.encrypt_key_for_member(group_id, member_did, &key)?;
+
+        let members = self
+            .members(group_id)?
+            .into_iter()
+            .map(|m| InviteMember {
+                did: m.member_did,
+                display_name: m.display_name,
+                role: m.role,
+            })
+            .collect();
+
+        let invite_id = uuid::Uuid::new_v4().to_string();
+        let envelope = GroupEnvelope::Invite(GroupInvitePayload {
+            invite_id: invite_id.clone(),
+            group_id: group_id.to_string(),
+            group_name: group.name,
+            description: group.description,
+            inviter_did: self.our_did(),
+            inviter_name: self.identity().profile().display_name.clone(),
+            encrypted_group_key: wrapped.encrypted_key,
+            nonce: wrapped.nonce,
+            key_version: key.key_version,
+            members,
+            timestamp: crate::time::now_timestamp_millis(),
+        })
+        .to_payload()?;
+
+        Ok(SentInvite {
+            invite_id,
+            relay_messages: vec![RelayMessage::new(member_did, &envelope)],
+        })
+    }
+
+    /// Store an invite we received.
+    pub fn store_invite(&self, invite: &GroupInviteRecord) -> Result<()> {
+        self.db().store_group_invite(invite)
+    }
+
+    /// Get invites we haven't answered yet.
+    pub fn pending_invites(&self) -> Result<Vec<GroupInviteRecord>> {
+        self.db().get_pending_group_invites()
+    }
+
+    /// Accept an invite: import the group key and create our copy of the
+    /// group with the members it lists.
+    ///
+    /// The inviter still has to be told, with
+    /// [`invite_accept_envelope`](Self::invite_accept_envelope).
+    pub fn accept_invite(&self, invite_id: &str) -> Result<GroupRecord> {
+        let invite = self.invite(invite_id)?;
+        let group_id = &invite.group_id;
+        let our_did = self.our_did();
+        let now = crate::time::now_timestamp_millis();
+
+        let wrapped = WrappedGroupKey {
+            encrypted_key: invite.encrypted_group_key.clone(),
+            nonce: invite.nonce.clone(),
+        };
+        let key = self.unwrap_key_from(group_id, 1, &wrapped, &invite.inviter_did)?;
+
+        self.db().create_group(
+            group_id,
+            &invite.group_name,
+            invite.description.as_deref(),
+            &invite.inviter_did,
+            now,
+        )?;
+        self.store_key(group_id, &key)?;
+        let display_name = self.identity().profile().display_name.clone();
+        self.add_member(group_id, &our_did, Some(&display_name), GroupRole::Member)?;
+
+        let members: Vec<InviteMember> =
+            serde_json::from_str(&invite.members_json).unwrap_or_default();
+        for member in members.iter().filter(|m| m.did != our_did) {
+            let _ = self.add_member(
+                group_id,
+                &member.did,
+                member.display_name.as_deref(),
+                GroupRole::parse(&member.role),
+            );
+        }
+
+        self.db()
+            .create_group_conversation(&conversation_id(group_id), group_id)?;
+        self.db()
+            .update_group_invite_status(invite_id, "accepted")?;
+
+        self.get_group(group_id)
+    }
+
+    /// Decline an invite.
+    pub fn decline_invite(&self, invite_id: &str) -> Result<()> {
+        self.db()
+            .update_group_invite_status(invite_id, "declined")?;
+        Ok(())
+    }
+
+    /// The `group_invite_accept` envelope for the inviter.
+    ///
+    /// It carries a fresh key package, which the inviter adds to the
+    /// group's MLS session.
+    pub fn invite_accept_envelope(&self, invite_id: &str) -> Result<RelayMessage> {
+        let invite = self.invite(invite_id)?;
+        let envelope = GroupEnvelope::InviteAccept(GroupInviteAcceptPayload {
+            invite_id: invite_id.to_string(),
+            group_id: invite.group_id,
+            accepter_did: self.our_did(),
+            accepter_name: self.identity().profile().display_name.clone(),
+            key_package: Some(self.new_key_package()?),
+            timestamp: crate::time::now_timestamp_millis(),
+        })
+        .to_payload()?;
+        Ok(RelayMessage::new(&invite.inviter_did, &envelope))
+    }
+
+    /// The `group_invite_decline` envelope for the inviter.
+    pub fn invite_decline_envelope(&self, invite_id: &str) -> Result<RelayMessage> {
+        let invite = self.invite(invite_id)?;
+        let envelope = GroupEnvelope::InviteDecline(GroupInviteDeclinePayload {
+            invite_id: invite_id.to_string(),
+            group_id: invite.group_id,
+            decliner_did: self.our_did(),
+            timestamp: crate::time::now_timestamp_millis(),
+        })
+        .to_payload()?;
+        Ok(RelayMessage::new(&invite.inviter_did, &envelope))
+    }
+}
@@ -0,0 +1,289 @@
+//! # Group Keys
+//!
+//! Shared group keys: generation, rotation, storage, and hand-off to
+//! members. Groups created before MLS encrypt their messages with these.
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::{GroupEnvelope, GroupKeyRotationPayload};
+use crate::crypto::{EncryptionKey, Nonce, NONCE_SIZE};
+use crate::error::{Error, Result};
+use crate::storage::GroupKeyRecord;
+
+/// A shared group key
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct GroupKey {
+    /// Version of the key; the first key of a group is version 1
+    pub key_version: i32,
+    /// The raw AES-256 key
+    pub raw_key: [u8; 32],
+}
+
+/// A group key encrypted for one member
+#[derive(Debug, Clone)]
+pub struct WrappedGroupKey {
+    /// Encrypted key (hex encoded)
+    pub encrypted_key: String,
+    /// Nonce (hex encoded)
+    pub nonce: String,
+}
+
+fn transfer_aad(group_id: &str, key_version: i32) -> String {
+    format!("group-key-transfer:{}:{}", group_id, key_version)
+}
+
+fn storage_aad(group_id: &str, key_version: i32) -> String {
+    format!("group-key:{}:{}", group_id, key_version)
+}
+
+fn decode_key(key_hex: &str) -> Result<[u8; 32]> {
+    hex::decode(key_hex)
+        .map_err(|e| Error::InvalidKey(format!("Invalid key hex: {}", e)))?
+        .try_into()
+        .map_err(|_| Error::InvalidKey("Key must be 32 bytes".into()))
+}
+
+pub(crate) fn decode_nonce(nonce_hex: &str) -> Result<Nonce> {
+    let bytes: [u8; NONCE_SIZE] = hex::decode(nonce_hex)
+        .map_err(|e| Error::InvalidKey(format!("Invalid nonce hex: {}", e)))?
+        .try_into()
+        .map_err(|_| Error::InvalidKey(format!("Nonce must be {} bytes", NONCE_SIZE)))?;
+    Ok(Nonce::from_bytes(bytes))
+}
+
+impl super::GroupService {
+    /// Key our stored group keys are encrypted with, derived from our
+    /// encryption secret.
+    fn wrapping_key(&self) -> EncryptionKey {
+        use hkdf::Hkdf;
+        use sha2::Sha256;
+        let ikm = self.identity().keypair().encryption.secret_bytes();
+        let hkdf = Hkdf::<Sha256>::new(None, &ikm);
+        let mut key = [0u8; 32];
+        hkdf.expand(b"umbra-group-key-wrapping-v1", &mut key)
+            .expect("HKDF-SHA256 expansion should not fail for 32 bytes");
+        EncryptionKey::from_bytes(key)
+    }
+
+    /// Store a group key, encrypted with our wrapping key as nonce || ciphertext.
+    pub(crate) fn store_key(&self, group_id: &str, key: &GroupKey) -> Result<()> {
+        let aad = storage_aad(group_id, key.key_version);
+        let (nonce, ciphertext) =
+            crate::crypto::encrypt(&self.wrapping_key(), &key.raw_key, aad.as_bytes())?;
+
+        let mut stored = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        stored.extend_from_slice(&nonce.0);
+        stored.extend_from_slice(&ciphertext);
+
+        let now = crate::time::now_timestamp_millis();
+        self.db()
+            .store_group_key(group_id, key.key_version, &stored, now)
+    }
+
+    fn unwrap_key(&self, group_id: &str, record: &GroupKeyRecord) -> Result<GroupKey> {
+        let stored = &record.encrypted_key;
+        if stored.len() < NONCE_SIZE {
+            return Err(Error::InvalidKey("Invalid stored key format".into()));
+        }
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&stored[..NONCE_SIZE]);
+
+        let aad = storage_aad(group_id, record.key_version);
+        let raw = crate::crypto::decrypt(
+            &self.wrapping_key(),
+            &Nonce::from_bytes(nonce),
+            &stored[NONCE_SIZE..],
+            aad.as_bytes(),
+        )?;
+        Ok(GroupKey {
+            key_version: record.key_version,
+            raw_key: raw
+                .try_into()
+                .map_err(|_| Error::InvalidKey("Group key must be 32 bytes".into()))?,
+        })
+    }
+
+    /// Get a group key: `key_version`, or the latest one.
+    pub fn key(&self, group_id: &str, key_version: Option<i32>) -> Result<GroupKey> {
+        let record = match key_version {
+            Some(version) => self.db().get_group_key(group_id, version)?.ok_or_else(|| {
+                Error::InvalidKey(format!("Group key version {} not found", version))
+            })?,
+            None => self
+                .db()
+                .get_latest_group_key(group_id)?
+                .ok_or_else(|| Error::InvalidKey("No group key found".into()))?,
+        };
+        self.unwrap_key(group_id, &record)
+    }
+
+    /// A friend's X25519 public key; group keys can only be exchanged with friends.
+    fn friend_encryption_key(&self, did: &str) -> Result<[u8; 32]> {
+        let friend = self.db().get_friend(did)?.ok_or(Error::NotFriends)?;
+        decode_key(&friend.encryption_key)
+    }
+
+    fn new_key(&self, group_id: &str, key_version: i32) -> Result<GroupKey> {
+        use rand::RngCore;
+
+        let mut key = GroupKey {
+            key_version,
+            raw_key: [0u8; 32],
+        };
+        rand::rngs::OsRng.fill_bytes(&mut key.raw_key);
+        self.store_key(group_id, &key)?;
+        Ok(key)
+    }
+
+    /// Generate and store a group's first key (version 1).
+    pub fn generate_key(&self, group_id: &str) -> Result<GroupKey> {
+        self.new_key(group_id, 1)
+    }
+
+    /// Generate and store the next version of a group's key.
+    pub fn rotate_key(&self, group_id: &str) -> Result<GroupKey> {
+        let current = self.db().get_latest_group_key(group_id)?;
+        self.new_key(group_id, current.map(|k| k.key_version + 1).unwrap_or(1))
+    }
+
+    /// Decrypt a group key a member encrypted for us.
+    pub(crate) fn unwrap_key_from(
+        &self,
+        group_id: &str,
+        key_version: i32,
+        wrapped: &WrappedGroupKey,
+        sender_did: &str,
+    ) -> Result<GroupKey> {
+        let sender_key = self.friend_encryption_key(sender_did)?;
+        let encrypted_key = hex::decode(&wrapped.encrypted_key)
+            .map_err(|e| Error::InvalidKey(format!("Invalid encrypted key hex: {}", e)))?;
+
+        let raw = crate::crypto::decrypt_from_sender(
+            &self.identity().keypair().encryption,
+            &sender_key,
+            group_id.as_bytes(),
+            &decode_nonce(&wrapped.nonce)?,
+            &encrypted_key,
+            transfer_aad(group_id, key_version).as_bytes(),
+        )?;
+        Ok(GroupKey {
+            key_version,
+            raw_key: raw
+                .try_into()
+                .map_err(|_| Error::InvalidKey("Group key must be 32 bytes".into()))?,
+        })
+    }
+
+    /// Store a group key a member encrypted for us.
+    pub fn import_key(
+        &self,
+        group_id: &str,
+        key_version: i32,
+        wrapped: &WrappedGroupKey,
+        sender_did: &str,
+    ) -> Result<()> {
+        let key = self.unwrap_key_from(group_id, key_version, wrapped, sender_did)?;
+        self.store_key(group_id, &key)
+    }
+
+    /// Encrypt a group key for a member (who must be a friend).
+    pub fn encrypt_key_for_member(
+        &self,
+        group_id: &str,
+        member_did: &str,
+        key: &GroupKey,
+    ) -> Result<WrappedGroupKey> {
+        let member_key = self.friend_encryption_key(member_did)?;
+        let (nonce, ciphertext) = crate::crypto::encrypt_for_recipient(
+            &self.identity().keypair().encryption,
+            &member_key,
+            group_id.as_bytes(),
+            &key.raw_key,
+            transfer_aad(group_id, key.key_version).as_bytes(),
+        )?;
+        Ok(WrappedGroupKey {
+            encrypted_key: hex::encode(&ciphertext),
+            nonce: hex::encode(nonce.0),
+        })
+    }
+
+    /// A `group_key_rotation` envelope handing `key` to a member.
+    pub(crate) fn key_rotation_envelope(
+        &self,
+        group_id: &str,
+        member_did: &str,
+        key: &GroupKey,
+    ) -> Result<String> {
+        let wrapped = self.encrypt_key_for_member(group_id, member_did, key)?;
+        GroupEnvelope::KeyRotation(GroupKeyRotationPayload {
+            group_id: group_id.to_string(),
+            key_version: key.key_version,
+            encrypted_key: wrapped.encrypted_key,
+            nonce: wrapped.nonce,
+            sender_did: self.our_did(),
+            timestamp: crate::time::now_timestamp_millis(),
+        })
+        .to_payload()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::groups::GroupService;
+    use crate::identity::Identity;
+    use crate::messaging::mls;
+    use crate::storage::Database;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_rotation_keeps_old_keys() {
+        let (identity, _) = Identity::create("Alice".to_string()).unwrap();
+        let svc = GroupService::new(
+            Arc::new(identity),
+            Arc::new(Database::open(None).await.unwrap()),
+        );
+        let group = svc.create_group("Team", None).unwrap();
+        // Shared keys only encrypt messages for groups from before MLS
+        mls::delete_session(svc.db(), &group.id).unwrap();
+
+        let first = svc.key(&group.id, None).unwrap();
+        assert_eq!(first.key_version, 1);
+        let old = svc.encrypt_message(&group.id, "before").unwrap();
+        assert_eq!(old.key_version, 1);
+
+        let second = svc.rotate_key(&group.id).unwrap();
+        assert_eq!(second.key_version, 2);
+        assert_ne!(second.raw_key, first.raw_key);
+        assert_eq!(svc.key(&group.id, None).unwrap().key_version, 2);
+        assert_eq!(svc.key(&group.id, Some(1)).unwrap().raw_key, first.raw_key);
+        assert!(matches!(
+            svc.key(&group.id, Some(3)),
+            Err(Error::InvalidKey(_))
+        ));
+
+        // Messages from before the rotation still decrypt with their key
+        let decrypt = |version| {
+            svc.decrypt_message(
+                &group.id,
+                &svc.our_did(),
+                &old.ciphertext_hex,
+                &old.nonce_hex,
+                old.timestamp,
+                Some(version),
+            )
+        };
+        assert_eq!(decrypt(1).unwrap(), "before");
+        assert!(decrypt(2).is_err());
+        assert_eq!(
+            svc.encrypt_message(&group.id, "after").unwrap().key_version,
+            2
+        );
+
+        // Keys are only wrapped for friends
+        assert!(matches!(
+            svc.encrypt_key_for_member(&group.id, "did:key:zStranger", &second),
+            Err(Error::NotFriends)
+        ));
+    }
+}
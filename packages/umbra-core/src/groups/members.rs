@@ -0,0 +1,239 @@
+//! # Member Management
+//!
+//! Member lists, roles, and removal with re-keying.
+
+use super::{GroupEnvelope, GroupMemberRemovedPayload, RelayMessage};
+use crate::error::{Error, Result};
+use crate::messaging::mls;
+use crate::storage::GroupMemberRecord;
+
+/// A member's role in a group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupRole {
+    /// Can delete the group and remove members; the creator is the admin
+    Admin,
+    /// Regular member
+    Member,
+}
+
+impl GroupRole {
+    /// Name stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupRole::Admin => "admin",
+            GroupRole::Member => "member",
+        }
+    }
+
+    /// Parse a stored role; anything but `"admin"` is a regular member
+    pub fn parse(role: &str) -> Self {
+        match role {
+            "admin" => GroupRole::Admin,
+            _ => GroupRole::Member,
+        }
+    }
+}
+
+/// Result of removing a member and re-keying the group
+#[derive(Debug, Clone)]
+pub struct MemberRemoval {
+    /// Key version messages are now sent with
+    /// ([`MLS_KEY_VERSION`](crate::messaging::mls::MLS_KEY_VERSION) for MLS groups)
+    pub key_version: i32,
+    /// New MLS epoch, for MLS groups
+    pub epoch: Option<u64>,
+    /// Members the new shared key couldn't be wrapped for (not in our
+    /// friends list); they are left on the old key
+    pub unreachable_members: Vec<String>,
+    /// Envelopes to send
+    pub relay_messages: Vec<RelayMessage>,
+}
+
+impl super::GroupService {
+    /// Add a member to our copy of the group.
+    pub fn add_member(
+        &self,
+        group_id: &str,
+        did: &str,
+        display_name: Option<&str>,
+        role: GroupRole,
+    ) -> Result<()> {
+        let now = crate::time::now_timestamp_millis();
+        self.db()
+            .add_group_member(group_id, did, display_name, role.as_str(), now)
+    }
+
+    /// Remove a member from our copy of the group, without re-keying.
+    pub fn remove_member(&self, group_id: &str, did: &str) -> Result<()> {
+        self.db().remove_group_member(group_id, did)?;
+        Ok(())
+    }
+
+    /// Get a group's members.
+    pub fn members(&self, group_id: &str) -> Result<Vec<GroupMemberRecord>> {
+        self.db().get_group_members(group_id)
+    }
+
+    /// Get a member's role, or `None` if `did` isn't a member.
+    pub fn member_role(&self, group_id: &str, did: &str) -> Result<Option<GroupRole>> {
+        Ok(self
+            .members(group_id)?
+            .into_iter()
+            .find(|m| m.member_did == did)
+            .map(|m| GroupRole::parse(&m.role)))
+    }
+
+    /// Fail unless we're an admin of the group.
+    pub(crate) fn require_admin(&self, group_id: &str) -> Result<()> {
+        match self.member_role(group_id, &self.our_did())? {
+            Some(GroupRole::Admin) => Ok(()),
+            Some(GroupRole::Member) => Err(Error::InsufficientPermissions(
+                "Only group admins can do this".into(),
+            )),
+            None => Err(Error::NotGroupMember),
+        }
+    }
+
+    /// Remove a member and re-key the group so they can't read what's
+    /// sent after.
+    ///
+    /// MLS groups move to a new epoch with one commit, which the removed
+    /// member gets too so they learn they've been removed. Older groups
+    /// get a new shared key, wrapped for each remaining member.
+    pub fn remove_member_with_rotation(
+        &self,
+        group_id: &str,
+        member_did: &str,
+    ) -> Result<MemberRemoval> {
+        let our_did = self.our_did();
+
+        // Members only accept removals from admins
+        self.require_admin(group_id)?;
+        self.remove_member(group_id, member_did)?;
+        let remaining_members = self.members(group_id)?;
+
+        let removed_envelope = GroupEnvelope::MemberRemoved(GroupMemberRemovedPayload {
+            group_id: group_id.to_string(),
+            removed_did: member_did.to_string(),
+            removed_by: our_did.clone(),
+            timestamp: crate::time::now_timestamp_millis(),
+        })
+        .to_payload()?;
+
+        let mut relay_messages = Vec::new();
+
+        if mls::has_session(self.db(), group_id)? {
+            let change = mls::remove_member(self.identity(), self.db(), group_id, member_did)?;
+            let commit_envelope = self.mls_commit_envelope(group_id, &change.commit)?;
+
+            relay_messages.push(RelayMessage::new(member_did, &commit_envelope));
+            for member in remaining_members.iter().filter(|m| m.member_did != our_did) {
+                relay_messages.push(RelayMessage::new(&member.member_did, &commit_envelope));
+                relay_messages.push(RelayMessage::new(&member.member_did, &removed_envelope));
+            }
+
+            return Ok(MemberRemoval {
+                key_version: mls::MLS_KEY_VERSION,
+                epoch: Some(change.epoch),
+                unreachable_members: Vec::new(),
+                relay_messages,
+            });
+        }
+
+        let key = self.rotate_key(group_id)?;
+        let mut unreachable_members = Vec::new();
+
+        for member in remaining_members.iter().filter(|m| m.member_did != our_did) {
+            let envelope = match self.key_rotation_envelope(group_id, &member.member_did, &key) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    tracing::warn!(
+                        "Can't send rotated key for group {} to {}: {}",
+                        group_id,
+                        member.member_did,
+                        e
+                    );
+                    unreachable_members.push(member.member_did.clone());
+                    continue;
+                }
+            };
+            relay_messages.push(RelayMessage::new(&member.member_did, &envelope));
+            relay_messages.push(RelayMessage::new(&member.member_did, &removed_envelope));
+        }
+
+        Ok(MemberRemoval {
+            key_version: key.key_version,
+            epoch: None,
+            unreachable_members,
+            relay_messages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groups::GroupService;
+    use crate::identity::Identity;
+    use crate::storage::Database;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_roles_and_legacy_rotation() {
+        let (identity, _) = Identity::create("Alice".to_string()).unwrap();
+        let svc = GroupService::new(
+            Arc::new(identity),
+            Arc::new(Database::open(None).await.unwrap()),
+        );
+
+        let group = svc.create_group("Team", Some("desc")).unwrap();
+        assert_eq!(
+            svc.member_role(&group.id, &svc.our_did()).unwrap(),
+            Some(GroupRole::Admin)
+        );
+        svc.add_member(&group.id, "did:key:zBob", Some("Bob"), GroupRole::Member)
+            .unwrap();
+        svc.add_member(&group.id, "did:key:zCarol", None, GroupRole::Member)
+            .unwrap();
+        assert_eq!(
+            svc.member_role(&group.id, "did:key:zBob").unwrap(),
+            Some(GroupRole::Member)
+        );
+
+        // A group from before MLS gets a new shared key; Carol isn't a
+        // friend, so the key can't be wrapped for her
+        mls::delete_session(svc.db(), &group.id).unwrap();
+        let removal = svc
+            .remove_member_with_rotation(&group.id, "did:key:zBob")
+            .unwrap();
+        assert_eq!(removal.key_version, 2);
+        assert_eq!(removal.epoch, None);
+        assert_eq!(
+            removal.unreachable_members,
+            vec!["did:key:zCarol".to_string()]
+        );
+        assert!(removal.relay_messages.is_empty());
+        assert_eq!(svc.members(&group.id).unwrap().len(), 2);
+
+        // Shared-key messages round-trip with the latest key
+        let encrypted = svc.encrypt_message(&group.id, "hi").unwrap();
+        assert_eq!(encrypted.key_version, 2);
+        let text = svc
+            .decrypt_message(
+                &group.id,
+                &svc.our_did(),
+                &encrypted.ciphertext_hex,
+                &encrypted.nonce_hex,
+                encrypted.timestamp,
+                Some(encrypted.key_version),
+            )
+            .unwrap();
+        assert_eq!(text, "hi");
+
+        svc.delete_group(&group.id).unwrap();
+        assert!(matches!(
+            svc.get_group(&group.id),
+            Err(Error::GroupNotFound)
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+//! # Group Messaging
+//!
+//! Encrypting, sending, and decrypting group messages.
+
+use super::{conversation_id, GroupEnvelope, GroupMessagePayload, RelayMessage};
+use crate::crypto::{EncryptionKey, NONCE_SIZE};
+use crate::error::{Error, Result};
+use crate::messaging::mls;
+
+/// A group message encrypted for the wire
+#[derive(Debug, Clone)]
+pub struct EncryptedGroupMessage {
+    /// Ciphertext (hex encoded)
+    pub ciphertext_hex: String,
+    /// Nonce (hex encoded); empty for MLS messages
+    pub nonce_hex: String,
+    /// Key version the message was encrypted with
+    pub key_version: i32,
+    /// Timestamp bound into the AAD (Unix millis)
+    pub timestamp: i64,
+}
+
+/// A group message we sent
+#[derive(Debug, Clone)]
+pub struct SentGroupMessage {
+    /// Message ID
+    pub id: String,
+    /// Conversation the message was stored in
+    pub conversation_id: String,
+    /// Our DID
+    pub sender_did: String,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+    /// The `group_message` envelope for every other member
+    pub relay_messages: Vec<RelayMessage>,
+}
+
+/// A group message we received and decrypted
+#[derive(Debug, Clone)]
+pub struct GroupMessage {
+    /// Message ID
+    pub id: String,
+    /// Group ID
+    pub group_id: String,
+    /// Conversation ID
+    pub conversation_id: String,
+    /// Sender's DID
+    pub sender_did: String,
+    /// Plaintext
+    pub text: String,
+    /// Timestamp (Unix millis)
+    pub timestamp: i64,
+}
+
+impl super::GroupService {
+    /// Encrypt a message for the group: with its MLS session if it has one,
+    /// otherwise with its latest shared key.
+    pub fn encrypt_message(
+        &self,
+        group_id: &str,
+        plaintext: &str,
+    ) -> Result<EncryptedGroupMessage> {
+        let our_did = self.our_did();
+        let timestamp = crate::time::now_timestamp_millis();
+        let aad = mls::message_aad(group_id, &our_did, timestamp);
+
+        if self.uses_mls(group_id)? {
+            let ciphertext_hex = mls::encrypt_message(
+                self.identity(),
+                self.db(),
+                group_id,
+                plaintext.as_bytes(),
+                &aad,
+            )?;
+            return Ok(EncryptedGroupMessage {
+                ciphertext_hex,
+                nonce_hex: String::new(),
+                key_version: mls::MLS_KEY_VERSION,
+                timestamp,
+            });
+        }
+
+        let key = self.key(group_id, None)?;
+        let (nonce, ciphertext) = crate::crypto::encrypt(
+            &EncryptionKey::from_bytes(key.raw_key),
+            plaintext.as_bytes(),
+            aad.as_bytes(),
+        )?;
+        Ok(EncryptedGroupMessage {
+            ciphertext_hex: hex::encode(&ciphertext),
+            nonce_hex: hex::encode(nonce.0),
+            key_version: key.key_version,
+            timestamp,
+        })
+    }
+
+    /// Decrypt a group message and index it for search if it's stored.
+    ///
+    /// `key_version` picks the shared key (the latest if `None`);
+    /// [`MLS_KEY_VERSION`](mls::MLS_KEY_VERSION) means the group's MLS
+    /// session, and the message must be signed by `sender_did`.
+    pub fn decrypt_message(
+        &self,
+        group_id: &str,
+        sender_did: &str,
+        ciphertext_hex: &str,
+        nonce_hex: &str,
+        timestamp: i64,
+        key_version: Option<i32>,
+    ) -> Result<String> {
+        let aad = mls::message_aad(group_id, sender_did, timestamp);
+
+        let plaintext = if key_version == Some(mls::MLS_KEY_VERSION) {
+            let (mls_sender, plaintext) =
+                mls::decrypt_message(self.db(), group_id, ciphertext_hex, &aad)?;
+            if mls_sender != sender_did {
+                return Err(Error::DecryptionFailed(
+                    "Message was signed by a different group member".into(),
+                ));
+            }
+            plaintext
+        } else {
+            let key = self.key(group_id, key_version)?;
+            let ciphertext = hex::decode(ciphertext_hex).map_err(|e| {
+                Error::InvalidMessageContent(format!("Invalid ciphertext hex: {}", e))
+            })?;
+            crate::crypto::decrypt(
+                &EncryptionKey::from_bytes(key.raw_key),
+                &super::keys::decode_nonce(nonce_hex)?,
+                &ciphertext,
+                aad.as_bytes(),
+            )?
+        };
+
+        let text = String::from_utf8(plaintext)
+            .map_err(|e| Error::InvalidMessageContent(format!("Invalid UTF-8: {}", e)))?;
+        if let Ok(Some(message_id)) = self.db().find_message_id(group_id, sender_did, timestamp) {
+            let _ = self.db().index_message(&message_id, &text);
+        }
+        Ok(text)
+    }
+
+    /// Encrypt, store, and address a message to every other member.
+    pub fn send_message(
+        &self,
+        group_id: &str,
+        conversation_id: &str,
+        text: &str,
+    ) -> Result<SentGroupMessage> {
+        let our_did = self.our_did();
+        let message_id = uuid::Uuid::new_v4().to_string();
+        let encrypted = self.encrypt_message(group_id, text)?;
+
+        if encrypted.key_version == mls::MLS_KEY_VERSION {
+            // MLS message keys are single use, so we keep the plaintext of
+            // our own message, as for ratcheted DMs
+            self.db().store_message(
+                &message_id,
+                conversation_id,
+                &our_did,
+                text.as_bytes(),
+                &[0u8; NONCE_SIZE],
+                encrypted.timestamp,
+            )?;
+        } else {
+            let ciphertext = hex::decode(&encrypted.ciphertext_hex)
+                .map_err(|e| Error::Internal(format!("Invalid ciphertext hex: {}", e)))?;
+            let nonce = hex::decode(&encrypted.nonce_hex)
+                .map_err(|e| Error::Internal(format!("Invalid nonce hex: {}", e)))?;
+            self.db().store_message(
+                &message_id,
+                conversation_id,
+                &our_did,
+                &ciphertext,
+                &nonce,
+                encrypted.timestamp,
+            )?;
+        }
+        let _ = self.db().index_message(&message_id, text);
+
+        let envelope = GroupEnvelope::Message(GroupMessagePayload {
+            message_id: message_id.clone(),
+            group_id: group_id.to_string(),
+            conversation_id: conversation_id.to_string(),
+            sender_did: our_did.clone(),
+            ciphertext_hex: encrypted.ciphertext_hex,
+            nonce_hex: encrypted.nonce_hex,
+            key_version: encrypted.key_version,
+            timestamp: encrypted.timestamp,
+        })
+        .to_payload()?;
+
+        let relay_messages = self
+            .members(group_id)?
+            .iter()
+            .filter(|m| m.member_did != our_did)
+            .map(|m| RelayMessage::new(&m.member_did, &envelope))
+            .collect();
+
+        Ok(SentGroupMessage {
+            id: message_id,
+            conversation_id: conversation_id.to_string(),
+            sender_did: our_did,
+            timestamp: encrypted.timestamp,
+            relay_messages,
+        })
+    }
+
+    /// Decrypt a `group_message` from a member.
+    pub fn receive_message(&self, payload: &GroupMessagePayload) -> Result<GroupMessage> {
+        if self
+            .member_role(&payload.group_id, &payload.sender_did)?
+            .is_none()
+        {
+            return Err(Error::NotGroupMember);
+        }
+
+        let text = self.decrypt_message(
+            &payload.group_id,
+            &payload.sender_did,
+            &payload.ciphertext_hex,
+            &payload.nonce_hex,
+            payload.timestamp,
+            Some(payload.key_version),
+        )?;
+        Ok(GroupMessage {
+            id: payload.message_id.clone(),
+            group_id: payload.group_id.clone(),
+            conversation_id: conversation_id(&payload.group_id),
+            sender_did: payload.sender_did.clone(),
+            text,
+            timestamp: payload.timestamp,
+        })
+    }
+}
@@ -0,0 +1,62 @@
+//! # Groups Module
+//!
+//! Private group chats for Umbra — small, invite-only groups whose
+//! messages are end-to-end encrypted for every member.
+//!
+//! ## Architecture
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                         GROUPS MODULE                                   │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  ┌─────────────┐  ┌─────────────┐  ┌─────────────┐  ┌──────────────┐   │
+//! │  │  Service    │  │  Members    │  │  Invites    │  │  Messages    │   │
+//! │  │             │  │             │  │             │  │              │   │
+//! │  │ - Create    │  │ - Roles     │  │ - Send      │  │ - Encrypt    │   │
+//! │  │ - Update    │  │ - Add       │  │ - Store     │  │ - Decrypt    │   │
+//! │  │ - Delete    │  │ - Remove +  │  │ - Accept    │  │ - Send       │   │
+//! │  │ - Leave     │  │   re-key    │  │ - Decline   │  │ - Receive    │   │
+//! │  └──────┬──────┘  └──────┬──────┘  └──────┬──────┘  └──────┬───────┘   │
+//! │         │                │                │                │           │
+//! │  ┌──────┴──────┐  ┌──────┴──────┐  ┌──────┴──────┐                     │
+//! │  │    Keys     │  │   Epochs    │  │  Envelopes  │                     │
+//! │  │             │  │             │  │             │                     │
+//! │  │ - Generate  │  │ - MLS add   │  │ - Types     │                     │
+//! │  │ - Rotate    │  │ - Update    │  │ - Receive   │                     │
+//! │  │ - Wrap for  │  │ - Commits   │  │             │                     │
+//! │  │   members   │  │ - Welcomes  │  │             │                     │
+//! │  └─────────────┘  └─────────────┘  └─────────────┘                     │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! New groups encrypt messages with an MLS session (see
+//! [`messaging::mls`](crate::messaging::mls)); groups created before MLS
+//! use a shared key that's rotated when a member is removed.
+//!
+//! The service never talks to the network: anything other members need
+//! comes back as [`RelayMessage`]s for the caller to send, and envelopes
+//! that arrive are handed to [`GroupService::receive`].
+
+mod envelopes;
+mod epochs;
+mod invites;
+mod keys;
+mod members;
+mod messages;
+mod service;
+
+pub use envelopes::{
+    GroupEnvelope, GroupEvent, GroupInviteAcceptPayload, GroupInviteDeclinePayload,
+    GroupInvitePayload, GroupKeyRotationPayload, GroupMemberRemovedPayload, GroupMessagePayload,
+    GroupMlsCommitPayload, GroupMlsWelcomePayload, InviteMember, ReceivedEnvelope,
+};
+pub use epochs::EpochChange;
+pub use invites::SentInvite;
+pub use keys::{GroupKey, WrappedGroupKey};
+pub use members::{GroupRole, MemberRemoval};
+pub use messages::{EncryptedGroupMessage, GroupMessage, SentGroupMessage};
+pub use service::{conversation_id, GroupService};
+
+pub use crate::network::relay_client::RelayMessage;
@@ -0,0 +1,235 @@
+//! # Group Service
+//!
+//! Core service struct for group CRUD operations.
+
+use std::sync::Arc;
+
+use super::GroupRole;
+use crate::error::{Error, Result};
+use crate::identity::Identity;
+use crate::messaging::mls;
+use crate::storage::{Database, GroupMemberRecord, GroupRecord};
+
+/// ID of the conversation holding a group's messages
+pub fn conversation_id(group_id: &str) -> String {
+    format!("group-{}", group_id)
+}
+
+/// The main group service — coordinates all group operations.
+///
+/// Acts on behalf of the loaded identity. Methods that talk to other
+/// members return the [`RelayMessage`](super::RelayMessage)s to send;
+/// delivering them is up to the caller.
+pub struct GroupService {
+    identity: Arc<Identity>,
+    db: Arc<Database>,
+}
+
+impl GroupService {
+    /// Create a new group service for `identity`, backed by the given database.
+    pub fn new(identity: Arc<Identity>, db: Arc<Database>) -> Self {
+        Self { identity, db }
+    }
+
+    /// Get a reference to the identity the service acts for.
+    pub(crate) fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    /// Get a reference to the underlying database.
+    pub(crate) fn db(&self) -> &Database {
+        &self.db
+    }
+
+    /// Our DID.
+    pub(crate) fn our_did(&self) -> String {
+        self.identity.did_string()
+    }
+
+    /// Create a group with us as its only member and admin.
+    ///
+    /// Also creates the group's conversation, its first shared key (kept
+    /// for groups created before MLS and for invites) and its MLS session,
+    /// which encrypts all of its messages.
+    pub fn create_group(&self, name: &str, description: Option<&str>) -> Result<GroupRecord> {
+        let our_did = self.our_did();
+        let display_name = self.identity.profile().display_name.clone();
+        let group_id = uuid::Uuid::new_v4().to_string();
+        let now = crate::time::now_timestamp_millis();
+
+        self.db()
+            .create_group(&group_id, name, description, &our_did, now)?;
+        self.db().add_group_member(
+            &group_id,
+            &our_did,
+            Some(&display_name),
+            GroupRole::Admin.as_str(),
+            now,
+        )?;
+        self.db()
+            .create_group_conversation(&conversation_id(&group_id), &group_id)?;
+
+        self.generate_key(&group_id)?;
+        mls::create_session(self.identity(), self.db(), &group_id)?;
+
+        self.get_group(&group_id)
+    }
+
+    /// Import a group kept by an older client, with its members.
+    ///
+    /// Groups we created get a fresh key and MLS session, like new ones;
+    /// for the rest we wait for the creator to share theirs. Returns
+    /// `false` if we already have the group.
+    pub fn import_group(&self, group: &GroupRecord, members: &[GroupMemberRecord]) -> Result<bool> {
+        if self.db().get_group(&group.id)?.is_some() {
+            return Ok(false);
+        }
+
+        self.db().create_group(
+            &group.id,
+            &group.name,
+            group.description.as_deref(),
+            &group.created_by,
+            group.created_at,
+        )?;
+        for member in members {
+            self.db().add_group_member(
+                &group.id,
+                &member.member_did,
+                member.display_name.as_deref(),
+                GroupRole::parse(&member.role).as_str(),
+                member.joined_at,
+            )?;
+        }
+        self.db()
+            .create_group_conversation(&conversation_id(&group.id), &group.id)?;
+
+        if group.created_by == self.our_did() {
+            self.generate_key(&group.id)?;
+            mls::create_session(self.identity(), self.db(), &group.id)?;
+        }
+        Ok(true)
+    }
+
+    /// Get a group by ID.
+    pub fn get_group(&self, group_id: &str) -> Result<GroupRecord> {
+        self.db().get_group(group_id)?.ok_or(Error::GroupNotFound)
+    }
+
+    /// Get all groups we belong to.
+    pub fn list_groups(&self) -> Result<Vec<GroupRecord>> {
+        self.db().get_all_groups()
+    }
+
+    /// Rename a group or change its description.
+    ///
+    /// Returns the update time.
+    pub fn update_group(
+        &self,
+        group_id: &str,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<i64> {
+        let now = crate::time::now_timestamp_millis();
+        self.db().update_group(group_id, name, description, now)?;
+        Ok(now)
+    }
+
+    /// Delete a group (admin only).
+    pub fn delete_group(&self, group_id: &str) -> Result<()> {
+        self.require_admin(group_id)?;
+        self.leave_group(group_id)
+    }
+
+    /// Drop our copy of a group: its record, members and MLS state.
+    ///
+    /// Used when we leave or are removed; other members aren't told.
+    pub fn leave_group(&self, group_id: &str) -> Result<()> {
+        self.db().delete_group(group_id)?;
+        mls::delete_session(self.db(), group_id)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOB: &str = "did:key:zBob";
+
+    fn member(group_id: &str, did: &str, role: GroupRole) -> GroupMemberRecord {
+        GroupMemberRecord {
+            group_id: group_id.to_string(),
+            member_did: did.to_string(),
+            display_name: None,
+            role: role.as_str().to_string(),
+            joined_at: 1000,
+        }
+    }
+
+    fn record(id: &str, created_by: &str) -> GroupRecord {
+        GroupRecord {
+            id: id.to_string(),
+            name: "Old group".to_string(),
+            description: None,
+            avatar: None,
+            created_by: created_by.to_string(),
+            created_at: 1000,
+            updated_at: 1000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_and_admin_checks() {
+        let (identity, _) = Identity::create("Alice".to_string()).unwrap();
+        let svc = GroupService::new(
+            Arc::new(identity),
+            Arc::new(Database::open(None).await.unwrap()),
+        );
+        let me = svc.our_did();
+
+        // Bob's group: we're a plain member and wait for his key
+        let theirs = record("legacy-1", BOB);
+        let members = [
+            member(&theirs.id, BOB, GroupRole::Admin),
+            member(&theirs.id, &me, GroupRole::Member),
+        ];
+        assert!(svc.import_group(&theirs, &members).unwrap());
+        assert!(!svc.import_group(&theirs, &members).unwrap());
+        assert_eq!(svc.members(&theirs.id).unwrap().len(), 2);
+        assert!(!svc.uses_mls(&theirs.id).unwrap());
+        assert!(svc.key(&theirs.id, None).is_err());
+
+        assert!(matches!(
+            svc.delete_group(&theirs.id),
+            Err(Error::InsufficientPermissions(_))
+        ));
+        assert!(matches!(
+            svc.remove_member_with_rotation(&theirs.id, BOB),
+            Err(Error::InsufficientPermissions(_))
+        ));
+        assert_eq!(svc.members(&theirs.id).unwrap().len(), 2);
+
+        // Our own group is keyed like a new one
+        let ours = record("legacy-2", &me);
+        let members = [
+            member(&ours.id, &me, GroupRole::Admin),
+            member(&ours.id, BOB, GroupRole::Member),
+        ];
+        assert!(svc.import_group(&ours, &members).unwrap());
+        assert!(svc.uses_mls(&ours.id).unwrap());
+        assert_eq!(svc.key(&ours.id, None).unwrap().key_version, 1);
+        assert_eq!(
+            svc.member_role(&ours.id, &me).unwrap(),
+            Some(GroupRole::Admin)
+        );
+        assert_eq!(svc.list_groups().unwrap().len(), 2);
+
+        // Once we've left, we're not a member at all
+        svc.leave_group(&theirs.id).unwrap();
+        assert!(matches!(
+            svc.delete_group(&theirs.id),
+            Err(Error::NotGroupMember)
+        ));
+    }
+}
@@ -0,0 +1,387 @@
+//! # Devices
+//!
+//! Per-device keys linked to one DID.
+//!
+//! The root identity key (derived from the recovery phrase) never signs
+//! messages on other devices' behalf and never has to be copied to them.
+//! Instead each install generates its own device keypair, and the root key
+//! certifies it:
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                          DEVICE KEY HIERARCHY                           │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │                 Root identity key (Ed25519, DID)                        │
+//! │                              │                                          │
+//! │              signs ──────────┼───────────── signs                       │
+//! │                ▼             ▼               ▼                          │
+//! │  ┌──────────────────┐ ┌──────────────────┐ ┌──────────────────┐        │
+//! │  │ DeviceCertificate│ │ DeviceCertificate│ │    DeviceList    │        │
+//! │  │ phone            │ │ laptop           │ │ version: 3       │        │
+//! │  │ • Ed25519 key    │ │ • Ed25519 key    │ │ devices: [..]    │        │
+//! │  │ • X25519 key     │ │ • X25519 key     │ │ revoked: [..]    │        │
+//! │  └──────────────────┘ └──────────────────┘ └──────────────────┘        │
+//! │                                                                         │
+//! │  The DeviceList is published with the profile (PublicIdentity).        │
+//! │  Senders encrypt every envelope to each listed device's X25519 key.    │
+//! │  A higher `version` replaces a lower one; revoked device IDs can       │
+//! │  never be re-added.                                                    │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{Did, Identity};
+use crate::crypto::{
+    sign, verify, EncryptionKeyPair, KeyPair, PublicKey, Signature, SigningKeyPair,
+};
+use crate::error::{Error, Result};
+
+/// Prefix for data signed into a device certificate
+const DEVICE_CERTIFICATE_DOMAIN: &[u8] = b"umbra-device-cert-v1";
+
+/// Prefix for data signed into a device list
+const DEVICE_LIST_DOMAIN: &[u8] = b"umbra-device-list-v1";
+
+/// Maximum number of active devices per identity
+pub const MAX_DEVICES: usize = 16;
+
+/// Derive a device ID from the device's signing key (16 hex chars)
+pub fn device_id_for(signing_public: &[u8; 32]) -> String {
+    hex::encode(&Sha256::digest(signing_public)[..8])
+}
+
+/// This install's device keypair (secret; never leaves the device)
+pub struct DeviceKeys {
+    device_id: String,
+    keypair: KeyPair,
+}
+
+impl DeviceKeys {
+    /// Generate a fresh device keypair
+    pub fn generate() -> Self {
+        Self::from_keypair(KeyPair::generate())
+    }
+
+    /// Restore device keys from stored secret bytes
+    pub fn from_secret_bytes(signing: &[u8; 32], encryption: &[u8; 32]) -> Result<Self> {
+        Ok(Self::from_keypair(KeyPair {
+            signing: SigningKeyPair::from_bytes(signing)?,
+            encryption: EncryptionKeyPair::from_bytes(encryption),
+        }))
+    }
+
+    fn from_keypair(keypair: KeyPair) -> Self {
+        Self {
+            device_id: device_id_for(&keypair.signing.public_bytes()),
+            keypair,
+        }
+    }
+
+    /// The device ID
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    /// Public keys to hand to the root identity for certification
+    pub fn public_keys(&self) -> PublicKey {
+        self.keypair.public_keys()
+    }
+
+    /// The device keypair
+    pub fn keypair(&self) -> &KeyPair {
+        &self.keypair
+    }
+}
+
+/// A device's public keys, certified by the root identity key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeviceCertificate {
+    /// The identity this device belongs to
+    pub did: String,
+    /// Device ID (derived from the device signing key)
+    pub device_id: String,
+    /// Human-readable device name ("Laptop", "Phone", ...)
+    pub name: String,
+    /// Device signing and encryption keys
+    pub public_keys: PublicKey,
+    /// When the device was linked (Unix timestamp)
+    pub created_at: i64,
+    /// Root-key Ed25519 signature (hex)
+    pub signature: String,
+}
+
+impl DeviceCertificate {
+    /// Certify a device's public keys with the root identity key
+    pub fn issue(identity: &Identity, public_keys: PublicKey, name: &str) -> Result<Self> {
+        let mut cert = Self {
+            did: identity.did_string(),
+            device_id: device_id_for(&public_keys.signing),
+            name: name.to_string(),
+            public_keys,
+            created_at: crate::time::now_timestamp(),
+            signature: String::new(),
+        };
+        cert.signature = sign(&identity.keypair().signing, &cert.sign_bytes()?).to_hex();
+        Ok(cert)
+    }
+
+    /// Check the certificate was issued by `did`'s root key for these keys
+    pub fn verify(&self, did: &str) -> Result<()> {
+        if self.did != did {
+            return Err(Error::InvalidDid(format!(
+                "Device certificate for {} presented for {}",
+                self.did, did
+            )));
+        }
+        if self.device_id != device_id_for(&self.public_keys.signing) {
+            return Err(Error::VerificationFailed);
+        }
+        let root_key = Did::parse(did)?.public_key()?;
+        verify(
+            &root_key,
+            &self.sign_bytes()?,
+            &Signature::from_hex(&self.signature)?,
+        )
+    }
+
+    fn sign_bytes(&self) -> Result<Vec<u8>> {
+        let data = (
+            &self.did,
+            &self.device_id,
+            &self.name,
+            &self.public_keys.signing,
+            &self.public_keys.encryption,
+            self.created_at,
+        );
+        let mut bytes = DEVICE_CERTIFICATE_DOMAIN.to_vec();
+        bytes.extend(
+            bincode::serialize(&data).map_err(|e| Error::SerializationError(e.to_string()))?,
+        );
+        Ok(bytes)
+    }
+}
+
+/// The signed set of devices linked to an identity
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeviceList {
+    /// The identity the devices belong to
+    pub did: String,
+    /// Monotonic version; receivers ignore lists older than what they hold
+    pub version: u64,
+    /// Active devices
+    pub devices: Vec<DeviceCertificate>,
+    /// IDs of revoked devices
+    pub revoked: Vec<String>,
+    /// When this version was signed (Unix timestamp)
+    pub updated_at: i64,
+    /// Root-key Ed25519 signature (hex)
+    pub signature: String,
+}
+
+impl DeviceList {
+    /// Create the first (version 1) list
+    pub fn new(identity: &Identity, devices: Vec<DeviceCertificate>) -> Result<Self> {
+        Self::signed(identity, 1, devices, Vec::new())
+    }
+
+    /// A new version with `cert` added (or replacing the same device)
+    pub fn with_device(&self, identity: &Identity, cert: DeviceCertificate) -> Result<Self> {
+        cert.verify(&self.did)?;
+        if self.revoked.contains(&cert.device_id) {
+            return Err(Error::InvalidKey(format!(
+                "Device {} has been revoked",
+                cert.device_id
+            )));
+        }
+
+        let mut devices: Vec<_> = self
+            .devices
+            .iter()
+            .filter(|d| d.device_id != cert.device_id)
+            .cloned()
+            .collect();
+        if devices.len() >= MAX_DEVICES {
+            return Err(Error::InvalidKey(format!(
+                "Too many devices (max {})",
+                MAX_DEVICES
+            )));
+        }
+        devices.push(cert);
+
+        Self::signed(identity, self.version + 1, devices, self.revoked.clone())
+    }
+
+    /// A new version with `device_id` moved to the revoked set
+    pub fn without_device(&self, identity: &Identity, device_id: &str) -> Result<Self> {
+        if self.device(device_id).is_none() {
+            return Err(Error::InvalidKey(format!("Unknown device {}", device_id)));
+        }
+
+        let devices = self
+            .devices
+            .iter()
+            .filter(|d| d.device_id != device_id)
+            .cloned()
+            .collect();
+        let mut revoked = self.revoked.clone();
+        revoked.push(device_id.to_string());
+
+        Self::signed(identity, self.version + 1, devices, revoked)
+    }
+
+    /// Look up an active device
+    pub fn device(&self, device_id: &str) -> Option<&DeviceCertificate> {
+        self.devices.iter().find(|d| d.device_id == device_id)
+    }
+
+    /// Verify the list signature and every certificate against the DID
+    pub fn verify(&self) -> Result<()> {
+        let root_key = Did::parse(&self.did)?.public_key()?;
+        verify(
+            &root_key,
+            &self.sign_bytes()?,
+            &Signature::from_hex(&self.signature)?,
+        )?;
+
+        for cert in &self.devices {
+            cert.verify(&self.did)?;
+            if self.revoked.contains(&cert.device_id) {
+                return Err(Error::VerificationFailed);
+            }
+        }
+        Ok(())
+    }
+
+    fn signed(
+        identity: &Identity,
+        version: u64,
+        devices: Vec<DeviceCertificate>,
+        revoked: Vec<String>,
+    ) -> Result<Self> {
+        let mut list = Self {
+            did: identity.did_string(),
+            version,
+            devices,
+            revoked,
+            updated_at: crate::time::now_timestamp(),
+            signature: String::new(),
+        };
+        list.signature = sign(&identity.keypair().signing, &list.sign_bytes()?).to_hex();
+        Ok(list)
+    }
+
+    fn sign_bytes(&self) -> Result<Vec<u8>> {
+        let certificates: Vec<_> = self
+            .devices
+            .iter()
+            .map(|d| d.sign_bytes())
+            .collect::<Result<_>>()?;
+        let data = (
+            &self.did,
+            self.version,
+            &certificates,
+            &self.revoked,
+            self.updated_at,
+        );
+        let mut bytes = DEVICE_LIST_DOMAIN.to_vec();
+        bytes.extend(
+            bincode::serialize(&data).map_err(|e| Error::SerializationError(e.to_string()))?,
+        );
+        Ok(bytes)
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity() -> Identity {
+        Identity::create("Alice".to_string()).unwrap().0
+    }
+
+    #[test]
+    fn test_certificate_round_trip() {
+        let alice = identity();
+        let device = DeviceKeys::generate();
+
+        let cert = DeviceCertificate::issue(&alice, device.public_keys(), "Laptop").unwrap();
+        assert_eq!(cert.device_id, device.device_id());
+        assert!(cert.verify(&alice.did_string()).is_ok());
+
+        // Another identity's DID doesn't vouch for it
+        let mallory = identity();
+        assert!(cert.verify(&mallory.did_string()).is_err());
+
+        // Swapping in a different encryption key breaks the signature
+        let mut forged = cert.clone();
+        forged.public_keys.encryption = [9u8; 32];
+        assert!(forged.verify(&alice.did_string()).is_err());
+    }
+
+    #[test]
+    fn test_device_list_link_and_revoke() {
+        let alice = identity();
+        let phone = DeviceKeys::generate();
+        let laptop = DeviceKeys::generate();
+
+        let phone_cert = DeviceCertificate::issue(&alice, phone.public_keys(), "Phone").unwrap();
+        let list = DeviceList::new(&alice, vec![phone_cert]).unwrap();
+        assert!(list.verify().is_ok());
+
+        let laptop_cert = DeviceCertificate::issue(&alice, laptop.public_keys(), "Laptop").unwrap();
+        let list = list.with_device(&alice, laptop_cert.clone()).unwrap();
+        assert_eq!(list.version, 2);
+        assert_eq!(list.devices.len(), 2);
+        assert!(list.verify().is_ok());
+
+        let list = list.without_device(&alice, laptop.device_id()).unwrap();
+        assert_eq!(list.version, 3);
+        assert!(list.device(laptop.device_id()).is_none());
+        assert!(list.verify().is_ok());
+
+        // A revoked device stays revoked
+        assert!(list.with_device(&alice, laptop_cert).is_err());
+    }
+
+    #[test]
+    fn test_device_list_tampering_detected() {
+        let alice = identity();
+        let mallory = identity();
+        let phone = DeviceKeys::generate();
+        let cert = DeviceCertificate::issue(&alice, phone.public_keys(), "Phone").unwrap();
+        let list = DeviceList::new(&alice, vec![cert]).unwrap();
+
+        // Injecting a device certified by someone else
+        let rogue = DeviceKeys::generate();
+        let mut tampered = list.clone();
+        tampered
+            .devices
+            .push(DeviceCertificate::issue(&mallory, rogue.public_keys(), "Rogue").unwrap());
+        assert!(tampered.verify().is_err());
+
+        // Rolling the version forward without re-signing
+        let mut bumped = list.clone();
+        bumped.version += 1;
+        assert!(bumped.verify().is_err());
+    }
+
+    #[test]
+    fn test_device_keys_restore() {
+        let device = DeviceKeys::generate();
+        let restored = DeviceKeys::from_secret_bytes(
+            &device.keypair().signing.secret_bytes(),
+            &device.keypair().encryption.secret_bytes(),
+        )
+        .unwrap();
+        assert_eq!(restored.device_id(), device.device_id());
+        assert_eq!(restored.public_keys(), device.public_keys());
+    }
+}
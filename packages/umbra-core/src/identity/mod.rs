@@ -133,19 +133,24 @@
 //! The `z` prefix indicates base58btc encoding, and `6Mk` is the multicodec
 //! prefix for Ed25519 public keys.
 
+mod device;
 mod did;
 mod profile;
 mod recovery;
+mod rotation;
 
+pub use device::{device_id_for, DeviceCertificate, DeviceKeys, DeviceList, MAX_DEVICES};
 pub use did::{Did, DID_KEY_PREFIX};
 pub use profile::{Profile, ProfileUpdate};
 pub use recovery::{RecoveryPhrase, WORD_COUNT};
+pub use rotation::{within_grace_period, KeyRotation, KEY_ROTATION_GRACE_PERIOD};
 
 use serde::{Deserialize, Serialize};
 use zeroize::ZeroizeOnDrop;
 
-use crate::crypto::{KeyPair, PublicKey};
+use crate::crypto::{EncryptionKeyPair, KeyPair, PublicKey};
 use crate::error::{Error, Result};
+use rotation::PreviousEncryptionKey;
 
 /// A user's complete identity including keys and profile
 ///
@@ -170,6 +175,10 @@ pub struct Identity {
 
     /// When the identity was created (Unix timestamp)
     created_at: i64,
+
+    /// Encryption key replaced by the latest rotation, if any
+    #[zeroize(skip)]
+    previous_encryption: Option<PreviousEncryptionKey>,
 }
 
 impl Identity {
@@ -201,6 +210,7 @@ impl Identity {
             did,
             profile: Profile::new(display_name),
             created_at: crate::time::now_timestamp(),
+            previous_encryption: None,
         })
     }
 
@@ -238,6 +248,8 @@ impl Identity {
             avatar: self.profile.avatar.clone(),
             public_keys: self.keypair.public_keys(),
             created_at: self.created_at,
+            prekey_bundle: None,
+            device_list: None,
         }
     }
 
@@ -254,27 +266,63 @@ impl Identity {
     /// Rotate the X25519 encryption key.
     ///
     /// Generates a new random encryption keypair. The signing keypair
-    /// and DID remain unchanged. Returns the new public encryption key.
+    /// and DID remain unchanged. Returns the signed announcement to send
+    /// to friends.
     ///
     /// ## Security
     ///
-    /// The new key is NOT derived from the mnemonic — it is purely random.
-    /// After rotation, messages encrypted with the old shared secret
-    /// cannot be decrypted.
-    pub fn rotate_encryption_key(&mut self) -> Result<[u8; 32]> {
-        let new_encryption = crate::crypto::EncryptionKeyPair::generate();
-        let new_public = new_encryption.public_bytes();
-
-        // Reconstruct the keypair with the existing signing key and new encryption key
-        let signing_bytes = self.keypair.signing.secret_bytes();
-        let signing = crate::crypto::SigningKeyPair::from_bytes(&signing_bytes)?;
+    /// The new key is NOT derived from the mnemonic — it is purely random,
+    /// so it must be persisted (`storage::store_rotated_encryption_key`)
+    /// for a restore from the phrase to recover it. The old key is kept
+    /// for [`KEY_ROTATION_GRACE_PERIOD`] so in-flight messages still
+    /// decrypt.
+    pub fn rotate_encryption_key(&mut self) -> Result<KeyRotation> {
+        let new_encryption = EncryptionKeyPair::generate();
+        let previous = std::mem::replace(&mut self.keypair.encryption, new_encryption);
+        let previous_public = previous.public_bytes();
+        let rotated_at = crate::time::now_timestamp();
+
+        self.previous_encryption = Some(PreviousEncryptionKey {
+            keypair: previous,
+            rotated_at,
+        });
+
+        KeyRotation::sign(self, previous_public, rotated_at)
+    }
 
-        self.keypair = KeyPair {
-            signing,
-            encryption: new_encryption,
-        };
+    /// The encryption key replaced by the latest rotation, while it is
+    /// still inside the grace window
+    pub fn previous_encryption_key(&self) -> Option<&EncryptionKeyPair> {
+        self.previous_encryption
+            .as_ref()
+            .filter(|previous| within_grace_period(previous.rotated_at))
+            .map(|previous| &previous.keypair)
+    }
 
-        Ok(new_public)
+    /// Rotated key material to persist: `(current secret, previous secret,
+    /// rotated_at)`. `None` if the key was never rotated.
+    pub fn rotated_encryption_key(&self) -> Option<([u8; 32], [u8; 32], i64)> {
+        self.previous_encryption.as_ref().map(|previous| {
+            (
+                self.keypair.encryption.secret_bytes(),
+                previous.keypair.secret_bytes(),
+                previous.rotated_at,
+            )
+        })
+    }
+
+    /// Re-apply a persisted rotation (e.g. after restoring from the phrase)
+    pub fn restore_rotated_encryption_key(
+        &mut self,
+        current: &[u8; 32],
+        previous: &[u8; 32],
+        rotated_at: i64,
+    ) {
+        self.keypair.encryption = EncryptionKeyPair::from_bytes(current);
+        self.previous_encryption = Some(PreviousEncryptionKey {
+            keypair: EncryptionKeyPair::from_bytes(previous),
+            rotated_at,
+        });
     }
 
     /// Create a safe copy of this identity for passing into service constructors.
@@ -294,12 +342,20 @@ impl Identity {
             encryption,
         };
         let did = Did::from_public_key(&keypair.signing.public_bytes());
+        let previous_encryption =
+            self.previous_encryption
+                .as_ref()
+                .map(|previous| PreviousEncryptionKey {
+                    keypair: EncryptionKeyPair::from_bytes(&previous.keypair.secret_bytes()),
+                    rotated_at: previous.rotated_at,
+                });
 
         Ok(Self {
             keypair,
             did,
             profile: self.profile.clone(),
             created_at: self.created_at,
+            previous_encryption,
         })
     }
 }
@@ -326,9 +382,29 @@ pub struct PublicIdentity {
 
     /// When the identity was created
     pub created_at: i64,
+
+    /// X3DH prekeys for starting a Double Ratchet session with this user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prekey_bundle: Option<crate::crypto::PrekeyBundle>,
+
+    /// Signed list of this identity's devices (multi-device fan-out)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_list: Option<DeviceList>,
 }
 
 impl PublicIdentity {
+    /// Attach a prekey bundle so recipients can open a ratchet session
+    pub fn with_prekey_bundle(mut self, bundle: crate::crypto::PrekeyBundle) -> Self {
+        self.prekey_bundle = Some(bundle);
+        self
+    }
+
+    /// Attach the signed device list so senders can fan out to each device
+    pub fn with_device_list(mut self, list: DeviceList) -> Self {
+        self.device_list = Some(list);
+        self
+    }
+
     /// Verify that a message was signed by this identity
     pub fn verify_signature(
         &self,
@@ -0,0 +1,167 @@
+//! # Encryption Key Rotation
+//!
+//! The X25519 encryption key can be replaced without changing the DID. The
+//! Ed25519 signing key (and so the DID) stays put and vouches for the new
+//! key:
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                        KEY ROTATION                                     │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  Alice                                        Bob (friend)             │
+//! │  ─────                                        ────────────              │
+//! │  rotate_encryption_key()                                               │
+//! │   • new random X25519 key                                              │
+//! │   • old key kept for the grace window                                  │
+//! │   • both persisted in SecureStore                                      │
+//! │        │                                                                │
+//! │        └──── KeyRotation (signed) ────────►  FriendsService::          │
+//! │                                              handle_key_rotation()     │
+//! │                                               • verify with Alice's    │
+//! │                                                 signing key            │
+//! │                                               • store new key, keep    │
+//! │                                                 old one as previous    │
+//! │                                                                         │
+//! │  For KEY_ROTATION_GRACE_PERIOD after `rotated_at`, messages under      │
+//! │  either key still decrypt on both sides.                               │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! The rotated key is random, so it cannot be re-derived from the recovery
+//! phrase. It is kept in the SecureStore (see
+//! `storage::store_rotated_encryption_key`) and re-applied after a restore.
+
+use serde::{Deserialize, Serialize};
+
+use super::Identity;
+use crate::crypto::{hex_bytes, sign, verify, EncryptionKeyPair, Signature};
+use crate::error::{Error, Result};
+
+/// How long the previous encryption key keeps working after a rotation
+/// (seconds)
+pub const KEY_ROTATION_GRACE_PERIOD: i64 = 7 * 24 * 60 * 60;
+
+/// Prefix for data signed over a key rotation
+const KEY_ROTATION_DOMAIN: &[u8] = b"umbra-key-rotation-v1";
+
+/// Check whether a rotation at `rotated_at` is still inside the grace window
+pub fn within_grace_period(rotated_at: i64) -> bool {
+    crate::time::now_timestamp() - rotated_at <= KEY_ROTATION_GRACE_PERIOD
+}
+
+/// Signed announcement that an identity replaced its encryption key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyRotation {
+    /// The identity whose key changed
+    pub did: String,
+    /// The new X25519 public key
+    #[serde(with = "hex_bytes")]
+    pub new_encryption_key: [u8; 32],
+    /// The X25519 public key being replaced
+    #[serde(with = "hex_bytes")]
+    pub previous_encryption_key: [u8; 32],
+    /// When the rotation happened (Unix timestamp)
+    pub rotated_at: i64,
+    /// Ed25519 signature by the identity's signing key (hex)
+    pub signature: String,
+}
+
+impl KeyRotation {
+    /// Verify the announcement against the sender's signing key
+    pub fn verify(&self, signing_key: &[u8; 32]) -> Result<()> {
+        verify(
+            signing_key,
+            &self.sign_bytes()?,
+            &Signature::from_hex(&self.signature)?,
+        )
+    }
+
+    pub(super) fn sign(
+        identity: &Identity,
+        previous_encryption_key: [u8; 32],
+        rotated_at: i64,
+    ) -> Result<Self> {
+        let mut rotation = Self {
+            did: identity.did_string(),
+            new_encryption_key: identity.keypair().encryption.public_bytes(),
+            previous_encryption_key,
+            rotated_at,
+            signature: String::new(),
+        };
+        rotation.signature = sign(&identity.keypair().signing, &rotation.sign_bytes()?).to_hex();
+        Ok(rotation)
+    }
+
+    fn sign_bytes(&self) -> Result<Vec<u8>> {
+        let data = (
+            &self.did,
+            &self.new_encryption_key,
+            &self.previous_encryption_key,
+            self.rotated_at,
+        );
+        let mut bytes = KEY_ROTATION_DOMAIN.to_vec();
+        bytes.extend(
+            bincode::serialize(&data).map_err(|e| Error::SerializationError(e.to_string()))?,
+        );
+        Ok(bytes)
+    }
+}
+
+/// Our encryption key from before the latest rotation
+pub(super) struct PreviousEncryptionKey {
+    pub(super) keypair: EncryptionKeyPair,
+    pub(super) rotated_at: i64,
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_signature() {
+        let (mut identity, _) = Identity::create("Alice".to_string()).unwrap();
+        let old_key = identity.keypair().encryption.public_bytes();
+        let signing_key = identity.keypair().signing.public_bytes();
+
+        let rotation = identity.rotate_encryption_key().unwrap();
+        assert_eq!(rotation.previous_encryption_key, old_key);
+        assert_eq!(
+            rotation.new_encryption_key,
+            identity.keypair().encryption.public_bytes()
+        );
+        rotation.verify(&signing_key).unwrap();
+
+        let mut forged = rotation.clone();
+        forged.new_encryption_key = EncryptionKeyPair::generate().public_bytes();
+        assert!(forged.verify(&signing_key).is_err());
+
+        let (other, _) = Identity::create("Mallory".to_string()).unwrap();
+        assert!(rotation
+            .verify(&other.keypair().signing.public_bytes())
+            .is_err());
+    }
+
+    #[test]
+    fn test_previous_key_kept_for_grace_period() {
+        let (mut identity, _) = Identity::create("Alice".to_string()).unwrap();
+        assert!(identity.previous_encryption_key().is_none());
+
+        let old_key = identity.keypair().encryption.public_bytes();
+        identity.rotate_encryption_key().unwrap();
+        assert_eq!(
+            identity.previous_encryption_key().unwrap().public_bytes(),
+            old_key
+        );
+
+        assert!(within_grace_period(crate::time::now_timestamp()));
+        assert!(!within_grace_period(
+            crate::time::now_timestamp() - KEY_ROTATION_GRACE_PERIOD - 1
+        ));
+    }
+}
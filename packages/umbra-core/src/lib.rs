@@ -41,6 +41,7 @@
 //! - [`discovery`] - Peer discovery (DHT, bootstrap, direct connect)
 //! - [`friends`] - Friend relationships (requests, management)
 //! - [`messaging`] - Encrypted messaging (conversations, messages)
+//! - [`groups`] - Private group chats (members, invites, group keys)
 //!
 //! ## Security Model
 //!
@@ -96,6 +97,7 @@ pub mod crypto;
 pub mod discovery;
 pub mod error;
 pub mod friends;
+pub mod groups;
 pub mod identity;
 pub mod messaging;
 pub mod network;
@@ -0,0 +1,230 @@
+//! # Device Management
+//!
+//! Local device keys and the signed device lists used for multi-device
+//! fan-out (protocol v3).
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                          LINKING A DEVICE                               │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  New device                          Existing device                   │
+//! │  ──────────                          ───────────────                    │
+//! │  local_device() ──► device keys                                        │
+//! │        │                                                                │
+//! │        └── public keys (QR / sync) ──► link_device()                   │
+//! │                                         • root key issues certificate  │
+//! │                                         • DeviceList version + 1       │
+//! │                                                │                        │
+//! │  install_device_list() ◄──── signed list ──────┘                        │
+//! │                                                                         │
+//! │  Peers pick up the new list from our PublicIdentity and start          │
+//! │  wrapping message keys for the new device.                             │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+
+use crate::crypto::PublicKey;
+use crate::error::{Error, Result};
+use crate::identity::{DeviceCertificate, DeviceKeys, DeviceList, Identity};
+use crate::storage::{Database, LocalDeviceRecord};
+
+/// Name given to the first device of an identity
+const DEFAULT_DEVICE_NAME: &str = "This device";
+
+/// This install's device keys, generated and stored on first use
+pub fn local_device(db: &Database) -> Result<DeviceKeys> {
+    if let Some(record) = db.get_local_device()? {
+        return DeviceKeys::from_secret_bytes(
+            &secret_bytes(&record.signing_secret)?,
+            &secret_bytes(&record.encryption_secret)?,
+        );
+    }
+
+    let keys = DeviceKeys::generate();
+    db.save_local_device(&LocalDeviceRecord {
+        device_id: keys.device_id().to_string(),
+        signing_secret: keys.keypair().signing.secret_bytes().to_vec(),
+        encryption_secret: keys.keypair().encryption.secret_bytes().to_vec(),
+        certificate: None,
+        created_at: crate::time::now_timestamp(),
+    })?;
+
+    tracing::info!("Generated device keys {}", keys.device_id());
+    Ok(keys)
+}
+
+/// Our own signed device list
+///
+/// On first use this creates version 1, containing only this device.
+pub fn own_device_list(identity: &Identity, db: &Database) -> Result<DeviceList> {
+    let did = identity.did_string();
+    if let Some(list) = stored_device_list(db, &did)? {
+        return Ok(list);
+    }
+
+    let device = local_device(db)?;
+    let cert = DeviceCertificate::issue(identity, device.public_keys(), DEFAULT_DEVICE_NAME)?;
+    save_certificate(db, &cert)?;
+
+    let list = DeviceList::new(identity, vec![cert])?;
+    save_list(db, &list)?;
+    Ok(list)
+}
+
+/// Certify another device's keys and add it to our device list
+pub fn link_device(
+    identity: &Identity,
+    db: &Database,
+    public_keys: PublicKey,
+    name: &str,
+) -> Result<DeviceList> {
+    let cert = DeviceCertificate::issue(identity, public_keys, name)?;
+    let list = own_device_list(identity, db)?.with_device(identity, cert)?;
+    save_list(db, &list)?;
+
+    tracing::info!(
+        "Linked device {}",
+        list.devices.last().map_or("", |d| &d.device_id)
+    );
+    Ok(list)
+}
+
+/// Revoke a device from our device list
+///
+/// The revoked ID can never be re-added. This device cannot revoke itself.
+pub fn revoke_device(identity: &Identity, db: &Database, device_id: &str) -> Result<DeviceList> {
+    if local_device(db)?.device_id() == device_id {
+        return Err(Error::InvalidKey("Cannot revoke the current device".into()));
+    }
+
+    let list = own_device_list(identity, db)?.without_device(identity, device_id)?;
+    save_list(db, &list)?;
+
+    tracing::info!("Revoked device {}", device_id);
+    Ok(list)
+}
+
+/// Install our device list on a newly linked device
+///
+/// The list must be signed by our identity and contain this device.
+pub fn install_device_list(identity: &Identity, db: &Database, list: &DeviceList) -> Result<()> {
+    if list.did != identity.did_string() {
+        return Err(Error::InvalidDid(
+            "Device list is for another identity".into(),
+        ));
+    }
+    list.verify()?;
+
+    let device = local_device(db)?;
+    let cert = list
+        .device(device.device_id())
+        .ok_or_else(|| Error::InvalidKey("This device is not in the device list".into()))?;
+    save_certificate(db, cert)?;
+    save_list(db, list)?;
+    Ok(())
+}
+
+/// Store a peer's device list after verifying it
+///
+/// Returns false if we already hold the same or a newer version.
+pub fn import_device_list(db: &Database, list: &DeviceList) -> Result<bool> {
+    list.verify()?;
+    save_list(db, list)
+}
+
+/// The stored device list for a DID, if any
+pub fn stored_device_list(db: &Database, did: &str) -> Result<Option<DeviceList>> {
+    db.get_device_list(did)?
+        .map(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| Error::StorageCorrupted(format!("Invalid device list: {}", e)))
+        })
+        .transpose()
+}
+
+fn save_list(db: &Database, list: &DeviceList) -> Result<bool> {
+    let json = serde_json::to_string(list).map_err(|e| Error::SerializationError(e.to_string()))?;
+    db.save_device_list(&list.did, list.version, &json)
+}
+
+fn save_certificate(db: &Database, cert: &DeviceCertificate) -> Result<()> {
+    let mut record = db
+        .get_local_device()?
+        .ok_or_else(|| Error::StorageCorrupted("Local device keys missing".into()))?;
+    record.certificate =
+        Some(serde_json::to_string(cert).map_err(|e| Error::SerializationError(e.to_string()))?);
+    db.save_local_device(&record)
+}
+
+fn secret_bytes(bytes: &[u8]) -> Result<[u8; 32]> {
+    bytes
+        .try_into()
+        .map_err(|_| Error::StorageCorrupted("Invalid device key length".into()))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup() -> (Identity, Database) {
+        let (identity, _) = Identity::create("Alice".to_string()).unwrap();
+        let db = Database::open(None).await.unwrap();
+        (identity, db)
+    }
+
+    #[tokio::test]
+    async fn test_local_device_is_stable() {
+        let (_, db) = setup().await;
+        let first = local_device(&db).unwrap();
+        let second = local_device(&db).unwrap();
+        assert_eq!(first.device_id(), second.device_id());
+    }
+
+    #[tokio::test]
+    async fn test_link_and_revoke() {
+        let (identity, db) = setup().await;
+        let list = own_device_list(&identity, &db).unwrap();
+        assert_eq!(list.version, 1);
+        assert_eq!(list.devices.len(), 1);
+
+        let laptop = DeviceKeys::generate();
+        let list = link_device(&identity, &db, laptop.public_keys(), "Laptop").unwrap();
+        assert_eq!(list.version, 2);
+        assert!(list.device(laptop.device_id()).is_some());
+
+        let list = revoke_device(&identity, &db, laptop.device_id()).unwrap();
+        assert_eq!(list.version, 3);
+        assert!(list.device(laptop.device_id()).is_none());
+        assert_eq!(own_device_list(&identity, &db).unwrap(), list);
+
+        let own_id = local_device(&db).unwrap().device_id().to_string();
+        assert!(revoke_device(&identity, &db, &own_id).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_keeps_newest() {
+        let (identity, db) = setup().await;
+        let (_, peer_db) = setup().await;
+
+        let v1 = own_device_list(&identity, &db).unwrap();
+        let v2 = link_device(
+            &identity,
+            &db,
+            DeviceKeys::generate().public_keys(),
+            "Phone",
+        )
+        .unwrap();
+
+        assert!(import_device_list(&peer_db, &v2).unwrap());
+        assert!(!import_device_list(&peer_db, &v1).unwrap());
+        assert_eq!(
+            stored_device_list(&peer_db, &identity.did_string()).unwrap(),
+            Some(v2)
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! # Disappearing Messages
+//!
+//! Per-conversation timers after which messages (and files shared in the
+//! conversation) are deleted on both sides.
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                      DISAPPEARING MESSAGES                              │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  Alice                                        Bob                      │
+//! │  ─────                                        ───                       │
+//! │  set_disappearing_timer(1 day)                                         │
+//! │   • DisappearingTimer signed by Alice                                  │
+//! │   • applied locally                                                    │
+//! │        │                                                                │
+//! │        └──── DisappearingTimer (signed) ───►  verify with Alice's      │
+//! │                                               signing key, apply       │
+//! │                                                                         │
+//! │  Every later envelope carries `expires_in`, and each side stores       │
+//! │  messages with expires_at = timestamp + expires_in.                    │
+//! │                                                                         │
+//! │  Sweeper (every SWEEP_INTERVAL):                                       │
+//! │    DELETE messages, reactions, dm_shared_files (+ file_chunks,         │
+//! │    file_manifests) WHERE expires_at <= now                             │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! Timer changes are last-writer-wins by `set_at`, so the two sides converge
+//! if both change the timer at once. A duration of 0 turns the timer off.
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{sign, verify, Signature};
+use crate::error::{Error, Result};
+use crate::identity::Identity;
+use crate::storage::{Database, ExpiredSweep};
+
+/// How often the background sweeper deletes expired messages
+pub const SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Prefix for data signed over a timer change
+const DISAPPEARING_TIMER_DOMAIN: &[u8] = b"umbra-disappearing-timer-v1";
+
+/// Signed change to a conversation's disappearing-message timer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DisappearingTimer {
+    /// The conversation the timer applies to
+    pub conversation_id: String,
+    /// Message lifetime in seconds (0 = off)
+    pub duration_secs: u32,
+    /// DID of whoever changed the timer
+    pub set_by: String,
+    /// When the timer was changed (Unix timestamp ms)
+    pub set_at: i64,
+    /// Ed25519 signature by `set_by`'s signing key (hex)
+    pub signature: String,
+}
+
+impl DisappearingTimer {
+    /// Sign a new timer setting for a conversation (`None` turns it off)
+    pub fn new(identity: &Identity, conversation_id: &str, duration: Option<u32>) -> Result<Self> {
+        let mut timer = Self {
+            conversation_id: conversation_id.to_string(),
+            duration_secs: duration.unwrap_or(0),
+            set_by: identity.did_string(),
+            set_at: crate::time::now_timestamp_millis(),
+            signature: String::new(),
+        };
+        timer.signature = sign(&identity.keypair().signing, &timer.sign_bytes()?).to_hex();
+        Ok(timer)
+    }
+
+    /// The message lifetime, or `None` if the timer is off
+    pub fn duration(&self) -> Option<u32> {
+        (self.duration_secs > 0).then_some(self.duration_secs)
+    }
+
+    /// Verify the setting against the signing key of `set_by`
+    pub fn verify(&self, signing_key: &[u8; 32]) -> Result<()> {
+        verify(
+            signing_key,
+            &self.sign_bytes()?,
+            &Signature::from_hex(&self.signature)?,
+        )
+    }
+
+    fn sign_bytes(&self) -> Result<Vec<u8>> {
+        let data = (
+            &self.conversation_id,
+            self.duration_secs,
+            &self.set_by,
+            self.set_at,
+        );
+        let mut bytes = DISAPPEARING_TIMER_DOMAIN.to_vec();
+        bytes.extend(
+            bincode::serialize(&data).map_err(|e| Error::SerializationError(e.to_string()))?,
+        );
+        Ok(bytes)
+    }
+}
+
+/// Store a timer setting, unless a newer one is already in place
+///
+/// The signature must have been checked by the caller.
+pub fn apply_timer(db: &Database, timer: &DisappearingTimer) -> Result<bool> {
+    db.set_disappearing_timer(&timer.conversation_id, timer.duration(), timer.set_at)
+}
+
+/// Delete everything whose disappearing-message timer has run out
+pub fn sweep_expired(db: &Database) -> Result<ExpiredSweep> {
+    let sweep = db.delete_expired(crate::time::now_timestamp_millis())?;
+    if sweep.messages > 0 || sweep.files > 0 {
+        tracing::debug!(
+            "Deleted {} expired messages and {} expired files",
+            sweep.messages,
+            sweep.files
+        );
+    }
+    Ok(sweep)
+}
+
+/// Sweep expired messages every `interval`, forever
+///
+/// `on_expired` is called after each sweep that deleted something. Spawn
+/// this on the runtime that owns the database; on the web the host calls
+/// [`sweep_expired`] from a timer instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_sweeper(
+    db: std::sync::Arc<Database>,
+    interval: std::time::Duration,
+    on_expired: impl Fn(ExpiredSweep) + Send,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match sweep_expired(&db) {
+            Ok(sweep) if sweep != ExpiredSweep::default() => on_expired(sweep),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Disappearing message sweep failed: {}", e),
+        }
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_signature() {
+        let (alice, _) = Identity::create("Alice".to_string()).unwrap();
+        let signing_key = alice.keypair().signing.public_bytes();
+
+        let timer = DisappearingTimer::new(&alice, "conv-1", Some(3600)).unwrap();
+        assert_eq!(timer.duration(), Some(3600));
+        timer.verify(&signing_key).unwrap();
+
+        let mut forged = timer.clone();
+        forged.duration_secs = 1;
+        assert!(forged.verify(&signing_key).is_err());
+
+        let off = DisappearingTimer::new(&alice, "conv-1", None).unwrap();
+        assert_eq!(off.duration(), None);
+    }
+
+    #[tokio::test]
+    async fn test_expired_messages_and_files_are_swept() {
+        let (alice, _) = Identity::create("Alice".to_string()).unwrap();
+        let db = Database::open(None).await.unwrap();
+        db.create_conversation("conv-1", "did:key:bob").unwrap();
+
+        let timer = DisappearingTimer::new(&alice, "conv-1", Some(60)).unwrap();
+        assert!(apply_timer(&db, &timer).unwrap());
+        // An older setting arriving late does not win
+        let mut stale = timer.clone();
+        stale.set_at -= 1;
+        stale.duration_secs = 0;
+        assert!(!apply_timer(&db, &stale).unwrap());
+        assert_eq!(
+            db.get_conversation("conv-1")
+                .unwrap()
+                .unwrap()
+                .disappearing_timer,
+            Some(60)
+        );
+
+        let now = crate::time::now_timestamp_millis();
+        db.store_message("kept", "conv-1", "did:key:bob", b"hi", &[0; 12], now)
+            .unwrap();
+        assert_eq!(
+            db.get_message("kept").unwrap().unwrap().expires_at,
+            Some(now + 60_000)
+        );
+        db.store_message_with_expiry(
+            "gone",
+            "conv-1",
+            "did:key:bob",
+            b"bye",
+            &[0; 12],
+            now - 120_000,
+            Some(now - 60_000),
+        )
+        .unwrap();
+
+        db.store_chunk("chunk-1", "file-1", 0, b"data", 4, now / 1000)
+            .unwrap();
+        db.store_dm_shared_file(
+            "dm-file-1",
+            "conv-1",
+            None,
+            "a.txt",
+            None,
+            4,
+            None,
+            r#"{"file_id":"file-1"}"#,
+            "did:key:bob",
+            None,
+            None,
+            now / 1000 - 120,
+        )
+        .unwrap();
+
+        let sweep = sweep_expired(&db).unwrap();
+        assert_eq!(
+            sweep,
+            ExpiredSweep {
+                messages: 1,
+                files: 1
+            }
+        );
+        assert!(db.get_message("gone").unwrap().is_none());
+        assert!(db.get_message("kept").unwrap().is_some());
+        assert!(db.get_chunks_for_file("file-1").unwrap().is_empty());
+    }
+}
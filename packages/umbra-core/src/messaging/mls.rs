@@ -0,0 +1,315 @@
+//! # MLS Group Sessions
+//!
+//! Keeps each group's [`MlsGroup`] state in the `mls_groups` table and
+//! turns membership changes into hex-encoded messages for relay delivery.
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                      MLS GROUP LIFECYCLE                                │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  Admin                          Invitee               Other members    │
+//! │  ─────                          ───────               ─────────────    │
+//! │  create_session                                                         │
+//! │   ── group_invite ───────────►                                          │
+//! │                                 new_key_package                         │
+//! │   ◄── group_invite_accept ────  (key package stored                     │
+//! │        { key_package }           with its secrets)                      │
+//! │  add_member                                                             │
+//! │   ── group_mls_welcome ──────►  process_welcome                         │
+//! │   ── group_mls_commit ─────────────────────────────►  process_commit    │
+//! │                                                                         │
+//! │  remove_member / update                                                 │
+//! │   ── group_mls_commit ─────────────────────────────►  process_commit    │
+//! │                                                                         │
+//! │  Messages: encrypt_message / decrypt_message, sent with                 │
+//! │  key_version = MLS_KEY_VERSION                                          │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! Only the admin commits, so commits arrive in order. A member who misses
+//! a commit can't read later messages until it's redelivered; it is never
+//! left on the old epoch's keys without noticing.
+
+use crate::crypto::{
+    Commit, CommitOutcome, KeyPackage, KeyPackageSecrets, MlsCiphertext, MlsGroup, MlsMessage,
+    Welcome,
+};
+use crate::error::{Error, Result};
+use crate::identity::Identity;
+use crate::storage::Database;
+
+/// `key_version` of group messages encrypted with the group's MLS session
+/// (legacy shared group keys start at 1)
+pub const MLS_KEY_VERSION: i32 = 0;
+
+/// Messages produced by a commit, hex encoded for relay delivery
+#[derive(Debug, Clone)]
+pub struct MembershipChange {
+    /// Epoch the group moved to
+    pub epoch: u64,
+    /// Commit for every member of the previous epoch (other than us)
+    pub commit: String,
+    /// Welcome for the added members, if any
+    pub welcome: Option<String>,
+    /// DIDs added by the commit
+    pub added: Vec<String>,
+    /// DIDs removed by the commit
+    pub removed: Vec<String>,
+}
+
+/// AAD binding an MLS group message to its envelope
+pub fn message_aad(group_id: &str, sender_did: &str, timestamp: i64) -> String {
+    format!("group-msg:{}:{}:{}", group_id, sender_did, timestamp)
+}
+
+fn decode_hex<T: MlsMessage>(hex_str: &str) -> Result<T> {
+    let bytes = hex::decode(hex_str)
+        .map_err(|e| Error::DeserializationError(format!("Invalid MLS message hex: {}", e)))?;
+    T::from_bytes(&bytes)
+}
+
+/// Load a group's MLS state, if it has one
+pub fn load(db: &Database, group_id: &str) -> Result<Option<MlsGroup>> {
+    db.get_mls_group(group_id)?
+        .map(|state| MlsGroup::from_bytes(&state))
+        .transpose()
+}
+
+fn load_required(db: &Database, group_id: &str) -> Result<MlsGroup> {
+    load(db, group_id)?
+        .ok_or_else(|| Error::ProtocolError(format!("No MLS session for group {}", group_id)))
+}
+
+fn save(db: &Database, group: &MlsGroup) -> Result<()> {
+    db.save_mls_group(group.group_id(), group.epoch(), &group.to_bytes()?)
+}
+
+/// Whether a group's messages are encrypted with MLS
+pub fn has_session(db: &Database, group_id: &str) -> Result<bool> {
+    Ok(db.get_mls_group(group_id)?.is_some())
+}
+
+/// Start an MLS session for a group we just created
+pub fn create_session(identity: &Identity, db: &Database, group_id: &str) -> Result<()> {
+    let group = MlsGroup::create(group_id, &identity.keypair().signing, &identity.did_string())?;
+    save(db, &group)
+}
+
+/// Drop a group's MLS state (group deleted, or we were removed)
+pub fn delete_session(db: &Database, group_id: &str) -> Result<bool> {
+    db.delete_mls_group(group_id)
+}
+
+/// Generate a key package for a group we're joining
+///
+/// Its secrets are stored until a Welcome for it arrives. Returns the key
+/// package, hex encoded.
+pub fn new_key_package(identity: &Identity, db: &Database) -> Result<String> {
+    let (key_package, secrets) =
+        KeyPackage::generate(&identity.keypair().signing, &identity.did_string());
+    let bytes = key_package.to_bytes()?;
+    db.store_mls_key_package(
+        &hex::encode(key_package.reference()?),
+        &bytes,
+        &secrets.to_bytes()?,
+    )?;
+    Ok(hex::encode(bytes))
+}
+
+fn commit_change(
+    db: &Database,
+    group: &MlsGroup,
+    output: crate::crypto::CommitOutput,
+) -> Result<MembershipChange> {
+    save(db, group)?;
+    Ok(MembershipChange {
+        epoch: group.epoch(),
+        commit: hex::encode(output.commit.to_bytes()?),
+        welcome: output
+            .welcome
+            .map(|w| w.to_bytes().map(hex::encode))
+            .transpose()?,
+        added: output.added,
+        removed: output.removed,
+    })
+}
+
+/// Add `did` to the group from the key package they sent
+pub fn add_member(
+    identity: &Identity,
+    db: &Database,
+    group_id: &str,
+    did: &str,
+    key_package_hex: &str,
+) -> Result<MembershipChange> {
+    let key_package: KeyPackage = decode_hex(key_package_hex)?;
+    if key_package.leaf.did != did {
+        return Err(Error::ProtocolError(format!(
+            "Key package belongs to {}, not {}",
+            key_package.leaf.did, did
+        )));
+    }
+
+    let mut group = load_required(db, group_id)?;
+    let output = group.add_members(&identity.keypair().signing, vec![key_package])?;
+    commit_change(db, &group, output)
+}
+
+/// Remove `did` from the group
+pub fn remove_member(
+    identity: &Identity,
+    db: &Database,
+    group_id: &str,
+    did: &str,
+) -> Result<MembershipChange> {
+    let mut group = load_required(db, group_id)?;
+    let output = group.remove_members(&identity.keypair().signing, &[did])?;
+    commit_change(db, &group, output)
+}
+
+/// Refresh our keys in the group (post-compromise security)
+pub fn update(identity: &Identity, db: &Database, group_id: &str) -> Result<MembershipChange> {
+    let mut group = load_required(db, group_id)?;
+    let output = group.commit(&identity.keypair().signing, Vec::new())?;
+    commit_change(db, &group, output)
+}
+
+/// Join a group from a Welcome sent to one of our key packages
+///
+/// The key package is deleted once used.
+pub fn process_welcome(db: &Database, welcome_hex: &str) -> Result<MlsGroup> {
+    let welcome: Welcome = decode_hex(welcome_hex)?;
+
+    for entry in &welcome.secrets {
+        let reference = hex::encode(entry.key_package_ref);
+        let Some((key_package, secrets)) = db.get_mls_key_package(&reference)? else {
+            continue;
+        };
+        let key_package = KeyPackage::from_bytes(&key_package)?;
+        let secrets = KeyPackageSecrets::from_bytes(&secrets)?;
+
+        let group = MlsGroup::join(&welcome, &key_package, &secrets)?;
+        save(db, &group)?;
+        db.delete_mls_key_package(&reference)?;
+        return Ok(group);
+    }
+
+    Err(Error::ProtocolError(
+        "Welcome is not addressed to any of our key packages".into(),
+    ))
+}
+
+/// Apply a commit from the group admin
+///
+/// If the commit removed us, the group's MLS state is deleted.
+pub fn process_commit(db: &Database, group_id: &str, commit_hex: &str) -> Result<CommitOutcome> {
+    let commit: Commit = decode_hex(commit_hex)?;
+    let mut group = load_required(db, group_id)?;
+
+    let outcome = group.process_commit(&commit)?;
+    match &outcome {
+        CommitOutcome::Applied { .. } => save(db, &group)?,
+        CommitOutcome::Removed { .. } => {
+            delete_session(db, group_id)?;
+        }
+    }
+    Ok(outcome)
+}
+
+/// Encrypt a group message; returns the hex-encoded ciphertext
+pub fn encrypt_message(
+    identity: &Identity,
+    db: &Database,
+    group_id: &str,
+    plaintext: &[u8],
+    aad: &str,
+) -> Result<String> {
+    let mut group = load_required(db, group_id)?;
+    let message = group.encrypt(&identity.keypair().signing, plaintext, aad.as_bytes())?;
+    save(db, &group)?;
+    Ok(hex::encode(message.to_bytes()?))
+}
+
+/// Decrypt a group message; returns the sender's DID and the plaintext
+pub fn decrypt_message(
+    db: &Database,
+    group_id: &str,
+    ciphertext_hex: &str,
+    aad: &str,
+) -> Result<(String, Vec<u8>)> {
+    let message: MlsCiphertext = decode_hex(ciphertext_hex)?;
+    let mut group = load_required(db, group_id)?;
+    let decrypted = group.decrypt(&message, aad.as_bytes())?;
+    save(db, &group)?;
+    Ok(decrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+
+    async fn member(name: &str) -> (Identity, Database) {
+        let (identity, _) = Identity::create(name.to_string()).unwrap();
+        (identity, Database::open(None).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_group_session_lifecycle() {
+        let (alice, alice_db) = member("Alice").await;
+        let (bob, bob_db) = member("Bob").await;
+        let (carol, carol_db) = member("Carol").await;
+
+        create_session(&alice, &alice_db, "group-1").unwrap();
+        assert!(has_session(&alice_db, "group-1").unwrap());
+
+        // Bob joins
+        let key_package = new_key_package(&bob, &bob_db).unwrap();
+        let change =
+            add_member(&alice, &alice_db, "group-1", &bob.did_string(), &key_package).unwrap();
+        let group = process_welcome(&bob_db, change.welcome.as_ref().unwrap()).unwrap();
+        assert_eq!(group.group_id(), "group-1");
+
+        // Carol joins; Bob follows the commit
+        let key_package = new_key_package(&carol, &carol_db).unwrap();
+        assert!(add_member(&alice, &alice_db, "group-1", &bob.did_string(), &key_package).is_err());
+        let change =
+            add_member(&alice, &alice_db, "group-1", &carol.did_string(), &key_package).unwrap();
+        process_welcome(&carol_db, change.welcome.as_ref().unwrap()).unwrap();
+        process_commit(&bob_db, "group-1", &change.commit).unwrap();
+
+        let aad = message_aad("group-1", &carol.did_string(), 1000);
+        let ciphertext = encrypt_message(&carol, &carol_db, "group-1", b"hi", &aad).unwrap();
+        let (sender, plaintext) = decrypt_message(&bob_db, "group-1", &ciphertext, &aad).unwrap();
+        assert_eq!(sender, carol.did_string());
+        assert_eq!(plaintext, b"hi");
+
+        // Removing Bob deletes his session
+        let change = remove_member(&alice, &alice_db, "group-1", &bob.did_string()).unwrap();
+        assert_eq!(change.removed, vec![bob.did_string()]);
+        assert!(matches!(
+            process_commit(&bob_db, "group-1", &change.commit).unwrap(),
+            CommitOutcome::Removed { .. }
+        ));
+        assert!(!has_session(&bob_db, "group-1").unwrap());
+        process_commit(&carol_db, "group-1", &change.commit).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_welcome_needs_a_stored_key_package() {
+        let (alice, alice_db) = member("Alice").await;
+        let (bob, bob_db) = member("Bob").await;
+
+        create_session(&alice, &alice_db, "group-1").unwrap();
+        let key_package = new_key_package(&bob, &bob_db).unwrap();
+        let change =
+            add_member(&alice, &alice_db, "group-1", &bob.did_string(), &key_package).unwrap();
+        let welcome = change.welcome.unwrap();
+
+        process_welcome(&bob_db, &welcome).unwrap();
+        // The key package is single use
+        assert!(process_welcome(&bob_db, &welcome).is_err());
+    }
+}
@@ -94,6 +94,82 @@
 //! └─────────────────────────────────────────────────────────────────────────┘
 //! ```
 //!
+//! ## Forward Secrecy (Protocol v2)
+//!
+//! The flows above describe protocol v1, where every message in a
+//! conversation shares one static ECDH-derived key. Protocol v2 replaces
+//! steps 1–2 with a per-peer Double Ratchet session (see
+//! `crypto::RatchetSession`), so each message gets its own key:
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                      RATCHETED MESSAGING (v2)                           │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  1. Bob publishes a PrekeyBundle with his PublicIdentity               │
+//! │     (MessagingService::public_identity)                                │
+//! │                                                                         │
+//! │  2. Alice runs X3DH against the bundle → RatchetSession                │
+//! │     (MessagingService::establish_session)                              │
+//! │                                                                         │
+//! │  3. Every envelope carries a ratchet header; Alice's envelopes also    │
+//! │     carry the X3DH handshake until Bob first replies                   │
+//! │                                                                         │
+//! │  4. Bob accepts the handshake with his private prekeys, consumes the   │
+//! │     one-time prekey and stores his side of the session                 │
+//! │                                                                         │
+//! │  Sessions live in the `ratchet_sessions` table. Friends without a      │
+//! │  session still get v1 envelopes, and v1 envelopes still decrypt.       │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! Over the relay, bundles travel as `prekey_bundle` payloads (the
+//! `messaging_publish_prekeys` / `messaging_request_prekeys` calls), and
+//! DMs to a friend with a session go out as version 2 `chat_message`
+//! payloads carrying the envelope. Older peers without one still get the
+//! version 1 payload under the static key. See [`OutgoingText`].
+//!
+//! ## Multi-Device Fan-Out (Protocol v3)
+//!
+//! A ratchet session is between two keys, so it cannot reach more than one
+//! device. Until a session exists, and the recipient has published a signed
+//! `DeviceList` (see `identity::DeviceList`), the sender encrypts the
+//! content once under a random content key and wraps that key for every
+//! listed device:
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                      DEVICE FAN-OUT (v3)                                │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  content_key = random 32 bytes                                         │
+//! │  ciphertext  = AES-GCM(content_key, content, aad)                       │
+//! │                                                                         │
+//! │  for each device in recipient's DeviceList:                            │
+//! │      eph        = fresh X25519 keypair                                 │
+//! │      wrap_key   = HKDF(eph × device_key, salt = message id,            │
+//! │                        "umbra-device-fanout-v1")                       │
+//! │      device_keys += { device_id, eph.public,                           │
+//! │                       AES-GCM(wrap_key, content_key, aad = device_id) }│
+//! │                                                                         │
+//! │  Each device finds its own entry, unwraps the content key and          │
+//! │  decrypts. Revoked devices are no longer in the list and get no entry. │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! The wraps use static device keys, so they have no forward secrecy: a
+//! ratchet session always takes precedence over the device list. The
+//! envelope is still signed by the sender's root identity key.
+//!
+//! ## Sealed Sender
+//!
+//! Envelopes carry `sender_did` in the clear. To keep it from the relay,
+//! the serialized envelope can be wrapped in a `SealedEnvelope` and sent
+//! with a per-friend access key instead of an authenticated DID (see the
+//! `sealed` module).
+//!
 //! ## Wire Protocol
 //!
 //! ```text
@@ -104,7 +180,7 @@
 //! │  MessageEnvelope (JSON serialized)                                     │
 //! │  ─────────────────────────────────                                      │
 //! │  {                                                                      │
-//! │    "version": 1,                    // Protocol version                 │
+//! │    "version": 2,                    // Protocol version (1 = legacy)    │
 //! │    "id": "uuid-v4",                 // Unique message ID                │
 //! │    "msg_type": "ChatMessage",       // Type of message                  │
 //! │    "sender_did": "did:key:...",     // Sender's DID                     │
@@ -113,7 +189,11 @@
 //! │    "timestamp": 1234567890,         // Unix timestamp (ms)              │
 //! │    "nonce": "base64...",            // 12-byte nonce (base64)           │
 //! │    "ciphertext": "base64...",       // Encrypted content (base64)       │
-//! │    "signature": "hex..."            // Ed25519 signature (hex)          │
+//! │    "signature": "hex...",           // Ed25519 signature (hex)          │
+//! │    "ratchet": { ... },              // v2: Double Ratchet header        │
+//! │    "x3dh": { ... },                 // v2: handshake (first messages)   │
+//! │    "device_keys": [ ... ],          // v3: per-device wrapped keys      │
+//! │    "expires_in": 86400              // disappearing timer (seconds)     │
 //! │  }                                                                      │
 //! │                                                                         │
 //! │  Message Types:                                                        │
@@ -125,7 +205,14 @@
 //! └─────────────────────────────────────────────────────────────────────────┘
 //! ```
 
+pub mod devices;
+pub mod disappearing;
 pub mod files;
+pub mod mls;
+pub mod sealed;
+
+pub use disappearing::DisappearingTimer;
+pub use sealed::{SealedContent, SealedEnvelope};
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use parking_lot::RwLock;
@@ -133,14 +220,44 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::crypto::{decrypt, encrypt, sign, verify, Nonce, SharedSecret, Signature, NONCE_SIZE};
+use crate::crypto::{
+    decrypt, derive_device_wrap_key, encrypt, sign, sign_prekey, verify, EncryptionKey,
+    EncryptionKeyPair, Nonce, OneTimePrekey, PrekeyBundle, RatchetHeader, RatchetSession,
+    SharedSecret, Signature, X3dhInit, NONCE_SIZE,
+};
 use crate::error::{Error, Result};
 use crate::friends::Friend;
-use crate::identity::Identity;
-use crate::storage::Database;
+use crate::identity::{
+    DeviceCertificate, DeviceKeys, DeviceList, Identity, KeyRotation, PublicIdentity,
+};
+use crate::storage::{Database, PrekeyRecord};
+
+/// Current message protocol version (Double Ratchet sessions)
+pub const MESSAGE_PROTOCOL_VERSION: u8 = 2;
 
-/// Current message protocol version
-pub const MESSAGE_PROTOCOL_VERSION: u8 = 1;
+/// Legacy protocol version (static ECDH key per conversation)
+///
+/// Still produced for friends we have no ratchet session with, and
+/// always accepted on receive.
+pub const LEGACY_MESSAGE_PROTOCOL_VERSION: u8 = 1;
+
+/// Multi-device protocol version (content key wrapped per recipient device)
+pub const FANOUT_MESSAGE_PROTOCOL_VERSION: u8 = 3;
+
+/// Prekey kind for signed prekeys in the `ratchet_prekeys` table
+const PREKEY_SIGNED: &str = "signed";
+
+/// Prekey kind for one-time prekeys in the `ratchet_prekeys` table
+const PREKEY_ONE_TIME: &str = "one_time";
+
+/// Most unused one-time prekeys kept; issuing another drops the oldest
+const MAX_ONE_TIME_PREKEYS: usize = 100;
+
+/// How long a signed prekey is handed out before it is replaced (7 days)
+const SIGNED_PREKEY_ROTATION_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+/// How long a replaced signed prekey still completes handshakes (7 days)
+const SIGNED_PREKEY_GRACE_MS: i64 = 7 * 24 * 60 * 60 * 1000;
 
 /// Maximum message content size (64KB)
 pub const MAX_MESSAGE_SIZE: usize = 64 * 1024;
@@ -164,6 +281,9 @@ pub struct Message {
     pub read: bool,
     /// Delivery status
     pub status: MessageStatus,
+    /// Disappearing-message lifetime in seconds, counted from `timestamp`
+    #[serde(default)]
+    pub expires_in: Option<u32>,
 }
 
 impl Message {
@@ -183,6 +303,7 @@ impl Message {
             timestamp: crate::time::now_timestamp_millis(),
             read: true, // Sender has "read" their own message
             status: MessageStatus::Sending,
+            expires_in: None,
         }
     }
 
@@ -209,6 +330,10 @@ pub enum MessageContent {
         /// The ID of the message that was delivered
         message_id: String,
     },
+    /// Signed announcement that the sender rotated their encryption key
+    KeyRotation(KeyRotation),
+    /// Signed change to the conversation's disappearing-message timer
+    DisappearingTimer(DisappearingTimer),
 }
 
 impl MessageContent {
@@ -229,7 +354,11 @@ impl MessageContent {
     pub fn is_control(&self) -> bool {
         matches!(
             self,
-            Self::Typing | Self::ReadReceipt { .. } | Self::DeliveryReceipt { .. }
+            Self::Typing
+                | Self::ReadReceipt { .. }
+                | Self::DeliveryReceipt { .. }
+                | Self::KeyRotation(_)
+                | Self::DisappearingTimer(_)
         )
     }
 }
@@ -276,6 +405,10 @@ pub enum MessageType {
     ReadReceipt,
     /// Delivery receipt
     DeliveryReceipt,
+    /// Encryption key rotation
+    KeyRotation,
+    /// Disappearing-message timer change
+    DisappearingTimer,
 }
 
 impl MessageType {
@@ -286,6 +419,8 @@ impl MessageType {
             Self::TypingIndicator => 2,
             Self::ReadReceipt => 3,
             Self::DeliveryReceipt => 4,
+            Self::KeyRotation => 5,
+            Self::DisappearingTimer => 6,
         }
     }
 
@@ -296,6 +431,8 @@ impl MessageType {
             2 => Some(Self::TypingIndicator),
             3 => Some(Self::ReadReceipt),
             4 => Some(Self::DeliveryReceipt),
+            5 => Some(Self::KeyRotation),
+            6 => Some(Self::DisappearingTimer),
             _ => None,
         }
     }
@@ -327,32 +464,84 @@ pub struct MessageEnvelope {
     pub ciphertext: String,
     /// Ed25519 signature over envelope data (hex encoded)
     pub signature: String,
+    /// Double Ratchet header (v2 only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ratchet: Option<RatchetHeader>,
+    /// X3DH handshake data, sent until the recipient replies (v2 only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x3dh: Option<X3dhInit>,
+    /// Content key wrapped for each recipient device (v3 only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub device_keys: Vec<DeviceKeyWrap>,
+    /// Disappearing-message lifetime in seconds, enforced by both sides
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<u32>,
+}
+
+/// A text message encrypted for the wire and for local storage
+///
+/// Clients keep DMs in the database as ciphertext under the static
+/// conversation key and decrypt them for display. What goes over the wire
+/// is separate: a signed `MessageEnvelope` (v2/v3) for friends we have a
+/// ratchet session or device list for. Only older peers, which can't read
+/// an envelope, get the stored ciphertext itself in the legacy relay
+/// payload.
+#[derive(Debug, Clone)]
+pub struct OutgoingText {
+    /// Nonce of the stored copy
+    pub nonce: Nonce,
+    /// Ciphertext of the stored copy
+    pub ciphertext: Vec<u8>,
+    /// Envelope to relay, or `None` to fall back to the legacy payload
+    pub envelope: Option<MessageEnvelope>,
+}
+
+/// The message content key, wrapped for one recipient device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceKeyWrap {
+    /// Recipient device ID
+    pub device_id: String,
+    /// Sender's ephemeral X25519 public key (hex)
+    pub ephemeral_key: String,
+    /// AES-GCM nonce (base64)
+    pub nonce: String,
+    /// Encrypted content key (base64)
+    pub wrapped_key: String,
 }
 
 impl MessageEnvelope {
-    /// Create an encrypted envelope from a message
+    /// Create a legacy (v1) encrypted envelope from a message
+    ///
+    /// Uses a static ECDH key for the whole conversation. Prefer
+    /// [`encrypt_ratcheted`](Self::encrypt_ratcheted) whenever a ratchet
+    /// session with the recipient exists.
     pub fn encrypt(
         message: &Message,
         sender_identity: &Identity,
         recipient_encryption_key: &[u8; 32],
     ) -> Result<Self> {
-        // Validate message size
-        let content_bytes = bincode::serialize(&message.content)
-            .map_err(|e| Error::SerializationError(e.to_string()))?;
+        Self::encrypt_with_key(
+            message,
+            sender_identity,
+            &sender_identity.keypair().encryption,
+            recipient_encryption_key,
+        )
+    }
 
-        if content_bytes.len() > MAX_MESSAGE_SIZE {
-            return Err(Error::InvalidMessageContent(format!(
-                "Message too large: {} bytes (max {})",
-                content_bytes.len(),
-                MAX_MESSAGE_SIZE
-            )));
-        }
+    /// Create a legacy (v1) envelope using a specific sender encryption key
+    ///
+    /// Used to announce a key rotation under the key the recipient still
+    /// has on file.
+    pub fn encrypt_with_key(
+        message: &Message,
+        sender_identity: &Identity,
+        sender_encryption_key: &EncryptionKeyPair,
+        recipient_encryption_key: &[u8; 32],
+    ) -> Result<Self> {
+        let content_bytes = serialize_content(&message.content)?;
 
         // Compute shared secret using ECDH
-        let dh_output = sender_identity
-            .keypair()
-            .encryption
-            .diffie_hellman(recipient_encryption_key);
+        let dh_output = sender_encryption_key.diffie_hellman(recipient_encryption_key);
 
         let shared_secret = SharedSecret::from_bytes(dh_output);
 
@@ -371,62 +560,275 @@ impl MessageEnvelope {
         // Encrypt the content
         let (nonce, ciphertext) = encrypt(&encryption_key, &content_bytes, &aad)?;
 
-        // Create the data to sign (everything except signature)
-        let sign_data = EnvelopeSignData {
-            version: MESSAGE_PROTOCOL_VERSION,
-            id: message.id.clone(),
-            msg_type: content_to_msg_type(&message.content),
-            sender_did: message.sender_did.clone(),
-            recipient_did: message.recipient_did.clone(),
-            conversation_id: message.conversation_id.clone(),
-            timestamp: message.timestamp,
-            nonce: BASE64.encode(nonce.as_bytes()),
-            ciphertext: BASE64.encode(&ciphertext),
-        };
+        Self::seal(
+            message,
+            sender_identity,
+            LEGACY_MESSAGE_PROTOCOL_VERSION,
+            &nonce,
+            &ciphertext,
+            None,
+            None,
+            Vec::new(),
+        )
+    }
 
-        let sign_bytes =
-            bincode::serialize(&sign_data).map_err(|e| Error::SerializationError(e.to_string()))?;
+    /// Create a v2 envelope using a Double Ratchet session
+    ///
+    /// Advances the session's sending chain; the caller must persist the
+    /// session afterwards.
+    pub fn encrypt_ratcheted(
+        message: &Message,
+        sender_identity: &Identity,
+        session: &mut RatchetSession,
+    ) -> Result<Self> {
+        let content_bytes = serialize_content(&message.content)?;
 
-        // Sign the envelope
-        let signature = sign(&sender_identity.keypair().signing, &sign_bytes);
+        let aad = build_aad(
+            &message.sender_did,
+            &message.recipient_did,
+            message.timestamp,
+        );
 
-        Ok(Self {
-            version: MESSAGE_PROTOCOL_VERSION,
-            id: message.id.clone(),
-            msg_type: sign_data.msg_type,
-            sender_did: message.sender_did.clone(),
-            recipient_did: message.recipient_did.clone(),
-            conversation_id: message.conversation_id.clone(),
-            timestamp: message.timestamp,
-            nonce: sign_data.nonce,
-            ciphertext: sign_data.ciphertext,
-            signature: signature.to_hex(),
-        })
+        let sealed = session.encrypt(&content_bytes, &aad)?;
+
+        Self::seal(
+            message,
+            sender_identity,
+            MESSAGE_PROTOCOL_VERSION,
+            &sealed.nonce,
+            &sealed.ciphertext,
+            Some(sealed.header),
+            session.pending_init().cloned(),
+            Vec::new(),
+        )
+    }
+
+    /// Create a v3 envelope readable by each of the recipient's devices
+    ///
+    /// `devices` should come from the recipient's verified `DeviceList`.
+    pub fn encrypt_for_devices(
+        message: &Message,
+        sender_identity: &Identity,
+        devices: &[DeviceCertificate],
+    ) -> Result<Self> {
+        if devices.is_empty() {
+            return Err(Error::InvalidKey("Recipient has no devices".into()));
+        }
+
+        let content_bytes = serialize_content(&message.content)?;
+        let aad = build_aad(
+            &message.sender_did,
+            &message.recipient_did,
+            message.timestamp,
+        );
+
+        let mut content_key = zeroize::Zeroizing::new([0u8; 32]);
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, content_key.as_mut());
+        let (nonce, ciphertext) = encrypt(
+            &EncryptionKey::from_bytes(*content_key),
+            &content_bytes,
+            &aad,
+        )?;
+
+        let device_keys = devices
+            .iter()
+            .map(|device| wrap_content_key(&content_key, &message.id, device))
+            .collect::<Result<Vec<_>>>()?;
+
+        Self::seal(
+            message,
+            sender_identity,
+            FANOUT_MESSAGE_PROTOCOL_VERSION,
+            &nonce,
+            &ciphertext,
+            None,
+            None,
+            device_keys,
+        )
     }
 
-    /// Decrypt an envelope and verify its authenticity
+    /// Decrypt a legacy (v1) envelope and verify its authenticity
+    ///
+    /// v2 envelopes need the sender's ratchet session — use
+    /// [`decrypt_ratcheted`](Self::decrypt_ratcheted).
     pub fn decrypt(&self, recipient_identity: &Identity, sender: &Friend) -> Result<Message> {
+        self.decrypt_with_keys(
+            recipient_identity,
+            &recipient_identity.keypair().encryption,
+            sender,
+            &sender.encryption_public_key,
+        )
+    }
+
+    /// Decrypt a legacy (v1) envelope with specific encryption keys
+    ///
+    /// During a key rotation grace window either side may still be using
+    /// its previous key, so the caller picks the pair to try.
+    pub fn decrypt_with_keys(
+        &self,
+        recipient_identity: &Identity,
+        recipient_encryption_key: &EncryptionKeyPair,
+        sender: &Friend,
+        sender_encryption_key: &[u8; 32],
+    ) -> Result<Message> {
         // Verify protocol version
+        match self.version {
+            LEGACY_MESSAGE_PROTOCOL_VERSION => {}
+            MESSAGE_PROTOCOL_VERSION => {
+                return Err(Error::ProtocolError(
+                    "Protocol v2 envelopes require a ratchet session".into(),
+                ));
+            }
+            FANOUT_MESSAGE_PROTOCOL_VERSION => {
+                return Err(Error::ProtocolError(
+                    "Protocol v3 envelopes require device keys".into(),
+                ));
+            }
+            other => {
+                return Err(Error::ProtocolError(format!(
+                    "Unsupported message protocol version: {} (expected {})",
+                    other, MESSAGE_PROTOCOL_VERSION
+                )));
+            }
+        }
+
+        self.verify_sender(recipient_identity, sender)?;
+        let (nonce, ciphertext) = self.decode_payload()?;
+
+        // Compute shared secret using ECDH
+        let dh_output = recipient_encryption_key.diffie_hellman(sender_encryption_key);
+
+        let shared_secret = SharedSecret::from_bytes(dh_output);
+
+        // Derive encryption key for this conversation
+        let encryption_key = shared_secret
+            .derive_key(self.conversation_id.as_bytes())
+            .map_err(|e| Error::KeyExchangeFailed(e.to_string()))?;
+
+        // Build AAD
+        let aad = build_aad(&self.sender_did, &self.recipient_did, self.timestamp);
+
+        // Decrypt
+        let plaintext = decrypt(&encryption_key, &nonce, &ciphertext, &aad)?;
+
+        self.to_message(&plaintext)
+    }
+
+    /// Decrypt a v2 envelope with the sender's ratchet session
+    ///
+    /// The session is only advanced if the envelope verifies and decrypts.
+    pub fn decrypt_ratcheted(
+        &self,
+        recipient_identity: &Identity,
+        sender: &Friend,
+        session: &mut RatchetSession,
+    ) -> Result<Message> {
         if self.version != MESSAGE_PROTOCOL_VERSION {
             return Err(Error::ProtocolError(format!(
-                "Unsupported message protocol version: {} (expected {})",
-                self.version, MESSAGE_PROTOCOL_VERSION
+                "Expected a protocol v{} envelope, got v{}",
+                MESSAGE_PROTOCOL_VERSION, self.version
             )));
         }
 
-        // Verify sender DID matches friend
-        if self.sender_did != sender.did {
-            return Err(Error::InvalidMessageContent("Sender DID mismatch".into()));
-        }
+        let header = self
+            .ratchet
+            .as_ref()
+            .ok_or_else(|| Error::ProtocolError("Missing ratchet header".into()))?;
 
-        // Verify recipient is us
-        if self.recipient_did != recipient_identity.did_string() {
-            return Err(Error::InvalidMessageContent("Message not for us".into()));
+        self.verify_sender(recipient_identity, sender)?;
+        let (nonce, ciphertext) = self.decode_payload()?;
+
+        let aad = build_aad(&self.sender_did, &self.recipient_did, self.timestamp);
+        let plaintext = session.decrypt(header, &nonce, &ciphertext, &aad)?;
+
+        self.to_message(&plaintext)
+    }
+
+    /// Decrypt a v3 envelope with this device's keys
+    pub fn decrypt_for_device(
+        &self,
+        recipient_identity: &Identity,
+        sender: &Friend,
+        device: &DeviceKeys,
+    ) -> Result<Message> {
+        if self.version != FANOUT_MESSAGE_PROTOCOL_VERSION {
+            return Err(Error::ProtocolError(format!(
+                "Expected a protocol v{} envelope, got v{}",
+                FANOUT_MESSAGE_PROTOCOL_VERSION, self.version
+            )));
         }
 
-        // Verify signature
-        let signature = Signature::from_hex(&self.signature)?;
+        let wrap = self
+            .device_keys
+            .iter()
+            .find(|wrap| wrap.device_id == device.device_id())
+            .ok_or_else(|| {
+                Error::DecryptionFailed("Message was not encrypted for this device".into())
+            })?;
+
+        self.verify_sender(recipient_identity, sender)?;
+        let (nonce, ciphertext) = self.decode_payload()?;
+
+        let content_key = unwrap_content_key(wrap, &self.id, device)?;
+        let aad = build_aad(&self.sender_did, &self.recipient_did, self.timestamp);
+        let plaintext = decrypt(&content_key, &nonce, &ciphertext, &aad)?;
 
+        self.to_message(&plaintext)
+    }
+
+    /// Serialize to JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::SerializationError(e.to_string()))
+    }
+
+    /// Deserialize from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::DeserializationError(e.to_string()))
+    }
+
+    /// Build and sign an envelope around already-encrypted content
+    #[allow(clippy::too_many_arguments)]
+    fn seal(
+        message: &Message,
+        sender_identity: &Identity,
+        version: u8,
+        nonce: &Nonce,
+        ciphertext: &[u8],
+        ratchet: Option<RatchetHeader>,
+        x3dh: Option<X3dhInit>,
+        device_keys: Vec<DeviceKeyWrap>,
+    ) -> Result<Self> {
+        let mut envelope = Self {
+            version,
+            id: message.id.clone(),
+            msg_type: content_to_msg_type(&message.content),
+            sender_did: message.sender_did.clone(),
+            recipient_did: message.recipient_did.clone(),
+            conversation_id: message.conversation_id.clone(),
+            timestamp: message.timestamp,
+            nonce: BASE64.encode(nonce.as_bytes()),
+            ciphertext: BASE64.encode(ciphertext),
+            signature: String::new(),
+            ratchet,
+            x3dh,
+            device_keys,
+            expires_in: message.expires_in,
+        };
+
+        // Sign the envelope
+        let signature = sign(&sender_identity.keypair().signing, &envelope.sign_bytes()?);
+        envelope.signature = signature.to_hex();
+
+        Ok(envelope)
+    }
+
+    /// Bytes covered by the envelope signature (everything except the signature)
+    ///
+    /// v1 signs only `EnvelopeSignData`, which keeps old signatures valid;
+    /// v2 additionally covers the ratchet header and X3DH data, and v3 the
+    /// per-device key wraps. A disappearing timer is covered whenever one
+    /// is set.
+    fn sign_bytes(&self) -> Result<Vec<u8>> {
         let sign_data = EnvelopeSignData {
             version: self.version,
             id: self.id.clone(),
@@ -439,12 +841,47 @@ impl MessageEnvelope {
             ciphertext: self.ciphertext.clone(),
         };
 
-        let sign_bytes =
+        let mut bytes =
             bincode::serialize(&sign_data).map_err(|e| Error::SerializationError(e.to_string()))?;
 
-        verify(&sender.signing_public_key, &sign_bytes, &signature)?;
+        if self.version >= MESSAGE_PROTOCOL_VERSION {
+            let session_data = bincode::serialize(&(&self.ratchet, &self.x3dh))
+                .map_err(|e| Error::SerializationError(e.to_string()))?;
+            bytes.extend_from_slice(&session_data);
+        }
+
+        if self.version >= FANOUT_MESSAGE_PROTOCOL_VERSION {
+            let device_data = bincode::serialize(&self.device_keys)
+                .map_err(|e| Error::SerializationError(e.to_string()))?;
+            bytes.extend_from_slice(&device_data);
+        }
+
+        if let Some(expires_in) = self.expires_in {
+            bytes.extend_from_slice(&expires_in.to_be_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    /// Check the envelope is from `sender`, addressed to us and correctly signed
+    fn verify_sender(&self, recipient_identity: &Identity, sender: &Friend) -> Result<()> {
+        // Verify sender DID matches friend
+        if self.sender_did != sender.did {
+            return Err(Error::InvalidMessageContent("Sender DID mismatch".into()));
+        }
+
+        // Verify recipient is us
+        if self.recipient_did != recipient_identity.did_string() {
+            return Err(Error::InvalidMessageContent("Message not for us".into()));
+        }
+
+        // Verify signature
+        let signature = Signature::from_hex(&self.signature)?;
+        verify(&sender.signing_public_key, &self.sign_bytes()?, &signature)
+    }
 
-        // Decode base64 fields
+    /// Decode the base64 nonce and ciphertext
+    fn decode_payload(&self) -> Result<(Nonce, Vec<u8>)> {
         let nonce_bytes = BASE64
             .decode(&self.nonce)
             .map_err(|e| Error::DeserializationError(format!("Invalid nonce: {}", e)))?;
@@ -453,33 +890,16 @@ impl MessageEnvelope {
             .decode(&self.ciphertext)
             .map_err(|e| Error::DeserializationError(format!("Invalid ciphertext: {}", e)))?;
 
-        // Parse nonce
         let nonce_array: [u8; NONCE_SIZE] = nonce_bytes
             .try_into()
             .map_err(|_| Error::DeserializationError("Invalid nonce length".into()))?;
-        let nonce = Nonce::from_bytes(nonce_array);
-
-        // Compute shared secret using ECDH
-        let dh_output = recipient_identity
-            .keypair()
-            .encryption
-            .diffie_hellman(&sender.encryption_public_key);
-
-        let shared_secret = SharedSecret::from_bytes(dh_output);
-
-        // Derive encryption key for this conversation
-        let encryption_key = shared_secret
-            .derive_key(self.conversation_id.as_bytes())
-            .map_err(|e| Error::KeyExchangeFailed(e.to_string()))?;
-
-        // Build AAD
-        let aad = build_aad(&self.sender_did, &self.recipient_did, self.timestamp);
 
-        // Decrypt
-        let plaintext = decrypt(&encryption_key, &nonce, &ciphertext, &aad)?;
+        Ok((Nonce::from_bytes(nonce_array), ciphertext))
+    }
 
-        // Deserialize content
-        let content: MessageContent = bincode::deserialize(&plaintext)
+    /// Build the received message from decrypted content bytes
+    fn to_message(&self, plaintext: &[u8]) -> Result<Message> {
+        let content: MessageContent = bincode::deserialize(plaintext)
             .map_err(|e| Error::DeserializationError(format!("Invalid message content: {}", e)))?;
 
         Ok(Message {
@@ -491,18 +911,87 @@ impl MessageEnvelope {
             timestamp: self.timestamp,
             read: false,
             status: MessageStatus::Delivered,
+            expires_in: self.expires_in,
         })
     }
+}
 
-    /// Serialize to JSON
-    pub fn to_json(&self) -> Result<String> {
-        serde_json::to_string(self).map_err(|e| Error::SerializationError(e.to_string()))
-    }
+/// Convert a stored key to a fixed-size array
+fn key_bytes(bytes: &[u8]) -> Result<[u8; 32]> {
+    bytes
+        .try_into()
+        .map_err(|_| Error::StorageCorrupted("Invalid prekey length".into()))
+}
 
-    /// Deserialize from JSON
-    pub fn from_json(json: &str) -> Result<Self> {
-        serde_json::from_str(json).map_err(|e| Error::DeserializationError(e.to_string()))
+/// Wrap a content key for one device with an ephemeral X25519 key
+fn wrap_content_key(
+    content_key: &[u8; 32],
+    message_id: &str,
+    device: &DeviceCertificate,
+) -> Result<DeviceKeyWrap> {
+    let ephemeral = EncryptionKeyPair::generate();
+    let dh_output = ephemeral.diffie_hellman(&device.public_keys.encryption);
+    let wrap_key = derive_device_wrap_key(&dh_output, message_id.as_bytes())?;
+
+    let (nonce, wrapped) = encrypt(
+        &EncryptionKey::from_bytes(wrap_key),
+        content_key,
+        device.device_id.as_bytes(),
+    )?;
+
+    Ok(DeviceKeyWrap {
+        device_id: device.device_id.clone(),
+        ephemeral_key: hex::encode(ephemeral.public_bytes()),
+        nonce: BASE64.encode(nonce.as_bytes()),
+        wrapped_key: BASE64.encode(wrapped),
+    })
+}
+
+/// Recover the content key from this device's wrap
+fn unwrap_content_key(
+    wrap: &DeviceKeyWrap,
+    message_id: &str,
+    device: &DeviceKeys,
+) -> Result<EncryptionKey> {
+    let ephemeral: [u8; 32] = hex::decode(&wrap.ephemeral_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::DeserializationError("Invalid ephemeral key".into()))?;
+    let nonce: [u8; NONCE_SIZE] = BASE64
+        .decode(&wrap.nonce)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::DeserializationError("Invalid wrap nonce".into()))?;
+    let wrapped = BASE64
+        .decode(&wrap.wrapped_key)
+        .map_err(|e| Error::DeserializationError(format!("Invalid wrapped key: {}", e)))?;
+
+    let dh_output = device.keypair().encryption.diffie_hellman(&ephemeral);
+    let wrap_key = derive_device_wrap_key(&dh_output, message_id.as_bytes())?;
+    let content_key = decrypt(
+        &EncryptionKey::from_bytes(wrap_key),
+        &Nonce::from_bytes(nonce),
+        &wrapped,
+        device.device_id().as_bytes(),
+    )?;
+
+    Ok(EncryptionKey::from_bytes(key_bytes(&content_key)?))
+}
+
+/// Serialize message content, enforcing the size limit
+fn serialize_content(content: &MessageContent) -> Result<Vec<u8>> {
+    let content_bytes =
+        bincode::serialize(content).map_err(|e| Error::SerializationError(e.to_string()))?;
+
+    if content_bytes.len() > MAX_MESSAGE_SIZE {
+        return Err(Error::InvalidMessageContent(format!(
+            "Message too large: {} bytes (max {})",
+            content_bytes.len(),
+            MAX_MESSAGE_SIZE
+        )));
     }
+
+    Ok(content_bytes)
 }
 
 /// Data that gets signed for an envelope
@@ -537,6 +1026,8 @@ fn content_to_msg_type(content: &MessageContent) -> MessageType {
         MessageContent::Typing => MessageType::TypingIndicator,
         MessageContent::ReadReceipt { .. } => MessageType::ReadReceipt,
         MessageContent::DeliveryReceipt { .. } => MessageType::DeliveryReceipt,
+        MessageContent::KeyRotation(_) => MessageType::KeyRotation,
+        MessageContent::DisappearingTimer(_) => MessageType::DisappearingTimer,
     }
 }
 
@@ -561,6 +1052,9 @@ pub struct Conversation {
     pub unread_count: u32,
     /// When conversation was created
     pub created_at: i64,
+    /// Disappearing-message timer in seconds (None = off)
+    #[serde(default)]
+    pub disappearing_timer: Option<u32>,
 }
 
 impl Conversation {
@@ -576,6 +1070,7 @@ impl Conversation {
             last_message_at: None,
             unread_count: 0,
             created_at: crate::time::now_timestamp(),
+            disappearing_timer: None,
         }
     }
 
@@ -641,6 +1136,7 @@ impl MessagingService {
                 last_message_at: record.last_message_at,
                 unread_count: record.unread_count as u32,
                 created_at: record.created_at,
+                disappearing_timer: record.disappearing_timer,
             });
         }
 
@@ -687,16 +1183,16 @@ impl MessagingService {
         let conversation = self.get_or_create_conversation(friend)?;
 
         // Create the message
-        let message = Message::new(
+        let mut message = Message::new(
             conversation.id.clone(),
             self.identity.did_string(),
             friend.did.clone(),
             content,
         );
+        message.expires_in = conversation.disappearing_timer;
 
         // Encrypt it
-        let envelope =
-            MessageEnvelope::encrypt(&message, &self.identity, &friend.encryption_public_key)?;
+        let envelope = self.seal(&message, friend)?;
 
         // Store in database
         self.store_message(&message)?;
@@ -724,7 +1220,7 @@ impl MessagingService {
         );
 
         // Typing indicators are not stored, just encrypted and sent
-        MessageEnvelope::encrypt(&message, &self.identity, &friend.encryption_public_key)
+        self.seal(&message, friend)
     }
 
     /// Send a read receipt
@@ -740,23 +1236,74 @@ impl MessagingService {
             },
         );
 
-        MessageEnvelope::encrypt(&message, &self.identity, &friend.encryption_public_key)
+        self.seal(&message, friend)
     }
 
-    /// Handle a received message envelope
-    pub fn receive_message(
+    /// Announce an encryption key rotation to a friend
+    ///
+    /// On the legacy path the announcement is sealed with our previous key,
+    /// since that is the one the friend still has on file.
+    pub fn send_key_rotation(
         &self,
-        envelope: &MessageEnvelope,
-        sender: &Friend,
-    ) -> Result<Option<Message>> {
-        // Decrypt and verify
-        let message = envelope.decrypt(&self.identity, sender)?;
-
-        // Handle based on content type
-        match &message.content {
-            MessageContent::Text(_) => {
-                // Store the message
-                self.store_message(&message)?;
+        friend: &Friend,
+        rotation: &KeyRotation,
+    ) -> Result<MessageEnvelope> {
+        let conversation = self.get_or_create_conversation(friend)?;
+
+        let message = Message::new(
+            conversation.id,
+            self.identity.did_string(),
+            friend.did.clone(),
+            MessageContent::KeyRotation(rotation.clone()),
+        );
+
+        self.seal(&message, friend)
+    }
+
+    /// Change the disappearing-message timer for our conversation with a
+    /// friend (`None` turns it off)
+    ///
+    /// The new timer applies locally right away; the returned envelope
+    /// carries the signed setting to the friend.
+    pub fn set_disappearing_timer(
+        &self,
+        friend: &Friend,
+        duration: Option<u32>,
+    ) -> Result<(DisappearingTimer, MessageEnvelope)> {
+        let conversation = self.get_or_create_conversation(friend)?;
+        let timer = DisappearingTimer::new(&self.identity, &conversation.id, duration)?;
+        self.apply_disappearing_timer(&timer)?;
+
+        let message = Message::new(
+            conversation.id,
+            self.identity.did_string(),
+            friend.did.clone(),
+            MessageContent::DisappearingTimer(timer.clone()),
+        );
+        let envelope = self.seal(&message, friend)?;
+        self.outbox.write().push(envelope.clone());
+
+        Ok((timer, envelope))
+    }
+
+    /// Handle a received message envelope
+    ///
+    /// Returns stored text messages, timer changes and key rotations. A key
+    /// rotation is not stored here; pass it to
+    /// `FriendsService::handle_key_rotation`.
+    pub fn receive_message(
+        &self,
+        envelope: &MessageEnvelope,
+        sender: &Friend,
+    ) -> Result<Option<Message>> {
+        // Decrypt and verify
+        let message = self.open(envelope, sender)?;
+
+        // Handle based on content type
+        match &message.content {
+            MessageContent::Text(_) => {
+                // Store the message
+                self.store_message(&message)?;
 
                 // Update conversation
                 self.update_conversation_from_message(&message)?;
@@ -777,7 +1324,383 @@ impl MessagingService {
                 self.mark_message_delivered(message_id)?;
                 Ok(None)
             }
+            MessageContent::KeyRotation(_) => {
+                // Applied by the friends service, which owns the friend's keys
+                Ok(Some(message))
+            }
+            MessageContent::DisappearingTimer(timer) => {
+                if timer.set_by != sender.did || timer.conversation_id != message.conversation_id {
+                    return Err(Error::InvalidMessageContent(
+                        "Disappearing timer does not match its envelope".into(),
+                    ));
+                }
+                timer.verify(&sender.signing_public_key)?;
+                self.apply_disappearing_timer(timer)?;
+                Ok(Some(message))
+            }
+        }
+    }
+
+    /// Decrypt and verify a received envelope without acting on it
+    ///
+    /// Ratchet sessions advance (or a new one is accepted) exactly as in
+    /// [`receive_message`](Self::receive_message); storing the message is
+    /// up to the caller.
+    pub fn open(&self, envelope: &MessageEnvelope, sender: &Friend) -> Result<Message> {
+        match envelope.version {
+            LEGACY_MESSAGE_PROTOCOL_VERSION => self.open_legacy(envelope, sender),
+            FANOUT_MESSAGE_PROTOCOL_VERSION => {
+                let device = devices::local_device(&self.database)?;
+                envelope.decrypt_for_device(&self.identity, sender, &device)
+            }
+            _ => self.open_ratcheted(envelope, sender),
+        }
+    }
+
+    /// Encrypt a text message for a friend, for the wire and for storage
+    ///
+    /// `message` carries the ID and timestamp the caller stores it under.
+    /// See [`OutgoingText`].
+    pub fn encrypt_text(&self, message: &Message, friend: &Friend) -> Result<OutgoingText> {
+        let text = message
+            .content
+            .as_text()
+            .ok_or_else(|| Error::InvalidMessageContent("Not a text message".into()))?;
+        let (nonce, ciphertext) = self.encrypt_for_storage(
+            friend,
+            &message.conversation_id,
+            &message.sender_did,
+            message.timestamp,
+            text,
+        )?;
+        let envelope = if self.supports_envelopes(&friend.did)? {
+            Some(self.seal(message, friend)?)
+        } else {
+            None
+        };
+
+        Ok(OutgoingText {
+            nonce,
+            ciphertext,
+            envelope,
+        })
+    }
+
+    /// Encrypt DM text for local storage under the static conversation key
+    ///
+    /// This is the format the clients' `messaging_decrypt` reads back: AAD
+    /// is `sender || recipient || timestamp`, whoever sent it.
+    pub fn encrypt_for_storage(
+        &self,
+        friend: &Friend,
+        conversation_id: &str,
+        sender_did: &str,
+        timestamp: i64,
+        text: &str,
+    ) -> Result<(Nonce, Vec<u8>)> {
+        let our_did = self.identity.did_string();
+        let recipient_did = if sender_did == our_did {
+            friend.did.as_str()
+        } else {
+            our_did.as_str()
+        };
+        let aad = format!("{}{}{}", sender_did, recipient_did, timestamp);
+
+        crate::crypto::encrypt_for_recipient(
+            &self.identity.keypair().encryption,
+            &friend.encryption_public_key,
+            conversation_id.as_bytes(),
+            text.as_bytes(),
+            aad.as_bytes(),
+        )
+    }
+
+    // ========================================================================
+    // RATCHET SESSIONS
+    // ========================================================================
+
+    /// Our public identity, with a fresh prekey bundle and our device list
+    ///
+    /// This is what should be shared with new contacts so they can open a
+    /// ratchet session with us and reach all our devices. Each call issues
+    /// a one-time prekey (see [`prekey_bundle`](Self::prekey_bundle)).
+    pub fn public_identity(&self) -> Result<PublicIdentity> {
+        let bundle = self.prekey_bundle()?;
+        let device_list = devices::own_device_list(&self.identity, &self.database)?;
+        Ok(self
+            .identity
+            .public_identity()
+            .with_prekey_bundle(bundle)
+            .with_device_list(device_list))
+    }
+
+    /// Import the device list a friend published with their profile
+    ///
+    /// Once stored, messages to that friend use protocol v3 until a ratchet
+    /// session with them exists.
+    pub fn import_device_list(&self, friend: &Friend, list: &DeviceList) -> Result<bool> {
+        if list.did != friend.did {
+            return Err(Error::InvalidDid(
+                "Device list is for another identity".into(),
+            ));
+        }
+        devices::import_device_list(&self.database, list)
+    }
+
+    /// Build a prekey bundle for a peer to start a session with us
+    ///
+    /// The signed prekey is replaced every `SIGNED_PREKEY_ROTATION_MS`;
+    /// each call issues a new one-time prekey from a pool capped at
+    /// `MAX_ONE_TIME_PREKEYS`.
+    pub fn prekey_bundle(&self) -> Result<PrekeyBundle> {
+        let signed = self.current_signed_prekey()?;
+        let one_time = self.issue_one_time_prekey()?;
+
+        Ok(PrekeyBundle {
+            identity_key: self.identity.keypair().encryption.public_bytes(),
+            signed_prekey_id: signed.key_id,
+            signed_prekey: key_bytes(&signed.public_key)?,
+            signed_prekey_signature: signed
+                .signature
+                .clone()
+                .ok_or_else(|| Error::StorageCorrupted("Signed prekey has no signature".into()))?,
+            one_time_prekey: Some(OneTimePrekey {
+                id: one_time.key_id,
+                public_key: key_bytes(&one_time.public_key)?,
+            }),
+        })
+    }
+
+    /// Open a ratchet session with a friend from their prekey bundle
+    ///
+    /// All later messages to this friend use protocol v2.
+    pub fn establish_session(&self, friend: &Friend, bundle: &PrekeyBundle) -> Result<()> {
+        if bundle.identity_key != friend.encryption_public_key {
+            return Err(Error::KeyExchangeFailed(
+                "Prekey bundle identity key does not match friend".into(),
+            ));
+        }
+
+        let session = RatchetSession::initiate(
+            &self.identity.keypair().encryption,
+            bundle,
+            &friend.signing_public_key,
+        )?;
+        self.database
+            .save_ratchet_session(&friend.did, &session.to_bytes()?)?;
+
+        tracing::info!("Established ratchet session with {}", friend.did);
+        Ok(())
+    }
+
+    /// Check whether a ratchet session exists with a peer
+    pub fn has_session(&self, peer_did: &str) -> Result<bool> {
+        Ok(self.database.get_ratchet_session(peer_did)?.is_some())
+    }
+
+    /// Whether a peer can read our `MessageEnvelope`s: we have a ratchet
+    /// session with them, or they published a device list
+    ///
+    /// Peers that can't are older clients, which only read the legacy
+    /// relay payload.
+    pub fn supports_envelopes(&self, peer_did: &str) -> Result<bool> {
+        if self.has_session(peer_did)? {
+            return Ok(true);
+        }
+        Ok(devices::stored_device_list(&self.database, peer_did)?
+            .is_some_and(|list| !list.devices.is_empty()))
+    }
+
+    /// Encrypt a message for a friend: ratcheted if we have a session,
+    /// otherwise fanned out to their devices if they published a device
+    /// list, otherwise a legacy (v1) envelope
+    pub fn seal(&self, message: &Message, friend: &Friend) -> Result<MessageEnvelope> {
+        if let Some(mut session) = self.load_session(&friend.did)? {
+            let envelope =
+                MessageEnvelope::encrypt_ratcheted(message, &self.identity, &mut session)?;
+            self.database
+                .save_ratchet_session(&friend.did, &session.to_bytes()?)?;
+            return Ok(envelope);
+        }
+
+        match devices::stored_device_list(&self.database, &friend.did)? {
+            Some(list) if !list.devices.is_empty() => {
+                MessageEnvelope::encrypt_for_devices(message, &self.identity, &list.devices)
+            }
+            _ => {
+                // A rotation has to reach a friend who only knows our old key
+                let sender_key = match (&message.content, self.identity.previous_encryption_key()) {
+                    (MessageContent::KeyRotation(_), Some(previous)) => previous,
+                    _ => &self.identity.keypair().encryption,
+                };
+                MessageEnvelope::encrypt_with_key(
+                    message,
+                    &self.identity,
+                    sender_key,
+                    &friend.encryption_public_key,
+                )
+            }
+        }
+    }
+
+    /// Decrypt a v1 envelope, falling back to pre-rotation keys on either
+    /// side while they are inside the grace window
+    fn open_legacy(&self, envelope: &MessageEnvelope, sender: &Friend) -> Result<Message> {
+        let our_keys = std::iter::once(&self.identity.keypair().encryption)
+            .chain(self.identity.previous_encryption_key());
+        let their_keys: Vec<[u8; 32]> = std::iter::once(sender.encryption_public_key)
+            .chain(sender.previous_encryption_key())
+            .collect();
+
+        let mut last_error = None;
+        for our_key in our_keys {
+            for their_key in &their_keys {
+                match envelope.decrypt_with_keys(&self.identity, our_key, sender, their_key) {
+                    Ok(message) => return Ok(message),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+        }
+        Err(last_error.expect("at least one key pair is tried"))
+    }
+
+    /// Decrypt a v2 envelope, accepting a new session if it carries a handshake
+    ///
+    /// A handshake only replaces a live session if it consumes one of our
+    /// one-time prekeys: those are single-use, so a replayed first message
+    /// can't reset the session. The new session is stored only once it has
+    /// decrypted the message the old one couldn't.
+    fn open_ratcheted(&self, envelope: &MessageEnvelope, sender: &Friend) -> Result<Message> {
+        // Try the existing session first. Decryption is transactional, so a
+        // failure leaves the stored session untouched.
+        let existing_error = match self.load_session(&sender.did)? {
+            Some(mut session) => {
+                match envelope.decrypt_ratcheted(&self.identity, sender, &mut session) {
+                    Ok(message) => {
+                        self.database
+                            .save_ratchet_session(&sender.did, &session.to_bytes()?)?;
+                        return Ok(message);
+                    }
+                    Err(e) => Some(e),
+                }
+            }
+            None => None,
+        };
+
+        // The peer may have started a new session (e.g. after reinstalling)
+        let Some(init) = &envelope.x3dh else {
+            return Err(existing_error.unwrap_or_else(|| {
+                Error::KeyExchangeFailed(format!("No ratchet session with {}", sender.did))
+            }));
+        };
+        if let Some(e) = existing_error.filter(|_| init.one_time_prekey_id.is_none()) {
+            tracing::warn!(
+                "Ignoring handshake without a one-time prekey from {}: session exists",
+                sender.did
+            );
+            return Err(e);
+        }
+
+        let signed_prekey = self
+            .database
+            .get_prekey(PREKEY_SIGNED, init.signed_prekey_id)?
+            .ok_or_else(|| Error::KeyExchangeFailed("Unknown signed prekey".into()))?;
+        let one_time_prekey =
+            match init.one_time_prekey_id {
+                Some(id) => Some(self.database.get_prekey(PREKEY_ONE_TIME, id)?.ok_or_else(
+                    || Error::KeyExchangeFailed("One-time prekey already used".into()),
+                )?),
+                None => None,
+            };
+
+        let signed_pair = EncryptionKeyPair::from_bytes(&key_bytes(&signed_prekey.secret_key)?);
+        let one_time_pair = one_time_prekey
+            .as_ref()
+            .map(|record| key_bytes(&record.secret_key).map(|k| EncryptionKeyPair::from_bytes(&k)))
+            .transpose()?;
+
+        let mut session = RatchetSession::respond(
+            &self.identity.keypair().encryption,
+            &signed_pair,
+            one_time_pair.as_ref(),
+            &sender.encryption_public_key,
+            init,
+        )?;
+        let message = envelope.decrypt_ratcheted(&self.identity, sender, &mut session)?;
+
+        self.database
+            .save_ratchet_session(&sender.did, &session.to_bytes()?)?;
+        if let Some(record) = one_time_prekey {
+            self.database
+                .delete_prekey(PREKEY_ONE_TIME, record.key_id)?;
+        }
+
+        tracing::info!("Accepted ratchet session from {}", sender.did);
+        Ok(message)
+    }
+
+    /// The signed prekey to hand out, rotating it when it's due
+    ///
+    /// Replaced signed prekeys are kept for `SIGNED_PREKEY_GRACE_MS` so
+    /// handshakes from bundles fetched just before the rotation complete.
+    fn current_signed_prekey(&self) -> Result<PrekeyRecord> {
+        let now = crate::time::now_timestamp();
+        let current = match self.database.get_latest_prekey(PREKEY_SIGNED)? {
+            Some(record) if now - record.created_at < SIGNED_PREKEY_ROTATION_MS => record,
+            _ => self.generate_prekey(PREKEY_SIGNED)?,
+        };
+
+        // A signed prekey stops being handed out when its successor is made
+        let signed = self.database.get_prekeys(PREKEY_SIGNED)?;
+        for pair in signed.windows(2) {
+            if now - pair[1].created_at >= SIGNED_PREKEY_GRACE_MS {
+                self.database.delete_prekey(PREKEY_SIGNED, pair[0].key_id)?;
+            }
+        }
+        Ok(current)
+    }
+
+    /// Issue a new one-time prekey, dropping the oldest past the cap
+    fn issue_one_time_prekey(&self) -> Result<PrekeyRecord> {
+        let issued = self.generate_prekey(PREKEY_ONE_TIME)?;
+
+        let unused = self.database.get_prekeys(PREKEY_ONE_TIME)?;
+        let excess = unused.len().saturating_sub(MAX_ONE_TIME_PREKEYS);
+        for record in &unused[..excess] {
+            self.database
+                .delete_prekey(PREKEY_ONE_TIME, record.key_id)?;
         }
+        Ok(issued)
+    }
+
+    /// Load the stored ratchet session for a peer
+    fn load_session(&self, peer_did: &str) -> Result<Option<RatchetSession>> {
+        self.database
+            .get_ratchet_session(peer_did)?
+            .map(|bytes| RatchetSession::from_bytes(&bytes))
+            .transpose()
+    }
+
+    /// Generate and store a new prekey of the given kind
+    fn generate_prekey(&self, kind: &str) -> Result<PrekeyRecord> {
+        let key_id = self.database.next_prekey_id(kind)?;
+        let keypair = EncryptionKeyPair::generate();
+        let public_key = keypair.public_bytes();
+
+        let signature = (kind == PREKEY_SIGNED)
+            .then(|| sign_prekey(&self.identity.keypair().signing, key_id, &public_key));
+
+        let record = PrekeyRecord {
+            kind: kind.to_string(),
+            key_id,
+            secret_key: keypair.secret_bytes().to_vec(),
+            public_key: public_key.to_vec(),
+            signature,
+            created_at: crate::time::now_timestamp(),
+        };
+        self.database.store_prekey(&record)?;
+
+        Ok(record)
     }
 
     /// Get messages for a conversation
@@ -814,6 +1737,9 @@ impl MessagingService {
                 } else {
                     MessageStatus::Sent
                 },
+                expires_in: record
+                    .expires_at
+                    .map(|at| ((at - record.timestamp) / 1000) as u32),
             });
         }
 
@@ -887,6 +1813,20 @@ impl MessagingService {
 
     // Private helper methods
 
+    /// Store a timer setting and refresh the cached conversation
+    fn apply_disappearing_timer(&self, timer: &DisappearingTimer) -> Result<()> {
+        if disappearing::apply_timer(&self.database, timer)? {
+            let mut conversations = self.conversations.write();
+            if let Some(conv) = conversations
+                .iter_mut()
+                .find(|c| c.id == timer.conversation_id)
+            {
+                conv.disappearing_timer = timer.duration();
+            }
+        }
+        Ok(())
+    }
+
     fn store_message(&self, message: &Message) -> Result<()> {
         // Serialize content for storage
         let content_bytes = bincode::serialize(&message.content)
@@ -894,15 +1834,23 @@ impl MessagingService {
 
         // For local storage, we store the plaintext (already decrypted)
         // In production, you might want to encrypt with a local storage key
-        self.database.store_message(
+        self.database.store_message_with_expiry(
             &message.id,
             &message.conversation_id,
             &message.sender_did,
             &content_bytes,
             &[0u8; 12], // Placeholder nonce for stored messages
             message.timestamp,
+            message
+                .expires_in
+                .map(|secs| message.timestamp + secs as i64 * 1000),
         )?;
 
+        // Search indexing is best-effort; it shouldn't fail the send/receive
+        if let MessageContent::Text(text) = &message.content {
+            let _ = self.database.index_message(&message.id, text);
+        }
+
         Ok(())
     }
 
@@ -1031,7 +1979,7 @@ mod tests {
         let envelope =
             MessageEnvelope::encrypt(&message, &alice, &bob_friend.encryption_public_key).unwrap();
 
-        assert_eq!(envelope.version, MESSAGE_PROTOCOL_VERSION);
+        assert_eq!(envelope.version, LEGACY_MESSAGE_PROTOCOL_VERSION);
         assert_eq!(envelope.sender_did, alice.did_string());
         assert_eq!(envelope.recipient_did, bob.did_string());
 
@@ -1220,4 +2168,589 @@ mod tests {
         assert_eq!(service.get_outbox().len(), 1);
         assert_eq!(service.get_outbox()[0].id, envelope.id);
     }
+
+    async fn create_test_service(identity: &Arc<Identity>, friend: &Friend) -> MessagingService {
+        let database = Arc::new(Database::open(None).await.unwrap());
+        database
+            .add_friend(
+                &friend.did,
+                &friend.display_name,
+                &friend.signing_public_key,
+                &friend.encryption_public_key,
+                None,
+            )
+            .unwrap();
+        let service = MessagingService::new(identity.clone(), database);
+        service.get_or_create_conversation(friend).unwrap();
+        service
+    }
+
+    #[tokio::test]
+    async fn test_messaging_service_ratchet_session() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+
+        // Alice opens a session from Bob's published identity
+        let published = bob_service.public_identity().unwrap();
+        let bundle = published.prekey_bundle.expect("bundle attached");
+        alice_service
+            .establish_session(&bob_friend, &bundle)
+            .unwrap();
+        assert!(alice_service.has_session(&bob_friend.did).unwrap());
+
+        let (_, first) = alice_service.send_text(&bob_friend, "Hello Bob!").unwrap();
+        assert_eq!(first.version, MESSAGE_PROTOCOL_VERSION);
+        assert!(first.ratchet.is_some());
+        assert!(first.x3dh.is_some());
+
+        // Bob accepts the handshake
+        let received = bob_service
+            .receive_message(&first, &alice_friend)
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.content, MessageContent::Text("Hello Bob!".into()));
+        assert!(bob_service.has_session(&alice_friend.did).unwrap());
+
+        // Replaying the handshake fails: the one-time prekey is consumed
+        assert!(bob_service.receive_message(&first, &alice_friend).is_err());
+
+        // Bob replies over the session
+        let (_, reply) = bob_service.send_text(&alice_friend, "Hi Alice!").unwrap();
+        assert_eq!(reply.version, MESSAGE_PROTOCOL_VERSION);
+        assert!(reply.x3dh.is_none());
+        let received = alice_service
+            .receive_message(&reply, &bob_friend)
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.content, MessageContent::Text("Hi Alice!".into()));
+
+        // Once Bob has replied, Alice stops sending the handshake
+        let (_, next) = alice_service
+            .send_text(&bob_friend, "How are you?")
+            .unwrap();
+        assert!(next.x3dh.is_none());
+        assert_ne!(
+            next.ratchet.as_ref().unwrap().ratchet_key,
+            first.ratchet.unwrap().ratchet_key
+        );
+        assert!(bob_service
+            .receive_message(&next, &alice_friend)
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_replay_keeps_session() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+
+        // A bundle without a one-time prekey can't be consumed
+        let mut bundle = bob_service.prekey_bundle().unwrap();
+        bundle.one_time_prekey = None;
+        alice_service
+            .establish_session(&bob_friend, &bundle)
+            .unwrap();
+        let (_, first) = alice_service.send_text(&bob_friend, "Hello Bob!").unwrap();
+        assert!(first.x3dh.is_some());
+        bob_service
+            .receive_message(&first, &alice_friend)
+            .unwrap()
+            .unwrap();
+
+        let (_, reply) = bob_service.send_text(&alice_friend, "Hi Alice!").unwrap();
+        alice_service
+            .receive_message(&reply, &bob_friend)
+            .unwrap()
+            .unwrap();
+
+        // A relay replaying the first message must not reset Bob's session
+        assert!(bob_service.receive_message(&first, &alice_friend).is_err());
+        let (_, next) = alice_service
+            .send_text(&bob_friend, "How are you?")
+            .unwrap();
+        assert_eq!(
+            bob_service
+                .receive_message(&next, &alice_friend)
+                .unwrap()
+                .unwrap()
+                .content,
+            MessageContent::Text("How are you?".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_one_time_prekeys_are_bounded() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob_friend = create_test_friend(&create_test_identity("Bob"));
+        let service = create_test_service(&alice, &bob_friend).await;
+
+        let first = service.prekey_bundle().unwrap();
+        for _ in 0..MAX_ONE_TIME_PREKEYS + 5 {
+            service.public_identity().unwrap();
+        }
+        let unused = service.database.get_prekeys(PREKEY_ONE_TIME).unwrap();
+        assert_eq!(unused.len(), MAX_ONE_TIME_PREKEYS);
+
+        // The oldest issued keys were dropped
+        let first_id = first.one_time_prekey.unwrap().id;
+        assert!(service
+            .database
+            .get_prekey(PREKEY_ONE_TIME, first_id)
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_signed_prekey_rotation() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob_friend = create_test_friend(&create_test_identity("Bob"));
+        let service = create_test_service(&alice, &bob_friend).await;
+        let backdate = |key_id: u32, age: i64| {
+            let mut record = service
+                .database
+                .get_prekey(PREKEY_SIGNED, key_id)
+                .unwrap()
+                .unwrap();
+            record.created_at = crate::time::now_timestamp() - age;
+            service.database.store_prekey(&record).unwrap();
+        };
+
+        let first = service.prekey_bundle().unwrap().signed_prekey_id;
+        assert_eq!(service.prekey_bundle().unwrap().signed_prekey_id, first);
+
+        // Once due, a new signed prekey is handed out; the old one stays
+        backdate(first, SIGNED_PREKEY_ROTATION_MS);
+        let second = service.prekey_bundle().unwrap().signed_prekey_id;
+        assert_ne!(second, first);
+        assert!(service
+            .database
+            .get_prekey(PREKEY_SIGNED, first)
+            .unwrap()
+            .is_some());
+
+        // ... until its successor has been out for the grace window
+        let expired = SIGNED_PREKEY_GRACE_MS.max(SIGNED_PREKEY_ROTATION_MS);
+        backdate(second, expired);
+        let third = service.prekey_bundle().unwrap().signed_prekey_id;
+        assert_ne!(third, second);
+        let remaining: Vec<u32> = service
+            .database
+            .get_prekeys(PREKEY_SIGNED)
+            .unwrap()
+            .iter()
+            .map(|record| record.key_id)
+            .collect();
+        assert_eq!(remaining, vec![second, third]);
+    }
+
+    #[tokio::test]
+    async fn test_session_beats_device_list() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+
+        // Both publish a device list along with their prekey bundle
+        let bob_published = bob_service.public_identity().unwrap();
+        let alice_published = alice_service.public_identity().unwrap();
+        alice_service
+            .import_device_list(&bob_friend, &bob_published.device_list.unwrap())
+            .unwrap();
+        bob_service
+            .import_device_list(&alice_friend, &alice_published.device_list.unwrap())
+            .unwrap();
+        alice_service
+            .establish_session(&bob_friend, &bob_published.prekey_bundle.unwrap())
+            .unwrap();
+
+        let (_, first) = alice_service.send_text(&bob_friend, "Hello Bob!").unwrap();
+        assert_eq!(first.version, MESSAGE_PROTOCOL_VERSION);
+        assert!(first.ratchet.is_some());
+        assert!(first.device_keys.is_empty());
+        bob_service
+            .receive_message(&first, &alice_friend)
+            .unwrap()
+            .unwrap();
+
+        let (_, reply) = bob_service.send_text(&alice_friend, "Hi Alice!").unwrap();
+        assert_eq!(reply.version, MESSAGE_PROTOCOL_VERSION);
+        assert!(reply.ratchet.is_some());
+        assert!(alice_service
+            .receive_message(&reply, &bob_friend)
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_messaging_service_legacy_without_session() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+
+        // Without a session, messages fall back to protocol v1
+        let (_, envelope) = alice_service.send_text(&bob_friend, "Hello Bob!").unwrap();
+        assert_eq!(envelope.version, LEGACY_MESSAGE_PROTOCOL_VERSION);
+        assert!(envelope.ratchet.is_none());
+
+        let received = bob_service
+            .receive_message(&envelope, &alice_friend)
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.content, MessageContent::Text("Hello Bob!".into()));
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_text_uses_envelopes_once_a_session_exists() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+        let text = |s: &str| {
+            Message::new(
+                "conv".into(),
+                alice.did_string(),
+                bob.did_string(),
+                MessageContent::text(s),
+            )
+        };
+
+        // An older peer gets no envelope: the stored ciphertext is the payload
+        let message = text("Hello Bob!");
+        let legacy = alice_service.encrypt_text(&message, &bob_friend).unwrap();
+        assert!(legacy.envelope.is_none());
+        let aad = format!("{}{}{}", alice.did_string(), bob.did_string(), message.timestamp);
+        let plaintext = crate::crypto::decrypt_from_sender(
+            &bob.keypair().encryption,
+            &alice_friend.encryption_public_key,
+            b"conv",
+            &legacy.nonce,
+            &legacy.ciphertext,
+            aad.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(plaintext, b"Hello Bob!");
+
+        // With a session the wire copy is a ratcheted envelope
+        let bundle = bob_service.prekey_bundle().unwrap();
+        alice_service
+            .establish_session(&bob_friend, &bundle)
+            .unwrap();
+        assert!(alice_service.supports_envelopes(&bob_friend.did).unwrap());
+        let message = text("Ratcheted");
+        let outgoing = alice_service.encrypt_text(&message, &bob_friend).unwrap();
+        let envelope = outgoing.envelope.unwrap();
+        assert_eq!(envelope.version, MESSAGE_PROTOCOL_VERSION);
+        assert_eq!(envelope.id, message.id);
+
+        // Bob opens it without storing, then keeps his copy the same way
+        let received = bob_service.open(&envelope, &alice_friend).unwrap();
+        assert_eq!(received.content, MessageContent::Text("Ratcheted".into()));
+        assert!(bob_service.get_messages("conv", 10, 0).unwrap().is_empty());
+        let (nonce, ciphertext) = bob_service
+            .encrypt_for_storage(
+                &alice_friend,
+                "conv",
+                &received.sender_did,
+                received.timestamp,
+                "Ratcheted",
+            )
+            .unwrap();
+        let aad = format!("{}{}{}", alice.did_string(), bob.did_string(), message.timestamp);
+        let plaintext = crate::crypto::decrypt_from_sender(
+            &bob.keypair().encryption,
+            &alice_friend.encryption_public_key,
+            b"conv",
+            &nonce,
+            &ciphertext,
+            aad.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(plaintext, b"Ratcheted");
+
+        // Only text goes through encrypt_text
+        let typing = Message::new(
+            "conv".into(),
+            alice.did_string(),
+            bob.did_string(),
+            MessageContent::Typing,
+        );
+        assert!(alice_service.encrypt_text(&typing, &bob_friend).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ratcheted_envelope_tamper_detection() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+
+        let bundle = bob_service.prekey_bundle().unwrap();
+        alice_service
+            .establish_session(&bob_friend, &bundle)
+            .unwrap();
+        let (_, envelope) = alice_service.send_text(&bob_friend, "Hello Bob!").unwrap();
+
+        // v2 envelopes can't be opened without the session
+        assert!(envelope.decrypt(&bob, &alice_friend).is_err());
+
+        // The ratchet header is covered by the signature
+        let mut tampered = envelope.clone();
+        tampered.ratchet.as_mut().unwrap().message_number += 1;
+        assert!(bob_service
+            .receive_message(&tampered, &alice_friend)
+            .is_err());
+
+        // The untouched envelope still goes through
+        assert!(bob_service
+            .receive_message(&envelope, &alice_friend)
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_establish_session_rejects_mismatched_bundle() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let mallory = Arc::new(create_test_identity("Mallory"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let mallory_service = create_test_service(&mallory, &alice_friend).await;
+
+        let bundle = mallory_service.prekey_bundle().unwrap();
+        assert!(alice_service
+            .establish_session(&bob_friend, &bundle)
+            .is_err());
+        assert!(!alice_service.has_session(&bob_friend.did).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_messaging_service_device_fanout() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+
+        // Bob links a laptop, then publishes his profile
+        let laptop = DeviceKeys::generate();
+        devices::link_device(&bob, &bob_service.database, laptop.public_keys(), "Laptop").unwrap();
+        let published = bob_service.public_identity().unwrap();
+        let list = published.device_list.expect("device list attached");
+        assert_eq!(list.devices.len(), 2);
+        assert!(alice_service
+            .import_device_list(&bob_friend, &list)
+            .unwrap());
+
+        let (_, envelope) = alice_service.send_text(&bob_friend, "Hello Bob!").unwrap();
+        assert_eq!(envelope.version, FANOUT_MESSAGE_PROTOCOL_VERSION);
+        assert_eq!(envelope.device_keys.len(), 2);
+
+        // Both of Bob's devices can read it
+        let received = bob_service
+            .receive_message(&envelope, &alice_friend)
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.content, MessageContent::Text("Hello Bob!".into()));
+        let on_laptop = envelope
+            .decrypt_for_device(&bob, &alice_friend, &laptop)
+            .unwrap();
+        assert_eq!(on_laptop.content, received.content);
+
+        // Stripping a device's wrap breaks the signature
+        let mut tampered = envelope.clone();
+        tampered.device_keys.pop();
+        assert!(bob_service
+            .receive_message(&tampered, &alice_friend)
+            .is_err());
+
+        // After revocation the laptop gets no key
+        let revoked =
+            devices::revoke_device(&bob, &bob_service.database, laptop.device_id()).unwrap();
+        alice_service
+            .import_device_list(&bob_friend, &revoked)
+            .unwrap();
+        let (_, envelope) = alice_service
+            .send_text(&bob_friend, "Still there?")
+            .unwrap();
+        assert_eq!(envelope.device_keys.len(), 1);
+        assert!(envelope
+            .decrypt_for_device(&bob, &alice_friend, &laptop)
+            .is_err());
+        assert!(bob_service
+            .receive_message(&envelope, &alice_friend)
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_messaging_service_key_rotation() {
+        let (mut alice, _) = Identity::create("Alice".to_string()).unwrap();
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+
+        // A message Alice sent just before rotating, still in flight
+        let alice_before = Arc::new(alice.clone_for_service().unwrap());
+        let (_, in_flight) = create_test_service(&alice_before, &bob_friend)
+            .await
+            .send_text(&bob_friend, "Sent before rotating")
+            .unwrap();
+
+        let rotation = alice.rotate_encryption_key().unwrap();
+        let alice = Arc::new(alice);
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+
+        // The announcement is readable with the key Bob has on file
+        let envelope = alice_service
+            .send_key_rotation(&bob_friend, &rotation)
+            .unwrap();
+        let received = bob_service
+            .receive_message(&envelope, &alice_friend)
+            .unwrap()
+            .unwrap();
+        let MessageContent::KeyRotation(received_rotation) = received.content else {
+            panic!("expected a key rotation");
+        };
+
+        let bob_friends =
+            crate::friends::FriendsService::new(bob.clone(), bob_service.database.clone());
+        bob_friends.load().unwrap();
+        let updated = bob_friends.handle_key_rotation(&received_rotation).unwrap();
+        assert_eq!(updated.encryption_public_key, rotation.new_encryption_key);
+
+        // New-key messages need the updated friend
+        let (_, envelope) = alice_service.send_text(&bob_friend, "New key").unwrap();
+        assert!(bob_service
+            .receive_message(&envelope, &alice_friend)
+            .is_err());
+        let received = bob_service
+            .receive_message(&envelope, &updated)
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.content, MessageContent::Text("New key".into()));
+
+        // Old-key messages still decrypt during the grace window, both ways
+        let received = bob_service
+            .receive_message(&in_flight, &updated)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            received.content,
+            MessageContent::Text("Sent before rotating".into())
+        );
+
+        let (_, envelope) = bob_service.send_text(&alice_friend, "Old key").unwrap();
+        let received = alice_service
+            .receive_message(&envelope, &bob_friend)
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.content, MessageContent::Text("Old key".into()));
+    }
+
+    #[tokio::test]
+    async fn test_messaging_service_disappearing_timer() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let alice_friend = create_test_friend(&alice);
+        let bob_friend = create_test_friend(&bob);
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let bob_service = create_test_service(&bob, &alice_friend).await;
+
+        let (timer, envelope) = alice_service
+            .set_disappearing_timer(&bob_friend, Some(3600))
+            .unwrap();
+        let received = bob_service
+            .receive_message(&envelope, &alice_friend)
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.content, MessageContent::DisappearingTimer(timer));
+        let conversation_id = received.conversation_id.clone();
+        assert_eq!(
+            bob_service
+                .get_conversation(&conversation_id)
+                .unwrap()
+                .disappearing_timer,
+            Some(3600)
+        );
+
+        // Later messages carry the timer, and it is covered by the signature
+        let (_, envelope) = alice_service.send_text(&bob_friend, "Gone soon").unwrap();
+        assert_eq!(envelope.expires_in, Some(3600));
+        let mut tampered = envelope.clone();
+        tampered.expires_in = None;
+        assert!(bob_service
+            .receive_message(&tampered, &alice_friend)
+            .is_err());
+
+        let received = bob_service
+            .receive_message(&envelope, &alice_friend)
+            .unwrap()
+            .unwrap();
+        let stored = bob_service
+            .database
+            .get_message(&received.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.expires_at, Some(envelope.timestamp + 3_600_000));
+
+        // A timer signed by someone else is rejected
+        let mallory = create_test_identity("Mallory");
+        let forged = DisappearingTimer::new(&mallory, &conversation_id, None).unwrap();
+        let (message, _) = alice_service.send_text(&bob_friend, "placeholder").unwrap();
+        let mut forged_message = message;
+        forged_message.id = Uuid::new_v4().to_string();
+        forged_message.content = MessageContent::DisappearingTimer(forged);
+        let envelope = alice_service.seal(&forged_message, &bob_friend).unwrap();
+        assert!(bob_service
+            .receive_message(&envelope, &alice_friend)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_device_list_rejects_other_identity() {
+        let alice = Arc::new(create_test_identity("Alice"));
+        let bob = Arc::new(create_test_identity("Bob"));
+        let mallory = Arc::new(create_test_identity("Mallory"));
+        let bob_friend = create_test_friend(&bob);
+
+        let alice_service = create_test_service(&alice, &bob_friend).await;
+        let mallory_service = create_test_service(&mallory, &bob_friend).await;
+
+        let list = mallory_service
+            .public_identity()
+            .unwrap()
+            .device_list
+            .unwrap();
+        assert!(alice_service
+            .import_device_list(&bob_friend, &list)
+            .is_err());
+    }
 }
@@ -0,0 +1,321 @@
+//! # Sealed Sender
+//!
+//! Hides who is talking to whom from the relay. The sender's DID and
+//! signature go inside a payload only the recipient can decrypt; the relay
+//! sees the recipient's DID and an access key, nothing about the sender.
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                      SEALED SENDER                                      │
+//! ├─────────────────────────────────────────────────────────────────────────┤
+//! │                                                                         │
+//! │  Alice                                                                  │
+//! │  ─────                                                                  │
+//! │  content    = { sender_did, recipient_did, payload, timestamp,         │
+//! │                 Ed25519_sign(alice_signing_key, ...) }                 │
+//! │  eph        = fresh X25519 keypair                                     │
+//! │  key        = HKDF(eph × bob_x25519_public,                            │
+//! │                    salt = eph.public || bob_x25519_public,             │
+//! │                    "umbra-sealed-sender-v1")                           │
+//! │  sealed     = { eph.public, AES-GCM(key, content) }                    │
+//! │                                                                         │
+//! │  access_key = HKDF(alice_x25519 × bob_x25519, salt = bob_did,          │
+//! │                    "umbra-sealed-access-v1")                           │
+//! │                                                                         │
+//! │  SendSealed { to_did: bob, access_key, payload: sealed } ──► relay     │
+//! │  (on a connection that never registered a DID)                         │
+//! │                                                                         │
+//! │  Bob                                                                    │
+//! │  ───                                                                    │
+//! │  registered SHA-256(access_key) for each friend beforehand, so the     │
+//! │  relay can check the key without learning which friend presented it   │
+//! │                                                                         │
+//! │  open(sealed) → content; look up sender_did, verify its signature      │
+//! │                                                                         │
+//! └─────────────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! The payload is usually a serialized `MessageEnvelope`, which is signed
+//! again on its own; sealing changes what the relay sees, not how messages
+//! are encrypted between friends.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{
+    decrypt, derive_sealed_access_key, derive_sealed_sender_key, encrypt, sign, EncryptionKey,
+    EncryptionKeyPair, Nonce, Signature, NONCE_SIZE,
+};
+use crate::error::{Error, Result};
+use crate::friends::Friend;
+use crate::identity::Identity;
+
+/// Current sealed envelope version
+pub const SEALED_SENDER_VERSION: u8 = 1;
+
+/// Prefix for data signed inside a sealed envelope
+const SEALED_CONTENT_DOMAIN: &[u8] = b"umbra-sealed-content-v1";
+
+/// A sealed-sender envelope, as handed to the relay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedEnvelope {
+    /// Envelope version
+    pub version: u8,
+    /// Sender's ephemeral X25519 public key (hex)
+    pub ephemeral_key: String,
+    /// AES-GCM nonce (base64)
+    pub nonce: String,
+    /// Encrypted `SealedContent` (base64)
+    pub ciphertext: String,
+}
+
+/// What the recipient finds inside a sealed envelope
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedContent {
+    /// Sender's DID
+    pub sender_did: String,
+    /// Recipient's DID, so a friend can't re-seal it to someone else
+    pub recipient_did: String,
+    /// The sealed payload
+    pub payload: String,
+    /// When it was sealed (Unix timestamp ms)
+    pub timestamp: i64,
+    /// Ed25519 signature by the sender's signing key (hex)
+    pub signature: String,
+}
+
+impl SealedContent {
+    /// Verify the signature against the friend named in `sender_did`
+    pub fn verify(&self, sender: &Friend) -> Result<()> {
+        if sender.did != self.sender_did {
+            return Err(Error::VerificationFailed);
+        }
+        sender.verify_signature(&self.sign_bytes()?, &Signature::from_hex(&self.signature)?)
+    }
+
+    fn sign_bytes(&self) -> Result<Vec<u8>> {
+        let data = (
+            &self.sender_did,
+            &self.recipient_did,
+            &self.payload,
+            self.timestamp,
+        );
+        let mut bytes = SEALED_CONTENT_DOMAIN.to_vec();
+        bytes.extend(
+            bincode::serialize(&data).map_err(|e| Error::SerializationError(e.to_string()))?,
+        );
+        Ok(bytes)
+    }
+}
+
+impl SealedEnvelope {
+    /// Sign `payload` as `identity` and seal it to `recipient`
+    pub fn seal(identity: &Identity, recipient: &Friend, payload: &str) -> Result<Self> {
+        let mut content = SealedContent {
+            sender_did: identity.did_string(),
+            recipient_did: recipient.did.clone(),
+            payload: payload.to_string(),
+            timestamp: crate::time::now_timestamp_millis(),
+            signature: String::new(),
+        };
+        content.signature = sign(&identity.keypair().signing, &content.sign_bytes()?).to_hex();
+        let plaintext =
+            serde_json::to_vec(&content).map_err(|e| Error::SerializationError(e.to_string()))?;
+
+        let ephemeral = EncryptionKeyPair::generate();
+        let key = envelope_key(
+            &ephemeral.diffie_hellman(&recipient.encryption_public_key),
+            &ephemeral.public_bytes(),
+            &recipient.encryption_public_key,
+        )?;
+        let (nonce, ciphertext) = encrypt(&key, &plaintext, &[SEALED_SENDER_VERSION])?;
+
+        Ok(Self {
+            version: SEALED_SENDER_VERSION,
+            ephemeral_key: hex::encode(ephemeral.public_bytes()),
+            nonce: BASE64.encode(nonce.as_bytes()),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// Decrypt the envelope with our encryption key (or the one it replaced,
+    /// inside the rotation grace window)
+    ///
+    /// The sender's signature is not checked here: look up the friend named
+    /// in `sender_did` and call [`SealedContent::verify`].
+    pub fn open(&self, identity: &Identity) -> Result<SealedContent> {
+        if self.version != SEALED_SENDER_VERSION {
+            return Err(Error::ProtocolError(format!(
+                "Unsupported sealed envelope version {}",
+                self.version
+            )));
+        }
+
+        let ephemeral: [u8; 32] = hex::decode(&self.ephemeral_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| Error::DeserializationError("Invalid ephemeral key".into()))?;
+        let nonce: [u8; NONCE_SIZE] = BASE64
+            .decode(&self.nonce)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| Error::DeserializationError("Invalid sealed nonce".into()))?;
+        let ciphertext = BASE64
+            .decode(&self.ciphertext)
+            .map_err(|e| Error::DeserializationError(format!("Invalid ciphertext: {}", e)))?;
+
+        let our_keys = std::iter::once(&identity.keypair().encryption)
+            .chain(identity.previous_encryption_key());
+        let plaintext = our_keys
+            .filter_map(|our_key| {
+                let key = envelope_key(
+                    &our_key.diffie_hellman(&ephemeral),
+                    &ephemeral,
+                    &our_key.public_bytes(),
+                )
+                .ok()?;
+                decrypt(
+                    &key,
+                    &Nonce::from_bytes(nonce),
+                    &ciphertext,
+                    &[SEALED_SENDER_VERSION],
+                )
+                .ok()
+            })
+            .next()
+            .ok_or_else(|| Error::DecryptionFailed("Sealed envelope is not for us".into()))?;
+
+        let content: SealedContent = serde_json::from_slice(&plaintext)
+            .map_err(|e| Error::DeserializationError(e.to_string()))?;
+        if content.recipient_did != identity.did_string() {
+            return Err(Error::InvalidMessageContent(
+                "Sealed content is addressed to someone else".into(),
+            ));
+        }
+        Ok(content)
+    }
+
+    /// Serialize for the relay `payload` field
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::SerializationError(e.to_string()))
+    }
+
+    /// Parse a relay `payload` field
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::DeserializationError(e.to_string()))
+    }
+}
+
+/// The access key to present when sending sealed messages to `friend` (hex)
+pub fn access_key_for(identity: &Identity, friend: &Friend) -> Result<String> {
+    let dh_output = identity
+        .keypair()
+        .encryption
+        .diffie_hellman(&friend.encryption_public_key);
+    Ok(hex::encode(derive_sealed_access_key(
+        &dh_output,
+        friend.did.as_bytes(),
+    )?))
+}
+
+/// Hashes of the access keys our friends will present, to register with
+/// the relay
+///
+/// Keys from both sides' pre-rotation encryption keys are included while
+/// they are inside the grace window, so friends who haven't seen a rotation
+/// yet can still deliver.
+pub fn access_hashes(identity: &Identity, friends: &[Friend]) -> Result<Vec<String>> {
+    let did = identity.did_string();
+    let our_keys: Vec<&EncryptionKeyPair> = std::iter::once(&identity.keypair().encryption)
+        .chain(identity.previous_encryption_key())
+        .collect();
+
+    let mut hashes = Vec::new();
+    for friend in friends {
+        let their_keys =
+            std::iter::once(friend.encryption_public_key).chain(friend.previous_encryption_key());
+        for their_key in their_keys {
+            for our_key in &our_keys {
+                let dh_output = our_key.diffie_hellman(&their_key);
+                let access_key = derive_sealed_access_key(&dh_output, did.as_bytes())?;
+                hashes.push(hex::encode(Sha256::digest(access_key)));
+            }
+        }
+    }
+    hashes.sort();
+    hashes.dedup();
+    Ok(hashes)
+}
+
+/// Derive the envelope key for an ephemeral/recipient key pair
+fn envelope_key(
+    dh_output: &[u8; 32],
+    ephemeral_public: &[u8; 32],
+    recipient_public: &[u8; 32],
+) -> Result<EncryptionKey> {
+    let mut salt = ephemeral_public.to_vec();
+    salt.extend_from_slice(recipient_public);
+    Ok(EncryptionKey::from_bytes(derive_sealed_sender_key(
+        dh_output, &salt,
+    )?))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn friend_of(identity: &Identity) -> Friend {
+        Friend::from_public_identity(&identity.public_identity())
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let (alice, _) = Identity::create("Alice".to_string()).unwrap();
+        let (bob, _) = Identity::create("Bob".to_string()).unwrap();
+
+        let sealed = SealedEnvelope::seal(&alice, &friend_of(&bob), "hello").unwrap();
+        let json = sealed.to_json().unwrap();
+        assert!(!json.contains(&alice.did_string()));
+
+        let content = SealedEnvelope::from_json(&json)
+            .unwrap()
+            .open(&bob)
+            .unwrap();
+        assert_eq!(content.sender_did, alice.did_string());
+        assert_eq!(content.payload, "hello");
+        content.verify(&friend_of(&alice)).unwrap();
+
+        // Only Bob can open it, and only Alice's key verifies it
+        let (carol, _) = Identity::create("Carol".to_string()).unwrap();
+        assert!(sealed.open(&carol).is_err());
+        assert!(content.verify(&friend_of(&carol)).is_err());
+
+        let mut forged = content.clone();
+        forged.payload = "goodbye".to_string();
+        assert!(forged.verify(&friend_of(&alice)).is_err());
+    }
+
+    #[test]
+    fn test_access_key_matches_registered_hash() {
+        let (alice, _) = Identity::create("Alice".to_string()).unwrap();
+        let (bob, _) = Identity::create("Bob".to_string()).unwrap();
+        let (carol, _) = Identity::create("Carol".to_string()).unwrap();
+
+        let registered = access_hashes(&bob, &[friend_of(&alice)]).unwrap();
+        let hash_of = |key: &str| hex::encode(Sha256::digest(hex::decode(key).unwrap()));
+
+        let alice_key = access_key_for(&alice, &friend_of(&bob)).unwrap();
+        assert!(registered.contains(&hash_of(&alice_key)));
+
+        // A key for another recipient, or from a non-friend, is not accepted
+        let alice_to_carol = access_key_for(&alice, &friend_of(&carol)).unwrap();
+        let carol_key = access_key_for(&carol, &friend_of(&bob)).unwrap();
+        assert!(!registered.contains(&hash_of(&alice_to_carol)));
+        assert!(!registered.contains(&hash_of(&carol_key)));
+    }
+}
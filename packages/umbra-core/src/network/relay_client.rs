@@ -14,9 +14,94 @@
 //! - **Session management**: Create/join single-scan friend adding sessions
 //!
 //! All message payloads are opaque encrypted blobs — the relay never sees plaintext.
+//!
+//! ## Registration
+//!
+//! ```text
+//! Client                                   Relay
+//!   │── Register { did } ───────────────────►│
+//!   │◄─────────────────── AuthChallenge ─────│  random nonce
+//!   │── Authenticate { signature } ─────────►│  Ed25519 over prefix || nonce
+//!   │◄──────────────────── Registered ───────│  key checked against did:key
+//! ```
+//!
+//! The relay refuses every other message (except `Ping`) until the
+//! challenge has been answered, so nobody can register as — or drain the
+//! offline queue of — a DID they don't hold the key for.
+//!
+//! ## Offline Delivery
+//!
+//! Offline messages are delivered at-least-once. `FetchOffline` returns
+//! everything still queued; the relay only deletes messages once they're
+//! acknowledged with `AckOffline`, so a client that crashes mid-batch gets
+//! them again next time. If a recipient's queue is full, the relay answers
+//! a `Send` with `QueueFull` instead of `Ack`.
+//!
+//! ## Published Invites
+//!
+//! Community owners publish invite codes so they resolve while the owner is
+//! offline. `PublishInvite` carries a signature by the owner's identity key
+//! ([`sign_invite`]); relays check it before storing or replicating the
+//! invite, so nobody can publish or alter an invite in someone else's name.
+//!
+//! ## Push Wake-ups
+//!
+//! A client that may go offline (e.g. a backgrounded mobile app) can
+//! register a UnifiedPush endpoint or webhook with `RegisterPush`. When
+//! messages are queued for it, the relay sends that endpoint a content-free
+//! wake-up — bursts coalesced, held during the user's quiet hours — and the
+//! app connects and fetches its offline queue as usual.
+//!
+//! ## SFU Call Rooms
+//!
+//! Group calls can be created in SFU mode (`CreateCallRoom` with
+//! [`RelayCallRoomMode::Sfu`]) on relays with a selective forwarding unit.
+//! Each participant then negotiates a single peer connection with the SFU
+//! through `SfuOffer`/`SfuAnswer`/`SfuCandidate` instead of one per peer,
+//! and publishes simulcast video the SFU picks a layer from per receiver.
+//! Frames stay end-to-end encrypted with keys the participants exchange
+//! over `CallSignal`; the SFU forwards them without decrypting.
+//!
+//! ## STUN/TURN
+//!
+//! Relays that run a TURN server list it in `Registered`, with credentials
+//! minted for the registered DID that expire after a day by default. Pass
+//! them to `webrtc_transport::set_relay_ice_servers` so peer connections can
+//! fall back to relaying media when no direct path works.
+//!
+//! ## Relay Selection
+//!
+//! Every relay serves a directory of itself and the federated relays it can
+//! reach at `GET /relays`, with each relay's region and load. Clients pick
+//! one with [`RelaySelector`]:
+//!
+//! ```text
+//! GET {seed}/relays ──► entries ──► measure latency ──► rank by score
+//!                                   (host does I/O)     latency + load
+//!
+//! connect(best) ── disconnect ──► next candidate (no delay)
+//!                  ...every candidate failed ──► back off 2s, 4s, ... 30s
+//! ```
+//!
+//! Registration is repeated on every relay the client moves to, so the DID
+//! stays registered across failovers.
+
+use std::time::Duration;
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde::{Deserialize, Serialize};
 
+use crate::crypto::{sign, SigningKeyPair};
+use crate::error::{Error, Result};
+
+/// Domain-separation prefix for relay registration challenges.
+/// Must match the relay server's `AUTH_CHALLENGE_PREFIX`.
+pub const RELAY_AUTH_CHALLENGE_PREFIX: &str = "umbra-relay-auth-v1:";
+
+/// Domain-separation prefix for published invite signatures.
+/// Must match the relay server's `INVITE_SIGNATURE_PREFIX`.
+pub const RELAY_INVITE_SIGNATURE_PREFIX: &str = "umbra-relay-invite-v1:";
+
 /// Messages sent from client to relay server.
 /// Must match the relay server's `ClientMessage` enum.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +112,11 @@ pub enum RelayClientMessage {
         /// The DID to register.
         did: String,
     },
+    /// Answer the relay's `AuthChallenge` (see [`sign_auth_challenge`]).
+    Authenticate {
+        /// Base64 Ed25519 signature over the challenge.
+        signature: String,
+    },
     /// Forward a signaling payload to another peer via the relay.
     Signal {
         /// The recipient's DID.
@@ -41,6 +131,34 @@ pub enum RelayClientMessage {
         /// The encrypted message payload.
         payload: String,
     },
+    /// Send a sealed-sender message. Accepted without registering, so it
+    /// can go over a connection that never reveals our DID.
+    SendSealed {
+        /// The recipient's DID.
+        to_did: String,
+        /// Our access key for the recipient (hex, see `messaging::sealed`).
+        access_key: String,
+        /// The `SealedEnvelope` JSON.
+        payload: String,
+    },
+    /// Replace the sealed-sender access hashes registered for our DID.
+    RegisterSealedAccess {
+        /// SHA-256 of each friend's access key (hex).
+        access_hashes: Vec<String>,
+    },
+    /// Register (or replace) our push endpoint. While we're offline, the
+    /// relay sends it a content-free wake-up when messages are queued.
+    RegisterPush {
+        /// How the endpoint is reached.
+        kind: RelayPushKind,
+        /// The UnifiedPush endpoint or webhook URL (https).
+        endpoint: String,
+        /// Daily window in which wake-ups are held back.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        quiet_hours: Option<RelayQuietHours>,
+    },
+    /// Remove our push endpoint.
+    UnregisterPush,
     /// Create a new single-scan friend-adding session.
     CreateSession {
         /// The SDP offer payload for the session.
@@ -55,12 +173,28 @@ pub enum RelayClientMessage {
     },
     /// Fetch any queued offline messages from the relay.
     FetchOffline,
+    /// Acknowledge offline messages so the relay can delete them.
+    AckOffline {
+        /// IDs of the `OfflineMessageData` entries that were processed.
+        ids: Vec<String>,
+    },
     /// Keepalive ping to the relay server.
     Ping,
+    /// Publish a community invite so its code can be resolved by anyone.
+    PublishInvite {
+        /// The invite being published.
+        #[serde(flatten)]
+        invite: RelayInvite,
+        /// Base64 Ed25519 signature over the invite (see [`sign_invite`]).
+        signature: String,
+    },
     /// Create a new call room for a group.
     CreateCallRoom {
         /// The group identifier for the call room.
         group_id: String,
+        /// Whether media goes peer-to-peer or through the relay's SFU.
+        #[serde(default)]
+        mode: RelayCallRoomMode,
     },
     /// Join an existing call room.
     JoinCallRoom {
@@ -81,6 +215,27 @@ pub enum RelayClientMessage {
         /// The signaling payload (SDP, ICE candidates, etc.).
         payload: String,
     },
+    /// Send an SDP offer to the SFU of an SFU-mode call room.
+    SfuOffer {
+        /// The call room identifier.
+        room_id: String,
+        /// The SDP offer.
+        sdp: String,
+    },
+    /// Answer an offer the SFU sent.
+    SfuAnswer {
+        /// The call room identifier.
+        room_id: String,
+        /// The SDP answer.
+        sdp: String,
+    },
+    /// Send an ICE candidate to the SFU.
+    SfuCandidate {
+        /// The call room identifier.
+        room_id: String,
+        /// `RTCIceCandidateInit` JSON.
+        candidate: String,
+    },
     /// Forward a file transfer protocol message to a peer via the relay.
     /// The payload is a JSON-serialized `FileTransferMessage`.
     FileTransfer {
@@ -96,10 +251,20 @@ pub enum RelayClientMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum RelayServerMessage {
-    /// Confirmation that the client has been registered.
+    /// Challenge issued after `Register`; answer with `Authenticate`.
+    AuthChallenge {
+        /// The nonce to sign.
+        nonce: String,
+    },
+    /// Confirmation that the client has been registered and authenticated.
     Registered {
         /// The registered DID.
         did: String,
+        /// The relay's STUN/TURN servers, with TURN credentials for this
+        /// DID (empty if the relay doesn't run one). See
+        /// `webrtc_transport::set_relay_ice_servers`.
+        #[serde(default)]
+        ice_servers: Vec<RelayIceServer>,
     },
     /// A forwarded signaling payload from another peer.
     Signal {
@@ -117,6 +282,18 @@ pub enum RelayServerMessage {
         /// Unix timestamp of when the message was sent.
         timestamp: i64,
     },
+    /// An incoming sealed-sender message; the sender is inside the payload.
+    SealedMessage {
+        /// The `SealedEnvelope` JSON.
+        payload: String,
+        /// Unix timestamp of when the message was sent.
+        timestamp: i64,
+    },
+    /// Confirmation of `RegisterSealedAccess`.
+    SealedAccessRegistered {
+        /// How many access hashes the relay stored.
+        count: usize,
+    },
     /// Confirmation that a session was created.
     SessionCreated {
         /// The newly created session identifier.
@@ -141,16 +318,28 @@ pub enum RelayServerMessage {
         offer_payload: String,
     },
     /// A batch of offline messages that were queued while the client was away.
+    /// They stay queued on the relay until acknowledged with `AckOffline`.
     OfflineMessages {
         /// The list of offline messages.
         messages: Vec<OfflineMessageData>,
     },
+    /// The recipient's offline queue is full; the message was not queued.
+    QueueFull {
+        /// The recipient whose queue is full.
+        to_did: String,
+    },
     /// Keepalive pong response from the relay server.
     Pong,
     /// An error message from the relay server.
     Error {
         /// The error description.
         message: String,
+        /// Machine-readable error code (e.g. `rate_limited`).
+        #[serde(default)]
+        code: Option<String>,
+        /// How long to wait before retrying, if the request may be retried.
+        #[serde(default)]
+        retry_after_ms: Option<u64>,
     },
     /// Acknowledgment of a previously sent message.
     Ack {
@@ -163,6 +352,9 @@ pub enum RelayServerMessage {
         room_id: String,
         /// The group identifier associated with the room.
         group_id: String,
+        /// Whether media goes peer-to-peer or through the relay's SFU.
+        #[serde(default)]
+        mode: RelayCallRoomMode,
     },
     /// Notification that a participant joined a call room.
     CallParticipantJoined {
@@ -187,6 +379,34 @@ pub enum RelayServerMessage {
         /// The signaling payload.
         payload: String,
     },
+    /// An SDP offer from the SFU (tracks to receive changed).
+    SfuOffer {
+        /// The call room identifier.
+        room_id: String,
+        /// The SDP offer.
+        sdp: String,
+    },
+    /// The SFU's answer to our offer.
+    SfuAnswer {
+        /// The call room identifier.
+        room_id: String,
+        /// The SDP answer.
+        sdp: String,
+    },
+    /// An ICE candidate of the SFU.
+    SfuCandidate {
+        /// The call room identifier.
+        room_id: String,
+        /// `RTCIceCandidateInit` JSON.
+        candidate: String,
+    },
+    /// The dominant speaker of an SFU call room changed.
+    CallDominantSpeaker {
+        /// The call room identifier.
+        room_id: String,
+        /// The speaker's DID.
+        did: String,
+    },
     /// A forwarded file transfer protocol message from another peer.
     FileTransferMessage {
         /// The sender's DID.
@@ -196,12 +416,159 @@ pub enum RelayServerMessage {
     },
 }
 
+impl RelayClientMessage {
+    /// Build the `Authenticate` reply to a relay `AuthChallenge`.
+    pub fn authenticate(signing_key: &SigningKeyPair, nonce: &str) -> Self {
+        Self::Authenticate {
+            signature: sign_auth_challenge(signing_key, nonce),
+        }
+    }
+
+    /// Build a `PublishInvite` signed by the community owner.
+    /// `publisher_did` must be the DID we registered with.
+    pub fn publish_invite(
+        signing_key: &SigningKeyPair,
+        publisher_did: &str,
+        invite: RelayInvite,
+    ) -> Self {
+        let signature = sign_invite(signing_key, publisher_did, &invite);
+        Self::PublishInvite { invite, signature }
+    }
+}
+
+/// Sign a relay registration challenge with our identity's signing key.
+///
+/// Returns the base64 signature over `RELAY_AUTH_CHALLENGE_PREFIX || nonce`.
+/// The relay verifies it against the key embedded in our `did:key`.
+pub fn sign_auth_challenge(signing_key: &SigningKeyPair, nonce: &str) -> String {
+    let message = format!("{}{}", RELAY_AUTH_CHALLENGE_PREFIX, nonce);
+    BASE64.encode(sign(signing_key, message.as_bytes()).as_bytes())
+}
+
+/// How media flows in a call room. Must match the relay server's
+/// `CallRoomMode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayCallRoomMode {
+    /// Participants connect to each other directly.
+    #[default]
+    Mesh,
+    /// Participants connect to the relay's SFU.
+    Sfu,
+}
+
+/// A STUN/TURN server run by the relay, in the shape of WebRTC's
+/// `RTCIceServer`. Must match the relay server's `IceServer`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayIceServer {
+    /// `stun:` and `turn:` URLs.
+    pub urls: Vec<String>,
+    /// TURN username (`<expiry unix time>:<did>`).
+    pub username: String,
+    /// TURN password, valid until the expiry in the username.
+    pub credential: String,
+}
+
+/// How a push endpoint registered with the relay is reached.
+/// Must match the relay server's `PushKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayPushKind {
+    /// A UnifiedPush endpoint issued by the user's distributor.
+    UnifiedPush,
+    /// A plain HTTPS webhook.
+    Webhook,
+}
+
+/// A daily window in local time during which the relay holds back push
+/// wake-ups. Must match the relay server's `QuietHours`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayQuietHours {
+    /// Start, in minutes after local midnight.
+    pub start_minute: u16,
+    /// End, in minutes after local midnight (earlier than the start for a
+    /// window spanning midnight).
+    pub end_minute: u16,
+    /// Local offset from UTC in minutes.
+    pub utc_offset_minutes: i16,
+}
+
+/// A community invite published to the relay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayInvite {
+    /// The invite code.
+    pub code: String,
+    /// The community the invite joins.
+    pub community_id: String,
+    /// Community name shown in the invite preview.
+    pub community_name: String,
+    /// Community description shown in the invite preview.
+    pub community_description: Option<String>,
+    /// Community icon URL shown in the invite preview.
+    pub community_icon: Option<String>,
+    /// Current member count.
+    pub member_count: u32,
+    /// Maximum number of uses (`None` or 0 for unlimited).
+    pub max_uses: Option<i32>,
+    /// Expiry as a Unix timestamp in milliseconds.
+    pub expires_at: Option<i64>,
+    /// The data a joiner needs to bootstrap the community.
+    pub invite_payload: String,
+}
+
+/// The invite fields the relay checks the signature against, in the
+/// relay's order. Use count and publish time are kept by the relays.
+#[derive(Serialize)]
+struct SignedInviteFields<'a> {
+    code: &'a str,
+    publisher_did: &'a str,
+    community_id: &'a str,
+    community_name: &'a str,
+    community_description: Option<&'a str>,
+    community_icon: Option<&'a str>,
+    member_count: u32,
+    max_uses: Option<i32>,
+    expires_at: Option<i64>,
+    invite_payload: &'a str,
+}
+
+/// Sign an invite for publishing with our identity's signing key.
+///
+/// Returns the base64 signature over `RELAY_INVITE_SIGNATURE_PREFIX`
+/// followed by the signed fields as compact JSON. Relays verify it against
+/// the key in `publisher_did` and drop invites that don't match.
+pub fn sign_invite(
+    signing_key: &SigningKeyPair,
+    publisher_did: &str,
+    invite: &RelayInvite,
+) -> String {
+    let message = invite_signing_message(publisher_did, invite);
+    BASE64.encode(sign(signing_key, message.as_bytes()).as_bytes())
+}
+
+fn invite_signing_message(publisher_did: &str, invite: &RelayInvite) -> String {
+    let fields = SignedInviteFields {
+        code: &invite.code,
+        publisher_did,
+        community_id: &invite.community_id,
+        community_name: &invite.community_name,
+        community_description: invite.community_description.as_deref(),
+        community_icon: invite.community_icon.as_deref(),
+        member_count: invite.member_count,
+        max_uses: invite.max_uses,
+        expires_at: invite.expires_at,
+        invite_payload: &invite.invite_payload,
+    };
+    let json = serde_json::to_string(&fields).unwrap_or_default();
+    format!("{}{}", RELAY_INVITE_SIGNATURE_PREFIX, json)
+}
+
 /// An offline message received from the relay.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OfflineMessageData {
     /// Unique message identifier.
     pub id: String,
-    /// The sender's DID.
+    /// The sender's DID (empty for sealed-sender messages).
     pub from_did: String,
     /// The encrypted message payload.
     pub payload: String,
@@ -209,6 +576,25 @@ pub struct OfflineMessageData {
     pub timestamp: i64,
 }
 
+/// A payload to send to one DID through the relay
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RelayMessage {
+    /// Recipient DID
+    pub to_did: String,
+    /// Serialized envelope
+    pub payload: String,
+}
+
+impl RelayMessage {
+    /// Address `payload` to `to_did`
+    pub fn new(to_did: &str, payload: &str) -> Self {
+        Self {
+            to_did: to_did.to_string(),
+            payload: payload.to_string(),
+        }
+    }
+}
+
 /// Relay connection status.
 #[derive(Debug, Clone, PartialEq)]
 pub enum RelayStatus {
@@ -218,12 +604,225 @@ pub enum RelayStatus {
     Connecting,
     /// WebSocket connection established but not yet registered.
     Connected,
+    /// Registration sent; answering the relay's auth challenge.
+    Authenticating,
     /// Registered with the relay server and ready for operations.
     Registered,
     /// Connection is in an error state.
     Error(String),
 }
 
+// ============================================================================
+// RELAY SELECTION
+// ============================================================================
+
+/// Path of the relay directory on a relay's HTTP API.
+pub const RELAY_DIRECTORY_PATH: &str = "/relays";
+
+/// Delay before retrying after every candidate relay has failed once.
+pub const RELAY_RECONNECT_MIN_DELAY: Duration = Duration::from_secs(2);
+
+/// Upper bound for the reconnect backoff.
+pub const RELAY_RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Connected clients that add 1 ms to a relay's score.
+const CLIENTS_PER_PENALTY_MS: usize = 10;
+
+/// Queued offline messages that add 1 ms to a relay's score.
+const QUEUED_PER_PENALTY_MS: usize = 1000;
+
+/// How busy a relay is, as reported by the directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayLoad {
+    /// Clients connected to the relay.
+    pub online_count: usize,
+    /// Messages waiting in the relay's offline queue.
+    pub offline_queue_size: usize,
+}
+
+/// A relay listed by the `GET /relays` directory.
+/// Must match the relay server's `RelayDirectoryEntry`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayDirectoryEntry {
+    /// Relay ID (`None` for a relay that is not federated).
+    #[serde(default)]
+    pub relay_id: Option<String>,
+    /// Public WebSocket URL (`None` means the relay serving the directory).
+    #[serde(default)]
+    pub relay_url: Option<String>,
+    /// Human-readable region.
+    pub region: String,
+    /// Human-readable location.
+    pub location: String,
+    /// Current load, if known.
+    #[serde(default)]
+    pub load: Option<RelayLoad>,
+}
+
+/// Parse a `GET /relays` response fetched from the relay at `seed_url`.
+///
+/// Entries without a URL refer to the seed itself and get `seed_url`;
+/// duplicate URLs are dropped.
+pub fn parse_relay_directory(json: &str, seed_url: &str) -> Result<Vec<RelayDirectoryEntry>> {
+    #[derive(Deserialize)]
+    struct Directory {
+        relays: Vec<RelayDirectoryEntry>,
+    }
+
+    let directory: Directory =
+        serde_json::from_str(json).map_err(|e| Error::DeserializationError(e.to_string()))?;
+
+    let mut entries: Vec<RelayDirectoryEntry> = Vec::with_capacity(directory.relays.len());
+    for mut entry in directory.relays {
+        if entry.relay_url.as_deref().map_or(true, str::is_empty) {
+            entry.relay_url = Some(seed_url.to_string());
+        }
+        if !entries.iter().any(|e| e.relay_url == entry.relay_url) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// The HTTP base URL of a relay, from its WebSocket URL.
+///
+/// `wss://relay.umbra.chat/ws` becomes `https://relay.umbra.chat`.
+pub fn relay_http_url(ws_url: &str) -> String {
+    let url = ws_url.trim_end_matches('/');
+    let url = url.strip_suffix("/ws").unwrap_or(url);
+    if let Some(rest) = url.strip_prefix("wss://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("ws://") {
+        format!("http://{}", rest)
+    } else {
+        url.to_string()
+    }
+}
+
+/// A relay the client could connect to, with its measured latency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayCandidate {
+    /// The directory entry.
+    #[serde(flatten)]
+    pub relay: RelayDirectoryEntry,
+    /// Round-trip time to the relay in ms (`None` if it didn't answer).
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+}
+
+impl RelayCandidate {
+    /// The relay's WebSocket URL.
+    pub fn url(&self) -> &str {
+        self.relay.relay_url.as_deref().unwrap_or_default()
+    }
+
+    /// Lower is better: latency plus a penalty for load.
+    /// `None` if the relay didn't answer.
+    pub fn score(&self) -> Option<u64> {
+        let latency = self.latency_ms?;
+        let penalty = self.relay.load.map_or(0, |load| {
+            load.online_count / CLIENTS_PER_PENALTY_MS
+                + load.offline_queue_size / QUEUED_PER_PENALTY_MS
+        });
+        Some(latency.saturating_add(penalty as u64))
+    }
+}
+
+/// Order candidates best first. Relays that didn't answer go last, in
+/// their original order.
+pub fn rank_relays(mut candidates: Vec<RelayCandidate>) -> Vec<RelayCandidate> {
+    candidates.sort_by_key(|c| (c.score().is_none(), c.score()));
+    candidates
+}
+
+/// Picks which relay to connect to, failing over and backing off.
+///
+/// Call [`next_relay`](Self::next_relay) for the relay to (re)connect to,
+/// and [`connected`](Self::connected) once registered with it.
+#[derive(Debug, Clone)]
+pub struct RelaySelector {
+    candidates: Vec<RelayCandidate>,
+    current: Option<usize>,
+    /// Candidates tried since the round started
+    tried: usize,
+    /// Rounds in a row in which every candidate failed
+    failed_rounds: u32,
+}
+
+impl RelaySelector {
+    /// Create a selector over the given candidates (ranked here).
+    pub fn new(candidates: Vec<RelayCandidate>) -> Self {
+        Self {
+            candidates: rank_relays(candidates),
+            current: None,
+            tried: 0,
+            failed_rounds: 0,
+        }
+    }
+
+    /// The ranked candidates.
+    pub fn candidates(&self) -> &[RelayCandidate] {
+        &self.candidates
+    }
+
+    /// The relay most recently handed out by `next_relay`.
+    pub fn current(&self) -> Option<&RelayCandidate> {
+        self.current.map(|i| &self.candidates[i])
+    }
+
+    /// The relay to connect to next, and how long to wait first.
+    ///
+    /// The first call returns the best relay. After that each call moves
+    /// on to the next candidate without waiting, until every candidate
+    /// has failed since the last successful connection. The next round
+    /// then starts again from the best relay, after an exponential backoff.
+    pub fn next_relay(&mut self) -> Option<(RelayCandidate, Duration)> {
+        if self.candidates.is_empty() {
+            return None;
+        }
+        let mut delay = Duration::ZERO;
+        if self.exhausted() {
+            self.failed_rounds += 1;
+            self.tried = 0;
+            self.current = None;
+            delay = reconnect_backoff(self.failed_rounds);
+        }
+        let index = self.current.map_or(0, |i| (i + 1) % self.candidates.len());
+        self.tried += 1;
+        self.current = Some(index);
+        Some((self.candidates[index].clone(), delay))
+    }
+
+    /// Whether every candidate has failed since the last success, which
+    /// is a good time to fetch the directory again.
+    pub fn exhausted(&self) -> bool {
+        self.tried >= self.candidates.len()
+    }
+
+    /// Record a successful connection to the current relay.
+    pub fn connected(&mut self) {
+        self.tried = 0;
+        self.failed_rounds = 0;
+    }
+
+    /// Replace the candidates (after fetching the directory again).
+    ///
+    /// The next relay handed out is the new best one. If the last round
+    /// had failed, the backoff still applies.
+    pub fn refresh(&mut self, candidates: Vec<RelayCandidate>) {
+        let exhausted = self.exhausted();
+        self.candidates = rank_relays(candidates);
+        self.current = None;
+        self.tried = if exhausted { self.candidates.len() } else { 0 };
+    }
+}
+
+/// Backoff before the given round of reconnect attempts (1-based).
+fn reconnect_backoff(round: u32) -> Duration {
+    let factor = 1u32 << round.saturating_sub(1).min(16);
+    (RELAY_RECONNECT_MIN_DELAY * factor).min(RELAY_RECONNECT_MAX_DELAY)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,6 +837,80 @@ mod tests {
         assert!(json.contains("did:key:z6MkTest"));
     }
 
+    #[test]
+    fn test_relay_client_message_authenticate() {
+        use crate::crypto::verify;
+        use crate::crypto::Signature;
+
+        let signing_key = SigningKeyPair::generate();
+        let msg = RelayClientMessage::authenticate(&signing_key, "nonce-123");
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"authenticate\""));
+
+        let RelayClientMessage::Authenticate { signature } = msg else {
+            panic!("Wrong variant");
+        };
+        let bytes: [u8; 64] = BASE64.decode(signature).unwrap().try_into().unwrap();
+        let signed = format!("{}nonce-123", RELAY_AUTH_CHALLENGE_PREFIX);
+        assert!(verify(
+            &signing_key.public_bytes(),
+            signed.as_bytes(),
+            &Signature::from_bytes(bytes)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_relay_client_message_publish_invite() {
+        use crate::crypto::verify;
+        use crate::crypto::Signature;
+
+        let signing_key = SigningKeyPair::generate();
+        let invite = RelayInvite {
+            code: "abc123".to_string(),
+            community_id: "comm-1".to_string(),
+            community_name: "Test".to_string(),
+            community_description: None,
+            community_icon: Some("icon.png".to_string()),
+            member_count: 3,
+            max_uses: Some(10),
+            expires_at: None,
+            invite_payload: "{}".to_string(),
+        };
+
+        // Must match the relay's `PublishedInvite::signing_message`
+        let signed = invite_signing_message("did:key:z6MkOwner", &invite);
+        assert_eq!(
+            signed,
+            "umbra-relay-invite-v1:{\"code\":\"abc123\",\"publisher_did\":\"did:key:z6MkOwner\",\
+             \"community_id\":\"comm-1\",\"community_name\":\"Test\",\"community_description\":null,\
+             \"community_icon\":\"icon.png\",\"member_count\":3,\"max_uses\":10,\"expires_at\":null,\
+             \"invite_payload\":\"{}\"}"
+        );
+
+        let msg =
+            RelayClientMessage::publish_invite(&signing_key, "did:key:z6MkOwner", invite.clone());
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"publish_invite\""));
+        assert!(json.contains("\"code\":\"abc123\""));
+
+        let RelayClientMessage::PublishInvite {
+            invite: parsed,
+            signature,
+        } = serde_json::from_str(&json).unwrap()
+        else {
+            panic!("Wrong variant");
+        };
+        assert_eq!(parsed, invite);
+        let bytes: [u8; 64] = BASE64.decode(signature).unwrap().try_into().unwrap();
+        assert!(verify(
+            &signing_key.public_bytes(),
+            signed.as_bytes(),
+            &Signature::from_bytes(bytes)
+        )
+        .is_ok());
+    }
+
     #[test]
     fn test_relay_client_message_signal() {
         let msg = RelayClientMessage::Signal {
@@ -258,6 +931,38 @@ mod tests {
         assert!(json.contains("\"type\":\"send\""));
     }
 
+    #[test]
+    fn test_relay_client_message_send_sealed() {
+        let msg = RelayClientMessage::SendSealed {
+            to_did: "did:key:z6MkBob".to_string(),
+            access_key: "ab".repeat(32),
+            payload: "sealed".to_string(),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"send_sealed\""));
+        assert!(!json.contains("from_did"));
+    }
+
+    #[test]
+    fn test_relay_client_message_register_push() {
+        let msg = RelayClientMessage::RegisterPush {
+            kind: RelayPushKind::UnifiedPush,
+            endpoint: "https://push.example.com/up/token".to_string(),
+            quiet_hours: Some(RelayQuietHours {
+                start_minute: 22 * 60,
+                end_minute: 7 * 60,
+                utc_offset_minutes: -300,
+            }),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"register_push\""));
+        assert!(json.contains("\"kind\":\"unified_push\""));
+        assert!(json.contains("\"start_minute\":1320"));
+
+        let json = serde_json::to_string(&RelayClientMessage::UnregisterPush).unwrap();
+        assert_eq!(json, r#"{"type":"unregister_push"}"#);
+    }
+
     #[test]
     fn test_relay_client_message_create_session() {
         let msg = RelayClientMessage::CreateSession {
@@ -284,6 +989,16 @@ mod tests {
         assert!(json.contains("\"type\":\"fetch_offline\""));
     }
 
+    #[test]
+    fn test_relay_client_message_ack_offline() {
+        let msg = RelayClientMessage::AckOffline {
+            ids: vec!["msg-1".to_string(), "msg-2".to_string()],
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"ack_offline\""));
+        assert!(json.contains("\"ids\":[\"msg-1\",\"msg-2\"]"));
+    }
+
     #[test]
     fn test_relay_client_message_ping() {
         let msg = RelayClientMessage::Ping;
@@ -296,8 +1011,39 @@ mod tests {
         let json = r#"{"type":"registered","did":"did:key:z6MkTest"}"#;
         let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
         match msg {
-            RelayServerMessage::Registered { did } => {
+            RelayServerMessage::Registered { did, ice_servers } => {
                 assert_eq!(did, "did:key:z6MkTest");
+                assert!(ice_servers.is_empty());
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_relay_server_message_registered_with_ice_servers() {
+        let json = r#"{"type":"registered","did":"did:key:z6MkTest","ice_servers":[{"urls":["stun:203.0.113.7:3478","turn:203.0.113.7:3478?transport=udp"],"username":"1900000000:did:key:z6MkTest","credential":"c2VjcmV0"}]}"#;
+        let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
+        match msg {
+            RelayServerMessage::Registered { ice_servers, .. } => {
+                assert_eq!(ice_servers.len(), 1);
+                assert_eq!(
+                    ice_servers[0].urls[1],
+                    "turn:203.0.113.7:3478?transport=udp"
+                );
+                assert_eq!(ice_servers[0].username, "1900000000:did:key:z6MkTest");
+                assert_eq!(ice_servers[0].credential, "c2VjcmV0");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_relay_server_message_auth_challenge() {
+        let json = r#"{"type":"auth_challenge","nonce":"abc123"}"#;
+        let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
+        match msg {
+            RelayServerMessage::AuthChallenge { nonce } => {
+                assert_eq!(nonce, "abc123");
             }
             _ => panic!("Wrong variant"),
         }
@@ -334,6 +1080,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_relay_server_message_sealed_message() {
+        let json = r#"{"type":"sealed_message","payload":"sealed","timestamp":12345}"#;
+        let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
+        match msg {
+            RelayServerMessage::SealedMessage { payload, timestamp } => {
+                assert_eq!(payload, "sealed");
+                assert_eq!(timestamp, 12345);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
     #[test]
     fn test_relay_server_message_session_created() {
         let json = r#"{"type":"session_created","session_id":"sess-abc"}"#;
@@ -395,13 +1154,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_relay_server_message_queue_full() {
+        let json = r#"{"type":"queue_full","to_did":"did:key:z6MkBob"}"#;
+        let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
+        match msg {
+            RelayServerMessage::QueueFull { to_did } => {
+                assert_eq!(to_did, "did:key:z6MkBob");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
     #[test]
     fn test_relay_server_message_error() {
         let json = r#"{"type":"error","message":"Something went wrong"}"#;
         let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
         match msg {
-            RelayServerMessage::Error { message } => {
+            RelayServerMessage::Error { message, code, .. } => {
                 assert_eq!(message, "Something went wrong");
+                assert_eq!(code, None);
+            }
+            _ => panic!("Wrong variant"),
+        }
+
+        let json = r#"{"type":"error","message":"Rate limit exceeded for send","code":"rate_limited","retry_after_ms":1500}"#;
+        let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
+        match msg {
+            RelayServerMessage::Error {
+                code,
+                retry_after_ms,
+                ..
+            } => {
+                assert_eq!(code.as_deref(), Some("rate_limited"));
+                assert_eq!(retry_after_ms, Some(1500));
             }
             _ => panic!("Wrong variant"),
         }
@@ -430,6 +1216,9 @@ mod tests {
             RelayClientMessage::Register {
                 did: "did:key:z6MkTest".to_string(),
             },
+            RelayClientMessage::Authenticate {
+                signature: "sig".to_string(),
+            },
             RelayClientMessage::Signal {
                 to_did: "did:key:z6MkBob".to_string(),
                 payload: "offer".to_string(),
@@ -446,9 +1235,13 @@ mod tests {
                 answer_payload: "answer".to_string(),
             },
             RelayClientMessage::FetchOffline,
+            RelayClientMessage::AckOffline {
+                ids: vec!["msg-1".to_string()],
+            },
             RelayClientMessage::Ping,
             RelayClientMessage::CreateCallRoom {
                 group_id: "group-1".to_string(),
+                mode: RelayCallRoomMode::Mesh,
             },
             RelayClientMessage::JoinCallRoom {
                 room_id: "room-1".to_string(),
@@ -461,6 +1254,18 @@ mod tests {
                 to_did: "did:key:z6MkBob".to_string(),
                 payload: "sdp".to_string(),
             },
+            RelayClientMessage::SfuOffer {
+                room_id: "room-1".to_string(),
+                sdp: "v=0".to_string(),
+            },
+            RelayClientMessage::SfuAnswer {
+                room_id: "room-1".to_string(),
+                sdp: "v=0".to_string(),
+            },
+            RelayClientMessage::SfuCandidate {
+                room_id: "room-1".to_string(),
+                candidate: "{}".to_string(),
+            },
         ];
 
         for msg in messages {
@@ -476,9 +1281,39 @@ mod tests {
         let json = r#"{"type":"call_room_created","room_id":"room-123","group_id":"group-abc"}"#;
         let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
         match msg {
-            RelayServerMessage::CallRoomCreated { room_id, group_id } => {
+            RelayServerMessage::CallRoomCreated {
+                room_id,
+                group_id,
+                mode,
+            } => {
                 assert_eq!(room_id, "room-123");
                 assert_eq!(group_id, "group-abc");
+                assert_eq!(mode, RelayCallRoomMode::Mesh);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_relay_sfu_messages() {
+        let msg = RelayClientMessage::CreateCallRoom {
+            group_id: "group-1".to_string(),
+            mode: RelayCallRoomMode::Sfu,
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"mode\":\"sfu\""));
+
+        let json = r#"{"type":"sfu_offer","room_id":"room-1","sdp":"v=0"}"#;
+        let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
+        assert!(matches!(msg, RelayServerMessage::SfuOffer { .. }));
+
+        let json =
+            r#"{"type":"call_dominant_speaker","room_id":"room-1","did":"did:key:z6MkAlice"}"#;
+        let msg: RelayServerMessage = serde_json::from_str(json).unwrap();
+        match msg {
+            RelayServerMessage::CallDominantSpeaker { room_id, did } => {
+                assert_eq!(room_id, "room-1");
+                assert_eq!(did, "did:key:z6MkAlice");
             }
             _ => panic!("Wrong variant"),
         }
@@ -528,4 +1363,109 @@ mod tests {
             _ => panic!("Wrong variant"),
         }
     }
+
+    fn candidate(url: &str, latency_ms: Option<u64>, online_count: usize) -> RelayCandidate {
+        RelayCandidate {
+            relay: RelayDirectoryEntry {
+                relay_id: Some(url.to_string()),
+                relay_url: Some(url.to_string()),
+                region: "Test".to_string(),
+                location: "Test City".to_string(),
+                load: Some(RelayLoad {
+                    online_count,
+                    offline_queue_size: 0,
+                }),
+            },
+            latency_ms,
+        }
+    }
+
+    #[test]
+    fn test_parse_relay_directory() {
+        let json = r#"{"relays":[
+            {"relay_id":null,"relay_url":null,"region":"US East","location":"New York","load":{"online_count":3,"offline_queue_size":7}},
+            {"relay_id":"r2","relay_url":"wss://r2.example.com/ws","region":"EU","location":"Frankfurt","load":null},
+            {"relay_id":"r2","relay_url":"wss://r2.example.com/ws","region":"EU","location":"Frankfurt"}
+        ],"timestamp":1}"#;
+        let entries = parse_relay_directory(json, "wss://seed.example.com/ws").unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].relay_url.as_deref(),
+            Some("wss://seed.example.com/ws")
+        );
+        assert_eq!(entries[0].load.unwrap().offline_queue_size, 7);
+        assert_eq!(entries[1].load, None);
+
+        assert!(parse_relay_directory("{}", "wss://seed.example.com/ws").is_err());
+    }
+
+    #[test]
+    fn test_relay_http_url() {
+        assert_eq!(
+            relay_http_url("wss://relay.umbra.chat/ws"),
+            "https://relay.umbra.chat"
+        );
+        assert_eq!(
+            relay_http_url("ws://localhost:8080/ws/"),
+            "http://localhost:8080"
+        );
+    }
+
+    #[test]
+    fn test_rank_relays_by_latency_and_load() {
+        let ranked = rank_relays(vec![
+            candidate("wss://down", None, 0),
+            candidate("wss://busy", Some(40), 1000),
+            candidate("wss://far", Some(120), 0),
+            candidate("wss://near", Some(50), 0),
+        ]);
+        let urls: Vec<_> = ranked.iter().map(|c| c.url()).collect();
+        // busy: 40 ms + 100 ms load penalty
+        assert_eq!(
+            urls,
+            ["wss://near", "wss://far", "wss://busy", "wss://down"]
+        );
+    }
+
+    #[test]
+    fn test_selector_fails_over_then_backs_off() {
+        let mut selector = RelaySelector::new(vec![
+            candidate("wss://b", Some(80), 0),
+            candidate("wss://a", Some(20), 0),
+        ]);
+
+        let (relay, delay) = selector.next_relay().unwrap();
+        assert_eq!((relay.url(), delay), ("wss://a", Duration::ZERO));
+        selector.connected();
+
+        // Disconnect: fail over straight away
+        let (relay, delay) = selector.next_relay().unwrap();
+        assert_eq!((relay.url(), delay), ("wss://b", Duration::ZERO));
+        let (relay, delay) = selector.next_relay().unwrap();
+        assert_eq!((relay.url(), delay), ("wss://a", Duration::ZERO));
+        assert!(selector.exhausted());
+
+        // Every relay failed: each round starts from the best relay again,
+        // after a backoff that doubles up to the cap
+        let (relay, delay) = selector.next_relay().unwrap();
+        assert_eq!((relay.url(), delay), ("wss://a", RELAY_RECONNECT_MIN_DELAY));
+        let delays: Vec<_> = (0..12)
+            .map(|_| selector.next_relay().unwrap().1)
+            .filter(|d| !d.is_zero())
+            .collect();
+        assert_eq!(delays[0], RELAY_RECONNECT_MIN_DELAY * 2);
+        assert_eq!(*delays.last().unwrap(), RELAY_RECONNECT_MAX_DELAY);
+
+        // A fresh directory starts from the new best relay; success resets
+        selector.next_relay().unwrap();
+        selector.refresh(vec![candidate("wss://c", Some(5), 0)]);
+        let (relay, delay) = selector.next_relay().unwrap();
+        assert_eq!((relay.url(), delay), ("wss://c", RELAY_RECONNECT_MAX_DELAY));
+        selector.connected();
+        assert_eq!(selector.next_relay().unwrap().1, Duration::ZERO);
+        assert_eq!(selector.current().unwrap().url(), "wss://c");
+
+        assert!(RelaySelector::new(vec![]).next_relay().is_none());
+    }
 }
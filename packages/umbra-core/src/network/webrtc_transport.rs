@@ -23,7 +23,7 @@
 //! │       │                       │                             │
 //! │       │                       └── libp2p protocols          │
 //! │       │                                                     │
-//! │       └── ICE candidates (STUN for NAT traversal)           │
+//! │       └── ICE candidates (STUN/TURN for NAT traversal)      │
 //! │                                                             │
 //! │  Signaling: Out-of-band via QR code / connection link       │
 //! │                                                             │
@@ -56,6 +56,8 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
 
+use super::relay_client::RelayIceServer;
+
 // ============================================================================
 // SIGNALING DATA
 // ============================================================================
@@ -510,7 +512,17 @@ const STUN_SERVERS: &[&str] = &[
     "stun:stun1.l.google.com:19302",
 ];
 
-/// Create an RTCPeerConnection with STUN configuration
+/// Use the STUN/TURN servers the relay handed out on registration
+/// (`RelayServerMessage::Registered`) for peer connections created from now
+/// on, in addition to the public STUN servers.
+pub fn set_relay_ice_servers(servers: Vec<RelayIceServer>) {
+    RELAY_ICE_SERVERS.with(|cell| {
+        *cell.borrow_mut() = servers;
+    });
+}
+
+/// Create an RTCPeerConnection with STUN configuration, plus the relay's
+/// TURN servers if it has any
 fn create_peer_connection() -> Result<web_sys::RtcPeerConnection, JsValue> {
     let ice_servers = js_sys::Array::new();
 
@@ -522,6 +534,29 @@ fn create_peer_connection() -> Result<web_sys::RtcPeerConnection, JsValue> {
     js_sys::Reflect::set(&server, &"urls".into(), &urls)?;
     ice_servers.push(&server);
 
+    RELAY_ICE_SERVERS.with(|cell| -> Result<(), JsValue> {
+        for relay_server in cell.borrow().iter() {
+            let server = js_sys::Object::new();
+            let urls = js_sys::Array::new();
+            for url in &relay_server.urls {
+                urls.push(&JsValue::from_str(url));
+            }
+            js_sys::Reflect::set(&server, &"urls".into(), &urls)?;
+            js_sys::Reflect::set(
+                &server,
+                &"username".into(),
+                &relay_server.username.as_str().into(),
+            )?;
+            js_sys::Reflect::set(
+                &server,
+                &"credential".into(),
+                &relay_server.credential.as_str().into(),
+            )?;
+            ice_servers.push(&server);
+        }
+        Ok(())
+    })?;
+
     let config = web_sys::RtcConfiguration::new();
     config.set_ice_servers(&ice_servers);
 
@@ -839,6 +874,8 @@ thread_local! {
     static PENDING_OFFER_PC: RefCell<Option<web_sys::RtcPeerConnection>> = RefCell::new(None);
     /// The pending RTCPeerConnection from accept_offer()
     static PENDING_ANSWER_PC: RefCell<Option<web_sys::RtcPeerConnection>> = RefCell::new(None);
+    /// STUN/TURN servers from the relay (see set_relay_ice_servers())
+    static RELAY_ICE_SERVERS: RefCell<Vec<RelayIceServer>> = RefCell::new(Vec::new());
 }
 
 fn store_pending_offer(pc: web_sys::RtcPeerConnection) {
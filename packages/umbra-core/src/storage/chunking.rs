@@ -272,6 +272,18 @@ pub fn verify_chunk_hash(data: &[u8], expected_hash: &str) -> bool {
     actual == expected_hash
 }
 
+/// Read the file ID out of a stored manifest JSON.
+///
+/// Accepts both the Rust (`file_id`) and the frontend (`fileId`) spelling.
+pub(crate) fn manifest_file_id(manifest_json: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(manifest_json).ok()?;
+    value
+        .get("file_id")
+        .or_else(|| value.get("fileId"))
+        .and_then(|id| id.as_str())
+        .map(str::to_string)
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -2,6 +2,26 @@
 //!
 //! SQLite database wrapper with encryption support.
 //!
+//! ## Encryption at Rest
+//!
+//! Native builds link SQLCipher. Opening with a `DatabaseConfig` that carries
+//! an `encryption_key` encrypts every page with that key; an existing
+//! plaintext file is exported into an encrypted copy and swapped in place on
+//! first open. `Database::encrypt` does the same for a database that is
+//! already open, and `Database::rekey` re-encrypts under a new key.
+//!
+//! ```text
+//!   open(path, key)
+//!        │
+//!        ├── PRAGMA key ──► readable? ──────────────────► ready
+//!        │
+//!        └── file starts "SQLite format 3"?
+//!                 │ yes
+//!                 ▼
+//!            ATTACH '<path>.encrypting' KEY …
+//!            SELECT sqlcipher_export(…)       ──► rename over <path> ──► ready
+//! ```
+//!
 //! ## Database Operations
 //!
 //! ```text
@@ -40,18 +60,35 @@
 
 use parking_lot::Mutex;
 use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use zeroize::Zeroizing;
 
 use super::schema;
+use super::search::{self, match_expression, MessageSearchHit, SearchSource};
 use crate::error::{Error, Result};
 
 /// Database configuration
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct DatabaseConfig {
     /// Path to the database file
     pub path: Option<String>,
+    /// SQLCipher key; `None` opens a plaintext database
+    pub encryption_key: Option<[u8; 32]>,
 }
 
+impl std::fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseConfig")
+            .field("path", &self.path)
+            .field("encrypted", &self.encryption_key.is_some())
+            .finish()
+    }
+}
+
+/// First bytes of every unencrypted SQLite file
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
 /// The main database handle
 ///
 /// This wraps a SQLite connection and provides high-level methods
@@ -59,6 +96,10 @@ pub struct DatabaseConfig {
 pub struct Database {
     /// The underlying SQLite connection
     conn: Arc<Mutex<Connection>>,
+    /// Path of the database file (`None` for in-memory)
+    path: Option<String>,
+    /// Whether the connection is keyed (SQLCipher)
+    encrypted: AtomicBool,
 }
 
 impl Database {
@@ -66,16 +107,37 @@ impl Database {
     ///
     /// If path is None, creates an in-memory database (useful for testing).
     pub async fn open(path: Option<&str>) -> Result<Self> {
-        let conn = match path {
-            Some(p) => Connection::open(p)
+        Self::open_with_config(DatabaseConfig {
+            path: path.map(String::from),
+            encryption_key: None,
+        })
+        .await
+    }
+
+    /// Open or create a database, encrypting it if the config carries a key
+    ///
+    /// A plaintext file at `path` is re-encrypted in place on first open
+    /// with a key. A wrong key fails with `DatabaseError`.
+    pub async fn open_with_config(config: DatabaseConfig) -> Result<Self> {
+        let conn = match (&config.path, &config.encryption_key) {
+            (Some(p), Some(key)) => open_encrypted(p, key)?,
+            (Some(p), None) => Connection::open(p)
                 .map_err(|e| Error::DatabaseError(format!("Failed to open database: {}", e)))?,
-            None => Connection::open_in_memory().map_err(|e| {
-                Error::DatabaseError(format!("Failed to create in-memory database: {}", e))
-            })?,
+            (None, key) => {
+                let conn = Connection::open_in_memory().map_err(|e| {
+                    Error::DatabaseError(format!("Failed to create in-memory database: {}", e))
+                })?;
+                if let Some(key) = key {
+                    apply_key(&conn, key)?;
+                }
+                conn
+            }
         };
 
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
+            path: config.path,
+            encrypted: AtomicBool::new(config.encryption_key.is_some()),
         };
 
         // Initialize schema
@@ -84,6 +146,78 @@ impl Database {
         Ok(db)
     }
 
+    /// Whether the database is encrypted at rest
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted.load(Ordering::SeqCst)
+    }
+
+    /// Whether this build can encrypt databases (links SQLCipher)
+    pub fn encryption_available() -> bool {
+        Connection::open_in_memory()
+            .ok()
+            .and_then(|conn| {
+                conn.query_row("PRAGMA cipher_version", [], |row| row.get::<_, String>(0))
+                    .optional()
+                    .unwrap_or(None)
+            })
+            .is_some()
+    }
+
+    /// Encrypt an open plaintext database in place under `key`
+    ///
+    /// Used when the key only becomes known after the database was opened
+    /// (e.g. the first identity on this device). The connection is closed,
+    /// the file migrated as in `open_with_config`, and reopened keyed. On
+    /// failure the plaintext database is reopened and the error returned.
+    pub fn encrypt(&self, key: &[u8; 32]) -> Result<()> {
+        if self.is_encrypted() {
+            return Err(Error::DatabaseError("Database is already encrypted".into()));
+        }
+        let path = self
+            .path
+            .as_deref()
+            .ok_or_else(|| Error::DatabaseError("Cannot encrypt an in-memory database".into()))?;
+        if !Self::encryption_available() {
+            return Err(Error::DatabaseError(
+                "Database encryption is not available on this platform".into(),
+            ));
+        }
+
+        let mut conn = self.conn.lock();
+        let placeholder = Connection::open_in_memory()
+            .map_err(|e| Error::DatabaseError(format!("Failed to open database: {}", e)))?;
+        drop(std::mem::replace(&mut *conn, placeholder));
+
+        match open_encrypted(path, key) {
+            Ok(encrypted) => {
+                *conn = encrypted;
+                self.encrypted.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+            Err(e) => {
+                *conn = Connection::open(path).map_err(|e| {
+                    Error::DatabaseError(format!("Failed to reopen database: {}", e))
+                })?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Re-encrypt the database under `new_key`
+    ///
+    /// Only valid for an encrypted database; use `encrypt` for a plaintext
+    /// one.
+    pub fn rekey(&self, new_key: &[u8; 32]) -> Result<()> {
+        if !self.is_encrypted() {
+            return Err(Error::DatabaseError("Database is not encrypted".into()));
+        }
+        let conn = self.conn.lock();
+        conn.execute_batch(&format!("PRAGMA rekey = {};", *key_literal(new_key)))
+            .map_err(|e| Error::DatabaseError(format!("Failed to rekey database: {}", e)))?;
+        tracing::info!("Database re-encrypted under a new key");
+        Ok(())
+    }
+
     /// Initialize the database schema
     fn init_schema(&self) -> Result<()> {
         let conn = self.conn.lock();
@@ -231,6 +365,69 @@ impl Database {
                             Error::DatabaseError(format!("Migration v16→v17 failed: {}", e))
                         })?;
                 }
+                if v < 18 {
+                    tracing::info!("Running migration v17 → v18 (ratchet sessions)");
+                    conn.execute_batch(schema::MIGRATE_V17_TO_V18)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v17→v18 failed: {}", e))
+                        })?;
+                }
+                if v < 19 {
+                    tracing::info!("Running migration v18 → v19 (message search index)");
+                    conn.execute_batch(schema::MIGRATE_V18_TO_V19)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v18→v19 failed: {}", e))
+                        })?;
+                }
+                if v < 20 {
+                    tracing::info!("Running migration v19 → v20 (device keys)");
+                    conn.execute_batch(schema::MIGRATE_V19_TO_V20)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v19→v20 failed: {}", e))
+                        })?;
+                }
+                if v < 21 {
+                    tracing::info!("Running migration v20 → v21 (friend key rotation)");
+                    conn.execute_batch(schema::MIGRATE_V20_TO_V21)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v20→v21 failed: {}", e))
+                        })?;
+                }
+                if v < 22 {
+                    tracing::info!("Running migration v21 → v22 (friend verification)");
+                    conn.execute_batch(schema::MIGRATE_V21_TO_V22)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v21→v22 failed: {}", e))
+                        })?;
+                }
+                if v < 23 {
+                    tracing::info!("Running migration v22 → v23 (disappearing messages)");
+                    conn.execute_batch(schema::MIGRATE_V22_TO_V23)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v22→v23 failed: {}", e))
+                        })?;
+                }
+                if v < 24 {
+                    tracing::info!("Running migration v23 → v24 (MLS groups)");
+                    conn.execute_batch(schema::MIGRATE_V23_TO_V24)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v23→v24 failed: {}", e))
+                        })?;
+                }
+                if v < 25 {
+                    tracing::info!("Running migration v24 → v25 (community op log)");
+                    conn.execute_batch(schema::MIGRATE_V24_TO_V25)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v24→v25 failed: {}", e))
+                        })?;
+                }
+                if v < 26 {
+                    tracing::info!("Running migration v25 → v26 (channel keyring)");
+                    conn.execute_batch(schema::MIGRATE_V25_TO_V26)
+                        .map_err(|e| {
+                            Error::DatabaseError(format!("Migration v25→v26 failed: {}", e))
+                        })?;
+                }
 
                 tracing::info!(
                     "All migrations complete (now at version {})",
@@ -319,7 +516,8 @@ impl Database {
         let conn = self.conn.lock();
 
         let result = conn.query_row(
-            "SELECT id, did, display_name, signing_key, encryption_key, status, avatar, created_at, updated_at
+            "SELECT id, did, display_name, signing_key, encryption_key, status, avatar, created_at, updated_at,
+                    previous_encryption_key, key_rotated_at, verified, verified_at
              FROM friends WHERE did = ?",
             params![did],
             |row| {
@@ -333,6 +531,10 @@ impl Database {
                     avatar: row.get(6)?,
                     created_at: row.get(7)?,
                     updated_at: row.get(8)?,
+                    previous_encryption_key: row.get(9)?,
+                    key_rotated_at: row.get(10)?,
+                    verified: row.get(11)?,
+                    verified_at: row.get(12)?,
                 })
             },
         );
@@ -349,7 +551,8 @@ impl Database {
         let conn = self.conn.lock();
         let mut stmt = conn
             .prepare(
-                "SELECT id, did, display_name, signing_key, encryption_key, status, avatar, created_at, updated_at
+                "SELECT id, did, display_name, signing_key, encryption_key, status, avatar, created_at, updated_at,
+                    previous_encryption_key, key_rotated_at, verified, verified_at
                  FROM friends ORDER BY display_name",
             )
             .map_err(|e| Error::DatabaseError(format!("Failed to prepare query: {}", e)))?;
@@ -366,6 +569,10 @@ impl Database {
                     avatar: row.get(6)?,
                     created_at: row.get(7)?,
                     updated_at: row.get(8)?,
+                    previous_encryption_key: row.get(9)?,
+                    key_rotated_at: row.get(10)?,
+                    verified: row.get(11)?,
+                    verified_at: row.get(12)?,
                 })
             })
             .map_err(|e| Error::DatabaseError(format!("Failed to query friends: {}", e)))?;
@@ -432,17 +639,37 @@ impl Database {
     }
 
     /// Update a friend's encryption key (for key rotation)
+    ///
+    /// The replaced key is kept as `previous_encryption_key` for the
+    /// rotation grace window.
     pub fn update_friend_encryption_key(
         &self,
         did: &str,
         new_encryption_key: &[u8; 32],
+    ) -> Result<bool> {
+        self.rotate_friend_encryption_key(did, new_encryption_key, crate::time::now_timestamp())
+    }
+
+    /// Apply a friend's key rotation made at `rotated_at`
+    ///
+    /// Ignored (returns false) if the key is unchanged or we already applied
+    /// a later rotation. A key change clears the friend's verification.
+    pub fn rotate_friend_encryption_key(
+        &self,
+        did: &str,
+        new_encryption_key: &[u8; 32],
+        rotated_at: i64,
     ) -> Result<bool> {
         let conn = self.conn.lock();
         let now = crate::time::now_timestamp();
+        let new_key = hex::encode(new_encryption_key);
         let rows = conn
             .execute(
-                "UPDATE friends SET encryption_key = ?, updated_at = ? WHERE did = ?",
-                params![hex::encode(new_encryption_key), now, did],
+                "UPDATE friends SET previous_encryption_key = encryption_key, encryption_key = ?,
+                    key_rotated_at = ?, verified = 0, verified_at = NULL, updated_at = ?
+                 WHERE did = ? AND encryption_key != ?
+                    AND (key_rotated_at IS NULL OR key_rotated_at < ?)",
+                params![new_key, rotated_at, now, did, new_key, rotated_at],
             )
             .map_err(|e| {
                 Error::DatabaseError(format!("Failed to update friend encryption key: {}", e))
@@ -450,6 +677,22 @@ impl Database {
         Ok(rows > 0)
     }
 
+    /// Mark a friend's safety number as verified (or clear it)
+    pub fn set_friend_verified(&self, did: &str, verified: bool) -> Result<bool> {
+        let conn = self.conn.lock();
+        let now = crate::time::now_timestamp();
+        let verified_at = verified.then_some(now);
+        let rows = conn
+            .execute(
+                "UPDATE friends SET verified = ?, verified_at = ?, updated_at = ? WHERE did = ?",
+                params![verified, verified_at, now, did],
+            )
+            .map_err(|e| {
+                Error::DatabaseError(format!("Failed to update friend verification: {}", e))
+            })?;
+        Ok(rows > 0)
+    }
+
     /// Update a friend's avatar
     pub fn update_friend_avatar(&self, did: &str, avatar: Option<&str>) -> Result<bool> {
         let conn = self.conn.lock();
@@ -502,7 +745,8 @@ impl Database {
         let conn = self.conn.lock();
 
         let result = conn.query_row(
-            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count
+            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count,
+                    disappearing_timer
              FROM conversations WHERE id = ?",
             params![id],
             |row| {
@@ -514,6 +758,7 @@ impl Database {
                     created_at: row.get(4)?,
                     last_message_at: row.get(5)?,
                     unread_count: row.get(6)?,
+                    disappearing_timer: row.get(7)?,
                 })
             },
         );
@@ -536,7 +781,8 @@ impl Database {
         let conn = self.conn.lock();
 
         let result = conn.query_row(
-            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count
+            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count,
+                    disappearing_timer
              FROM conversations WHERE friend_did = ?",
             params![friend_did],
             |row| {
@@ -548,6 +794,7 @@ impl Database {
                     created_at: row.get(4)?,
                     last_message_at: row.get(5)?,
                     unread_count: row.get(6)?,
+                    disappearing_timer: row.get(7)?,
                 })
             },
         );
@@ -567,7 +814,8 @@ impl Database {
         let conn = self.conn.lock();
         let mut stmt = conn
             .prepare(
-                "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count
+                "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count,
+                    disappearing_timer
                  FROM conversations ORDER BY last_message_at DESC NULLS LAST",
             )
             .map_err(|e| Error::DatabaseError(format!("Failed to prepare query: {}", e)))?;
@@ -582,6 +830,7 @@ impl Database {
                     created_at: row.get(4)?,
                     last_message_at: row.get(5)?,
                     unread_count: row.get(6)?,
+                    disappearing_timer: row.get(7)?,
                 })
             })
             .map_err(|e| Error::DatabaseError(format!("Failed to query conversations: {}", e)))?;
@@ -601,6 +850,9 @@ impl Database {
     // ========================================================================
 
     /// Store an encrypted message
+    ///
+    /// If the conversation has a disappearing-message timer, the message
+    /// expires `timer` seconds after `timestamp`.
     pub fn store_message(
         &self,
         id: &str,
@@ -609,12 +861,39 @@ impl Database {
         content_encrypted: &[u8],
         nonce: &[u8],
         timestamp: i64,
+    ) -> Result<()> {
+        self.store_message_with_expiry(
+            id,
+            conversation_id,
+            sender_did,
+            content_encrypted,
+            nonce,
+            timestamp,
+            None,
+        )
+    }
+
+    /// Store an encrypted message with an explicit expiry (Unix timestamp ms)
+    ///
+    /// `None` falls back to the conversation's disappearing-message timer.
+    #[allow(clippy::too_many_arguments)]
+    pub fn store_message_with_expiry(
+        &self,
+        id: &str,
+        conversation_id: &str,
+        sender_did: &str,
+        content_encrypted: &[u8],
+        nonce: &[u8],
+        timestamp: i64,
+        expires_at: Option<i64>,
     ) -> Result<()> {
         let conn = self.conn.lock();
 
         conn.execute(
-            "INSERT OR IGNORE INTO messages (id, conversation_id, sender_did, content_encrypted, nonce, timestamp)
-             VALUES (?, ?, ?, ?, ?, ?)",
+            "INSERT OR IGNORE INTO messages (id, conversation_id, sender_did, content_encrypted, nonce, timestamp, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, COALESCE(?7, (
+                 SELECT ?6 + disappearing_timer * 1000 FROM conversations WHERE id = ?2
+             )))",
             params![
                 id,
                 conversation_id,
@@ -622,6 +901,7 @@ impl Database {
                 hex::encode(content_encrypted),
                 hex::encode(nonce),
                 timestamp,
+                expires_at,
             ],
         )
         .map_err(|e| Error::DatabaseError(format!("Failed to store message: {}", e)))?;
@@ -646,15 +926,21 @@ impl Database {
         let conn = self.conn.lock();
         let mut stmt = conn
             .prepare(
-                "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read
-                 FROM messages WHERE conversation_id = ?
+                "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read,
+                    expires_at
+                 FROM messages WHERE conversation_id = ? AND (expires_at IS NULL OR expires_at > ?)
                  ORDER BY timestamp DESC LIMIT ? OFFSET ?",
             )
             .map_err(|e| Error::DatabaseError(format!("Failed to prepare query: {}", e)))?;
 
         let rows = stmt
             .query_map(
-                params![conversation_id, limit as i64, offset as i64],
+                params![
+                    conversation_id,
+                    crate::time::now_timestamp_millis(),
+                    limit as i64,
+                    offset as i64
+                ],
                 |row| {
                     Ok(MessageRecord {
                         id: row.get(0)?,
@@ -665,6 +951,7 @@ impl Database {
                         timestamp: row.get(5)?,
                         delivered: row.get(6)?,
                         read: row.get(7)?,
+                        expires_at: row.get(8)?,
                     })
                 },
             )
@@ -688,7 +975,8 @@ impl Database {
         let conn = self.conn.lock();
 
         let result = conn.query_row(
-            "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read
+            "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read,
+                    expires_at
              FROM messages WHERE id = ?",
             params![id],
             |row| {
@@ -701,6 +989,7 @@ impl Database {
                     timestamp: row.get(5)?,
                     delivered: row.get(6)?,
                     read: row.get(7)?,
+                    expires_at: row.get(8)?,
                 })
             },
         );
@@ -778,7 +1067,8 @@ impl Database {
         let conn = self.conn.lock();
         let mut stmt = conn
             .prepare(
-                "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read
+                "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read,
+                    expires_at
                  FROM messages WHERE conversation_id = ? AND pinned = 1
                  ORDER BY pinned_at DESC",
             )
@@ -795,6 +1085,7 @@ impl Database {
                     timestamp: row.get(5)?,
                     delivered: row.get(6)?,
                     read: row.get(7)?,
+                    expires_at: row.get(8)?,
                 })
             })
             .map_err(|e| Error::DatabaseError(format!("Failed to query pinned messages: {}", e)))?;
@@ -950,6 +1241,233 @@ impl Database {
         Ok(count as i32)
     }
 
+    // ========================================================================
+    // DISAPPEARING MESSAGES
+    // ========================================================================
+
+    /// Set a conversation's disappearing-message timer (seconds, None = off)
+    ///
+    /// Timers are last-writer-wins by `set_at` (Unix timestamp ms), so both
+    /// sides converge when changes cross in flight. Returns false if a newer
+    /// setting is already stored or the conversation doesn't exist.
+    pub fn set_disappearing_timer(
+        &self,
+        conversation_id: &str,
+        timer: Option<u32>,
+        set_at: i64,
+    ) -> Result<bool> {
+        let conn = self.conn.lock();
+        let rows = conn
+            .execute(
+                "UPDATE conversations SET disappearing_timer = ?, disappearing_timer_set_at = ?
+                 WHERE id = ? AND (disappearing_timer_set_at IS NULL OR disappearing_timer_set_at < ?)",
+                params![timer, set_at, conversation_id, set_at],
+            )
+            .map_err(|e| {
+                Error::DatabaseError(format!("Failed to set disappearing timer: {}", e))
+            })?;
+        Ok(rows > 0)
+    }
+
+    /// Delete every message and DM shared file whose expiry has passed
+    ///
+    /// Reactions on the deleted messages and the chunks and manifests of
+    /// the deleted files go with them. The search index is cleaned up by
+    /// the messages delete trigger.
+    pub fn delete_expired(&self, now: i64) -> Result<ExpiredSweep> {
+        let mut conn = self.conn.lock();
+        let tx = conn
+            .transaction()
+            .map_err(|e| Error::DatabaseError(format!("Failed to begin sweep: {}", e)))?;
+
+        tx.execute(
+            "DELETE FROM reactions WHERE message_id IN
+                (SELECT id FROM messages WHERE expires_at IS NOT NULL AND expires_at <= ?)",
+            params![now],
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to delete reactions: {}", e)))?;
+        let messages = tx
+            .execute(
+                "DELETE FROM messages WHERE expires_at IS NOT NULL AND expires_at <= ?",
+                params![now],
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to delete messages: {}", e)))?;
+
+        let expired_files: Vec<(String, String)> = {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT id, storage_chunks_json FROM dm_shared_files
+                     WHERE expires_at IS NOT NULL AND expires_at <= ?",
+                )
+                .map_err(|e| Error::DatabaseError(e.to_string()))?;
+            let rows = stmt
+                .query_map(params![now], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| Error::DatabaseError(e.to_string()))?;
+            rows.collect::<std::result::Result<_, _>>()
+                .map_err(|e| Error::DatabaseError(e.to_string()))?
+        };
+        for (id, chunks_json) in &expired_files {
+            if let Some(file_id) = super::chunking::manifest_file_id(chunks_json) {
+                tx.execute(
+                    "DELETE FROM file_chunks WHERE file_id = ?",
+                    params![file_id],
+                )
+                .map_err(|e| Error::DatabaseError(e.to_string()))?;
+                tx.execute(
+                    "DELETE FROM file_manifests WHERE file_id = ?",
+                    params![file_id],
+                )
+                .map_err(|e| Error::DatabaseError(e.to_string()))?;
+            }
+            tx.execute("DELETE FROM dm_shared_files WHERE id = ?", params![id])
+                .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        }
+
+        tx.commit()
+            .map_err(|e| Error::DatabaseError(format!("Failed to commit sweep: {}", e)))?;
+
+        Ok(ExpiredSweep {
+            messages,
+            files: expired_files.len(),
+        })
+    }
+
+    // ========================================================================
+    // MESSAGE SEARCH OPERATIONS
+    // ========================================================================
+
+    /// Add or refresh the search index entry for a stored DM or group message.
+    ///
+    /// The messages table only holds ciphertext, so callers pass the
+    /// plaintext once they have it (on send, edit, or decrypt). Does nothing
+    /// if the message doesn't exist, is deleted, or is already indexed with
+    /// the same content. Community messages are indexed by triggers instead.
+    pub fn index_message(&self, message_id: &str, content: &str) -> Result<()> {
+        let conn = self.conn.lock();
+
+        let indexed: Option<String> = conn
+            .query_row(
+                "SELECT s.content FROM message_search_docs d
+                 JOIN message_search s ON s.rowid = d.id
+                 WHERE d.source IN ('dm', 'group') AND d.message_id = ?",
+                params![message_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::DatabaseError(format!("Failed to check search index: {}", e)))?;
+        if indexed.as_deref() == Some(content) {
+            return Ok(());
+        }
+
+        conn.execute(
+            "DELETE FROM message_search WHERE rowid IN (
+                SELECT id FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = ?
+             )",
+            params![message_id],
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to update search index: {}", e)))?;
+        conn.execute(
+            "DELETE FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = ?",
+            params![message_id],
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to update search index: {}", e)))?;
+
+        let inserted = conn
+            .execute(
+                "INSERT INTO message_search_docs (message_id, source, scope_id, sender_did, created_at)
+                 SELECT m.id, CASE WHEN c.type = 'group' THEN 'group' ELSE 'dm' END,
+                        m.conversation_id, m.sender_did, m.timestamp
+                 FROM messages m JOIN conversations c ON c.id = m.conversation_id
+                 WHERE m.id = ? AND m.deleted = 0",
+                params![message_id],
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to index message: {}", e)))?;
+        if inserted > 0 {
+            conn.execute(
+                "INSERT INTO message_search (rowid, content) VALUES (?, ?)",
+                params![conn.last_insert_rowid(), content],
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to index message: {}", e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Find a stored message by the fields the decrypt paths have on hand.
+    ///
+    /// `scope_id` is either a conversation ID or a group ID.
+    pub fn find_message_id(
+        &self,
+        scope_id: &str,
+        sender_did: &str,
+        timestamp: i64,
+    ) -> Result<Option<String>> {
+        let conn = self.conn.lock();
+        conn.query_row(
+            "SELECT m.id FROM messages m JOIN conversations c ON c.id = m.conversation_id
+             WHERE (c.id = ?1 OR c.group_id = ?1) AND m.sender_did = ?2 AND m.timestamp = ?3
+             LIMIT 1",
+            params![scope_id, sender_did, timestamp],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::DatabaseError(format!("Failed to find message: {}", e)))
+    }
+
+    /// Ranked full-text search across everything `reader_did` can read.
+    ///
+    /// `query` is user input (see [`match_expression`] for the supported
+    /// syntax). An empty `sources` slice searches DMs, groups, and
+    /// communities. Community hits are limited to `visible_channels`,
+    /// the channels the reader can view (see
+    /// [`CommunityService::visible_channels`](crate::community::CommunityService::visible_channels)).
+    pub fn search_messages(
+        &self,
+        query: &str,
+        reader_did: &str,
+        sources: &[SearchSource],
+        visible_channels: &[String],
+        limit: usize,
+    ) -> Result<Vec<MessageSearchHit>> {
+        let Some(expr) = match_expression(query) else {
+            return Ok(Vec::new());
+        };
+        let visible_channels = serde_json::to_string(visible_channels)
+            .map_err(|e| Error::SerializationError(e.to_string()))?;
+
+        let conn = self.conn.lock();
+        let mut stmt = conn
+            .prepare(&search::search_sql(sources))
+            .map_err(|e| Error::DatabaseError(format!("Failed to prepare search: {}", e)))?;
+
+        let rows = stmt
+            .query_map(
+                params![expr, reader_did, limit as i64, visible_channels],
+                |row| {
+                    let source: String = row.get(1)?;
+                    Ok(MessageSearchHit {
+                        message_id: row.get(0)?,
+                        source: SearchSource::parse(&source).unwrap_or(SearchSource::Dm),
+                        scope_id: row.get(2)?,
+                        community_id: row.get(3)?,
+                        sender_did: row.get(4)?,
+                        created_at: row.get(5)?,
+                        snippet: row.get(6)?,
+                        rank: row.get(7)?,
+                    })
+                },
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to search messages: {}", e)))?;
+
+        let mut hits = Vec::new();
+        for row in rows {
+            hits.push(
+                row.map_err(|e| Error::DatabaseError(format!("Failed to read search hit: {}", e)))?,
+            );
+        }
+        Ok(hits)
+    }
+
     // ========================================================================
     // GROUP OPERATIONS
     // ========================================================================
@@ -1319,22 +1837,354 @@ impl Database {
             },
         );
 
-        match result {
-            Ok(record) => Ok(Some(record)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(Error::DatabaseError(format!("Failed to get invite: {}", e))),
-        }
+        match result {
+            Ok(record) => Ok(Some(record)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(format!("Failed to get invite: {}", e))),
+        }
+    }
+
+    /// Update group invite status
+    pub fn update_group_invite_status(&self, id: &str, status: &str) -> Result<bool> {
+        let conn = self.conn.lock();
+        let rows = conn
+            .execute(
+                "UPDATE group_invites SET status = ? WHERE id = ?",
+                params![status, id],
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to update invite: {}", e)))?;
+
+        Ok(rows > 0)
+    }
+
+    // ========================================================================
+    // RATCHET SESSION OPERATIONS
+    // ========================================================================
+
+    /// Store (or replace) the serialized Double Ratchet session for a peer
+    pub fn save_ratchet_session(&self, peer_did: &str, state: &[u8]) -> Result<()> {
+        let conn = self.conn.lock();
+        let now = crate::time::now_timestamp();
+
+        conn.execute(
+            "INSERT INTO ratchet_sessions (peer_did, state, created_at, updated_at)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT(peer_did) DO UPDATE SET state = excluded.state, updated_at = excluded.updated_at",
+            params![peer_did, state, now, now],
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to save ratchet session: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Get the serialized Double Ratchet session for a peer
+    pub fn get_ratchet_session(&self, peer_did: &str) -> Result<Option<Vec<u8>>> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT state FROM ratchet_sessions WHERE peer_did = ?",
+            params![peer_did],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::DatabaseError(format!("Failed to get ratchet session: {}", e)))
+    }
+
+    /// Delete the Double Ratchet session for a peer
+    pub fn delete_ratchet_session(&self, peer_did: &str) -> Result<bool> {
+        let conn = self.conn.lock();
+
+        let rows = conn
+            .execute(
+                "DELETE FROM ratchet_sessions WHERE peer_did = ?",
+                params![peer_did],
+            )
+            .map_err(|e| {
+                Error::DatabaseError(format!("Failed to delete ratchet session: {}", e))
+            })?;
+
+        Ok(rows > 0)
+    }
+
+    /// Store one of our X3DH prekeys
+    pub fn store_prekey(&self, prekey: &PrekeyRecord) -> Result<()> {
+        let conn = self.conn.lock();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO ratchet_prekeys (kind, key_id, secret_key, public_key, signature, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                prekey.kind,
+                prekey.key_id,
+                prekey.secret_key,
+                prekey.public_key,
+                prekey.signature,
+                prekey.created_at,
+            ],
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to store prekey: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Get a prekey by kind ("signed" or "one_time") and ID
+    pub fn get_prekey(&self, kind: &str, key_id: u32) -> Result<Option<PrekeyRecord>> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT kind, key_id, secret_key, public_key, signature, created_at
+             FROM ratchet_prekeys WHERE kind = ? AND key_id = ?",
+            params![kind, key_id],
+            Self::map_prekey,
+        )
+        .optional()
+        .map_err(|e| Error::DatabaseError(format!("Failed to get prekey: {}", e)))
+    }
+
+    /// Get the most recently created prekey of a kind
+    pub fn get_latest_prekey(&self, kind: &str) -> Result<Option<PrekeyRecord>> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT kind, key_id, secret_key, public_key, signature, created_at
+             FROM ratchet_prekeys WHERE kind = ?
+             ORDER BY key_id DESC LIMIT 1",
+            params![kind],
+            Self::map_prekey,
+        )
+        .optional()
+        .map_err(|e| Error::DatabaseError(format!("Failed to get prekey: {}", e)))
+    }
+
+    /// Get all prekeys of a kind, oldest first
+    pub fn get_prekeys(&self, kind: &str) -> Result<Vec<PrekeyRecord>> {
+        let conn = self.conn.lock();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT kind, key_id, secret_key, public_key, signature, created_at
+                 FROM ratchet_prekeys WHERE kind = ? ORDER BY key_id",
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+        let prekeys = stmt
+            .query_map(params![kind], Self::map_prekey)
+            .map_err(|e| Error::DatabaseError(format!("Failed to get prekeys: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::DatabaseError(format!("Failed to read prekey: {}", e)))?;
+
+        Ok(prekeys)
+    }
+
+    /// Get the next unused prekey ID for a kind
+    pub fn next_prekey_id(&self, kind: &str) -> Result<u32> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT COALESCE(MAX(key_id), 0) + 1 FROM ratchet_prekeys WHERE kind = ?",
+            params![kind],
+            |row| row.get(0),
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to get next prekey ID: {}", e)))
+    }
+
+    /// Delete a prekey (one-time prekeys are deleted once consumed)
+    pub fn delete_prekey(&self, kind: &str, key_id: u32) -> Result<bool> {
+        let conn = self.conn.lock();
+
+        let rows = conn
+            .execute(
+                "DELETE FROM ratchet_prekeys WHERE kind = ? AND key_id = ?",
+                params![kind, key_id],
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to delete prekey: {}", e)))?;
+
+        Ok(rows > 0)
+    }
+
+    fn map_prekey(row: &rusqlite::Row) -> rusqlite::Result<PrekeyRecord> {
+        Ok(PrekeyRecord {
+            kind: row.get(0)?,
+            key_id: row.get(1)?,
+            secret_key: row.get(2)?,
+            public_key: row.get(3)?,
+            signature: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+
+    // ========================================================================
+    // MLS GROUP OPERATIONS
+    // ========================================================================
+
+    /// Store (or replace) the serialized MLS state of a group
+    pub fn save_mls_group(&self, group_id: &str, epoch: u64, state: &[u8]) -> Result<()> {
+        let conn = self.conn.lock();
+        let now = crate::time::now_timestamp();
+
+        conn.execute(
+            "INSERT INTO mls_groups (group_id, state, epoch, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(group_id) DO UPDATE SET state = excluded.state, epoch = excluded.epoch, updated_at = excluded.updated_at",
+            params![group_id, state, epoch as i64, now, now],
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to save MLS group: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Get the serialized MLS state of a group
+    pub fn get_mls_group(&self, group_id: &str) -> Result<Option<Vec<u8>>> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT state FROM mls_groups WHERE group_id = ?",
+            params![group_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::DatabaseError(format!("Failed to get MLS group: {}", e)))
+    }
+
+    /// Delete the MLS state of a group
+    pub fn delete_mls_group(&self, group_id: &str) -> Result<bool> {
+        let conn = self.conn.lock();
+
+        let rows = conn
+            .execute(
+                "DELETE FROM mls_groups WHERE group_id = ?",
+                params![group_id],
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to delete MLS group: {}", e)))?;
+
+        Ok(rows > 0)
+    }
+
+    /// Store one of our MLS key packages and its private keys
+    pub fn store_mls_key_package(
+        &self,
+        reference: &str,
+        key_package: &[u8],
+        secrets: &[u8],
+    ) -> Result<()> {
+        let conn = self.conn.lock();
+        let now = crate::time::now_timestamp();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO mls_key_packages (reference, key_package, secrets, created_at)
+             VALUES (?, ?, ?, ?)",
+            params![reference, key_package, secrets, now],
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to store MLS key package: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Get one of our MLS key packages and its private keys by reference
+    pub fn get_mls_key_package(&self, reference: &str) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT key_package, secrets FROM mls_key_packages WHERE reference = ?",
+            params![reference],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| Error::DatabaseError(format!("Failed to get MLS key package: {}", e)))
+    }
+
+    /// Delete an MLS key package once it has been used
+    pub fn delete_mls_key_package(&self, reference: &str) -> Result<bool> {
+        let conn = self.conn.lock();
+
+        let rows = conn
+            .execute(
+                "DELETE FROM mls_key_packages WHERE reference = ?",
+                params![reference],
+            )
+            .map_err(|e| {
+                Error::DatabaseError(format!("Failed to delete MLS key package: {}", e))
+            })?;
+
+        Ok(rows > 0)
+    }
+
+    // ========================================================================
+    // DEVICE OPERATIONS
+    // ========================================================================
+
+    /// Get this install's device keys, if they have been generated
+    pub fn get_local_device(&self) -> Result<Option<LocalDeviceRecord>> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT device_id, signing_secret, encryption_secret, certificate, created_at
+             FROM local_device WHERE id = 1",
+            [],
+            |row| {
+                Ok(LocalDeviceRecord {
+                    device_id: row.get(0)?,
+                    signing_secret: row.get(1)?,
+                    encryption_secret: row.get(2)?,
+                    certificate: row.get(3)?,
+                    created_at: row.get(4)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| Error::DatabaseError(format!("Failed to get local device: {}", e)))
+    }
+
+    /// Store (or replace) this install's device keys
+    pub fn save_local_device(&self, device: &LocalDeviceRecord) -> Result<()> {
+        let conn = self.conn.lock();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO local_device (id, device_id, signing_secret, encryption_secret, certificate, created_at)
+             VALUES (1, ?, ?, ?, ?, ?)",
+            params![
+                device.device_id,
+                device.signing_secret,
+                device.encryption_secret,
+                device.certificate,
+                device.created_at,
+            ],
+        )
+        .map_err(|e| Error::DatabaseError(format!("Failed to save local device: {}", e)))?;
+
+        Ok(())
     }
 
-    /// Update group invite status
-    pub fn update_group_invite_status(&self, id: &str, status: &str) -> Result<bool> {
+    /// Get the stored device list JSON for a DID
+    pub fn get_device_list(&self, did: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT list_json FROM device_lists WHERE did = ?",
+            params![did],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::DatabaseError(format!("Failed to get device list: {}", e)))
+    }
+
+    /// Store a device list for a DID if it is newer than the stored one.
+    /// Returns true if the list was written.
+    pub fn save_device_list(&self, did: &str, version: u64, list_json: &str) -> Result<bool> {
         let conn = self.conn.lock();
+        let now = crate::time::now_timestamp();
+
         let rows = conn
             .execute(
-                "UPDATE group_invites SET status = ? WHERE id = ?",
-                params![status, id],
+                "INSERT INTO device_lists (did, version, list_json, updated_at)
+                 VALUES (?, ?, ?, ?)
+                 ON CONFLICT(did) DO UPDATE SET
+                    version = excluded.version,
+                    list_json = excluded.list_json,
+                    updated_at = excluded.updated_at
+                 WHERE excluded.version > device_lists.version",
+                params![did, version as i64, list_json, now],
             )
-            .map_err(|e| Error::DatabaseError(format!("Failed to update invite: {}", e)))?;
+            .map_err(|e| Error::DatabaseError(format!("Failed to save device list: {}", e)))?;
 
         Ok(rows > 0)
     }
@@ -2775,6 +3625,28 @@ impl Database {
         Ok(roles)
     }
 
+    /// Get a role by ID
+    pub fn get_community_role(&self, id: &str) -> Result<Option<CommunityRoleRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT id, community_id, name, color, icon, badge, position, hoisted, mentionable, is_preset, permissions_bitfield, created_at, updated_at
+             FROM community_roles WHERE id = ?",
+            params![id],
+            |row| Ok(CommunityRoleRecord {
+                id: row.get(0)?, community_id: row.get(1)?, name: row.get(2)?,
+                color: row.get(3)?, icon: row.get(4)?, badge: row.get(5)?,
+                position: row.get(6)?, hoisted: row.get::<_, i32>(7)? != 0,
+                mentionable: row.get::<_, i32>(8)? != 0, is_preset: row.get::<_, i32>(9)? != 0,
+                permissions_bitfield: row.get(10)?, created_at: row.get(11)?, updated_at: row.get(12)?,
+            }),
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
     /// Get the owner role for a community
     pub fn get_owner_role(&self, community_id: &str) -> Result<Option<CommunityRoleRecord>> {
         let conn = self.conn.lock();
@@ -3410,6 +4282,14 @@ impl Database {
         Ok(())
     }
 
+    /// Remove a message row outright
+    pub fn delete_community_message(&self, id: &str) -> Result<()> {
+        let conn = self.conn.lock();
+        conn.execute("DELETE FROM community_messages WHERE id = ?", params![id])
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
     /// Delete a message for a specific member only
     pub fn delete_community_message_for_me(
         &self,
@@ -3733,6 +4613,33 @@ impl Database {
         Ok(overrides)
     }
 
+    /// Get a channel permission override by ID
+    pub fn get_channel_permission_override(
+        &self,
+        id: &str,
+    ) -> Result<Option<ChannelPermissionOverrideRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT id, channel_id, target_type, target_id, allow_bitfield, deny_bitfield FROM channel_permission_overrides WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(ChannelPermissionOverrideRecord {
+                    id: row.get(0)?,
+                    channel_id: row.get(1)?,
+                    target_type: row.get(2)?,
+                    target_id: row.get(3)?,
+                    allow_bitfield: row.get(4)?,
+                    deny_bitfield: row.get(5)?,
+                })
+            },
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
     /// Remove a channel permission override
     pub fn remove_channel_permission_override(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -3920,6 +4827,31 @@ impl Database {
         Ok(warnings)
     }
 
+    /// Get a warning by ID
+    pub fn get_community_warning(&self, id: &str) -> Result<Option<CommunityWarningRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT id, community_id, member_did, reason, warned_by, expires_at, created_at FROM community_warnings WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(CommunityWarningRecord {
+                    id: row.get(0)?,
+                    community_id: row.get(1)?,
+                    member_did: row.get(2)?,
+                    reason: row.get(3)?,
+                    warned_by: row.get(4)?,
+                    expires_at: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            },
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
     /// Get active (non-expired) warning count for a member
     pub fn get_active_warning_count(
         &self,
@@ -4249,6 +5181,30 @@ impl Database {
         Ok(folders)
     }
 
+    /// Get a folder by ID
+    pub fn get_community_file_folder(&self, id: &str) -> Result<Option<CommunityFileFolderRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT id, channel_id, parent_folder_id, name, created_by, created_at FROM community_file_folders WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(CommunityFileFolderRecord {
+                    id: row.get(0)?,
+                    channel_id: row.get(1)?,
+                    parent_folder_id: row.get(2)?,
+                    name: row.get(3)?,
+                    created_by: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            },
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
     /// Delete a folder (cascades to subfolders and contained files)
     pub fn delete_community_file_folder(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -4309,6 +5265,31 @@ impl Database {
         Ok(emojis)
     }
 
+    /// Get a custom emoji by ID
+    pub fn get_community_emoji_by_id(&self, id: &str) -> Result<Option<CommunityEmojiRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT id, community_id, name, image_url, animated, uploaded_by, created_at FROM community_emoji WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(CommunityEmojiRecord {
+                    id: row.get(0)?,
+                    community_id: row.get(1)?,
+                    name: row.get(2)?,
+                    image_url: row.get(3)?,
+                    animated: row.get::<_, i32>(4)? != 0,
+                    uploaded_by: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            },
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
     /// Rename a custom emoji
     pub fn rename_community_emoji(&self, id: &str, new_name: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -4383,6 +5364,33 @@ impl Database {
         Ok(stickers)
     }
 
+    /// Get a sticker by ID
+    pub fn get_community_sticker(&self, id: &str) -> Result<Option<CommunityStickerRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT id, community_id, pack_id, name, image_url, animated, format, uploaded_by, created_at FROM community_stickers WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(CommunityStickerRecord {
+                    id: row.get(0)?,
+                    community_id: row.get(1)?,
+                    pack_id: row.get(2)?,
+                    name: row.get(3)?,
+                    image_url: row.get(4)?,
+                    animated: row.get::<_, i32>(5)? != 0,
+                    format: row.get(6)?,
+                    uploaded_by: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            },
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
     /// Delete a custom sticker
     pub fn delete_community_sticker(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -4444,6 +5452,34 @@ impl Database {
         Ok(packs)
     }
 
+    /// Get a sticker pack by ID
+    pub fn get_community_sticker_pack(
+        &self,
+        id: &str,
+    ) -> Result<Option<CommunityStickerPackRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT id, community_id, name, description, cover_sticker_id, created_by, created_at FROM community_sticker_packs WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(CommunityStickerPackRecord {
+                    id: row.get(0)?,
+                    community_id: row.get(1)?,
+                    name: row.get(2)?,
+                    description: row.get(3)?,
+                    cover_sticker_id: row.get(4)?,
+                    created_by: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            },
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
     /// Delete a sticker pack (stickers with this pack_id will have pack_id set to NULL)
     pub fn delete_community_sticker_pack(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -4565,41 +5601,142 @@ impl Database {
         Ok(())
     }
 
-    /// Delete a webhook
-    pub fn delete_community_webhook(&self, id: &str) -> Result<()> {
+    /// Delete a webhook
+    pub fn delete_community_webhook(&self, id: &str) -> Result<()> {
+        let conn = self.conn.lock();
+        conn.execute("DELETE FROM community_webhooks WHERE id = ?", params![id])
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    // ── Channel Keys (Phase 2 E2EE) ────────────────────────────────────
+
+    /// Store a channel encryption key
+    pub fn store_channel_key(
+        &self,
+        channel_id: &str,
+        key_version: i32,
+        encrypted_key: &[u8],
+        created_at: i64,
+    ) -> Result<()> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "INSERT INTO community_channel_keys (channel_id, key_version, encrypted_key, created_at) VALUES (?, ?, ?, ?)",
+            params![channel_id, key_version, encrypted_key, created_at],
+        ).map_err(|e| Error::DatabaseError(format!("Failed to store channel key: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get the latest key version for a channel
+    pub fn get_latest_channel_key(&self, channel_id: &str) -> Result<Option<ChannelKeyRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT channel_id, key_version, encrypted_key, created_at FROM community_channel_keys WHERE channel_id = ? ORDER BY key_version DESC LIMIT 1",
+            params![channel_id],
+            |row| Ok(ChannelKeyRecord {
+                channel_id: row.get(0)?, key_version: row.get(1)?,
+                encrypted_key: row.get(2)?, created_at: row.get(3)?,
+            }),
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
+    /// Get one version of a channel's key
+    pub fn get_channel_key(
+        &self,
+        channel_id: &str,
+        key_version: i32,
+    ) -> Result<Option<ChannelKeyRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT channel_id, key_version, encrypted_key, created_at FROM community_channel_keys WHERE channel_id = ? AND key_version = ?",
+            params![channel_id, key_version],
+            |row| Ok(ChannelKeyRecord {
+                channel_id: row.get(0)?, key_version: row.get(1)?,
+                encrypted_key: row.get(2)?, created_at: row.get(3)?,
+            }),
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
+    /// Get every version of a channel's key, oldest first
+    pub fn get_channel_keys(&self, channel_id: &str) -> Result<Vec<ChannelKeyRecord>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT channel_id, key_version, encrypted_key, created_at FROM community_channel_keys WHERE channel_id = ? ORDER BY key_version",
+        ).map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let rows = stmt.query_map(params![channel_id], |row| Ok(ChannelKeyRecord {
+            channel_id: row.get(0)?, key_version: row.get(1)?,
+            encrypted_key: row.get(2)?, created_at: row.get(3)?,
+        })).map_err(|e| Error::DatabaseError(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Record that a member has been handed a version of a channel's key
+    pub fn add_channel_key_holder(
+        &self,
+        channel_id: &str,
+        key_version: i32,
+        member_did: &str,
+    ) -> Result<()> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "INSERT OR IGNORE INTO community_channel_key_holders (channel_id, key_version, member_did) VALUES (?, ?, ?)",
+            params![channel_id, key_version, member_did],
+        ).map_err(|e| Error::DatabaseError(format!("Failed to add channel key holder: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get the members holding a version of a channel's key
+    pub fn get_channel_key_holders(&self, channel_id: &str, key_version: i32) -> Result<Vec<String>> {
         let conn = self.conn.lock();
-        conn.execute("DELETE FROM community_webhooks WHERE id = ?", params![id])
+        let mut stmt = conn.prepare(
+            "SELECT member_did FROM community_channel_key_holders WHERE channel_id = ? AND key_version = ?",
+        ).map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let rows = stmt.query_map(params![channel_id, key_version], |row| row.get(0))
             .map_err(|e| Error::DatabaseError(e.to_string()))?;
-        Ok(())
+        rows.collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
     }
 
-    // ── Channel Keys (Phase 2 E2EE) ────────────────────────────────────
-
-    /// Store a channel encryption key
-    pub fn store_channel_key(
+    /// Store the encryption key a member announced to a community
+    pub fn set_community_member_key(
         &self,
-        channel_id: &str,
-        key_version: i32,
-        encrypted_key: &[u8],
-        created_at: i64,
+        community_id: &str,
+        member_did: &str,
+        encryption_key: &str,
+        announced_at: i64,
     ) -> Result<()> {
         let conn = self.conn.lock();
         conn.execute(
-            "INSERT INTO community_channel_keys (channel_id, key_version, encrypted_key, created_at) VALUES (?, ?, ?, ?)",
-            params![channel_id, key_version, encrypted_key, created_at],
-        ).map_err(|e| Error::DatabaseError(format!("Failed to store channel key: {}", e)))?;
+            "INSERT OR REPLACE INTO community_member_keys (community_id, member_did, encryption_key, announced_at) VALUES (?, ?, ?, ?)",
+            params![community_id, member_did, encryption_key, announced_at],
+        ).map_err(|e| Error::DatabaseError(format!("Failed to store member key: {}", e)))?;
         Ok(())
     }
 
-    /// Get the latest key version for a channel
-    pub fn get_latest_channel_key(&self, channel_id: &str) -> Result<Option<ChannelKeyRecord>> {
+    /// Get the encryption key a member announced to a community
+    pub fn get_community_member_key(
+        &self,
+        community_id: &str,
+        member_did: &str,
+    ) -> Result<Option<CommunityMemberKeyRecord>> {
         let conn = self.conn.lock();
         let result = conn.query_row(
-            "SELECT channel_id, key_version, encrypted_key, created_at FROM community_channel_keys WHERE channel_id = ? ORDER BY key_version DESC LIMIT 1",
-            params![channel_id],
-            |row| Ok(ChannelKeyRecord {
-                channel_id: row.get(0)?, key_version: row.get(1)?,
-                encrypted_key: row.get(2)?, created_at: row.get(3)?,
+            "SELECT community_id, member_did, encryption_key, announced_at FROM community_member_keys WHERE community_id = ? AND member_did = ?",
+            params![community_id, member_did],
+            |row| Ok(CommunityMemberKeyRecord {
+                community_id: row.get(0)?, member_did: row.get(1)?,
+                encryption_key: row.get(2)?, announced_at: row.get(3)?,
             }),
         );
         match result {
@@ -4807,21 +5944,24 @@ impl Database {
 
     // ── Search (Phase 3) ────────────────────────────────────────────────
 
-    /// Search messages in a channel by content (plaintext only)
+    /// Search messages in a channel by content (plaintext only), newest first
     pub fn search_community_messages(
         &self,
         channel_id: &str,
         query: &str,
         limit: usize,
     ) -> Result<Vec<CommunityMessageRecord>> {
+        let Some(expr) = match_expression(query) else {
+            return Ok(Vec::new());
+        };
         let conn = self.conn.lock();
-        let like_query = format!("%{}%", query);
-        let mut stmt = conn.prepare(
-            "SELECT id, channel_id, sender_did, content_encrypted, content_plaintext, nonce, key_version, is_e2ee, reply_to_id, thread_id, has_embed, has_attachment, content_warning, edited_at, deleted_for_everyone, created_at FROM community_messages WHERE channel_id = ? AND content_plaintext LIKE ? AND deleted_for_everyone = 0 ORDER BY created_at DESC LIMIT ?",
-        ).map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, channel_id, sender_did, content_encrypted, content_plaintext, nonce, key_version, is_e2ee, reply_to_id, thread_id, has_embed, has_attachment, content_warning, edited_at, deleted_for_everyone, created_at, metadata_json FROM community_messages WHERE channel_id = ? AND {} AND deleted_for_everyone = 0 ORDER BY created_at DESC LIMIT ?",
+            search::COMMUNITY_MATCH_FILTER
+        )).map_err(|e| Error::DatabaseError(e.to_string()))?;
 
         let rows = stmt
-            .query_map(params![channel_id, like_query, limit as i64], |row| {
+            .query_map(params![channel_id, expr, limit as i64], |row| {
                 Ok(CommunityMessageRecord {
                     id: row.get(0)?,
                     channel_id: row.get(1)?,
@@ -4940,6 +6080,32 @@ impl Database {
         Ok(results)
     }
 
+    /// Get a timeout by ID
+    pub fn get_community_timeout(&self, id: &str) -> Result<Option<CommunityTimeoutRecord>> {
+        let conn = self.conn.lock();
+        let result = conn.query_row(
+            "SELECT id, community_id, member_did, reason, timeout_type, issued_by, expires_at, created_at FROM community_timeouts WHERE id = ?",
+            params![id],
+            |row| {
+                Ok(CommunityTimeoutRecord {
+                    id: row.get(0)?,
+                    community_id: row.get(1)?,
+                    member_did: row.get(2)?,
+                    reason: row.get(3)?,
+                    timeout_type: row.get(4)?,
+                    issued_by: row.get(5)?,
+                    expires_at: row.get(6)?,
+                    created_at: row.get(7)?,
+                })
+            },
+        );
+        match result {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::DatabaseError(e.to_string())),
+        }
+    }
+
     /// Remove a timeout by ID
     pub fn remove_community_timeout(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -5189,10 +6355,11 @@ impl Database {
         // Not deleted
         conditions.push("deleted_for_everyone = 0".to_string());
 
-        // Content query (LIKE)
-        if let Some(q) = query {
-            conditions.push("content_plaintext LIKE ?".to_string());
-            param_values.push(Box::new(format!("%{}%", q)));
+        // Content query (full-text index); input with no searchable terms
+        // doesn't filter on content
+        if let Some(expr) = query.and_then(match_expression) {
+            conditions.push(search::COMMUNITY_MATCH_FILTER.to_string());
+            param_values.push(Box::new(expr));
         }
 
         // From user
@@ -5246,7 +6413,7 @@ impl Database {
         };
 
         let sql = format!(
-            "SELECT id, channel_id, sender_did, content_encrypted, content_plaintext, nonce, key_version, is_e2ee, reply_to_id, thread_id, has_embed, has_attachment, content_warning, edited_at, deleted_for_everyone, created_at FROM community_messages WHERE {} ORDER BY created_at DESC LIMIT ?",
+            "SELECT id, channel_id, sender_did, content_encrypted, content_plaintext, nonce, key_version, is_e2ee, reply_to_id, thread_id, has_embed, has_attachment, content_warning, edited_at, deleted_for_everyone, created_at, metadata_json FROM community_messages WHERE {} ORDER BY created_at DESC LIMIT ?",
             where_clause
         );
         param_values.push(Box::new(limit as i64));
@@ -5530,8 +6697,9 @@ impl Database {
         created_at: i64,
     ) -> Result<()> {
         let conn = self.conn.lock();
+        // Files shared into a disappearing conversation expire with it
         conn.execute(
-            "INSERT INTO dm_shared_files (id, conversation_id, folder_id, filename, description, file_size, mime_type, storage_chunks_json, uploaded_by, encrypted_metadata, encryption_nonce, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO dm_shared_files (id, conversation_id, folder_id, filename, description, file_size, mime_type, storage_chunks_json, uploaded_by, encrypted_metadata, encryption_nonce, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, (SELECT (?12 + disappearing_timer) * 1000 FROM conversations WHERE id = ?2))",
             params![id, conversation_id, folder_id, filename, description, file_size, mime_type, storage_chunks_json, uploaded_by, encrypted_metadata, encryption_nonce, created_at],
         ).map_err(|e| Error::DatabaseError(format!("Failed to store DM file: {}", e)))?;
         Ok(())
@@ -5733,6 +6901,65 @@ impl Database {
         Ok(())
     }
 
+    // ========================================================================
+    // COMMUNITY OP LOG OPERATIONS
+    // ========================================================================
+
+    /// Store a signed community operation
+    ///
+    /// Returns `false` if it was already stored.
+    pub fn store_community_op(
+        &self,
+        id: &str,
+        community_id: &str,
+        author_did: &str,
+        lamport: u64,
+        op_json: &str,
+        received_at: i64,
+    ) -> Result<bool> {
+        let conn = self.conn.lock();
+
+        let rows = conn
+            .execute(
+                "INSERT OR IGNORE INTO community_ops (id, community_id, author_did, lamport, op_json, received_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![id, community_id, author_did, lamport as i64, op_json, received_at],
+            )
+            .map_err(|e| Error::DatabaseError(format!("Failed to store community op: {}", e)))?;
+
+        Ok(rows > 0)
+    }
+
+    /// Get every stored operation of a community (as JSON), in Lamport order
+    pub fn get_community_ops(&self, community_id: &str) -> Result<Vec<String>> {
+        let conn = self.conn.lock();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT op_json FROM community_ops WHERE community_id = ? ORDER BY lamport, id",
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![community_id], |row| row.get(0))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        rows.collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| Error::DatabaseError(format!("Failed to get community ops: {}", e)))
+    }
+
+    /// Count the stored operations of a community
+    pub fn count_community_ops(&self, community_id: &str) -> Result<usize> {
+        let conn = self.conn.lock();
+
+        conn.query_row(
+            "SELECT COUNT(*) FROM community_ops WHERE community_id = ?",
+            params![community_id],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count as usize)
+        .map_err(|e| Error::DatabaseError(format!("Failed to count community ops: {}", e)))
+    }
+
     // ========================================================================
     // COMMUNITY SEAT OPERATIONS
     // ========================================================================
@@ -6257,6 +7484,14 @@ pub struct FriendRecord {
     pub created_at: i64,
     /// Updated timestamp
     pub updated_at: i64,
+    /// Encryption key replaced by the friend's latest rotation (hex)
+    pub previous_encryption_key: Option<String>,
+    /// When the friend last rotated their encryption key
+    pub key_rotated_at: Option<i64>,
+    /// Whether the user verified this friend's safety number
+    pub verified: bool,
+    /// When the safety number was verified
+    pub verified_at: Option<i64>,
 }
 
 /// A conversation record from the database
@@ -6276,6 +7511,8 @@ pub struct ConversationRecord {
     pub last_message_at: Option<i64>,
     /// Unread message count
     pub unread_count: i32,
+    /// Disappearing-message timer in seconds (None = off)
+    pub disappearing_timer: Option<u32>,
 }
 
 /// A message record from the database
@@ -6297,6 +7534,17 @@ pub struct MessageRecord {
     pub delivered: bool,
     /// Read flag
     pub read: bool,
+    /// When this disappearing message is deleted (Unix timestamp ms)
+    pub expires_at: Option<i64>,
+}
+
+/// What a disappearing-message sweep removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExpiredSweep {
+    /// Messages deleted
+    pub messages: usize,
+    /// DM shared files deleted, with their chunks
+    pub files: usize,
 }
 
 /// A group record from the database
@@ -6346,6 +7594,38 @@ pub struct GroupKeyRecord {
     pub created_at: i64,
 }
 
+/// One of our X3DH prekeys
+#[derive(Debug, Clone)]
+pub struct PrekeyRecord {
+    /// "signed" or "one_time"
+    pub kind: String,
+    /// Prekey ID (unique per kind)
+    pub key_id: u32,
+    /// X25519 private key bytes
+    pub secret_key: Vec<u8>,
+    /// X25519 public key bytes
+    pub public_key: Vec<u8>,
+    /// Hex Ed25519 signature (signed prekeys only)
+    pub signature: Option<String>,
+    /// When the prekey was generated
+    pub created_at: i64,
+}
+
+/// This install's device keypair (multi-device)
+#[derive(Debug, Clone)]
+pub struct LocalDeviceRecord {
+    /// Device ID derived from the signing public key
+    pub device_id: String,
+    /// Ed25519 private key bytes
+    pub signing_secret: Vec<u8>,
+    /// X25519 private key bytes
+    pub encryption_secret: Vec<u8>,
+    /// Root-signed DeviceCertificate JSON, once linked
+    pub certificate: Option<String>,
+    /// When the keys were generated
+    pub created_at: i64,
+}
+
 /// A group invite record
 #[derive(Debug, Clone)]
 pub struct GroupInviteRecord {
@@ -6818,6 +8098,16 @@ pub struct ChannelKeyRecord {
     pub created_at: i64,
 }
 
+#[allow(missing_docs)]
+/// The encryption key a member announced to a community
+#[derive(Debug, Clone)]
+pub struct CommunityMemberKeyRecord {
+    pub community_id: String,
+    pub member_did: String,
+    pub encryption_key: String,
+    pub announced_at: i64,
+}
+
 #[allow(missing_docs)]
 /// A deleted message tracking record
 #[derive(Debug, Clone)]
@@ -7001,6 +8291,101 @@ pub struct CommunitySeatRecord {
 // TESTS
 // ============================================================================
 
+// ============================================================================
+// ENCRYPTION AT REST
+// ============================================================================
+
+/// SQLCipher blob literal for a raw 32-byte key (skips its internal KDF)
+fn key_literal(key: &[u8; 32]) -> Zeroizing<String> {
+    Zeroizing::new(format!("\"x'{}'\"", hex::encode(key)))
+}
+
+/// Key `conn` and confirm the key actually decrypts the file
+fn apply_key(conn: &Connection, key: &[u8; 32]) -> Result<()> {
+    let cipher: Option<String> = conn
+        .query_row("PRAGMA cipher_version", [], |row| row.get(0))
+        .optional()
+        .unwrap_or(None);
+    if cipher.is_none() {
+        return Err(Error::DatabaseError(
+            "Database encryption is not available on this platform".into(),
+        ));
+    }
+
+    conn.execute_batch(&format!("PRAGMA key = {};", *key_literal(key)))
+        .map_err(|e| Error::DatabaseError(format!("Failed to key database: {}", e)))?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map_err(|_| Error::DatabaseError("Failed to unlock database: wrong key".into()))?;
+    Ok(())
+}
+
+/// Open an encrypted database, migrating a plaintext file if necessary
+fn open_encrypted(path: &str, key: &[u8; 32]) -> Result<Connection> {
+    let open = || {
+        Connection::open(path)
+            .map_err(|e| Error::DatabaseError(format!("Failed to open database: {}", e)))
+    };
+
+    let conn = open()?;
+    match apply_key(&conn, key) {
+        Ok(()) => return Ok(conn),
+        Err(e) if !is_plaintext_database(path) => return Err(e),
+        Err(_) => drop(conn),
+    }
+
+    encrypt_plaintext_database(path, key)?;
+    let conn = open()?;
+    apply_key(&conn, key)?;
+    Ok(conn)
+}
+
+/// Whether the file at `path` is an unencrypted SQLite database
+fn is_plaintext_database(path: &str) -> bool {
+    use std::io::Read;
+
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .map(|_| &header == PLAINTEXT_HEADER)
+        .unwrap_or(false)
+}
+
+/// Re-encrypt a plaintext database file in place under `key`
+///
+/// The data is exported into `<path>.encrypting`, which then replaces the
+/// original with a rename, so an interrupted migration leaves the plaintext
+/// file intact and is simply retried on the next open.
+fn encrypt_plaintext_database(path: &str, key: &[u8; 32]) -> Result<()> {
+    let tmp = format!("{}.encrypting", path);
+    let _ = std::fs::remove_file(&tmp);
+
+    let conn = Connection::open(path)
+        .map_err(|e| Error::DatabaseError(format!("Failed to open database: {}", e)))?;
+    conn.execute_batch(&format!(
+        "ATTACH DATABASE '{}' AS encrypted KEY {};",
+        tmp.replace('\'', "''"),
+        *key_literal(key)
+    ))
+    .map_err(|e| Error::DatabaseError(format!("Failed to create encrypted copy: {}", e)))?;
+    conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+        .map_err(|e| Error::DatabaseError(format!("Failed to export database: {}", e)))?;
+    conn.execute_batch("DETACH DATABASE encrypted;")
+        .map_err(|e| Error::DatabaseError(format!("Failed to detach encrypted copy: {}", e)))?;
+    drop(conn);
+
+    std::fs::rename(&tmp, path)
+        .map_err(|e| Error::DatabaseError(format!("Failed to replace database: {}", e)))?;
+    // Journals belong to the old plaintext file
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+    }
+
+    tracing::info!("Encrypted existing plaintext database at {}", path);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -7011,6 +8396,117 @@ mod tests {
         assert!(db.get_all_friends().unwrap().is_empty());
     }
 
+    fn encrypted_config(path: &std::path::Path, key: [u8; 32]) -> DatabaseConfig {
+        DatabaseConfig {
+            path: Some(path.to_string_lossy().into_owned()),
+            encryption_key: Some(key),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_database_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("umbra.db");
+
+        {
+            let db = Database::open_with_config(encrypted_config(&path, [7u8; 32]))
+                .await
+                .unwrap();
+            assert!(db.is_encrypted());
+            db.set_setting("secret", "kept at rest").unwrap();
+        }
+
+        // No plaintext header or content on disk
+        let raw = std::fs::read(&path).unwrap();
+        assert_ne!(&raw[..16], PLAINTEXT_HEADER);
+        assert!(!raw.windows(12).any(|w| w == b"kept at rest"));
+
+        assert!(Database::open_with_config(encrypted_config(&path, [8u8; 32]))
+            .await
+            .is_err());
+        let db = Database::open_with_config(encrypted_config(&path, [7u8; 32]))
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_setting("secret").unwrap().as_deref(),
+            Some("kept at rest")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_database_migrates_to_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("umbra.db");
+        let path_str = path.to_string_lossy().into_owned();
+
+        {
+            let db = Database::open(Some(&path_str)).await.unwrap();
+            assert!(!db.is_encrypted());
+            db.set_setting("theme", "dark").unwrap();
+        }
+        assert_eq!(&std::fs::read(&path).unwrap()[..16], PLAINTEXT_HEADER);
+
+        let db = Database::open_with_config(encrypted_config(&path, [3u8; 32]))
+            .await
+            .unwrap();
+        assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("dark"));
+        drop(db);
+
+        assert_ne!(&std::fs::read(&path).unwrap()[..16], PLAINTEXT_HEADER);
+        assert!(!std::path::Path::new(&format!("{}.encrypting", path_str)).exists());
+        assert!(Database::open(Some(&path_str)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rekey_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("umbra.db");
+
+        {
+            let db = Database::open_with_config(encrypted_config(&path, [1u8; 32]))
+                .await
+                .unwrap();
+            db.set_setting("k", "v").unwrap();
+            db.rekey(&[2u8; 32]).unwrap();
+        }
+
+        assert!(Database::open_with_config(encrypted_config(&path, [1u8; 32]))
+            .await
+            .is_err());
+        let db = Database::open_with_config(encrypted_config(&path, [2u8; 32]))
+            .await
+            .unwrap();
+        assert_eq!(db.get_setting("k").unwrap().as_deref(), Some("v"));
+
+        let plain = Database::open(None).await.unwrap();
+        assert!(plain.rekey(&[2u8; 32]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_open_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("umbra.db");
+
+        {
+            let db = Database::open(Some(path.to_str().unwrap())).await.unwrap();
+            db.set_setting("k", "v").unwrap();
+            db.encrypt(&[3u8; 32]).unwrap();
+            assert!(db.is_encrypted());
+            assert_eq!(db.get_setting("k").unwrap().as_deref(), Some("v"));
+            db.set_setting("k2", "v2").unwrap();
+            assert!(db.encrypt(&[3u8; 32]).is_err());
+        }
+
+        assert!(!is_plaintext_database(path.to_str().unwrap()));
+        let db = Database::open_with_config(encrypted_config(&path, [3u8; 32]))
+            .await
+            .unwrap();
+        assert_eq!(db.get_setting("k2").unwrap().as_deref(), Some("v2"));
+
+        let memory = Database::open(None).await.unwrap();
+        assert!(memory.encrypt(&[3u8; 32]).is_err());
+    }
+
     #[tokio::test]
     async fn test_friend_operations() {
         let db = Database::open(None).await.unwrap();
@@ -7125,6 +8621,176 @@ mod tests {
         assert_eq!(conv.last_message_at, Some(2000));
     }
 
+    #[tokio::test]
+    async fn test_index_and_search_messages() {
+        let db = Database::open(None).await.unwrap();
+        let me = "did:key:z6MkMe";
+        let nonce = [0u8; 12];
+
+        db.add_friend("did:key:z6MkTest", "Alice", &[1u8; 32], &[2u8; 32], None)
+            .unwrap();
+        db.create_conversation("conv-1", "did:key:z6MkTest")
+            .unwrap();
+        db.create_group_conversation("conv-g", "group-1").unwrap();
+
+        db.store_message("dm-1", "conv-1", me, b"ct", &nonce, 1000)
+            .unwrap();
+        db.store_message("dm-2", "conv-1", "did:key:z6MkTest", b"ct", &nonce, 2000)
+            .unwrap();
+        db.store_message("g-1", "conv-g", "did:key:z6MkTest", b"ct", &nonce, 3000)
+            .unwrap();
+
+        db.index_message("dm-1", "Lunch at the noodle place?")
+            .unwrap();
+        db.index_message("dm-2", "Noodles sound great").unwrap();
+        db.index_message("g-1", "Who wants noodles tonight")
+            .unwrap();
+        // Unknown messages are ignored
+        db.index_message("missing", "noodles").unwrap();
+
+        // Decrypt paths look messages up by conversation or group ID
+        assert_eq!(
+            db.find_message_id("group-1", "did:key:z6MkTest", 3000)
+                .unwrap()
+                .as_deref(),
+            Some("g-1")
+        );
+
+        let hits = db.search_messages("nood*", me, &[], &[], 10).unwrap();
+        assert_eq!(hits.len(), 3);
+        let group_hit = hits.iter().find(|h| h.message_id == "g-1").unwrap();
+        assert_eq!(group_hit.source, SearchSource::Group);
+        assert_eq!(group_hit.scope_id, "conv-g");
+        assert!(group_hit.snippet.contains("<mark>noodles</mark>"));
+
+        let dm_only = db
+            .search_messages("nood*", me, &[SearchSource::Dm], &[], 10)
+            .unwrap();
+        assert_eq!(dm_only.len(), 2);
+        assert!(dm_only.iter().all(|h| h.source == SearchSource::Dm));
+
+        // Phrases must match in order
+        let hits = db
+            .search_messages("\"noodle place\"", me, &[], &[], 10)
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, "dm-1");
+
+        // Re-indexing replaces the old content
+        db.index_message("dm-1", "Lunch at the taco truck?")
+            .unwrap();
+        assert!(db
+            .search_messages("\"noodle place\"", me, &[], &[], 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            db.search_messages("taco", me, &[], &[], 10).unwrap().len(),
+            1
+        );
+
+        // Deleted messages drop out of the index
+        db.delete_message("dm-2", 4000).unwrap();
+        let hits = db.search_messages("noodles", me, &[], &[], 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, "g-1");
+        // ...and can't be re-added by a late decrypt
+        db.index_message("dm-2", "Noodles sound great").unwrap();
+        assert_eq!(
+            db.search_messages("noodles", me, &[], &[], 10)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(db
+            .search_messages("  ** ", me, &[], &[], 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_community_messages_respects_membership() {
+        let db = Database::open(None).await.unwrap();
+        let me = "did:key:z6MkMe";
+        let visible = ["ch-1".to_string()];
+
+        db.create_community_record("comm-1", "Test", None, "did:key:z6MkOwner", None, 1000)
+            .unwrap();
+        db.create_community_space("space-1", "comm-1", "General", 0, 1000)
+            .unwrap();
+        db.create_community_channel(
+            "ch-1", "comm-1", "space-1", None, "general", "text", None, 0, 1000,
+        )
+        .unwrap();
+
+        for (id, text, created_at) in [
+            ("cm-1", "Ranking works with bm25", 1000),
+            ("cm-2", "ranking ranking ranking", 2000),
+        ] {
+            db.store_community_message(
+                id,
+                "ch-1",
+                "did:key:z6MkOwner",
+                None,
+                Some(text),
+                None,
+                None,
+                false,
+                None,
+                None,
+                false,
+                false,
+                None,
+                created_at,
+                None,
+            )
+            .unwrap();
+        }
+
+        // Not a member yet: nothing is readable
+        assert!(db
+            .search_messages("ranking", me, &[], &visible, 10)
+            .unwrap()
+            .is_empty());
+
+        db.add_community_member("comm-1", me, 1000, None).unwrap();
+        let hits = db
+            .search_messages("ranking", me, &[], &visible, 10)
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        // Best match first
+        assert_eq!(hits[0].message_id, "cm-2");
+        assert_eq!(hits[0].community_id.as_deref(), Some("comm-1"));
+        assert!(hits[0].rank <= hits[1].rank);
+        // Channels the reader can't view are filtered out
+        assert!(db
+            .search_messages("ranking", me, &[], &[], 10)
+            .unwrap()
+            .is_empty());
+
+        // Per-channel search stays newest-first and returns full records
+        let msgs = db.search_community_messages("ch-1", "rank*", 10).unwrap();
+        assert_eq!(
+            msgs.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(),
+            vec!["cm-2", "cm-1"]
+        );
+
+        db.delete_community_message_for_me("cm-2", me, 3000)
+            .unwrap();
+        db.edit_community_message("cm-1", Some("edited text"), None, None, 3000)
+            .unwrap();
+        assert!(db
+            .search_messages("ranking", me, &[], &visible, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            db.search_messages("edited", me, &[], &visible, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
     #[tokio::test]
     async fn test_settings() {
         let db = Database::open(None).await.unwrap();
@@ -7167,6 +8833,100 @@ mod tests {
         assert!(!db.is_blocked("did:key:z6MkBad").unwrap());
     }
 
+    #[tokio::test]
+    async fn test_ratchet_session_operations() {
+        let db = Database::open(None).await.unwrap();
+
+        assert!(db.get_ratchet_session("did:key:z6MkBob").unwrap().is_none());
+
+        db.save_ratchet_session("did:key:z6MkBob", &[1, 2, 3]).unwrap();
+        db.save_ratchet_session("did:key:z6MkBob", &[4, 5]).unwrap();
+        assert_eq!(
+            db.get_ratchet_session("did:key:z6MkBob").unwrap(),
+            Some(vec![4, 5])
+        );
+
+        assert!(db.delete_ratchet_session("did:key:z6MkBob").unwrap());
+        assert!(!db.delete_ratchet_session("did:key:z6MkBob").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mls_group_operations() {
+        let db = Database::open(None).await.unwrap();
+
+        assert!(db.get_mls_group("group-1").unwrap().is_none());
+        db.save_mls_group("group-1", 0, &[1, 2, 3]).unwrap();
+        db.save_mls_group("group-1", 1, &[4, 5]).unwrap();
+        assert_eq!(db.get_mls_group("group-1").unwrap(), Some(vec![4, 5]));
+        assert!(db.delete_mls_group("group-1").unwrap());
+        assert!(!db.delete_mls_group("group-1").unwrap());
+
+        db.store_mls_key_package("abcd", &[1], &[2]).unwrap();
+        assert_eq!(
+            db.get_mls_key_package("abcd").unwrap(),
+            Some((vec![1], vec![2]))
+        );
+        assert!(db.delete_mls_key_package("abcd").unwrap());
+        assert!(db.get_mls_key_package("abcd").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_community_op_log_operations() {
+        let db = Database::open(None).await.unwrap();
+
+        assert!(db
+            .store_community_op("op-b", "c1", "did:key:z6MkA", 2, "{\"b\":1}", 20)
+            .unwrap());
+        assert!(db
+            .store_community_op("op-a", "c1", "did:key:z6MkA", 1, "{\"a\":1}", 10)
+            .unwrap());
+        assert!(db
+            .store_community_op("op-c", "c2", "did:key:z6MkA", 1, "{}", 10)
+            .unwrap());
+        assert!(!db
+            .store_community_op("op-a", "c1", "did:key:z6MkA", 1, "{}", 30)
+            .unwrap());
+
+        assert_eq!(
+            db.get_community_ops("c1").unwrap(),
+            vec!["{\"a\":1}".to_string(), "{\"b\":1}".to_string()]
+        );
+        assert_eq!(db.count_community_ops("c1").unwrap(), 2);
+        assert!(db.get_community_ops("c3").unwrap().is_empty());
+        assert_eq!(db.count_community_ops("c3").unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_prekey_operations() {
+        let db = Database::open(None).await.unwrap();
+
+        assert_eq!(db.next_prekey_id("one_time").unwrap(), 1);
+
+        for key_id in 1..=2 {
+            db.store_prekey(&PrekeyRecord {
+                kind: "one_time".to_string(),
+                key_id,
+                secret_key: vec![key_id as u8; 32],
+                public_key: vec![0xAA; 32],
+                signature: None,
+                created_at: 1000,
+            })
+            .unwrap();
+        }
+
+        assert_eq!(db.next_prekey_id("one_time").unwrap(), 3);
+        assert_eq!(db.next_prekey_id("signed").unwrap(), 1);
+        assert!(db.get_latest_prekey("signed").unwrap().is_none());
+
+        let latest = db.get_latest_prekey("one_time").unwrap().unwrap();
+        assert_eq!(latest.key_id, 2);
+        assert_eq!(latest.secret_key, vec![2; 32]);
+
+        assert!(db.delete_prekey("one_time", 1).unwrap());
+        assert!(db.get_prekey("one_time", 1).unwrap().is_none());
+        assert!(db.get_prekey("one_time", 2).unwrap().is_some());
+    }
+
     #[tokio::test]
     async fn test_store_and_get_call_history() {
         let db = Database::open(None).await.unwrap();
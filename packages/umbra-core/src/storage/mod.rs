@@ -16,7 +16,7 @@
 //! │  │  iOS: Keychain                                                 │   │
 //! │  │  Android: Keystore + EncryptedSharedPreferences               │   │
 //! │  │  Web: IndexedDB + WebCrypto                                    │   │
-//! │  │  Desktop: OS keyring (libsecret, Windows Credential Manager)  │   │
+//! │  │  Desktop: scrypt-encrypted file vault (FileVault)              │   │
 //! │  │                                                                 │   │
 //! │  │  Stored: Identity private keys (encrypted)                     │   │
 //! │  └─────────────────────────────────────────────────────────────────┘   │
@@ -75,7 +75,10 @@ mod database;
 
 pub mod chunking;
 mod schema;
+mod search;
 mod secure_store;
+#[cfg(not(target_arch = "wasm32"))]
+mod vault;
 
 /// OPFS storage adapter for web chunk data (WASM-only)
 #[cfg(target_arch = "wasm32")]
@@ -94,6 +97,7 @@ pub use database::{
     CommunityFileFolderRecord,
     CommunityFileRecord,
     CommunityInviteRecord,
+    CommunityMemberKeyRecord,
     CommunityMemberRecord,
     CommunityMemberRoleRecord,
     CommunityMemberStatusRecord,
@@ -122,6 +126,8 @@ pub use database::{
     DatabaseConfig,
     DmSharedFileRecord,
     DmSharedFolderRecord,
+    // Disappearing-message sweep result
+    ExpiredSweep,
     // File chunk and DM file record types
     FileChunkRecord,
     FileManifestRecord,
@@ -133,12 +139,23 @@ pub use database::{
     GroupRecord,
     // Account backup import stats
     ImportStats,
+    // Multi-device record type
+    LocalDeviceRecord,
     MessageRecord,
+    // X3DH prekey record type (Double Ratchet sessions)
+    PrekeyRecord,
     ReactionRecord,
     // Transfer session record type
     TransferSessionRecord,
 };
-pub use secure_store::SecureStore;
+pub use search::{match_expression, MessageSearchHit, SearchSource, SNIPPET_CLOSE, SNIPPET_OPEN};
+pub use secure_store::{
+    derive_database_key, load_storage_key, restore_rotated_encryption_key,
+    store_rotated_encryption_key, store_storage_key, MemoryBackend, SecureStore,
+    SecureStoreBackend,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use vault::{FileVault, VAULT_FILE_NAME};
 
 use crate::error::Result;
 
@@ -53,7 +53,7 @@
 //! ```
 
 /// Current schema version
-pub const SCHEMA_VERSION: i32 = 17;
+pub const SCHEMA_VERSION: i32 = 26;
 
 /// SQL to create all tables
 pub const CREATE_TABLES: &str = r#"
@@ -78,6 +78,14 @@ CREATE TABLE IF NOT EXISTS friends (
     status TEXT,
     -- Avatar (base64-encoded image data or URL)
     avatar TEXT,
+    -- X25519 key replaced by the friend's latest rotation (hex)
+    previous_encryption_key TEXT,
+    -- When the friend last rotated their encryption key
+    key_rotated_at INTEGER,
+    -- Whether the user verified this friend's safety number
+    verified INTEGER NOT NULL DEFAULT 0,
+    -- When the safety number was verified
+    verified_at INTEGER,
     -- When this friend was added
     created_at INTEGER NOT NULL,
     -- Last profile update
@@ -102,7 +110,11 @@ CREATE TABLE IF NOT EXISTS conversations (
     -- Last message timestamp (for sorting)
     last_message_at INTEGER,
     -- Number of unread messages
-    unread_count INTEGER NOT NULL DEFAULT 0
+    unread_count INTEGER NOT NULL DEFAULT 0,
+    -- Disappearing-message timer in seconds (NULL = off)
+    disappearing_timer INTEGER,
+    -- When the timer was last changed (Unix timestamp ms), latest change wins
+    disappearing_timer_set_at INTEGER
 );
 CREATE INDEX IF NOT EXISTS idx_conversations_friend ON conversations(friend_did);
 CREATE INDEX IF NOT EXISTS idx_conversations_last_message ON conversations(last_message_at DESC);
@@ -141,6 +153,8 @@ CREATE TABLE IF NOT EXISTS messages (
     thread_id TEXT,
     -- Forwarding support
     forwarded_from TEXT,
+    -- When a disappearing message is deleted (Unix timestamp ms)
+    expires_at INTEGER,
     -- Reference to conversations table
     FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
 );
@@ -148,6 +162,7 @@ CREATE INDEX IF NOT EXISTS idx_messages_conversation ON messages(conversation_id
 CREATE INDEX IF NOT EXISTS idx_messages_timestamp ON messages(timestamp DESC);
 CREATE INDEX IF NOT EXISTS idx_messages_thread ON messages(thread_id);
 CREATE INDEX IF NOT EXISTS idx_messages_pinned ON messages(conversation_id, pinned) WHERE pinned = 1;
+CREATE INDEX IF NOT EXISTS idx_messages_expires ON messages(expires_at) WHERE expires_at IS NOT NULL;
 
 -- Reactions table
 -- Many-to-many: users can react to messages with emoji
@@ -788,7 +803,9 @@ CREATE TABLE IF NOT EXISTS dm_shared_files (
     needs_reencryption INTEGER NOT NULL DEFAULT 0,
     key_version INTEGER NOT NULL DEFAULT 1,
     encryption_fingerprint TEXT,
-    created_at INTEGER NOT NULL
+    created_at INTEGER NOT NULL,
+    -- Set when shared in a conversation with a disappearing timer (Unix timestamp ms)
+    expires_at INTEGER
 );
 CREATE INDEX IF NOT EXISTS idx_dm_shared_files_conversation ON dm_shared_files(conversation_id);
 CREATE INDEX IF NOT EXISTS idx_dm_shared_files_folder ON dm_shared_files(folder_id);
@@ -845,6 +862,166 @@ CREATE TABLE IF NOT EXISTS community_seats (
 CREATE INDEX IF NOT EXISTS idx_community_seats_community ON community_seats(community_id);
 CREATE INDEX IF NOT EXISTS idx_community_seats_unclaimed ON community_seats(community_id, claimed_by_did) WHERE claimed_by_did IS NULL;
 CREATE INDEX IF NOT EXISTS idx_community_seats_platform ON community_seats(platform, platform_user_id);
+
+-- Double Ratchet sessions (one per DM peer, serialized RatchetSession)
+CREATE TABLE IF NOT EXISTS ratchet_sessions (
+    peer_did TEXT PRIMARY KEY,
+    state BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- Our X3DH prekeys (signed prekeys + one-time prekeys)
+CREATE TABLE IF NOT EXISTS ratchet_prekeys (
+    kind TEXT NOT NULL CHECK(kind IN ('signed', 'one_time')),
+    key_id INTEGER NOT NULL,
+    secret_key BLOB NOT NULL,
+    public_key BLOB NOT NULL,
+    signature TEXT,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (kind, key_id)
+);
+
+-- MLS group state (one per group, serialized MlsGroup)
+CREATE TABLE IF NOT EXISTS mls_groups (
+    group_id TEXT PRIMARY KEY,
+    state BLOB NOT NULL,
+    epoch INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- Our MLS key packages awaiting a Welcome, keyed by hex reference
+CREATE TABLE IF NOT EXISTS mls_key_packages (
+    reference TEXT PRIMARY KEY,
+    key_package BLOB NOT NULL,
+    secrets BLOB NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+-- Signed community operations (the replicated community event log).
+-- op_json is the full signed operation; community tables are derived
+-- from replaying it.
+CREATE TABLE IF NOT EXISTS community_ops (
+    id TEXT PRIMARY KEY,
+    community_id TEXT NOT NULL,
+    author_did TEXT NOT NULL,
+    lamport INTEGER NOT NULL,
+    op_json TEXT NOT NULL,
+    received_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_community_ops_community ON community_ops(community_id, lamport);
+
+-- Channel keyring: the X25519 key each member announced to a community,
+-- and who has been handed each version of a channel's key.
+CREATE TABLE IF NOT EXISTS community_member_keys (
+    community_id TEXT NOT NULL,
+    member_did TEXT NOT NULL,
+    encryption_key TEXT NOT NULL,
+    announced_at INTEGER NOT NULL,
+    PRIMARY KEY (community_id, member_did)
+);
+CREATE TABLE IF NOT EXISTS community_channel_key_holders (
+    channel_id TEXT NOT NULL,
+    key_version INTEGER NOT NULL,
+    member_did TEXT NOT NULL,
+    PRIMARY KEY (channel_id, key_version, member_did)
+);
+
+-- Full-text search index over readable message plaintext.
+-- message_search_docs maps each FTS5 row (rowid = docs.id) back to its
+-- message. Community rows are kept in sync by triggers; DM and group rows
+-- are written by Database::index_message once the plaintext is known,
+-- since the messages table only holds ciphertext.
+CREATE TABLE IF NOT EXISTS message_search_docs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    source TEXT NOT NULL CHECK(source IN ('dm', 'group', 'community')),
+    -- conversation_id for DMs/groups, channel_id for communities
+    scope_id TEXT NOT NULL,
+    sender_did TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    UNIQUE(source, message_id)
+);
+CREATE INDEX IF NOT EXISTS idx_message_search_docs_scope ON message_search_docs(scope_id);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS message_search USING fts5(
+    content,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+CREATE TRIGGER IF NOT EXISTS community_messages_search_insert
+AFTER INSERT ON community_messages
+WHEN NEW.content_plaintext IS NOT NULL AND NEW.deleted_for_everyone = 0
+BEGIN
+    INSERT INTO message_search_docs (message_id, source, scope_id, sender_did, created_at)
+        VALUES (NEW.id, 'community', NEW.channel_id, NEW.sender_did, NEW.created_at);
+    INSERT INTO message_search (rowid, content)
+        VALUES (last_insert_rowid(), NEW.content_plaintext);
+END;
+
+CREATE TRIGGER IF NOT EXISTS community_messages_search_update
+AFTER UPDATE OF content_plaintext, deleted_for_everyone ON community_messages
+BEGIN
+    DELETE FROM message_search WHERE rowid IN (
+        SELECT id FROM message_search_docs WHERE source = 'community' AND message_id = OLD.id
+    );
+    DELETE FROM message_search_docs WHERE source = 'community' AND message_id = OLD.id;
+    INSERT INTO message_search_docs (message_id, source, scope_id, sender_did, created_at)
+        SELECT NEW.id, 'community', NEW.channel_id, NEW.sender_did, NEW.created_at
+        WHERE NEW.content_plaintext IS NOT NULL AND NEW.deleted_for_everyone = 0;
+    INSERT INTO message_search (rowid, content)
+        SELECT last_insert_rowid(), NEW.content_plaintext
+        WHERE NEW.content_plaintext IS NOT NULL AND NEW.deleted_for_everyone = 0;
+END;
+
+CREATE TRIGGER IF NOT EXISTS community_messages_search_delete
+AFTER DELETE ON community_messages
+BEGIN
+    DELETE FROM message_search WHERE rowid IN (
+        SELECT id FROM message_search_docs WHERE source = 'community' AND message_id = OLD.id
+    );
+    DELETE FROM message_search_docs WHERE source = 'community' AND message_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS messages_search_soft_delete
+AFTER UPDATE OF deleted ON messages
+WHEN NEW.deleted = 1
+BEGIN
+    DELETE FROM message_search WHERE rowid IN (
+        SELECT id FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = OLD.id
+    );
+    DELETE FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS messages_search_delete
+AFTER DELETE ON messages
+BEGIN
+    DELETE FROM message_search WHERE rowid IN (
+        SELECT id FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = OLD.id
+    );
+    DELETE FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = OLD.id;
+END;
+
+-- This install's device keypair (multi-device). The certificate is the
+-- root-signed DeviceCertificate JSON, set once the device is linked.
+CREATE TABLE IF NOT EXISTS local_device (
+    id INTEGER PRIMARY KEY CHECK(id = 1),
+    device_id TEXT NOT NULL,
+    signing_secret BLOB NOT NULL,
+    encryption_secret BLOB NOT NULL,
+    certificate TEXT,
+    created_at INTEGER NOT NULL
+);
+
+-- Signed device lists (our own and our contacts'), highest version wins
+CREATE TABLE IF NOT EXISTS device_lists (
+    did TEXT PRIMARY KEY,
+    version INTEGER NOT NULL,
+    list_json TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
 "#;
 
 /// Migration SQL from schema version 1 → 2
@@ -1718,9 +1895,248 @@ CREATE INDEX IF NOT EXISTS idx_communities_origin ON communities(origin_communit
 UPDATE schema_version SET version = 17;
 "#;
 
+/// Migration v17 → v18: Double Ratchet session state and X3DH prekeys for DMs.
+pub const MIGRATE_V17_TO_V18: &str = r#"
+CREATE TABLE IF NOT EXISTS ratchet_sessions (
+    peer_did TEXT PRIMARY KEY,
+    state BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ratchet_prekeys (
+    kind TEXT NOT NULL CHECK(kind IN ('signed', 'one_time')),
+    key_id INTEGER NOT NULL,
+    secret_key BLOB NOT NULL,
+    public_key BLOB NOT NULL,
+    signature TEXT,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (kind, key_id)
+);
+
+UPDATE schema_version SET version = 18;
+"#;
+
+/// Migration v18 → v19: FTS5 message search index.
+///
+/// Creates the index and its sync triggers, then backfills it from existing
+/// community messages. DM and group history is only stored encrypted, so it
+/// is indexed as messages are sent or decrypted.
+pub const MIGRATE_V18_TO_V19: &str = r#"
+CREATE TABLE IF NOT EXISTS message_search_docs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    source TEXT NOT NULL CHECK(source IN ('dm', 'group', 'community')),
+    -- conversation_id for DMs/groups, channel_id for communities
+    scope_id TEXT NOT NULL,
+    sender_did TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    UNIQUE(source, message_id)
+);
+CREATE INDEX IF NOT EXISTS idx_message_search_docs_scope ON message_search_docs(scope_id);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS message_search USING fts5(
+    content,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+CREATE TRIGGER IF NOT EXISTS community_messages_search_insert
+AFTER INSERT ON community_messages
+WHEN NEW.content_plaintext IS NOT NULL AND NEW.deleted_for_everyone = 0
+BEGIN
+    INSERT INTO message_search_docs (message_id, source, scope_id, sender_did, created_at)
+        VALUES (NEW.id, 'community', NEW.channel_id, NEW.sender_did, NEW.created_at);
+    INSERT INTO message_search (rowid, content)
+        VALUES (last_insert_rowid(), NEW.content_plaintext);
+END;
+
+CREATE TRIGGER IF NOT EXISTS community_messages_search_update
+AFTER UPDATE OF content_plaintext, deleted_for_everyone ON community_messages
+BEGIN
+    DELETE FROM message_search WHERE rowid IN (
+        SELECT id FROM message_search_docs WHERE source = 'community' AND message_id = OLD.id
+    );
+    DELETE FROM message_search_docs WHERE source = 'community' AND message_id = OLD.id;
+    INSERT INTO message_search_docs (message_id, source, scope_id, sender_did, created_at)
+        SELECT NEW.id, 'community', NEW.channel_id, NEW.sender_did, NEW.created_at
+        WHERE NEW.content_plaintext IS NOT NULL AND NEW.deleted_for_everyone = 0;
+    INSERT INTO message_search (rowid, content)
+        SELECT last_insert_rowid(), NEW.content_plaintext
+        WHERE NEW.content_plaintext IS NOT NULL AND NEW.deleted_for_everyone = 0;
+END;
+
+CREATE TRIGGER IF NOT EXISTS community_messages_search_delete
+AFTER DELETE ON community_messages
+BEGIN
+    DELETE FROM message_search WHERE rowid IN (
+        SELECT id FROM message_search_docs WHERE source = 'community' AND message_id = OLD.id
+    );
+    DELETE FROM message_search_docs WHERE source = 'community' AND message_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS messages_search_soft_delete
+AFTER UPDATE OF deleted ON messages
+WHEN NEW.deleted = 1
+BEGIN
+    DELETE FROM message_search WHERE rowid IN (
+        SELECT id FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = OLD.id
+    );
+    DELETE FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS messages_search_delete
+AFTER DELETE ON messages
+BEGIN
+    DELETE FROM message_search WHERE rowid IN (
+        SELECT id FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = OLD.id
+    );
+    DELETE FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = OLD.id;
+END;
+
+INSERT INTO message_search_docs (message_id, source, scope_id, sender_did, created_at)
+    SELECT id, 'community', channel_id, sender_did, created_at FROM community_messages
+    WHERE content_plaintext IS NOT NULL AND deleted_for_everyone = 0;
+INSERT INTO message_search (rowid, content)
+    SELECT d.id, m.content_plaintext FROM message_search_docs d
+    JOIN community_messages m ON m.id = d.message_id
+    WHERE d.source = 'community';
+
+UPDATE schema_version SET version = 19;
+"#;
+
+/// Migration v19 → v20: per-device keys and signed device lists.
+pub const MIGRATE_V19_TO_V20: &str = r#"
+-- This install's device keypair (multi-device). The certificate is the
+-- root-signed DeviceCertificate JSON, set once the device is linked.
+CREATE TABLE IF NOT EXISTS local_device (
+    id INTEGER PRIMARY KEY CHECK(id = 1),
+    device_id TEXT NOT NULL,
+    signing_secret BLOB NOT NULL,
+    encryption_secret BLOB NOT NULL,
+    certificate TEXT,
+    created_at INTEGER NOT NULL
+);
+
+-- Signed device lists (our own and our contacts'), highest version wins
+CREATE TABLE IF NOT EXISTS device_lists (
+    did TEXT PRIMARY KEY,
+    version INTEGER NOT NULL,
+    list_json TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+UPDATE schema_version SET version = 20;
+"#;
+
+/// Migration v20 → v21: keep a friend's previous encryption key after rotation.
+pub const MIGRATE_V20_TO_V21: &str = r#"
+ALTER TABLE friends ADD COLUMN previous_encryption_key TEXT;
+ALTER TABLE friends ADD COLUMN key_rotated_at INTEGER;
+
+UPDATE schema_version SET version = 21;
+"#;
+
+/// Migration from schema v21 to v22.
+///
+/// Adds safety number verification state to friends. Verification is
+/// cleared whenever the friend's keys change.
+pub const MIGRATE_V21_TO_V22: &str = r#"
+ALTER TABLE friends ADD COLUMN verified INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE friends ADD COLUMN verified_at INTEGER;
+
+UPDATE schema_version SET version = 22;
+"#;
+
+/// Migration from schema v22 to v23.
+///
+/// Adds disappearing messages: a per-conversation timer and an `expires_at`
+/// on messages and DM shared files for the sweeper.
+pub const MIGRATE_V22_TO_V23: &str = r#"
+ALTER TABLE conversations ADD COLUMN disappearing_timer INTEGER;
+ALTER TABLE conversations ADD COLUMN disappearing_timer_set_at INTEGER;
+ALTER TABLE messages ADD COLUMN expires_at INTEGER;
+ALTER TABLE dm_shared_files ADD COLUMN expires_at INTEGER;
+CREATE INDEX IF NOT EXISTS idx_messages_expires ON messages(expires_at) WHERE expires_at IS NOT NULL;
+
+UPDATE schema_version SET version = 23;
+"#;
+
+/// Migration from schema v23 to v24.
+///
+/// Adds MLS group state and the key packages we've handed out to be added
+/// to groups.
+pub const MIGRATE_V23_TO_V24: &str = r#"
+CREATE TABLE IF NOT EXISTS mls_groups (
+    group_id TEXT PRIMARY KEY,
+    state BLOB NOT NULL,
+    epoch INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS mls_key_packages (
+    reference TEXT PRIMARY KEY,
+    key_package BLOB NOT NULL,
+    secrets BLOB NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+UPDATE schema_version SET version = 24;
+"#;
+
+/// Migration from schema v24 to v25.
+///
+/// Adds the signed community operation log.
+pub const MIGRATE_V24_TO_V25: &str = r#"
+CREATE TABLE IF NOT EXISTS community_ops (
+    id TEXT PRIMARY KEY,
+    community_id TEXT NOT NULL,
+    author_did TEXT NOT NULL,
+    lamport INTEGER NOT NULL,
+    op_json TEXT NOT NULL,
+    received_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_community_ops_community ON community_ops(community_id, lamport);
+
+UPDATE schema_version SET version = 25;
+"#;
+
+/// Migration from schema v25 to v26.
+///
+/// Adds community member keys and channel key holders for the channel keyring.
+pub const MIGRATE_V25_TO_V26: &str = r#"
+CREATE TABLE IF NOT EXISTS community_member_keys (
+    community_id TEXT NOT NULL,
+    member_did TEXT NOT NULL,
+    encryption_key TEXT NOT NULL,
+    announced_at INTEGER NOT NULL,
+    PRIMARY KEY (community_id, member_did)
+);
+CREATE TABLE IF NOT EXISTS community_channel_key_holders (
+    channel_id TEXT NOT NULL,
+    key_version INTEGER NOT NULL,
+    member_did TEXT NOT NULL,
+    PRIMARY KEY (channel_id, key_version, member_did)
+);
+
+UPDATE schema_version SET version = 26;
+"#;
+
 /// SQL to drop all tables (for testing/reset)
 #[allow(dead_code)]
 pub const DROP_TABLES: &str = r#"
+DROP TABLE IF EXISTS community_channel_key_holders;
+DROP TABLE IF EXISTS community_member_keys;
+DROP TABLE IF EXISTS community_ops;
+DROP TABLE IF EXISTS mls_key_packages;
+DROP TABLE IF EXISTS mls_groups;
+DROP TABLE IF EXISTS device_lists;
+DROP TABLE IF EXISTS local_device;
+DROP TABLE IF EXISTS message_search;
+DROP TABLE IF EXISTS message_search_docs;
+DROP TABLE IF EXISTS ratchet_prekeys;
+DROP TABLE IF EXISTS ratchet_sessions;
 DROP TABLE IF EXISTS notifications;
 DROP TABLE IF EXISTS sticker_placements;
 DROP TABLE IF EXISTS community_sticker_packs;
@@ -2089,6 +2505,404 @@ mod tests {
         assert_eq!(notif_count, 1);
     }
 
+    #[test]
+    fn test_migrate_v17_to_v18_sql_is_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (17)", [])
+            .unwrap();
+        conn.execute_batch("DROP TABLE ratchet_sessions; DROP TABLE ratchet_prekeys;")
+            .unwrap();
+
+        conn.execute_batch(MIGRATE_V17_TO_V18).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 18);
+
+        conn.execute(
+            "INSERT INTO ratchet_sessions (peer_did, state, created_at, updated_at)
+             VALUES ('did:key:z6MkBob', x'00', 1000, 1000)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO ratchet_prekeys (kind, key_id, secret_key, public_key, created_at)
+             VALUES ('one_time', 1, x'00', x'00', 1000)",
+            [],
+        )
+        .unwrap();
+
+        // Unknown prekey kinds are rejected
+        assert!(conn
+            .execute(
+                "INSERT INTO ratchet_prekeys (kind, key_id, secret_key, public_key, created_at)
+                 VALUES ('bogus', 1, x'00', x'00', 1000)",
+                [],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_migrate_v19_to_v20_sql_is_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (19)", [])
+            .unwrap();
+        conn.execute_batch("DROP TABLE local_device; DROP TABLE device_lists;")
+            .unwrap();
+
+        conn.execute_batch(MIGRATE_V19_TO_V20).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 20);
+
+        conn.execute(
+            "INSERT INTO local_device (id, device_id, signing_secret, encryption_secret, created_at)
+             VALUES (1, 'abcd', x'00', x'00', 1000)",
+            [],
+        )
+        .unwrap();
+        // Only one local device row
+        assert!(conn
+            .execute(
+                "INSERT INTO local_device (id, device_id, signing_secret, encryption_secret, created_at)
+                 VALUES (2, 'efgh', x'00', x'00', 1000)",
+                [],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_migrate_v20_to_v21_sql_is_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (20)", [])
+            .unwrap();
+        conn.execute_batch(
+            "ALTER TABLE friends DROP COLUMN previous_encryption_key;
+             ALTER TABLE friends DROP COLUMN key_rotated_at;",
+        )
+        .unwrap();
+
+        conn.execute_batch(MIGRATE_V20_TO_V21).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 21);
+
+        conn.execute(
+            "INSERT INTO friends (did, display_name, signing_key, encryption_key, previous_encryption_key, key_rotated_at, created_at, updated_at)
+             VALUES ('did:key:z6MkTest', 'Bob', 'aa', 'bb', 'cc', 1000, 1000, 1000)",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_migrate_v21_to_v22_sql_is_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (21)", [])
+            .unwrap();
+        conn.execute_batch(
+            "ALTER TABLE friends DROP COLUMN verified;
+             ALTER TABLE friends DROP COLUMN verified_at;",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO friends (did, display_name, signing_key, encryption_key, created_at, updated_at)
+             VALUES ('did:key:z6MkTest', 'Bob', 'aa', 'bb', 1000, 1000)",
+            [],
+        )
+        .unwrap();
+
+        conn.execute_batch(MIGRATE_V21_TO_V22).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 22);
+
+        // Existing friends start unverified
+        let verified: bool = conn
+            .query_row("SELECT verified FROM friends", [], |row| row.get(0))
+            .unwrap();
+        assert!(!verified);
+    }
+
+    #[test]
+    fn test_migrate_v22_to_v23_sql_is_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (22)", [])
+            .unwrap();
+        conn.execute_batch(
+            "DROP INDEX idx_messages_expires;
+             ALTER TABLE conversations DROP COLUMN disappearing_timer;
+             ALTER TABLE conversations DROP COLUMN disappearing_timer_set_at;
+             ALTER TABLE messages DROP COLUMN expires_at;
+             ALTER TABLE dm_shared_files DROP COLUMN expires_at;",
+        )
+        .unwrap();
+
+        conn.execute_batch(MIGRATE_V22_TO_V23).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 23);
+
+        conn.execute(
+            "INSERT INTO conversations (id, created_at, disappearing_timer, disappearing_timer_set_at)
+             VALUES ('conv-1', 1000, 3600, 1000)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO messages (id, conversation_id, sender_did, content_encrypted, nonce, timestamp, expires_at)
+             VALUES ('msg-1', 'conv-1', 'did:key:z6MkTest', 'aa', 'bb', 1000, 3601000)",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_migrate_v23_to_v24_sql_is_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (23)", [])
+            .unwrap();
+        conn.execute_batch("DROP TABLE mls_groups; DROP TABLE mls_key_packages;")
+            .unwrap();
+
+        conn.execute_batch(MIGRATE_V23_TO_V24).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 24);
+
+        conn.execute(
+            "INSERT INTO mls_groups (group_id, state, epoch, created_at, updated_at)
+             VALUES ('group-1', x'00', 0, 1000, 1000)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO mls_key_packages (reference, key_package, secrets, created_at)
+             VALUES ('abcd', x'00', x'00', 1000)",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_migrate_v24_to_v25_sql_is_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (24)", [])
+            .unwrap();
+        conn.execute_batch("DROP TABLE community_ops;").unwrap();
+
+        conn.execute_batch(MIGRATE_V24_TO_V25).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 25);
+
+        conn.execute(
+            "INSERT INTO community_ops (id, community_id, author_did, lamport, op_json, received_at)
+             VALUES ('op-1', 'c-1', 'did:key:z6MkAlice', 0, '{}', 1000)",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_migrate_v25_to_v26_sql_is_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (25)", [])
+            .unwrap();
+        conn.execute_batch(
+            "DROP TABLE community_member_keys; DROP TABLE community_channel_key_holders;",
+        )
+        .unwrap();
+
+        conn.execute_batch(MIGRATE_V25_TO_V26).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 26);
+
+        conn.execute(
+            "INSERT INTO community_member_keys (community_id, member_did, encryption_key, announced_at)
+             VALUES ('c-1', 'did:key:z6MkAlice', 'abcd', 1000)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO community_channel_key_holders (channel_id, key_version, member_did)
+             VALUES ('ch-1', 1, 'did:key:z6MkAlice')",
+            [],
+        )
+        .unwrap();
+    }
+
+    fn insert_test_channel(conn: &Connection) {
+        conn.execute_batch(
+            "INSERT INTO communities (id, name, owner_did, created_at, updated_at)
+                 VALUES ('comm-1', 'Test', 'did:key:z6MkOwner', 1000, 1000);
+             INSERT INTO community_spaces (id, community_id, name, position, created_at, updated_at)
+                 VALUES ('space-1', 'comm-1', 'General', 0, 1000, 1000);
+             INSERT INTO community_channels (id, community_id, space_id, name, type, position, created_at, updated_at)
+                 VALUES ('ch1', 'comm-1', 'space-1', 'general', 'text', 0, 1000, 1000);",
+        )
+        .unwrap();
+    }
+
+    fn search_matches(conn: &Connection, query: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT d.message_id FROM message_search
+                 JOIN message_search_docs d ON d.id = message_search.rowid
+                 WHERE message_search MATCH ? ORDER BY d.message_id",
+            )
+            .unwrap();
+        stmt.query_map([query], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migrate_v18_to_v19_backfills_community_messages() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (18)", [])
+            .unwrap();
+        conn.execute_batch(
+            "DROP TRIGGER community_messages_search_insert;
+             DROP TRIGGER community_messages_search_update;
+             DROP TRIGGER community_messages_search_delete;
+             DROP TRIGGER messages_search_soft_delete;
+             DROP TRIGGER messages_search_delete;
+             DROP TABLE message_search;
+             DROP TABLE message_search_docs;",
+        )
+        .unwrap();
+        insert_test_channel(&conn);
+
+        conn.execute_batch(
+            "INSERT INTO community_messages (id, channel_id, sender_did, content_plaintext, created_at)
+                 VALUES ('m1', 'ch1', 'did:key:z6MkAlice', 'Release notes are up', 1000);
+             INSERT INTO community_messages (id, channel_id, sender_did, content_plaintext, deleted_for_everyone, created_at)
+                 VALUES ('m2', 'ch1', 'did:key:z6MkAlice', 'deleted release', 1, 2000);
+             INSERT INTO community_messages (id, channel_id, sender_did, content_encrypted, is_e2ee, created_at)
+                 VALUES ('m3', 'ch1', 'did:key:z6MkAlice', x'00', 1, 3000);",
+        )
+        .unwrap();
+
+        conn.execute_batch(MIGRATE_V18_TO_V19).unwrap();
+
+        let version: i32 = conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, 19);
+
+        // Only live plaintext messages are indexed
+        assert_eq!(search_matches(&conn, "release"), vec!["m1"]);
+        let docs: i64 = conn
+            .query_row("SELECT COUNT(*) FROM message_search_docs", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(docs, 1);
+    }
+
+    #[test]
+    fn test_search_triggers_track_community_messages() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES).unwrap();
+        insert_test_channel(&conn);
+
+        conn.execute(
+            "INSERT INTO community_messages (id, channel_id, sender_did, content_plaintext, created_at)
+             VALUES ('m1', 'ch1', 'did:key:z6MkAlice', 'Café opens at nine', 1000)",
+            [],
+        )
+        .unwrap();
+
+        // Diacritics are folded and prefixes match
+        assert_eq!(search_matches(&conn, "cafe"), vec!["m1"]);
+        assert_eq!(search_matches(&conn, "op*"), vec!["m1"]);
+
+        conn.execute(
+            "UPDATE community_messages SET content_plaintext = 'Bakery opens at ten' WHERE id = 'm1'",
+            [],
+        )
+        .unwrap();
+        assert!(search_matches(&conn, "cafe").is_empty());
+        assert_eq!(search_matches(&conn, "bakery"), vec!["m1"]);
+
+        // Pinning or threading doesn't touch the index
+        conn.execute(
+            "UPDATE community_messages SET thread_id = 't1' WHERE id = 'm1'",
+            [],
+        )
+        .unwrap();
+        assert_eq!(search_matches(&conn, "bakery"), vec!["m1"]);
+
+        conn.execute(
+            "UPDATE community_messages SET deleted_for_everyone = 1 WHERE id = 'm1'",
+            [],
+        )
+        .unwrap();
+        assert!(search_matches(&conn, "bakery").is_empty());
+
+        conn.execute(
+            "INSERT INTO community_messages (id, channel_id, sender_did, content_plaintext, created_at)
+             VALUES ('m2', 'ch1', 'did:key:z6MkAlice', 'another bakery', 2000)",
+            [],
+        )
+        .unwrap();
+        conn.execute("DELETE FROM community_messages WHERE id = 'm2'", [])
+            .unwrap();
+        assert!(search_matches(&conn, "bakery").is_empty());
+
+        let docs: i64 = conn
+            .query_row("SELECT COUNT(*) FROM message_search_docs", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(docs, 0);
+    }
+
     #[test]
     fn test_drop_tables_includes_call_history() {
         let conn = Connection::open_in_memory().unwrap();
@@ -0,0 +1,254 @@
+//! # Message Search
+//!
+//! Full-text search over DMs, groups, and community channels, backed by the
+//! SQLite FTS5 index created in schema v19.
+//!
+//! ```text
+//! ┌──────────────────────┐   triggers    ┌──────────────────────┐
+//! │ community_messages   │──────────────►│ message_search_docs  │
+//! │ (content_plaintext)  │               │ message_id, source,  │
+//! └──────────────────────┘               │ scope_id, sender_did │
+//!                                        └──────────┬───────────┘
+//! ┌──────────────────────┐  index_message           │ rowid = id
+//! │ messages (DM/group)  │──────────────►┌──────────▼───────────┐
+//! │ ciphertext only      │  on send /    │ message_search (FTS5)│
+//! └──────────────────────┘  decrypt      │ content              │
+//!                                        └──────────────────────┘
+//! ```
+//!
+//! User input is never passed to `MATCH` verbatim. [`match_expression`]
+//! quotes every term so FTS5 operators and punctuation are treated as
+//! text, while keeping the two bits of syntax we support:
+//!
+//! - `"exact phrase"` — terms must appear next to each other
+//! - `prefix*` — matches any word starting with `prefix`
+//!
+//! All remaining terms must match (implicit AND).
+
+use serde::{Deserialize, Serialize};
+
+/// Marker inserted before each matched term in a snippet.
+pub const SNIPPET_OPEN: &str = "<mark>";
+
+/// Marker inserted after each matched term in a snippet.
+pub const SNIPPET_CLOSE: &str = "</mark>";
+
+/// Approximate number of tokens in a snippet.
+const SNIPPET_TOKENS: i32 = 16;
+
+/// Where an indexed message lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSource {
+    /// One-to-one conversation
+    Dm,
+    /// Group conversation
+    Group,
+    /// Community channel
+    Community,
+}
+
+impl SearchSource {
+    /// All sources, in the order results are usually grouped.
+    pub const ALL: [SearchSource; 3] = [Self::Dm, Self::Group, Self::Community];
+
+    /// The value stored in `message_search_docs.source`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dm => "dm",
+            Self::Group => "group",
+            Self::Community => "community",
+        }
+    }
+
+    /// Parse a stored source value.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "dm" => Some(Self::Dm),
+            "group" => Some(Self::Group),
+            "community" => Some(Self::Community),
+            _ => None,
+        }
+    }
+}
+
+/// A ranked search result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageSearchHit {
+    /// ID of the matching message
+    pub message_id: String,
+    /// Which kind of conversation it belongs to
+    pub source: SearchSource,
+    /// Conversation ID (DMs and groups) or channel ID (communities)
+    pub scope_id: String,
+    /// Community the channel belongs to (communities only)
+    pub community_id: Option<String>,
+    /// Who sent the message
+    pub sender_did: String,
+    /// When the message was sent (Unix timestamp)
+    pub created_at: i64,
+    /// Excerpt around the match, with terms wrapped in
+    /// [`SNIPPET_OPEN`] / [`SNIPPET_CLOSE`]
+    pub snippet: String,
+    /// BM25 score; lower is a better match
+    pub rank: f64,
+}
+
+/// Turn user input into a safe FTS5 `MATCH` expression.
+///
+/// Returns `None` when the input has nothing searchable in it (empty, or
+/// only punctuation), in which case callers should return no results.
+pub fn match_expression(query: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut rest = query;
+
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        rest = &rest[start..];
+
+        let (text, prefix, consumed) = if let Some(phrase) = rest.strip_prefix('"') {
+            // Unterminated quotes run to the end of the input
+            let end = phrase.find('"').unwrap_or(phrase.len());
+            let after = &phrase[(end + 1).min(phrase.len())..];
+            let prefix = after.starts_with('*');
+            let consumed = 1 + end + 1 + prefix as usize;
+            (&phrase[..end], prefix, consumed.min(rest.len()))
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let word = &rest[..end];
+            let trimmed = word.trim_end_matches('*');
+            (trimmed, trimmed.len() < word.len(), end)
+        };
+        rest = &rest[consumed..];
+
+        if !text.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+        let quoted = format!("\"{}\"", text.replace('"', "\"\""));
+        terms.push(if prefix { quoted + "*" } else { quoted });
+    }
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// `WHERE` condition restricting `community_messages` rows to those matching
+/// a `MATCH` expression. Takes one parameter: the expression.
+pub(crate) const COMMUNITY_MATCH_FILTER: &str = "id IN (
+    SELECT d.message_id FROM message_search
+    JOIN message_search_docs d ON d.id = message_search.rowid
+    WHERE message_search MATCH ? AND d.source = 'community'
+)";
+
+/// Build the unified search query.
+///
+/// Parameters: `?1` the `MATCH` expression, `?2` the reader's DID,
+/// `?3` the result limit, `?4` a JSON array of the community channels the
+/// reader can view. Only messages the reader can see are returned: DMs and
+/// groups from local conversations, and community messages from those
+/// channels in communities the reader is a member of (minus anything they
+/// deleted for themselves). An empty `sources` slice searches everything.
+///
+/// Channel visibility depends on roles, overrides and the owner, so it's
+/// resolved by the community authorization rules rather than in SQL (see
+/// `CommunityService::visible_channels`).
+pub(crate) fn search_sql(sources: &[SearchSource]) -> String {
+    let sources = if sources.is_empty() {
+        &SearchSource::ALL[..]
+    } else {
+        sources
+    };
+    let source_list = sources
+        .iter()
+        .map(|s| format!("'{}'", s.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "SELECT d.message_id, d.source, d.scope_id, ch.community_id, d.sender_did, d.created_at,
+                snippet(message_search, 0, '{open}', '{close}', '…', {tokens}) AS snippet,
+                bm25(message_search) AS rank
+         FROM message_search
+         JOIN message_search_docs d ON d.id = message_search.rowid
+         LEFT JOIN community_channels ch ON d.source = 'community' AND ch.id = d.scope_id
+         WHERE message_search MATCH ?1
+           AND d.source IN ({source_list})
+           AND (
+             (d.source IN ('dm', 'group')
+               AND EXISTS (SELECT 1 FROM conversations c WHERE c.id = d.scope_id))
+             OR (d.source = 'community'
+               AND d.scope_id IN (SELECT value FROM json_each(?4))
+               AND EXISTS (SELECT 1 FROM community_members cm
+                           WHERE cm.community_id = ch.community_id AND cm.member_did = ?2)
+               AND NOT EXISTS (SELECT 1 FROM community_deleted_messages x
+                               WHERE x.message_id = d.message_id AND x.member_did = ?2))
+           )
+         ORDER BY rank
+         LIMIT ?3",
+        open = SNIPPET_OPEN,
+        close = SNIPPET_CLOSE,
+        tokens = SNIPPET_TOKENS,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_terms_are_quoted() {
+        assert_eq!(
+            match_expression("hello world").as_deref(),
+            Some("\"hello\" \"world\"")
+        );
+    }
+
+    #[test]
+    fn test_phrase_and_prefix() {
+        assert_eq!(
+            match_expression("\"release notes\" deplo*").as_deref(),
+            Some("\"release notes\" \"deplo\"*")
+        );
+        assert_eq!(
+            match_expression("\"open sou\"*").as_deref(),
+            Some("\"open sou\"*")
+        );
+    }
+
+    #[test]
+    fn test_fts_operators_are_literal() {
+        assert_eq!(
+            match_expression("cats OR dogs NEAR(x)").as_deref(),
+            Some("\"cats\" \"OR\" \"dogs\" \"NEAR(x)\"")
+        );
+        assert_eq!(
+            match_expression("col:value").as_deref(),
+            Some("\"col:value\"")
+        );
+    }
+
+    #[test]
+    fn test_unterminated_quote_runs_to_end() {
+        assert_eq!(
+            match_expression("say \"hello there").as_deref(),
+            Some("\"say\" \"hello there\"")
+        );
+    }
+
+    #[test]
+    fn test_nothing_searchable() {
+        assert_eq!(match_expression(""), None);
+        assert_eq!(match_expression("   "), None);
+        assert_eq!(match_expression("* \"\" - ?!"), None);
+    }
+
+    #[test]
+    fn test_search_source_round_trip() {
+        for source in SearchSource::ALL {
+            assert_eq!(SearchSource::parse(source.as_str()), Some(source));
+        }
+        assert_eq!(SearchSource::parse("email"), None);
+    }
+}
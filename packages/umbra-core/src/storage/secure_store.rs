@@ -34,12 +34,12 @@
 //! │  └───────────────┘  └───────────────┘  └───────────────┘              │
 //! │                                                                         │
 //! │  ┌───────────────┐  ┌───────────────┐                                  │
-//! │  │    macOS      │  │    Linux      │                                  │
-//! │  │   Keychain    │  │  libsecret    │                                  │
-//! │  │               │  │               │                                  │
-//! │  │ - System      │  │ - GNOME       │                                  │
-//! │  │   keyring     │  │   keyring     │                                  │
-//! │  └───────────────┘  └───────────────┘                                  │
+//! │  │    macOS      │  │    Linux      │  │   Tests       │              │
+//! │  │   Keychain    │  │  File vault   │  │   In-memory   │              │
+//! │  │               │  │               │  │               │              │
+//! │  │ - System      │  │ - scrypt +    │  │ - Lost on     │              │
+//! │  │   keyring     │  │   AES-GCM     │  │   drop        │              │
+//! │  └───────────────┘  └───────────────┘  └───────────────┘              │
 //! │                                                                         │
 //! └─────────────────────────────────────────────────────────────────────────┘
 //! ```
@@ -73,15 +73,22 @@
 //! │                                                                         │
 //! └─────────────────────────────────────────────────────────────────────────┘
 //! ```
+//!
+//! ## Backends
+//!
+//! `SecureStore` layers optional encryption over a [`SecureStoreBackend`].
+//! `SecureStore::new()` picks the platform default (Keychain on iOS,
+//! in-memory elsewhere); hosts without a platform keystore — the CLI and
+//! Tauri builds on headless Linux — unlock a [`FileVault`](super::FileVault)
+//! and hand it to [`SecureStore::with_backend`].
 
-#[cfg(not(target_os = "ios"))]
 use parking_lot::RwLock;
-#[cfg(not(target_os = "ios"))]
 use std::collections::HashMap;
 use zeroize::Zeroizing;
 
 use crate::crypto::{decrypt, encrypt, EncryptionKey, Nonce};
 use crate::error::{Error, Result};
+use crate::identity::Identity;
 
 /// Key names for secure storage
 #[allow(dead_code)]
@@ -92,30 +99,68 @@ pub mod keys {
     /// The DID for quick lookup
     pub const IDENTITY_DID: &str = "umbra.identity.did";
 
-    /// Storage encryption key (derived from identity)
+    /// SQLCipher key for the local database, cached so it can be opened
+    /// before the identity is loaded (see [`derive_database_key`](super::derive_database_key))
     pub const STORAGE_KEY: &str = "umbra.storage.key";
+
+    /// Prefix for a DID's rotated encryption key (`<prefix>.<did>`)
+    pub const ROTATED_ENCRYPTION_KEY: &str = "umbra.identity.rotated_encryption_key";
+}
+
+/// Raw key/value persistence behind a [`SecureStore`].
+///
+/// Backends store opaque bytes; any encryption requested through
+/// [`SecureStore::with_encryption`] has already been applied.
+pub trait SecureStoreBackend: Send + Sync {
+    /// Store `value` under `key`, replacing any previous value
+    fn store_raw(&self, key: &str, value: &[u8]) -> Result<()>;
+
+    /// Retrieve the value stored under `key`
+    fn retrieve_raw(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Delete `key`, returning whether it existed
+    fn delete_raw(&self, key: &str) -> Result<bool>;
+
+    /// Check if `key` exists
+    fn exists_raw(&self, key: &str) -> Result<bool> {
+        Ok(self.retrieve_raw(key)?.is_some())
+    }
+
+    /// Whether stored values survive a process restart
+    fn is_persistent(&self) -> bool {
+        true
+    }
+
+    /// A secret of the backend to bind the database key to, e.g. the
+    /// key a passphrase vault derives; `None` for platform keystores
+    fn storage_secret(&self) -> Option<Zeroizing<[u8; 32]>> {
+        None
+    }
+
+    /// Change the passphrase protecting the stored values
+    fn change_passphrase(&self, _new_passphrase: &str) -> Result<()> {
+        Err(Error::StorageWriteError(
+            "This secure store has no passphrase".into(),
+        ))
+    }
 }
 
 /// Secure storage interface
 ///
-/// This is a platform-agnostic interface. Platform-specific implementations
-/// will be added via conditional compilation.
+/// This is a platform-agnostic interface over a [`SecureStoreBackend`].
 pub struct SecureStore {
-    /// In-memory storage (for development/testing)
-    /// In production, this is backed by platform keychain/keystore
-    #[cfg(not(target_os = "ios"))]
-    memory: RwLock<HashMap<String, Vec<u8>>>,
+    /// Where the (possibly encrypted) bytes actually live
+    backend: Box<dyn SecureStoreBackend>,
 
     /// Optional encryption key for additional protection
     encryption_key: Option<EncryptionKey>,
 }
 
 impl SecureStore {
-    /// Create a new secure store
+    /// Create a new secure store on the platform default backend
     pub fn new() -> Self {
         Self {
-            #[cfg(not(target_os = "ios"))]
-            memory: RwLock::new(HashMap::new()),
+            backend: default_backend(),
             encryption_key: None,
         }
     }
@@ -125,12 +170,19 @@ impl SecureStore {
     /// All data will be encrypted before storage.
     pub fn with_encryption(key: [u8; 32]) -> Self {
         Self {
-            #[cfg(not(target_os = "ios"))]
-            memory: RwLock::new(HashMap::new()),
+            backend: default_backend(),
             encryption_key: Some(EncryptionKey::from_bytes(key)),
         }
     }
 
+    /// Create a secure store on an explicit backend (e.g. a file vault)
+    pub fn with_backend(backend: impl SecureStoreBackend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+            encryption_key: None,
+        }
+    }
+
     /// Store data securely
     ///
     /// The data will be encrypted if an encryption key was provided.
@@ -145,14 +197,14 @@ impl SecureStore {
             value.to_vec()
         };
 
-        self.store_raw(key, &data)
+        self.backend.store_raw(key, &data)
     }
 
     /// Retrieve data securely
     ///
     /// The data will be decrypted if an encryption key was provided.
     pub fn retrieve(&self, key: &str) -> Result<Option<Zeroizing<Vec<u8>>>> {
-        let data = match self.retrieve_raw(key)? {
+        let data = match self.backend.retrieve_raw(key)? {
             Some(d) => d,
             None => return Ok(None),
         };
@@ -177,51 +229,110 @@ impl SecureStore {
 
     /// Delete data from secure storage
     pub fn delete(&self, key: &str) -> Result<bool> {
-        self.delete_raw(key)
+        self.backend.delete_raw(key)
     }
 
     /// Check if a key exists
     pub fn exists(&self, key: &str) -> Result<bool> {
-        self.exists_raw(key)
+        self.backend.exists_raw(key)
+    }
+
+    /// Whether stored values survive a process restart
+    pub fn is_persistent(&self) -> bool {
+        self.backend.is_persistent()
+    }
+
+    /// The backend's secret for binding the database key, if any
+    pub fn storage_secret(&self) -> Option<Zeroizing<[u8; 32]>> {
+        self.backend.storage_secret()
     }
 
-    // ========================================================================
-    // PLATFORM-SPECIFIC IMPLEMENTATIONS
-    // ========================================================================
+    /// Change the passphrase protecting the stored values
+    ///
+    /// Only passphrase-backed stores (file vaults) support this. The
+    /// [`storage_secret`](Self::storage_secret) changes with it, so the
+    /// database has to be rekeyed afterwards.
+    pub fn change_passphrase(&self, new_passphrase: &str) -> Result<()> {
+        self.backend.change_passphrase(new_passphrase)
+    }
+}
 
-    // Development/Testing implementation (in-memory)
+impl Default for SecureStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// BACKENDS
+// ============================================================================
+
+/// The backend `SecureStore::new()` uses on this platform
+fn default_backend() -> Box<dyn SecureStoreBackend> {
+    #[cfg(target_os = "ios")]
+    {
+        Box::new(KeychainBackend)
+    }
+    // Android: Uses in-memory storage for now.
+    // TODO: Implement Android Keystore via the dispatcher FFI pattern.
     #[cfg(not(target_os = "ios"))]
+    {
+        Box::new(MemoryBackend::new())
+    }
+}
+
+/// In-memory backend (development/testing; contents are lost on drop)
+#[derive(Default)]
+pub struct MemoryBackend {
+    memory: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    /// Create an empty in-memory backend
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SecureStoreBackend for MemoryBackend {
     fn store_raw(&self, key: &str, value: &[u8]) -> Result<()> {
         let mut storage = self.memory.write();
         storage.insert(key.to_string(), value.to_vec());
         Ok(())
     }
 
-    #[cfg(not(target_os = "ios"))]
     fn retrieve_raw(&self, key: &str) -> Result<Option<Vec<u8>>> {
         let storage = self.memory.read();
         Ok(storage.get(key).cloned())
     }
 
-    #[cfg(not(target_os = "ios"))]
     fn delete_raw(&self, key: &str) -> Result<bool> {
         let mut storage = self.memory.write();
         Ok(storage.remove(key).is_some())
     }
 
-    #[cfg(not(target_os = "ios"))]
     fn exists_raw(&self, key: &str) -> Result<bool> {
         let storage = self.memory.read();
         Ok(storage.contains_key(key))
     }
 
-    // iOS Keychain implementation
-    #[cfg(target_os = "ios")]
+    fn is_persistent(&self) -> bool {
+        false
+    }
+}
+
+/// iOS Keychain backend
+#[cfg(target_os = "ios")]
+pub struct KeychainBackend;
+
+#[cfg(target_os = "ios")]
+const SERVICE_NAME: &str = "com.umbra.keychain";
+
+#[cfg(target_os = "ios")]
+impl SecureStoreBackend for KeychainBackend {
     fn store_raw(&self, key: &str, value: &[u8]) -> Result<()> {
         use security_framework::passwords::{delete_generic_password, set_generic_password};
 
-        const SERVICE_NAME: &str = "com.umbra.keychain";
-
         // First try to delete any existing item (update pattern)
         let _ = delete_generic_password(SERVICE_NAME, key);
 
@@ -232,12 +343,9 @@ impl SecureStore {
         Ok(())
     }
 
-    #[cfg(target_os = "ios")]
     fn retrieve_raw(&self, key: &str) -> Result<Option<Vec<u8>>> {
         use security_framework::passwords::get_generic_password;
 
-        const SERVICE_NAME: &str = "com.umbra.keychain";
-
         match get_generic_password(SERVICE_NAME, key) {
             Ok(data) => Ok(Some(data.to_vec())),
             Err(e) => {
@@ -255,12 +363,9 @@ impl SecureStore {
         }
     }
 
-    #[cfg(target_os = "ios")]
     fn delete_raw(&self, key: &str) -> Result<bool> {
         use security_framework::passwords::delete_generic_password;
 
-        const SERVICE_NAME: &str = "com.umbra.keychain";
-
         match delete_generic_password(SERVICE_NAME, key) {
             Ok(_) => Ok(true),
             Err(e) => {
@@ -278,7 +383,6 @@ impl SecureStore {
         }
     }
 
-    #[cfg(target_os = "ios")]
     fn exists_raw(&self, key: &str) -> Result<bool> {
         // Use retrieve_raw and check if it returns Some
         match self.retrieve_raw(key)? {
@@ -286,15 +390,6 @@ impl SecureStore {
             None => Ok(false),
         }
     }
-
-    // Android: Uses in-memory storage for now.
-    // TODO: Implement Android Keystore via the dispatcher FFI pattern.
-}
-
-impl Default for SecureStore {
-    fn default() -> Self {
-        Self::new()
-    }
 }
 
 // ============================================================================
@@ -358,6 +453,91 @@ pub fn retrieve_identity_keys(
     Ok(Some((signing_key, encryption_key, did)))
 }
 
+/// Derive the database key for `identity`
+///
+/// The key comes from the identity's private keys (so it can be
+/// re-derived from the recovery phrase) bound to the store's
+/// [`storage_secret`](SecureStore::storage_secret).
+pub fn derive_database_key(
+    store: &SecureStore,
+    identity: &Identity,
+) -> Result<Zeroizing<[u8; 32]>> {
+    let keypair = identity.keypair();
+    let signing = Zeroizing::new(keypair.signing.secret_bytes());
+    let encryption = Zeroizing::new(keypair.encryption.secret_bytes());
+    let secret = store.storage_secret();
+    let host_secret: &[u8] = secret.as_ref().map_or(&[], |s| s.as_slice());
+    Ok(Zeroizing::new(crate::crypto::derive_storage_key(
+        &signing,
+        &encryption,
+        host_secret,
+    )?))
+}
+
+/// Load the cached database key, if an identity has keyed the database
+pub fn load_storage_key(store: &SecureStore) -> Result<Option<Zeroizing<[u8; 32]>>> {
+    match store.retrieve(keys::STORAGE_KEY)? {
+        Some(stored) if stored.len() == 32 => {
+            let mut key = Zeroizing::new([0u8; 32]);
+            key.copy_from_slice(&stored);
+            Ok(Some(key))
+        }
+        Some(_) => Err(Error::StorageReadError("Invalid storage key length".into())),
+        None => Ok(None),
+    }
+}
+
+/// Cache the database key (after keying or rekeying the database)
+pub fn store_storage_key(store: &SecureStore, key: &[u8; 32]) -> Result<()> {
+    store.store(keys::STORAGE_KEY, key)
+}
+
+/// Persist a rotated encryption key so restoring from the phrase keeps it
+///
+/// Stored as `current secret ‖ previous secret ‖ rotated_at (BE)`. Does
+/// nothing if the identity's key was never rotated.
+pub fn store_rotated_encryption_key(store: &SecureStore, identity: &Identity) -> Result<()> {
+    let Some((current, previous, rotated_at)) = identity.rotated_encryption_key() else {
+        return Ok(());
+    };
+
+    let mut value = Zeroizing::new(Vec::with_capacity(72));
+    value.extend_from_slice(&current);
+    value.extend_from_slice(&previous);
+    value.extend_from_slice(&rotated_at.to_be_bytes());
+    store.store(&rotated_key_name(identity), &value)
+}
+
+/// Re-apply a persisted rotated encryption key to a freshly restored identity
+///
+/// Returns whether a rotated key was found.
+pub fn restore_rotated_encryption_key(
+    store: &SecureStore,
+    identity: &mut Identity,
+) -> Result<bool> {
+    let Some(value) = store.retrieve(&rotated_key_name(identity))? else {
+        return Ok(false);
+    };
+    if value.len() != 72 {
+        return Err(Error::StorageReadError(
+            "Invalid rotated key data length".into(),
+        ));
+    }
+
+    let mut current = Zeroizing::new([0u8; 32]);
+    let mut previous = Zeroizing::new([0u8; 32]);
+    current.copy_from_slice(&value[..32]);
+    previous.copy_from_slice(&value[32..64]);
+    let rotated_at = i64::from_be_bytes(value[64..].try_into().unwrap_or_default());
+
+    identity.restore_rotated_encryption_key(&current, &previous, rotated_at);
+    Ok(true)
+}
+
+fn rotated_key_name(identity: &Identity) -> String {
+    format!("{}.{}", keys::ROTATED_ENCRYPTION_KEY, identity.did_string())
+}
+
 /// Delete identity keys from secure storage
 #[allow(dead_code)]
 pub fn delete_identity_keys(store: &SecureStore) -> Result<()> {
@@ -418,6 +598,50 @@ mod tests {
         assert!(store.exists("exists").unwrap());
     }
 
+    #[test]
+    fn test_database_key_follows_identity() {
+        let store = SecureStore::new();
+        assert!(!store.is_persistent());
+        assert!(store.storage_secret().is_none());
+
+        let (identity, phrase) = Identity::create("Alice".to_string()).unwrap();
+        let key = derive_database_key(&store, &identity).unwrap();
+        let restored = Identity::from_recovery_phrase(&phrase, "Alice".to_string()).unwrap();
+        assert_eq!(*key, *derive_database_key(&store, &restored).unwrap());
+
+        let (other, _) = Identity::create("Bob".to_string()).unwrap();
+        assert_ne!(*key, *derive_database_key(&store, &other).unwrap());
+
+        assert!(load_storage_key(&store).unwrap().is_none());
+        store_storage_key(&store, &key).unwrap();
+        assert_eq!(*load_storage_key(&store).unwrap().unwrap(), *key);
+    }
+
+    #[test]
+    fn test_rotated_key_survives_restore() {
+        let store = SecureStore::new();
+        let recovery = crate::identity::RecoveryPhrase::generate().unwrap();
+        let mut identity = Identity::from_recovery_phrase(&recovery, "Alice".into()).unwrap();
+        let original = identity.keypair().encryption.public_bytes();
+
+        identity.rotate_encryption_key().unwrap();
+        store_rotated_encryption_key(&store, &identity).unwrap();
+        let rotated = identity.keypair().encryption.public_bytes();
+
+        let mut restored = Identity::from_recovery_phrase(&recovery, "Alice".into()).unwrap();
+        assert_eq!(restored.keypair().encryption.public_bytes(), original);
+        assert!(restore_rotated_encryption_key(&store, &mut restored).unwrap());
+        assert_eq!(restored.keypair().encryption.public_bytes(), rotated);
+        assert_eq!(
+            restored.previous_encryption_key().unwrap().public_bytes(),
+            original
+        );
+
+        // Other identities are unaffected
+        let (mut other, _) = Identity::create("Bob".into()).unwrap();
+        assert!(!restore_rotated_encryption_key(&store, &mut other).unwrap());
+    }
+
     #[test]
     fn test_identity_keys_storage() {
         let store = SecureStore::new();
@@ -0,0 +1,480 @@
+//! # File Vault
+//!
+//! Passphrase-encrypted, on-disk [`SecureStoreBackend`] for hosts without a
+//! platform keystore (CLI and Tauri builds on headless Linux).
+//!
+//! ## File Format
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────────────┐
+//! │                         secure_store.vault                              │
+//! ├──────────┬─────────┬────────────────────┬────────┬───────┬──────────────┤
+//! │  magic   │ version │ scrypt log_n, r, p │  salt  │ nonce │  ciphertext  │
+//! │ UMBRAVLT │ 1 byte  │ 1 + 4 + 4 bytes    │ 16 B   │ 12 B  │  AES-256-GCM │
+//! └──────────┴─────────┴────────────────────┴────────┴───────┴──────────────┘
+//!
+//!   key        = scrypt(passphrase, salt, log_n, r, p) → 32 bytes
+//!   aad        = every header byte before the nonce
+//!   plaintext  = bincode(BTreeMap<String, Vec<u8>>)
+//! ```
+//!
+//! ## Durability & Permissions
+//!
+//! Every mutation re-encrypts the whole map under a fresh nonce and writes
+//! it to `<path>.tmp`, fsyncs, then renames over the vault, so a crash
+//! leaves either the old or the new file — never a torn one. On Unix the
+//! vault is created `0600` inside a `0700` directory, and opening a vault
+//! that is readable or writable by group/other is refused.
+
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+use super::secure_store::SecureStoreBackend;
+use crate::crypto::{decrypt, encrypt, scrypt, EncryptionKey, Nonce, ScryptParams, NONCE_SIZE};
+use crate::error::{Error, Result};
+
+/// File magic identifying a vault
+const MAGIC: &[u8; 8] = b"UMBRAVLT";
+
+/// Current on-disk format version
+const VERSION: u8 = 1;
+
+/// Salt length for the passphrase KDF
+const SALT_SIZE: usize = 16;
+
+/// Header length: magic + version + log_n + r + p + salt
+const HEADER_SIZE: usize = 8 + 1 + 1 + 4 + 4 + SALT_SIZE;
+
+/// Default vault file name inside the app's storage directory
+pub const VAULT_FILE_NAME: &str = "secure_store.vault";
+
+/// Unlocked vault state
+struct VaultState {
+    /// Decrypted entries
+    entries: BTreeMap<String, Vec<u8>>,
+    /// Key derived from the passphrase
+    key: EncryptionKey,
+    /// Header (magic through salt), also the AEAD associated data
+    header: [u8; HEADER_SIZE],
+}
+
+/// Passphrase-encrypted file vault
+pub struct FileVault {
+    path: PathBuf,
+    state: RwLock<VaultState>,
+}
+
+impl FileVault {
+    /// Open the vault at `path`, creating it if it does not exist
+    ///
+    /// New vaults use [`ScryptParams::INTERACTIVE`].
+    pub fn open(path: impl AsRef<Path>, passphrase: &str) -> Result<Self> {
+        Self::open_with_params(path, passphrase, ScryptParams::INTERACTIVE)
+    }
+
+    /// Open the vault at `path`, creating it with `params` if it does not
+    /// exist. Existing vaults keep the parameters recorded in their header.
+    pub fn open_with_params(
+        path: impl AsRef<Path>,
+        passphrase: &str,
+        params: ScryptParams,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if path.exists() {
+            check_permissions(&path)?;
+            let bytes = fs::read(&path)
+                .map_err(|e| Error::StorageReadError(format!("Failed to read vault: {}", e)))?;
+            let state = unlock(&bytes, passphrase)?;
+            return Ok(Self {
+                path,
+                state: RwLock::new(state),
+            });
+        }
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            create_private_dir(parent)?;
+        }
+
+        let state = new_key_state(passphrase, params)?;
+        persist(&path, &state)?;
+        tracing::info!("Created secure store vault at {}", path.display());
+
+        Ok(Self {
+            path,
+            state: RwLock::new(state),
+        })
+    }
+
+    /// Path of the vault file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Re-encrypt the vault under a new passphrase (fresh salt)
+    pub fn change_passphrase(&self, new_passphrase: &str) -> Result<()> {
+        let mut state = self.state.write();
+        let params = read_params(&state.header);
+        let rekeyed = VaultState {
+            entries: state.entries.clone(),
+            ..new_key_state(new_passphrase, params)?
+        };
+        persist(&self.path, &rekeyed)?;
+        *state = rekeyed;
+        Ok(())
+    }
+
+    /// Apply `change` to a copy of the entries, persist, then commit it.
+    /// A failed write leaves both the file and the in-memory map untouched.
+    fn mutate<T>(&self, change: impl FnOnce(&mut BTreeMap<String, Vec<u8>>) -> T) -> Result<T> {
+        let mut state = self.state.write();
+        let mut entries = state.entries.clone();
+        let out = change(&mut entries);
+        let entries = std::mem::replace(&mut state.entries, entries);
+        if let Err(e) = persist(&self.path, &state) {
+            state.entries = entries;
+            return Err(e);
+        }
+        Ok(out)
+    }
+}
+
+impl SecureStoreBackend for FileVault {
+    fn store_raw(&self, key: &str, value: &[u8]) -> Result<()> {
+        self.mutate(|entries| {
+            entries.insert(key.to_string(), value.to_vec());
+        })
+    }
+
+    fn retrieve_raw(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.state.read().entries.get(key).cloned())
+    }
+
+    fn delete_raw(&self, key: &str) -> Result<bool> {
+        if !self.exists_raw(key)? {
+            return Ok(false);
+        }
+        self.mutate(|entries| entries.remove(key).is_some())
+    }
+
+    fn exists_raw(&self, key: &str) -> Result<bool> {
+        Ok(self.state.read().entries.contains_key(key))
+    }
+
+    fn storage_secret(&self) -> Option<Zeroizing<[u8; 32]>> {
+        Some(Zeroizing::new(self.state.read().key.as_inner()))
+    }
+
+    fn change_passphrase(&self, new_passphrase: &str) -> Result<()> {
+        FileVault::change_passphrase(self, new_passphrase)
+    }
+}
+
+// ============================================================================
+// ENCODING
+// ============================================================================
+
+/// Derive a fresh key under a random salt (entries left empty)
+fn new_key_state(passphrase: &str, params: ScryptParams) -> Result<VaultState> {
+    let mut salt = [0u8; SALT_SIZE];
+    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut salt);
+
+    let mut header = [0u8; HEADER_SIZE];
+    header[..8].copy_from_slice(MAGIC);
+    header[8] = VERSION;
+    header[9] = params.log_n;
+    header[10..14].copy_from_slice(&params.r.to_le_bytes());
+    header[14..18].copy_from_slice(&params.p.to_le_bytes());
+    header[18..].copy_from_slice(&salt);
+
+    Ok(VaultState {
+        entries: BTreeMap::new(),
+        key: derive_key(passphrase, &header)?,
+        header,
+    })
+}
+
+fn read_params(header: &[u8; HEADER_SIZE]) -> ScryptParams {
+    ScryptParams {
+        log_n: header[9],
+        r: u32::from_le_bytes(header[10..14].try_into().unwrap()),
+        p: u32::from_le_bytes(header[14..18].try_into().unwrap()),
+    }
+}
+
+fn derive_key(passphrase: &str, header: &[u8; HEADER_SIZE]) -> Result<EncryptionKey> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt(
+        passphrase.as_bytes(),
+        &header[18..],
+        &read_params(header),
+        key.as_mut(),
+    )?;
+    Ok(EncryptionKey::from_bytes(*key))
+}
+
+/// Parse and decrypt a vault file
+fn unlock(bytes: &[u8], passphrase: &str) -> Result<VaultState> {
+    if bytes.len() < HEADER_SIZE + NONCE_SIZE || &bytes[..8] != MAGIC {
+        return Err(Error::StorageCorrupted("Not an Umbra vault file".into()));
+    }
+    if bytes[8] != VERSION {
+        return Err(Error::StorageCorrupted(format!(
+            "Unsupported vault version {}",
+            bytes[8]
+        )));
+    }
+
+    let header: [u8; HEADER_SIZE] = bytes[..HEADER_SIZE].try_into().unwrap();
+    read_params(&header).validate()?;
+    let key = derive_key(passphrase, &header)?;
+
+    let nonce = Nonce::from_bytes(
+        bytes[HEADER_SIZE..HEADER_SIZE + NONCE_SIZE]
+            .try_into()
+            .unwrap(),
+    );
+    let plaintext = Zeroizing::new(
+        decrypt(&key, &nonce, &bytes[HEADER_SIZE + NONCE_SIZE..], &header)
+            .map_err(|_| Error::StorageReadError("Wrong passphrase or corrupted vault".into()))?,
+    );
+    let entries = bincode::deserialize(&plaintext)
+        .map_err(|e| Error::StorageCorrupted(format!("Invalid vault contents: {}", e)))?;
+
+    Ok(VaultState {
+        entries,
+        key,
+        header,
+    })
+}
+
+/// Encrypt `state` and atomically replace the file at `path`
+fn persist(path: &Path, state: &VaultState) -> Result<()> {
+    let plaintext = Zeroizing::new(
+        bincode::serialize(&state.entries)
+            .map_err(|e| Error::StorageWriteError(format!("Failed to encode vault: {}", e)))?,
+    );
+    let (nonce, ciphertext) = encrypt(&state.key, &plaintext, &state.header)?;
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp = PathBuf::from(tmp_name);
+
+    let write = || -> std::io::Result<()> {
+        let mut file = open_private(&tmp)?;
+        file.write_all(&state.header)?;
+        file.write_all(nonce.as_bytes())?;
+        file.write_all(&ciphertext)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        // Make the rename itself durable
+        #[cfg(unix)]
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::File::open(parent)?.sync_all()?;
+        }
+        Ok(())
+    };
+
+    write().map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        Error::StorageWriteError(format!("Failed to write vault: {}", e))
+    })
+}
+
+// ============================================================================
+// PERMISSIONS
+// ============================================================================
+
+#[cfg(unix)]
+fn open_private(path: &Path) -> std::io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // A stale temp file may have been created with other permissions
+    let _ = fs::remove_file(path);
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn open_private(path: &Path) -> std::io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    if dir.exists() {
+        return Ok(());
+    }
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| Error::StorageWriteError(format!("Failed to create vault directory: {}", e)))
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)
+        .map_err(|e| Error::StorageWriteError(format!("Failed to create vault directory: {}", e)))
+}
+
+/// Refuse vaults that other local users can read or replace
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let meta = fs::symlink_metadata(path)
+        .map_err(|e| Error::StorageReadError(format!("Failed to stat vault: {}", e)))?;
+    if !meta.file_type().is_file() {
+        return Err(Error::StorageReadError(
+            "Vault path is not a regular file".into(),
+        ));
+    }
+    let mode = meta.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        return Err(Error::StorageReadError(format!(
+            "Vault {} has insecure permissions {:o} (expected 600)",
+            path.display(),
+            mode
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SecureStore;
+
+    /// Cheap KDF cost so tests stay fast
+    const TEST_PARAMS: ScryptParams = ScryptParams {
+        log_n: 4,
+        r: 1,
+        p: 1,
+    };
+
+    fn vault_path(dir: &tempfile::TempDir) -> PathBuf {
+        dir.path().join("keys").join(VAULT_FILE_NAME)
+    }
+
+    #[test]
+    fn test_vault_persists_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_path(&dir);
+
+        {
+            let vault = FileVault::open_with_params(&path, "hunter2", TEST_PARAMS).unwrap();
+            let store = SecureStore::with_backend(vault);
+            store
+                .store("umbra.identity.did", b"did:key:z6MkTest")
+                .unwrap();
+            store.store("gone", b"x").unwrap();
+            assert!(store.delete("gone").unwrap());
+        }
+
+        let vault = FileVault::open_with_params(&path, "hunter2", TEST_PARAMS).unwrap();
+        let store = SecureStore::with_backend(vault);
+        let did = store.retrieve("umbra.identity.did").unwrap().unwrap();
+        assert_eq!(&*did, b"did:key:z6MkTest");
+        assert!(!store.exists("gone").unwrap());
+
+        // The passphrase-derived key protects the contents at rest
+        let raw = fs::read(&path).unwrap();
+        assert!(!raw.windows(16).any(|w| w == b"did:key:z6MkTest"));
+    }
+
+    #[test]
+    fn test_vault_wrong_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_path(&dir);
+        FileVault::open_with_params(&path, "right", TEST_PARAMS).unwrap();
+
+        let result = FileVault::open_with_params(&path, "wrong", TEST_PARAMS);
+        assert!(matches!(result, Err(Error::StorageReadError(_))));
+    }
+
+    #[test]
+    fn test_vault_change_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_path(&dir);
+
+        let vault = FileVault::open_with_params(&path, "old", TEST_PARAMS).unwrap();
+        vault.store_raw("k", b"v").unwrap();
+        let old_secret = vault.storage_secret().unwrap();
+        vault.change_passphrase("new").unwrap();
+        drop(vault);
+
+        assert!(FileVault::open_with_params(&path, "old", TEST_PARAMS).is_err());
+        let vault = FileVault::open_with_params(&path, "new", TEST_PARAMS).unwrap();
+        assert_eq!(vault.retrieve_raw("k").unwrap().unwrap(), b"v");
+
+        // The database key is bound to the passphrase, so it changes too
+        let new_secret = vault.storage_secret().unwrap();
+        assert_ne!(*old_secret, *new_secret);
+        drop(vault);
+        let vault = FileVault::open_with_params(&path, "new", TEST_PARAMS).unwrap();
+        assert_eq!(*vault.storage_secret().unwrap(), *new_secret);
+    }
+
+    #[test]
+    fn test_vault_rejects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_path(&dir);
+        let vault = FileVault::open_with_params(&path, "pw", TEST_PARAMS).unwrap();
+        vault.store_raw("k", b"v").unwrap();
+        drop(vault);
+
+        // Flipping a header byte breaks the AEAD binding
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[HEADER_SIZE - 1] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(FileVault::open_with_params(&path, "pw", TEST_PARAMS).is_err());
+
+        fs::write(&path, b"not a vault").unwrap();
+        assert!(matches!(
+            FileVault::open_with_params(&path, "pw", TEST_PARAMS),
+            Err(Error::StorageCorrupted(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_vault_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_path(&dir);
+        FileVault::open_with_params(&path, "pw", TEST_PARAMS).unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        let dir_mode = fs::metadata(path.parent().unwrap())
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o777;
+        assert_eq!(dir_mode, 0o700);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let result = FileVault::open_with_params(&path, "pw", TEST_PARAMS);
+        assert!(matches!(result, Err(Error::StorageReadError(_))));
+    }
+}
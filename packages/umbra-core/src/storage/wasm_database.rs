@@ -8,6 +8,7 @@
 //! target `wasm32-unknown-unknown`).
 
 use super::schema;
+use super::search::{self, match_expression, MessageSearchHit, SearchSource};
 use crate::error::{Error, Result};
 use serde_json::json;
 use wasm_bindgen::prelude::*;
@@ -199,6 +200,51 @@ impl Database {
             sql_bridge_execute_batch(schema::MIGRATE_V16_TO_V17).map_err(js_err)?;
             tracing::info!("Migration v16 → v17 complete");
         }
+        if from_version < 18 {
+            tracing::info!("Running migration v17 → v18 (ratchet sessions)");
+            sql_bridge_execute_batch(schema::MIGRATE_V17_TO_V18).map_err(js_err)?;
+            tracing::info!("Migration v17 → v18 complete");
+        }
+        if from_version < 19 {
+            tracing::info!("Running migration v18 → v19 (message search index)");
+            sql_bridge_execute_batch(schema::MIGRATE_V18_TO_V19).map_err(js_err)?;
+            tracing::info!("Migration v18 → v19 complete");
+        }
+        if from_version < 20 {
+            tracing::info!("Running migration v19 → v20 (device keys)");
+            sql_bridge_execute_batch(schema::MIGRATE_V19_TO_V20).map_err(js_err)?;
+            tracing::info!("Migration v19 → v20 complete");
+        }
+        if from_version < 21 {
+            tracing::info!("Running migration v20 → v21 (friend key rotation)");
+            sql_bridge_execute_batch(schema::MIGRATE_V20_TO_V21).map_err(js_err)?;
+            tracing::info!("Migration v20 → v21 complete");
+        }
+        if from_version < 22 {
+            tracing::info!("Running migration v21 → v22 (friend verification)");
+            sql_bridge_execute_batch(schema::MIGRATE_V21_TO_V22).map_err(js_err)?;
+            tracing::info!("Migration v21 → v22 complete");
+        }
+        if from_version < 23 {
+            tracing::info!("Running migration v22 → v23 (disappearing messages)");
+            sql_bridge_execute_batch(schema::MIGRATE_V22_TO_V23).map_err(js_err)?;
+            tracing::info!("Migration v22 → v23 complete");
+        }
+        if from_version < 24 {
+            tracing::info!("Running migration v23 → v24 (MLS groups)");
+            sql_bridge_execute_batch(schema::MIGRATE_V23_TO_V24).map_err(js_err)?;
+            tracing::info!("Migration v23 → v24 complete");
+        }
+        if from_version < 25 {
+            tracing::info!("Running migration v24 → v25 (community op log)");
+            sql_bridge_execute_batch(schema::MIGRATE_V24_TO_V25).map_err(js_err)?;
+            tracing::info!("Migration v24 → v25 complete");
+        }
+        if from_version < 26 {
+            tracing::info!("Running migration v25 → v26 (channel keyring)");
+            sql_bridge_execute_batch(schema::MIGRATE_V25_TO_V26).map_err(js_err)?;
+            tracing::info!("Migration v25 → v26 complete");
+        }
         Ok(())
     }
 
@@ -251,6 +297,12 @@ impl Database {
             avatar: row["avatar"].as_str().map(|s| s.to_string()),
             created_at: row["created_at"].as_i64().unwrap_or(0),
             updated_at: row["updated_at"].as_i64().unwrap_or(0),
+            previous_encryption_key: row["previous_encryption_key"]
+                .as_str()
+                .map(|s| s.to_string()),
+            key_rotated_at: row["key_rotated_at"].as_i64(),
+            verified: row["verified"].as_i64().unwrap_or(0) != 0,
+            verified_at: row["verified_at"].as_i64(),
         }
     }
 
@@ -263,6 +315,7 @@ impl Database {
             created_at: row["created_at"].as_i64().unwrap_or(0),
             last_message_at: row["last_message_at"].as_i64(),
             unread_count: row["unread_count"].as_i64().unwrap_or(0) as i32,
+            disappearing_timer: row["disappearing_timer"].as_i64().map(|t| t as u32),
         }
     }
 
@@ -286,6 +339,7 @@ impl Database {
             reply_to_id: row["reply_to_id"].as_str().map(|s| s.to_string()),
             thread_id: row["thread_id"].as_str().map(|s| s.to_string()),
             forwarded_from: row["forwarded_from"].as_str().map(|s| s.to_string()),
+            expires_at: row["expires_at"].as_i64(),
         }
     }
 
@@ -407,7 +461,7 @@ impl Database {
     /// Get a friend by DID
     pub fn get_friend(&self, did: &str) -> Result<Option<FriendRecord>> {
         let rows = self.query(
-            "SELECT id, did, display_name, signing_key, encryption_key, status, avatar, created_at, updated_at FROM friends WHERE did = ?",
+            "SELECT id, did, display_name, signing_key, encryption_key, status, avatar, created_at, updated_at, previous_encryption_key, key_rotated_at, verified, verified_at FROM friends WHERE did = ?",
             json!([did]),
         )?;
         Ok(rows.first().map(Self::parse_friend))
@@ -416,7 +470,7 @@ impl Database {
     /// Get all friends
     pub fn get_all_friends(&self) -> Result<Vec<FriendRecord>> {
         let rows = self.query(
-            "SELECT id, did, display_name, signing_key, encryption_key, status, avatar, created_at, updated_at FROM friends ORDER BY display_name",
+            "SELECT id, did, display_name, signing_key, encryption_key, status, avatar, created_at, updated_at, previous_encryption_key, key_rotated_at, verified, verified_at FROM friends ORDER BY display_name",
             json!([]),
         )?;
         Ok(rows.iter().map(Self::parse_friend).collect())
@@ -450,15 +504,43 @@ impl Database {
     }
 
     /// Update a friend's encryption key (for key rotation)
+    ///
+    /// The replaced key is kept as `previous_encryption_key` for the
+    /// rotation grace window.
     pub fn update_friend_encryption_key(
         &self,
         did: &str,
         new_encryption_key: &[u8; 32],
+    ) -> Result<bool> {
+        self.rotate_friend_encryption_key(did, new_encryption_key, crate::time::now_timestamp())
+    }
+
+    /// Apply a friend's key rotation made at `rotated_at`
+    ///
+    /// Ignored (returns false) if the key is unchanged or we already applied
+    /// a later rotation. A key change clears the friend's verification.
+    pub fn rotate_friend_encryption_key(
+        &self,
+        did: &str,
+        new_encryption_key: &[u8; 32],
+        rotated_at: i64,
     ) -> Result<bool> {
         let now = crate::time::now_timestamp();
+        let new_key = hex::encode(new_encryption_key);
         let affected = self.exec(
-            "UPDATE friends SET encryption_key = ?, updated_at = ? WHERE did = ?",
-            json!([hex::encode(new_encryption_key), now, did]),
+            "UPDATE friends SET previous_encryption_key = encryption_key, encryption_key = ?, key_rotated_at = ?, verified = 0, verified_at = NULL, updated_at = ? WHERE did = ? AND encryption_key != ? AND (key_rotated_at IS NULL OR key_rotated_at < ?)",
+            json!([new_key, rotated_at, now, did, new_key, rotated_at]),
+        )?;
+        Ok(affected > 0)
+    }
+
+    /// Mark a friend's safety number as verified (or clear it)
+    pub fn set_friend_verified(&self, did: &str, verified: bool) -> Result<bool> {
+        let now = crate::time::now_timestamp();
+        let verified_at = verified.then_some(now);
+        let affected = self.exec(
+            "UPDATE friends SET verified = ?, verified_at = ?, updated_at = ? WHERE did = ?",
+            json!([verified as i32, verified_at, now, did]),
         )?;
         Ok(affected > 0)
     }
@@ -488,7 +570,7 @@ impl Database {
     /// Get a conversation by ID
     pub fn get_conversation(&self, id: &str) -> Result<Option<ConversationRecord>> {
         let rows = self.query(
-            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count FROM conversations WHERE id = ?",
+            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count, disappearing_timer FROM conversations WHERE id = ?",
             json!([id]),
         )?;
         Ok(rows.first().map(Self::parse_conversation))
@@ -500,7 +582,7 @@ impl Database {
         friend_did: &str,
     ) -> Result<Option<ConversationRecord>> {
         let rows = self.query(
-            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count FROM conversations WHERE friend_did = ?",
+            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count, disappearing_timer FROM conversations WHERE friend_did = ?",
             json!([friend_did]),
         )?;
         Ok(rows.first().map(Self::parse_conversation))
@@ -509,7 +591,7 @@ impl Database {
     /// Get all conversations sorted by last message
     pub fn get_all_conversations(&self) -> Result<Vec<ConversationRecord>> {
         let rows = self.query(
-            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count FROM conversations ORDER BY last_message_at DESC",
+            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count, disappearing_timer FROM conversations ORDER BY last_message_at DESC",
             json!([]),
         )?;
         Ok(rows.iter().map(Self::parse_conversation).collect())
@@ -520,6 +602,9 @@ impl Database {
     // ========================================================================
 
     /// Store an encrypted message
+    ///
+    /// If the conversation has a disappearing-message timer, the message
+    /// expires `timer` seconds after `timestamp`.
     pub fn store_message(
         &self,
         id: &str,
@@ -528,10 +613,36 @@ impl Database {
         content_encrypted: &[u8],
         nonce: &[u8],
         timestamp: i64,
+    ) -> Result<()> {
+        self.store_message_with_expiry(
+            id,
+            conversation_id,
+            sender_did,
+            content_encrypted,
+            nonce,
+            timestamp,
+            None,
+        )
+    }
+
+    /// Store an encrypted message with an explicit expiry (Unix timestamp ms)
+    ///
+    /// `None` falls back to the conversation's disappearing-message timer.
+    #[allow(clippy::too_many_arguments)]
+    pub fn store_message_with_expiry(
+        &self,
+        id: &str,
+        conversation_id: &str,
+        sender_did: &str,
+        content_encrypted: &[u8],
+        nonce: &[u8],
+        timestamp: i64,
+        expires_at: Option<i64>,
     ) -> Result<()> {
         self.exec(
-            "INSERT INTO messages (id, conversation_id, sender_did, content_encrypted, nonce, timestamp) VALUES (?, ?, ?, ?, ?, ?)",
-            json!([id, conversation_id, sender_did, hex::encode(content_encrypted), hex::encode(nonce), timestamp]),
+            "INSERT INTO messages (id, conversation_id, sender_did, content_encrypted, nonce, timestamp, expires_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, COALESCE(?7, (SELECT ?6 + disappearing_timer * 1000 FROM conversations WHERE id = ?2)))",
+            json!([id, conversation_id, sender_did, hex::encode(content_encrypted), hex::encode(nonce), timestamp, expires_at]),
         )?;
         self.exec(
             "UPDATE conversations SET last_message_at = ? WHERE id = ?",
@@ -549,9 +660,15 @@ impl Database {
     ) -> Result<Vec<MessageRecord>> {
         let rows = self.query(
             "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read, \
-             edited, edited_at, deleted, deleted_at, pinned, pinned_by, pinned_at, reply_to_id, thread_id, forwarded_from \
-             FROM messages WHERE conversation_id = ? AND thread_id IS NULL ORDER BY timestamp DESC LIMIT ? OFFSET ?",
-            json!([conversation_id, limit as i64, offset as i64]),
+             edited, edited_at, deleted, deleted_at, pinned, pinned_by, pinned_at, reply_to_id, thread_id, forwarded_from, expires_at \
+             FROM messages WHERE conversation_id = ? AND thread_id IS NULL AND (expires_at IS NULL OR expires_at > ?) \
+             ORDER BY timestamp DESC LIMIT ? OFFSET ?",
+            json!([
+                conversation_id,
+                crate::time::now_timestamp_millis(),
+                limit as i64,
+                offset as i64
+            ]),
         )?;
         let mut messages: Vec<MessageRecord> = rows.iter().map(Self::parse_message).collect();
         messages.reverse();
@@ -614,6 +731,154 @@ impl Database {
         Ok(count)
     }
 
+    // ── Disappearing Messages ────────────────────────────────────────────
+
+    /// Set a conversation's disappearing-message timer (seconds, None = off)
+    ///
+    /// Last-writer-wins by `set_at` (Unix timestamp ms). Returns false if a
+    /// newer setting is already stored or the conversation doesn't exist.
+    pub fn set_disappearing_timer(
+        &self,
+        conversation_id: &str,
+        timer: Option<u32>,
+        set_at: i64,
+    ) -> Result<bool> {
+        Ok(self.exec(
+            "UPDATE conversations SET disappearing_timer = ?, disappearing_timer_set_at = ? \
+             WHERE id = ? AND (disappearing_timer_set_at IS NULL OR disappearing_timer_set_at < ?)",
+            json!([timer, set_at, conversation_id, set_at]),
+        )? > 0)
+    }
+
+    /// Delete every message and DM shared file whose expiry has passed
+    pub fn delete_expired(&self, now: i64) -> Result<ExpiredSweep> {
+        self.exec(
+            "DELETE FROM reactions WHERE message_id IN \
+             (SELECT id FROM messages WHERE expires_at IS NOT NULL AND expires_at <= ?)",
+            json!([now]),
+        )?;
+        let messages = self.exec(
+            "DELETE FROM messages WHERE expires_at IS NOT NULL AND expires_at <= ?",
+            json!([now]),
+        )?;
+
+        let expired_files = self.query(
+            "SELECT id, storage_chunks_json FROM dm_shared_files \
+             WHERE expires_at IS NOT NULL AND expires_at <= ?",
+            json!([now]),
+        )?;
+        for row in &expired_files {
+            let chunks_json = row["storage_chunks_json"].as_str().unwrap_or("");
+            if let Some(file_id) = super::chunking::manifest_file_id(chunks_json) {
+                self.delete_chunks_for_file(&file_id)?;
+                self.delete_manifest(&file_id)?;
+            }
+            self.exec(
+                "DELETE FROM dm_shared_files WHERE id = ?",
+                json!([row["id"].as_str().unwrap_or("")]),
+            )?;
+        }
+
+        Ok(ExpiredSweep {
+            messages: messages as usize,
+            files: expired_files.len(),
+        })
+    }
+
+    // ── Message Search ───────────────────────────────────────────────────
+
+    /// Add or refresh the search index entry for a stored DM or group message
+    pub fn index_message(&self, message_id: &str, content: &str) -> Result<()> {
+        let indexed: Option<String> = self.query_scalar(
+            "SELECT s.content FROM message_search_docs d
+             JOIN message_search s ON s.rowid = d.id
+             WHERE d.source IN ('dm', 'group') AND d.message_id = ?",
+            json!([message_id]),
+        )?;
+        if indexed.as_deref() == Some(content) {
+            return Ok(());
+        }
+
+        self.exec(
+            "DELETE FROM message_search WHERE rowid IN (
+                SELECT id FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = ?
+             )",
+            json!([message_id]),
+        )?;
+        self.exec(
+            "DELETE FROM message_search_docs WHERE source IN ('dm', 'group') AND message_id = ?",
+            json!([message_id]),
+        )?;
+
+        let inserted = self.exec(
+            "INSERT INTO message_search_docs (message_id, source, scope_id, sender_did, created_at)
+             SELECT m.id, CASE WHEN c.type = 'group' THEN 'group' ELSE 'dm' END,
+                    m.conversation_id, m.sender_did, m.timestamp
+             FROM messages m JOIN conversations c ON c.id = m.conversation_id
+             WHERE m.id = ? AND m.deleted = 0",
+            json!([message_id]),
+        )?;
+        if inserted > 0 {
+            self.exec(
+                "INSERT INTO message_search (rowid, content) VALUES (last_insert_rowid(), ?)",
+                json!([content]),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Find a stored message by conversation or group ID, sender, and timestamp
+    pub fn find_message_id(
+        &self,
+        scope_id: &str,
+        sender_did: &str,
+        timestamp: i64,
+    ) -> Result<Option<String>> {
+        self.query_scalar(
+            "SELECT m.id FROM messages m JOIN conversations c ON c.id = m.conversation_id
+             WHERE (c.id = ?1 OR c.group_id = ?1) AND m.sender_did = ?2 AND m.timestamp = ?3
+             LIMIT 1",
+            json!([scope_id, sender_did, timestamp]),
+        )
+    }
+
+    /// Ranked full-text search across everything `reader_did` can read,
+    /// with community hits limited to `visible_channels`
+    pub fn search_messages(
+        &self,
+        query: &str,
+        reader_did: &str,
+        sources: &[SearchSource],
+        visible_channels: &[String],
+        limit: usize,
+    ) -> Result<Vec<MessageSearchHit>> {
+        let Some(expr) = match_expression(query) else {
+            return Ok(Vec::new());
+        };
+        let visible_channels = serde_json::to_string(visible_channels)
+            .map_err(|e| Error::SerializationError(e.to_string()))?;
+        let rows = self.query(
+            &search::search_sql(sources),
+            json!([expr, reader_did, limit as i64, visible_channels]),
+        )?;
+        Ok(rows
+            .iter()
+            .map(|row| MessageSearchHit {
+                message_id: row["message_id"].as_str().unwrap_or("").to_string(),
+                source: row["source"]
+                    .as_str()
+                    .and_then(SearchSource::parse)
+                    .unwrap_or(SearchSource::Dm),
+                scope_id: row["scope_id"].as_str().unwrap_or("").to_string(),
+                community_id: row["community_id"].as_str().map(|s| s.to_string()),
+                sender_did: row["sender_did"].as_str().unwrap_or("").to_string(),
+                created_at: row["created_at"].as_i64().unwrap_or(0),
+                snippet: row["snippet"].as_str().unwrap_or("").to_string(),
+                rank: row["rank"].as_f64().unwrap_or(0.0),
+            })
+            .collect())
+    }
+
     /// Increment unread count for a conversation
     pub fn increment_unread_count(&self, conversation_id: &str) -> Result<i32> {
         self.exec(
@@ -634,7 +899,7 @@ impl Database {
     pub fn get_message(&self, message_id: &str) -> Result<Option<MessageRecord>> {
         let rows = self.query(
             "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read, \
-             edited, edited_at, deleted, deleted_at, pinned, pinned_by, pinned_at, reply_to_id, thread_id, forwarded_from \
+             edited, edited_at, deleted, deleted_at, pinned, pinned_by, pinned_at, reply_to_id, thread_id, forwarded_from, expires_at \
              FROM messages WHERE id = ?",
             json!([message_id]),
         )?;
@@ -685,7 +950,7 @@ impl Database {
     pub fn get_pinned_messages(&self, conversation_id: &str) -> Result<Vec<MessageRecord>> {
         let rows = self.query(
             "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read, \
-             edited, edited_at, deleted, deleted_at, pinned, pinned_by, pinned_at, reply_to_id, thread_id, forwarded_from \
+             edited, edited_at, deleted, deleted_at, pinned, pinned_by, pinned_at, reply_to_id, thread_id, forwarded_from, expires_at \
              FROM messages WHERE conversation_id = ? AND pinned = 1 ORDER BY pinned_at DESC",
             json!([conversation_id]),
         )?;
@@ -696,7 +961,7 @@ impl Database {
     pub fn get_thread_messages(&self, thread_id: &str) -> Result<Vec<MessageRecord>> {
         let rows = self.query(
             "SELECT id, conversation_id, sender_did, content_encrypted, nonce, timestamp, delivered, read, \
-             edited, edited_at, deleted, deleted_at, pinned, pinned_by, pinned_at, reply_to_id, thread_id, forwarded_from \
+             edited, edited_at, deleted, deleted_at, pinned, pinned_by, pinned_at, reply_to_id, thread_id, forwarded_from, expires_at \
              FROM messages WHERE thread_id = ? OR id = ? ORDER BY timestamp ASC",
             json!([thread_id, thread_id]),
         )?;
@@ -720,7 +985,8 @@ impl Database {
         let nonce_hex = hex::encode(nonce);
         self.exec(
             "INSERT INTO messages (id, conversation_id, sender_did, content_encrypted, nonce, timestamp, \
-             reply_to_id, thread_id, forwarded_from) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+             reply_to_id, thread_id, forwarded_from, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, \
+             (SELECT ?6 + disappearing_timer * 1000 FROM conversations WHERE id = ?2))",
             json!([id, conversation_id, sender_did, ct_hex, nonce_hex, timestamp,
                    reply_to_id, thread_id, forwarded_from]),
         )?;
@@ -872,7 +1138,7 @@ impl Database {
     /// Get conversation by group ID
     pub fn get_conversation_by_group(&self, group_id: &str) -> Result<Option<ConversationRecord>> {
         let rows = self.query(
-            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count FROM conversations WHERE group_id = ?",
+            "SELECT id, friend_did, type, group_id, created_at, last_message_at, unread_count, disappearing_timer FROM conversations WHERE group_id = ?",
             json!([group_id]),
         )?;
         Ok(rows.first().map(Self::parse_conversation))
@@ -958,6 +1224,242 @@ impl Database {
         )? > 0)
     }
 
+    // ========================================================================
+    // RATCHET SESSION OPERATIONS
+    // ========================================================================
+
+    /// Store (or replace) the serialized Double Ratchet session for a peer
+    pub fn save_ratchet_session(&self, peer_did: &str, state: &[u8]) -> Result<()> {
+        let now = crate::time::now_timestamp();
+        self.exec(
+            "INSERT INTO ratchet_sessions (peer_did, state, created_at, updated_at) VALUES (?, ?, ?, ?) ON CONFLICT(peer_did) DO UPDATE SET state = excluded.state, updated_at = excluded.updated_at",
+            json!([peer_did, hex::encode(state), now, now]),
+        )?;
+        Ok(())
+    }
+
+    /// Get the serialized Double Ratchet session for a peer
+    pub fn get_ratchet_session(&self, peer_did: &str) -> Result<Option<Vec<u8>>> {
+        let rows = self.query(
+            "SELECT state FROM ratchet_sessions WHERE peer_did = ?",
+            json!([peer_did]),
+        )?;
+        Ok(rows
+            .first()
+            .and_then(|r| r["state"].as_str())
+            .and_then(|s| hex::decode(s).ok()))
+    }
+
+    /// Delete the Double Ratchet session for a peer
+    pub fn delete_ratchet_session(&self, peer_did: &str) -> Result<bool> {
+        let rows = self.exec(
+            "DELETE FROM ratchet_sessions WHERE peer_did = ?",
+            json!([peer_did]),
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Store one of our X3DH prekeys
+    pub fn store_prekey(&self, prekey: &PrekeyRecord) -> Result<()> {
+        self.exec(
+            "INSERT OR REPLACE INTO ratchet_prekeys (kind, key_id, secret_key, public_key, signature, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+            json!([prekey.kind, prekey.key_id, hex::encode(&prekey.secret_key), hex::encode(&prekey.public_key), prekey.signature, prekey.created_at]),
+        )?;
+        Ok(())
+    }
+
+    /// Get a prekey by kind ("signed" or "one_time") and ID
+    pub fn get_prekey(&self, kind: &str, key_id: u32) -> Result<Option<PrekeyRecord>> {
+        let rows = self.query(
+            "SELECT kind, key_id, secret_key, public_key, signature, created_at FROM ratchet_prekeys WHERE kind = ? AND key_id = ?",
+            json!([kind, key_id]),
+        )?;
+        Ok(rows.first().map(Self::parse_prekey))
+    }
+
+    /// Get the most recently created prekey of a kind
+    pub fn get_latest_prekey(&self, kind: &str) -> Result<Option<PrekeyRecord>> {
+        let rows = self.query(
+            "SELECT kind, key_id, secret_key, public_key, signature, created_at FROM ratchet_prekeys WHERE kind = ? ORDER BY key_id DESC LIMIT 1",
+            json!([kind]),
+        )?;
+        Ok(rows.first().map(Self::parse_prekey))
+    }
+
+    /// Get all prekeys of a kind, oldest first
+    pub fn get_prekeys(&self, kind: &str) -> Result<Vec<PrekeyRecord>> {
+        let rows = self.query(
+            "SELECT kind, key_id, secret_key, public_key, signature, created_at FROM ratchet_prekeys WHERE kind = ? ORDER BY key_id",
+            json!([kind]),
+        )?;
+        Ok(rows.iter().map(Self::parse_prekey).collect())
+    }
+
+    /// Get the next unused prekey ID for a kind
+    pub fn next_prekey_id(&self, kind: &str) -> Result<u32> {
+        let id: Option<u32> = self.query_scalar(
+            "SELECT COALESCE(MAX(key_id), 0) + 1 FROM ratchet_prekeys WHERE kind = ?",
+            json!([kind]),
+        )?;
+        Ok(id.unwrap_or(1))
+    }
+
+    /// Delete a prekey (one-time prekeys are deleted once consumed)
+    pub fn delete_prekey(&self, kind: &str, key_id: u32) -> Result<bool> {
+        let rows = self.exec(
+            "DELETE FROM ratchet_prekeys WHERE kind = ? AND key_id = ?",
+            json!([kind, key_id]),
+        )?;
+        Ok(rows > 0)
+    }
+
+    fn parse_prekey(row: &serde_json::Value) -> PrekeyRecord {
+        let bytes = |field: &str| {
+            row[field]
+                .as_str()
+                .map(|s| hex::decode(s).unwrap_or_default())
+                .unwrap_or_default()
+        };
+        PrekeyRecord {
+            kind: row["kind"].as_str().unwrap_or("").to_string(),
+            key_id: row["key_id"].as_u64().unwrap_or(0) as u32,
+            secret_key: bytes("secret_key"),
+            public_key: bytes("public_key"),
+            signature: row["signature"].as_str().map(|s| s.to_string()),
+            created_at: row["created_at"].as_i64().unwrap_or(0),
+        }
+    }
+
+    // ========================================================================
+    // MLS GROUP OPERATIONS
+    // ========================================================================
+
+    /// Store (or replace) the serialized MLS state of a group
+    pub fn save_mls_group(&self, group_id: &str, epoch: u64, state: &[u8]) -> Result<()> {
+        let now = crate::time::now_timestamp();
+        self.exec(
+            "INSERT INTO mls_groups (group_id, state, epoch, created_at, updated_at) VALUES (?, ?, ?, ?, ?) ON CONFLICT(group_id) DO UPDATE SET state = excluded.state, epoch = excluded.epoch, updated_at = excluded.updated_at",
+            json!([group_id, hex::encode(state), epoch, now, now]),
+        )?;
+        Ok(())
+    }
+
+    /// Get the serialized MLS state of a group
+    pub fn get_mls_group(&self, group_id: &str) -> Result<Option<Vec<u8>>> {
+        let rows = self.query(
+            "SELECT state FROM mls_groups WHERE group_id = ?",
+            json!([group_id]),
+        )?;
+        Ok(rows
+            .first()
+            .and_then(|r| r["state"].as_str())
+            .and_then(|s| hex::decode(s).ok()))
+    }
+
+    /// Delete the MLS state of a group
+    pub fn delete_mls_group(&self, group_id: &str) -> Result<bool> {
+        let rows = self.exec(
+            "DELETE FROM mls_groups WHERE group_id = ?",
+            json!([group_id]),
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Store one of our MLS key packages and its private keys
+    pub fn store_mls_key_package(
+        &self,
+        reference: &str,
+        key_package: &[u8],
+        secrets: &[u8],
+    ) -> Result<()> {
+        self.exec(
+            "INSERT OR REPLACE INTO mls_key_packages (reference, key_package, secrets, created_at) VALUES (?, ?, ?, ?)",
+            json!([reference, hex::encode(key_package), hex::encode(secrets), crate::time::now_timestamp()]),
+        )?;
+        Ok(())
+    }
+
+    /// Get one of our MLS key packages and its private keys by reference
+    pub fn get_mls_key_package(&self, reference: &str) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let rows = self.query(
+            "SELECT key_package, secrets FROM mls_key_packages WHERE reference = ?",
+            json!([reference]),
+        )?;
+        Ok(rows.first().and_then(|r| {
+            let key_package = hex::decode(r["key_package"].as_str()?).ok()?;
+            let secrets = hex::decode(r["secrets"].as_str()?).ok()?;
+            Some((key_package, secrets))
+        }))
+    }
+
+    /// Delete an MLS key package once it has been used
+    pub fn delete_mls_key_package(&self, reference: &str) -> Result<bool> {
+        let rows = self.exec(
+            "DELETE FROM mls_key_packages WHERE reference = ?",
+            json!([reference]),
+        )?;
+        Ok(rows > 0)
+    }
+
+    // ========================================================================
+    // DEVICE OPERATIONS
+    // ========================================================================
+
+    /// Get this install's device keys, if they have been generated
+    pub fn get_local_device(&self) -> Result<Option<LocalDeviceRecord>> {
+        let rows = self.query(
+            "SELECT device_id, signing_secret, encryption_secret, certificate, created_at FROM local_device WHERE id = 1",
+            json!([]),
+        )?;
+        Ok(rows.first().map(|row| {
+            let bytes = |field: &str| {
+                row[field]
+                    .as_str()
+                    .map(|s| hex::decode(s).unwrap_or_default())
+                    .unwrap_or_default()
+            };
+            LocalDeviceRecord {
+                device_id: row["device_id"].as_str().unwrap_or("").to_string(),
+                signing_secret: bytes("signing_secret"),
+                encryption_secret: bytes("encryption_secret"),
+                certificate: row["certificate"].as_str().map(|s| s.to_string()),
+                created_at: row["created_at"].as_i64().unwrap_or(0),
+            }
+        }))
+    }
+
+    /// Store (or replace) this install's device keys
+    pub fn save_local_device(&self, device: &LocalDeviceRecord) -> Result<()> {
+        self.exec(
+            "INSERT OR REPLACE INTO local_device (id, device_id, signing_secret, encryption_secret, certificate, created_at) VALUES (1, ?, ?, ?, ?, ?)",
+            json!([device.device_id, hex::encode(&device.signing_secret), hex::encode(&device.encryption_secret), device.certificate, device.created_at]),
+        )?;
+        Ok(())
+    }
+
+    /// Get the stored device list JSON for a DID
+    pub fn get_device_list(&self, did: &str) -> Result<Option<String>> {
+        let rows = self.query(
+            "SELECT list_json FROM device_lists WHERE did = ?",
+            json!([did]),
+        )?;
+        Ok(rows
+            .first()
+            .and_then(|r| r["list_json"].as_str())
+            .map(|s| s.to_string()))
+    }
+
+    /// Store a device list for a DID if it is newer than the stored one.
+    /// Returns true if the list was written.
+    pub fn save_device_list(&self, did: &str, version: u64, list_json: &str) -> Result<bool> {
+        let now = crate::time::now_timestamp();
+        let rows = self.exec(
+            "INSERT INTO device_lists (did, version, list_json, updated_at) VALUES (?, ?, ?, ?) ON CONFLICT(did) DO UPDATE SET version = excluded.version, list_json = excluded.list_json, updated_at = excluded.updated_at WHERE excluded.version > device_lists.version",
+            json!([did, version, list_json, now]),
+        )?;
+        Ok(rows > 0)
+    }
+
     // ========================================================================
     // FRIEND REQUEST OPERATIONS
     // ========================================================================
@@ -2034,6 +2536,15 @@ impl Database {
         Ok(rows.iter().map(Self::parse_community_role).collect())
     }
 
+    /// Get a role by ID
+    pub fn get_community_role(&self, id: &str) -> Result<Option<CommunityRoleRecord>> {
+        let rows = self.query(
+            "SELECT id, community_id, name, color, icon, badge, position, hoisted, mentionable, is_preset, permissions_bitfield, created_at, updated_at FROM community_roles WHERE id = ?",
+            json!([id]),
+        )?;
+        Ok(rows.first().map(Self::parse_community_role))
+    }
+
     /// Get the owner role for a community
     pub fn get_owner_role(&self, community_id: &str) -> Result<Option<CommunityRoleRecord>> {
         let rows = self.query(
@@ -2340,6 +2851,12 @@ impl Database {
         Ok(())
     }
 
+    /// Remove a message row outright
+    pub fn delete_community_message(&self, id: &str) -> Result<()> {
+        self.exec("DELETE FROM community_messages WHERE id = ?", json!([id]))?;
+        Ok(())
+    }
+
     /// Delete a community message for everyone
     pub fn delete_community_message_for_everyone(&self, id: &str) -> Result<()> {
         self.exec(
@@ -2363,17 +2880,22 @@ impl Database {
         Ok(())
     }
 
-    /// Search community messages in a channel
+    /// Search community messages in a channel, newest first
     pub fn search_community_messages(
         &self,
         channel_id: &str,
         query: &str,
         limit: usize,
     ) -> Result<Vec<CommunityMessageRecord>> {
-        let like_query = format!("%{}%", query);
+        let Some(expr) = match_expression(query) else {
+            return Ok(Vec::new());
+        };
         let rows = self.query(
-            "SELECT id, channel_id, sender_did, content_encrypted, content_plaintext, nonce, key_version, is_e2ee, reply_to_id, thread_id, has_embed, has_attachment, content_warning, edited_at, deleted_for_everyone, created_at FROM community_messages WHERE channel_id = ? AND content_plaintext LIKE ? ORDER BY created_at DESC LIMIT ?",
-            json!([channel_id, like_query, limit as i64]),
+            &format!(
+                "SELECT id, channel_id, sender_did, content_encrypted, content_plaintext, nonce, key_version, is_e2ee, reply_to_id, thread_id, has_embed, has_attachment, content_warning, edited_at, deleted_for_everyone, created_at, metadata_json FROM community_messages WHERE channel_id = ? AND {} AND deleted_for_everyone = 0 ORDER BY created_at DESC LIMIT ?",
+                search::COMMUNITY_MATCH_FILTER
+            ),
+            json!([channel_id, expr, limit as i64]),
         )?;
         Ok(rows.iter().map(Self::parse_community_message).collect())
     }
@@ -2407,9 +2929,9 @@ impl Database {
 
         conditions.push("deleted_for_everyone = 0".to_string());
 
-        if let Some(q) = query {
-            conditions.push("content_plaintext LIKE ?".to_string());
-            param_values.push(json!(format!("%{}%", q)));
+        if let Some(expr) = query.and_then(match_expression) {
+            conditions.push(search::COMMUNITY_MATCH_FILTER.to_string());
+            param_values.push(json!(expr));
         }
         if let Some(did) = from_did {
             conditions.push("sender_did = ?".to_string());
@@ -2449,7 +2971,7 @@ impl Database {
         };
 
         let sql = format!(
-            "SELECT id, channel_id, sender_did, content_encrypted, content_plaintext, nonce, key_version, is_e2ee, reply_to_id, thread_id, has_embed, has_attachment, content_warning, edited_at, deleted_for_everyone, created_at FROM community_messages {} ORDER BY created_at DESC LIMIT ?",
+            "SELECT id, channel_id, sender_did, content_encrypted, content_plaintext, nonce, key_version, is_e2ee, reply_to_id, thread_id, has_embed, has_attachment, content_warning, edited_at, deleted_for_everyone, created_at, metadata_json FROM community_messages {} ORDER BY created_at DESC LIMIT ?",
             where_clause
         );
 
@@ -2815,6 +3337,23 @@ impl Database {
             .collect())
     }
 
+    /// Get a warning by ID
+    pub fn get_community_warning(&self, id: &str) -> Result<Option<CommunityWarningRecord>> {
+        let rows = self.query(
+            "SELECT id, community_id, member_did, reason, warned_by, expires_at, created_at FROM community_warnings WHERE id = ?",
+            json!([id]),
+        )?;
+        Ok(rows.first().map(|row| CommunityWarningRecord {
+            id: row["id"].as_str().unwrap_or("").to_string(),
+            community_id: row["community_id"].as_str().unwrap_or("").to_string(),
+            member_did: row["member_did"].as_str().unwrap_or("").to_string(),
+            reason: row["reason"].as_str().unwrap_or("").to_string(),
+            warned_by: row["warned_by"].as_str().unwrap_or("").to_string(),
+            expires_at: row["expires_at"].as_i64(),
+            created_at: row["created_at"].as_i64().unwrap_or(0),
+        }))
+    }
+
     /// Get active warning count for a member
     pub fn get_active_warning_count(
         &self,
@@ -3097,6 +3636,24 @@ impl Database {
             .collect())
     }
 
+    /// Get a timeout by ID
+    pub fn get_community_timeout(&self, id: &str) -> Result<Option<CommunityTimeoutRecord>> {
+        let rows = self.query(
+            "SELECT id, community_id, member_did, reason, timeout_type, issued_by, expires_at, created_at FROM community_timeouts WHERE id = ?",
+            json!([id]),
+        )?;
+        Ok(rows.first().map(|row| CommunityTimeoutRecord {
+            id: row["id"].as_str().unwrap_or("").to_string(),
+            community_id: row["community_id"].as_str().unwrap_or("").to_string(),
+            member_did: row["member_did"].as_str().unwrap_or("").to_string(),
+            reason: row["reason"].as_str().map(|s| s.to_string()),
+            timeout_type: row["timeout_type"].as_str().unwrap_or("mute").to_string(),
+            issued_by: row["issued_by"].as_str().unwrap_or("").to_string(),
+            expires_at: row["expires_at"].as_i64().unwrap_or(0),
+            created_at: row["created_at"].as_i64().unwrap_or(0),
+        }))
+    }
+
     /// Remove a timeout
     pub fn remove_community_timeout(&self, id: &str) -> Result<()> {
         self.exec("DELETE FROM community_timeouts WHERE id = ?", json!([id]))?;
@@ -3272,6 +3829,22 @@ impl Database {
             .collect())
     }
 
+    /// Get a folder by ID
+    pub fn get_community_file_folder(&self, id: &str) -> Result<Option<CommunityFileFolderRecord>> {
+        let rows = self.query(
+            "SELECT id, channel_id, parent_folder_id, name, created_by, created_at FROM community_file_folders WHERE id = ?",
+            json!([id]),
+        )?;
+        Ok(rows.first().map(|row| CommunityFileFolderRecord {
+            id: row["id"].as_str().unwrap_or("").to_string(),
+            channel_id: row["channel_id"].as_str().unwrap_or("").to_string(),
+            parent_folder_id: row["parent_folder_id"].as_str().map(|s| s.to_string()),
+            name: row["name"].as_str().unwrap_or("").to_string(),
+            created_by: row["created_by"].as_str().unwrap_or("").to_string(),
+            created_at: row["created_at"].as_i64().unwrap_or(0),
+        }))
+    }
+
     /// Delete a file folder
     pub fn delete_community_file_folder(&self, id: &str) -> Result<()> {
         self.exec(
@@ -3513,6 +4086,23 @@ impl Database {
             .collect())
     }
 
+    /// Get a custom emoji by ID
+    pub fn get_community_emoji_by_id(&self, id: &str) -> Result<Option<CommunityEmojiRecord>> {
+        let rows = self.query(
+            "SELECT id, community_id, name, image_url, animated, uploaded_by, created_at FROM community_emoji WHERE id = ?",
+            json!([id]),
+        )?;
+        Ok(rows.first().map(|row| CommunityEmojiRecord {
+            id: row["id"].as_str().unwrap_or("").to_string(),
+            community_id: row["community_id"].as_str().unwrap_or("").to_string(),
+            name: row["name"].as_str().unwrap_or("").to_string(),
+            image_url: row["image_url"].as_str().unwrap_or("").to_string(),
+            animated: row["animated"].as_i64().unwrap_or(0) != 0,
+            uploaded_by: row["uploaded_by"].as_str().unwrap_or("").to_string(),
+            created_at: row["created_at"].as_i64().unwrap_or(0),
+        }))
+    }
+
     /// Rename a custom emoji
     pub fn rename_community_emoji(&self, id: &str, new_name: &str) -> Result<()> {
         self.exec(
@@ -3574,6 +4164,25 @@ impl Database {
             .collect())
     }
 
+    /// Get a sticker by ID
+    pub fn get_community_sticker(&self, id: &str) -> Result<Option<CommunityStickerRecord>> {
+        let rows = self.query(
+            "SELECT id, community_id, pack_id, name, image_url, animated, format, uploaded_by, created_at FROM community_stickers WHERE id = ?",
+            json!([id]),
+        )?;
+        Ok(rows.first().map(|row| CommunityStickerRecord {
+            id: row["id"].as_str().unwrap_or("").to_string(),
+            community_id: row["community_id"].as_str().unwrap_or("").to_string(),
+            pack_id: row["pack_id"].as_str().map(|s| s.to_string()),
+            name: row["name"].as_str().unwrap_or("").to_string(),
+            image_url: row["image_url"].as_str().unwrap_or("").to_string(),
+            animated: row["animated"].as_i64().unwrap_or(0) != 0,
+            format: row["format"].as_str().unwrap_or("png").to_string(),
+            uploaded_by: row["uploaded_by"].as_str().unwrap_or("").to_string(),
+            created_at: row["created_at"].as_i64().unwrap_or(0),
+        }))
+    }
+
     /// Delete a sticker
     pub fn delete_community_sticker(&self, id: &str) -> Result<()> {
         self.exec("DELETE FROM community_stickers WHERE id = ?", json!([id]))?;
@@ -3624,6 +4233,26 @@ impl Database {
             .collect())
     }
 
+    /// Get a sticker pack by ID
+    pub fn get_community_sticker_pack(
+        &self,
+        id: &str,
+    ) -> Result<Option<CommunityStickerPackRecord>> {
+        let rows = self.query(
+            "SELECT id, community_id, name, description, cover_sticker_id, created_by, created_at FROM community_sticker_packs WHERE id = ?",
+            json!([id]),
+        )?;
+        Ok(rows.first().map(|row| CommunityStickerPackRecord {
+            id: row["id"].as_str().unwrap_or("").to_string(),
+            community_id: row["community_id"].as_str().unwrap_or("").to_string(),
+            name: row["name"].as_str().unwrap_or("").to_string(),
+            description: row["description"].as_str().map(|s| s.to_string()),
+            cover_sticker_id: row["cover_sticker_id"].as_str().map(|s| s.to_string()),
+            created_by: row["created_by"].as_str().unwrap_or("").to_string(),
+            created_at: row["created_at"].as_i64().unwrap_or(0),
+        }))
+    }
+
     /// Delete a sticker pack
     pub fn delete_community_sticker_pack(&self, id: &str) -> Result<()> {
         // First nullify pack_id on stickers referencing this pack
@@ -3759,6 +4388,99 @@ impl Database {
         }))
     }
 
+    /// Get one version of a channel's key
+    pub fn get_channel_key(
+        &self,
+        channel_id: &str,
+        key_version: i32,
+    ) -> Result<Option<ChannelKeyRecord>> {
+        let rows = self.query(
+            "SELECT channel_id, key_version, encrypted_key, created_at FROM community_channel_keys WHERE channel_id = ? AND key_version = ?",
+            json!([channel_id, key_version]),
+        )?;
+        Ok(rows.first().map(Self::parse_channel_key))
+    }
+
+    /// Get every version of a channel's key, oldest first
+    pub fn get_channel_keys(&self, channel_id: &str) -> Result<Vec<ChannelKeyRecord>> {
+        let rows = self.query(
+            "SELECT channel_id, key_version, encrypted_key, created_at FROM community_channel_keys WHERE channel_id = ? ORDER BY key_version",
+            json!([channel_id]),
+        )?;
+        Ok(rows.iter().map(Self::parse_channel_key).collect())
+    }
+
+    fn parse_channel_key(row: &serde_json::Value) -> ChannelKeyRecord {
+        ChannelKeyRecord {
+            channel_id: row["channel_id"].as_str().unwrap_or("").to_string(),
+            key_version: row["key_version"].as_i64().unwrap_or(0) as i32,
+            encrypted_key: row["encrypted_key"]
+                .as_str()
+                .map(|s| hex::decode(s).unwrap_or_default())
+                .unwrap_or_default(),
+            created_at: row["created_at"].as_i64().unwrap_or(0),
+        }
+    }
+
+    /// Record that a member has been handed a version of a channel's key
+    pub fn add_channel_key_holder(
+        &self,
+        channel_id: &str,
+        key_version: i32,
+        member_did: &str,
+    ) -> Result<()> {
+        self.exec(
+            "INSERT OR IGNORE INTO community_channel_key_holders (channel_id, key_version, member_did) VALUES (?, ?, ?)",
+            json!([channel_id, key_version, member_did]),
+        )?;
+        Ok(())
+    }
+
+    /// Get the members holding a version of a channel's key
+    pub fn get_channel_key_holders(&self, channel_id: &str, key_version: i32) -> Result<Vec<String>> {
+        let rows = self.query(
+            "SELECT member_did FROM community_channel_key_holders WHERE channel_id = ? AND key_version = ?",
+            json!([channel_id, key_version]),
+        )?;
+        Ok(rows
+            .iter()
+            .filter_map(|r| r["member_did"].as_str().map(String::from))
+            .collect())
+    }
+
+    /// Store the encryption key a member announced to a community
+    pub fn set_community_member_key(
+        &self,
+        community_id: &str,
+        member_did: &str,
+        encryption_key: &str,
+        announced_at: i64,
+    ) -> Result<()> {
+        self.exec(
+            "INSERT OR REPLACE INTO community_member_keys (community_id, member_did, encryption_key, announced_at) VALUES (?, ?, ?, ?)",
+            json!([community_id, member_did, encryption_key, announced_at]),
+        )?;
+        Ok(())
+    }
+
+    /// Get the encryption key a member announced to a community
+    pub fn get_community_member_key(
+        &self,
+        community_id: &str,
+        member_did: &str,
+    ) -> Result<Option<CommunityMemberKeyRecord>> {
+        let rows = self.query(
+            "SELECT community_id, member_did, encryption_key, announced_at FROM community_member_keys WHERE community_id = ? AND member_did = ?",
+            json!([community_id, member_did]),
+        )?;
+        Ok(rows.first().map(|row| CommunityMemberKeyRecord {
+            community_id: row["community_id"].as_str().unwrap_or("").to_string(),
+            member_did: row["member_did"].as_str().unwrap_or("").to_string(),
+            encryption_key: row["encryption_key"].as_str().unwrap_or("").to_string(),
+            announced_at: row["announced_at"].as_i64().unwrap_or(0),
+        }))
+    }
+
     // ── Channel Permission Overrides ─────────────────────────────────────
 
     /// Set a channel permission override
@@ -3809,6 +4531,25 @@ impl Database {
             .collect())
     }
 
+    /// Get a channel permission override by ID
+    pub fn get_channel_permission_override(
+        &self,
+        id: &str,
+    ) -> Result<Option<ChannelPermissionOverrideRecord>> {
+        let rows = self.query(
+            "SELECT id, channel_id, target_type, target_id, allow_bitfield, deny_bitfield FROM channel_permission_overrides WHERE id = ?",
+            json!([id]),
+        )?;
+        Ok(rows.first().map(|row| ChannelPermissionOverrideRecord {
+            id: row["id"].as_str().unwrap_or("").to_string(),
+            channel_id: row["channel_id"].as_str().unwrap_or("").to_string(),
+            target_type: row["target_type"].as_str().unwrap_or("").to_string(),
+            target_id: row["target_id"].as_str().unwrap_or("").to_string(),
+            allow_bitfield: row["allow_bitfield"].as_str().unwrap_or("0").to_string(),
+            deny_bitfield: row["deny_bitfield"].as_str().unwrap_or("0").to_string(),
+        }))
+    }
+
     // ── Member Status ────────────────────────────────────────────────────
 
     /// Set a member's custom status
@@ -4199,7 +4940,7 @@ impl Database {
         created_at: i64,
     ) -> Result<()> {
         self.exec(
-            "INSERT INTO dm_shared_files (id, conversation_id, folder_id, filename, description, file_size, mime_type, storage_chunks_json, uploaded_by, version, download_count, encrypted_metadata, encryption_nonce, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 1, 0, ?, ?, ?)",
+            "INSERT INTO dm_shared_files (id, conversation_id, folder_id, filename, description, file_size, mime_type, storage_chunks_json, uploaded_by, version, download_count, encrypted_metadata, encryption_nonce, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 1, 0, ?10, ?11, ?12, (SELECT (?12 + disappearing_timer) * 1000 FROM conversations WHERE id = ?2))",
             json!([id, conversation_id, folder_id, filename, description, file_size, mime_type, storage_chunks_json, uploaded_by, encrypted_metadata, encryption_nonce, created_at]),
         )?;
         Ok(())
@@ -4363,6 +5104,50 @@ impl Database {
         Ok(())
     }
 
+    // ========================================================================
+    // COMMUNITY OP LOG OPERATIONS
+    // ========================================================================
+
+    /// Store a signed community operation
+    ///
+    /// Returns `false` if it was already stored.
+    pub fn store_community_op(
+        &self,
+        id: &str,
+        community_id: &str,
+        author_did: &str,
+        lamport: u64,
+        op_json: &str,
+        received_at: i64,
+    ) -> Result<bool> {
+        let rows = self.exec(
+            "INSERT OR IGNORE INTO community_ops (id, community_id, author_did, lamport, op_json, received_at) VALUES (?, ?, ?, ?, ?, ?)",
+            json!([id, community_id, author_did, lamport, op_json, received_at]),
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Get every stored operation of a community (as JSON), in Lamport order
+    pub fn get_community_ops(&self, community_id: &str) -> Result<Vec<String>> {
+        let rows = self.query(
+            "SELECT op_json FROM community_ops WHERE community_id = ? ORDER BY lamport, id",
+            json!([community_id]),
+        )?;
+        Ok(rows
+            .iter()
+            .filter_map(|r| r["op_json"].as_str().map(String::from))
+            .collect())
+    }
+
+    /// Count the stored operations of a community
+    pub fn count_community_ops(&self, community_id: &str) -> Result<usize> {
+        let count: Option<i64> = self.query_scalar(
+            "SELECT COUNT(*) FROM community_ops WHERE community_id = ?",
+            json!([community_id]),
+        )?;
+        Ok(count.unwrap_or(0) as usize)
+    }
+
     // ========================================================================
     // TRANSFER SESSION METHODS (P2P file transfer state persistence)
     // ========================================================================
@@ -4878,6 +5663,10 @@ pub struct FriendRecord {
     pub created_at: i64,
     /// Updated timestamp
     pub updated_at: i64,
+    /// Encryption key replaced by the friend's latest rotation (hex)
+    pub previous_encryption_key: Option<String>,
+    /// When the friend last rotated their encryption key
+    pub key_rotated_at: Option<i64>,
 }
 
 /// A conversation record from the database
@@ -4897,6 +5686,8 @@ pub struct ConversationRecord {
     pub last_message_at: Option<i64>,
     /// Unread message count
     pub unread_count: i32,
+    /// Disappearing-message timer in seconds (None = off)
+    pub disappearing_timer: Option<u32>,
 }
 
 /// A message record from the database
@@ -4938,6 +5729,17 @@ pub struct MessageRecord {
     pub thread_id: Option<String>,
     /// Original message ID if forwarded
     pub forwarded_from: Option<String>,
+    /// When this disappearing message is deleted (Unix timestamp ms)
+    pub expires_at: Option<i64>,
+}
+
+/// What a disappearing-message sweep removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExpiredSweep {
+    /// Messages deleted
+    pub messages: usize,
+    /// DM shared files deleted, with their chunks
+    pub files: usize,
 }
 
 /// A reaction record from the database
@@ -5002,6 +5804,38 @@ pub struct GroupKeyRecord {
     pub created_at: i64,
 }
 
+/// One of our X3DH prekeys
+#[derive(Debug, Clone)]
+pub struct PrekeyRecord {
+    /// "signed" or "one_time"
+    pub kind: String,
+    /// Prekey ID (unique per kind)
+    pub key_id: u32,
+    /// X25519 private key bytes
+    pub secret_key: Vec<u8>,
+    /// X25519 public key bytes
+    pub public_key: Vec<u8>,
+    /// Hex Ed25519 signature (signed prekeys only)
+    pub signature: Option<String>,
+    /// When the prekey was generated
+    pub created_at: i64,
+}
+
+/// This install's device keypair (multi-device)
+#[derive(Debug, Clone)]
+pub struct LocalDeviceRecord {
+    /// Device ID derived from the signing public key
+    pub device_id: String,
+    /// Ed25519 private key bytes
+    pub signing_secret: Vec<u8>,
+    /// X25519 private key bytes
+    pub encryption_secret: Vec<u8>,
+    /// Root-signed DeviceCertificate JSON, once linked
+    pub certificate: Option<String>,
+    /// When the keys were generated
+    pub created_at: i64,
+}
+
 /// A group invite record
 #[derive(Debug, Clone)]
 pub struct GroupInviteRecord {
@@ -5449,6 +6283,15 @@ pub struct ChannelKeyRecord {
     pub created_at: i64,
 }
 
+/// The encryption key a member announced to a community
+#[derive(Debug, Clone)]
+pub struct CommunityMemberKeyRecord {
+    pub community_id: String,
+    pub member_did: String,
+    pub encryption_key: String,
+    pub announced_at: i64,
+}
+
 /// A community deleted message record
 #[derive(Debug, Clone)]
 pub struct CommunityDeletedMessageRecord {
@@ -0,0 +1,164 @@
+//! Admin API
+//!
+//! Operator endpoints, mounted only when `RELAY_ADMIN_TOKEN` is set. Every
+//! request must carry `Authorization: Bearer <token>`; the same token also
+//! protects `/metrics`.
+//!
+//! ```text
+//!   GET    /admin/peers          federation peers and relays reached through them
+//!   DELETE /admin/clients/:did   disconnect every session of a DID
+//!   DELETE /admin/offline/:did   drop a DID's offline queue
+//! ```
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Json, Router,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::state::RelayState;
+use crate::sync::auth::extract_bearer_token;
+
+/// The configured admin token.
+///
+/// Only its SHA-256 digest is kept, and presented tokens are compared by
+/// digest so the comparison doesn't leak how much of the token matched.
+#[derive(Clone)]
+pub struct AdminToken(Arc<[u8; 32]>);
+
+impl AdminToken {
+    pub fn new(token: &str) -> Self {
+        Self(Arc::new(Sha256::digest(token.as_bytes()).into()))
+    }
+
+    pub fn matches(&self, presented: &str) -> bool {
+        let digest: [u8; 32] = Sha256::digest(presented.as_bytes()).into();
+        digest == *self.0
+    }
+}
+
+/// Middleware rejecting requests without the admin bearer token.
+pub async fn require_token(
+    State(token): State<AdminToken>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match extract_bearer_token(request.headers()) {
+        Ok(presented) if token.matches(&presented) => next.run(request).await,
+        Ok(_) => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Invalid admin token" })),
+        )
+            .into_response(),
+        Err((status, message)) => (status, Json(json!({ "error": message }))).into_response(),
+    }
+}
+
+/// Build the admin router.
+pub fn router(state: RelayState, token: AdminToken) -> Router {
+    Router::new()
+        .route("/admin/peers", get(list_peers))
+        .route("/admin/clients/:did", delete(evict_client))
+        .route("/admin/offline/:did", delete(purge_offline))
+        .layer(middleware::from_fn_with_state(token, require_token))
+        .with_state(state)
+}
+
+/// GET /admin/peers
+async fn list_peers(State(state): State<RelayState>) -> impl IntoResponse {
+    let Some(ref fed) = state.federation else {
+        return Json(json!({
+            "federation_enabled": false,
+            "peers": [],
+            "remote_relays": [],
+        }));
+    };
+
+    let mut peers: Vec<_> = fed
+        .peer_info
+        .iter()
+        .map(|entry| {
+            json!({
+                "relay_id": entry.relay_id,
+                "relay_url": entry.relay_url,
+                "region": entry.region,
+                "location": entry.location,
+                "public_key": entry.public_key,
+                "connected": fed.peer_senders.contains_key(entry.key()),
+                "online_dids": entry.online_dids.len(),
+            })
+        })
+        .collect();
+    peers.sort_by(|a, b| a["relay_id"].as_str().cmp(&b["relay_id"].as_str()));
+
+    let mut remote_relays: Vec<_> = fed
+        .remote_relays
+        .iter()
+        .map(|entry| {
+            json!({
+                "relay_id": entry.key(),
+                "via": entry.via,
+                "hops": entry.hops,
+                "online_dids": entry.online_dids.len(),
+                "last_seen_secs": entry.updated_at.elapsed().as_secs(),
+            })
+        })
+        .collect();
+    remote_relays.sort_by(|a, b| a["relay_id"].as_str().cmp(&b["relay_id"].as_str()));
+
+    Json(json!({
+        "federation_enabled": true,
+        "relay_id": fed.relay_id,
+        "peers": peers,
+        "remote_relays": remote_relays,
+    }))
+}
+
+/// DELETE /admin/clients/:did
+async fn evict_client(
+    State(state): State<RelayState>,
+    Path(did): Path<String>,
+) -> impl IntoResponse {
+    let sessions = state.evict_client(&did);
+    if sessions == 0 {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "DID is not connected" })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(json!({ "did": did, "sessions_closed": sessions })),
+    )
+}
+
+/// DELETE /admin/offline/:did
+async fn purge_offline(
+    State(state): State<RelayState>,
+    Path(did): Path<String>,
+) -> impl IntoResponse {
+    let purged = state.purge_offline_messages(&did);
+    Json(json!({ "did": did, "messages_purged": purged }))
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_token_matches() {
+        let token = AdminToken::new("s3cret");
+        assert!(token.matches("s3cret"));
+        assert!(!token.matches("s3cre"));
+        assert!(!token.matches(""));
+    }
+}
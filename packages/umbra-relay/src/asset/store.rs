@@ -6,6 +6,7 @@
 
 use dashmap::DashMap;
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -209,6 +210,15 @@ impl AssetStore {
             .sum()
     }
 
+    /// Total storage used by each community that has assets.
+    pub fn storage_by_community(&self) -> BTreeMap<String, u64> {
+        let mut usage = BTreeMap::new();
+        for entry in self.meta.iter() {
+            *usage.entry(entry.value().community_id.clone()).or_insert(0) += entry.value().size;
+        }
+        usage
+    }
+
     /// Store an asset on disk and return its metadata.
     ///
     /// Returns `Ok(AssetMeta)` with the stored asset's metadata.
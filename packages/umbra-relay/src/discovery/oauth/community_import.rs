@@ -449,7 +449,7 @@ pub async fn callback_discord_community_import(
     );
 
     // Verify state
-    let _oauth_state = match store.take_oauth_state(&query.state) {
+    let mut oauth_state = match store.take_oauth_state(&query.state) {
         Some(s) if s.platform == Platform::Discord && s.community_import => {
             tracing::info!("OAuth state validated successfully");
             s
@@ -531,6 +531,7 @@ pub async fn callback_discord_community_import(
 
     // Store result for Tauri/mobile polling
     store.store_community_import_result(&query.state, token.access_token.clone());
+    oauth_state.succeeded();
 
     // Return the access token via postMessage
     community_import_success_html(&token.access_token).into_response()
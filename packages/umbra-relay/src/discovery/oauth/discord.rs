@@ -115,7 +115,7 @@ pub async fn callback(
     Query(query): Query<CallbackQuery>,
 ) -> impl IntoResponse {
     // Verify state and get the associated DID
-    let oauth_state = match store.take_oauth_state(&query.state) {
+    let mut oauth_state = match store.take_oauth_state(&query.state) {
         Some(s) if s.platform == Platform::Discord => s,
         _ => {
             return error_html("Invalid or expired state. Please try again.").into_response();
@@ -204,6 +204,7 @@ pub async fn callback(
     };
 
     store.link_account(&oauth_state.did, account);
+    oauth_state.succeeded();
 
     tracing::info!(
         did = oauth_state.did.as_str(),
@@ -363,7 +363,7 @@ pub async fn callback_discord(
     );
 
     // Verify state
-    let oauth_state = match store.take_oauth_state(&query.state) {
+    let mut oauth_state = match store.take_oauth_state(&query.state) {
         Some(s) if s.platform == Platform::Discord && s.profile_import => {
             tracing::info!("OAuth state validated successfully");
             s
@@ -507,6 +507,7 @@ pub async fn callback_discord(
 
     // Store result for mobile polling
     store.store_profile_result(&query.state, profile.clone());
+    oauth_state.succeeded();
 
     profile_success_html(&profile).into_response()
 }
@@ -672,7 +673,7 @@ pub async fn callback_github(
     Query(query): Query<CallbackQuery>,
 ) -> impl IntoResponse {
     // Verify state
-    let oauth_state = match store.take_oauth_state(&query.state) {
+    let mut oauth_state = match store.take_oauth_state(&query.state) {
         Some(s) if s.platform == Platform::GitHub && s.profile_import => s,
         Some(_) => {
             return profile_error_html("Invalid OAuth state (not a profile import flow)")
@@ -807,6 +808,7 @@ pub async fn callback_github(
 
     // Store result for mobile polling
     store.store_profile_result(&query.state, profile.clone());
+    oauth_state.succeeded();
 
     profile_success_html(&profile).into_response()
 }
@@ -964,7 +966,7 @@ pub async fn callback_steam(
     };
 
     // Verify state
-    let oauth_state = match store.take_oauth_state(&state_nonce) {
+    let mut oauth_state = match store.take_oauth_state(&state_nonce) {
         Some(s) if s.platform == Platform::Steam && s.profile_import => s,
         Some(_) => {
             return profile_error_html("Invalid OAuth state (not a profile import flow)")
@@ -1083,6 +1085,7 @@ pub async fn callback_steam(
 
     // Store result for mobile polling
     store.store_profile_result(&state_nonce, profile.clone());
+    oauth_state.succeeded();
 
     profile_success_html(&profile).into_response()
 }
@@ -1394,7 +1397,7 @@ pub async fn callback_bluesky(
     );
 
     // Verify state
-    let oauth_state = match store.take_oauth_state(&query.state) {
+    let mut oauth_state = match store.take_oauth_state(&query.state) {
         Some(s) if s.platform == Platform::Bluesky && s.profile_import => s,
         Some(_) => {
             return profile_error_html("Invalid state (not a Bluesky profile import flow)")
@@ -1497,6 +1500,7 @@ pub async fn callback_bluesky(
 
     // Store result for mobile polling
     store.store_profile_result(&query.state, profile.clone());
+    oauth_state.succeeded();
 
     profile_success_html(&profile).into_response()
 }
@@ -1636,7 +1640,7 @@ pub async fn callback_xbox(
     );
 
     // Verify state
-    let oauth_state = match store.take_oauth_state(&query.state) {
+    let mut oauth_state = match store.take_oauth_state(&query.state) {
         Some(s) if s.platform == Platform::XboxLive && s.profile_import => s,
         Some(_) => {
             return profile_error_html("Invalid OAuth state (not a profile import flow)")
@@ -1874,6 +1878,7 @@ pub async fn callback_xbox(
 
     // Store result for mobile polling
     store.store_profile_result(&query.state, profile.clone());
+    oauth_state.succeeded();
 
     profile_success_html(&profile).into_response()
 }
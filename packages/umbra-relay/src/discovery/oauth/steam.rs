@@ -134,7 +134,7 @@ pub async fn callback(
     };
 
     // Verify state
-    let oauth_state = match store.take_oauth_state(&state_nonce) {
+    let mut oauth_state = match store.take_oauth_state(&state_nonce) {
         Some(s) if s.platform == Platform::Steam => s,
         _ => {
             return error_html("Invalid or expired state. Please try again.").into_response();
@@ -213,6 +213,7 @@ pub async fn callback(
     };
 
     store.link_account(&oauth_state.did, account);
+    oauth_state.succeeded();
 
     tracing::info!(
         did = oauth_state.did.as_str(),
@@ -122,7 +122,7 @@ pub async fn callback(
     State((store, config)): State<(DiscoveryStore, DiscoveryConfig)>,
     Query(query): Query<CallbackQuery>,
 ) -> impl IntoResponse {
-    let oauth_state = match store.take_oauth_state(&query.state) {
+    let mut oauth_state = match store.take_oauth_state(&query.state) {
         Some(s) if s.platform == Platform::XboxLive => s,
         _ => {
             return error_html("Invalid or expired state. Please try again.").into_response();
@@ -273,6 +273,7 @@ pub async fn callback(
     };
 
     store.link_account(&oauth_state.did, account);
+    oauth_state.succeeded();
 
     tracing::info!(
         did = oauth_state.did.as_str(),
@@ -5,6 +5,7 @@
 //! on disk when `data_dir` is configured.
 
 use std::collections::HashMap;
+use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -18,6 +19,7 @@ use super::types::{
     validate_username_name, DiscoveryEntry, HashedLookup, LinkedAccount, LookupResult, OAuthState,
     Platform, UsernameEntry, MAX_TAG,
 };
+use crate::metrics::CounterVec;
 
 /// On-disk persistence format.
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,6 +45,9 @@ pub struct DiscoveryStore {
     /// Used during OAuth flows to verify callbacks.
     oauth_states: Arc<DashMap<String, OAuthState>>,
 
+    /// OAuth flow outcomes by (platform, flow, outcome), for `/metrics`.
+    oauth_outcomes: Arc<CounterVec>,
+
     /// Lowercase "name#tag" → DID mapping for exact username lookup.
     username_index: Arc<DashMap<String, String>>,
 
@@ -72,6 +77,7 @@ impl DiscoveryStore {
             by_did: Arc::new(DashMap::new()),
             lookup_index: Arc::new(DashMap::new()),
             oauth_states: Arc::new(DashMap::new()),
+            oauth_outcomes: Arc::new(CounterVec::default()),
             username_index: Arc::new(DashMap::new()),
             name_tags: Arc::new(DashMap::new()),
             profile_results: Arc::new(DashMap::new()),
@@ -574,6 +580,7 @@ impl DiscoveryStore {
             profile_import = state.profile_import,
             "Storing OAuth state"
         );
+        self.record_oauth_outcome(&state, "started");
         self.oauth_states.insert(state.nonce.clone(), state);
         tracing::info!(
             pending_states = self.oauth_states.len(),
@@ -583,8 +590,9 @@ impl DiscoveryStore {
 
     /// Retrieve and remove an OAuth state.
     ///
-    /// Returns None if not found or expired.
-    pub fn take_oauth_state(&self, nonce: &str) -> Option<OAuthState> {
+    /// Returns None if not found or expired. The flow is counted as failed
+    /// unless the callback calls [`OAuthCallback::succeeded`].
+    pub fn take_oauth_state(&self, nonce: &str) -> Option<OAuthCallback> {
         tracing::info!(
             nonce = nonce,
             pending_states = self.oauth_states.len(),
@@ -607,10 +615,15 @@ impl DiscoveryStore {
                 // Check if expired (10 minute TTL)
                 if age > super::config::OAUTH_STATE_TTL_SECS {
                     tracing::warn!(nonce = nonce, age_secs = age, "OAuth state expired");
+                    self.record_oauth_outcome(s, "expired");
                     return None;
                 }
 
-                state
+                state.map(|state| OAuthCallback {
+                    state,
+                    outcomes: self.oauth_outcomes.clone(),
+                    succeeded: false,
+                })
             }
             None => {
                 tracing::warn!(
@@ -618,6 +631,8 @@ impl DiscoveryStore {
                     pending_states = self.oauth_states.len(),
                     "OAuth state not found"
                 );
+                self.oauth_outcomes
+                    .inc(&["unknown", "unknown", "invalid_state"]);
                 None
             }
         }
@@ -634,10 +649,22 @@ impl DiscoveryStore {
             .collect();
 
         for nonce in expired {
-            self.oauth_states.remove(&nonce);
+            if let Some((_, state)) = self.oauth_states.remove(&nonce) {
+                self.record_oauth_outcome(&state, "expired");
+            }
         }
     }
 
+    /// OAuth flow outcomes, labelled by platform, flow and outcome.
+    pub fn oauth_outcomes(&self) -> &CounterVec {
+        &self.oauth_outcomes
+    }
+
+    fn record_oauth_outcome(&self, state: &OAuthState, outcome: &str) {
+        self.oauth_outcomes
+            .inc(&[state.platform.as_str(), state.flow(), outcome]);
+    }
+
     // ── Profile Import Results ────────────────────────────────────────────────
 
     /// Store a profile import result for mobile polling.
@@ -688,6 +715,43 @@ impl DiscoveryStore {
     }
 }
 
+/// An OAuth state taken for a callback.
+///
+/// Dereferences to the [`OAuthState`]. The flow is recorded as `failed`
+/// when this is dropped, unless [`succeeded`](Self::succeeded) was called.
+pub struct OAuthCallback {
+    state: OAuthState,
+    outcomes: Arc<CounterVec>,
+    succeeded: bool,
+}
+
+impl OAuthCallback {
+    /// Record that the callback completed the flow.
+    pub fn succeeded(&mut self) {
+        self.succeeded = true;
+    }
+}
+
+impl Deref for OAuthCallback {
+    type Target = OAuthState;
+
+    fn deref(&self) -> &OAuthState {
+        &self.state
+    }
+}
+
+impl Drop for OAuthCallback {
+    fn drop(&mut self) {
+        let outcome = if self.succeeded {
+            "succeeded"
+        } else {
+            "failed"
+        };
+        self.outcomes
+            .inc(&[self.state.platform.as_str(), self.state.flow(), outcome]);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -826,6 +890,31 @@ mod tests {
         assert!(store.take_oauth_state("test-nonce-123").is_none());
     }
 
+    #[test]
+    fn test_oauth_outcomes() {
+        let store = DiscoveryStore::new(test_config());
+        let state = |nonce: &str| OAuthState {
+            did: "did:key:z6MkTest".to_string(),
+            nonce: nonce.to_string(),
+            platform: Platform::GitHub,
+            created_at: Utc::now(),
+            profile_import: false,
+            community_import: false,
+        };
+
+        store.store_oauth_state(state("ok"));
+        store.store_oauth_state(state("bad"));
+        store.take_oauth_state("ok").unwrap().succeeded();
+        drop(store.take_oauth_state("bad"));
+        assert!(store.take_oauth_state("bad").is_none());
+
+        let outcomes = store.oauth_outcomes();
+        assert_eq!(outcomes.get(&["github", "link", "started"]), 2);
+        assert_eq!(outcomes.get(&["github", "link", "succeeded"]), 1);
+        assert_eq!(outcomes.get(&["github", "link", "failed"]), 1);
+        assert_eq!(outcomes.get(&["unknown", "unknown", "invalid_state"]), 1);
+    }
+
     #[test]
     fn test_hash_consistency() {
         let store = DiscoveryStore::new(test_config());
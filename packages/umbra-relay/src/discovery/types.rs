@@ -145,6 +145,19 @@ pub struct OAuthState {
     pub community_import: bool,
 }
 
+impl OAuthState {
+    /// Which kind of flow this state belongs to.
+    pub fn flow(&self) -> &'static str {
+        if self.community_import {
+            "community_import"
+        } else if self.profile_import {
+            "profile_import"
+        } else {
+            "link"
+        }
+    }
+}
+
 /// Imported profile data from OAuth.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportedProfile {
@@ -5,30 +5,80 @@
 //!
 //! ## How it works
 //!
-//! 1. Each relay is configured with a list of peer relay WebSocket URLs.
+//! 1. Each relay is configured with a list of peer relay WebSocket URLs
+//!    and an allowlist of peer identity keys.
 //! 2. On startup, the relay connects to all peers via WebSocket.
-//! 3. Relays exchange `Hello` messages to identify each other.
+//! 3. Relays run a mutual signed handshake (below) to identify each other.
 //! 4. Relays broadcast presence changes (online/offline) to all peers.
 //! 5. When a message targets a DID not connected locally, the relay
 //!    forwards it through the mesh to the peer that has the DID online.
 //! 6. Signaling sessions are replicated across the mesh so they can
 //!    be joined from any relay.
 //!
+//! ## Discovery and routing
+//!
+//! `RELAY_PEERS` only needs to name a few seed relays. Peers periodically
+//! exchange the relays they know (`PeerExchange`), and each relay dials
+//! newly learned, allowlisted relays until it has `max_peers` outbound
+//! connections. The result is a partial mesh:
+//!
+//! ```text
+//!   A ──── B ──── C        A knows C's DIDs from presence flooded by C
+//!                          (via B, hops = 2), so A → C messages go
+//!   Routed { destination: C, ttl, message: Signed(ForwardMessage) }
+//!   A ───────► B ───────► C   (B looks up its own next hop to C)
+//! ```
+//!
+//! Presence is flooded in `Routed` envelopes; every relay remembers which
+//! neighbour it first heard a relay's presence from and uses that
+//! neighbour as the next hop towards it. Envelopes carry a TTL and a
+//! unique ID, and relays drop ones they have already seen.
+//!
+//! Each relay also advertises its load in the exchange, which is what the
+//! `GET /relays` directory reports to clients choosing a relay.
+//!
+//! ## Authentication
+//!
+//! Every relay has an Ed25519 identity (see `relay_identity`). Nothing a
+//! peer sends is acted on until it has proven it holds an allowlisted key:
+//!
+//! ```text
+//!   Relay A                                         Relay B
+//!   ───────                                         ───────
+//!   Challenge { nonce_a }  ───────────────────────►
+//!                          ◄───────────────────────  Challenge { nonce_b }
+//!   Hello { .., public_key, sig(nonce_b ‖ ..) } ───► key allowlisted?
+//!                                                    signature valid?
+//!   key allowlisted?  ◄─── Hello { .., public_key, sig(nonce_a ‖ ..) }
+//!   signature valid?
+//!
+//!   ── both sides authenticated: presence, forwarding, sessions ──
+//! ```
+//!
+//! `ForwardMessage`, `ForwardSealed`, `ForwardOffline` and `InviteSync` are
+//! additionally wrapped in `PeerMessage::Signed` by the relay they originate
+//! from, so they stay attributable to an allowlisted relay wherever they
+//! travel.
+//!
 //! ## Reconnection
 //!
 //! If a peer connection drops, the relay automatically retries with
 //! exponential backoff (up to 60 seconds).
 
 use std::collections::HashSet;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use futures::{SinkExt, StreamExt};
+use rand::RngCore;
 use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
 
-use crate::protocol::PeerMessage;
+use crate::protocol::{
+    KnownRelay, PeerMessage, RelayDirectoryEntry, RelayLoad, PEER_HELLO_PREFIX, PEER_MESSAGE_PREFIX,
+};
+use crate::relay_identity::{verify_signature, RelayIdentity};
 
 /// Information about a connected peer relay.
 #[derive(Debug, Clone)]
@@ -42,13 +92,96 @@ pub struct PeerInfo {
     pub region: String,
     /// Human-readable location
     pub location: String,
+    /// Identity key (hex) the peer authenticated with
+    pub public_key: String,
     /// DIDs currently online at this peer
     pub online_dids: HashSet<String>,
 }
 
+/// A relay reachable only through other relays.
+#[derive(Debug, Clone)]
+pub struct RemoteRelay {
+    /// URL of the direct peer that leads towards it
+    pub via: String,
+    /// Hops away, as of the last presence heard from it
+    pub hops: u8,
+    /// DIDs currently online at this relay
+    pub online_dids: HashSet<String>,
+    /// When presence from this relay was last heard
+    pub updated_at: Instant,
+}
+
+/// Default number of outbound peer connections to keep.
+pub const DEFAULT_MAX_PEERS: usize = 8;
+
+/// How many hops a `Routed` envelope may take.
+const ROUTE_TTL: u8 = 6;
+
+/// How often relays exchange their known peers.
+const PEER_EXCHANGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a multi-hop route lives without fresh presence.
+const REMOTE_ROUTE_EXPIRY: Duration = Duration::from_secs(180);
+
+/// How long routed message IDs are remembered for loop prevention.
+const SEEN_ROUTED_EXPIRY: Duration = Duration::from_secs(300);
+
+/// Consecutive failures after which a learned peer is given up on.
+const MAX_LEARNED_PEER_FAILURES: u32 = 5;
+
 /// Channel sender for pushing messages to a peer relay.
 pub type PeerSender = mpsc::UnboundedSender<PeerMessage>;
 
+/// Handshake state for one peer connection.
+pub struct PeerSession {
+    /// Nonce we challenged the peer with
+    challenge: String,
+    /// The peer's identity key, once its `Hello` has checked out
+    peer_key: Option<String>,
+}
+
+impl PeerSession {
+    /// Start a session with a fresh random challenge.
+    pub fn new() -> Self {
+        let mut nonce = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        Self {
+            challenge: hex::encode(nonce),
+            peer_key: None,
+        }
+    }
+
+    /// The `Challenge` to send when the connection opens.
+    pub fn challenge(&self) -> PeerMessage {
+        PeerMessage::Challenge {
+            nonce: self.challenge.clone(),
+        }
+    }
+
+    /// The peer's verified identity key, if the handshake is complete.
+    #[allow(dead_code)]
+    pub fn peer_key(&self) -> Option<&str> {
+        self.peer_key.as_deref()
+    }
+}
+
+impl Default for PeerSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the connection should do after a peer message was accepted.
+#[derive(Debug)]
+pub enum PeerInbound {
+    /// Send this message back to the peer.
+    Reply(Box<PeerMessage>),
+    /// The peer's `Hello` checked out; it can now be routed to.
+    Authenticated,
+    /// The message was dispatched; nothing to send.
+    Handled,
+}
+
 /// Manages federated connections to peer relays.
 #[derive(Clone)]
 pub struct Federation {
@@ -61,6 +194,12 @@ pub struct Federation {
     /// Our relay's location
     pub location: String,
 
+    /// Our relay's signing identity
+    pub identity: Arc<RelayIdentity>,
+
+    /// Identity keys (hex) of peer relays we accept
+    pub trusted_peer_keys: Arc<HashSet<String>>,
+
     /// Peer relay URL → sender channel (for sending messages to peers)
     pub peer_senders: Arc<DashMap<String, PeerSender>>,
 
@@ -75,6 +214,27 @@ pub struct Federation {
     /// Configured peer relay URLs to connect to
     pub peer_urls: Arc<Vec<String>>,
 
+    /// Maximum outbound peer connections (configured peers always count)
+    pub max_peers: usize,
+
+    /// Relay URL → relays learned through peer exchange
+    pub known_relays: Arc<DashMap<String, KnownRelay>>,
+
+    /// URLs we are running an outbound connection loop for
+    pub dialing: Arc<DashSet<String>>,
+
+    /// Relay ID → relays reachable through other relays
+    pub remote_relays: Arc<DashMap<String, RemoteRelay>>,
+
+    /// DID → ID of the multi-hop relay it is online at
+    pub did_to_relay: Arc<DashMap<String, String>>,
+
+    /// Recently seen `Routed` message IDs
+    seen_routed: Arc<DashMap<String, Instant>>,
+
+    /// Our own load, as advertised to peers
+    local_load: Arc<Mutex<RelayLoad>>,
+
     /// Callback sender: when a forwarded message arrives from a peer,
     /// it's pushed here so the main handler can deliver it locally.
     pub inbound_tx: mpsc::UnboundedSender<PeerMessage>,
@@ -87,13 +247,18 @@ impl Federation {
     /// - `relay_url`: This relay's public WebSocket URL
     /// - `region`: Human-readable region label
     /// - `location`: Human-readable location
+    /// - `identity`: This relay's signing identity
+    /// - `trusted_peer_keys`: Hex identity keys of the peers we accept
     /// - `peer_urls`: List of peer relay WebSocket URLs to connect to
     /// - `inbound_tx`: Channel for forwarding inbound peer messages to the main handler
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         relay_id: String,
         relay_url: String,
         region: String,
         location: String,
+        identity: RelayIdentity,
+        trusted_peer_keys: HashSet<String>,
         peer_urls: Vec<String>,
         inbound_tx: mpsc::UnboundedSender<PeerMessage>,
     ) -> Self {
@@ -102,37 +267,69 @@ impl Federation {
             relay_url,
             region,
             location,
+            identity: Arc::new(identity),
+            trusted_peer_keys: Arc::new(trusted_peer_keys),
             peer_senders: Arc::new(DashMap::new()),
             peer_info: Arc::new(DashMap::new()),
             did_to_peer: Arc::new(DashMap::new()),
             peer_urls: Arc::new(peer_urls),
+            max_peers: DEFAULT_MAX_PEERS,
+            known_relays: Arc::new(DashMap::new()),
+            dialing: Arc::new(DashSet::new()),
+            remote_relays: Arc::new(DashMap::new()),
+            did_to_relay: Arc::new(DashMap::new()),
+            seen_routed: Arc::new(DashMap::new()),
+            local_load: Arc::new(Mutex::new(RelayLoad::default())),
             inbound_tx,
         }
     }
 
+    /// Set how many outbound peer connections to keep (fan-out).
+    pub fn with_max_peers(mut self, max_peers: usize) -> Self {
+        self.max_peers = max_peers;
+        self
+    }
+
     /// Start federation: connect to all configured peers.
     /// Each peer connection runs in its own background task with reconnection.
+    /// A background task exchanges known peers and dials new ones.
     pub fn start(&self) {
         for peer_url in self.peer_urls.iter() {
+            self.dialing.insert(peer_url.clone());
             let fed = self.clone();
             let url = peer_url.clone();
             tokio::spawn(async move {
-                fed.peer_connection_loop(url).await;
+                fed.peer_connection_loop(url, true).await;
             });
         }
 
+        let fed = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PEER_EXCHANGE_INTERVAL);
+            loop {
+                interval.tick().await;
+                fed.broadcast_to_peers(fed.peer_exchange());
+                fed.prune_routes();
+                fed.dial_known_relays();
+            }
+        });
+
         tracing::info!(
             relay_id = self.relay_id.as_str(),
             peer_count = self.peer_urls.len(),
+            max_peers = self.max_peers,
             "Federation started"
         );
     }
 
-    /// Persistent connection loop for a single peer.
-    /// Reconnects with exponential backoff on failure.
-    async fn peer_connection_loop(&self, peer_url: String) {
+    /// Connection loop for a single peer.
+    /// Reconnects with exponential backoff on failure. Configured peers
+    /// (`persistent`) are retried forever; learned peers are dropped after
+    /// `MAX_LEARNED_PEER_FAILURES` failures in a row.
+    async fn peer_connection_loop(&self, peer_url: String, persistent: bool) {
         let mut backoff = Duration::from_secs(1);
         let max_backoff = Duration::from_secs(60);
+        let mut failures = 0;
 
         loop {
             tracing::info!(peer = peer_url.as_str(), "Connecting to peer relay...");
@@ -141,6 +338,7 @@ impl Federation {
                 Ok(()) => {
                     tracing::info!(peer = peer_url.as_str(), "Peer connection closed cleanly");
                     backoff = Duration::from_secs(1); // Reset backoff on clean close
+                    failures = 0;
                 }
                 Err(e) => {
                     tracing::warn!(
@@ -148,12 +346,20 @@ impl Federation {
                         error = %e,
                         "Peer connection failed"
                     );
+                    failures += 1;
                 }
             }
 
             // Clean up presence data for this peer
             self.remove_peer_presence(&peer_url);
 
+            if !persistent && failures >= MAX_LEARNED_PEER_FAILURES {
+                tracing::info!(peer = peer_url.as_str(), "Giving up on learned peer relay");
+                self.known_relays.remove(&peer_url);
+                self.dialing.remove(&peer_url);
+                return;
+            }
+
             tracing::info!(
                 peer = peer_url.as_str(),
                 backoff_secs = backoff.as_secs(),
@@ -182,19 +388,14 @@ impl Federation {
         let (ws_stream, _) = connect_async(&federation_url).await?;
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
-        // Create sender channel for this peer
+        // Create sender channel for this peer. It is only registered in
+        // `peer_senders` once the peer has authenticated.
         let (tx, mut rx) = mpsc::unbounded_channel::<PeerMessage>();
-        self.peer_senders.insert(peer_url.to_string(), tx);
 
-        // Send Hello
-        let hello = PeerMessage::Hello {
-            relay_id: self.relay_id.clone(),
-            relay_url: self.relay_url.clone(),
-            region: self.region.clone(),
-            location: self.location.clone(),
-        };
-        let hello_json = serde_json::to_string(&hello)?;
-        ws_sender.send(WsMessage::Text(hello_json)).await?;
+        // Challenge the peer to prove its identity
+        let mut session = PeerSession::new();
+        let challenge_json = serde_json::to_string(&session.challenge())?;
+        ws_sender.send(WsMessage::Text(challenge_json)).await?;
 
         // Spawn sender task
         let sender_task = tokio::spawn(async move {
@@ -217,7 +418,20 @@ impl Federation {
             match msg_result {
                 Ok(WsMessage::Text(text)) => match serde_json::from_str::<PeerMessage>(&text) {
                     Ok(peer_msg) => {
-                        self.handle_peer_message(peer_url, peer_msg);
+                        match self.receive_from_peer(&mut session, peer_url, peer_msg) {
+                            Ok(PeerInbound::Reply(reply)) => {
+                                let _ = tx.send(*reply);
+                            }
+                            Ok(PeerInbound::Authenticated) => {
+                                self.peer_senders.insert(peer_url.to_string(), tx.clone());
+                                let _ = tx.send(self.peer_exchange());
+                            }
+                            Ok(PeerInbound::Handled) => {}
+                            Err(e) => {
+                                tracing::warn!(peer = peer_url, error = %e, "Rejected peer message");
+                                break;
+                            }
+                        }
                     }
                     Err(e) => {
                         tracing::warn!(
@@ -250,7 +464,187 @@ impl Federation {
         Ok(())
     }
 
-    /// Handle an incoming message from a peer relay.
+    /// Authenticate and dispatch a message received on a peer connection.
+    ///
+    /// Answers `Challenge`s, verifies the peer's `Hello` against our
+    /// challenge and the allowlist, and refuses everything else until that
+    /// has happened. Messages that must be signed are unwrapped from
+    /// `PeerMessage::Signed` here. An error means the connection should be
+    /// dropped.
+    pub fn receive_from_peer(
+        &self,
+        session: &mut PeerSession,
+        peer_url: &str,
+        msg: PeerMessage,
+    ) -> Result<PeerInbound, String> {
+        match msg {
+            PeerMessage::Challenge { nonce } => {
+                Ok(PeerInbound::Reply(Box::new(self.hello(&nonce))))
+            }
+
+            PeerMessage::Hello { .. } => {
+                if session.peer_key.is_some() {
+                    return Err("Duplicate Hello".to_string());
+                }
+                let public_key = self.verify_hello(&session.challenge, &msg)?;
+                session.peer_key = Some(public_key);
+                self.handle_peer_message(peer_url, msg);
+                Ok(PeerInbound::Authenticated)
+            }
+
+            _ if session.peer_key.is_none() => {
+                Err("Peer sent a message before authenticating".to_string())
+            }
+
+            PeerMessage::Routed {
+                message_id,
+                origin_relay_id,
+                destination,
+                ttl,
+                hops,
+                message,
+            } => self.receive_routed(
+                session,
+                peer_url,
+                RoutedEnvelope {
+                    message_id,
+                    origin_relay_id,
+                    destination,
+                    ttl,
+                    hops: hops.saturating_add(1),
+                },
+                *message,
+            ),
+
+            PeerMessage::Signed {
+                public_key,
+                message,
+                signature,
+            } => {
+                let inner = self.open_signed(&public_key, &message, &signature)?;
+                self.handle_peer_message(peer_url, inner);
+                Ok(PeerInbound::Handled)
+            }
+
+            msg if msg.requires_signature() => {
+                Err("Peer sent an unsigned message that requires a signature".to_string())
+            }
+
+            msg => {
+                self.handle_peer_message(peer_url, msg);
+                Ok(PeerInbound::Handled)
+            }
+        }
+    }
+
+    /// Our `Hello`, signed over the peer's challenge nonce.
+    fn hello(&self, nonce: &str) -> PeerMessage {
+        let public_key = self.identity.public_key_hex();
+        let signature = self.identity.sign(&hello_signing_bytes(
+            nonce,
+            &self.relay_id,
+            &self.relay_url,
+            &self.region,
+            &self.location,
+            &public_key,
+        ));
+        PeerMessage::Hello {
+            relay_id: self.relay_id.clone(),
+            relay_url: self.relay_url.clone(),
+            region: self.region.clone(),
+            location: self.location.clone(),
+            public_key,
+            signature,
+        }
+    }
+
+    /// Check a peer's `Hello` against the nonce we sent and the allowlist.
+    /// Returns the peer's identity key.
+    fn verify_hello(&self, nonce: &str, hello: &PeerMessage) -> Result<String, String> {
+        let PeerMessage::Hello {
+            relay_id,
+            relay_url,
+            region,
+            location,
+            public_key,
+            signature,
+        } = hello
+        else {
+            return Err("Expected Hello".to_string());
+        };
+
+        let public_key = public_key.to_lowercase();
+        if !self.trusted_peer_keys.contains(&public_key) {
+            return Err(format!(
+                "Relay key {} is not in the peer allowlist",
+                public_key
+            ));
+        }
+
+        let signed = hello_signing_bytes(nonce, relay_id, relay_url, region, location, &public_key);
+        if !verify_signature(&public_key, &signed, signature) {
+            return Err("Invalid Hello signature".to_string());
+        }
+
+        Ok(public_key)
+    }
+
+    /// Wrap a message that must be signed in `PeerMessage::Signed`.
+    /// Other messages are returned unchanged.
+    fn seal(&self, msg: PeerMessage) -> PeerMessage {
+        if !msg.requires_signature() {
+            return msg;
+        }
+        let message = match serde_json::to_string(&msg) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::error!("Failed to serialize peer message for signing: {}", e);
+                return msg;
+            }
+        };
+        let mut signed = PEER_MESSAGE_PREFIX.as_bytes().to_vec();
+        signed.extend_from_slice(message.as_bytes());
+        PeerMessage::Signed {
+            public_key: self.identity.public_key_hex(),
+            signature: self.identity.sign(&signed),
+            message,
+        }
+    }
+
+    /// Verify a `PeerMessage::Signed` and return the message inside.
+    fn open_signed(
+        &self,
+        public_key: &str,
+        message: &str,
+        signature: &str,
+    ) -> Result<PeerMessage, String> {
+        let public_key = public_key.to_lowercase();
+        if !self.trusted_peer_keys.contains(&public_key) {
+            return Err(format!(
+                "Relay key {} is not in the peer allowlist",
+                public_key
+            ));
+        }
+
+        let mut signed = PEER_MESSAGE_PREFIX.as_bytes().to_vec();
+        signed.extend_from_slice(message.as_bytes());
+        if !verify_signature(&public_key, &signed, signature) {
+            return Err("Invalid peer message signature".to_string());
+        }
+
+        let inner: PeerMessage = serde_json::from_str(message)
+            .map_err(|e| format!("Invalid signed peer message: {}", e))?;
+        // Only the messages that need a signature travel signed; this also
+        // keeps handshake messages and nested wrappers out.
+        if !inner.requires_signature() {
+            return Err("Unexpected message inside Signed".to_string());
+        }
+        Ok(inner)
+    }
+
+    /// Handle an incoming message from an authenticated peer relay.
+    ///
+    /// Callers must have run the message through `receive_from_peer`.
     pub fn handle_peer_message(&self, peer_url: &str, msg: PeerMessage) {
         match &msg {
             PeerMessage::Hello {
@@ -258,6 +652,8 @@ impl Federation {
                 relay_url,
                 region,
                 location,
+                public_key,
+                ..
             } => {
                 tracing::info!(
                     peer = peer_url,
@@ -274,10 +670,14 @@ impl Federation {
                         relay_url: relay_url.clone(),
                         region: region.clone(),
                         location: location.clone(),
+                        public_key: public_key.to_lowercase(),
                         online_dids: HashSet::new(),
                     },
                 );
 
+                // A direct link beats any multi-hop route to the same relay
+                self.forget_remote_relay(relay_id);
+
                 // Send our full presence to the new peer
                 self.send_full_presence_to_peer(peer_url);
             }
@@ -350,9 +750,25 @@ impl Federation {
                 // Keepalive acknowledged — nothing to do
             }
 
+            PeerMessage::PeerExchange { relays } => {
+                let learned = self.learn_relays(relays);
+                if learned > 0 {
+                    tracing::info!(peer = peer_url, learned, "Learned new peer relays");
+                    self.dial_known_relays();
+                }
+            }
+
+            // Handled by `receive_from_peer` before dispatch
+            PeerMessage::Challenge { .. }
+            | PeerMessage::Signed { .. }
+            | PeerMessage::Routed { .. } => {
+                tracing::warn!(peer = peer_url, "Unexpected envelope message from peer");
+            }
+
             // All forwarding messages get passed to the main handler via inbound_tx
             PeerMessage::ForwardSignal { .. }
             | PeerMessage::ForwardMessage { .. }
+            | PeerMessage::ForwardSealed { .. }
             | PeerMessage::ForwardSessionJoin { .. }
             | PeerMessage::SessionSync { .. }
             | PeerMessage::ForwardOffline { .. }
@@ -371,56 +787,415 @@ impl Federation {
         }
     }
 
+    /// Handle a `Routed` envelope that arrived from `peer_url`.
+    ///
+    /// `envelope.hops` already counts the hop from `peer_url` to us.
+    fn receive_routed(
+        &self,
+        session: &mut PeerSession,
+        peer_url: &str,
+        envelope: RoutedEnvelope,
+        message: PeerMessage,
+    ) -> Result<PeerInbound, String> {
+        if envelope.origin_relay_id == self.relay_id || !self.mark_seen(&envelope.message_id) {
+            return Ok(PeerInbound::Handled);
+        }
+
+        match envelope.destination.as_deref() {
+            // Flooded presence
+            None => {
+                if !matches!(
+                    message,
+                    PeerMessage::PresenceSync { .. }
+                        | PeerMessage::PresenceOnline { .. }
+                        | PeerMessage::PresenceOffline { .. }
+                ) {
+                    return Err("Only presence may be flooded".to_string());
+                }
+
+                if envelope.ttl > 1 {
+                    let onward = envelope.onward(message.clone());
+                    for entry in self.peer_senders.iter() {
+                        if entry.key() != peer_url {
+                            let _ = entry.value().send(onward.clone());
+                        }
+                    }
+                }
+
+                if envelope.hops <= 1 {
+                    self.handle_peer_message(peer_url, message);
+                } else {
+                    self.apply_remote_presence(
+                        &envelope.origin_relay_id,
+                        peer_url,
+                        envelope.hops,
+                        message,
+                    );
+                }
+                Ok(PeerInbound::Handled)
+            }
+
+            // Addressed to us: unwrap and verify as if sent directly
+            Some(destination) if destination == self.relay_id => {
+                if !matches!(message, PeerMessage::Signed { .. }) {
+                    return Err("Routed messages must be signed by their origin".to_string());
+                }
+                self.receive_from_peer(session, peer_url, message)
+            }
+
+            // Pass it on towards the destination
+            Some(destination) => {
+                if envelope.ttl <= 1 {
+                    tracing::debug!(
+                        destination,
+                        origin = envelope.origin_relay_id.as_str(),
+                        "Dropping routed message: TTL expired"
+                    );
+                    return Ok(PeerInbound::Handled);
+                }
+                match self.next_hop_to_relay(destination) {
+                    Some(next) if next != peer_url => {
+                        let onward = envelope.onward(message);
+                        if let Some(sender) = self.peer_senders.get(&next) {
+                            let _ = sender.send(onward);
+                        }
+                    }
+                    _ => {
+                        tracing::debug!(destination, "Dropping routed message: no route");
+                    }
+                }
+                Ok(PeerInbound::Handled)
+            }
+        }
+    }
+
+    /// Record presence flooded by a relay we are not directly connected to.
+    fn apply_remote_presence(&self, origin: &str, via: &str, hops: u8, msg: PeerMessage) {
+        // Presence from a direct peer that happened to arrive the long way
+        // round still belongs to that peer.
+        let direct_url = self
+            .peer_info
+            .iter()
+            .find(|entry| entry.relay_id == origin)
+            .map(|entry| entry.key().clone());
+        if let Some(url) = direct_url {
+            self.handle_peer_message(&url, msg);
+            return;
+        }
+
+        let mut relay = self
+            .remote_relays
+            .entry(origin.to_string())
+            .or_insert_with(|| RemoteRelay {
+                via: via.to_string(),
+                hops,
+                online_dids: HashSet::new(),
+                updated_at: Instant::now(),
+            });
+        relay.via = via.to_string();
+        relay.hops = hops;
+        relay.updated_at = Instant::now();
+
+        match msg {
+            PeerMessage::PresenceSync { online_dids, .. } => {
+                for old_did in &relay.online_dids {
+                    self.did_to_relay.remove(old_did);
+                }
+                relay.online_dids = online_dids.into_iter().collect();
+                for did in &relay.online_dids {
+                    self.did_to_relay.insert(did.clone(), origin.to_string());
+                }
+            }
+            PeerMessage::PresenceOnline { did, .. } => {
+                self.did_to_relay.insert(did.clone(), origin.to_string());
+                relay.online_dids.insert(did);
+            }
+            PeerMessage::PresenceOffline { did, .. } => {
+                self.did_to_relay.remove(&did);
+                relay.online_dids.remove(&did);
+            }
+            _ => {}
+        }
+    }
+
+    /// Remember a `Routed` message ID. Returns false if it was already seen.
+    fn mark_seen(&self, message_id: &str) -> bool {
+        self.seen_routed
+            .insert(message_id.to_string(), Instant::now())
+            .is_none()
+    }
+
+    /// The direct peer URL to send to in order to reach `relay_id`.
+    fn next_hop_to_relay(&self, relay_id: &str) -> Option<String> {
+        if let Some(entry) = self.peer_info.iter().find(|e| e.relay_id == relay_id) {
+            return Some(entry.key().clone());
+        }
+        self.remote_relays
+            .get(relay_id)
+            .filter(|relay| relay.updated_at.elapsed() < REMOTE_ROUTE_EXPIRY)
+            .map(|relay| relay.via.clone())
+    }
+
+    /// Send a message to all peers in a `Routed` flood envelope.
+    fn flood(&self, msg: PeerMessage) {
+        let envelope = self.routed(None, msg);
+        if let PeerMessage::Routed { message_id, .. } = &envelope {
+            self.mark_seen(message_id);
+        }
+        self.broadcast_to_peers(envelope);
+    }
+
+    /// Wrap a message in a new `Routed` envelope from us.
+    fn routed(&self, destination: Option<String>, msg: PeerMessage) -> PeerMessage {
+        PeerMessage::Routed {
+            message_id: uuid::Uuid::new_v4().to_string(),
+            origin_relay_id: self.relay_id.clone(),
+            destination,
+            ttl: ROUTE_TTL,
+            hops: 0,
+            message: Box::new(self.seal(msg)),
+        }
+    }
+
+    // ── Peer discovery ──────────────────────────────────────────────────────
+
+    /// Our `PeerExchange`: ourselves and every authenticated direct peer.
+    pub fn peer_exchange(&self) -> PeerMessage {
+        let mut relays = vec![KnownRelay {
+            relay_id: self.relay_id.clone(),
+            relay_url: self.relay_url.clone(),
+            region: self.region.clone(),
+            location: self.location.clone(),
+            public_key: self.identity.public_key_hex(),
+            load: Some(self.local_load()),
+        }];
+        relays.extend(self.peer_info.iter().map(|entry| KnownRelay {
+            relay_id: entry.relay_id.clone(),
+            relay_url: entry.relay_url.clone(),
+            region: entry.region.clone(),
+            location: entry.location.clone(),
+            public_key: entry.public_key.clone(),
+            load: self.known_load(&entry.relay_url),
+        }));
+        PeerMessage::PeerExchange { relays }
+    }
+
+    /// Record our current load, to advertise in the next peer exchange.
+    pub fn set_local_load(&self, load: RelayLoad) {
+        *self.local_load.lock().unwrap_or_else(|e| e.into_inner()) = load;
+    }
+
+    /// Our load as last recorded.
+    pub fn local_load(&self) -> RelayLoad {
+        *self.local_load.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Last load advertised for a relay URL, if any.
+    fn known_load(&self, relay_url: &str) -> Option<RelayLoad> {
+        self.known_relays
+            .get(relay_url)
+            .and_then(|relay| relay.load)
+    }
+
+    /// The relay directory: ourselves first, then every relay we can reach
+    /// (directly connected, or through other relays), by relay ID.
+    pub fn directory(&self) -> Vec<RelayDirectoryEntry> {
+        let mut relays: Vec<RelayDirectoryEntry> = self
+            .peer_info
+            .iter()
+            .map(|entry| RelayDirectoryEntry {
+                relay_id: Some(entry.relay_id.clone()),
+                relay_url: Some(entry.relay_url.clone()),
+                region: entry.region.clone(),
+                location: entry.location.clone(),
+                load: self.known_load(&entry.relay_url),
+            })
+            .collect();
+        relays.extend(
+            self.known_relays
+                .iter()
+                .filter(|entry| {
+                    self.remote_relays.contains_key(&entry.relay_id)
+                        && !self.peer_info.iter().any(|p| p.relay_id == entry.relay_id)
+                })
+                .map(|entry| RelayDirectoryEntry {
+                    relay_id: Some(entry.relay_id.clone()),
+                    relay_url: Some(entry.relay_url.clone()),
+                    region: entry.region.clone(),
+                    location: entry.location.clone(),
+                    load: entry.load,
+                }),
+        );
+        relays.sort_by(|a, b| a.relay_id.cmp(&b.relay_id));
+
+        relays.insert(
+            0,
+            RelayDirectoryEntry {
+                relay_id: Some(self.relay_id.clone()),
+                relay_url: Some(self.relay_url.clone()),
+                region: self.region.clone(),
+                location: self.location.clone(),
+                load: Some(self.local_load()),
+            },
+        );
+        relays
+    }
+
+    /// Remember relays from a peer exchange. Relays outside the allowlist
+    /// are ignored since they could never authenticate. Returns how many
+    /// relays were new.
+    fn learn_relays(&self, relays: &[KnownRelay]) -> usize {
+        let mut learned = 0;
+        for relay in relays {
+            let is_self = relay.relay_id == self.relay_id || relay.relay_url == self.relay_url;
+            if is_self
+                || !self
+                    .trusted_peer_keys
+                    .contains(&relay.public_key.to_lowercase())
+            {
+                continue;
+            }
+            let mut relay = relay.clone();
+            if let Some(previous) = self.known_relays.get(&relay.relay_url) {
+                // Keep the last load we heard if this entry doesn't carry one
+                relay.load = relay.load.or(previous.load);
+            }
+            if self
+                .known_relays
+                .insert(relay.relay_url.clone(), relay)
+                .is_none()
+            {
+                learned += 1;
+            }
+        }
+        learned
+    }
+
+    /// Learned relays we should open a connection to, within the fan-out.
+    ///
+    /// Of two relays that learn about each other, only the one with the
+    /// smaller relay ID dials, so they don't end up with two connections.
+    fn relays_to_dial(&self) -> Vec<String> {
+        let budget = self.max_peers.saturating_sub(self.dialing.len());
+        self.known_relays
+            .iter()
+            .filter(|entry| {
+                let relay = entry.value();
+                self.relay_id < relay.relay_id
+                    && !self.dialing.contains(&relay.relay_url)
+                    && !self.peer_info.iter().any(|p| p.relay_id == relay.relay_id)
+            })
+            .map(|entry| entry.key().clone())
+            .take(budget)
+            .collect()
+    }
+
+    /// Start connection loops for learned relays, within the fan-out.
+    fn dial_known_relays(&self) {
+        // Peer messages are also handled outside a runtime in tests
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        for url in self.relays_to_dial() {
+            tracing::info!(peer = url.as_str(), "Dialing learned peer relay");
+            self.dialing.insert(url.clone());
+            let fed = self.clone();
+            runtime.spawn(async move {
+                fed.peer_connection_loop(url, false).await;
+            });
+        }
+    }
+
+    /// Drop expired multi-hop routes and old loop-prevention entries.
+    fn prune_routes(&self) {
+        let expired: Vec<String> = self
+            .remote_relays
+            .iter()
+            .filter(|entry| entry.updated_at.elapsed() >= REMOTE_ROUTE_EXPIRY)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for relay_id in expired {
+            self.forget_remote_relay(&relay_id);
+        }
+        self.seen_routed
+            .retain(|_, seen_at| seen_at.elapsed() < SEEN_ROUTED_EXPIRY);
+    }
+
+    /// Remove a multi-hop relay and the DIDs routed to it.
+    fn forget_remote_relay(&self, relay_id: &str) {
+        if let Some((_, relay)) = self.remote_relays.remove(relay_id) {
+            for did in &relay.online_dids {
+                self.did_to_relay.remove(did);
+            }
+        }
+    }
+
     // ── Public API for the main handler ─────────────────────────────────────
 
-    /// Look up which peer relay a DID is connected to.
-    /// Returns None if the DID is not known to any peer.
+    /// Look up which directly connected peer relay a DID is connected to.
+    /// Returns None if the DID is not known to any direct peer.
     pub fn find_peer_for_did(&self, did: &str) -> Option<String> {
         self.did_to_peer.get(did).map(|v| v.value().clone())
     }
 
+    /// Look up the relay (possibly several hops away) a DID is online at.
+    pub fn find_relay_for_did(&self, did: &str) -> Option<String> {
+        self.did_to_relay.get(did).map(|v| v.value().clone())
+    }
+
+    /// Whether a DID is online anywhere in the mesh we can route to.
+    pub fn is_reachable(&self, did: &str) -> bool {
+        self.find_peer_for_did(did).is_some()
+            || self
+                .find_relay_for_did(did)
+                .is_some_and(|relay_id| self.next_hop_to_relay(&relay_id).is_some())
+    }
+
     /// Send a message to a specific peer relay.
+    /// Messages that must be signed are signed with our identity.
     pub fn send_to_peer(&self, peer_url: &str, msg: PeerMessage) -> bool {
         if let Some(sender) = self.peer_senders.get(peer_url) {
-            sender.send(msg).is_ok()
+            sender.send(self.seal(msg)).is_ok()
         } else {
             false
         }
     }
 
     /// Broadcast a message to all connected peers.
+    /// Messages that must be signed are signed with our identity.
     pub fn broadcast_to_peers(&self, msg: PeerMessage) {
+        let msg = self.seal(msg);
         for entry in self.peer_senders.iter() {
             let _ = entry.value().send(msg.clone());
         }
     }
 
-    /// Notify all peers that a DID came online locally.
+    /// Notify the mesh that a DID came online locally.
     pub fn broadcast_presence_online(&self, did: &str) {
         let msg = PeerMessage::PresenceOnline {
             relay_id: self.relay_id.clone(),
             did: did.to_string(),
         };
-        self.broadcast_to_peers(msg);
+        self.flood(msg);
     }
 
-    /// Notify all peers that a DID went offline locally.
+    /// Notify the mesh that a DID went offline locally.
     pub fn broadcast_presence_offline(&self, did: &str) {
         let msg = PeerMessage::PresenceOffline {
             relay_id: self.relay_id.clone(),
             did: did.to_string(),
         };
-        self.broadcast_to_peers(msg);
+        self.flood(msg);
     }
 
-    /// Broadcast the full set of locally-connected DIDs to all peers.
+    /// Broadcast the full set of locally-connected DIDs to the mesh.
     /// Called periodically as a heartbeat and on initial connection.
     pub fn broadcast_full_presence(&self, local_dids: Vec<String>) {
         let msg = PeerMessage::PresenceSync {
             relay_id: self.relay_id.clone(),
             online_dids: local_dids,
         };
-        self.broadcast_to_peers(msg);
+        self.flood(msg);
     }
 
     /// Send full presence to a specific peer (e.g. on new connection).
@@ -466,7 +1241,8 @@ impl Federation {
         }
     }
 
-    /// Forward a message to the peer that has the target DID.
+    /// Forward a message to the relay that has the target DID, directly
+    /// or across several hops.
     pub fn forward_message(
         &self,
         from_did: &str,
@@ -474,18 +1250,45 @@ impl Federation {
         payload: &str,
         timestamp: i64,
     ) -> bool {
+        let msg = PeerMessage::ForwardMessage {
+            from_did: from_did.to_string(),
+            to_did: to_did.to_string(),
+            payload: payload.to_string(),
+            timestamp,
+        };
+        self.forward_to_did(to_did, msg)
+    }
+
+    /// Forward a sealed-sender message to the relay that has the target
+    /// DID. That relay checks `access_key` before delivering it.
+    pub fn forward_sealed_message(
+        &self,
+        to_did: &str,
+        access_key: &str,
+        payload: &str,
+        timestamp: i64,
+    ) -> bool {
+        let msg = PeerMessage::ForwardSealed {
+            to_did: to_did.to_string(),
+            access_key: access_key.to_string(),
+            payload: payload.to_string(),
+            timestamp,
+        };
+        self.forward_to_did(to_did, msg)
+    }
+
+    /// Send `msg` towards the relay that has `to_did` online.
+    fn forward_to_did(&self, to_did: &str, msg: PeerMessage) -> bool {
         if let Some(peer_url) = self.find_peer_for_did(to_did) {
-            self.send_to_peer(
-                &peer_url,
-                PeerMessage::ForwardMessage {
-                    from_did: from_did.to_string(),
-                    to_did: to_did.to_string(),
-                    payload: payload.to_string(),
-                    timestamp,
-                },
-            )
-        } else {
-            false
+            return self.send_to_peer(&peer_url, msg);
+        }
+
+        let Some(relay_id) = self.find_relay_for_did(to_did) else {
+            return false;
+        };
+        match self.next_hop_to_relay(&relay_id) {
+            Some(next) => self.send_to_peer(&next, self.routed(Some(relay_id), msg)),
+            None => false,
         }
     }
 
@@ -536,7 +1339,7 @@ impl Federation {
 
     /// Get count of remote DIDs known across the mesh.
     pub fn remote_did_count(&self) -> usize {
-        self.did_to_peer.len()
+        self.did_to_peer.len() + self.did_to_relay.len()
     }
 
     // ── Internal ────────────────────────────────────────────────────────────
@@ -546,8 +1349,19 @@ impl Federation {
         self.remove_peer_presence(peer_url);
     }
 
-    /// Remove all presence data for a disconnected peer.
+    /// Remove all presence data for a disconnected peer, including the
+    /// multi-hop routes that went through it.
     fn remove_peer_presence(&self, peer_url: &str) {
+        let routed_via: Vec<String> = self
+            .remote_relays
+            .iter()
+            .filter(|entry| entry.via == peer_url)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for relay_id in routed_via {
+            self.forget_remote_relay(&relay_id);
+        }
+
         if let Some((_, info)) = self.peer_info.remove(peer_url) {
             for did in &info.online_dids {
                 self.did_to_peer.remove(did);
@@ -562,6 +1376,45 @@ impl Federation {
     }
 }
 
+/// Routing header of a `PeerMessage::Routed` being processed.
+struct RoutedEnvelope {
+    message_id: String,
+    origin_relay_id: String,
+    destination: Option<String>,
+    ttl: u8,
+    hops: u8,
+}
+
+impl RoutedEnvelope {
+    /// The envelope to pass on to the next hop.
+    fn onward(&self, message: PeerMessage) -> PeerMessage {
+        PeerMessage::Routed {
+            message_id: self.message_id.clone(),
+            origin_relay_id: self.origin_relay_id.clone(),
+            destination: self.destination.clone(),
+            ttl: self.ttl - 1,
+            hops: self.hops,
+            message: Box::new(message),
+        }
+    }
+}
+
+/// Bytes a relay signs in its `Hello`.
+fn hello_signing_bytes(
+    nonce: &str,
+    relay_id: &str,
+    relay_url: &str,
+    region: &str,
+    location: &str,
+    public_key: &str,
+) -> Vec<u8> {
+    let fields = (nonce, relay_id, relay_url, region, location, public_key);
+    let mut bytes = PEER_HELLO_PREFIX.as_bytes().to_vec();
+    // Serializing a tuple of strings cannot fail
+    bytes.extend(serde_json::to_vec(&fields).unwrap_or_default());
+    bytes
+}
+
 // ── Tests ────────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -570,19 +1423,130 @@ mod tests {
 
     fn make_federation(
         peer_urls: Vec<String>,
+    ) -> (Federation, mpsc::UnboundedReceiver<PeerMessage>) {
+        make_relay(
+            "test-relay",
+            RelayIdentity::generate(),
+            HashSet::new(),
+            peer_urls,
+        )
+    }
+
+    fn make_relay(
+        relay_id: &str,
+        identity: RelayIdentity,
+        trusted_peer_keys: HashSet<String>,
+        peer_urls: Vec<String>,
     ) -> (Federation, mpsc::UnboundedReceiver<PeerMessage>) {
         let (tx, rx) = mpsc::unbounded_channel();
         let fed = Federation::new(
-            "test-relay".to_string(),
-            "wss://test.example.com/ws".to_string(),
+            relay_id.to_string(),
+            format!("wss://{}.example.com/ws", relay_id),
             "US East".to_string(),
             "Test City".to_string(),
+            identity,
+            trusted_peer_keys,
             peer_urls,
             tx,
         );
         (fed, rx)
     }
 
+    /// Two relays that trust each other.
+    fn make_trusted_pair() -> (
+        (Federation, mpsc::UnboundedReceiver<PeerMessage>),
+        (Federation, mpsc::UnboundedReceiver<PeerMessage>),
+    ) {
+        let (a_id, b_id) = (RelayIdentity::generate(), RelayIdentity::generate());
+        let (a_key, b_key) = (a_id.public_key_hex(), b_id.public_key_hex());
+        (
+            make_relay("relay-a", a_id, HashSet::from([b_key]), vec![]),
+            make_relay("relay-b", b_id, HashSet::from([a_key]), vec![]),
+        )
+    }
+
+    /// Relays that all trust each other.
+    fn make_mesh(relay_ids: &[&str]) -> Vec<(Federation, mpsc::UnboundedReceiver<PeerMessage>)> {
+        let identities: Vec<RelayIdentity> = relay_ids
+            .iter()
+            .map(|_| RelayIdentity::generate())
+            .collect();
+        let keys: HashSet<String> = identities.iter().map(|i| i.public_key_hex()).collect();
+        relay_ids
+            .iter()
+            .zip(identities)
+            .map(|(relay_id, identity)| make_relay(relay_id, identity, keys.clone(), vec![]))
+            .collect()
+    }
+
+    /// Run the handshake from `b`'s side of a connection to `a`, which `b`
+    /// knows as `a_url`.
+    fn authenticate(b: &Federation, a: &Federation, a_url: &str, session: &mut PeerSession) {
+        let PeerMessage::Challenge { nonce } = session.challenge() else {
+            unreachable!()
+        };
+        let hello = a.hello(&nonce);
+        let result = b.receive_from_peer(session, a_url, hello);
+        assert!(matches!(result, Ok(PeerInbound::Authenticated)));
+    }
+
+    /// One direction of an in-memory peer connection.
+    struct Link {
+        from: usize,
+        to: usize,
+        rx: mpsc::UnboundedReceiver<PeerMessage>,
+        session: PeerSession,
+    }
+
+    /// Connect two relays in both directions, as after a handshake.
+    fn connect(relays: &[Federation], links: &mut Vec<Link>, a: usize, b: usize) {
+        for (from, to) in [(a, b), (b, a)] {
+            let mut session = PeerSession::new();
+            authenticate(
+                &relays[to],
+                &relays[from],
+                &relays[from].relay_url,
+                &mut session,
+            );
+            let (tx, rx) = mpsc::unbounded_channel();
+            relays[from]
+                .peer_senders
+                .insert(relays[to].relay_url.clone(), tx);
+            links.push(Link {
+                from,
+                to,
+                rx,
+                session,
+            });
+        }
+    }
+
+    /// Deliver queued messages over the links until the mesh goes quiet.
+    fn pump(relays: &[Federation], links: &mut [Link]) {
+        loop {
+            let mut idle = true;
+            for link in links.iter_mut() {
+                while let Ok(msg) = link.rx.try_recv() {
+                    idle = false;
+                    let from_url = relays[link.from].relay_url.clone();
+                    let _ = relays[link.to].receive_from_peer(&mut link.session, &from_url, msg);
+                }
+            }
+            if idle {
+                break;
+            }
+        }
+    }
+
+    fn forward_message() -> PeerMessage {
+        PeerMessage::ForwardMessage {
+            from_did: "did:key:z6MkAlice".to_string(),
+            to_did: "did:key:z6MkBob".to_string(),
+            payload: "hello".to_string(),
+            timestamp: 100,
+        }
+    }
+
     #[test]
     fn test_federation_creation() {
         let (fed, _rx) = make_federation(vec!["wss://peer1.example.com/ws".to_string()]);
@@ -622,6 +1586,7 @@ mod tests {
                 relay_url: peer_url.to_string(),
                 region: "US".to_string(),
                 location: "NYC".to_string(),
+                public_key: String::new(),
                 online_dids: {
                     let mut s = HashSet::new();
                     s.insert("did:key:z6MkAlice".to_string());
@@ -659,6 +1624,7 @@ mod tests {
                 relay_url: peer_url.to_string(),
                 region: "US".to_string(),
                 location: "NYC".to_string(),
+                public_key: String::new(),
                 online_dids: HashSet::new(),
             },
         );
@@ -694,6 +1660,7 @@ mod tests {
                 relay_url: peer_url.to_string(),
                 region: "US".to_string(),
                 location: "NYC".to_string(),
+                public_key: String::new(),
                 online_dids: HashSet::new(),
             },
         );
@@ -749,4 +1716,366 @@ mod tests {
             _ => panic!("Expected ForwardMessage"),
         }
     }
+
+    #[test]
+    fn test_mutual_handshake() {
+        let ((a, _a_rx), (b, _b_rx)) = make_trusted_pair();
+        let mut a_session = PeerSession::new();
+        let mut b_session = PeerSession::new();
+
+        // Each side answers the other's challenge with a signed Hello
+        let Ok(PeerInbound::Reply(a_hello)) =
+            a.receive_from_peer(&mut a_session, "wss://relay-b/ws", b_session.challenge())
+        else {
+            panic!("Expected a Hello reply");
+        };
+        let Ok(PeerInbound::Reply(b_hello)) =
+            b.receive_from_peer(&mut b_session, "wss://relay-a/ws", a_session.challenge())
+        else {
+            panic!("Expected a Hello reply");
+        };
+
+        assert!(matches!(
+            a.receive_from_peer(&mut a_session, "wss://relay-b/ws", *b_hello),
+            Ok(PeerInbound::Authenticated)
+        ));
+        assert!(matches!(
+            b.receive_from_peer(&mut b_session, "wss://relay-a/ws", *a_hello),
+            Ok(PeerInbound::Authenticated)
+        ));
+        assert_eq!(
+            a_session.peer_key(),
+            Some(b.identity.public_key_hex().as_str())
+        );
+        assert_eq!(
+            b_session.peer_key(),
+            Some(a.identity.public_key_hex().as_str())
+        );
+        assert_eq!(
+            b.peer_info.get("wss://relay-a/ws").unwrap().relay_id,
+            "relay-a"
+        );
+    }
+
+    #[test]
+    fn test_hello_rejected_for_wrong_nonce_or_tampering() {
+        let ((a, _a_rx), (b, _b_rx)) = make_trusted_pair();
+        let mut session = PeerSession::new();
+
+        // Signed over someone else's nonce (a replayed Hello)
+        let replayed = a.hello("some-other-nonce");
+        assert!(b
+            .receive_from_peer(&mut session, "wss://relay-a/ws", replayed)
+            .is_err());
+
+        // Signed over our nonce, but with the URL changed in transit
+        let PeerMessage::Challenge { nonce } = session.challenge() else {
+            unreachable!()
+        };
+        let PeerMessage::Hello {
+            relay_id,
+            region,
+            location,
+            public_key,
+            signature,
+            ..
+        } = a.hello(&nonce)
+        else {
+            unreachable!()
+        };
+        let tampered = PeerMessage::Hello {
+            relay_id,
+            relay_url: "wss://evil.example.com/ws".to_string(),
+            region,
+            location,
+            public_key,
+            signature,
+        };
+        assert!(b
+            .receive_from_peer(&mut session, "wss://relay-a/ws", tampered)
+            .is_err());
+        assert!(session.peer_key().is_none());
+        assert!(b.peer_info.is_empty());
+    }
+
+    #[test]
+    fn test_hello_rejected_for_unknown_key() {
+        let ((_a, _a_rx), (b, _b_rx)) = make_trusted_pair();
+        let (stranger, _rx) = make_federation(vec![]);
+        let mut session = PeerSession::new();
+
+        let PeerMessage::Challenge { nonce } = session.challenge() else {
+            unreachable!()
+        };
+        let result = b.receive_from_peer(&mut session, "wss://test/ws", stranger.hello(&nonce));
+        assert!(result.unwrap_err().contains("allowlist"));
+        assert!(b.peer_info.is_empty());
+    }
+
+    #[test]
+    fn test_messages_before_handshake_rejected() {
+        let ((_a, _a_rx), (b, mut b_rx)) = make_trusted_pair();
+        let mut session = PeerSession::new();
+
+        let presence = PeerMessage::PresenceOnline {
+            relay_id: "relay-a".to_string(),
+            did: "did:key:z6MkAlice".to_string(),
+        };
+        assert!(b
+            .receive_from_peer(&mut session, "wss://relay-a/ws", presence)
+            .is_err());
+        assert_eq!(b.remote_did_count(), 0);
+        assert!(b_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_signed_forward_accepted() {
+        let ((a, _a_rx), (b, mut b_rx)) = make_trusted_pair();
+        let mut session = PeerSession::new();
+        authenticate(&b, &a, "wss://relay-a/ws", &mut session);
+
+        let sealed = a.seal(forward_message());
+        assert!(matches!(sealed, PeerMessage::Signed { .. }));
+        assert!(matches!(
+            b.receive_from_peer(&mut session, "wss://relay-a/ws", sealed),
+            Ok(PeerInbound::Handled)
+        ));
+
+        match b_rx.try_recv().unwrap() {
+            PeerMessage::ForwardMessage { to_did, .. } => assert_eq!(to_did, "did:key:z6MkBob"),
+            _ => panic!("Expected ForwardMessage"),
+        }
+    }
+
+    #[test]
+    fn test_unsigned_or_forged_forward_rejected() {
+        let ((a, _a_rx), (b, mut b_rx)) = make_trusted_pair();
+        let mut session = PeerSession::new();
+        authenticate(&b, &a, "wss://relay-a/ws", &mut session);
+
+        // Unsigned, even from an authenticated peer
+        assert!(b
+            .receive_from_peer(&mut session, "wss://relay-a/ws", forward_message())
+            .is_err());
+
+        // Payload swapped after signing
+        let PeerMessage::Signed {
+            public_key,
+            signature,
+            ..
+        } = a.seal(forward_message())
+        else {
+            unreachable!()
+        };
+        let forged = PeerMessage::Signed {
+            public_key,
+            message: serde_json::to_string(&PeerMessage::ForwardMessage {
+                from_did: "did:key:z6MkMallory".to_string(),
+                to_did: "did:key:z6MkBob".to_string(),
+                payload: "evil".to_string(),
+                timestamp: 100,
+            })
+            .unwrap(),
+            signature,
+        };
+        assert!(b
+            .receive_from_peer(&mut session, "wss://relay-a/ws", forged)
+            .is_err());
+
+        // Signed by a relay outside the allowlist
+        let (stranger, _rx) = make_federation(vec![]);
+        assert!(b
+            .receive_from_peer(
+                &mut session,
+                "wss://relay-a/ws",
+                stranger.seal(forward_message())
+            )
+            .is_err());
+
+        assert!(b_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_peer_exchange_learns_and_dials_within_fan_out() {
+        let mut mesh = make_mesh(&["relay-a", "relay-b", "relay-c", "relay-d"]);
+        let (d, _) = mesh.pop().unwrap();
+        let (c, _) = mesh.pop().unwrap();
+        let (b, _) = mesh.pop().unwrap();
+        let (a, _) = mesh.pop().unwrap();
+        let a = a.with_max_peers(1);
+        let (stranger, _rx) = make_federation(vec![]);
+
+        let PeerMessage::PeerExchange { mut relays } = b.peer_exchange() else {
+            unreachable!()
+        };
+        for other in [&c, &d, &stranger] {
+            let PeerMessage::PeerExchange { relays: theirs } = other.peer_exchange() else {
+                unreachable!()
+            };
+            relays.extend(theirs);
+        }
+        a.handle_peer_message(
+            "wss://relay-b.example.com/ws",
+            PeerMessage::PeerExchange { relays },
+        );
+
+        // Only allowlisted relays are remembered
+        assert_eq!(a.known_relays.len(), 3);
+        assert!(!a.known_relays.contains_key(&stranger.relay_url));
+
+        // Fan-out of one, and the relay with the smaller ID dials
+        assert_eq!(a.relays_to_dial().len(), 1);
+        let c_learned = c.clone();
+        c_learned.learn_relays(&[KnownRelay {
+            relay_id: a.relay_id.clone(),
+            relay_url: a.relay_url.clone(),
+            region: a.region.clone(),
+            location: a.location.clone(),
+            public_key: a.identity.public_key_hex(),
+            load: None,
+        }]);
+        assert!(c_learned.relays_to_dial().is_empty());
+    }
+
+    #[test]
+    fn test_directory_lists_reachable_relays_with_load() {
+        let mesh = make_mesh(&["relay-a", "relay-b", "relay-c"]);
+        let (relays, _inbound): (Vec<_>, Vec<_>) = mesh.into_iter().unzip();
+        let mut links = Vec::new();
+        connect(&relays, &mut links, 0, 1);
+        connect(&relays, &mut links, 1, 2);
+
+        for (relay, online_count) in relays.iter().zip([1, 2, 3]) {
+            relay.set_local_load(RelayLoad {
+                online_count,
+                offline_queue_size: 10 * online_count,
+            });
+        }
+
+        // C is only heard of through B: presence, then the exchanges
+        relays[2].broadcast_presence_online("did:key:z6MkCarol");
+        pump(&relays, &mut links);
+        for relay in relays.iter().rev() {
+            relay.broadcast_to_peers(relay.peer_exchange());
+            pump(&relays, &mut links);
+        }
+
+        let directory = relays[0].directory();
+        let ids: Vec<_> = directory
+            .iter()
+            .map(|entry| entry.relay_id.as_deref().unwrap())
+            .collect();
+        assert_eq!(ids, ["relay-a", "relay-b", "relay-c"]);
+        let loads: Vec<_> = directory
+            .iter()
+            .map(|entry| entry.load.unwrap().online_count)
+            .collect();
+        assert_eq!(loads, [1, 2, 3]);
+        assert_eq!(
+            directory[2].relay_url.as_deref(),
+            Some("wss://relay-c.example.com/ws")
+        );
+
+        // Relays that can no longer be reached drop out
+        relays[0].remove_peer_presence("wss://relay-b.example.com/ws");
+        relays[0].peer_info.remove("wss://relay-b.example.com/ws");
+        assert_eq!(relays[0].directory().len(), 1);
+    }
+
+    #[test]
+    fn test_multi_hop_forwarding() {
+        let mesh = make_mesh(&["relay-a", "relay-b", "relay-c"]);
+        let (relays, mut inbound): (Vec<_>, Vec<_>) = mesh.into_iter().unzip();
+        let mut links = Vec::new();
+        connect(&relays, &mut links, 0, 1);
+        connect(&relays, &mut links, 1, 2);
+
+        // C's presence floods through B to A
+        relays[2].broadcast_presence_online("did:key:z6MkCarol");
+        pump(&relays, &mut links);
+
+        assert_eq!(
+            relays[1].find_peer_for_did("did:key:z6MkCarol").as_deref(),
+            Some("wss://relay-c.example.com/ws")
+        );
+        assert_eq!(relays[0].find_peer_for_did("did:key:z6MkCarol"), None);
+        assert_eq!(
+            relays[0].find_relay_for_did("did:key:z6MkCarol").as_deref(),
+            Some("relay-c")
+        );
+        assert!(relays[0].is_reachable("did:key:z6MkCarol"));
+
+        // A → C goes through B and is delivered (signature intact) at C
+        assert!(relays[0].forward_message("did:key:z6MkAlice", "did:key:z6MkCarol", "hi", 1));
+        pump(&relays, &mut links);
+
+        assert!(inbound[1].try_recv().is_err());
+        match inbound[2].try_recv().unwrap() {
+            PeerMessage::ForwardMessage {
+                from_did, to_did, ..
+            } => {
+                assert_eq!(from_did, "did:key:z6MkAlice");
+                assert_eq!(to_did, "did:key:z6MkCarol");
+            }
+            _ => panic!("Expected ForwardMessage"),
+        }
+
+        // Sealed messages take the same route, still carrying no sender
+        let access_key = "ab".repeat(32);
+        assert!(relays[0].forward_sealed_message("did:key:z6MkCarol", &access_key, "sealed", 2));
+        pump(&relays, &mut links);
+
+        match inbound[2].try_recv().unwrap() {
+            PeerMessage::ForwardSealed {
+                to_did,
+                access_key: key,
+                ..
+            } => {
+                assert_eq!(to_did, "did:key:z6MkCarol");
+                assert_eq!(key, access_key);
+            }
+            _ => panic!("Expected ForwardSealed"),
+        }
+
+        // Losing the link to B drops the route through it
+        relays[0].remove_peer_presence("wss://relay-b.example.com/ws");
+        assert!(!relays[0].is_reachable("did:key:z6MkCarol"));
+    }
+
+    #[test]
+    fn test_routed_loop_prevention_and_ttl() {
+        let mesh = make_mesh(&["relay-a", "relay-b", "relay-c"]);
+        let (relays, mut inbound): (Vec<_>, Vec<_>) = mesh.into_iter().unzip();
+        let mut links = Vec::new();
+        connect(&relays, &mut links, 0, 1);
+        connect(&relays, &mut links, 1, 2);
+        connect(&relays, &mut links, 2, 0);
+
+        // The flood terminates in a cycle, and direct presence wins
+        relays[2].broadcast_full_presence(vec!["did:key:z6MkCarol".to_string()]);
+        pump(&relays, &mut links);
+        assert_eq!(
+            relays[0].find_peer_for_did("did:key:z6MkCarol").as_deref(),
+            Some("wss://relay-c.example.com/ws")
+        );
+        assert!(relays[0].remote_relays.is_empty());
+
+        // An envelope with no hops left is not passed on
+        let PeerMessage::Routed { message, .. } =
+            relays[0].routed(Some("relay-c".to_string()), forward_message())
+        else {
+            unreachable!()
+        };
+        let expired = PeerMessage::Routed {
+            message_id: "expired".to_string(),
+            origin_relay_id: "relay-a".to_string(),
+            destination: Some("relay-c".to_string()),
+            ttl: 1,
+            hops: 0,
+            message,
+        };
+        relays[0].send_to_peer("wss://relay-b.example.com/ws", expired);
+        pump(&relays, &mut links);
+        assert!(inbound[2].try_recv().is_err());
+    }
 }
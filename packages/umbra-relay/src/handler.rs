@@ -3,6 +3,8 @@
 //! Manages individual WebSocket connections: parsing client messages,
 //! routing them through the relay state, and sending responses.
 
+use std::net::IpAddr;
+
 use axum::extract::ws::{Message, WebSocket};
 use chrono::Utc;
 use futures::{SinkExt, StreamExt};
@@ -10,22 +12,39 @@ use tokio::sync::mpsc;
 
 use uuid::Uuid;
 
-use crate::protocol::{ClientMessage, PeerMessage, ServerMessage};
+use crate::federation::{PeerInbound, PeerSession};
+use crate::protocol::{
+    CallRoomMode, ClientMessage, PeerMessage, PublishedInvite, ServerMessage, AUTH_CHALLENGE_PREFIX,
+};
+use crate::push::PushRegistration;
+use crate::rate_limit;
+use crate::sfu::{SfuCommand, SfuEvent};
 use crate::state::{RelayState, RouteResult};
+use crate::sync::auth::{ed25519_public_key_from_did, verify_did_signature};
 
 /// Handle a single WebSocket connection.
 ///
 /// This function runs for the lifetime of the connection:
-/// 1. Waits for a `Register` message to associate the connection with a DID
+/// 1. Waits for a `Register` message, challenges the client to sign a nonce
+///    with the key in its `did:key`, and verifies the `Authenticate` reply
 /// 2. Spawns a sender task to forward outbound messages
 /// 3. Processes incoming messages until the connection closes
-pub async fn handle_websocket(socket: WebSocket, state: RelayState) {
+///
+/// The connection is only associated with a DID once authentication
+/// succeeds, so unauthenticated clients can't send as, receive for, or
+/// drain the offline queue of someone else's DID.
+///
+/// `client_ip` limits sealed sends made before authenticating.
+pub async fn handle_websocket(socket: WebSocket, state: RelayState, client_ip: Option<IpAddr>) {
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
     // Create the outbound channel for this client
     let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
 
-    // ── Step 1: Wait for Registration ─────────────────────────────────────
+    // ── Step 1: Wait for Registration + Authentication ────────────────────
+
+    // (did, nonce) of the outstanding challenge, if any
+    let mut pending_challenge: Option<(String, String)> = None;
 
     let client_did = loop {
         match ws_receiver.next().await {
@@ -33,17 +52,68 @@ pub async fn handle_websocket(socket: WebSocket, state: RelayState) {
                 match serde_json::from_str::<ClientMessage>(&text) {
                     Ok(ClientMessage::Register { did }) => {
                         if did.is_empty() || !did.starts_with("did:") {
-                            let err = ServerMessage::Error {
-                                message: "Invalid DID format".to_string(),
-                            };
+                            let err = ServerMessage::error("Invalid DID format");
+                            let _ = ws_sender
+                                .send(Message::Text(serde_json::to_string(&err).unwrap()))
+                                .await;
+                            continue;
+                        }
+
+                        if let Err(e) = ed25519_public_key_from_did(&did) {
+                            let err = ServerMessage::error(format!("Unsupported DID: {}", e));
+                            let _ = ws_sender
+                                .send(Message::Text(serde_json::to_string(&err).unwrap()))
+                                .await;
+                            continue;
+                        }
+
+                        // Challenge the client to prove it holds the DID's key
+                        let nonce = Uuid::new_v4().simple().to_string();
+                        let challenge = ServerMessage::AuthChallenge {
+                            nonce: nonce.clone(),
+                        };
+                        if ws_sender
+                            .send(Message::Text(serde_json::to_string(&challenge).unwrap()))
+                            .await
+                            .is_err()
+                        {
+                            return; // Connection closed
+                        }
+
+                        pending_challenge = Some((did, nonce));
+                    }
+                    Ok(ClientMessage::Authenticate { signature }) => {
+                        // Each challenge can only be answered once
+                        let Some((did, nonce)) = pending_challenge.take() else {
+                            let err = ServerMessage::error("Must register before authenticating");
                             let _ = ws_sender
                                 .send(Message::Text(serde_json::to_string(&err).unwrap()))
                                 .await;
                             continue;
+                        };
+
+                        let message = format!("{}{}", AUTH_CHALLENGE_PREFIX, nonce);
+                        match verify_did_signature(&did, &message, &signature) {
+                            Ok(true) => {}
+                            Ok(false) | Err(_) => {
+                                tracing::warn!(did = did.as_str(), "Relay authentication failed");
+                                let err = ServerMessage::error("Authentication failed");
+                                let _ = ws_sender
+                                    .send(Message::Text(serde_json::to_string(&err).unwrap()))
+                                    .await;
+                                continue;
+                            }
                         }
 
                         // Send registration confirmation
-                        let ack = ServerMessage::Registered { did: did.clone() };
+                        let ack = ServerMessage::Registered {
+                            did: did.clone(),
+                            ice_servers: state
+                                .ice
+                                .as_ref()
+                                .map(|ice| ice.ice_servers(&did))
+                                .unwrap_or_default(),
+                        };
                         if ws_sender
                             .send(Message::Text(serde_json::to_string(&ack).unwrap()))
                             .await
@@ -60,19 +130,32 @@ pub async fn handle_websocket(socket: WebSocket, state: RelayState) {
                             .send(Message::Text(serde_json::to_string(&pong).unwrap()))
                             .await;
                     }
+                    // Sealed sends don't need (and shouldn't reveal) a DID
+                    Ok(ClientMessage::SendSealed {
+                        to_did,
+                        access_key,
+                        payload,
+                    }) => {
+                        let reply =
+                            refuse_unauthenticated_sealed(&state, client_ip, &to_did, &access_key)
+                                .unwrap_or_else(|| {
+                                    handle_send_sealed(&state, &to_did, &access_key, &payload)
+                                });
+                        let _ = ws_sender
+                            .send(Message::Text(serde_json::to_string(&reply).unwrap()))
+                            .await;
+                    }
                     Ok(_) => {
-                        let err = ServerMessage::Error {
-                            message: "Must register before sending other messages".to_string(),
-                        };
+                        let err = ServerMessage::error(
+                            "Must register and authenticate before sending other messages",
+                        );
                         let _ = ws_sender
                             .send(Message::Text(serde_json::to_string(&err).unwrap()))
                             .await;
                     }
                     Err(e) => {
                         tracing::warn!("Failed to parse client message: {}", e);
-                        let err = ServerMessage::Error {
-                            message: format!("Invalid message format: {}", e),
-                        };
+                        let err = ServerMessage::error(format!("Invalid message format: {}", e));
                         let _ = ws_sender
                             .send(Message::Text(serde_json::to_string(&err).unwrap()))
                             .await;
@@ -97,12 +180,12 @@ pub async fn handle_websocket(socket: WebSocket, state: RelayState) {
 
     // ── Step 3: Spawn Sender Task ─────────────────────────────────────────
 
-    let sender_task = tokio::spawn(async move {
+    let mut sender_task = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
             match serde_json::to_string(&msg) {
                 Ok(json) => {
                     if ws_sender.send(Message::Text(json)).await.is_err() {
-                        break; // Connection closed
+                        return; // Connection closed
                     }
                 }
                 Err(e) => {
@@ -110,14 +193,42 @@ pub async fn handle_websocket(socket: WebSocket, state: RelayState) {
                 }
             }
         }
+        // The relay dropped this session (e.g. evicted by an admin)
+        let _ = ws_sender.send(Message::Close(None)).await;
     });
 
     // ── Step 4: Process Messages ──────────────────────────────────────────
 
-    while let Some(msg_result) = ws_receiver.next().await {
+    loop {
+        let msg_result = tokio::select! {
+            msg = ws_receiver.next() => match msg {
+                Some(msg_result) => msg_result,
+                None => break,
+            },
+            _ = &mut sender_task => {
+                tracing::info!(did = client_did.as_str(), session_id = session_id.as_str(), "Session closed by relay");
+                break;
+            }
+        };
+
         match msg_result {
             Ok(Message::Text(text)) => match serde_json::from_str::<ClientMessage>(&text) {
                 Ok(client_msg) => {
+                    if let Some(class) = rate_limit::message_class(&client_msg) {
+                        if let Err(retry_after) = state.rate_limiter.check(class, &client_did) {
+                            tracing::debug!(
+                                did = client_did.as_str(),
+                                class = class,
+                                "Rate limited"
+                            );
+                            state.send_to_session(
+                                &client_did,
+                                &session_id,
+                                ServerMessage::rate_limited(class, retry_after),
+                            );
+                            continue;
+                        }
+                    }
                     handle_client_message(&state, &client_did, &session_id, client_msg).await;
                 }
                 Err(e) => {
@@ -128,9 +239,7 @@ pub async fn handle_websocket(socket: WebSocket, state: RelayState) {
                     );
                     state.send_to_client(
                         &client_did,
-                        ServerMessage::Error {
-                            message: format!("Invalid message format: {}", e),
-                        },
+                        ServerMessage::error(format!("Invalid message format: {}", e)),
                     );
                 }
             },
@@ -179,14 +288,9 @@ pub async fn handle_websocket(socket: WebSocket, state: RelayState) {
 /// Handle a parsed client message.
 async fn handle_client_message(state: &RelayState, from_did: &str, session_id: &str, msg: ClientMessage) {
     match msg {
-        ClientMessage::Register { .. } => {
+        ClientMessage::Register { .. } | ClientMessage::Authenticate { .. } => {
             // Already registered — ignore duplicate registrations
-            state.send_to_client(
-                from_did,
-                ServerMessage::Error {
-                    message: "Already registered".to_string(),
-                },
-            );
+            state.send_to_client(from_did, ServerMessage::error("Already registered"));
         }
 
         ClientMessage::Signal { to_did, payload } => {
@@ -197,6 +301,63 @@ async fn handle_client_message(state: &RelayState, from_did: &str, session_id: &
             handle_send(state, from_did, &to_did, &payload);
         }
 
+        ClientMessage::SendSealed {
+            to_did,
+            access_key,
+            payload,
+        } => {
+            // The DID was charged on receipt; the key is charged here too
+            let reply = refuse_sealed_access(state, &to_did, &access_key)
+                .unwrap_or_else(|| handle_send_sealed(state, &to_did, &access_key, &payload));
+            state.send_to_session(from_did, session_id, reply);
+        }
+
+        ClientMessage::RegisterSealedAccess { access_hashes } => {
+            let reply = match state.register_sealed_access(from_did, &access_hashes) {
+                Ok(count) => {
+                    tracing::debug!(did = from_did, count = count, "Registered sealed access");
+                    ServerMessage::SealedAccessRegistered { count }
+                }
+                Err(e) => ServerMessage::error(e),
+            };
+            state.send_to_session(from_did, session_id, reply);
+        }
+
+        ClientMessage::RegisterPush {
+            kind,
+            endpoint,
+            quiet_hours,
+        } => {
+            let registration = PushRegistration {
+                kind,
+                endpoint,
+                quiet_hours,
+            };
+            let reply = match &state.push {
+                Some(push) => match push.register(from_did, registration) {
+                    Ok(()) => ServerMessage::Ack {
+                        id: "push_registered".to_string(),
+                    },
+                    Err(e) => ServerMessage::error(e),
+                },
+                None => ServerMessage::error("Push notifications are not enabled on this relay"),
+            };
+            state.send_to_session(from_did, session_id, reply);
+        }
+
+        ClientMessage::UnregisterPush => {
+            let reply = match &state.push {
+                Some(push) => {
+                    push.unregister(from_did);
+                    ServerMessage::Ack {
+                        id: "push_unregistered".to_string(),
+                    }
+                }
+                None => ServerMessage::error("Push notifications are not enabled on this relay"),
+            };
+            state.send_to_session(from_did, session_id, reply);
+        }
+
         ClientMessage::CreateSession { offer_payload } => {
             handle_create_session(state, from_did, &offer_payload);
         }
@@ -212,6 +373,10 @@ async fn handle_client_message(state: &RelayState, from_did: &str, session_id: &
             handle_fetch_offline(state, from_did);
         }
 
+        ClientMessage::AckOffline { ids } => {
+            handle_ack_offline(state, from_did, &ids);
+        }
+
         ClientMessage::Ping => {
             state.send_to_client(from_did, ServerMessage::Pong);
         }
@@ -226,20 +391,24 @@ async fn handle_client_message(state: &RelayState, from_did: &str, session_id: &
             max_uses,
             expires_at,
             invite_payload,
+            signature,
         } => {
-            handle_publish_invite(
-                state,
-                from_did,
-                &code,
-                &community_id,
-                &community_name,
-                community_description.as_deref(),
-                community_icon.as_deref(),
+            let invite = PublishedInvite {
+                code,
+                publisher_did: from_did.to_string(),
+                community_id,
+                community_name,
+                community_description,
+                community_icon,
                 member_count,
                 max_uses,
+                use_count: 0,
                 expires_at,
-                &invite_payload,
-            );
+                invite_payload,
+                published_at: Utc::now(),
+                signature,
+            };
+            handle_publish_invite(state, invite);
         }
 
         ClientMessage::RevokeInvite { code } => {
@@ -250,8 +419,8 @@ async fn handle_client_message(state: &RelayState, from_did: &str, session_id: &
             handle_resolve_invite(state, from_did, &code);
         }
 
-        ClientMessage::CreateCallRoom { group_id } => {
-            handle_create_call_room(state, from_did, &group_id);
+        ClientMessage::CreateCallRoom { group_id, mode } => {
+            handle_create_call_room(state, from_did, &group_id, mode);
         }
 
         ClientMessage::JoinCallRoom { room_id } => {
@@ -270,6 +439,33 @@ async fn handle_client_message(state: &RelayState, from_did: &str, session_id: &
             handle_call_signal(state, from_did, &room_id, &to_did, &payload);
         }
 
+        ClientMessage::SfuOffer { room_id, sdp } => {
+            let command = SfuCommand::Offer {
+                room_id: room_id.clone(),
+                did: from_did.to_string(),
+                sdp,
+            };
+            handle_sfu_signal(state, from_did, &room_id, command);
+        }
+
+        ClientMessage::SfuAnswer { room_id, sdp } => {
+            let command = SfuCommand::Answer {
+                room_id: room_id.clone(),
+                did: from_did.to_string(),
+                sdp,
+            };
+            handle_sfu_signal(state, from_did, &room_id, command);
+        }
+
+        ClientMessage::SfuCandidate { room_id, candidate } => {
+            let command = SfuCommand::Candidate {
+                room_id: room_id.clone(),
+                did: from_did.to_string(),
+                candidate,
+            };
+            handle_sfu_signal(state, from_did, &room_id, command);
+        }
+
         ClientMessage::SyncPush {
             section,
             version,
@@ -297,7 +493,15 @@ fn handle_signal(state: &RelayState, from_did: &str, to_did: &str, payload: &str
     }
 
     // Peer is unreachable — queue for later delivery
-    state.queue_offline_message(to_did, from_did, payload, Utc::now().timestamp());
+    if !state.queue_offline_message(to_did, from_did, payload, Utc::now().timestamp()) {
+        state.send_to_client(
+            from_did,
+            ServerMessage::QueueFull {
+                to_did: to_did.to_string(),
+            },
+        );
+        return;
+    }
 
     state.send_to_client(
         from_did,
@@ -339,7 +543,16 @@ fn handle_send(state: &RelayState, from_did: &str, to_did: &str, payload: &str)
         }
         RouteResult::Unreachable => {
             // Peer is unreachable everywhere — queue for later delivery
-            state.queue_offline_message(to_did, from_did, payload, timestamp);
+            if !state.queue_offline_message(to_did, from_did, payload, timestamp) {
+                // Nothing was stored, so tell the sender instead of acking
+                state.send_to_client(
+                    from_did,
+                    ServerMessage::QueueFull {
+                        to_did: to_did.to_string(),
+                    },
+                );
+                return;
+            }
 
             tracing::debug!(
                 from = from_did,
@@ -358,6 +571,82 @@ fn handle_send(state: &RelayState, from_did: &str, to_did: &str, payload: &str)
     );
 }
 
+/// Rate limit a sealed send from a socket that hasn't authenticated,
+/// returning the reply if it is refused.
+///
+/// The sending IP is charged first, then the access key (see
+/// [`refuse_sealed_access`]).
+fn refuse_unauthenticated_sealed(
+    state: &RelayState,
+    client_ip: Option<IpAddr>,
+    to_did: &str,
+    access_key: &str,
+) -> Option<ServerMessage> {
+    if let Some(ip) = client_ip {
+        if let Err(retry_after) = state.rate_limiter.check("sealed_send_ip", &ip.to_string()) {
+            return Some(ServerMessage::rate_limited("sealed_send", retry_after));
+        }
+    }
+    refuse_sealed_access(state, to_did, access_key)
+}
+
+/// Check and rate limit the access key of a sealed send, returning the
+/// reply if it is refused.
+///
+/// The key is only charged once it is well formed and, if the recipient
+/// registered here, one of theirs, so made-up keys can't each get a fresh
+/// bucket. Whoever sent it (IP or DID) has been charged already.
+fn refuse_sealed_access(
+    state: &RelayState,
+    to_did: &str,
+    access_key: &str,
+) -> Option<ServerMessage> {
+    if !crate::sealed_sender::is_access_key(access_key)
+        || (state.has_sealed_registration(to_did) && !state.check_sealed_access(to_did, access_key))
+    {
+        tracing::debug!(to = to_did, "Sealed message refused");
+        return Some(ServerMessage::sealed_access_denied(to_did));
+    }
+
+    match state.rate_limiter.check("sealed_send", access_key) {
+        Ok(()) => None,
+        Err(retry_after) => Some(ServerMessage::rate_limited("sealed_send", retry_after)),
+    }
+}
+
+/// Send a sealed-sender message and return the reply for the sender.
+/// Like `handle_send`, but nothing about the sender is stored: offline
+/// copies are queued with an empty `from_did`. A message forwarded to a
+/// peer isn't queued here as well, since the peer queues it if the
+/// recipient has gone offline there.
+fn handle_send_sealed(
+    state: &RelayState,
+    to_did: &str,
+    access_key: &str,
+    payload: &str,
+) -> ServerMessage {
+    let timestamp = Utc::now().timestamp();
+
+    match state.route_sealed_message(to_did, access_key, payload, timestamp) {
+        None => {
+            tracing::debug!(to = to_did, "Sealed message refused");
+            return ServerMessage::sealed_access_denied(to_did);
+        }
+        Some(RouteResult::DeliveredLocally | RouteResult::ForwardedToPeer) => {}
+        Some(RouteResult::Unreachable) => {
+            if !state.queue_offline_message(to_did, "", payload, timestamp) {
+                return ServerMessage::QueueFull {
+                    to_did: to_did.to_string(),
+                };
+            }
+        }
+    }
+
+    ServerMessage::Ack {
+        id: format!("sealed_{}_{}", to_did, timestamp),
+    }
+}
+
 /// Create a signaling session for single-scan friend adding.
 fn handle_create_session(state: &RelayState, creator_did: &str, offer_payload: &str) {
     let session_id = state.create_session(creator_did, offer_payload);
@@ -379,9 +668,7 @@ fn handle_join_session(
         None => {
             state.send_to_client(
                 joiner_did,
-                ServerMessage::Error {
-                    message: format!("Session '{}' not found or expired", session_id),
-                },
+                ServerMessage::error(format!("Session '{}' not found or expired", session_id)),
             );
             return;
         }
@@ -418,12 +705,21 @@ fn handle_join_session(
 
         if !fed_sent {
             // Creator unreachable — queue the answer
-            state.queue_offline_message(
+            let queued = state.queue_offline_message(
                 &session.creator_did,
                 joiner_did,
                 answer_payload,
                 Utc::now().timestamp(),
             );
+
+            if !queued {
+                state.send_to_client(
+                    joiner_did,
+                    ServerMessage::QueueFull {
+                        to_did: session.creator_did.clone(),
+                    },
+                );
+            }
         }
     }
 
@@ -432,8 +728,9 @@ fn handle_join_session(
 }
 
 /// Deliver all queued offline messages.
+/// They stay queued until the client acknowledges them with `AckOffline`.
 fn handle_fetch_offline(state: &RelayState, did: &str) {
-    let messages = state.drain_offline_messages(did);
+    let messages = state.pending_offline_messages(did);
 
     tracing::debug!(
         did = did,
@@ -444,42 +741,35 @@ fn handle_fetch_offline(state: &RelayState, did: &str) {
     state.send_to_client(did, ServerMessage::OfflineMessages { messages });
 }
 
+/// Delete offline messages the client has acknowledged.
+fn handle_ack_offline(state: &RelayState, did: &str, ids: &[String]) {
+    let removed = state.ack_offline_messages(did, ids);
+
+    tracing::debug!(
+        did = did,
+        acked = ids.len(),
+        removed = removed,
+        "Acknowledged offline messages"
+    );
+}
+
 // ── Invite Handlers ──────────────────────────────────────────────────────────
 
 /// Publish a community invite to the relay.
-#[allow(clippy::too_many_arguments)]
-fn handle_publish_invite(
-    state: &RelayState,
-    publisher_did: &str,
-    code: &str,
-    community_id: &str,
-    community_name: &str,
-    community_description: Option<&str>,
-    community_icon: Option<&str>,
-    member_count: u32,
-    max_uses: Option<i32>,
-    expires_at: Option<i64>,
-    invite_payload: &str,
-) {
-    state.publish_invite(
-        code,
-        publisher_did,
-        community_id,
-        community_name,
-        community_description,
-        community_icon,
-        member_count,
-        max_uses,
-        expires_at,
-        invite_payload,
-    );
+/// The invite must be signed by the publisher's DID key.
+fn handle_publish_invite(state: &RelayState, invite: PublishedInvite) {
+    let publisher_did = invite.publisher_did.clone();
+    let code = invite.code.clone();
 
-    state.send_to_client(
-        publisher_did,
+    let reply = if state.publish_invite(invite) {
         ServerMessage::Ack {
             id: format!("invite_published_{}", code),
-        },
-    );
+        }
+    } else {
+        ServerMessage::invalid_invite_signature(&code)
+    };
+
+    state.send_to_client(&publisher_did, reply);
 }
 
 /// Revoke a published invite.
@@ -544,14 +834,28 @@ fn handle_resolve_invite(state: &RelayState, requester_did: &str, code: &str) {
 // ── Call Room Handlers ────────────────────────────────────────────────────────
 
 /// Create a new call room for group calling.
-fn handle_create_call_room(state: &RelayState, creator_did: &str, group_id: &str) {
-    let room_id = state.create_call_room(group_id, creator_did);
+fn handle_create_call_room(
+    state: &RelayState,
+    creator_did: &str,
+    group_id: &str,
+    mode: CallRoomMode,
+) {
+    if mode == CallRoomMode::Sfu && state.sfu.is_none() {
+        state.send_to_client(
+            creator_did,
+            ServerMessage::error("SFU call rooms are not enabled on this relay"),
+        );
+        return;
+    }
+
+    let room_id = state.create_call_room(group_id, creator_did, mode);
 
     state.send_to_client(
         creator_did,
         ServerMessage::CallRoomCreated {
             room_id,
             group_id: group_id.to_string(),
+            mode,
         },
     );
 }
@@ -582,9 +886,7 @@ fn handle_join_call_room(state: &RelayState, joiner_did: &str, room_id: &str) {
         None => {
             state.send_to_client(
                 joiner_did,
-                ServerMessage::Error {
-                    message: format!("Call room '{}' not found or full", room_id),
-                },
+                ServerMessage::error(format!("Call room '{}' not found or full", room_id)),
             );
         }
     }
@@ -618,9 +920,7 @@ fn handle_call_signal(
     if !state.is_in_call_room(room_id, from_did) {
         state.send_to_client(
             from_did,
-            ServerMessage::Error {
-                message: "You are not in this call room".to_string(),
-            },
+            ServerMessage::error("You are not in this call room"),
         );
         return;
     }
@@ -628,9 +928,7 @@ fn handle_call_signal(
     if !state.is_in_call_room(room_id, to_did) {
         state.send_to_client(
             from_did,
-            ServerMessage::Error {
-                message: format!("Target '{}' is not in this call room", to_did),
-            },
+            ServerMessage::error(format!("Target '{}' is not in this call room", to_did)),
         );
         return;
     }
@@ -645,14 +943,37 @@ fn handle_call_signal(
     );
 }
 
+/// Pass a participant's signaling for an SFU-mode room on to the SFU.
+fn handle_sfu_signal(state: &RelayState, from_did: &str, room_id: &str, command: SfuCommand) {
+    if !state.is_in_call_room(room_id, from_did) {
+        state.send_to_client(
+            from_did,
+            ServerMessage::error("You are not in this call room"),
+        );
+        return;
+    }
+
+    if state.call_room_mode(room_id) != Some(CallRoomMode::Sfu) {
+        state.send_to_client(
+            from_did,
+            ServerMessage::error(format!("Call room '{}' does not use an SFU", room_id)),
+        );
+        return;
+    }
+
+    if !state.send_to_sfu(command) {
+        state.send_to_client(from_did, ServerMessage::error("The SFU is unavailable"));
+    }
+}
+
 // ── Federation Peer Connection Handler ────────────────────────────────────────
 
 /// Handle an inbound WebSocket connection from a peer relay.
 ///
 /// This is the server-side handler for when another relay connects to us
 /// via the `/federation` endpoint. It mirrors the outbound connection logic
-/// in `federation.rs` — both sides exchange Hello, PresenceSync, and forward
-/// messages bidirectionally.
+/// in `federation.rs` — both sides challenge each other, exchange signed
+/// Hellos, then PresenceSync, and forward messages bidirectionally.
 pub async fn handle_federation_peer(socket: WebSocket, state: RelayState) {
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
@@ -660,34 +981,18 @@ pub async fn handle_federation_peer(socket: WebSocket, state: RelayState) {
         Some(fed) => fed.clone(),
         None => {
             tracing::warn!("Federation connection rejected: federation not enabled");
-            let err = serde_json::to_string(&ServerMessage::Error {
-                message: "Federation not enabled on this relay".to_string(),
-            })
+            let err = serde_json::to_string(&ServerMessage::error(
+                "Federation not enabled on this relay",
+            ))
             .unwrap();
             let _ = ws_sender.send(Message::Text(err)).await;
             return;
         }
     };
 
-    // Send our Hello
-    let hello = PeerMessage::Hello {
-        relay_id: federation.relay_id.clone(),
-        relay_url: federation.relay_url.clone(),
-        region: federation.region.clone(),
-        location: federation.location.clone(),
-    };
-    if let Ok(json) = serde_json::to_string(&hello) {
-        if ws_sender.send(Message::Text(json)).await.is_err() {
-            return;
-        }
-    }
-
-    // Send our current presence
-    let presence = PeerMessage::PresenceSync {
-        relay_id: federation.relay_id.clone(),
-        online_dids: state.local_online_dids(),
-    };
-    if let Ok(json) = serde_json::to_string(&presence) {
+    // Challenge the peer to prove its identity
+    let mut session = PeerSession::new();
+    if let Ok(json) = serde_json::to_string(&session.challenge()) {
         if ws_sender.send(Message::Text(json)).await.is_err() {
             return;
         }
@@ -714,17 +1019,37 @@ pub async fn handle_federation_peer(socket: WebSocket, state: RelayState) {
             Ok(Message::Text(text)) => {
                 match serde_json::from_str::<PeerMessage>(&text) {
                     Ok(peer_msg) => {
-                        // Capture peer URL from Hello
-                        if let PeerMessage::Hello { ref relay_url, .. } = peer_msg {
-                            peer_url = Some(relay_url.clone());
-                            // Register this inbound peer's sender
-                            federation
-                                .peer_senders
-                                .insert(relay_url.clone(), tx.clone());
-                        }
+                        // Inbound peers are keyed by the URL in their Hello
+                        let url = match &peer_msg {
+                            PeerMessage::Hello { relay_url, .. } => relay_url.clone(),
+                            _ => peer_url.clone().unwrap_or_else(|| "unknown".to_string()),
+                        };
 
-                        let url = peer_url.as_deref().unwrap_or("unknown");
-                        federation.handle_peer_message(url, peer_msg);
+                        match federation.receive_from_peer(&mut session, &url, peer_msg) {
+                            Ok(PeerInbound::Reply(reply)) => {
+                                let _ = tx.send(*reply);
+                            }
+                            Ok(PeerInbound::Authenticated) => {
+                                // Register this inbound peer's sender and
+                                // send it our current presence and peers
+                                federation.peer_senders.insert(url.clone(), tx.clone());
+                                let _ = tx.send(PeerMessage::PresenceSync {
+                                    relay_id: federation.relay_id.clone(),
+                                    online_dids: state.local_online_dids(),
+                                });
+                                let _ = tx.send(federation.peer_exchange());
+                                peer_url = Some(url);
+                            }
+                            Ok(PeerInbound::Handled) => {}
+                            Err(e) => {
+                                tracing::warn!(
+                                    peer = url.as_str(),
+                                    error = %e,
+                                    "Rejected federation message"
+                                );
+                                break;
+                            }
+                        }
                     }
                     Err(e) => {
                         tracing::warn!(error = %e, "Failed to parse federation message");
@@ -803,6 +1128,27 @@ pub async fn handle_federation_inbound(
                 }
             }
 
+            PeerMessage::ForwardSealed {
+                to_did,
+                access_key,
+                payload,
+                timestamp,
+            } => {
+                if !state.check_sealed_access(&to_did, &access_key) {
+                    tracing::debug!(to = to_did.as_str(), "Federated sealed message refused");
+                    continue;
+                }
+
+                let server_msg = ServerMessage::SealedMessage {
+                    payload: payload.clone(),
+                    timestamp,
+                };
+                if !state.send_to_client(&to_did, server_msg) {
+                    // Target went offline — queue locally
+                    state.queue_offline_message(&to_did, "", &payload, timestamp);
+                }
+            }
+
             PeerMessage::ForwardSessionJoin {
                 session_id,
                 joiner_did,
@@ -880,6 +1226,7 @@ pub async fn handle_federation_inbound(
                 expires_at,
                 invite_payload,
                 published_at,
+                signature,
             } => {
                 tracing::debug!(
                     code = code.as_str(),
@@ -901,6 +1248,7 @@ pub async fn handle_federation_inbound(
                     invite_payload,
                     published_at: chrono::DateTime::from_timestamp(published_at, 0)
                         .unwrap_or_else(Utc::now),
+                    signature,
                 };
 
                 state.import_invite(invite);
@@ -912,7 +1260,7 @@ pub async fn handle_federation_inbound(
                     "Processing federated invite revocation"
                 );
                 // Remove locally without re-broadcasting (avoid loops)
-                state.published_invites.remove(&code);
+                state.remove_invite(&code);
             }
 
             PeerMessage::ForwardResolveInvite {
@@ -986,6 +1334,62 @@ pub async fn handle_federation_inbound(
     }
 }
 
+// ── SFU Event Handler ────────────────────────────────────────────────────────
+
+/// Deliver events from the SFU to the call room participants they concern.
+///
+/// Runs in a background task for as long as the SFU link exists. Events
+/// for participants who have since left the room are dropped.
+pub async fn handle_sfu_events(state: RelayState, mut rx: mpsc::UnboundedReceiver<SfuEvent>) {
+    while let Some(event) = rx.recv().await {
+        let (did, room_id, msg) = match event {
+            SfuEvent::Answer { room_id, did, sdp } => (
+                did,
+                room_id.clone(),
+                ServerMessage::SfuAnswer { room_id, sdp },
+            ),
+            SfuEvent::Offer { room_id, did, sdp } => (
+                did,
+                room_id.clone(),
+                ServerMessage::SfuOffer { room_id, sdp },
+            ),
+            SfuEvent::Candidate {
+                room_id,
+                did,
+                candidate,
+            } => (
+                did,
+                room_id.clone(),
+                ServerMessage::SfuCandidate { room_id, candidate },
+            ),
+            SfuEvent::Error {
+                room_id,
+                did,
+                message,
+            } => (did, room_id, ServerMessage::error(message)),
+            SfuEvent::DominantSpeaker { room_id, did } => {
+                let participants = state
+                    .get_call_room_participants(&room_id)
+                    .unwrap_or_default();
+                for participant_did in &participants {
+                    state.send_to_client(
+                        participant_did,
+                        ServerMessage::CallDominantSpeaker {
+                            room_id: room_id.clone(),
+                            did: did.clone(),
+                        },
+                    );
+                }
+                continue;
+            }
+        };
+
+        if state.is_in_call_room(&room_id, &did) {
+            state.send_to_client(&did, msg);
+        }
+    }
+}
+
 // ── Sync Handlers ────────────────────────────────────────────────────────────
 
 /// Handle SyncPush — broadcast a sync delta to all OTHER sessions of the same DID.
@@ -1022,23 +1426,18 @@ fn handle_sync_push(
     // If federation is enabled, broadcast to peers so other relays can deliver
     // to sessions of the same DID connected there.
     if let Some(ref fed) = state.federation {
-        if let Some(_peer_url) = fed.find_peer_for_did(from_did) {
-            let _ = fed.send_to_peer(
-                &_peer_url,
-                crate::protocol::PeerMessage::ForwardMessage {
-                    from_did: from_did.to_string(),
-                    to_did: from_did.to_string(),
-                    payload: serde_json::to_string(&serde_json::json!({
-                        "type": "sync_update",
-                        "section": section,
-                        "version": version,
-                        "encrypted_data": encrypted_data,
-                    }))
-                    .unwrap_or_default(),
-                    timestamp: chrono::Utc::now().timestamp(),
-                },
-            );
-        }
+        let _ = fed.forward_message(
+            from_did,
+            from_did,
+            &serde_json::to_string(&serde_json::json!({
+                "type": "sync_update",
+                "section": section,
+                "version": version,
+                "encrypted_data": encrypted_data,
+            }))
+            .unwrap_or_default(),
+            chrono::Utc::now().timestamp(),
+        );
     }
 
     state.send_to_client(
@@ -1067,3 +1466,70 @@ fn handle_sync_fetch(
         },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::federation::Federation;
+    use crate::relay_identity::RelayIdentity;
+    use crate::state::RelayConfig;
+    use std::collections::HashSet;
+
+    fn test_config() -> RelayConfig {
+        RelayConfig {
+            port: 8080,
+            max_offline_per_did: 5,
+            session_ttl_secs: 60,
+            offline_ttl_secs: 300,
+            region: "Test".to_string(),
+            location: "Test City".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_forwarded_sealed_message_is_not_queued() {
+        let (inbound_tx, _inbound_rx) = mpsc::unbounded_channel();
+        let federation = Federation::new(
+            "relay-a".to_string(),
+            "wss://relay-a.example.com/ws".to_string(),
+            "Test".to_string(),
+            "Test City".to_string(),
+            RelayIdentity::generate(),
+            HashSet::new(),
+            vec![],
+            inbound_tx,
+        );
+        let peer_url = "wss://relay-b.example.com/ws".to_string();
+        let (peer_tx, mut peer_rx) = mpsc::unbounded_channel();
+        federation.peer_senders.insert(peer_url.clone(), peer_tx);
+        let state = RelayState::with_federation(test_config(), federation);
+
+        // Bob registered here, but is online at the peer
+        let access_key = hex::encode([1u8; 32]);
+        let hash = crate::sealed_sender::access_hash(&access_key).unwrap();
+        state
+            .register_sealed_access("did:key:z6MkBob", &[hash])
+            .unwrap();
+        state
+            .federation
+            .as_ref()
+            .unwrap()
+            .did_to_peer
+            .insert("did:key:z6MkBob".to_string(), peer_url);
+
+        let reply = handle_send_sealed(&state, "did:key:z6MkBob", &access_key, "sealed");
+        assert!(matches!(reply, ServerMessage::Ack { .. }));
+        // Forwards go out signed
+        assert!(matches!(
+            peer_rx.try_recv().unwrap(),
+            PeerMessage::Signed { .. }
+        ));
+        assert!(state.pending_offline_messages("did:key:z6MkBob").is_empty());
+
+        // With nowhere to forward it, it's queued
+        state.federation.as_ref().unwrap().peer_senders.clear();
+        let reply = handle_send_sealed(&state, "did:key:z6MkBob", &access_key, "sealed");
+        assert!(matches!(reply, ServerMessage::Ack { .. }));
+        assert_eq!(state.pending_offline_messages("did:key:z6MkBob").len(), 1);
+    }
+}
@@ -0,0 +1,122 @@
+//! Time-limited TURN credentials.
+//!
+//! Credentials follow the TURN REST API scheme (draft-uberti-behave-turn-rest,
+//! as used by coturn's `use-auth-secret`): the username is
+//! `<expiry unix time>:<did>` and the password is the base64 HMAC-SHA1 of
+//! the username under a secret only the relay knows. The TURN server can
+//! check any credential without storing it, and a leaked credential stops
+//! working once it expires.
+
+use std::net::SocketAddr;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use turn::auth::{generate_auth_key, AuthHandler};
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Username and password for a DID, valid until `expires_at` (unix seconds).
+pub fn mint(secret: &[u8], did: &str, expires_at: i64) -> (String, String) {
+    let username = format!("{}:{}", expires_at, did);
+    let password = password(secret, &username);
+    (username, password)
+}
+
+/// The password that goes with `username`.
+pub fn password(secret: &[u8], username: &str) -> String {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(username.as_bytes());
+    BASE64.encode(mac.finalize().into_bytes())
+}
+
+/// Split a username into its expiry and DID.
+pub fn parse_username(username: &str) -> Option<(i64, &str)> {
+    let (expires_at, did) = username.split_once(':')?;
+    let expires_at = expires_at.parse().ok()?;
+    if did.is_empty() {
+        return None;
+    }
+    Some((expires_at, did))
+}
+
+/// Looks up the long-term key of a TURN request's username.
+///
+/// Only the expiry is checked here; the server then verifies the request's
+/// MESSAGE-INTEGRITY with the key, which fails unless the password was
+/// minted by this relay.
+pub struct RestAuthHandler {
+    secret: Vec<u8>,
+}
+
+impl RestAuthHandler {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self { secret }
+    }
+}
+
+impl AuthHandler for RestAuthHandler {
+    fn auth_handle(
+        &self,
+        username: &str,
+        realm: &str,
+        src_addr: SocketAddr,
+    ) -> Result<Vec<u8>, turn::Error> {
+        let Some((expires_at, _)) = parse_username(username) else {
+            return Err(turn::Error::Other(format!(
+                "malformed username {}",
+                username
+            )));
+        };
+        if expires_at < chrono::Utc::now().timestamp() {
+            tracing::debug!(%src_addr, "Expired TURN credential");
+            return Err(turn::Error::Other("credential expired".to_string()));
+        }
+
+        let password = password(&self.secret, username);
+        Ok(generate_auth_key(username, realm, &password))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"turn-secret";
+
+    fn addr() -> SocketAddr {
+        "192.0.2.1:40000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_mint_and_parse() {
+        let (username, password) = mint(SECRET, "did:key:z6MkAlice", 1_900_000_000);
+        assert_eq!(username, "1900000000:did:key:z6MkAlice");
+        assert_eq!(
+            parse_username(&username),
+            Some((1_900_000_000, "did:key:z6MkAlice"))
+        );
+
+        // Deterministic, and bound to the secret and the username
+        assert_eq!(password, self::password(SECRET, &username));
+        assert_ne!(password, self::password(b"other", &username));
+        assert_ne!(password, mint(SECRET, "did:key:z6MkBob", 1_900_000_000).1);
+
+        assert_eq!(parse_username("did:key:z6MkAlice"), None);
+        assert_eq!(parse_username("1900000000:"), None);
+    }
+
+    #[test]
+    fn test_auth_handler() {
+        let handler = RestAuthHandler::new(SECRET.to_vec());
+        let expires_at = chrono::Utc::now().timestamp() + 60;
+        let (username, password) = mint(SECRET, "did:key:z6MkAlice", expires_at);
+
+        let key = handler.auth_handle(&username, "umbra", addr()).unwrap();
+        assert_eq!(key, generate_auth_key(&username, "umbra", &password));
+
+        let (expired, _) = mint(SECRET, "did:key:z6MkAlice", expires_at - 120);
+        assert!(handler.auth_handle(&expired, "umbra", addr()).is_err());
+        assert!(handler.auth_handle("alice", "umbra", addr()).is_err());
+    }
+}
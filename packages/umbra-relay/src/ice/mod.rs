@@ -0,0 +1,681 @@
+//! STUN/TURN Server
+//!
+//! Calls between two peers behind symmetric NATs can't connect directly;
+//! their media has to go through a TURN server. The relay runs one next to
+//! the WebSocket server, so a self-hosted relay is all a community needs for
+//! calls to work everywhere:
+//!
+//! ```text
+//!   Client ──Register/Authenticate──► Relay
+//!   Client ◄──Registered { ice_servers: [stun:, turn: + credentials] }──
+//!   Client ◄──── UDP :3478 (STUN, TURN allocations, relayed media) ────►
+//! ```
+//!
+//! TURN credentials are minted for the authenticated DID and expire after
+//! `RELAY_TURN_TTL_SECS` (see [`credentials`]). Once a client's request
+//! carries valid credentials, its address is tied to their DID, and every
+//! packet to or from that address is charged to the DID's `turn_bytes`
+//! rate limit. Packets over the limit are dropped, which congestion control
+//! on the media streams adapts to. Until then an address gets only a small
+//! `turn_unauth_bytes` allowance per IP, enough for binding requests and the
+//! authentication handshake, and anything that isn't STUN is dropped.
+//!
+//! Like coturn's `denied-peer-ip`, permissions and channel bindings to
+//! loopback, private, link-local and other internal addresses are refused,
+//! so the relay can't be used to reach its own network. Deployments that
+//! relay between hosts on a LAN can allow private peers with
+//! `RELAY_TURN_ALLOW_PRIVATE_PEERS`.
+
+pub mod credentials;
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use dashmap::DashMap;
+use stun::attributes::{ATTR_REALM, ATTR_USERNAME, ATTR_XOR_PEER_ADDRESS};
+use stun::error_code::{ErrorCodeAttribute, CODE_FORBIDDEN};
+use stun::integrity::MessageIntegrity;
+use stun::message::{
+    is_message, Getter, Message, MessageType, CLASS_ERROR_RESPONSE, CLASS_REQUEST,
+    METHOD_CHANNEL_BIND, METHOD_CREATE_PERMISSION, METHOD_SEND,
+};
+use stun::textattrs::TextAttribute;
+use tokio::net::UdpSocket;
+use turn::proto::peeraddr::PeerAddress;
+use turn::relay::relay_range::RelayAddressGeneratorRanges;
+use turn::server::config::{ConnConfig, ServerConfig};
+use turn::server::Server;
+use webrtc_util::vnet::net::Net;
+use webrtc_util::Conn;
+
+use crate::metrics::CounterVec;
+use crate::protocol::IceServer;
+use crate::rate_limit::RateLimiter;
+
+use credentials::RestAuthHandler;
+
+/// Default STUN/TURN port.
+pub const DEFAULT_TURN_PORT: u16 = 3478;
+
+/// Default lifetime of TURN credentials (24 hours).
+pub const DEFAULT_CREDENTIAL_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Rate limit class TURN traffic is charged to.
+const BANDWIDTH_CLASS: &str = "turn_bytes";
+
+/// Rate limit class traffic from addresses without credentials is charged
+/// to, per IP.
+const UNAUTHENTICATED_CLASS: &str = "turn_unauth_bytes";
+
+/// How long an allocation can outlive its credentials: refreshes need valid
+/// credentials, and allocations last at most an hour without one.
+const ALLOCATION_GRACE_SECS: i64 = 60 * 60;
+
+/// TURN server settings.
+#[derive(Debug, Clone)]
+pub struct IceConfig {
+    /// Address advertised in STUN/TURN URLs and as the relayed address
+    pub public_ip: IpAddr,
+    /// UDP port for STUN and TURN
+    pub port: u16,
+    pub realm: String,
+    /// Secret TURN passwords are derived from
+    pub secret: Vec<u8>,
+    /// How long minted credentials are valid
+    pub credential_ttl: Duration,
+    /// UDP port range for relayed addresses
+    pub min_relay_port: u16,
+    pub max_relay_port: u16,
+    /// Allow relaying to private (RFC 1918, ULA, link-local, CGNAT) peers
+    pub allow_private_peers: bool,
+}
+
+/// The DID a client address authenticated as.
+struct Binding {
+    did: String,
+    /// Expiry of the credentials it authenticated with (unix seconds)
+    expires_at: i64,
+}
+
+/// The relay's STUN/TURN server.
+#[derive(Clone)]
+pub struct IceService {
+    config: Arc<IceConfig>,
+    /// Client address → DID
+    bindings: Arc<DashMap<SocketAddr, Binding>>,
+    /// Relayed bytes by direction (`in`, `out`), for `/metrics`
+    relayed: Arc<CounterVec>,
+    /// Keeps the server running; it stops when the last clone is dropped
+    _server: Arc<Server>,
+}
+
+impl IceService {
+    /// Bind the UDP port and start serving STUN and TURN.
+    pub async fn start(
+        mut config: IceConfig,
+        rate_limiter: RateLimiter,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let socket = UdpSocket::bind(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            config.port,
+        ))
+        .await?;
+        config.port = socket.local_addr()?.port();
+
+        let bindings = Arc::new(DashMap::new());
+        let relayed = Arc::new(CounterVec::default());
+        let conn = MeteredConn {
+            socket,
+            secret: config.secret.clone(),
+            allow_private_peers: config.allow_private_peers,
+            bindings: bindings.clone(),
+            rate_limiter,
+            relayed: relayed.clone(),
+        };
+
+        let server = Server::new(ServerConfig {
+            conn_configs: vec![ConnConfig {
+                conn: Arc::new(conn),
+                relay_addr_generator: Box::new(RelayAddressGeneratorRanges {
+                    relay_address: config.public_ip,
+                    min_port: config.min_relay_port,
+                    max_port: config.max_relay_port,
+                    max_retries: 10,
+                    address: "0.0.0.0".to_string(),
+                    net: Arc::new(Net::new(None)),
+                }),
+            }],
+            realm: config.realm.clone(),
+            auth_handler: Arc::new(RestAuthHandler::new(config.secret.clone())),
+            channel_bind_timeout: Duration::from_secs(0),
+        })
+        .await?;
+
+        Ok(Self {
+            config: Arc::new(config),
+            bindings,
+            relayed,
+            _server: Arc::new(server),
+        })
+    }
+
+    /// STUN/TURN servers for a client, with fresh credentials for `did`.
+    pub fn ice_servers(&self, did: &str) -> Vec<IceServer> {
+        let expires_at = Utc::now().timestamp() + self.config.credential_ttl.as_secs() as i64;
+        let (username, credential) = credentials::mint(&self.config.secret, did, expires_at);
+        let addr = SocketAddr::new(self.config.public_ip, self.config.port);
+        vec![IceServer {
+            urls: vec![
+                format!("stun:{}", addr),
+                format!("turn:{}?transport=udp", addr),
+            ],
+            username,
+            credential,
+        }]
+    }
+
+    /// Relayed bytes by direction.
+    pub fn relayed(&self) -> &CounterVec {
+        &self.relayed
+    }
+
+    /// Number of client addresses with valid TURN credentials.
+    pub fn client_count(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Forget addresses whose credentials (and so allocations) have expired.
+    pub fn cleanup(&self) -> usize {
+        let cutoff = Utc::now().timestamp() - ALLOCATION_GRACE_SECS;
+        let before = self.bindings.len();
+        self.bindings
+            .retain(|_, binding| binding.expires_at > cutoff);
+        before - self.bindings.len()
+    }
+}
+
+/// Whether TURN refuses to relay to `ip`: loopback, unspecified, multicast
+/// and broadcast always, and private ranges unless `allow_private` is set.
+fn is_denied_peer(ip: IpAddr, allow_private: bool) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            let reserved = ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_broadcast()
+                || a == 0
+                || a >= 240;
+            let private = ip.is_private()
+                || ip.is_link_local()
+                // Carrier-grade NAT (100.64.0.0/10)
+                || (a == 100 && (b & 0xc0) == 64);
+            reserved || (private && !allow_private)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_denied_peer(IpAddr::V4(v4), allow_private);
+            }
+            let first = ip.segments()[0];
+            let reserved = ip.is_loopback() || ip.is_unspecified() || ip.is_multicast();
+            // Unique local (fc00::/7) and link-local (fe80::/10)
+            let private = (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80;
+            reserved || (private && !allow_private)
+        }
+    }
+}
+
+/// The server's UDP socket, metering traffic per DID.
+struct MeteredConn {
+    socket: UdpSocket,
+    secret: Vec<u8>,
+    allow_private_peers: bool,
+    bindings: Arc<DashMap<SocketAddr, Binding>>,
+    rate_limiter: RateLimiter,
+    relayed: Arc<CounterVec>,
+}
+
+impl MeteredConn {
+    /// Tie `addr` to the DID of a STUN request that carries valid
+    /// credentials. The TURN server runs the same check before it does
+    /// anything for the request, so every address with an allocation is
+    /// bound to the DID it authenticated as.
+    fn authenticate(&self, msg: &mut Message, addr: SocketAddr) {
+        let Ok(username) = TextAttribute::get_from_as(msg, ATTR_USERNAME) else {
+            return;
+        };
+        let Some((expires_at, did)) = credentials::parse_username(&username.text) else {
+            return;
+        };
+        if expires_at < Utc::now().timestamp() {
+            return;
+        }
+        if let Some(binding) = self.bindings.get(&addr) {
+            if binding.did == did && binding.expires_at == expires_at {
+                return;
+            }
+        }
+        let Ok(realm) = TextAttribute::get_from_as(msg, ATTR_REALM) else {
+            return;
+        };
+
+        let password = credentials::password(&self.secret, &username.text);
+        let integrity =
+            MessageIntegrity::new_long_term_integrity(username.text.clone(), realm.text, password);
+        if integrity.check(msg).is_ok() {
+            tracing::debug!(did = did, %addr, "TURN client authenticated");
+            self.bindings.insert(
+                addr,
+                Binding {
+                    did: did.to_string(),
+                    expires_at,
+                },
+            );
+        }
+    }
+
+    /// Whether a permission, channel bind or send names a peer we won't
+    /// relay to. Every XOR-PEER-ADDRESS is checked, and one that doesn't
+    /// parse counts as denied.
+    fn denies_peer(&self, msg: &Message) -> bool {
+        let method = msg.typ.method;
+        if method != METHOD_CREATE_PERMISSION
+            && method != METHOD_CHANNEL_BIND
+            && method != METHOD_SEND
+        {
+            return false;
+        }
+        msg.attributes
+            .0
+            .iter()
+            .filter(|attr| attr.typ == ATTR_XOR_PEER_ADDRESS)
+            .any(|attr| {
+                let mut single = Message::new();
+                single.transaction_id = msg.transaction_id;
+                single.add(attr.typ, &attr.value);
+                let mut peer = PeerAddress::default();
+                peer.get_from(&single).is_err() || is_denied_peer(peer.ip, self.allow_private_peers)
+            })
+    }
+
+    /// Answer a request for a denied peer with 403 Forbidden.
+    async fn forbid(&self, msg: &Message, addr: SocketAddr) {
+        if msg.typ.class != CLASS_REQUEST {
+            return;
+        }
+        let mut response = Message::new();
+        let built = response.build(&[
+            Box::new(msg.transaction_id),
+            Box::new(MessageType::new(msg.typ.method, CLASS_ERROR_RESPONSE)),
+            Box::new(ErrorCodeAttribute {
+                code: CODE_FORBIDDEN,
+                reason: b"Forbidden".to_vec(),
+            }),
+        ]);
+        if built.is_ok() && self.charge(addr, response.raw.len(), "out") {
+            let _ = self.socket.send_to(&response.raw, addr).await;
+        }
+    }
+
+    /// Charge a packet to the DID bound to `addr`, or to its IP's
+    /// unauthenticated allowance. Returns false if it should be dropped.
+    fn charge(&self, addr: SocketAddr, len: usize, direction: &str) -> bool {
+        let Some(binding) = self.bindings.get(&addr) else {
+            // Unauthenticated: binding requests and the auth handshake
+            return self
+                .rate_limiter
+                .check_cost(UNAUTHENTICATED_CLASS, &addr.ip().to_string(), len as u32)
+                .is_ok();
+        };
+        if binding.expires_at < Utc::now().timestamp() {
+            return false;
+        }
+        match self
+            .rate_limiter
+            .check_cost(BANDWIDTH_CLASS, &binding.did, len as u32)
+        {
+            Ok(()) => {
+                self.relayed.add(&[direction], len as u64);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+#[async_trait]
+impl Conn for MeteredConn {
+    async fn connect(&self, addr: SocketAddr) -> webrtc_util::Result<()> {
+        Ok(self.socket.connect(addr).await?)
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> webrtc_util::Result<usize> {
+        Ok(self.socket.recv(buf).await?)
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> webrtc_util::Result<(usize, SocketAddr)> {
+        loop {
+            let (len, addr) = self.socket.recv_from(buf).await?;
+            if is_message(&buf[..len]) {
+                let mut msg = Message::new();
+                if msg.unmarshal_binary(&buf[..len]).is_err() {
+                    continue;
+                }
+                self.authenticate(&mut msg, addr);
+                if !self.charge(addr, len, "in") {
+                    continue;
+                }
+                if self.denies_peer(&msg) {
+                    tracing::debug!(%addr, "TURN request for a denied peer refused");
+                    self.forbid(&msg, addr).await;
+                    continue;
+                }
+                return Ok((len, addr));
+            }
+            // Channel data needs an allocation, so credentials
+            if self.bindings.contains_key(&addr) && self.charge(addr, len, "in") {
+                return Ok((len, addr));
+            }
+        }
+    }
+
+    async fn send(&self, buf: &[u8]) -> webrtc_util::Result<usize> {
+        Ok(self.socket.send(buf).await?)
+    }
+
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> webrtc_util::Result<usize> {
+        if !self.charge(target, buf.len(), "out") {
+            // Dropped, as if lost on the way
+            return Ok(buf.len());
+        }
+        Ok(self.socket.send_to(buf, target).await?)
+    }
+
+    fn local_addr(&self) -> webrtc_util::Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    async fn close(&self) -> webrtc_util::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stun::agent::TransactionId;
+    use stun::message::{Method, METHOD_BINDING};
+    use turn::client::{Client, ClientConfig};
+
+    const SECRET: &[u8] = b"turn-secret";
+
+    async fn start(rate_limiter: RateLimiter) -> IceService {
+        let config = IceConfig {
+            public_ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 0,
+            realm: "umbra".to_string(),
+            secret: SECRET.to_vec(),
+            credential_ttl: Duration::from_secs(60),
+            min_relay_port: 49152,
+            max_relay_port: 65535,
+            allow_private_peers: false,
+        };
+        IceService::start(config, rate_limiter).await.unwrap()
+    }
+
+    async fn client(service: &IceService, username: &str, password: &str) -> (Client, SocketAddr) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = socket.local_addr().unwrap();
+        let server = format!("127.0.0.1:{}", service.config.port);
+        let client = Client::new(ClientConfig {
+            stun_serv_addr: server.clone(),
+            turn_serv_addr: server,
+            username: username.to_string(),
+            password: password.to_string(),
+            realm: "umbra".to_string(),
+            software: String::new(),
+            rto_in_ms: 0,
+            conn: Arc::new(socket),
+            vnet: None,
+        })
+        .await
+        .unwrap();
+        client.listen().await.unwrap();
+        (client, local_addr)
+    }
+
+    /// A STUN request of `method`, naming `peer` if given.
+    fn request(method: Method, peer: Option<SocketAddr>) -> Message {
+        let mut msg = Message::new();
+        let mut setters: Vec<Box<dyn stun::message::Setter>> = vec![
+            Box::new(TransactionId::new()),
+            Box::new(MessageType::new(method, CLASS_REQUEST)),
+        ];
+        if let Some(peer) = peer {
+            setters.push(Box::new(PeerAddress {
+                ip: peer.ip(),
+                port: peer.port(),
+            }));
+        }
+        msg.build(&setters).unwrap();
+        msg
+    }
+
+    fn metered(rate_limiter: RateLimiter, socket: UdpSocket) -> MeteredConn {
+        MeteredConn {
+            socket,
+            secret: SECRET.to_vec(),
+            allow_private_peers: false,
+            bindings: Arc::new(DashMap::new()),
+            rate_limiter,
+            relayed: Arc::new(CounterVec::default()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_allocation_binds_did() {
+        let service = start(RateLimiter::default()).await;
+        let servers = service.ice_servers("did:key:z6MkAlice");
+        assert_eq!(
+            servers[0].urls,
+            vec![
+                format!("stun:127.0.0.1:{}", service.config.port),
+                format!("turn:127.0.0.1:{}?transport=udp", service.config.port),
+            ]
+        );
+
+        let (client, addr) = client(&service, &servers[0].username, &servers[0].credential).await;
+        let relay_conn = client.allocate().await.unwrap();
+        assert!(relay_conn.local_addr().is_ok());
+        assert_eq!(
+            service.bindings.get(&addr).map(|b| b.did.clone()),
+            Some("did:key:z6MkAlice".to_string())
+        );
+        assert!(service.relayed().get(&["in"]) > 0);
+        assert!(service.relayed().get(&["out"]) > 0);
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password_is_refused() {
+        let service = start(RateLimiter::default()).await;
+        let username = service.ice_servers("did:key:z6MkAlice")[0].username.clone();
+
+        let (client, addr) = client(&service, &username, "bm90IHRoZSBwYXNzd29yZA==").await;
+        assert!(client.allocate().await.is_err());
+        assert!(service.bindings.get(&addr).is_none());
+        assert_eq!(service.client_count(), 0);
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_cap_drops_packets() {
+        let limiter = RateLimiter::with_overrides(&["turn_bytes=1000/60".to_string()]).unwrap();
+        let conn = metered(limiter, UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let server_addr = conn.local_addr().unwrap();
+
+        let alice = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let stranger = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        conn.bindings.insert(
+            alice.local_addr().unwrap(),
+            Binding {
+                did: "did:key:z6MkAlice".to_string(),
+                expires_at: Utc::now().timestamp() + 60,
+            },
+        );
+
+        let mut buf = [0u8; 1500];
+        alice.send_to(&[1; 600], server_addr).await.unwrap();
+        assert_eq!(conn.recv_from(&mut buf).await.unwrap().0, 600);
+
+        // Over the cap: dropped, so the next packet is the first one received
+        alice.send_to(&[2; 600], server_addr).await.unwrap();
+        alice.send_to(&[3; 300], server_addr).await.unwrap();
+        assert_eq!(conn.recv_from(&mut buf).await.unwrap().0, 300);
+        assert_eq!(buf[0], 3);
+
+        // Addresses without credentials can only send STUN, and only a
+        // little of it
+        stranger.send_to(&[4; 1200], server_addr).await.unwrap();
+        let binding = request(METHOD_BINDING, None);
+        stranger.send_to(&binding.raw, server_addr).await.unwrap();
+        assert_eq!(conn.recv_from(&mut buf).await.unwrap().0, binding.raw.len());
+        assert_eq!(conn.relayed.get(&["in"]), 900);
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_allowance() {
+        let limiter =
+            RateLimiter::with_overrides(&["turn_unauth_bytes=100/60".to_string()]).unwrap();
+        let conn = metered(limiter, UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let server_addr = conn.local_addr().unwrap();
+        let stranger = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let alice = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        conn.bindings.insert(
+            alice.local_addr().unwrap(),
+            Binding {
+                did: "did:key:z6MkAlice".to_string(),
+                expires_at: Utc::now().timestamp() + 60,
+            },
+        );
+
+        let binding = request(METHOD_BINDING, None);
+        for _ in 0..10 {
+            stranger.send_to(&binding.raw, server_addr).await.unwrap();
+        }
+        alice.send_to(&[5; 10], server_addr).await.unwrap();
+
+        // Past the allowance, the stranger's requests are dropped
+        let mut buf = [0u8; 1500];
+        let mut received = 0;
+        loop {
+            let (len, addr) = conn.recv_from(&mut buf).await.unwrap();
+            if addr == alice.local_addr().unwrap() {
+                assert_eq!(len, 10);
+                break;
+            }
+            received += len;
+        }
+        assert!(received > 0 && received <= 100);
+    }
+
+    #[tokio::test]
+    async fn test_denied_peers_are_forbidden() {
+        let conn = metered(
+            RateLimiter::default(),
+            UdpSocket::bind("127.0.0.1:0").await.unwrap(),
+        );
+        let server_addr = conn.local_addr().unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut buf = [0u8; 1500];
+
+        for (method, peer) in [
+            (METHOD_CREATE_PERMISSION, "10.0.0.5:5000"),
+            (METHOD_CHANNEL_BIND, "127.0.0.1:5000"),
+            (METHOD_CREATE_PERMISSION, "[fd00::1]:5000"),
+        ] {
+            let denied = request(method, Some(peer.parse().unwrap()));
+            client.send_to(&denied.raw, server_addr).await.unwrap();
+            let allowed = request(
+                METHOD_CREATE_PERMISSION,
+                Some("203.0.113.7:5000".parse().unwrap()),
+            );
+            client.send_to(&allowed.raw, server_addr).await.unwrap();
+
+            // Only the public peer reaches the TURN server
+            let (len, _) = conn.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], &allowed.raw[..]);
+
+            let len = client.recv(&mut buf).await.unwrap();
+            let mut response = Message::new();
+            response.unmarshal_binary(&buf[..len]).unwrap();
+            assert_eq!(response.transaction_id, denied.transaction_id);
+            assert_eq!(response.typ, MessageType::new(method, CLASS_ERROR_RESPONSE));
+            let mut code = ErrorCodeAttribute::default();
+            code.get_from(&response).unwrap();
+            assert!(code.code == CODE_FORBIDDEN);
+        }
+    }
+
+    #[test]
+    fn test_denied_peer_ranges() {
+        for ip in [
+            "127.0.0.1",
+            "0.0.0.0",
+            "0.1.2.3",
+            "224.0.0.1",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "ff02::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(is_denied_peer(ip.parse().unwrap(), true), "{}", ip);
+        }
+        for ip in [
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "fd12::1",
+            "fe80::1",
+            "::ffff:192.168.1.1",
+        ] {
+            assert!(is_denied_peer(ip.parse().unwrap(), false), "{}", ip);
+            assert!(!is_denied_peer(ip.parse().unwrap(), true), "{}", ip);
+        }
+        for ip in ["203.0.113.7", "8.8.8.8", "100.128.0.1", "2001:db8::1"] {
+            assert!(!is_denied_peer(ip.parse().unwrap(), false), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_keeps_live_allocations() {
+        let service = start(RateLimiter::default()).await;
+        let now = Utc::now().timestamp();
+        for (port, expires_at) in [
+            (1, now + 60),
+            (2, now - 60),
+            (3, now - ALLOCATION_GRACE_SECS - 60),
+        ] {
+            service.bindings.insert(
+                SocketAddr::from(([192, 0, 2, 1], port)),
+                Binding {
+                    did: "did:key:z6MkAlice".to_string(),
+                    expires_at,
+                },
+            );
+        }
+
+        assert_eq!(service.cleanup(), 1);
+        assert_eq!(service.client_count(), 2);
+    }
+}
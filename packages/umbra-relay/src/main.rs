@@ -10,21 +10,33 @@
 //!    automatically. No second scan needed.
 //!
 //! 3. **Offline message queue**: If a recipient is offline, the relay stores
-//!    encrypted message blobs and delivers them when the peer reconnects.
+//!    encrypted message blobs (persisted in SQLite) and delivers them when the
+//!    peer reconnects, deleting them only once the peer acknowledges receipt.
 //!
 //! **Privacy**: The relay never sees plaintext content. All E2E encryption
 //! happens client-side — the relay only handles opaque encrypted blobs.
 
+mod admin;
 mod asset;
 mod bridge;
 mod discovery;
 mod gif;
 mod federation;
 mod handler;
+mod ice;
+mod metrics;
+mod offline_store;
 mod protocol;
+mod push;
+mod rate_limit;
+mod relay_identity;
+mod sealed_sender;
+mod sfu;
 mod state;
+mod state_store;
 mod sync;
 
+use std::sync::Arc;
 use std::time::Duration;
 
 use axum::{
@@ -32,7 +44,7 @@ use axum::{
     http::{Method, StatusCode},
     response::IntoResponse,
     routing::{delete, get, post, put},
-    Json, Router,
+    Extension, Json, Router,
 };
 use clap::Parser;
 use serde_json::json;
@@ -83,10 +95,28 @@ struct Args {
     public_url: Option<String>,
 
     /// Peer relay WebSocket URLs to form a mesh with (comma-separated).
+    /// These seed the mesh; further relays are discovered from peers.
     /// Example: wss://relay2.example.com/ws,wss://relay3.example.com/ws
     #[arg(long, env = "RELAY_PEERS", value_delimiter = ',')]
     peers: Vec<String>,
 
+    /// Hex-encoded Ed25519 identity keys of the peer relays this relay
+    /// federates with (comma-separated). Peers whose key is not listed are
+    /// refused.
+    #[arg(long, env = "RELAY_PEER_KEYS", value_delimiter = ',')]
+    peer_keys: Vec<String>,
+
+    /// Maximum outbound connections to peer relays, counting the configured
+    /// ones. Further relays learned through peer exchange are dialed until
+    /// this is reached.
+    #[arg(long, default_value_t = federation::DEFAULT_MAX_PEERS, env = "RELAY_MAX_PEERS")]
+    max_peers: usize,
+
+    /// Hex-encoded 32-byte secret seed for this relay's federation
+    /// identity. Defaults to a key generated once and stored in DATA_DIR.
+    #[arg(long, env = "RELAY_IDENTITY_KEY", hide_env_values = true)]
+    identity_key: Option<String>,
+
     /// Relay ID — unique identifier for this relay instance.
     /// Defaults to a random UUID if not set.
     #[arg(long, env = "RELAY_ID")]
@@ -96,6 +126,83 @@ struct Args {
     /// presence with peers).
     #[arg(long, default_value_t = 30, env = "PRESENCE_HEARTBEAT_SECS")]
     presence_heartbeat_secs: u64,
+
+    /// Bearer token for the admin API (`/admin/*`) and `/metrics`.
+    /// The admin API is disabled and `/metrics` is public when unset.
+    #[arg(long, env = "RELAY_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Rate limit overrides (comma-separated), as `class=burst/secs` or
+    /// `class=off`. Example: send=300/60,username=off
+    #[arg(long, env = "RELAY_RATE_LIMITS", value_delimiter = ',')]
+    rate_limits: Vec<String>,
+
+    /// Take client IPs for HTTP rate limiting from X-Forwarded-For.
+    /// Only enable behind a reverse proxy that sets it.
+    #[arg(long, env = "RELAY_TRUST_PROXY")]
+    trust_proxy: bool,
+
+    /// Don't accept push registrations or send wake-ups.
+    #[arg(long, env = "RELAY_DISABLE_PUSH")]
+    disable_push: bool,
+
+    /// Hex-encoded 32-byte key push endpoints are encrypted with at rest.
+    /// Defaults to a key generated once and stored in DATA_DIR.
+    #[arg(long, env = "RELAY_PUSH_KEY", hide_env_values = true)]
+    push_key: Option<String>,
+
+    /// Seconds to wait for further messages before waking an offline
+    /// recipient (bursts are coalesced into one wake-up).
+    #[arg(long, default_value_t = 10, env = "PUSH_COALESCE_SECS")]
+    push_coalesce_secs: u64,
+
+    /// Accept plain `http://` push endpoints. For local development only.
+    #[arg(long, env = "RELAY_PUSH_ALLOW_HTTP")]
+    push_allow_http: bool,
+
+    /// Control URL of an umbra-sfu instance (e.g. ws://sfu:8090/control).
+    /// Enables SFU-mode call rooms.
+    #[arg(long, env = "RELAY_SFU_URL")]
+    sfu_url: Option<String>,
+
+    /// Shared secret for the SFU's control API (its SFU_SECRET).
+    #[arg(long, env = "RELAY_SFU_SECRET", hide_env_values = true)]
+    sfu_secret: Option<String>,
+
+    /// Maximum participants in an SFU call room.
+    #[arg(long, default_value_t = sfu::DEFAULT_MAX_SFU_PARTICIPANTS, env = "RELAY_SFU_MAX_PARTICIPANTS")]
+    sfu_max_participants: usize,
+
+    /// Public IP of this host. Enables the built-in STUN/TURN server, whose
+    /// credentials are handed to clients when they register.
+    #[arg(long, env = "RELAY_TURN_PUBLIC_IP")]
+    turn_public_ip: Option<std::net::IpAddr>,
+
+    /// UDP port for STUN and TURN
+    #[arg(long, default_value_t = ice::DEFAULT_TURN_PORT, env = "RELAY_TURN_PORT")]
+    turn_port: u16,
+
+    /// Secret TURN credentials are derived from. Defaults to a random one,
+    /// so credentials stop working when the relay restarts.
+    #[arg(long, env = "RELAY_TURN_SECRET", hide_env_values = true)]
+    turn_secret: Option<String>,
+
+    /// How long TURN credentials are valid, in seconds
+    #[arg(long, default_value_t = ice::DEFAULT_CREDENTIAL_TTL_SECS, env = "RELAY_TURN_TTL_SECS")]
+    turn_ttl_secs: u64,
+
+    /// Lowest UDP port for relayed addresses
+    #[arg(long, default_value_t = 49152, env = "RELAY_TURN_MIN_PORT")]
+    turn_min_port: u16,
+
+    /// Highest UDP port for relayed addresses
+    #[arg(long, default_value_t = 65535, env = "RELAY_TURN_MAX_PORT")]
+    turn_max_port: u16,
+
+    /// Let TURN relay to private (RFC 1918, ULA, link-local) peers. Off by
+    /// default, so clients can't use the relay to reach its own network.
+    #[arg(long, env = "RELAY_TURN_ALLOW_PRIVATE_PEERS")]
+    turn_allow_private_peers: bool,
 }
 
 // ── Entry Point ───────────────────────────────────────────────────────────────
@@ -124,6 +231,68 @@ async fn main() {
         location: args.location,
     };
 
+    let rate_limiter = match rate_limit::RateLimiter::with_overrides(&args.rate_limits) {
+        Ok(limiter) => limiter,
+        Err(e) => {
+            tracing::error!("Invalid RELAY_RATE_LIMITS: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // ── Offline Queue Setup ───────────────────────────────────────────────
+    let data_dir = std::env::var("DATA_DIR").ok();
+    let offline_store = match offline_store::OfflineStore::new(data_dir.as_deref()) {
+        Ok(store) => {
+            tracing::info!(
+                queued = store.count(),
+                "Offline message store initialized"
+            );
+            store
+        }
+        Err(e) => {
+            tracing::error!("Failed to initialize offline message store: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // ── Invite & Session Store Setup ──────────────────────────────────────
+    let state_store = match state_store::StateStore::new(data_dir.as_deref()) {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::error!("Failed to initialize relay state store: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // ── Push Gateway Setup ────────────────────────────────────────────────
+    let push_gateway = if args.disable_push {
+        tracing::info!("Push notifications disabled");
+        None
+    } else {
+        let key = push::store::load_key(args.push_key.as_deref(), data_dir.as_deref());
+        let store = key.and_then(|key| {
+            push::store::PushStore::new(data_dir.as_deref(), &key).map_err(|e| e.to_string())
+        });
+        match store {
+            Ok(store) => {
+                tracing::info!(registrations = store.count(), "Push store initialized");
+                Some(push::PushGateway::new(
+                    store,
+                    Arc::new(push::notifier::HttpNotifier::new()),
+                    push::PushConfig {
+                        coalesce_window: Duration::from_secs(args.push_coalesce_secs),
+                        allow_http: args.push_allow_http,
+                        ..Default::default()
+                    },
+                ))
+            }
+            Err(e) => {
+                tracing::error!("Failed to initialize push store: {}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+
     // ── Federation Setup ──────────────────────────────────────────────────
 
     let peer_urls: Vec<String> = args
@@ -151,6 +320,37 @@ async fn main() {
             tracing::info!(peer = peer.as_str(), "Configured peer relay");
         }
 
+        let identity = match relay_identity::RelayIdentity::load(
+            args.identity_key.as_deref(),
+            data_dir.as_deref(),
+        ) {
+            Ok(identity) => identity,
+            Err(e) => {
+                tracing::error!("Failed to load relay identity: {}", e);
+                std::process::exit(1);
+            }
+        };
+        tracing::info!(
+            public_key = identity.public_key_hex().as_str(),
+            "Relay identity loaded"
+        );
+
+        let mut trusted_peer_keys = std::collections::HashSet::new();
+        for key in args.peer_keys.iter().filter(|k| !k.trim().is_empty()) {
+            match relay_identity::parse_public_key(key) {
+                Ok(key) => {
+                    trusted_peer_keys.insert(key);
+                }
+                Err(e) => {
+                    tracing::error!(key = key.as_str(), "Invalid RELAY_PEER_KEYS entry: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        if trusted_peer_keys.is_empty() {
+            tracing::warn!("RELAY_PEER_KEYS is empty — every peer relay will be refused");
+        }
+
         let (inbound_tx, inbound_rx) = tokio::sync::mpsc::unbounded_channel();
 
         let federation = Federation::new(
@@ -158,11 +358,20 @@ async fn main() {
             public_url,
             config.region.clone(),
             config.location.clone(),
+            identity,
+            trusted_peer_keys,
             peer_urls,
             inbound_tx,
-        );
+        )
+        .with_max_peers(args.max_peers);
 
-        let state = RelayState::with_federation(config, federation.clone());
+        let mut state = RelayState::with_federation(config, federation.clone())
+            .with_offline_store(offline_store)
+            .with_state_store(state_store)
+            .with_rate_limiter(rate_limiter.clone());
+        if let Some(push) = push_gateway {
+            state = state.with_push(push);
+        }
 
         // Start federation connections
         federation.start();
@@ -182,6 +391,7 @@ async fn main() {
                 interval.tick().await;
                 let dids = heartbeat_state.local_online_dids();
                 if let Some(ref fed) = heartbeat_state.federation {
+                    fed.set_local_load(heartbeat_state.load());
                     fed.broadcast_full_presence(dids);
                 }
             }
@@ -190,7 +400,79 @@ async fn main() {
         state
     } else {
         tracing::info!("Federation disabled (no peers configured)");
-        RelayState::new(config)
+        let state = RelayState::new(config)
+            .with_offline_store(offline_store)
+            .with_state_store(state_store)
+            .with_rate_limiter(rate_limiter.clone());
+        match push_gateway {
+            Some(push) => state.with_push(push),
+            None => state,
+        }
+    };
+
+    tracing::info!(
+        invites = state.published_invites.len(),
+        sessions = state.sessions.len(),
+        "Relay state store initialized"
+    );
+
+    // ── SFU Setup ─────────────────────────────────────────────────────────
+    let state = match (args.sfu_url, args.sfu_secret) {
+        (Some(url), Some(secret)) => {
+            tracing::info!(url = url.as_str(), "SFU call rooms enabled");
+            let link = sfu::SfuLink::new(url, secret, args.sfu_max_participants);
+            let events = link.start();
+            let state = state.with_sfu(link);
+
+            let sfu_state = state.clone();
+            tokio::spawn(async move {
+                handler::handle_sfu_events(sfu_state, events).await;
+            });
+            state
+        }
+        (Some(_), None) => {
+            tracing::error!("RELAY_SFU_URL is set but RELAY_SFU_SECRET is not");
+            std::process::exit(1);
+        }
+        (None, _) => state,
+    };
+
+    // ── STUN/TURN Setup ───────────────────────────────────────────────────
+    let state = match args.turn_public_ip {
+        Some(public_ip) => {
+            let secret = match args.turn_secret {
+                Some(secret) => secret.into_bytes(),
+                None => rand::random::<[u8; 32]>().to_vec(),
+            };
+            let config = ice::IceConfig {
+                public_ip,
+                port: args.turn_port,
+                realm: "umbra".to_string(),
+                secret,
+                credential_ttl: Duration::from_secs(args.turn_ttl_secs),
+                min_relay_port: args.turn_min_port,
+                max_relay_port: args.turn_max_port,
+                allow_private_peers: args.turn_allow_private_peers,
+            };
+            match ice::IceService::start(config, rate_limiter.clone()).await {
+                Ok(ice) => {
+                    tracing::info!(
+                        public_ip = %public_ip,
+                        port = args.turn_port,
+                        "STUN/TURN server enabled"
+                    );
+                    state.with_ice(ice)
+                }
+                Err(e) => {
+                    tracing::error!("Failed to start STUN/TURN server: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => {
+            tracing::info!("STUN/TURN server disabled (RELAY_TURN_PUBLIC_IP not set)");
+            state
+        }
     };
 
     // Spawn periodic cleanup task
@@ -240,7 +522,6 @@ async fn main() {
     });
 
     // ── Bridge Config Store Setup ──────────────────────────────────────────
-    let data_dir = std::env::var("DATA_DIR").ok();
     let bridge_store = BridgeStore::new(data_dir.as_deref());
     let bridge_loaded = bridge_store.load_from_disk();
     if bridge_loaded > 0 {
@@ -278,7 +559,7 @@ async fn main() {
     });
 
     // Build sync router
-    let sync_router = sync::router(sync_store);
+    let sync_router = sync::router(sync_store.clone());
 
     // ── Asset Store Setup ────────────────────────────────────────────────
     let asset_store = asset::store::AssetStore::new(data_dir.as_deref());
@@ -297,7 +578,7 @@ async fn main() {
             "/api/community/:communityId/assets/:filename",
             get(asset::api::get_asset),
         )
-        .with_state(asset_store);
+        .with_state(asset_store.clone());
 
     // Build bridge router
     let bridge_router = Router::new()
@@ -307,7 +588,7 @@ async fn main() {
         .route("/api/bridge/:id", delete(bridge::api::delete_bridge))
         .route("/api/bridge/:id/members", put(bridge::api::update_members))
         .route("/api/bridge/:id/enabled", put(bridge::api::set_enabled))
-        .with_state(bridge_store);
+        .with_state(bridge_store.clone());
 
     // Build discovery router with its own state
     let discovery_router = Router::new()
@@ -457,7 +738,7 @@ async fn main() {
             "/discovery/username/release",
             delete(discovery::api::release_username),
         )
-        .with_state((discovery_store, discovery_config));
+        .with_state((discovery_store.clone(), discovery_config));
 
     // GIF proxy (Tenor)
     let gif_config = gif::config::GifConfig::from_env();
@@ -472,6 +753,38 @@ async fn main() {
         .route("/api/gif/trending", get(gif::api::trending))
         .with_state(gif_config);
 
+    // Metrics and admin API
+    let admin_token = args
+        .admin_token
+        .filter(|token| !token.trim().is_empty())
+        .map(|token| admin::AdminToken::new(&token));
+    let metrics_router = Router::new()
+        .route("/metrics", get(metrics::metrics_handler))
+        .with_state(metrics::MetricsSources {
+            relay: state.clone(),
+            discovery: discovery_store,
+            bridges: bridge_store,
+            sync: sync_store,
+            assets: asset_store,
+        });
+    let admin_router = match admin_token {
+        Some(token) => {
+            tracing::info!("Admin API enabled");
+            metrics_router
+                .layer(axum::middleware::from_fn_with_state(
+                    token.clone(),
+                    admin::require_token,
+                ))
+                .merge(admin::router(state.clone(), token))
+        }
+        None => {
+            tracing::warn!(
+                "Admin API disabled and /metrics is public (set RELAY_ADMIN_TOKEN to enable)"
+            );
+            metrics_router
+        }
+    };
+
     // Build main router
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -484,6 +797,7 @@ async fn main() {
         .route("/health", get(health_handler))
         .route("/stats", get(stats_handler))
         .route("/info", get(info_handler))
+        .route("/relays", get(relays_handler))
         .route("/api/invite/:code", get(invite_resolve_handler))
         .with_state(state)
         .merge(discovery_router)
@@ -491,6 +805,14 @@ async fn main() {
         .merge(asset_router)
         .merge(gif_router)
         .merge(sync_router)
+        .merge(admin_router)
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limit::HttpRateLimit {
+                limiter: rate_limiter,
+                trust_forwarded_for: args.trust_proxy,
+            },
+            rate_limit::limit_http,
+        ))
         .layer(cors)
         .layer(TraceLayer::new_for_http());
 
@@ -501,14 +823,24 @@ async fn main() {
         .await
         .expect("Failed to bind address");
 
-    axum::serve(listener, app).await.expect("Server error");
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
+    .expect("Server error");
 }
 
 // ── Route Handlers ────────────────────────────────────────────────────────────
 
 /// WebSocket upgrade handler for client connections.
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<RelayState>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handler::handle_websocket(socket, state))
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<RelayState>,
+    client_ip: Option<Extension<rate_limit::ClientIp>>,
+) -> impl IntoResponse {
+    let client_ip = client_ip.map(|Extension(rate_limit::ClientIp(ip))| ip);
+    ws.on_upgrade(move |socket| handler::handle_websocket(socket, state, client_ip))
 }
 
 /// WebSocket upgrade handler for federation (relay-to-relay) connections.
@@ -538,6 +870,8 @@ async fn stats_handler(State(state): State<RelayState>) -> impl IntoResponse {
         "published_invites": state.published_invites.len(),
         "connected_peers": state.connected_peers(),
         "federation_enabled": state.federation.is_some(),
+        "sfu_connected": state.sfu.as_ref().map(|sfu| sfu.is_connected()),
+        "turn_clients": state.ice.as_ref().map(|ice| ice.client_count()),
     }))
 }
 
@@ -557,6 +891,16 @@ async fn info_handler(State(state): State<RelayState>) -> impl IntoResponse {
     }))
 }
 
+/// Relay directory endpoint.
+/// Lists this relay and the federated relays reachable from it, with their
+/// region and load, so clients can pick the best one to connect to.
+async fn relays_handler(State(state): State<RelayState>) -> impl IntoResponse {
+    Json(json!({
+        "relays": state.relay_directory(),
+        "timestamp": chrono::Utc::now().timestamp_millis(),
+    }))
+}
+
 /// HTTP invite resolution endpoint.
 /// Allows clients to resolve an invite code without a WebSocket connection.
 /// Returns invite preview metadata (community name, member count, etc.)
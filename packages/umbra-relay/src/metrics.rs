@@ -0,0 +1,411 @@
+//! Prometheus Metrics
+//!
+//! Counters are kept where the events happen (`RelayState::metrics`, the
+//! discovery store's OAuth outcomes, the push gateway's wake-ups); gauges are read from the stores when
+//! `/metrics` is scraped. Everything is rendered in the Prometheus text
+//! exposition format, prefixed with `umbra_relay_`.
+//!
+//! ```text
+//!   GET /metrics
+//!     connections, messages routed (local / federation / unreachable),
+//!     sealed-sender refusals,
+//!     offline queue depth and drops, push registrations and wake-ups,
+//!     signaling sessions, call rooms, TURN relayed bytes,
+//!     sync blob bytes, asset storage per community, OAuth outcomes
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::{extract::State, http::header, response::IntoResponse};
+use dashmap::DashMap;
+
+use crate::asset::store::AssetStore;
+use crate::bridge::BridgeStore;
+use crate::discovery::DiscoveryStore;
+use crate::state::RelayState;
+use crate::sync::blob_store::SyncBlobStore;
+
+/// Prefix for every metric name.
+const PREFIX: &str = "umbra_relay_";
+
+/// Content type of the Prometheus text format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// ── Counters ──────────────────────────────────────────────────────────────────
+
+/// A monotonically increasing counter.
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Counters split by label values (in the order of the family's label names).
+#[derive(Debug, Default)]
+pub struct CounterVec(DashMap<Vec<String>, u64>);
+
+impl CounterVec {
+    pub fn inc(&self, labels: &[&str]) {
+        self.add(labels, 1);
+    }
+
+    pub fn add(&self, labels: &[&str], n: u64) {
+        let key = labels.iter().map(|l| l.to_string()).collect();
+        *self.0.entry(key).or_insert(0) += n;
+    }
+
+    #[allow(dead_code)]
+    pub fn get(&self, labels: &[&str]) -> u64 {
+        let key: Vec<String> = labels.iter().map(|l| l.to_string()).collect();
+        self.0.get(&key).map(|v| *v).unwrap_or(0)
+    }
+
+    /// All samples, sorted by label values.
+    pub fn samples(&self) -> Vec<(Vec<String>, u64)> {
+        let samples: BTreeMap<Vec<String>, u64> = self
+            .0
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect();
+        samples.into_iter().collect()
+    }
+}
+
+/// Counters for events on the client and federation paths.
+#[derive(Debug, Default)]
+pub struct RelayMetrics {
+    /// Client sessions that registered
+    pub connections: Counter,
+    /// Messages by how they were routed: `local`, `federation`, `unreachable`
+    pub messages_routed: CounterVec,
+    /// Messages stored in the offline queue
+    pub offline_queued: Counter,
+    /// Offline messages not stored or removed unread, by reason:
+    /// `queue_full`, `error`, `expired`, `purged`
+    pub offline_dropped: CounterVec,
+    /// Signaling sessions created on this relay
+    pub sessions_created: Counter,
+    /// Call rooms created on this relay
+    pub call_rooms_created: Counter,
+    /// Client sessions closed by an admin
+    pub evictions: Counter,
+    /// Sealed-sender messages refused for an unregistered access key
+    pub sealed_denied: Counter,
+}
+
+// ── Text Format ───────────────────────────────────────────────────────────────
+
+/// Builds a Prometheus text exposition.
+#[derive(Default)]
+pub struct MetricsWriter {
+    out: String,
+}
+
+impl MetricsWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write an unlabelled counter. `name` should end in `_total`.
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.family(name, "counter", help, &[], [(Vec::new(), value)]);
+    }
+
+    /// Write an unlabelled gauge.
+    pub fn gauge(&mut self, name: &str, help: &str, value: u64) {
+        self.family(name, "gauge", help, &[], [(Vec::new(), value)]);
+    }
+
+    /// Write a metric family with one sample per set of label values.
+    pub fn family(
+        &mut self,
+        name: &str,
+        kind: &str,
+        help: &str,
+        label_names: &[&str],
+        samples: impl IntoIterator<Item = (Vec<String>, u64)>,
+    ) {
+        let _ = writeln!(self.out, "# HELP {PREFIX}{name} {help}");
+        let _ = writeln!(self.out, "# TYPE {PREFIX}{name} {kind}");
+        for (values, value) in samples {
+            let labels: Vec<String> = label_names
+                .iter()
+                .zip(&values)
+                .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+                .collect();
+            if labels.is_empty() {
+                let _ = writeln!(self.out, "{PREFIX}{name} {value}");
+            } else {
+                let _ = writeln!(self.out, "{PREFIX}{name}{{{}}} {value}", labels.join(","));
+            }
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+/// Escape a label value for the text format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// ── Endpoint ──────────────────────────────────────────────────────────────────
+
+/// Everything `/metrics` reads from.
+#[derive(Clone)]
+pub struct MetricsSources {
+    pub relay: RelayState,
+    pub discovery: DiscoveryStore,
+    pub bridges: BridgeStore,
+    pub sync: Arc<SyncBlobStore>,
+    pub assets: AssetStore,
+}
+
+impl MetricsSources {
+    /// Render all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let relay = &self.relay;
+        let counters = &relay.metrics;
+        let mut w = MetricsWriter::new();
+
+        // Connections
+        w.gauge(
+            "online_clients",
+            "DIDs with at least one session on this relay.",
+            relay.online_count() as u64,
+        );
+        w.gauge(
+            "client_sessions",
+            "Client sessions (connections) on this relay.",
+            relay.session_count() as u64,
+        );
+        w.counter(
+            "client_connections_total",
+            "Client sessions that registered.",
+            counters.connections.get(),
+        );
+        w.counter(
+            "client_evictions_total",
+            "Client sessions closed through the admin API.",
+            counters.evictions.get(),
+        );
+        w.gauge(
+            "mesh_online_clients",
+            "DIDs reachable through this relay, including federated relays.",
+            relay.mesh_online_count() as u64,
+        );
+        w.family(
+            "rate_limited_total",
+            "counter",
+            "Requests and messages refused by the rate limiter, by class.",
+            &["class"],
+            relay.rate_limiter.rejected().samples(),
+        );
+        w.gauge(
+            "federation_peers",
+            "Connected federation peers.",
+            relay.connected_peers() as u64,
+        );
+
+        // Routing
+        w.family(
+            "messages_routed_total",
+            "counter",
+            "Client messages by route: delivered locally, forwarded through federation, or unreachable.",
+            &["route"],
+            counters.messages_routed.samples(),
+        );
+        w.counter(
+            "sealed_access_denied_total",
+            "Sealed-sender messages refused because the access key was not registered.",
+            counters.sealed_denied.get(),
+        );
+
+        // Offline queue
+        w.gauge(
+            "offline_queue_depth",
+            "Messages waiting in the offline queue.",
+            relay.offline_queue_size() as u64,
+        );
+        w.counter(
+            "offline_messages_queued_total",
+            "Messages stored in the offline queue.",
+            counters.offline_queued.get(),
+        );
+        w.family(
+            "offline_messages_dropped_total",
+            "counter",
+            "Offline messages rejected or removed before delivery, by reason.",
+            &["reason"],
+            counters.offline_dropped.samples(),
+        );
+
+        // Push wake-ups
+        if let Some(push) = &relay.push {
+            w.gauge(
+                "push_registrations",
+                "DIDs with a registered push endpoint.",
+                push.registration_count() as u64,
+            );
+            w.family(
+                "push_wakeups_total",
+                "counter",
+                "Push wake-ups by outcome: sent, coalesced, deferred (quiet hours), online, failed, or gone.",
+                &["outcome"],
+                push.outcomes().samples(),
+            );
+        }
+
+        // Signaling and calls
+        w.gauge(
+            "signaling_sessions",
+            "Open signaling sessions.",
+            relay.sessions.len() as u64,
+        );
+        w.counter(
+            "signaling_sessions_created_total",
+            "Signaling sessions created on this relay.",
+            counters.sessions_created.get(),
+        );
+        w.gauge(
+            "call_rooms",
+            "Active call rooms.",
+            relay.call_rooms.len() as u64,
+        );
+        w.gauge(
+            "call_participants",
+            "Participants across all call rooms.",
+            relay.call_participant_count() as u64,
+        );
+        w.counter(
+            "call_rooms_created_total",
+            "Call rooms created on this relay.",
+            counters.call_rooms_created.get(),
+        );
+        if let Some(ice) = &relay.ice {
+            w.gauge(
+                "turn_clients",
+                "Client addresses authenticated with TURN credentials.",
+                ice.client_count() as u64,
+            );
+            w.family(
+                "turn_relayed_bytes_total",
+                "counter",
+                "Bytes through the TURN server from (in) and to (out) authenticated clients.",
+                &["direction"],
+                ice.relayed().samples(),
+            );
+        }
+
+        // Storage
+        let (blobs, blob_bytes) = self.sync.usage();
+        w.gauge("sync_blobs", "Stored account sync blobs.", blobs);
+        w.gauge(
+            "sync_blob_bytes",
+            "Total size of stored account sync blobs.",
+            blob_bytes,
+        );
+        w.family(
+            "asset_storage_bytes",
+            "gauge",
+            "Community asset storage used, per community.",
+            &["community_id"],
+            self.assets
+                .storage_by_community()
+                .into_iter()
+                .map(|(community_id, bytes)| (vec![community_id], bytes)),
+        );
+        w.gauge(
+            "bridges",
+            "Registered bridges.",
+            self.bridges.count() as u64,
+        );
+
+        // Discovery
+        w.gauge(
+            "discovery_users",
+            "DIDs with linked accounts.",
+            self.discovery.user_count() as u64,
+        );
+        w.family(
+            "oauth_flows_total",
+            "counter",
+            "OAuth flows by platform, flow and outcome (started, succeeded, failed, expired, invalid_state).",
+            &["platform", "flow", "outcome"],
+            self.discovery.oauth_outcomes().samples(),
+        );
+
+        w.finish()
+    }
+}
+
+/// Prometheus scrape endpoint.
+pub async fn metrics_handler(State(sources): State<MetricsSources>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], sources.render())
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_format() {
+        let routed = CounterVec::default();
+        routed.inc(&["local"]);
+        routed.add(&["federation"], 2);
+        routed.inc(&["local"]);
+        assert_eq!(routed.get(&["local"]), 2);
+
+        let mut w = MetricsWriter::new();
+        w.gauge("online_clients", "Online.", 3);
+        w.family(
+            "messages_routed_total",
+            "counter",
+            "Routed.",
+            &["route"],
+            routed.samples(),
+        );
+        w.family(
+            "asset_storage_bytes",
+            "gauge",
+            "Assets.",
+            &["community_id"],
+            [(vec!["a\"b\\c".to_string()], 10)],
+        );
+
+        assert_eq!(
+            w.finish(),
+            "# HELP umbra_relay_online_clients Online.\n\
+             # TYPE umbra_relay_online_clients gauge\n\
+             umbra_relay_online_clients 3\n\
+             # HELP umbra_relay_messages_routed_total Routed.\n\
+             # TYPE umbra_relay_messages_routed_total counter\n\
+             umbra_relay_messages_routed_total{route=\"federation\"} 2\n\
+             umbra_relay_messages_routed_total{route=\"local\"} 2\n\
+             # HELP umbra_relay_asset_storage_bytes Assets.\n\
+             # TYPE umbra_relay_asset_storage_bytes gauge\n\
+             umbra_relay_asset_storage_bytes{community_id=\"a\\\"b\\\\c\"} 10\n"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! SQLite-backed offline message queue.
+//!
+//! Messages for DIDs that aren't reachable are persisted here so they
+//! survive relay restarts. Delivery is at-least-once: `pending` returns
+//! queued messages without removing them, and a message is only deleted
+//! once the recipient acknowledges it (or its TTL runs out).
+//!
+//! Like the rest of the relay, payloads are opaque encrypted blobs.
+//!
+//! The same database holds the sealed-sender access hashes each DID has
+//! registered, since they decide what may be queued for it anonymously.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use crate::protocol::OfflineMessage;
+
+pub struct OfflineStore {
+    conn: Mutex<Connection>,
+}
+
+impl OfflineStore {
+    /// Create a new store. If `data_dir` is provided, uses a file-backed DB;
+    /// otherwise uses in-memory SQLite.
+    pub fn new(data_dir: Option<&str>) -> Result<Self, rusqlite::Error> {
+        let conn = if let Some(dir) = data_dir {
+            let path = Path::new(dir).join("offline_queue.db");
+            Connection::open(path)?
+        } else {
+            Connection::open_in_memory()?
+        };
+
+        let store = Self {
+            conn: Mutex::new(conn),
+        };
+
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn init_schema(&self) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS offline_messages (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                id TEXT NOT NULL UNIQUE,
+                to_did TEXT NOT NULL,
+                from_did TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                queued_at INTEGER NOT NULL,
+                expires_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_offline_messages_to_did
+                ON offline_messages(to_did, seq);
+            CREATE INDEX IF NOT EXISTS idx_offline_messages_expires_at
+                ON offline_messages(expires_at);
+
+            CREATE TABLE IF NOT EXISTS sealed_access (
+                did TEXT NOT NULL,
+                access_hash TEXT NOT NULL,
+                PRIMARY KEY (did, access_hash)
+            );
+            ",
+        )?;
+
+        Ok(())
+    }
+
+    /// Queue a message for `to_did`, expiring `ttl_secs` from now.
+    ///
+    /// Returns `Ok(false)` without storing anything if the recipient already
+    /// has `max_per_did` unexpired messages waiting.
+    pub fn enqueue(
+        &self,
+        to_did: &str,
+        message: &OfflineMessage,
+        ttl_secs: i64,
+        max_per_did: usize,
+    ) -> Result<bool, String> {
+        let queued_at = message.queued_at.timestamp_millis();
+        let expires_at = queued_at + ttl_secs * 1000;
+
+        // Count and insert under the same lock so concurrent senders
+        // can't overshoot the quota.
+        let conn = self.conn.lock().unwrap();
+
+        let queued: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM offline_messages WHERE to_did = ?1 AND expires_at > ?2",
+                params![to_did, Utc::now().timestamp_millis()],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to count offline messages: {}", e))?;
+
+        if queued as usize >= max_per_did {
+            return Ok(false);
+        }
+
+        conn.execute(
+            "INSERT INTO offline_messages
+                (id, to_did, from_did, payload, timestamp, queued_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                message.id,
+                to_did,
+                message.from_did,
+                message.payload,
+                message.timestamp,
+                queued_at,
+                expires_at
+            ],
+        )
+        .map_err(|e| format!("Failed to queue offline message: {}", e))?;
+
+        Ok(true)
+    }
+
+    /// All unexpired messages waiting for a DID, oldest first.
+    /// Messages stay queued until acknowledged with `ack`.
+    pub fn pending(&self, to_did: &str) -> Result<Vec<OfflineMessage>, String> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().timestamp_millis();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, from_did, payload, timestamp, queued_at FROM offline_messages
+                 WHERE to_did = ?1 AND expires_at > ?2
+                 ORDER BY seq",
+            )
+            .map_err(|e| format!("Failed to prepare offline query: {}", e))?;
+
+        let rows = stmt
+            .query_map(params![to_did, now], |row| {
+                Ok(OfflineMessage {
+                    id: row.get(0)?,
+                    from_did: row.get(1)?,
+                    payload: row.get(2)?,
+                    timestamp: row.get(3)?,
+                    queued_at: DateTime::from_timestamp_millis(row.get(4)?)
+                        .unwrap_or_else(Utc::now),
+                })
+            })
+            .map_err(|e| format!("Failed to load offline messages: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read offline message: {}", e))
+    }
+
+    /// Delete acknowledged messages. Only messages addressed to `to_did`
+    /// are removed, so a client can't ack away someone else's queue.
+    /// Returns the number of messages deleted.
+    pub fn ack(&self, to_did: &str, ids: &[String]) -> Result<usize, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let mut deleted = 0;
+        for id in ids {
+            deleted += tx
+                .execute(
+                    "DELETE FROM offline_messages WHERE to_did = ?1 AND id = ?2",
+                    params![to_did, id],
+                )
+                .map_err(|e| format!("Failed to ack offline message: {}", e))?;
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit acks: {}", e))?;
+        Ok(deleted)
+    }
+
+    /// Total number of queued messages across all DIDs.
+    pub fn count(&self) -> usize {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM offline_messages", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .map(|n| n as usize)
+        .unwrap_or(0)
+    }
+
+    /// Remove every queued message for a DID. Returns the number removed.
+    pub fn purge(&self, to_did: &str) -> Result<usize, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM offline_messages WHERE to_did = ?1",
+            params![to_did],
+        )
+        .map_err(|e| format!("Failed to purge offline messages: {}", e))
+    }
+
+    /// Replace the sealed-sender access hashes registered for a DID.
+    /// Returns the number stored (duplicates collapse).
+    pub fn set_sealed_access(&self, did: &str, access_hashes: &[String]) -> Result<usize, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        tx.execute("DELETE FROM sealed_access WHERE did = ?1", params![did])
+            .map_err(|e| format!("Failed to clear sealed access: {}", e))?;
+
+        let mut stored = 0;
+        for hash in access_hashes {
+            stored += tx
+                .execute(
+                    "INSERT OR IGNORE INTO sealed_access (did, access_hash) VALUES (?1, ?2)",
+                    params![did, hash],
+                )
+                .map_err(|e| format!("Failed to store sealed access: {}", e))?;
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit sealed access: {}", e))?;
+        Ok(stored)
+    }
+
+    /// Whether `did` has registered any access hashes.
+    pub fn has_sealed_registration(&self, did: &str) -> bool {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT 1 FROM sealed_access WHERE did = ?1 LIMIT 1",
+            params![did],
+            |_| Ok(()),
+        )
+        .is_ok()
+    }
+
+    /// Whether `did` has registered `access_hash` for sealed delivery.
+    pub fn has_sealed_access(&self, did: &str, access_hash: &str) -> bool {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT 1 FROM sealed_access WHERE did = ?1 AND access_hash = ?2",
+            params![did, access_hash],
+            |_| Ok(()),
+        )
+        .is_ok()
+    }
+
+    /// Remove messages whose TTL has run out. Returns the number removed.
+    pub fn cleanup_expired(&self) -> usize {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM offline_messages WHERE expires_at <= ?1",
+            params![Utc::now().timestamp_millis()],
+        )
+        .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn message(payload: &str) -> OfflineMessage {
+        OfflineMessage {
+            id: Uuid::new_v4().to_string(),
+            from_did: "did:key:z6MkAlice".to_string(),
+            payload: payload.to_string(),
+            timestamp: 1000,
+            queued_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_pending_does_not_remove_until_acked() {
+        let store = OfflineStore::new(None).unwrap();
+        let first = message("hello");
+        let second = message("world");
+
+        assert!(store.enqueue("did:key:z6MkBob", &first, 300, 10).unwrap());
+        assert!(store.enqueue("did:key:z6MkBob", &second, 300, 10).unwrap());
+
+        let pending = store.pending("did:key:z6MkBob").unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].payload, "hello");
+        assert_eq!(pending[1].payload, "world");
+
+        // Fetching again redelivers the same messages
+        assert_eq!(store.pending("did:key:z6MkBob").unwrap().len(), 2);
+
+        assert_eq!(store.ack("did:key:z6MkBob", &[first.id]).unwrap(), 1);
+        let pending = store.pending("did:key:z6MkBob").unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, second.id);
+    }
+
+    #[test]
+    fn test_ack_is_scoped_to_recipient() {
+        let store = OfflineStore::new(None).unwrap();
+        let msg = message("for bob");
+        store.enqueue("did:key:z6MkBob", &msg, 300, 10).unwrap();
+
+        assert_eq!(store.ack("did:key:z6MkEve", &[msg.id]).unwrap(), 0);
+        assert_eq!(store.count(), 1);
+    }
+
+    #[test]
+    fn test_quota_rejects_when_full() {
+        let store = OfflineStore::new(None).unwrap();
+
+        assert!(store
+            .enqueue("did:key:z6MkBob", &message("a"), 300, 2)
+            .unwrap());
+        assert!(store
+            .enqueue("did:key:z6MkBob", &message("b"), 300, 2)
+            .unwrap());
+        assert!(!store
+            .enqueue("did:key:z6MkBob", &message("c"), 300, 2)
+            .unwrap());
+
+        // Other recipients have their own quota
+        assert!(store
+            .enqueue("did:key:z6MkCarol", &message("d"), 300, 2)
+            .unwrap());
+        assert_eq!(store.count(), 3);
+    }
+
+    #[test]
+    fn test_expired_messages_are_hidden_and_cleaned() {
+        let store = OfflineStore::new(None).unwrap();
+        store
+            .enqueue("did:key:z6MkBob", &message("stale"), -1, 10)
+            .unwrap();
+
+        assert!(store.pending("did:key:z6MkBob").unwrap().is_empty());
+        assert_eq!(store.cleanup_expired(), 1);
+        assert_eq!(store.count(), 0);
+    }
+
+    #[test]
+    fn test_sealed_access_is_replaced() {
+        let store = OfflineStore::new(None).unwrap();
+        let hashes = vec!["aa".to_string(), "bb".to_string(), "aa".to_string()];
+
+        assert_eq!(
+            store.set_sealed_access("did:key:z6MkBob", &hashes).unwrap(),
+            2
+        );
+        assert!(store.has_sealed_access("did:key:z6MkBob", "aa"));
+        assert!(!store.has_sealed_access("did:key:z6MkCarol", "aa"));
+        assert!(!store.has_sealed_registration("did:key:z6MkCarol"));
+
+        // Registering again replaces the whole set
+        store
+            .set_sealed_access("did:key:z6MkBob", &["cc".to_string()])
+            .unwrap();
+        assert!(!store.has_sealed_access("did:key:z6MkBob", "aa"));
+        assert!(store.has_sealed_access("did:key:z6MkBob", "cc"));
+
+        // An empty set opts out of sealed delivery
+        store.set_sealed_access("did:key:z6MkBob", &[]).unwrap();
+        assert!(!store.has_sealed_registration("did:key:z6MkBob"));
+    }
+
+    #[test]
+    fn test_messages_survive_reopen() {
+        let dir = std::env::temp_dir().join(format!("umbra-offline-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        {
+            let store = OfflineStore::new(Some(dir_str)).unwrap();
+            store
+                .enqueue("did:key:z6MkBob", &message("durable"), 300, 10)
+                .unwrap();
+        }
+
+        let store = OfflineStore::new(Some(dir_str)).unwrap();
+        let pending = store.pending("did:key:z6MkBob").unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].payload, "durable");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
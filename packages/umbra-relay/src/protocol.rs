@@ -6,23 +6,74 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::push::{PushKind, QuietHours};
+
 // ── Client → Relay ────────────────────────────────────────────────────────────
 
+/// Domain-separation prefix for registration challenges.
+///
+/// Clients prove ownership of their DID by signing `AUTH_CHALLENGE_PREFIX`
+/// followed by the nonce from `AuthChallenge`, using the Ed25519 key
+/// embedded in their `did:key`. The prefix keeps these signatures from
+/// being replayed against other challenge flows (e.g. sync auth).
+pub const AUTH_CHALLENGE_PREFIX: &str = "umbra-relay-auth-v1:";
+
+/// Domain-separation prefix for published invite signatures.
+///
+/// Community owners sign `INVITE_SIGNATURE_PREFIX` followed by
+/// [`PublishedInvite::signing_message`] with the key in their `did:key`.
+pub const INVITE_SIGNATURE_PREFIX: &str = "umbra-relay-invite-v1:";
+
 /// Messages sent from a client to the relay server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClientMessage {
     /// Register this WebSocket connection with a DID.
-    /// Must be sent first after connecting.
+    /// Must be sent first after connecting. The relay answers with an
+    /// `AuthChallenge` that must be signed before the session is usable.
     Register { did: String },
 
+    /// Answer an `AuthChallenge`.
+    /// `signature` is the base64 Ed25519 signature over
+    /// `AUTH_CHALLENGE_PREFIX + nonce`.
+    Authenticate { signature: String },
+
     /// Forward a signaling payload (SDP offer/answer) to another peer.
     Signal { to_did: String, payload: String },
 
     /// Send an encrypted message to another peer.
-    /// If the peer is offline, the relay queues it for later delivery.
+    /// If the peer is offline, the relay queues it for later delivery
+    /// (or answers `QueueFull` if the peer's queue is at its quota).
     Send { to_did: String, payload: String },
 
+    /// Send a sealed-sender message (see `sealed_sender`).
+    /// Accepted before authentication, so it can be sent on a connection
+    /// that never reveals the sender's DID. `access_key` is the hex
+    /// delivery credential the sender shares with the recipient.
+    SendSealed {
+        to_did: String,
+        access_key: String,
+        payload: String,
+    },
+
+    /// Replace the sealed-sender access hashes registered for this DID
+    /// (hex SHA-256 of each friend's access key).
+    RegisterSealedAccess { access_hashes: Vec<String> },
+
+    /// Register (or replace) this DID's push endpoint. While the DID is
+    /// offline, queued messages trigger a content-free wake-up to it
+    /// (see `push`).
+    RegisterPush {
+        kind: PushKind,
+        endpoint: String,
+        /// Daily window in which wake-ups are held back
+        #[serde(default)]
+        quiet_hours: Option<QuietHours>,
+    },
+
+    /// Remove this DID's push endpoint.
+    UnregisterPush,
+
     /// Create a signaling session for single-scan friend adding.
     /// Returns a session_id that can be shared via QR code/link.
     CreateSession {
@@ -39,8 +90,12 @@ pub enum ClientMessage {
     },
 
     /// Fetch all queued offline messages.
+    /// Messages are redelivered on every fetch until acknowledged.
     FetchOffline,
 
+    /// Acknowledge offline messages by ID so the relay can delete them.
+    AckOffline { ids: Vec<String> },
+
     /// Ping to keep connection alive.
     Ping,
 
@@ -61,6 +116,9 @@ pub enum ClientMessage {
         /// The full invite data blob (encrypted community structure) that
         /// the joiner needs to bootstrap their local DB
         invite_payload: String,
+        /// Base64 Ed25519 signature by the publisher over the invite
+        /// (see `PublishedInvite::signing_message`)
+        signature: String,
     },
 
     /// Revoke a previously published invite.
@@ -71,7 +129,12 @@ pub enum ClientMessage {
 
     /// Create a call room for group calling.
     /// Returns a room_id that participants can join.
-    CreateCallRoom { group_id: String },
+    CreateCallRoom {
+        group_id: String,
+        /// How media flows in the room (mesh if omitted)
+        #[serde(default)]
+        mode: CallRoomMode,
+    },
 
     /// Join an existing call room.
     JoinCallRoom { room_id: String },
@@ -87,6 +150,16 @@ pub enum ClientMessage {
         payload: String,
     },
 
+    /// SDP offer for the SFU of an SFU-mode call room (first connection or
+    /// a renegotiation after publishing changes).
+    SfuOffer { room_id: String, sdp: String },
+
+    /// Answer to an `SfuOffer` the SFU sent.
+    SfuAnswer { room_id: String, sdp: String },
+
+    /// ICE candidate for the SFU (`RTCIceCandidateInit` JSON).
+    SfuCandidate { room_id: String, candidate: String },
+
     /// Push a sync delta to all other sessions of the same DID.
     /// Used for real-time preference/friend/group sync between devices.
     SyncPush {
@@ -107,8 +180,19 @@ pub enum ClientMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ServerMessage {
+    /// Challenge issued in response to `Register`.
+    /// The client must reply with `Authenticate`.
+    AuthChallenge { nonce: String },
+
     /// Acknowledgement of successful registration.
-    Registered { did: String },
+    /// Sent once the client has answered the challenge.
+    Registered {
+        did: String,
+        /// STUN/TURN servers for WebRTC calls, with TURN credentials minted
+        /// for this DID. Empty if the relay doesn't run a TURN server.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        ice_servers: Vec<IceServer>,
+    },
 
     /// A signaling payload forwarded from another peer.
     Signal { from_did: String, payload: String },
@@ -120,6 +204,13 @@ pub enum ServerMessage {
         timestamp: i64,
     },
 
+    /// A sealed-sender message. The sender is only known to the recipient,
+    /// from inside the decrypted payload.
+    SealedMessage { payload: String, timestamp: i64 },
+
+    /// Response to RegisterSealedAccess — how many access hashes are stored.
+    SealedAccessRegistered { count: usize },
+
     /// Response to CreateSession — contains the session ID to share.
     SessionCreated { session_id: String },
 
@@ -138,13 +229,27 @@ pub enum ServerMessage {
     },
 
     /// All queued offline messages, delivered in response to FetchOffline.
+    /// The client must `AckOffline` them once they're safely stored.
     OfflineMessages { messages: Vec<OfflineMessage> },
 
+    /// The recipient's offline queue is full, so the message was NOT queued.
+    /// Sent in place of `Ack`.
+    QueueFull { to_did: String },
+
     /// Pong response to keep connection alive.
     Pong,
 
     /// Error response.
-    Error { message: String },
+    Error {
+        message: String,
+        /// Machine-readable error code (e.g. `rate_limited`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<String>,
+        /// How long to wait before retrying, when the request was refused
+        /// for now rather than for good.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after_ms: Option<u64>,
+    },
 
     /// Generic acknowledgement.
     Ack { id: String },
@@ -167,7 +272,11 @@ pub enum ServerMessage {
     InviteNotFound { code: String },
 
     /// A call room was successfully created.
-    CallRoomCreated { room_id: String, group_id: String },
+    CallRoomCreated {
+        room_id: String,
+        group_id: String,
+        mode: CallRoomMode,
+    },
 
     /// A participant joined the call room.
     CallParticipantJoined { room_id: String, did: String },
@@ -182,6 +291,18 @@ pub enum ServerMessage {
         payload: String,
     },
 
+    /// An SDP offer from the SFU (tracks to receive were added or removed).
+    SfuOffer { room_id: String, sdp: String },
+
+    /// The SFU's answer to the client's `SfuOffer`.
+    SfuAnswer { room_id: String, sdp: String },
+
+    /// An ICE candidate of the SFU (`RTCIceCandidateInit` JSON).
+    SfuCandidate { room_id: String, candidate: String },
+
+    /// The dominant speaker of an SFU call room changed.
+    CallDominantSpeaker { room_id: String, did: String },
+
     /// A sync delta pushed from another device using the same account.
     SyncUpdate {
         section: String,
@@ -195,8 +316,52 @@ pub enum ServerMessage {
     },
 }
 
+impl ServerMessage {
+    /// A plain error response.
+    pub fn error(message: impl Into<String>) -> Self {
+        ServerMessage::Error {
+            message: message.into(),
+            code: None,
+            retry_after_ms: None,
+        }
+    }
+
+    /// Error response for a message refused by the rate limiter.
+    pub fn rate_limited(class: &str, retry_after: std::time::Duration) -> Self {
+        ServerMessage::Error {
+            message: format!("Rate limit exceeded for {}", class),
+            code: Some("rate_limited".to_string()),
+            retry_after_ms: Some(retry_after.as_millis() as u64),
+        }
+    }
+
+    /// Error response for a sealed send whose access key was refused.
+    pub fn sealed_access_denied(to_did: &str) -> Self {
+        ServerMessage::Error {
+            message: format!("Sealed delivery to {} not authorized", to_did),
+            code: Some(crate::sealed_sender::ACCESS_DENIED_CODE.to_string()),
+            retry_after_ms: None,
+        }
+    }
+
+    /// Error response for a `PublishInvite` whose signature didn't verify.
+    pub fn invalid_invite_signature(code: &str) -> Self {
+        ServerMessage::Error {
+            message: format!("Invite {} is not signed by its publisher", code),
+            code: Some("invalid_invite_signature".to_string()),
+            retry_after_ms: None,
+        }
+    }
+}
+
 // ── Relay ↔ Relay (Federation) ────────────────────────────────────────────────
 
+/// Domain-separation prefix for federation `Hello` signatures.
+pub const PEER_HELLO_PREFIX: &str = "umbra-relay-hello-v1:";
+
+/// Domain-separation prefix for `PeerMessage::Signed` signatures.
+pub const PEER_MESSAGE_PREFIX: &str = "umbra-relay-peer-message-v1:";
+
 /// Messages exchanged between federated relay peers.
 ///
 /// Relays form a mesh network: each relay connects to its configured peers
@@ -205,7 +370,11 @@ pub enum ServerMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum PeerMessage {
-    /// Announce this relay's identity to a peer on first connect.
+    /// Open the federation handshake. Both sides send one on connect,
+    /// and each answers the other's nonce with a signed `Hello`.
+    Challenge { nonce: String },
+
+    /// Announce this relay's identity in answer to a peer's `Challenge`.
     Hello {
         /// Unique relay ID (stable across restarts)
         relay_id: String,
@@ -215,6 +384,23 @@ pub enum PeerMessage {
         region: String,
         /// Human-readable location
         location: String,
+        /// The relay's Ed25519 identity key (hex)
+        public_key: String,
+        /// Signature over `PEER_HELLO_PREFIX`, the peer's nonce and the
+        /// fields above (hex)
+        signature: String,
+    },
+
+    /// A peer message authenticated by the relay it originated from.
+    /// `ForwardMessage`, `ForwardSealed`, `ForwardOffline` and `InviteSync`
+    /// are only accepted in this wrapper.
+    Signed {
+        /// The originating relay's identity key (hex)
+        public_key: String,
+        /// The wrapped `PeerMessage`, as JSON
+        message: String,
+        /// Signature over `PEER_MESSAGE_PREFIX` followed by `message` (hex)
+        signature: String,
     },
 
     /// Broadcast the full set of DIDs currently online at the sending relay.
@@ -261,6 +447,15 @@ pub enum PeerMessage {
         created_at: i64,
     },
 
+    /// Forward a sealed-sender message to the relay the recipient is
+    /// connected to, which checks `access_key` against its registration.
+    ForwardSealed {
+        to_did: String,
+        access_key: String,
+        payload: String,
+        timestamp: i64,
+    },
+
     /// Queue an offline message on the relay that owns the recipient DID.
     ForwardOffline {
         to_did: String,
@@ -282,6 +477,8 @@ pub enum PeerMessage {
         expires_at: Option<i64>,
         invite_payload: String,
         published_at: i64,
+        /// The publisher's signature, checked again by every relay
+        signature: String,
     },
 
     /// Revoke a published invite across the federation mesh.
@@ -304,6 +501,30 @@ pub enum PeerMessage {
         invite_payload: String,
     },
 
+    /// Share the relays this relay knows about (itself and its direct
+    /// peers) so the mesh can discover new members.
+    PeerExchange { relays: Vec<KnownRelay> },
+
+    /// Carry a message across more than one hop.
+    ///
+    /// With no `destination`, the message is flooded (presence); otherwise
+    /// it is passed hop by hop towards the relay with that ID. Relays drop
+    /// envelopes they have already seen, that came from themselves, or
+    /// whose `ttl` runs out.
+    Routed {
+        /// Unique ID for loop prevention
+        message_id: String,
+        /// The relay that sent the envelope first
+        origin_relay_id: String,
+        /// Target relay ID, or `None` to flood
+        destination: Option<String>,
+        /// Hops the envelope may still take
+        ttl: u8,
+        /// Hops the envelope has taken so far
+        hops: u8,
+        message: Box<PeerMessage>,
+    },
+
     /// Ping to keep inter-relay connection alive.
     PeerPing,
 
@@ -311,18 +532,92 @@ pub enum PeerMessage {
     PeerPong,
 }
 
+impl PeerMessage {
+    /// Whether peers only accept this message inside `PeerMessage::Signed`.
+    pub fn requires_signature(&self) -> bool {
+        matches!(
+            self,
+            PeerMessage::ForwardMessage { .. }
+                | PeerMessage::ForwardSealed { .. }
+                | PeerMessage::ForwardOffline { .. }
+                | PeerMessage::InviteSync { .. }
+        )
+    }
+}
+
 // ── Supporting Types ──────────────────────────────────────────────────────────
 
+/// A federation member, as shared through `PeerMessage::PeerExchange`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnownRelay {
+    pub relay_id: String,
+    /// Public WebSocket URL
+    pub relay_url: String,
+    pub region: String,
+    pub location: String,
+    /// Ed25519 identity key (hex)
+    pub public_key: String,
+    /// Load as last advertised by the relay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load: Option<RelayLoad>,
+}
+
+/// How busy a relay is, shared with peers and clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayLoad {
+    /// Clients connected to the relay
+    pub online_count: usize,
+    /// Messages waiting in the offline queue
+    pub offline_queue_size: usize,
+}
+
+/// A relay listed by the `GET /relays` directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayDirectoryEntry {
+    /// Relay ID (`None` for a relay that is not federated)
+    pub relay_id: Option<String>,
+    /// Public WebSocket URL (`None` means the relay serving the directory)
+    pub relay_url: Option<String>,
+    pub region: String,
+    pub location: String,
+    /// Current load, if known
+    pub load: Option<RelayLoad>,
+}
+
+/// A STUN/TURN server, in the shape of WebRTC's `RTCIceServer`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IceServer {
+    /// `stun:` and `turn:` URLs
+    pub urls: Vec<String>,
+    /// TURN username (`<expiry unix time>:<did>`)
+    pub username: String,
+    /// TURN password
+    pub credential: String,
+}
+
 /// A message that was queued while the recipient was offline.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OfflineMessage {
     pub id: String,
+    /// Empty for sealed-sender messages
     pub from_did: String,
     pub payload: String,
     pub timestamp: i64,
     pub queued_at: DateTime<Utc>,
 }
 
+/// How media flows in a call room.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallRoomMode {
+    /// Participants connect to each other directly (`CallSignal`).
+    #[default]
+    Mesh,
+    /// Participants connect to the relay's SFU (`SfuOffer`), which forwards
+    /// their end-to-end encrypted media to the rest of the room.
+    Sfu,
+}
+
 /// A call room for group calling.
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -337,6 +632,8 @@ pub struct CallRoom {
     pub participants: Vec<String>,
     /// Maximum number of participants allowed
     pub max_participants: usize,
+    /// Whether media goes peer-to-peer or through the SFU
+    pub mode: CallRoomMode,
     /// When the room was created
     pub created_at: DateTime<Utc>,
 }
@@ -362,6 +659,56 @@ pub struct PublishedInvite {
     pub invite_payload: String,
     /// When the invite was published to the relay
     pub published_at: DateTime<Utc>,
+    /// Base64 Ed25519 signature by `publisher_did` over `signing_message()`
+    pub signature: String,
+}
+
+/// The invite fields covered by the publisher's signature. `use_count` and
+/// `published_at` are kept by the relays, so they aren't signed.
+#[derive(Serialize)]
+struct SignedInviteFields<'a> {
+    code: &'a str,
+    publisher_did: &'a str,
+    community_id: &'a str,
+    community_name: &'a str,
+    community_description: Option<&'a str>,
+    community_icon: Option<&'a str>,
+    member_count: u32,
+    max_uses: Option<i32>,
+    expires_at: Option<i64>,
+    invite_payload: &'a str,
+}
+
+impl PublishedInvite {
+    /// The string the publisher signs: `INVITE_SIGNATURE_PREFIX` followed
+    /// by the signed fields as compact JSON, in declaration order.
+    pub fn signing_message(&self) -> String {
+        let fields = SignedInviteFields {
+            code: &self.code,
+            publisher_did: &self.publisher_did,
+            community_id: &self.community_id,
+            community_name: &self.community_name,
+            community_description: self.community_description.as_deref(),
+            community_icon: self.community_icon.as_deref(),
+            member_count: self.member_count,
+            max_uses: self.max_uses,
+            expires_at: self.expires_at,
+            invite_payload: &self.invite_payload,
+        };
+        let json = serde_json::to_string(&fields).unwrap_or_default();
+        format!("{}{}", INVITE_SIGNATURE_PREFIX, json)
+    }
+
+    /// Whether `signature` was made over this invite with the key in
+    /// `publisher_did`.
+    pub fn verify_signature(&self) -> bool {
+        crate::sync::auth::verify_did_signature(
+            &self.publisher_did,
+            &self.signing_message(),
+            &self.signature,
+        )
+        .unwrap_or(false)
+    }
 }
 
 /// A signaling session for single-scan friend adding.
@@ -400,6 +747,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_client_message_authenticate_serialization() {
+        let msg = ClientMessage::Authenticate {
+            signature: "c2lnbmF0dXJl".to_string(),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"authenticate\""));
+
+        let parsed: ClientMessage = serde_json::from_str(&json).unwrap();
+        match parsed {
+            ClientMessage::Authenticate { signature } => assert_eq!(signature, "c2lnbmF0dXJl"),
+            _ => panic!("Wrong variant"),
+        }
+    }
+
     #[test]
     fn test_client_message_signal_serialization() {
         let msg = ClientMessage::Signal {
@@ -464,6 +826,16 @@ mod tests {
         assert!(json.contains("\"type\":\"fetch_offline\""));
     }
 
+    #[test]
+    fn test_client_message_ack_offline_serialization() {
+        let json = r#"{"type":"ack_offline","ids":["msg-1","msg-2"]}"#;
+        let parsed: ClientMessage = serde_json::from_str(json).unwrap();
+        match parsed {
+            ClientMessage::AckOffline { ids } => assert_eq!(ids, vec!["msg-1", "msg-2"]),
+            _ => panic!("Wrong variant"),
+        }
+    }
+
     #[test]
     fn test_client_message_ping_serialization() {
         let msg = ClientMessage::Ping;
@@ -475,9 +847,63 @@ mod tests {
     fn test_server_message_registered_serialization() {
         let msg = ServerMessage::Registered {
             did: "did:key:z6MkAlice".to_string(),
+            ice_servers: Vec::new(),
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("\"type\":\"registered\""));
+        assert!(!json.contains("ice_servers"));
+
+        let msg = ServerMessage::Registered {
+            did: "did:key:z6MkAlice".to_string(),
+            ice_servers: vec![IceServer {
+                urls: vec!["turn:203.0.113.7:3478?transport=udp".to_string()],
+                username: "1900000000:did:key:z6MkAlice".to_string(),
+                credential: "c2VjcmV0".to_string(),
+            }],
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(
+            json.contains("\"ice_servers\":[{\"urls\":[\"turn:203.0.113.7:3478?transport=udp\"]")
+        );
+    }
+
+    #[test]
+    fn test_invite_signing_message() {
+        let invite = PublishedInvite {
+            code: "abc123".to_string(),
+            publisher_did: "did:key:z6MkOwner".to_string(),
+            community_id: "comm-1".to_string(),
+            community_name: "Test".to_string(),
+            community_description: None,
+            community_icon: Some("icon.png".to_string()),
+            member_count: 3,
+            max_uses: Some(10),
+            use_count: 7,
+            expires_at: None,
+            invite_payload: "{}".to_string(),
+            published_at: Utc::now(),
+            signature: String::new(),
+        };
+
+        // Clients build the same string, so its format is part of the protocol
+        assert_eq!(
+            invite.signing_message(),
+            "umbra-relay-invite-v1:{\"code\":\"abc123\",\"publisher_did\":\"did:key:z6MkOwner\",\
+             \"community_id\":\"comm-1\",\"community_name\":\"Test\",\"community_description\":null,\
+             \"community_icon\":\"icon.png\",\"member_count\":3,\"max_uses\":10,\"expires_at\":null,\
+             \"invite_payload\":\"{}\"}"
+        );
+        assert!(!invite.verify_signature());
+    }
+
+    #[test]
+    fn test_server_message_auth_challenge_serialization() {
+        let msg = ServerMessage::AuthChallenge {
+            nonce: "abc123".to_string(),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"auth_challenge\""));
+        assert!(json.contains("abc123"));
     }
 
     #[test]
@@ -507,6 +933,27 @@ mod tests {
         assert!(json.contains("msg-1"));
     }
 
+    #[test]
+    fn test_server_message_queue_full_serialization() {
+        let msg = ServerMessage::QueueFull {
+            to_did: "did:key:z6MkBob".to_string(),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"queue_full\""));
+        assert!(json.contains("did:key:z6MkBob"));
+    }
+
+    #[test]
+    fn test_server_message_sealed_message_serialization() {
+        let msg = ServerMessage::SealedMessage {
+            payload: "sealed".to_string(),
+            timestamp: 1234567890,
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"sealed_message\""));
+        assert!(!json.contains("from_did"));
+    }
+
     #[test]
     fn test_server_message_session_created_serialization() {
         let msg = ServerMessage::SessionCreated {
@@ -519,11 +966,15 @@ mod tests {
 
     #[test]
     fn test_server_message_error_serialization() {
-        let msg = ServerMessage::Error {
-            message: "Something went wrong".to_string(),
-        };
+        let msg = ServerMessage::error("Something went wrong");
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("\"type\":\"error\""));
+        assert!(!json.contains("retry_after_ms"));
+
+        let msg = ServerMessage::rate_limited("send", std::time::Duration::from_millis(1500));
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"code\":\"rate_limited\""));
+        assert!(json.contains("\"retry_after_ms\":1500"));
     }
 
     #[test]
@@ -539,6 +990,9 @@ mod tests {
             ClientMessage::Register {
                 did: "did:key:z6MkTest".to_string(),
             },
+            ClientMessage::Authenticate {
+                signature: "sig".to_string(),
+            },
             ClientMessage::Signal {
                 to_did: "did:key:z6MkBob".to_string(),
                 payload: "offer".to_string(),
@@ -547,6 +1001,24 @@ mod tests {
                 to_did: "did:key:z6MkBob".to_string(),
                 payload: "msg".to_string(),
             },
+            ClientMessage::SendSealed {
+                to_did: "did:key:z6MkBob".to_string(),
+                access_key: "ab".repeat(32),
+                payload: "sealed".to_string(),
+            },
+            ClientMessage::RegisterSealedAccess {
+                access_hashes: vec!["cd".repeat(32)],
+            },
+            ClientMessage::RegisterPush {
+                kind: PushKind::UnifiedPush,
+                endpoint: "https://push.example.com/up/token".to_string(),
+                quiet_hours: Some(QuietHours {
+                    start_minute: 22 * 60,
+                    end_minute: 7 * 60,
+                    utc_offset_minutes: 60,
+                }),
+            },
+            ClientMessage::UnregisterPush,
             ClientMessage::CreateSession {
                 offer_payload: "offer".to_string(),
             },
@@ -555,6 +1027,9 @@ mod tests {
                 answer_payload: "answer".to_string(),
             },
             ClientMessage::FetchOffline,
+            ClientMessage::AckOffline {
+                ids: vec!["msg-1".to_string()],
+            },
             ClientMessage::Ping,
             ClientMessage::PublishInvite {
                 code: "abc123".to_string(),
@@ -566,6 +1041,7 @@ mod tests {
                 max_uses: Some(100),
                 expires_at: None,
                 invite_payload: "{}".to_string(),
+                signature: "c2ln".to_string(),
             },
             ClientMessage::RevokeInvite {
                 code: "abc123".to_string(),
@@ -575,6 +1051,7 @@ mod tests {
             },
             ClientMessage::CreateCallRoom {
                 group_id: "group-1".to_string(),
+                mode: CallRoomMode::Sfu,
             },
             ClientMessage::JoinCallRoom {
                 room_id: "room-1".to_string(),
@@ -587,6 +1064,18 @@ mod tests {
                 to_did: "did:key:z6MkBob".to_string(),
                 payload: "sdp".to_string(),
             },
+            ClientMessage::SfuOffer {
+                room_id: "room-1".to_string(),
+                sdp: "v=0".to_string(),
+            },
+            ClientMessage::SfuAnswer {
+                room_id: "room-1".to_string(),
+                sdp: "v=0".to_string(),
+            },
+            ClientMessage::SfuCandidate {
+                room_id: "room-1".to_string(),
+                candidate: "{}".to_string(),
+            },
         ];
 
         for msg in messages {
@@ -601,6 +1090,7 @@ mod tests {
     fn test_client_message_create_call_room_serialization() {
         let msg = ClientMessage::CreateCallRoom {
             group_id: "group-abc".to_string(),
+            mode: CallRoomMode::Mesh,
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("\"type\":\"create_call_room\""));
@@ -608,9 +1098,30 @@ mod tests {
 
         let parsed: ClientMessage = serde_json::from_str(&json).unwrap();
         match parsed {
-            ClientMessage::CreateCallRoom { group_id } => assert_eq!(group_id, "group-abc"),
+            ClientMessage::CreateCallRoom { group_id, .. } => assert_eq!(group_id, "group-abc"),
             _ => panic!("Wrong variant"),
         }
+
+        let parsed: ClientMessage =
+            serde_json::from_str(r#"{"type":"create_call_room","group_id":"g","mode":"sfu"}"#)
+                .unwrap();
+        assert!(matches!(
+            parsed,
+            ClientMessage::CreateCallRoom {
+                mode: CallRoomMode::Sfu,
+                ..
+            }
+        ));
+        // Older clients don't send a mode
+        let parsed: ClientMessage =
+            serde_json::from_str(r#"{"type":"create_call_room","group_id":"g"}"#).unwrap();
+        assert!(matches!(
+            parsed,
+            ClientMessage::CreateCallRoom {
+                mode: CallRoomMode::Mesh,
+                ..
+            }
+        ));
     }
 
     #[test]
@@ -643,11 +1154,13 @@ mod tests {
         let msg = ServerMessage::CallRoomCreated {
             room_id: "room-123".to_string(),
             group_id: "group-abc".to_string(),
+            mode: CallRoomMode::Sfu,
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("\"type\":\"call_room_created\""));
         assert!(json.contains("room-123"));
         assert!(json.contains("group-abc"));
+        assert!(json.contains("\"mode\":\"sfu\""));
     }
 
     #[test]
@@ -672,6 +1185,26 @@ mod tests {
         assert!(json.contains("from_did"));
     }
 
+    #[test]
+    fn test_server_message_sfu_serialization() {
+        let msg = ServerMessage::SfuOffer {
+            room_id: "room-1".to_string(),
+            sdp: "v=0".to_string(),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"sfu_offer","room_id":"room-1","sdp":"v=0"}"#
+        );
+
+        let msg = ServerMessage::CallDominantSpeaker {
+            room_id: "room-1".to_string(),
+            did: "did:key:z6MkAlice".to_string(),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"call_dominant_speaker\""));
+    }
+
     // ── Peer (Federation) Message Tests ──────────────────────────────────
 
     #[test]
@@ -681,6 +1214,8 @@ mod tests {
             relay_url: "wss://relay.example.com/ws".to_string(),
             region: "US East".to_string(),
             location: "New York".to_string(),
+            public_key: "ab".repeat(32),
+            signature: "cd".repeat(64),
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("\"type\":\"hello\""));
@@ -743,6 +1278,16 @@ mod tests {
                 relay_url: "wss://r1.example.com/ws".to_string(),
                 region: "US".to_string(),
                 location: "NYC".to_string(),
+                public_key: "ab".repeat(32),
+                signature: "cd".repeat(64),
+            },
+            PeerMessage::Challenge {
+                nonce: "00ff".to_string(),
+            },
+            PeerMessage::Signed {
+                public_key: "ab".repeat(32),
+                message: r#"{"type":"peer_ping"}"#.to_string(),
+                signature: "cd".repeat(64),
             },
             PeerMessage::PresenceSync {
                 relay_id: "r1".to_string(),
@@ -778,6 +1323,12 @@ mod tests {
                 offer_payload: "offer".to_string(),
                 created_at: 100,
             },
+            PeerMessage::ForwardSealed {
+                to_did: "did:key:z6MkB".to_string(),
+                access_key: "ab".repeat(32),
+                payload: "sealed".to_string(),
+                timestamp: 100,
+            },
             PeerMessage::ForwardOffline {
                 to_did: "did:key:z6MkB".to_string(),
                 from_did: "did:key:z6MkA".to_string(),
@@ -796,6 +1347,7 @@ mod tests {
                 expires_at: None,
                 invite_payload: "{}".to_string(),
                 published_at: 100,
+                signature: "c2ln".to_string(),
             },
             PeerMessage::InviteRevoke {
                 code: "test123".to_string(),
@@ -816,6 +1368,30 @@ mod tests {
                 expires_at: None,
                 invite_payload: "{}".to_string(),
             },
+            PeerMessage::PeerExchange {
+                relays: vec![KnownRelay {
+                    relay_id: "r2".to_string(),
+                    relay_url: "wss://r2.example.com/ws".to_string(),
+                    region: "EU".to_string(),
+                    location: "Frankfurt".to_string(),
+                    public_key: "ab".repeat(32),
+                    load: Some(RelayLoad {
+                        online_count: 12,
+                        offline_queue_size: 3,
+                    }),
+                }],
+            },
+            PeerMessage::Routed {
+                message_id: "m1".to_string(),
+                origin_relay_id: "r1".to_string(),
+                destination: Some("r3".to_string()),
+                ttl: 5,
+                hops: 1,
+                message: Box::new(PeerMessage::PresenceOnline {
+                    relay_id: "r1".to_string(),
+                    did: "did:key:z6Mk1".to_string(),
+                }),
+            },
             PeerMessage::PeerPing,
             PeerMessage::PeerPong,
         ];
@@ -0,0 +1,482 @@
+//! Push notification gateway for offline recipients.
+//!
+//! When a message is queued for a DID that isn't connected, the relay asks
+//! the DID's registered push endpoint to wake the app, which then connects
+//! and drains its queue with `FetchOffline`. The wake-up carries no content
+//! at all ([`notifier::WAKE_UP_BODY`]); the push service learns only that
+//! *something* is waiting.
+//!
+//! ```text
+//!   Client                      Relay                         Push server
+//!   RegisterPush { kind,
+//!     endpoint, quiet_hours } ─► stored, endpoint encrypted
+//!                               with this relay's push key
+//!
+//!   (client goes offline)
+//!   Send ─► queue_offline ─► schedule(did)
+//!                              wait coalesce window ─────┐  more messages
+//!                              still offline?            │  → coalesced
+//!                              in quiet hours? → wait ───┘
+//!                              Notifier::wake ─────────────► POST {"type":"wake"}
+//! ```
+//!
+//! Bursts are coalesced: a DID has at most one wake-up scheduled at a time,
+//! and wake-ups to the same DID are spaced at least `min_interval` apart.
+//! During the user's quiet hours the wake-up is held until they end.
+//! Endpoints the push server reports as gone are dropped.
+
+pub mod notifier;
+pub mod store;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Timelike, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::metrics::CounterVec;
+use notifier::{Notifier, NotifyError};
+use store::PushStore;
+
+/// Default time to wait for further messages before waking a device.
+pub const DEFAULT_COALESCE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Default minimum time between two wake-ups to the same DID.
+pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest endpoint URL accepted.
+const MAX_ENDPOINT_LEN: usize = 2048;
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// How a push endpoint is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushKind {
+    /// A UnifiedPush endpoint issued by the user's distributor
+    UnifiedPush,
+    /// A plain HTTPS webhook (e.g. a self-hosted bridge to APNs/FCM)
+    Webhook,
+}
+
+impl PushKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PushKind::UnifiedPush => "unified_push",
+            PushKind::Webhook => "webhook",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "unified_push" => Some(PushKind::UnifiedPush),
+            "webhook" => Some(PushKind::Webhook),
+            _ => None,
+        }
+    }
+}
+
+/// A daily window in the user's local time during which wake-ups are held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Start, in minutes after local midnight
+    pub start_minute: u16,
+    /// End, in minutes after local midnight. Earlier than `start_minute`
+    /// for a window that spans midnight (e.g. 22:00–07:00).
+    pub end_minute: u16,
+    /// The user's offset from UTC in minutes (e.g. -300 for UTC−5)
+    pub utc_offset_minutes: i16,
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        if i64::from(self.start_minute) >= MINUTES_PER_DAY
+            || i64::from(self.end_minute) >= MINUTES_PER_DAY
+        {
+            return Err("Quiet hours must be minutes within a day".to_string());
+        }
+        if self.start_minute == self.end_minute {
+            return Err("Quiet hours must not be empty".to_string());
+        }
+        if self.utc_offset_minutes.abs() > 14 * 60 {
+            return Err("Invalid UTC offset".to_string());
+        }
+        Ok(())
+    }
+
+    /// If `now` falls within quiet hours, the time they end.
+    pub fn end_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local = now + chrono::Duration::minutes(self.utc_offset_minutes.into());
+        let minute = i64::from(local.hour() * 60 + local.minute());
+        let (start, end) = (i64::from(self.start_minute), i64::from(self.end_minute));
+
+        let quiet = if start < end {
+            minute >= start && minute < end
+        } else {
+            minute >= start || minute < end
+        };
+        if !quiet {
+            return None;
+        }
+
+        let wait = (end - minute).rem_euclid(MINUTES_PER_DAY);
+        Some(
+            now + chrono::Duration::minutes(wait)
+                - chrono::Duration::seconds(local.second().into()),
+        )
+    }
+}
+
+/// A DID's push endpoint and preferences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushRegistration {
+    pub kind: PushKind,
+    pub endpoint: String,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// Gateway settings.
+#[derive(Debug, Clone)]
+pub struct PushConfig {
+    /// How long to wait for further messages before waking a device
+    pub coalesce_window: Duration,
+    /// Minimum time between two wake-ups to the same DID
+    pub min_interval: Duration,
+    /// Accept `http://` endpoints (local development only)
+    pub allow_http: bool,
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        Self {
+            coalesce_window: DEFAULT_COALESCE_WINDOW,
+            min_interval: DEFAULT_MIN_INTERVAL,
+            allow_http: false,
+        }
+    }
+}
+
+/// Schedules and sends wake-ups for DIDs with queued messages.
+#[derive(Clone)]
+pub struct PushGateway {
+    store: Arc<PushStore>,
+    notifier: Arc<dyn Notifier>,
+    config: PushConfig,
+    /// DIDs with a wake-up waiting to be sent
+    scheduled: Arc<DashMap<String, ()>>,
+    /// When each DID was last woken
+    last_sent: Arc<DashMap<String, Instant>>,
+    /// Wake-ups by outcome: `sent`, `coalesced`, `deferred`, `online`,
+    /// `failed`, `gone`
+    outcomes: Arc<CounterVec>,
+}
+
+impl PushGateway {
+    pub fn new(store: PushStore, notifier: Arc<dyn Notifier>, config: PushConfig) -> Self {
+        Self {
+            store: Arc::new(store),
+            notifier,
+            config,
+            scheduled: Arc::new(DashMap::new()),
+            last_sent: Arc::new(DashMap::new()),
+            outcomes: Arc::new(CounterVec::default()),
+        }
+    }
+
+    /// Register (or replace) a DID's push endpoint.
+    pub fn register(&self, did: &str, registration: PushRegistration) -> Result<(), String> {
+        if registration.endpoint.len() > MAX_ENDPOINT_LEN {
+            return Err("Push endpoint is too long".to_string());
+        }
+        let url = reqwest::Url::parse(&registration.endpoint)
+            .map_err(|e| format!("Invalid push endpoint: {}", e))?;
+        match url.scheme() {
+            "https" => {}
+            "http" if self.config.allow_http => {}
+            _ => return Err("Push endpoint must use https".to_string()),
+        }
+        if let Some(quiet_hours) = registration.quiet_hours {
+            quiet_hours.validate()?;
+        }
+
+        self.store.set(did, &registration)?;
+        tracing::debug!(
+            did = did,
+            kind = registration.kind.as_str(),
+            "Push endpoint registered"
+        );
+        Ok(())
+    }
+
+    /// Remove a DID's push endpoint. Returns whether it had one.
+    pub fn unregister(&self, did: &str) -> bool {
+        self.store.remove(did).unwrap_or_else(|e| {
+            tracing::error!(did = did, "{}", e);
+            false
+        })
+    }
+
+    /// A message was queued for `did`: wake its device unless a wake-up is
+    /// already on its way. `still_offline` is checked just before sending.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn schedule<F>(&self, did: &str, still_offline: F)
+    where
+        F: Fn() -> bool + Send + 'static,
+    {
+        if self.store.get(did).is_none() {
+            return;
+        }
+        if self.scheduled.insert(did.to_string(), ()).is_some() {
+            self.outcomes.inc(&["coalesced"]);
+            return;
+        }
+
+        let since_last = self.last_sent.get(did).map(|sent| sent.elapsed());
+        let delay = match since_last {
+            Some(elapsed) => self
+                .config
+                .coalesce_window
+                .max(self.config.min_interval.saturating_sub(elapsed)),
+            None => self.config.coalesce_window,
+        };
+
+        let gateway = self.clone();
+        let did = did.to_string();
+        tokio::spawn(async move {
+            gateway.deliver(&did, delay, still_offline).await;
+        });
+    }
+
+    async fn deliver<F>(&self, did: &str, delay: Duration, still_offline: F)
+    where
+        F: Fn() -> bool,
+    {
+        tokio::time::sleep(delay).await;
+
+        // Hold the wake-up until the user's quiet hours are over
+        let quiet_end = self
+            .store
+            .get(did)
+            .and_then(|r| r.quiet_hours)
+            .and_then(|q| q.end_after(Utc::now()));
+        if let Some(end) = quiet_end {
+            self.outcomes.inc(&["deferred"]);
+            let wait = (end - Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+        }
+
+        // Messages queued from here on schedule a new wake-up
+        self.scheduled.remove(did);
+
+        if !still_offline() {
+            self.outcomes.inc(&["online"]);
+            return;
+        }
+        // The registration may have changed or gone while we waited
+        let Some(registration) = self.store.get(did) else {
+            return;
+        };
+
+        self.last_sent.insert(did.to_string(), Instant::now());
+        match self
+            .notifier
+            .wake(registration.kind, &registration.endpoint)
+            .await
+        {
+            Ok(()) => {
+                self.outcomes.inc(&["sent"]);
+                tracing::debug!(did = did, "Push wake-up sent");
+            }
+            Err(NotifyError::Gone) => {
+                self.outcomes.inc(&["gone"]);
+                tracing::info!(did = did, "Push endpoint gone — removing registration");
+                self.unregister(did);
+            }
+            Err(NotifyError::Failed(e)) => {
+                self.outcomes.inc(&["failed"]);
+                tracing::warn!(did = did, "Push wake-up failed: {}", e);
+            }
+        }
+    }
+
+    /// Number of DIDs with a push endpoint.
+    pub fn registration_count(&self) -> usize {
+        self.store.count()
+    }
+
+    /// Wake-up counts by outcome.
+    pub fn outcomes(&self) -> &CounterVec {
+        &self.outcomes
+    }
+
+    /// Forget send times older than `min_interval`.
+    pub fn cleanup(&self) {
+        let min_interval = self.config.min_interval;
+        self.last_sent
+            .retain(|_, sent| sent.elapsed() < min_interval);
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use tokio::sync::mpsc;
+
+    /// Reports every wake-up instead of sending it.
+    struct RecordingNotifier(mpsc::UnboundedSender<String>);
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        async fn wake(&self, _kind: PushKind, endpoint: &str) -> Result<(), NotifyError> {
+            let _ = self.0.send(endpoint.to_string());
+            Ok(())
+        }
+    }
+
+    fn gateway(config: PushConfig) -> (PushGateway, mpsc::UnboundedReceiver<String>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let store = PushStore::new(None, &[1u8; 32]).unwrap();
+        (
+            PushGateway::new(store, Arc::new(RecordingNotifier(tx)), config),
+            rx,
+        )
+    }
+
+    fn registration(endpoint: &str) -> PushRegistration {
+        PushRegistration {
+            kind: PushKind::UnifiedPush,
+            endpoint: endpoint.to_string(),
+            quiet_hours: None,
+        }
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let at = |h: u32, m: u32| {
+            DateTime::parse_from_rfc3339(&format!("2026-01-10T{:02}:{:02}:30Z", h, m))
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        // 22:00–07:00 local, at UTC−5
+        let overnight = QuietHours {
+            start_minute: 22 * 60,
+            end_minute: 7 * 60,
+            utc_offset_minutes: -300,
+        };
+
+        // 04:30 UTC is 23:30 local: quiet until 07:00 local (12:00 UTC)
+        assert_eq!(
+            overnight.end_after(at(4, 30)),
+            Some(at(12, 0) - chrono::Duration::seconds(30))
+        );
+        // 15:00 UTC is 10:00 local
+        assert_eq!(overnight.end_after(at(15, 0)), None);
+
+        let lunch = QuietHours {
+            start_minute: 12 * 60,
+            end_minute: 13 * 60,
+            utc_offset_minutes: 0,
+        };
+        assert!(lunch.end_after(at(12, 59)).is_some());
+        assert!(lunch.end_after(at(13, 0)).is_none());
+
+        assert!(QuietHours {
+            start_minute: 1440,
+            ..lunch
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registration_requires_https() {
+        let (push, _) = gateway(PushConfig::default());
+        assert!(push
+            .register(
+                "did:key:z6MkBob",
+                registration("http://push.example.com/up")
+            )
+            .is_err());
+        assert!(push
+            .register("did:key:z6MkBob", registration("not a url"))
+            .is_err());
+        push.register(
+            "did:key:z6MkBob",
+            registration("https://push.example.com/up"),
+        )
+        .unwrap();
+        assert_eq!(push.registration_count(), 1);
+        assert!(push.unregister("did:key:z6MkBob"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bursts_are_coalesced() {
+        let (push, mut rx) = gateway(PushConfig::default());
+        push.register(
+            "did:key:z6MkBob",
+            registration("https://push.example.com/bob"),
+        )
+        .unwrap();
+
+        for _ in 0..5 {
+            push.schedule("did:key:z6MkBob", || true);
+        }
+        // Unregistered DIDs are ignored
+        push.schedule("did:key:z6MkCarol", || true);
+
+        tokio::time::sleep(DEFAULT_COALESCE_WINDOW * 2).await;
+        assert_eq!(rx.recv().await.unwrap(), "https://push.example.com/bob");
+        assert!(rx.try_recv().is_err());
+        assert_eq!(push.outcomes().get(&["coalesced"]), 4);
+
+        // The next wake-up waits out the minimum interval
+        push.schedule("did:key:z6MkBob", || true);
+        tokio::time::sleep(DEFAULT_COALESCE_WINDOW * 2).await;
+        assert!(rx.try_recv().is_err());
+        tokio::time::sleep(DEFAULT_MIN_INTERVAL).await;
+        assert!(rx.recv().await.is_some());
+
+        // Nothing is sent if the recipient came back online
+        tokio::time::sleep(DEFAULT_MIN_INTERVAL).await;
+        push.schedule("did:key:z6MkBob", || false);
+        tokio::time::sleep(DEFAULT_COALESCE_WINDOW * 2).await;
+        assert!(rx.try_recv().is_err());
+        assert_eq!(push.outcomes().get(&["online"]), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_quiet_hours_defer_wake_up() {
+        let (push, mut rx) = gateway(PushConfig::default());
+
+        // Quiet for the next hour, whatever the time is now
+        let now = Utc::now();
+        let start = (now.hour() * 60 + now.minute()) as u16;
+        let end = (start + 60) % MINUTES_PER_DAY as u16;
+        push.register(
+            "did:key:z6MkBob",
+            PushRegistration {
+                quiet_hours: Some(QuietHours {
+                    start_minute: start,
+                    end_minute: end,
+                    utc_offset_minutes: 0,
+                }),
+                ..registration("https://push.example.com/bob")
+            },
+        )
+        .unwrap();
+
+        push.schedule("did:key:z6MkBob", || true);
+        tokio::time::sleep(Duration::from_secs(30 * 60)).await;
+        assert!(rx.try_recv().is_err());
+        assert_eq!(push.outcomes().get(&["deferred"]), 1);
+
+        tokio::time::sleep(Duration::from_secs(31 * 60)).await;
+        assert!(rx.recv().await.is_some());
+    }
+}
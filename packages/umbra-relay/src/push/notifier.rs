@@ -0,0 +1,173 @@
+//! Push notifiers — how a wake-up reaches a device.
+//!
+//! The gateway only ever asks a notifier to deliver a fixed, content-free
+//! wake-up; the app then connects and fetches its offline queue itself.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+
+use super::PushKind;
+
+/// The body of every wake-up. It carries nothing about the message, its
+/// sender or its recipient.
+pub const WAKE_UP_BODY: &str = r#"{"type":"wake"}"#;
+
+/// How long a push server gets to accept a wake-up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a wake-up wasn't delivered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyError {
+    /// The endpoint no longer exists (the app was uninstalled or the
+    /// distributor unregistered it); the registration should be dropped.
+    Gone,
+    /// Any other failure; the registration is kept.
+    Failed(String),
+}
+
+/// Delivers wake-ups to push endpoints.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Send a content-free wake-up to `endpoint`.
+    async fn wake(&self, kind: PushKind, endpoint: &str) -> Result<(), NotifyError>;
+}
+
+/// Delivers wake-ups over HTTP: a UnifiedPush endpoint or a plain webhook.
+///
+/// Both get a `POST` of [`WAKE_UP_BODY`]. UnifiedPush endpoints treat the
+/// body as opaque bytes; webhooks get it as JSON.
+pub struct HttpNotifier {
+    client: reqwest::Client,
+}
+
+impl HttpNotifier {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+}
+
+impl Default for HttpNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Notifier for HttpNotifier {
+    async fn wake(&self, kind: PushKind, endpoint: &str) -> Result<(), NotifyError> {
+        let request = match kind {
+            PushKind::UnifiedPush => self
+                .client
+                .post(endpoint)
+                .header("Content-Type", "application/octet-stream")
+                // Push servers may drop wake-ups nobody picked up within a day
+                .header("TTL", "86400")
+                .header("Urgency", "high"),
+            PushKind::Webhook => self
+                .client
+                .post(endpoint)
+                .header("Content-Type", "application/json"),
+        };
+
+        let response = request
+            .body(WAKE_UP_BODY)
+            .send()
+            .await
+            .map_err(|e| NotifyError::Failed(format!("Request failed: {}", e)))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND | StatusCode::GONE => Err(NotifyError::Gone),
+            status => Err(NotifyError::Failed(format!(
+                "Push server returned {}",
+                status
+            ))),
+        }
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::{Path, State},
+        http::HeaderMap,
+        routing::post,
+        Router,
+    };
+    use tokio::sync::mpsc;
+
+    type Received = (String, Option<String>, String);
+
+    /// A local stand-in for a push server: `/ok/:id` accepts wake-ups and
+    /// reports them, `/gone/:id` answers 410.
+    async fn push_server() -> (String, mpsc::UnboundedReceiver<Received>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        async fn accept(
+            State(tx): State<mpsc::UnboundedSender<Received>>,
+            Path(id): Path<String>,
+            headers: HeaderMap,
+            body: String,
+        ) -> StatusCode {
+            let content_type = headers
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+            let _ = tx.send((id, content_type, body));
+            StatusCode::CREATED
+        }
+
+        let app = Router::new()
+            .route("/ok/:id", post(accept))
+            .route("/gone/:id", post(|| async { StatusCode::GONE }))
+            .with_state(tx);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        (format!("http://{}", addr), rx)
+    }
+
+    #[tokio::test]
+    async fn test_http_notifier_against_local_server() {
+        let (base, mut rx) = push_server().await;
+        let notifier = HttpNotifier::new();
+
+        notifier
+            .wake(PushKind::UnifiedPush, &format!("{}/ok/up", base))
+            .await
+            .unwrap();
+        let (id, content_type, body) = rx.recv().await.unwrap();
+        assert_eq!(id, "up");
+        assert_eq!(content_type.as_deref(), Some("application/octet-stream"));
+        assert_eq!(body, WAKE_UP_BODY);
+
+        notifier
+            .wake(PushKind::Webhook, &format!("{}/ok/hook", base))
+            .await
+            .unwrap();
+        let (id, content_type, _) = rx.recv().await.unwrap();
+        assert_eq!(id, "hook");
+        assert_eq!(content_type.as_deref(), Some("application/json"));
+
+        assert_eq!(
+            notifier
+                .wake(PushKind::Webhook, &format!("{}/gone/x", base))
+                .await,
+            Err(NotifyError::Gone)
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! SQLite-backed push registrations.
+//!
+//! Push endpoints are capability URLs — anyone holding one can wake the
+//! device — so they are encrypted with this relay's push key (AES-256-GCM,
+//! bound to the DID) before they touch disk. A copied database is useless
+//! without the key, and an endpoint can't be moved to another DID's row.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use chrono::Utc;
+use rusqlite::{params, Connection};
+
+use super::{PushKind, PushRegistration, QuietHours};
+
+/// File (inside the data directory) holding the hex-encoded push key.
+const KEY_FILE: &str = "push.key";
+
+/// Load this relay's 32-byte push key.
+///
+/// An explicit `key_hex` wins. Otherwise the key is read from
+/// `{data_dir}/push.key`, and generated and saved there on first run.
+/// Without a data directory the key only lives for this process (as do
+/// the registrations).
+pub fn load_key(key_hex: Option<&str>, data_dir: Option<&str>) -> Result<[u8; 32], String> {
+    if let Some(key) = key_hex.filter(|k| !k.trim().is_empty()) {
+        return parse_key(key);
+    }
+
+    let Some(dir) = data_dir else {
+        return Ok(Aes256Gcm::generate_key(OsRng).into());
+    };
+
+    let path = PathBuf::from(dir).join(KEY_FILE);
+    if path.exists() {
+        let key = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        return parse_key(&key);
+    }
+
+    let key: [u8; 32] = Aes256Gcm::generate_key(OsRng).into();
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+    std::fs::write(&path, hex::encode(key))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+    }
+    tracing::info!(path = %path.display(), "Generated new push key");
+    Ok(key)
+}
+
+fn parse_key(key_hex: &str) -> Result<[u8; 32], String> {
+    hex::decode(key_hex.trim())
+        .map_err(|e| format!("Invalid push key: {}", e))?
+        .try_into()
+        .map_err(|_| "Push key must be 32 bytes".to_string())
+}
+
+pub struct PushStore {
+    conn: Mutex<Connection>,
+    cipher: Aes256Gcm,
+}
+
+impl PushStore {
+    /// Create a new store. If `data_dir` is provided, uses a file-backed DB;
+    /// otherwise uses in-memory SQLite.
+    pub fn new(data_dir: Option<&str>, key: &[u8; 32]) -> Result<Self, rusqlite::Error> {
+        let conn = if let Some(dir) = data_dir {
+            let path = Path::new(dir).join("push.db");
+            Connection::open(path)?
+        } else {
+            Connection::open_in_memory()?
+        };
+
+        let store = Self {
+            conn: Mutex::new(conn),
+            cipher: Aes256Gcm::new(key.into()),
+        };
+
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn init_schema(&self) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS push_registrations (
+                did TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                endpoint_nonce BLOB NOT NULL,
+                endpoint BLOB NOT NULL,
+                quiet_start INTEGER,
+                quiet_end INTEGER,
+                utc_offset INTEGER,
+                updated_at INTEGER NOT NULL
+            );
+            ",
+        )?;
+
+        Ok(())
+    }
+
+    /// Store (or replace) the registration for a DID.
+    pub fn set(&self, did: &str, registration: &PushRegistration) -> Result<(), String> {
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let endpoint = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: registration.endpoint.as_bytes(),
+                    aad: did.as_bytes(),
+                },
+            )
+            .map_err(|_| "Failed to encrypt push endpoint".to_string())?;
+        let quiet = registration.quiet_hours;
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO push_registrations
+                (did, kind, endpoint_nonce, endpoint, quiet_start, quiet_end, utc_offset, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                did,
+                registration.kind.as_str(),
+                nonce.as_slice(),
+                endpoint,
+                quiet.map(|q| q.start_minute),
+                quiet.map(|q| q.end_minute),
+                quiet.map(|q| q.utc_offset_minutes),
+                Utc::now().timestamp()
+            ],
+        )
+        .map_err(|e| format!("Failed to store push registration: {}", e))?;
+        Ok(())
+    }
+
+    /// The registration for a DID, if it has one that decrypts.
+    pub fn get(&self, did: &str) -> Option<PushRegistration> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn.query_row(
+            "SELECT kind, endpoint_nonce, endpoint, quiet_start, quiet_end, utc_offset
+             FROM push_registrations WHERE did = ?1",
+            params![did],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Vec<u8>>(1)?,
+                    row.get::<_, Vec<u8>>(2)?,
+                    row.get::<_, Option<u16>>(3)?,
+                    row.get::<_, Option<u16>>(4)?,
+                    row.get::<_, Option<i16>>(5)?,
+                ))
+            },
+        );
+        let (kind, nonce, endpoint, quiet_start, quiet_end, utc_offset) = row.ok()?;
+
+        if nonce.len() != 12 {
+            return None;
+        }
+        let endpoint = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &endpoint,
+                    aad: did.as_bytes(),
+                },
+            )
+            .map_err(|_| tracing::warn!(did = did, "Push endpoint failed to decrypt"))
+            .ok()?;
+
+        let quiet_hours = match (quiet_start, quiet_end, utc_offset) {
+            (Some(start_minute), Some(end_minute), Some(utc_offset_minutes)) => Some(QuietHours {
+                start_minute,
+                end_minute,
+                utc_offset_minutes,
+            }),
+            _ => None,
+        };
+
+        Some(PushRegistration {
+            kind: PushKind::parse(&kind)?,
+            endpoint: String::from_utf8(endpoint).ok()?,
+            quiet_hours,
+        })
+    }
+
+    /// Remove the registration for a DID. Returns whether it had one.
+    pub fn remove(&self, did: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM push_registrations WHERE did = ?1",
+            params![did],
+        )
+        .map(|n| n > 0)
+        .map_err(|e| format!("Failed to remove push registration: {}", e))
+    }
+
+    /// Number of DIDs with a push registration.
+    pub fn count(&self) -> usize {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM push_registrations", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .map(|n| n as usize)
+        .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoints_are_encrypted_at_rest() {
+        let dir = std::env::temp_dir().join(format!("umbra-push-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        let key = load_key(None, Some(dir_str)).unwrap();
+        assert_eq!(load_key(None, Some(dir_str)).unwrap(), key);
+
+        let registration = PushRegistration {
+            kind: PushKind::UnifiedPush,
+            endpoint: "https://push.example.com/up/secret-token".to_string(),
+            quiet_hours: Some(QuietHours {
+                start_minute: 22 * 60,
+                end_minute: 7 * 60,
+                utc_offset_minutes: -300,
+            }),
+        };
+
+        {
+            let store = PushStore::new(Some(dir_str), &key).unwrap();
+            store.set("did:key:z6MkBob", &registration).unwrap();
+        }
+
+        let raw = std::fs::read(dir.join("push.db")).unwrap();
+        assert!(!raw.windows(12).any(|w| w == b"secret-token"));
+
+        let store = PushStore::new(Some(dir_str), &key).unwrap();
+        assert_eq!(store.get("did:key:z6MkBob"), Some(registration));
+        assert_eq!(store.count(), 1);
+
+        // Another relay's key can't read it
+        let other = PushStore::new(Some(dir_str), &[7u8; 32]).unwrap();
+        assert_eq!(other.get("did:key:z6MkBob"), None);
+
+        assert!(store.remove("did:key:z6MkBob").unwrap());
+        assert_eq!(store.get("did:key:z6MkBob"), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
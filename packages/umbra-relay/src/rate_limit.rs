@@ -0,0 +1,429 @@
+//! Rate Limiting
+//!
+//! Token buckets keyed by class and caller. WebSocket messages are limited
+//! per DID (all sessions of a DID share a bucket) in `handler.rs`, except
+//! sealed sends, which are limited per delivery credential (and per IP on
+//! unauthenticated sockets); HTTP
+//! routes are limited per client IP by [`limit_http`], applied as a layer on
+//! the router.
+//!
+//! ```text
+//!   class "send", limit 600/60   →  bursts of up to 600 messages,
+//!                                   refilled at 10 per second
+//! ```
+//!
+//! Refused WebSocket messages get a `ServerMessage::Error` with code
+//! `rate_limited` and `retry_after_ms`; refused HTTP requests get a 429
+//! with a `Retry-After` header. Limits can be overridden per class with
+//! `RELAY_RATE_LIMITS` (e.g. `send=300/60,username=off`).
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use dashmap::DashMap;
+use serde_json::json;
+
+use crate::metrics::CounterVec;
+use crate::protocol::ClientMessage;
+
+/// How long an idle bucket is kept before `cleanup` drops it.
+const IDLE_BUCKET_EXPIRY: Duration = Duration::from_secs(600);
+
+/// Default limits: class → (burst, period in seconds).
+const DEFAULT_LIMITS: &[(&str, u32, u64)] = &[
+    // WebSocket messages, per DID
+    ("send", 600, 60),
+    ("signal", 300, 60),
+    ("call_signal", 1200, 60),
+    ("create_session", 10, 60),
+    ("join_session", 30, 60),
+    ("publish_invite", 30, 60),
+    ("resolve_invite", 60, 60),
+    ("create_call_room", 20, 60),
+    ("sync_push", 120, 60),
+    ("register_push", 10, 60),
+    // TURN relay bandwidth in bytes, per DID
+    ("turn_bytes", 4_000_000, 4),
+    // TURN traffic before authenticating, in bytes, per IP
+    ("turn_unauth_bytes", 64_000, 60),
+    // Sealed sends, per access key (and per DID on authenticated sockets)
+    ("sealed_send", 120, 60),
+    // Sealed sends on unauthenticated sockets, per IP
+    ("sealed_send_ip", 300, 60),
+    // HTTP routes, per IP
+    ("ws_connect", 30, 60),
+    ("oauth", 60, 60),
+    ("username", 30, 60),
+    ("discovery", 120, 60),
+    ("sync", 60, 60),
+    ("invite", 60, 60),
+    ("assets", 60, 60),
+    ("bridge", 30, 60),
+    ("gif", 120, 60),
+];
+
+/// A token bucket limit: `burst` requests at once, refilled evenly over
+/// `period`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    fn tokens_per_sec(&self) -> f64 {
+        self.burst as f64 / self.period.as_secs_f64()
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Shared token-bucket limiter.
+#[derive(Clone)]
+pub struct RateLimiter {
+    /// Class → limit. Classes not listed are unlimited.
+    limits: Arc<HashMap<String, RateLimit>>,
+    /// (class, caller) → bucket
+    buckets: Arc<DashMap<(String, String), Bucket>>,
+    /// Refusals by class, for `/metrics`
+    rejected: Arc<CounterVec>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_LIMITS
+                .iter()
+                .map(|&(class, burst, secs)| {
+                    (
+                        class.to_string(),
+                        RateLimit {
+                            burst,
+                            period: Duration::from_secs(secs),
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl RateLimiter {
+    pub fn new(limits: HashMap<String, RateLimit>) -> Self {
+        Self {
+            limits: Arc::new(limits),
+            buckets: Arc::new(DashMap::new()),
+            rejected: Arc::new(CounterVec::default()),
+        }
+    }
+
+    /// The default limits with overrides applied.
+    ///
+    /// Each override is `class=burst/period_secs`, or `class=off` to lift
+    /// the limit.
+    pub fn with_overrides(overrides: &[String]) -> Result<Self, String> {
+        let mut limits = (*Self::default().limits).clone();
+        for spec in overrides.iter().filter(|s| !s.trim().is_empty()) {
+            let (class, value) = spec.split_once('=').ok_or_else(|| {
+                format!("Invalid rate limit '{}': expected class=burst/secs", spec)
+            })?;
+            let class = class.trim();
+            if !limits.contains_key(class) {
+                return Err(format!("Unknown rate limit class '{}'", class));
+            }
+            if value.trim() == "off" {
+                limits.remove(class);
+                continue;
+            }
+            let (burst, secs) = value
+                .trim()
+                .split_once('/')
+                .and_then(|(b, s)| Some((b.parse::<u32>().ok()?, s.parse::<u64>().ok()?)))
+                .filter(|&(burst, secs)| burst > 0 && secs > 0)
+                .ok_or_else(|| {
+                    format!("Invalid rate limit '{}': expected class=burst/secs", spec)
+                })?;
+            limits.insert(
+                class.to_string(),
+                RateLimit {
+                    burst,
+                    period: Duration::from_secs(secs),
+                },
+            );
+        }
+        Ok(Self::new(limits))
+    }
+
+    /// Take a token for `caller` in `class`.
+    ///
+    /// Returns how long to wait before retrying if the bucket is empty.
+    pub fn check(&self, class: &str, caller: &str) -> Result<(), Duration> {
+        self.check_cost(class, caller, 1)
+    }
+
+    /// Take `cost` tokens for `caller` in `class` (e.g. bytes, for limits
+    /// on bandwidth rather than requests).
+    pub fn check_cost(&self, class: &str, caller: &str, cost: u32) -> Result<(), Duration> {
+        let Some(limit) = self.limits.get(class) else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let mut bucket = self
+            .buckets
+            .entry((class.to_string(), caller.to_string()))
+            .or_insert(Bucket {
+                tokens: limit.burst as f64,
+                updated_at: now,
+            });
+
+        let rate = limit.tokens_per_sec();
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(limit.burst as f64);
+        bucket.updated_at = now;
+
+        let cost = cost as f64;
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            Ok(())
+        } else {
+            let wait = Duration::from_secs_f64((cost - bucket.tokens) / rate);
+            drop(bucket);
+            self.rejected.inc(&[class]);
+            Err(wait)
+        }
+    }
+
+    /// Requests refused, by class.
+    pub fn rejected(&self) -> &CounterVec {
+        &self.rejected
+    }
+
+    /// Drop buckets that have been idle long enough to be full again.
+    pub fn cleanup(&self) -> usize {
+        let before = self.buckets.len();
+        self.buckets
+            .retain(|_, bucket| bucket.updated_at.elapsed() < IDLE_BUCKET_EXPIRY);
+        before - self.buckets.len()
+    }
+}
+
+/// The rate limit class of a client message, if it is limited.
+pub fn message_class(msg: &ClientMessage) -> Option<&'static str> {
+    match msg {
+        ClientMessage::Send { .. } => Some("send"),
+        ClientMessage::Signal { .. } => Some("signal"),
+        ClientMessage::CallSignal { .. }
+        | ClientMessage::SfuOffer { .. }
+        | ClientMessage::SfuAnswer { .. }
+        | ClientMessage::SfuCandidate { .. } => Some("call_signal"),
+        ClientMessage::CreateSession { .. } => Some("create_session"),
+        ClientMessage::JoinSession { .. } => Some("join_session"),
+        ClientMessage::PublishInvite { .. } => Some("publish_invite"),
+        ClientMessage::ResolveInvite { .. } => Some("resolve_invite"),
+        ClientMessage::CreateCallRoom { .. } => Some("create_call_room"),
+        ClientMessage::SyncPush { .. } => Some("sync_push"),
+        ClientMessage::SendSealed { .. } => Some("sealed_send"),
+        ClientMessage::RegisterPush { .. } => Some("register_push"),
+        _ => None,
+    }
+}
+
+/// The rate limit class of an HTTP route, if it is limited.
+///
+/// Health, info and directory endpoints, `/metrics`, the admin API and the
+/// federation socket are not limited.
+pub fn route_class(path: &str) -> Option<&'static str> {
+    if path == "/ws" {
+        Some("ws_connect")
+    } else if path.starts_with("/auth/")
+        || path.starts_with("/profile/import/")
+        || path.starts_with("/community/import/")
+    {
+        Some("oauth")
+    } else if path.starts_with("/discovery/username") {
+        Some("username")
+    } else if path.starts_with("/discovery/") {
+        Some("discovery")
+    } else if path.starts_with("/api/sync/") {
+        Some("sync")
+    } else if path.starts_with("/api/invite/") {
+        Some("invite")
+    } else if path.starts_with("/api/community/") {
+        Some("assets")
+    } else if path.starts_with("/api/bridge/") {
+        Some("bridge")
+    } else if path.starts_with("/api/gif/") {
+        Some("gif")
+    } else {
+        None
+    }
+}
+
+/// The client IP [`limit_http`] limited a request as, stored in the request
+/// extensions for handlers that keep limiting after the request (the
+/// WebSocket upgrade).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Limiter state for [`limit_http`].
+#[derive(Clone)]
+pub struct HttpRateLimit {
+    pub limiter: RateLimiter,
+    /// Take the client IP from `X-Forwarded-For` (set when the relay runs
+    /// behind a reverse proxy).
+    pub trust_forwarded_for: bool,
+}
+
+/// Middleware limiting HTTP requests per client IP.
+pub async fn limit_http(
+    State(config): State<HttpRateLimit>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(class) = route_class(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let Some(ip) = client_ip(&request, config.trust_forwarded_for) else {
+        return next.run(request).await;
+    };
+    request.extensions_mut().insert(ClientIp(ip));
+
+    match config.limiter.check(class, &ip.to_string()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(json!({
+                    "error": format!("Rate limit exceeded for {}", class),
+                    "code": "rate_limited",
+                    "retry_after_ms": retry_after.as_millis() as u64,
+                })),
+            )
+                .into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            response
+        }
+    }
+}
+
+/// The caller's IP: the first `X-Forwarded-For` entry if trusted, else the
+/// socket peer address.
+fn client_ip(request: &Request, trust_forwarded_for: bool) -> Option<IpAddr> {
+    if trust_forwarded_for {
+        let forwarded = request
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip())
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(burst: u32, secs: u64) -> RateLimiter {
+        RateLimiter::new(HashMap::from([(
+            "send".to_string(),
+            RateLimit {
+                burst,
+                period: Duration::from_secs(secs),
+            },
+        )]))
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_then_refuses() {
+        let limiter = limiter(3, 60);
+        for _ in 0..3 {
+            assert!(limiter.check("send", "did:key:z6MkAlice").is_ok());
+        }
+        let retry = limiter.check("send", "did:key:z6MkAlice").unwrap_err();
+        assert!(retry <= Duration::from_secs(20) && retry > Duration::from_secs(19));
+
+        // Other callers and unlisted classes are unaffected
+        assert!(limiter.check("send", "did:key:z6MkBob").is_ok());
+        assert!(limiter.check("signal", "did:key:z6MkAlice").is_ok());
+        assert_eq!(limiter.rejected().get(&["send"]), 1);
+    }
+
+    #[test]
+    fn test_bucket_refills() {
+        let limiter = limiter(1, 1);
+        assert!(limiter.check("send", "a").is_ok());
+        assert!(limiter.check("send", "a").is_err());
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(limiter.check("send", "a").is_ok());
+    }
+
+    #[test]
+    fn test_weighted_checks() {
+        let limiter = limiter(1000, 1);
+        assert!(limiter.check_cost("send", "a", 600).is_ok());
+        let retry = limiter.check_cost("send", "a", 600).unwrap_err();
+        assert!(retry <= Duration::from_millis(200) && retry > Duration::from_millis(190));
+        assert!(limiter.check_cost("send", "a", 400).is_ok());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let limiter =
+            RateLimiter::with_overrides(&["send=5/10".to_string(), "username=off".to_string()])
+                .unwrap();
+        assert_eq!(
+            limiter.limits.get("send"),
+            Some(&RateLimit {
+                burst: 5,
+                period: Duration::from_secs(10)
+            })
+        );
+        assert!(!limiter.limits.contains_key("username"));
+        assert!(limiter.limits.contains_key("signal"));
+
+        assert!(RateLimiter::with_overrides(&["nope=1/1".to_string()]).is_err());
+        assert!(RateLimiter::with_overrides(&["send=0/10".to_string()]).is_err());
+        assert!(RateLimiter::with_overrides(&["send".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_route_classes() {
+        assert_eq!(route_class("/ws"), Some("ws_connect"));
+        assert_eq!(
+            route_class("/discovery/username/register"),
+            Some("username")
+        );
+        assert_eq!(route_class("/discovery/lookup"), Some("discovery"));
+        assert_eq!(route_class("/auth/github/start"), Some("oauth"));
+        assert_eq!(route_class("/profile/import/result/abc"), Some("oauth"));
+        assert_eq!(route_class("/health"), None);
+        assert_eq!(route_class("/federation"), None);
+        assert_eq!(route_class("/admin/peers"), None);
+    }
+}
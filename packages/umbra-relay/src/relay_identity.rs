@@ -0,0 +1,173 @@
+//! Relay Identity
+//!
+//! Each relay holds a long-lived Ed25519 keypair that identifies it to
+//! federation peers. The public key (hex) is what operators exchange and
+//! list in `RELAY_PEER_KEYS`; the secret seed comes from
+//! `RELAY_IDENTITY_KEY`, or is generated once and kept in
+//! `{DATA_DIR}/relay_identity.key`.
+
+use std::path::PathBuf;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+/// File (inside the data directory) holding the hex-encoded secret seed.
+const IDENTITY_FILE: &str = "relay_identity.key";
+
+/// A relay's Ed25519 signing identity.
+pub struct RelayIdentity {
+    signing_key: SigningKey,
+}
+
+impl RelayIdentity {
+    /// Generate a fresh random identity.
+    pub fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::generate(&mut rand::rngs::OsRng),
+        }
+    }
+
+    /// Build an identity from a hex-encoded 32-byte secret seed.
+    pub fn from_seed_hex(seed_hex: &str) -> Result<Self, String> {
+        let bytes =
+            hex::decode(seed_hex.trim()).map_err(|e| format!("Invalid identity key: {}", e))?;
+        let seed: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| "Identity key must be 32 bytes".to_string())?;
+        Ok(Self {
+            signing_key: SigningKey::from_bytes(&seed),
+        })
+    }
+
+    /// Load the relay identity.
+    ///
+    /// An explicit `seed_hex` wins. Otherwise the seed is read from
+    /// `{data_dir}/relay_identity.key`, and generated and saved there on
+    /// first run. Without a data directory the identity only lives for
+    /// this process.
+    pub fn load(seed_hex: Option<&str>, data_dir: Option<&str>) -> Result<Self, String> {
+        if let Some(seed) = seed_hex.filter(|s| !s.trim().is_empty()) {
+            return Self::from_seed_hex(seed);
+        }
+
+        let Some(dir) = data_dir else {
+            tracing::warn!("No DATA_DIR configured — relay identity will change on restart");
+            return Ok(Self::generate());
+        };
+
+        let path = PathBuf::from(dir).join(IDENTITY_FILE);
+        if path.exists() {
+            let seed = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            return Self::from_seed_hex(&seed);
+        }
+
+        let identity = Self::generate();
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        std::fs::write(&path, hex::encode(identity.signing_key.to_bytes()))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+        }
+        tracing::info!(path = %path.display(), "Generated new relay identity");
+        Ok(identity)
+    }
+
+    /// Hex-encoded Ed25519 public key.
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.signing_key.verifying_key().to_bytes())
+    }
+
+    /// Sign `message`, returning the hex-encoded signature.
+    pub fn sign(&self, message: &[u8]) -> String {
+        hex::encode(self.signing_key.sign(message).to_bytes())
+    }
+}
+
+/// Normalize a hex-encoded public key, checking it is a valid Ed25519 key.
+pub fn parse_public_key(public_key_hex: &str) -> Result<String, String> {
+    let bytes = hex::decode(public_key_hex.trim())
+        .map_err(|e| format!("Invalid public key encoding: {}", e))?;
+    let array: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "Public key must be 32 bytes".to_string())?;
+    VerifyingKey::from_bytes(&array).map_err(|e| format!("Invalid public key: {}", e))?;
+    Ok(hex::encode(array))
+}
+
+/// Verify a hex-encoded signature made by a relay identity.
+pub fn verify_signature(public_key_hex: &str, message: &[u8], signature_hex: &str) -> bool {
+    let Ok(key_bytes) = hex::decode(public_key_hex) else {
+        return false;
+    };
+    let Ok(sig_bytes) = hex::decode(signature_hex) else {
+        return false;
+    };
+    let (Ok(key_array), Ok(sig_array)) = (
+        <[u8; 32]>::try_from(key_bytes),
+        <[u8; 64]>::try_from(sig_bytes),
+    ) else {
+        return false;
+    };
+    let Ok(key) = VerifyingKey::from_bytes(&key_array) else {
+        return false;
+    };
+    key.verify(message, &Signature::from_bytes(&sig_array))
+        .is_ok()
+}
+
+// ── Tests ────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let identity = RelayIdentity::generate();
+        let signature = identity.sign(b"hello");
+
+        assert!(verify_signature(
+            &identity.public_key_hex(),
+            b"hello",
+            &signature
+        ));
+        assert!(!verify_signature(
+            &identity.public_key_hex(),
+            b"other",
+            &signature
+        ));
+        assert!(!verify_signature(
+            &RelayIdentity::generate().public_key_hex(),
+            b"hello",
+            &signature
+        ));
+        assert!(!verify_signature("not hex", b"hello", &signature));
+    }
+
+    #[test]
+    fn test_identity_persisted_in_data_dir() {
+        let dir = std::env::temp_dir().join(format!("umbra-relay-id-{}", uuid::Uuid::new_v4()));
+        let dir_str = dir.to_str().unwrap();
+
+        let first = RelayIdentity::load(None, Some(dir_str)).unwrap();
+        let second = RelayIdentity::load(None, Some(dir_str)).unwrap();
+        assert_eq!(first.public_key_hex(), second.public_key_hex());
+
+        // An explicit seed overrides the stored one
+        let explicit = RelayIdentity::load(Some(&"11".repeat(32)), Some(dir_str)).unwrap();
+        assert_ne!(explicit.public_key_hex(), first.public_key_hex());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_public_key() {
+        let identity = RelayIdentity::generate();
+        let key = identity.public_key_hex();
+        assert_eq!(parse_public_key(&key.to_uppercase()).unwrap(), key);
+        assert!(parse_public_key("abcd").is_err());
+    }
+}
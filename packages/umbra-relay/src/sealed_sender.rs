@@ -0,0 +1,91 @@
+//! Sealed Sender
+//!
+//! Lets a client deliver a message without telling the relay who sent it.
+//! The sender's DID and signature travel inside the encrypted payload; the
+//! relay only sees the recipient DID and a delivery credential.
+//!
+//! ```text
+//!   Recipient (authenticated)              Relay
+//!   RegisterSealedAccess {                 stores SHA-256(access_key)
+//!     access_hashes: [h(k_alice), ..] } ─► per friend, under the DID
+//!
+//!   Sender (unauthenticated socket)
+//!   SendSealed { to_did, access_key: k_alice, payload } ─►
+//!                                          h(k_alice) registered for to_did?
+//!                                          yes → SealedMessage { payload }
+//!                                          no  → sealed_access_denied
+//! ```
+//!
+//! Access keys are derived by both friends from their shared secret, so a
+//! credential says "one of the recipient's friends" without saying which.
+//! Sealed sends are rate limited per credential, and also per client IP on
+//! unauthenticated sockets or per DID on authenticated ones. A key that
+//! isn't well formed, or that the recipient registered here and doesn't
+//! match, is refused before it is charged, so made-up keys can't fill the
+//! limiter or stand in for the sender's own bucket.
+//!
+//! ## Linkability
+//!
+//! The credential is static: a pair of friends uses the same access key
+//! for every message until one of them re-registers, and it travels in
+//! cleartext inside the TLS connection. The relay never learns who the
+//! sender is, but it *can* tell that two sealed messages to the same
+//! recipient came from the same friend, and count and time them. Sealed
+//! sender hides the sender's identity from the relay, not the shape of the
+//! recipient's conversations; clients that want to limit this should
+//! re-derive and re-register access keys periodically.
+//!
+//! The relay holding the recipient's registration checks the credential.
+//! A relay that doesn't have it forwards `ForwardSealed` to the relay the
+//! recipient is connected to, which checks it there.
+
+use sha2::{Digest, Sha256};
+
+/// Most access hashes a DID may register (one per friend).
+pub const MAX_ACCESS_HASHES: usize = 10_000;
+
+/// Error code sent when a sealed send presents an unknown credential.
+pub const ACCESS_DENIED_CODE: &str = "sealed_access_denied";
+
+/// The hash the recipient registers for a hex-encoded access key.
+///
+/// Returns `None` if the key isn't 32 bytes of hex.
+pub fn access_hash(access_key: &str) -> Option<String> {
+    if !is_access_key(access_key) {
+        return None;
+    }
+    let key = hex::decode(access_key).ok()?;
+    Some(hex::encode(Sha256::digest(&key)))
+}
+
+/// Whether `access_key` looks like an access key (32 bytes of hex).
+pub fn is_access_key(access_key: &str) -> bool {
+    access_key.len() == 64 && access_key.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Whether `hash` looks like an access hash (64 lowercase hex digits).
+pub fn is_access_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_hash() {
+        let key = hex::encode([7u8; 32]);
+        let hash = access_hash(&key).unwrap();
+        assert!(is_access_hash(&hash));
+        assert_eq!(hash, hex::encode(Sha256::digest([7u8; 32])));
+
+        assert!(access_hash("zz").is_none());
+        assert!(access_hash(&"z".repeat(64)).is_none());
+        assert!(!is_access_key(&"a".repeat(100_000)));
+        assert!(access_hash(&hex::encode([7u8; 16])).is_none());
+        assert!(!is_access_hash(&hash.to_uppercase()));
+        assert!(!is_access_hash(&hash[..63]));
+    }
+}
@@ -0,0 +1,325 @@
+//! SFU Link
+//!
+//! Group calls with more than a handful of people don't work as a full
+//! mesh: each participant would upload their video once per peer. Call
+//! rooms created in SFU mode instead send media through an `umbra-sfu`
+//! instance (see `packages/umbra-sfu`), which forwards each participant's
+//! media to the rest of the room.
+//!
+//! The relay stays in charge of the room: it authenticates clients, checks
+//! membership and participant limits, and passes each participant's WebRTC
+//! signaling to the SFU over one control WebSocket:
+//!
+//! ```text
+//!   Client ──SfuOffer──► Relay ──Offer──────► SFU
+//!   Client ◄─SfuAnswer── Relay ◄─Answer────── SFU
+//!   Client ◄─CallDominantSpeaker── Relay ◄─DominantSpeaker── SFU
+//! ```
+//!
+//! Media is end-to-end encrypted by the clients with frame keys they
+//! exchange over `CallSignal`, so neither the relay nor the SFU can decrypt
+//! it. The control connection is authenticated with `RELAY_SFU_SECRET` and
+//! reconnects with exponential backoff (up to 60 seconds).
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// Default maximum participants in an SFU call room.
+pub const DEFAULT_MAX_SFU_PARTICIPANTS: usize = 25;
+
+/// Commands for the SFU. Must match umbra-sfu's `SfuCommand`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SfuCommand {
+    /// A participant's SDP offer (their first, or a renegotiation).
+    Offer {
+        room_id: String,
+        did: String,
+        sdp: String,
+    },
+    /// A participant's answer to an offer from the SFU.
+    Answer {
+        room_id: String,
+        did: String,
+        sdp: String,
+    },
+    /// A trickled ICE candidate (`RTCIceCandidateInit` JSON).
+    Candidate {
+        room_id: String,
+        did: String,
+        candidate: String,
+    },
+    /// A participant left the room or disconnected.
+    Leave { room_id: String, did: String },
+    /// The room is gone.
+    CloseRoom { room_id: String },
+}
+
+/// Events from the SFU. Must match umbra-sfu's `SfuEvent`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SfuEvent {
+    /// The SFU's answer to a participant's offer.
+    Answer {
+        room_id: String,
+        did: String,
+        sdp: String,
+    },
+    /// An SFU-initiated offer (tracks to receive were added or removed).
+    Offer {
+        room_id: String,
+        did: String,
+        sdp: String,
+    },
+    /// A trickled ICE candidate of the SFU.
+    Candidate {
+        room_id: String,
+        did: String,
+        candidate: String,
+    },
+    /// The room's dominant speaker changed.
+    DominantSpeaker { room_id: String, did: String },
+    /// A command for a participant failed.
+    Error {
+        room_id: String,
+        did: String,
+        message: String,
+    },
+}
+
+/// Connection to the SFU's control API.
+#[derive(Clone)]
+pub struct SfuLink {
+    url: Arc<String>,
+    secret: Arc<String>,
+    /// Participant limit of SFU call rooms
+    max_participants: usize,
+    /// Sender for the current control connection; None while disconnected
+    sender: Arc<Mutex<Option<mpsc::UnboundedSender<SfuCommand>>>>,
+}
+
+impl SfuLink {
+    pub fn new(url: String, secret: String, max_participants: usize) -> Self {
+        Self {
+            url: Arc::new(url),
+            secret: Arc::new(secret),
+            max_participants,
+            sender: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn max_participants(&self) -> usize {
+        self.max_participants
+    }
+
+    /// Connect to the SFU in the background. Events it sends arrive on the
+    /// returned channel (see `handler::handle_sfu_events`).
+    pub fn start(&self) -> mpsc::UnboundedReceiver<SfuEvent> {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let link = self.clone();
+        tokio::spawn(async move {
+            link.connection_loop(event_tx).await;
+        });
+        event_rx
+    }
+
+    /// Send a command to the SFU. Returns false if it isn't connected.
+    pub fn send(&self, command: SfuCommand) -> bool {
+        match self.sender.lock().unwrap().as_ref() {
+            Some(sender) => sender.send(command).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.sender.lock().unwrap().is_some()
+    }
+
+    /// Keep the control connection up, reconnecting with exponential
+    /// backoff.
+    async fn connection_loop(&self, events: mpsc::UnboundedSender<SfuEvent>) {
+        let mut backoff = Duration::from_secs(1);
+        let max_backoff = Duration::from_secs(60);
+
+        loop {
+            match self.connect(&events).await {
+                Ok(()) => {
+                    tracing::info!(url = self.url.as_str(), "SFU connection closed");
+                    backoff = Duration::from_secs(1);
+                }
+                Err(e) => {
+                    tracing::warn!(url = self.url.as_str(), error = %e, "SFU connection failed");
+                }
+            }
+            *self.sender.lock().unwrap() = None;
+
+            if events.is_closed() {
+                return;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(max_backoff);
+        }
+    }
+
+    /// Connect once and pass messages both ways until the connection drops.
+    async fn connect(
+        &self,
+        events: &mpsc::UnboundedSender<SfuEvent>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut request = self.url.as_str().into_client_request()?;
+        request.headers_mut().insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {}", self.secret))?,
+        );
+        let (ws_stream, _) = connect_async(request).await?;
+        let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+        tracing::info!(url = self.url.as_str(), "Connected to SFU");
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<SfuCommand>();
+        *self.sender.lock().unwrap() = Some(tx);
+
+        let sender_task = tokio::spawn(async move {
+            while let Some(command) = rx.recv().await {
+                match serde_json::to_string(&command) {
+                    Ok(json) => {
+                        if ws_sender.send(WsMessage::Text(json)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => tracing::error!("Failed to serialize SFU command: {}", e),
+                }
+            }
+        });
+
+        while let Some(msg) = ws_receiver.next().await {
+            match msg {
+                Ok(WsMessage::Text(text)) => match serde_json::from_str::<SfuEvent>(&text) {
+                    Ok(event) => {
+                        let _ = events.send(event);
+                    }
+                    Err(e) => tracing::warn!("Failed to parse SFU event: {}", e),
+                },
+                Ok(WsMessage::Close(_)) => break,
+                Err(e) => {
+                    sender_task.abort();
+                    return Err(e.into());
+                }
+                _ => {}
+            }
+        }
+
+        sender_task.abort();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::ws::{Message, WebSocketUpgrade},
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+
+    /// A stand-in SFU that answers every offer with "answer:<sdp>".
+    async fn fake_sfu() -> String {
+        async fn control(headers: HeaderMap, ws: WebSocketUpgrade) -> axum::response::Response {
+            if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some("Bearer s3cret") {
+                return StatusCode::UNAUTHORIZED.into_response();
+            }
+            ws.on_upgrade(|mut socket| async move {
+                while let Some(Ok(Message::Text(text))) = socket.recv().await {
+                    if let Ok(SfuCommand::Offer { room_id, did, sdp }) = serde_json::from_str(&text)
+                    {
+                        let answer = SfuEvent::Answer {
+                            room_id,
+                            did,
+                            sdp: format!("answer:{}", sdp),
+                        };
+                        let json = serde_json::to_string(&answer).unwrap();
+                        if socket.send(Message::Text(json)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            })
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/control", get(control)))
+                .await
+                .unwrap();
+        });
+        format!("ws://{}/control", addr)
+    }
+
+    async fn wait_connected(link: &SfuLink) -> bool {
+        for _ in 0..100 {
+            if link.is_connected() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    #[test]
+    fn test_command_serialization() {
+        let command = SfuCommand::Leave {
+            room_id: "room-1".to_string(),
+            did: "did:key:z6MkAlice".to_string(),
+        };
+        let json = serde_json::to_string(&command).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"leave","room_id":"room-1","did":"did:key:z6MkAlice"}"#
+        );
+        assert_eq!(serde_json::from_str::<SfuCommand>(&json).unwrap(), command);
+    }
+
+    #[tokio::test]
+    async fn test_link_relays_commands_and_events() {
+        let url = fake_sfu().await;
+        let link = SfuLink::new(url, "s3cret".to_string(), DEFAULT_MAX_SFU_PARTICIPANTS);
+        let mut events = link.start();
+        assert!(wait_connected(&link).await);
+
+        assert!(link.send(SfuCommand::Offer {
+            room_id: "room-1".to_string(),
+            did: "did:key:z6MkAlice".to_string(),
+            sdp: "v=0".to_string(),
+        }));
+        assert_eq!(
+            events.recv().await.unwrap(),
+            SfuEvent::Answer {
+                room_id: "room-1".to_string(),
+                did: "did:key:z6MkAlice".to_string(),
+                sdp: "answer:v=0".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_link_with_wrong_secret_stays_disconnected() {
+        let url = fake_sfu().await;
+        let link = SfuLink::new(url, "wrong".to_string(), DEFAULT_MAX_SFU_PARTICIPANTS);
+        let _events = link.start();
+        assert!(!wait_connected(&link).await);
+        assert!(!link.send(SfuCommand::CloseRoom {
+            room_id: "room-1".to_string(),
+        }));
+    }
+}
@@ -1,7 +1,8 @@
 //! Server state management.
 //!
 //! Tracks online clients, offline message queues, and signaling sessions.
-//! All data structures are concurrent (DashMap) for lock-free access.
+//! In-memory data structures are concurrent (DashMap) for lock-free access;
+//! the offline queue is persisted in SQLite (see `offline_store`).
 
 use std::sync::Arc;
 
@@ -11,7 +12,18 @@ use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use crate::federation::Federation;
-use crate::protocol::{CallRoom, OfflineMessage, PublishedInvite, ServerMessage, SignalingSession};
+use crate::ice::IceService;
+use crate::metrics::RelayMetrics;
+use crate::offline_store::OfflineStore;
+use crate::protocol::{
+    CallRoom, CallRoomMode, OfflineMessage, PublishedInvite, RelayDirectoryEntry, RelayLoad,
+    ServerMessage, SignalingSession,
+};
+use crate::push::PushGateway;
+use crate::rate_limit::RateLimiter;
+use crate::sealed_sender;
+use crate::sfu::{SfuCommand, SfuLink};
+use crate::state_store::StateStore;
 
 /// Result of attempting to route a message to a DID.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,6 +36,17 @@ pub enum RouteResult {
     Unreachable,
 }
 
+impl RouteResult {
+    /// Label used for the `messages_routed_total` metric.
+    pub fn label(self) -> &'static str {
+        match self {
+            RouteResult::DeliveredLocally => "local",
+            RouteResult::ForwardedToPeer => "federation",
+            RouteResult::Unreachable => "unreachable",
+        }
+    }
+}
+
 /// Maximum number of offline messages to store per DID.
 const DEFAULT_MAX_OFFLINE_PER_DID: usize = 1000;
 
@@ -69,6 +92,16 @@ impl Default for RelayConfig {
     }
 }
 
+/// In-memory offline store used until a persistent one is configured.
+fn in_memory_offline_store() -> OfflineStore {
+    OfflineStore::new(None).expect("Failed to open in-memory SQLite")
+}
+
+/// In-memory invite and session store used until a persistent one is configured.
+fn in_memory_state_store() -> StateStore {
+    StateStore::new(None).expect("Failed to open in-memory SQLite")
+}
+
 /// A connected client's sender channel.
 pub type ClientSender = mpsc::UnboundedSender<ServerMessage>;
 
@@ -84,10 +117,10 @@ pub struct RelayState {
     /// When they disconnect, their specific session is removed.
     pub online_clients: Arc<DashMap<String, Vec<(SessionId, ClientSender)>>>,
 
-    /// DID → queued offline messages.
-    /// Messages sent to offline peers are stored here until they reconnect
-    /// and call FetchOffline.
-    pub offline_queue: Arc<DashMap<String, Vec<OfflineMessage>>>,
+    /// Persistent queue of messages for offline DIDs.
+    /// Messages sent to offline peers are stored here until the recipient
+    /// fetches them with FetchOffline and acknowledges them with AckOffline.
+    pub offline_store: Arc<OfflineStore>,
 
     /// Session ID → signaling session.
     /// Used for single-scan friend adding flow.
@@ -102,25 +135,54 @@ pub struct RelayState {
     /// anyone on the network, even if the owner is offline.
     pub published_invites: Arc<DashMap<String, PublishedInvite>>,
 
+    /// Persistent copy of `sessions` and `published_invites`.
+    /// Every change to either map is written through so they survive restarts.
+    pub state_store: Arc<StateStore>,
+
     /// Server configuration.
     pub config: RelayConfig,
 
     /// Federation manager for relay-to-relay mesh networking.
     /// None if federation is disabled (no peer URLs configured).
     pub federation: Option<Federation>,
+
+    /// Counters exported on `/metrics`.
+    pub metrics: Arc<RelayMetrics>,
+
+    /// Per-DID limits on client messages (shared with the HTTP layer).
+    pub rate_limiter: RateLimiter,
+
+    /// Wakes offline recipients through their push endpoint.
+    /// None if push notifications are disabled.
+    pub push: Option<PushGateway>,
+
+    /// Control connection to the SFU that carries SFU-mode call rooms.
+    /// None if no SFU is configured (only mesh rooms can be created).
+    pub sfu: Option<SfuLink>,
+
+    /// STUN/TURN server whose credentials are handed out on registration.
+    /// None if TURN is disabled (no public IP configured).
+    pub ice: Option<IceService>,
 }
 
 impl RelayState {
     /// Create a new relay state with the given configuration.
+    /// The offline queue is in-memory until `with_offline_store` is called.
     pub fn new(config: RelayConfig) -> Self {
         Self {
             online_clients: Arc::new(DashMap::new()),
-            offline_queue: Arc::new(DashMap::new()),
+            offline_store: Arc::new(in_memory_offline_store()),
             sessions: Arc::new(DashMap::new()),
             call_rooms: Arc::new(DashMap::new()),
             published_invites: Arc::new(DashMap::new()),
+            state_store: Arc::new(in_memory_state_store()),
             config,
             federation: None,
+            metrics: Arc::new(RelayMetrics::default()),
+            rate_limiter: RateLimiter::default(),
+            push: None,
+            sfu: None,
+            ice: None,
         }
     }
 
@@ -128,13 +190,74 @@ impl RelayState {
     pub fn with_federation(config: RelayConfig, federation: Federation) -> Self {
         Self {
             online_clients: Arc::new(DashMap::new()),
-            offline_queue: Arc::new(DashMap::new()),
+            offline_store: Arc::new(in_memory_offline_store()),
             sessions: Arc::new(DashMap::new()),
             call_rooms: Arc::new(DashMap::new()),
             published_invites: Arc::new(DashMap::new()),
+            state_store: Arc::new(in_memory_state_store()),
             config,
             federation: Some(federation),
+            metrics: Arc::new(RelayMetrics::default()),
+            rate_limiter: RateLimiter::default(),
+            push: None,
+            sfu: None,
+            ice: None,
+        }
+    }
+
+    /// Use a limiter with non-default limits.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = limiter;
+        self
+    }
+
+    /// Use a (typically file-backed) store for the offline queue.
+    pub fn with_offline_store(mut self, store: OfflineStore) -> Self {
+        self.offline_store = Arc::new(store);
+        self
+    }
+
+    /// Enable push wake-ups for offline recipients.
+    pub fn with_push(mut self, push: PushGateway) -> Self {
+        self.push = Some(push);
+        self
+    }
+
+    /// Enable SFU-mode call rooms.
+    pub fn with_sfu(mut self, sfu: SfuLink) -> Self {
+        self.sfu = Some(sfu);
+        self
+    }
+
+    /// Hand out credentials for the relay's TURN server.
+    pub fn with_ice(mut self, ice: IceService) -> Self {
+        self.ice = Some(ice);
+        self
+    }
+
+    /// Use a (typically file-backed) store for invites and sessions, and
+    /// load what it already holds. Expired entries are dropped by the next
+    /// `cleanup_expired`.
+    pub fn with_state_store(mut self, store: StateStore) -> Self {
+        match store.load_invites() {
+            Ok(invites) => {
+                for invite in invites {
+                    self.published_invites.insert(invite.code.clone(), invite);
+                }
+            }
+            Err(e) => tracing::error!("Failed to load published invites: {}", e),
+        }
+        match store.load_sessions() {
+            Ok(sessions) => {
+                for session in sessions {
+                    self.sessions.insert(session.id.clone(), session);
+                }
+            }
+            Err(e) => tracing::error!("Failed to load signaling sessions: {}", e),
         }
+
+        self.state_store = Arc::new(store);
+        self
     }
 
     // ── Client Management ─────────────────────────────────────────────────
@@ -148,6 +271,7 @@ impl RelayState {
         sessions.push((session_id.to_string(), sender));
         let session_count = sessions.len();
         drop(sessions);
+        self.metrics.connections.inc();
 
         tracing::info!(did = did, session_id = session_id, sessions = session_count, "Client session registered");
 
@@ -185,6 +309,24 @@ impl RelayState {
         }
     }
 
+    /// Disconnect every session of a DID.
+    ///
+    /// Dropping the senders ends each session's outgoing channel, which
+    /// closes its WebSocket. Returns the number of sessions closed.
+    pub fn evict_client(&self, did: &str) -> usize {
+        let Some((_, sessions)) = self.online_clients.remove(did) else {
+            return 0;
+        };
+
+        tracing::info!(did = did, sessions = sessions.len(), "Client evicted");
+        self.metrics.evictions.add(sessions.len() as u64);
+
+        if let Some(ref fed) = self.federation {
+            fed.broadcast_presence_offline(did);
+        }
+        sessions.len()
+    }
+
     /// Check if a client is currently online (has at least one session).
     #[allow(dead_code)]
     pub fn is_online(&self, did: &str) -> bool {
@@ -210,6 +352,20 @@ impl RelayState {
         }
     }
 
+    /// Send a message to one session of a DID.
+    /// Returns true if the session exists and the message was queued.
+    pub fn send_to_session(&self, did: &str, session_id: &str, message: ServerMessage) -> bool {
+        self.online_clients
+            .get(did)
+            .and_then(|sessions| {
+                sessions
+                    .iter()
+                    .find(|(sid, _)| sid == session_id)
+                    .map(|(_, sender)| sender.send(message).is_ok())
+            })
+            .unwrap_or(false)
+    }
+
     /// Send a message to all sessions of a DID EXCEPT the specified session.
     /// Used for sync broadcasts (don't echo back to the sender).
     /// Returns true if sent to at least one other session.
@@ -236,7 +392,7 @@ impl RelayState {
             return true;
         }
         if let Some(ref fed) = self.federation {
-            return fed.find_peer_for_did(did).is_some();
+            return fed.is_reachable(did);
         }
         false
     }
@@ -276,18 +432,66 @@ impl RelayState {
             payload: payload.to_string(),
             timestamp,
         };
-        if self.send_to_client(to_did, local_msg) {
-            return RouteResult::DeliveredLocally;
-        }
+        let result = if self.send_to_client(to_did, local_msg) {
+            RouteResult::DeliveredLocally
+        } else if self
+            .federation
+            .as_ref()
+            .is_some_and(|fed| fed.forward_message(from_did, to_did, payload, timestamp))
+        {
+            RouteResult::ForwardedToPeer
+        } else {
+            RouteResult::Unreachable
+        };
 
-        // Try federation
-        if let Some(ref fed) = self.federation {
-            if fed.forward_message(from_did, to_did, payload, timestamp) {
-                return RouteResult::ForwardedToPeer;
+        self.metrics.messages_routed.inc(&[result.label()]);
+        result
+    }
+
+    /// Route a sealed-sender message.
+    ///
+    /// If `to_did` registered its access hashes here, the access key is
+    /// checked here and the message is delivered as `SealedMessage` or
+    /// forwarded. Otherwise it can only be forwarded to the relay `to_did`
+    /// is connected to, which checks the key itself. Returns `None` if the
+    /// access key was refused.
+    pub fn route_sealed_message(
+        &self,
+        to_did: &str,
+        access_key: &str,
+        payload: &str,
+        timestamp: i64,
+    ) -> Option<RouteResult> {
+        let forward = || {
+            self.federation.as_ref().is_some_and(|fed| {
+                fed.forward_sealed_message(to_did, access_key, payload, timestamp)
+            })
+        };
+
+        let result = if self.has_sealed_registration(to_did) {
+            if !self.check_sealed_access(to_did, access_key) {
+                return None;
             }
-        }
+            let local_msg = ServerMessage::SealedMessage {
+                payload: payload.to_string(),
+                timestamp,
+            };
+            if self.send_to_client(to_did, local_msg) {
+                RouteResult::DeliveredLocally
+            } else if forward() {
+                RouteResult::ForwardedToPeer
+            } else {
+                RouteResult::Unreachable
+            }
+        } else if forward() {
+            RouteResult::ForwardedToPeer
+        } else {
+            self.metrics.sealed_denied.inc();
+            return None;
+        };
 
-        RouteResult::Unreachable
+        self.metrics.messages_routed.inc(&[result.label()]);
+        Some(result)
     }
 
     /// Get the number of currently connected clients.
@@ -295,6 +499,11 @@ impl RelayState {
         self.online_clients.len()
     }
 
+    /// Get the number of client sessions (one DID may have several).
+    pub fn session_count(&self) -> usize {
+        self.online_clients.iter().map(|entry| entry.len()).sum()
+    }
+
     /// Get the total number of reachable clients across the mesh.
     pub fn mesh_online_count(&self) -> usize {
         let local = self.online_clients.len();
@@ -325,7 +534,7 @@ impl RelayState {
     // ── Offline Message Queue ─────────────────────────────────────────────
 
     /// Queue a message for offline delivery.
-    /// Returns false if the queue is full.
+    /// Returns false if the recipient's queue is full (nothing is stored).
     pub fn queue_offline_message(
         &self,
         to_did: &str,
@@ -341,50 +550,153 @@ impl RelayState {
             queued_at: Utc::now(),
         };
 
-        let mut queue = self.offline_queue.entry(to_did.to_string()).or_default();
-
-        if queue.len() >= self.config.max_offline_per_did {
-            tracing::warn!(
-                to_did = to_did,
-                queue_size = queue.len(),
-                "Offline queue full for DID, dropping oldest message"
-            );
-            // Remove oldest message to make room
-            if !queue.is_empty() {
-                queue.remove(0);
+        match self.offline_store.enqueue(
+            to_did,
+            &message,
+            self.config.offline_ttl_secs,
+            self.config.max_offline_per_did,
+        ) {
+            Ok(true) => {
+                self.metrics.offline_queued.inc();
+                tracing::debug!(
+                    to_did = to_did,
+                    from_did = from_did,
+                    "Queued offline message"
+                );
+                self.wake_recipient(to_did);
+                true
+            }
+            Ok(false) => {
+                self.metrics.offline_dropped.inc(&["queue_full"]);
+                tracing::warn!(
+                    to_did = to_did,
+                    max = self.config.max_offline_per_did,
+                    "Offline queue full for DID, rejecting message"
+                );
+                false
+            }
+            Err(e) => {
+                self.metrics.offline_dropped.inc(&["error"]);
+                tracing::error!(to_did = to_did, "Failed to queue offline message: {}", e);
+                false
             }
         }
+    }
 
-        queue.push(message);
-        tracing::debug!(
-            to_did = to_did,
-            from_did = from_did,
-            "Queued offline message"
-        );
-        true
+    /// Schedule a push wake-up for an offline recipient, if push is enabled
+    /// and they registered an endpoint.
+    fn wake_recipient(&self, did: &str) {
+        let Some(push) = &self.push else {
+            return;
+        };
+        let state = self.clone();
+        let recipient = did.to_string();
+        push.schedule(did, move || !state.is_online(&recipient));
     }
 
-    /// Drain all offline messages for a DID.
-    /// Returns the messages and removes them from the queue.
-    pub fn drain_offline_messages(&self, did: &str) -> Vec<OfflineMessage> {
-        if let Some((_, messages)) = self.offline_queue.remove(did) {
-            let now = Utc::now().timestamp();
-            // Filter out expired messages
-            messages
-                .into_iter()
-                .filter(|m| now - m.queued_at.timestamp() < self.config.offline_ttl_secs)
-                .collect()
-        } else {
+    /// Get all unexpired offline messages for a DID.
+    /// Messages are NOT removed — they stay queued until acknowledged via
+    /// `ack_offline_messages`, so a client that disconnects mid-delivery
+    /// gets them again on its next fetch.
+    pub fn pending_offline_messages(&self, did: &str) -> Vec<OfflineMessage> {
+        self.offline_store.pending(did).unwrap_or_else(|e| {
+            tracing::error!(did = did, "Failed to load offline messages: {}", e);
             Vec::new()
+        })
+    }
+
+    /// Delete offline messages the recipient has acknowledged.
+    /// Returns the number of messages removed.
+    pub fn ack_offline_messages(&self, did: &str, ids: &[String]) -> usize {
+        self.offline_store.ack(did, ids).unwrap_or_else(|e| {
+            tracing::error!(did = did, "Failed to ack offline messages: {}", e);
+            0
+        })
+    }
+
+    /// Delete every queued offline message for a DID.
+    /// Returns the number of messages removed.
+    pub fn purge_offline_messages(&self, did: &str) -> usize {
+        let purged = self.offline_store.purge(did).unwrap_or_else(|e| {
+            tracing::error!(did = did, "Failed to purge offline messages: {}", e);
+            0
+        });
+        if purged > 0 {
+            self.metrics.offline_dropped.add(&["purged"], purged as u64);
+            tracing::info!(did = did, count = purged, "Purged offline queue");
         }
+        purged
     }
 
     /// Get the number of queued offline messages across all DIDs.
     pub fn offline_queue_size(&self) -> usize {
-        self.offline_queue
+        self.offline_store.count()
+    }
+
+    /// Current load of this relay.
+    pub fn load(&self) -> RelayLoad {
+        RelayLoad {
+            online_count: self.online_count(),
+            offline_queue_size: self.offline_queue_size(),
+        }
+    }
+
+    /// Relays clients can connect to: this one first, then the federated
+    /// relays it can reach.
+    pub fn relay_directory(&self) -> Vec<RelayDirectoryEntry> {
+        match self.federation {
+            Some(ref fed) => {
+                fed.set_local_load(self.load());
+                fed.directory()
+            }
+            None => vec![RelayDirectoryEntry {
+                relay_id: None,
+                relay_url: None,
+                region: self.config.region.clone(),
+                location: self.config.location.clone(),
+                load: Some(self.load()),
+            }],
+        }
+    }
+
+    // ── Sealed Sender ─────────────────────────────────────────────────────
+
+    /// Replace the sealed-sender access hashes registered for a DID.
+    pub fn register_sealed_access(
+        &self,
+        did: &str,
+        access_hashes: &[String],
+    ) -> Result<usize, String> {
+        if access_hashes.len() > sealed_sender::MAX_ACCESS_HASHES {
+            return Err(format!(
+                "Too many access hashes (max {})",
+                sealed_sender::MAX_ACCESS_HASHES
+            ));
+        }
+        if !access_hashes
             .iter()
-            .map(|entry| entry.value().len())
-            .sum()
+            .all(|h| sealed_sender::is_access_hash(h))
+        {
+            return Err("Access hashes must be hex SHA-256 digests".to_string());
+        }
+        self.offline_store.set_sealed_access(did, access_hashes)
+    }
+
+    /// Whether `did` has registered sealed-sender access hashes here, making
+    /// this relay the one that checks access keys for it.
+    pub fn has_sealed_registration(&self, did: &str) -> bool {
+        self.offline_store.has_sealed_registration(did)
+    }
+
+    /// Whether `access_key` is a sealed-sender credential `to_did` registered
+    /// on this relay. Refusals are counted in the metrics.
+    pub fn check_sealed_access(&self, to_did: &str, access_key: &str) -> bool {
+        let allowed = sealed_sender::access_hash(access_key)
+            .is_some_and(|hash| self.offline_store.has_sealed_access(to_did, &hash));
+        if !allowed {
+            self.metrics.sealed_denied.inc();
+        }
+        allowed
     }
 
     // ── Signaling Sessions ────────────────────────────────────────────────
@@ -408,7 +720,9 @@ impl RelayState {
             creator_did = creator_did,
             "Created signaling session"
         );
+        self.save_session(&session);
         self.sessions.insert(session_id.clone(), session);
+        self.metrics.sessions_created.inc();
 
         // Replicate to federated peers so the session can be joined from any relay
         if let Some(ref fed) = self.federation {
@@ -444,6 +758,7 @@ impl RelayState {
             creator_did = creator_did,
             "Imported federated session"
         );
+        self.save_session(&session);
         self.sessions.insert(session_id.to_string(), session);
     }
 
@@ -457,7 +772,7 @@ impl RelayState {
             if age > self.config.session_ttl_secs {
                 tracing::debug!(session_id = session_id, "Session expired");
                 drop(session);
-                self.sessions.remove(session_id);
+                self.remove_session(session_id);
                 return None;
             }
 
@@ -476,25 +791,54 @@ impl RelayState {
     pub fn consume_session(&self, session_id: &str) -> bool {
         if let Some(mut session) = self.sessions.get_mut(session_id) {
             session.consumed = true;
+            drop(session);
+            if let Err(e) = self.state_store.consume_session(session_id) {
+                tracing::error!(session_id = session_id, "{}", e);
+            }
             true
         } else {
             false
         }
     }
 
+    /// Store a session in the persistent store (errors are logged).
+    fn save_session(&self, session: &SignalingSession) {
+        if let Err(e) = self.state_store.save_session(session) {
+            tracing::error!(session_id = session.id.as_str(), "{}", e);
+        }
+    }
+
+    /// Remove a session from memory and the persistent store.
+    fn remove_session(&self, session_id: &str) {
+        self.sessions.remove(session_id);
+        if let Err(e) = self.state_store.remove_session(session_id) {
+            tracing::error!(session_id = session_id, "{}", e);
+        }
+    }
+
     // ── Call Room Management ──────────────────────────────────────────────
 
     /// Create a new call room for group calling.
     /// Returns the room ID.
-    pub fn create_call_room(&self, group_id: &str, creator_did: &str) -> String {
+    pub fn create_call_room(
+        &self,
+        group_id: &str,
+        creator_did: &str,
+        mode: CallRoomMode,
+    ) -> String {
         let room_id = Uuid::new_v4().to_string();
+        let max_participants = match (mode, &self.sfu) {
+            (CallRoomMode::Sfu, Some(sfu)) => sfu.max_participants(),
+            _ => DEFAULT_MAX_CALL_PARTICIPANTS,
+        };
 
         let room = CallRoom {
             room_id: room_id.clone(),
             group_id: group_id.to_string(),
             creator_did: creator_did.to_string(),
             participants: vec![creator_did.to_string()],
-            max_participants: DEFAULT_MAX_CALL_PARTICIPANTS,
+            max_participants,
+            mode,
             created_at: Utc::now(),
         };
 
@@ -502,9 +846,11 @@ impl RelayState {
             room_id = room_id.as_str(),
             group_id = group_id,
             creator = creator_did,
+            mode = ?mode,
             "Created call room"
         );
         self.call_rooms.insert(room_id.clone(), room);
+        self.metrics.call_rooms_created.inc();
         room_id
     }
 
@@ -540,15 +886,23 @@ impl RelayState {
     /// Leave a call room. Returns the remaining participants.
     /// Removes the room if it becomes empty.
     pub fn leave_call_room(&self, room_id: &str, did: &str) -> Vec<String> {
-        let remaining = if let Some(mut room) = self.call_rooms.get_mut(room_id) {
+        let (remaining, mode) = if let Some(mut room) = self.call_rooms.get_mut(room_id) {
             room.participants.retain(|p| p != did);
             let remaining = room.participants.clone();
+            let mode = room.mode;
             drop(room);
-            remaining
+            (remaining, mode)
         } else {
             return Vec::new();
         };
 
+        if mode == CallRoomMode::Sfu {
+            self.send_to_sfu(SfuCommand::Leave {
+                room_id: room_id.to_string(),
+                did: did.to_string(),
+            });
+        }
+
         tracing::info!(
             room_id = room_id,
             did = did,
@@ -558,15 +912,36 @@ impl RelayState {
 
         // Remove empty rooms
         if remaining.is_empty() {
-            self.call_rooms.remove(room_id);
+            self.remove_call_room(room_id);
             tracing::debug!(room_id = room_id, "Removed empty call room");
         }
 
         remaining
     }
 
+    /// Remove a call room, closing it on the SFU if it used one.
+    fn remove_call_room(&self, room_id: &str) {
+        if let Some((_, room)) = self.call_rooms.remove(room_id) {
+            if room.mode == CallRoomMode::Sfu {
+                self.send_to_sfu(SfuCommand::CloseRoom {
+                    room_id: room_id.to_string(),
+                });
+            }
+        }
+    }
+
+    /// The media mode of a call room, if it exists.
+    pub fn call_room_mode(&self, room_id: &str) -> Option<CallRoomMode> {
+        self.call_rooms.get(room_id).map(|r| r.mode)
+    }
+
+    /// Send a command to the SFU. Returns false if there is no SFU or it
+    /// isn't connected.
+    pub fn send_to_sfu(&self, command: SfuCommand) -> bool {
+        self.sfu.as_ref().is_some_and(|sfu| sfu.send(command))
+    }
+
     /// Get the participants in a call room.
-    #[allow(dead_code)]
     pub fn get_call_room_participants(&self, room_id: &str) -> Option<Vec<String>> {
         self.call_rooms.get(room_id).map(|r| r.participants.clone())
     }
@@ -579,6 +954,14 @@ impl RelayState {
             .unwrap_or(false)
     }
 
+    /// Get the number of participants across all call rooms.
+    pub fn call_participant_count(&self) -> usize {
+        self.call_rooms
+            .iter()
+            .map(|entry| entry.participants.len())
+            .sum()
+    }
+
     /// Remove a disconnected client from all call rooms they're in.
     /// Returns room_id → remaining participants for rooms they were in.
     pub fn remove_from_all_call_rooms(&self, did: &str) -> Vec<(String, Vec<String>)> {
@@ -602,76 +985,74 @@ impl RelayState {
 
     /// Publish a community invite so it can be resolved by other clients.
     /// Replicates to federated peers.
-    pub fn publish_invite(
-        &self,
-        code: &str,
-        publisher_did: &str,
-        community_id: &str,
-        community_name: &str,
-        community_description: Option<&str>,
-        community_icon: Option<&str>,
-        member_count: u32,
-        max_uses: Option<i32>,
-        expires_at: Option<i64>,
-        invite_payload: &str,
-    ) {
-        let now = Utc::now();
-
-        let invite = PublishedInvite {
-            code: code.to_string(),
-            publisher_did: publisher_did.to_string(),
-            community_id: community_id.to_string(),
-            community_name: community_name.to_string(),
-            community_description: community_description.map(|s| s.to_string()),
-            community_icon: community_icon.map(|s| s.to_string()),
-            member_count,
-            max_uses,
-            use_count: 0,
-            expires_at,
-            invite_payload: invite_payload.to_string(),
-            published_at: now,
-        };
+    ///
+    /// Returns false, storing nothing, if the invite isn't signed by its
+    /// publisher.
+    pub fn publish_invite(&self, invite: PublishedInvite) -> bool {
+        if !invite.verify_signature() {
+            tracing::warn!(
+                code = invite.code.as_str(),
+                publisher = invite.publisher_did.as_str(),
+                "Rejected invite with invalid signature"
+            );
+            return false;
+        }
 
         tracing::info!(
-            code = code,
-            community = community_name,
-            publisher = publisher_did,
+            code = invite.code.as_str(),
+            community = invite.community_name.as_str(),
+            publisher = invite.publisher_did.as_str(),
             "Published community invite"
         );
 
-        self.published_invites.insert(code.to_string(), invite);
+        self.save_invite(&invite);
 
         // Replicate to federated peers
         if let Some(ref fed) = self.federation {
             fed.broadcast_to_peers(crate::protocol::PeerMessage::InviteSync {
-                code: code.to_string(),
-                publisher_did: publisher_did.to_string(),
-                community_id: community_id.to_string(),
-                community_name: community_name.to_string(),
-                community_description: community_description.map(|s| s.to_string()),
-                community_icon: community_icon.map(|s| s.to_string()),
-                member_count,
-                max_uses,
-                expires_at,
-                invite_payload: invite_payload.to_string(),
-                published_at: now.timestamp(),
+                code: invite.code.clone(),
+                publisher_did: invite.publisher_did.clone(),
+                community_id: invite.community_id.clone(),
+                community_name: invite.community_name.clone(),
+                community_description: invite.community_description.clone(),
+                community_icon: invite.community_icon.clone(),
+                member_count: invite.member_count,
+                max_uses: invite.max_uses,
+                expires_at: invite.expires_at,
+                invite_payload: invite.invite_payload.clone(),
+                published_at: invite.published_at.timestamp(),
+                signature: invite.signature.clone(),
             });
         }
+
+        self.published_invites.insert(invite.code.clone(), invite);
+        true
     }
 
     /// Import a published invite from a federated peer.
+    /// Invites whose publisher signature doesn't verify are dropped.
     pub fn import_invite(&self, invite: PublishedInvite) {
         // Don't overwrite existing invites
         if self.published_invites.contains_key(&invite.code) {
             return;
         }
 
+        if !invite.verify_signature() {
+            tracing::warn!(
+                code = invite.code.as_str(),
+                publisher = invite.publisher_did.as_str(),
+                "Dropped federated invite with invalid signature"
+            );
+            return;
+        }
+
         tracing::debug!(
             code = invite.code.as_str(),
             community = invite.community_name.as_str(),
             "Imported federated invite"
         );
 
+        self.save_invite(&invite);
         self.published_invites.insert(invite.code.clone(), invite);
     }
 
@@ -685,7 +1066,7 @@ impl RelayState {
                 if now * 1000 > expires_at {
                     // Expired in millis — remove it
                     drop(invite);
-                    self.published_invites.remove(code);
+                    self.remove_invite(code);
                     return None;
                 }
             }
@@ -694,7 +1075,7 @@ impl RelayState {
             if let Some(max_uses) = invite.max_uses {
                 if max_uses > 0 && invite.use_count >= max_uses {
                     drop(invite);
-                    self.published_invites.remove(code);
+                    self.remove_invite(code);
                     return None;
                 }
             }
@@ -707,7 +1088,7 @@ impl RelayState {
 
     /// Revoke a published invite.
     pub fn revoke_invite(&self, code: &str) {
-        if self.published_invites.remove(code).is_some() {
+        if self.remove_invite(code) {
             tracing::info!(code = code, "Revoked published invite");
 
             // Propagate revocation to federation
@@ -719,10 +1100,28 @@ impl RelayState {
         }
     }
 
+    /// Remove a published invite from memory and the persistent store,
+    /// without telling peers. Returns whether it was published here.
+    pub fn remove_invite(&self, code: &str) -> bool {
+        if let Err(e) = self.state_store.remove_invite(code) {
+            tracing::error!(code = code, "{}", e);
+        }
+        self.published_invites.remove(code).is_some()
+    }
+
     /// Increment the use count for a published invite.
     pub fn increment_invite_use_count(&self, code: &str) {
         if let Some(mut invite) = self.published_invites.get_mut(code) {
             invite.use_count += 1;
+            let invite = invite.clone();
+            self.save_invite(&invite);
+        }
+    }
+
+    /// Store an invite in the persistent store (errors are logged).
+    fn save_invite(&self, invite: &PublishedInvite) {
+        if let Err(e) = self.state_store.save_invite(invite) {
+            tracing::error!(code = invite.code.as_str(), "{}", e);
         }
     }
 
@@ -740,7 +1139,7 @@ impl RelayState {
             .collect();
 
         for session_id in &expired_sessions {
-            self.sessions.remove(session_id);
+            self.remove_session(session_id);
         }
 
         if !expired_sessions.is_empty() {
@@ -751,26 +1150,12 @@ impl RelayState {
         }
 
         // Clean expired offline messages
-        let mut cleaned_messages = 0usize;
-        let mut empty_queues = Vec::new();
-
-        for mut entry in self.offline_queue.iter_mut() {
-            let before = entry.value().len();
-            entry
-                .value_mut()
-                .retain(|m| now - m.queued_at.timestamp() < self.config.offline_ttl_secs);
-            cleaned_messages += before - entry.value().len();
-
-            if entry.value().is_empty() {
-                empty_queues.push(entry.key().clone());
-            }
-        }
-
-        for did in &empty_queues {
-            self.offline_queue.remove(did);
-        }
+        let cleaned_messages = self.offline_store.cleanup_expired();
 
         if cleaned_messages > 0 {
+            self.metrics
+                .offline_dropped
+                .add(&["expired"], cleaned_messages as u64);
             tracing::debug!(
                 count = cleaned_messages,
                 "Cleaned up expired offline messages"
@@ -786,7 +1171,7 @@ impl RelayState {
             .collect();
 
         for room_id in &expired_rooms {
-            self.call_rooms.remove(room_id);
+            self.remove_call_room(room_id);
         }
 
         if !expired_rooms.is_empty() {
@@ -820,7 +1205,7 @@ impl RelayState {
             .collect();
 
         for code in &expired_invites {
-            self.published_invites.remove(code);
+            self.remove_invite(code);
         }
 
         if !expired_invites.is_empty() {
@@ -829,6 +1214,20 @@ impl RelayState {
                 "Cleaned up expired published invites"
             );
         }
+
+        if let Some(push) = &self.push {
+            push.cleanup();
+        }
+
+        if let Some(ice) = &self.ice {
+            let removed = ice.cleanup();
+            if removed > 0 {
+                tracing::debug!(count = removed, "Cleaned up expired TURN clients");
+            }
+        }
+
+        // Drop idle rate limit buckets
+        self.rate_limiter.cleanup();
     }
 }
 
@@ -893,6 +1292,68 @@ mod tests {
         assert!(!state.is_online("did:key:z6MkAlice"));
     }
 
+    #[test]
+    fn test_standalone_relay_directory() {
+        let state = RelayState::new(test_config());
+        let (tx, _rx) = mpsc::unbounded_channel();
+        state.register_client("did:key:z6MkAlice", "session-1", tx);
+        state.queue_offline_message("did:key:z6MkBob", "did:key:z6MkAlice", "hello", 1000);
+
+        let directory = state.relay_directory();
+        assert_eq!(directory.len(), 1);
+        assert_eq!(directory[0].relay_url, None);
+        assert_eq!(directory[0].region, "Test");
+        assert_eq!(
+            directory[0].load,
+            Some(RelayLoad {
+                online_count: 1,
+                offline_queue_size: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_evict_client_and_purge_offline_queue() {
+        let state = RelayState::new(test_config());
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx2, _rx2) = mpsc::unbounded_channel();
+        state.register_client("did:key:z6MkAlice", "session-1", tx1);
+        state.register_client("did:key:z6MkAlice", "session-2", tx2);
+        assert_eq!(state.session_count(), 2);
+
+        assert_eq!(
+            state.route_message("did:key:z6MkBob", "did:key:z6MkAlice", "hi", 1000),
+            RouteResult::DeliveredLocally
+        );
+        assert_eq!(state.evict_client("did:key:z6MkAlice"), 2);
+        assert!(!state.is_online("did:key:z6MkAlice"));
+        assert_eq!(state.evict_client("did:key:z6MkAlice"), 0);
+
+        // The session's channel is closed once the queued message is drained
+        assert!(rx1.try_recv().is_ok());
+        assert!(matches!(
+            rx1.try_recv(),
+            Err(mpsc::error::TryRecvError::Disconnected)
+        ));
+
+        assert_eq!(
+            state.route_message("did:key:z6MkBob", "did:key:z6MkAlice", "hi", 1000),
+            RouteResult::Unreachable
+        );
+        state.queue_offline_message("did:key:z6MkAlice", "did:key:z6MkBob", "a", 1000);
+        state.queue_offline_message("did:key:z6MkAlice", "did:key:z6MkBob", "b", 1000);
+        assert_eq!(state.purge_offline_messages("did:key:z6MkAlice"), 2);
+        assert_eq!(state.offline_queue_size(), 0);
+
+        let metrics = &state.metrics;
+        assert_eq!(metrics.connections.get(), 2);
+        assert_eq!(metrics.evictions.get(), 2);
+        assert_eq!(metrics.messages_routed.get(&["local"]), 1);
+        assert_eq!(metrics.messages_routed.get(&["unreachable"]), 1);
+        assert_eq!(metrics.offline_queued.get(), 2);
+        assert_eq!(metrics.offline_dropped.get(&["purged"]), 2);
+    }
+
     #[test]
     fn test_send_to_online_client() {
         let state = RelayState::new(test_config());
@@ -918,7 +1379,53 @@ mod tests {
     }
 
     #[test]
-    fn test_queue_and_drain_offline_messages() {
+    fn test_sealed_message_requires_registered_access() {
+        let state = RelayState::new(test_config());
+        let access_key = hex::encode([1u8; 32]);
+        let hash = crate::sealed_sender::access_hash(&access_key).unwrap();
+
+        // Nothing registered and no federation: refused
+        assert!(state
+            .route_sealed_message("did:key:z6MkBob", &access_key, "sealed", 1000)
+            .is_none());
+
+        state
+            .register_sealed_access("did:key:z6MkBob", &[hash])
+            .unwrap();
+        assert!(state
+            .register_sealed_access("did:key:z6MkBob", &["not-a-hash".to_string()])
+            .is_err());
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        state.register_client("did:key:z6MkBob", "session-1", tx);
+
+        let wrong_key = hex::encode([2u8; 32]);
+        assert!(state
+            .route_sealed_message("did:key:z6MkBob", &wrong_key, "sealed", 1000)
+            .is_none());
+        assert!(rx.try_recv().is_err());
+
+        assert!(matches!(
+            state.route_sealed_message("did:key:z6MkBob", &access_key, "sealed", 1000),
+            Some(RouteResult::DeliveredLocally)
+        ));
+        match rx.try_recv().unwrap() {
+            ServerMessage::SealedMessage { payload, .. } => assert_eq!(payload, "sealed"),
+            _ => panic!("Expected SealedMessage"),
+        }
+
+        // Offline: reported unreachable so the caller can queue it
+        state.unregister_client("did:key:z6MkBob", "session-1");
+        assert!(matches!(
+            state.route_sealed_message("did:key:z6MkBob", &access_key, "sealed", 1000),
+            Some(RouteResult::Unreachable)
+        ));
+
+        assert_eq!(state.metrics.sealed_denied.get(), 2);
+    }
+
+    #[test]
+    fn test_queue_and_ack_offline_messages() {
         let state = RelayState::new(test_config());
 
         state.queue_offline_message("did:key:z6MkBob", "did:key:z6MkAlice", "hello", 1000);
@@ -926,13 +1433,19 @@ mod tests {
 
         assert_eq!(state.offline_queue_size(), 2);
 
-        let messages = state.drain_offline_messages("did:key:z6MkBob");
+        let messages = state.pending_offline_messages("did:key:z6MkBob");
         assert_eq!(messages.len(), 2);
         assert_eq!(messages[0].payload, "hello");
         assert_eq!(messages[1].payload, "world");
 
-        // Should be empty after drain
-        let messages = state.drain_offline_messages("did:key:z6MkBob");
+        // Fetching doesn't remove anything until the client acks
+        assert_eq!(state.pending_offline_messages("did:key:z6MkBob").len(), 2);
+
+        let ids: Vec<String> = messages.iter().map(|m| m.id.clone()).collect();
+        assert_eq!(state.ack_offline_messages("did:key:z6MkBob", &ids), 2);
+
+        // Should be empty after ack
+        let messages = state.pending_offline_messages("did:key:z6MkBob");
         assert!(messages.is_empty());
         assert_eq!(state.offline_queue_size(), 0);
     }
@@ -943,19 +1456,38 @@ mod tests {
 
         // Queue 6 messages (limit is 5)
         for i in 0..6 {
-            state.queue_offline_message(
+            let queued = state.queue_offline_message(
                 "did:key:z6MkBob",
                 "did:key:z6MkAlice",
                 &format!("msg-{}", i),
                 i as i64,
             );
+            // The sixth is rejected rather than evicting an older one
+            assert_eq!(queued, i < 5);
         }
 
-        let messages = state.drain_offline_messages("did:key:z6MkBob");
+        let messages = state.pending_offline_messages("did:key:z6MkBob");
         assert_eq!(messages.len(), 5);
-        // Oldest should have been dropped
-        assert_eq!(messages[0].payload, "msg-1");
-        assert_eq!(messages[4].payload, "msg-5");
+        assert_eq!(messages[0].payload, "msg-0");
+        assert_eq!(messages[4].payload, "msg-4");
+
+        // Acking frees up quota
+        state.ack_offline_messages("did:key:z6MkBob", &[messages[0].id.clone()]);
+        assert!(state.queue_offline_message("did:key:z6MkBob", "did:key:z6MkAlice", "msg-6", 6));
+    }
+
+    #[test]
+    fn test_cleanup_removes_expired_offline_messages() {
+        let state = RelayState::new(RelayConfig {
+            offline_ttl_secs: -1, // Expire immediately
+            ..test_config()
+        });
+
+        state.queue_offline_message("did:key:z6MkBob", "did:key:z6MkAlice", "hello", 1000);
+        assert!(state.pending_offline_messages("did:key:z6MkBob").is_empty());
+
+        state.cleanup_expired();
+        assert_eq!(state.offline_queue_size(), 0);
     }
 
     #[test]
@@ -1003,10 +1535,94 @@ mod tests {
         assert!(!state.sessions.contains_key(&session_id));
     }
 
+    fn signed_invite(key: &ed25519_dalek::SigningKey, code: &str) -> PublishedInvite {
+        use base64::Engine;
+        use ed25519_dalek::Signer;
+
+        let mut invite = PublishedInvite {
+            code: code.to_string(),
+            publisher_did: crate::sync::auth::did_for(key),
+            community_id: "comm-1".to_string(),
+            community_name: "Test".to_string(),
+            community_description: None,
+            community_icon: None,
+            member_count: 3,
+            max_uses: None,
+            use_count: 0,
+            expires_at: None,
+            invite_payload: "{}".to_string(),
+            published_at: Utc::now(),
+            signature: String::new(),
+        };
+        let signature = key.sign(invite.signing_message().as_bytes());
+        invite.signature = base64::engine::general_purpose::STANDARD.encode(signature.to_bytes());
+        invite
+    }
+
     #[test]
-    fn test_drain_offline_with_no_messages() {
+    fn test_publish_invite_requires_owner_signature() {
         let state = RelayState::new(test_config());
-        let messages = state.drain_offline_messages("did:key:z6MkNobody");
+        let owner = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+
+        assert!(state.publish_invite(signed_invite(&owner, "good")));
+        assert!(state.resolve_invite("good").is_some());
+
+        // Tampering with a signed field breaks the signature
+        let mut tampered = signed_invite(&owner, "tampered");
+        tampered.community_name = "Not Test".to_string();
+        assert!(!state.publish_invite(tampered));
+
+        // So does claiming someone else's invite
+        let other = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+        let mut forged = signed_invite(&owner, "forged");
+        forged.publisher_did = crate::sync::auth::did_for(&other);
+        state.import_invite(forged);
+
+        assert!(state.resolve_invite("tampered").is_none());
+        assert!(state.resolve_invite("forged").is_none());
+    }
+
+    #[test]
+    fn test_invites_and_sessions_survive_restart() {
+        let dir = std::env::temp_dir().join(format!("umbra-relay-state-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+        let owner = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+
+        let session_id = {
+            let state = RelayState::new(test_config())
+                .with_state_store(StateStore::new(Some(dir_str)).unwrap());
+            state.publish_invite(signed_invite(&owner, "kept"));
+            state.publish_invite(signed_invite(&owner, "revoked"));
+            state.revoke_invite("revoked");
+
+            let session_id = state.create_session("did:key:z6MkAlice", "offer");
+            state.consume_session(&session_id);
+            session_id
+        };
+
+        let state = RelayState::new(test_config())
+            .with_state_store(StateStore::new(Some(dir_str)).unwrap());
+        assert!(state.resolve_invite("kept").unwrap().verify_signature());
+        assert!(state.resolve_invite("revoked").is_none());
+        assert!(state.sessions.get(&session_id).unwrap().consumed);
+
+        // Expired entries are removed from the store as well
+        let state = RelayState::new(RelayConfig {
+            session_ttl_secs: -1,
+            ..test_config()
+        })
+        .with_state_store(StateStore::new(Some(dir_str)).unwrap());
+        state.cleanup_expired();
+        assert!(state.state_store.load_sessions().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_pending_offline_with_no_messages() {
+        let state = RelayState::new(test_config());
+        let messages = state.pending_offline_messages("did:key:z6MkNobody");
         assert!(messages.is_empty());
     }
 
@@ -1015,7 +1631,7 @@ mod tests {
     #[test]
     fn test_create_call_room() {
         let state = RelayState::new(test_config());
-        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice");
+        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice", CallRoomMode::Mesh);
         assert!(!room_id.is_empty());
 
         let participants = state.get_call_room_participants(&room_id).unwrap();
@@ -1026,7 +1642,7 @@ mod tests {
     #[test]
     fn test_join_call_room() {
         let state = RelayState::new(test_config());
-        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice");
+        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice", CallRoomMode::Mesh);
 
         let existing = state.join_call_room(&room_id, "did:key:z6MkBob").unwrap();
         assert_eq!(existing.len(), 1);
@@ -1039,7 +1655,7 @@ mod tests {
     #[test]
     fn test_join_call_room_no_duplicates() {
         let state = RelayState::new(test_config());
-        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice");
+        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice", CallRoomMode::Mesh);
 
         state.join_call_room(&room_id, "did:key:z6MkAlice");
         let participants = state.get_call_room_participants(&room_id).unwrap();
@@ -1049,7 +1665,7 @@ mod tests {
     #[test]
     fn test_leave_call_room() {
         let state = RelayState::new(test_config());
-        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice");
+        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice", CallRoomMode::Mesh);
         state.join_call_room(&room_id, "did:key:z6MkBob");
 
         let remaining = state.leave_call_room(&room_id, "did:key:z6MkAlice");
@@ -1060,7 +1676,7 @@ mod tests {
     #[test]
     fn test_leave_call_room_removes_empty() {
         let state = RelayState::new(test_config());
-        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice");
+        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice", CallRoomMode::Mesh);
 
         state.leave_call_room(&room_id, "did:key:z6MkAlice");
         assert!(state.get_call_room_participants(&room_id).is_none());
@@ -1069,7 +1685,7 @@ mod tests {
     #[test]
     fn test_is_in_call_room() {
         let state = RelayState::new(test_config());
-        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice");
+        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice", CallRoomMode::Mesh);
 
         assert!(state.is_in_call_room(&room_id, "did:key:z6MkAlice"));
         assert!(!state.is_in_call_room(&room_id, "did:key:z6MkBob"));
@@ -1078,8 +1694,8 @@ mod tests {
     #[test]
     fn test_remove_from_all_call_rooms() {
         let state = RelayState::new(test_config());
-        let room1 = state.create_call_room("group-1", "did:key:z6MkAlice");
-        let room2 = state.create_call_room("group-2", "did:key:z6MkAlice");
+        let room1 = state.create_call_room("group-1", "did:key:z6MkAlice", CallRoomMode::Mesh);
+        let room2 = state.create_call_room("group-2", "did:key:z6MkAlice", CallRoomMode::Mesh);
         state.join_call_room(&room1, "did:key:z6MkBob");
 
         let affected = state.remove_from_all_call_rooms("did:key:z6MkAlice");
@@ -1094,6 +1710,28 @@ mod tests {
         assert!(state.get_call_room_participants(&room2).is_none());
     }
 
+    #[test]
+    fn test_sfu_call_room_uses_sfu_limit() {
+        let sfu = SfuLink::new(
+            "ws://127.0.0.1:9/control".to_string(),
+            "s3cret".to_string(),
+            2,
+        );
+        let state = RelayState::new(test_config()).with_sfu(sfu);
+        let room_id = state.create_call_room("group-1", "did:key:z6MkAlice", CallRoomMode::Sfu);
+        assert_eq!(state.call_room_mode(&room_id), Some(CallRoomMode::Sfu));
+
+        assert!(state.join_call_room(&room_id, "did:key:z6MkBob").is_some());
+        assert!(state
+            .join_call_room(&room_id, "did:key:z6MkCarol")
+            .is_none());
+
+        // Leaving works while the SFU is unreachable
+        state.leave_call_room(&room_id, "did:key:z6MkAlice");
+        state.leave_call_room(&room_id, "did:key:z6MkBob");
+        assert!(state.call_room_mode(&room_id).is_none());
+    }
+
     #[test]
     fn test_join_nonexistent_room() {
         let state = RelayState::new(test_config());
@@ -0,0 +1,295 @@
+//! SQLite-backed storage for published invites and signaling sessions.
+//!
+//! `RelayState` keeps both in DashMaps for lookups and writes every change
+//! through to this store, so they survive a restart of the whole mesh
+//! (federation only re-syncs invites between relays that are running).
+//! The maps are filled from here at startup; expiry is still driven by
+//! `RelayState::cleanup_expired`.
+//!
+//! Invites are stored with their publisher's signature so they can be
+//! replicated again after a restart.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use crate::protocol::{PublishedInvite, SignalingSession};
+
+pub struct StateStore {
+    conn: Mutex<Connection>,
+}
+
+impl StateStore {
+    /// Create a new store. If `data_dir` is provided, uses a file-backed DB;
+    /// otherwise uses in-memory SQLite.
+    pub fn new(data_dir: Option<&str>) -> Result<Self, rusqlite::Error> {
+        let conn = if let Some(dir) = data_dir {
+            let path = Path::new(dir).join("relay_state.db");
+            Connection::open(path)?
+        } else {
+            Connection::open_in_memory()?
+        };
+
+        let store = Self {
+            conn: Mutex::new(conn),
+        };
+
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn init_schema(&self) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS published_invites (
+                code TEXT PRIMARY KEY,
+                publisher_did TEXT NOT NULL,
+                community_id TEXT NOT NULL,
+                community_name TEXT NOT NULL,
+                community_description TEXT,
+                community_icon TEXT,
+                member_count INTEGER NOT NULL,
+                max_uses INTEGER,
+                use_count INTEGER NOT NULL,
+                expires_at INTEGER,
+                invite_payload TEXT NOT NULL,
+                published_at INTEGER NOT NULL,
+                signature TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS signaling_sessions (
+                id TEXT PRIMARY KEY,
+                creator_did TEXT NOT NULL,
+                offer_payload TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                consumed INTEGER NOT NULL
+            );
+            ",
+        )?;
+
+        Ok(())
+    }
+
+    // ── Published Invites ─────────────────────────────────────────────────
+
+    /// Insert or replace a published invite.
+    pub fn save_invite(&self, invite: &PublishedInvite) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO published_invites
+                (code, publisher_did, community_id, community_name, community_description,
+                 community_icon, member_count, max_uses, use_count, expires_at,
+                 invite_payload, published_at, signature)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                invite.code,
+                invite.publisher_did,
+                invite.community_id,
+                invite.community_name,
+                invite.community_description,
+                invite.community_icon,
+                invite.member_count,
+                invite.max_uses,
+                invite.use_count,
+                invite.expires_at,
+                invite.invite_payload,
+                invite.published_at.timestamp(),
+                invite.signature
+            ],
+        )
+        .map_err(|e| format!("Failed to save invite: {}", e))?;
+        Ok(())
+    }
+
+    /// Delete a published invite. Returns whether it existed.
+    pub fn remove_invite(&self, code: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM published_invites WHERE code = ?1",
+            params![code],
+        )
+        .map(|n| n > 0)
+        .map_err(|e| format!("Failed to remove invite: {}", e))
+    }
+
+    /// Every stored invite, expired or not.
+    pub fn load_invites(&self) -> Result<Vec<PublishedInvite>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT code, publisher_did, community_id, community_name, community_description,
+                        community_icon, member_count, max_uses, use_count, expires_at,
+                        invite_payload, published_at, signature
+                 FROM published_invites",
+            )
+            .map_err(|e| format!("Failed to prepare invite query: {}", e))?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(PublishedInvite {
+                    code: row.get(0)?,
+                    publisher_did: row.get(1)?,
+                    community_id: row.get(2)?,
+                    community_name: row.get(3)?,
+                    community_description: row.get(4)?,
+                    community_icon: row.get(5)?,
+                    member_count: row.get(6)?,
+                    max_uses: row.get(7)?,
+                    use_count: row.get(8)?,
+                    expires_at: row.get(9)?,
+                    invite_payload: row.get(10)?,
+                    published_at: DateTime::from_timestamp(row.get(11)?, 0)
+                        .unwrap_or_else(Utc::now),
+                    signature: row.get(12)?,
+                })
+            })
+            .map_err(|e| format!("Failed to load invites: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read invite: {}", e))
+    }
+
+    // ── Signaling Sessions ────────────────────────────────────────────────
+
+    /// Insert or replace a signaling session.
+    pub fn save_session(&self, session: &SignalingSession) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO signaling_sessions
+                (id, creator_did, offer_payload, created_at, consumed)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                session.id,
+                session.creator_did,
+                session.offer_payload,
+                session.created_at.timestamp(),
+                session.consumed
+            ],
+        )
+        .map_err(|e| format!("Failed to save session: {}", e))?;
+        Ok(())
+    }
+
+    /// Mark a session as consumed.
+    pub fn consume_session(&self, session_id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE signaling_sessions SET consumed = 1 WHERE id = ?1",
+            params![session_id],
+        )
+        .map_err(|e| format!("Failed to consume session: {}", e))?;
+        Ok(())
+    }
+
+    /// Delete a signaling session.
+    pub fn remove_session(&self, session_id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM signaling_sessions WHERE id = ?1",
+            params![session_id],
+        )
+        .map_err(|e| format!("Failed to remove session: {}", e))?;
+        Ok(())
+    }
+
+    /// Every stored session, expired or not.
+    pub fn load_sessions(&self) -> Result<Vec<SignalingSession>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT id, creator_did, offer_payload, created_at, consumed FROM signaling_sessions")
+            .map_err(|e| format!("Failed to prepare session query: {}", e))?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(SignalingSession {
+                    id: row.get(0)?,
+                    creator_did: row.get(1)?,
+                    offer_payload: row.get(2)?,
+                    created_at: DateTime::from_timestamp(row.get(3)?, 0).unwrap_or_else(Utc::now),
+                    consumed: row.get(4)?,
+                })
+            })
+            .map_err(|e| format!("Failed to load sessions: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read session: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn invite(code: &str) -> PublishedInvite {
+        PublishedInvite {
+            code: code.to_string(),
+            publisher_did: "did:key:z6MkOwner".to_string(),
+            community_id: "comm-1".to_string(),
+            community_name: "Test".to_string(),
+            community_description: Some("A test".to_string()),
+            community_icon: None,
+            member_count: 3,
+            max_uses: Some(10),
+            use_count: 0,
+            expires_at: None,
+            invite_payload: "{}".to_string(),
+            published_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            signature: "c2ln".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_invites_and_sessions_survive_reopen() {
+        let dir = std::env::temp_dir().join(format!("umbra-state-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        {
+            let store = StateStore::new(Some(dir_str)).unwrap();
+            store.save_invite(&invite("abc")).unwrap();
+            store.save_invite(&invite("gone")).unwrap();
+            store
+                .save_invite(&PublishedInvite {
+                    use_count: 4,
+                    ..invite("abc")
+                })
+                .unwrap();
+            assert!(store.remove_invite("gone").unwrap());
+            assert!(!store.remove_invite("gone").unwrap());
+
+            store
+                .save_session(&SignalingSession {
+                    id: "session-1".to_string(),
+                    creator_did: "did:key:z6MkAlice".to_string(),
+                    offer_payload: "offer".to_string(),
+                    created_at: Utc::now(),
+                    consumed: false,
+                })
+                .unwrap();
+            store.consume_session("session-1").unwrap();
+        }
+
+        let store = StateStore::new(Some(dir_str)).unwrap();
+        let invites = store.load_invites().unwrap();
+        assert_eq!(invites.len(), 1);
+        assert_eq!(invites[0].code, "abc");
+        assert_eq!(invites[0].use_count, 4);
+        assert_eq!(invites[0].community_description.as_deref(), Some("A test"));
+        assert_eq!(invites[0].published_at, invite("abc").published_at);
+        assert_eq!(invites[0].signature, "c2ln");
+
+        let sessions = store.load_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].consumed);
+
+        store.remove_session("session-1").unwrap();
+        assert!(store.load_sessions().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,9 +1,16 @@
-//! Sync auth helpers — Ed25519 signature verification and Bearer token extraction.
+//! Auth helpers — Ed25519 signature verification, did:key decoding and
+//! Bearer token extraction.
+//!
+//! Used by the sync REST endpoints and by the WebSocket registration
+//! challenge in `handler.rs`.
 
 use axum::http::{HeaderMap, StatusCode};
 use base64::Engine;
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 
+/// Multicodec prefix for Ed25519 public keys in a `did:key`.
+const ED25519_MULTICODEC_PREFIX: [u8; 2] = [0xed, 0x01];
+
 /// Extract a Bearer token from the Authorization header.
 pub fn extract_bearer_token(headers: &HeaderMap) -> Result<String, (StatusCode, &'static str)> {
     let auth = headers
@@ -51,3 +58,80 @@ pub fn verify_ed25519_signature(
 
     Ok(verifying_key.verify(nonce.as_bytes(), &signature).is_ok())
 }
+
+/// Extract the Ed25519 public key embedded in a `did:key` DID.
+///
+/// Only base58btc-encoded (`z`-prefixed) Ed25519 keys are supported,
+/// which is what Umbra clients generate.
+pub fn ed25519_public_key_from_did(did: &str) -> Result<[u8; 32], String> {
+    let encoded = did
+        .strip_prefix("did:key:z")
+        .ok_or_else(|| "Only base58btc did:key DIDs are supported".to_string())?;
+
+    let decoded = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|e| format!("Invalid did:key encoding: {}", e))?;
+
+    if decoded.len() != 34 || decoded[..2] != ED25519_MULTICODEC_PREFIX {
+        return Err("DID does not contain an Ed25519 key".to_string());
+    }
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&decoded[2..]);
+    Ok(key)
+}
+
+/// Verify an Ed25519 signature made with the key embedded in `did`.
+/// `signature_b64` is base64-encoded.
+pub fn verify_did_signature(did: &str, message: &str, signature_b64: &str) -> Result<bool, String> {
+    let public_key = ed25519_public_key_from_did(did)?;
+    let public_key_b64 = base64::engine::general_purpose::STANDARD.encode(public_key);
+
+    verify_ed25519_signature(message, &public_key_b64, signature_b64)
+}
+
+/// The `did:key` for an Ed25519 key (for tests that sign as a client).
+#[cfg(test)]
+pub(crate) fn did_for(key: &ed25519_dalek::SigningKey) -> String {
+    let mut bytes = ED25519_MULTICODEC_PREFIX.to_vec();
+    bytes.extend_from_slice(key.verifying_key().as_bytes());
+    format!("did:key:z{}", bs58::encode(bytes).into_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn test_public_key_from_did() {
+        let key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let did = did_for(&key);
+
+        let public_key = ed25519_public_key_from_did(&did).unwrap();
+        assert_eq!(&public_key, key.verifying_key().as_bytes());
+    }
+
+    #[test]
+    fn test_public_key_from_invalid_did() {
+        assert!(ed25519_public_key_from_did("did:web:example.com").is_err());
+        assert!(ed25519_public_key_from_did("did:key:z0OIl").is_err());
+        assert!(ed25519_public_key_from_did("did:key:z6Mk").is_err());
+    }
+
+    #[test]
+    fn test_verify_did_signature() {
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let did = did_for(&key);
+        let signature = b64.encode(key.sign(b"nonce-123").to_bytes());
+
+        assert!(verify_did_signature(&did, "nonce-123", &signature).unwrap());
+        assert!(!verify_did_signature(&did, "nonce-456", &signature).unwrap());
+
+        // A signature from a different key doesn't verify for this DID
+        let other = SigningKey::generate(&mut rand::rngs::OsRng);
+        let forged = b64.encode(other.sign(b"nonce-123").to_bytes());
+        assert!(!verify_did_signature(&did, "nonce-123", &forged).unwrap());
+    }
+}
@@ -165,6 +165,17 @@ impl SyncBlobStore {
         Ok(affected > 0)
     }
 
+    /// Number of stored blobs and their total size in bytes.
+    pub fn usage(&self) -> (u64, u64) {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM sync_blobs",
+            [],
+            |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+        )
+        .unwrap_or((0, 0))
+    }
+
     // ── Challenge-Response Auth ──────────────────────────────────────────────
 
     /// Create an auth challenge nonce for a DID.
@@ -294,10 +294,12 @@ export async function updateCategory(
 export async function reorderCategories(
   spaceId: string,
   categoryIds: string[],
+  actorDid: string,
 ): Promise<void> {
   const json = JSON.stringify({
     space_id: spaceId,
     category_ids: categoryIds,
+    actor_did: actorDid,
   });
   wasm().umbra_wasm_community_category_reorder(json);
 }
@@ -416,10 +418,15 @@ export async function deleteChannel(channelId: string, actorDid: string): Promis
 /**
  * Reorder channels within a space.
  */
-export async function reorderChannels(spaceId: string, channelIds: string[]): Promise<void> {
+export async function reorderChannels(
+  spaceId: string,
+  channelIds: string[],
+  actorDid: string,
+): Promise<void> {
   const json = JSON.stringify({
     space_id: spaceId,
     channel_ids: channelIds,
+    actor_did: actorDid,
   });
   wasm().umbra_wasm_community_channel_reorder(json);
 }
@@ -826,6 +833,8 @@ export interface RelayInviteResolution {
  * This is called after creating an invite locally. The relay stores the
  * invite metadata and propagates it across the federation mesh so any
  * client can resolve the code, even if the community owner is offline.
+ * The invite is signed with the identity key; relays reject unsigned or
+ * altered invites.
  *
  * @param relayWs - The active relay WebSocket connection
  * @param invite - The local invite record just created
@@ -835,7 +844,7 @@ export interface RelayInviteResolution {
  * @param memberCount - Current member count
  * @param invitePayload - The full invite bootstrap payload (community structure JSON)
  */
-export function publishInviteToRelay(
+export async function publishInviteToRelay(
   relayWs: WebSocket | null,
   invite: CommunityInvite,
   communityName: string,
@@ -843,7 +852,7 @@ export function publishInviteToRelay(
   communityIcon?: string | null,
   memberCount?: number,
   invitePayload?: string,
-): void {
+): Promise<void> {
   if (!relayWs || relayWs.readyState !== WebSocket.OPEN) {
     console.warn(
       '[publishInviteToRelay] Relay WS not connected — invite not published:',
@@ -853,8 +862,7 @@ export function publishInviteToRelay(
     return;
   }
 
-  const msg = {
-    type: 'publish_invite',
+  const relayInvite = {
     code: invite.code,
     community_id: invite.communityId,
     community_name: communityName,
@@ -866,8 +874,11 @@ export function publishInviteToRelay(
     invite_payload: invitePayload ?? '{}',
   };
 
+  const resultJson = await wasm().umbra_wasm_relay_publish_invite(JSON.stringify(relayInvite));
+  const { relayMessage } = await parseWasm<{ relayMessage: string }>(resultJson);
+
   console.log('[publishInviteToRelay] Publishing invite to relay:', invite.code);
-  relayWs.send(JSON.stringify(msg));
+  relayWs.send(relayMessage);
 }
 
 /**
@@ -1024,8 +1035,8 @@ export async function editMessage(
 /**
  * Delete a message.
  */
-export async function deleteMessage(messageId: string): Promise<void> {
-  wasm().umbra_wasm_community_message_delete(messageId);
+export async function deleteMessage(messageId: string, actorDid: string): Promise<void> {
+  wasm().umbra_wasm_community_message_delete(messageId, actorDid);
 }
 
 // =============================================================================
@@ -1090,10 +1101,12 @@ export async function pinMessage(
 export async function unpinMessage(
   messageId: string,
   channelId: string,
+  actorDid: string,
 ): Promise<void> {
   const json = JSON.stringify({
     message_id: messageId,
     channel_id: channelId,
+    actor_did: actorDid,
   });
   wasm().umbra_wasm_community_unpin_message(json);
 }
@@ -1287,8 +1300,8 @@ export async function getFolders(
 /**
  * Delete a folder (and optionally its contents).
  */
-export async function deleteFolder(id: string): Promise<void> {
-  const json = JSON.stringify({ id });
+export async function deleteFolder(id: string, actorDid: string): Promise<void> {
+  const json = JSON.stringify({ id, actor_did: actorDid });
   wasm().umbra_wasm_community_delete_folder(json);
 }
 
@@ -2006,10 +2019,11 @@ export async function createSeatsBatch(
     nickname?: string;
     avatar_url?: string;
     role_ids: string[];
-  }>
+  }>,
+  actorDid: string,
 ): Promise<number> {
   const resultJson = wasm().umbra_wasm_community_seat_create_batch(
-    JSON.stringify({ community_id: communityId, seats })
+    JSON.stringify({ community_id: communityId, seats, actor_did: actorDid })
   );
   const result = await parseWasm<{ created: number }>(resultJson);
   return result.created;
@@ -2137,10 +2151,15 @@ export async function deleteEmoji(emojiId: string, actorDid: string): Promise<vo
 /**
  * Rename a custom emoji.
  */
-export async function renameEmoji(emojiId: string, newName: string): Promise<void> {
+export async function renameEmoji(
+  emojiId: string,
+  newName: string,
+  actorDid: string,
+): Promise<void> {
   const json = JSON.stringify({
     emoji_id: emojiId,
     new_name: newName,
+    actor_did: actorDid,
   });
   wasm().umbra_wasm_community_emoji_rename(json);
 }
@@ -2209,8 +2228,8 @@ export async function listStickers(communityId: string): Promise<CommunitySticke
 /**
  * Delete a custom sticker.
  */
-export async function deleteSticker(stickerId: string): Promise<void> {
-  wasm().umbra_wasm_community_sticker_delete(stickerId);
+export async function deleteSticker(stickerId: string, actorDid: string): Promise<void> {
+  wasm().umbra_wasm_community_sticker_delete(stickerId, actorDid);
 }
 
 /**
@@ -2277,15 +2296,19 @@ export async function listStickerPacks(communityId: string): Promise<StickerPack
 /**
  * Delete a sticker pack.
  */
-export async function deleteStickerPack(packId: string): Promise<void> {
-  wasm().umbra_wasm_community_sticker_pack_delete(packId);
+export async function deleteStickerPack(packId: string, actorDid: string): Promise<void> {
+  wasm().umbra_wasm_community_sticker_pack_delete(packId, actorDid);
 }
 
 /**
  * Rename a sticker pack.
  */
-export async function renameStickerPack(packId: string, newName: string): Promise<void> {
-  const json = JSON.stringify({ pack_id: packId, new_name: newName });
+export async function renameStickerPack(
+  packId: string,
+  newName: string,
+  actorDid: string,
+): Promise<void> {
+  const json = JSON.stringify({ pack_id: packId, new_name: newName, actor_did: actorDid });
   wasm().umbra_wasm_community_sticker_pack_rename(json);
 }
 
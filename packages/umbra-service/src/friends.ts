@@ -10,6 +10,7 @@ import type {
   Friend,
   FriendEvent,
   BlockedUser,
+  KeyRotation,
 } from './types';
 
 /**
@@ -257,18 +258,17 @@ export async function sendFriendAcceptAck(
 /**
  * Update a friend's encryption key after receiving a key_rotation envelope.
  *
- * Verifies the signature using the friend's (unchanged) Ed25519 signing key,
- * then updates the X25519 encryption key in the database.
+ * Verifies the signed rotation using the friend's (unchanged) Ed25519 signing
+ * key, then updates the X25519 encryption key in the database. Rotations no
+ * newer than the last one applied are ignored.
  */
 export async function updateFriendEncryptionKey(
   fromDid: string,
-  newEncryptionKey: string,
-  signature: string,
+  rotation: KeyRotation,
 ): Promise<void> {
   const json = JSON.stringify({
     from_did: fromDid,
-    new_encryption_key: newEncryptionKey,
-    signature,
+    rotation,
   });
   const resultJson = wasm().umbra_wasm_friends_update_encryption_key(json);
   await parseWasm<{ updated: boolean }>(resultJson);
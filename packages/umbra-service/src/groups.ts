@@ -466,24 +466,121 @@ export async function sendGroupFileMessage(
  * encryption, and building both `group_key_rotation` and
  * `group_member_removed` envelopes for all remaining members.
  *
+ * For groups with an MLS session, Rust instead builds a single
+ * `group_mls_commit` that re-keys the group for everyone left in it.
+ *
  * @param groupId - Group ID
  * @param memberDid - DID of the member to remove
  * @param relayWs - WebSocket for relay notifications
+ * @returns DIDs of members the rotated key couldn't be sent to
  */
 export async function removeGroupMemberWithRotation(
   groupId: string,
   memberDid: string,
   relayWs?: WebSocket | null
-): Promise<void> {
+): Promise<string[]> {
   const json = JSON.stringify({ group_id: groupId, member_did: memberDid });
   const resultJson = wasm().umbra_wasm_groups_remove_member_with_rotation(json);
   const raw = await parseWasm<{
     keyVersion: number;
+    unreachableMembers?: string[];
     relayMessages: Array<{ toDid: string; payload: string }>;
   }>(resultJson);
 
-  if (relayWs && relayWs.readyState === WebSocket.OPEN && raw.relayMessages) {
-    for (const rm of raw.relayMessages) {
+  sendRelayMessages(raw.relayMessages, relayWs);
+  return raw.unreachableMembers ?? [];
+}
+
+/**
+ * Add a member to a group's MLS session.
+ *
+ * Called by the admin when a `group_invite_accept` carries a key package.
+ * Sends the `group_mls_welcome` to the new member and the
+ * `group_mls_commit` to everyone else.
+ *
+ * @param groupId - Group ID
+ * @param memberDid - DID of the new member
+ * @param keyPackage - Key package from their acceptance (hex)
+ * @param relayWs - WebSocket for relay notifications
+ * @returns The group's new epoch
+ */
+export async function addGroupMemberMls(
+  groupId: string,
+  memberDid: string,
+  keyPackage: string,
+  relayWs?: WebSocket | null
+): Promise<number> {
+  const json = JSON.stringify({ group_id: groupId, member_did: memberDid, key_package: keyPackage });
+  const resultJson = wasm().umbra_wasm_groups_mls_add_member(json);
+  const raw = await parseWasm<{
+    epoch: number;
+    relayMessages: Array<{ toDid: string; payload: string }>;
+  }>(resultJson);
+
+  sendRelayMessages(raw.relayMessages, relayWs);
+  return raw.epoch;
+}
+
+/**
+ * Join a group's MLS session from a `group_mls_welcome`.
+ *
+ * @param welcome - Encoded welcome (hex)
+ * @returns The group and the epoch we joined at
+ */
+export async function processGroupMlsWelcome(
+  welcome: string
+): Promise<{ groupId: string; epoch: number; members: string[] }> {
+  const json = JSON.stringify({ welcome });
+  const resultJson = wasm().umbra_wasm_groups_mls_process_welcome(json);
+  return await parseWasm<{ groupId: string; epoch: number; members: string[] }>(resultJson);
+}
+
+/**
+ * Apply a `group_mls_commit` from the group admin.
+ *
+ * @param groupId - Group ID
+ * @param commit - Encoded commit (hex)
+ * @returns `removed` if the commit removed us from the group
+ */
+export async function processGroupMlsCommit(
+  groupId: string,
+  commit: string
+): Promise<{ status: 'applied' | 'removed'; committer: string }> {
+  const json = JSON.stringify({ group_id: groupId, commit });
+  const resultJson = wasm().umbra_wasm_groups_mls_process_commit(json);
+  return await parseWasm<{ status: 'applied' | 'removed'; committer: string }>(resultJson);
+}
+
+/**
+ * Refresh our keys in a group's MLS session.
+ *
+ * Recovers the group's secrecy if our device's keys may have leaked.
+ *
+ * @param groupId - Group ID
+ * @param relayWs - WebSocket for relay notifications
+ * @returns The group's new epoch
+ */
+export async function updateGroupMlsKeys(
+  groupId: string,
+  relayWs?: WebSocket | null
+): Promise<number> {
+  const json = JSON.stringify({ group_id: groupId });
+  const resultJson = wasm().umbra_wasm_groups_mls_update(json);
+  const raw = await parseWasm<{
+    epoch: number;
+    relayMessages: Array<{ toDid: string; payload: string }>;
+  }>(resultJson);
+
+  sendRelayMessages(raw.relayMessages, relayWs);
+  return raw.epoch;
+}
+
+function sendRelayMessages(
+  relayMessages: Array<{ toDid: string; payload: string }> | undefined,
+  relayWs?: WebSocket | null
+): void {
+  if (relayWs && relayWs.readyState === WebSocket.OPEN && relayMessages) {
+    for (const rm of relayMessages) {
       try {
         relayWs.send(JSON.stringify({ type: 'send', to_did: rm.toDid, payload: rm.payload }));
       } catch (err) {
@@ -11,6 +11,8 @@ import type {
   Conversation,
   MessageReaction,
   MessageEvent,
+  MessageSearchResult,
+  MessageSearchSource,
   ChatMessagePayload,
 } from './types';
 
@@ -523,6 +525,30 @@ export async function updateMessageStatus(messageId: string, status: 'sent' | 'd
   wasm().umbra_wasm_messaging_update_status(json);
 }
 
+/**
+ * Search DMs, groups, and community channels the user can read
+ *
+ * Supports `"exact phrases"` and `prefix*` terms; everything else is
+ * matched literally. DM and group messages become searchable once they
+ * have been sent or decrypted on this device.
+ *
+ * @param query - Search text
+ * @param options - Sources to search (default: all) and max results (default: 50)
+ * @returns Results ranked best match first
+ */
+export async function searchMessages(
+  query: string,
+  options: { sources?: MessageSearchSource[]; limit?: number } = {}
+): Promise<MessageSearchResult[]> {
+  const json = JSON.stringify({
+    query,
+    sources: options.sources,
+    limit: options.limit,
+  });
+  const resultJson = await wasm().umbra_wasm_search(json);
+  return parseWasm<MessageSearchResult[]>(resultJson);
+}
+
 /**
  * Send a delivery receipt to the message sender via relay.
  *
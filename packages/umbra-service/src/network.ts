@@ -28,6 +28,16 @@ export async function stopNetwork(): Promise<void> {
   console.log('[UmbraService] Network stopped');
 }
 
+/**
+ * Use the relay's STUN/TURN servers for WebRTC connections
+ *
+ * Pass the `ice_servers` from the relay's `registered` message; they carry
+ * TURN credentials minted for our DID.
+ */
+export function setIceServers(iceServers: unknown[]): void {
+  wasm().umbra_wasm_network_set_ice_servers(JSON.stringify(iceServers));
+}
+
 /**
  * Create a WebRTC offer for signaling (step 1 of connection)
  *
@@ -90,6 +90,19 @@ export async function relaySend(
   return await parseWasm<{ relayMessage: string }>(resultJson);
 }
 
+/**
+ * Answer the relay's registration challenge
+ *
+ * @param nonce - Nonce from the relay's `auth_challenge` message
+ * @returns Relay message to send via WebSocket
+ */
+export async function relayAuthenticate(
+  nonce: string
+): Promise<{ relayMessage: string }> {
+  const resultJson = await wasm().umbra_wasm_relay_authenticate(nonce);
+  return await parseWasm<{ relayMessage: string }>(resultJson);
+}
+
 /**
  * Fetch offline messages from the relay
  *
@@ -99,6 +112,19 @@ export async function relayFetchOffline(): Promise<string> {
   return wasm().umbra_wasm_relay_fetch_offline();
 }
 
+/**
+ * Acknowledge offline messages so the relay deletes them
+ *
+ * The relay redelivers offline messages on every fetch until they are
+ * acknowledged, so call this once a batch has been processed.
+ *
+ * @param ids - IDs of the processed offline messages
+ * @returns The ack_offline message to send via WebSocket
+ */
+export async function relayAckOffline(ids: string[]): Promise<string> {
+  return wasm().umbra_wasm_relay_ack_offline(JSON.stringify(ids));
+}
+
 /**
  * Relay event listener management
  */
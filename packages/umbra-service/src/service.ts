@@ -21,6 +21,7 @@ import type {
   InitConfig,
   Identity,
   PublicIdentity,
+  KeyRotation,
   CreateIdentityResult,
   ProfileUpdate,
   NetworkStatus,
@@ -33,6 +34,8 @@ import type {
   Message,
   Conversation,
   MessageReaction,
+  MessageSearchResult,
+  MessageSearchSource,
   MessageEvent,
   ChatMessagePayload,
   Group,
@@ -240,6 +243,10 @@ export class UmbraService {
     return network.stopNetwork();
   }
 
+  setIceServers(iceServers: unknown[]): void {
+    network.setIceServers(iceServers);
+  }
+
   createOffer(): Promise<string> {
     return network.createOffer();
   }
@@ -363,10 +370,9 @@ export class UmbraService {
 
   updateFriendEncryptionKey(
     fromDid: string,
-    newEncryptionKey: string,
-    signature: string,
+    rotation: KeyRotation,
   ): Promise<void> {
-    return friends.updateFriendEncryptionKey(fromDid, newEncryptionKey, signature);
+    return friends.updateFriendEncryptionKey(fromDid, rotation);
   }
 
   onFriendEvent(callback: (event: FriendEvent) => void): () => void {
@@ -472,6 +478,13 @@ export class UmbraService {
     return messaging.getPinnedMessages(conversationId);
   }
 
+  searchMessages(
+    query: string,
+    options?: { sources?: MessageSearchSource[]; limit?: number }
+  ): Promise<MessageSearchResult[]> {
+    return messaging.searchMessages(query, options);
+  }
+
   sendTypingIndicator(
     conversationId: string,
     recipientDid: string,
@@ -752,10 +765,36 @@ export class UmbraService {
     groupId: string,
     memberDid: string,
     relayWs?: WebSocket | null
-  ): Promise<void> {
+  ): Promise<string[]> {
     return groups.removeGroupMemberWithRotation(groupId, memberDid, relayWs);
   }
 
+  addGroupMemberMls(
+    groupId: string,
+    memberDid: string,
+    keyPackage: string,
+    relayWs?: WebSocket | null
+  ): Promise<number> {
+    return groups.addGroupMemberMls(groupId, memberDid, keyPackage, relayWs);
+  }
+
+  processGroupMlsWelcome(
+    welcome: string
+  ): Promise<{ groupId: string; epoch: number; members: string[] }> {
+    return groups.processGroupMlsWelcome(welcome);
+  }
+
+  processGroupMlsCommit(
+    groupId: string,
+    commit: string
+  ): Promise<{ status: 'applied' | 'removed'; committer: string }> {
+    return groups.processGroupMlsCommit(groupId, commit);
+  }
+
+  updateGroupMlsKeys(groupId: string, relayWs?: WebSocket | null): Promise<number> {
+    return groups.updateGroupMlsKeys(groupId, relayWs);
+  }
+
   onGroupEvent(callback: (event: GroupEvent) => void): () => void {
     this._groupListeners.push(callback);
     return () => {
@@ -838,8 +877,8 @@ export class UmbraService {
     return communityModule.updateCategory(categoryId, name, actorDid);
   }
 
-  reorderCategories(spaceId: string, categoryIds: string[]): Promise<void> {
-    return communityModule.reorderCategories(spaceId, categoryIds);
+  reorderCategories(spaceId: string, categoryIds: string[], actorDid: string): Promise<void> {
+    return communityModule.reorderCategories(spaceId, categoryIds, actorDid);
   }
 
   deleteCategory(categoryId: string, actorDid: string): Promise<void> {
@@ -878,8 +917,8 @@ export class UmbraService {
     return communityModule.deleteChannel(channelId, actorDid);
   }
 
-  reorderChannels(spaceId: string, channelIds: string[]): Promise<void> {
-    return communityModule.reorderChannels(spaceId, channelIds);
+  reorderChannels(spaceId: string, channelIds: string[], actorDid: string): Promise<void> {
+    return communityModule.reorderChannels(spaceId, channelIds, actorDid);
   }
 
   setSlowMode(channelId: string, seconds: number, actorDid: string): Promise<void> {
@@ -999,9 +1038,10 @@ export class UmbraService {
       nickname?: string;
       avatar_url?: string;
       role_ids: string[];
-    }>
+    }>,
+    actorDid: string,
   ): Promise<number> {
-    return communityModule.createSeatsBatch(communityId, seats);
+    return communityModule.createSeatsBatch(communityId, seats, actorDid);
   }
 
   // Invites
@@ -1030,8 +1070,8 @@ export class UmbraService {
     communityIcon?: string | null,
     memberCount?: number,
     invitePayload?: string,
-  ): void {
-    communityModule.publishInviteToRelay(
+  ): Promise<void> {
+    return communityModule.publishInviteToRelay(
       relayWs, invite, communityName, communityDescription, communityIcon, memberCount, invitePayload,
     );
   }
@@ -1114,8 +1154,8 @@ export class UmbraService {
     return communityModule.editMessage(messageId, newContent, editorDid);
   }
 
-  deleteCommunityMessage(messageId: string): Promise<void> {
-    return communityModule.deleteMessage(messageId);
+  deleteCommunityMessage(messageId: string, actorDid: string): Promise<void> {
+    return communityModule.deleteMessage(messageId, actorDid);
   }
 
   // Reactions
@@ -1132,8 +1172,8 @@ export class UmbraService {
     return communityModule.pinMessage(messageId, channelId, actorDid);
   }
 
-  unpinCommunityMessage(messageId: string, channelId: string, _actorDid?: string): Promise<void> {
-    return communityModule.unpinMessage(messageId, channelId);
+  unpinCommunityMessage(messageId: string, channelId: string, actorDid: string): Promise<void> {
+    return communityModule.unpinMessage(messageId, channelId, actorDid);
   }
 
   getCommunityPinnedMessages(channelId: string): Promise<CommunityMessage[]> {
@@ -1160,8 +1200,8 @@ export class UmbraService {
     return communityModule.deleteEmoji(emojiId, actorDid);
   }
 
-  renameCommunityEmoji(emojiId: string, newName: string): Promise<void> {
-    return communityModule.renameEmoji(emojiId, newName);
+  renameCommunityEmoji(emojiId: string, newName: string, actorDid: string): Promise<void> {
+    return communityModule.renameEmoji(emojiId, newName, actorDid);
   }
 
   storeReceivedCommunityEmoji(
@@ -1182,8 +1222,8 @@ export class UmbraService {
     return communityModule.listStickers(communityId);
   }
 
-  deleteCommunitySticker(stickerId: string): Promise<void> {
-    return communityModule.deleteSticker(stickerId);
+  deleteCommunitySticker(stickerId: string, actorDid: string): Promise<void> {
+    return communityModule.deleteSticker(stickerId, actorDid);
   }
 
   storeReceivedCommunitySticker(
@@ -1205,12 +1245,12 @@ export class UmbraService {
     return communityModule.listStickerPacks(communityId);
   }
 
-  deleteCommunityStickerPack(packId: string): Promise<void> {
-    return communityModule.deleteStickerPack(packId);
+  deleteCommunityStickerPack(packId: string, actorDid: string): Promise<void> {
+    return communityModule.deleteStickerPack(packId, actorDid);
   }
 
-  renameCommunityStickerPack(packId: string, newName: string): Promise<void> {
-    return communityModule.renameStickerPack(packId, newName);
+  renameCommunityStickerPack(packId: string, newName: string, actorDid: string): Promise<void> {
+    return communityModule.renameStickerPack(packId, newName, actorDid);
   }
 
   storeReceivedCommunityStickerPack(
@@ -1258,8 +1298,8 @@ export class UmbraService {
     return communityModule.getFolders(channelId, parentFolderId);
   }
 
-  deleteCommunityFolder(id: string): Promise<void> {
-    return communityModule.deleteFolder(id);
+  deleteCommunityFolder(id: string, actorDid: string): Promise<void> {
+    return communityModule.deleteFolder(id, actorDid);
   }
 
   // ── DM Shared Files ──────────────────────────────────────────────────
@@ -1555,10 +1595,18 @@ export class UmbraService {
     return relay.relaySend(toDid, payload);
   }
 
+  relayAuthenticate(nonce: string): Promise<{ relayMessage: string }> {
+    return relay.relayAuthenticate(nonce);
+  }
+
   relayFetchOffline(): Promise<string> {
     return relay.relayFetchOffline();
   }
 
+  relayAckOffline(ids: string[]): Promise<string> {
+    return relay.relayAckOffline(ids);
+  }
+
   onRelayEvent(callback: (event: RelayEvent) => void): () => void {
     this._relayListeners.push(callback);
     return () => {
@@ -293,6 +293,33 @@ export interface Message {
   attachments?: MessageAttachment[];
 }
 
+/**
+ * Where a search result came from
+ */
+export type MessageSearchSource = 'dm' | 'group' | 'community';
+
+/**
+ * A ranked full-text search result
+ */
+export interface MessageSearchResult {
+  /** ID of the matching message */
+  messageId: string;
+  /** Which kind of conversation it belongs to */
+  source: MessageSearchSource;
+  /** Conversation ID (DMs and groups) or channel ID (communities) */
+  scopeId: string;
+  /** Community the channel belongs to (communities only) */
+  communityId: string | null;
+  /** Sender's DID */
+  senderDid: string;
+  /** When sent (Unix timestamp) */
+  createdAt: number;
+  /** Excerpt around the match, with matched terms wrapped in <mark>…</mark> */
+  snippet: string;
+  /** BM25 score; lower is a better match */
+  rank: number;
+}
+
 /**
  * A conversation (DM or group)
  */
@@ -470,6 +497,8 @@ export type RelayEnvelope =
   | { envelope: 'group_key_rotation'; version: 1; payload: GroupKeyRotationPayload }
   | { envelope: 'key_rotation'; version: 1; payload: KeyRotationPayload }
   | { envelope: 'group_member_removed'; version: 1; payload: GroupMemberRemovedPayload }
+  | { envelope: 'group_mls_commit'; version: 1; payload: GroupMlsCommitPayload }
+  | { envelope: 'group_mls_welcome'; version: 1; payload: GroupMlsWelcomePayload }
   | { envelope: 'message_status'; version: 1; payload: MessageStatusPayload }
   | { envelope: 'typing_indicator'; version: 1; payload: TypingIndicatorPayload }
   | { envelope: 'call_offer'; version: 1; payload: any }
@@ -646,6 +675,8 @@ export interface GroupInviteResponsePayload {
   fromDid: string;
   /** Responder's display name */
   fromDisplayName: string;
+  /** Responder's MLS key package (hex), on acceptances only */
+  keyPackage?: string;
   /** Unix timestamp */
   timestamp: number;
 }
@@ -674,16 +705,30 @@ export interface GroupMessagePayload {
   timestamp: number;
 }
 
+/** Signed announcement that an identity replaced its encryption key */
+export interface KeyRotation {
+  /** The identity whose key changed */
+  did: string;
+  /** New X25519 public encryption key (hex) */
+  new_encryption_key: string;
+  /** The X25519 public key being replaced (hex) */
+  previous_encryption_key: string;
+  /** When the rotation happened (Unix timestamp) */
+  rotated_at: number;
+  /** Ed25519 signature over the rotation (hex) */
+  signature: string;
+}
+
 /** Payload for encryption key rotation envelope */
 export interface KeyRotationPayload {
   /** Sender's DID */
   fromDid: string;
   /** New X25519 public encryption key (hex) */
   newEncryptionKey: string;
-  /** Ed25519 signature over the new key (hex) */
-  signature: string;
   /** Unix timestamp */
   timestamp: number;
+  /** The signed rotation to verify and apply */
+  rotation: KeyRotation;
 }
 
 /**
@@ -718,6 +763,34 @@ export interface GroupMemberRemovedPayload {
   timestamp: number;
 }
 
+/**
+ * Payload for an MLS commit moving a group to its next epoch
+ */
+export interface GroupMlsCommitPayload {
+  /** Group ID */
+  groupId: string;
+  /** Admin who made the commit */
+  senderDid: string;
+  /** Encoded commit (hex) */
+  commit: string;
+  /** Unix timestamp */
+  timestamp: number;
+}
+
+/**
+ * Payload for an MLS welcome bringing a new member into a group
+ */
+export interface GroupMlsWelcomePayload {
+  /** Group ID */
+  groupId: string;
+  /** Admin who added the member */
+  senderDid: string;
+  /** Encoded welcome (hex) */
+  welcome: string;
+  /** Unix timestamp */
+  timestamp: number;
+}
+
 /**
  * Payload for friend acceptance acknowledgment (second leg of two-phase friend sync).
  * Sent by the original requester back to the accepter to confirm friendship is fully synced.
@@ -796,6 +869,7 @@ export type GroupEvent =
   | { type: 'inviteDeclined'; groupId: string; fromDid: string }
   | { type: 'memberRemoved'; groupId: string; removedDid: string }
   | { type: 'keyRotated'; groupId: string; keyVersion: number }
+  | { type: 'epochChanged'; groupId: string; epoch: number }
   | { type: 'groupMessageReceived'; groupId: string; message: Message };
 
 // =============================================================================
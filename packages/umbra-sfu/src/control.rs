@@ -0,0 +1,211 @@
+//! Control API — how umbra-relay drives the SFU.
+//!
+//! The SFU has no idea who is allowed in which room: the relay
+//! authenticates clients, owns `CallRoom` membership and participant
+//! limits, and relays each participant's WebRTC signaling to the SFU over
+//! one WebSocket at `/control`:
+//!
+//! ```text
+//!   Client ──SfuOffer──► Relay ──Offer──────► SFU   (answers, trickles ICE)
+//!   Client ◄─SfuAnswer── Relay ◄─Answer────── SFU
+//!   Client ◄─SfuOffer─── Relay ◄─Offer─────── SFU   (someone started publishing)
+//!   Client ──SfuAnswer─► Relay ──Answer─────► SFU
+//!   Client ◄─────────── Relay ◄─DominantSpeaker SFU
+//! ```
+//!
+//! The connection must carry `Authorization: Bearer <SFU_SECRET>`.
+//! Media itself goes straight between the clients and the SFU's UDP port.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use crate::room::Sfu;
+
+/// Commands from the relay. Must match umbra-relay's `SfuCommand`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SfuCommand {
+    /// A participant's SDP offer (their first, or a renegotiation).
+    Offer {
+        room_id: String,
+        did: String,
+        sdp: String,
+    },
+    /// A participant's answer to an offer from the SFU.
+    Answer {
+        room_id: String,
+        did: String,
+        sdp: String,
+    },
+    /// A trickled ICE candidate (`RTCIceCandidateInit` JSON).
+    Candidate {
+        room_id: String,
+        did: String,
+        candidate: String,
+    },
+    /// A participant left the room or disconnected.
+    Leave { room_id: String, did: String },
+    /// The room is gone.
+    CloseRoom { room_id: String },
+}
+
+/// Events for the relay to pass on. Must match umbra-relay's `SfuEvent`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SfuEvent {
+    /// The SFU's answer to a participant's offer.
+    Answer {
+        room_id: String,
+        did: String,
+        sdp: String,
+    },
+    /// An SFU-initiated offer (tracks to receive were added or removed).
+    Offer {
+        room_id: String,
+        did: String,
+        sdp: String,
+    },
+    /// A trickled ICE candidate of the SFU (`RTCIceCandidateInit` JSON).
+    Candidate {
+        room_id: String,
+        did: String,
+        candidate: String,
+    },
+    /// The room's dominant speaker changed (sent to every participant).
+    DominantSpeaker { room_id: String, did: String },
+    /// A command for a participant failed.
+    Error {
+        room_id: String,
+        did: String,
+        message: String,
+    },
+}
+
+/// The shared secret the relay authenticates with.
+///
+/// Only its SHA-256 digest is kept, and presented secrets are compared by
+/// digest so the comparison doesn't leak how much of the secret matched.
+#[derive(Clone)]
+pub struct ControlSecret(Arc<[u8; 32]>);
+
+impl ControlSecret {
+    pub fn new(secret: &str) -> Self {
+        Self(Arc::new(Sha256::digest(secret.as_bytes()).into()))
+    }
+
+    pub fn matches(&self, presented: &str) -> bool {
+        let digest: [u8; 32] = Sha256::digest(presented.as_bytes()).into();
+        digest == *self.0
+    }
+}
+
+#[derive(Clone)]
+pub struct ControlState {
+    pub sfu: Sfu,
+    pub secret: ControlSecret,
+}
+
+/// GET /control — WebSocket upgrade for the relay.
+pub async fn control_handler(
+    State(state): State<ControlState>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let presented = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if !presented.is_some_and(|secret| state.secret.matches(secret)) {
+        return (StatusCode::UNAUTHORIZED, "Invalid control secret").into_response();
+    }
+
+    ws.on_upgrade(move |socket| handle_control(state.sfu, socket))
+}
+
+/// Run one relay's control connection.
+async fn handle_control(sfu: Sfu, socket: WebSocket) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<SfuEvent>();
+    tracing::info!("Relay connected to control API");
+
+    let sender_task = tokio::spawn(async move {
+        while let Some(event) = event_rx.recv().await {
+            match serde_json::to_string(&event) {
+                Ok(json) => {
+                    if ws_sender.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => tracing::error!("Failed to serialize SFU event: {}", e),
+            }
+        }
+    });
+
+    while let Some(msg) = ws_receiver.next().await {
+        match msg {
+            Ok(Message::Text(text)) => match serde_json::from_str::<SfuCommand>(&text) {
+                Ok(command) => {
+                    // Commands for different participants don't wait on each other
+                    let sfu = sfu.clone();
+                    let events = event_tx.clone();
+                    tokio::spawn(async move { sfu.handle(command, &events).await });
+                }
+                Err(e) => tracing::warn!("Failed to parse control command: {}", e),
+            },
+            Ok(Message::Close(_)) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    sender_task.abort();
+    tracing::info!("Relay disconnected from control API");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_serialization() {
+        let command = SfuCommand::Offer {
+            room_id: "room-1".to_string(),
+            did: "did:key:z6MkAlice".to_string(),
+            sdp: "v=0".to_string(),
+        };
+        let json = serde_json::to_string(&command).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"offer","room_id":"room-1","did":"did:key:z6MkAlice","sdp":"v=0"}"#
+        );
+        assert_eq!(serde_json::from_str::<SfuCommand>(&json).unwrap(), command);
+
+        let event: SfuEvent =
+            serde_json::from_str(r#"{"type":"dominant_speaker","room_id":"r","did":"d"}"#).unwrap();
+        assert_eq!(
+            event,
+            SfuEvent::DominantSpeaker {
+                room_id: "r".to_string(),
+                did: "d".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_control_secret() {
+        let secret = ControlSecret::new("s3cret");
+        assert!(secret.matches("s3cret"));
+        assert!(!secret.matches("s3cre"));
+    }
+}
@@ -0,0 +1,124 @@
+//! WebRTC engine setup.
+//!
+//! The SFU negotiates Opus and VP8 only — VP8 because simulcast layer
+//! switches must land on keyframes, and [`crate::rewrite::is_vp8_keyframe`]
+//! is how the SFU finds them. All peer connections share one UDP port, so
+//! only that port (plus the control API) has to be reachable.
+
+use std::net::SocketAddr;
+
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_OPUS, MIME_TYPE_VP8};
+use webrtc::api::setting_engine::SettingEngine;
+use webrtc::api::{APIBuilder, API};
+use webrtc::ice::udp_mux::{UDPMuxDefault, UDPMuxParams};
+use webrtc::ice::udp_network::UDPNetwork;
+use webrtc::ice_transport::ice_candidate_type::RTCIceCandidateType;
+use webrtc::interceptor::registry::Registry;
+use webrtc::rtp_transceiver::rtp_codec::{
+    RTCRtpCodecCapability, RTCRtpCodecParameters, RTCRtpHeaderExtensionCapability, RTPCodecType,
+};
+use webrtc::rtp_transceiver::RTCPFeedback;
+
+/// RTP header extension carrying the audio level of each packet.
+pub const AUDIO_LEVEL_URI: &str = "urn:ietf:params:rtp-hdrext:ssrc-audio-level";
+
+/// Header extensions needed to demultiplex simulcast layers.
+const SIMULCAST_URIS: &[&str] = &[
+    "urn:ietf:params:rtp-hdrext:sdes:mid",
+    "urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id",
+    "urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id",
+];
+
+/// Network settings for media.
+#[derive(Debug, Clone)]
+pub struct MediaConfig {
+    /// UDP address all peer connections share
+    pub udp_addr: SocketAddr,
+    /// Public IP to advertise in ICE candidates (when behind 1:1 NAT)
+    pub public_ip: Option<String>,
+}
+
+/// Build the WebRTC API every peer connection is created from.
+pub async fn build_api(config: &MediaConfig) -> Result<API, Box<dyn std::error::Error>> {
+    let mut media = MediaEngine::default();
+    media.register_codec(
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_OPUS.to_string(),
+                clock_rate: 48000,
+                channels: 2,
+                sdp_fmtp_line: "minptime=10;useinbandfec=1".to_string(),
+                rtcp_feedback: Vec::new(),
+            },
+            payload_type: 111,
+            ..Default::default()
+        },
+        RTPCodecType::Audio,
+    )?;
+    media.register_codec(
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_VP8.to_string(),
+                clock_rate: 90000,
+                channels: 0,
+                sdp_fmtp_line: String::new(),
+                rtcp_feedback: vec![
+                    RTCPFeedback {
+                        typ: "goog-remb".to_string(),
+                        parameter: String::new(),
+                    },
+                    RTCPFeedback {
+                        typ: "ccm".to_string(),
+                        parameter: "fir".to_string(),
+                    },
+                    RTCPFeedback {
+                        typ: "nack".to_string(),
+                        parameter: String::new(),
+                    },
+                    RTCPFeedback {
+                        typ: "nack".to_string(),
+                        parameter: "pli".to_string(),
+                    },
+                ],
+            },
+            payload_type: 96,
+            ..Default::default()
+        },
+        RTPCodecType::Video,
+    )?;
+
+    for uri in SIMULCAST_URIS {
+        media.register_header_extension(
+            RTCRtpHeaderExtensionCapability {
+                uri: uri.to_string(),
+            },
+            RTPCodecType::Video,
+            None,
+        )?;
+    }
+    media.register_header_extension(
+        RTCRtpHeaderExtensionCapability {
+            uri: AUDIO_LEVEL_URI.to_string(),
+        },
+        RTPCodecType::Audio,
+        None,
+    )?;
+
+    let registry = register_default_interceptors(Registry::new(), &mut media)?;
+
+    let socket = tokio::net::UdpSocket::bind(config.udp_addr).await?;
+    let mut settings = SettingEngine::default();
+    settings.set_udp_network(UDPNetwork::Muxed(UDPMuxDefault::new(UDPMuxParams::new(
+        socket,
+    ))));
+    if let Some(ip) = &config.public_ip {
+        settings.set_nat_1to1_ips(vec![ip.clone()], RTCIceCandidateType::Host);
+    }
+
+    Ok(APIBuilder::new()
+        .with_media_engine(media)
+        .with_interceptor_registry(registry)
+        .with_setting_engine(settings)
+        .build())
+}
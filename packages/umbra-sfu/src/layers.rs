@@ -0,0 +1,106 @@
+//! Simulcast layer selection.
+//!
+//! Publishers send video as up to three simulcast encodings, identified by
+//! their RTP stream ID (`rid`):
+//!
+//! ```text
+//!   rid "q"  quarter resolution   Layer::Low
+//!   rid "h"  half resolution      Layer::Medium
+//!   rid "f"  full resolution      Layer::High   (also a track without a rid)
+//! ```
+//!
+//! Each subscriber gets one encoding of each publisher's video. The current
+//! dominant speaker is forwarded at full resolution and everyone else at a
+//! lower one, so a large room costs subscribers little more downlink than a
+//! small one. In a one-to-one call both sides get full resolution.
+
+/// A simulcast encoding, from lowest to highest resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Low,
+    Medium,
+    High,
+}
+
+impl Layer {
+    /// The layer of a track with RTP stream ID `rid`. A track without a rid
+    /// is a publisher that doesn't simulcast, so it counts as full resolution.
+    pub fn from_rid(rid: &str) -> Option<Self> {
+        match rid {
+            "q" => Some(Layer::Low),
+            "h" => Some(Layer::Medium),
+            "f" | "" => Some(Layer::High),
+            _ => None,
+        }
+    }
+}
+
+/// Rooms up to this size forward non-speakers at `Medium` instead of `Low`.
+const SMALL_ROOM: usize = 4;
+
+/// The layer a subscriber should receive of a publisher's video.
+pub fn desired_layer(publisher_is_dominant: bool, participants: usize) -> Layer {
+    if publisher_is_dominant || participants <= 2 {
+        Layer::High
+    } else if participants <= SMALL_ROOM {
+        Layer::Medium
+    } else {
+        Layer::Low
+    }
+}
+
+/// The best layer the publisher actually sends: the highest one at or
+/// below `desired`, or failing that the lowest one above it.
+pub fn pick_layer(desired: Layer, available: impl IntoIterator<Item = Layer>) -> Option<Layer> {
+    let mut below = None;
+    let mut above = None;
+    for layer in available {
+        if layer <= desired {
+            below = below.max(Some(layer));
+        } else {
+            above = Some(above.map_or(layer, |a: Layer| a.min(layer)));
+        }
+    }
+    below.or(above)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_from_rid() {
+        assert_eq!(Layer::from_rid("q"), Some(Layer::Low));
+        assert_eq!(Layer::from_rid("h"), Some(Layer::Medium));
+        assert_eq!(Layer::from_rid("f"), Some(Layer::High));
+        assert_eq!(Layer::from_rid(""), Some(Layer::High));
+        assert_eq!(Layer::from_rid("x"), None);
+    }
+
+    #[test]
+    fn test_speaker_gets_full_resolution() {
+        assert_eq!(desired_layer(true, 10), Layer::High);
+        assert_eq!(desired_layer(false, 10), Layer::Low);
+        assert_eq!(desired_layer(false, 3), Layer::Medium);
+        assert_eq!(desired_layer(false, 2), Layer::High);
+    }
+
+    #[test]
+    fn test_pick_layer_falls_back_to_what_is_sent() {
+        let all = [Layer::Low, Layer::Medium, Layer::High];
+        assert_eq!(pick_layer(Layer::Medium, all), Some(Layer::Medium));
+
+        // Publisher only sends low and high
+        let sparse = [Layer::Low, Layer::High];
+        assert_eq!(pick_layer(Layer::Medium, sparse), Some(Layer::Low));
+
+        // Publisher only sends full resolution (no simulcast)
+        assert_eq!(pick_layer(Layer::Low, [Layer::High]), Some(Layer::High));
+        assert_eq!(
+            pick_layer(Layer::Low, [Layer::High, Layer::Medium]),
+            Some(Layer::Medium)
+        );
+
+        assert_eq!(pick_layer(Layer::High, []), None);
+    }
+}
@@ -0,0 +1,119 @@
+//! Umbra SFU
+//!
+//! A selective forwarding unit for group call rooms that are too big for a
+//! full mesh. Each participant sends their media once, to the SFU, which
+//! forwards it to everyone else in the room:
+//!
+//! 1. **End-to-end encrypted media**: clients encrypt frames with keys they
+//!    distribute among themselves over Umbra's encrypted call signaling.
+//!    The SFU forwards RTP without being able to decrypt it.
+//!
+//! 2. **Relay-controlled rooms**: umbra-relay authenticates clients, owns
+//!    room membership and participant limits, and passes each participant's
+//!    signaling to the SFU over the control API (see [`control`]).
+//!
+//! 3. **Speaker-based simulcast**: publishers send video in three layers,
+//!    and each subscriber gets the layer that fits — full quality for the
+//!    dominant speaker and small rooms, lower layers for everyone else.
+
+mod control;
+mod engine;
+mod layers;
+mod rewrite;
+mod room;
+mod speaker;
+
+use std::net::SocketAddr;
+
+use axum::{extract::State, response::IntoResponse, routing::get, Json, Router};
+use clap::Parser;
+use serde_json::json;
+
+use control::{ControlSecret, ControlState};
+use engine::MediaConfig;
+use room::Sfu;
+
+// ── CLI Arguments ─────────────────────────────────────────────────────────────
+
+#[derive(Parser, Debug)]
+#[command(name = "umbra-sfu", version, about = "Umbra group call SFU")]
+struct Args {
+    /// Port for the control API and health check
+    #[arg(short, long, default_value_t = 8090, env = "SFU_PORT")]
+    port: u16,
+
+    /// Shared secret umbra-relay authenticates to the control API with
+    /// (the relay's RELAY_SFU_SECRET)
+    #[arg(long, env = "SFU_SECRET", hide_env_values = true)]
+    secret: String,
+
+    /// UDP port all media flows through
+    #[arg(long, default_value_t = 50000, env = "SFU_UDP_PORT")]
+    udp_port: u16,
+
+    /// Public IP to advertise to clients, when the SFU is behind 1:1 NAT
+    /// (e.g. a cloud VM or a container with a published port)
+    #[arg(long, env = "SFU_PUBLIC_IP")]
+    public_ip: Option<String>,
+
+    /// Maximum participants per room
+    #[arg(long, default_value_t = room::DEFAULT_MAX_PARTICIPANTS, env = "SFU_MAX_PARTICIPANTS")]
+    max_participants: usize,
+}
+
+// ── Main ──────────────────────────────────────────────────────────────────────
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "umbra_sfu=info".into()),
+        )
+        .init();
+
+    let args = Args::parse();
+
+    let media = MediaConfig {
+        udp_addr: SocketAddr::from(([0, 0, 0, 0], args.udp_port)),
+        public_ip: args.public_ip,
+    };
+    let api = engine::build_api(&media)
+        .await
+        .expect("Failed to set up WebRTC");
+    let sfu = Sfu::new(api, args.max_participants);
+
+    tracing::info!(
+        udp_port = args.udp_port,
+        max_participants = args.max_participants,
+        "Media listening"
+    );
+
+    let app = Router::new()
+        .route("/control", get(control::control_handler))
+        .with_state(ControlState {
+            sfu: sfu.clone(),
+            secret: ControlSecret::new(&args.secret),
+        })
+        .route("/health", get(health_handler).with_state(sfu));
+
+    let addr = format!("0.0.0.0:{}", args.port);
+    tracing::info!("Umbra SFU starting on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .expect("Failed to bind address");
+
+    axum::serve(listener, app).await.expect("Server error");
+}
+
+/// Health check endpoint.
+async fn health_handler(State(sfu): State<Sfu>) -> impl IntoResponse {
+    Json(json!({
+        "status": "ok",
+        "service": "umbra-sfu",
+        "version": env!("CARGO_PKG_VERSION"),
+        "rooms": sfu.room_count(),
+        "participants": sfu.participant_count(),
+    }))
+}
@@ -0,0 +1,149 @@
+//! Keeping a forwarded video stream continuous across layer switches.
+//!
+//! Each simulcast layer is its own RTP stream with its own sequence numbers
+//! and timestamps. A subscriber sees a single stream, so when the SFU
+//! switches it to another layer the numbering has to carry on from where
+//! the old layer left off, and the switch has to land on a keyframe — the
+//! subscriber can't decode the new layer's delta frames.
+//!
+//! Payloads are end-to-end encrypted by the clients (insertable streams /
+//! SFrame style). Clients leave the VP8 payload descriptor and the first
+//! byte of the VP8 payload header in the clear, which is all that
+//! [`is_vp8_keyframe`] reads.
+
+/// Timestamp step inserted at a switch (one frame at 30fps, 90kHz clock).
+const SWITCH_TIMESTAMP_GAP: u32 = 3000;
+
+/// Rewrites sequence numbers and timestamps of one forwarded stream.
+#[derive(Debug, Default)]
+pub struct Rewriter {
+    seq_offset: u16,
+    timestamp_offset: u32,
+    last: Option<(u16, u32)>,
+    switching: bool,
+}
+
+impl Rewriter {
+    /// The next packet starts a new source stream.
+    pub fn switch(&mut self) {
+        self.switching = true;
+    }
+
+    /// Map a source packet's sequence number and timestamp onto the
+    /// forwarded stream.
+    pub fn rewrite(&mut self, seq: u16, timestamp: u32) -> (u16, u32) {
+        if std::mem::take(&mut self.switching) {
+            if let Some((last_seq, last_timestamp)) = self.last {
+                self.seq_offset = last_seq.wrapping_add(1).wrapping_sub(seq);
+                self.timestamp_offset = last_timestamp
+                    .wrapping_add(SWITCH_TIMESTAMP_GAP)
+                    .wrapping_sub(timestamp);
+            }
+        }
+
+        let out = (
+            seq.wrapping_add(self.seq_offset),
+            timestamp.wrapping_add(self.timestamp_offset),
+        );
+        // Retransmitted or reordered packets don't move the high-water mark
+        match self.last {
+            Some((last_seq, _)) if !seq_newer(out.0, last_seq) => {}
+            _ => self.last = Some(out),
+        }
+        out
+    }
+}
+
+/// Whether sequence number `a` comes after `b`, allowing for wraparound.
+fn seq_newer(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000
+}
+
+/// Whether a VP8 RTP payload starts a keyframe (RFC 7741).
+pub fn is_vp8_keyframe(payload: &[u8]) -> bool {
+    let Some(&descriptor) = payload.first() else {
+        return false;
+    };
+    let extended = descriptor & 0x80 != 0;
+    let start_of_partition = descriptor & 0x10 != 0;
+    let partition_index = descriptor & 0x07;
+    if !start_of_partition || partition_index != 0 {
+        return false;
+    }
+
+    let mut header = 1;
+    if extended {
+        let Some(&extensions) = payload.get(1) else {
+            return false;
+        };
+        header = 2;
+        // PictureID, 7 or 15 bits
+        if extensions & 0x80 != 0 {
+            let Some(&picture_id) = payload.get(header) else {
+                return false;
+            };
+            header += if picture_id & 0x80 != 0 { 2 } else { 1 };
+        }
+        // TL0PICIDX
+        if extensions & 0x40 != 0 {
+            header += 1;
+        }
+        // TID / KEYIDX
+        if extensions & 0x30 != 0 {
+            header += 1;
+        }
+    }
+
+    // The inverse keyframe flag of the VP8 payload header
+    payload.get(header).is_some_and(|b| b & 0x01 == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewriter_continues_across_switch() {
+        let mut rewriter = Rewriter::default();
+        assert_eq!(rewriter.rewrite(100, 9000), (100, 9000));
+        assert_eq!(rewriter.rewrite(101, 12000), (101, 12000));
+
+        // New layer with unrelated numbering
+        rewriter.switch();
+        assert_eq!(rewriter.rewrite(40000, 555), (102, 15000));
+        assert_eq!(rewriter.rewrite(40001, 3555), (103, 18000));
+
+        // A late packet from before is mapped but doesn't move the mark
+        assert_eq!(rewriter.rewrite(39999, 0), (101, 14445));
+        rewriter.switch();
+        assert_eq!(rewriter.rewrite(7, 0), (104, 21000));
+    }
+
+    #[test]
+    fn test_rewriter_wraps() {
+        let mut rewriter = Rewriter::default();
+        rewriter.rewrite(u16::MAX, u32::MAX);
+        rewriter.switch();
+        assert_eq!(rewriter.rewrite(500, 0), (0, SWITCH_TIMESTAMP_GAP - 1));
+    }
+
+    #[test]
+    fn test_vp8_keyframe_detection() {
+        // S=1, PID=0, no extensions, then payload header with P=0
+        assert!(is_vp8_keyframe(&[0x10, 0x00]));
+        // P=1: delta frame
+        assert!(!is_vp8_keyframe(&[0x10, 0x01]));
+        // Not the start of the first partition
+        assert!(!is_vp8_keyframe(&[0x00, 0x00]));
+        assert!(!is_vp8_keyframe(&[0x11, 0x00]));
+
+        // X=1 with a 15-bit PictureID, TL0PICIDX and TID
+        assert!(is_vp8_keyframe(&[0x90, 0xE0, 0x80, 0x01, 0x05, 0x20, 0x9C]));
+        assert!(!is_vp8_keyframe(&[
+            0x90, 0xE0, 0x80, 0x01, 0x05, 0x20, 0x9D
+        ]));
+        // Truncated
+        assert!(!is_vp8_keyframe(&[0x90, 0x80]));
+        assert!(!is_vp8_keyframe(&[]));
+    }
+}
@@ -0,0 +1,1003 @@
+//! Rooms, participants and RTP forwarding.
+//!
+//! Each participant has one peer connection with the SFU. They publish
+//! their audio and (simulcast) video on it, and receive everyone else's
+//! media on it as one audio and one video track per publisher:
+//!
+//! ```text
+//!   Alice ──audio, video q/h/f──► SFU ──audio, video (one layer)──► Bob
+//!                                     ──audio, video (one layer)──► Carol
+//! ```
+//!
+//! Payloads are end-to-end encrypted by the clients with keys they exchange
+//! among themselves; the SFU forwards packets without decrypting them. It
+//! only reads RTP headers (audio levels for speaker detection) and the
+//! VP8 descriptor bytes clients leave in the clear (keyframes for layer
+//! switches).
+//!
+//! The client always offers when it changes what it publishes; the SFU
+//! offers when the set of tracks it forwards to the client changes. If
+//! both happen at once the client's offer is refused, and it retries after
+//! answering the SFU's.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use tokio::sync::mpsc;
+use webrtc::api::API;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::signaling_state::RTCSignalingState;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtp::packet::Packet;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTPCodecType};
+use webrtc::rtp_transceiver::rtp_sender::RTCRtpSender;
+use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+use webrtc::track::track_local::{TrackLocal, TrackLocalWriter};
+use webrtc::track::track_remote::TrackRemote;
+
+use crate::control::{SfuCommand, SfuEvent};
+use crate::engine::AUDIO_LEVEL_URI;
+use crate::layers::{desired_layer, pick_layer, Layer};
+use crate::rewrite::{is_vp8_keyframe, Rewriter};
+use crate::speaker::SpeakerDetector;
+
+/// Default maximum participants per room.
+pub const DEFAULT_MAX_PARTICIPANTS: usize = 25;
+
+/// How often layers and the dominant speaker are re-evaluated.
+const LAYER_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where a room's events go (the control connection of its relay).
+pub type EventSender = mpsc::UnboundedSender<SfuEvent>;
+
+type SfuResult<T> = Result<T, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MediaKind {
+    Audio,
+    Video,
+}
+
+impl MediaKind {
+    fn of(kind: RTPCodecType) -> Option<Self> {
+        match kind {
+            RTPCodecType::Audio => Some(MediaKind::Audio),
+            RTPCodecType::Video => Some(MediaKind::Video),
+            _ => None,
+        }
+    }
+
+    fn track_id(&self) -> &'static str {
+        match self {
+            MediaKind::Audio => "audio",
+            MediaKind::Video => "video",
+        }
+    }
+}
+
+// ── SFU ───────────────────────────────────────────────────────────────────────
+
+/// All rooms on this SFU.
+#[derive(Clone)]
+pub struct Sfu {
+    inner: Arc<SfuInner>,
+}
+
+struct SfuInner {
+    api: API,
+    rooms: DashMap<String, Arc<Room>>,
+    max_participants: usize,
+}
+
+impl Sfu {
+    pub fn new(api: API, max_participants: usize) -> Self {
+        Self {
+            inner: Arc::new(SfuInner {
+                api,
+                rooms: DashMap::new(),
+                max_participants,
+            }),
+        }
+    }
+
+    /// Run a command from the relay. Failures are reported to the relay as
+    /// `SfuEvent::Error` for the participant concerned.
+    pub async fn handle(&self, command: SfuCommand, events: &EventSender) {
+        let (room_id, did, result) = match command {
+            SfuCommand::Offer { room_id, did, sdp } => {
+                let room = self
+                    .inner
+                    .rooms
+                    .entry(room_id.clone())
+                    .or_insert_with(|| {
+                        Room::new(&room_id, self.inner.max_participants, events.clone())
+                    })
+                    .clone();
+                room.set_events(events.clone());
+                let result = room.offer(&self.inner.api, &did, sdp).await;
+                (room_id, did, result)
+            }
+            SfuCommand::Answer { room_id, did, sdp } => {
+                let result = match self.room(&room_id) {
+                    Some(room) => room.answer(&did, sdp).await,
+                    None => Err("Unknown room".to_string()),
+                };
+                (room_id, did, result)
+            }
+            SfuCommand::Candidate {
+                room_id,
+                did,
+                candidate,
+            } => {
+                let result = match self.room(&room_id) {
+                    Some(room) => room.candidate(&did, &candidate).await,
+                    None => Err("Unknown room".to_string()),
+                };
+                (room_id, did, result)
+            }
+            SfuCommand::Leave { room_id, did } => {
+                if let Some(room) = self.room(&room_id) {
+                    room.leave(&did).await;
+                    if room.participants.is_empty() {
+                        self.inner.rooms.remove(&room_id);
+                    }
+                }
+                return;
+            }
+            SfuCommand::CloseRoom { room_id } => {
+                if let Some((_, room)) = self.inner.rooms.remove(&room_id) {
+                    room.close().await;
+                }
+                return;
+            }
+        };
+
+        if let Err(message) = result {
+            tracing::warn!(
+                room_id = room_id.as_str(),
+                did = did.as_str(),
+                "{}",
+                message
+            );
+            let _ = events.send(SfuEvent::Error {
+                room_id,
+                did,
+                message,
+            });
+        }
+    }
+
+    fn room(&self, room_id: &str) -> Option<Arc<Room>> {
+        self.inner.rooms.get(room_id).map(|r| r.clone())
+    }
+
+    pub fn room_count(&self) -> usize {
+        self.inner.rooms.len()
+    }
+
+    pub fn participant_count(&self) -> usize {
+        self.inner
+            .rooms
+            .iter()
+            .map(|room| room.participants.len())
+            .sum()
+    }
+}
+
+// ── Room ──────────────────────────────────────────────────────────────────────
+
+struct Room {
+    id: String,
+    max_participants: usize,
+    events: Mutex<EventSender>,
+    participants: DashMap<String, Arc<Participant>>,
+    speakers: Mutex<SpeakerDetector>,
+}
+
+/// A participant's peer connection and what flows over it.
+struct Participant {
+    did: String,
+    pc: Arc<RTCPeerConnection>,
+    /// Held while a description is being applied
+    negotiation: tokio::sync::Mutex<()>,
+    /// Held while forwarded tracks are added or removed
+    tracks: tokio::sync::Mutex<()>,
+    /// The SFU needs to offer once the current negotiation completes
+    renegotiate: AtomicBool,
+    /// What this participant publishes, by kind and layer
+    published: DashMap<(MediaKind, Layer), Arc<TrackRemote>>,
+    /// What this participant receives, by publisher DID and kind
+    downtracks: DashMap<(String, MediaKind), Arc<Downtrack>>,
+}
+
+impl Room {
+    fn new(id: &str, max_participants: usize, events: EventSender) -> Arc<Self> {
+        let room = Arc::new(Self {
+            id: id.to_string(),
+            max_participants,
+            events: Mutex::new(events),
+            participants: DashMap::new(),
+            speakers: Mutex::new(SpeakerDetector::new()),
+        });
+
+        let weak = Arc::downgrade(&room);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(LAYER_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(room) = weak.upgrade() else {
+                    break;
+                };
+                room.update_layers().await;
+            }
+        });
+
+        tracing::info!(room_id = id, "SFU room opened");
+        room
+    }
+
+    fn set_events(&self, events: EventSender) {
+        *self.events.lock().unwrap() = events;
+    }
+
+    fn emit(&self, event: SfuEvent) {
+        let _ = self.events.lock().unwrap().send(event);
+    }
+
+    fn participant(&self, did: &str) -> SfuResult<Arc<Participant>> {
+        self.participants
+            .get(did)
+            .map(|p| p.clone())
+            .ok_or_else(|| "Not connected to the SFU".to_string())
+    }
+
+    // ── Signaling ─────────────────────────────────────────────────────────
+
+    /// Apply a participant's offer and send back the answer. The first
+    /// offer creates the participant.
+    async fn offer(self: &Arc<Self>, api: &API, did: &str, sdp: String) -> SfuResult<()> {
+        let (participant, joined) = match self.participants.get(did).map(|p| p.clone()) {
+            Some(participant) => (participant, false),
+            None => {
+                if self.participants.len() >= self.max_participants {
+                    return Err("Room is full".to_string());
+                }
+                let participant = self.connect(api, did).await?;
+                self.participants
+                    .insert(did.to_string(), participant.clone());
+                (participant, true)
+            }
+        };
+
+        {
+            let _negotiating = participant.negotiation.lock().await;
+            if participant.pc.signaling_state() == RTCSignalingState::HaveLocalOffer {
+                return Err("Renegotiation in progress; answer the SFU's offer first".to_string());
+            }
+            let offer = RTCSessionDescription::offer(sdp).map_err(|e| e.to_string())?;
+            participant
+                .pc
+                .set_remote_description(offer)
+                .await
+                .map_err(|e| format!("Invalid offer: {}", e))?;
+            let answer = participant
+                .pc
+                .create_answer(None)
+                .await
+                .map_err(|e| e.to_string())?;
+            participant
+                .pc
+                .set_local_description(answer.clone())
+                .await
+                .map_err(|e| e.to_string())?;
+
+            self.emit(SfuEvent::Answer {
+                room_id: self.id.clone(),
+                did: did.to_string(),
+                sdp: answer.sdp,
+            });
+        }
+
+        if joined {
+            tracing::info!(
+                room_id = self.id.as_str(),
+                did = did,
+                "Participant joined SFU room"
+            );
+            // Start receiving everyone who is already publishing
+            let publishers: Vec<Arc<Participant>> = self
+                .participants
+                .iter()
+                .filter(|p| p.did != did)
+                .map(|p| p.clone())
+                .collect();
+            for publisher in publishers {
+                for kind in [MediaKind::Audio, MediaKind::Video] {
+                    if let Some(codec) = publisher.codec(kind).await {
+                        self.add_downtrack(&participant, &publisher, kind, codec)
+                            .await;
+                    }
+                }
+            }
+        }
+
+        if participant.renegotiate.load(Ordering::SeqCst) || joined {
+            self.negotiate(&participant).await;
+        }
+        Ok(())
+    }
+
+    /// Apply a participant's answer to our offer.
+    async fn answer(&self, did: &str, sdp: String) -> SfuResult<()> {
+        let participant = self.participant(did)?;
+        {
+            let _negotiating = participant.negotiation.lock().await;
+            let answer = RTCSessionDescription::answer(sdp).map_err(|e| e.to_string())?;
+            participant
+                .pc
+                .set_remote_description(answer)
+                .await
+                .map_err(|e| format!("Invalid answer: {}", e))?;
+        }
+
+        if participant.renegotiate.load(Ordering::SeqCst) {
+            self.negotiate(&participant).await;
+        }
+        Ok(())
+    }
+
+    async fn candidate(&self, did: &str, candidate: &str) -> SfuResult<()> {
+        let participant = self.participant(did)?;
+        let candidate: RTCIceCandidateInit =
+            serde_json::from_str(candidate).map_err(|e| format!("Invalid candidate: {}", e))?;
+        participant
+            .pc
+            .add_ice_candidate(candidate)
+            .await
+            .map_err(|e| format!("Invalid candidate: {}", e))
+    }
+
+    /// Offer the participant our current set of tracks, unless a
+    /// negotiation is already under way (then offer once it completes).
+    async fn negotiate(&self, participant: &Participant) {
+        let _negotiating = participant.negotiation.lock().await;
+        if participant.pc.signaling_state() != RTCSignalingState::Stable {
+            participant.renegotiate.store(true, Ordering::SeqCst);
+            return;
+        }
+        participant.renegotiate.store(false, Ordering::SeqCst);
+
+        let offer = match participant.pc.create_offer(None).await {
+            Ok(offer) => offer,
+            Err(e) => {
+                tracing::warn!(
+                    did = participant.did.as_str(),
+                    "Failed to create offer: {}",
+                    e
+                );
+                return;
+            }
+        };
+        if let Err(e) = participant.pc.set_local_description(offer.clone()).await {
+            tracing::warn!(
+                did = participant.did.as_str(),
+                "Failed to apply offer: {}",
+                e
+            );
+            return;
+        }
+
+        self.emit(SfuEvent::Offer {
+            room_id: self.id.clone(),
+            did: participant.did.clone(),
+            sdp: offer.sdp,
+        });
+    }
+
+    /// Create the peer connection for a new participant.
+    async fn connect(self: &Arc<Self>, api: &API, did: &str) -> SfuResult<Arc<Participant>> {
+        let pc = Arc::new(
+            api.new_peer_connection(RTCConfiguration::default())
+                .await
+                .map_err(|e| e.to_string())?,
+        );
+
+        let room = Arc::downgrade(self);
+        let owner = did.to_string();
+        pc.on_ice_candidate(Box::new(move |candidate| {
+            let room = room.clone();
+            let did = owner.clone();
+            Box::pin(async move {
+                let (Some(candidate), Some(room)) = (candidate, room.upgrade()) else {
+                    return;
+                };
+                let Ok(json) = candidate.to_json().and_then(|c| {
+                    serde_json::to_string(&c).map_err(|e| webrtc::Error::new(e.to_string()))
+                }) else {
+                    return;
+                };
+                room.emit(SfuEvent::Candidate {
+                    room_id: room.id.clone(),
+                    did,
+                    candidate: json,
+                });
+            })
+        }));
+
+        let room = Arc::downgrade(self);
+        let owner = did.to_string();
+        pc.on_track(Box::new(move |track, _receiver| {
+            let room = room.clone();
+            let did = owner.clone();
+            Box::pin(async move {
+                if let (Some(track), Some(room)) = (track, room.upgrade()) {
+                    room.publish(&did, track).await;
+                }
+            })
+        }));
+
+        Ok(Arc::new(Participant {
+            did: did.to_string(),
+            pc,
+            negotiation: tokio::sync::Mutex::new(()),
+            tracks: tokio::sync::Mutex::new(()),
+            renegotiate: AtomicBool::new(false),
+            published: DashMap::new(),
+            downtracks: DashMap::new(),
+        }))
+    }
+
+    // ── Media ─────────────────────────────────────────────────────────────
+
+    /// A participant started sending a track: forward it to everyone else.
+    async fn publish(self: &Arc<Self>, did: &str, track: Arc<TrackRemote>) {
+        let Some(kind) = MediaKind::of(track.kind()) else {
+            return;
+        };
+        let layer = match kind {
+            MediaKind::Audio => Layer::High,
+            MediaKind::Video => match Layer::from_rid(track.rid()) {
+                Some(layer) => layer,
+                None => {
+                    tracing::warn!(
+                        did = did,
+                        rid = track.rid(),
+                        "Ignoring unknown simulcast layer"
+                    );
+                    return;
+                }
+            },
+        };
+        let Ok(publisher) = self.participant(did) else {
+            return;
+        };
+
+        publisher.published.insert((kind, layer), track.clone());
+        tracing::debug!(
+            room_id = self.id.as_str(),
+            did = did,
+            kind = kind.track_id(),
+            rid = track.rid(),
+            "Participant published track"
+        );
+
+        let codec = track.codec().await.capability;
+        let subscribers: Vec<Arc<Participant>> = self
+            .participants
+            .iter()
+            .filter(|p| p.did != did)
+            .map(|p| p.clone())
+            .collect();
+        for subscriber in subscribers {
+            if self
+                .add_downtrack(&subscriber, &publisher, kind, codec.clone())
+                .await
+            {
+                self.negotiate(&subscriber).await;
+            }
+        }
+
+        let level_id = track
+            .params()
+            .await
+            .header_extensions
+            .iter()
+            .find(|ext| ext.uri == AUDIO_LEVEL_URI)
+            .map(|ext| ext.id as u8);
+
+        let room = Arc::downgrade(self);
+        let did = did.to_string();
+        tokio::spawn(async move {
+            while let Ok((packet, _)) = track.read_rtp().await {
+                let Some(room) = room.upgrade() else {
+                    return;
+                };
+                room.forward(&did, kind, layer, level_id, packet).await;
+            }
+            if let Some(room) = room.upgrade() {
+                room.unpublish(&did, kind, layer).await;
+            }
+        });
+    }
+
+    /// Forward one packet from `publisher` to every subscriber.
+    async fn forward(
+        &self,
+        publisher: &str,
+        kind: MediaKind,
+        layer: Layer,
+        level_id: Option<u8>,
+        mut packet: Packet,
+    ) {
+        if let Some(level) = level_id
+            .and_then(|id| packet.header.get_extension(id))
+            .and_then(|ext| ext.first().copied())
+        {
+            self.speakers
+                .lock()
+                .unwrap()
+                .update(publisher, level & 0x7F, Instant::now());
+        }
+
+        // Extension IDs are negotiated per connection; don't pass ours on
+        packet.header.extension = false;
+        packet.header.extensions.clear();
+
+        let key = (publisher.to_string(), kind);
+        let downtracks: Vec<Arc<Downtrack>> = self
+            .participants
+            .iter()
+            .filter_map(|p| p.downtracks.get(&key).map(|d| d.clone()))
+            .collect();
+        for downtrack in downtracks {
+            let mut packet = packet.clone();
+            if downtrack.prepare(layer, &mut packet) {
+                let _ = downtrack.track.write_rtp(&packet).await;
+            }
+        }
+    }
+
+    /// A published track ended. Once a publisher has no layers of a kind
+    /// left, subscribers stop receiving it.
+    async fn unpublish(&self, did: &str, kind: MediaKind, layer: Layer) {
+        let Ok(publisher) = self.participant(did) else {
+            return;
+        };
+        publisher.published.remove(&(kind, layer));
+        if publisher.published.iter().any(|t| t.key().0 == kind) {
+            return;
+        }
+
+        let subscribers: Vec<Arc<Participant>> =
+            self.participants.iter().map(|p| p.clone()).collect();
+        for subscriber in subscribers {
+            if self.remove_downtrack(&subscriber, did, kind).await {
+                self.negotiate(&subscriber).await;
+            }
+        }
+    }
+
+    /// Start sending `publisher`'s `kind` track to `subscriber`.
+    /// Returns whether a track was added (and renegotiation is needed).
+    async fn add_downtrack(
+        &self,
+        subscriber: &Participant,
+        publisher: &Arc<Participant>,
+        kind: MediaKind,
+        codec: RTCRtpCodecCapability,
+    ) -> bool {
+        let _tracks = subscriber.tracks.lock().await;
+        let key = (publisher.did.clone(), kind);
+        if subscriber.downtracks.contains_key(&key) {
+            return false;
+        }
+
+        let track = Arc::new(TrackLocalStaticRTP::new(
+            codec,
+            kind.track_id().to_string(),
+            publisher.did.clone(),
+        ));
+        let sender = match subscriber
+            .pc
+            .add_track(track.clone() as Arc<dyn TrackLocal + Send + Sync>)
+            .await
+        {
+            Ok(sender) => sender,
+            Err(e) => {
+                tracing::warn!(did = subscriber.did.as_str(), "Failed to add track: {}", e);
+                return false;
+            }
+        };
+
+        let downtrack = Arc::new(Downtrack {
+            track,
+            sender: sender.clone(),
+            publisher: Arc::downgrade(publisher),
+            kind,
+            state: Mutex::new(ForwardState::default()),
+        });
+        subscriber.downtracks.insert(key, downtrack.clone());
+
+        // Pass the subscriber's keyframe requests on to the publisher
+        let weak = Arc::downgrade(&downtrack);
+        tokio::spawn(async move {
+            while let Ok((packets, _)) = sender.read_rtcp().await {
+                let Some(downtrack) = weak.upgrade() else {
+                    return;
+                };
+                let wants_keyframe = packets.iter().any(|p| {
+                    p.as_any().is::<PictureLossIndication>() || p.as_any().is::<FullIntraRequest>()
+                });
+                if wants_keyframe {
+                    downtrack.request_keyframe().await;
+                }
+            }
+        });
+
+        true
+    }
+
+    /// Stop sending `publisher`'s `kind` track to `subscriber`.
+    /// Returns whether a track was removed (and renegotiation is needed).
+    async fn remove_downtrack(
+        &self,
+        subscriber: &Participant,
+        publisher: &str,
+        kind: MediaKind,
+    ) -> bool {
+        let _tracks = subscriber.tracks.lock().await;
+        let Some((_, downtrack)) = subscriber.downtracks.remove(&(publisher.to_string(), kind))
+        else {
+            return false;
+        };
+        if let Err(e) = subscriber.pc.remove_track(&downtrack.sender).await {
+            tracing::debug!(
+                did = subscriber.did.as_str(),
+                "Failed to remove track: {}",
+                e
+            );
+        }
+        true
+    }
+
+    /// Re-evaluate the dominant speaker and which layer each subscriber
+    /// gets of each publisher.
+    async fn update_layers(&self) {
+        let dominant = {
+            let mut speakers = self.speakers.lock().unwrap();
+            if let Some(did) = speakers.refresh(Instant::now()) {
+                self.emit(SfuEvent::DominantSpeaker {
+                    room_id: self.id.clone(),
+                    did,
+                });
+            }
+            speakers.dominant().map(str::to_string)
+        };
+
+        let participants = self.participants.len();
+        let downtracks: Vec<Arc<Downtrack>> = self
+            .participants
+            .iter()
+            .flat_map(|p| {
+                p.downtracks
+                    .iter()
+                    .filter(|d| d.kind == MediaKind::Video)
+                    .map(|d| d.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for downtrack in downtracks {
+            let Some(publisher) = downtrack.publisher.upgrade() else {
+                continue;
+            };
+            let available: Vec<Layer> = publisher
+                .published
+                .iter()
+                .filter(|t| t.key().0 == MediaKind::Video)
+                .map(|t| t.key().1)
+                .collect();
+            let desired = desired_layer(dominant.as_deref() == Some(&publisher.did), participants);
+            let Some(layer) = pick_layer(desired, available) else {
+                continue;
+            };
+            // Keep asking for a keyframe until the switch has happened
+            if downtrack.set_target(layer) {
+                downtrack.request_keyframe().await;
+            }
+        }
+    }
+
+    async fn leave(&self, did: &str) {
+        let Some((_, participant)) = self.participants.remove(did) else {
+            return;
+        };
+        self.speakers.lock().unwrap().remove(did);
+        let _ = participant.pc.close().await;
+
+        let subscribers: Vec<Arc<Participant>> =
+            self.participants.iter().map(|p| p.clone()).collect();
+        for subscriber in subscribers {
+            let audio = self
+                .remove_downtrack(&subscriber, did, MediaKind::Audio)
+                .await;
+            let video = self
+                .remove_downtrack(&subscriber, did, MediaKind::Video)
+                .await;
+            if audio || video {
+                self.negotiate(&subscriber).await;
+            }
+        }
+        tracing::info!(
+            room_id = self.id.as_str(),
+            did = did,
+            "Participant left SFU room"
+        );
+    }
+
+    async fn close(&self) {
+        let participants: Vec<Arc<Participant>> =
+            self.participants.iter().map(|p| p.clone()).collect();
+        self.participants.clear();
+        for participant in participants {
+            let _ = participant.pc.close().await;
+        }
+        tracing::info!(room_id = self.id.as_str(), "SFU room closed");
+    }
+}
+
+impl Participant {
+    /// The codec of a kind of media this participant publishes.
+    async fn codec(&self, kind: MediaKind) -> Option<RTCRtpCodecCapability> {
+        let track = self
+            .published
+            .iter()
+            .find(|t| t.key().0 == kind)
+            .map(|t| t.value().clone())?;
+        Some(track.codec().await.capability)
+    }
+}
+
+// ── Downtrack ─────────────────────────────────────────────────────────────────
+
+/// One publisher's audio or video as sent to one subscriber.
+struct Downtrack {
+    track: Arc<TrackLocalStaticRTP>,
+    sender: Arc<RTCRtpSender>,
+    publisher: Weak<Participant>,
+    kind: MediaKind,
+    state: Mutex<ForwardState>,
+}
+
+#[derive(Default)]
+struct ForwardState {
+    /// The layer the subscriber should get
+    target: Option<Layer>,
+    /// The layer being forwarded (switches to `target` at a keyframe)
+    current: Option<Layer>,
+    rewriter: Rewriter,
+}
+
+impl Downtrack {
+    /// Decide whether a packet from `layer` goes to this subscriber, and
+    /// renumber it if so.
+    fn prepare(&self, layer: Layer, packet: &mut Packet) -> bool {
+        if self.kind == MediaKind::Audio {
+            return true;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.current != Some(layer) {
+            if state.target != Some(layer) || !is_vp8_keyframe(&packet.payload) {
+                return false;
+            }
+            state.current = Some(layer);
+            state.rewriter.switch();
+        }
+
+        let (seq, timestamp) = state
+            .rewriter
+            .rewrite(packet.header.sequence_number, packet.header.timestamp);
+        packet.header.sequence_number = seq;
+        packet.header.timestamp = timestamp;
+        true
+    }
+
+    /// Set the layer this subscriber should get. Returns whether it is
+    /// still waiting for a keyframe of that layer.
+    fn set_target(&self, layer: Layer) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.target = Some(layer);
+        state.current != state.target
+    }
+
+    /// Ask the publisher for a keyframe of the layer this subscriber is
+    /// switching to (or getting).
+    async fn request_keyframe(&self) {
+        let Some(publisher) = self.publisher.upgrade() else {
+            return;
+        };
+        let layer = {
+            let state = self.state.lock().unwrap();
+            state.target.or(state.current)
+        };
+        let Some(ssrc) = layer
+            .and_then(|layer| publisher.published.get(&(self.kind, layer)))
+            .map(|track| track.ssrc())
+        else {
+            return;
+        };
+        let _ = publisher
+            .pc
+            .write_rtcp(&[Box::new(PictureLossIndication {
+                sender_ssrc: 0,
+                media_ssrc: ssrc,
+            })])
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{build_api, MediaConfig};
+    use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_OPUS};
+    use webrtc::api::APIBuilder;
+    use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+
+    async fn sfu(max_participants: usize) -> Sfu {
+        let api = build_api(&MediaConfig {
+            udp_addr: "127.0.0.1:0".parse().unwrap(),
+            public_ip: None,
+        })
+        .await
+        .unwrap();
+        Sfu::new(api, max_participants)
+    }
+
+    /// A client peer connection publishing one audio track.
+    async fn client_offer() -> (Arc<RTCPeerConnection>, String) {
+        let mut media = MediaEngine::default();
+        media.register_default_codecs().unwrap();
+        let api = APIBuilder::new().with_media_engine(media).build();
+        let pc = Arc::new(
+            api.new_peer_connection(RTCConfiguration::default())
+                .await
+                .unwrap(),
+        );
+        let track = Arc::new(TrackLocalStaticSample::new(
+            RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_OPUS.to_string(),
+                ..Default::default()
+            },
+            "audio".to_string(),
+            "client".to_string(),
+        ));
+        pc.add_track(track as Arc<dyn TrackLocal + Send + Sync>)
+            .await
+            .unwrap();
+        let offer = pc.create_offer(None).await.unwrap();
+        pc.set_local_description(offer.clone()).await.unwrap();
+        (pc, offer.sdp)
+    }
+
+    async fn next_answer_or_error(rx: &mut mpsc::UnboundedReceiver<SfuEvent>) -> SfuEvent {
+        loop {
+            let event = rx.recv().await.unwrap();
+            if matches!(event, SfuEvent::Answer { .. } | SfuEvent::Error { .. }) {
+                return event;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_offer_is_answered_and_room_is_capped() {
+        let sfu = sfu(1).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let (alice, sdp) = client_offer().await;
+        sfu.handle(
+            SfuCommand::Offer {
+                room_id: "room-1".to_string(),
+                did: "did:key:z6MkAlice".to_string(),
+                sdp,
+            },
+            &tx,
+        )
+        .await;
+        match next_answer_or_error(&mut rx).await {
+            SfuEvent::Answer { did, sdp, .. } => {
+                assert_eq!(did, "did:key:z6MkAlice");
+                alice
+                    .set_remote_description(RTCSessionDescription::answer(sdp).unwrap())
+                    .await
+                    .unwrap();
+            }
+            other => panic!("expected answer, got {:?}", other),
+        }
+        assert_eq!(sfu.participant_count(), 1);
+
+        // The room holds one participant
+        let (_bob, sdp) = client_offer().await;
+        sfu.handle(
+            SfuCommand::Offer {
+                room_id: "room-1".to_string(),
+                did: "did:key:z6MkBob".to_string(),
+                sdp,
+            },
+            &tx,
+        )
+        .await;
+        match next_answer_or_error(&mut rx).await {
+            SfuEvent::Error { did, message, .. } => {
+                assert_eq!(did, "did:key:z6MkBob");
+                assert_eq!(message, "Room is full");
+            }
+            other => panic!("expected error, got {:?}", other),
+        }
+
+        sfu.handle(
+            SfuCommand::Leave {
+                room_id: "room-1".to_string(),
+                did: "did:key:z6MkAlice".to_string(),
+            },
+            &tx,
+        )
+        .await;
+        assert_eq!(sfu.room_count(), 0);
+        let _ = alice.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_video_switches_layers_on_keyframes() {
+        let downtrack = Downtrack {
+            track: Arc::new(TrackLocalStaticRTP::new(
+                RTCRtpCodecCapability::default(),
+                "video".to_string(),
+                "did:key:z6MkAlice".to_string(),
+            )),
+            sender: unused_sender().await,
+            publisher: Weak::new(),
+            kind: MediaKind::Video,
+            state: Mutex::new(ForwardState::default()),
+        };
+        let packet = |seq: u16, keyframe: bool| {
+            let mut packet = Packet::default();
+            packet.header.sequence_number = seq;
+            packet.payload = if keyframe {
+                vec![0x10, 0x00].into()
+            } else {
+                vec![0x10, 0x01].into()
+            };
+            packet
+        };
+
+        // Nothing is forwarded until the target layer's keyframe
+        assert!(downtrack.set_target(Layer::Low));
+        assert!(!downtrack.prepare(Layer::Low, &mut packet(10, false)));
+        assert!(!downtrack.prepare(Layer::High, &mut packet(500, true)));
+        assert!(downtrack.prepare(Layer::Low, &mut packet(11, true)));
+        assert!(!downtrack.set_target(Layer::Low));
+
+        // Switching up: low keeps flowing until high has a keyframe
+        assert!(downtrack.set_target(Layer::High));
+        assert!(downtrack.prepare(Layer::Low, &mut packet(12, false)));
+        assert!(!downtrack.prepare(Layer::High, &mut packet(501, false)));
+        let mut switched = packet(502, true);
+        assert!(downtrack.prepare(Layer::High, &mut switched));
+        assert_eq!(switched.header.sequence_number, 13);
+        assert!(!downtrack.prepare(Layer::Low, &mut packet(13, false)));
+    }
+
+    /// A sender for a downtrack whose forwarding state is all a test uses.
+    async fn unused_sender() -> Arc<RTCRtpSender> {
+        let (pc, _) = client_offer().await;
+        pc.get_senders().await.remove(0)
+    }
+}
@@ -0,0 +1,169 @@
+//! Dominant speaker detection.
+//!
+//! Publishers tag their audio packets with the `ssrc-audio-level` RTP header
+//! extension (RFC 6464). The header sits outside the end-to-end encrypted
+//! payload, so the SFU can tell who is talking without hearing what is said.
+//! Levels are smoothed per participant, and the dominant speaker only
+//! changes when someone is clearly louder, so brief noises don't flip the
+//! video layout around.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Weight of each new sample in the smoothed loudness.
+const SMOOTHING: f32 = 0.1;
+
+/// Smoothed loudness below which a participant counts as silent
+/// (loudness is 127 minus the level in -dBov, so this is about -60 dBov).
+const SPEAKING_THRESHOLD: f32 = 67.0;
+
+/// How much louder a challenger must be to take over as dominant speaker.
+const SWITCH_MARGIN: f32 = 6.0;
+
+/// A participant whose audio stopped this long ago (muted, or discontinuous
+/// transmission during silence) counts as silent.
+const STALE_AFTER: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    loudness: f32,
+    updated_at: Instant,
+}
+
+/// Tracks who in a room is speaking.
+#[derive(Debug, Default)]
+pub struct SpeakerDetector {
+    levels: HashMap<String, Level>,
+    dominant: Option<String>,
+}
+
+impl SpeakerDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an audio level from `did`: 0 is the loudest, 127 silence.
+    pub fn update(&mut self, did: &str, level: u8, now: Instant) {
+        let loudness = 127.0 - f32::from(level.min(127));
+        let entry = self.levels.entry(did.to_string()).or_insert(Level {
+            loudness: 0.0,
+            updated_at: now,
+        });
+        let previous = Self::current(entry, now);
+        entry.loudness = previous + SMOOTHING * (loudness - previous);
+        entry.updated_at = now;
+    }
+
+    /// Forget a participant who left.
+    pub fn remove(&mut self, did: &str) {
+        self.levels.remove(did);
+        if self.dominant.as_deref() == Some(did) {
+            self.dominant = None;
+        }
+    }
+
+    /// Re-evaluate the dominant speaker. Returns the new one if it changed.
+    ///
+    /// The last dominant speaker stays dominant through silence, so the
+    /// layout doesn't change when everybody stops talking.
+    pub fn refresh(&mut self, now: Instant) -> Option<String> {
+        let loudest = self
+            .levels
+            .iter()
+            .map(|(did, level)| (did, Self::current(level, now)))
+            .filter(|(_, loudness)| *loudness >= SPEAKING_THRESHOLD)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let (candidate, loudness) = loudest?;
+
+        if self.dominant.as_ref() == Some(candidate) {
+            return None;
+        }
+        let incumbent = self
+            .dominant
+            .as_ref()
+            .and_then(|did| self.levels.get(did))
+            .map(|level| Self::current(level, now))
+            .unwrap_or(0.0);
+        if incumbent >= SPEAKING_THRESHOLD && loudness < incumbent + SWITCH_MARGIN {
+            return None;
+        }
+
+        self.dominant = Some(candidate.clone());
+        self.dominant.clone()
+    }
+
+    /// The current dominant speaker.
+    pub fn dominant(&self) -> Option<&str> {
+        self.dominant.as_deref()
+    }
+
+    fn current(level: &Level, now: Instant) -> f32 {
+        if now.duration_since(level.updated_at) > STALE_AFTER {
+            0.0
+        } else {
+            level.loudness
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `ms` milliseconds of 20ms packets at the given levels.
+    fn speak(detector: &mut SpeakerDetector, now: &mut Instant, ms: u64, levels: &[(&str, u8)]) {
+        for _ in 0..ms / 20 {
+            *now += Duration::from_millis(20);
+            for (did, level) in levels {
+                detector.update(did, *level, *now);
+            }
+        }
+    }
+
+    #[test]
+    fn test_loudest_speaker_becomes_dominant() {
+        let mut detector = SpeakerDetector::new();
+        let mut now = Instant::now();
+
+        // Alice talks, Bob is quiet
+        speak(
+            &mut detector,
+            &mut now,
+            1000,
+            &[("alice", 20), ("bob", 100)],
+        );
+        assert_eq!(detector.refresh(now).as_deref(), Some("alice"));
+        assert_eq!(detector.refresh(now), None);
+
+        // Bob talks up while Alice goes quiet
+        speak(
+            &mut detector,
+            &mut now,
+            1000,
+            &[("alice", 110), ("bob", 25)],
+        );
+        assert_eq!(detector.refresh(now).as_deref(), Some("bob"));
+        assert_eq!(detector.dominant(), Some("bob"));
+    }
+
+    #[test]
+    fn test_dominant_speaker_is_sticky() {
+        let mut detector = SpeakerDetector::new();
+        let mut now = Instant::now();
+
+        speak(&mut detector, &mut now, 1000, &[("alice", 30)]);
+        assert_eq!(detector.refresh(now).as_deref(), Some("alice"));
+
+        // Bob is only slightly louder than Alice: no switch
+        speak(&mut detector, &mut now, 1000, &[("alice", 30), ("bob", 28)]);
+        assert_eq!(detector.refresh(now), None);
+
+        // Everyone stops (no packets): Alice stays dominant
+        now += Duration::from_secs(5);
+        assert_eq!(detector.refresh(now), None);
+        assert_eq!(detector.dominant(), Some("alice"));
+
+        detector.remove("alice");
+        assert_eq!(detector.dominant(), None);
+    }
+}
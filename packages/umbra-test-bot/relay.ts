@@ -2,7 +2,7 @@
  * Relay WebSocket client for the test bot.
  *
  * Handles:
- * - Connection and DID registration
+ * - Connection, DID registration and the relay's auth challenge
  * - Sending/receiving relay envelopes
  * - Offline message fetching
  * - Automatic reconnection with exponential backoff
@@ -10,13 +10,19 @@
  */
 
 import WebSocket from 'ws';
+import { sign } from './crypto';
+
+/** Prefix the relay expects in front of the auth challenge nonce. */
+const AUTH_CHALLENGE_PREFIX = 'umbra-relay-auth-v1:';
 
 export type ServerMessage =
+  | { type: 'auth_challenge'; nonce: string }
   | { type: 'registered'; did: string }
   | { type: 'message'; from_did: string; payload: string; timestamp: number }
   | { type: 'ack'; id: string }
   | { type: 'pong' }
-  | { type: 'offline_messages'; messages: { from_did: string; payload: string; timestamp: number }[] }
+  | { type: 'offline_messages'; messages: { id: string; from_did: string; payload: string; timestamp: number }[] }
+  | { type: 'queue_full'; to_did: string }
   | { type: 'error'; message: string }
   | { type: 'session_created'; session_id: string }
   | { type: 'session_joined'; session_id: string; from_did: string; answer_payload: string };
@@ -26,6 +32,7 @@ export type MessageHandler = (msg: ServerMessage) => void;
 export class RelayClient {
   private ws: WebSocket | null = null;
   private did: string;
+  private signingPrivateKey: string;
   private url: string;
   private handlers: MessageHandler[] = [];
   private _registered = false;
@@ -46,9 +53,10 @@ export class RelayClient {
   onReconnected: (() => void) | null = null;
   onDisconnected: (() => void) | null = null;
 
-  constructor(url: string, did: string) {
+  constructor(url: string, did: string, signingPrivateKey: string) {
     this.url = url;
     this.did = did;
+    this.signingPrivateKey = signingPrivateKey;
   }
 
   get registered(): boolean {
@@ -115,6 +123,13 @@ export class RelayClient {
           const str = typeof raw === 'string' ? raw : raw.toString('utf-8');
           const msg = JSON.parse(str) as ServerMessage;
 
+          if (msg.type === 'auth_challenge') {
+            // Prove we own our DID by signing the nonce
+            const challenge = new TextEncoder().encode(AUTH_CHALLENGE_PREFIX + msg.nonce);
+            const signature = Buffer.from(sign(challenge, this.signingPrivateKey), 'hex').toString('base64');
+            this.send({ type: 'authenticate', signature });
+          }
+
           if (msg.type === 'registered') {
             this._registered = true;
             this._reconnectAttempts = 0; // Reset on successful registration
@@ -208,6 +223,13 @@ export class RelayClient {
     this.send({ type: 'fetch_offline' });
   }
 
+  /**
+   * Acknowledge processed offline messages so the relay deletes them.
+   */
+  ackOffline(ids: string[]): void {
+    if (ids.length > 0) this.send({ type: 'ack_offline', ids });
+  }
+
   /**
    * Send raw JSON to relay. Queues if disconnected and reconnect is enabled.
    */
@@ -206,7 +206,7 @@ export class TestBot {
   constructor(config: Partial<BotConfig> = {}) {
     this.config = { ...DEFAULT_CONFIG, ...config };
     this.identity = createIdentity(this.config.name);
-    this.relay = new RelayClient(this.config.relayUrl, this.identity.did);
+    this.relay = new RelayClient(this.config.relayUrl, this.identity.did, this.identity.signingPrivateKey);
 
     this.log('info', `Created identity: ${this.identity.did}`);
     this.log('debug', `Signing key:    ${this.identity.signingPublicKey}`);
@@ -1264,6 +1264,9 @@ export class TestBot {
       for (const m of msg.messages) {
         this.handleIncomingEnvelope(m.from_did, m.payload);
       }
+      this.relay.ackOffline(msg.messages.map((m) => m.id));
+    } else if (msg.type === 'queue_full') {
+      this.log('warn', `Offline queue full for ${msg.to_did.slice(0, 24)}... — message not queued`);
     } else if (msg.type === 'ack') {
       this.log('debug', `Relay ack: ${msg.id}`);
     } else if (msg.type === 'error') {
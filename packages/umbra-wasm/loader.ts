@@ -109,6 +109,9 @@ export interface UmbraWasmModule {
   umbra_wasm_messaging_reply_thread(json: string): string;
   umbra_wasm_messaging_get_pinned(json: string): string;
 
+  // Search (DMs, groups, communities)
+  umbra_wasm_search(json: string): string;
+
   // Groups — CRUD (implemented in Rust WASM)
   umbra_wasm_groups_create(json: string): string;
   umbra_wasm_groups_get(group_id: string): string;
@@ -140,6 +143,7 @@ export interface UmbraWasmModule {
   umbra_wasm_network_status(): string;
   umbra_wasm_network_start(): Promise<boolean>;
   umbra_wasm_network_stop(): Promise<boolean>;
+  umbra_wasm_network_set_ice_servers(ice_servers_json: string): boolean;
   umbra_wasm_network_create_offer(): Promise<string>;
   umbra_wasm_network_accept_offer(offer_json: string): Promise<string>;
   umbra_wasm_network_complete_handshake(answer_json: string): Promise<boolean>;
@@ -151,7 +155,10 @@ export interface UmbraWasmModule {
   umbra_wasm_relay_create_session(relay_url: string): Promise<string>;
   umbra_wasm_relay_accept_session(session_id: string, offer_payload: string): Promise<string>;
   umbra_wasm_relay_send(to_did: string, payload: string): Promise<string>;
+  umbra_wasm_relay_authenticate(nonce: string): Promise<string>;
+  umbra_wasm_relay_publish_invite(invite_json: string): Promise<string>;
   umbra_wasm_relay_fetch_offline(): Promise<string>;
+  umbra_wasm_relay_ack_offline(ids_json: string): Promise<string>;
 
   // Events
   umbra_wasm_subscribe_events(callback: (event_json: string) => void): void;
@@ -258,7 +265,7 @@ export interface UmbraWasmModule {
   umbra_wasm_community_message_list(json: string): string;
   umbra_wasm_community_message_get(message_id: string): string;
   umbra_wasm_community_message_edit(json: string): string;
-  umbra_wasm_community_message_delete(message_id: string): string;
+  umbra_wasm_community_message_delete(message_id: string, actor_did: string): string;
 
   // Community — Reactions
   umbra_wasm_community_reaction_add(json: string): string;
@@ -274,12 +281,12 @@ export interface UmbraWasmModule {
   // Community — Stickers
   umbra_wasm_community_sticker_create(json: string): string;
   umbra_wasm_community_sticker_list(community_id: string): string;
-  umbra_wasm_community_sticker_delete(sticker_id: string): string;
+  umbra_wasm_community_sticker_delete(sticker_id: string, actor_did: string): string;
 
   // Community — Sticker Packs
   umbra_wasm_community_sticker_pack_create(json: string): string;
   umbra_wasm_community_sticker_pack_list(community_id: string): string;
-  umbra_wasm_community_sticker_pack_delete(pack_id: string): string;
+  umbra_wasm_community_sticker_pack_delete(pack_id: string, actor_did: string): string;
   umbra_wasm_community_sticker_pack_rename(json: string): string;
 
   // Community — Pins
@@ -324,6 +331,10 @@ export interface UmbraWasmModule {
   umbra_wasm_groups_build_invite_decline_envelope(json: string): string;
   umbra_wasm_groups_send_message(json: string): string;
   umbra_wasm_groups_remove_member_with_rotation(json: string): string;
+  umbra_wasm_groups_mls_add_member(json: string): string;
+  umbra_wasm_groups_mls_process_welcome(json: string): string;
+  umbra_wasm_groups_mls_process_commit(json: string): string;
+  umbra_wasm_groups_mls_update(json: string): string;
 
   // Relay envelope builders
   umbra_wasm_community_build_event_relay_batch(json: string): string;
@@ -822,6 +833,10 @@ function buildModule(wasmPkg: any): UmbraWasmModule {
     umbra_wasm_messaging_get_pinned: (json: string) =>
       wasmPkg.umbra_wasm_messaging_get_pinned(json),
 
+    // ── Search (real WASM) ─────────────────────────────────────────
+    umbra_wasm_search: (json: string) =>
+      wasmPkg.umbra_wasm_search(json),
+
     // ── Groups — CRUD (real WASM) ──────────────────────────────────
     umbra_wasm_groups_create: (json: string) =>
       wasmPkg.umbra_wasm_groups_create(json),
@@ -872,6 +887,8 @@ function buildModule(wasmPkg: any): UmbraWasmModule {
     umbra_wasm_network_status: () => wasmPkg.umbra_wasm_network_status(),
     umbra_wasm_network_start: () => wasmPkg.umbra_wasm_network_start(),
     umbra_wasm_network_stop: () => wasmPkg.umbra_wasm_network_stop(),
+    umbra_wasm_network_set_ice_servers: (iceServersJson: string) =>
+      wasmPkg.umbra_wasm_network_set_ice_servers(iceServersJson),
     umbra_wasm_network_create_offer: () => wasmPkg.umbra_wasm_network_create_offer(),
     umbra_wasm_network_accept_offer: (offerJson: string) =>
       wasmPkg.umbra_wasm_network_accept_offer(offerJson),
@@ -891,8 +908,14 @@ function buildModule(wasmPkg: any): UmbraWasmModule {
       wasmPkg.umbra_wasm_relay_accept_session(sessionId, offerPayload),
     umbra_wasm_relay_send: (toDid: string, payload: string) =>
       wasmPkg.umbra_wasm_relay_send(toDid, payload),
+    umbra_wasm_relay_authenticate: (nonce: string) =>
+      wasmPkg.umbra_wasm_relay_authenticate(nonce),
+    umbra_wasm_relay_publish_invite: (inviteJson: string) =>
+      wasmPkg.umbra_wasm_relay_publish_invite(inviteJson),
     umbra_wasm_relay_fetch_offline: () =>
       wasmPkg.umbra_wasm_relay_fetch_offline(),
+    umbra_wasm_relay_ack_offline: (idsJson: string) =>
+      wasmPkg.umbra_wasm_relay_ack_offline(idsJson),
 
     // ── Calls (real WASM) ───────────────────────────────────────────
     umbra_wasm_calls_store: (json: string) =>
@@ -1080,8 +1103,8 @@ function buildModule(wasmPkg: any): UmbraWasmModule {
       wasmPkg.umbra_wasm_community_message_get(messageId),
     umbra_wasm_community_message_edit: (json: string) =>
       wasmPkg.umbra_wasm_community_message_edit(json),
-    umbra_wasm_community_message_delete: (messageId: string) =>
-      wasmPkg.umbra_wasm_community_message_delete(messageId),
+    umbra_wasm_community_message_delete: (messageId: string, actorDid: string) =>
+      wasmPkg.umbra_wasm_community_message_delete(messageId, actorDid),
 
     // Community — Reactions
     umbra_wasm_community_reaction_add: (json: string) =>
@@ -1106,16 +1129,16 @@ function buildModule(wasmPkg: any): UmbraWasmModule {
       wasmPkg.umbra_wasm_community_sticker_create(json),
     umbra_wasm_community_sticker_list: (communityId: string) =>
       wasmPkg.umbra_wasm_community_sticker_list(communityId),
-    umbra_wasm_community_sticker_delete: (stickerId: string) =>
-      wasmPkg.umbra_wasm_community_sticker_delete(stickerId),
+    umbra_wasm_community_sticker_delete: (stickerId: string, actorDid: string) =>
+      wasmPkg.umbra_wasm_community_sticker_delete(stickerId, actorDid),
 
     // Community — Sticker Packs
     umbra_wasm_community_sticker_pack_create: (json: string) =>
       wasmPkg.umbra_wasm_community_sticker_pack_create(json),
     umbra_wasm_community_sticker_pack_list: (communityId: string) =>
       wasmPkg.umbra_wasm_community_sticker_pack_list(communityId),
-    umbra_wasm_community_sticker_pack_delete: (packId: string) =>
-      wasmPkg.umbra_wasm_community_sticker_pack_delete(packId),
+    umbra_wasm_community_sticker_pack_delete: (packId: string, actorDid: string) =>
+      wasmPkg.umbra_wasm_community_sticker_pack_delete(packId, actorDid),
     umbra_wasm_community_sticker_pack_rename: (json: string) =>
       wasmPkg.umbra_wasm_community_sticker_pack_rename(json),
 
@@ -1192,6 +1215,14 @@ function buildModule(wasmPkg: any): UmbraWasmModule {
       wasmPkg.umbra_wasm_groups_send_message(json),
     umbra_wasm_groups_remove_member_with_rotation: (json: string) =>
       wasmPkg.umbra_wasm_groups_remove_member_with_rotation(json),
+    umbra_wasm_groups_mls_add_member: (json: string) =>
+      wasmPkg.umbra_wasm_groups_mls_add_member(json),
+    umbra_wasm_groups_mls_process_welcome: (json: string) =>
+      wasmPkg.umbra_wasm_groups_mls_process_welcome(json),
+    umbra_wasm_groups_mls_process_commit: (json: string) =>
+      wasmPkg.umbra_wasm_groups_mls_process_commit(json),
+    umbra_wasm_groups_mls_update: (json: string) =>
+      wasmPkg.umbra_wasm_groups_mls_update(json),
 
     // Relay envelope builders
     umbra_wasm_community_build_event_relay_batch: (json: string) =>
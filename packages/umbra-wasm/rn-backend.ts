@@ -267,6 +267,7 @@ function createNativeBackend(native: NativeUmbraCore): UmbraWasmModule {
     umbra_wasm_messaging_get_thread: (json: string) => call('messaging_get_thread', JSON.parse(json)),
     umbra_wasm_messaging_reply_thread: (json: string) => call('messaging_reply_thread', JSON.parse(json)),
     umbra_wasm_messaging_get_pinned: (json: string) => call('messaging_get_pinned', JSON.parse(json)),
+    umbra_wasm_search: (json: string) => call('search', JSON.parse(json)),
     umbra_wasm_messaging_update_status: (json: string) => call('messaging_update_status', JSON.parse(json)),
 
     // ── Groups (via dispatcher) ─────────────────────────────────────────
@@ -310,6 +311,8 @@ function createNativeBackend(native: NativeUmbraCore): UmbraWasmModule {
         return true;
       } catch (e) { console.warn('[rn-backend] network_stop failed:', e); return false; }
     },
+    // Native peers connect directly; there is no WebRTC transport to configure
+    umbra_wasm_network_set_ice_servers: () => true,
     umbra_wasm_network_create_offer: () => call('network_create_offer'),
     umbra_wasm_network_accept_offer: (offer: string) => call('network_accept_offer', { offer }),
     umbra_wasm_network_complete_handshake: (answer: string) => call('network_complete_handshake', { answer }),
@@ -322,7 +325,12 @@ function createNativeBackend(native: NativeUmbraCore): UmbraWasmModule {
     umbra_wasm_relay_accept_session: (session_id: string, offer_payload: string) =>
       call('relay_accept_session', { session_id, offer_payload }),
     umbra_wasm_relay_send: (to_did: string, payload: string) => call('relay_send', { to_did, payload }),
+    umbra_wasm_relay_authenticate: (nonce: string) => call('relay_authenticate', { nonce }),
+    umbra_wasm_relay_publish_invite: (invite_json: string) =>
+      call('relay_publish_invite', { invite: JSON.parse(invite_json) }),
     umbra_wasm_relay_fetch_offline: async () => JSON.stringify({ type: 'fetch_offline' }),
+    umbra_wasm_relay_ack_offline: async (idsJson: string) =>
+      JSON.stringify({ type: 'ack_offline', ids: JSON.parse(idsJson) }),
 
     // ── Events ──────────────────────────────────────────────────────────
     umbra_wasm_subscribe_events: (callback: (event_json: string) => void) => {
@@ -447,7 +455,7 @@ function createNativeBackend(native: NativeUmbraCore): UmbraWasmModule {
     umbra_wasm_community_message_list: (json: string) => call('community_message_list', JSON.parse(json)),
     umbra_wasm_community_message_get: (message_id: string) => call('community_message_get', { message_id }),
     umbra_wasm_community_message_edit: (json: string) => call('community_message_edit', JSON.parse(json)),
-    umbra_wasm_community_message_delete: (message_id: string) => call('community_message_delete', { message_id }),
+    umbra_wasm_community_message_delete: (id: string, actor_did: string) => call('community_message_delete', { id, actor_did }),
 
     // ── Community — Reactions (via dispatcher) ──────────────────────────
     umbra_wasm_community_reaction_add: (json: string) => call('community_reaction_add', JSON.parse(json)),
@@ -463,12 +471,12 @@ function createNativeBackend(native: NativeUmbraCore): UmbraWasmModule {
     // ── Community — Stickers (via dispatcher) ───────────────────────────
     umbra_wasm_community_sticker_create: (json: string) => call('community_sticker_create', JSON.parse(json)),
     umbra_wasm_community_sticker_list: (community_id: string) => call('community_sticker_list', { community_id }),
-    umbra_wasm_community_sticker_delete: (sticker_id: string) => call('community_sticker_delete', { sticker_id }),
+    umbra_wasm_community_sticker_delete: (sticker_id: string, actor_did: string) => call('community_sticker_delete', { sticker_id, actor_did }),
 
     // ── Community — Sticker Packs (via dispatcher) ──────────────────────
     umbra_wasm_community_sticker_pack_create: (json: string) => call('community_sticker_pack_create', JSON.parse(json)),
     umbra_wasm_community_sticker_pack_list: (community_id: string) => call('community_sticker_pack_list', { community_id }),
-    umbra_wasm_community_sticker_pack_delete: (pack_id: string) => call('community_sticker_pack_delete', { pack_id }),
+    umbra_wasm_community_sticker_pack_delete: (pack_id: string, actor_did: string) => call('community_sticker_pack_delete', { pack_id, actor_did }),
     umbra_wasm_community_sticker_pack_rename: (json: string) => call('community_sticker_pack_rename', JSON.parse(json)),
 
     // ── Community — Pins (via dispatcher) ───────────────────────────────
@@ -493,7 +501,7 @@ function createNativeBackend(native: NativeUmbraCore): UmbraWasmModule {
     umbra_wasm_community_record_file_download: (file_id: string) => call('community_record_file_download', { file_id }),
     umbra_wasm_community_create_folder: (json: string) => call('community_create_folder', JSON.parse(json)),
     umbra_wasm_community_get_folders: (json: string) => call('community_get_folders', JSON.parse(json)),
-    umbra_wasm_community_delete_folder: (folder_id: string) => call('community_delete_folder', { folder_id }),
+    umbra_wasm_community_delete_folder: (json: string) => call('community_delete_folder', JSON.parse(json)),
 
     // ── DM — Files (via dispatcher) ─────────────────────────────────────
     umbra_wasm_dm_upload_file: (json: string) => call('dm_upload_file', JSON.parse(json)),
@@ -513,6 +521,10 @@ function createNativeBackend(native: NativeUmbraCore): UmbraWasmModule {
     umbra_wasm_groups_build_invite_decline_envelope: (json: string) => call('groups_build_invite_decline_envelope', JSON.parse(json)),
     umbra_wasm_groups_send_message: (json: string) => call('groups_send_message', JSON.parse(json)),
     umbra_wasm_groups_remove_member_with_rotation: (json: string) => call('groups_remove_member_with_rotation', JSON.parse(json)),
+    umbra_wasm_groups_mls_add_member: (json: string) => call('groups_mls_add_member', JSON.parse(json)),
+    umbra_wasm_groups_mls_process_welcome: (json: string) => call('groups_mls_process_welcome', JSON.parse(json)),
+    umbra_wasm_groups_mls_process_commit: (json: string) => call('groups_mls_process_commit', JSON.parse(json)),
+    umbra_wasm_groups_mls_update: (json: string) => call('groups_mls_update', JSON.parse(json)),
 
     // ── Relay Envelope Builders (via dispatcher) ────────────────────────
     umbra_wasm_community_build_event_relay_batch: (json: string) => call('community_build_event_relay_batch', JSON.parse(json)),
@@ -635,6 +647,7 @@ function createStubBackend(): UmbraWasmModule {
     umbra_wasm_messaging_get_thread: () => notImplemented('messaging_get_thread'),
     umbra_wasm_messaging_reply_thread: () => notImplemented('messaging_reply_thread'),
     umbra_wasm_messaging_get_pinned: () => notImplemented('messaging_get_pinned'),
+    umbra_wasm_search: () => notImplemented('search'),
     umbra_wasm_groups_create: () => notImplemented('groups_create'),
     umbra_wasm_groups_get: () => notImplemented('groups_get'),
     umbra_wasm_groups_list: () => notImplemented('groups_list'),
@@ -657,6 +670,7 @@ function createStubBackend(): UmbraWasmModule {
     umbra_wasm_network_status: () => JSON.stringify({ connected: false, peers: 0 }),
     umbra_wasm_network_start: async () => false,
     umbra_wasm_network_stop: async () => true,
+    umbra_wasm_network_set_ice_servers: () => true,
     umbra_wasm_network_create_offer: () => notImplemented('network_create_offer'),
     umbra_wasm_network_accept_offer: () => notImplemented('network_accept_offer'),
     umbra_wasm_network_complete_handshake: () => notImplemented('network_complete_handshake'),
@@ -666,7 +680,11 @@ function createStubBackend(): UmbraWasmModule {
     umbra_wasm_relay_create_session: () => notImplemented('relay_create_session'),
     umbra_wasm_relay_accept_session: () => notImplemented('relay_accept_session'),
     umbra_wasm_relay_send: () => notImplemented('relay_send'),
+    umbra_wasm_relay_authenticate: () => notImplemented('relay_authenticate'),
+    umbra_wasm_relay_publish_invite: () => notImplemented('relay_publish_invite'),
     umbra_wasm_relay_fetch_offline: async () => JSON.stringify({ type: 'fetch_offline' }),
+    umbra_wasm_relay_ack_offline: async (idsJson: string) =>
+      JSON.stringify({ type: 'ack_offline', ids: JSON.parse(idsJson) }),
     umbra_wasm_subscribe_events: () => { console.log('[rn-backend] Event subscription (stub)'); },
     umbra_wasm_calls_store: () => notImplemented('calls_store'),
     umbra_wasm_calls_end: () => notImplemented('calls_end'),
@@ -793,6 +811,10 @@ function createStubBackend(): UmbraWasmModule {
     umbra_wasm_groups_build_invite_decline_envelope: () => notImplemented('groups_build_invite_decline_envelope'),
     umbra_wasm_groups_send_message: () => notImplemented('groups_send_message'),
     umbra_wasm_groups_remove_member_with_rotation: () => notImplemented('groups_remove_member_with_rotation'),
+    umbra_wasm_groups_mls_add_member: () => notImplemented('groups_mls_add_member'),
+    umbra_wasm_groups_mls_process_welcome: () => notImplemented('groups_mls_process_welcome'),
+    umbra_wasm_groups_mls_process_commit: () => notImplemented('groups_mls_process_commit'),
+    umbra_wasm_groups_mls_update: () => notImplemented('groups_mls_update'),
     umbra_wasm_community_build_event_relay_batch: () => notImplemented('community_build_event_relay_batch'),
     umbra_wasm_build_dm_file_event_envelope: () => notImplemented('build_dm_file_event_envelope'),
     umbra_wasm_build_metadata_envelope: () => notImplemented('build_metadata_envelope'),
@@ -304,6 +304,11 @@ export function createTauriBackend(
       return call('messaging_get_pinned', json) as any;
     },
 
+    // ── Search ─────────────────────────────────────────────────────
+    umbra_wasm_search: (json: string) => {
+      return call('search', json) as any;
+    },
+
     // ── Groups ──────────────────────────────────────────────────────
     umbra_wasm_groups_create: (json: string) => {
       return call('groups_create', json) as any;
@@ -399,6 +404,9 @@ export function createTauriBackend(
       return invoke('stop_network') as Promise<boolean>;
     },
 
+    // Native peers connect directly; there is no WebRTC transport to configure
+    umbra_wasm_network_set_ice_servers: () => true,
+
     umbra_wasm_network_create_offer: () => {
       return invoke('create_offer').then(ensureJsonString) as Promise<string>;
     },
@@ -458,9 +466,22 @@ export function createTauriBackend(
       }).then(ensureJsonString) as Promise<string>;
     },
 
+    umbra_wasm_relay_authenticate: (nonce: string) => {
+      return invoke('relay_authenticate', { nonce }).then(ensureJsonString) as Promise<string>;
+    },
+
+    umbra_wasm_relay_publish_invite: (invite_json: string) => {
+      return invoke('relay_publish_invite', {
+        invite: JSON.parse(invite_json),
+      }).then(ensureJsonString) as Promise<string>;
+    },
+
     umbra_wasm_relay_fetch_offline: () => {
       return invoke('relay_fetch_offline').then(ensureJsonString) as Promise<string>;
     },
+    umbra_wasm_relay_ack_offline: (idsJson: string) => {
+      return invoke('relay_ack_offline', { ids: JSON.parse(idsJson) }).then(ensureJsonString) as Promise<string>;
+    },
 
     // ── Calls ──────────────────────────────────────────────────────
     umbra_wasm_calls_store: (json: string) => {
@@ -749,8 +770,8 @@ export function createTauriBackend(
     umbra_wasm_community_message_edit: (json: string) => {
       return call('community_message_edit', json) as any;
     },
-    umbra_wasm_community_message_delete: (messageId: string) => {
-      return call('community_message_delete', JSON.stringify({ message_id: messageId })) as any;
+    umbra_wasm_community_message_delete: (messageId: string, actorDid: string) => {
+      return call('community_message_delete', JSON.stringify({ id: messageId, actor_did: actorDid })) as any;
     },
 
     // ── Community — Reactions ───────────────────────────────────────
@@ -785,8 +806,8 @@ export function createTauriBackend(
     umbra_wasm_community_sticker_list: (communityId: string) => {
       return call('community_sticker_list', JSON.stringify({ community_id: communityId })) as any;
     },
-    umbra_wasm_community_sticker_delete: (stickerId: string) => {
-      return call('community_sticker_delete', JSON.stringify({ sticker_id: stickerId })) as any;
+    umbra_wasm_community_sticker_delete: (stickerId: string, actorDid: string) => {
+      return call('community_sticker_delete', JSON.stringify({ sticker_id: stickerId, actor_did: actorDid })) as any;
     },
 
     // ── Community — Sticker Packs ─────────────────────────────────────
@@ -796,8 +817,8 @@ export function createTauriBackend(
     umbra_wasm_community_sticker_pack_list: (communityId: string) => {
       return call('community_sticker_pack_list', JSON.stringify({ community_id: communityId })) as any;
     },
-    umbra_wasm_community_sticker_pack_delete: (packId: string) => {
-      return call('community_sticker_pack_delete', JSON.stringify({ pack_id: packId })) as any;
+    umbra_wasm_community_sticker_pack_delete: (packId: string, actorDid: string) => {
+      return call('community_sticker_pack_delete', JSON.stringify({ pack_id: packId, actor_did: actorDid })) as any;
     },
     umbra_wasm_community_sticker_pack_rename: (json: string) => {
       return call('community_sticker_pack_rename', json) as any;
@@ -907,6 +928,18 @@ export function createTauriBackend(
     umbra_wasm_groups_remove_member_with_rotation: (json: string) => {
       return call('groups_remove_member_with_rotation', json) as any;
     },
+    umbra_wasm_groups_mls_add_member: (json: string) => {
+      return call('groups_mls_add_member', json) as any;
+    },
+    umbra_wasm_groups_mls_process_welcome: (json: string) => {
+      return call('groups_mls_process_welcome', json) as any;
+    },
+    umbra_wasm_groups_mls_process_commit: (json: string) => {
+      return call('groups_mls_process_commit', json) as any;
+    },
+    umbra_wasm_groups_mls_update: (json: string) => {
+      return call('groups_mls_update', json) as any;
+    },
 
     // ── Relay envelope builders ───────────────────────────────────
     umbra_wasm_community_build_event_relay_batch: (json: string) => {
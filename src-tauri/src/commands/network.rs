@@ -272,6 +272,52 @@ pub async fn relay_send(
     Ok(result.to_string())
 }
 
+#[tauri::command]
+pub async fn relay_authenticate(
+    nonce: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let identity_guard = state.identity.read().await;
+    let identity = identity_guard.as_ref()
+        .ok_or("No identity loaded")?;
+
+    let auth_msg = umbra_core::network::relay_client::RelayClientMessage::authenticate(
+        &identity.keypair().signing,
+        &nonce,
+    );
+
+    let result = serde_json::json!({
+        "did": identity.did_string(),
+        "relay_message": serde_json::to_string(&auth_msg).map_err(|e| e.to_string())?,
+    });
+
+    Ok(result.to_string())
+}
+
+#[tauri::command]
+pub async fn relay_publish_invite(
+    invite: umbra_core::network::relay_client::RelayInvite,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let identity_guard = state.identity.read().await;
+    let identity = identity_guard.as_ref()
+        .ok_or("No identity loaded")?;
+    let did = identity.did_string();
+
+    let msg = umbra_core::network::relay_client::RelayClientMessage::publish_invite(
+        &identity.keypair().signing,
+        &did,
+        invite,
+    );
+
+    let result = serde_json::json!({
+        "did": did,
+        "relay_message": serde_json::to_string(&msg).map_err(|e| e.to_string())?,
+    });
+
+    Ok(result.to_string())
+}
+
 #[tauri::command]
 pub async fn relay_fetch_offline() -> Result<String, String> {
     let msg = serde_json::json!({
@@ -279,3 +325,12 @@ pub async fn relay_fetch_offline() -> Result<String, String> {
     });
     Ok(msg.to_string())
 }
+
+#[tauri::command]
+pub async fn relay_ack_offline(ids: Vec<String>) -> Result<String, String> {
+    let msg = serde_json::json!({
+        "type": "ack_offline",
+        "ids": ids,
+    });
+    Ok(msg.to_string())
+}
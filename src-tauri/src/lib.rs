@@ -154,7 +154,10 @@ pub fn run() {
             commands::network::relay_create_session,
             commands::network::relay_accept_session,
             commands::network::relay_send,
+            commands::network::relay_authenticate,
+            commands::network::relay_publish_invite,
             commands::network::relay_fetch_offline,
+            commands::network::relay_ack_offline,
 
             // Crypto
             commands::crypto::sign,
@@ -236,7 +236,7 @@ export function CommunityEmojiPanel({ communityId, emoji }: CommunityEmojiPanelP
   }, []);
 
   const confirmRename = useCallback(async () => {
-    if (!service || !renamingId) return;
+    if (!service || !renamingId || !identity?.did) return;
     const trimmed = renameValue.trim();
     if (!EMOJI_NAME_REGEX.test(trimmed)) {
       setError('Name must be 2-32 characters, alphanumeric + underscores only.');
@@ -248,7 +248,7 @@ export function CommunityEmojiPanel({ communityId, emoji }: CommunityEmojiPanelP
       return;
     }
     try {
-      await service.renameCommunityEmoji(renamingId, trimmed);
+      await service.renameCommunityEmoji(renamingId, trimmed, identity.did);
       syncEvent({ type: 'emojiRenamed', communityId, emojiId: renamingId, newName: trimmed });
     } catch (err) {
       setError(err instanceof Error ? err.message : String(err));
@@ -256,7 +256,7 @@ export function CommunityEmojiPanel({ communityId, emoji }: CommunityEmojiPanelP
       setRenamingId(null);
       setRenameValue('');
     }
-  }, [service, renamingId, renameValue, emoji, communityId, syncEvent]);
+  }, [service, identity?.did, renamingId, renameValue, emoji, communityId, syncEvent]);
 
   const cancelRename = useCallback(() => {
     setRenamingId(null);
@@ -785,7 +785,7 @@ export function CommunitySettingsDialog({
       const CHUNK_SIZE = 100;
       for (let i = 0; i < seatData.length; i += CHUNK_SIZE) {
         const chunk = seatData.slice(i, i + CHUNK_SIZE);
-        await service!.createSeatsBatch(communityId, chunk);
+        await service!.createSeatsBatch(communityId, chunk, identity?.did ?? '');
         if (i + CHUNK_SIZE < seatData.length) await new Promise((r) => setTimeout(r, 0));
       }
 
@@ -795,7 +795,7 @@ export function CommunitySettingsDialog({
     } finally {
       setFetchingUsers(false);
     }
-  }, [service, communityId, community]);
+  }, [service, communityId, community, identity?.did]);
 
   const handleFetchUsers = useCallback(async () => {
     if (fetchingUsers || !service) return;
@@ -98,11 +98,11 @@ export function CommunityStickerPanel({
 
   // Delete pack
   const handleDeletePack = useCallback(async (packId: string) => {
-    if (!service) return;
+    if (!service || !identity?.did) return;
     if (packId === '__uncategorized__') return; // Cannot delete uncategorized
     try {
       setError(null);
-      await service.deleteCommunityStickerPack(packId);
+      await service.deleteCommunityStickerPack(packId, identity.did);
       syncEvent({
         type: 'stickerPackDeleted',
         communityId,
@@ -111,7 +111,7 @@ export function CommunityStickerPanel({
     } catch (err) {
       setError(err instanceof Error ? err.message : String(err));
     }
-  }, [service, communityId, syncEvent]);
+  }, [service, identity?.did, communityId, syncEvent]);
 
   // Upload sticker
   const handleUploadSticker = useCallback(async (packId: string, _file: never, name: string) => {
@@ -196,10 +196,10 @@ export function CommunityStickerPanel({
 
   // Delete sticker
   const handleDeleteSticker = useCallback(async (_packId: string, stickerId: string) => {
-    if (!service) return;
+    if (!service || !identity?.did) return;
     try {
       setError(null);
-      await service.deleteCommunitySticker(stickerId);
+      await service.deleteCommunitySticker(stickerId, identity.did);
       syncEvent({
         type: 'stickerDeleted',
         communityId,
@@ -208,7 +208,7 @@ export function CommunityStickerPanel({
     } catch (err) {
       setError(err instanceof Error ? err.message : String(err));
     }
-  }, [service, communityId, syncEvent]);
+  }, [service, identity?.did, communityId, syncEvent]);
 
   return (
     <View style={{ flex: 1 }}>
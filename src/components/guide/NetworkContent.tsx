@@ -18,12 +18,13 @@ export default function NetworkContent() {
 <FeatureCard
         icon={<ServerIcon size={16} color="#EAB308" />}
         title="Relay Server"
-        description="The relay server is a lightweight message router written in Rust using the Tokio async runtime and Axum HTTP framework. It maintains a persistent WebSocket connection per client, acting as a mailbox for encrypted messages. When both peers are online, messages are delivered in real-time. When a peer is offline, encrypted payloads are queued until the recipient reconnects. The relay authenticates clients by their DID on connection and routes messages by matching sender to recipient DIDs."
+        description="The relay server is a lightweight message router written in Rust using the Tokio async runtime and Axum HTTP framework. It maintains a persistent WebSocket connection per client, acting as a mailbox for encrypted messages. When both peers are online, messages are delivered in real-time. When a peer is offline, encrypted payloads are queued until the recipient reconnects. The relay authenticates clients on connection by challenging them to sign a nonce with the Ed25519 key embedded in their DID, and routes messages by matching sender to recipient DIDs."
         status="working"
         howTo={[
           'Your client connects to the relay automatically on startup',
           'Registration: send { type: "register", did: your_did }',
-          'The relay authenticates your DID and registers your presence',
+          'The relay replies with an auth_challenge nonce; your client signs it with the key in your DID',
+          'Once the signature verifies, the relay registers your presence',
           'Messages are routed by DID — relay matches sender to recipient',
           'Multiple relay servers can be configured for redundancy',
         ]}
@@ -46,6 +47,7 @@ export default function NetworkContent() {
         howTo={[
           'Connection established automatically when you open Umbra',
           'Registration: { type: "register", did: "did:key:z..." }',
+          'Challenge: { type: "auth_challenge", nonce } → { type: "authenticate", signature }',
           'Messages: { type: "send", to_did, payload: JSON }',
           'Ack: relay confirms delivery of each message',
           'Status indicator shows green when connected',
@@ -108,7 +110,7 @@ export default function NetworkContent() {
       <FeatureCard
         icon={<DatabaseIcon size={16} color="#06B6D4" />}
         title="Offline Delivery"
-        description="When the recipient is offline, the relay queues the encrypted message payload. No plaintext data is ever stored — only the encrypted blob, sender DID, and timestamp. When the recipient comes online, all queued messages are delivered immediately as a batch ('offline_messages' event) and removed from the queue. Each offline message is processed identically to real-time messages: decrypted, stored in the local database, and dispatched to the UI. Messages are never stored permanently on any server."
+        description="When the recipient is offline, the relay queues the encrypted message payload. No plaintext data is ever stored — only the encrypted blob, sender DID, and timestamp. The queue is persisted in SQLite, so it survives relay restarts, and each recipient has a quota — once it's full the sender gets a 'queue_full' response instead of an ack. When the recipient comes online, all queued messages are delivered immediately as a batch ('offline_messages' event) and are removed from the queue once the client acknowledges them ('ack_offline'), so nothing is lost if the client drops mid-delivery. Each offline message is processed identically to real-time messages: decrypted, stored in the local database, and dispatched to the UI. Messages are never stored permanently on any server."
         status="working"
         howTo={[
           'Offline delivery is automatic — no configuration needed',
@@ -118,6 +120,7 @@ export default function NetworkContent() {
         ]}
         sourceLinks={[
           { label: 'state.rs', path: 'packages/umbra-relay/src/state.rs' },
+          { label: 'offline_store.rs', path: 'packages/umbra-relay/src/offline_store.rs' },
           { label: 'handler.rs', path: 'packages/umbra-relay/src/handler.rs' },
           { label: 'useNetwork.ts', path: 'hooks/useNetwork.ts' },
         ]}
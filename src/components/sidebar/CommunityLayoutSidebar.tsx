@@ -1033,14 +1033,14 @@ export function CommunityLayoutSidebar({ communityId }: CommunityLayoutSidebarPr
       const reordered = [...spaceCategories];
       [reordered[idx - 1], reordered[idx]] = [reordered[idx], reordered[idx - 1]];
       try {
-        await service.reorderCategories(activeSpaceId, reordered.map((c) => c.id));
+        await service.reorderCategories(activeSpaceId, reordered.map((c) => c.id), myDid);
         // Dispatch event to refresh categories (WASM doesn't emit reorder events)
         syncEvent({ type: 'categoryUpdated', categoryId });
       } catch (err) {
         console.warn('[CommunityLayoutSidebar] Failed to move category up:', err);
       }
     },
-    [service, activeSpaceId, realCategories],
+    [service, activeSpaceId, realCategories, myDid],
   );
 
   // Move category down in the list (swap with next)
@@ -1055,14 +1055,14 @@ export function CommunityLayoutSidebar({ communityId }: CommunityLayoutSidebarPr
       const reordered = [...spaceCategories];
       [reordered[idx], reordered[idx + 1]] = [reordered[idx + 1], reordered[idx]];
       try {
-        await service.reorderCategories(activeSpaceId, reordered.map((c) => c.id));
+        await service.reorderCategories(activeSpaceId, reordered.map((c) => c.id), myDid);
         // Dispatch event to refresh categories (WASM doesn't emit reorder events)
         syncEvent({ type: 'categoryUpdated', categoryId });
       } catch (err) {
         console.warn('[CommunityLayoutSidebar] Failed to move category down:', err);
       }
     },
-    [service, activeSpaceId, realCategories],
+    [service, activeSpaceId, realCategories, myDid],
   );
 
   // ---------------------------------------------------------------------------
@@ -1134,7 +1134,7 @@ export function CommunityLayoutSidebar({ communityId }: CommunityLayoutSidebarPr
         categoryChannels.splice(newIndex, 0, channel);
 
         // 3. Reorder: send all channel IDs in this category
-        await service.reorderChannels(activeSpaceId, categoryChannels.map((c) => c.id));
+        await service.reorderChannels(activeSpaceId, categoryChannels.map((c) => c.id), myDid);
 
         // 4. Manually dispatch channelUpdated event to trigger useCommunity refresh.
         //    The WASM reorder functions update the DB but don't emit events.
@@ -1158,7 +1158,7 @@ export function CommunityLayoutSidebar({ communityId }: CommunityLayoutSidebarPr
         const reordered = [...spaceCategories];
         const [moved] = reordered.splice(current, 1);
         reordered.splice(newIndex, 0, moved);
-        await service.reorderCategories(activeSpaceId, reordered.map((c) => c.id));
+        await service.reorderCategories(activeSpaceId, reordered.map((c) => c.id), myDid);
 
         // Manually dispatch categoryUpdated event to trigger useCommunity refresh.
         // The WASM reorder function updates the DB but doesn't emit events.
@@ -1167,7 +1167,7 @@ export function CommunityLayoutSidebar({ communityId }: CommunityLayoutSidebarPr
         console.warn('[CommunityLayoutSidebar] Failed to reorder category:', err);
       }
     },
-    [service, realCategories, activeSpaceId],
+    [service, realCategories, activeSpaceId, myDid],
   );
 
   // ---------------------------------------------------------------------------
@@ -247,7 +247,7 @@ export function useCommunity(communityId: string | null): UseCommunityResult {
         case 'emojiRenamed':
           // Rename locally and refresh
           if (event.emojiId && event.newName) {
-            service.renameCommunityEmoji(event.emojiId, event.newName).catch(() => {});
+            service.renameCommunityEmoji(event.emojiId, event.newName, '').catch(() => {});
           }
           service.listCommunityEmoji(communityId).then(setEmoji).catch(() => {});
           break;
@@ -266,7 +266,7 @@ export function useCommunity(communityId: string | null): UseCommunityResult {
 
         case 'stickerDeleted':
           if (event.stickerId) {
-            service.deleteCommunitySticker(event.stickerId).catch(() => {});
+            service.deleteCommunitySticker(event.stickerId, '').catch(() => {});
           }
           service.listCommunityStickers(communityId).then(setStickers).catch(() => {});
           break;
@@ -284,7 +284,7 @@ export function useCommunity(communityId: string | null): UseCommunityResult {
 
         case 'stickerPackDeleted':
           if (event.packId) {
-            service.deleteCommunityStickerPack(event.packId).catch(() => {});
+            service.deleteCommunityStickerPack(event.packId, '').catch(() => {});
           }
           service.listCommunityStickerPacks(communityId).then(setStickerPacks).catch(() => {});
           // Also refresh stickers since pack deletion nullifies pack_id
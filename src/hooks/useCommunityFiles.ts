@@ -327,16 +327,16 @@ export function useCommunityFiles(channelId: string | null): UseCommunityFilesRe
 
   const deleteFolder = useCallback(
     async (folderId: string): Promise<void> => {
-      if (!service) return;
+      if (!service || !identity?.did) return;
       try {
-        await service.deleteCommunityFolder(folderId);
+        await service.deleteCommunityFolder(folderId, identity.did);
         setFolders((prev) => prev.filter((f) => f.id !== folderId));
         setAllFolders((prev) => prev.filter((f) => f.id !== folderId));
       } catch (err) {
         setError(err instanceof Error ? err : new Error(String(err)));
       }
     },
-    [service],
+    [service, identity?.did],
   );
 
   const recordDownload = useCallback(
@@ -109,7 +109,7 @@ export function useCommunityInvites(communityId: string | null): UseCommunityInv
             owner_nickname: identity.displayName,
             owner_avatar: identity.avatar,
           });
-          service.publishCommunityInviteToRelay(
+          await service.publishCommunityInviteToRelay(
             relayWs,
             invite,
             community.name,
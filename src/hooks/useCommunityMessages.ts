@@ -330,16 +330,16 @@ export function useCommunityMessages(channelId: string | null, communityId?: str
 
   const deleteMessage = useCallback(
     async (messageId: string): Promise<void> => {
-      if (!service) return;
+      if (!service || !identity?.did) return;
       try {
-        await service.deleteCommunityMessage(messageId);
+        await service.deleteCommunityMessage(messageId, identity.did);
         setMessages((prev) => prev.filter((m) => m.id !== messageId));
         if (channelId) broadcast({ type: 'communityMessageDeleted', channelId, messageId });
       } catch (err) {
         setError(err instanceof Error ? err : new Error(String(err)));
       }
     },
-    [service, channelId, broadcast],
+    [service, channelId, identity?.did, broadcast],
   );
 
   const pinMessage = useCallback(
@@ -120,7 +120,7 @@ export function useGroups(): UseGroupsResult {
       if (event.type === 'inviteReceived') {
         // Refresh pending invites when a new one arrives
         fetchPendingInvites();
-      } else if (event.type === 'inviteAccepted' || event.type === 'memberRemoved' || event.type === 'keyRotated') {
+      } else if (event.type === 'inviteAccepted' || event.type === 'memberRemoved' || event.type === 'keyRotated' || event.type === 'epochChanged') {
         // Refresh groups when membership changes
         fetchGroups();
       } else if (event.type === 'inviteDeclined') {
@@ -43,6 +43,8 @@ import type {
   GroupMessagePayload,
   GroupKeyRotationPayload,
   GroupMemberRemovedPayload,
+  GroupMlsCommitPayload,
+  GroupMlsWelcomePayload,
   KeyRotationPayload,
   MessageStatusPayload,
   FriendRequest,
@@ -397,8 +399,23 @@ async function _handleRelayMessage(ws: WebSocket, event: MessageEvent): Promise<
     console.log('[useNetwork] Relay message:', msg.type);
 
     switch (msg.type) {
+      case 'auth_challenge': {
+        // Prove we own our DID before the relay accepts the registration
+        service.relayAuthenticate(msg.nonce).then(({ relayMessage }) => {
+          if (ws.readyState === WebSocket.OPEN) ws.send(relayMessage);
+        }).catch((err: any) => console.error('[useNetwork] Failed to answer relay auth challenge:', err));
+        break;
+      }
+
       case 'registered': {
         console.log('[useNetwork] Registered with relay as', msg.did);
+        if (Array.isArray(msg.ice_servers) && msg.ice_servers.length > 0) {
+          try {
+            service.setIceServers(msg.ice_servers);
+          } catch (err) {
+            console.warn('[useNetwork] Failed to set relay ICE servers:', err);
+          }
+        }
         service.relayFetchOffline().then((fetchMsg: string) => {
           if (ws.readyState === WebSocket.OPEN) ws.send(fetchMsg);
         }).catch((err: any) => console.error('[useNetwork] Failed to fetch offline messages:', err));
@@ -431,7 +448,7 @@ async function _handleRelayMessage(ws: WebSocket, event: MessageEvent): Promise<
                 for (const invite of invites) {
                   if (ws.readyState !== WebSocket.OPEN) break;
                   const invitePayload = JSON.stringify({ owner_did: myDid, owner_nickname: ownerNickname ?? null, owner_avatar: null });
-                  service.publishCommunityInviteToRelay(ws, invite, community.name, community.description, community.iconUrl, members.length, invitePayload);
+                  await service.publishCommunityInviteToRelay(ws, invite, community.name, community.description, community.iconUrl, members.length, invitePayload);
                   published++;
                 }
               } catch { /* Best-effort */ }
@@ -504,7 +521,11 @@ async function _handleRelayMessage(ws: WebSocket, event: MessageEvent): Promise<
 
           } else if (envelope.envelope === 'group_invite_accept' && envelope.version === 1) {
             const acceptPayload = envelope.payload as GroupInviteResponsePayload;
-            try { await service.addGroupMember(acceptPayload.groupId, acceptPayload.fromDid, acceptPayload.fromDisplayName); service.dispatchGroupEvent({ type: 'inviteAccepted', groupId: acceptPayload.groupId, fromDid: acceptPayload.fromDid }); } catch (err) { console.warn('[useNetwork] Failed to process group invite acceptance:', err); }
+            try {
+              await service.addGroupMember(acceptPayload.groupId, acceptPayload.fromDid, acceptPayload.fromDisplayName);
+              if (acceptPayload.keyPackage) await service.addGroupMemberMls(acceptPayload.groupId, acceptPayload.fromDid, acceptPayload.keyPackage, ws);
+              service.dispatchGroupEvent({ type: 'inviteAccepted', groupId: acceptPayload.groupId, fromDid: acceptPayload.fromDid });
+            } catch (err) { console.warn('[useNetwork] Failed to process group invite acceptance:', err); }
 
           } else if (envelope.envelope === 'group_invite_decline' && envelope.version === 1) {
             const declinePayload = envelope.payload as GroupInviteResponsePayload;
@@ -527,7 +548,7 @@ async function _handleRelayMessage(ws: WebSocket, event: MessageEvent): Promise<
           } else if (envelope.envelope === 'key_rotation' && envelope.version === 1) {
             const keyPayload = envelope.payload as KeyRotationPayload;
             try {
-              await service.updateFriendEncryptionKey(keyPayload.fromDid, keyPayload.newEncryptionKey, keyPayload.signature);
+              await service.updateFriendEncryptionKey(keyPayload.fromDid, keyPayload.rotation);
               service.dispatchFriendEvent({ type: 'friendKeyRotated', did: keyPayload.fromDid });
             } catch (err) { console.warn('[useNetwork] Failed to process key rotation:', err); }
 
@@ -535,6 +556,17 @@ async function _handleRelayMessage(ws: WebSocket, event: MessageEvent): Promise<
             const removePayload = envelope.payload as GroupMemberRemovedPayload;
             service.dispatchGroupEvent({ type: 'memberRemoved', groupId: removePayload.groupId, removedDid: removePayload.removedDid });
 
+          } else if (envelope.envelope === 'group_mls_welcome' && envelope.version === 1) {
+            const welcomePayload = envelope.payload as GroupMlsWelcomePayload;
+            try { const joined = await service.processGroupMlsWelcome(welcomePayload.welcome); service.dispatchGroupEvent({ type: 'epochChanged', groupId: joined.groupId, epoch: joined.epoch }); } catch (err) { console.warn('[useNetwork] Failed to process group welcome:', err); }
+
+          } else if (envelope.envelope === 'group_mls_commit' && envelope.version === 1) {
+            const commitPayload = envelope.payload as GroupMlsCommitPayload;
+            try {
+              const outcome = await service.processGroupMlsCommit(commitPayload.groupId, commitPayload.commit);
+              if (outcome.status === 'removed') service.dispatchGroupEvent({ type: 'memberRemoved', groupId: commitPayload.groupId, removedDid: _lastRelayDid ?? '' });
+            } catch (err) { console.warn('[useNetwork] Failed to process group commit:', err); }
+
           } else if (envelope.envelope === 'message_status' && envelope.version === 1) {
             const statusPayload = envelope.payload as MessageStatusPayload;
             try { await service.updateMessageStatus(statusPayload.messageId, statusPayload.status); service.dispatchMessageEvent({ type: 'messageStatusChanged', messageId: statusPayload.messageId, status: statusPayload.status }); } catch (err) { console.warn('[useNetwork] Failed to update message status:', err); }
@@ -660,7 +692,11 @@ async function _handleRelayMessage(ws: WebSocket, event: MessageEvent): Promise<
               try { await service.storeGroupInvite(invitePayload); service.dispatchGroupEvent({ type: 'inviteReceived', invite: { id: invitePayload.inviteId, groupId: invitePayload.groupId, groupName: invitePayload.groupName, description: invitePayload.description, inviterDid: invitePayload.inviterDid, inviterName: invitePayload.inviterName, encryptedGroupKey: invitePayload.encryptedGroupKey, nonce: invitePayload.nonce, membersJson: invitePayload.membersJson, status: 'pending', createdAt: invitePayload.timestamp } }); } catch (err) { console.warn('[useNetwork] Failed to store offline group invite:', err); }
             } else if (envelope.envelope === 'group_invite_accept' && envelope.version === 1) {
               const acceptPayload = envelope.payload as GroupInviteResponsePayload;
-              try { await service.addGroupMember(acceptPayload.groupId, acceptPayload.fromDid, acceptPayload.fromDisplayName); service.dispatchGroupEvent({ type: 'inviteAccepted', groupId: acceptPayload.groupId, fromDid: acceptPayload.fromDid }); } catch (err) { console.warn('[useNetwork] Failed to process offline group invite acceptance:', err); }
+              try {
+                await service.addGroupMember(acceptPayload.groupId, acceptPayload.fromDid, acceptPayload.fromDisplayName);
+                if (acceptPayload.keyPackage) await service.addGroupMemberMls(acceptPayload.groupId, acceptPayload.fromDid, acceptPayload.keyPackage, ws);
+                service.dispatchGroupEvent({ type: 'inviteAccepted', groupId: acceptPayload.groupId, fromDid: acceptPayload.fromDid });
+              } catch (err) { console.warn('[useNetwork] Failed to process offline group invite acceptance:', err); }
             } else if (envelope.envelope === 'group_message' && envelope.version === 1) {
               const groupMsgPayload = envelope.payload as GroupMessagePayload;
               try {
@@ -676,12 +712,21 @@ async function _handleRelayMessage(ws: WebSocket, event: MessageEvent): Promise<
             } else if (envelope.envelope === 'key_rotation' && envelope.version === 1) {
               const keyPayload = envelope.payload as KeyRotationPayload;
               try {
-                await service.updateFriendEncryptionKey(keyPayload.fromDid, keyPayload.newEncryptionKey, keyPayload.signature);
+                await service.updateFriendEncryptionKey(keyPayload.fromDid, keyPayload.rotation);
                 service.dispatchFriendEvent({ type: 'friendKeyRotated', did: keyPayload.fromDid });
               } catch (err) { console.warn('[useNetwork] Failed to process offline key rotation:', err); }
             } else if (envelope.envelope === 'group_member_removed' && envelope.version === 1) {
               const removePayload = envelope.payload as GroupMemberRemovedPayload;
               service.dispatchGroupEvent({ type: 'memberRemoved', groupId: removePayload.groupId, removedDid: removePayload.removedDid });
+            } else if (envelope.envelope === 'group_mls_welcome' && envelope.version === 1) {
+              const welcomePayload = envelope.payload as GroupMlsWelcomePayload;
+              try { const joined = await service.processGroupMlsWelcome(welcomePayload.welcome); service.dispatchGroupEvent({ type: 'epochChanged', groupId: joined.groupId, epoch: joined.epoch }); } catch (err) { console.warn('[useNetwork] Failed to process offline group welcome:', err); }
+            } else if (envelope.envelope === 'group_mls_commit' && envelope.version === 1) {
+              const commitPayload = envelope.payload as GroupMlsCommitPayload;
+              try {
+                const outcome = await service.processGroupMlsCommit(commitPayload.groupId, commitPayload.commit);
+                if (outcome.status === 'removed') service.dispatchGroupEvent({ type: 'memberRemoved', groupId: commitPayload.groupId, removedDid: _lastRelayDid ?? '' });
+              } catch (err) { console.warn('[useNetwork] Failed to process offline group commit:', err); }
             } else if (envelope.envelope === 'message_status' && envelope.version === 1) {
               const statusPayload = envelope.payload as MessageStatusPayload;
               try { await service.updateMessageStatus(statusPayload.messageId, statusPayload.status); service.dispatchMessageEvent({ type: 'messageStatusChanged', messageId: statusPayload.messageId, status: statusPayload.status }); } catch (err) { console.warn('[useNetwork] Failed to update offline message status:', err); }
@@ -759,6 +804,25 @@ async function _handleRelayMessage(ws: WebSocket, event: MessageEvent): Promise<
             console.warn('[useNetwork] Backup restore error:', backupErr);
           }
         }
+
+        // The relay keeps offline messages until we ack them, so a crash
+        // mid-batch means they're redelivered on the next fetch.
+        const offlineIds = messages.map((m: any) => m.id).filter(Boolean);
+        if (offlineIds.length > 0) {
+          service.relayAckOffline(offlineIds).then((ackMsg: string) => {
+            if (ws.readyState === WebSocket.OPEN) ws.send(ackMsg);
+          }).catch((err: any) => console.error('[useNetwork] Failed to ack offline messages:', err));
+        }
+        break;
+      }
+
+      case 'queue_full': {
+        // Recipient's offline queue is full — the relay sends this instead of an ack
+        const pendingMsgId = _pendingRelayAcks.shift();
+        console.warn('[useNetwork] Relay offline queue full for', msg.to_did);
+        if (pendingMsgId && service) {
+          service.dispatchMessageEvent({ type: 'messageStatusChanged', messageId: pendingMsgId, status: { failed: 'Recipient offline queue is full' } });
+        }
         break;
       }
 